@@ -22,13 +22,13 @@ use mas_http::RequestBuilderExt;
 use mas_iana::oauth::OAuthClientAuthenticationMethod;
 use mas_jose::{jwk::PublicJsonWebKeySet, jwt::Jwt};
 use mas_keystore::Encrypter;
-use mas_storage::{RepositoryAccess, oauth2::OAuth2ClientRepository};
+use mas_storage::{Clock, RepositoryAccess, oauth2::OAuth2ClientRepository};
 use oauth2_types::errors::{ClientError, ClientErrorCode};
 use serde::{Deserialize, de::DeserializeOwned};
 use serde_json::Value;
 use thiserror::Error;
 
-use crate::record_error;
+use crate::{jwks_cache::ClientJwksCache, record_error};
 
 static JWT_BEARER_CLIENT_ASSERTION: &str = "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
 
@@ -103,7 +103,9 @@ impl Credentials {
     pub async fn verify(
         &self,
         http_client: &reqwest::Client,
+        jwks_cache: &ClientJwksCache,
         encrypter: &Encrypter,
+        clock: &impl Clock,
         method: &OAuthClientAuthenticationMethod,
         client: &Client,
     ) -> Result<(), CredentialsVerificationError> {
@@ -118,18 +120,24 @@ impl Credentials {
                 Credentials::ClientSecretBasic { client_secret, .. },
                 OAuthClientAuthenticationMethod::ClientSecretBasic,
             ) => {
-                // Decrypt the client_secret
-                let encrypted_client_secret = client
-                    .encrypted_client_secret
-                    .as_ref()
-                    .ok_or(CredentialsVerificationError::InvalidClientConfig)?;
+                if client.encrypted_client_secret.is_none() {
+                    return Err(CredentialsVerificationError::InvalidClientConfig);
+                }
+
+                // Check the client_secret against each of the secrets currently accepted
+                let mut matched = false;
+                for encrypted_client_secret in client.active_encrypted_client_secrets(clock.now()) {
+                    let decrypted_client_secret = encrypter
+                        .decrypt_string(encrypted_client_secret)
+                        .map_err(|_e| CredentialsVerificationError::DecryptionError)?;
 
-                let decrypted_client_secret = encrypter
-                    .decrypt_string(encrypted_client_secret)
-                    .map_err(|_e| CredentialsVerificationError::DecryptionError)?;
+                    if client_secret.as_bytes() == decrypted_client_secret {
+                        matched = true;
+                        break;
+                    }
+                }
 
-                // Check if the client_secret matches
-                if client_secret.as_bytes() != decrypted_client_secret {
+                if !matched {
                     return Err(CredentialsVerificationError::ClientSecretMismatch);
                 }
             }
@@ -144,30 +152,62 @@ impl Credentials {
                     .as_ref()
                     .ok_or(CredentialsVerificationError::InvalidClientConfig)?;
 
-                let jwks = fetch_jwks(http_client, jwks)
-                    .await
-                    .map_err(CredentialsVerificationError::JwksFetchFailed)?;
+                match jwks {
+                    JwksOrJwksUri::Jwks(jwks) => {
+                        jwt.verify_with_jwks(jwks)
+                            .map_err(|_| CredentialsVerificationError::InvalidAssertionSignature)?;
+                    }
 
-                jwt.verify_with_jwks(&jwks)
-                    .map_err(|_| CredentialsVerificationError::InvalidAssertionSignature)?;
+                    JwksOrJwksUri::JwksUri(uri) => {
+                        let jwks = jwks_cache
+                            .get(http_client, clock, uri)
+                            .await
+                            .map_err(CredentialsVerificationError::JwksFetchFailed)?;
+
+                        if jwt.verify_with_jwks(&jwks).is_err() {
+                            // The client may have rotated its keys since we
+                            // fetched them, in which case it uses a key ID we
+                            // don't know about yet
+                            let refreshed = jwks_cache
+                                .get_with_kid(http_client, clock, uri, jwt.header().kid())
+                                .await
+                                .map_err(CredentialsVerificationError::JwksFetchFailed)?;
+
+                            jwt.verify_with_jwks(&refreshed).map_err(|_| {
+                                CredentialsVerificationError::InvalidAssertionSignature
+                            })?;
+                        }
+                    }
+                }
             }
 
             (
                 Credentials::ClientAssertionJwtBearer { jwt, .. },
                 OAuthClientAuthenticationMethod::ClientSecretJwt,
             ) => {
-                // Decrypt the client_secret
-                let encrypted_client_secret = client
-                    .encrypted_client_secret
-                    .as_ref()
-                    .ok_or(CredentialsVerificationError::InvalidClientConfig)?;
+                if client.encrypted_client_secret.is_none() {
+                    return Err(CredentialsVerificationError::InvalidClientConfig);
+                }
 
-                let decrypted_client_secret = encrypter
-                    .decrypt_string(encrypted_client_secret)
-                    .map_err(|_e| CredentialsVerificationError::DecryptionError)?;
+                // The assertion may be signed with any of the secrets currently accepted
+                let mut verified = false;
+                for encrypted_client_secret in client.active_encrypted_client_secrets(clock.now()) {
+                    let decrypted_client_secret = encrypter
+                        .decrypt_string(encrypted_client_secret)
+                        .map_err(|_e| CredentialsVerificationError::DecryptionError)?;
+
+                    if jwt
+                        .verify_with_shared_secret(decrypted_client_secret)
+                        .is_ok()
+                    {
+                        verified = true;
+                        break;
+                    }
+                }
 
-                jwt.verify_with_shared_secret(decrypted_client_secret)
-                    .map_err(|_| CredentialsVerificationError::InvalidAssertionSignature)?;
+                if !verified {
+                    return Err(CredentialsVerificationError::InvalidAssertionSignature);
+                }
             }
 
             (_, _) => {
@@ -178,7 +218,12 @@ impl Credentials {
     }
 }
 
-async fn fetch_jwks(
+/// Get the JWKS of a client, fetching it if it was registered by URI
+///
+/// # Errors
+///
+/// Returns an error if the JWKS could not be fetched or parsed
+pub async fn fetch_jwks(
     http_client: &reqwest::Client,
     jwks: &JwksOrJwksUri,
 ) -> Result<PublicJsonWebKeySet, BoxError> {
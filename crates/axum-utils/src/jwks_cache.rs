@@ -0,0 +1,167 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::BoxError;
+use chrono::{DateTime, Duration, Utc};
+use mas_data_model::JwksOrJwksUri;
+use mas_http::RequestBuilderExt;
+use mas_jose::{constraints::Constrainable, jwk::PublicJsonWebKeySet};
+use mas_storage::{Clock, RepositoryAccess, SystemClock, oauth2::OAuth2ClientRepository};
+use tokio::sync::RwLock;
+use url::Url;
+
+/// The minimum time between two fetches of the same JWKS triggered by an
+/// unknown key ID, so that clients can't make us hammer their `jwks_uri`
+const MIN_REFRESH_INTERVAL: Duration = Duration::microseconds(60 * 1000 * 1000);
+
+#[derive(Debug)]
+struct CacheEntry {
+    jwks: Arc<PublicJsonWebKeySet>,
+    fetched_at: DateTime<Utc>,
+}
+
+/// A cache of the JWKS of clients registered with a `jwks_uri`
+///
+/// Keys are fetched the first time a client uses them, refreshed in the
+/// background, and fetched again when a client presents a key ID which isn't
+/// in the cached set, at most once every minute per URI. Failures are not
+/// cached.
+#[derive(Debug, Clone, Default)]
+pub struct ClientJwksCache {
+    cache: Arc<RwLock<HashMap<Url, CacheEntry>>>,
+}
+
+impl ClientJwksCache {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Warm up the cache by fetching the JWKS of all the static clients which
+    /// have a `jwks_uri`.
+    ///
+    /// This spawns a background task that will refresh the cached JWKS at the
+    /// given interval.
+    #[tracing::instrument(name = "client_jwks_cache.warm_up_and_run", skip_all)]
+    pub async fn warm_up_and_run<R: RepositoryAccess>(
+        &self,
+        http_client: &reqwest::Client,
+        interval: std::time::Duration,
+        repository: &mut R,
+    ) -> Result<tokio::task::JoinHandle<()>, R::Error> {
+        let clients = repository.oauth2_client().all_static().await?;
+        let clock = SystemClock::default();
+
+        for client in clients {
+            let Some(JwksOrJwksUri::JwksUri(uri)) = &client.jwks else {
+                continue;
+            };
+
+            if let Err(e) = self.fetch(http_client, &clock, uri).await {
+                tracing::error!(%client.id, %uri, error = &*e as &dyn std::error::Error, "Failed to fetch client JWKS");
+            }
+        }
+
+        // Spawn a background task to refresh the cache regularly
+        let cache = self.clone();
+        let http_client = http_client.clone();
+        Ok(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                cache.refresh_all(&http_client, &clock).await;
+            }
+        }))
+    }
+
+    #[tracing::instrument(name = "client_jwks_cache.fetch", fields(%uri), skip_all)]
+    async fn fetch(
+        &self,
+        http_client: &reqwest::Client,
+        clock: &impl Clock,
+        uri: &Url,
+    ) -> Result<Arc<PublicJsonWebKeySet>, BoxError> {
+        let jwks: PublicJsonWebKeySet = http_client
+            .get(uri.as_str())
+            .send_traced()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let jwks = Arc::new(jwks);
+
+        self.cache.write().await.insert(
+            uri.clone(),
+            CacheEntry {
+                jwks: jwks.clone(),
+                fetched_at: clock.now(),
+            },
+        );
+
+        Ok(jwks)
+    }
+
+    /// Get the JWKS served at the given URI, fetching it if it isn't cached
+    /// yet
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JWKS could not be fetched or parsed
+    pub async fn get(
+        &self,
+        http_client: &reqwest::Client,
+        clock: &impl Clock,
+        uri: &Url,
+    ) -> Result<Arc<PublicJsonWebKeySet>, BoxError> {
+        if let Some(entry) = self.cache.read().await.get(uri) {
+            return Ok(entry.jwks.clone());
+        }
+
+        self.fetch(http_client, clock, uri).await
+    }
+
+    /// Get the JWKS served at the given URI, fetching it again if the cached
+    /// one doesn't have a key with the given ID
+    ///
+    /// Keys without a key ID can't be matched, so a missing `kid` always
+    /// counts as a miss. Fetches triggered by a miss are rate-limited: the
+    /// cached JWKS is returned as-is if it was fetched less than a minute ago.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JWKS could not be fetched or parsed
+    pub async fn get_with_kid(
+        &self,
+        http_client: &reqwest::Client,
+        clock: &impl Clock,
+        uri: &Url,
+        kid: Option<&str>,
+    ) -> Result<Arc<PublicJsonWebKeySet>, BoxError> {
+        if let Some(entry) = self.cache.read().await.get(uri) {
+            let has_key =
+                kid.is_some_and(|kid| entry.jwks.iter().any(|key| key.kid() == Some(kid)));
+            if has_key || clock.now() - entry.fetched_at < MIN_REFRESH_INTERVAL {
+                return Ok(entry.jwks.clone());
+            }
+
+            tracing::info!(%uri, ?kid, "Key ID not found in the cached client JWKS, fetching it again");
+        }
+
+        self.fetch(http_client, clock, uri).await
+    }
+
+    #[tracing::instrument(name = "client_jwks_cache.refresh_all", skip_all)]
+    async fn refresh_all(&self, http_client: &reqwest::Client, clock: &impl Clock) {
+        // Grab all the keys first to avoid locking the cache for too long
+        let uris: Vec<Url> = self.cache.read().await.keys().cloned().collect();
+
+        for uri in uris {
+            if let Err(e) = self.fetch(http_client, clock, &uri).await {
+                tracing::error!(%uri, error = &*e as &dyn std::error::Error, "Failed to refresh client JWKS");
+            }
+        }
+    }
+}
@@ -12,6 +12,7 @@ pub mod cookies;
 pub mod csrf;
 pub mod error_wrapper;
 pub mod fancy_error;
+pub mod jwks_cache;
 pub mod jwt;
 pub mod language_detection;
 pub mod sentry;
@@ -23,5 +24,6 @@ pub use axum;
 pub use self::{
     error_wrapper::ErrorWrapper,
     fancy_error::{GenericError, InternalError},
+    jwks_cache::ClientJwksCache,
     session::{SessionInfo, SessionInfoExt},
 };
@@ -4,8 +4,8 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
-use mas_data_model::BrowserSession;
-use mas_storage::RepositoryAccess;
+use mas_data_model::{BrowserSession, SiteConfig};
+use mas_storage::{Clock, RepositoryAccess};
 use serde::{Deserialize, Serialize};
 use ulid::Ulid;
 
@@ -35,23 +35,35 @@ impl SessionInfo {
 
     /// Load the active [`BrowserSession`] from database
     ///
+    /// Sessions past the idle timeout or the maximum lifetime configured in the
+    /// [`SiteConfig`] are not returned, even if they weren't finished yet.
+    ///
     /// # Errors
     ///
     /// Returns an error if the underlying repository fails to load the session.
     pub async fn load_active_session<E>(
         &self,
         repo: &mut impl RepositoryAccess<Error = E>,
+        clock: &impl Clock,
+        site_config: &SiteConfig,
     ) -> Result<Option<BrowserSession>, E> {
         let Some(session_id) = self.current else {
             return Ok(None);
         };
 
+        let now = clock.now();
         let maybe_session = repo
             .browser_session()
             .lookup(session_id)
             .await?
             // Ensure that the session is still active
-            .filter(BrowserSession::active);
+            .filter(BrowserSession::active)
+            .filter(|session| {
+                !site_config
+                    .session_expiration
+                    .as_ref()
+                    .is_some_and(|config| session.expired(now, config))
+            });
 
         Ok(maybe_session)
     }
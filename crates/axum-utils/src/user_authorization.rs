@@ -8,13 +8,15 @@ use std::{collections::HashMap, error::Error};
 
 use axum::{
     extract::{
-        Form, FromRequest, FromRequestParts,
+        Form, FromRequest,
         rejection::{FailedToDeserializeForm, FormRejection},
     },
     response::{IntoResponse, Response},
 };
-use axum_extra::typed_header::{TypedHeader, TypedHeaderRejectionReason};
-use headers::{Authorization, Header, HeaderMapExt, HeaderName, authorization::Bearer};
+use headers::{
+    Authorization, Header, HeaderMapExt, HeaderName,
+    authorization::{Bearer, Credentials},
+};
 use http::{HeaderMap, HeaderValue, Request, StatusCode, header::WWW_AUTHENTICATE};
 use mas_data_model::Session;
 use mas_storage::{
@@ -33,10 +35,39 @@ struct AuthorizedForm<F> {
     inner: F,
 }
 
+/// Credentials for the `DPoP` authorization scheme, as defined in [RFC9449]
+///
+/// [RFC9449]: https://www.rfc-editor.org/rfc/rfc9449#section-7.1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DPoP(HeaderValue);
+
+impl DPoP {
+    /// The access token presented with this scheme
+    #[must_use]
+    pub fn token(&self) -> &str {
+        // The value was checked to be valid ASCII when decoding
+        self.0.to_str().unwrap_or_default()[Self::SCHEME.len()..].trim_start()
+    }
+}
+
+impl Credentials for DPoP {
+    const SCHEME: &'static str = "DPoP";
+
+    fn decode(value: &HeaderValue) -> Option<Self> {
+        value.to_str().ok()?;
+        Some(Self(value.clone()))
+    }
+
+    fn encode(&self) -> HeaderValue {
+        self.0.clone()
+    }
+}
+
 #[derive(Debug)]
 enum AccessToken {
     Form(String),
     Header(String),
+    DPoP(String),
     None,
 }
 
@@ -44,9 +75,10 @@ impl AccessToken {
     async fn fetch<E>(
         &self,
         repo: &mut impl RepositoryAccess<Error = E>,
+        dpop_jkt: Option<&str>,
     ) -> Result<(mas_data_model::AccessToken, Session), AuthorizationVerificationError<E>> {
         let token = match self {
-            AccessToken::Form(t) | AccessToken::Header(t) => t,
+            AccessToken::Form(t) | AccessToken::Header(t) | AccessToken::DPoP(t) => t,
             AccessToken::None => return Err(AuthorizationVerificationError::MissingToken),
         };
 
@@ -56,12 +88,30 @@ impl AccessToken {
             .await?
             .ok_or(AuthorizationVerificationError::InvalidToken)?;
 
+        // Tokens bound to a key must be presented with the DPoP scheme, along a
+        // proof signed with that key. Only those can use the DPoP scheme.
+        let presented_as_bound = match (self, token.dpop_jkt.as_deref()) {
+            (AccessToken::DPoP(_), Some(jkt)) => dpop_jkt == Some(jkt),
+            (AccessToken::DPoP(_), None) | (_, Some(_)) => false,
+            (_, None) => true,
+        };
+
+        if !presented_as_bound {
+            return Err(AuthorizationVerificationError::InvalidToken);
+        }
+
         let session = repo
             .oauth2_session()
             .lookup(token.session_id)
             .await?
             .ok_or(AuthorizationVerificationError::InvalidToken)?;
 
+        // Tokens requested with a resource indicator are meant for that resource
+        // server only, not for the endpoints of the service itself
+        if session.resource.is_some() {
+            return Err(AuthorizationVerificationError::InvalidToken);
+        }
+
         Ok((token, session))
     }
 }
@@ -73,24 +123,42 @@ pub struct UserAuthorization<F = ()> {
 }
 
 impl<F: Send> UserAuthorization<F> {
+    /// The access token, if it was presented with the `DPoP` scheme
+    ///
+    /// The DPoP proof sent along the token must then be validated, and the
+    /// thumbprint of its key given to [`Self::protected`] or
+    /// [`Self::protected_form`].
+    #[must_use]
+    pub fn dpop_token(&self) -> Option<&str> {
+        match &self.access_token {
+            AccessToken::DPoP(token) => Some(token),
+            _ => None,
+        }
+    }
+
     // TODO: take scopes to validate as parameter
     /// Verify a user authorization and return the session and the protected
     /// form value
     ///
+    /// `dpop_jkt` is the thumbprint of the key of the validated DPoP proof
+    /// sent with the request, if any.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the token is invalid, if the user session ended or
-    /// if the form is missing
+    /// Returns an error if the token is invalid, if the user session ended, if
+    /// the token is not presented with the proof it is bound to, if it was
+    /// issued for another resource server, or if the form is missing
     pub async fn protected_form<E>(
         self,
         repo: &mut impl RepositoryAccess<Error = E>,
         clock: &impl Clock,
+        dpop_jkt: Option<&str>,
     ) -> Result<(Session, F), AuthorizationVerificationError<E>> {
         let Some(form) = self.form else {
             return Err(AuthorizationVerificationError::MissingForm);
         };
 
-        let (token, session) = self.access_token.fetch(repo).await?;
+        let (token, session) = self.access_token.fetch(repo, dpop_jkt).await?;
 
         if !token.is_valid(clock.now()) || !session.is_valid() {
             return Err(AuthorizationVerificationError::InvalidToken);
@@ -102,15 +170,21 @@ impl<F: Send> UserAuthorization<F> {
     // TODO: take scopes to validate as parameter
     /// Verify a user authorization and return the session
     ///
+    /// `dpop_jkt` is the thumbprint of the key of the validated DPoP proof
+    /// sent with the request, if any.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the token is invalid or if the user session ended
+    /// Returns an error if the token is invalid, if the user session ended, if
+    /// the token is not presented with the proof it is bound to, or if it was
+    /// issued for another resource server
     pub async fn protected<E>(
         self,
         repo: &mut impl RepositoryAccess<Error = E>,
         clock: &impl Clock,
+        dpop_jkt: Option<&str>,
     ) -> Result<Session, AuthorizationVerificationError<E>> {
-        let (token, session) = self.access_token.fetch(repo).await?;
+        let (token, session) = self.access_token.fetch(repo, dpop_jkt).await?;
 
         if !token.is_valid(clock.now()) || !session.is_valid() {
             return Err(AuthorizationVerificationError::InvalidToken);
@@ -294,23 +368,22 @@ where
         req: Request<axum::body::Body>,
         state: &S,
     ) -> Result<Self, Self::Rejection> {
-        let (mut parts, body) = req.into_parts();
-        let header =
-            TypedHeader::<Authorization<Bearer>>::from_request_parts(&mut parts, state).await;
-
-        // Take the Authorization header
-        let token_from_header = match header {
-            Ok(header) => Some(header.token().to_owned()),
-            Err(err) => match err.reason() {
-                // If it's missing it is fine
-                TypedHeaderRejectionReason::Missing => None,
-                // If the header could not be parsed, return the error
-                _ => return Err(UserAuthorizationError::InvalidHeader),
-            },
+        // Take the Authorization header, which can use either the Bearer or the
+        // DPoP scheme
+        let token_from_header = match (
+            req.headers().typed_try_get::<Authorization<Bearer>>(),
+            req.headers().typed_try_get::<Authorization<DPoP>>(),
+        ) {
+            (Ok(Some(Authorization(bearer))), _) => {
+                Some(AccessToken::Header(bearer.token().to_owned()))
+            }
+            (_, Ok(Some(Authorization(dpop)))) => Some(AccessToken::DPoP(dpop.token().to_owned())),
+            // If it's missing it is fine
+            (Ok(None), Ok(None)) => None,
+            // If the header could not be parsed, return the error
+            _ => return Err(UserAuthorizationError::InvalidHeader),
         };
 
-        let req = Request::from_parts(parts, body);
-
         // Take the form value
         let (token_from_form, form) =
             match Form::<AuthorizedForm<F>>::from_request(req, state).await {
@@ -328,7 +401,7 @@ where
         let access_token = match (token_from_header, token_from_form) {
             // Ensure the token should not be in both the form and the access token
             (Some(_), Some(_)) => return Err(UserAuthorizationError::TokenInFormAndHeader),
-            (Some(t), None) => AccessToken::Header(t),
+            (Some(t), None) => t,
             (None, Some(t)) => AccessToken::Form(t),
             (None, None) => AccessToken::None,
         };
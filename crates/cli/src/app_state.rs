@@ -11,8 +11,10 @@ use ipnetwork::IpNetwork;
 use mas_context::LogContext;
 use mas_data_model::SiteConfig;
 use mas_handlers::{
-    ActivityTracker, BoundActivityTracker, CookieManager, ErrorWrapper, GraphQLSchema, Limiter,
-    MetadataCache, RequesterFingerprint, passwords::PasswordManager,
+    ActivityTracker, BoundActivityTracker, ClientJwksCache, CookieManager, ErrorWrapper,
+    FeatureFlags, GraphQLSchema, IntrospectionCache, Limiter, MetadataCache,
+    ProofOfWorkReplayCache, ProviderCircuitBreaker, ProviderHealthTracker, RequesterFingerprint,
+    passwords::PasswordManager,
 };
 use mas_i18n::Translator;
 use mas_keystore::{Encrypter, Keystore};
@@ -20,9 +22,9 @@ use mas_matrix::HomeserverConnection;
 use mas_policy::{Policy, PolicyFactory};
 use mas_router::UrlBuilder;
 use mas_storage::{
-    BoxClock, BoxRepository, BoxRepositoryFactory, BoxRng, RepositoryFactory, SystemClock,
+    ArcRepositoryFactory, BoxClock, BoxRepository, BoxRepositoryFactory, BoxRng, RepositoryFactory,
+    SystemClock,
 };
-use mas_storage_pg::PgRepositoryFactory;
 use mas_templates::Templates;
 use opentelemetry::KeyValue;
 use rand::SeedableRng;
@@ -33,7 +35,7 @@ use crate::telemetry::METER;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub repository_factory: PgRepositoryFactory,
+    pub repository_factory: ArcRepositoryFactory,
     pub templates: Templates,
     pub key_store: Keystore,
     pub cookie_manager: CookieManager,
@@ -45,40 +47,19 @@ pub struct AppState {
     pub http_client: reqwest::Client,
     pub password_manager: PasswordManager,
     pub metadata_cache: MetadataCache,
+    pub provider_health: ProviderHealthTracker,
+    pub circuit_breaker: ProviderCircuitBreaker,
+    pub proof_of_work_replay_cache: ProofOfWorkReplayCache,
+    pub introspection_cache: IntrospectionCache,
+    pub client_jwks_cache: ClientJwksCache,
     pub site_config: SiteConfig,
     pub activity_tracker: ActivityTracker,
     pub trusted_proxies: Vec<IpNetwork>,
     pub limiter: Limiter,
+    pub feature_flags: FeatureFlags,
 }
 
 impl AppState {
-    /// Init the metrics for the app state.
-    pub fn init_metrics(&mut self) {
-        let pool = self.repository_factory.pool();
-        METER
-            .i64_observable_up_down_counter("db.connections.usage")
-            .with_description("The number of connections that are currently in `state` described by the state attribute.")
-            .with_unit("{connection}")
-            .with_callback(move |instrument| {
-                let idle = u32::try_from(pool.num_idle()).unwrap_or(u32::MAX);
-                let used = pool.size() - idle;
-                instrument.observe(i64::from(idle), &[KeyValue::new("state", "idle")]);
-                instrument.observe(i64::from(used), &[KeyValue::new("state", "used")]);
-            })
-            .build();
-
-        let pool = self.repository_factory.pool();
-        METER
-            .i64_observable_up_down_counter("db.connections.max")
-            .with_description("The maximum number of open connections allowed.")
-            .with_unit("{connection}")
-            .with_callback(move |instrument| {
-                let max_conn = pool.options().get_max_connections();
-                instrument.observe(i64::from(max_conn), &[]);
-            })
-            .build();
-    }
-
     /// Init the metadata cache in the background
     pub fn init_metadata_cache(&self) {
         let factory = self.repository_factory.clone();
@@ -99,7 +80,42 @@ impl AppState {
                         }
                     };
 
+                    let interval = metadata_cache.refresh_interval();
                     if let Err(e) = metadata_cache
+                        .warm_up_and_run(&http_client, interval, &mut repo)
+                        .await
+                    {
+                        tracing::error!(
+                            error = &e as &dyn std::error::Error,
+                            "Failed to warm up the metadata cache"
+                        );
+                    }
+                })
+                .instrument(tracing::info_span!("metadata_cache.background_warmup")),
+        );
+    }
+
+    /// Init the cache of client JWKS in the background
+    pub fn init_client_jwks_cache(&self) {
+        let factory = self.repository_factory.clone();
+        let client_jwks_cache = self.client_jwks_cache.clone();
+        let http_client = self.http_client.clone();
+
+        tokio::spawn(
+            LogContext::new("client-jwks-cache-warmup")
+                .run(async move || {
+                    let mut repo = match factory.create().await {
+                        Ok(conn) => conn,
+                        Err(e) => {
+                            tracing::error!(
+                                error = &e as &dyn std::error::Error,
+                                "Failed to acquire a database connection"
+                            );
+                            return;
+                        }
+                    };
+
+                    if let Err(e) = client_jwks_cache
                         .warm_up_and_run(
                             &http_client,
                             std::time::Duration::from_secs(60 * 15),
@@ -109,26 +125,54 @@ impl AppState {
                     {
                         tracing::error!(
                             error = &e as &dyn std::error::Error,
-                            "Failed to warm up the metadata cache"
+                            "Failed to warm up the client JWKS cache"
                         );
                     }
                 })
-                .instrument(tracing::info_span!("metadata_cache.background_warmup")),
+                .instrument(tracing::info_span!("client_jwks_cache.background_warmup")),
         );
     }
-}
 
-// XXX(quenting): we only use this for the healthcheck endpoint, checking the db
-// should be part of the repository
-impl FromRef<AppState> for PgPool {
-    fn from_ref(input: &AppState) -> Self {
-        input.repository_factory.pool()
+    /// Start checking the health of the upstream providers in the background
+    pub fn init_provider_health_checks(&self) {
+        self.provider_health.run(
+            self.http_client.clone(),
+            self.metadata_cache.clone(),
+            Box::new(self.repository_factory.clone()),
+        );
     }
 }
 
+/// Init the metrics of the database connection pool
+pub fn init_database_metrics(pool: &PgPool) {
+    let usage_pool = pool.clone();
+    METER
+        .i64_observable_up_down_counter("db.connections.usage")
+        .with_description("The number of connections that are currently in `state` described by the state attribute.")
+        .with_unit("{connection}")
+        .with_callback(move |instrument| {
+            let idle = u32::try_from(usage_pool.num_idle()).unwrap_or(u32::MAX);
+            let used = usage_pool.size() - idle;
+            instrument.observe(i64::from(idle), &[KeyValue::new("state", "idle")]);
+            instrument.observe(i64::from(used), &[KeyValue::new("state", "used")]);
+        })
+        .build();
+
+    let max_pool = pool.clone();
+    METER
+        .i64_observable_up_down_counter("db.connections.max")
+        .with_description("The maximum number of open connections allowed.")
+        .with_unit("{connection}")
+        .with_callback(move |instrument| {
+            let max_conn = max_pool.options().get_max_connections();
+            instrument.observe(i64::from(max_conn), &[]);
+        })
+        .build();
+}
+
 impl FromRef<AppState> for BoxRepositoryFactory {
     fn from_ref(input: &AppState) -> Self {
-        input.repository_factory.clone().boxed()
+        Box::new(input.repository_factory.clone())
     }
 }
 
@@ -192,6 +236,36 @@ impl FromRef<AppState> for MetadataCache {
     }
 }
 
+impl FromRef<AppState> for ProviderHealthTracker {
+    fn from_ref(input: &AppState) -> Self {
+        input.provider_health.clone()
+    }
+}
+
+impl FromRef<AppState> for ProviderCircuitBreaker {
+    fn from_ref(input: &AppState) -> Self {
+        input.circuit_breaker.clone()
+    }
+}
+
+impl FromRef<AppState> for ProofOfWorkReplayCache {
+    fn from_ref(input: &AppState) -> Self {
+        input.proof_of_work_replay_cache.clone()
+    }
+}
+
+impl FromRef<AppState> for IntrospectionCache {
+    fn from_ref(input: &AppState) -> Self {
+        input.introspection_cache.clone()
+    }
+}
+
+impl FromRef<AppState> for ClientJwksCache {
+    fn from_ref(input: &AppState) -> Self {
+        input.client_jwks_cache.clone()
+    }
+}
+
 impl FromRef<AppState> for SiteConfig {
     fn from_ref(input: &AppState) -> Self {
         input.site_config.clone()
@@ -204,6 +278,12 @@ impl FromRef<AppState> for Limiter {
     }
 }
 
+impl FromRef<AppState> for FeatureFlags {
+    fn from_ref(input: &AppState) -> Self {
+        input.feature_flags.clone()
+    }
+}
+
 impl FromRef<AppState> for Arc<PolicyFactory> {
     fn from_ref(input: &AppState) -> Self {
         input.policy_factory.clone()
@@ -4,13 +4,14 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
-use std::process::ExitCode;
+use std::{collections::BTreeMap, process::ExitCode};
 
 use anyhow::Context;
 use clap::Parser;
 use figment::Figment;
 use mas_config::{ConfigurationSectionExt, DatabaseConfig};
 use mas_storage_pg::MIGRATOR;
+use sqlx::{PgConnection, migrate::Migration};
 use tracing::{Instrument, info_span};
 
 use crate::util::database_connection_from_config;
@@ -24,21 +25,149 @@ pub(super) struct Options {
 #[derive(Parser, Debug)]
 enum Subcommand {
     /// Run database migrations
-    Migrate,
+    Migrate {
+        /// Print the pending migrations and their SQL, without running them
+        #[arg(long, conflicts_with = "status")]
+        dry_run: bool,
+
+        /// Print the state of every migration, without running them
+        #[arg(long)]
+        status: bool,
+    },
+}
+
+/// SQL statements which are considered as potentially destroying data
+const DESTRUCTIVE_STATEMENTS: &[&str] = &[
+    "DROP TABLE",
+    "DROP COLUMN",
+    "DROP SCHEMA",
+    "TRUNCATE",
+    "DELETE FROM",
+];
+
+/// Check whether a migration contains statements which may destroy data
+fn is_destructive(migration: &Migration) -> bool {
+    // Strip the comments and normalise the whitespace before looking for
+    // statements
+    let sql = migration
+        .sql
+        .lines()
+        .map(|line| line.split_once("--").map_or(line, |(code, _)| code))
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase();
+
+    DESTRUCTIVE_STATEMENTS
+        .iter()
+        .any(|statement| sql.contains(statement))
+}
+
+/// Load the checksums of the migrations which were successfully applied,
+/// without creating the migrations table if it doesn't exist
+async fn applied_migrations(conn: &mut PgConnection) -> anyhow::Result<BTreeMap<i64, Vec<u8>>> {
+    let exists: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if !exists {
+        return Ok(BTreeMap::new());
+    }
+
+    let applied: Vec<(i64, Vec<u8>)> =
+        sqlx::query_as("SELECT version, checksum FROM _sqlx_migrations WHERE success")
+            .fetch_all(&mut *conn)
+            .await?;
+
+    Ok(applied.into_iter().collect())
 }
 
 impl Options {
     pub async fn run(self, figment: &Figment) -> anyhow::Result<ExitCode> {
+        let Subcommand::Migrate { dry_run, status } = self.subcommand;
+
         let _span = info_span!("cli.database.migrate").entered();
         let config = DatabaseConfig::extract_or_default(figment)?;
         let mut conn = database_connection_from_config(&config).await?;
 
-        // Run pending migrations
-        MIGRATOR
-            .run(&mut conn)
-            .instrument(info_span!("db.migrate"))
+        if !dry_run && !status {
+            // Run pending migrations
+            MIGRATOR
+                .run(&mut conn)
+                .instrument(info_span!("db.migrate"))
+                .await
+                .context("could not run migrations")?;
+
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        let applied = applied_migrations(&mut conn)
             .await
-            .context("could not run migrations")?;
+            .context("could not load the applied migrations")?;
+
+        let migrations = MIGRATOR
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration());
+
+        if status {
+            let mut pending = 0;
+            for migration in migrations {
+                let state = match applied.get(&migration.version) {
+                    Some(checksum) if checksum[..] == migration.checksum[..] => "applied",
+                    Some(_) => "modified",
+                    None => {
+                        pending += 1;
+                        "pending"
+                    }
+                };
+                let destructive = if is_destructive(migration) {
+                    " (destructive)"
+                } else {
+                    ""
+                };
+                println!(
+                    "{state:<8} {} {}{destructive}",
+                    migration.version, migration.description
+                );
+            }
+
+            // Migrations which were applied by a newer version of MAS
+            for version in applied
+                .keys()
+                .filter(|version| MIGRATOR.iter().all(|m| m.version != **version))
+            {
+                println!("unknown  {version}");
+            }
+
+            println!("\n{pending} pending migration(s)");
+        } else {
+            let pending: Vec<_> = migrations
+                .filter(|migration| !applied.contains_key(&migration.version))
+                .collect();
+
+            if pending.is_empty() {
+                println!("No pending migrations");
+            }
+
+            for migration in &pending {
+                let destructive = if is_destructive(migration) {
+                    " (destructive)"
+                } else {
+                    ""
+                };
+                println!(
+                    "-- {} {}{destructive}",
+                    migration.version, migration.description
+                );
+                println!("{}\n", migration.sql.trim());
+            }
+
+            if pending.iter().any(|migration| is_destructive(migration)) {
+                println!(
+                    "Some of the pending migrations may destroy data, make sure to have a backup of the database"
+                );
+            }
+        }
 
         Ok(ExitCode::SUCCESS)
     }
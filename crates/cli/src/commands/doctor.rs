@@ -40,6 +40,11 @@ impl Options {
         let base_url = config.http.public_base.as_str();
         let issuer = config.http.issuer.as_ref().map(url::Url::as_str);
         let issuer = issuer.unwrap_or(base_url);
+        let url_builder = mas_router::UrlBuilder::new(
+            config.http.public_base.clone(),
+            config.http.issuer.clone(),
+            None,
+        );
         let matrix_domain: Host = Host::parse(&config.matrix.homeserver).context(
             r"The homeserver host in the config (`matrix.homeserver`) is not a valid domain.
 See {DOCS_BASE}/setup/homeserver.html",
@@ -63,7 +68,7 @@ This means some clients will refuse to use it."
             },
             "org.matrix.msc2965.authentication": {
                 "issuer": issuer,
-                "account": format!("{base_url}account/"),
+                "account": url_builder.account_management_uri().as_str(),
             },
         });
 
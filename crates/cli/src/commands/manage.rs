@@ -7,6 +7,7 @@
 use std::{collections::BTreeMap, process::ExitCode};
 
 use anyhow::Context;
+use camino::{Utf8Path, Utf8PathBuf};
 use chrono::Duration;
 use clap::{ArgAction, CommandFactory, Parser};
 use console::{Alignment, Style, Term, pad_str, style};
@@ -14,6 +15,7 @@ use dialoguer::{Confirm, FuzzySelect, Input, Password, theme::ColorfulTheme};
 use figment::Figment;
 use mas_config::{
     ConfigurationSection, ConfigurationSectionExt, DatabaseConfig, MatrixConfig, PasswordsConfig,
+    SecretsConfig, UpstreamOAuth2Config,
 };
 use mas_data_model::{Device, TokenType, Ulid, UpstreamOAuthProvider, User};
 use mas_email::Address;
@@ -37,9 +39,13 @@ use sqlx::{Acquire, types::Uuid};
 use tracing::{error, info, info_span, warn};
 use zeroize::Zeroizing;
 
-use crate::util::{
-    database_connection_from_config, homeserver_connection_from_config,
-    password_manager_from_config,
+use crate::{
+    link_export::{self, LinkExportFormat},
+    subject_migration::{self, SubjectMigrationRules},
+    util::{
+        database_connection_from_config, homeserver_connection_from_config,
+        password_manager_from_config, request_signer_from_config,
+    },
 };
 
 const USER_ATTRIBUTES_HEADING: &str = "User attributes";
@@ -151,6 +157,14 @@ enum Subcommand {
         username: String,
     },
 
+    /// Require a user to change their password the next time they log in
+    ///
+    /// This doesn't end the existing sessions of the user.
+    RequirePasswordChange {
+        /// User who has to change their password
+        username: String,
+    },
+
     /// Register a user
     ///
     /// This will interactively prompt for the user's attributes unless the
@@ -200,6 +214,75 @@ enum Subcommand {
         #[clap(long)]
         ignore_password_complexity: bool,
     },
+
+    /// Export the links between the accounts of an upstream provider and the
+    /// local users
+    ///
+    /// The export is signed with a key derived from the encryption secret. The
+    /// signature is written next to the export, in a file with the `.sig`
+    /// extension.
+    ExportLinks {
+        /// ID of the upstream provider to export the links of
+        #[arg(long)]
+        provider: Ulid,
+
+        /// Format of the export
+        #[arg(long, value_enum, default_value_t)]
+        format: LinkExportFormat,
+
+        /// Path of the file to write the export to
+        #[arg(short, long)]
+        output: Utf8PathBuf,
+    },
+
+    /// Import links previously exported with `export-links` into an upstream
+    /// provider
+    ///
+    /// Subjects which are already linked to another user are left untouched.
+    ImportLinks {
+        /// ID of the upstream provider to import the links into. This can be a
+        /// different provider than the one they were exported from.
+        #[arg(long)]
+        provider: Ulid,
+
+        /// Format of the export
+        #[arg(long, value_enum, default_value_t)]
+        format: LinkExportFormat,
+
+        /// Path of the export to import
+        input: Utf8PathBuf,
+
+        /// Path of the signature of the export. Defaults to the path of the
+        /// export, with the `.sig` extension appended.
+        #[arg(long)]
+        signature: Option<Utf8PathBuf>,
+
+        /// Do a dry run
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Rewrite the subjects of the links of an upstream provider, using the
+    /// `subject_migrations` rules of the provider in the configuration
+    ///
+    /// All the links are migrated in a single transaction: if any of them
+    /// can't be migrated, nothing is changed.
+    MigrateSubjects {
+        /// ID of the upstream provider to migrate the links of
+        #[arg(long)]
+        provider: Ulid,
+
+        /// Do a dry run
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Get the path of the signature of an export
+fn signature_path(export: &Utf8Path) -> Utf8PathBuf {
+    let mut path = export.as_str().to_owned();
+    path.push_str(".sig");
+    path.into()
 }
 
 impl Options {
@@ -333,7 +416,7 @@ impl Options {
 
                 let compat_session = repo
                     .compat_session()
-                    .add(&mut rng, &clock, &user, device, None, admin, None)
+                    .add(&mut rng, &clock, &user, device, None, admin, None, None)
                     .await?;
 
                 let token = TokenType::CompatAccessToken.generate(&mut rng);
@@ -550,6 +633,196 @@ impl Options {
                 Ok(ExitCode::SUCCESS)
             }
 
+            SC::RequirePasswordChange { username } => {
+                let _span = info_span!(
+                    "cli.manage.require_password_change",
+                    user.username = username
+                )
+                .entered();
+                let config = DatabaseConfig::extract_or_default(figment)?;
+                let mut conn = database_connection_from_config(&config).await?;
+                let txn = conn.begin().await?;
+                let mut repo = PgRepository::from_conn(txn);
+
+                let user = repo
+                    .user()
+                    .find_by_username(&username)
+                    .await?
+                    .context("User not found")?;
+
+                let password = repo
+                    .user_password()
+                    .active(&user)
+                    .await?
+                    .context("User has no password")?;
+
+                info!(%user.id, "Requiring the user to change their password");
+                repo.user_password()
+                    .require_change(&clock, password)
+                    .await?;
+
+                repo.into_inner().commit().await?;
+
+                Ok(ExitCode::SUCCESS)
+            }
+
+            SC::ExportLinks {
+                provider,
+                format,
+                output,
+            } => {
+                let _span =
+                    info_span!("cli.manage.export_links", upstream_oauth_provider.id = %provider)
+                        .entered();
+                let database_config = DatabaseConfig::extract_or_default(figment)?;
+                let secrets_config = SecretsConfig::extract(figment)?;
+                let key = secrets_config.encryption().await?;
+
+                let mut conn = database_connection_from_config(&database_config).await?;
+                let txn = conn.begin().await?;
+                let mut repo = PgRepository::from_conn(txn);
+
+                let provider = repo
+                    .upstream_oauth_provider()
+                    .lookup(provider)
+                    .await?
+                    .context("Upstream provider not found")?;
+
+                let records = link_export::export_links(&mut repo, &provider).await?;
+                repo.into_inner().rollback().await?;
+
+                let content = link_export::serialize(format, &records)?;
+                let signature = link_export::sign(&key, &content);
+                let signature_output = signature_path(&output);
+
+                tokio::fs::write(&output, &content).await?;
+                tokio::fs::write(&signature_output, signature).await?;
+
+                info!(
+                    %provider.id,
+                    count = records.len(),
+                    "Exported links to {output} (signature in {signature_output})"
+                );
+
+                Ok(ExitCode::SUCCESS)
+            }
+
+            SC::ImportLinks {
+                provider,
+                format,
+                input,
+                signature,
+                dry_run,
+            } => {
+                let _span =
+                    info_span!("cli.manage.import_links", upstream_oauth_provider.id = %provider)
+                        .entered();
+                let database_config = DatabaseConfig::extract_or_default(figment)?;
+                let secrets_config = SecretsConfig::extract(figment)?;
+                let key = secrets_config.encryption().await?;
+
+                let signature_input = signature.unwrap_or_else(|| signature_path(&input));
+                let content = tokio::fs::read(&input)
+                    .await
+                    .with_context(|| format!("could not read {input}"))?;
+                let signature = tokio::fs::read_to_string(&signature_input)
+                    .await
+                    .with_context(|| format!("could not read {signature_input}"))?;
+                link_export::verify(&key, &content, &signature)?;
+
+                let records = link_export::deserialize(format, &content)?;
+
+                let mut conn = database_connection_from_config(&database_config).await?;
+                let txn = conn.begin().await?;
+                let mut repo = PgRepository::from_conn(txn);
+
+                let provider = repo
+                    .upstream_oauth_provider()
+                    .lookup(provider)
+                    .await?
+                    .context("Upstream provider not found")?;
+
+                let summary =
+                    link_export::import_links(&mut repo, &mut rng, &clock, &provider, records)
+                        .await?;
+
+                info!(
+                    %provider.id,
+                    imported = summary.imported,
+                    unchanged = summary.unchanged,
+                    skipped = summary.skipped,
+                    "Imported links"
+                );
+
+                let txn = repo.into_inner();
+                if dry_run {
+                    info!("Dry run, not saving");
+                    txn.rollback().await?;
+                } else {
+                    txn.commit().await?;
+                }
+
+                Ok(ExitCode::SUCCESS)
+            }
+
+            SC::MigrateSubjects { provider, dry_run } => {
+                let _span = info_span!(
+                    "cli.manage.migrate_subjects",
+                    upstream_oauth_provider.id = %provider
+                )
+                .entered();
+                let database_config = DatabaseConfig::extract_or_default(figment)?;
+                let upstream_oauth2_config = UpstreamOAuth2Config::extract_or_default(figment)?;
+
+                let provider_config = upstream_oauth2_config
+                    .providers
+                    .iter()
+                    .find(|provider_config| provider_config.id == provider)
+                    .context("Upstream provider not found in the configuration")?;
+                if provider_config.subject_migrations.is_empty() {
+                    error!("No `subject_migrations` rules are configured for this provider");
+                    return Ok(ExitCode::from(1));
+                }
+                let rules = SubjectMigrationRules::new(&provider_config.subject_migrations)?;
+
+                let mut conn = database_connection_from_config(&database_config).await?;
+                let txn = conn.begin().await?;
+                let mut repo = PgRepository::from_conn(txn);
+
+                let provider = repo
+                    .upstream_oauth_provider()
+                    .lookup(provider)
+                    .await?
+                    .context("Upstream provider not found")?;
+
+                let summary =
+                    subject_migration::migrate_subjects(&mut repo, &provider, &rules).await?;
+
+                info!(
+                    %provider.id,
+                    migrated = summary.migrated,
+                    unchanged = summary.unchanged,
+                    failed = summary.failed,
+                    "Migrated subjects"
+                );
+
+                let txn = repo.into_inner();
+                if summary.failed > 0 {
+                    error!("Some links can't be migrated, not saving");
+                    txn.rollback().await?;
+                    return Ok(ExitCode::from(1));
+                }
+
+                if dry_run {
+                    info!("Dry run, not saving");
+                    txn.rollback().await?;
+                } else {
+                    txn.commit().await?;
+                }
+
+                Ok(ExitCode::SUCCESS)
+            }
+
             SC::RegisterUser {
                 username,
                 password,
@@ -565,9 +838,13 @@ impl Options {
                 let password_config = PasswordsConfig::extract_or_default(figment)?;
                 let database_config = DatabaseConfig::extract_or_default(figment)?;
                 let matrix_config = MatrixConfig::extract(figment)?;
+                let secrets_config = SecretsConfig::extract(figment)?;
 
                 let password_manager = password_manager_from_config(&password_config).await?;
-                let homeserver = homeserver_connection_from_config(&matrix_config, http_client);
+                let request_signer =
+                    request_signer_from_config(&matrix_config, &secrets_config).await?;
+                let homeserver =
+                    homeserver_connection_from_config(&matrix_config, http_client, request_signer);
                 let mut conn = database_connection_from_config(&database_config).await?;
                 let txn = conn.begin().await?;
                 let mut repo = PgRepository::from_conn(txn);
@@ -14,7 +14,10 @@ use mas_config::{
     AppConfig, ClientsConfig, ConfigurationSection, ConfigurationSectionExt, UpstreamOAuth2Config,
 };
 use mas_context::LogContext;
-use mas_handlers::{ActivityTracker, CookieManager, Limiter, MetadataCache};
+use mas_handlers::{
+    ActivityTracker, ClientJwksCache, CookieManager, IntrospectionCache, Limiter,
+    ProofOfWorkReplayCache,
+};
 use mas_listener::server::Server;
 use mas_router::UrlBuilder;
 use mas_storage::SystemClock;
@@ -23,13 +26,16 @@ use sqlx::migrate::Migrate;
 use tracing::{Instrument, info, info_span, warn};
 
 use crate::{
-    app_state::AppState,
+    app_state::{AppState, init_database_metrics},
     lifecycle::LifecycleManager,
     util::{
-        database_pool_from_config, homeserver_connection_from_config,
-        load_policy_factory_dynamic_data_continuously, mailer_from_config,
-        password_manager_from_config, policy_factory_from_config, site_config_from_config,
-        templates_from_config, test_mailer_in_background,
+        database_pool_from_config, feature_flags_from_config, geoip_resolver_from_config,
+        homeserver_connection_from_config, load_policy_factory_dynamic_data_continuously,
+        mailer_from_config, metadata_cache_from_config, password_manager_from_config,
+        policy_factory_from_config, provider_circuit_breaker_from_config,
+        provider_health_tracker_from_config, repository_factory_from_config,
+        request_signer_from_config, site_config_from_config, sms_sender_from_config,
+        templates_from_config, test_mailer_in_background, test_sms_sender_in_background,
     },
 };
 
@@ -72,6 +78,8 @@ impl Options {
         // Connect to the database
         info!("Connecting to the database");
         let pool = database_pool_from_config(&config.database).await?;
+        init_database_metrics(&pool);
+        let repository_factory = repository_factory_from_config(&config.database, &pool);
 
         if self.no_migrate {
             // Check that we applied all the migrations
@@ -124,8 +132,17 @@ impl Options {
             .await
             .context("could not import keys from config")?;
 
-        let cookie_manager = CookieManager::derive_from(
+        let url_builder = UrlBuilder::new(
             config.http.public_base.clone(),
+            config.http.issuer.clone(),
+            None,
+        )
+        .with_issuer_aliases(config.http.issuer_aliases.clone());
+
+        // Derive the cookie options from the normalized base URL, so that cookies are
+        // scoped to the path MAS is deployed under
+        let cookie_manager = CookieManager::derive_from(
+            url_builder.http_base(),
             &config.secrets.encryption().await?,
         );
 
@@ -136,18 +153,12 @@ impl Options {
 
         load_policy_factory_dynamic_data_continuously(
             &policy_factory,
-            PgRepositoryFactory::new(pool.clone()).boxed(),
+            Box::new(repository_factory.clone()),
             shutdown.soft_shutdown_token(),
             shutdown.task_tracker(),
         )
         .await?;
 
-        let url_builder = UrlBuilder::new(
-            config.http.public_base.clone(),
-            config.http.issuer.clone(),
-            None,
-        );
-
         // Load the site configuration
         let site_config = site_config_from_config(
             &config.branding,
@@ -156,6 +167,10 @@ impl Options {
             &config.passwords,
             &config.account,
             &config.captcha,
+            &config.scim,
+            &config.user_attributes,
+            &config.client_registration,
+            &config.acr,
         )?;
 
         // Load and compile the templates
@@ -165,20 +180,36 @@ impl Options {
 
         let http_client = mas_http::reqwest_client();
 
-        let homeserver_connection =
-            homeserver_connection_from_config(&config.matrix, http_client.clone());
+        let request_signer = request_signer_from_config(&config.matrix, &config.secrets).await?;
+        let homeserver_connection = homeserver_connection_from_config(
+            &config.matrix,
+            http_client.clone(),
+            request_signer.clone(),
+        );
 
         if !self.no_worker {
             let mailer = mailer_from_config(&config.email, &templates)?;
             test_mailer_in_background(&mailer, Duration::from_secs(30));
-
+            let sms_sender = sms_sender_from_config(
+                &config.sms,
+                &templates,
+                &http_client,
+                request_signer.as_ref(),
+            )?;
+            test_sms_sender_in_background(&sms_sender, Duration::from_secs(30));
+
+            // The job queue relies on PostgreSQL features, so the worker always uses
+            // the PostgreSQL pool directly
             info!("Starting task worker");
             mas_tasks::init(
                 PgRepositoryFactory::new(pool.clone()),
                 &mailer,
+                &sms_sender,
                 homeserver_connection.clone(),
                 url_builder.clone(),
                 &site_config,
+                key_store.clone(),
+                http_client.clone(),
                 shutdown.soft_shutdown_token(),
                 shutdown.task_tracker(),
             )
@@ -190,12 +221,36 @@ impl Options {
         let password_manager = password_manager_from_config(&config.passwords).await?;
 
         // The upstream OIDC metadata cache
-        let metadata_cache = MetadataCache::new();
+        let metadata_cache = metadata_cache_from_config(
+            &config.upstream_oauth2.metadata_cache,
+            repository_factory.clone(),
+        );
+
+        // The periodic health checks of the upstream providers
+        let provider_health =
+            provider_health_tracker_from_config(&config.upstream_oauth2.health_checks)?;
+        let provider_health_checks_enabled = config.upstream_oauth2.health_checks.enabled;
+
+        // The circuit breaker around the requests to the upstream providers
+        let circuit_breaker =
+            provider_circuit_breaker_from_config(&config.upstream_oauth2.circuit_breaker);
+
+        // The cache of active token introspection results
+        let introspection_cache =
+            IntrospectionCache::new(config.experimental.introspection_cache_ttl);
+
+        // The JWKS of clients registered with a `jwks_uri`
+        let client_jwks_cache = ClientJwksCache::new();
+
+        // Load the GeoIP databases used to locate the IP of sessions
+        let geoip = geoip_resolver_from_config(&config.geoip).await?;
+        shutdown.register_reloadable(&geoip);
 
         // Initialize the activity tracker
         // Activity is flushed every minute
         let activity_tracker = ActivityTracker::new(
-            PgRepositoryFactory::new(pool.clone()).boxed(),
+            Box::new(repository_factory.clone()),
+            geoip,
             Duration::from_secs(60),
             shutdown.task_tracker(),
             shutdown.soft_shutdown_token(),
@@ -211,44 +266,53 @@ impl Options {
         let limiter = Limiter::new(&config.rate_limiting)
             .context("rate-limiting configuration is not valid")?;
 
+        let feature_flags = feature_flags_from_config(&config.feature_flags)?;
+
         // Explicitly the config to properly zeroize secret keys
         drop(config);
 
         limiter.start();
 
         let graphql_schema = mas_handlers::graphql_schema(
-            PgRepositoryFactory::new(pool.clone()).boxed(),
+            Box::new(repository_factory.clone()),
             &policy_factory,
             homeserver_connection.clone(),
             site_config.clone(),
             password_manager.clone(),
             url_builder.clone(),
             limiter.clone(),
+            encrypter.clone(),
         );
 
-        let state = {
-            let mut s = AppState {
-                repository_factory: PgRepositoryFactory::new(pool),
-                templates,
-                key_store,
-                cookie_manager,
-                encrypter,
-                url_builder,
-                homeserver_connection,
-                policy_factory,
-                graphql_schema,
-                http_client,
-                password_manager,
-                metadata_cache,
-                site_config,
-                activity_tracker,
-                trusted_proxies,
-                limiter,
-            };
-            s.init_metrics();
-            s.init_metadata_cache();
-            s
+        let state = AppState {
+            repository_factory,
+            templates,
+            key_store,
+            cookie_manager,
+            encrypter,
+            url_builder,
+            homeserver_connection,
+            policy_factory,
+            graphql_schema,
+            http_client,
+            password_manager,
+            metadata_cache,
+            provider_health,
+            circuit_breaker,
+            proof_of_work_replay_cache: ProofOfWorkReplayCache::default(),
+            introspection_cache,
+            client_jwks_cache,
+            site_config,
+            activity_tracker,
+            trusted_proxies,
+            limiter,
+            feature_flags,
         };
+        state.init_metadata_cache();
+        state.init_client_jwks_cache();
+        if provider_health_checks_enabled {
+            state.init_provider_health_checks();
+        }
 
         let mut fd_manager = listenfd::ListenFd::from_env();
 
@@ -9,8 +9,9 @@ use std::process::ExitCode;
 use clap::Parser;
 use figment::Figment;
 use mas_config::{
-    AccountConfig, BrandingConfig, CaptchaConfig, ConfigurationSection, ConfigurationSectionExt,
-    ExperimentalConfig, MatrixConfig, PasswordsConfig, TemplatesConfig,
+    AccountConfig, AcrConfig, BrandingConfig, CaptchaConfig, ClientRegistrationConfig,
+    ConfigurationSection, ConfigurationSectionExt, ExperimentalConfig, MatrixConfig,
+    PasswordsConfig, ScimConfig, TemplatesConfig, UserAttributesConfig,
 };
 use mas_storage::{Clock, SystemClock};
 use rand::SeedableRng;
@@ -44,6 +45,11 @@ impl Options {
                 let password_config = PasswordsConfig::extract_or_default(figment)?;
                 let account_config = AccountConfig::extract_or_default(figment)?;
                 let captcha_config = CaptchaConfig::extract_or_default(figment)?;
+                let scim_config = ScimConfig::extract_or_default(figment)?;
+                let user_attributes_config = UserAttributesConfig::extract_or_default(figment)?;
+                let client_registration_config =
+                    ClientRegistrationConfig::extract_or_default(figment)?;
+                let acr_config = AcrConfig::extract_or_default(figment)?;
 
                 let clock = SystemClock::default();
                 // XXX: we should disallow SeedableRng::from_entropy
@@ -57,6 +63,10 @@ impl Options {
                     &password_config,
                     &account_config,
                     &captcha_config,
+                    &scim_config,
+                    &user_attributes_config,
+                    &client_registration_config,
+                    &acr_config,
                 )?;
                 let templates =
                     templates_from_config(&template_config, &site_config, &url_builder).await?;
@@ -6,6 +6,7 @@
 
 use std::{process::ExitCode, time::Duration};
 
+use anyhow::Context;
 use clap::Parser;
 use figment::Figment;
 use mas_config::{AppConfig, ConfigurationSection};
@@ -17,7 +18,8 @@ use crate::{
     lifecycle::LifecycleManager,
     util::{
         database_pool_from_config, homeserver_connection_from_config, mailer_from_config,
-        site_config_from_config, templates_from_config, test_mailer_in_background,
+        request_signer_from_config, site_config_from_config, sms_sender_from_config,
+        templates_from_config, test_mailer_in_background, test_sms_sender_in_background,
     },
 };
 
@@ -48,6 +50,10 @@ impl Options {
             &config.passwords,
             &config.account,
             &config.captcha,
+            &config.scim,
+            &config.user_attributes,
+            &config.client_registration,
+            &config.acr,
         )?;
 
         // Load and compile the templates
@@ -57,8 +63,25 @@ impl Options {
         let mailer = mailer_from_config(&config.email, &templates)?;
         test_mailer_in_background(&mailer, Duration::from_secs(30));
 
+        // Initialize the key store, used to sign back-channel logout tokens
+        let key_store = config
+            .secrets
+            .key_store()
+            .await
+            .context("could not import keys from config")?;
+
         let http_client = mas_http::reqwest_client();
-        let conn = homeserver_connection_from_config(&config.matrix, http_client);
+        let request_signer = request_signer_from_config(&config.matrix, &config.secrets).await?;
+        let sms_sender = sms_sender_from_config(
+            &config.sms,
+            &templates,
+            &http_client,
+            request_signer.as_ref(),
+        )?;
+        test_sms_sender_in_background(&sms_sender, Duration::from_secs(30));
+
+        let conn =
+            homeserver_connection_from_config(&config.matrix, http_client.clone(), request_signer);
 
         drop(config);
 
@@ -66,9 +89,12 @@ impl Options {
         mas_tasks::init(
             PgRepositoryFactory::new(pool.clone()),
             &mailer,
+            &sms_sender,
             conn,
             url_builder,
             &site_config,
+            key_store,
+            http_client,
             shutdown.soft_shutdown_token(),
             shutdown.task_tracker(),
         )
@@ -6,7 +6,7 @@
 use std::{process::ExitCode, time::Duration};
 
 use futures_util::future::{BoxFuture, Either};
-use mas_handlers::ActivityTracker;
+use mas_handlers::{ActivityTracker, GeoIpResolver};
 use mas_templates::Templates;
 use tokio::signal::unix::{Signal, SignalKind};
 use tokio_util::{sync::CancellationToken, task::TaskTracker};
@@ -61,6 +61,17 @@ impl Reloadable for Templates {
     }
 }
 
+impl Reloadable for GeoIpResolver {
+    async fn reload(&self) {
+        if let Err(err) = self.reload().await {
+            tracing::error!(
+                error = &err as &dyn std::error::Error,
+                "Failed to reload the GeoIP databases"
+            );
+        }
+    }
+}
+
 /// A wrapper around [`sd_notify::notify`] that logs any errors
 fn notify(states: &[sd_notify::NotifyState]) {
     if let Err(e) = sd_notify::notify(false, states) {
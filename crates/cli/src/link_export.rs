@@ -0,0 +1,290 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Export and import of the links between upstream accounts and local users.
+//!
+//! This lets operators rebuild the links of a provider after changing its
+//! issuer or client ID. Exports are signed with a key derived from the
+//! encryption secret, so that a tampered file is refused on import.
+
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use mas_data_model::{Ulid, UpstreamOAuthProvider};
+use mas_storage::{Clock, Pagination, RepositoryAccess, upstream_oauth2::UpstreamOAuthLinkFilter};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::{info, warn};
+
+/// Domain separation string mixed in the signature, so that the key can't be
+/// used to forge anything else
+const SIGNATURE_CONTEXT: &[u8] = b"mas-upstream-link-export-v1";
+
+/// The format of an export file
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum LinkExportFormat {
+    /// A JSON array of records
+    #[default]
+    Json,
+
+    /// A CSV file with a header row
+    Csv,
+}
+
+/// A link between an upstream subject and a local user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkRecord {
+    /// The subject of the upstream account
+    pub subject: String,
+
+    /// The ID of the local user
+    pub user_id: Ulid,
+
+    /// The username of the local user, used if the ID can't be found
+    pub username: String,
+
+    /// The human-readable name of the upstream account
+    pub human_account_name: Option<String>,
+}
+
+/// What happened when importing links
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// Links which were created or associated to their user
+    pub imported: usize,
+
+    /// Links which already existed for the same user
+    pub unchanged: usize,
+
+    /// Links which were skipped, because the user is missing or the subject is
+    /// linked to another user
+    pub skipped: usize,
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn mac(key: &[u8; 32], content: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(SIGNATURE_CONTEXT);
+    mac.update(content);
+    mac
+}
+
+/// Compute the hex-encoded signature of an export file
+#[must_use]
+pub fn sign(key: &[u8; 32], content: &[u8]) -> String {
+    hex::encode(mac(key, content).finalize().into_bytes())
+}
+
+/// Check the hex-encoded signature of an export file
+///
+/// # Errors
+///
+/// Returns an error if the signature is malformed or doesn't match
+pub fn verify(key: &[u8; 32], content: &[u8], signature: &str) -> anyhow::Result<()> {
+    let signature = hex::decode(signature.trim()).context("signature is not valid hex")?;
+    mac(key, content)
+        .verify_slice(&signature)
+        .context("signature does not match the file, was it exported with another secret?")
+}
+
+/// Serialize records in the given format
+///
+/// # Errors
+///
+/// Returns an error if the records could not be serialized
+pub fn serialize(format: LinkExportFormat, records: &[LinkRecord]) -> anyhow::Result<Vec<u8>> {
+    match format {
+        LinkExportFormat::Json => Ok(serde_json::to_vec_pretty(records)?),
+        LinkExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for record in records {
+                writer.serialize(record)?;
+            }
+            Ok(writer.into_inner()?)
+        }
+    }
+}
+
+/// Deserialize records from the given format
+///
+/// # Errors
+///
+/// Returns an error if the content is malformed
+pub fn deserialize(format: LinkExportFormat, content: &[u8]) -> anyhow::Result<Vec<LinkRecord>> {
+    match format {
+        LinkExportFormat::Json => Ok(serde_json::from_slice(content)?),
+        LinkExportFormat::Csv => csv::Reader::from_reader(content)
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .context("invalid CSV record"),
+    }
+}
+
+/// Collect the links of a provider which are associated with a user
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub async fn export_links<R: RepositoryAccess + ?Sized>(
+    repo: &mut R,
+    provider: &UpstreamOAuthProvider,
+) -> anyhow::Result<Vec<LinkRecord>> {
+    let mut records = Vec::new();
+    let filter = UpstreamOAuthLinkFilter::new().for_provider(provider);
+
+    let mut cursor = Pagination::first(100);
+    loop {
+        let page = repo.upstream_oauth_link().list(filter, cursor).await?;
+
+        for link in page.edges {
+            cursor = cursor.after(link.id);
+
+            // Links which were never associated with a user carry nothing worth
+            // restoring
+            let Some(user_id) = link.user_id else {
+                continue;
+            };
+
+            let user = repo
+                .user()
+                .lookup(user_id)
+                .await?
+                .context("link references a user which does not exist")?;
+
+            records.push(LinkRecord {
+                subject: link.subject,
+                user_id,
+                username: user.username,
+                human_account_name: link.human_account_name,
+            });
+        }
+
+        if !page.has_next_page {
+            break;
+        }
+    }
+
+    Ok(records)
+}
+
+/// Recreate links on a provider from exported records
+///
+/// Records are matched with users by ID first, then by username. Subjects
+/// which are already linked to another user are left untouched.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub async fn import_links<R: RepositoryAccess + ?Sized>(
+    repo: &mut R,
+    rng: &mut (dyn RngCore + Send),
+    clock: &dyn Clock,
+    provider: &UpstreamOAuthProvider,
+    records: Vec<LinkRecord>,
+) -> anyhow::Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+
+    for record in records {
+        let user = match repo.user().lookup(record.user_id).await? {
+            Some(user) => Some(user),
+            None => repo.user().find_by_username(&record.username).await?,
+        };
+
+        let Some(user) = user else {
+            warn!(
+                subject = record.subject,
+                user.id = %record.user_id,
+                user.username = record.username,
+                "User not found, skipping"
+            );
+            summary.skipped += 1;
+            continue;
+        };
+
+        let existing = repo
+            .upstream_oauth_link()
+            .find_by_subject(provider, &record.subject)
+            .await?;
+
+        let link = match existing {
+            Some(link) if link.user_id == Some(user.id) => {
+                summary.unchanged += 1;
+                continue;
+            }
+
+            Some(link) if link.user_id.is_some() => {
+                warn!(
+                    subject = record.subject,
+                    %user.id,
+                    "Subject is already linked to another user, skipping"
+                );
+                summary.skipped += 1;
+                continue;
+            }
+
+            Some(link) => link,
+
+            None => {
+                repo.upstream_oauth_link()
+                    .add(
+                        rng,
+                        clock,
+                        provider,
+                        record.subject,
+                        record.human_account_name,
+                    )
+                    .await?
+            }
+        };
+
+        repo.upstream_oauth_link()
+            .associate_to_user(&link, &user)
+            .await?;
+
+        info!(%link.id, link.subject, %user.id, %user.username, "Link imported");
+        summary.imported += 1;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_signature() {
+        let key = [0x42; 32];
+        let records = vec![
+            LinkRecord {
+                subject: "alice-subject".to_owned(),
+                user_id: Ulid::from_parts(1, 1),
+                username: "alice".to_owned(),
+                human_account_name: Some("alice@example.com".to_owned()),
+            },
+            LinkRecord {
+                subject: "bob-subject".to_owned(),
+                user_id: Ulid::from_parts(2, 2),
+                username: "bob".to_owned(),
+                human_account_name: None,
+            },
+        ];
+
+        for format in [LinkExportFormat::Json, LinkExportFormat::Csv] {
+            let content = serialize(format, &records).unwrap();
+            assert_eq!(deserialize(format, &content).unwrap(), records);
+
+            let signature = sign(&key, &content);
+            verify(&key, &content, &signature).unwrap();
+
+            // Another key or a modified file is refused
+            verify(&[0x43; 32], &content, &signature).unwrap_err();
+            let mut tampered = content.clone();
+            tampered.push(b'\n');
+            verify(&key, &tampered, &signature).unwrap_err();
+        }
+    }
+}
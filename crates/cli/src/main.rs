@@ -22,7 +22,9 @@ use tracing_subscriber::{
 mod app_state;
 mod commands;
 mod lifecycle;
+mod link_export;
 mod server;
+mod subject_migration;
 mod sync;
 mod telemetry;
 mod util;
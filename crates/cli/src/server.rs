@@ -5,6 +5,7 @@
 // Please see LICENSE files in the repository root for full details.
 
 use std::{
+    collections::BTreeSet,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, ToSocketAddrs},
     os::unix::net::UnixListener,
     time::Duration,
@@ -21,7 +22,7 @@ use listenfd::ListenFd;
 use mas_config::{HttpBindConfig, HttpResource, HttpTlsConfig, UnixOrTcp};
 use mas_context::LogContext;
 use mas_listener::{ConnectionInfo, unix_or_tcp::UnixOrTcpListener};
-use mas_router::Route;
+use mas_router::{Route, UrlBuilder};
 use mas_templates::Templates;
 use mas_tower::{
     DurationRecorderLayer, InFlightCounterLayer, KV, TraceLayer, make_span_fn,
@@ -296,6 +297,40 @@ pub fn build_router(
         router = Router::new().nest(&prefix, router);
     }
 
+    // The OIDC discovery document must be served under the issuer, which can have
+    // a different path than the one the resources are mounted on. In this case,
+    // we also serve the discovery resources under the issuer path, and under the
+    // path of each issuer alias.
+    if resources
+        .iter()
+        .any(|resource| matches!(resource, HttpResource::Discovery))
+    {
+        let url_builder = UrlBuilder::from_ref(&state);
+        let issuer_prefixes: BTreeSet<String> =
+            std::iter::once(url_builder.issuer_prefix().unwrap_or_default())
+                .chain(
+                    url_builder
+                        .issuer_aliases()
+                        .iter()
+                        .map(|alias| alias.path().trim_end_matches('/')),
+                )
+                .map(|issuer_prefix| format!("{issuer_prefix}/"))
+                .collect();
+
+        for issuer_prefix in issuer_prefixes {
+            if issuer_prefix == prefix {
+                continue;
+            }
+
+            let discovery_router = mas_handlers::discovery_router::<AppState>();
+            router = if issuer_prefix == "/" {
+                router.merge(discovery_router)
+            } else {
+                router.nest(&issuer_prefix, discovery_router)
+            };
+        }
+    }
+
     router = router.fallback(mas_handlers::fallback);
 
     router
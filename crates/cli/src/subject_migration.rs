@@ -0,0 +1,185 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Rewriting of the subjects of the links of an upstream provider.
+//!
+//! When a provider changes the format of its subjects, for example after a
+//! migration on its side, the existing links no longer match the users logging
+//! in. The `subject_migrations` rules of the provider describe how to turn the
+//! old subjects into the new ones.
+
+use std::collections::HashSet;
+
+use anyhow::Context;
+use mas_config::UpstreamOAuth2SubjectMigrationRule;
+use mas_data_model::UpstreamOAuthProvider;
+use mas_storage::{Pagination, RepositoryAccess, upstream_oauth2::UpstreamOAuthLinkFilter};
+use regex::Regex;
+use tracing::{info, warn};
+
+/// Compiled subject migration rules
+#[derive(Debug)]
+pub struct SubjectMigrationRules {
+    rules: Vec<(Regex, String)>,
+}
+
+impl SubjectMigrationRules {
+    /// Compile the rules from the configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a pattern is not a valid regular expression
+    pub fn new(rules: &[UpstreamOAuth2SubjectMigrationRule]) -> anyhow::Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                // The pattern has to match the whole subject
+                let regex = Regex::new(&format!("^(?:{})$", rule.pattern))
+                    .with_context(|| format!("invalid pattern {:?}", rule.pattern))?;
+                Ok((regex, rule.template.clone()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Get the new subject for the given subject, using the first rule which
+    /// matches it, or `None` if no rule does
+    #[must_use]
+    pub fn apply(&self, subject: &str) -> Option<String> {
+        self.rules.iter().find_map(|(regex, template)| {
+            let captures = regex.captures(subject)?;
+            let mut new_subject = String::new();
+            captures.expand(template, &mut new_subject);
+            Some(new_subject)
+        })
+    }
+}
+
+/// What happened when migrating subjects
+#[derive(Debug, Default)]
+pub struct MigrationSummary {
+    /// Links whose subject was rewritten
+    pub migrated: usize,
+
+    /// Links which no rule matched, or which keep the same subject
+    pub unchanged: usize,
+
+    /// Links which can't be migrated, because the new subject is empty or
+    /// already used by another link
+    pub failed: usize,
+}
+
+/// Rewrite the subjects of the links of a provider
+///
+/// Each migration is logged. If any link can't be migrated, nothing is
+/// changed, so that the migration can be fixed and run again.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub async fn migrate_subjects<R: RepositoryAccess + ?Sized>(
+    repo: &mut R,
+    provider: &UpstreamOAuthProvider,
+    rules: &SubjectMigrationRules,
+) -> anyhow::Result<MigrationSummary> {
+    let mut links = Vec::new();
+    let filter = UpstreamOAuthLinkFilter::new().for_provider(provider);
+    let mut cursor = Pagination::first(100);
+    loop {
+        let page = repo.upstream_oauth_link().list(filter, cursor).await?;
+        if let Some(last) = page.edges.last() {
+            cursor = cursor.after(last.id);
+        }
+        links.extend(page.edges);
+
+        if !page.has_next_page {
+            break;
+        }
+    }
+
+    let mut summary = MigrationSummary::default();
+    let current_subjects: HashSet<String> = links.iter().map(|link| link.subject.clone()).collect();
+    let mut new_subjects = HashSet::new();
+    let mut migrations = Vec::new();
+
+    for link in links {
+        let Some(new_subject) = rules.apply(&link.subject) else {
+            summary.unchanged += 1;
+            continue;
+        };
+
+        if new_subject == link.subject {
+            summary.unchanged += 1;
+            continue;
+        }
+
+        // Links are migrated one by one, so the new subject must not be in use
+        // at any point, even by a link which is migrated as well
+        if new_subject.is_empty()
+            || current_subjects.contains(&new_subject)
+            || !new_subjects.insert(new_subject.clone())
+        {
+            warn!(
+                %link.id,
+                link.subject,
+                new_subject,
+                "The new subject is empty or already used by another link"
+            );
+            summary.failed += 1;
+            continue;
+        }
+
+        info!(%link.id, link.subject, new_subject, user.id = ?link.user_id, "Migrating subject");
+        migrations.push((link, new_subject));
+    }
+
+    if summary.failed > 0 {
+        return Ok(summary);
+    }
+
+    for (link, new_subject) in migrations {
+        repo.upstream_oauth_link()
+            .set_subject(link, new_subject)
+            .await?;
+        summary.migrated += 1;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_rules() {
+        let rules = SubjectMigrationRules::new(&[
+            UpstreamOAuth2SubjectMigrationRule {
+                pattern: "legacy:(?<id>[0-9]+)".to_owned(),
+                template: "user-${id}".to_owned(),
+            },
+            UpstreamOAuth2SubjectMigrationRule {
+                pattern: "([^@]+)@example\\.com".to_owned(),
+                template: "$1".to_owned(),
+            },
+        ])
+        .unwrap();
+
+        assert_eq!(rules.apply("legacy:42").as_deref(), Some("user-42"));
+        assert_eq!(rules.apply("alice@example.com").as_deref(), Some("alice"));
+
+        // The patterns must match the whole subject
+        assert_eq!(rules.apply("legacy:42a"), None);
+        assert_eq!(rules.apply("alice@example.com.evil"), None);
+        assert_eq!(rules.apply("something-else"), None);
+
+        SubjectMigrationRules::new(&[UpstreamOAuth2SubjectMigrationRule {
+            pattern: "(".to_owned(),
+            template: String::new(),
+        }])
+        .unwrap_err();
+    }
+}
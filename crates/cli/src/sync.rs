@@ -8,15 +8,18 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 
+use anyhow::Context as _;
 use mas_config::{ClientsConfig, UpstreamOAuth2Config};
 use mas_keystore::Encrypter;
 use mas_storage::{
     Clock, Pagination, RepositoryAccess,
+    oauth2::OAuth2StaticClientParams,
     upstream_oauth2::{UpstreamOAuthProviderFilter, UpstreamOAuthProviderParams},
 };
 use mas_storage_pg::PgRepository;
 use sqlx::{Connection, PgConnection, postgres::PgAdvisoryLock};
 use tracing::{error, info, info_span, warn};
+use zeroize::Zeroizing;
 
 fn map_import_action(
     config: mas_config::UpstreamOAuth2ImportAction,
@@ -37,6 +40,60 @@ fn map_import_action(
     }
 }
 
+fn map_sync_mode(
+    sync_mode: mas_config::UpstreamOAuth2SyncMode,
+) -> mas_data_model::UpstreamOAuthProviderSyncMode {
+    match sync_mode {
+        mas_config::UpstreamOAuth2SyncMode::FirstLogin => {
+            mas_data_model::UpstreamOAuthProviderSyncMode::FirstLogin
+        }
+        mas_config::UpstreamOAuth2SyncMode::Always => {
+            mas_data_model::UpstreamOAuthProviderSyncMode::Always
+        }
+    }
+}
+
+fn map_on_conflict(
+    on_conflict: mas_config::UpstreamOAuth2OnConflict,
+) -> mas_data_model::UpstreamOAuthProviderOnConflict {
+    match on_conflict {
+        mas_config::UpstreamOAuth2OnConflict::KeepLocal => {
+            mas_data_model::UpstreamOAuthProviderOnConflict::KeepLocal
+        }
+        mas_config::UpstreamOAuth2OnConflict::Overwrite => {
+            mas_data_model::UpstreamOAuthProviderOnConflict::Overwrite
+        }
+    }
+}
+
+fn map_deprovisioning(
+    config: mas_config::UpstreamOAuth2DeprovisioningConfig,
+) -> mas_data_model::UpstreamOAuthProviderDeprovisioning {
+    let source = match config.source {
+        mas_config::UpstreamOAuth2DeprovisioningSource::Scim => {
+            mas_data_model::UpstreamOAuthProviderDeprovisioningSource::Scim
+        }
+        mas_config::UpstreamOAuth2DeprovisioningSource::RefreshToken => {
+            mas_data_model::UpstreamOAuthProviderDeprovisioningSource::RefreshToken
+        }
+    };
+
+    let action = match config.action {
+        mas_config::UpstreamOAuth2DeprovisioningAction::Lock => {
+            mas_data_model::UpstreamOAuthProviderDeprovisioningAction::Lock
+        }
+        mas_config::UpstreamOAuth2DeprovisioningAction::Deactivate => {
+            mas_data_model::UpstreamOAuthProviderDeprovisioningAction::Deactivate
+        }
+    };
+
+    mas_data_model::UpstreamOAuthProviderDeprovisioning {
+        source,
+        action,
+        dry_run: config.dry_run,
+    }
+}
+
 fn map_claims_imports(
     config: &mas_config::UpstreamOAuth2ClaimsImports,
 ) -> mas_data_model::UpstreamOAuthProviderClaimsImports {
@@ -47,18 +104,38 @@ fn map_claims_imports(
         localpart: mas_data_model::UpstreamOAuthProviderImportPreference {
             action: map_import_action(config.localpart.action),
             template: config.localpart.template.clone(),
+            ..Default::default()
         },
         displayname: mas_data_model::UpstreamOAuthProviderImportPreference {
             action: map_import_action(config.displayname.action),
             template: config.displayname.template.clone(),
+            sync: map_sync_mode(config.displayname.sync),
+            on_conflict: map_on_conflict(config.displayname.on_conflict),
         },
         email: mas_data_model::UpstreamOAuthProviderImportPreference {
             action: map_import_action(config.email.action),
             template: config.email.template.clone(),
+            sync: map_sync_mode(config.email.sync),
+            on_conflict: map_on_conflict(config.email.on_conflict),
+        },
+        avatar: mas_data_model::UpstreamOAuthProviderImportPreference {
+            action: map_import_action(config.avatar.action),
+            template: config.avatar.template.clone(),
+            sync: map_sync_mode(config.avatar.sync),
+            on_conflict: map_on_conflict(config.avatar.on_conflict),
         },
         account_name: mas_data_model::UpstreamOAuthProviderSubjectPreference {
             template: config.account_name.template.clone(),
         },
+        can_request_admin: mas_data_model::UpstreamOAuthProviderRolePreference {
+            expression: config.can_request_admin.expression.clone(),
+            allowed_values: config.can_request_admin.allowed_values.clone(),
+        },
+        email_domains: mas_data_model::UpstreamOAuthProviderEmailDomains {
+            allowed: config.email.allowed_domains.clone(),
+            forbidden: config.email.forbidden_domains.clone(),
+        },
+        avatar_max_size: config.avatar.max_size,
     }
 }
 
@@ -170,6 +247,8 @@ pub async fn config_sync(
                 continue;
             }
 
+            let provider = provider.with_preset();
+
             // Use the position in the config of the provider as position in the UI
             let ui_order = index.try_into().unwrap_or(i32::MAX);
 
@@ -186,9 +265,11 @@ pub async fn config_sync(
                 continue;
             }
 
+            // Provider secrets are envelope-encrypted, so that each of them has
+            // its own data key
             let encrypted_client_secret =
                 if let Some(client_secret) = provider.client_secret.as_deref() {
-                    Some(encrypter.encrypt_to_string(client_secret.as_bytes())?)
+                    Some(encrypter.encrypt_envelope(client_secret.as_bytes())?)
                 } else if let Some(mut siwa) = provider.sign_in_with_apple.clone() {
                     // if private key file is defined and not private key (raw), we populate the
                     // private key to hold the content of the private key file.
@@ -201,11 +282,37 @@ pub async fn config_sync(
                         }
                     }
                     let encoded = serde_json::to_vec(&siwa)?;
-                    Some(encrypter.encrypt_to_string(&encoded)?)
+                    Some(encrypter.encrypt_envelope(&encoded)?)
                 } else {
                     None
                 };
 
+            // The TLS client certificate is stored as PEM, with its key
+            // envelope-encrypted like the other secrets
+            let (tls_client_certificate, encrypted_tls_client_key) =
+                if let Some(tls_config) = &provider.tls_client_certificate {
+                    let (key, certificate_chain) = tls_config.load()?;
+                    let mut certificate_chain_pem = String::new();
+                    for certificate in &certificate_chain {
+                        certificate_chain_pem.push_str(&pem_rfc7468::encode_string(
+                            "CERTIFICATE",
+                            pem_rfc7468::LineEnding::LF,
+                            certificate,
+                        )?);
+                    }
+                    let key_pem = Zeroizing::new(pem_rfc7468::encode_string(
+                        "PRIVATE KEY",
+                        pem_rfc7468::LineEnding::LF,
+                        key.secret_der(),
+                    )?);
+                    (
+                        Some(certificate_chain_pem),
+                        Some(encrypter.encrypt_envelope(key_pem.as_bytes())?),
+                    )
+                } else {
+                    (None, None)
+                };
+
             let discovery_mode = match provider.discovery_mode {
                 mas_config::UpstreamOAuth2DiscoveryMode::Oidc => {
                     mas_data_model::UpstreamOAuthProviderDiscoveryMode::Oidc
@@ -218,7 +325,10 @@ pub async fn config_sync(
                 }
             };
 
-            let token_endpoint_auth_method = match provider.token_endpoint_auth_method {
+            let token_endpoint_auth_method = match provider
+                .token_endpoint_auth_method
+                .context("Missing `token_endpoint_auth_method`")?
+            {
                 mas_config::UpstreamOAuth2TokenAuthMethod::None => {
                     mas_data_model::UpstreamOAuthProviderTokenAuthMethod::None
                 }
@@ -234,6 +344,9 @@ pub async fn config_sync(
                 mas_config::UpstreamOAuth2TokenAuthMethod::PrivateKeyJwt => {
                     mas_data_model::UpstreamOAuthProviderTokenAuthMethod::PrivateKeyJwt
                 }
+                mas_config::UpstreamOAuth2TokenAuthMethod::TlsClientAuth => {
+                    mas_data_model::UpstreamOAuthProviderTokenAuthMethod::TlsClientAuth
+                }
                 mas_config::UpstreamOAuth2TokenAuthMethod::SignInWithApple => {
                     mas_data_model::UpstreamOAuthProviderTokenAuthMethod::SignInWithApple
                 }
@@ -287,9 +400,12 @@ pub async fn config_sync(
                         scope: provider.scope.parse()?,
                         token_endpoint_auth_method,
                         token_endpoint_signing_alg: provider.token_endpoint_auth_signing_alg,
+                        token_endpoint_signing_key_id: provider.token_endpoint_auth_signing_key_id,
                         id_token_signed_response_alg: provider.id_token_signed_response_alg,
                         client_id: provider.client_id,
                         encrypted_client_secret,
+                        tls_client_certificate,
+                        encrypted_tls_client_key,
                         claims_imports: map_claims_imports(&provider.claims_imports),
                         token_endpoint_override: provider.token_endpoint,
                         userinfo_endpoint_override: provider.userinfo_endpoint,
@@ -305,6 +421,11 @@ pub async fn config_sync(
                             .into_iter()
                             .collect(),
                         forward_login_hint: provider.forward_login_hint,
+                        forward_prompt: provider.forward_prompt,
+                        forward_acr_values: provider.forward_acr_values,
+                        forward_ui_locales: provider.forward_ui_locales,
+                        store_tokens: provider.store_tokens,
+                        deprovisioning: provider.deprovisioning.map(map_deprovisioning),
                         ui_order,
                     },
                 )
@@ -362,21 +483,106 @@ pub async fn config_sync(
             let client_auth_method = client.client_auth_method();
             let jwks = client.jwks.as_ref();
             let jwks_uri = client.jwks_uri.as_ref();
+            let service_account_scope = client
+                .service_account
+                .as_ref()
+                .map(|service_account| service_account.allowed_scope.parse())
+                .transpose()?;
+
+            let refresh_token_rotation = match client.refresh_token_rotation {
+                mas_config::RefreshTokenRotationConfig::RotateOnUse => {
+                    mas_data_model::RefreshTokenRotation::RotateOnUse
+                }
+                mas_config::RefreshTokenRotationConfig::Static => {
+                    mas_data_model::RefreshTokenRotation::Static
+                }
+            };
+
+            let access_token_format = match client.access_token_format {
+                mas_config::AccessTokenFormatConfig::Opaque => {
+                    mas_data_model::AccessTokenFormat::Opaque
+                }
+                mas_config::AccessTokenFormatConfig::Jwt => mas_data_model::AccessTokenFormat::Jwt,
+            };
+
+            let (
+                backchannel_logout_uri,
+                backchannel_logout_session_required,
+                backchannel_logout_include_sub,
+            ) = match client.backchannel_logout {
+                Some(config) => (Some(config.uri), config.include_sid, config.include_sub),
+                None => (None, false, true),
+            };
+
+            let resource_server_audience = client.resource_server.map(|config| {
+                config.audience.map_or(
+                    mas_data_model::TokenAudience::Matrix,
+                    mas_data_model::TokenAudience::Resource,
+                )
+            });
+
+            let scope_policy = client
+                .scope_policy
+                .map(|config| mas_data_model::ScopePolicy {
+                    allowed_scopes: config.allowed_scopes,
+                    disallowed: match config.disallowed {
+                        mas_config::DisallowedScopesConfig::Reject => {
+                            mas_data_model::DisallowedScopeHandling::Reject
+                        }
+                        mas_config::DisallowedScopesConfig::Filter => {
+                            mas_data_model::DisallowedScopeHandling::Filter
+                        }
+                    },
+                });
 
             // TODO: should be moved somewhere else
             let encrypted_client_secret = client_secret
                 .map(|client_secret| encrypter.encrypt_to_string(client_secret.as_bytes()))
                 .transpose()?;
+            let encrypted_next_client_secret = client
+                .next_client_secret
+                .as_deref()
+                .map(|client_secret| encrypter.encrypt_to_string(client_secret.as_bytes()))
+                .transpose()?;
 
             repo.oauth2_client()
                 .upsert_static(
                     client.client_id,
-                    client_name.cloned(),
                     client_auth_method,
-                    encrypted_client_secret,
-                    jwks.cloned(),
-                    jwks_uri.cloned(),
-                    client.redirect_uris,
+                    OAuth2StaticClientParams {
+                        client_name: client_name.cloned(),
+                        encrypted_client_secret,
+                        jwks: jwks.cloned(),
+                        jwks_uri: jwks_uri.cloned(),
+                        redirect_uris: client.redirect_uris,
+                        require_pushed_authorization_requests: client
+                            .require_pushed_authorization_requests,
+                        authorization_signed_response_alg: client.authorization_signed_response_alg,
+                        service_account_scope,
+                        backchannel_client_notification_endpoint: client
+                            .backchannel_client_notification_endpoint,
+                        allowed_resources: client.allowed_resources,
+                        refresh_token_rotation,
+                        backchannel_logout_uri,
+                        backchannel_logout_session_required,
+                        backchannel_logout_include_sub,
+                        post_logout_redirect_uris: client.post_logout_redirect_uris,
+                        access_token_format,
+                        access_token_ttl: client.access_token_ttl,
+                        refresh_token_ttl: client.refresh_token_ttl,
+                        device_code_ttl: client.device_code_ttl,
+                        request_object_signing_alg: client.request_object_signing_alg,
+                        require_signed_request_object: client.require_signed_request_object,
+                        id_token_encrypted_response_alg: client.id_token_encrypted_response_alg,
+                        id_token_encrypted_response_enc: client.id_token_encrypted_response_enc,
+                        default_acr_values: client.default_acr_values,
+                        resource_server_audience,
+                        client_secret_expires_at: client.client_secret_expires_at,
+                        encrypted_next_client_secret,
+                        next_client_secret_expires_at: client.next_client_secret_expires_at,
+                        pairwise_sector_identifier: client.pairwise_sector_identifier,
+                        scope_policy,
+                    },
                 )
                 .await?;
         }
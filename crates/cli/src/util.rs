@@ -8,19 +8,37 @@ use std::{sync::Arc, time::Duration};
 
 use anyhow::Context;
 use mas_config::{
-    AccountConfig, BrandingConfig, CaptchaConfig, DatabaseConfig, EmailConfig, EmailSmtpMode,
-    EmailTransportKind, ExperimentalConfig, HomeserverKind, MatrixConfig, PasswordsConfig,
-    PolicyConfig, TemplatesConfig,
+    AccountConfig, AcrConfig, BrandingConfig, CaptchaConfig, ClientRegistrationConfig,
+    DatabaseBackend, DatabaseConfig, EmailConfig, EmailSmtpMode, EmailTransportKind,
+    ExperimentalConfig, FeatureFlagsConfig, GeoIpConfig, HomeserverKind, MatrixConfig,
+    PasswordsConfig, PolicyConfig, ScimConfig, SecretsConfig, SmsConfig, SmsTransportKind,
+    TemplatesConfig, TotpPolicyConfig, UpstreamOAuth2CircuitBreakerConfig,
+    UpstreamOAuth2HealthChecksConfig, UpstreamOAuth2MetadataCacheConfig, UserAttributeType,
+    UserAttributesConfig,
 };
 use mas_context::LogContext;
-use mas_data_model::{SessionExpirationConfig, SiteConfig};
+use mas_data_model::{
+    AcrValue, FeatureFlag, FeatureFlagRollout, HomeRealmDiscoveryConfig, JwksOrJwksUri,
+    PasswordLockoutConfig, ScimClientConfig, SessionExpirationConfig, SiteConfig,
+    SoftwareStatementIssuer, TermsDocument, TermsDocumentKind, TotpPolicy, UserAttributeDefinition,
+    UserAttributeKind, UserInvitesConfig,
+};
 use mas_email::{MailTransport, Mailer};
-use mas_handlers::passwords::PasswordManager;
+use mas_handlers::{
+    CircuitBreakerSettings, FeatureFlags, GeoIpResolver, MetadataCache, MetadataCacheSettings,
+    ProviderCircuitBreaker, ProviderHealthSettings, ProviderHealthTracker,
+    passwords::PasswordManager,
+};
+use mas_http::RequestSigner;
 use mas_matrix::{HomeserverConnection, ReadOnlyHomeserverConnection};
 use mas_matrix_synapse::SynapseConnection;
 use mas_policy::PolicyFactory;
 use mas_router::UrlBuilder;
-use mas_storage::{BoxRepositoryFactory, RepositoryAccess, RepositoryFactory};
+use mas_sms::{SmsSender, SmsTransport};
+use mas_storage::{
+    ArcRepositoryFactory, BoxRepositoryFactory, RepositoryAccess, RepositoryFactory,
+};
+use mas_storage_pg::PgRepositoryFactory;
 use mas_templates::{SiteConfigExt, Templates};
 use sqlx::{
     ConnectOptions, Executor, PgConnection, PgPool,
@@ -49,6 +67,10 @@ pub async fn password_manager_from_config(
                 mas_config::PasswordAlgorithm::Argon2id => {
                     Hasher::argon2id(secret, unicode_normalization)
                 }
+                mas_config::PasswordAlgorithm::DjangoPbkdf2 => {
+                    Hasher::django_pbkdf2(unicode_normalization)
+                }
+                mas_config::PasswordAlgorithm::Ldap => Hasher::ldap(unicode_normalization),
             };
 
             (version, hasher)
@@ -132,6 +154,101 @@ pub fn test_mailer_in_background(mailer: &Mailer, timeout: Duration) {
     );
 }
 
+pub fn sms_sender_from_config(
+    config: &SmsConfig,
+    templates: &Templates,
+    http_client: &reqwest::Client,
+    request_signer: Option<&RequestSigner>,
+) -> Result<SmsSender, anyhow::Error> {
+    // The presence of the required fields should have been checked when
+    // loading the configuration
+    let transport = match config.transport() {
+        SmsTransportKind::Blackhole => SmsTransport::blackhole(),
+        SmsTransportKind::Webhook => {
+            let url = config
+                .url()
+                .context("invalid SMS configuration: missing url")?;
+
+            SmsTransport::webhook(
+                http_client.clone(),
+                url.clone(),
+                config.token().map(ToOwned::to_owned),
+                config.from().map(ToOwned::to_owned),
+                request_signer.cloned(),
+            )
+        }
+        SmsTransportKind::Twilio => {
+            let from = config
+                .from()
+                .context("invalid SMS configuration: missing from")?;
+            let account_sid = config
+                .account_sid()
+                .context("invalid SMS configuration: missing account_sid")?;
+            let auth_token = config
+                .auth_token()
+                .context("invalid SMS configuration: missing auth_token")?;
+
+            SmsTransport::twilio(
+                http_client.clone(),
+                config.url().cloned(),
+                account_sid.to_owned(),
+                auth_token.to_owned(),
+                from.to_owned(),
+            )
+            .context("invalid SMS configuration: invalid Twilio API URL")?
+        }
+        SmsTransportKind::Smpp => {
+            let from = config
+                .from()
+                .context("invalid SMS configuration: missing from")?;
+            let hostname = config
+                .hostname()
+                .context("invalid SMS configuration: missing hostname")?;
+            let system_id = config
+                .system_id()
+                .context("invalid SMS configuration: missing system_id")?;
+            let password = config
+                .password()
+                .context("invalid SMS configuration: missing password")?;
+            let port = config.port().map_or(2775, u16::from);
+
+            SmsTransport::smpp(
+                hostname.to_owned(),
+                port,
+                system_id.to_owned(),
+                password.to_owned(),
+                from.to_owned(),
+            )
+        }
+    };
+
+    Ok(SmsSender::new(templates.clone(), transport))
+}
+
+/// Test the connection to the SMS gateway in a background task
+pub fn test_sms_sender_in_background(sms_sender: &SmsSender, timeout: Duration) {
+    let sms_sender = sms_sender.clone();
+
+    let span = tracing::info_span!("cli.test_sms_sender");
+    tokio::spawn(
+        LogContext::new("sms-test").run(async move || {
+            match tokio::time::timeout(timeout, sms_sender.test_connection()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => {
+                    tracing::warn!(
+                        error = &err as &dyn std::error::Error,
+                        "Could not connect to the SMS gateway, tasks sending text messages may fail!"
+                    );
+                }
+                Err(_) => {
+                    tracing::warn!("Timed out while testing the SMS gateway connection, tasks sending text messages may fail!");
+                }
+            }
+        })
+        .instrument(span)
+    );
+}
+
 pub async fn policy_factory_from_config(
     config: &PolicyConfig,
     matrix_config: &MatrixConfig,
@@ -145,6 +262,9 @@ pub async fn policy_factory_from_config(
         client_registration: config.client_registration_entrypoint.clone(),
         authorization_grant: config.authorization_grant_entrypoint.clone(),
         email: config.email_entrypoint.clone(),
+        login: config.login_entrypoint.clone(),
+        display_name: config.display_name_entrypoint.clone(),
+        upstream_login: config.upstream_login_entrypoint.clone(),
     };
 
     let data =
@@ -168,14 +288,23 @@ pub fn captcha_config_from_config(
             mas_data_model::CaptchaService::CloudflareTurnstile
         }
         mas_config::CaptchaServiceKind::HCaptcha => mas_data_model::CaptchaService::HCaptcha,
+        mas_config::CaptchaServiceKind::ProofOfWork => mas_data_model::CaptchaService::ProofOfWork,
     };
 
-    Ok(Some(mas_data_model::CaptchaConfig {
-        service,
-        site_key: captcha_config
+    // The proof-of-work challenges are issued by MAS itself, so there is no site
+    // key to give to the client
+    let site_key = if matches!(service, mas_data_model::CaptchaService::ProofOfWork) {
+        captcha_config.site_key.clone().unwrap_or_default()
+    } else {
+        captcha_config
             .site_key
             .clone()
-            .context("missing site key")?,
+            .context("missing site key")?
+    };
+
+    Ok(Some(mas_data_model::CaptchaConfig {
+        service,
+        site_key,
         secret_key: captcha_config
             .secret_key
             .clone()
@@ -183,6 +312,7 @@ pub fn captcha_config_from_config(
     }))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn site_config_from_config(
     branding_config: &BrandingConfig,
     matrix_config: &MatrixConfig,
@@ -190,16 +320,41 @@ pub fn site_config_from_config(
     password_config: &PasswordsConfig,
     account_config: &AccountConfig,
     captcha_config: &CaptchaConfig,
+    scim_config: &ScimConfig,
+    user_attributes_config: &UserAttributesConfig,
+    client_registration_config: &ClientRegistrationConfig,
+    acr_config: &AcrConfig,
 ) -> Result<SiteConfig, anyhow::Error> {
     let captcha = captcha_config_from_config(captcha_config)?;
-    let session_expiration = experimental_config
-        .inactive_session_expiration
-        .as_ref()
-        .map(|c| SessionExpirationConfig {
-            oauth_session_inactivity_ttl: c.expire_oauth_sessions.then_some(c.ttl),
-            compat_session_inactivity_ttl: c.expire_compat_sessions.then_some(c.ttl),
-            user_session_inactivity_ttl: c.expire_user_sessions.then_some(c.ttl),
-        });
+    let inactive_session_expiration = experimental_config.inactive_session_expiration.as_ref();
+    // The idle timeout of browser sessions can be set in both sections, in which
+    // case the shortest one applies
+    let user_session_inactivity_ttl = inactive_session_expiration
+        .and_then(|c| c.expire_user_sessions.then_some(c.ttl))
+        .into_iter()
+        .chain(account_config.session_idle_timeout)
+        .min();
+    let session_expiration = SessionExpirationConfig {
+        oauth_session_inactivity_ttl: inactive_session_expiration
+            .and_then(|c| c.expire_oauth_sessions.then_some(c.ttl)),
+        compat_session_inactivity_ttl: inactive_session_expiration
+            .and_then(|c| c.expire_compat_sessions.then_some(c.ttl)),
+        user_session_inactivity_ttl,
+        user_session_max_lifetime: account_config.session_max_lifetime,
+    };
+    let session_expiration = (inactive_session_expiration.is_some()
+        || session_expiration.user_session_inactivity_ttl.is_some()
+        || session_expiration.user_session_max_lifetime.is_some())
+    .then_some(session_expiration);
+
+    // The second factor is only asked after a password login
+    let totp_policy = match account_config.totp {
+        _ if !password_config.enabled() => TotpPolicy::Disabled,
+        TotpPolicyConfig::Disabled => TotpPolicy::Disabled,
+        TotpPolicyConfig::Optional => TotpPolicy::Optional,
+        TotpPolicyConfig::Required => TotpPolicy::Required,
+        TotpPolicyConfig::RequiredForAdmins => TotpPolicy::RequiredForAdmins,
+    };
 
     Ok(SiteConfig {
         access_token_ttl: experimental_config.access_token_ttl,
@@ -214,16 +369,134 @@ pub fn site_config_from_config(
         registration_token_required: account_config.registration_token_required,
         email_change_allowed: account_config.email_change_allowed,
         displayname_change_allowed: account_config.displayname_change_allowed,
+        avatar_change_allowed: account_config.avatar_change_allowed,
         password_change_allowed: password_config.enabled()
             && account_config.password_change_allowed,
         account_recovery_allowed: password_config.enabled()
             && account_config.password_recovery_enabled,
+        account_recovery_requires_approval: account_config.password_recovery_requires_approval,
         account_deactivation_allowed: account_config.account_deactivation_allowed,
+        account_deletion_grace_period: account_config.account_deletion_grace_period,
         captcha,
         minimum_password_complexity: password_config.minimum_complexity(),
+        password_lockout: password_config
+            .lockout
+            .as_ref()
+            .filter(|_| password_config.enabled())
+            .map(|c| PasswordLockoutConfig {
+                max_failures: c.max_failures,
+                max_failures_per_ip: c.max_failures_per_ip,
+                duration: c.duration,
+            }),
+        password_max_age: password_config.max_age,
+        password_history_size: password_config.history_size,
         session_expiration,
         login_with_email_allowed: account_config.login_with_email_allowed,
+        login_with_phone_number_allowed: account_config.login_with_phone_number_allowed,
+        passkeys_enabled: account_config.passkeys_enabled,
+        email_code_login_enabled: account_config.email_code_login_enabled,
+        totp_policy,
+        phone_number_change_allowed: account_config.phone_number_change_allowed,
+        // Like TOTP, the SMS second factor is only asked after a password login
+        sms_second_factor_enabled: password_config.enabled()
+            && account_config.sms_second_factor_enabled,
+        trusted_device_ttl: account_config.trusted_device_ttl,
+        sudo_mode_ttl: account_config.sudo_mode_ttl,
+        user_invites: account_config
+            .user_invites
+            .as_ref()
+            .map(|c| UserInvitesConfig {
+                trusted_attribute: c.trusted_attribute.clone(),
+                usage_limit: c.usage_limit,
+                ttl: c.ttl,
+                max_active: c.max_active,
+                assign_referrer: c.assign_referrer,
+            }),
+        home_realm_discovery: account_config.home_realm_discovery.as_ref().map(|c| {
+            HomeRealmDiscoveryConfig {
+                domains: c
+                    .domains
+                    .iter()
+                    .map(|(domain, provider)| (domain.to_lowercase(), *provider))
+                    .collect(),
+                fallback: c.fallback,
+            }
+        }),
+        single_provider_auto_redirect: account_config.single_provider_auto_redirect,
+        terms_documents: [
+            (
+                TermsDocumentKind::TermsOfService,
+                &account_config.terms_of_service,
+            ),
+            (
+                TermsDocumentKind::PrivacyPolicy,
+                &account_config.privacy_policy,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(kind, document)| {
+            document.as_ref().map(|d| TermsDocument {
+                kind,
+                version: d.version.clone(),
+                url: d.url.clone(),
+            })
+        })
+        .collect(),
+        sign_in_notifications_enabled: account_config.sign_in_notifications_enabled,
         plan_management_iframe_uri: experimental_config.plan_management_iframe_uri.clone(),
+        scim_client: scim_config.client.as_ref().map(|c| ScimClientConfig {
+            endpoint: c.endpoint.clone(),
+            token: c.token.clone(),
+            group: c.group.clone(),
+            admin_group: c.admin_group.clone(),
+            lock_missing_users: c.lock_missing_users,
+        }),
+        user_attributes: user_attributes_config
+            .attributes
+            .iter()
+            .map(|a| UserAttributeDefinition {
+                name: a.name.clone(),
+                kind: match a.kind {
+                    UserAttributeType::String => UserAttributeKind::String,
+                    UserAttributeType::Integer => UserAttributeKind::Integer,
+                    UserAttributeType::Boolean => UserAttributeKind::Boolean,
+                },
+                claim: a.claim_name().map(ToOwned::to_owned),
+            })
+            .collect(),
+        authorization_details_types: experimental_config.authorization_details_types.clone(),
+        software_statement_issuers: client_registration_config
+            .software_statement_issuers
+            .iter()
+            .map(|i| {
+                let jwks = match (&i.jwks, &i.jwks_uri) {
+                    (Some(jwks), _) => JwksOrJwksUri::Jwks(jwks.clone()),
+                    (None, Some(jwks_uri)) => JwksOrJwksUri::JwksUri(jwks_uri.clone()),
+                    (None, None) => {
+                        anyhow::bail!("Software statement issuer {:?} has no keys", i.issuer)
+                    }
+                };
+
+                Ok(SoftwareStatementIssuer {
+                    issuer: i.issuer.clone(),
+                    jwks,
+                })
+            })
+            .collect::<Result<_, _>>()?,
+        software_statement_required: client_registration_config.require_software_statement,
+        acr_values: acr_config
+            .values
+            .iter()
+            .map(|v| AcrValue {
+                value: v.value.clone(),
+                amr: v.amr.iter().map(|amr| amr.as_str().to_owned()).collect(),
+            })
+            .collect(),
+        step_up_scopes: acr_config.step_up_scopes.clone(),
+        step_up_ttl: acr_config.step_up_ttl,
+        device_code_user_code_length: experimental_config.device_code.user_code_length,
+        device_code_user_code_alphabet: experimental_config.device_code.user_code_alphabet.clone(),
+        oauth2_1_strict: experimental_config.oauth2_1_strict,
     })
 }
 
@@ -244,6 +517,79 @@ pub async fn templates_from_config(
     .with_context(|| format!("Failed to load the templates at {}", config.path))
 }
 
+pub async fn geoip_resolver_from_config(
+    config: &GeoIpConfig,
+) -> Result<GeoIpResolver, anyhow::Error> {
+    GeoIpResolver::load(config.country_database.clone(), config.asn_database.clone())
+        .await
+        .context("Failed to load the GeoIP databases")
+}
+
+pub fn feature_flags_from_config(
+    config: &FeatureFlagsConfig,
+) -> Result<FeatureFlags, anyhow::Error> {
+    let defaults = config
+        .flags
+        .iter()
+        .map(|(name, flag)| {
+            let name: FeatureFlag = name
+                .parse()
+                .with_context(|| format!("Invalid feature flag in the configuration: {name}"))?;
+            let rollout = FeatureFlagRollout {
+                enabled: flag.enabled,
+                percentage: flag.rollout_percentage,
+            };
+            Ok((name, rollout))
+        })
+        .collect::<Result<_, anyhow::Error>>()?;
+
+    Ok(FeatureFlags::new(defaults))
+}
+
+pub fn provider_health_tracker_from_config(
+    config: &UpstreamOAuth2HealthChecksConfig,
+) -> Result<ProviderHealthTracker, anyhow::Error> {
+    let interval = config
+        .interval
+        .to_std()
+        .context("Invalid upstream provider health checks interval")?;
+
+    Ok(ProviderHealthTracker::new(ProviderHealthSettings {
+        interval,
+        certificate_expiry_warning: config.certificate_expiry_warning,
+        jwks_max_age: config.jwks_max_age,
+        disable_unhealthy_providers: config.disable_unhealthy_providers,
+    }))
+}
+
+#[must_use]
+pub fn provider_circuit_breaker_from_config(
+    config: &UpstreamOAuth2CircuitBreakerConfig,
+) -> ProviderCircuitBreaker {
+    ProviderCircuitBreaker::new(CircuitBreakerSettings {
+        enabled: config.enabled,
+        failure_threshold: config.failure_threshold,
+        open_duration: config.open_duration,
+    })
+}
+
+pub fn metadata_cache_from_config(
+    config: &UpstreamOAuth2MetadataCacheConfig,
+    repository_factory: ArcRepositoryFactory,
+) -> MetadataCache {
+    let metadata_cache = MetadataCache::new().with_settings(MetadataCacheSettings {
+        discovery_ttl: config.discovery_ttl,
+        jwks_ttl: config.jwks_ttl,
+        max_stale_age: config.max_stale_age,
+    });
+
+    if config.persist {
+        metadata_cache.with_repository_factory(repository_factory)
+    } else {
+        metadata_cache
+    }
+}
+
 fn database_connect_options_from_config(
     config: &DatabaseConfig,
     opts: &DatabaseConnectOptions,
@@ -364,6 +710,20 @@ pub async fn database_pool_from_config(config: &DatabaseConfig) -> Result<PgPool
         .context("could not connect to the database")
 }
 
+/// Create the repository factory for the storage backend selected in the
+/// configuration
+///
+/// The PostgreSQL pool is still needed by the caller for the migrations and
+/// the job queue, which only work with this backend.
+pub fn repository_factory_from_config(
+    config: &DatabaseConfig,
+    pool: &PgPool,
+) -> ArcRepositoryFactory {
+    match config.backend {
+        DatabaseBackend::Postgres => Arc::new(PgRepositoryFactory::new(pool.clone())),
+    }
+}
+
 pub struct DatabaseConnectOptions {
     pub log_slow_statements: bool,
 }
@@ -462,26 +822,52 @@ pub async fn load_policy_factory_dynamic_data(
     Ok(())
 }
 
+/// Create the signer for the requests made to the homeserver and to the SMS
+/// webhook, if request signing is enabled
+pub async fn request_signer_from_config(
+    config: &MatrixConfig,
+    secrets: &SecretsConfig,
+) -> Result<Option<RequestSigner>, anyhow::Error> {
+    let Some(alg) = config.request_signing_alg.clone() else {
+        return Ok(None);
+    };
+
+    let key_store = secrets
+        .key_store()
+        .await
+        .context("could not import keys from config")?;
+
+    // This rng is used to seed the signer's own rng
+    #[allow(clippy::disallowed_methods)]
+    let mut rng = rand::thread_rng();
+
+    let signer = RequestSigner::new(key_store, alg, &mut rng)
+        .context("could not set up the signing of outgoing requests")?;
+
+    Ok(Some(signer))
+}
+
 /// Create a clonable, type-erased [`HomeserverConnection`] from the
 /// configuration
 pub fn homeserver_connection_from_config(
     config: &MatrixConfig,
     http_client: reqwest::Client,
+    request_signer: Option<RequestSigner>,
 ) -> Arc<dyn HomeserverConnection> {
+    let mut connection = SynapseConnection::new(
+        config.homeserver.clone(),
+        config.endpoint.clone(),
+        config.secret.clone(),
+        http_client,
+    );
+
+    if let Some(signer) = request_signer {
+        connection = connection.with_request_signer(signer);
+    }
+
     match config.kind {
-        HomeserverKind::Synapse => Arc::new(SynapseConnection::new(
-            config.homeserver.clone(),
-            config.endpoint.clone(),
-            config.secret.clone(),
-            http_client,
-        )),
+        HomeserverKind::Synapse => Arc::new(connection),
         HomeserverKind::SynapseReadOnly => {
-            let connection = SynapseConnection::new(
-                config.homeserver.clone(),
-                config.endpoint.clone(),
-                config.secret.clone(),
-                http_client,
-            );
             let readonly = ReadOnlyHomeserverConnection::new(connection);
             Arc::new(readonly)
         }
@@ -558,5 +944,38 @@ mod tests {
         .unwrap();
         let manager = password_manager_from_config(&config).await;
         assert!(manager.is_err());
+
+        // Schemes only verifying imported passwords can't be used to hash new ones
+        let config = serde_json::from_value(serde_json::json!({
+            "schemes": [{
+                "version": 2,
+                "algorithm": "ldap"
+            }, {
+                "version": 1,
+                "algorithm": "argon2id"
+            }]
+        }))
+        .unwrap();
+        let manager = password_manager_from_config(&config).await;
+        assert!(manager.is_err());
+
+        // But they can be used to verify older passwords
+        let config = serde_json::from_value(serde_json::json!({
+            "schemes": [{
+                "version": 2,
+                "algorithm": "argon2id"
+            }, {
+                "version": 1,
+                "algorithm": "django_pbkdf2"
+            }]
+        }))
+        .unwrap();
+        let manager = password_manager_from_config(&config).await.unwrap();
+        let hash = "pbkdf2_sha256$1000$Kq6ZmYbQ9kP2$p9OHR2OeuazPI07JSqb7jkcVlHebRhUr00sTmhRkV7o=";
+        let upgraded = manager
+            .verify_and_upgrade(&mut rng, 1, password.clone(), hash.to_owned())
+            .await
+            .unwrap();
+        assert!(matches!(upgraded, Some((2, _))));
     }
 }
@@ -0,0 +1,58 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! The error types used in this crate.
+
+use std::fmt;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+/// All possible errors when calling the API.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The request failed, or the server returned an HTTP error status code.
+    #[error("Request to the GraphQL API failed")]
+    Http(#[from] reqwest::Error),
+
+    /// The server returned GraphQL errors.
+    #[error("The GraphQL API returned errors: {}", DisplayErrors(.0))]
+    GraphQL(Vec<GraphQLError>),
+
+    /// The response didn't contain any data.
+    #[error("The GraphQL API returned no data")]
+    MissingData,
+
+    /// The access token is not bound to a user.
+    #[error("The access token is not bound to a user")]
+    Anonymous,
+}
+
+/// An error returned by the GraphQL API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GraphQLError {
+    /// A description of the error.
+    pub message: String,
+}
+
+impl fmt::Display for GraphQLError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+struct DisplayErrors<'a>(&'a [GraphQLError]);
+
+impl fmt::Display for DisplayErrors<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            error.fmt(f)?;
+        }
+        Ok(())
+    }
+}
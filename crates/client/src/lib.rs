@@ -0,0 +1,441 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! A typed client for the account management APIs of the [Matrix
+//! Authentication Service].
+//!
+//! It wraps the GraphQL API used by the account management interface, so that
+//! Rust-based Matrix clients and tests can manage the account of a user
+//! without writing GraphQL documents by hand.
+//!
+//! The access token given to the [`Client`] must have the
+//! `urn:mas:graphql:*` scope.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use mas_client::Client;
+//! use url::Url;
+//!
+//! let base = Url::parse("https://auth.example.com/")?;
+//! let client = Client::new(reqwest::Client::new(), &base, "access_token")?;
+//!
+//! let user = client.viewer().await?;
+//! println!("Logged in as {}", user.username);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [Matrix Authentication Service]: https://github.com/element-hq/matrix-authentication-service
+
+#![deny(missing_docs)]
+
+pub mod error;
+pub mod types;
+
+use mas_http::RequestBuilderExt as _;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::json;
+use url::Url;
+
+use self::{
+    error::{Error, GraphQLError},
+    types::{
+        CompleteEmailAuthenticationStatus, EndSessionStatus, Page, RemoveEmailStatus,
+        SetDisplayNameStatus, SetPasswordStatus, StartEmailAuthentication, User, UserEmail,
+    },
+};
+
+/// A client for the account management APIs, acting on behalf of the user
+/// bound to an access token.
+#[derive(Clone)]
+pub struct Client {
+    http_client: reqwest::Client,
+    endpoint: Url,
+    access_token: String,
+}
+
+#[derive(Serialize)]
+struct Request<'a, V> {
+    query: &'static str,
+    #[serde(rename = "operationName")]
+    operation_name: &'a str,
+    variables: V,
+}
+
+#[derive(Deserialize)]
+struct Response<D> {
+    data: Option<D>,
+    #[serde(default)]
+    errors: Vec<GraphQLError>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "__typename")]
+enum Viewer<T> {
+    User(T),
+    Anonymous,
+}
+
+impl<T> Viewer<T> {
+    fn user(self) -> Result<T, Error> {
+        match self {
+            Self::User(user) => Ok(user),
+            Self::Anonymous => Err(Error::Anonymous),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ViewerData<T> {
+    viewer: Viewer<T>,
+}
+
+#[derive(Deserialize)]
+struct UserEmails {
+    emails: Connection<UserEmail>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Connection<T> {
+    total_count: usize,
+    page_info: PageInfo,
+    nodes: Vec<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+impl<T> From<Connection<T>> for Page<T> {
+    fn from(connection: Connection<T>) -> Self {
+        let next_cursor = if connection.page_info.has_next_page {
+            connection.page_info.end_cursor
+        } else {
+            None
+        };
+
+        Self {
+            items: connection.nodes,
+            total_count: connection.total_count,
+            next_cursor,
+        }
+    }
+}
+
+/// The payload of mutations which only return a status
+#[derive(Deserialize)]
+struct StatusPayload<S> {
+    status: S,
+}
+
+/// Mutation responses are keyed by the name of the mutation field
+#[derive(Deserialize)]
+struct MutationData<T> {
+    #[serde(
+        alias = "startEmailAuthentication",
+        alias = "completeEmailAuthentication",
+        alias = "removeEmail",
+        alias = "setDisplayName",
+        alias = "setPassword",
+        alias = "endBrowserSession",
+        alias = "endOauth2Session",
+        alias = "endCompatSession"
+    )]
+    payload: T,
+}
+
+impl Client {
+    /// Create a new client
+    ///
+    /// # Parameters
+    ///
+    /// * `http_client`: The HTTP client to use to make requests
+    /// * `base`: The base URL of the service, e.g. `https://auth.example.com/`
+    /// * `access_token`: The access token of the user
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL of the GraphQL endpoint could not be built
+    /// from the base URL
+    pub fn new(
+        http_client: reqwest::Client,
+        base: &Url,
+        access_token: impl Into<String>,
+    ) -> Result<Self, url::ParseError> {
+        Ok(Self {
+            http_client,
+            endpoint: base.join("graphql")?,
+            access_token: access_token.into(),
+        })
+    }
+
+    async fn request<V: Serialize, D: DeserializeOwned>(
+        &self,
+        query: &'static str,
+        operation_name: &str,
+        variables: V,
+    ) -> Result<D, Error> {
+        let request = Request {
+            query,
+            operation_name,
+            variables,
+        };
+
+        let response: Response<D> = self
+            .http_client
+            .post(self.endpoint.as_str())
+            .bearer_auth(&self.access_token)
+            .json(&request)
+            .send_traced()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if !response.errors.is_empty() {
+            return Err(Error::GraphQL(response.errors));
+        }
+
+        response.data.ok_or(Error::MissingData)
+    }
+
+    async fn mutation<V: Serialize, S: DeserializeOwned>(
+        &self,
+        query: &'static str,
+        operation_name: &str,
+        variables: V,
+    ) -> Result<S, Error> {
+        let data: MutationData<StatusPayload<S>> =
+            self.request(query, operation_name, variables).await?;
+        Ok(data.payload.status)
+    }
+
+    /// Get the user bound to the access token
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, or if the access token is not
+    /// bound to a user
+    #[tracing::instrument(name = "mas_client.viewer", skip_all)]
+    pub async fn viewer(&self) -> Result<User, Error> {
+        let data: ViewerData<User> = self
+            .request(
+                include_str!("operations/viewer.graphql"),
+                "Viewer",
+                json!({}),
+            )
+            .await?;
+        data.viewer.user()
+    }
+
+    /// List the email addresses of the user, ordered by creation date
+    ///
+    /// # Parameters
+    ///
+    /// * `first`: The maximum number of email addresses to return
+    /// * `after`: The cursor returned in the previous page, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, or if the access token is not
+    /// bound to a user
+    #[tracing::instrument(name = "mas_client.emails", skip_all)]
+    pub async fn emails(&self, first: u32, after: Option<&str>) -> Result<Page<UserEmail>, Error> {
+        let data: ViewerData<UserEmails> = self
+            .request(
+                include_str!("operations/viewer_emails.graphql"),
+                "ViewerEmails",
+                json!({ "first": first, "after": after }),
+            )
+            .await?;
+        Ok(data.viewer.user()?.emails.into())
+    }
+
+    /// Start adding an email address to the account
+    ///
+    /// This sends a code to the email address, which must then be passed to
+    /// [`Client::complete_email_authentication`].
+    ///
+    /// # Parameters
+    ///
+    /// * `email`: The email address to add
+    /// * `password`: The current password of the user, required if they have
+    ///   one
+    /// * `language`: The language to use for the email, e.g. `en`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails
+    #[tracing::instrument(name = "mas_client.start_email_authentication", skip_all)]
+    pub async fn start_email_authentication(
+        &self,
+        email: &str,
+        password: Option<&str>,
+        language: &str,
+    ) -> Result<StartEmailAuthentication, Error> {
+        let data: MutationData<StartEmailAuthentication> = self
+            .request(
+                include_str!("operations/start_email_authentication.graphql"),
+                "StartEmailAuthentication",
+                json!({ "email": email, "password": password, "language": language }),
+            )
+            .await?;
+        Ok(data.payload)
+    }
+
+    /// Complete adding an email address to the account, with the code which
+    /// was sent to it
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the email authentication session
+    /// * `code`: The code which was sent to the email address
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails
+    #[tracing::instrument(name = "mas_client.complete_email_authentication", skip_all)]
+    pub async fn complete_email_authentication(
+        &self,
+        id: &str,
+        code: &str,
+    ) -> Result<CompleteEmailAuthenticationStatus, Error> {
+        self.mutation(
+            include_str!("operations/complete_email_authentication.graphql"),
+            "CompleteEmailAuthentication",
+            json!({ "id": id, "code": code }),
+        )
+        .await
+    }
+
+    /// Remove an email address from the account
+    ///
+    /// # Parameters
+    ///
+    /// * `user_email_id`: The ID of the email address to remove
+    /// * `password`: The current password of the user, required if they have
+    ///   one
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails
+    #[tracing::instrument(name = "mas_client.remove_email", skip_all)]
+    pub async fn remove_email(
+        &self,
+        user_email_id: &str,
+        password: Option<&str>,
+    ) -> Result<RemoveEmailStatus, Error> {
+        self.mutation(
+            include_str!("operations/remove_email.graphql"),
+            "RemoveEmail",
+            json!({ "userEmailId": user_email_id, "password": password }),
+        )
+        .await
+    }
+
+    /// Set the display name of a user
+    ///
+    /// # Parameters
+    ///
+    /// * `user_id`: The ID of the user, as returned by [`Client::viewer`]
+    /// * `display_name`: The new display name, or `None` to remove it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails
+    #[tracing::instrument(name = "mas_client.set_display_name", skip_all)]
+    pub async fn set_display_name(
+        &self,
+        user_id: &str,
+        display_name: Option<&str>,
+    ) -> Result<SetDisplayNameStatus, Error> {
+        self.mutation(
+            include_str!("operations/set_display_name.graphql"),
+            "SetDisplayName",
+            json!({ "userId": user_id, "displayName": display_name }),
+        )
+        .await
+    }
+
+    /// Change the password of a user
+    ///
+    /// # Parameters
+    ///
+    /// * `user_id`: The ID of the user, as returned by [`Client::viewer`]
+    /// * `current_password`: The current password of the user, required if the
+    ///   access token is not an admin one
+    /// * `new_password`: The new password
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails
+    #[tracing::instrument(name = "mas_client.set_password", skip_all)]
+    pub async fn set_password(
+        &self,
+        user_id: &str,
+        current_password: Option<&str>,
+        new_password: &str,
+    ) -> Result<SetPasswordStatus, Error> {
+        self.mutation(
+            include_str!("operations/set_password.graphql"),
+            "SetPassword",
+            json!({
+                "userId": user_id,
+                "currentPassword": current_password,
+                "newPassword": new_password,
+            }),
+        )
+        .await
+    }
+
+    /// End a browser session of the user
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails
+    #[tracing::instrument(name = "mas_client.end_browser_session", skip_all)]
+    pub async fn end_browser_session(&self, id: &str) -> Result<EndSessionStatus, Error> {
+        self.mutation(
+            include_str!("operations/end_browser_session.graphql"),
+            "EndBrowserSession",
+            json!({ "id": id }),
+        )
+        .await
+    }
+
+    /// End an OAuth 2.0 session of the user
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails
+    #[tracing::instrument(name = "mas_client.end_oauth2_session", skip_all)]
+    pub async fn end_oauth2_session(&self, id: &str) -> Result<EndSessionStatus, Error> {
+        self.mutation(
+            include_str!("operations/end_oauth2_session.graphql"),
+            "EndOAuth2Session",
+            json!({ "id": id }),
+        )
+        .await
+    }
+
+    /// End a compatibility session of the user
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails
+    #[tracing::instrument(name = "mas_client.end_compat_session", skip_all)]
+    pub async fn end_compat_session(&self, id: &str) -> Result<EndSessionStatus, Error> {
+        self.mutation(
+            include_str!("operations/end_compat_session.graphql"),
+            "EndCompatSession",
+            json!({ "id": id }),
+        )
+        .await
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Types returned by the API.
+//!
+//! IDs are opaque strings, which should be passed back as-is to the API.
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+/// The user bound to the access token.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    /// The ID of the user.
+    pub id: String,
+
+    /// The username of the user.
+    pub username: String,
+
+    /// When the user was created.
+    pub created_at: DateTime<Utc>,
+
+    /// When the user was locked out, if they are.
+    pub locked_at: Option<DateTime<Utc>>,
+
+    /// Whether the user can request admin privileges.
+    pub can_request_admin: bool,
+
+    /// Whether the user has a password set.
+    pub has_password: bool,
+
+    /// The Matrix account of the user.
+    pub matrix: MatrixUser,
+}
+
+/// The Matrix account of a user.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatrixUser {
+    /// The Matrix ID of the user.
+    pub mxid: String,
+
+    /// The display name of the user, if any.
+    pub display_name: Option<String>,
+
+    /// The avatar URL of the user, if any.
+    pub avatar_url: Option<String>,
+
+    /// Whether the user is deactivated on the homeserver.
+    pub deactivated: bool,
+}
+
+/// An email address of a user.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEmail {
+    /// The ID of the email address.
+    pub id: String,
+
+    /// The email address.
+    pub email: String,
+
+    /// When the email address was added.
+    pub created_at: DateTime<Utc>,
+}
+
+/// An email authentication session, used to verify an email address before
+/// adding it to the account.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEmailAuthentication {
+    /// The ID of the authentication session.
+    pub id: String,
+
+    /// The email address being verified.
+    pub email: String,
+
+    /// When the authentication session was started.
+    pub created_at: DateTime<Utc>,
+
+    /// When the authentication session was completed, if it was.
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// A page of items.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    /// The items in this page.
+    pub items: Vec<T>,
+
+    /// The total number of items.
+    pub total_count: usize,
+
+    /// The cursor to pass to get the next page, if there is one.
+    pub next_cursor: Option<String>,
+}
+
+/// The outcome of starting an email authentication.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StartEmailAuthentication {
+    /// The status of the operation.
+    pub status: StartEmailAuthenticationStatus,
+
+    /// The email authentication session that was started.
+    pub authentication: Option<UserEmailAuthentication>,
+
+    /// The list of policy violations if the email address was denied.
+    pub violations: Option<Vec<String>>,
+}
+
+/// The status of starting an email authentication.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StartEmailAuthenticationStatus {
+    /// The email authentication was started.
+    Started,
+
+    /// The email address is invalid.
+    InvalidEmailAddress,
+
+    /// Too many attempts to start an email authentication.
+    RateLimited,
+
+    /// The email address isn't allowed by the policy.
+    Denied,
+
+    /// The email address is already in use on this account.
+    InUse,
+
+    /// The password provided is incorrect.
+    IncorrectPassword,
+}
+
+/// The status of completing an email authentication.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CompleteEmailAuthenticationStatus {
+    /// The authentication was completed, and the email address added.
+    Completed,
+
+    /// The authentication code is invalid.
+    InvalidCode,
+
+    /// The authentication code has expired.
+    CodeExpired,
+
+    /// Too many attempts to complete an email authentication.
+    RateLimited,
+
+    /// The email address is already in use.
+    InUse,
+}
+
+/// The status of removing an email address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RemoveEmailStatus {
+    /// The email address was removed.
+    Removed,
+
+    /// The email address was not found.
+    NotFound,
+
+    /// The password provided is incorrect.
+    IncorrectPassword,
+}
+
+/// The status of setting the display name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SetDisplayNameStatus {
+    /// The display name was set.
+    Set,
+
+    /// The display name is invalid.
+    Invalid,
+}
+
+/// The status of setting the password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SetPasswordStatus {
+    /// The password was updated.
+    Allowed,
+
+    /// The user was not found.
+    NotFound,
+
+    /// The user doesn't have a current password to match against.
+    NoCurrentPassword,
+
+    /// The current password was wrong.
+    WrongPassword,
+
+    /// The new password doesn't meet the security requirements.
+    InvalidNewPassword,
+
+    /// Setting the password of this user is not allowed.
+    NotAllowed,
+
+    /// Password changes are disabled on this server.
+    PasswordChangesDisabled,
+
+    /// The recovery ticket does not exist.
+    NoSuchRecoveryTicket,
+
+    /// The recovery ticket was already used.
+    RecoveryTicketAlreadyUsed,
+
+    /// The recovery ticket has expired.
+    ExpiredRecoveryTicket,
+
+    /// The account is locked.
+    AccountLocked,
+}
+
+/// The status of ending a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EndSessionStatus {
+    /// The session was ended.
+    Ended,
+
+    /// The session was not found.
+    NotFound,
+}
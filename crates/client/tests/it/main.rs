@@ -0,0 +1,210 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use mas_client::{
+    Client,
+    error::Error,
+    types::{EndSessionStatus, SetPasswordStatus},
+};
+use serde_json::json;
+use url::Url;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, header, method, path},
+};
+
+const ACCESS_TOKEN: &str = "AccessToken1";
+
+async fn init_test() -> (Client, MockServer) {
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+
+    let mock_server = MockServer::start().await;
+    let base = Url::parse(&mock_server.uri()).expect("Couldn't parse URL");
+    let client = Client::new(mas_http::reqwest_client(), &base, ACCESS_TOKEN).unwrap();
+
+    (client, mock_server)
+}
+
+#[tokio::test]
+async fn viewer() {
+    let (client, mock_server) = init_test().await;
+
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(header("authorization", format!("Bearer {ACCESS_TOKEN}")))
+        .and(body_partial_json(json!({ "operationName": "Viewer" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": {
+                "viewer": {
+                    "__typename": "User",
+                    "id": "user:01FSHN9AG0MZAA6S4AF7CTV32E",
+                    "username": "alice",
+                    "createdAt": "2022-01-16T14:40:00Z",
+                    "lockedAt": null,
+                    "canRequestAdmin": false,
+                    "hasPassword": true,
+                    "matrix": {
+                        "mxid": "@alice:example.com",
+                        "displayName": "Alice",
+                        "avatarUrl": null,
+                        "deactivated": false,
+                    },
+                },
+            },
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let user = client.viewer().await.unwrap();
+    assert_eq!(user.id, "user:01FSHN9AG0MZAA6S4AF7CTV32E");
+    assert_eq!(user.username, "alice");
+    assert!(user.has_password);
+    assert_eq!(user.matrix.mxid, "@alice:example.com");
+    assert_eq!(user.matrix.display_name.as_deref(), Some("Alice"));
+}
+
+#[tokio::test]
+async fn anonymous_viewer() {
+    let (client, mock_server) = init_test().await;
+
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": {
+                "viewer": {
+                    "__typename": "Anonymous",
+                },
+            },
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let error = client.viewer().await.unwrap_err();
+    assert!(matches!(error, Error::Anonymous));
+}
+
+#[tokio::test]
+async fn emails_pagination() {
+    let (client, mock_server) = init_test().await;
+
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_partial_json(json!({
+            "operationName": "ViewerEmails",
+            "variables": { "first": 1, "after": null },
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": {
+                "viewer": {
+                    "__typename": "User",
+                    "emails": {
+                        "totalCount": 2,
+                        "pageInfo": {
+                            "hasNextPage": true,
+                            "endCursor": "cursor1",
+                        },
+                        "nodes": [{
+                            "id": "user_email:01FSHN9AG0AJ6AC5HQ9X6H4RP4",
+                            "email": "alice@example.com",
+                            "createdAt": "2022-01-16T14:40:00Z",
+                        }],
+                    },
+                },
+            },
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let page = client.emails(1, None).await.unwrap();
+    assert_eq!(page.total_count, 2);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].email, "alice@example.com");
+    assert_eq!(page.next_cursor.as_deref(), Some("cursor1"));
+}
+
+#[tokio::test]
+async fn mutation_status() {
+    let (client, mock_server) = init_test().await;
+
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_partial_json(json!({
+            "operationName": "SetPassword",
+            "variables": {
+                "userId": "user:01FSHN9AG0MZAA6S4AF7CTV32E",
+                "currentPassword": "hunter2",
+                "newPassword": "correct horse battery staple",
+            },
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": {
+                "setPassword": {
+                    "status": "WRONG_PASSWORD",
+                },
+            },
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_partial_json(
+            json!({ "operationName": "EndOAuth2Session" }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": {
+                "endOauth2Session": {
+                    "status": "ENDED",
+                },
+            },
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let status = client
+        .set_password(
+            "user:01FSHN9AG0MZAA6S4AF7CTV32E",
+            Some("hunter2"),
+            "correct horse battery staple",
+        )
+        .await
+        .unwrap();
+    assert_eq!(status, SetPasswordStatus::WrongPassword);
+
+    let status = client
+        .end_oauth2_session("oauth2_session:01FSHN9AG0AJ6AC5HQ9X6H4RP4")
+        .await
+        .unwrap();
+    assert_eq!(status, EndSessionStatus::Ended);
+}
+
+#[tokio::test]
+async fn graphql_errors() {
+    let (client, mock_server) = init_test().await;
+
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": null,
+            "errors": [{
+                "message": "Invalid token",
+                "locations": [],
+                "path": [],
+            }],
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let error = client.viewer().await.unwrap_err();
+    let Error::GraphQL(errors) = error else {
+        panic!("Expected a GraphQL error, got {error:?}");
+    };
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Invalid token");
+}
@@ -4,8 +4,14 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::collections::BTreeMap;
+
+use chrono::Duration;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use ulid::Ulid;
+use url::Url;
 
 use crate::ConfigurationSection;
 
@@ -27,7 +33,115 @@ const fn is_default_false(value: &bool) -> bool {
     *value == default_false()
 }
 
+fn default_invite_usage_limit() -> u32 {
+    1
+}
+
+fn default_invite_ttl() -> Duration {
+    Duration::microseconds(7 * 24 * 60 * 60 * 1000 * 1000)
+}
+
+fn default_invite_max_active() -> u32 {
+    5
+}
+
+/// Invite codes which trusted users can issue themselves
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct UserInvitesConfig {
+    /// Name of a boolean custom user attribute marking the users who can issue
+    /// invites. If not set, only users who can request admin access can issue
+    /// invites.
+    ///
+    /// The attribute must be declared in the `user_attributes` section.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trusted_attribute: Option<String>,
+
+    /// How many times each invite can be used. Defaults to 1.
+    #[schemars(range(min = 1))]
+    #[serde(default = "default_invite_usage_limit")]
+    pub usage_limit: u32,
+
+    /// How long invites can be used for, in seconds. Defaults to 7 days.
+    #[schemars(with = "u64", range(min = 60))]
+    #[serde(default = "default_invite_ttl")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub ttl: Duration,
+
+    /// Maximum number of valid invites a user can have at the same time.
+    /// Defaults to 5.
+    #[schemars(range(min = 1))]
+    #[serde(default = "default_invite_max_active")]
+    pub max_active: u32,
+
+    /// Whether users registering with an invite get its issuer recorded as
+    /// their referrer. Defaults to `true`.
+    #[serde(default = "default_true", skip_serializing_if = "is_default_true")]
+    pub assign_referrer: bool,
+}
+
+/// Sends users to an upstream provider based on the domain of the email
+/// address they enter on the login page
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+pub struct HomeRealmDiscoveryConfig {
+    /// Map of email domains to the ID of the upstream provider users with an
+    /// address in this domain are sent to
+    ///
+    /// Domains are matched exactly, so subdomains have to be listed separately.
+    #[schemars(with = "BTreeMap<String, String>")]
+    #[serde(default)]
+    pub domains: BTreeMap<String, Ulid>,
+
+    /// ID of the upstream provider users are sent to when what they entered
+    /// doesn't match any of the domains. If not set, they are asked for their
+    /// password instead.
+    #[schemars(with = "Option<String>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<Ulid>,
+}
+
+/// The current version of a legal document users have to accept
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct TermsDocumentConfig {
+    /// An opaque version identifier, like a date. Users are asked to accept
+    /// the document again when it changes.
+    pub version: String,
+
+    /// Where the document is published
+    pub url: Url,
+}
+
+/// Whether users must enroll a TOTP second factor to log in with a password
+#[derive(Clone, Copy, Debug, Default, Deserialize, JsonSchema, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TotpPolicyConfig {
+    /// `disabled`: users can't enroll a TOTP second factor
+    #[default]
+    Disabled,
+
+    /// `optional`: users can enroll a TOTP second factor from their account
+    /// settings, and must use it to log in once enrolled
+    Optional,
+
+    /// `required`: all users must enroll a TOTP second factor to log in with a
+    /// password
+    Required,
+
+    /// `required_for_admins`: users who can request admin access must enroll a
+    /// TOTP second factor to log in with a password, other users can enroll
+    /// one optionally
+    RequiredForAdmins,
+}
+
+impl TotpPolicyConfig {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    const fn is_default(&self) -> bool {
+        matches!(self, TotpPolicyConfig::Disabled)
+    }
+}
+
 /// Configuration section to configure features related to account management
+#[serde_as]
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 pub struct AccountConfig {
@@ -43,6 +157,12 @@ pub struct AccountConfig {
     #[serde(default = "default_true", skip_serializing_if = "is_default_true")]
     pub displayname_change_allowed: bool,
 
+    /// Whether users are allowed to upload a new avatar. Defaults to `true`.
+    ///
+    /// This should be in sync with the policy in the homeserver configuration.
+    #[serde(default = "default_true", skip_serializing_if = "is_default_true")]
+    pub avatar_change_allowed: bool,
+
     /// Whether to enable self-service password registration. Defaults to
     /// `false` if password authentication is enabled.
     ///
@@ -62,17 +182,46 @@ pub struct AccountConfig {
     #[serde(default = "default_false", skip_serializing_if = "is_default_false")]
     pub password_recovery_enabled: bool,
 
+    /// Whether password recovery requests must be approved by an administrator
+    /// through the admin API before the recovery link is sent. Defaults to
+    /// `false`.
+    ///
+    /// When enabled, users can also say they lost access to their second
+    /// factor, which is then removed once they pick a new password.
+    #[serde(default = "default_false", skip_serializing_if = "is_default_false")]
+    pub password_recovery_requires_approval: bool,
+
     /// Whether users are allowed to delete their own account. Defaults to
     /// `true`.
     #[serde(default = "default_true", skip_serializing_if = "is_default_true")]
     pub account_deactivation_allowed: bool,
 
+    /// How long to wait, in seconds, before erasing an account its owner asked
+    /// to delete
+    ///
+    /// The account is deactivated straight away, but its owner can get it back
+    /// by signing in again with their password until the grace period is over.
+    /// By default, accounts are erased straight away.
+    #[schemars(with = "Option<u64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<serde_with::DurationSeconds<i64>>")]
+    pub account_deletion_grace_period: Option<Duration>,
+
     /// Whether users can log in with their email address. Defaults to `false`.
     ///
     /// This has no effect if password login is disabled.
     #[serde(default = "default_false", skip_serializing_if = "is_default_false")]
     pub login_with_email_allowed: bool,
 
+    /// Whether users can log in with their confirmed phone number, in the
+    /// international format. Defaults to `false`.
+    ///
+    /// This is meant for deployments migrating from systems where users were
+    /// identified by their phone number. This has no effect if password login
+    /// is disabled.
+    #[serde(default = "default_false", skip_serializing_if = "is_default_false")]
+    pub login_with_phone_number_allowed: bool,
+
     /// Whether registration tokens are required for password registrations.
     /// Defaults to `false`.
     ///
@@ -81,6 +230,146 @@ pub struct AccountConfig {
     /// is disabled.
     #[serde(default = "default_false", skip_serializing_if = "is_default_false")]
     pub registration_token_required: bool,
+
+    /// Whether users can register passkeys and use them to log in. Defaults to
+    /// `false`.
+    ///
+    /// Passkeys are bound to the domain of `http.public_base`, so changing it
+    /// will make the existing passkeys unusable.
+    #[serde(default = "default_false", skip_serializing_if = "is_default_false")]
+    pub passkeys_enabled: bool,
+
+    /// Whether users can log in with a code sent to one of their email
+    /// addresses, without entering their password. Defaults to `false`.
+    ///
+    /// The email also contains a link which logs in like entering the code.
+    /// When it is opened in another browser than the one which asked for it,
+    /// the user has to confirm the login first.
+    ///
+    /// Users who have a second factor are asked for it after the code, like
+    /// after their password.
+    #[serde(default = "default_false", skip_serializing_if = "is_default_false")]
+    pub email_code_login_enabled: bool,
+
+    /// Whether users can, or must, enroll a TOTP second factor to log in with
+    /// a password or a code sent by email. Defaults to `disabled`.
+    ///
+    /// Logins with a passkey or through an upstream provider don't ask for
+    /// the second factor. TOTP secrets are encrypted with the
+    /// `secrets.encryption` key, so changing it will make the enrolled
+    /// second factors unusable.
+    #[serde(default, skip_serializing_if = "TotpPolicyConfig::is_default")]
+    pub totp: TotpPolicyConfig,
+
+    /// Whether users can add a phone number to their account, which is
+    /// confirmed with a code sent by SMS. Defaults to `false`.
+    ///
+    /// This requires an SMS gateway to be configured in the `sms` section.
+    #[serde(default = "default_false", skip_serializing_if = "is_default_false")]
+    pub phone_number_change_allowed: bool,
+
+    /// Whether users who have confirmed a phone number must enter a code sent
+    /// to it by SMS after their password, or the code sent to their email
+    /// address. Defaults to `false`.
+    ///
+    /// Users who have a TOTP second factor are asked for it instead. This has
+    /// no effect if both password and email code login are disabled.
+    #[serde(default = "default_false", skip_serializing_if = "is_default_false")]
+    pub sms_second_factor_enabled: bool,
+
+    /// How long a browser stays trusted, in seconds, when the user chooses to
+    /// remember it after entering their second factor
+    ///
+    /// Password and email code logins from a trusted browser skip the second
+    /// factor. Users can revoke the browsers they trusted from their account
+    /// settings. The option to remember the browser isn't offered by default.
+    #[schemars(with = "Option<u64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<serde_with::DurationSeconds<i64>>")]
+    pub trusted_device_ttl: Option<Duration>,
+
+    /// Time in seconds after which a browser session which wasn't used is
+    /// finished, logging the user out of the service
+    ///
+    /// This only applies to the sessions of the service itself, not to the
+    /// sessions of applications. If
+    /// `experimental.inactive_session_expiration` also expires user sessions,
+    /// the shortest of the two applies. Disabled by default.
+    #[schemars(with = "Option<u64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<serde_with::DurationSeconds<i64>>")]
+    pub session_idle_timeout: Option<Duration>,
+
+    /// Time in seconds after which a browser session is finished, however
+    /// active it is
+    ///
+    /// Users then have to log in again. This only applies to the sessions of
+    /// the service itself, not to the sessions of applications. Disabled by
+    /// default.
+    #[schemars(with = "Option<u64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<serde_with::DurationSeconds<i64>>")]
+    pub session_max_lifetime: Option<Duration>,
+
+    /// Time in seconds during which users who authenticated in their browser
+    /// session can change sensitive account settings
+    ///
+    /// Once it has passed, users have to authenticate again, with their
+    /// password, a passkey or their upstream provider, before changing their
+    /// email addresses, phone number, second factors, or deactivating their
+    /// account. Disabled by default, in which case only the password is asked
+    /// for, if the user has one.
+    #[schemars(with = "Option<u64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<serde_with::DurationSeconds<i64>>")]
+    pub sudo_mode_ttl: Option<Duration>,
+
+    /// Let trusted users issue invite codes from the `/invites` page
+    ///
+    /// Disabled by default. Invites are registration tokens, so this is only
+    /// useful if `registration_token_required` is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_invites: Option<UserInvitesConfig>,
+
+    /// Ask users for their username or email address first on the login page,
+    /// and send them to an upstream provider based on its domain
+    ///
+    /// Disabled by default. The providers are referenced by their ID, and
+    /// users are asked for their password if the provider is disabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub home_realm_discovery: Option<HomeRealmDiscoveryConfig>,
+
+    /// Whether the login page redirects to the upstream provider right away
+    /// when it is the only way to log in. Defaults to `true`.
+    ///
+    /// This only happens if password login, passkeys and email code login are
+    /// disabled, and a single provider is enabled. Adding `?no_auto=true` (or
+    /// `?no_auto=1`) to the URL of the login page shows it anyway.
+    #[serde(default = "default_true", skip_serializing_if = "is_default_true")]
+    pub single_provider_auto_redirect: bool,
+
+    /// The current version of the terms of service
+    ///
+    /// Users who haven't accepted this version yet are asked to accept it
+    /// after logging in or registering. Not set by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terms_of_service: Option<TermsDocumentConfig>,
+
+    /// The current version of the privacy policy
+    ///
+    /// Users who haven't accepted this version yet are asked to accept it
+    /// after logging in or registering. Not set by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub privacy_policy: Option<TermsDocumentConfig>,
+
+    /// Whether users get an email when their account is used to log in from a
+    /// new IP address or device. Defaults to `false`.
+    ///
+    /// The email is sent a few minutes after the login, to the email addresses
+    /// of the user, who can opt out from the `/notifications` page. Setting up
+    /// the `geoip` section adds the location of the IP address to the email.
+    #[serde(default = "default_false", skip_serializing_if = "is_default_false")]
+    pub sign_in_notifications_enabled: bool,
 }
 
 impl Default for AccountConfig {
@@ -88,12 +377,31 @@ impl Default for AccountConfig {
         Self {
             email_change_allowed: default_true(),
             displayname_change_allowed: default_true(),
+            avatar_change_allowed: default_true(),
             password_registration_enabled: default_false(),
             password_change_allowed: default_true(),
             password_recovery_enabled: default_false(),
+            password_recovery_requires_approval: default_false(),
             account_deactivation_allowed: default_true(),
+            account_deletion_grace_period: None,
             login_with_email_allowed: default_false(),
+            login_with_phone_number_allowed: default_false(),
             registration_token_required: default_false(),
+            passkeys_enabled: default_false(),
+            email_code_login_enabled: default_false(),
+            totp: TotpPolicyConfig::default(),
+            phone_number_change_allowed: default_false(),
+            sms_second_factor_enabled: default_false(),
+            trusted_device_ttl: None,
+            session_idle_timeout: None,
+            session_max_lifetime: None,
+            sudo_mode_ttl: None,
+            user_invites: None,
+            home_realm_discovery: None,
+            single_provider_auto_redirect: default_true(),
+            terms_of_service: None,
+            privacy_policy: None,
+            sign_in_notifications_enabled: default_false(),
         }
     }
 }
@@ -104,11 +412,30 @@ impl AccountConfig {
         is_default_false(&self.password_registration_enabled)
             && is_default_true(&self.email_change_allowed)
             && is_default_true(&self.displayname_change_allowed)
+            && is_default_true(&self.avatar_change_allowed)
             && is_default_true(&self.password_change_allowed)
             && is_default_false(&self.password_recovery_enabled)
+            && is_default_false(&self.password_recovery_requires_approval)
             && is_default_true(&self.account_deactivation_allowed)
+            && self.account_deletion_grace_period.is_none()
             && is_default_false(&self.login_with_email_allowed)
+            && is_default_false(&self.login_with_phone_number_allowed)
             && is_default_false(&self.registration_token_required)
+            && is_default_false(&self.passkeys_enabled)
+            && is_default_false(&self.email_code_login_enabled)
+            && self.totp.is_default()
+            && is_default_false(&self.phone_number_change_allowed)
+            && is_default_false(&self.sms_second_factor_enabled)
+            && self.trusted_device_ttl.is_none()
+            && self.session_idle_timeout.is_none()
+            && self.session_max_lifetime.is_none()
+            && self.sudo_mode_ttl.is_none()
+            && self.user_invites.is_none()
+            && self.home_realm_discovery.is_none()
+            && is_default_true(&self.single_provider_auto_redirect)
+            && self.terms_of_service.is_none()
+            && self.privacy_policy.is_none()
+            && is_default_false(&self.sign_in_notifications_enabled)
     }
 }
 
@@ -0,0 +1,245 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::BTreeSet;
+
+use chrono::Duration;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, de::Error};
+use serde_with::serde_as;
+
+use crate::ConfigurationSection;
+
+fn default_step_up_ttl() -> Duration {
+    Duration::microseconds(5 * 60 * 1000 * 1000)
+}
+
+fn is_default_step_up_ttl(value: &Duration) -> bool {
+    *value == default_step_up_ttl()
+}
+
+/// An Authentication Method Reference, as exposed in the `amr` claim of ID
+/// tokens
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, Serialize, PartialEq, Eq)]
+pub enum AuthenticationMethodReference {
+    /// Authentication with a password
+    #[serde(rename = "pwd")]
+    Password,
+
+    /// Authentication through an upstream identity provider
+    #[serde(rename = "fed")]
+    Federated,
+
+    /// Authentication with a one-time password, as a second factor
+    #[serde(rename = "otp")]
+    OneTimePassword,
+
+    /// Authentication with a code sent by SMS, as a second factor
+    #[serde(rename = "sms")]
+    Sms,
+}
+
+impl AuthenticationMethodReference {
+    /// The value of this method in the `amr` claim
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Password => "pwd",
+            Self::Federated => "fed",
+            Self::OneTimePassword => "otp",
+            Self::Sms => "sms",
+        }
+    }
+}
+
+/// An Authentication Context Class Reference value sessions can satisfy
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct AcrValueConfig {
+    /// The value, as requested by clients in `acr_values` and exposed in the
+    /// `acr` claim of ID tokens
+    pub value: String,
+
+    /// The authentication methods a session must have used to satisfy this
+    /// value. Any session satisfies it if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<AuthenticationMethodReference>,
+}
+
+/// Configuration section for the Authentication Context Class Reference
+/// values clients can require, and the step-up authentication of sessions
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct AcrConfig {
+    /// The ACR values sessions can satisfy, from the weakest to the strongest.
+    ///
+    /// Clients requiring a value are also satisfied by the stronger ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<AcrValueConfig>,
+
+    /// Scopes which require the user to step up their authentication before
+    /// authorizing them, unless they authenticated recently. A `*` at the end
+    /// of a scope matches any scope starting with the rest of it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub step_up_scopes: Vec<String>,
+
+    /// How long a session stays elevated after a step-up, in seconds.
+    /// Defaults to 5 minutes.
+    #[schemars(with = "u64", range(min = 60, max = 86400))]
+    #[serde(
+        default = "default_step_up_ttl",
+        skip_serializing_if = "is_default_step_up_ttl"
+    )]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub step_up_ttl: Duration,
+}
+
+impl Default for AcrConfig {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            step_up_scopes: Vec::new(),
+            step_up_ttl: default_step_up_ttl(),
+        }
+    }
+}
+
+impl AcrConfig {
+    /// Returns true if the configuration is the default one
+    pub(crate) fn is_default(&self) -> bool {
+        self.values.is_empty()
+            && self.step_up_scopes.is_empty()
+            && is_default_step_up_ttl(&self.step_up_ttl)
+    }
+}
+
+impl ConfigurationSection for AcrConfig {
+    const PATH: Option<&'static str> = Some("acr");
+
+    fn validate(&self, figment: &figment::Figment) -> Result<(), figment::Error> {
+        let mut values = BTreeSet::new();
+
+        for (index, value) in self.values.iter().enumerate() {
+            let annotate = |mut error: figment::Error| {
+                error.metadata = figment
+                    .find_metadata(&format!("{root}.values", root = Self::PATH.unwrap()))
+                    .cloned();
+                error.profile = Some(figment::Profile::Default);
+                error.path = vec![
+                    Self::PATH.unwrap().to_owned(),
+                    "values".to_owned(),
+                    index.to_string(),
+                ];
+                Err(error)
+            };
+
+            if value.value.is_empty() || value.value.contains(' ') {
+                return annotate(figment::Error::custom(
+                    "ACR values must not be empty or contain spaces",
+                ));
+            }
+
+            if !values.insert(value.value.as_str()) {
+                return annotate(figment::Error::custom(format!(
+                    "Duplicate ACR value {:?}",
+                    value.value
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::{
+        Figment, Jail,
+        providers::{Format, Yaml},
+    };
+
+    use super::*;
+
+    #[test]
+    fn load_config() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r"
+                    acr:
+                      values:
+                        - value: urn:example:acr:any
+                        - value: urn:example:acr:password
+                          amr: [pwd]
+                ",
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<AcrConfig>("acr")?;
+            config.validate(&figment)?;
+
+            assert_eq!(config.values.len(), 2);
+            assert!(config.values[0].amr.is_empty());
+            assert_eq!(
+                config.values[1].amr,
+                vec![AuthenticationMethodReference::Password]
+            );
+            assert!(config.step_up_scopes.is_empty());
+            assert_eq!(config.step_up_ttl, default_step_up_ttl());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn load_step_up_config() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    acr:
+                      step_up_scopes:
+                        - "urn:synapse:admin:*"
+                        - urn:mas:admin
+                      step_up_ttl: 120
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<AcrConfig>("acr")?;
+            config.validate(&figment)?;
+
+            assert_eq!(
+                config.step_up_scopes,
+                vec!["urn:synapse:admin:*".to_owned(), "urn:mas:admin".to_owned()]
+            );
+            assert_eq!(config.step_up_ttl, Duration::try_minutes(2).unwrap());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reject_duplicate_values() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r"
+                    acr:
+                      values:
+                        - value: urn:example:acr:password
+                          amr: [pwd]
+                        - value: urn:example:acr:password
+                          amr: [fed]
+                ",
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<AcrConfig>("acr")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
+}
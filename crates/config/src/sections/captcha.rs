@@ -23,6 +23,11 @@ pub enum CaptchaServiceKind {
     /// Use ``HCaptcha``
     #[serde(rename = "hcaptcha")]
     HCaptcha,
+
+    /// Use a proof-of-work challenge issued and verified by MAS itself,
+    /// compatible with ALTCHA. Only the secret key is needed.
+    #[serde(rename = "proof_of_work")]
+    ProofOfWork,
 }
 
 /// Configuration section to setup CAPTCHA protection on a few operations
@@ -36,7 +41,8 @@ pub struct CaptchaConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub site_key: Option<String>,
 
-    /// The secret key to use
+    /// The secret key to use. With the proof-of-work service, it is used to
+    /// sign the challenges
     #[serde(skip_serializing_if = "Option::is_none")]
     pub secret_key: Option<String>,
 }
@@ -75,6 +81,12 @@ impl ConfigurationSection for CaptchaConfig {
             }
         }
 
+        if let Some(CaptchaServiceKind::ProofOfWork) = self.service {
+            if self.secret_key.is_none() {
+                return Err(missing_field("secret_key"));
+            }
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,185 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::BTreeSet;
+
+use mas_jose::jwk::PublicJsonWebKeySet;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, de::Error};
+use url::Url;
+
+use crate::ConfigurationSection;
+
+const fn is_default_false(value: &bool) -> bool {
+    !*value
+}
+
+/// An issuer trusted to sign software statements
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct SoftwareStatementIssuerConfig {
+    /// The identifier of the issuer, matched against the `iss` claim of the
+    /// software statements
+    pub issuer: String,
+
+    /// The JSON Web Key Set used to verify the software statements signed by
+    /// this issuer. Mutually exclusive with `jwks_uri`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks: Option<PublicJsonWebKeySet>,
+
+    /// URL to fetch the JSON Web Key Set used to verify the software
+    /// statements signed by this issuer. Mutually exclusive with `jwks`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks_uri: Option<Url>,
+}
+
+/// Configuration section for the dynamic registration of clients
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+pub struct ClientRegistrationConfig {
+    /// Issuers trusted to sign the software statements (RFC 7591) presented
+    /// by clients when they register. The client metadata values in a
+    /// software statement take precedence over the ones sent alongside it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub software_statement_issuers: Vec<SoftwareStatementIssuerConfig>,
+
+    /// Whether clients must present a software statement signed by one of the
+    /// trusted issuers to register. Defaults to `false`.
+    #[serde(default, skip_serializing_if = "is_default_false")]
+    pub require_software_statement: bool,
+}
+
+impl ClientRegistrationConfig {
+    /// Returns true if the configuration is the default one
+    pub(crate) fn is_default(&self) -> bool {
+        self.software_statement_issuers.is_empty() && !self.require_software_statement
+    }
+}
+
+impl ConfigurationSection for ClientRegistrationConfig {
+    const PATH: Option<&'static str> = Some("client_registration");
+
+    fn validate(&self, figment: &figment::Figment) -> Result<(), figment::Error> {
+        if self.require_software_statement && self.software_statement_issuers.is_empty() {
+            let mut error = figment::Error::custom(
+                "At least one software statement issuer is required when software statements are required",
+            );
+            error.metadata = figment
+                .find_metadata(&format!(
+                    "{root}.require_software_statement",
+                    root = Self::PATH.unwrap()
+                ))
+                .cloned();
+            error.profile = Some(figment::Profile::Default);
+            error.path = vec![
+                Self::PATH.unwrap().to_owned(),
+                "require_software_statement".to_owned(),
+            ];
+            return Err(error);
+        }
+
+        let mut issuers = BTreeSet::new();
+
+        for (index, issuer) in self.software_statement_issuers.iter().enumerate() {
+            let annotate = |mut error: figment::Error| {
+                error.metadata = figment
+                    .find_metadata(&format!(
+                        "{root}.software_statement_issuers",
+                        root = Self::PATH.unwrap()
+                    ))
+                    .cloned();
+                error.profile = Some(figment::Profile::Default);
+                error.path = vec![
+                    Self::PATH.unwrap().to_owned(),
+                    "software_statement_issuers".to_owned(),
+                    index.to_string(),
+                ];
+                Err(error)
+            };
+
+            if !issuers.insert(issuer.issuer.as_str()) {
+                return annotate(figment::Error::custom(format!(
+                    "Duplicate software statement issuer {:?}",
+                    issuer.issuer
+                )));
+            }
+
+            match (&issuer.jwks, &issuer.jwks_uri) {
+                (None, None) => {
+                    return annotate(figment::Error::custom(
+                        "One of `jwks` or `jwks_uri` is required",
+                    ));
+                }
+                (Some(_), Some(_)) => {
+                    return annotate(figment::Error::custom(
+                        "`jwks` and `jwks_uri` are mutually exclusive",
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::{
+        Figment, Jail,
+        providers::{Format, Yaml},
+    };
+
+    use super::*;
+
+    #[test]
+    fn load_config() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r"
+                    client_registration:
+                      require_software_statement: true
+                      software_statement_issuers:
+                        - issuer: https://statements.example.com/
+                          jwks_uri: https://statements.example.com/jwks.json
+                ",
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config =
+                figment.extract_inner::<ClientRegistrationConfig>("client_registration")?;
+            config.validate(&figment)?;
+
+            assert!(config.require_software_statement);
+            assert_eq!(config.software_statement_issuers.len(), 1);
+            assert_eq!(
+                config.software_statement_issuers[0].issuer,
+                "https://statements.example.com/"
+            );
+            assert!(config.software_statement_issuers[0].jwks.is_none());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reject_missing_issuers() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r"
+                    client_registration:
+                      require_software_statement: true
+                ",
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config =
+                figment.extract_inner::<ClientRegistrationConfig>("client_registration")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
+}
@@ -6,11 +6,19 @@
 
 use std::ops::Deref;
 
+use chrono::{DateTime, Duration, Utc};
 use figment::Figment;
-use mas_iana::oauth::OAuthClientAuthenticationMethod;
-use mas_jose::jwk::PublicJsonWebKeySet;
+use mas_iana::{
+    jose::{JsonWebEncryptionAlg, JsonWebEncryptionEnc, JsonWebSignatureAlg},
+    oauth::OAuthClientAuthenticationMethod,
+};
+use mas_jose::{
+    jwa::{SUPPORTED_CONTENT_ENCRYPTION_ALGORITHMS, SUPPORTED_ENCRYPTION_ALGORITHMS},
+    jwk::PublicJsonWebKeySet,
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize, de::Error};
+use serde_with::serde_as;
 use ulid::Ulid;
 use url::Url;
 
@@ -65,7 +73,50 @@ impl std::fmt::Display for ClientAuthMethodConfig {
     }
 }
 
+/// How refresh tokens are handled when a client uses them
+#[derive(JsonSchema, Serialize, Deserialize, Copy, Clone, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RefreshTokenRotationConfig {
+    /// `rotate_on_use`: each use of a refresh token consumes it and issues a
+    /// new one. Using a refresh token which was already rotated ends the
+    /// session
+    #[default]
+    RotateOnUse,
+
+    /// `static`: the same refresh token is used for the whole lifetime of the
+    /// session
+    Static,
+}
+
+impl RefreshTokenRotationConfig {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    const fn is_default(&self) -> bool {
+        matches!(self, RefreshTokenRotationConfig::RotateOnUse)
+    }
+}
+
+/// The format of the access tokens issued to a client
+#[derive(JsonSchema, Serialize, Deserialize, Copy, Clone, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessTokenFormatConfig {
+    /// `opaque`: random strings, which resource servers have to introspect
+    #[default]
+    Opaque,
+
+    /// `jwt`: self-contained JWTs following the RFC 9068 profile, signed with
+    /// the keys of the service
+    Jwt,
+}
+
+impl AccessTokenFormatConfig {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    const fn is_default(&self) -> bool {
+        matches!(self, AccessTokenFormatConfig::Opaque)
+    }
+}
+
 /// An OAuth 2.0 client configuration
+#[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ClientConfig {
     /// The client ID
@@ -88,6 +139,21 @@ pub struct ClientConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub client_secret: Option<String>,
 
+    /// When the `client_secret` stops being accepted. By default, it doesn't
+    /// expire.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret_expires_at: Option<DateTime<Utc>>,
+
+    /// A second secret, accepted alongside `client_secret` while the client
+    /// switches to it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_client_secret: Option<String>,
+
+    /// When the `next_client_secret` stops being accepted. By default, it
+    /// doesn't expire.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_client_secret_expires_at: Option<DateTime<Utc>>,
+
     /// The JSON Web Key Set (JWKS) used by the `private_key_jwt` authentication
     /// method. Mutually exclusive with `jwks_uri`
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -101,6 +167,209 @@ pub struct ClientConfig {
     /// List of allowed redirect URIs
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub redirect_uris: Vec<Url>,
+
+    /// Whether the client must push its authorization requests to the pushed
+    /// authorization request endpoint before sending users to the
+    /// authorization endpoint. Defaults to `false`.
+    #[serde(default, skip_serializing_if = "is_default_false")]
+    pub require_pushed_authorization_requests: bool,
+
+    /// The algorithm used to sign JWT-secured authorization responses, when
+    /// the client asks for one with a `*.jwt` response mode. Defaults to
+    /// `RS256`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization_signed_response_alg: Option<JsonWebSignatureAlg>,
+
+    /// Makes this client a service account. Its `client_credentials` tokens
+    /// get a synthetic subject, and can only be issued for the allowed scopes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_account: Option<ServiceAccountConfig>,
+
+    /// The endpoint on which the client is notified once a user responded to
+    /// a backchannel authentication request. If set, the client uses the
+    /// `ping` token delivery mode, else it has to poll the token endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backchannel_client_notification_endpoint: Option<Url>,
+
+    /// List of resource indicators (RFC 8707) this client is allowed to
+    /// request tokens for. Tokens issued for a resource have it as their
+    /// audience.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_resources: Vec<Url>,
+
+    /// How refresh tokens are handled when this client uses them. Defaults to
+    /// `rotate_on_use`.
+    #[serde(
+        default,
+        skip_serializing_if = "RefreshTokenRotationConfig::is_default"
+    )]
+    pub refresh_token_rotation: RefreshTokenRotationConfig,
+
+    /// Notify this client using OpenID Connect Back-Channel Logout when one of
+    /// its sessions ends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backchannel_logout: Option<BackchannelLogoutConfig>,
+
+    /// List of URIs the client can redirect the user to after an RP-initiated
+    /// logout
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_logout_redirect_uris: Vec<Url>,
+
+    /// The format of the access tokens issued to this client. Defaults to
+    /// `opaque`.
+    #[serde(default, skip_serializing_if = "AccessTokenFormatConfig::is_default")]
+    pub access_token_format: AccessTokenFormatConfig,
+
+    /// Lifetime of the access tokens issued to this client, in seconds.
+    /// Defaults to the server-wide `experimental.access_token_ttl`.
+    #[schemars(with = "Option<u64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<serde_with::DurationSeconds<i64>>")]
+    pub access_token_ttl: Option<Duration>,
+
+    /// Lifetime of the refresh tokens issued to this client, in seconds. By
+    /// default, refresh tokens don't expire.
+    #[schemars(with = "Option<u64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<serde_with::DurationSeconds<i64>>")]
+    pub refresh_token_ttl: Option<Duration>,
+
+    /// Lifetime of the device codes issued to this client, in seconds.
+    /// Defaults to 20 minutes.
+    #[schemars(with = "Option<u64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<serde_with::DurationSeconds<i64>>")]
+    pub device_code_ttl: Option<Duration>,
+
+    /// The algorithm request objects sent by this client must be signed with.
+    /// By default, any algorithm supported by the keys of the client is
+    /// accepted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_object_signing_alg: Option<JsonWebSignatureAlg>,
+
+    /// Whether the client must send its authorization requests as signed
+    /// request objects (RFC 9101), verified with its `jwks` or `jwks_uri`.
+    /// Defaults to `false`.
+    #[serde(default, skip_serializing_if = "is_default_false")]
+    pub require_signed_request_object: bool,
+
+    /// The algorithm used to encrypt the ID tokens issued to this client,
+    /// with a key from its `jwks` or `jwks_uri`. By default, ID tokens are
+    /// only signed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token_encrypted_response_alg: Option<JsonWebEncryptionAlg>,
+
+    /// The algorithm used to encrypt the content of the ID tokens issued to
+    /// this client. Defaults to `A128CBC-HS256` if
+    /// `id_token_encrypted_response_alg` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token_encrypted_response_enc: Option<JsonWebEncryptionEnc>,
+
+    /// The authentication context classes, from the `acr` section, required by
+    /// default when the client doesn't send `acr_values` in its authorization
+    /// requests
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub default_acr_values: Vec<String>,
+
+    /// Makes this client a resource server. When it introspects tokens, only
+    /// the ones issued for its audience are reported as active.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_server: Option<ResourceServerConfig>,
+
+    /// Give this client pairwise subject identifiers for this sector
+    /// identifier, instead of the public subject identifiers of users. Clients
+    /// sharing a sector identifier get the same subject identifiers; it is
+    /// usually the host of their redirect URIs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pairwise_sector_identifier: Option<String>,
+
+    /// Restrict the scopes this client may request. It can request any scope
+    /// allowed by the policy if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope_policy: Option<ScopePolicyConfig>,
+}
+
+/// Settings for clients acting as service accounts
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ServiceAccountConfig {
+    /// Space-separated list of scopes the service account is allowed to
+    /// request with the `client_credentials` grant
+    pub allowed_scope: String,
+}
+
+/// What happens to a request for scopes a client isn't allowed to request
+#[derive(JsonSchema, Serialize, Deserialize, Copy, Clone, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DisallowedScopesConfig {
+    /// `reject`: the request is denied by the policy engine
+    #[default]
+    Reject,
+
+    /// `filter`: the scopes are removed from the request, which goes on with
+    /// the allowed ones
+    Filter,
+}
+
+impl DisallowedScopesConfig {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    const fn is_default(&self) -> bool {
+        matches!(self, DisallowedScopesConfig::Reject)
+    }
+}
+
+/// Restrictions on the scopes a client may request
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScopePolicyConfig {
+    /// The scopes the client is allowed to request. A `*` at the end of a
+    /// scope matches any scope starting with the rest of it, like
+    /// `urn:matrix:org.matrix.msc2967.client:device:*` for any device scope.
+    pub allowed_scopes: Vec<String>,
+
+    /// What happens to requests for other scopes. Defaults to `reject`.
+    #[serde(default, skip_serializing_if = "DisallowedScopesConfig::is_default")]
+    pub disallowed: DisallowedScopesConfig,
+}
+
+/// Settings for clients acting as resource servers
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ResourceServerConfig {
+    /// The resource indicator (RFC 8707) of the API served by this client.
+    /// If not set, the client serves the Matrix Client-Server API, which gets
+    /// the tokens issued without a resource indicator and the compatibility
+    /// tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audience: Option<Url>,
+}
+
+/// Settings for clients receiving back-channel logout notifications
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BackchannelLogoutConfig {
+    /// The URI on which the logout tokens are sent
+    pub uri: Url,
+
+    /// Whether to include the `sid` claim, identifying the browser session,
+    /// in the logout tokens. Defaults to `true`.
+    #[serde(default = "default_true", skip_serializing_if = "is_default_true")]
+    pub include_sid: bool,
+
+    /// Whether to include the `sub` claim, identifying the user, in the
+    /// logout tokens. Defaults to `true`.
+    #[serde(default = "default_true", skip_serializing_if = "is_default_true")]
+    pub include_sub: bool,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+const fn is_default_false(value: &bool) -> bool {
+    !*value
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+const fn is_default_true(value: &bool) -> bool {
+    *value == default_true()
 }
 
 impl ClientConfig {
@@ -127,6 +396,13 @@ impl ClientConfig {
                     );
                     return Err(error.with_path("client_secret"));
                 }
+
+                if self.next_client_secret.is_some() {
+                    let error = figment::error::Error::custom(
+                        "next_client_secret is not allowed with private_key_jwt",
+                    );
+                    return Err(error.with_path("next_client_secret"));
+                }
             }
 
             ClientAuthMethodConfig::ClientSecretPost
@@ -155,6 +431,20 @@ impl ClientConfig {
             }
 
             ClientAuthMethodConfig::None => {
+                if self.service_account.is_some() {
+                    let error = figment::error::Error::custom(
+                        "service accounts can't use the none authentication method",
+                    );
+                    return Err(error.with_path("service_account"));
+                }
+
+                if self.resource_server.is_some() {
+                    let error = figment::error::Error::custom(
+                        "resource servers can't use the none authentication method",
+                    );
+                    return Err(error.with_path("resource_server"));
+                }
+
                 if self.client_secret.is_some() {
                     let error = figment::error::Error::custom(
                         "client_secret is not allowed with none authentication method",
@@ -162,6 +452,13 @@ impl ClientConfig {
                     return Err(error.with_path("client_secret"));
                 }
 
+                if self.next_client_secret.is_some() {
+                    let error = figment::error::Error::custom(
+                        "next_client_secret is not allowed with none authentication method",
+                    );
+                    return Err(error.with_path("next_client_secret"));
+                }
+
                 if self.jwks.is_some() {
                     let error = figment::error::Error::custom(
                         "jwks is not allowed with none authentication method",
@@ -178,6 +475,89 @@ impl ClientConfig {
             }
         }
 
+        if let Some(backchannel_logout) = &self.backchannel_logout {
+            if !backchannel_logout.include_sid && !backchannel_logout.include_sub {
+                let error = figment::error::Error::custom(
+                    "at least one of include_sid or include_sub must be set",
+                );
+                return Err(error.with_path("backchannel_logout"));
+            }
+
+            if backchannel_logout.uri.fragment().is_some() {
+                let error = figment::error::Error::custom(
+                    "the back-channel logout URI can't have a fragment",
+                );
+                return Err(error.with_path("backchannel_logout"));
+            }
+        }
+
+        if self
+            .post_logout_redirect_uris
+            .iter()
+            .any(|uri| uri.fragment().is_some())
+        {
+            let error =
+                figment::error::Error::custom("post-logout redirect URIs can't have a fragment");
+            return Err(error.with_path("post_logout_redirect_uris"));
+        }
+
+        if self.request_object_signing_alg == Some(JsonWebSignatureAlg::None) {
+            let error = figment::error::Error::custom(
+                "request objects must be signed with a real algorithm",
+            );
+            return Err(error.with_path("request_object_signing_alg"));
+        }
+
+        if self.require_signed_request_object && self.jwks.is_none() && self.jwks_uri.is_none() {
+            let error = figment::error::Error::custom(
+                "jwks or jwks_uri is required to verify signed request objects",
+            );
+            return Err(error.with_path("require_signed_request_object"));
+        }
+
+        if let Some(alg) = &self.id_token_encrypted_response_alg {
+            if !SUPPORTED_ENCRYPTION_ALGORITHMS.contains(alg) {
+                let error = figment::error::Error::custom(format!(
+                    "ID token encryption algorithm {alg} is not supported"
+                ));
+                return Err(error.with_path("id_token_encrypted_response_alg"));
+            }
+
+            if self.jwks.is_none() && self.jwks_uri.is_none() {
+                let error = figment::error::Error::custom(
+                    "jwks or jwks_uri is required to encrypt ID tokens",
+                );
+                return Err(error.with_path("id_token_encrypted_response_alg"));
+            }
+        }
+
+        if let Some(enc) = &self.id_token_encrypted_response_enc {
+            if self.id_token_encrypted_response_alg.is_none() {
+                let error = figment::error::Error::custom(
+                    "id_token_encrypted_response_alg must be set too",
+                );
+                return Err(error.with_path("id_token_encrypted_response_enc"));
+            }
+
+            if !SUPPORTED_CONTENT_ENCRYPTION_ALGORITHMS.contains(enc) {
+                let error = figment::error::Error::custom(format!(
+                    "ID token content encryption algorithm {enc} is not supported"
+                ));
+                return Err(error.with_path("id_token_encrypted_response_enc"));
+            }
+        }
+
+        for (path, ttl) in [
+            ("access_token_ttl", self.access_token_ttl),
+            ("refresh_token_ttl", self.refresh_token_ttl),
+            ("device_code_ttl", self.device_code_ttl),
+        ] {
+            if ttl.is_some_and(|ttl| ttl <= Duration::zero()) {
+                let error = figment::error::Error::custom("token lifetimes must be positive");
+                return Err(error.with_path(path));
+            }
+        }
+
         Ok(())
     }
 
@@ -270,14 +650,24 @@ mod tests {
                       client_auth_method: none
                       redirect_uris:
                         - https://exemple.fr/callback
+                      post_logout_redirect_uris:
+                        - https://exemple.fr/logged-out
 
                     - client_id: 01GFWR32NCQ12B8Z0J8CPXRRB6
                       client_auth_method: client_secret_basic
                       client_secret: hello
+                      backchannel_logout:
+                        uri: https://exemple.fr/backchannel-logout
+                        include_sub: false
 
                     - client_id: 01GFWR3WHR93Y5HK389H28VHZ9
                       client_auth_method: client_secret_post
                       client_secret: hello
+                      service_account:
+                        allowed_scope: urn:mas:admin
+                      access_token_format: jwt
+                      access_token_ttl: 60
+                      refresh_token_ttl: 86400
 
                     - client_id: 01GFWR43R2ZZ8HX9CVBNW9TJWG
                       client_auth_method: client_secret_jwt
@@ -285,6 +675,9 @@ mod tests {
 
                     - client_id: 01GFWR4BNFDCC4QDG6AMSP1VRR
                       client_auth_method: private_key_jwt
+                      require_signed_request_object: true
+                      request_object_signing_alg: RS256
+                      id_token_encrypted_response_alg: RSA-OAEP-256
                       jwks:
                         keys:
                         - kid: "03e84aed4ef4431014e8617567864c4efaaaede9"
@@ -317,12 +710,65 @@ mod tests {
                 config.0[0].redirect_uris,
                 vec!["https://exemple.fr/callback".parse().unwrap()]
             );
+            assert_eq!(
+                config.0[0].post_logout_redirect_uris,
+                vec!["https://exemple.fr/logged-out".parse().unwrap()]
+            );
 
             assert_eq!(
                 config.0[1].client_id,
                 Ulid::from_str("01GFWR32NCQ12B8Z0J8CPXRRB6").unwrap()
             );
             assert_eq!(config.0[1].redirect_uris, Vec::new());
+            assert!(config.0[1].service_account.is_none());
+            let backchannel_logout = config.0[1].backchannel_logout.as_ref().unwrap();
+            assert_eq!(
+                backchannel_logout.uri.as_str(),
+                "https://exemple.fr/backchannel-logout"
+            );
+            assert!(backchannel_logout.include_sid);
+            assert!(!backchannel_logout.include_sub);
+            assert!(config.0[0].backchannel_logout.is_none());
+
+            assert_eq!(
+                config.0[2]
+                    .service_account
+                    .as_ref()
+                    .map(|sa| sa.allowed_scope.as_str()),
+                Some("urn:mas:admin")
+            );
+            assert!(matches!(
+                config.0[2].access_token_format,
+                AccessTokenFormatConfig::Jwt
+            ));
+            assert!(matches!(
+                config.0[0].access_token_format,
+                AccessTokenFormatConfig::Opaque
+            ));
+            assert_eq!(
+                config.0[2].access_token_ttl,
+                Some(Duration::microseconds(60 * 1000 * 1000))
+            );
+            assert_eq!(
+                config.0[2].refresh_token_ttl,
+                Some(Duration::microseconds(86400 * 1000 * 1000))
+            );
+            assert!(config.0[2].device_code_ttl.is_none());
+            assert!(config.0[0].access_token_ttl.is_none());
+
+            assert!(config.0[4].require_signed_request_object);
+            assert_eq!(
+                config.0[4].request_object_signing_alg,
+                Some(JsonWebSignatureAlg::Rs256)
+            );
+            assert!(!config.0[0].require_signed_request_object);
+
+            assert_eq!(
+                config.0[4].id_token_encrypted_response_alg,
+                Some(JsonWebEncryptionAlg::RsaOaep256)
+            );
+            assert!(config.0[4].id_token_encrypted_response_enc.is_none());
+            assert!(config.0[0].id_token_encrypted_response_alg.is_none());
 
             Ok(())
         });
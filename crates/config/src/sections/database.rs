@@ -40,6 +40,7 @@ fn default_max_lifetime() -> Option<Duration> {
 impl Default for DatabaseConfig {
     fn default() -> Self {
         Self {
+            backend: DatabaseBackend::default(),
             uri: default_connection_string(),
             host: None,
             port: None,
@@ -63,6 +64,22 @@ impl Default for DatabaseConfig {
     }
 }
 
+/// The storage backend used to persist the data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseBackend {
+    /// Store the data in a PostgreSQL database
+    #[default]
+    Postgres,
+}
+
+impl DatabaseBackend {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 /// Options for controlling the level of protection provided for PostgreSQL SSL
 /// connections.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
@@ -95,6 +112,13 @@ pub enum PgSslMode {
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DatabaseConfig {
+    /// The storage backend to use
+    ///
+    /// Only `postgres` is supported for now. The connection options below
+    /// apply to this backend.
+    #[serde(default, skip_serializing_if = "DatabaseBackend::is_default")]
+    pub backend: DatabaseBackend,
+
     /// Connection URI
     ///
     /// This must not be specified if `host`, `port`, `socket`, `username`,
@@ -4,9 +4,11 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::collections::BTreeSet;
+
 use chrono::Duration;
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::Error};
 use serde_with::serde_as;
 
 use crate::ConfigurationSection;
@@ -45,6 +47,50 @@ pub struct InactiveSessionExpirationConfig {
     pub expire_user_sessions: bool,
 }
 
+fn default_user_code_length() -> usize {
+    6
+}
+
+fn default_user_code_alphabet() -> String {
+    // Uppercase letters and digits, without the easily confused `0`, `O`, `1`
+    // and `I`
+    "ABCDEFGHJKLMNPQRSTUVWXYZ23456789".to_owned()
+}
+
+/// Configuration options for the device authorization grant
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct DeviceCodeConfig {
+    /// Number of characters in the user codes users have to type. Defaults to
+    /// 6.
+    #[schemars(range(min = 4, max = 16))]
+    #[serde(default = "default_user_code_length")]
+    pub user_code_length: usize,
+
+    /// Characters user codes are made of. Defaults to uppercase letters and
+    /// digits, excluding the ambiguous `0`, `O`, `1` and `I`.
+    ///
+    /// Codes are matched case-insensitively, so this must not contain
+    /// lowercase letters.
+    #[serde(default = "default_user_code_alphabet")]
+    pub user_code_alphabet: String,
+}
+
+impl Default for DeviceCodeConfig {
+    fn default() -> Self {
+        Self {
+            user_code_length: default_user_code_length(),
+            user_code_alphabet: default_user_code_alphabet(),
+        }
+    }
+}
+
+impl DeviceCodeConfig {
+    pub(crate) fn is_default(&self) -> bool {
+        self.user_code_length == default_user_code_length()
+            && self.user_code_alphabet == default_user_code_alphabet()
+    }
+}
+
 /// Configuration sections for experimental options
 ///
 /// Do not change these options unless you know what you are doing.
@@ -81,6 +127,45 @@ pub struct ExperimentalConfig {
     /// validation.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub plan_management_iframe_uri: Option<String>,
+
+    /// Experimental support for rich authorization requests (RFC 9396).
+    ///
+    /// List of the `authorization_details` types clients are allowed to
+    /// request. Those are not interpreted by the service: they are stored on
+    /// the session and returned by the introspection endpoint, so that
+    /// resource servers can enforce them.
+    ///
+    /// Rich authorization requests are rejected if this is empty, which is the
+    /// default.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authorization_details_types: Vec<String>,
+
+    /// Options for the user codes of the device authorization grant
+    #[serde(default, skip_serializing_if = "DeviceCodeConfig::is_default")]
+    pub device_code: DeviceCodeConfig,
+
+    /// Time-to-live in seconds of the cache of active token introspection
+    /// results.
+    ///
+    /// Cached results are dropped when the token is revoked through the
+    /// revocation endpoint, but sessions ended by other means may still be
+    /// reported as active until their cache entry expires, so this should be
+    /// kept short.
+    ///
+    /// Disabled by default.
+    #[schemars(with = "Option<u64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<serde_with::DurationSeconds<i64>>")]
+    pub introspection_cache_ttl: Option<Duration>,
+
+    /// Enforce the OAuth 2.1 behaviour for all clients: PKCE is required in
+    /// authorization code flows, the implicit and password grants are
+    /// rejected, redirect URIs must match exactly, including the port of
+    /// loopback URIs, and refresh tokens are always rotated on use.
+    ///
+    /// Disabled by default.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub oauth2_1_strict: bool,
 }
 
 impl Default for ExperimentalConfig {
@@ -90,6 +175,10 @@ impl Default for ExperimentalConfig {
             compat_token_ttl: default_token_ttl(),
             inactive_session_expiration: None,
             plan_management_iframe_uri: None,
+            authorization_details_types: Vec::new(),
+            device_code: DeviceCodeConfig::default(),
+            introspection_cache_ttl: None,
+            oauth2_1_strict: false,
         }
     }
 }
@@ -100,9 +189,79 @@ impl ExperimentalConfig {
             && is_default_token_ttl(&self.compat_token_ttl)
             && self.inactive_session_expiration.is_none()
             && self.plan_management_iframe_uri.is_none()
+            && self.authorization_details_types.is_empty()
+            && self.device_code.is_default()
+            && self.introspection_cache_ttl.is_none()
+            && !self.oauth2_1_strict
     }
 }
 
 impl ConfigurationSection for ExperimentalConfig {
     const PATH: Option<&'static str> = Some("experimental");
+
+    fn validate(&self, figment: &figment::Figment) -> Result<(), figment::Error> {
+        if self
+            .introspection_cache_ttl
+            .is_some_and(|ttl| ttl <= Duration::zero())
+        {
+            let mut error = figment::Error::custom("introspection cache TTL must be positive");
+            error.metadata = figment
+                .find_metadata(&format!(
+                    "{root}.introspection_cache_ttl",
+                    root = Self::PATH.unwrap()
+                ))
+                .cloned();
+            error.profile = Some(figment::Profile::Default);
+            error.path = vec![
+                Self::PATH.unwrap().to_owned(),
+                "introspection_cache_ttl".to_owned(),
+            ];
+            return Err(error);
+        }
+
+        let annotate = |mut error: figment::Error, field: &str| {
+            error.metadata = figment
+                .find_metadata(&format!("{root}.device_code", root = Self::PATH.unwrap()))
+                .cloned();
+            error.profile = Some(figment::Profile::Default);
+            error.path = vec![
+                Self::PATH.unwrap().to_owned(),
+                "device_code".to_owned(),
+                field.to_owned(),
+            ];
+            Err(error)
+        };
+
+        if !(4..=16).contains(&self.device_code.user_code_length) {
+            return annotate(
+                figment::Error::custom("user code length must be between 4 and 16"),
+                "user_code_length",
+            );
+        }
+
+        let alphabet = &self.device_code.user_code_alphabet;
+        let unique: BTreeSet<char> = alphabet.chars().collect();
+        if unique.len() < 10 || unique.len() != alphabet.chars().count() {
+            return annotate(
+                figment::Error::custom(
+                    "user code alphabet must have at least 10 distinct characters, without duplicates",
+                ),
+                "user_code_alphabet",
+            );
+        }
+
+        if alphabet
+            .chars()
+            .any(|c| c.is_lowercase() || c.is_whitespace() || c.is_control())
+        {
+            return annotate(
+                figment::Error::custom(
+                    "user code alphabet must not contain lowercase letters or whitespace",
+                ),
+                "user_code_alphabet",
+            );
+        }
+
+        Ok(())
+    }
 }
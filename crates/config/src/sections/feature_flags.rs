@@ -0,0 +1,96 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, de::Error};
+
+use crate::ConfigurationSection;
+
+const fn default_true() -> bool {
+    true
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+const fn is_default_true(value: &bool) -> bool {
+    *value
+}
+
+const fn default_rollout_percentage() -> u8 {
+    100
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+const fn is_default_rollout_percentage(value: &u8) -> bool {
+    *value == default_rollout_percentage()
+}
+
+/// How a single feature is rolled out
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct FeatureFlagConfig {
+    /// Whether the feature is enabled at all. Setting this to `false` turns
+    /// the feature off for everyone, regardless of the rollout percentage.
+    ///
+    /// Defaults to `true`.
+    #[serde(default = "default_true", skip_serializing_if = "is_default_true")]
+    pub enabled: bool,
+
+    /// The percentage of users for which the feature is enabled. Users are
+    /// picked using a hash of their ID, so that they keep the feature as the
+    /// percentage grows.
+    ///
+    /// Defaults to 100.
+    #[serde(
+        default = "default_rollout_percentage",
+        skip_serializing_if = "is_default_rollout_percentage"
+    )]
+    #[schemars(range(min = 0, max = 100))]
+    pub rollout_percentage: u8,
+}
+
+/// Configuration section to roll out features gradually, or to turn them off
+///
+/// Each entry is keyed by the name of the feature. Features which are not
+/// listed here are enabled for everyone. The rollouts set here can be
+/// overridden at runtime through the admin API.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(transparent)]
+pub struct FeatureFlagsConfig {
+    /// The rollout of each feature
+    pub flags: BTreeMap<String, FeatureFlagConfig>,
+}
+
+impl FeatureFlagsConfig {
+    /// Returns true if the configuration is the default one
+    pub(crate) fn is_default(&self) -> bool {
+        self.flags.is_empty()
+    }
+}
+
+impl ConfigurationSection for FeatureFlagsConfig {
+    const PATH: Option<&'static str> = Some("feature_flags");
+
+    fn validate(&self, figment: &figment::Figment) -> Result<(), figment::error::Error> {
+        for (name, flag) in &self.flags {
+            if flag.rollout_percentage > 100 {
+                let metadata = figment.find_metadata(Self::PATH.unwrap());
+                let mut error = figment::error::Error::custom(format!(
+                    "the rollout percentage of {name} must be between 0 and 100"
+                ));
+                error.metadata = metadata.cloned();
+                error.profile = Some(figment::Profile::Default);
+                error.path = vec![
+                    Self::PATH.unwrap().to_owned(),
+                    name.clone(),
+                    "rollout_percentage".to_owned(),
+                ];
+                return Err(error);
+            }
+        }
+
+        Ok(())
+    }
+}
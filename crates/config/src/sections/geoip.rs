@@ -0,0 +1,41 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use camino::Utf8PathBuf;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::ConfigurationSection;
+
+/// Configuration section to resolve where the IP addresses of sessions are
+/// located, using MaxMind-style GeoIP databases
+///
+/// The databases are reloaded when the service receives a `SIGHUP`, so they
+/// can be updated in place.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+pub struct GeoIpConfig {
+    /// Path to a database resolving IP addresses to countries, like the
+    /// GeoLite2 Country or City databases
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    pub country_database: Option<Utf8PathBuf>,
+
+    /// Path to a database resolving IP addresses to autonomous systems, like
+    /// the GeoLite2 ASN database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    pub asn_database: Option<Utf8PathBuf>,
+}
+
+impl GeoIpConfig {
+    /// Returns true if the configuration is the default one
+    pub(crate) fn is_default(&self) -> bool {
+        self.country_database.is_none() && self.asn_database.is_none()
+    }
+}
+
+impl ConfigurationSection for GeoIpConfig {
+    const PATH: Option<&'static str> = Some("geoip");
+}
@@ -145,7 +145,7 @@ pub enum BindConfig {
     },
 }
 
-/// Configuration related to TLS on a listener
+/// Configuration of a TLS certificate chain and its private key
 #[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct TlsConfig {
     /// PEM-encoded X509 certificate chain
@@ -329,6 +329,9 @@ pub struct ListenerConfig {
     pub resources: Vec<Resource>,
 
     /// HTTP prefix to mount the resources on
+    ///
+    /// If this listener serves public resources, it must match the path of
+    /// `http.public_base`.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub prefix: Option<String>,
 
@@ -362,6 +365,16 @@ pub struct HttpConfig {
     /// OIDC issuer URL. Defaults to `public_base` if not set.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub issuer: Option<Url>,
+
+    /// Additional OIDC issuer URLs the service answers as, for example during
+    /// a domain migration.
+    ///
+    /// The issuer is picked from the `Host` header of each request. Requests
+    /// sent to the host of an alias get its discovery document and tokens
+    /// with it as issuer, with the endpoints served under the same path as
+    /// `public_base` on that host.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issuer_aliases: Vec<Url>,
 }
 
 impl Default for HttpConfig {
@@ -404,6 +417,7 @@ impl Default for HttpConfig {
             ],
             trusted_proxies: default_trusted_proxies(),
             issuer: Some(default_public_base()),
+            issuer_aliases: Vec::new(),
             public_base: default_public_base(),
         }
     }
@@ -413,6 +427,89 @@ impl ConfigurationSection for HttpConfig {
     const PATH: Option<&'static str> = Some("http");
 
     fn validate(&self, figment: &figment::Figment) -> Result<(), figment::Error> {
+        let annotate_field = |mut error: figment::Error, field: &str| {
+            error.metadata = figment
+                .find_metadata(&format!("{root}.{field}", root = Self::PATH.unwrap()))
+                .cloned();
+            error.profile = Some(figment::Profile::Default);
+            error.path = vec![Self::PATH.unwrap().to_owned(), field.to_owned()];
+            Err(error)
+        };
+
+        if !matches!(self.public_base.scheme(), "http" | "https") {
+            return annotate_field(
+                figment::Error::from("public base URL must use http or https".to_owned()),
+                "public_base",
+            );
+        }
+
+        if self.public_base.query().is_some() || self.public_base.fragment().is_some() {
+            return annotate_field(
+                figment::Error::from(
+                    "public base URL must not have a query or a fragment".to_owned(),
+                ),
+                "public_base",
+            );
+        }
+
+        if !self.public_base.username().is_empty() || self.public_base.password().is_some() {
+            return annotate_field(
+                figment::Error::from("public base URL must not have credentials".to_owned()),
+                "public_base",
+            );
+        }
+
+        if let Some(issuer) = &self.issuer {
+            if !matches!(issuer.scheme(), "http" | "https") {
+                return annotate_field(
+                    figment::Error::from("issuer must use http or https".to_owned()),
+                    "issuer",
+                );
+            }
+
+            if issuer.query().is_some() || issuer.fragment().is_some() {
+                return annotate_field(
+                    figment::Error::from("issuer must not have a query or a fragment".to_owned()),
+                    "issuer",
+                );
+            }
+        }
+
+        let issuer = self.issuer.as_ref().unwrap_or(&self.public_base);
+        let mut hosts = vec![(issuer.host_str(), issuer.port())];
+        for alias in &self.issuer_aliases {
+            if !matches!(alias.scheme(), "http" | "https") {
+                return annotate_field(
+                    figment::Error::from(format!("issuer alias {alias} must use http or https")),
+                    "issuer_aliases",
+                );
+            }
+
+            if alias.query().is_some() || alias.fragment().is_some() {
+                return annotate_field(
+                    figment::Error::from(format!(
+                        "issuer alias {alias} must not have a query or a fragment"
+                    )),
+                    "issuer_aliases",
+                );
+            }
+
+            // The issuer is picked from the host of the request, so each of them
+            // must be on its own host
+            let host = (alias.host_str(), alias.port());
+            if hosts.contains(&host) {
+                return annotate_field(
+                    figment::Error::from(format!(
+                        "issuer alias {alias} is on the same host as another issuer"
+                    )),
+                    "issuer_aliases",
+                );
+            }
+            hosts.push(host);
+        }
+
+        let public_base_path = self.public_base.path().trim_end_matches('/');
+
         for (index, listener) in self.listeners.iter().enumerate() {
             let annotate = |mut error: figment::Error| {
                 error.metadata = figment
@@ -437,6 +534,34 @@ impl ConfigurationSection for HttpConfig {
                 ));
             }
 
+            if let Some(prefix) = &listener.prefix {
+                if !prefix.starts_with('/') {
+                    return annotate(figment::Error::from(format!(
+                        "listener prefix {prefix:?} must start with a slash"
+                    )));
+                }
+
+                // Public resources generate URLs from `public_base`, so if the listener
+                // mounts them under a prefix, it must match the path of the public base
+                let serves_public_resources = listener.resources.iter().any(|resource| {
+                    matches!(
+                        resource,
+                        Resource::Human
+                            | Resource::OAuth
+                            | Resource::Compat
+                            | Resource::GraphQL { .. }
+                            | Resource::Assets { .. }
+                    )
+                });
+
+                if serves_public_resources && prefix.trim_end_matches('/') != public_base_path {
+                    return annotate(figment::Error::from(format!(
+                        "listener prefix {prefix:?} does not match the path of `http.public_base` ({path:?})",
+                        path = self.public_base.path(),
+                    )));
+                }
+            }
+
             if let Some(tls_config) = &listener.tls {
                 if tls_config.certificate.is_some() && tls_config.certificate_file.is_some() {
                     return annotate(figment::Error::from(
@@ -474,3 +599,147 @@ impl ConfigurationSection for HttpConfig {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use figment::{
+        Figment, Jail,
+        providers::{Format, Yaml},
+    };
+
+    use super::*;
+
+    #[test]
+    fn accept_matching_prefix() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    http:
+                      public_base: https://example.com/auth/
+                      issuer: https://example.com/
+                      listeners:
+                        - name: web
+                          prefix: /auth
+                          resources:
+                            - name: discovery
+                            - name: human
+                            - name: oauth
+                            - name: compat
+                          binds:
+                            - address: "[::]:8080"
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<HttpConfig>("http")?;
+            assert!(config.validate(&figment).is_ok());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn accept_sub_path_without_prefix() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    http:
+                      public_base: https://example.com/auth
+                      listeners:
+                        - name: web
+                          resources:
+                            - name: human
+                          binds:
+                            - address: "[::]:8080"
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<HttpConfig>("http")?;
+            assert!(config.validate(&figment).is_ok());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reject_mismatched_prefix() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    http:
+                      public_base: https://example.com/auth/
+                      listeners:
+                        - name: web
+                          prefix: /mas
+                          resources:
+                            - name: human
+                          binds:
+                            - address: "[::]:8080"
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<HttpConfig>("http")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reject_issuer_alias_on_same_host() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    http:
+                      public_base: https://auth.example.com/
+                      issuer_aliases:
+                        - https://auth.example.org/
+                        - https://auth.example.com/other/
+                      listeners:
+                        - name: web
+                          resources:
+                            - name: human
+                          binds:
+                            - address: "[::]:8080"
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<HttpConfig>("http")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reject_invalid_public_base() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    http:
+                      public_base: https://example.com/auth/?foo=bar
+                      listeners:
+                        - name: web
+                          resources:
+                            - name: human
+                          binds:
+                            - address: "[::]:8080"
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<HttpConfig>("http")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
+}
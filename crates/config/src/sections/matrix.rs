@@ -4,6 +4,7 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use mas_iana::jose::JsonWebSignatureAlg;
 use rand::{
     Rng,
     distributions::{Alphanumeric, DistString},
@@ -56,10 +57,46 @@ pub struct MatrixConfig {
     /// The base URL of the homeserver's client API
     #[serde(default = "default_endpoint")]
     pub endpoint: Url,
+
+    /// Sign the requests made to the homeserver and to the SMS webhook using
+    /// HTTP Message Signatures (RFC 9421), with a key of the keystore matching
+    /// this algorithm
+    ///
+    /// Supported algorithms are `RS256`, `PS512`, `ES256` and `ES384`.
+    /// Requests are not signed if this is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_signing_alg: Option<JsonWebSignatureAlg>,
 }
 
 impl ConfigurationSection for MatrixConfig {
     const PATH: Option<&'static str> = Some("matrix");
+
+    fn validate(&self, figment: &figment::Figment) -> Result<(), figment::Error> {
+        let unsupported_alg = self.request_signing_alg.as_ref().filter(|alg| {
+            !matches!(
+                alg,
+                JsonWebSignatureAlg::Rs256
+                    | JsonWebSignatureAlg::Ps512
+                    | JsonWebSignatureAlg::Es256
+                    | JsonWebSignatureAlg::Es384
+            )
+        });
+
+        if let Some(alg) = unsupported_alg {
+            let mut error = figment::error::Error::custom(format!(
+                "the {alg} algorithm can't be used to sign requests"
+            ));
+            error.metadata = figment.find_metadata(Self::PATH.unwrap()).cloned();
+            error.profile = Some(figment::Profile::Default);
+            error.path = vec![
+                Self::PATH.unwrap().to_owned(),
+                "request_signing_alg".to_owned(),
+            ];
+            return Err(error);
+        }
+
+        Ok(())
+    }
 }
 
 impl MatrixConfig {
@@ -72,6 +109,7 @@ impl MatrixConfig {
             homeserver: default_homeserver(),
             secret: Alphanumeric.sample_string(&mut rng, 32),
             endpoint: default_endpoint(),
+            request_signing_alg: None,
         }
     }
 
@@ -81,6 +119,7 @@ impl MatrixConfig {
             homeserver: default_homeserver(),
             secret: "test".to_owned(),
             endpoint: default_endpoint(),
+            request_signing_alg: None,
         }
     }
 }
@@ -9,52 +9,87 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 mod account;
+mod acr;
 mod branding;
 mod captcha;
+mod client_registration;
 mod clients;
 mod database;
 mod email;
 mod experimental;
+mod feature_flags;
+mod geoip;
 mod http;
 mod matrix;
 mod passwords;
 mod policy;
 mod rate_limiting;
+mod scim;
 mod secrets;
+mod sms;
 mod telemetry;
 mod templates;
 mod upstream_oauth2;
+mod user_attributes;
 
 pub use self::{
-    account::AccountConfig,
+    account::{
+        AccountConfig, HomeRealmDiscoveryConfig, TermsDocumentConfig, TotpPolicyConfig,
+        UserInvitesConfig,
+    },
+    acr::{AcrConfig, AcrValueConfig, AuthenticationMethodReference},
     branding::BrandingConfig,
     captcha::{CaptchaConfig, CaptchaServiceKind},
-    clients::{ClientAuthMethodConfig, ClientConfig, ClientsConfig},
-    database::{DatabaseConfig, PgSslMode},
+    client_registration::{ClientRegistrationConfig, SoftwareStatementIssuerConfig},
+    clients::{
+        AccessTokenFormatConfig, BackchannelLogoutConfig, ClientAuthMethodConfig, ClientConfig,
+        ClientsConfig, DisallowedScopesConfig, RefreshTokenRotationConfig, ResourceServerConfig,
+        ScopePolicyConfig, ServiceAccountConfig,
+    },
+    database::{DatabaseBackend, DatabaseConfig, PgSslMode},
     email::{EmailConfig, EmailSmtpMode, EmailTransportKind},
     experimental::ExperimentalConfig,
+    feature_flags::{FeatureFlagConfig, FeatureFlagsConfig},
+    geoip::GeoIpConfig,
     http::{
         BindConfig as HttpBindConfig, HttpConfig, ListenerConfig as HttpListenerConfig,
         Resource as HttpResource, TlsConfig as HttpTlsConfig, UnixOrTcp,
     },
     matrix::{HomeserverKind, MatrixConfig},
     passwords::{
-        Algorithm as PasswordAlgorithm, HashingScheme as PasswordHashingScheme, PasswordsConfig,
+        Algorithm as PasswordAlgorithm, HashingScheme as PasswordHashingScheme,
+        PasswordLockoutConfig, PasswordsConfig,
     },
     policy::PolicyConfig,
     rate_limiting::RateLimitingConfig,
+    scim::{ScimClientConfig, ScimConfig},
     secrets::SecretsConfig,
+    sms::{SmsConfig, SmsTransportKind},
     telemetry::{
         MetricsConfig, MetricsExporterKind, Propagator, TelemetryConfig, TracingConfig,
         TracingExporterKind,
     },
     templates::TemplatesConfig,
     upstream_oauth2::{
-        ClaimsImports as UpstreamOAuth2ClaimsImports, DiscoveryMode as UpstreamOAuth2DiscoveryMode,
+        CircuitBreakerConfig as UpstreamOAuth2CircuitBreakerConfig,
+        ClaimsImports as UpstreamOAuth2ClaimsImports,
+        DeprovisioningAction as UpstreamOAuth2DeprovisioningAction,
+        DeprovisioningConfig as UpstreamOAuth2DeprovisioningConfig,
+        DeprovisioningSource as UpstreamOAuth2DeprovisioningSource,
+        DiscoveryMode as UpstreamOAuth2DiscoveryMode,
         EmailImportPreference as UpstreamOAuth2EmailImportPreference,
-        ImportAction as UpstreamOAuth2ImportAction, PkceMethod as UpstreamOAuth2PkceMethod,
-        Provider as UpstreamOAuth2Provider, ResponseMode as UpstreamOAuth2ResponseMode,
-        TokenAuthMethod as UpstreamOAuth2TokenAuthMethod, UpstreamOAuth2Config,
+        HealthChecksConfig as UpstreamOAuth2HealthChecksConfig,
+        ImportAction as UpstreamOAuth2ImportAction,
+        MetadataCacheConfig as UpstreamOAuth2MetadataCacheConfig,
+        OnConflict as UpstreamOAuth2OnConflict, PkceMethod as UpstreamOAuth2PkceMethod,
+        Provider as UpstreamOAuth2Provider, ProviderPreset as UpstreamOAuth2ProviderPreset,
+        ResponseMode as UpstreamOAuth2ResponseMode,
+        SubjectMigrationRule as UpstreamOAuth2SubjectMigrationRule,
+        SyncMode as UpstreamOAuth2SyncMode, TokenAuthMethod as UpstreamOAuth2TokenAuthMethod,
+        UpstreamOAuth2Config,
+    },
+    user_attributes::{
+        UserAttributeConfig, UserAttributeType, UserAttributeVisibility, UserAttributesConfig,
     },
 };
 use crate::util::ConfigurationSection;
@@ -86,6 +121,10 @@ pub struct RootConfig {
     #[serde(default)]
     pub email: EmailConfig,
 
+    /// Configuration related to sending text messages
+    #[serde(default, skip_serializing_if = "SmsConfig::is_default")]
+    pub sms: SmsConfig,
+
     /// Application secrets
     pub secrets: SecretsConfig,
 
@@ -122,6 +161,31 @@ pub struct RootConfig {
     #[serde(default, skip_serializing_if = "AccountConfig::is_default")]
     pub account: AccountConfig,
 
+    /// Configuration related to SCIM directories
+    #[serde(default, skip_serializing_if = "ScimConfig::is_default")]
+    pub scim: ScimConfig,
+
+    /// Custom attributes which can be set on users
+    #[serde(default, skip_serializing_if = "UserAttributesConfig::is_default")]
+    pub user_attributes: UserAttributesConfig,
+
+    /// Configuration related to resolving where IP addresses are located
+    #[serde(default, skip_serializing_if = "GeoIpConfig::is_default")]
+    pub geoip: GeoIpConfig,
+
+    /// Configuration related to the dynamic registration of clients
+    #[serde(default, skip_serializing_if = "ClientRegistrationConfig::is_default")]
+    pub client_registration: ClientRegistrationConfig,
+
+    /// Configuration related to the authentication context classes clients can
+    /// require
+    #[serde(default, skip_serializing_if = "AcrConfig::is_default")]
+    pub acr: AcrConfig,
+
+    /// Configuration related to the gradual rollout of features
+    #[serde(default, skip_serializing_if = "FeatureFlagsConfig::is_default")]
+    pub feature_flags: FeatureFlagsConfig,
+
     /// Experimental configuration options
     #[serde(default, skip_serializing_if = "ExperimentalConfig::is_default")]
     pub experimental: ExperimentalConfig,
@@ -135,6 +199,7 @@ impl ConfigurationSection for RootConfig {
         self.telemetry.validate(figment)?;
         self.templates.validate(figment)?;
         self.email.validate(figment)?;
+        self.sms.validate(figment)?;
         self.passwords.validate(figment)?;
         self.secrets.validate(figment)?;
         self.matrix.validate(figment)?;
@@ -144,6 +209,12 @@ impl ConfigurationSection for RootConfig {
         self.branding.validate(figment)?;
         self.captcha.validate(figment)?;
         self.account.validate(figment)?;
+        self.scim.validate(figment)?;
+        self.user_attributes.validate(figment)?;
+        self.geoip.validate(figment)?;
+        self.client_registration.validate(figment)?;
+        self.acr.validate(figment)?;
+        self.feature_flags.validate(figment)?;
         self.experimental.validate(figment)?;
 
         Ok(())
@@ -167,6 +238,7 @@ impl RootConfig {
             telemetry: TelemetryConfig::default(),
             templates: TemplatesConfig::default(),
             email: EmailConfig::default(),
+            sms: SmsConfig::default(),
             passwords: PasswordsConfig::default(),
             secrets: SecretsConfig::generate(&mut rng).await?,
             matrix: MatrixConfig::generate(&mut rng),
@@ -176,6 +248,12 @@ impl RootConfig {
             branding: BrandingConfig::default(),
             captcha: CaptchaConfig::default(),
             account: AccountConfig::default(),
+            scim: ScimConfig::default(),
+            user_attributes: UserAttributesConfig::default(),
+            geoip: GeoIpConfig::default(),
+            client_registration: ClientRegistrationConfig::default(),
+            acr: AcrConfig::default(),
+            feature_flags: FeatureFlagsConfig::default(),
             experimental: ExperimentalConfig::default(),
         })
     }
@@ -191,6 +269,7 @@ impl RootConfig {
             templates: TemplatesConfig::default(),
             passwords: PasswordsConfig::default(),
             email: EmailConfig::default(),
+            sms: SmsConfig::default(),
             secrets: SecretsConfig::test(),
             matrix: MatrixConfig::test(),
             policy: PolicyConfig::default(),
@@ -199,6 +278,12 @@ impl RootConfig {
             branding: BrandingConfig::default(),
             captcha: CaptchaConfig::default(),
             account: AccountConfig::default(),
+            scim: ScimConfig::default(),
+            user_attributes: UserAttributesConfig::default(),
+            geoip: GeoIpConfig::default(),
+            client_registration: ClientRegistrationConfig::default(),
+            acr: AcrConfig::default(),
+            feature_flags: FeatureFlagsConfig::default(),
             experimental: ExperimentalConfig::default(),
         }
     }
@@ -220,6 +305,9 @@ pub struct AppConfig {
     #[serde(default)]
     pub email: EmailConfig,
 
+    #[serde(default)]
+    pub sms: SmsConfig,
+
     pub secrets: SecretsConfig,
 
     #[serde(default)]
@@ -242,6 +330,24 @@ pub struct AppConfig {
     #[serde(default)]
     pub account: AccountConfig,
 
+    #[serde(default)]
+    pub scim: ScimConfig,
+
+    #[serde(default)]
+    pub user_attributes: UserAttributesConfig,
+
+    #[serde(default)]
+    pub geoip: GeoIpConfig,
+
+    #[serde(default)]
+    pub client_registration: ClientRegistrationConfig,
+
+    #[serde(default)]
+    pub acr: AcrConfig,
+
+    #[serde(default)]
+    pub feature_flags: FeatureFlagsConfig,
+
     #[serde(default)]
     pub experimental: ExperimentalConfig,
 }
@@ -252,6 +358,7 @@ impl ConfigurationSection for AppConfig {
         self.database.validate(figment)?;
         self.templates.validate(figment)?;
         self.email.validate(figment)?;
+        self.sms.validate(figment)?;
         self.passwords.validate(figment)?;
         self.secrets.validate(figment)?;
         self.matrix.validate(figment)?;
@@ -260,6 +367,12 @@ impl ConfigurationSection for AppConfig {
         self.branding.validate(figment)?;
         self.captcha.validate(figment)?;
         self.account.validate(figment)?;
+        self.scim.validate(figment)?;
+        self.user_attributes.validate(figment)?;
+        self.geoip.validate(figment)?;
+        self.client_registration.validate(figment)?;
+        self.acr.validate(figment)?;
+        self.feature_flags.validate(figment)?;
         self.experimental.validate(figment)?;
 
         Ok(())
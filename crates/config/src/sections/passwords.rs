@@ -8,8 +8,10 @@ use std::cmp::Reverse;
 
 use anyhow::bail;
 use camino::Utf8PathBuf;
+use chrono::Duration;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 
 use crate::ConfigurationSection;
 
@@ -32,7 +34,52 @@ fn default_minimum_complexity() -> u8 {
     3
 }
 
+fn default_lockout_max_failures() -> u32 {
+    10
+}
+
+fn default_lockout_max_failures_per_ip() -> u32 {
+    50
+}
+
+fn default_lockout_duration() -> Duration {
+    Duration::microseconds(15 * 60 * 1000 * 1000)
+}
+
+/// Temporary lockout of accounts after repeated failed password attempts
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PasswordLockoutConfig {
+    /// Number of consecutive failed password attempts on an account after
+    /// which it is temporarily locked. Defaults to 10.
+    #[schemars(range(min = 1))]
+    #[serde(default = "default_lockout_max_failures")]
+    pub max_failures: u32,
+
+    /// Number of failed password attempts coming from the same IP address,
+    /// across all accounts, after which logins from this address have to solve
+    /// a CAPTCHA. If no CAPTCHA service is configured, logins from this address
+    /// are refused until the lockout expires. Defaults to 50.
+    #[schemars(range(min = 1))]
+    #[serde(default = "default_lockout_max_failures_per_ip")]
+    pub max_failures_per_ip: u32,
+
+    /// How long failed attempts are remembered, in seconds. Accounts and IP
+    /// addresses are unlocked once they have fewer failed attempts than the
+    /// thresholds over this period. Defaults to 15 minutes.
+    #[schemars(with = "u64", range(min = 60, max = 86400))]
+    #[serde(default = "default_lockout_duration")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub duration: Duration,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+const fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
 /// User password hashing config
+#[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PasswordsConfig {
     /// Whether password-based authentication is enabled
@@ -57,6 +104,32 @@ pub struct PasswordsConfig {
     /// - 4: any more than that
     #[serde(default = "default_minimum_complexity")]
     minimum_complexity: u8,
+
+    /// Temporarily lock accounts after repeated failed password attempts
+    ///
+    /// Disabled by default. Administrators can clear the lockout of an
+    /// account through the admin API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lockout: Option<PasswordLockoutConfig>,
+
+    /// Maximum age of passwords, in seconds
+    ///
+    /// Users whose password is older than this have to change it the next
+    /// time they log in. Passwords never expire by default. Administrators can
+    /// override this for individual users through the admin API.
+    #[schemars(with = "Option<u64>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<serde_with::DurationSeconds<i64>>")]
+    pub max_age: Option<Duration>,
+
+    /// Number of previous passwords users can't reuse when changing their
+    /// password
+    ///
+    /// Defaults to 0, which allows reusing any previous password.
+    /// Administrators can override this for individual users through the admin
+    /// API.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub history_size: u32,
 }
 
 impl Default for PasswordsConfig {
@@ -65,6 +138,9 @@ impl Default for PasswordsConfig {
             enabled: default_enabled(),
             schemes: default_schemes(),
             minimum_complexity: default_minimum_complexity(),
+            lockout: None,
+            max_age: None,
+            history_size: 0,
         }
     }
 }
@@ -85,6 +161,15 @@ impl ConfigurationSection for PasswordsConfig {
             return Ok(());
         }
 
+        if self
+            .max_age
+            .is_some_and(|max_age| max_age <= Duration::zero())
+        {
+            return annotate(figment::Error::from(
+                "The maximum age of passwords must be positive".to_owned(),
+            ));
+        }
+
         if self.schemes.is_empty() {
             return annotate(figment::Error::from(
                 "Requires at least one password scheme in the config".to_owned(),
@@ -97,6 +182,25 @@ impl ConfigurationSection for PasswordsConfig {
                     "Cannot specify both `secret` and `secret_file`".to_owned(),
                 ));
             }
+
+            if !scheme.algorithm.can_hash()
+                && (scheme.secret.is_some() || scheme.secret_file.is_some())
+            {
+                return annotate(figment::Error::from(format!(
+                    "The `{}` algorithm does not support secrets",
+                    scheme.algorithm
+                )));
+            }
+        }
+
+        // The current scheme is the one with the highest version, and it is used to
+        // hash new passwords
+        let current = self.schemes.iter().max_by_key(|scheme| scheme.version);
+        if let Some(current) = current.filter(|scheme| !scheme.algorithm.can_hash()) {
+            return annotate(figment::Error::from(format!(
+                "The `{}` algorithm can only verify imported passwords, it can't be used by the password scheme with the highest version",
+                current.algorithm
+            )));
         }
 
         Ok(())
@@ -222,4 +326,41 @@ pub enum Algorithm {
 
     /// PBKDF2
     Pbkdf2,
+
+    /// PBKDF2 hashes in the format used by Django, like
+    /// `pbkdf2_sha256$<iterations>$<salt>$<hash>`
+    ///
+    /// This can only verify passwords imported from a Django application. They
+    /// get upgraded to the current scheme when the user logs in.
+    #[serde(rename = "django_pbkdf2")]
+    DjangoPbkdf2,
+
+    /// Salted SHA hashes in the format used by LDAP directories, like
+    /// `{SSHA}<base64>`. `{SHA}`, `{SSHA}`, `{SHA256}`, `{SSHA256}`, `{SHA512}`
+    /// and `{SSHA512}` are supported.
+    ///
+    /// This can only verify passwords imported from an LDAP directory. They
+    /// get upgraded to the current scheme when the user logs in.
+    Ldap,
+}
+
+impl Algorithm {
+    /// Whether this algorithm can hash new passwords, or only verify imported
+    /// ones
+    #[must_use]
+    pub const fn can_hash(self) -> bool {
+        matches!(self, Self::Bcrypt | Self::Argon2id | Self::Pbkdf2)
+    }
+}
+
+impl std::fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bcrypt => f.write_str("bcrypt"),
+            Self::Argon2id => f.write_str("argon2id"),
+            Self::Pbkdf2 => f.write_str("pbkdf2"),
+            Self::DjangoPbkdf2 => f.write_str("django_pbkdf2"),
+            Self::Ldap => f.write_str("ldap"),
+        }
+    }
 }
@@ -70,6 +70,30 @@ fn is_default_email_entrypoint(value: &String) -> bool {
     *value == default_email_entrypoint()
 }
 
+fn default_login_entrypoint() -> String {
+    "login/violation".to_owned()
+}
+
+fn is_default_login_entrypoint(value: &String) -> bool {
+    *value == default_login_entrypoint()
+}
+
+fn default_display_name_entrypoint() -> String {
+    "display_name/violation".to_owned()
+}
+
+fn is_default_display_name_entrypoint(value: &String) -> bool {
+    *value == default_display_name_entrypoint()
+}
+
+fn default_upstream_login_entrypoint() -> String {
+    "upstream_login/violation".to_owned()
+}
+
+fn is_default_upstream_login_entrypoint(value: &String) -> bool {
+    *value == default_upstream_login_entrypoint()
+}
+
 fn default_data() -> serde_json::Value {
     serde_json::json!({})
 }
@@ -125,6 +149,27 @@ pub struct PolicyConfig {
     )]
     pub email_entrypoint: String,
 
+    /// Entrypoint to use when evaluating the risk of a login
+    #[serde(
+        default = "default_login_entrypoint",
+        skip_serializing_if = "is_default_login_entrypoint"
+    )]
+    pub login_entrypoint: String,
+
+    /// Entrypoint to use when changing the display name of a user
+    #[serde(
+        default = "default_display_name_entrypoint",
+        skip_serializing_if = "is_default_display_name_entrypoint"
+    )]
+    pub display_name_entrypoint: String,
+
+    /// Entrypoint to use when evaluating a login through an upstream provider
+    #[serde(
+        default = "default_upstream_login_entrypoint",
+        skip_serializing_if = "is_default_upstream_login_entrypoint"
+    )]
+    pub upstream_login_entrypoint: String,
+
     /// Arbitrary data to pass to the policy
     #[serde(default = "default_data", skip_serializing_if = "is_default_data")]
     pub data: serde_json::Value,
@@ -139,6 +184,9 @@ impl Default for PolicyConfig {
             authorization_grant_entrypoint: default_authorization_grant_entrypoint(),
             password_entrypoint: default_password_entrypoint(),
             email_entrypoint: default_email_entrypoint(),
+            login_entrypoint: default_login_entrypoint(),
+            display_name_entrypoint: default_display_name_entrypoint(),
+            upstream_login_entrypoint: default_upstream_login_entrypoint(),
             data: default_data(),
         }
     }
@@ -153,6 +201,9 @@ impl PolicyConfig {
             && is_default_authorization_grant_entrypoint(&self.authorization_grant_entrypoint)
             && is_default_password_entrypoint(&self.password_entrypoint)
             && is_default_email_entrypoint(&self.email_entrypoint)
+            && is_default_login_entrypoint(&self.login_entrypoint)
+            && is_default_display_name_entrypoint(&self.display_name_entrypoint)
+            && is_default_upstream_login_entrypoint(&self.upstream_login_entrypoint)
             && is_default_data(&self.data)
     }
 }
@@ -31,6 +31,13 @@ pub struct RateLimitingConfig {
     /// Email authentication-specific rate limits
     #[serde(default)]
     pub email_authentication: EmailauthenticationRateLimitingConfig,
+
+    /// Controls how many requests are made to the token and userinfo
+    /// endpoints of each upstream OAuth 2.0 provider.
+    /// This prevents a misbehaving provider from exhausting the outbound
+    /// connections.
+    #[serde(default = "default_upstream_oauth2_per_provider")]
+    pub upstream_oauth2_per_provider: RateLimiterConfiguration,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
@@ -175,6 +182,10 @@ impl ConfigurationSection for RateLimitingConfig {
             return Err(error_on_nested_field(error, "login", "per_account"));
         }
 
+        if let Some(error) = error_on_limiter(&self.upstream_oauth2_per_provider) {
+            return Err(error_on_field(error, "upstream_oauth2_per_provider"));
+        }
+
         Ok(())
     }
 }
@@ -258,6 +269,13 @@ fn default_email_authentication_attempt_per_session() -> RateLimiterConfiguratio
     }
 }
 
+fn default_upstream_oauth2_per_provider() -> RateLimiterConfiguration {
+    RateLimiterConfiguration {
+        burst: NonZeroU32::new(100).unwrap(),
+        per_second: 20.0,
+    }
+}
+
 impl Default for RateLimitingConfig {
     fn default() -> Self {
         RateLimitingConfig {
@@ -265,6 +283,7 @@ impl Default for RateLimitingConfig {
             registration: default_registration(),
             account_recovery: AccountRecoveryRateLimitingConfig::default(),
             email_authentication: EmailauthenticationRateLimitingConfig::default(),
+            upstream_oauth2_per_provider: default_upstream_oauth2_per_provider(),
         }
     }
 }
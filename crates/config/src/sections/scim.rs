@@ -0,0 +1,136 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::ConfigurationSection;
+
+fn default_true() -> bool {
+    true
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_default_true(value: &bool) -> bool {
+    *value == default_true()
+}
+
+/// Configuration of the SCIM client, which periodically pulls users from an
+/// upstream SCIM directory
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct ScimClientConfig {
+    /// The base URL of the SCIM v2 API of the directory, e.g.
+    /// `https://directory.example.com/scim/v2/`
+    pub endpoint: Url,
+
+    /// The bearer token used to authenticate against the directory
+    pub token: String,
+
+    /// Only users which are members of this group are synchronised.
+    ///
+    /// This is matched against both the `value` and the `display` of the
+    /// user's groups. If not set, all users in the directory are synchronised.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+
+    /// Members of this group are allowed to request admin privileges.
+    ///
+    /// If not set, the admin privileges of users are left untouched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin_group: Option<String>,
+
+    /// Whether users previously synchronised from the directory should be
+    /// locked if they are no longer present in it. Defaults to `true`.
+    #[serde(default = "default_true", skip_serializing_if = "is_default_true")]
+    pub lock_missing_users: bool,
+}
+
+/// Configuration section related to SCIM directories
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+pub struct ScimConfig {
+    /// Pull users from an upstream SCIM directory on a schedule, creating and
+    /// locking accounts accordingly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<ScimClientConfig>,
+}
+
+impl ScimConfig {
+    /// Returns true if the configuration is the default one
+    pub(crate) fn is_default(&self) -> bool {
+        self.client.is_none()
+    }
+}
+
+impl ConfigurationSection for ScimConfig {
+    const PATH: Option<&'static str> = Some("scim");
+
+    fn validate(&self, figment: &figment::Figment) -> Result<(), figment::Error> {
+        let annotate = |mut error: figment::Error| {
+            error.metadata = figment.find_metadata(Self::PATH.unwrap()).cloned();
+            error.profile = Some(figment::Profile::Default);
+            error.path = vec![
+                Self::PATH.unwrap().to_owned(),
+                "client".to_owned(),
+                "endpoint".to_owned(),
+            ];
+            Err(error)
+        };
+
+        if self
+            .client
+            .as_ref()
+            .is_some_and(|client| client.endpoint.cannot_be_a_base())
+        {
+            return annotate(figment::Error::from(
+                "The SCIM endpoint must be a valid base URL".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::{
+        Figment, Jail,
+        providers::{Format, Yaml},
+    };
+
+    use super::*;
+
+    #[test]
+    fn load_config() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r"
+                    scim:
+                      client:
+                        endpoint: https://directory.example.com/scim/v2/
+                        token: secret
+                        group: matrix-users
+                ",
+            )?;
+
+            let config = Figment::new()
+                .merge(Yaml::file("config.yaml"))
+                .extract_inner::<ScimConfig>("scim")?;
+
+            let client = config.client.unwrap();
+            assert_eq!(
+                client.endpoint.as_str(),
+                "https://directory.example.com/scim/v2/"
+            );
+            assert_eq!(client.token, "secret");
+            assert_eq!(client.group.as_deref(), Some("matrix-users"));
+            assert_eq!(client.admin_group, None);
+            assert!(client.lock_missing_users);
+
+            Ok(())
+        });
+    }
+}
@@ -4,13 +4,13 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashSet};
 
 use anyhow::{Context, bail};
 use camino::Utf8PathBuf;
 use futures_util::future::{try_join, try_join_all};
 use mas_jose::jwk::{JsonWebKey, JsonWebKeySet};
-use mas_keystore::{Encrypter, Keystore, PrivateKey};
+use mas_keystore::{Encrypter, Keystore, LocalKeyWrapper, PrivateKey};
 use rand::{
     Rng, SeedableRng,
     distributions::{Alphanumeric, DistString, Standard},
@@ -239,6 +239,90 @@ impl From<Encryption> for EncryptionRaw {
     }
 }
 
+/// Envelope key fields as serialized in JSON.
+#[serde_as]
+#[derive(JsonSchema, Serialize, Deserialize, Debug, Clone)]
+struct EnvelopeKeyRaw {
+    /// File containing the master key.
+    #[schemars(with = "Option<String>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key_file: Option<Utf8PathBuf>,
+
+    /// The master key, as 32 hex-encoded bytes.
+    #[schemars(
+        with = "Option<String>",
+        regex(pattern = r"[0-9a-fA-F]{64}"),
+        example = "example_secret"
+    )]
+    #[serde_as(as = "Option<serde_with::hex::Hex>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<[u8; 32]>,
+}
+
+impl TryFrom<EnvelopeKeyRaw> for Encryption {
+    type Error = anyhow::Error;
+
+    fn try_from(value: EnvelopeKeyRaw) -> Result<Encryption, Self::Error> {
+        match (value.key, value.key_file) {
+            (None, None) => bail!("Missing `key` or `key_file`"),
+            (None, Some(path)) => Ok(Encryption::File(path)),
+            (Some(key), None) => Ok(Encryption::Value(key)),
+            (Some(_), Some(_)) => bail!("Cannot specify both `key` and `key_file`"),
+        }
+    }
+}
+
+impl From<Encryption> for EnvelopeKeyRaw {
+    fn from(value: Encryption) -> Self {
+        match value {
+            Encryption::File(path) => EnvelopeKeyRaw {
+                key_file: Some(path),
+                key: None,
+            },
+            Encryption::Value(key) => EnvelopeKeyRaw {
+                key_file: None,
+                key: Some(key),
+            },
+        }
+    }
+}
+
+/// The ID under which the encryption secret wraps data keys when no envelope
+/// key is configured
+const DEFAULT_ENVELOPE_KEY_ID: &str = "default";
+
+/// A master key used to wrap the data keys of envelope-encrypted secrets
+#[serde_as]
+#[derive(JsonSchema, Serialize, Deserialize, Clone, Debug)]
+pub struct EnvelopeKeyConfig {
+    /// Identifier of the key, stored alongside the data keys it wraps
+    id: String,
+
+    #[schemars(with = "EnvelopeKeyRaw")]
+    #[serde_as(as = "serde_with::TryFromInto<EnvelopeKeyRaw>")]
+    #[serde(flatten)]
+    key: Encryption,
+}
+
+impl EnvelopeKeyConfig {
+    /// Returns the master key.
+    ///
+    /// If `key_file` was given, the key is read from that file.
+    async fn key(&self) -> anyhow::Result<[u8; 32]> {
+        match self.key {
+            Encryption::Value(key) => Ok(key),
+            Encryption::File(ref path) => {
+                let mut bytes = [0; 32];
+                let content = tokio::fs::read(path).await?;
+                hex::decode_to_slice(content, &mut bytes).context(
+                    "Content of `key_file` must contain hex characters encoding exactly 32 bytes",
+                )?;
+                Ok(bytes)
+            }
+        }
+    }
+}
+
 /// Application secrets
 #[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -252,6 +336,12 @@ pub struct SecretsConfig {
     /// List of private keys to use for signing and encrypting payloads
     #[serde(default)]
     keys: Vec<KeyConfig>,
+
+    /// List of master keys used for envelope encryption of the upstream
+    /// provider secrets. The first one wraps new data keys, the others are only
+    /// used to unwrap existing ones. If empty, the encryption secret is used.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    envelope_keys: Vec<EnvelopeKeyConfig>,
 }
 
 impl SecretsConfig {
@@ -273,7 +363,22 @@ impl SecretsConfig {
     ///
     /// Returns an error when the Encryptor can not be created.
     pub async fn encrypter(&self) -> anyhow::Result<Encrypter> {
-        Ok(Encrypter::new(&self.encryption().await?))
+        let encryption = self.encryption().await?;
+        let encrypter = Encrypter::new(&encryption);
+
+        let Some((current, previous)) = self.envelope_keys.split_first() else {
+            return Ok(encrypter);
+        };
+
+        // Data keys wrapped before envelope keys were configured used the
+        // encryption secret, so keep it around to unwrap them
+        let mut key_wrapper = LocalKeyWrapper::new(current.id.clone(), &current.key().await?)
+            .with_previous_key(DEFAULT_ENVELOPE_KEY_ID, &encryption);
+        for key in previous {
+            key_wrapper = key_wrapper.with_previous_key(key.id.clone(), &key.key().await?);
+        }
+
+        Ok(encrypter.with_key_wrapper(key_wrapper))
     }
 
     /// Returns the encryption secret.
@@ -300,6 +405,40 @@ impl SecretsConfig {
 
 impl ConfigurationSection for SecretsConfig {
     const PATH: Option<&'static str> = Some("secrets");
+
+    fn validate(&self, figment: &figment::Figment) -> Result<(), figment::Error> {
+        let annotate = |mut error: figment::Error| {
+            error.metadata = figment.find_metadata(Self::PATH.unwrap()).cloned();
+            error.profile = Some(figment::Profile::Default);
+            error.path = vec![Self::PATH.unwrap().to_owned(), "envelope_keys".to_owned()];
+            Err(error)
+        };
+
+        let mut ids = HashSet::new();
+        for key in &self.envelope_keys {
+            if key.id.is_empty() || key.id.contains(':') {
+                return annotate(figment::Error::from(format!(
+                    "Invalid envelope key ID {:?}, it must be non-empty and not contain `:`",
+                    key.id
+                )));
+            }
+
+            if key.id == DEFAULT_ENVELOPE_KEY_ID {
+                return annotate(figment::Error::from(format!(
+                    "The envelope key ID {DEFAULT_ENVELOPE_KEY_ID:?} is reserved"
+                )));
+            }
+
+            if !ids.insert(&key.id) {
+                return annotate(figment::Error::from(format!(
+                    "Duplicate envelope key ID {:?}",
+                    key.id
+                )));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl SecretsConfig {
@@ -377,6 +516,7 @@ impl SecretsConfig {
         Ok(Self {
             encryption: Encryption::Value(Standard.sample(&mut rng)),
             keys: vec![rsa_key, ec_p256_key, ec_p384_key, ec_k256_key],
+            envelope_keys: Vec::new(),
         })
     }
 
@@ -418,6 +558,7 @@ impl SecretsConfig {
         Self {
             encryption: Encryption::Value([0xEA; 32]),
             keys: vec![rsa_key, ecdsa_key],
+            envelope_keys: Vec::new(),
         }
     }
 }
@@ -0,0 +1,298 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::num::NonZeroU16;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::ConfigurationSection;
+
+/// What backend should be used when sending text messages
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmsTransportKind {
+    /// Don't send text messages anywhere
+    #[default]
+    Blackhole,
+
+    /// Send text messages by POSTing them as JSON to an HTTP endpoint
+    Webhook,
+
+    /// Send text messages through the Twilio Messages API, or any API
+    /// compatible with it
+    Twilio,
+
+    /// Send text messages to an SMSC, using the SMPP protocol
+    Smpp,
+}
+
+/// Configuration related to sending text messages
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SmsConfig {
+    /// What backend should be used when sending text messages
+    #[serde(default)]
+    transport: SmsTransportKind,
+
+    /// Phone number or alphanumeric sender ID to send text messages from
+    ///
+    /// Required for the Twilio and SMPP transports
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<String>,
+
+    /// Webhook and Twilio transports: URL of the endpoint to call. The Twilio
+    /// transport defaults to `https://api.twilio.com/`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<Url>,
+
+    /// Webhook transport: Token sent in the `Authorization` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+
+    /// Twilio transport: SID of the account sending the messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account_sid: Option<String>,
+
+    /// Twilio transport: Auth token of the account sending the messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auth_token: Option<String>,
+
+    /// SMPP transport: Hostname of the SMSC to connect to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<crate::schema::Hostname>")]
+    hostname: Option<String>,
+
+    /// SMPP transport: Port to connect to. Default is 2775
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 1, max = 65535))]
+    port: Option<NonZeroU16>,
+
+    /// SMPP transport: System ID used to bind to the SMSC
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_id: Option<String>,
+
+    /// SMPP transport: Password used to bind to the SMSC
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<String>,
+}
+
+impl SmsConfig {
+    /// What backend should be used when sending text messages
+    #[must_use]
+    pub fn transport(&self) -> SmsTransportKind {
+        self.transport
+    }
+
+    /// Phone number or alphanumeric sender ID to send text messages from
+    #[must_use]
+    pub fn from(&self) -> Option<&str> {
+        self.from.as_deref()
+    }
+
+    /// URL of the endpoint to call
+    #[must_use]
+    pub fn url(&self) -> Option<&Url> {
+        self.url.as_ref()
+    }
+
+    /// Token sent in the `Authorization` header of webhook calls
+    #[must_use]
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// SID of the Twilio account sending the messages
+    #[must_use]
+    pub fn account_sid(&self) -> Option<&str> {
+        self.account_sid.as_deref()
+    }
+
+    /// Auth token of the Twilio account sending the messages
+    #[must_use]
+    pub fn auth_token(&self) -> Option<&str> {
+        self.auth_token.as_deref()
+    }
+
+    /// Hostname of the SMSC to connect to
+    #[must_use]
+    pub fn hostname(&self) -> Option<&str> {
+        self.hostname.as_deref()
+    }
+
+    /// Port of the SMSC to connect to
+    #[must_use]
+    pub fn port(&self) -> Option<NonZeroU16> {
+        self.port
+    }
+
+    /// System ID used to bind to the SMSC
+    #[must_use]
+    pub fn system_id(&self) -> Option<&str> {
+        self.system_id.as_deref()
+    }
+
+    /// Password used to bind to the SMSC
+    #[must_use]
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
+    /// Returns true if the configuration is the default one
+    pub(crate) fn is_default(&self) -> bool {
+        self.transport == SmsTransportKind::Blackhole
+            && self.from.is_none()
+            && self.url.is_none()
+            && self.token.is_none()
+            && self.account_sid.is_none()
+            && self.auth_token.is_none()
+            && self.hostname.is_none()
+            && self.port.is_none()
+            && self.system_id.is_none()
+            && self.password.is_none()
+    }
+}
+
+impl ConfigurationSection for SmsConfig {
+    const PATH: Option<&'static str> = Some("sms");
+
+    fn validate(&self, figment: &figment::Figment) -> Result<(), figment::error::Error> {
+        let metadata = figment.find_metadata(Self::PATH.unwrap());
+
+        let error_on_field = |mut error: figment::error::Error, field: &'static str| {
+            error.metadata = metadata.cloned();
+            error.profile = Some(figment::Profile::Default);
+            error.path = vec![Self::PATH.unwrap().to_owned(), field.to_owned()];
+            error
+        };
+
+        let fields = [
+            ("from", self.from.is_some()),
+            ("url", self.url.is_some()),
+            ("token", self.token.is_some()),
+            ("account_sid", self.account_sid.is_some()),
+            ("auth_token", self.auth_token.is_some()),
+            ("hostname", self.hostname.is_some()),
+            ("port", self.port.is_some()),
+            ("system_id", self.system_id.is_some()),
+            ("password", self.password.is_some()),
+        ];
+
+        let (required_fields, expected_fields): (&[&str], &'static [&'static str]) =
+            match self.transport {
+                SmsTransportKind::Blackhole => (&[], &["transport"]),
+                SmsTransportKind::Webhook => (&["url"], &["transport", "from", "url", "token"]),
+                SmsTransportKind::Twilio => (
+                    &["from", "account_sid", "auth_token"],
+                    &["transport", "from", "url", "account_sid", "auth_token"],
+                ),
+                SmsTransportKind::Smpp => (
+                    &["from", "hostname", "system_id", "password"],
+                    &[
+                        "transport",
+                        "from",
+                        "hostname",
+                        "port",
+                        "system_id",
+                        "password",
+                    ],
+                ),
+            };
+
+        for (field, is_set) in fields {
+            if !is_set && required_fields.contains(&field) {
+                return Err(error_on_field(
+                    figment::error::Error::missing_field(field),
+                    field,
+                ));
+            }
+
+            if is_set && !expected_fields.contains(&field) {
+                return Err(error_on_field(
+                    figment::error::Error::unknown_field(field, expected_fields),
+                    field,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::{
+        Figment, Jail,
+        providers::{Format, Yaml},
+    };
+
+    use super::*;
+
+    #[test]
+    fn load_config() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r"
+                    sms:
+                      transport: twilio
+                      from: '+15005550006'
+                      account_sid: AC0123456789
+                      auth_token: secret
+                ",
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<SmsConfig>("sms")?;
+            config.validate(&figment)?;
+
+            assert_eq!(config.transport(), SmsTransportKind::Twilio);
+            assert_eq!(config.from(), Some("+15005550006"));
+            assert_eq!(config.account_sid(), Some("AC0123456789"));
+            assert_eq!(config.auth_token(), Some("secret"));
+            assert_eq!(config.url(), None);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn load_config_invalid() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r"
+                    sms:
+                      transport: smpp
+                      from: MAS
+                      hostname: smsc.example.com
+                      system_id: mas
+                ",
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<SmsConfig>("sms")?;
+            let error = config.validate(&figment).unwrap_err();
+            assert_eq!(error.path, vec!["sms".to_owned(), "password".to_owned()]);
+
+            jail.create_file(
+                "config.yaml",
+                r"
+                    sms:
+                      transport: webhook
+                      url: https://sms.example.com/send
+                      account_sid: AC0123456789
+                ",
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<SmsConfig>("sms")?;
+            let error = config.validate(&figment).unwrap_err();
+            assert_eq!(error.path, vec!["sms".to_owned(), "account_sid".to_owned()]);
+
+            Ok(())
+        });
+    }
+}
@@ -7,18 +7,218 @@
 use std::collections::BTreeMap;
 
 use camino::Utf8PathBuf;
+use chrono::Duration;
 use mas_iana::jose::JsonWebSignatureAlg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize, de::Error};
-use serde_with::skip_serializing_none;
+use serde_with::{serde_as, skip_serializing_none};
 use ulid::Ulid;
 use url::Url;
 
-use crate::ConfigurationSection;
+use crate::{ConfigurationSection, HttpTlsConfig as TlsConfig};
+
+fn default_health_check_interval() -> Duration {
+    Duration::minutes(5)
+}
+
+fn default_certificate_expiry_warning() -> Duration {
+    Duration::days(14)
+}
+
+fn default_jwks_max_age() -> Duration {
+    Duration::days(1)
+}
+
+fn default_circuit_breaker_failure_threshold() -> u32 {
+    5
+}
+
+fn default_circuit_breaker_open_duration() -> Duration {
+    Duration::seconds(30)
+}
+
+fn default_discovery_ttl() -> Duration {
+    Duration::minutes(15)
+}
+
+fn default_jwks_ttl() -> Duration {
+    Duration::minutes(15)
+}
+
+fn default_max_stale_age() -> Duration {
+    Duration::days(7)
+}
+
+/// Configuration of the periodic health checks of the upstream providers
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HealthChecksConfig {
+    /// Whether the discovery document and the JWKS of each enabled provider
+    /// are probed periodically. Defaults to `true`.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// Time between two checks of the providers, in seconds. Defaults to 5
+    /// minutes.
+    #[schemars(with = "u64", range(min = 60, max = 86400))]
+    #[serde(default = "default_health_check_interval")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub interval: Duration,
+
+    /// How long before the expiry of the TLS certificate of a provider it is
+    /// reported as expiring, in seconds. Defaults to 14 days.
+    #[schemars(with = "u64", range(min = 0))]
+    #[serde(default = "default_certificate_expiry_warning")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub certificate_expiry_warning: Duration,
+
+    /// How long the JWKS of a provider can fail to be fetched before it is
+    /// reported as stale, in seconds. Defaults to 1 day.
+    #[schemars(with = "u64", range(min = 0))]
+    #[serde(default = "default_jwks_max_age")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub jwks_max_age: Duration,
+
+    /// Whether the providers which are unreachable or have a stale JWKS are
+    /// shown as unavailable on the login page. Defaults to `false`.
+    #[serde(default)]
+    pub disable_unhealthy_providers: bool,
+}
+
+impl Default for HealthChecksConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: default_health_check_interval(),
+            certificate_expiry_warning: default_certificate_expiry_warning(),
+            jwks_max_age: default_jwks_max_age(),
+            disable_unhealthy_providers: false,
+        }
+    }
+}
+
+impl HealthChecksConfig {
+    pub(crate) fn is_default(&self) -> bool {
+        self.enabled
+            && self.interval == default_health_check_interval()
+            && self.certificate_expiry_warning == default_certificate_expiry_warning()
+            && self.jwks_max_age == default_jwks_max_age()
+            && !self.disable_unhealthy_providers
+    }
+}
+
+/// Configuration of the circuit breaker around the requests to the token and
+/// userinfo endpoints of the upstream providers
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CircuitBreakerConfig {
+    /// Whether requests to a provider are suspended after it failed too many
+    /// times in a row. Defaults to `true`.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    /// How many requests to a provider have to fail in a row, because it
+    /// couldn't be reached or answered with a server error, before requests to
+    /// it are suspended. Defaults to 5.
+    #[schemars(range(min = 1))]
+    #[serde(default = "default_circuit_breaker_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// How long requests to a failing provider are suspended, in seconds.
+    /// After that, a single request is let through to check whether the
+    /// provider recovered. Defaults to 30 seconds.
+    #[schemars(with = "u64", range(min = 1))]
+    #[serde(default = "default_circuit_breaker_open_duration")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub open_duration: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_threshold: default_circuit_breaker_failure_threshold(),
+            open_duration: default_circuit_breaker_open_duration(),
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    pub(crate) fn is_default(&self) -> bool {
+        self.enabled
+            && self.failure_threshold == default_circuit_breaker_failure_threshold()
+            && self.open_duration == default_circuit_breaker_open_duration()
+    }
+}
+
+/// Configuration of the cache of the discovery documents and JWKS of the
+/// upstream providers
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MetadataCacheConfig {
+    /// How long a discovery document is used before it is fetched again, in
+    /// seconds. Defaults to 15 minutes.
+    #[schemars(with = "u64", range(min = 60))]
+    #[serde(default = "default_discovery_ttl")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub discovery_ttl: Duration,
+
+    /// How long a JWKS is used before it is fetched again, in seconds. It is
+    /// also fetched again if it doesn't have the key a token is signed with.
+    /// Defaults to 15 minutes.
+    #[schemars(with = "u64", range(min = 0))]
+    #[serde(default = "default_jwks_ttl")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub jwks_ttl: Duration,
+
+    /// How long a document can still be used after it expired, if the provider
+    /// can't be reached to fetch it again, in seconds. Defaults to 7 days.
+    #[schemars(with = "u64", range(min = 0))]
+    #[serde(default = "default_max_stale_age")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub max_stale_age: Duration,
+
+    /// Whether the documents are stored in the database, so that they are
+    /// shared between instances and survive restarts. Defaults to `true`.
+    #[serde(default = "default_true")]
+    pub persist: bool,
+}
+
+impl Default for MetadataCacheConfig {
+    fn default() -> Self {
+        Self {
+            discovery_ttl: default_discovery_ttl(),
+            jwks_ttl: default_jwks_ttl(),
+            max_stale_age: default_max_stale_age(),
+            persist: true,
+        }
+    }
+}
+
+impl MetadataCacheConfig {
+    pub(crate) fn is_default(&self) -> bool {
+        self.discovery_ttl == default_discovery_ttl()
+            && self.jwks_ttl == default_jwks_ttl()
+            && self.max_stale_age == default_max_stale_age()
+            && self.persist
+    }
+}
 
 /// Upstream OAuth 2.0 providers configuration
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct UpstreamOAuth2Config {
+    /// Periodic health checks of the providers
+    #[serde(default, skip_serializing_if = "HealthChecksConfig::is_default")]
+    pub health_checks: HealthChecksConfig,
+
+    /// Cache of the discovery documents and JWKS of the providers
+    #[serde(default, skip_serializing_if = "MetadataCacheConfig::is_default")]
+    pub metadata_cache: MetadataCacheConfig,
+
+    /// Circuit breaker around the requests to the providers
+    #[serde(default, skip_serializing_if = "CircuitBreakerConfig::is_default")]
+    pub circuit_breaker: CircuitBreakerConfig,
+
     /// List of OAuth 2.0 providers
     pub providers: Vec<Provider>,
 }
@@ -26,7 +226,10 @@ pub struct UpstreamOAuth2Config {
 impl UpstreamOAuth2Config {
     /// Returns true if the configuration is the default one
     pub(crate) fn is_default(&self) -> bool {
-        self.providers.is_empty()
+        self.health_checks.is_default()
+            && self.metadata_cache.is_default()
+            && self.circuit_breaker.is_default()
+            && self.providers.is_empty()
     }
 }
 
@@ -34,7 +237,61 @@ impl ConfigurationSection for UpstreamOAuth2Config {
     const PATH: Option<&'static str> = Some("upstream_oauth2");
 
     fn validate(&self, figment: &figment::Figment) -> Result<(), figment::Error> {
+        let health_checks = &self.health_checks;
+        if health_checks.interval < Duration::minutes(1)
+            || health_checks.certificate_expiry_warning < Duration::zero()
+            || health_checks.jwks_max_age < Duration::zero()
+        {
+            let mut error = figment::Error::custom(
+                "The health check interval must be at least a minute, and the thresholds can't be negative",
+            );
+            error.metadata = figment
+                .find_metadata(&format!("{root}.health_checks", root = Self::PATH.unwrap()))
+                .cloned();
+            error.profile = Some(figment::Profile::Default);
+            error.path = vec![Self::PATH.unwrap().to_owned(), "health_checks".to_owned()];
+            return Err(error);
+        }
+
+        let metadata_cache = &self.metadata_cache;
+        if metadata_cache.discovery_ttl < Duration::minutes(1)
+            || metadata_cache.jwks_ttl < Duration::zero()
+            || metadata_cache.max_stale_age < Duration::zero()
+        {
+            let mut error = figment::Error::custom(
+                "The discovery documents must be cached for at least a minute, and the other durations can't be negative",
+            );
+            error.metadata = figment
+                .find_metadata(&format!(
+                    "{root}.metadata_cache",
+                    root = Self::PATH.unwrap()
+                ))
+                .cloned();
+            error.profile = Some(figment::Profile::Default);
+            error.path = vec![Self::PATH.unwrap().to_owned(), "metadata_cache".to_owned()];
+            return Err(error);
+        }
+
+        let circuit_breaker = &self.circuit_breaker;
+        if circuit_breaker.failure_threshold == 0
+            || circuit_breaker.open_duration < Duration::seconds(1)
+        {
+            let mut error = figment::Error::custom(
+                "The circuit breaker failure threshold and open duration must be at least 1",
+            );
+            error.metadata = figment
+                .find_metadata(&format!(
+                    "{root}.circuit_breaker",
+                    root = Self::PATH.unwrap()
+                ))
+                .cloned();
+            error.profile = Some(figment::Profile::Default);
+            error.path = vec![Self::PATH.unwrap().to_owned(), "circuit_breaker".to_owned()];
+            return Err(error);
+        }
+
         for (index, provider) in self.providers.iter().enumerate() {
+            let provider = &provider.clone().with_preset();
             let annotate = |mut error: figment::Error| {
                 error.metadata = figment
                     .find_metadata(&format!("{root}.providers", root = Self::PATH.unwrap()))
@@ -48,6 +305,10 @@ impl ConfigurationSection for UpstreamOAuth2Config {
                 Err(error)
             };
 
+            let Some(token_endpoint_auth_method) = provider.token_endpoint_auth_method else {
+                return annotate(figment::Error::missing_field("token_endpoint_auth_method"));
+            };
+
             if !matches!(provider.discovery_mode, DiscoveryMode::Disabled)
                 && provider.issuer.is_none()
             {
@@ -56,9 +317,102 @@ impl ConfigurationSection for UpstreamOAuth2Config {
                 ));
             }
 
-            match provider.token_endpoint_auth_method {
+            // Without the `openid` scope, the provider is a plain OAuth 2.0 one which
+            // won't give us an ID token, so the userinfo endpoint is the only source of
+            // claims we have
+            let is_openid = provider.scope.split(' ').any(|token| token == "openid");
+            if !is_openid && !provider.fetch_userinfo {
+                return annotate(figment::Error::custom(
+                    "The `fetch_userinfo` field must be enabled when the `openid` scope isn't requested",
+                ));
+            }
+
+            if matches!(provider.discovery_mode, DiscoveryMode::Disabled) {
+                if provider.authorization_endpoint.is_none() {
+                    return annotate(figment::Error::missing_field("authorization_endpoint"));
+                }
+
+                if provider.token_endpoint.is_none() {
+                    return annotate(figment::Error::missing_field("token_endpoint"));
+                }
+
+                if provider.fetch_userinfo && provider.userinfo_endpoint.is_none() {
+                    return annotate(figment::Error::missing_field("userinfo_endpoint"));
+                }
+
+                if (is_openid || provider.userinfo_signed_response_alg.is_some())
+                    && provider.jwks_uri.is_none()
+                {
+                    return annotate(figment::Error::missing_field("jwks_uri"));
+                }
+            }
+
+            let claims_imports = &provider.claims_imports;
+            for (name, action, sync) in [
+                (
+                    "displayname",
+                    claims_imports.displayname.action,
+                    claims_imports.displayname.sync,
+                ),
+                (
+                    "email",
+                    claims_imports.email.action,
+                    claims_imports.email.sync,
+                ),
+                (
+                    "avatar",
+                    claims_imports.avatar.action,
+                    claims_imports.avatar.sync,
+                ),
+            ] {
+                // Refreshing an attribute on each login would override the choice
+                // the user made when registering
+                if sync == SyncMode::Always
+                    && !matches!(action, ImportAction::Force | ImportAction::Require)
+                {
+                    return annotate(figment::Error::custom(format!(
+                        "The `claims_imports.{name}.sync` field can only be set to `always` with the `force` or `require` actions"
+                    )));
+                }
+            }
+
+            if claims_imports.avatar.action == ImportAction::Suggest {
+                return annotate(figment::Error::custom(
+                    "The `suggest` action isn't supported for `claims_imports.avatar`",
+                ));
+            }
+
+            if claims_imports.avatar.max_size == Some(0) {
+                return annotate(figment::Error::custom(
+                    "The `claims_imports.avatar.max_size` field must be greater than 0",
+                ));
+            }
+
+            let can_request_admin = &claims_imports.can_request_admin;
+            if can_request_admin.expression.is_none()
+                && !can_request_admin.allowed_values.is_empty()
+            {
+                return annotate(figment::Error::custom(
+                    "The `claims_imports.can_request_admin.allowed_values` field requires an `expression`",
+                ));
+            }
+
+            let email = &claims_imports.email;
+            if email
+                .allowed_domains
+                .iter()
+                .chain(&email.forbidden_domains)
+                .any(|domain| domain.is_empty() || domain.contains('@'))
+            {
+                return annotate(figment::Error::custom(
+                    "The `claims_imports.email` domains must be bare domain names, like `example.com`",
+                ));
+            }
+
+            match token_endpoint_auth_method {
                 TokenAuthMethod::None
                 | TokenAuthMethod::PrivateKeyJwt
+                | TokenAuthMethod::TlsClientAuth
                 | TokenAuthMethod::SignInWithApple => {
                     if provider.client_secret.is_some() {
                         return annotate(figment::Error::custom(
@@ -75,10 +429,11 @@ impl ConfigurationSection for UpstreamOAuth2Config {
                 }
             }
 
-            match provider.token_endpoint_auth_method {
+            match token_endpoint_auth_method {
                 TokenAuthMethod::None
                 | TokenAuthMethod::ClientSecretBasic
                 | TokenAuthMethod::ClientSecretPost
+                | TokenAuthMethod::TlsClientAuth
                 | TokenAuthMethod::SignInWithApple => {
                     if provider.token_endpoint_auth_signing_alg.is_some() {
                         return annotate(figment::Error::custom(
@@ -95,10 +450,61 @@ impl ConfigurationSection for UpstreamOAuth2Config {
                 }
             }
 
-            match provider.token_endpoint_auth_method {
+            if provider.token_endpoint_auth_signing_key_id.is_some()
+                && !matches!(token_endpoint_auth_method, TokenAuthMethod::PrivateKeyJwt)
+            {
+                return annotate(figment::Error::custom(
+                    "Unexpected field `token_endpoint_auth_signing_key_id` for the selected authentication method",
+                ));
+            }
+
+            if matches!(token_endpoint_auth_method, TokenAuthMethod::TlsClientAuth)
+                && provider.tls_client_certificate.is_none()
+            {
+                return annotate(figment::Error::missing_field("tls_client_certificate"));
+            }
+
+            if provider.deprovisioning.is_some_and(|deprovisioning| {
+                deprovisioning.source == DeprovisioningSource::RefreshToken
+            }) && !provider.store_tokens
+            {
+                return annotate(figment::Error::custom(
+                    "The `refresh_token` deprovisioning source requires `store_tokens` to be enabled",
+                ));
+            }
+
+            for rule in &provider.subject_migrations {
+                if let Err(e) = regex::Regex::new(&rule.pattern) {
+                    return annotate(figment::Error::custom(format!(
+                        "Invalid pattern in `subject_migrations`: {e}"
+                    )));
+                }
+            }
+
+            match token_endpoint_auth_method {
                 TokenAuthMethod::SignInWithApple => {
-                    if provider.sign_in_with_apple.is_none() {
+                    let Some(sign_in_with_apple) = &provider.sign_in_with_apple else {
                         return annotate(figment::Error::missing_field("sign_in_with_apple"));
+                    };
+
+                    if sign_in_with_apple.private_key.is_none()
+                        && sign_in_with_apple.private_key_file.is_none()
+                    {
+                        return annotate(figment::Error::missing_field("private_key"));
+                    }
+
+                    // Apple only sends back the name and email of the user with the
+                    // `form_post` response mode
+                    let needs_form_post = provider
+                        .scope
+                        .split(' ')
+                        .any(|token| token == "name" || token == "email");
+                    if needs_form_post
+                        && !matches!(provider.response_mode, Some(ResponseMode::FormPost))
+                    {
+                        return annotate(figment::Error::custom(
+                            "The `response_mode` field must be set to `form_post` when requesting the `name` or `email` scopes from Apple",
+                        ));
                     }
                 }
 
@@ -154,10 +560,67 @@ pub enum TokenAuthMethod {
     /// signed by an asymmetric key
     PrivateKeyJwt,
 
+    /// `tls_client_auth`: the client authenticates with the TLS client
+    /// certificate set in `tls_client_certificate`
+    TlsClientAuth,
+
     /// `sign_in_with_apple`: a special method for Signin with Apple
     SignInWithApple,
 }
 
+/// How to find out that the upstream identity of a user was removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeprovisioningSource {
+    /// `scim`: the user is missing or inactive in the SCIM directory set in
+    /// the `scim.client` section. The subject of the upstream account is
+    /// matched against the `id` and `externalId` of the directory users
+    Scim,
+
+    /// `refresh_token`: the provider refused the refresh token stored for the
+    /// user the last time it was used. This requires `store_tokens`
+    RefreshToken,
+}
+
+/// What to do with the users whose upstream identity was removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeprovisioningAction {
+    /// `lock`: lock the user, which can be unlocked later on
+    #[default]
+    Lock,
+
+    /// `deactivate`: deactivate the user, also on the homeserver
+    Deactivate,
+}
+
+impl DeprovisioningAction {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    const fn is_default(&self) -> bool {
+        matches!(self, DeprovisioningAction::Lock)
+    }
+}
+
+/// Automatic deprovisioning of the users whose upstream identity was removed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub struct DeprovisioningConfig {
+    /// How to find out that the upstream identity of a user was removed
+    pub source: DeprovisioningSource,
+
+    /// What to do with the users whose upstream identity was removed.
+    ///
+    /// Defaults to `lock`.
+    #[serde(default, skip_serializing_if = "DeprovisioningAction::is_default")]
+    pub action: DeprovisioningAction,
+
+    /// Only report which users would be deprovisioned in the logs, without
+    /// touching them.
+    ///
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
 /// How to handle a claim
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(rename_all = "lowercase")]
@@ -183,6 +646,45 @@ impl ImportAction {
     }
 }
 
+/// When an imported attribute is refreshed from the upstream provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    /// Only import the attribute when the user registers
+    #[default]
+    FirstLogin,
+
+    /// Import the attribute again each time the user logs in
+    Always,
+}
+
+impl SyncMode {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    const fn is_default(&self) -> bool {
+        matches!(self, SyncMode::FirstLogin)
+    }
+}
+
+/// What to do when refreshing an attribute which the user changed locally
+/// since it was last imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    /// Keep the value the user set
+    #[default]
+    KeepLocal,
+
+    /// Replace the value the user set with the one from the provider
+    Overwrite,
+}
+
+impl OnConflict {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    const fn is_default(&self) -> bool {
+        matches!(self, OnConflict::KeepLocal)
+    }
+}
+
 /// What should be done for the subject attribute
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
 pub struct SubjectImportPreference {
@@ -231,11 +733,24 @@ pub struct DisplaynameImportPreference {
     /// If not provided, the default template is `{{ user.name }}`
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub template: Option<String>,
+
+    /// When to import the attribute. With `always`, it is refreshed each time
+    /// the user logs in, which requires the `force` or `require` action
+    #[serde(default, skip_serializing_if = "SyncMode::is_default")]
+    pub sync: SyncMode,
+
+    /// What to do on login if the user changed the attribute since it was last
+    /// imported. Only used if `sync` is set to `always`
+    #[serde(default, skip_serializing_if = "OnConflict::is_default")]
+    pub on_conflict: OnConflict,
 }
 
 impl DisplaynameImportPreference {
     const fn is_default(&self) -> bool {
-        self.action.is_default() && self.template.is_none()
+        self.action.is_default()
+            && self.template.is_none()
+            && self.sync.is_default()
+            && self.on_conflict.is_default()
     }
 }
 
@@ -251,11 +766,81 @@ pub struct EmailImportPreference {
     /// If not provided, the default template is `{{ user.email }}`
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub template: Option<String>,
+
+    /// When to import the attribute. With `always`, it is refreshed each time
+    /// the user logs in, which requires the `force` or `require` action
+    #[serde(default, skip_serializing_if = "SyncMode::is_default")]
+    pub sync: SyncMode,
+
+    /// What to do on login if the user changed the attribute since it was last
+    /// imported. Only used if `sync` is set to `always`
+    #[serde(default, skip_serializing_if = "OnConflict::is_default")]
+    pub on_conflict: OnConflict,
+
+    /// Only allow logins asserting an email address in one of these domains.
+    ///
+    /// The email address is the one rendered by the `template`, regardless of
+    /// the `action`. If set, logins without an email address are rejected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_domains: Vec<String>,
+
+    /// Reject logins asserting an email address in one of these domains
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forbidden_domains: Vec<String>,
 }
 
 impl EmailImportPreference {
     const fn is_default(&self) -> bool {
-        self.action.is_default() && self.template.is_none()
+        self.action.is_default()
+            && self.template.is_none()
+            && self.sync.is_default()
+            && self.on_conflict.is_default()
+            && self.allowed_domains.is_empty()
+            && self.forbidden_domains.is_empty()
+    }
+}
+
+/// What should be done with the avatar attribute
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
+pub struct AvatarImportPreference {
+    /// How to handle the claim. The `suggest` action isn't supported, as users
+    /// aren't asked whether to import their avatar.
+    #[serde(default, skip_serializing_if = "ImportAction::is_default")]
+    pub action: ImportAction,
+
+    /// The Jinja2 template to use for the URL of the avatar. The image is
+    /// downloaded from this URL and uploaded to the homeserver.
+    ///
+    /// If not provided, the default template is `{{ user.picture }}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    /// When to import the attribute. With `always`, it is refreshed each time
+    /// the user logs in, which requires the `force` or `require` action
+    #[serde(default, skip_serializing_if = "SyncMode::is_default")]
+    pub sync: SyncMode,
+
+    /// What to do on login if the user changed the attribute since it was last
+    /// imported. Only used if `sync` is set to `always`
+    #[serde(default, skip_serializing_if = "OnConflict::is_default")]
+    pub on_conflict: OnConflict,
+
+    /// The maximum size of the image, in bytes. Larger images aren't
+    /// imported.
+    ///
+    /// Defaults to 1 MiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 1))]
+    pub max_size: Option<u32>,
+}
+
+impl AvatarImportPreference {
+    const fn is_default(&self) -> bool {
+        self.action.is_default()
+            && self.template.is_none()
+            && self.sync.is_default()
+            && self.on_conflict.is_default()
+            && self.max_size.is_none()
     }
 }
 
@@ -276,6 +861,35 @@ impl AccountNameImportPreference {
     }
 }
 
+/// How the `can_request_admin` property of the user should be derived from the
+/// upstream claims
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
+pub struct CanRequestAdminImportPreference {
+    /// The Jinja2 expression which extracts the values from the claims, for
+    /// example `user.groups`. It can evaluate to a single value or to a list
+    /// of values.
+    ///
+    /// If not provided, the property is not managed by this provider. If
+    /// provided, it is evaluated again on each login through this provider,
+    /// which means it can both grant and revoke the property.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+
+    /// The values which allow the user to request admin access, for example
+    /// a list of group names.
+    ///
+    /// If empty, the user can request admin access if the expression evaluates
+    /// to a truthy value.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_values: Vec<String>,
+}
+
+impl CanRequestAdminImportPreference {
+    fn is_default(&self) -> bool {
+        self.expression.is_none() && self.allowed_values.is_empty()
+    }
+}
+
 /// How claims should be imported
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
 pub struct ClaimsImports {
@@ -299,20 +913,35 @@ pub struct ClaimsImports {
     #[serde(default, skip_serializing_if = "EmailImportPreference::is_default")]
     pub email: EmailImportPreference,
 
+    /// Import the avatar of the user based on the `picture` claim
+    #[serde(default, skip_serializing_if = "AvatarImportPreference::is_default")]
+    pub avatar: AvatarImportPreference,
+
     /// Set a human-readable name for the upstream account for display purposes
     #[serde(
         default,
         skip_serializing_if = "AccountNameImportPreference::is_default"
     )]
     pub account_name: AccountNameImportPreference,
+
+    /// Whether the user can request admin access, based on the groups or roles
+    /// they have on the upstream provider
+    #[serde(
+        default,
+        skip_serializing_if = "CanRequestAdminImportPreference::is_default"
+    )]
+    pub can_request_admin: CanRequestAdminImportPreference,
 }
 
 impl ClaimsImports {
-    const fn is_default(&self) -> bool {
+    fn is_default(&self) -> bool {
         self.subject.is_default()
             && self.localpart.is_default()
             && self.displayname.is_default()
             && self.email.is_default()
+            && self.avatar.is_default()
+            && self.account_name.is_default()
+            && self.can_request_admin.is_default()
     }
 }
 
@@ -384,12 +1013,16 @@ fn signed_response_alg_default() -> JsonWebSignatureAlg {
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SignInWithApple {
-    /// The private key file used to sign the `id_token`
+    /// The private key file used to sign the client secret
+    ///
+    /// This is the `.p8` file which can be downloaded from the Apple Developer
+    /// Portal
     #[serde(skip_serializing_if = "Option::is_none")]
     #[schemars(with = "Option<String>")]
     pub private_key_file: Option<Utf8PathBuf>,
 
-    /// The private key used to sign the `id_token`
+    /// The private key used to sign the client secret, as the PEM-encoded
+    /// contents of the `.p8` file
     #[serde(skip_serializing_if = "Option::is_none")]
     pub private_key: Option<String>,
 
@@ -408,6 +1041,135 @@ fn is_default_scope(scope: &str) -> bool {
     scope == default_scope()
 }
 
+/// A rule to rewrite the subjects of the existing links of a provider, after
+/// the provider changed the format of its subjects
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SubjectMigrationRule {
+    /// The regular expression the current subject must fully match for the
+    /// rule to apply
+    pub pattern: String,
+
+    /// The new subject. Groups captured by the pattern can be referenced with
+    /// `$1` or `${name}`
+    pub template: String,
+}
+
+/// Settings for a well-known identity provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProviderPreset {
+    /// `google`: Google accounts, with `https://accounts.google.com` as
+    /// issuer
+    Google,
+
+    /// `azure-ad`: Microsoft Entra ID, formerly Azure Active Directory. The
+    /// issuer is `https://login.microsoftonline.com/<tenant-id>/v2.0`
+    AzureAd,
+
+    /// `keycloak`: a Keycloak realm. The issuer is
+    /// `https://<keycloak>/realms/<realm>`
+    Keycloak,
+
+    /// `okta`: an Okta organization. The issuer is `https://<org>.okta.com` or
+    /// the URL of a custom authorization server
+    Okta,
+
+    /// `authentik`: an authentik application. The issuer is
+    /// `https://<authentik>/application/o/<app-slug>/`
+    Authentik,
+}
+
+impl ProviderPreset {
+    /// The issuer, for providers which have a single one
+    const fn issuer(self) -> Option<&'static str> {
+        match self {
+            Self::Google => Some("https://accounts.google.com"),
+            Self::AzureAd | Self::Keycloak | Self::Okta | Self::Authentik => None,
+        }
+    }
+
+    const fn human_name(self) -> &'static str {
+        match self {
+            Self::Google => "Google",
+            Self::AzureAd => "Microsoft",
+            Self::Keycloak => "Keycloak",
+            Self::Okta => "Okta",
+            Self::Authentik => "authentik",
+        }
+    }
+
+    const fn brand_name(self) -> Option<&'static str> {
+        match self {
+            Self::Google => Some("google"),
+            Self::AzureAd | Self::Keycloak | Self::Okta | Self::Authentik => None,
+        }
+    }
+
+    const fn token_endpoint_auth_method(self) -> TokenAuthMethod {
+        match self {
+            Self::Google | Self::AzureAd => TokenAuthMethod::ClientSecretPost,
+            Self::Keycloak | Self::Okta | Self::Authentik => TokenAuthMethod::ClientSecretBasic,
+        }
+    }
+
+    const fn discovery_mode(self) -> DiscoveryMode {
+        match self {
+            // The discovery document of the multi-tenant endpoints has a
+            // templated issuer, which doesn't match the issuer of the tokens
+            Self::AzureAd => DiscoveryMode::Insecure,
+            Self::Google | Self::Keycloak | Self::Okta | Self::Authentik => DiscoveryMode::Oidc,
+        }
+    }
+
+    const fn pkce_method(self) -> PkceMethod {
+        match self {
+            // Supported, but not advertised in the discovery document
+            Self::AzureAd => PkceMethod::Always,
+            Self::Google | Self::Keycloak | Self::Okta | Self::Authentik => PkceMethod::Auto,
+        }
+    }
+
+    fn claims_imports(self) -> ClaimsImports {
+        let (localpart_action, localpart_template, account_name) = match self {
+            // Google usernames are email addresses, which don't make good
+            // localparts
+            Self::Google => (ImportAction::Ignore, None, "{{ user.email }}"),
+            // The usernames are email addresses, so only keep the local part
+            Self::AzureAd | Self::Okta => (
+                ImportAction::Require,
+                Some("{{ (user.preferred_username | split('@'))[0] }}"),
+                "{{ user.preferred_username }}",
+            ),
+            Self::Keycloak | Self::Authentik => (
+                ImportAction::Require,
+                Some("{{ user.preferred_username }}"),
+                "{{ user.preferred_username }}",
+            ),
+        };
+
+        ClaimsImports {
+            localpart: LocalpartImportPreference {
+                action: localpart_action,
+                template: localpart_template.map(ToOwned::to_owned),
+            },
+            displayname: DisplaynameImportPreference {
+                action: ImportAction::Suggest,
+                template: Some("{{ user.name }}".to_owned()),
+                ..DisplaynameImportPreference::default()
+            },
+            email: EmailImportPreference {
+                action: ImportAction::Suggest,
+                template: Some("{{ user.email }}".to_owned()),
+                ..EmailImportPreference::default()
+            },
+            account_name: AccountNameImportPreference {
+                template: Some(account_name.to_owned()),
+            },
+            ..ClaimsImports::default()
+        }
+    }
+}
+
 /// Configuration for one upstream OAuth 2 provider.
 #[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -443,6 +1205,16 @@ pub struct Provider {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub synapse_idp_id: Option<String>,
 
+    /// Settings for a well-known identity provider, like `google` or
+    /// `keycloak`.
+    ///
+    /// The options which are left to their default value are filled in with
+    /// the ones recommended for this provider: the issuer if there is a
+    /// single one, the name, the authentication method, discovery, PKCE, the
+    /// scope and the claims imports.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<ProviderPreset>,
+
     /// The OIDC issuer URL
     ///
     /// This is required if OIDC discovery is enabled (which is the default)
@@ -479,7 +1251,10 @@ pub struct Provider {
     pub client_secret: Option<String>,
 
     /// The method to authenticate the client with the provider
-    pub token_endpoint_auth_method: TokenAuthMethod,
+    ///
+    /// Required, unless a `preset` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_endpoint_auth_method: Option<TokenAuthMethod>,
 
     /// Additional parameters for the `sign_in_with_apple` method
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -492,6 +1267,22 @@ pub struct Provider {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub token_endpoint_auth_signing_alg: Option<JsonWebSignatureAlg>,
 
+    /// The key ID of the key from `secrets.keys` to sign the client
+    /// assertions with
+    ///
+    /// Used by the `private_key_jwt` method. If not set, any key compatible
+    /// with the signing algorithm is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_endpoint_auth_signing_key_id: Option<String>,
+
+    /// The TLS client certificate to present to the provider when calling its
+    /// token endpoint, for mutual TLS
+    ///
+    /// Required by the `tls_client_auth` method, and can be used with the
+    /// other methods if the provider requires it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_client_certificate: Option<TlsConfig>,
+
     /// Expected signature for the JWT payload returned by the token
     /// authentication endpoint.
     ///
@@ -526,6 +1317,9 @@ pub struct Provider {
     /// or to rely on the data returned in the `id_token` from the
     /// `token_endpoint`.
     ///
+    /// This must be enabled for plain OAuth 2.0 providers, which don't return
+    /// an `id_token` because the `openid` scope isn't requested.
+    ///
     /// Defaults to `false`.
     #[serde(default)]
     pub fetch_userinfo: bool,
@@ -583,4 +1377,784 @@ pub struct Provider {
     /// Defaults to `false`.
     #[serde(default)]
     pub forward_login_hint: bool,
+
+    /// Whether `prompt=login` should be forwarded to the provider when a
+    /// client asked for the user to authenticate again, forcing the provider
+    /// to re-authenticate the user as well.
+    ///
+    /// Defaults to `true`.
+    #[serde(default = "default_true", skip_serializing_if = "is_default_true")]
+    pub forward_prompt: bool,
+
+    /// Whether the ACR value required by the client should be forwarded to
+    /// the provider as `acr_values` in the authorization request, for example
+    /// to make it enforce multi-factor authentication.
+    ///
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub forward_acr_values: bool,
+
+    /// Whether the user's locale should be forwarded to the provider as
+    /// `ui_locales` in the authorization request.
+    ///
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub forward_ui_locales: bool,
+
+    /// Whether to keep the access and refresh tokens issued by the provider
+    /// when a user logs in.
+    ///
+    /// The tokens are stored encrypted, and can be retrieved by clients which
+    /// were granted the `urn:mas:upstream-tokens:<provider id>` scope, so that
+    /// they can call the provider's APIs on behalf of the user.
+    ///
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub store_tokens: bool,
+
+    /// Lock or deactivate the users whose upstream identity was removed, as
+    /// found by a periodic reconciliation job.
+    ///
+    /// Disabled by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprovisioning: Option<DeprovisioningConfig>,
+
+    /// Rules to rewrite the subjects of the existing links of this provider,
+    /// applied with the `mas-cli manage migrate-subjects` command.
+    ///
+    /// The first rule whose pattern matches a subject is applied to it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subject_migrations: Vec<SubjectMigrationRule>,
+}
+
+impl Provider {
+    /// Fill in the options left to their default value with the ones of the
+    /// `preset`, if one is set
+    #[must_use]
+    pub fn with_preset(mut self) -> Self {
+        let Some(preset) = self.preset else {
+            return self;
+        };
+
+        if self.issuer.is_none() {
+            self.issuer = preset.issuer().map(ToOwned::to_owned);
+        }
+
+        if self.human_name.is_none() {
+            self.human_name = Some(preset.human_name().to_owned());
+        }
+
+        if self.brand_name.is_none() {
+            self.brand_name = preset.brand_name().map(ToOwned::to_owned);
+        }
+
+        if self.token_endpoint_auth_method.is_none() {
+            self.token_endpoint_auth_method = Some(preset.token_endpoint_auth_method());
+        }
+
+        if self.discovery_mode.is_default() {
+            self.discovery_mode = preset.discovery_mode();
+        }
+
+        if self.pkce_method.is_default() {
+            self.pkce_method = preset.pkce_method();
+        }
+
+        if is_default_scope(&self.scope) {
+            "openid profile email".clone_into(&mut self.scope);
+        }
+
+        // Each attribute is only filled in if it wasn't configured at all
+        let defaults = preset.claims_imports();
+        let claims_imports = &mut self.claims_imports;
+        if claims_imports.localpart.is_default() {
+            claims_imports.localpart = defaults.localpart;
+        }
+        if claims_imports.displayname.is_default() {
+            claims_imports.displayname = defaults.displayname;
+        }
+        if claims_imports.email.is_default() {
+            claims_imports.email = defaults.email;
+        }
+        if claims_imports.account_name.is_default() {
+            claims_imports.account_name = defaults.account_name;
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::{
+        Figment, Jail,
+        providers::{Format, Yaml},
+    };
+
+    use super::*;
+
+    #[test]
+    fn load_oauth2_provider() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_post
+                          discovery_mode: disabled
+                          fetch_userinfo: true
+                          authorization_endpoint: https://github.com/login/oauth/authorize
+                          token_endpoint: https://github.com/login/oauth/access_token
+                          userinfo_endpoint: https://api.github.com/user
+                          scope: "read:user user:email"
+                          claims_imports:
+                            subject:
+                              template: "{{ userinfo_claims.id }}"
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            config.validate(&figment)?;
+
+            assert_eq!(config.providers.len(), 1);
+            assert!(config.providers[0].fetch_userinfo);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reject_oauth2_provider_without_userinfo() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_post
+                          discovery_mode: disabled
+                          authorization_endpoint: https://github.com/login/oauth/authorize
+                          token_endpoint: https://github.com/login/oauth/access_token
+                          userinfo_endpoint: https://api.github.com/user
+                          scope: "read:user"
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reject_missing_endpoints_without_discovery() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_post
+                          discovery_mode: disabled
+                          fetch_userinfo: true
+                          authorization_endpoint: https://github.com/login/oauth/authorize
+                          token_endpoint: https://github.com/login/oauth/access_token
+                          scope: "read:user"
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn load_can_request_admin_import() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_post
+                          claims_imports:
+                            can_request_admin:
+                              expression: "user.groups"
+                              allowed_values: ["matrix-admins"]
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            config.validate(&figment)?;
+
+            let can_request_admin = &config.providers[0].claims_imports.can_request_admin;
+            assert_eq!(can_request_admin.expression.as_deref(), Some("user.groups"));
+            assert_eq!(can_request_admin.allowed_values, vec!["matrix-admins"]);
+
+            // Allowed values without an expression to check them against are
+            // rejected
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_post
+                          claims_imports:
+                            can_request_admin:
+                              allowed_values: ["matrix-admins"]
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reject_sync_without_forced_import() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_post
+                          claims_imports:
+                            displayname:
+                              action: suggest
+                              sync: always
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_post
+                          claims_imports:
+                            displayname:
+                              action: force
+                              sync: always
+                              on_conflict: overwrite
+                            avatar:
+                              action: force
+                              sync: always
+                              max_size: 2097152
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            config.validate(&figment)?;
+
+            let claims_imports = &config.providers[0].claims_imports;
+            assert_eq!(claims_imports.displayname.sync, SyncMode::Always);
+            assert_eq!(
+                claims_imports.displayname.on_conflict,
+                OnConflict::Overwrite
+            );
+            assert_eq!(claims_imports.avatar.on_conflict, OnConflict::KeepLocal);
+            assert_eq!(claims_imports.avatar.max_size, Some(2_097_152));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn load_provider_preset() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          preset: google
+                          client_id: client
+                          client_secret: secret
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3K
+                          preset: azure-ad
+                          issuer: https://login.microsoftonline.com/tenant/v2.0
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_basic
+                          scope: "openid email"
+                          claims_imports:
+                            localpart:
+                              action: suggest
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            config.validate(&figment)?;
+
+            let google = config.providers[0].clone().with_preset();
+            assert_eq!(
+                google.issuer.as_deref(),
+                Some("https://accounts.google.com")
+            );
+            assert_eq!(google.brand_name.as_deref(), Some("google"));
+            assert!(matches!(
+                google.token_endpoint_auth_method,
+                Some(TokenAuthMethod::ClientSecretPost)
+            ));
+            assert_eq!(google.scope, "openid profile email");
+            assert_eq!(google.claims_imports.localpart.action, ImportAction::Ignore);
+            assert_eq!(google.claims_imports.email.action, ImportAction::Suggest);
+
+            // What is explicitly configured takes precedence over the preset
+            let azure_ad = config.providers[1].clone().with_preset();
+            assert!(matches!(azure_ad.discovery_mode, DiscoveryMode::Insecure));
+            assert!(matches!(azure_ad.pkce_method, PkceMethod::Always));
+            assert!(matches!(
+                azure_ad.token_endpoint_auth_method,
+                Some(TokenAuthMethod::ClientSecretBasic)
+            ));
+            assert_eq!(azure_ad.scope, "openid email");
+            assert_eq!(
+                azure_ad.claims_imports.localpart,
+                LocalpartImportPreference {
+                    action: ImportAction::Suggest,
+                    template: None,
+                }
+            );
+            assert_eq!(
+                azure_ad.claims_imports.account_name.template.as_deref(),
+                Some("{{ user.preferred_username }}")
+            );
+
+            // Without a preset, the authentication method is required
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          client_secret: secret
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn validate_subject_migrations() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_post
+                          subject_migrations:
+                            - pattern: "^legacy:(?<id>[0-9]+)$"
+                              template: "user-${id}"
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            config.validate(&figment)?;
+            assert_eq!(config.providers[0].subject_migrations.len(), 1);
+
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_post
+                          subject_migrations:
+                            - pattern: "^legacy:([0-9]+$"
+                              template: "user-$1"
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reject_invalid_email_domains() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_post
+                          claims_imports:
+                            email:
+                              forbidden_domains:
+                                - "@example.com"
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_post
+                          claims_imports:
+                            email:
+                              allowed_domains:
+                                - example.com
+                              forbidden_domains:
+                                - corp.example.com
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            config.validate(&figment)?;
+
+            let email = &config.providers[0].claims_imports.email;
+            assert_eq!(email.allowed_domains, ["example.com"]);
+            assert_eq!(email.forbidden_domains, ["corp.example.com"]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reject_sign_in_with_apple_without_form_post() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01JAYS74TCG3BTWKADN5Q4518C
+                          issuer: https://appleid.apple.com
+                          client_id: com.example.service
+                          scope: "openid name email"
+                          token_endpoint_auth_method: sign_in_with_apple
+                          sign_in_with_apple:
+                            private_key_file: AuthKey_ABC123DEFG.p8
+                            team_id: TEAMID1234
+                            key_id: ABC123DEFG
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01JAYS74TCG3BTWKADN5Q4518C
+                          issuer: https://appleid.apple.com
+                          client_id: com.example.service
+                          scope: "openid name email"
+                          response_mode: form_post
+                          token_endpoint_auth_method: sign_in_with_apple
+                          sign_in_with_apple:
+                            private_key_file: AuthKey_ABC123DEFG.p8
+                            team_id: TEAMID1234
+                            key_id: ABC123DEFG
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            config.validate(&figment)?;
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn validate_client_authentication() {
+        Jail::expect_with(|jail| {
+            // `tls_client_auth` requires a client certificate
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          token_endpoint_auth_method: tls_client_auth
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          token_endpoint_auth_method: tls_client_auth
+                          tls_client_certificate:
+                            certificate_file: client.crt
+                            key_file: client.key
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            config.validate(&figment)?;
+
+            // The signing key ID is only used by `private_key_jwt`
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          client_secret: secret
+                          token_endpoint_auth_method: client_secret_jwt
+                          token_endpoint_auth_signing_alg: HS256
+                          token_endpoint_auth_signing_key_id: abcdef
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          token_endpoint_auth_method: private_key_jwt
+                          token_endpoint_auth_signing_alg: ES256
+                          token_endpoint_auth_signing_key_id: abcdef
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            config.validate(&figment)?;
+
+            let provider = &config.providers[0];
+            assert_eq!(
+                provider.token_endpoint_auth_signing_key_id.as_deref(),
+                Some("abcdef")
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn validate_deprovisioning() {
+        Jail::expect_with(|jail| {
+            // Refused refresh tokens can only be noticed if tokens are stored
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          token_endpoint_auth_method: none
+                          deprovisioning:
+                            source: refresh_token
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      providers:
+                        - id: 01HFS67GJ145HCM9ZASYS9DC3J
+                          issuer: https://sso.example.com/
+                          client_id: client
+                          token_endpoint_auth_method: none
+                          store_tokens: true
+                          deprovisioning:
+                            source: refresh_token
+                            action: deactivate
+                            dry_run: true
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            config.validate(&figment)?;
+
+            let deprovisioning = config.providers[0].deprovisioning.unwrap();
+            assert_eq!(deprovisioning.source, DeprovisioningSource::RefreshToken);
+            assert_eq!(deprovisioning.action, DeprovisioningAction::Deactivate);
+            assert!(deprovisioning.dry_run);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn load_health_checks() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      health_checks:
+                        interval: 600
+                        disable_unhealthy_providers: true
+                      providers: []
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            config.validate(&figment)?;
+
+            assert!(config.health_checks.enabled);
+            assert_eq!(config.health_checks.interval, Duration::minutes(10));
+            assert_eq!(config.health_checks.jwks_max_age, Duration::days(1));
+            assert!(config.health_checks.disable_unhealthy_providers);
+
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      health_checks:
+                        interval: 10
+                      providers: []
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn load_circuit_breaker() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      circuit_breaker:
+                        failure_threshold: 10
+                      providers: []
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            config.validate(&figment)?;
+
+            assert!(config.circuit_breaker.enabled);
+            assert_eq!(config.circuit_breaker.failure_threshold, 10);
+            assert_eq!(config.circuit_breaker.open_duration, Duration::seconds(30));
+
+            jail.create_file(
+                "config.yaml",
+                r#"
+                    upstream_oauth2:
+                      circuit_breaker:
+                        failure_threshold: 0
+                      providers: []
+                "#,
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UpstreamOAuth2Config>("upstream_oauth2")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
 }
@@ -0,0 +1,258 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::BTreeSet;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::ConfigurationSection;
+
+/// Claims which are set by the service itself, and can't be used by custom
+/// attributes
+const RESERVED_CLAIMS: &[&str] = &[
+    "iss",
+    "sub",
+    "aud",
+    "exp",
+    "iat",
+    "nbf",
+    "jti",
+    "nonce",
+    "auth_time",
+    "at_hash",
+    "c_hash",
+    "acr",
+    "amr",
+    "azp",
+    "sid",
+    "username",
+    "preferred_username",
+    "email",
+    "email_verified",
+];
+
+/// The type of the values of a custom user attribute
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UserAttributeType {
+    /// A string value
+    String,
+
+    /// An integer value
+    Integer,
+
+    /// A boolean value
+    Boolean,
+}
+
+/// Who can see a custom user attribute
+#[derive(Clone, Copy, Debug, Default, Deserialize, JsonSchema, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UserAttributeVisibility {
+    /// The attribute is only visible through the admin API
+    #[default]
+    Private,
+
+    /// The attribute is also exposed to clients, as a claim in the ID tokens
+    /// and through the userinfo endpoint
+    Public,
+}
+
+impl UserAttributeVisibility {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A custom attribute which can be set on users
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct UserAttributeConfig {
+    /// The name of the attribute.
+    ///
+    /// Must only contain lowercase letters, digits and underscores.
+    pub name: String,
+
+    /// The type of the values of the attribute
+    #[serde(rename = "type")]
+    pub kind: UserAttributeType,
+
+    /// A human-readable description of the attribute
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Who can see the attribute. Defaults to `private`.
+    #[serde(default, skip_serializing_if = "UserAttributeVisibility::is_default")]
+    pub visibility: UserAttributeVisibility,
+
+    /// The name of the claim under which a public attribute is exposed to
+    /// clients. Defaults to the name of the attribute.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claim: Option<String>,
+}
+
+impl UserAttributeConfig {
+    /// The name of the claim under which the attribute is exposed to clients,
+    /// if it is public
+    #[must_use]
+    pub fn claim_name(&self) -> Option<&str> {
+        match self.visibility {
+            UserAttributeVisibility::Private => None,
+            UserAttributeVisibility::Public => Some(self.claim.as_deref().unwrap_or(&self.name)),
+        }
+    }
+}
+
+/// Configuration section to declare custom attributes on users
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+pub struct UserAttributesConfig {
+    /// List of custom attributes which can be set on users
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<UserAttributeConfig>,
+}
+
+impl UserAttributesConfig {
+    /// Returns true if the configuration is the default one
+    pub(crate) fn is_default(&self) -> bool {
+        self.attributes.is_empty()
+    }
+}
+
+impl ConfigurationSection for UserAttributesConfig {
+    const PATH: Option<&'static str> = Some("user_attributes");
+
+    fn validate(&self, figment: &figment::Figment) -> Result<(), figment::Error> {
+        let mut names = BTreeSet::new();
+        let mut claims = BTreeSet::new();
+
+        for (index, attribute) in self.attributes.iter().enumerate() {
+            let annotate = |mut error: figment::Error| {
+                error.metadata = figment
+                    .find_metadata(&format!("{root}.attributes", root = Self::PATH.unwrap()))
+                    .cloned();
+                error.profile = Some(figment::Profile::Default);
+                error.path = vec![
+                    Self::PATH.unwrap().to_owned(),
+                    "attributes".to_owned(),
+                    index.to_string(),
+                ];
+                Err(error)
+            };
+
+            let valid_name = !attribute.name.is_empty()
+                && attribute
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+            if !valid_name {
+                return annotate(figment::Error::custom(
+                    "Attribute names must only contain lowercase letters, digits and underscores",
+                ));
+            }
+
+            if !names.insert(attribute.name.as_str()) {
+                return annotate(figment::Error::custom(format!(
+                    "Duplicate attribute name {:?}",
+                    attribute.name
+                )));
+            }
+
+            if attribute.claim.is_some() && attribute.visibility == UserAttributeVisibility::Private
+            {
+                return annotate(figment::Error::custom(
+                    "The `claim` field is only allowed on public attributes",
+                ));
+            }
+
+            if let Some(claim) = attribute.claim_name() {
+                if RESERVED_CLAIMS.contains(&claim) {
+                    return annotate(figment::Error::custom(format!(
+                        "The claim {claim:?} is reserved and can't be used by a custom attribute"
+                    )));
+                }
+
+                if !claims.insert(claim) {
+                    return annotate(figment::Error::custom(format!(
+                        "Duplicate claim name {claim:?}"
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::{
+        Figment, Jail,
+        providers::{Format, Yaml},
+    };
+
+    use super::*;
+
+    #[test]
+    fn load_config() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r"
+                    user_attributes:
+                      attributes:
+                        - name: employee_id
+                          type: string
+                          visibility: public
+                        - name: department
+                          type: string
+                          visibility: public
+                          claim: dept
+                        - name: clearance
+                          type: integer
+                ",
+            )?;
+
+            let config = Figment::new()
+                .merge(Yaml::file("config.yaml"))
+                .extract_inner::<UserAttributesConfig>("user_attributes")?;
+
+            assert_eq!(config.attributes.len(), 3);
+            assert_eq!(config.attributes[0].kind, UserAttributeType::String);
+            assert_eq!(config.attributes[0].claim_name(), Some("employee_id"));
+            assert_eq!(config.attributes[1].claim_name(), Some("dept"));
+            assert_eq!(
+                config.attributes[2].visibility,
+                UserAttributeVisibility::Private
+            );
+            assert_eq!(config.attributes[2].claim_name(), None);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn reject_reserved_claim() {
+        Jail::expect_with(|jail| {
+            jail.create_file(
+                "config.yaml",
+                r"
+                    user_attributes:
+                      attributes:
+                        - name: subject
+                          type: string
+                          visibility: public
+                          claim: sub
+                ",
+            )?;
+
+            let figment = Figment::new().merge(Yaml::file("config.yaml"));
+            let config = figment.extract_inner::<UserAttributesConfig>("user_attributes")?;
+            assert!(config.validate(&figment).is_err());
+
+            Ok(())
+        });
+    }
+}
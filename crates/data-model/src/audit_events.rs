@@ -0,0 +1,71 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+/// A security-relevant event, persisted for later review
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditEvent {
+    pub id: Ulid,
+
+    /// The user the event relates to, if any
+    pub user_id: Option<Ulid>,
+
+    /// What happened
+    pub kind: AuditEventKind,
+
+    /// When the event happened
+    pub created_at: DateTime<Utc>,
+}
+
+/// The kind of an [`AuditEvent`], with the details specific to it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditEventKind {
+    /// An already rotated refresh token was presented again, and the whole
+    /// session was ended as a result
+    RefreshTokenReused {
+        oauth2_session_id: Ulid,
+        oauth2_client_id: Ulid,
+        refresh_token_id: Ulid,
+    },
+
+    /// A client revoked one of the tokens of a session, which ended the
+    /// session and revoked all the tokens derived from it
+    OAuth2SessionRevoked {
+        oauth2_session_id: Ulid,
+        oauth2_client_id: Ulid,
+        revoked_access_tokens: usize,
+        revoked_refresh_tokens: usize,
+    },
+
+    /// A browser session authenticated through an upstream provider ended,
+    /// which ended an OAuth 2.0 session started from it and revoked its tokens
+    OAuth2SessionEndedWithBrowserSession {
+        oauth2_session_id: Ulid,
+        oauth2_client_id: Ulid,
+        browser_session_id: Ulid,
+        revoked_access_tokens: usize,
+        revoked_refresh_tokens: usize,
+    },
+
+    /// An administrator started impersonating the user through a
+    /// time-limited compatibility session
+    ImpersonationStarted {
+        compat_session_id: Ulid,
+        admin_session_id: Ulid,
+        admin_user_id: Option<Ulid>,
+        expires_at: DateTime<Utc>,
+    },
+
+    /// An administrator changed the username of the user
+    UsernameChanged {
+        old_username: String,
+        new_username: String,
+        admin_session_id: Ulid,
+    },
+}
@@ -11,7 +11,7 @@ use serde::Serialize;
 use ulid::Ulid;
 
 use super::Device;
-use crate::InvalidTransitionError;
+use crate::{InvalidTransitionError, IpLocation};
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub enum CompatSessionState {
@@ -76,9 +76,15 @@ pub struct CompatSession {
     pub user_session_id: Option<Ulid>,
     pub created_at: DateTime<Utc>,
     pub is_synapse_admin: bool,
+
+    /// The admin API session which minted this session to act as the user, if
+    /// this is an impersonation
+    pub impersonated_by_session_id: Option<Ulid>,
+
     pub user_agent: Option<String>,
     pub last_active_at: Option<DateTime<Utc>>,
     pub last_active_ip: Option<IpAddr>,
+    pub last_active_location: IpLocation,
 }
 
 impl std::ops::Deref for CompatSession {
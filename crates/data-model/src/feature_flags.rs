@@ -0,0 +1,194 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use chrono::{DateTime, Utc};
+use crc::{CRC_32_ISO_HDLC, Crc};
+use serde::Serialize;
+use thiserror::Error;
+use ulid::Ulid;
+
+const CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// A feature which can be rolled out gradually, or turned off, at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureFlag {
+    /// The password grant, for the first-party clients allowed to use it
+    PasswordGrant,
+
+    /// Claiming pre-provisioned accounts through claim links
+    AccountClaim,
+}
+
+impl FeatureFlag {
+    /// All the known feature flags
+    pub const ALL: &'static [Self] = &[Self::PasswordGrant, Self::AccountClaim];
+
+    /// The name of the flag, as used in the configuration and the admin API
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::PasswordGrant => "password_grant",
+            Self::AccountClaim => "account_claim",
+        }
+    }
+}
+
+impl std::fmt::Display for FeatureFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The name doesn't match any known feature flag
+#[derive(Debug, Error)]
+#[error("unknown feature flag {0:?}")]
+pub struct UnknownFeatureFlagError(String);
+
+impl std::str::FromStr for FeatureFlag {
+    type Err = UnknownFeatureFlagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|flag| flag.as_str() == s)
+            .ok_or_else(|| UnknownFeatureFlagError(s.to_owned()))
+    }
+}
+
+/// How a feature flag is rolled out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FeatureFlagRollout {
+    /// Whether the feature is enabled at all. This acts as a kill switch.
+    pub enabled: bool,
+
+    /// The percentage of users for which the feature is enabled, from 0 to
+    /// 100
+    pub percentage: u8,
+}
+
+impl Default for FeatureFlagRollout {
+    fn default() -> Self {
+        Self::ENABLED
+    }
+}
+
+impl FeatureFlagRollout {
+    /// The feature is enabled for everyone
+    pub const ENABLED: Self = Self {
+        enabled: true,
+        percentage: 100,
+    };
+
+    /// The feature is disabled for everyone
+    pub const DISABLED: Self = Self {
+        enabled: false,
+        percentage: 0,
+    };
+
+    /// Whether the given flag is enabled for a user
+    ///
+    /// Users are put in one of 100 buckets using a hash of the flag name and
+    /// their ID, so that a user stays in the rollout as the percentage grows,
+    /// and that different flags reach different users first. When there is no
+    /// user, the feature is only enabled if it is rolled out to everyone.
+    #[must_use]
+    pub fn is_enabled_for(&self, flag: FeatureFlag, user_id: Option<Ulid>) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        if self.percentage >= 100 {
+            return true;
+        }
+
+        let Some(user_id) = user_id else {
+            return false;
+        };
+
+        let mut digest = CRC.digest();
+        digest.update(flag.as_str().as_bytes());
+        digest.update(b":");
+        digest.update(&user_id.to_bytes());
+        let bucket = digest.finalize() % 100;
+
+        bucket < u32::from(self.percentage)
+    }
+}
+
+/// A rollout of a feature flag set through the admin API, overriding the one
+/// from the configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeatureFlagOverride {
+    pub id: Ulid,
+    pub flag: FeatureFlag,
+    pub rollout: FeatureFlagRollout,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollout() {
+        let flag = FeatureFlag::PasswordGrant;
+        let users: Vec<Ulid> = (0..1000u128).map(Ulid::from).collect();
+
+        for user in &users {
+            assert!(FeatureFlagRollout::ENABLED.is_enabled_for(flag, Some(*user)));
+            assert!(!FeatureFlagRollout::DISABLED.is_enabled_for(flag, Some(*user)));
+        }
+        assert!(FeatureFlagRollout::ENABLED.is_enabled_for(flag, None));
+        assert!(!FeatureFlagRollout::DISABLED.is_enabled_for(flag, None));
+
+        // The kill switch wins over the percentage
+        let killed = FeatureFlagRollout {
+            enabled: false,
+            percentage: 100,
+        };
+        assert!(!killed.is_enabled_for(flag, Some(users[0])));
+
+        let count = |percentage| {
+            let rollout = FeatureFlagRollout {
+                enabled: true,
+                percentage,
+            };
+            users
+                .iter()
+                .filter(|user| rollout.is_enabled_for(flag, Some(**user)))
+                .count()
+        };
+
+        // Roughly the right share of users gets the feature
+        let ten = count(10);
+        assert!((50..150).contains(&ten), "{ten} users out of 1000");
+        let fifty = count(50);
+        assert!((400..600).contains(&fifty), "{fifty} users out of 1000");
+
+        // Users keep the feature as the rollout grows
+        let rollout = |percentage| FeatureFlagRollout {
+            enabled: true,
+            percentage,
+        };
+        for user in &users {
+            if rollout(10).is_enabled_for(flag, Some(*user)) {
+                assert!(rollout(50).is_enabled_for(flag, Some(*user)));
+            }
+        }
+
+        // Partial rollouts never apply without a user
+        assert!(!rollout(99).is_enabled_for(flag, None));
+    }
+
+    #[test]
+    fn test_parse_flag() {
+        for flag in FeatureFlag::ALL {
+            assert_eq!(flag.as_str().parse::<FeatureFlag>().unwrap(), *flag);
+        }
+        assert!("passkeys".parse::<FeatureFlag>().is_err());
+    }
+}
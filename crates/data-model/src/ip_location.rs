@@ -0,0 +1,27 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use serde::Serialize;
+
+/// Where an IP address is located, as resolved from the GeoIP databases
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IpLocation {
+    /// The ISO 3166-1 alpha-2 code of the country, like `FR`
+    pub country: Option<String>,
+
+    /// The number of the autonomous system announcing the address
+    pub asn: Option<u32>,
+
+    /// The name of the organisation operating the autonomous system
+    pub as_organization: Option<String>,
+}
+
+impl IpLocation {
+    /// Returns `true` if nothing is known about the location
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.country.is_none() && self.asn.is_none() && self.as_organization.is_none()
+    }
+}
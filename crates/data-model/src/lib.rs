@@ -8,9 +8,14 @@
 
 use thiserror::Error;
 
+pub(crate) mod audit_events;
 pub(crate) mod compat;
+pub(crate) mod feature_flags;
+pub(crate) mod ip_location;
+pub(crate) mod login_stats;
 pub mod oauth2;
 pub(crate) mod policy_data;
+pub(crate) mod scim;
 mod site_config;
 pub(crate) mod tokens;
 pub(crate) mod upstream_oauth2;
@@ -25,31 +30,56 @@ pub struct InvalidTransitionError;
 pub use ulid::Ulid;
 
 pub use self::{
+    audit_events::{AuditEvent, AuditEventKind},
     compat::{
         CompatAccessToken, CompatRefreshToken, CompatRefreshTokenState, CompatSession,
         CompatSessionState, CompatSsoLogin, CompatSsoLoginState, Device, ToScopeTokenError,
     },
+    feature_flags::{
+        FeatureFlag, FeatureFlagOverride, FeatureFlagRollout, UnknownFeatureFlagError,
+    },
+    ip_location::IpLocation,
+    login_stats::DailyLoginStats,
     oauth2::{
-        AuthorizationCode, AuthorizationGrant, AuthorizationGrantStage, Client, DeviceCodeGrant,
-        DeviceCodeGrantState, InvalidRedirectUriError, JwksOrJwksUri, Pkce, Session, SessionState,
+        AccessTokenFormat, AuthorizationCode, AuthorizationGrant, AuthorizationGrantStage,
+        BackchannelAuthenticationGrant, BackchannelAuthenticationGrantState, Client, Consent,
+        DeviceCodeGrant, DeviceCodeGrantState, DisallowedScopeHandling,
+        InvalidAccessTokenFormatError, InvalidDisallowedScopeHandlingError,
+        InvalidRedirectUriError, InvalidRefreshTokenRotationError, InvalidTokenAudienceError,
+        JwksOrJwksUri, PUSHED_AUTHORIZATION_REQUEST_URI_PREFIX, Pkce, PushedAuthorizationRequest,
+        RefreshTokenRotation, ScopePolicy, Session, SessionState, TokenAudience,
     },
     policy_data::PolicyData,
-    site_config::{CaptchaConfig, CaptchaService, SessionExpirationConfig, SiteConfig},
+    scim::{ScimSyncAction, ScimSyncChange, ScimSyncRun, ScimSyncRunState, ScimUserLink},
+    site_config::{
+        AcrValue, CaptchaConfig, CaptchaService, HomeRealmDiscoveryConfig, PasswordLockoutConfig,
+        ScimClientConfig, SessionExpirationConfig, SiteConfig, SoftwareStatementIssuer,
+        TermsDocument, TotpPolicy, UserAttributeDefinition, UserAttributeKind, UserInvitesConfig,
+    },
     tokens::{
         AccessToken, AccessTokenState, RefreshToken, RefreshTokenState, TokenFormatError, TokenType,
     },
     upstream_oauth2::{
         UpstreamOAuthAuthorizationSession, UpstreamOAuthAuthorizationSessionState,
-        UpstreamOAuthLink, UpstreamOAuthProvider, UpstreamOAuthProviderClaimsImports,
-        UpstreamOAuthProviderDiscoveryMode, UpstreamOAuthProviderImportAction,
-        UpstreamOAuthProviderImportPreference, UpstreamOAuthProviderPkceMode,
-        UpstreamOAuthProviderResponseMode, UpstreamOAuthProviderSubjectPreference,
+        UpstreamOAuthCachedDocument, UpstreamOAuthCachedDocumentKind, UpstreamOAuthLink,
+        UpstreamOAuthLinkImportedAttributes, UpstreamOAuthLinkTokens, UpstreamOAuthProvider,
+        UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderDeprovisioning,
+        UpstreamOAuthProviderDeprovisioningAction, UpstreamOAuthProviderDeprovisioningSource,
+        UpstreamOAuthProviderDiscoveryMode, UpstreamOAuthProviderEmailDomains,
+        UpstreamOAuthProviderImportAction, UpstreamOAuthProviderImportPreference,
+        UpstreamOAuthProviderOnConflict, UpstreamOAuthProviderPkceMode,
+        UpstreamOAuthProviderResponseMode, UpstreamOAuthProviderRolePreference,
+        UpstreamOAuthProviderSubjectPreference, UpstreamOAuthProviderSyncMode,
         UpstreamOAuthProviderTokenAuthMethod,
     },
     user_agent::{DeviceType, UserAgent},
     users::{
-        Authentication, AuthenticationMethod, BrowserSession, Password, User, UserEmail,
-        UserEmailAuthentication, UserEmailAuthenticationCode, UserRecoverySession,
+        Authentication, AuthenticationMethod, BrowserSession, BrowserSessionElevation,
+        InvalidTermsDocumentKindError, Password, TermsDocumentKind, User, UserAction,
+        UserActionToken, UserClaimLink, UserDeletion, UserEmail, UserEmailAuthentication,
+        UserEmailAuthenticationCode, UserMetadata, UserPasskey, UserPasskeyChallenge,
+        UserPasswordPolicy, UserPhone, UserPhoneCode, UserRecoveryCode, UserRecoverySession,
         UserRecoveryTicket, UserRegistration, UserRegistrationPassword, UserRegistrationToken,
+        UserTermsAcceptance, UserTotp, UserTrustedDevice,
     },
 };
@@ -0,0 +1,21 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// The number of logins which happened on a given day, for a single upstream
+/// OAuth 2.0 provider or OAuth 2.0 client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DailyLoginStats {
+    /// The day, in UTC
+    pub day: NaiveDate,
+
+    /// The number of successful logins on that day
+    pub successful: u64,
+
+    /// The number of failed or abandoned logins on that day
+    pub failed: u64,
+}
@@ -4,9 +4,13 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
-use chrono::{DateTime, Utc};
+use std::{collections::BTreeSet, num::NonZeroU32};
+
+use chrono::{DateTime, Duration, Utc};
 use mas_iana::oauth::PkceCodeChallengeMethod;
 use oauth2_types::{
+    authorization_details::AuthorizationDetail,
+    claims::ClaimsRequest,
     pkce::{CodeChallengeError, CodeChallengeMethodExt},
     requests::ResponseMode,
     scope::{OPENID, PROFILE, Scope},
@@ -21,7 +25,7 @@ use ulid::Ulid;
 use url::Url;
 
 use super::session::Session;
-use crate::InvalidTransitionError;
+use crate::{Authentication, InvalidTransitionError};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Pkce {
@@ -145,6 +149,35 @@ pub enum LoginHint<'a> {
     None,
 }
 
+impl<'a> LoginHint<'a> {
+    /// Parse a `login_hint`, only accepting MXIDs on the given homeserver
+    #[must_use]
+    pub fn parse(login_hint: &'a str, homeserver: &str) -> Self {
+        // Return none if the format is incorrect
+        let Some((prefix, value)) = login_hint.split_once(':') else {
+            return LoginHint::None;
+        };
+
+        match prefix {
+            "mxid" => {
+                // Instead of erroring just return none
+                let Ok(mxid) = <&UserId>::try_from(value) else {
+                    return LoginHint::None;
+                };
+
+                // Only handle MXIDs for current homeserver
+                if mxid.server_name() != homeserver {
+                    return LoginHint::None;
+                }
+
+                LoginHint::MXID(mxid)
+            }
+            // Unknown hint type, treat as none
+            _ => LoginHint::None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct AuthorizationGrant {
     pub id: Ulid,
@@ -161,6 +194,14 @@ pub struct AuthorizationGrant {
     pub created_at: DateTime<Utc>,
     pub login_hint: Option<String>,
     pub locale: Option<String>,
+    pub device_fingerprint: Option<String>,
+    pub authorization_details: Vec<AuthorizationDetail>,
+    pub resource: Option<Url>,
+    pub required_acr: Option<String>,
+    pub max_age: Option<NonZeroU32>,
+    pub prompt_login: bool,
+    pub prompt_consent: bool,
+    pub claims: Option<ClaimsRequest>,
 }
 
 impl std::ops::Deref for AuthorizationGrant {
@@ -178,28 +219,62 @@ impl AuthorizationGrant {
             return LoginHint::None;
         };
 
-        // Return none if the format is incorrect
-        let Some((prefix, value)) = login_hint.split_once(':') else {
-            return LoginHint::None;
-        };
+        LoginHint::parse(login_hint, homeserver)
+    }
 
-        match prefix {
-            "mxid" => {
-                // Instead of erroring just return none
-                let Ok(mxid) = <&UserId>::try_from(value) else {
-                    return LoginHint::None;
-                };
+    /// The names of all the claims the client requested individually with the
+    /// `claims` parameter
+    #[must_use]
+    pub fn requested_claims(&self) -> BTreeSet<&str> {
+        self.claims
+            .as_ref()
+            .map(ClaimsRequest::names)
+            .unwrap_or_default()
+    }
 
-                // Only handle MXIDs for current homeserver
-                if mxid.server_name() != homeserver {
-                    return LoginHint::None;
-                }
+    /// The names of the claims the client requested individually in the ID
+    /// token, with the `claims` parameter
+    pub fn id_token_claims(&self) -> impl Iterator<Item = &str> {
+        self.claims
+            .iter()
+            .flat_map(|claims| claims.id_token.keys())
+            .map(String::as_str)
+    }
 
-                LoginHint::MXID(mxid)
-            }
-            // Unknown hint type, treat as none
-            _ => LoginHint::None,
+    /// The names of the claims the client requested individually in the
+    /// userinfo response, with the `claims` parameter
+    #[must_use]
+    pub fn userinfo_claims(&self) -> Vec<String> {
+        self.claims
+            .iter()
+            .flat_map(|claims| claims.userinfo.keys())
+            .cloned()
+            .collect()
+    }
+
+    /// The time after which the user must have authenticated for this grant to
+    /// be fulfilled, if the client asked for a fresh authentication with the
+    /// `prompt=login` or `max_age` parameters
+    #[must_use]
+    pub fn authenticated_after(&self) -> Option<DateTime<Utc>> {
+        if self.prompt_login {
+            return Some(self.created_at);
         }
+
+        self.max_age
+            .map(|max_age| self.created_at - Duration::seconds(max_age.get().into()))
+    }
+
+    /// Check whether the last authentication of a browser session is recent
+    /// enough for this grant
+    #[must_use]
+    pub fn is_authentication_fresh(&self, last_authentication: Option<&Authentication>) -> bool {
+        let Some(authenticated_after) = self.authenticated_after() else {
+            return true;
+        };
+
+        last_authentication
+            .is_some_and(|authentication| authentication.created_at >= authenticated_after)
     }
 
     /// Mark the authorization grant as exchanged.
@@ -265,6 +340,25 @@ impl AuthorizationGrant {
             created_at: now,
             login_hint: Some(String::from("mxid:@example-user:example.com")),
             locale: Some(String::from("fr")),
+            device_fingerprint: None,
+            authorization_details: vec![AuthorizationDetail {
+                r#type: String::from("account_information"),
+                locations: Some(vec![String::from("https://example.com/accounts")]),
+                actions: Some(vec![
+                    String::from("list_accounts"),
+                    String::from("read_balances"),
+                ]),
+                datatypes: None,
+                identifier: None,
+                privileges: None,
+                extra: std::collections::BTreeMap::new(),
+            }],
+            resource: None,
+            required_acr: None,
+            max_age: None,
+            prompt_login: false,
+            prompt_consent: false,
+            claims: None,
         }
     }
 }
@@ -364,4 +458,43 @@ mod tests {
 
         assert!(matches!(hint, LoginHint::None));
     }
+
+    #[test]
+    fn authentication_freshness() {
+        #[allow(clippy::disallowed_methods)]
+        let mut rng = thread_rng();
+
+        #[allow(clippy::disallowed_methods)]
+        let now = Utc::now();
+
+        let authentication = |created_at| Authentication {
+            id: Ulid::nil(),
+            created_at,
+            authentication_method: crate::AuthenticationMethod::Unknown,
+        };
+        let old = authentication(now - Duration::minutes(10));
+        let recent = authentication(now + Duration::seconds(1));
+
+        // Any authentication is fine by default
+        let grant = AuthorizationGrant::sample(now, &mut rng);
+        assert!(grant.is_authentication_fresh(Some(&old)));
+
+        // With max_age, the authentication must be recent enough
+        let grant = AuthorizationGrant {
+            max_age: NonZeroU32::new(60),
+            ..AuthorizationGrant::sample(now, &mut rng)
+        };
+        assert!(!grant.is_authentication_fresh(Some(&old)));
+        assert!(!grant.is_authentication_fresh(None));
+        assert!(grant.is_authentication_fresh(Some(&recent)));
+
+        // With prompt=login, the user must authenticate after the grant was created
+        let grant = AuthorizationGrant {
+            prompt_login: true,
+            max_age: NonZeroU32::new(3600),
+            ..AuthorizationGrant::sample(now, &mut rng)
+        };
+        assert!(!grant.is_authentication_fresh(Some(&old)));
+        assert!(grant.is_authentication_fresh(Some(&recent)));
+    }
 }
@@ -0,0 +1,260 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use chrono::{DateTime, Utc};
+use oauth2_types::scope::Scope;
+use serde::Serialize;
+use ulid::Ulid;
+
+use crate::{BrowserSession, InvalidTransitionError, Session};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "state")]
+pub enum BackchannelAuthenticationGrantState {
+    /// The request is waiting for the user to approve or reject it.
+    Pending,
+
+    /// The request has been approved by the user.
+    Fulfilled {
+        /// The browser session which was used to approve this request.
+        browser_session_id: Ulid,
+
+        /// The time at which this request was approved.
+        fulfilled_at: DateTime<Utc>,
+    },
+
+    /// The request has been rejected by the user.
+    Rejected {
+        /// The browser session which was used to reject this request.
+        browser_session_id: Ulid,
+
+        /// The time at which this request was rejected.
+        rejected_at: DateTime<Utc>,
+    },
+
+    /// The request was exchanged for an access token.
+    Exchanged {
+        /// The browser session which was used to approve this request.
+        browser_session_id: Ulid,
+
+        /// The time at which this request was approved.
+        fulfilled_at: DateTime<Utc>,
+
+        /// The time at which this request was exchanged.
+        exchanged_at: DateTime<Utc>,
+
+        /// The OAuth 2.0 session ID which was created by this request.
+        session_id: Ulid,
+    },
+}
+
+impl BackchannelAuthenticationGrantState {
+    /// Mark this request as fulfilled, returning a new state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request is not in the [`Pending`] state.
+    ///
+    /// [`Pending`]: BackchannelAuthenticationGrantState::Pending
+    pub fn fulfill(
+        self,
+        browser_session: &BrowserSession,
+        fulfilled_at: DateTime<Utc>,
+    ) -> Result<Self, InvalidTransitionError> {
+        match self {
+            Self::Pending => Ok(Self::Fulfilled {
+                browser_session_id: browser_session.id,
+                fulfilled_at,
+            }),
+            _ => Err(InvalidTransitionError),
+        }
+    }
+
+    /// Mark this request as rejected, returning a new state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request is not in the [`Pending`] state.
+    ///
+    /// [`Pending`]: BackchannelAuthenticationGrantState::Pending
+    pub fn reject(
+        self,
+        browser_session: &BrowserSession,
+        rejected_at: DateTime<Utc>,
+    ) -> Result<Self, InvalidTransitionError> {
+        match self {
+            Self::Pending => Ok(Self::Rejected {
+                browser_session_id: browser_session.id,
+                rejected_at,
+            }),
+            _ => Err(InvalidTransitionError),
+        }
+    }
+
+    /// Mark this request as exchanged, returning a new state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request is not in the [`Fulfilled`] state.
+    ///
+    /// [`Fulfilled`]: BackchannelAuthenticationGrantState::Fulfilled
+    pub fn exchange(
+        self,
+        session: &Session,
+        exchanged_at: DateTime<Utc>,
+    ) -> Result<Self, InvalidTransitionError> {
+        match self {
+            Self::Fulfilled {
+                fulfilled_at,
+                browser_session_id,
+            } => Ok(Self::Exchanged {
+                browser_session_id,
+                fulfilled_at,
+                exchanged_at,
+                session_id: session.id,
+            }),
+            _ => Err(InvalidTransitionError),
+        }
+    }
+
+    /// Returns `true` if the request state is [`Pending`].
+    ///
+    /// [`Pending`]: BackchannelAuthenticationGrantState::Pending
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Self::Pending)
+    }
+
+    /// Returns `true` if the request state is [`Fulfilled`].
+    ///
+    /// [`Fulfilled`]: BackchannelAuthenticationGrantState::Fulfilled
+    #[must_use]
+    pub fn is_fulfilled(&self) -> bool {
+        matches!(self, Self::Fulfilled { .. })
+    }
+
+    /// Returns `true` if the request state is [`Rejected`].
+    ///
+    /// [`Rejected`]: BackchannelAuthenticationGrantState::Rejected
+    #[must_use]
+    pub fn is_rejected(&self) -> bool {
+        matches!(self, Self::Rejected { .. })
+    }
+
+    /// Returns `true` if the request state is [`Exchanged`].
+    ///
+    /// [`Exchanged`]: BackchannelAuthenticationGrantState::Exchanged
+    #[must_use]
+    pub fn is_exchanged(&self) -> bool {
+        matches!(self, Self::Exchanged { .. })
+    }
+}
+
+/// An authentication request started by a client through the backchannel
+/// authentication endpoint, as per the OpenID Connect Client-Initiated
+/// Backchannel Authentication (CIBA) specification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BackchannelAuthenticationGrant {
+    pub id: Ulid,
+    #[serde(flatten)]
+    pub state: BackchannelAuthenticationGrantState,
+
+    /// The client ID which started this request.
+    pub client_id: Ulid,
+
+    /// The user which has to approve this request.
+    pub user_id: Ulid,
+
+    /// The scope which was requested.
+    pub scope: Scope,
+
+    /// The identifier given to the client, which it uses to get the tokens
+    /// from the token endpoint.
+    pub auth_req_id: String,
+
+    /// A short message displayed both on the client and on the approval
+    /// screen, so that the user can check both refer to the same request.
+    pub binding_message: Option<String>,
+
+    /// The bearer token used to notify the client once the user responded,
+    /// if the client uses the `ping` token delivery mode.
+    pub client_notification_token: Option<String>,
+
+    /// The time at which this request was created.
+    pub created_at: DateTime<Utc>,
+
+    /// The time at which this request will expire.
+    pub expires_at: DateTime<Utc>,
+}
+
+impl std::ops::Deref for BackchannelAuthenticationGrant {
+    type Target = BackchannelAuthenticationGrantState;
+
+    fn deref(&self) -> &Self::Target {
+        &self.state
+    }
+}
+
+impl BackchannelAuthenticationGrant {
+    /// Whether this request expired at the given time
+    #[must_use]
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at < now
+    }
+
+    /// Mark this request as fulfilled, returning the updated request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request is not in the [`Pending`] state.
+    ///
+    /// [`Pending`]: BackchannelAuthenticationGrantState::Pending
+    pub fn fulfill(
+        self,
+        browser_session: &BrowserSession,
+        fulfilled_at: DateTime<Utc>,
+    ) -> Result<Self, InvalidTransitionError> {
+        Ok(Self {
+            state: self.state.fulfill(browser_session, fulfilled_at)?,
+            ..self
+        })
+    }
+
+    /// Mark this request as rejected, returning the updated request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request is not in the [`Pending`] state.
+    ///
+    /// [`Pending`]: BackchannelAuthenticationGrantState::Pending
+    pub fn reject(
+        self,
+        browser_session: &BrowserSession,
+        rejected_at: DateTime<Utc>,
+    ) -> Result<Self, InvalidTransitionError> {
+        Ok(Self {
+            state: self.state.reject(browser_session, rejected_at)?,
+            ..self
+        })
+    }
+
+    /// Mark this request as exchanged, returning the updated request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request is not in the [`Fulfilled`] state.
+    ///
+    /// [`Fulfilled`]: BackchannelAuthenticationGrantState::Fulfilled
+    pub fn exchange(
+        self,
+        session: &Session,
+        exchanged_at: DateTime<Utc>,
+    ) -> Result<Self, InvalidTransitionError> {
+        Ok(Self {
+            state: self.state.exchange(session, exchanged_at)?,
+            ..self
+        })
+    }
+}
@@ -4,13 +4,17 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
-use chrono::{DateTime, Utc};
-use mas_iana::{jose::JsonWebSignatureAlg, oauth::OAuthClientAuthenticationMethod};
+use chrono::{DateTime, Duration, Utc};
+use mas_iana::{
+    jose::{JsonWebEncryptionAlg, JsonWebEncryptionEnc, JsonWebSignatureAlg},
+    oauth::OAuthClientAuthenticationMethod,
+};
 use mas_jose::jwk::PublicJsonWebKeySet;
 use oauth2_types::{
-    oidc::ApplicationType,
+    oidc::{ApplicationType, SubjectType},
     registration::{ClientMetadata, Localized},
     requests::GrantType,
+    scope::Scope,
 };
 use rand::RngCore;
 use serde::Serialize;
@@ -28,6 +32,214 @@ pub enum JwksOrJwksUri {
     JwksUri(Url),
 }
 
+/// How refresh tokens of a client are handled when they are used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RefreshTokenRotation {
+    /// Each use of a refresh token consumes it and issues a new one
+    #[default]
+    RotateOnUse,
+
+    /// The same refresh token is used for the whole lifetime of the session
+    Static,
+}
+
+#[derive(Debug, Clone, Error)]
+#[error("Invalid refresh token rotation {0:?}")]
+pub struct InvalidRefreshTokenRotationError(String);
+
+impl std::str::FromStr for RefreshTokenRotation {
+    type Err = InvalidRefreshTokenRotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rotate_on_use" => Ok(Self::RotateOnUse),
+            "static" => Ok(Self::Static),
+            s => Err(InvalidRefreshTokenRotationError(s.to_owned())),
+        }
+    }
+}
+
+impl RefreshTokenRotation {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::RotateOnUse => "rotate_on_use",
+            Self::Static => "static",
+        }
+    }
+}
+
+impl std::fmt::Display for RefreshTokenRotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The format of the access tokens issued to a client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessTokenFormat {
+    /// Random strings, which can only be checked through introspection
+    #[default]
+    Opaque,
+
+    /// Self-contained JWTs, following the RFC 9068 profile
+    Jwt,
+}
+
+#[derive(Debug, Clone, Error)]
+#[error("Invalid access token format {0:?}")]
+pub struct InvalidAccessTokenFormatError(String);
+
+impl std::str::FromStr for AccessTokenFormat {
+    type Err = InvalidAccessTokenFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "opaque" => Ok(Self::Opaque),
+            "jwt" => Ok(Self::Jwt),
+            s => Err(InvalidAccessTokenFormatError(s.to_owned())),
+        }
+    }
+}
+
+impl AccessTokenFormat {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Opaque => "opaque",
+            Self::Jwt => "jwt",
+        }
+    }
+}
+
+impl std::fmt::Display for AccessTokenFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What happens to a request for scopes a client isn't allowed to request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DisallowedScopeHandling {
+    /// The request is rejected by the policy engine
+    #[default]
+    Reject,
+
+    /// The scopes are removed from the request, which goes on with the others
+    Filter,
+}
+
+#[derive(Debug, Clone, Error)]
+#[error("Invalid disallowed scope handling {0:?}")]
+pub struct InvalidDisallowedScopeHandlingError(String);
+
+impl std::str::FromStr for DisallowedScopeHandling {
+    type Err = InvalidDisallowedScopeHandlingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "filter" => Ok(Self::Filter),
+            s => Err(InvalidDisallowedScopeHandlingError(s.to_owned())),
+        }
+    }
+}
+
+impl DisallowedScopeHandling {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Reject => "reject",
+            Self::Filter => "filter",
+        }
+    }
+}
+
+impl std::fmt::Display for DisallowedScopeHandling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The scopes a client is allowed to request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScopePolicy {
+    /// The allowed scopes. A `*` at the end of one matches any scope starting
+    /// with the rest of it, like `urn:matrix:org.matrix.msc2967.client:device:*`
+    pub allowed_scopes: Vec<String>,
+
+    /// What happens to requests for other scopes
+    pub disallowed: DisallowedScopeHandling,
+}
+
+impl ScopePolicy {
+    /// Whether the given scope token is allowed by this policy
+    #[must_use]
+    pub fn allows(&self, scope: &str) -> bool {
+        self.allowed_scopes
+            .iter()
+            .any(|allowed| match allowed.strip_suffix('*') {
+                Some(prefix) => scope.starts_with(prefix),
+                None => scope == allowed,
+            })
+    }
+}
+
+/// The audience of the tokens a resource server accepts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenAudience {
+    /// The Matrix Client-Server API, which gets the tokens issued without a
+    /// resource indicator and the compatibility tokens
+    Matrix,
+
+    /// An API identified by a resource indicator (RFC 8707)
+    Resource(Url),
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid token audience {0:?}")]
+pub struct InvalidTokenAudienceError(String, #[source] url::ParseError);
+
+impl std::str::FromStr for TokenAudience {
+    type Err = InvalidTokenAudienceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "matrix" => Ok(Self::Matrix),
+            s => s
+                .parse()
+                .map(Self::Resource)
+                .map_err(|e| InvalidTokenAudienceError(s.to_owned(), e)),
+        }
+    }
+}
+
+impl std::fmt::Display for TokenAudience {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Matrix => f.write_str("matrix"),
+            Self::Resource(resource) => f.write_str(resource.as_str()),
+        }
+    }
+}
+
+impl TokenAudience {
+    /// Whether a token with the given `aud`, as returned by the introspection
+    /// endpoint, was issued for this audience
+    #[must_use]
+    pub fn matches(&self, aud: Option<&str>) -> bool {
+        match (self, aud) {
+            (Self::Matrix, None) => true,
+            (Self::Resource(resource), Some(aud)) => resource.as_str() == aud,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Client {
     pub id: Ulid,
@@ -40,6 +252,16 @@ pub struct Client {
 
     pub encrypted_client_secret: Option<String>,
 
+    /// When the client secret stops being accepted, if it expires
+    pub client_secret_expires_at: Option<DateTime<Utc>>,
+
+    /// The secret replacing the current one during a rotation, accepted
+    /// alongside it
+    pub encrypted_next_client_secret: Option<String>,
+
+    /// When the next client secret stops being accepted, if it expires
+    pub next_client_secret_expires_at: Option<DateTime<Utc>>,
+
     pub application_type: Option<ApplicationType>,
 
     /// Array of Redirection URI values used by the Client
@@ -75,6 +297,9 @@ pub struct Client {
     /// JWS alg algorithm REQUIRED for signing `UserInfo` Responses.
     pub userinfo_signed_response_alg: Option<JsonWebSignatureAlg>,
 
+    /// JWS alg algorithm used for signing JWT-secured authorization responses
+    pub authorization_signed_response_alg: Option<JsonWebSignatureAlg>,
+
     /// Requested authentication method for the token endpoint
     pub token_endpoint_auth_method: Option<OAuthClientAuthenticationMethod>,
 
@@ -86,6 +311,90 @@ pub struct Client {
     /// URI using the https scheme that a third party can use to initiate a
     /// login by the RP
     pub initiate_login_uri: Option<Url>,
+
+    /// Whether the client can only start authorization requests through the
+    /// pushed authorization request endpoint
+    pub require_pushed_authorization_requests: bool,
+
+    /// If this client is a service account, the scope it is allowed to request
+    /// through the client credentials grant
+    pub service_account_scope: Option<Scope>,
+
+    /// The endpoint on which the client wants to be notified once a user
+    /// responded to a backchannel authentication request, if it uses the
+    /// `ping` token delivery mode
+    pub backchannel_client_notification_endpoint: Option<Url>,
+
+    /// The resources (RFC 8707) this client can request tokens for
+    pub allowed_resources: Vec<Url>,
+
+    /// Whether refresh tokens are rotated each time they are used
+    pub refresh_token_rotation: RefreshTokenRotation,
+
+    /// The URI on which the client wants to receive back-channel logout tokens
+    pub backchannel_logout_uri: Option<Url>,
+
+    /// Whether the `sid` claim should be included in back-channel logout
+    /// tokens sent to this client
+    pub backchannel_logout_session_required: bool,
+
+    /// Whether the `sub` claim should be included in back-channel logout
+    /// tokens sent to this client
+    pub backchannel_logout_include_sub: bool,
+
+    /// List of URIs the client can redirect the user to after an RP-initiated
+    /// logout
+    pub post_logout_redirect_uris: Vec<Url>,
+
+    /// The format of the access tokens issued to this client
+    pub access_token_format: AccessTokenFormat,
+
+    /// Lifetime of the access tokens issued to this client, overriding the
+    /// one configured on the server
+    #[serde(skip)]
+    pub access_token_ttl: Option<Duration>,
+
+    /// Lifetime of the refresh tokens issued to this client. Refresh tokens
+    /// don't expire if this is not set
+    #[serde(skip)]
+    pub refresh_token_ttl: Option<Duration>,
+
+    /// Lifetime of the device codes issued to this client, overriding the
+    /// default one
+    #[serde(skip)]
+    pub device_code_ttl: Option<Duration>,
+
+    /// JWS alg algorithm that MUST be used for signing the request objects
+    /// sent by this client. Any algorithm is accepted if not set
+    pub request_object_signing_alg: Option<JsonWebSignatureAlg>,
+
+    /// Whether the client must send its authorization request parameters in a
+    /// signed request object
+    pub require_signed_request_object: bool,
+
+    /// JWE alg algorithm used to encrypt the ID tokens issued to this client.
+    /// ID tokens are only signed if not set
+    pub id_token_encrypted_response_alg: Option<JsonWebEncryptionAlg>,
+
+    /// JWE enc algorithm used to encrypt the ID tokens issued to this client
+    pub id_token_encrypted_response_enc: Option<JsonWebEncryptionEnc>,
+
+    /// Authentication Context Class Reference values requested by default
+    /// when the client doesn't send `acr_values` in its authorization requests
+    pub default_acr_values: Vec<String>,
+
+    /// The audience of the tokens this client gets as active when it
+    /// introspects them, if it is a resource server
+    pub resource_server_audience: Option<TokenAudience>,
+
+    /// The sector identifier used to compute the pairwise subject identifiers
+    /// given to this client. The client gets the public subject identifiers
+    /// of users if not set
+    pub pairwise_sector_identifier: Option<String>,
+
+    /// The scopes this client is allowed to request. It can request any scope
+    /// if not set
+    pub scope_policy: Option<ScopePolicy>,
 }
 
 #[derive(Debug, Error)]
@@ -101,8 +410,59 @@ pub enum InvalidRedirectUriError {
 }
 
 impl Client {
+    /// Whether this client is a service account
+    #[must_use]
+    pub fn is_service_account(&self) -> bool {
+        self.service_account_scope.is_some()
+    }
+
+    /// The synthetic subject given to tokens issued to this client through the
+    /// client credentials grant, if it is a service account
+    #[must_use]
+    pub fn service_account_subject(&self) -> Option<String> {
+        self.is_service_account()
+            .then(|| format!("service-account:{}", self.client_id))
+    }
+
+    /// Remove the scopes this client isn't allowed to request from a
+    /// requested scope, if its scope policy filters them.
+    ///
+    /// Scopes rejected by the scope policy are kept, so that the policy
+    /// engine denies the request.
+    #[must_use]
+    pub fn filter_scope(&self, scope: Scope) -> Scope {
+        match &self.scope_policy {
+            Some(policy) if policy.disallowed == DisallowedScopeHandling::Filter => scope
+                .iter()
+                .filter(|token| policy.allows(token))
+                .cloned()
+                .collect(),
+            _ => scope,
+        }
+    }
+
+    /// The encrypted client secrets accepted at the given time: the current
+    /// one and, during a rotation, the next one, unless they expired
+    pub fn active_encrypted_client_secrets(
+        &self,
+        now: DateTime<Utc>,
+    ) -> impl Iterator<Item = &str> + '_ {
+        [
+            (&self.encrypted_client_secret, self.client_secret_expires_at),
+            (
+                &self.encrypted_next_client_secret,
+                self.next_client_secret_expires_at,
+            ),
+        ]
+        .into_iter()
+        .filter(move |(_, expires_at)| expires_at.is_none_or(|expires_at| expires_at > now))
+        .filter_map(|(secret, _)| secret.as_deref())
+    }
+
     /// Determine which redirect URI to use for the given request.
     ///
+    /// Loopback redirect URIs match with any port, unless `exact` is set.
+    ///
     /// # Errors
     ///
     /// Returns an error if:
@@ -113,12 +473,14 @@ impl Client {
     pub fn resolve_redirect_uri<'a>(
         &'a self,
         redirect_uri: &'a Option<Url>,
+        exact: bool,
     ) -> Result<&'a Url, InvalidRedirectUriError> {
         match (&self.redirect_uris[..], redirect_uri) {
             ([], _) => Err(InvalidRedirectUriError::NoneRegistered),
             ([one], None) => Ok(one),
             (_, None) => Err(InvalidRedirectUriError::MultipleRegistered),
-            (uris, Some(uri)) if uri_matches_one_of(uri, uris) => Ok(uri),
+            (uris, Some(uri)) if exact && uris.contains(uri) => Ok(uri),
+            (uris, Some(uri)) if !exact && uri_matches_one_of(uri, uris) => Ok(uri),
             _ => Err(InvalidRedirectUriError::NotAllowed),
         }
     }
@@ -151,25 +513,36 @@ impl Client {
             contacts: None,
             software_id: None,
             software_version: None,
+            software_statement: None,
             sector_identifier_uri: None,
-            subject_type: None,
-            id_token_encrypted_response_alg: None,
-            id_token_encrypted_response_enc: None,
+            subject_type: self
+                .pairwise_sector_identifier
+                .as_ref()
+                .map(|_| SubjectType::Pairwise),
+            id_token_encrypted_response_alg: self.id_token_encrypted_response_alg,
+            id_token_encrypted_response_enc: self.id_token_encrypted_response_enc,
             userinfo_encrypted_response_alg: None,
             userinfo_encrypted_response_enc: None,
-            request_object_signing_alg: None,
+            request_object_signing_alg: self.request_object_signing_alg,
             request_object_encryption_alg: None,
             request_object_encryption_enc: None,
             default_max_age: None,
             require_auth_time: None,
-            default_acr_values: None,
+            default_acr_values: Some(self.default_acr_values).filter(|values| !values.is_empty()),
             request_uris: None,
-            require_signed_request_object: None,
-            require_pushed_authorization_requests: None,
+            require_signed_request_object: Some(self.require_signed_request_object),
+            require_pushed_authorization_requests: Some(self.require_pushed_authorization_requests),
             introspection_signed_response_alg: None,
             introspection_encrypted_response_alg: None,
             introspection_encrypted_response_enc: None,
-            post_logout_redirect_uris: None,
+            authorization_signed_response_alg: self.authorization_signed_response_alg,
+            post_logout_redirect_uris: Some(self.post_logout_redirect_uris)
+                .filter(|uris| !uris.is_empty()),
+            backchannel_logout_uri: self.backchannel_logout_uri,
+            backchannel_logout_session_required: Some(self.backchannel_logout_session_required),
+            access_token_ttl: self.access_token_ttl,
+            refresh_token_ttl: self.refresh_token_ttl,
+            device_code_ttl: self.device_code_ttl,
         }
     }
 
@@ -182,6 +555,9 @@ impl Client {
                 client_id: "client1".to_owned(),
                 metadata_digest: None,
                 encrypted_client_secret: None,
+                client_secret_expires_at: None,
+                encrypted_next_client_secret: None,
+                next_client_secret_expires_at: None,
                 application_type: Some(ApplicationType::Web),
                 redirect_uris: vec![
                     Url::parse("https://client1.example.com/redirect").unwrap(),
@@ -200,7 +576,29 @@ impl Client {
                 token_endpoint_auth_signing_alg: None,
                 id_token_signed_response_alg: None,
                 userinfo_signed_response_alg: None,
+                authorization_signed_response_alg: None,
                 jwks: None,
+                require_pushed_authorization_requests: false,
+                service_account_scope: None,
+                backchannel_client_notification_endpoint: None,
+                allowed_resources: Vec::new(),
+                refresh_token_rotation: RefreshTokenRotation::RotateOnUse,
+                backchannel_logout_uri: None,
+                backchannel_logout_session_required: false,
+                backchannel_logout_include_sub: true,
+                post_logout_redirect_uris: Vec::new(),
+                access_token_format: AccessTokenFormat::Opaque,
+                access_token_ttl: None,
+                refresh_token_ttl: None,
+                device_code_ttl: None,
+                request_object_signing_alg: None,
+                require_signed_request_object: false,
+                id_token_encrypted_response_alg: None,
+                id_token_encrypted_response_enc: None,
+                default_acr_values: Vec::new(),
+                resource_server_audience: None,
+                pairwise_sector_identifier: None,
+                scope_policy: None,
             },
             // Another client without any URIs set
             Self {
@@ -208,6 +606,9 @@ impl Client {
                 client_id: "client2".to_owned(),
                 metadata_digest: None,
                 encrypted_client_secret: None,
+                client_secret_expires_at: None,
+                encrypted_next_client_secret: None,
+                next_client_secret_expires_at: None,
                 application_type: Some(ApplicationType::Native),
                 redirect_uris: vec![Url::parse("https://client2.example.com/redirect").unwrap()],
                 grant_types: vec![GrantType::AuthorizationCode, GrantType::RefreshToken],
@@ -221,7 +622,29 @@ impl Client {
                 token_endpoint_auth_signing_alg: None,
                 id_token_signed_response_alg: None,
                 userinfo_signed_response_alg: None,
+                authorization_signed_response_alg: None,
                 jwks: None,
+                require_pushed_authorization_requests: false,
+                service_account_scope: None,
+                backchannel_client_notification_endpoint: None,
+                allowed_resources: Vec::new(),
+                refresh_token_rotation: RefreshTokenRotation::RotateOnUse,
+                backchannel_logout_uri: None,
+                backchannel_logout_session_required: false,
+                backchannel_logout_include_sub: true,
+                post_logout_redirect_uris: Vec::new(),
+                access_token_format: AccessTokenFormat::Opaque,
+                access_token_ttl: None,
+                refresh_token_ttl: None,
+                device_code_ttl: None,
+                request_object_signing_alg: None,
+                require_signed_request_object: false,
+                id_token_encrypted_response_alg: None,
+                id_token_encrypted_response_enc: None,
+                default_acr_values: Vec::new(),
+                resource_server_audience: None,
+                pairwise_sector_identifier: None,
+                scope_policy: None,
             },
         ]
     }
@@ -283,4 +706,42 @@ mod tests {
             registered_uris
         ));
     }
+
+    #[test]
+    fn test_filter_scope() {
+        let now = chrono::DateTime::UNIX_EPOCH;
+        let mut rng = rand::rngs::mock::StepRng::new(0, 1);
+        let mut client = Client::samples(now, &mut rng).swap_remove(0);
+        let scope: Scope =
+            "openid urn:mas:graphql:* urn:matrix:org.matrix.msc2967.client:device:ABCDEFGHIJ"
+                .parse()
+                .unwrap();
+
+        // Clients without a scope policy keep everything
+        assert_eq!(client.filter_scope(scope.clone()), scope);
+
+        // Rejecting clients keep everything too, the policy engine will deny the
+        // request
+        client.scope_policy = Some(ScopePolicy {
+            allowed_scopes: vec![
+                "openid".to_owned(),
+                "urn:matrix:org.matrix.msc2967.client:device:*".to_owned(),
+            ],
+            disallowed: DisallowedScopeHandling::Reject,
+        });
+        assert_eq!(client.filter_scope(scope.clone()), scope);
+
+        // Filtering clients only keep the allowed scopes
+        client.scope_policy = Some(ScopePolicy {
+            allowed_scopes: vec![
+                "openid".to_owned(),
+                "urn:matrix:org.matrix.msc2967.client:device:*".to_owned(),
+            ],
+            disallowed: DisallowedScopeHandling::Filter,
+        });
+        assert_eq!(
+            client.filter_scope(scope).to_string(),
+            "openid urn:matrix:org.matrix.msc2967.client:device:ABCDEFGHIJ"
+        );
+    }
 }
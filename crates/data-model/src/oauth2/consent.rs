@@ -0,0 +1,37 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use chrono::{DateTime, Utc};
+use oauth2_types::scope::ScopeToken;
+use ulid::Ulid;
+
+/// A scope token a user granted to a client, remembered so that they aren't
+/// asked for it again on subsequent authorization requests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Consent {
+    pub id: Ulid,
+
+    /// The ID of the user who granted the scope
+    pub user_id: Ulid,
+
+    /// The ID of the client the scope was granted to
+    pub client_id: Ulid,
+
+    /// The scope token which was granted
+    pub scope_token: ScopeToken,
+
+    pub created_at: DateTime<Utc>,
+
+    /// When the user last granted this scope again, if they did
+    pub refreshed_at: Option<DateTime<Utc>>,
+}
+
+impl Consent {
+    /// When the user last granted this scope
+    #[must_use]
+    pub fn last_granted_at(&self) -> DateTime<Utc> {
+        self.refreshed_at.unwrap_or(self.created_at)
+    }
+}
@@ -6,7 +6,7 @@
 
 use std::net::IpAddr;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use oauth2_types::scope::Scope;
 use serde::Serialize;
 use ulid::Ulid;
@@ -193,6 +193,17 @@ pub struct DeviceCodeGrant {
 
     /// The user agent used to request this device code grant.
     pub user_agent: Option<String>,
+
+    /// The hashed device identifier provided by the client, if any.
+    pub device_fingerprint: Option<String>,
+
+    /// The minimum amount of time the client must wait between two polling
+    /// requests. This grows every time the client polls too fast.
+    #[serde(skip)]
+    pub interval: Duration,
+
+    /// The last time the client polled the token endpoint for this grant.
+    pub last_polled_at: Option<DateTime<Utc>>,
 }
 
 impl std::ops::Deref for DeviceCodeGrant {
@@ -204,6 +215,18 @@ impl std::ops::Deref for DeviceCodeGrant {
 }
 
 impl DeviceCodeGrant {
+    /// How much the polling interval grows every time the client polls too
+    /// fast, as mandated by RFC 8628
+    pub const SLOW_DOWN_INCREMENT: Duration = Duration::microseconds(5 * 1000 * 1000);
+
+    /// Returns `true` if the client polled again before the polling interval
+    /// elapsed, in which case it should be told to slow down.
+    #[must_use]
+    pub fn is_polling_too_fast(&self, now: DateTime<Utc>) -> bool {
+        self.last_polled_at
+            .is_some_and(|last_polled_at| now < last_polled_at + self.interval)
+    }
+
     /// Mark this device code grant as fulfilled, returning the updated grant.
     ///
     /// # Errors
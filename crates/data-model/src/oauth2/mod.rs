@@ -5,15 +5,30 @@
 // Please see LICENSE files in the repository root for full details.
 
 mod authorization_grant;
+mod backchannel_authentication_grant;
 mod client;
+mod consent;
 mod device_code_grant;
+mod pushed_authorization_request;
 mod session;
 
 pub use self::{
     authorization_grant::{
         AuthorizationCode, AuthorizationGrant, AuthorizationGrantStage, LoginHint, Pkce,
     },
-    client::{Client, InvalidRedirectUriError, JwksOrJwksUri},
+    backchannel_authentication_grant::{
+        BackchannelAuthenticationGrant, BackchannelAuthenticationGrantState,
+    },
+    client::{
+        AccessTokenFormat, Client, DisallowedScopeHandling, InvalidAccessTokenFormatError,
+        InvalidDisallowedScopeHandlingError, InvalidRedirectUriError,
+        InvalidRefreshTokenRotationError, InvalidTokenAudienceError, JwksOrJwksUri,
+        RefreshTokenRotation, ScopePolicy, TokenAudience,
+    },
+    consent::Consent,
     device_code_grant::{DeviceCodeGrant, DeviceCodeGrantState},
-    session::{Session, SessionState},
+    pushed_authorization_request::{
+        PUSHED_AUTHORIZATION_REQUEST_URI_PREFIX, PushedAuthorizationRequest,
+    },
+    session::{Session, SessionState, is_valid_device_fingerprint},
 };
@@ -0,0 +1,72 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use ulid::Ulid;
+
+use crate::InvalidTransitionError;
+
+/// The prefix of the `request_uri` given out for pushed authorization requests
+pub const PUSHED_AUTHORIZATION_REQUEST_URI_PREFIX: &str = "urn:ietf:params:oauth:request_uri:";
+
+/// Authorization request parameters pushed by a client ahead of the
+/// authorization request, as defined in [RFC9126]
+///
+/// [RFC9126]: https://www.rfc-editor.org/rfc/rfc9126
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PushedAuthorizationRequest {
+    pub id: Ulid,
+
+    /// The ID of the client which pushed the request
+    pub client_id: Ulid,
+
+    /// The random part of the `request_uri`
+    pub reference: String,
+
+    /// The authorization request parameters, without the client credentials
+    pub parameters: BTreeMap<String, String>,
+
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+
+    /// When the request was used by an authorization request. Requests can
+    /// only be used once.
+    pub consumed_at: Option<DateTime<Utc>>,
+}
+
+impl PushedAuthorizationRequest {
+    /// The `request_uri` the client passes to the authorization endpoint
+    #[must_use]
+    pub fn request_uri(&self) -> String {
+        format!(
+            "{PUSHED_AUTHORIZATION_REQUEST_URI_PREFIX}{}",
+            self.reference
+        )
+    }
+
+    /// Whether the request can still be used, i.e. it is not expired and was
+    /// not used yet
+    #[must_use]
+    pub fn is_valid(&self, now: DateTime<Utc>) -> bool {
+        self.consumed_at.is_none() && now < self.expires_at
+    }
+
+    /// Mark the request as used
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request was already used
+    pub fn consume(mut self, consumed_at: DateTime<Utc>) -> Result<Self, InvalidTransitionError> {
+        if self.consumed_at.is_some() {
+            return Err(InvalidTransitionError);
+        }
+
+        self.consumed_at = Some(consumed_at);
+        Ok(self)
+    }
+}
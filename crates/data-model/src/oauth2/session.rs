@@ -7,11 +7,12 @@
 use std::net::IpAddr;
 
 use chrono::{DateTime, Utc};
-use oauth2_types::scope::Scope;
+use oauth2_types::{authorization_details::AuthorizationDetail, scope::Scope};
 use serde::Serialize;
 use ulid::Ulid;
+use url::Url;
 
-use crate::InvalidTransitionError;
+use crate::{InvalidTransitionError, IpLocation};
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub enum SessionState {
@@ -83,7 +84,13 @@ pub struct Session {
     pub user_agent: Option<String>,
     pub last_active_at: Option<DateTime<Utc>>,
     pub last_active_ip: Option<IpAddr>,
+    pub last_active_location: IpLocation,
     pub human_name: Option<String>,
+    pub device_fingerprint: Option<String>,
+    pub authorization_details: Vec<AuthorizationDetail>,
+    pub resource: Option<Url>,
+    pub userinfo_claims: Vec<String>,
+    pub offline_access_granted_at: Option<DateTime<Utc>>,
 }
 
 impl std::ops::Deref for Session {
@@ -94,6 +101,19 @@ impl std::ops::Deref for Session {
     }
 }
 
+/// Check whether a client-provided device fingerprint is acceptable
+///
+/// Clients are expected to send an opaque, hashed identifier, so we only
+/// accept between 16 and 128 characters from the hex, base64 and base64url
+/// alphabets. This avoids storing raw hardware identifiers or free-form text.
+#[must_use]
+pub fn is_valid_device_fingerprint(fingerprint: &str) -> bool {
+    (16..=128).contains(&fingerprint.len())
+        && fingerprint
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'=' | b'-' | b'_'))
+}
+
 impl Session {
     /// Marks the session as finished.
     ///
@@ -108,4 +128,32 @@ impl Session {
         self.state = self.state.finish(finished_at)?;
         Ok(self)
     }
+
+    /// Whether the user explicitly granted offline access to the client
+    #[must_use]
+    pub fn has_offline_access(&self) -> bool {
+        self.offline_access_granted_at.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_fingerprint_validation() {
+        assert!(is_valid_device_fingerprint(
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+        ));
+        assert!(is_valid_device_fingerprint(
+            "n4bQgYhMfWWaL-qgxVrQFaO_TxsrC4Is0V1sFbDwCgg"
+        ));
+
+        // Too short
+        assert!(!is_valid_device_fingerprint("abcdef"));
+        // Too long
+        assert!(!is_valid_device_fingerprint(&"a".repeat(129)));
+        // Not an encoded hash
+        assert!(!is_valid_device_fingerprint("Pixel 7 serial 1234567890"));
+    }
 }
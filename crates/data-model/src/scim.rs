@@ -0,0 +1,108 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+/// A link between a local user and a user resource in an upstream SCIM
+/// directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScimUserLink {
+    pub id: Ulid,
+    pub user_id: Ulid,
+    pub external_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What happened to a user during a SCIM directory sync
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScimSyncAction {
+    /// A new user was created from the directory
+    Created,
+
+    /// An existing user was linked to a directory entry with the same username
+    Linked,
+
+    /// The user was locked, because it is inactive or missing in the directory
+    Locked,
+
+    /// The user was unlocked, because it is active again in the directory
+    Unlocked,
+
+    /// The user was granted the ability to request admin privileges
+    AdminGranted,
+
+    /// The user lost the ability to request admin privileges
+    AdminRevoked,
+}
+
+/// A single change applied to a user during a SCIM directory sync
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScimSyncChange {
+    pub action: ScimSyncAction,
+    pub user_id: Ulid,
+    pub username: String,
+    pub external_id: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "state")]
+pub enum ScimSyncRunState {
+    #[default]
+    Running,
+    Completed {
+        finished_at: DateTime<Utc>,
+    },
+    Failed {
+        finished_at: DateTime<Utc>,
+        error: String,
+    },
+}
+
+impl ScimSyncRunState {
+    /// Returns `true` if the sync run is still in progress
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        matches!(self, Self::Running)
+    }
+
+    /// Returns `true` if the sync run failed
+    #[must_use]
+    pub fn is_failed(&self) -> bool {
+        matches!(self, Self::Failed { .. })
+    }
+
+    /// Get the time at which the sync run finished, if it did
+    #[must_use]
+    pub fn finished_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Running => None,
+            Self::Completed { finished_at } | Self::Failed { finished_at, .. } => {
+                Some(*finished_at)
+            }
+        }
+    }
+
+    /// Get the error which made the sync run fail, if any
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            Self::Failed { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A record of a single synchronisation run against the upstream SCIM
+/// directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScimSyncRun {
+    pub id: Ulid,
+    pub state: ScimSyncRunState,
+    pub started_at: DateTime<Utc>,
+    pub changes: Vec<ScimSyncChange>,
+}
@@ -4,15 +4,24 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::collections::BTreeMap;
+
 use chrono::Duration;
+use serde::Serialize;
+use ulid::Ulid;
 use url::Url;
 
+use crate::{JwksOrJwksUri, TermsDocumentKind, User};
+
 /// Which Captcha service is being used
 #[derive(Debug, Clone, Copy)]
 pub enum CaptchaService {
     RecaptchaV2,
     CloudflareTurnstile,
     HCaptcha,
+
+    /// Proof-of-work challenges issued and verified by MAS itself
+    ProofOfWork,
 }
 
 /// Captcha configuration
@@ -21,10 +30,11 @@ pub struct CaptchaConfig {
     /// Which Captcha service is being used
     pub service: CaptchaService,
 
-    /// The site key used by the instance
+    /// The site key used by the instance. Empty for the proof-of-work service
     pub site_key: String,
 
-    /// The secret key used by the instance
+    /// The secret key used by the instance. The proof-of-work service signs
+    /// its challenges with it
     pub secret_key: String,
 }
 
@@ -34,6 +44,203 @@ pub struct SessionExpirationConfig {
     pub user_session_inactivity_ttl: Option<Duration>,
     pub oauth_session_inactivity_ttl: Option<Duration>,
     pub compat_session_inactivity_ttl: Option<Duration>,
+
+    /// Time after which user sessions are finished, however active they are
+    pub user_session_max_lifetime: Option<Duration>,
+}
+
+/// Temporary lockout of accounts after repeated failed password attempts
+#[derive(Debug, Clone, Copy)]
+pub struct PasswordLockoutConfig {
+    /// Number of consecutive failed attempts on an account after which it is
+    /// locked
+    pub max_failures: u32,
+
+    /// Number of failed attempts from an IP address after which logins from
+    /// it need a CAPTCHA, or are refused if none is configured
+    pub max_failures_per_ip: u32,
+
+    /// How long failed attempts are remembered
+    pub duration: Duration,
+}
+
+/// Invite codes which trusted users can issue themselves
+#[derive(Debug, Clone)]
+pub struct UserInvitesConfig {
+    /// Name of the boolean custom user attribute marking the users who can
+    /// issue invites. If not set, only users who can request admin access can.
+    pub trusted_attribute: Option<String>,
+
+    /// How many times each invite can be used
+    pub usage_limit: u32,
+
+    /// How long invites can be used for
+    pub ttl: Duration,
+
+    /// Maximum number of valid invites a user can have at the same time
+    pub max_active: u32,
+
+    /// Whether users registering with an invite get its issuer recorded as
+    /// their referrer
+    pub assign_referrer: bool,
+}
+
+/// Sends users to an upstream provider based on the domain of the email
+/// address they enter on the login page
+#[derive(Debug, Clone, Default)]
+pub struct HomeRealmDiscoveryConfig {
+    /// Map of lowercase email domains to upstream provider IDs
+    pub domains: BTreeMap<String, Ulid>,
+
+    /// The upstream provider to use when nothing matches. If not set, users
+    /// are asked for their password.
+    pub fallback: Option<Ulid>,
+}
+
+impl HomeRealmDiscoveryConfig {
+    /// Returns the ID of the upstream provider to send a user to, given the
+    /// username or email address they entered
+    #[must_use]
+    pub fn provider_for(&self, identifier: &str) -> Option<Ulid> {
+        identifier
+            .rsplit_once('@')
+            .and_then(|(_, domain)| self.domains.get(&domain.to_lowercase()))
+            .copied()
+            .or(self.fallback)
+    }
+}
+
+/// The current version of a legal document users have to accept
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TermsDocument {
+    pub kind: TermsDocumentKind,
+
+    /// An opaque version identifier. Users are asked to accept the document
+    /// again when it changes.
+    pub version: String,
+
+    pub url: Url,
+}
+
+/// Configuration of the SCIM client, which periodically pulls users from an
+/// upstream directory
+#[derive(Debug, Clone)]
+pub struct ScimClientConfig {
+    /// Base URL of the SCIM 2.0 service provider
+    pub endpoint: Url,
+
+    /// Bearer token used to authenticate against the service provider
+    pub token: String,
+
+    /// Only users which are members of this group are provisioned
+    pub group: Option<String>,
+
+    /// Members of this group are allowed to request admin privileges
+    pub admin_group: Option<String>,
+
+    /// Whether linked users missing from the directory should be locked
+    pub lock_missing_users: bool,
+}
+
+/// An issuer trusted to sign the software statements presented by clients
+/// when they register
+#[derive(Debug, Clone)]
+pub struct SoftwareStatementIssuer {
+    /// The identifier of the issuer, matched against the `iss` claim of the
+    /// software statements
+    pub issuer: String,
+
+    /// The keys used to verify the software statements signed by this issuer
+    pub jwks: JwksOrJwksUri,
+}
+
+/// An Authentication Context Class Reference value, and the authentication
+/// methods a session must have used to satisfy it
+#[derive(Debug, Clone)]
+pub struct AcrValue {
+    /// The value, as requested by clients and exposed in the `acr` claim
+    pub value: String,
+
+    /// The Authentication Method Reference values a session must have to
+    /// satisfy this value
+    pub amr: Vec<String>,
+}
+
+/// The type of the values of a custom user attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserAttributeKind {
+    String,
+    Integer,
+    Boolean,
+}
+
+impl UserAttributeKind {
+    /// Returns `true` if the given JSON value is of this type
+    #[must_use]
+    pub fn accepts(self, value: &serde_json::Value) -> bool {
+        match self {
+            Self::String => value.is_string(),
+            Self::Integer => value.is_i64() || value.is_u64(),
+            Self::Boolean => value.is_boolean(),
+        }
+    }
+}
+
+/// A custom attribute which can be set on users
+#[derive(Debug, Clone)]
+pub struct UserAttributeDefinition {
+    /// The name of the attribute
+    pub name: String,
+
+    /// The type of the values of the attribute
+    pub kind: UserAttributeKind,
+
+    /// The name of the claim under which the attribute is exposed to clients,
+    /// if it is exposed at all
+    pub claim: Option<String>,
+}
+
+impl UserAttributeDefinition {
+    /// The namespace under which the attribute values are stored in the user
+    /// metadata
+    pub const METADATA_NAMESPACE: &'static str = "user_attributes";
+}
+
+/// Whether users can, or must, enroll a TOTP second factor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TotpPolicy {
+    /// Users can't enroll a TOTP second factor
+    #[default]
+    Disabled,
+
+    /// Users can enroll a TOTP second factor
+    Optional,
+
+    /// All users must enroll a TOTP second factor
+    Required,
+
+    /// Users who can request admin access must enroll a TOTP second factor,
+    /// other users can enroll one
+    RequiredForAdmins,
+}
+
+impl TotpPolicy {
+    /// Returns `true` if users can enroll a TOTP second factor
+    #[must_use]
+    pub const fn is_enabled(self) -> bool {
+        !matches!(self, Self::Disabled)
+    }
+
+    /// Returns `true` if the given user must enroll a TOTP second factor to log
+    /// in with a password
+    #[must_use]
+    pub const fn is_required_for(self, user: &User) -> bool {
+        match self {
+            Self::Disabled | Self::Optional => false,
+            Self::Required => true,
+            Self::RequiredForAdmins => user.can_request_admin,
+        }
+    }
 }
 
 /// Random site configuration we want accessible in various places.
@@ -73,15 +280,26 @@ pub struct SiteConfig {
     /// Whether users can change their display name.
     pub displayname_change_allowed: bool,
 
+    /// Whether users can change their avatar.
+    pub avatar_change_allowed: bool,
+
     /// Whether users can change their password.
     pub password_change_allowed: bool,
 
     /// Whether users can recover their account via email.
     pub account_recovery_allowed: bool,
 
+    /// Whether account recovery requests have to be approved by an
+    /// administrator before the recovery links are sent.
+    pub account_recovery_requires_approval: bool,
+
     /// Whether users can delete their own account.
     pub account_deactivation_allowed: bool,
 
+    /// How long accounts users asked to delete stay deactivated before being
+    /// erased, if they are not erased straight away.
+    pub account_deletion_grace_period: Option<Duration>,
+
     /// Captcha configuration
     pub captcha: Option<CaptchaConfig>,
 
@@ -89,11 +307,106 @@ pub struct SiteConfig {
     /// This is a score from zxcvbn.
     pub minimum_password_complexity: u8,
 
+    /// Temporary lockout of accounts after repeated failed password attempts,
+    /// if enabled
+    pub password_lockout: Option<PasswordLockoutConfig>,
+
+    /// Maximum age of passwords, after which users have to change it on their
+    /// next login. Can be overridden per user.
+    pub password_max_age: Option<Duration>,
+
+    /// Number of previous passwords users can't reuse. Can be overridden per
+    /// user.
+    pub password_history_size: u32,
+
+    /// Automatic expiration of inactive or long-lived sessions. `None` if
+    /// sessions never expire on their own.
     pub session_expiration: Option<SessionExpirationConfig>,
 
     /// Whether users can log in with their email address.
     pub login_with_email_allowed: bool,
 
+    /// Whether users can log in with their confirmed phone number.
+    pub login_with_phone_number_allowed: bool,
+
+    /// Whether users can register passkeys and log in with them.
+    pub passkeys_enabled: bool,
+
+    /// Whether users can log in with a code sent to their email address.
+    pub email_code_login_enabled: bool,
+
+    /// Whether users can, or must, enroll a TOTP second factor.
+    pub totp_policy: TotpPolicy,
+
+    /// Whether users can add and change their phone number.
+    pub phone_number_change_allowed: bool,
+
+    /// Whether users with a confirmed phone number must enter a code sent to
+    /// it by SMS after their password.
+    pub sms_second_factor_enabled: bool,
+
+    /// How long browsers stay trusted when users choose to remember them after
+    /// entering their second factor, if they can.
+    pub trusted_device_ttl: Option<Duration>,
+
+    /// How recently users must have authenticated in their browser session to
+    /// change sensitive account settings, if at all.
+    pub sudo_mode_ttl: Option<Duration>,
+
+    /// Invite codes which trusted users can issue themselves, if enabled
+    pub user_invites: Option<UserInvitesConfig>,
+
+    /// Routing of users to upstream providers based on the domain of their
+    /// email address, if enabled
+    pub home_realm_discovery: Option<HomeRealmDiscoveryConfig>,
+
+    /// Whether the login page redirects to the upstream provider right away
+    /// when it is the only way to log in
+    pub single_provider_auto_redirect: bool,
+
+    /// The current versions of the legal documents users have to accept,
+    /// at most one of each kind
+    pub terms_documents: Vec<TermsDocument>,
+
+    /// Whether users get an email when logging in from a new IP address or
+    /// device
+    pub sign_in_notifications_enabled: bool,
+
     /// The iframe URL to show in the plan tab of the UI
     pub plan_management_iframe_uri: Option<String>,
+
+    /// Configuration of the SCIM client, if enabled
+    pub scim_client: Option<ScimClientConfig>,
+
+    /// The custom attributes which can be set on users
+    pub user_attributes: Vec<UserAttributeDefinition>,
+
+    /// The authorization details types clients can request. Rich authorization
+    /// requests are rejected if this is empty.
+    pub authorization_details_types: Vec<String>,
+
+    /// The issuers trusted to sign software statements
+    pub software_statement_issuers: Vec<SoftwareStatementIssuer>,
+
+    /// Whether clients must present a software statement to register
+    pub software_statement_required: bool,
+
+    /// The ACR values sessions can satisfy, from the weakest to the strongest
+    pub acr_values: Vec<AcrValue>,
+
+    /// The scopes which require the user to step up their authentication. A
+    /// trailing `*` matches any scope starting with the rest of the pattern.
+    pub step_up_scopes: Vec<String>,
+
+    /// How long a browser session stays elevated after a step-up
+    pub step_up_ttl: Duration,
+
+    /// The number of characters in device authorization grant user codes
+    pub device_code_user_code_length: usize,
+
+    /// The characters device authorization grant user codes are made of
+    pub device_code_user_code_alphabet: String,
+
+    /// Whether the OAuth 2.1 behaviour is enforced for all clients
+    pub oauth2_1_strict: bool,
 }
@@ -57,6 +57,10 @@ pub struct AccessToken {
     pub created_at: DateTime<Utc>,
     pub expires_at: Option<DateTime<Utc>>,
     pub first_used_at: Option<DateTime<Utc>>,
+
+    /// The JWK SHA-256 thumbprint of the key this token is bound to, if it was
+    /// issued with a DPoP proof
+    pub dpop_jkt: Option<String>,
 }
 
 impl AccessToken {
@@ -302,6 +306,12 @@ impl TokenType {
             return Ok(TokenType::CompatRefreshToken);
         }
 
+        // JWT-formatted access tokens (RFC 9068) don't have a prefix, they are
+        // looked up as-is
+        if is_likely_jwt(token) {
+            return Ok(TokenType::AccessToken);
+        }
+
         let split: Vec<&str> = token.split('_').collect();
         let [prefix, random_part, crc]: [&str; 3] = split
             .try_into()
@@ -359,6 +369,24 @@ fn is_likely_synapse_macaroon(token: &str) -> bool {
     decoded.get(4..13) == Some(b"location ")
 }
 
+/// Returns true if and only if a token looks like a JWT.
+///
+/// This doesn't verify the token in any way, it only checks that it has three
+/// parts and that the first one decodes to a JSON object.
+fn is_likely_jwt(token: &str) -> bool {
+    let mut parts = token.split('.');
+    let (Some(header), Some(_), Some(_), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+
+    let Ok(decoded) = Base64UrlUnpadded::decode_vec(header) else {
+        return false;
+    };
+    decoded.first() == Some(&b'{')
+}
+
 const NUM: [u8; 62] = *b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
 
 fn base62_encode(mut num: u32) -> String {
@@ -456,6 +484,21 @@ mod tests {
         assert!(!is_likely_synapse_macaroon("aaa"));
     }
 
+    #[test]
+    fn test_is_likely_jwt() {
+        // The header is `{"alg":"none"}`
+        assert!(is_likely_jwt("eyJhbGciOiJub25lIn0.e30."));
+        assert_eq!(
+            TokenType::check("eyJhbGciOiJub25lIn0.e30.").unwrap(),
+            TokenType::AccessToken
+        );
+
+        assert!(!is_likely_jwt("eyJhbGciOiJub25lIn0.e30"));
+        assert!(!is_likely_jwt("eyJhbGciOiJub25lIn0.e30.."));
+        assert!(!is_likely_jwt("...."));
+        assert!(!is_likely_jwt("mat_abc.def.ghi"));
+    }
+
     #[test]
     fn test_generate_and_check() {
         const COUNT: usize = 500; // Generate 500 of each token type
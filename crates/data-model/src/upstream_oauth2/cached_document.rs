@@ -0,0 +1,44 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// The kind of document fetched from an upstream provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CachedDocumentKind {
+    /// The discovery document, keyed by the issuer of the provider
+    Discovery,
+
+    /// The JWKS, keyed by its URI
+    Jwks,
+}
+
+impl CachedDocumentKind {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Discovery => "discovery",
+            Self::Jwks => "jwks",
+        }
+    }
+}
+
+impl std::fmt::Display for CachedDocumentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A document fetched from an upstream provider, kept around so that it can
+/// still be used when the provider is temporarily unreachable
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UpstreamOAuthCachedDocument {
+    pub kind: CachedDocumentKind,
+    pub url: String,
+    pub document: serde_json::Value,
+    pub fetched_at: DateTime<Utc>,
+}
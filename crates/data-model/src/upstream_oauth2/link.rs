@@ -5,7 +5,7 @@
 // Please see LICENSE files in the repository root for full details.
 
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use ulid::Ulid;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -15,5 +15,49 @@ pub struct UpstreamOAuthLink {
     pub user_id: Option<Ulid>,
     pub subject: String,
     pub human_account_name: Option<String>,
+    pub imported_attributes: ImportedAttributes,
     pub created_at: DateTime<Utc>,
 }
+
+/// The attributes which were last imported from the upstream provider through
+/// a link, used to detect whether the user changed them locally since
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct ImportedAttributes {
+    /// The display name last imported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub displayname: Option<String>,
+
+    /// The email address last imported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+
+    /// The URL of the avatar on the upstream provider last imported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_source: Option<String>,
+
+    /// The `mxc://` URI the imported avatar was uploaded to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+}
+
+/// The latest tokens issued by the upstream provider for a link
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UpstreamOAuthLinkTokens {
+    pub link_id: Ulid,
+    pub encrypted_access_token: String,
+    pub encrypted_refresh_token: Option<String>,
+    pub access_token_expires_at: Option<DateTime<Utc>>,
+    pub refresh_failed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl UpstreamOAuthLinkTokens {
+    /// Returns `true` if the access token expired, or is about to expire
+    /// within the given leeway
+    #[must_use]
+    pub fn access_token_expired(&self, now: DateTime<Utc>, leeway: chrono::Duration) -> bool {
+        self.access_token_expires_at
+            .is_some_and(|expires_at| expires_at <= now + leeway)
+    }
+}
@@ -4,20 +4,33 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+mod cached_document;
 mod link;
 mod provider;
 mod session;
 
 pub use self::{
-    link::UpstreamOAuthLink,
+    cached_document::{
+        CachedDocumentKind as UpstreamOAuthCachedDocumentKind, UpstreamOAuthCachedDocument,
+    },
+    link::{
+        ImportedAttributes as UpstreamOAuthLinkImportedAttributes, UpstreamOAuthLink,
+        UpstreamOAuthLinkTokens,
+    },
     provider::{
         ClaimsImports as UpstreamOAuthProviderClaimsImports,
+        Deprovisioning as UpstreamOAuthProviderDeprovisioning,
+        DeprovisioningAction as UpstreamOAuthProviderDeprovisioningAction,
+        DeprovisioningSource as UpstreamOAuthProviderDeprovisioningSource,
         DiscoveryMode as UpstreamOAuthProviderDiscoveryMode,
+        EmailDomains as UpstreamOAuthProviderEmailDomains,
         ImportAction as UpstreamOAuthProviderImportAction,
         ImportPreference as UpstreamOAuthProviderImportPreference,
-        PkceMode as UpstreamOAuthProviderPkceMode,
+        OnConflict as UpstreamOAuthProviderOnConflict, PkceMode as UpstreamOAuthProviderPkceMode,
         ResponseMode as UpstreamOAuthProviderResponseMode,
+        RolePreference as UpstreamOAuthProviderRolePreference,
         SubjectPreference as UpstreamOAuthProviderSubjectPreference,
+        SyncMode as UpstreamOAuthProviderSyncMode,
         TokenAuthMethod as UpstreamOAuthProviderTokenAuthMethod, UpstreamOAuthProvider,
     },
     session::{UpstreamOAuthAuthorizationSession, UpstreamOAuthAuthorizationSessionState},
@@ -173,6 +173,7 @@ pub enum TokenAuthMethod {
     ClientSecretPost,
     ClientSecretJwt,
     PrivateKeyJwt,
+    TlsClientAuth,
     SignInWithApple,
 }
 
@@ -185,6 +186,7 @@ impl TokenAuthMethod {
             Self::ClientSecretPost => "client_secret_post",
             Self::ClientSecretJwt => "client_secret_jwt",
             Self::PrivateKeyJwt => "private_key_jwt",
+            Self::TlsClientAuth => "tls_client_auth",
             Self::SignInWithApple => "sign_in_with_apple",
         }
     }
@@ -206,6 +208,7 @@ impl std::str::FromStr for TokenAuthMethod {
             "client_secret_basic" => Ok(Self::ClientSecretBasic),
             "client_secret_jwt" => Ok(Self::ClientSecretJwt),
             "private_key_jwt" => Ok(Self::PrivateKeyJwt),
+            "tls_client_auth" => Ok(Self::TlsClientAuth),
             "sign_in_with_apple" => Ok(Self::SignInWithApple),
             s => Err(InvalidUpstreamOAuth2TokenAuthMethod(s.to_owned())),
         }
@@ -235,6 +238,9 @@ pub struct UpstreamOAuthProvider {
     pub encrypted_client_secret: Option<String>,
     pub token_endpoint_signing_alg: Option<JsonWebSignatureAlg>,
     pub token_endpoint_auth_method: TokenAuthMethod,
+    pub token_endpoint_signing_key_id: Option<String>,
+    pub tls_client_certificate: Option<String>,
+    pub encrypted_tls_client_key: Option<String>,
     pub id_token_signed_response_alg: JsonWebSignatureAlg,
     pub response_mode: Option<ResponseMode>,
     pub created_at: DateTime<Utc>,
@@ -242,6 +248,11 @@ pub struct UpstreamOAuthProvider {
     pub claims_imports: ClaimsImports,
     pub additional_authorization_parameters: Vec<(String, String)>,
     pub forward_login_hint: bool,
+    pub forward_prompt: bool,
+    pub forward_acr_values: bool,
+    pub forward_ui_locales: bool,
+    pub store_tokens: bool,
+    pub deprovisioning: Option<Deprovisioning>,
 }
 
 impl PartialOrd for UpstreamOAuthProvider {
@@ -278,8 +289,22 @@ pub struct ClaimsImports {
     #[serde(default)]
     pub email: ImportPreference,
 
+    #[serde(default)]
+    pub avatar: ImportPreference,
+
     #[serde(default)]
     pub account_name: SubjectPreference,
+
+    #[serde(default)]
+    pub can_request_admin: RolePreference,
+
+    #[serde(default)]
+    pub email_domains: EmailDomains,
+
+    /// The maximum size of the imported avatar image, in bytes. If not set, a
+    /// default limit is used.
+    #[serde(default)]
+    pub avatar_max_size: Option<u32>,
 }
 
 // XXX: this should have another name
@@ -289,6 +314,79 @@ pub struct SubjectPreference {
     pub template: Option<String>,
 }
 
+/// Maps a claim from the upstream provider, like a list of groups, to a
+/// boolean property of the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct RolePreference {
+    /// The Jinja2 expression which extracts the values from the claims. If
+    /// not set, the property is not managed by the upstream provider.
+    #[serde(default)]
+    pub expression: Option<String>,
+
+    /// The values which grant the property. If empty, the property is
+    /// granted if the expression evaluates to a truthy value.
+    #[serde(default)]
+    pub allowed_values: Vec<String>,
+}
+
+impl RolePreference {
+    /// Returns `true` if the property is managed by the upstream provider
+    #[must_use]
+    pub const fn is_managed(&self) -> bool {
+        self.expression.is_some()
+    }
+}
+
+/// Restricts the domains of the email addresses an upstream provider can
+/// assert
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct EmailDomains {
+    /// The domains which are allowed. If empty, all domains which aren't
+    /// forbidden are allowed.
+    #[serde(default)]
+    pub allowed: Vec<String>,
+
+    /// The domains which are forbidden, even if they are in the allowed list
+    #[serde(default)]
+    pub forbidden: Vec<String>,
+}
+
+impl EmailDomains {
+    /// Returns `true` if any restriction is set
+    #[must_use]
+    pub fn is_restricted(&self) -> bool {
+        !self.allowed.is_empty() || !self.forbidden.is_empty()
+    }
+
+    /// Returns `true` if the provider may assert the given email address.
+    ///
+    /// When an allow list is set, logins without an email address are
+    /// rejected, as nothing proves the account belongs to an allowed domain.
+    #[must_use]
+    pub fn allows(&self, email: Option<&str>) -> bool {
+        let Some(domain) = email
+            .and_then(|email| email.rsplit_once('@'))
+            .map(|(_, d)| d)
+        else {
+            return self.allowed.is_empty();
+        };
+
+        if self
+            .forbidden
+            .iter()
+            .any(|forbidden| forbidden.eq_ignore_ascii_case(domain))
+        {
+            return false;
+        }
+
+        self.allowed.is_empty()
+            || self
+                .allowed
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(domain))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct ImportPreference {
     #[serde(default)]
@@ -296,6 +394,12 @@ pub struct ImportPreference {
 
     #[serde(default)]
     pub template: Option<String>,
+
+    #[serde(default)]
+    pub sync: SyncMode,
+
+    #[serde(default)]
+    pub on_conflict: OnConflict,
 }
 
 impl std::ops::Deref for ImportPreference {
@@ -306,6 +410,47 @@ impl std::ops::Deref for ImportPreference {
     }
 }
 
+/// When an imported attribute is refreshed from the upstream provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    /// Only import the attribute when the user registers
+    #[default]
+    FirstLogin,
+
+    /// Import the attribute again each time the user logs in
+    Always,
+}
+
+impl SyncMode {
+    /// Returns `true` if the attribute should be refreshed on each login
+    #[must_use]
+    pub const fn is_always(self) -> bool {
+        matches!(self, Self::Always)
+    }
+}
+
+/// What to do when refreshing an attribute which the user changed locally
+/// since it was last imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    /// Keep the value the user set
+    #[default]
+    KeepLocal,
+
+    /// Replace the value the user set with the one from the provider
+    Overwrite,
+}
+
+impl OnConflict {
+    /// Returns `true` if local changes should be overwritten
+    #[must_use]
+    pub const fn overwrite(self) -> bool {
+        matches!(self, Self::Overwrite)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum ImportAction {
@@ -348,3 +493,45 @@ impl ImportAction {
         }
     }
 }
+
+/// Settings of the automatic deprovisioning of the accounts whose upstream
+/// identity was removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deprovisioning {
+    /// How to find out that an upstream identity was removed
+    pub source: DeprovisioningSource,
+
+    /// What to do with the accounts whose upstream identity was removed
+    #[serde(default)]
+    pub action: DeprovisioningAction,
+
+    /// Only report which accounts would be deprovisioned, without touching
+    /// them
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// How to find out that an upstream identity was removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeprovisioningSource {
+    /// The identity is missing or inactive in the SCIM directory, matching
+    /// the subject of the link against the `id` and `externalId` of the users
+    Scim,
+
+    /// The provider refused the refresh token stored for the link the last
+    /// time it was used
+    RefreshToken,
+}
+
+/// What to do with the accounts whose upstream identity was removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DeprovisioningAction {
+    /// Lock the account, which can be unlocked later on
+    #[default]
+    Lock,
+
+    /// Deactivate the account
+    Deactivate,
+}
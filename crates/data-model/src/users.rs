@@ -6,12 +6,15 @@
 
 use std::net::IpAddr;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
+use mas_jose::jwk::PublicJsonWebKey;
 use rand::Rng;
 use serde::Serialize;
 use ulid::Ulid;
 use url::Url;
 
+use crate::{InvalidTransitionError, IpLocation, SessionExpirationConfig};
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct User {
     pub id: Ulid,
@@ -54,6 +57,93 @@ pub struct Password {
     pub version: u16,
     pub upgraded_from_id: Option<Ulid>,
     pub created_at: DateTime<Utc>,
+
+    /// Set when an administrator requires the user to change this password
+    /// the next time they log in
+    pub change_required_at: Option<DateTime<Utc>>,
+}
+
+impl Password {
+    /// Whether the user has to change this password the next time they log in
+    #[must_use]
+    pub const fn change_required(&self) -> bool {
+        self.change_required_at.is_some()
+    }
+}
+
+/// Per-user overrides of the password expiry and history policies, set by
+/// administrators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UserPasswordPolicy {
+    /// Maximum age of the password of the user, overriding the global one. A
+    /// zero duration means the password never expires.
+    pub max_age: Option<Duration>,
+
+    /// Number of previous passwords the user can't reuse, overriding the
+    /// global one
+    pub history_size: Option<u32>,
+}
+
+impl UserPasswordPolicy {
+    /// Whether this doesn't override anything from the global policy
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.max_age.is_none() && self.history_size.is_none()
+    }
+}
+
+/// The kind of legal document users have to accept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TermsDocumentKind {
+    TermsOfService,
+    PrivacyPolicy,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Invalid terms document kind {0:?}")]
+pub struct InvalidTermsDocumentKindError(String);
+
+impl std::str::FromStr for TermsDocumentKind {
+    type Err = InvalidTermsDocumentKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "terms_of_service" => Ok(Self::TermsOfService),
+            "privacy_policy" => Ok(Self::PrivacyPolicy),
+            s => Err(InvalidTermsDocumentKindError(s.to_owned())),
+        }
+    }
+}
+
+impl TermsDocumentKind {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::TermsOfService => "terms_of_service",
+            Self::PrivacyPolicy => "privacy_policy",
+        }
+    }
+}
+
+impl std::fmt::Display for TermsDocumentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A version of a legal document accepted by a user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserTermsAcceptance {
+    pub id: Ulid,
+    pub user_id: Ulid,
+    pub kind: TermsDocumentKind,
+    pub version: String,
+
+    /// The URL the document was published at when the user accepted it
+    pub url: Url,
+
+    pub accepted_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -65,11 +155,334 @@ pub struct Authentication {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub enum AuthenticationMethod {
-    Password { user_password_id: Ulid },
-    UpstreamOAuth2 { upstream_oauth2_session_id: Ulid },
+    Password {
+        user_password_id: Ulid,
+    },
+    PasswordAndTotp {
+        user_password_id: Ulid,
+        user_totp_id: Ulid,
+    },
+    PasswordAndRecoveryCode {
+        user_password_id: Ulid,
+        user_recovery_code_id: Ulid,
+    },
+    PasswordAndSms {
+        user_password_id: Ulid,
+        user_phone_id: Ulid,
+    },
+    UpstreamOAuth2 {
+        upstream_oauth2_session_id: Ulid,
+    },
+    Passkey {
+        user_passkey_id: Ulid,
+    },
+    EmailCode {
+        user_email_authentication_id: Ulid,
+    },
+    EmailCodeAndTotp {
+        user_email_authentication_id: Ulid,
+        user_totp_id: Ulid,
+    },
+    EmailCodeAndRecoveryCode {
+        user_email_authentication_id: Ulid,
+        user_recovery_code_id: Ulid,
+    },
+    EmailCodeAndSms {
+        user_email_authentication_id: Ulid,
+        user_phone_id: Ulid,
+    },
     Unknown,
 }
 
+impl AuthenticationMethod {
+    /// The Authentication Method Reference values of this method, as exposed
+    /// in the `amr` claim of ID tokens.
+    ///
+    /// Password authentications use `pwd` from RFC 8176, along with `otp` if
+    /// the user also entered a TOTP code or a recovery code, which are both
+    /// one-time passwords. Authentications through an upstream
+    /// provider use `fed`, which isn't registered but is commonly used for
+    /// federated authentications. Passkey authentications use `hwk`, as they
+    /// prove the possession of a key held by an authenticator. Logins with a
+    /// code sent by email use `otp`, along with `mfa` if the user also entered
+    /// their second factor. Authentications completed with a code sent by SMS
+    /// use `sms`.
+    #[must_use]
+    pub fn amr(&self) -> &'static [&'static str] {
+        match self {
+            Self::Password { .. } => &["pwd"],
+            Self::PasswordAndTotp { .. } | Self::PasswordAndRecoveryCode { .. } => &["pwd", "otp"],
+            Self::PasswordAndSms { .. } => &["pwd", "sms"],
+            Self::UpstreamOAuth2 { .. } => &["fed"],
+            Self::Passkey { .. } => &["hwk"],
+            Self::EmailCode { .. } => &["otp"],
+            Self::EmailCodeAndTotp { .. } | Self::EmailCodeAndRecoveryCode { .. } => {
+                &["otp", "mfa"]
+            }
+            Self::EmailCodeAndSms { .. } => &["otp", "sms"],
+            Self::Unknown => &[],
+        }
+    }
+}
+
+/// A passkey, a WebAuthn credential registered by a user to log in without a
+/// password
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserPasskey {
+    pub id: Ulid,
+    pub user_id: Ulid,
+
+    /// The ID of the credential, as given by the authenticator, encoded as
+    /// unpadded base64url
+    pub credential_id: String,
+
+    /// The name the user gave to this passkey
+    pub name: String,
+
+    /// The AAGUID of the authenticator model which created the credential
+    pub aaguid: String,
+
+    /// The public key of the credential, with the algorithm it signs with
+    pub public_key: PublicJsonWebKey,
+
+    /// The last signature counter reported by the authenticator
+    pub sign_count: u32,
+
+    /// The transports the authenticator can be reached through, as hints for
+    /// browsers
+    pub transports: Vec<String>,
+
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// A WebAuthn challenge, sent to the browser to register or to log in with a
+/// passkey
+///
+/// Challenges to register a passkey are bound to the browser session of the
+/// user. Challenges to log in aren't bound to anything, as the user isn't
+/// known yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserPasskeyChallenge {
+    pub id: Ulid,
+    pub user_session_id: Option<Ulid>,
+    pub challenge: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl UserPasskeyChallenge {
+    /// Mark the challenge as completed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the challenge was already completed
+    pub fn complete(mut self, completed_at: DateTime<Utc>) -> Result<Self, InvalidTransitionError> {
+        if self.completed_at.is_some() {
+            return Err(InvalidTransitionError);
+        }
+
+        self.completed_at = Some(completed_at);
+        Ok(self)
+    }
+}
+
+/// A TOTP second factor enrolled by a user, as defined in [RFC6238]
+///
+/// The second factor is only used to log in once it is confirmed, by entering
+/// a first code.
+///
+/// [RFC6238]: https://www.rfc-editor.org/rfc/rfc6238
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserTotp {
+    pub id: Ulid,
+    pub user_id: Ulid,
+
+    /// The shared secret, encrypted with the site encryption key
+    pub encrypted_secret: String,
+
+    pub created_at: DateTime<Utc>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+
+    /// The time step of the last code accepted, so that a code can't be used
+    /// twice
+    pub last_used_step: Option<i64>,
+}
+
+impl UserTotp {
+    /// Returns `true` if the second factor was confirmed, and can be used to
+    /// log in
+    #[must_use]
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+}
+
+/// A single-use code which lets a user log in when their second factor is
+/// unavailable
+///
+/// Only a hash of the code is stored, the code itself is shown once to the
+/// user when it is generated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserRecoveryCode {
+    pub id: Ulid,
+    pub user_id: Ulid,
+
+    /// The SHA-256 hash of the code, hex-encoded
+    pub code_hash: String,
+
+    pub created_at: DateTime<Utc>,
+    pub consumed_at: Option<DateTime<Utc>>,
+}
+
+/// A browser the user chose to trust after entering their second factor
+///
+/// Password logins from this browser skip the second factor until the device
+/// expires or is revoked. The browser only holds the ID of the device, in an
+/// encrypted cookie.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserTrustedDevice {
+    pub id: Ulid,
+    pub user_id: Ulid,
+
+    /// The user agent of the browser when it was trusted
+    pub user_agent: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl UserTrustedDevice {
+    /// Returns `true` if the device wasn't revoked and hasn't expired
+    #[must_use]
+    pub fn is_valid(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && now < self.expires_at
+    }
+}
+
+/// An account deletion requested by a user
+///
+/// The account is deactivated as soon as the deletion is requested, but only
+/// erased once `delete_at` is reached. Until then, the user can cancel the
+/// deletion and get their account back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserDeletion {
+    pub id: Ulid,
+    pub user_id: Ulid,
+
+    /// Whether to ask the homeserver to erase the user once the deletion
+    /// completes
+    pub hs_erase: bool,
+
+    pub created_at: DateTime<Utc>,
+    pub delete_at: DateTime<Utc>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl UserDeletion {
+    /// Returns `true` if the deletion was neither cancelled nor completed yet
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        self.cancelled_at.is_none() && self.completed_at.is_none()
+    }
+
+    #[doc(hidden)]
+    #[must_use]
+    pub fn samples(now: chrono::DateTime<Utc>, rng: &mut impl Rng) -> Vec<Self> {
+        let pending = Self {
+            id: Ulid::from_datetime_with_source(now.into(), rng),
+            user_id: Ulid::from_datetime_with_source(now.into(), rng),
+            hs_erase: false,
+            created_at: now,
+            delete_at: now + Duration::days(30),
+            cancelled_at: None,
+            completed_at: None,
+        };
+
+        let cancelled = Self {
+            id: Ulid::from_datetime_with_source(now.into(), rng),
+            cancelled_at: Some(now),
+            ..pending.clone()
+        };
+
+        vec![pending, cancelled]
+    }
+}
+
+/// A phone number added by a user, to which codes can be sent by SMS
+///
+/// The phone number is only used as a second factor once it is confirmed, by
+/// entering a first code sent to it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserPhone {
+    pub id: Ulid,
+    pub user_id: Ulid,
+
+    /// The phone number, in the E.164 format
+    pub phone_number: String,
+
+    pub created_at: DateTime<Utc>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+impl UserPhone {
+    /// Returns `true` if the phone number was confirmed, and can be used as a
+    /// second factor
+    #[must_use]
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+
+    /// Returns the phone number with all but its last digits hidden, so that
+    /// it can be shown on pages where the user is not yet authenticated
+    #[must_use]
+    pub fn masked_phone_number(&self) -> String {
+        let visible = self.phone_number.len().saturating_sub(2);
+        self.phone_number
+            .char_indices()
+            .map(|(i, c)| if i == 0 || i >= visible { c } else { '•' })
+            .collect()
+    }
+}
+
+impl UserPhone {
+    #[doc(hidden)]
+    #[must_use]
+    pub fn samples(now: chrono::DateTime<Utc>, rng: &mut impl Rng) -> Vec<Self> {
+        vec![Self {
+            id: Ulid::from_datetime_with_source(now.into(), rng),
+            user_id: Ulid::from_datetime_with_source(now.into(), rng),
+            phone_number: "+33612345678".to_owned(),
+            created_at: now,
+            confirmed_at: Some(now),
+        }]
+    }
+}
+
+/// A code sent by SMS to a [`UserPhone`], either to confirm the phone number
+/// or to complete a login
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserPhoneCode {
+    pub id: Ulid,
+    pub user_phone_id: Ulid,
+    pub code: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub consumed_at: Option<DateTime<Utc>>,
+}
+
+impl UserPhoneCode {
+    /// Returns `true` if the code can still be used
+    #[must_use]
+    pub fn is_valid(&self, now: DateTime<Utc>) -> bool {
+        self.consumed_at.is_none() && now < self.expires_at
+    }
+}
+
 /// A session to recover a user if they have lost their credentials
 ///
 /// For each session intiated, there may be multiple [`UserRecoveryTicket`]s
@@ -82,8 +495,41 @@ pub struct UserRecoverySession {
     pub user_agent: String,
     pub ip_address: Option<IpAddr>,
     pub locale: String,
+
+    /// Whether the user also lost access to their second factor, in which case
+    /// it is removed once they pick a new password
+    pub lost_second_factor: bool,
+
+    /// Whether an administrator has to approve the session before the recovery
+    /// links are sent
+    pub requires_approval: bool,
+
     pub created_at: DateTime<Utc>,
     pub consumed_at: Option<DateTime<Utc>>,
+    pub approved_at: Option<DateTime<Utc>>,
+    pub denied_at: Option<DateTime<Utc>>,
+}
+
+impl UserRecoverySession {
+    /// Returns `true` if the recovery links can be sent, because the session
+    /// didn't need an approval or was approved
+    #[must_use]
+    pub fn is_approved(&self) -> bool {
+        !self.requires_approval || self.approved_at.is_some()
+    }
+
+    /// Returns `true` if the session is waiting for an administrator to
+    /// approve or deny it
+    #[must_use]
+    pub fn is_pending_approval(&self) -> bool {
+        self.requires_approval && self.approved_at.is_none() && self.denied_at.is_none()
+    }
+
+    /// Returns `true` if an administrator denied the session
+    #[must_use]
+    pub fn is_denied(&self) -> bool {
+        self.denied_at.is_some()
+    }
 }
 
 /// A single recovery ticket for a user recovery session
@@ -114,6 +560,10 @@ pub struct UserEmailAuthentication {
     pub id: Ulid,
     pub user_session_id: Option<Ulid>,
     pub user_registration_id: Option<Ulid>,
+
+    /// The user logging in with this email address, if this authentication is
+    /// used to log in with a code sent by email
+    pub user_id: Option<Ulid>,
     pub email: String,
     pub created_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
@@ -138,6 +588,7 @@ pub struct BrowserSession {
     pub user_agent: Option<String>,
     pub last_active_at: Option<DateTime<Utc>>,
     pub last_active_ip: Option<IpAddr>,
+    pub last_active_location: IpLocation,
 }
 
 impl BrowserSession {
@@ -145,6 +596,21 @@ impl BrowserSession {
     pub fn active(&self) -> bool {
         self.finished_at.is_none() && self.user.is_valid()
     }
+
+    /// Whether the session wasn't used for longer than the idle timeout, or is
+    /// older than the maximum lifetime of sessions. Such sessions are finished
+    /// by a background job, but may not have been yet.
+    #[must_use]
+    pub fn expired(&self, now: DateTime<Utc>, config: &SessionExpirationConfig) -> bool {
+        let last_active_at = self.last_active_at.unwrap_or(self.created_at);
+        let idle = config
+            .user_session_inactivity_ttl
+            .is_some_and(|ttl| last_active_at + ttl < now);
+        let too_old = config
+            .user_session_max_lifetime
+            .is_some_and(|max_lifetime| self.created_at + max_lifetime < now);
+        idle || too_old
+    }
 }
 
 impl BrowserSession {
@@ -162,11 +628,34 @@ impl BrowserSession {
                 ),
                 last_active_at: Some(now),
                 last_active_ip: None,
+                last_active_location: IpLocation::default(),
             })
             .collect()
     }
 }
 
+/// A step-up of a [`BrowserSession`]: the user confirmed their identity again
+/// to access something sensitive, which elevates the session for a limited
+/// time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BrowserSessionElevation {
+    pub id: Ulid,
+
+    /// The strongest ACR value satisfied by the step-up, if any
+    pub acr: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl BrowserSessionElevation {
+    /// Whether the session is still elevated at the given time
+    #[must_use]
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        now < self.expires_at
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct UserEmail {
     pub id: Ulid,
@@ -195,6 +684,32 @@ impl UserEmail {
     }
 }
 
+/// A piece of arbitrary metadata attached to a [`User`], scoped to a namespace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserMetadata {
+    pub id: Ulid,
+    pub user_id: Ulid,
+    pub namespace: String,
+    pub key: String,
+    pub value: serde_json::Value,
+
+    /// Incremented on every update, used to detect concurrent modifications
+    pub version: u32,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl UserMetadata {
+    /// The namespace under which the settings users change themselves are
+    /// stored
+    pub const SETTINGS_NAMESPACE: &'static str = "settings";
+
+    /// The setting telling whether the user gets an email when logging in from
+    /// a new IP address or device. Unset means they do.
+    pub const SIGN_IN_NOTIFICATIONS_SETTING: &'static str = "sign_in_notifications";
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct UserRegistrationPassword {
     pub hashed_password: String,
@@ -211,6 +726,13 @@ pub struct UserRegistrationToken {
     pub last_used_at: Option<DateTime<Utc>>,
     pub expires_at: Option<DateTime<Utc>>,
     pub revoked_at: Option<DateTime<Utc>>,
+
+    /// The user who issued this token as an invite, if any
+    pub created_by_user_id: Option<Ulid>,
+
+    /// Whether the users who register with this token get the user who issued
+    /// it as their referrer
+    pub assign_referrer: bool,
 }
 
 impl UserRegistrationToken {
@@ -247,6 +769,79 @@ impl UserRegistrationToken {
     }
 }
 
+/// A one-time link generated by an administrator, which lets someone claim a
+/// pre-provisioned account by setting its password on their first visit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserClaimLink {
+    pub id: Ulid,
+    pub user_id: Ulid,
+    pub token: String,
+    pub email: Option<String>,
+    pub created_by_oauth2_session_id: Option<Ulid>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    pub claimed_ip_address: Option<IpAddr>,
+    pub claimed_user_agent: Option<String>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl UserClaimLink {
+    /// Returns `true` if the link was neither claimed nor revoked, and hasn't
+    /// expired yet
+    #[must_use]
+    pub fn is_valid(&self, now: DateTime<Utc>) -> bool {
+        self.claimed_at.is_none() && self.revoked_at.is_none() && now < self.expires_at
+    }
+}
+
+/// Something a [`UserActionToken`] lets its bearer do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UserAction {
+    /// End a browser session, e.g. from a "this wasn't me" link in an email
+    EndBrowserSession { browser_session_id: Ulid },
+}
+
+/// A single-use token embedded in links sent to a user, letting them perform
+/// a [`UserAction`] without being logged in.
+///
+/// Following the link only shows a confirmation page: the action is performed
+/// when the form on that page is submitted, so that link scanners and
+/// prefetchers can't trigger it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserActionToken {
+    pub id: Ulid,
+    pub user_id: Ulid,
+    pub token: String,
+    pub action: UserAction,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub consumed_at: Option<DateTime<Utc>>,
+}
+
+impl UserActionToken {
+    /// Returns `true` if the token wasn't used yet and hasn't expired
+    #[must_use]
+    pub fn is_valid(&self, now: DateTime<Utc>) -> bool {
+        self.consumed_at.is_none() && now < self.expires_at
+    }
+
+    /// Mark the token as used
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token was already used
+    pub fn consume(mut self, consumed_at: DateTime<Utc>) -> Result<Self, InvalidTransitionError> {
+        if self.consumed_at.is_some() {
+            return Err(InvalidTransitionError);
+        }
+
+        self.consumed_at = Some(consumed_at);
+        Ok(self)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct UserRegistration {
     pub id: Ulid,
@@ -10,7 +10,10 @@ use lettre::{
     AsyncTransport, Message,
     message::{Mailbox, MessageBuilder, MultiPart},
 };
-use mas_templates::{EmailRecoveryContext, EmailVerificationContext, Templates, WithLanguage};
+use mas_templates::{
+    EmailBackchannelContext, EmailClaimContext, EmailRecoveryContext, EmailRecoveryReviewContext,
+    EmailSignInContext, EmailVerificationContext, Templates, WithLanguage,
+};
 use thiserror::Error;
 
 use crate::MailTransport;
@@ -99,6 +102,96 @@ impl Mailer {
         Ok(message)
     }
 
+    fn prepare_recovery_review_email(
+        &self,
+        to: Mailbox,
+        context: &WithLanguage<EmailRecoveryReviewContext>,
+    ) -> Result<Message, Error> {
+        let plain = self.templates.render_email_recovery_review_txt(context)?;
+
+        let html = self.templates.render_email_recovery_review_html(context)?;
+
+        let multipart = MultiPart::alternative_plain_html(plain, html);
+
+        let subject = self
+            .templates
+            .render_email_recovery_review_subject(context)?;
+
+        let message = self
+            .base_message()
+            .subject(subject.trim())
+            .to(to)
+            .multipart(multipart)?;
+
+        Ok(message)
+    }
+
+    fn prepare_claim_email(
+        &self,
+        to: Mailbox,
+        context: &WithLanguage<EmailClaimContext>,
+    ) -> Result<Message, Error> {
+        let plain = self.templates.render_email_claim_txt(context)?;
+
+        let html = self.templates.render_email_claim_html(context)?;
+
+        let multipart = MultiPart::alternative_plain_html(plain, html);
+
+        let subject = self.templates.render_email_claim_subject(context)?;
+
+        let message = self
+            .base_message()
+            .subject(subject.trim())
+            .to(to)
+            .multipart(multipart)?;
+
+        Ok(message)
+    }
+
+    fn prepare_backchannel_email(
+        &self,
+        to: Mailbox,
+        context: &WithLanguage<EmailBackchannelContext>,
+    ) -> Result<Message, Error> {
+        let plain = self.templates.render_email_backchannel_txt(context)?;
+
+        let html = self.templates.render_email_backchannel_html(context)?;
+
+        let multipart = MultiPart::alternative_plain_html(plain, html);
+
+        let subject = self.templates.render_email_backchannel_subject(context)?;
+
+        let message = self
+            .base_message()
+            .subject(subject.trim())
+            .to(to)
+            .multipart(multipart)?;
+
+        Ok(message)
+    }
+
+    fn prepare_sign_in_email(
+        &self,
+        to: Mailbox,
+        context: &WithLanguage<EmailSignInContext>,
+    ) -> Result<Message, Error> {
+        let plain = self.templates.render_email_sign_in_txt(context)?;
+
+        let html = self.templates.render_email_sign_in_html(context)?;
+
+        let multipart = MultiPart::alternative_plain_html(plain, html);
+
+        let subject = self.templates.render_email_sign_in_subject(context)?;
+
+        let message = self
+            .base_message()
+            .subject(subject.trim())
+            .to(to)
+            .multipart(multipart)?;
+
+        Ok(message)
+    }
+
     /// Send the verification email to a user
     ///
     /// # Errors
@@ -147,6 +240,107 @@ impl Mailer {
         Ok(())
     }
 
+    /// Tell a user their recovery request is waiting for an administrator, or
+    /// that it was denied
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the email failed rendering or failed sending
+    #[tracing::instrument(
+        name = "email.recovery_review.send",
+        skip_all,
+        fields(
+            email.to = %to,
+            email.language = %context.language(),
+            user.id = %context.user().id,
+            user_recovery_session.id = %context.session().id,
+        ),
+    )]
+    pub async fn send_recovery_review_email(
+        &self,
+        to: Mailbox,
+        context: &WithLanguage<EmailRecoveryReviewContext>,
+    ) -> Result<(), Error> {
+        let message = self.prepare_recovery_review_email(to, context)?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+
+    /// Send an account claim link to a user
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the email failed rendering or failed sending
+    #[tracing::instrument(
+        name = "email.claim.send",
+        skip_all,
+        fields(
+            email.to = %to,
+            email.language = %context.language(),
+            user.id = %context.user().id,
+        ),
+    )]
+    pub async fn send_claim_email(
+        &self,
+        to: Mailbox,
+        context: &WithLanguage<EmailClaimContext>,
+    ) -> Result<(), Error> {
+        let message = self.prepare_claim_email(to, context)?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+
+    /// Ask a user to approve a backchannel authentication request
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the email failed rendering or failed sending
+    #[tracing::instrument(
+        name = "email.backchannel.send",
+        skip_all,
+        fields(
+            email.to = %to,
+            email.language = %context.language(),
+            user.id = %context.user().id,
+            oauth2_backchannel_authentication_grant.id = %context.grant().id,
+        ),
+    )]
+    pub async fn send_backchannel_email(
+        &self,
+        to: Mailbox,
+        context: &WithLanguage<EmailBackchannelContext>,
+    ) -> Result<(), Error> {
+        let message = self.prepare_backchannel_email(to, context)?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+
+    /// Tell a user their account was used to log in from a new IP address or
+    /// device
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the email failed rendering or failed sending
+    #[tracing::instrument(
+        name = "email.sign_in.send",
+        skip_all,
+        fields(
+            email.to = %to,
+            email.language = %context.language(),
+            user.id = %context.user().id,
+            user_session.id = %context.session().id,
+        ),
+    )]
+    pub async fn send_sign_in_email(
+        &self,
+        to: Mailbox,
+        context: &WithLanguage<EmailSignInContext>,
+    ) -> Result<(), Error> {
+        let message = self.prepare_sign_in_email(to, context)?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+
     /// Test the connetion to the mail server
     ///
     /// # Errors
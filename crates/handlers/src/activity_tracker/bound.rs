@@ -6,7 +6,7 @@
 
 use std::net::IpAddr;
 
-use mas_data_model::{BrowserSession, CompatSession, Session};
+use mas_data_model::{BrowserSession, CompatSession, IpLocation, Session};
 use mas_storage::Clock;
 
 use crate::activity_tracker::ActivityTracker;
@@ -31,6 +31,14 @@ impl Bound {
         self.ip
     }
 
+    /// Resolve where the IP address bound to this activity tracker is located
+    #[must_use]
+    pub fn location(&self) -> IpLocation {
+        self.ip
+            .map(|ip| self.tracker.geoip.lookup(ip))
+            .unwrap_or_default()
+    }
+
     /// Record activity in an OAuth 2.0 session.
     pub async fn record_oauth2_session(&self, clock: &dyn Clock, session: &Session) {
         self.tracker
@@ -0,0 +1,161 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::{net::IpAddr, sync::Arc};
+
+use arc_swap::ArcSwap;
+use camino::{Utf8Path, Utf8PathBuf};
+use mas_data_model::IpLocation;
+use maxminddb::{MaxMindDbError, Reader, geoip2};
+use thiserror::Error;
+
+/// Failed to load the GeoIP databases
+#[derive(Debug, Error)]
+pub enum GeoIpLoadError {
+    /// A database could not be read
+    #[error("failed to load the GeoIP database at {path}")]
+    Database {
+        path: Utf8PathBuf,
+        #[source]
+        source: MaxMindDbError,
+    },
+
+    /// The task loading the databases failed
+    #[error(transparent)]
+    Join(#[from] tokio::task::JoinError),
+}
+
+fn open_database(path: Utf8PathBuf) -> Result<Reader<Vec<u8>>, GeoIpLoadError> {
+    Reader::open_readfile(&path).map_err(|source| GeoIpLoadError::Database { path, source })
+}
+
+#[derive(Default)]
+struct Databases {
+    country: Option<Reader<Vec<u8>>>,
+    asn: Option<Reader<Vec<u8>>>,
+}
+
+impl Databases {
+    async fn open(
+        country_path: Option<&Utf8Path>,
+        asn_path: Option<&Utf8Path>,
+    ) -> Result<Self, GeoIpLoadError> {
+        let country_path = country_path.map(ToOwned::to_owned);
+        let asn_path = asn_path.map(ToOwned::to_owned);
+
+        // The databases are fully read in memory, which can take a bit of time
+        tokio::task::spawn_blocking(move || {
+            let country = country_path.map(open_database).transpose()?;
+            let asn = asn_path.map(open_database).transpose()?;
+            Ok(Self { country, asn })
+        })
+        .await?
+    }
+
+    fn lookup(&self, ip: IpAddr) -> Result<IpLocation, MaxMindDbError> {
+        // This also works with the City databases, which are a superset
+        let country = match &self.country {
+            Some(reader) => reader.lookup::<geoip2::Country>(ip)?,
+            None => None,
+        };
+
+        let asn = match &self.asn {
+            Some(reader) => reader.lookup::<geoip2::Asn>(ip)?,
+            None => None,
+        };
+
+        Ok(IpLocation {
+            country: country
+                .and_then(|c| c.country)
+                .and_then(|c| c.iso_code)
+                .map(ToOwned::to_owned),
+            asn: asn.as_ref().and_then(|a| a.autonomous_system_number),
+            as_organization: asn
+                .and_then(|a| a.autonomous_system_organization)
+                .map(ToOwned::to_owned),
+        })
+    }
+}
+
+/// Resolves where IP addresses are located, using MaxMind-style GeoIP
+/// databases
+///
+/// The databases can be reloaded at runtime, to pick up updated versions of
+/// the files.
+#[derive(Clone, Default)]
+pub struct GeoIpResolver {
+    country_path: Option<Utf8PathBuf>,
+    asn_path: Option<Utf8PathBuf>,
+    databases: Arc<ArcSwap<Databases>>,
+}
+
+impl GeoIpResolver {
+    /// Create a resolver which doesn't resolve anything
+    #[must_use]
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Load the GeoIP databases from the given paths
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if one of the databases could not be read
+    #[tracing::instrument(name = "geoip.load", skip_all)]
+    pub async fn load(
+        country_path: Option<Utf8PathBuf>,
+        asn_path: Option<Utf8PathBuf>,
+    ) -> Result<Self, GeoIpLoadError> {
+        let databases = Databases::open(country_path.as_deref(), asn_path.as_deref()).await?;
+
+        Ok(Self {
+            country_path,
+            asn_path,
+            databases: Arc::new(ArcSwap::from_pointee(databases)),
+        })
+    }
+
+    /// Reload the GeoIP databases from disk
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if one of the databases could not be read, in which
+    /// case the previously loaded databases are kept
+    #[tracing::instrument(name = "geoip.reload", skip_all)]
+    pub async fn reload(&self) -> Result<(), GeoIpLoadError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let databases =
+            Databases::open(self.country_path.as_deref(), self.asn_path.as_deref()).await?;
+        self.databases.store(Arc::new(databases));
+        Ok(())
+    }
+
+    /// Returns `true` if at least one database is configured
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.country_path.is_some() || self.asn_path.is_some()
+    }
+
+    /// Resolve where the given IP address is located
+    ///
+    /// Lookup failures are logged and result in an empty location.
+    #[must_use]
+    pub fn lookup(&self, ip: IpAddr) -> IpLocation {
+        match self.databases.load().lookup(ip) {
+            Ok(location) => location,
+            Err(e) => {
+                tracing::warn!(
+                    error = &e as &dyn std::error::Error,
+                    %ip,
+                    "Failed to resolve the location of an IP address"
+                );
+                IpLocation::default()
+            }
+        }
+    }
+}
@@ -5,6 +5,7 @@
 // Please see LICENSE files in the repository root for full details.
 
 mod bound;
+mod geoip;
 mod worker;
 
 use std::net::IpAddr;
@@ -15,8 +16,11 @@ use mas_storage::{BoxRepositoryFactory, Clock};
 use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use ulid::Ulid;
 
-pub use self::bound::Bound;
 use self::worker::Worker;
+pub use self::{
+    bound::Bound,
+    geoip::{GeoIpLoadError, GeoIpResolver},
+};
 
 static MESSAGE_QUEUE_SIZE: usize = 1000;
 
@@ -50,6 +54,7 @@ enum Message {
 #[derive(Clone)]
 pub struct ActivityTracker {
     channel: tokio::sync::mpsc::Sender<Message>,
+    geoip: GeoIpResolver,
 }
 
 impl ActivityTracker {
@@ -58,16 +63,24 @@ impl ActivityTracker {
     /// It will spawn the background worker and a loop to flush the tracker on
     /// the task tracker, and both will shut themselves down, flushing one last
     /// time, when the cancellation token is cancelled.
+    ///
+    /// The IP addresses are resolved to locations through the given
+    /// [`GeoIpResolver`] when the activity is written to the database, or when
+    /// a request needs to know where it comes from.
     #[must_use]
     pub fn new(
         repository_factory: BoxRepositoryFactory,
+        geoip: GeoIpResolver,
         flush_interval: std::time::Duration,
         task_tracker: &TaskTracker,
         cancellation_token: CancellationToken,
     ) -> Self {
-        let worker = Worker::new(repository_factory);
+        let worker = Worker::new(repository_factory, geoip.clone());
         let (sender, receiver) = tokio::sync::mpsc::channel(MESSAGE_QUEUE_SIZE);
-        let tracker = ActivityTracker { channel: sender };
+        let tracker = ActivityTracker {
+            channel: sender,
+            geoip,
+        };
 
         // Spawn the flush loop and the worker
         task_tracker.spawn(
@@ -19,7 +19,7 @@ use ulid::Ulid;
 
 use crate::{
     METER,
-    activity_tracker::{Message, SessionKind},
+    activity_tracker::{GeoIpResolver, Message, SessionKind},
 };
 
 /// The maximum number of pending activity records before we flush them to the
@@ -45,6 +45,7 @@ struct ActivityRecord {
 /// Handles writing activity records to the database.
 pub struct Worker {
     repository_factory: BoxRepositoryFactory,
+    geoip: GeoIpResolver,
     pending_records: HashMap<(SessionKind, Ulid), ActivityRecord>,
     pending_records_gauge: Gauge<u64>,
     message_counter: Counter<u64>,
@@ -52,7 +53,7 @@ pub struct Worker {
 }
 
 impl Worker {
-    pub(crate) fn new(repository_factory: BoxRepositoryFactory) -> Self {
+    pub(crate) fn new(repository_factory: BoxRepositoryFactory, geoip: GeoIpResolver) -> Self {
         let message_counter = METER
             .u64_counter("mas.activity_tracker.messages")
             .with_description("The number of messages received by the activity tracker")
@@ -91,6 +92,7 @@ impl Worker {
 
         Self {
             repository_factory,
+            geoip,
             pending_records: HashMap::with_capacity(MAX_PENDING_RECORDS),
             pending_records_gauge,
             message_counter,
@@ -226,16 +228,16 @@ impl Worker {
         let mut compat_sessions = Vec::new();
 
         for ((kind, id), record) in pending_records {
+            let location = record
+                .ip
+                .map(|ip| self.geoip.lookup(ip))
+                .unwrap_or_default();
+            let activity = (*id, record.end_time, record.ip, location);
+
             match kind {
-                SessionKind::Browser => {
-                    browser_sessions.push((*id, record.end_time, record.ip));
-                }
-                SessionKind::OAuth2 => {
-                    oauth2_sessions.push((*id, record.end_time, record.ip));
-                }
-                SessionKind::Compat => {
-                    compat_sessions.push((*id, record.end_time, record.ip));
-                }
+                SessionKind::Browser => browser_sessions.push(activity),
+                SessionKind::OAuth2 => oauth2_sessions.push(activity),
+                SessionKind::Compat => compat_sessions.push(activity),
             }
         }
 
@@ -9,19 +9,22 @@ use std::convert::Infallible;
 use aide::OperationIo;
 use axum::{
     Json,
-    extract::FromRequestParts,
+    extract::{FromRef, FromRequestParts, OriginalUri},
     response::{IntoResponse, Response},
 };
-use axum_extra::TypedHeader;
-use headers::{Authorization, authorization::Bearer};
+use headers::{Authorization, HeaderMapExt, authorization::Bearer};
 use hyper::StatusCode;
-use mas_axum_utils::record_error;
+use mas_axum_utils::{record_error, user_authorization::DPoP};
 use mas_data_model::{Session, User};
-use mas_storage::{BoxClock, BoxRepository, RepositoryError};
+use mas_router::UrlBuilder;
+use mas_storage::{BoxClock, BoxRepository, BoxRng, RepositoryError};
 use ulid::Ulid;
 
 use super::response::ErrorResponse;
-use crate::BoundActivityTracker;
+use crate::{
+    BoundActivityTracker,
+    oauth2::dpop::{self, DPoPError},
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Rejection {
@@ -68,6 +71,28 @@ pub enum Rejection {
     /// The session does not have the `urn:mas:admin` scope
     #[error("Missing urn:mas:admin scope")]
     MissingScope,
+
+    /// The access token was issued for another resource server
+    #[error("Access token was issued for another resource")]
+    WrongAudience,
+
+    /// The access token is not presented with the DPoP proof it is bound to
+    #[error("Invalid DPoP proof")]
+    InvalidDPoPProof(#[source] DPoPError),
+
+    /// The DPoP proof could not be verified
+    #[error("Could not verify the DPoP proof")]
+    DPoPVerification(#[source] DPoPError),
+}
+
+impl From<DPoPError> for Rejection {
+    fn from(err: DPoPError) -> Self {
+        if err.is_internal() {
+            Self::DPoPVerification(err)
+        } else {
+            Self::InvalidDPoPProof(err)
+        }
+    }
 }
 
 impl IntoResponse for Rejection {
@@ -79,6 +104,7 @@ impl IntoResponse for Rejection {
                 | Self::Repository(_)
                 | Self::LoadSession(_)
                 | Self::LoadUser(_)
+                | Self::DPoPVerification(_)
         );
 
         let status = match &self {
@@ -90,12 +116,15 @@ impl IntoResponse for Rejection {
             | Rejection::TokenExpired
             | Rejection::SessionRevoked
             | Rejection::UserLocked
-            | Rejection::MissingScope => StatusCode::UNAUTHORIZED,
+            | Rejection::MissingScope
+            | Rejection::WrongAudience
+            | Rejection::InvalidDPoPProof(_) => StatusCode::UNAUTHORIZED,
 
             Rejection::RepositorySetup(_)
             | Rejection::Repository(_)
             | Rejection::LoadSession(_)
-            | Rejection::LoadUser(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            | Rejection::LoadUser(_)
+            | Rejection::DPoPVerification(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
         (status, sentry_event_id, Json(response)).into_response()
@@ -122,6 +151,8 @@ where
     BoundActivityTracker: FromRequestParts<S, Rejection = Infallible>,
     BoxRepository: FromRequestParts<S>,
     BoxClock: FromRequestParts<S, Rejection = Infallible>,
+    BoxRng: FromRequestParts<S, Rejection = Infallible>,
+    UrlBuilder: FromRef<S>,
     <BoxRepository as FromRequestParts<S>>::Rejection:
         Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
 {
@@ -133,6 +164,7 @@ where
     ) -> Result<Self, Self::Rejection> {
         let Ok(activity_tracker) = BoundActivityTracker::from_request_parts(parts, state).await;
         let Ok(clock) = BoxClock::from_request_parts(parts, state).await;
+        let Ok(mut rng) = BoxRng::from_request_parts(parts, state).await;
 
         // Load the database repository
         let mut repo = BoxRepository::from_request_parts(parts, state)
@@ -140,28 +172,42 @@ where
             .map_err(Into::into)
             .map_err(Rejection::RepositorySetup)?;
 
-        // Extract the access token from the authorization header
-        let token = TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
-            .await
-            .map_err(|e| {
-                // We map to two differentsson of errors depending on whether the header is
-                // missing or invalid
-                if e.is_missing() {
-                    Rejection::MissingAuthorizationHeader
-                } else {
-                    Rejection::InvalidAuthorizationHeader
-                }
-            })?;
-
-        let token = token.token();
+        // Extract the access token from the authorization header, which can use
+        // either the Bearer or the DPoP scheme
+        let (token, proof) = match (
+            parts.headers.typed_try_get::<Authorization<Bearer>>(),
+            parts.headers.typed_try_get::<Authorization<DPoP>>(),
+        ) {
+            (Ok(Some(Authorization(bearer))), _) => (bearer.token().to_owned(), None),
+            (_, Ok(Some(Authorization(credentials)))) => {
+                // Tokens presented with the DPoP scheme come with a proof of possession
+                // of the key they are bound to
+                let url = request_url(&UrlBuilder::from_ref(state), parts);
+                let proof = dpop::verify_token_proof(
+                    &mut rng,
+                    &clock,
+                    &mut repo,
+                    &parts.headers,
+                    &parts.method,
+                    &url,
+                    credentials.token(),
+                )
+                .await?;
+                (credentials.token().to_owned(), Some(proof))
+            }
+            (Ok(None), Ok(None)) => return Err(Rejection::MissingAuthorizationHeader),
+            _ => return Err(Rejection::InvalidAuthorizationHeader),
+        };
 
         // Look for the access token in the database
         let token = repo
             .oauth2_access_token()
-            .find_by_token(token)
+            .find_by_token(&token)
             .await?
             .ok_or(Rejection::UnknownAccessToken)?;
 
+        dpop::ensure_token_binding(&token, proof.as_ref())?;
+
         // Look for the associated session in the database
         let session = repo
             .oauth2_session()
@@ -201,6 +247,12 @@ where
             return Err(Rejection::TokenExpired);
         }
 
+        // Tokens requested with a resource indicator are meant for that
+        // resource server only, not for the admin API
+        if session.resource.is_some() {
+            return Err(Rejection::WrongAudience);
+        }
+
         // For now, we only check that the session has the admin scope
         // Later we might want to check other route-specific scopes
         if !session.scope.contains("urn:mas:admin") {
@@ -215,3 +267,130 @@ where
         })
     }
 }
+
+/// The public URL of the request, as covered by the `htu` claim of DPoP proofs
+fn request_url(url_builder: &UrlBuilder, parts: &axum::http::request::Parts) -> url::Url {
+    // The admin API is nested, so the URI of the request lost its prefix
+    let path = parts
+        .extensions
+        .get::<OriginalUri>()
+        .map_or_else(|| parts.uri.path(), |OriginalUri(uri)| uri.path());
+
+    let mut url = url_builder.http_base();
+    url.set_path(path);
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use base64ct::{Base64UrlUnpadded, Encoding};
+    use hyper::{Request, StatusCode, header::AUTHORIZATION};
+    use mas_iana::jose::JsonWebSignatureAlg;
+    use mas_jose::{
+        jwk::{JsonWebKey, JsonWebKeyPublicParameters},
+        jwt::{JsonWebSignatureHeader, Jwt},
+    };
+    use mas_storage::{Clock, RepositoryAccess};
+    use sha2::{Digest, Sha256};
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_dpop_bound_token(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        // Bind the token to a key
+        let key = mas_keystore::PrivateKey::generate_ec_p256(&mut rng);
+        let public_key = JsonWebKeyPublicParameters::from(&key);
+        let signer = key
+            .signing_key_for_alg(&JsonWebSignatureAlg::Es256)
+            .unwrap();
+
+        let mut repo = state.repository().await.unwrap();
+        let access_token = repo
+            .oauth2_access_token()
+            .find_by_token(&token)
+            .await
+            .unwrap()
+            .unwrap();
+        repo.oauth2_access_token()
+            .bind_dpop_key(access_token, public_key.thumbprint())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        // It can't be used as a bearer token anymore
+        let request = Request::get("/api/admin/v1/users").bearer(&token).empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+
+        // Nor with the DPoP scheme but without a proof
+        let request = Request::get("/api/admin/v1/users")
+            .header(AUTHORIZATION, format!("DPoP {token}"))
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+
+        // It works with a proof signed with the key, covering the token
+        let header = JsonWebSignatureHeader::new(JsonWebSignatureAlg::Es256)
+            .with_typ("dpop+jwt".to_owned())
+            .with_jwk(JsonWebKey::new(public_key));
+        let claims = serde_json::json!({
+            "htm": "GET",
+            "htu": state.url_builder.http_base().join("api/admin/v1/users").unwrap(),
+            "iat": state.clock.now().timestamp(),
+            "jti": "first",
+            "ath": Base64UrlUnpadded::encode_string(&Sha256::digest(token.as_bytes())),
+        });
+        let proof = Jwt::sign_with_rng(&mut rng, header, claims, &signer)
+            .unwrap()
+            .into_string();
+
+        let request = Request::get("/api/admin/v1/users")
+            .header(AUTHORIZATION, format!("DPoP {token}"))
+            .header("DPoP", proof)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_resource_token(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let request = Request::get("/api/admin/v1/users").bearer(&token).empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        // Restrict the session of the token to another resource server
+        let mut repo = state.repository().await.unwrap();
+        let access_token = repo
+            .oauth2_access_token()
+            .find_by_token(&token)
+            .await
+            .unwrap()
+            .unwrap();
+        let session = repo
+            .oauth2_session()
+            .lookup(access_token.session_id)
+            .await
+            .unwrap()
+            .unwrap();
+        repo.oauth2_session()
+            .record_resource(session, "https://api.example.com/".parse().unwrap())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        // The token isn't accepted by the admin API anymore
+        let request = Request::get("/api/admin/v1/users").bearer(&token).empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+    }
+}
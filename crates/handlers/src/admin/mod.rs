@@ -20,7 +20,9 @@ use axum::{
 use hyper::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
 use indexmap::IndexMap;
 use mas_axum_utils::InternalError;
+use mas_data_model::SiteConfig;
 use mas_http::CorsLayerExt;
+use mas_keystore::Encrypter;
 use mas_matrix::HomeserverConnection;
 use mas_policy::PolicyFactory;
 use mas_router::{
@@ -39,7 +41,7 @@ mod schema;
 mod v1;
 
 use self::call_context::CallContext;
-use crate::passwords::PasswordManager;
+use crate::{passwords::PasswordManager, upstream_oauth2::health::ProviderHealthTracker};
 
 fn finish(t: TransformOpenApi) -> TransformOpenApi {
     t.title("Matrix Authentication Service admin API")
@@ -48,11 +50,31 @@ fn finish(t: TransformOpenApi) -> TransformOpenApi {
             description: Some("Manage compatibility sessions from legacy clients".to_owned()),
             ..Tag::default()
         })
+        .tag(Tag {
+            name: "feature-flag-override".to_owned(),
+            description: Some(
+                "Override the rollout of features without restarting the service".to_owned(),
+            ),
+            ..Tag::default()
+        })
         .tag(Tag {
             name: "policy-data".to_owned(),
             description: Some("Manage the dynamic policy data".to_owned()),
             ..Tag::default()
         })
+        .tag(Tag {
+            name: "login-stats".to_owned(),
+            description: Some(
+                "Inspect the daily login statistics of upstream OAuth 2.0 providers and OAuth 2.0 clients"
+                    .to_owned(),
+            ),
+            ..Tag::default()
+        })
+        .tag(Tag {
+            name: "oauth2-client".to_owned(),
+            description: Some("Manage the credentials of OAuth 2.0 clients".to_owned()),
+            ..Tag::default()
+        })
         .tag(Tag {
             name: "oauth2-session".to_owned(),
             description: Some("Manage OAuth2 sessions".to_owned()),
@@ -73,11 +95,37 @@ fn finish(t: TransformOpenApi) -> TransformOpenApi {
             description: Some("Manage browser sessions of users".to_owned()),
             ..Tag::default()
         })
+        .tag(Tag {
+            name: "user-claim-link".to_owned(),
+            description: Some(
+                "Manage one-time links letting people claim pre-provisioned accounts".to_owned(),
+            ),
+            ..Tag::default()
+        })
+        .tag(Tag {
+            name: "user-recovery-session".to_owned(),
+            description: Some(
+                "Review the account recovery requests which need an approval".to_owned(),
+            ),
+            ..Tag::default()
+        })
         .tag(Tag {
             name: "user-registration-token".to_owned(),
             description: Some("Manage user registration tokens".to_owned()),
             ..Tag::default()
         })
+        .tag(Tag {
+            name: "scim-sync-run".to_owned(),
+            description: Some(
+                "Inspect the synchronisation runs against the upstream SCIM directory".to_owned(),
+            ),
+            ..Tag::default()
+        })
+        .tag(Tag {
+            name: "upstream-oauth-provider".to_owned(),
+            description: Some("Inspect the health of upstream OAuth 2.0 providers".to_owned()),
+            ..Tag::default()
+        })
         .tag(Tag {
             name: "upstream-oauth-link".to_owned(),
             description: Some(
@@ -148,11 +196,14 @@ where
     S: Clone + Send + Sync + 'static,
     Arc<dyn HomeserverConnection>: FromRef<S>,
     PasswordManager: FromRef<S>,
+    Encrypter: FromRef<S>,
     BoxRng: FromRequestParts<S>,
     CallContext: FromRequestParts<S>,
     Templates: FromRef<S>,
     UrlBuilder: FromRef<S>,
     Arc<PolicyFactory>: FromRef<S>,
+    SiteConfig: FromRef<S>,
+    ProviderHealthTracker: FromRef<S>,
 {
     // We *always* want to explicitly set the possible responses, beacuse the
     // infered ones are not necessarily correct
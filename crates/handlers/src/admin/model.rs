@@ -4,15 +4,17 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
-use std::net::IpAddr;
+use std::{collections::BTreeMap, net::IpAddr};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use mas_data_model::Device;
 use schemars::JsonSchema;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use ulid::Ulid;
 use url::Url;
 
+use crate::upstream_oauth2::health::ProviderHealth;
+
 /// A resource, with a type and an ID
 pub trait Resource {
     /// The type of the resource
@@ -52,6 +54,11 @@ pub struct User {
 
     /// Whether the user can request admin privileges.
     admin: bool,
+
+    /// Aggregated figures about the sessions of the user. Only present when
+    /// getting or listing users.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sessions: Option<UserSessionCounts>,
 }
 
 impl User {
@@ -65,6 +72,7 @@ impl User {
                 locked_at: None,
                 deactivated_at: None,
                 admin: false,
+                sessions: None,
             },
             Self {
                 id: Ulid::from_bytes([0x02; 16]),
@@ -73,6 +81,7 @@ impl User {
                 locked_at: None,
                 deactivated_at: None,
                 admin: true,
+                sessions: None,
             },
             Self {
                 id: Ulid::from_bytes([0x03; 16]),
@@ -81,9 +90,65 @@ impl User {
                 locked_at: Some(DateTime::default()),
                 deactivated_at: None,
                 admin: false,
+                sessions: None,
             },
         ]
     }
+
+    /// Samples of users with their session counts, for the endpoints which
+    /// include them
+    pub fn samples_with_sessions() -> [Self; 3] {
+        let [alice, bob, charlie] = Self::samples();
+        [
+            alice.with_sessions(mas_storage::user::UserSessionCounts {
+                active_browser_sessions: 1,
+                active_oauth2_sessions: 2,
+                active_compat_sessions: 1,
+                last_active_at: Some(DateTime::default()),
+            }),
+            bob.with_sessions(mas_storage::user::UserSessionCounts {
+                active_browser_sessions: 0,
+                active_oauth2_sessions: 1,
+                active_compat_sessions: 0,
+                last_active_at: Some(DateTime::default()),
+            }),
+            charlie.with_sessions(mas_storage::user::UserSessionCounts::default()),
+        ]
+    }
+
+    /// Attach the session counts to the user
+    #[must_use]
+    pub fn with_sessions(mut self, counts: mas_storage::user::UserSessionCounts) -> Self {
+        self.sessions = Some(counts.into());
+        self
+    }
+}
+
+/// Aggregated figures about the sessions of a user
+#[derive(Serialize, JsonSchema)]
+pub struct UserSessionCounts {
+    /// The number of active browser sessions
+    active_browser_sessions: usize,
+
+    /// The number of active OAuth 2.0 sessions
+    active_oauth2_sessions: usize,
+
+    /// The number of active compatibility sessions
+    active_compat_sessions: usize,
+
+    /// The last time any of the sessions of the user was active
+    last_active_at: Option<DateTime<Utc>>,
+}
+
+impl From<mas_storage::user::UserSessionCounts> for UserSessionCounts {
+    fn from(value: mas_storage::user::UserSessionCounts) -> Self {
+        Self {
+            active_browser_sessions: value.active_browser_sessions,
+            active_oauth2_sessions: value.active_oauth2_sessions,
+            active_compat_sessions: value.active_compat_sessions,
+            last_active_at: value.last_active_at,
+        }
+    }
 }
 
 impl From<mas_data_model::User> for User {
@@ -95,6 +160,7 @@ impl From<mas_data_model::User> for User {
             locked_at: user.locked_at,
             deactivated_at: user.deactivated_at,
             admin: user.can_request_admin,
+            sessions: None,
         }
     }
 }
@@ -108,6 +174,207 @@ impl Resource for User {
     }
 }
 
+/// The custom attributes of a user
+#[derive(Serialize, JsonSchema)]
+pub struct UserAttributes {
+    #[serde(skip)]
+    id: Ulid,
+
+    /// The values of the custom attributes set on the user, keyed by attribute
+    /// name
+    attributes: BTreeMap<String, serde_json::Value>,
+}
+
+impl UserAttributes {
+    /// Create the attributes resource of a user
+    pub fn new(user_id: Ulid, attributes: BTreeMap<String, serde_json::Value>) -> Self {
+        Self {
+            id: user_id,
+            attributes,
+        }
+    }
+
+    /// Samples of user attributes
+    pub fn samples() -> [Self; 1] {
+        [Self {
+            id: Ulid::from_bytes([0x01; 16]),
+            attributes: BTreeMap::from([
+                ("department".to_owned(), serde_json::json!("Engineering")),
+                ("employee_id".to_owned(), serde_json::json!("E-1234")),
+            ]),
+        }]
+    }
+}
+
+impl Resource for UserAttributes {
+    const KIND: &'static str = "user-attributes";
+    const PATH: &'static str = "/api/admin/v1/users";
+
+    fn id(&self) -> Ulid {
+        self.id
+    }
+
+    fn path(&self) -> String {
+        format!("{}/{}/attributes", Self::PATH, self.id())
+    }
+}
+
+/// The kind of a legal document users have to accept
+#[derive(Serialize, JsonSchema, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum TermsDocumentKind {
+    TermsOfService,
+    PrivacyPolicy,
+}
+
+impl From<mas_data_model::TermsDocumentKind> for TermsDocumentKind {
+    fn from(value: mas_data_model::TermsDocumentKind) -> Self {
+        match value {
+            mas_data_model::TermsDocumentKind::TermsOfService => Self::TermsOfService,
+            mas_data_model::TermsDocumentKind::PrivacyPolicy => Self::PrivacyPolicy,
+        }
+    }
+}
+
+/// The current version of a legal document, and whether a user accepted it
+#[derive(Serialize, JsonSchema)]
+pub struct UserTermsDocument {
+    /// The kind of document
+    kind: TermsDocumentKind,
+
+    /// The current version of the document
+    version: String,
+
+    /// Where the current version of the document is published
+    url: Url,
+
+    /// When the user accepted the current version of the document, if they did
+    accepted_at: Option<DateTime<Utc>>,
+}
+
+/// A version of a legal document accepted by a user
+#[derive(Serialize, JsonSchema)]
+pub struct UserTermsAcceptance {
+    /// The kind of document
+    kind: TermsDocumentKind,
+
+    /// The version of the document which was accepted
+    version: String,
+
+    /// Where the document was published when it was accepted
+    url: Url,
+
+    /// When the document was accepted
+    accepted_at: DateTime<Utc>,
+}
+
+impl From<mas_data_model::UserTermsAcceptance> for UserTermsAcceptance {
+    fn from(value: mas_data_model::UserTermsAcceptance) -> Self {
+        Self {
+            kind: value.kind.into(),
+            version: value.version,
+            url: value.url,
+            accepted_at: value.accepted_at,
+        }
+    }
+}
+
+/// The legal documents a user has to accept, and the versions they accepted
+#[derive(Serialize, JsonSchema)]
+pub struct UserTerms {
+    #[serde(skip)]
+    id: Ulid,
+
+    /// The current version of each configured document
+    documents: Vec<UserTermsDocument>,
+
+    /// All the versions of the documents the user accepted, oldest first
+    acceptances: Vec<UserTermsAcceptance>,
+}
+
+impl UserTerms {
+    /// Create the terms resource of a user, from the currently configured
+    /// documents and the user's acceptances
+    pub fn new(
+        user_id: Ulid,
+        documents: &[mas_data_model::TermsDocument],
+        acceptances: Vec<mas_data_model::UserTermsAcceptance>,
+    ) -> Self {
+        let documents = documents
+            .iter()
+            .map(|document| {
+                let accepted_at = acceptances
+                    .iter()
+                    .find(|acceptance| {
+                        acceptance.kind == document.kind && acceptance.version == document.version
+                    })
+                    .map(|acceptance| acceptance.accepted_at);
+
+                UserTermsDocument {
+                    kind: document.kind.into(),
+                    version: document.version.clone(),
+                    url: document.url.clone(),
+                    accepted_at,
+                }
+            })
+            .collect();
+
+        Self {
+            id: user_id,
+            documents,
+            acceptances: acceptances.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Samples of user terms
+    pub fn samples() -> [Self; 1] {
+        [Self {
+            id: Ulid::from_bytes([0x01; 16]),
+            documents: vec![
+                UserTermsDocument {
+                    kind: TermsDocumentKind::TermsOfService,
+                    version: "2025-07".to_owned(),
+                    url: "https://example.com/terms/2025-07".parse().unwrap(),
+                    accepted_at: Some(DateTime::default()),
+                },
+                UserTermsDocument {
+                    kind: TermsDocumentKind::PrivacyPolicy,
+                    version: "2025-07".to_owned(),
+                    url: "https://example.com/privacy/2025-07".parse().unwrap(),
+                    accepted_at: None,
+                },
+            ],
+            acceptances: vec![
+                UserTermsAcceptance {
+                    kind: TermsDocumentKind::TermsOfService,
+                    version: "2024-01".to_owned(),
+                    url: "https://example.com/terms/2024-01".parse().unwrap(),
+                    accepted_at: DateTime::default(),
+                },
+                UserTermsAcceptance {
+                    kind: TermsDocumentKind::TermsOfService,
+                    version: "2025-07".to_owned(),
+                    url: "https://example.com/terms/2025-07".parse().unwrap(),
+                    accepted_at: DateTime::default(),
+                },
+            ],
+        }]
+    }
+}
+
+impl Resource for UserTerms {
+    const KIND: &'static str = "user-terms";
+    const PATH: &'static str = "/api/admin/v1/users";
+
+    fn id(&self) -> Ulid {
+        self.id
+    }
+
+    fn path(&self) -> String {
+        format!("{}/{}/terms", Self::PATH, self.id())
+    }
+}
+
 /// An email address for a user
 #[derive(Serialize, JsonSchema)]
 pub struct UserEmail {
@@ -189,11 +456,28 @@ pub struct CompatSession {
     /// The last IP address recorded for this session
     pub last_active_ip: Option<std::net::IpAddr>,
 
+    /// The ISO 3166-1 code of the country the last IP address is located in,
+    /// if known
+    pub last_active_country: Option<String>,
+
+    /// The number of the autonomous system the last IP address belongs to, if
+    /// known
+    pub last_active_asn: Option<u32>,
+
+    /// The name of the organization operating the autonomous system the last
+    /// IP address belongs to, if known
+    pub last_active_as_organization: Option<String>,
+
     /// The time this session was finished
     pub finished_at: Option<DateTime<Utc>>,
 
     /// The user-provided name, if any
     pub human_name: Option<String>,
+
+    /// The ID of the admin API session which minted this session to
+    /// impersonate the user, if any
+    #[schemars(with = "Option<super::schema::Ulid>")]
+    pub impersonated_by_session_id: Option<Ulid>,
 }
 
 impl
@@ -219,8 +503,12 @@ impl
             user_agent: session.user_agent,
             last_active_at: session.last_active_at,
             last_active_ip: session.last_active_ip,
+            last_active_country: session.last_active_location.country,
+            last_active_asn: session.last_active_location.asn,
+            last_active_as_organization: session.last_active_location.as_organization,
             finished_at,
             human_name: session.human_name,
+            impersonated_by_session_id: session.impersonated_by_session_id,
         }
     }
 }
@@ -247,8 +535,12 @@ impl CompatSession {
                 user_agent: Some("Mozilla/5.0".to_owned()),
                 last_active_at: Some(DateTime::default()),
                 last_active_ip: Some([1, 2, 3, 4].into()),
+                last_active_country: Some("FR".to_owned()),
+                last_active_asn: Some(3215),
+                last_active_as_organization: Some("Orange".to_owned()),
                 finished_at: None,
                 human_name: Some("Laptop".to_owned()),
+                impersonated_by_session_id: None,
             },
             Self {
                 id: Ulid::from_bytes([0x02; 16]),
@@ -260,8 +552,12 @@ impl CompatSession {
                 user_agent: Some("Mozilla/5.0".to_owned()),
                 last_active_at: Some(DateTime::default()),
                 last_active_ip: Some([1, 2, 3, 4].into()),
+                last_active_country: Some("FR".to_owned()),
+                last_active_asn: Some(3215),
+                last_active_as_organization: Some("Orange".to_owned()),
                 finished_at: Some(DateTime::default()),
                 human_name: None,
+                impersonated_by_session_id: None,
             },
             Self {
                 id: Ulid::from_bytes([0x03; 16]),
@@ -273,13 +569,162 @@ impl CompatSession {
                 user_agent: None,
                 last_active_at: None,
                 last_active_ip: None,
+                last_active_country: None,
+                last_active_asn: None,
+                last_active_as_organization: None,
                 finished_at: None,
                 human_name: None,
+                impersonated_by_session_id: None,
+            },
+        ]
+    }
+}
+
+/// A short-lived compatibility session minted by an administrator to act as a
+/// user, for support purposes
+#[derive(Serialize, JsonSchema)]
+pub struct UserImpersonation {
+    #[serde(skip)]
+    id: Ulid,
+
+    /// The ID of the user being impersonated
+    #[schemars(with = "super::schema::Ulid")]
+    user_id: Ulid,
+
+    /// The Matrix device ID of the session
+    #[schemars(with = "super::schema::Device")]
+    device_id: Device,
+
+    /// The access token to use with the Matrix client-server API
+    access_token: String,
+
+    /// When the session was created
+    created_at: DateTime<Utc>,
+
+    /// When the access token expires, and the session is finished
+    expires_at: DateTime<Utc>,
+}
+
+impl UserImpersonation {
+    pub fn new(
+        session: &mas_data_model::CompatSession,
+        device: Device,
+        access_token: mas_data_model::CompatAccessToken,
+        expires_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: session.id,
+            user_id: session.user_id,
+            device_id: device,
+            access_token: access_token.token,
+            created_at: session.created_at,
+            expires_at,
+        }
+    }
+}
+
+impl Resource for UserImpersonation {
+    const KIND: &'static str = "user-impersonation";
+    // The ID is the one of the compatibility session, so that the self link
+    // points to it
+    const PATH: &'static str = "/api/admin/v1/compat-sessions";
+
+    fn id(&self) -> Ulid {
+        self.id
+    }
+}
+
+impl UserImpersonation {
+    /// Samples of user impersonations
+    pub fn samples() -> [Self; 1] {
+        [Self {
+            id: Ulid::from_bytes([0x01; 16]),
+            user_id: Ulid::from_bytes([0x02; 16]),
+            device_id: "AABBCCDDEE".to_owned().into(),
+            access_token: "mct_hjIqIBf8cLkBuqRYNMrQZKpEmvIz5q_ChpAJ0".to_owned(),
+            created_at: DateTime::default(),
+            expires_at: DateTime::default() + chrono::Duration::hours(1),
+        }]
+    }
+}
+
+/// An OAuth 2.0 client
+#[derive(Serialize, JsonSchema)]
+pub struct OAuth2Client {
+    #[serde(skip)]
+    id: Ulid,
+
+    /// The client ID used in OAuth 2.0 requests
+    client_id: String,
+
+    /// The human-readable name of the client, if any
+    client_name: Option<String>,
+
+    /// Whether the client has a client secret
+    has_client_secret: bool,
+
+    /// When the client secret stops being accepted. If null, it doesn't
+    /// expire.
+    client_secret_expires_at: Option<DateTime<Utc>>,
+
+    /// Whether the client has a second secret, accepted alongside the current
+    /// one during a rotation
+    has_next_client_secret: bool,
+
+    /// When the next client secret stops being accepted. If null, it doesn't
+    /// expire.
+    next_client_secret_expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<mas_data_model::Client> for OAuth2Client {
+    fn from(client: mas_data_model::Client) -> Self {
+        Self {
+            id: client.id,
+            client_id: client.client_id,
+            client_name: client.client_name,
+            has_client_secret: client.encrypted_client_secret.is_some(),
+            client_secret_expires_at: client.client_secret_expires_at,
+            has_next_client_secret: client.encrypted_next_client_secret.is_some(),
+            next_client_secret_expires_at: client.next_client_secret_expires_at,
+        }
+    }
+}
+
+impl OAuth2Client {
+    /// Samples of OAuth 2.0 clients
+    pub fn samples() -> [Self; 2] {
+        [
+            Self {
+                id: Ulid::from_bytes([0x01; 16]),
+                client_id: "01040G2081040G2081040G2081".to_owned(),
+                client_name: Some("Backend service".to_owned()),
+                has_client_secret: true,
+                client_secret_expires_at: None,
+                has_next_client_secret: false,
+                next_client_secret_expires_at: None,
+            },
+            Self {
+                id: Ulid::from_bytes([0x02; 16]),
+                client_id: "02081040G2081040G2081040G2".to_owned(),
+                client_name: Some("Backend service".to_owned()),
+                has_client_secret: true,
+                client_secret_expires_at: Some(DateTime::default()),
+                has_next_client_secret: true,
+                next_client_secret_expires_at: None,
             },
         ]
     }
 }
 
+impl Resource for OAuth2Client {
+    const KIND: &'static str = "oauth2-client";
+    const PATH: &'static str = "/api/admin/v1/oauth2-clients";
+
+    fn id(&self) -> Ulid {
+        self.id
+    }
+}
+
 /// A OAuth 2.0 session
 #[derive(Serialize, JsonSchema)]
 pub struct OAuth2Session {
@@ -316,8 +761,24 @@ pub struct OAuth2Session {
     /// The last IP address used by the session
     last_active_ip: Option<IpAddr>,
 
+    /// The ISO 3166-1 code of the country the last IP address is located in,
+    /// if known
+    last_active_country: Option<String>,
+
+    /// The number of the autonomous system the last IP address belongs to, if
+    /// known
+    last_active_asn: Option<u32>,
+
+    /// The name of the organization operating the autonomous system the last
+    /// IP address belongs to, if known
+    last_active_as_organization: Option<String>,
+
     /// The user-provided name, if any
     human_name: Option<String>,
+
+    /// The hashed device identifier provided by the client when starting this
+    /// session, if any
+    device_fingerprint: Option<String>,
 }
 
 impl From<mas_data_model::Session> for OAuth2Session {
@@ -333,7 +794,11 @@ impl From<mas_data_model::Session> for OAuth2Session {
             user_agent: session.user_agent,
             last_active_at: session.last_active_at,
             last_active_ip: session.last_active_ip,
+            last_active_country: session.last_active_location.country,
+            last_active_asn: session.last_active_location.asn,
+            last_active_as_organization: session.last_active_location.as_organization,
             human_name: session.human_name,
+            device_fingerprint: session.device_fingerprint,
         }
     }
 }
@@ -353,7 +818,11 @@ impl OAuth2Session {
                 user_agent: Some("Mozilla/5.0".to_owned()),
                 last_active_at: Some(DateTime::default()),
                 last_active_ip: Some("127.0.0.1".parse().unwrap()),
+                last_active_country: Some("FR".to_owned()),
+                last_active_asn: Some(3215),
+                last_active_as_organization: Some("Orange".to_owned()),
                 human_name: Some("Laptop".to_owned()),
+                device_fingerprint: Some("c2VjcmV0LWluc3RhbGwtaWQ".to_owned()),
             },
             Self {
                 id: Ulid::from_bytes([0x02; 16]),
@@ -366,7 +835,11 @@ impl OAuth2Session {
                 user_agent: None,
                 last_active_at: None,
                 last_active_ip: None,
+                last_active_country: None,
+                last_active_asn: None,
+                last_active_as_organization: None,
                 human_name: None,
+                device_fingerprint: None,
             },
             Self {
                 id: Ulid::from_bytes([0x03; 16]),
@@ -379,7 +852,11 @@ impl OAuth2Session {
                 user_agent: Some("Mozilla/5.0".to_owned()),
                 last_active_at: Some(DateTime::default()),
                 last_active_ip: Some("127.0.0.1".parse().unwrap()),
+                last_active_country: Some("FR".to_owned()),
+                last_active_asn: Some(3215),
+                last_active_as_organization: Some("Orange".to_owned()),
                 human_name: None,
+                device_fingerprint: None,
             },
         ]
     }
@@ -418,6 +895,18 @@ pub struct UserSession {
 
     /// The last IP address used by the session
     last_active_ip: Option<IpAddr>,
+
+    /// The ISO 3166-1 code of the country the last IP address is located in,
+    /// if known
+    last_active_country: Option<String>,
+
+    /// The number of the autonomous system the last IP address belongs to, if
+    /// known
+    last_active_asn: Option<u32>,
+
+    /// The name of the organization operating the autonomous system the last
+    /// IP address belongs to, if known
+    last_active_as_organization: Option<String>,
 }
 
 impl From<mas_data_model::BrowserSession> for UserSession {
@@ -430,6 +919,9 @@ impl From<mas_data_model::BrowserSession> for UserSession {
             user_agent: value.user_agent,
             last_active_at: value.last_active_at,
             last_active_ip: value.last_active_ip,
+            last_active_country: value.last_active_location.country,
+            last_active_asn: value.last_active_location.asn,
+            last_active_as_organization: value.last_active_location.as_organization,
         }
     }
 }
@@ -446,6 +938,9 @@ impl UserSession {
                 user_agent: Some("Mozilla/5.0".to_owned()),
                 last_active_at: Some(DateTime::default()),
                 last_active_ip: Some("127.0.0.1".parse().unwrap()),
+                last_active_country: Some("FR".to_owned()),
+                last_active_asn: Some(3215),
+                last_active_as_organization: Some("Orange".to_owned()),
             },
             Self {
                 id: Ulid::from_bytes([0x02; 16]),
@@ -455,6 +950,9 @@ impl UserSession {
                 user_agent: None,
                 last_active_at: None,
                 last_active_ip: None,
+                last_active_country: None,
+                last_active_asn: None,
+                last_active_as_organization: None,
             },
             Self {
                 id: Ulid::from_bytes([0x03; 16]),
@@ -464,6 +962,9 @@ impl UserSession {
                 user_agent: Some("Mozilla/5.0".to_owned()),
                 last_active_at: Some(DateTime::default()),
                 last_active_ip: Some("127.0.0.1".parse().unwrap()),
+                last_active_country: Some("FR".to_owned()),
+                last_active_asn: Some(3215),
+                last_active_as_organization: Some("Orange".to_owned()),
             },
         ]
     }
@@ -632,6 +1133,15 @@ pub struct UserRegistrationToken {
 
     /// When the token was revoked. If null, the token is not revoked.
     revoked_at: Option<DateTime<Utc>>,
+
+    /// The ID of the user who issued this token as an invite. If null, the
+    /// token was created by an administrator.
+    #[schemars(with = "Option<super::schema::Ulid>")]
+    created_by_user_id: Option<Ulid>,
+
+    /// Whether users registering with this token get the issuing user
+    /// recorded as their referrer
+    assign_referrer: bool,
 }
 
 impl UserRegistrationToken {
@@ -646,6 +1156,8 @@ impl UserRegistrationToken {
             last_used_at: token.last_used_at,
             expires_at: token.expires_at,
             revoked_at: token.revoked_at,
+            created_by_user_id: token.created_by_user_id,
+            assign_referrer: token.assign_referrer,
         }
     }
 }
@@ -673,6 +1185,8 @@ impl UserRegistrationToken {
                 last_used_at: Some(DateTime::default()),
                 expires_at: Some(DateTime::default() + chrono::Duration::days(30)),
                 revoked_at: None,
+                created_by_user_id: None,
+                assign_referrer: false,
             },
             Self {
                 id: Ulid::from_bytes([0x02; 16]),
@@ -684,6 +1198,793 @@ impl UserRegistrationToken {
                 last_used_at: None,
                 expires_at: None,
                 revoked_at: Some(DateTime::default()),
+                created_by_user_id: Some(Ulid::from_bytes([0x03; 16])),
+                assign_referrer: true,
+            },
+        ]
+    }
+}
+
+/// A one-time link letting someone set the password of a pre-provisioned
+/// account
+#[derive(Serialize, JsonSchema)]
+pub struct UserClaimLink {
+    #[serde(skip)]
+    id: Ulid,
+
+    /// The ID of the user this link lets claim
+    #[schemars(with = "super::schema::Ulid")]
+    user_id: Ulid,
+
+    /// The link to give to the person claiming the account
+    url: Url,
+
+    /// The email address the link was sent to, if any
+    email: Option<String>,
+
+    /// Whether the link can still be used
+    valid: bool,
+
+    /// The ID of the admin session which created the link
+    #[schemars(with = "Option<super::schema::Ulid>")]
+    created_by_session_id: Option<Ulid>,
+
+    /// When the link was created
+    created_at: DateTime<Utc>,
+
+    /// When the link expires
+    expires_at: DateTime<Utc>,
+
+    /// When the link was used to claim the account. If null, the link was
+    /// not used yet.
+    claimed_at: Option<DateTime<Utc>>,
+
+    /// The IP address from which the account was claimed
+    claimed_ip_address: Option<IpAddr>,
+
+    /// The user agent with which the account was claimed
+    claimed_user_agent: Option<String>,
+
+    /// When the link was revoked. If null, the link is not revoked.
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+impl UserClaimLink {
+    pub fn new(link: mas_data_model::UserClaimLink, url: Url, now: DateTime<Utc>) -> Self {
+        Self {
+            id: link.id,
+            user_id: link.user_id,
+            url,
+            valid: link.is_valid(now),
+            email: link.email,
+            created_by_session_id: link.created_by_oauth2_session_id,
+            created_at: link.created_at,
+            expires_at: link.expires_at,
+            claimed_at: link.claimed_at,
+            claimed_ip_address: link.claimed_ip_address,
+            claimed_user_agent: link.claimed_user_agent,
+            revoked_at: link.revoked_at,
+        }
+    }
+}
+
+impl Resource for UserClaimLink {
+    const KIND: &'static str = "user-claim-link";
+    const PATH: &'static str = "/api/admin/v1/user-claim-links";
+
+    fn id(&self) -> Ulid {
+        self.id
+    }
+}
+
+impl UserClaimLink {
+    /// Samples of account claim links
+    pub fn samples() -> [Self; 2] {
+        [
+            Self {
+                id: Ulid::from_bytes([0x01; 16]),
+                user_id: Ulid::from_bytes([0x02; 16]),
+                url: "https://auth.example.com/claim/kbjXsREyXrKfjwBKdOvCHrslLsM1s8NK"
+                    .parse()
+                    .unwrap(),
+                email: Some("alice@example.com".to_owned()),
+                valid: true,
+                created_by_session_id: Some(Ulid::from_bytes([0x03; 16])),
+                created_at: DateTime::default(),
+                expires_at: DateTime::default() + chrono::Duration::days(7),
+                claimed_at: None,
+                claimed_ip_address: None,
+                claimed_user_agent: None,
+                revoked_at: None,
+            },
+            Self {
+                id: Ulid::from_bytes([0x04; 16]),
+                user_id: Ulid::from_bytes([0x05; 16]),
+                url: "https://auth.example.com/claim/Q7vWWm3ZZl2zLQ6M0J9HfsXOsWo3jZIp"
+                    .parse()
+                    .unwrap(),
+                email: None,
+                valid: false,
+                created_by_session_id: None,
+                created_at: DateTime::default(),
+                expires_at: DateTime::default() + chrono::Duration::days(7),
+                claimed_at: Some(DateTime::default() + chrono::Duration::hours(2)),
+                claimed_ip_address: Some("1.2.3.4".parse().unwrap()),
+                claimed_user_agent: Some("Mozilla/5.0".to_owned()),
+                revoked_at: None,
+            },
+        ]
+    }
+}
+
+/// A request to recover an account, started from the "forgot password" page
+#[derive(Serialize, JsonSchema)]
+pub struct UserRecoverySession {
+    #[serde(skip)]
+    id: Ulid,
+
+    /// The email address the recovery was requested for
+    email: String,
+
+    /// The user agent of the browser which started the recovery
+    user_agent: String,
+
+    /// The IP address of the browser which started the recovery
+    ip_address: Option<IpAddr>,
+
+    /// The locale in which the recovery emails are sent
+    locale: String,
+
+    /// Whether the person told us they also lost their second factor. If the
+    /// request is approved, the second factor is removed once the password is
+    /// reset.
+    lost_second_factor: bool,
+
+    /// Whether an administrator has to approve the request before a recovery
+    /// link is sent
+    requires_approval: bool,
+
+    /// When the recovery was requested
+    created_at: DateTime<Utc>,
+
+    /// When the recovery was completed. If null, the password wasn't reset
+    /// yet.
+    consumed_at: Option<DateTime<Utc>>,
+
+    /// When an administrator approved the request
+    approved_at: Option<DateTime<Utc>>,
+
+    /// When an administrator denied the request
+    denied_at: Option<DateTime<Utc>>,
+}
+
+impl From<mas_data_model::UserRecoverySession> for UserRecoverySession {
+    fn from(session: mas_data_model::UserRecoverySession) -> Self {
+        Self {
+            id: session.id,
+            email: session.email,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            locale: session.locale,
+            lost_second_factor: session.lost_second_factor,
+            requires_approval: session.requires_approval,
+            created_at: session.created_at,
+            consumed_at: session.consumed_at,
+            approved_at: session.approved_at,
+            denied_at: session.denied_at,
+        }
+    }
+}
+
+impl Resource for UserRecoverySession {
+    const KIND: &'static str = "user-recovery-session";
+    const PATH: &'static str = "/api/admin/v1/user-recovery-sessions";
+
+    fn id(&self) -> Ulid {
+        self.id
+    }
+}
+
+impl UserRecoverySession {
+    /// Samples of account recovery sessions
+    pub fn samples() -> [Self; 2] {
+        [
+            Self {
+                id: Ulid::from_bytes([0x01; 16]),
+                email: "alice@example.com".to_owned(),
+                user_agent: "Mozilla/5.0".to_owned(),
+                ip_address: Some("1.2.3.4".parse().unwrap()),
+                locale: "en".to_owned(),
+                lost_second_factor: true,
+                requires_approval: true,
+                created_at: DateTime::default(),
+                consumed_at: None,
+                approved_at: None,
+                denied_at: None,
+            },
+            Self {
+                id: Ulid::from_bytes([0x02; 16]),
+                email: "bob@example.com".to_owned(),
+                user_agent: "Mozilla/5.0".to_owned(),
+                ip_address: None,
+                locale: "fr".to_owned(),
+                lost_second_factor: false,
+                requires_approval: true,
+                created_at: DateTime::default(),
+                consumed_at: Some(DateTime::default() + chrono::Duration::hours(2)),
+                approved_at: Some(DateTime::default() + chrono::Duration::hours(1)),
+                denied_at: None,
+            },
+        ]
+    }
+}
+
+/// The state of a SCIM sync run
+#[derive(Serialize, JsonSchema, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ScimSyncRunState {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// What happened to a user during a SCIM sync run
+#[derive(Serialize, JsonSchema, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ScimSyncAction {
+    Created,
+    Linked,
+    Locked,
+    Unlocked,
+    AdminGranted,
+    AdminRevoked,
+}
+
+impl From<mas_data_model::ScimSyncAction> for ScimSyncAction {
+    fn from(value: mas_data_model::ScimSyncAction) -> Self {
+        match value {
+            mas_data_model::ScimSyncAction::Created => Self::Created,
+            mas_data_model::ScimSyncAction::Linked => Self::Linked,
+            mas_data_model::ScimSyncAction::Locked => Self::Locked,
+            mas_data_model::ScimSyncAction::Unlocked => Self::Unlocked,
+            mas_data_model::ScimSyncAction::AdminGranted => Self::AdminGranted,
+            mas_data_model::ScimSyncAction::AdminRevoked => Self::AdminRevoked,
+        }
+    }
+}
+
+/// A change applied to a user during a SCIM sync run
+#[derive(Serialize, JsonSchema)]
+pub struct ScimSyncChange {
+    /// What happened to the user
+    ///
+    /// * `created`: the user was created from the directory
+    ///
+    /// * `linked`: an existing user with the same username was linked to the
+    ///   directory entry
+    ///
+    /// * `locked`: the user was locked, because it is inactive or missing in
+    ///   the directory
+    ///
+    /// * `unlocked`: the user was unlocked, because it is active again in the
+    ///   directory
+    ///
+    /// * `admin_granted` / `admin_revoked`: the user was allowed or disallowed
+    ///   to request admin privileges
+    action: ScimSyncAction,
+
+    /// The ID of the user
+    #[schemars(with = "super::schema::Ulid")]
+    user_id: Ulid,
+
+    /// The username of the user
+    username: String,
+
+    /// The ID of the user resource in the SCIM directory
+    external_id: String,
+}
+
+impl From<mas_data_model::ScimSyncChange> for ScimSyncChange {
+    fn from(value: mas_data_model::ScimSyncChange) -> Self {
+        Self {
+            action: value.action.into(),
+            user_id: value.user_id,
+            username: value.username,
+            external_id: value.external_id,
+        }
+    }
+}
+
+/// A synchronisation run against the upstream SCIM directory
+#[derive(Serialize, JsonSchema)]
+pub struct ScimSyncRun {
+    #[serde(skip)]
+    id: Ulid,
+
+    /// The state of the run
+    state: ScimSyncRunState,
+
+    /// When the run started
+    started_at: DateTime<Utc>,
+
+    /// When the run finished, if it did
+    finished_at: Option<DateTime<Utc>>,
+
+    /// Why the run failed, if it did
+    error: Option<String>,
+
+    /// The changes applied to users during the run
+    changes: Vec<ScimSyncChange>,
+}
+
+impl From<mas_data_model::ScimSyncRun> for ScimSyncRun {
+    fn from(value: mas_data_model::ScimSyncRun) -> Self {
+        let state = match &value.state {
+            mas_data_model::ScimSyncRunState::Running => ScimSyncRunState::Running,
+            mas_data_model::ScimSyncRunState::Completed { .. } => ScimSyncRunState::Completed,
+            mas_data_model::ScimSyncRunState::Failed { .. } => ScimSyncRunState::Failed,
+        };
+
+        Self {
+            id: value.id,
+            state,
+            started_at: value.started_at,
+            finished_at: value.state.finished_at(),
+            error: value.state.error().map(ToOwned::to_owned),
+            changes: value.changes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl Resource for ScimSyncRun {
+    const KIND: &'static str = "scim-sync-run";
+    const PATH: &'static str = "/api/admin/v1/scim-sync-runs";
+
+    fn id(&self) -> Ulid {
+        self.id
+    }
+}
+
+impl ScimSyncRun {
+    /// Samples of SCIM sync runs
+    pub fn samples() -> [Self; 3] {
+        [
+            Self {
+                id: Ulid::from_bytes([0x01; 16]),
+                state: ScimSyncRunState::Completed,
+                started_at: DateTime::default(),
+                finished_at: Some(DateTime::default()),
+                error: None,
+                changes: vec![ScimSyncChange {
+                    action: ScimSyncAction::Created,
+                    user_id: Ulid::from_bytes([0x02; 16]),
+                    username: "alice".to_owned(),
+                    external_id: "2819c223-7f76-453a-919d-413861904646".to_owned(),
+                }],
+            },
+            Self {
+                id: Ulid::from_bytes([0x02; 16]),
+                state: ScimSyncRunState::Failed,
+                started_at: DateTime::default(),
+                finished_at: Some(DateTime::default()),
+                error: Some("Failed to query the SCIM directory".to_owned()),
+                changes: Vec::new(),
+            },
+            Self {
+                id: Ulid::from_bytes([0x03; 16]),
+                state: ScimSyncRunState::Running,
+                started_at: DateTime::default(),
+                finished_at: None,
+                error: None,
+                changes: Vec::new(),
+            },
+        ]
+    }
+}
+
+/// The number of logins which happened on a given day
+#[derive(Serialize, JsonSchema)]
+pub struct DailyLoginStats {
+    /// The day, in UTC
+    day: NaiveDate,
+
+    /// The number of successful logins on that day
+    successful: u64,
+
+    /// The number of failed or abandoned logins on that day
+    failed: u64,
+}
+
+impl From<mas_data_model::DailyLoginStats> for DailyLoginStats {
+    fn from(stats: mas_data_model::DailyLoginStats) -> Self {
+        Self {
+            day: stats.day,
+            successful: stats.successful,
+            failed: stats.failed,
+        }
+    }
+}
+
+impl DailyLoginStats {
+    /// Samples of daily login statistics
+    fn samples() -> Vec<Self> {
+        vec![
+            Self {
+                day: NaiveDate::from_ymd_opt(2025, 6, 1).unwrap(),
+                successful: 12,
+                failed: 3,
+            },
+            Self {
+                day: NaiveDate::from_ymd_opt(2025, 6, 2).unwrap(),
+                successful: 8,
+                failed: 1,
+            },
+        ]
+    }
+
+    /// Sum the successful and failed logins of a list of days
+    fn totals(days: &[Self]) -> (u64, u64) {
+        days.iter().fold((0, 0), |(successful, failed), day| {
+            (successful + day.successful, failed + day.failed)
+        })
+    }
+}
+
+/// The daily login statistics of an upstream OAuth 2.0 provider
+#[derive(Serialize, JsonSchema)]
+pub struct UpstreamOAuthProviderLoginStats {
+    #[serde(skip)]
+    id: Ulid,
+
+    /// The first day covered by the statistics
+    since: NaiveDate,
+
+    /// The last day covered by the statistics
+    until: NaiveDate,
+
+    /// The number of authorization sessions with the provider which ended up
+    /// logging in a user during the period
+    successful: u64,
+
+    /// The number of authorization sessions with the provider which failed or
+    /// were abandoned during the period
+    failed: u64,
+
+    /// The statistics of each day of the period. Days without any login are
+    /// left out.
+    days: Vec<DailyLoginStats>,
+}
+
+impl UpstreamOAuthProviderLoginStats {
+    /// Create the login statistics resource of an upstream OAuth 2.0 provider
+    pub fn new(
+        provider_id: Ulid,
+        since: NaiveDate,
+        until: NaiveDate,
+        days: Vec<mas_data_model::DailyLoginStats>,
+    ) -> Self {
+        let days: Vec<DailyLoginStats> = days.into_iter().map(Into::into).collect();
+        let (successful, failed) = DailyLoginStats::totals(&days);
+        Self {
+            id: provider_id,
+            since,
+            until,
+            successful,
+            failed,
+            days,
+        }
+    }
+
+    /// Samples of upstream OAuth 2.0 provider login statistics
+    pub fn samples() -> [Self; 1] {
+        let days = DailyLoginStats::samples();
+        let (successful, failed) = DailyLoginStats::totals(&days);
+        [Self {
+            id: Ulid::from_bytes([0x01; 16]),
+            since: NaiveDate::from_ymd_opt(2025, 6, 1).unwrap(),
+            until: NaiveDate::from_ymd_opt(2025, 6, 30).unwrap(),
+            successful,
+            failed,
+            days,
+        }]
+    }
+}
+
+impl Resource for UpstreamOAuthProviderLoginStats {
+    const KIND: &'static str = "upstream-oauth-provider-login-stats";
+    const PATH: &'static str = "/api/admin/v1/upstream-oauth-providers";
+
+    fn id(&self) -> Ulid {
+        self.id
+    }
+
+    fn path(&self) -> String {
+        format!("{}/{}/login-stats", Self::PATH, self.id())
+    }
+}
+
+/// The health of an upstream OAuth 2.0 provider, as found by the last periodic
+/// check of its discovery document and JWKS
+#[derive(Serialize, JsonSchema)]
+pub struct UpstreamOAuthProviderHealth {
+    #[serde(skip)]
+    id: Ulid,
+
+    /// When the provider was last checked. If null, the provider wasn't
+    /// checked yet, and the other fields are meaningless.
+    checked_at: Option<DateTime<Utc>>,
+
+    /// Whether users can be expected to log in successfully with the provider
+    healthy: Option<bool>,
+
+    /// Whether all the endpoints of the provider could be reached
+    reachable: Option<bool>,
+
+    /// Whether the discovery document could be fetched. If null, discovery is
+    /// disabled for the provider.
+    discovery_reachable: Option<bool>,
+
+    /// Whether the JWKS could be fetched. If null, the provider has no JWKS.
+    jwks_reachable: Option<bool>,
+
+    /// When the JWKS was last fetched successfully
+    jwks_fetched_at: Option<DateTime<Utc>>,
+
+    /// Whether the JWKS couldn't be fetched for longer than allowed
+    jwks_stale: bool,
+
+    /// When the TLS certificate served by the provider expires, if known
+    certificate_expires_at: Option<DateTime<Utc>>,
+
+    /// Whether the TLS certificate served by the provider expires soon
+    certificate_expiring: bool,
+
+    /// The error which made the last check fail, if any
+    error: Option<String>,
+}
+
+impl UpstreamOAuthProviderHealth {
+    /// Create the health resource of an upstream OAuth 2.0 provider, from the
+    /// result of its last check, if any
+    pub fn new(provider_id: Ulid, health: Option<ProviderHealth>) -> Self {
+        let Some(health) = health else {
+            return Self {
+                id: provider_id,
+                checked_at: None,
+                healthy: None,
+                reachable: None,
+                discovery_reachable: None,
+                jwks_reachable: None,
+                jwks_fetched_at: None,
+                jwks_stale: false,
+                certificate_expires_at: None,
+                certificate_expiring: false,
+                error: None,
+            };
+        };
+
+        Self {
+            id: provider_id,
+            checked_at: Some(health.checked_at),
+            healthy: Some(health.is_healthy()),
+            reachable: Some(health.reachable()),
+            discovery_reachable: health.discovery_reachable,
+            jwks_reachable: health.jwks_reachable,
+            jwks_fetched_at: health.jwks_fetched_at,
+            jwks_stale: health.jwks_stale,
+            certificate_expires_at: health.certificate_expires_at,
+            certificate_expiring: health.certificate_expiring,
+            error: health.error,
+        }
+    }
+
+    /// Samples of upstream OAuth 2.0 provider health
+    pub fn samples() -> [Self; 2] {
+        [
+            Self {
+                id: Ulid::from_bytes([0x01; 16]),
+                checked_at: Some(DateTime::default()),
+                healthy: Some(true),
+                reachable: Some(true),
+                discovery_reachable: Some(true),
+                jwks_reachable: Some(true),
+                jwks_fetched_at: Some(DateTime::default()),
+                jwks_stale: false,
+                certificate_expires_at: Some(DateTime::default() + chrono::Duration::days(90)),
+                certificate_expiring: false,
+                error: None,
+            },
+            Self {
+                id: Ulid::from_bytes([0x02; 16]),
+                checked_at: Some(DateTime::default()),
+                healthy: Some(false),
+                reachable: Some(false),
+                discovery_reachable: Some(true),
+                jwks_reachable: Some(false),
+                jwks_fetched_at: None,
+                jwks_stale: true,
+                certificate_expires_at: None,
+                certificate_expiring: false,
+                error: Some("HTTP status client error (404 Not Found)".to_owned()),
+            },
+        ]
+    }
+}
+
+impl Resource for UpstreamOAuthProviderHealth {
+    const KIND: &'static str = "upstream-oauth-provider-health";
+    const PATH: &'static str = "/api/admin/v1/upstream-oauth-providers";
+
+    fn id(&self) -> Ulid {
+        self.id
+    }
+
+    fn path(&self) -> String {
+        format!("{}/{}/health", Self::PATH, self.id())
+    }
+}
+
+/// The daily login statistics of an OAuth 2.0 client
+#[derive(Serialize, JsonSchema)]
+pub struct OAuth2ClientLoginStats {
+    #[serde(skip)]
+    id: Ulid,
+
+    /// The first day covered by the statistics
+    since: NaiveDate,
+
+    /// The last day covered by the statistics
+    until: NaiveDate,
+
+    /// The number of authorization and device code grants of the client which
+    /// were fulfilled by a user during the period
+    successful: u64,
+
+    /// The number of authorization and device code grants of the client which
+    /// were rejected or never fulfilled during the period
+    failed: u64,
+
+    /// The statistics of each day of the period. Days without any login are
+    /// left out.
+    days: Vec<DailyLoginStats>,
+}
+
+impl OAuth2ClientLoginStats {
+    /// Create the login statistics resource of an OAuth 2.0 client
+    pub fn new(
+        client_id: Ulid,
+        since: NaiveDate,
+        until: NaiveDate,
+        days: Vec<mas_data_model::DailyLoginStats>,
+    ) -> Self {
+        let days: Vec<DailyLoginStats> = days.into_iter().map(Into::into).collect();
+        let (successful, failed) = DailyLoginStats::totals(&days);
+        Self {
+            id: client_id,
+            since,
+            until,
+            successful,
+            failed,
+            days,
+        }
+    }
+
+    /// Samples of OAuth 2.0 client login statistics
+    pub fn samples() -> [Self; 1] {
+        let days = DailyLoginStats::samples();
+        let (successful, failed) = DailyLoginStats::totals(&days);
+        [Self {
+            id: Ulid::from_bytes([0x01; 16]),
+            since: NaiveDate::from_ymd_opt(2025, 6, 1).unwrap(),
+            until: NaiveDate::from_ymd_opt(2025, 6, 30).unwrap(),
+            successful,
+            failed,
+            days,
+        }]
+    }
+}
+
+impl Resource for OAuth2ClientLoginStats {
+    const KIND: &'static str = "oauth2-client-login-stats";
+    const PATH: &'static str = "/api/admin/v1/oauth2-clients";
+
+    fn id(&self) -> Ulid {
+        self.id
+    }
+
+    fn path(&self) -> String {
+        format!("{}/{}/login-stats", Self::PATH, self.id())
+    }
+}
+
+/// A feature which can be rolled out gradually, or turned off
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureFlag {
+    PasswordGrant,
+    AccountClaim,
+}
+
+impl From<mas_data_model::FeatureFlag> for FeatureFlag {
+    fn from(value: mas_data_model::FeatureFlag) -> Self {
+        match value {
+            mas_data_model::FeatureFlag::PasswordGrant => Self::PasswordGrant,
+            mas_data_model::FeatureFlag::AccountClaim => Self::AccountClaim,
+        }
+    }
+}
+
+impl From<FeatureFlag> for mas_data_model::FeatureFlag {
+    fn from(value: FeatureFlag) -> Self {
+        match value {
+            FeatureFlag::PasswordGrant => Self::PasswordGrant,
+            FeatureFlag::AccountClaim => Self::AccountClaim,
+        }
+    }
+}
+
+/// A rollout of a feature, overriding the one from the configuration
+#[derive(Serialize, JsonSchema)]
+pub struct FeatureFlagOverride {
+    #[serde(skip)]
+    id: Ulid,
+
+    /// The feature which is overridden
+    ///
+    /// * `password_grant`: the password grant, for the clients allowed to use
+    ///   it
+    ///
+    /// * `account_claim`: claiming pre-provisioned accounts through claim links
+    flag: FeatureFlag,
+
+    /// Whether the feature is enabled at all
+    enabled: bool,
+
+    /// The percentage of users for which the feature is enabled
+    rollout_percentage: u8,
+
+    /// When the override was last changed
+    updated_at: DateTime<Utc>,
+}
+
+impl From<mas_data_model::FeatureFlagOverride> for FeatureFlagOverride {
+    fn from(value: mas_data_model::FeatureFlagOverride) -> Self {
+        Self {
+            id: value.id,
+            flag: value.flag.into(),
+            enabled: value.rollout.enabled,
+            rollout_percentage: value.rollout.percentage,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+impl Resource for FeatureFlagOverride {
+    const KIND: &'static str = "feature-flag-override";
+    const PATH: &'static str = "/api/admin/v1/feature-flag-overrides";
+
+    fn id(&self) -> Ulid {
+        self.id
+    }
+}
+
+impl FeatureFlagOverride {
+    /// Samples of feature flag overrides
+    pub fn samples() -> [Self; 2] {
+        [
+            Self {
+                id: Ulid::from_bytes([0x01; 16]),
+                flag: FeatureFlag::PasswordGrant,
+                enabled: true,
+                rollout_percentage: 10,
+                updated_at: DateTime::default(),
+            },
+            Self {
+                id: Ulid::from_bytes([0x02; 16]),
+                flag: FeatureFlag::AccountClaim,
+                enabled: false,
+                rollout_percentage: 100,
+                updated_at: DateTime::default(),
             },
         ]
     }
@@ -138,3 +138,47 @@ impl<S: Send + Sync> FromRequestParts<S> for Pagination {
         }))
     }
 }
+
+#[derive(Deserialize, JsonSchema, Clone, Copy)]
+struct CountParams {
+    /// Return an estimate of the total number of items instead of an exact
+    /// count
+    ///
+    /// Counting the items can be slow on large deployments. The estimate is
+    /// based on the database statistics and can be off, but small counts are
+    /// still exact. Defaults to `false`.
+    #[serde(default)]
+    estimate: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid count parameters")]
+pub struct CountRejection(#[from] QueryRejection);
+
+impl IntoResponse for CountRejection {
+    fn into_response(self) -> axum::response::Response {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::from_error(&self)),
+        )
+            .into_response()
+    }
+}
+
+/// An extractor for the `estimate` parameter in the query string, telling
+/// whether the total count of items can be estimated
+#[derive(OperationIo, Debug, Clone, Copy)]
+#[aide(input_with = "Query<CountParams>")]
+pub struct EstimateCount(pub bool);
+
+impl<S: Send + Sync> FromRequestParts<S> for EstimateCount {
+    type Rejection = CountRejection;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let params = Query::<CountParams>::from_request_parts(parts, state).await?;
+        Ok(Self(params.estimate))
+    }
+}
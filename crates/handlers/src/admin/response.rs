@@ -43,6 +43,12 @@ struct PaginationLinks {
 struct PaginationMeta {
     /// The total number of results
     count: usize,
+
+    /// Whether the total number of results is an estimate
+    ///
+    /// Only present if an estimate was requested
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    estimated: bool,
 }
 
 /// A top-level response with a page of resources
@@ -120,11 +126,33 @@ impl<T: Resource> PaginatedResponse<T> {
         let data = page.edges.into_iter().map(SingleResource::new).collect();
 
         Self {
-            meta: PaginationMeta { count },
+            meta: PaginationMeta {
+                count,
+                estimated: false,
+            },
             data,
             links,
         }
     }
+
+    /// Mark the total number of results as an estimate, and keep asking for
+    /// an estimate in the links to the other pages
+    #[must_use]
+    pub fn with_estimated_count(mut self) -> Self {
+        self.meta.estimated = true;
+
+        let links = &mut self.links;
+        for link in [&mut links.self_, &mut links.first, &mut links.last]
+            .into_iter()
+            .chain(links.next.as_mut())
+            .chain(links.prev.as_mut())
+        {
+            // Links always have a query string, as they have pagination parameters
+            link.push_str("&estimate=true");
+        }
+
+        self
+    }
 }
 
 /// A single resource, with its type, ID, attributes and related links
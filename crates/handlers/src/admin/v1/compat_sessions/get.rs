@@ -107,7 +107,16 @@ mod tests {
         let device = Device::generate(&mut rng);
         let session = repo
             .compat_session()
-            .add(&mut rng, &state.clock, &user, device, None, false, None)
+            .add(
+                &mut rng,
+                &state.clock,
+                &user,
+                device,
+                None,
+                false,
+                None,
+                None,
+            )
             .await
             .unwrap();
         repo.save().await.unwrap();
@@ -133,6 +142,9 @@ mod tests {
               "user_agent": null,
               "last_active_at": null,
               "last_active_ip": null,
+              "last_active_country": null,
+              "last_active_asn": null,
+              "last_active_as_organization": null,
               "finished_at": null,
               "human_name": null
             },
@@ -10,6 +10,7 @@ use axum::{
     response::IntoResponse,
 };
 use axum_macros::FromRequestParts;
+use chrono::{DateTime, SecondsFormat, Utc};
 use hyper::StatusCode;
 use mas_axum_utils::record_error;
 use mas_storage::{Page, compat::CompatSessionFilter};
@@ -21,7 +22,7 @@ use crate::{
     admin::{
         call_context::CallContext,
         model::{CompatSession, Resource},
-        params::Pagination,
+        params::{EstimateCount, Pagination},
         response::{ErrorResponse, PaginatedResponse},
     },
     impl_from_error_for_route,
@@ -67,6 +68,23 @@ pub struct FilterParams {
     /// * `finished`: Only retrieve finished sessions
     #[serde(rename = "filter[status]")]
     status: Option<CompatSessionStatus>,
+
+    /// Retrieve the items whose device ID starts with the given prefix
+    #[serde(rename = "filter[device-id-prefix]")]
+    device_id_prefix: Option<String>,
+
+    /// Retrieve the items which were (or were not) started through an SSO
+    /// login
+    #[serde(rename = "filter[sso-login]")]
+    sso_login: Option<bool>,
+
+    /// Retrieve the items which were last active after the given time
+    #[serde(rename = "filter[last-active-after]")]
+    last_active_after: Option<DateTime<Utc>>,
+
+    /// Retrieve the items which were last active before the given time
+    #[serde(rename = "filter[last-active-before]")]
+    last_active_before: Option<DateTime<Utc>>,
 }
 
 impl std::fmt::Display for FilterParams {
@@ -88,6 +106,29 @@ impl std::fmt::Display for FilterParams {
             sep = '&';
         }
 
+        if let Some(device_id_prefix) = &self.device_id_prefix {
+            write!(f, "{sep}filter[device-id-prefix]={device_id_prefix}")?;
+            sep = '&';
+        }
+
+        if let Some(sso_login) = self.sso_login {
+            write!(f, "{sep}filter[sso-login]={sso_login}")?;
+            sep = '&';
+        }
+
+        if let Some(last_active_after) = self.last_active_after {
+            let last_active_after = last_active_after.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+            write!(f, "{sep}filter[last-active-after]={last_active_after}")?;
+            sep = '&';
+        }
+
+        if let Some(last_active_before) = self.last_active_before {
+            let last_active_before =
+                last_active_before.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+            write!(f, "{sep}filter[last-active-before]={last_active_before}")?;
+            sep = '&';
+        }
+
         let _ = sep;
         Ok(())
     }
@@ -160,6 +201,7 @@ Use the `filter[status]` parameter to filter the sessions by their status and `p
 pub async fn handler(
     CallContext { mut repo, .. }: CallContext,
     Pagination(pagination): Pagination,
+    EstimateCount(estimate): EstimateCount,
     params: FilterParams,
 ) -> Result<Json<PaginatedResponse<CompatSession>>, RouteError> {
     let base = format!("{path}{params}", path = CompatSession::PATH);
@@ -206,15 +248,41 @@ pub async fn handler(
         None => filter,
     };
 
+    let filter = match params.device_id_prefix.as_deref() {
+        Some(device_id_prefix) => filter.with_device_prefix(device_id_prefix),
+        None => filter,
+    };
+
+    let filter = match params.sso_login {
+        Some(true) => filter.sso_login_only(),
+        Some(false) => filter.unknown_only(),
+        None => filter,
+    };
+
+    let filter = match params.last_active_after {
+        Some(last_active_after) => filter.with_last_active_after(last_active_after),
+        None => filter,
+    };
+
+    let filter = match params.last_active_before {
+        Some(last_active_before) => filter.with_last_active_before(last_active_before),
+        None => filter,
+    };
+
     let page = repo.compat_session().list(filter, pagination).await?;
-    let count = repo.compat_session().count(filter).await?;
-
-    Ok(Json(PaginatedResponse::new(
-        page.map(CompatSession::from),
-        pagination,
-        count,
-        &base,
-    )))
+    let count = if estimate {
+        repo.compat_session().estimate_count(filter).await?
+    } else {
+        repo.compat_session().count(filter).await?
+    };
+
+    let response = PaginatedResponse::new(page.map(CompatSession::from), pagination, count, &base);
+
+    if estimate {
+        Ok(Json(response.with_estimated_count()))
+    } else {
+        Ok(Json(response))
+    }
 }
 
 #[cfg(test)]
@@ -222,7 +290,8 @@ mod tests {
     use chrono::Duration;
     use hyper::{Request, StatusCode};
     use insta::assert_json_snapshot;
-    use mas_data_model::Device;
+    use mas_data_model::{Device, IpLocation};
+    use mas_storage::Clock;
     use sqlx::PgPool;
 
     use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
@@ -251,7 +320,16 @@ mod tests {
 
         let device = Device::generate(&mut rng);
         repo.compat_session()
-            .add(&mut rng, &state.clock, &alice, device, None, false, None)
+            .add(
+                &mut rng,
+                &state.clock,
+                &alice,
+                device,
+                None,
+                false,
+                None,
+                None,
+            )
             .await
             .unwrap();
         let device = Device::generate(&mut rng);
@@ -260,7 +338,16 @@ mod tests {
 
         let session = repo
             .compat_session()
-            .add(&mut rng, &state.clock, &bob, device, None, false, None)
+            .add(
+                &mut rng,
+                &state.clock,
+                &bob,
+                device,
+                None,
+                false,
+                None,
+                None,
+            )
             .await
             .unwrap();
         state.clock.advance(Duration::minutes(1));
@@ -294,6 +381,9 @@ mod tests {
                 "user_agent": null,
                 "last_active_at": null,
                 "last_active_ip": null,
+                "last_active_country": null,
+                "last_active_asn": null,
+                "last_active_as_organization": null,
                 "finished_at": null,
                 "human_name": null
               },
@@ -313,6 +403,9 @@ mod tests {
                 "user_agent": null,
                 "last_active_at": null,
                 "last_active_ip": null,
+                "last_active_country": null,
+                "last_active_asn": null,
+                "last_active_as_organization": null,
                 "finished_at": "2022-01-16T14:43:00Z",
                 "human_name": null
               },
@@ -357,6 +450,9 @@ mod tests {
                 "user_agent": null,
                 "last_active_at": null,
                 "last_active_ip": null,
+                "last_active_country": null,
+                "last_active_asn": null,
+                "last_active_as_organization": null,
                 "finished_at": null,
                 "human_name": null
               },
@@ -398,6 +494,9 @@ mod tests {
                 "user_agent": null,
                 "last_active_at": null,
                 "last_active_ip": null,
+                "last_active_country": null,
+                "last_active_asn": null,
+                "last_active_as_organization": null,
                 "finished_at": null,
                 "human_name": null
               },
@@ -439,6 +538,9 @@ mod tests {
                 "user_agent": null,
                 "last_active_at": null,
                 "last_active_ip": null,
+                "last_active_country": null,
+                "last_active_asn": null,
+                "last_active_as_organization": null,
                 "finished_at": "2022-01-16T14:43:00Z",
                 "human_name": null
               },
@@ -454,5 +556,80 @@ mod tests {
           }
         }
         "#);
+
+        // Filter by device ID prefix
+        let request = Request::get("/api/admin/v1/compat-sessions?filter[device-id-prefix]=Loie")
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["meta"]["count"], 1);
+        assert_eq!(body["data"][0]["id"], "01FSHNB530AAPR7PEV8KNBZD5Y");
+        assert_eq!(
+            body["links"]["self"],
+            "/api/admin/v1/compat-sessions?filter[device-id-prefix]=Loie&page[first]=10"
+        );
+
+        // None of the sessions were started through an SSO login
+        let request = Request::get("/api/admin/v1/compat-sessions?filter[sso-login]=true")
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["meta"]["count"], 0);
+
+        let request = Request::get("/api/admin/v1/compat-sessions?filter[sso-login]=false")
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["meta"]["count"], 2);
+
+        // Record some activity on the first session, and filter by activity
+        let mut repo = state.repository().await.unwrap();
+        let session = repo
+            .compat_session()
+            .lookup("01FSHNB530AAPR7PEV8KNBZD5Y".parse().unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        repo.compat_session()
+            .record_batch_activity(vec![(
+                session.id,
+                state.clock.now(),
+                None,
+                IpLocation::default(),
+            )])
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::get(
+            "/api/admin/v1/compat-sessions?filter[last-active-after]=2022-01-16T14:42:30Z",
+        )
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["meta"]["count"], 1);
+        assert_eq!(body["data"][0]["id"], "01FSHNB530AAPR7PEV8KNBZD5Y");
+        assert_eq!(
+            body["links"]["self"],
+            "/api/admin/v1/compat-sessions?filter[last-active-after]=2022-01-16T14:42:30Z&page[first]=10"
+        );
+
+        let request = Request::get(
+            "/api/admin/v1/compat-sessions?filter[last-active-before]=2022-01-16T14:42:30Z",
+        )
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["meta"]["count"], 0);
     }
 }
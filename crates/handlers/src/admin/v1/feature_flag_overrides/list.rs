@@ -0,0 +1,175 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{Json, response::IntoResponse};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_storage::Page;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::{FeatureFlagOverride, Resource},
+        params::Pagination,
+        response::{ErrorResponse, PaginatedResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("listFeatureFlagOverrides")
+        .summary("List feature flag overrides")
+        .description(
+            "Retrieve the features whose rollout was overridden through the API.
+Features which are not listed use the rollout from the configuration.",
+        )
+        .tag("feature-flag-override")
+        .response_with::<200, Json<PaginatedResponse<FeatureFlagOverride>>, _>(|t| {
+            let overrides = FeatureFlagOverride::samples();
+            let pagination = mas_storage::Pagination::first(overrides.len());
+            let page = Page {
+                edges: overrides.into(),
+                has_next_page: false,
+                has_previous_page: false,
+            };
+
+            t.description("Paginated response of feature flag overrides")
+                .example(PaginatedResponse::new(
+                    page,
+                    pagination,
+                    2,
+                    FeatureFlagOverride::PATH,
+                ))
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.feature_flag_overrides.list", skip_all)]
+pub async fn handler(
+    CallContext { mut repo, .. }: CallContext,
+    Pagination(pagination): Pagination,
+) -> Result<Json<PaginatedResponse<FeatureFlagOverride>>, RouteError> {
+    let page = repo.feature_flag_override().list(pagination).await?;
+    let count = repo.feature_flag_override().count().await?;
+
+    Ok(Json(PaginatedResponse::new(
+        page.map(FeatureFlagOverride::from),
+        pagination,
+        count,
+        FeatureFlagOverride::PATH,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use insta::assert_json_snapshot;
+    use mas_data_model::{FeatureFlag, FeatureFlagRollout};
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_list(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        repo.feature_flag_override()
+            .set(
+                &mut rng,
+                &state.clock,
+                FeatureFlag::PasswordGrant,
+                FeatureFlagRollout {
+                    enabled: true,
+                    percentage: 10,
+                },
+            )
+            .await
+            .unwrap();
+        state.clock.advance(Duration::minutes(1));
+        repo.feature_flag_override()
+            .set(
+                &mut rng,
+                &state.clock,
+                FeatureFlag::AccountClaim,
+                FeatureFlagRollout::DISABLED,
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::get("/api/admin/v1/feature-flag-overrides")
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r###"
+        {
+          "meta": {
+            "count": 2
+          },
+          "data": [
+            {
+              "type": "feature-flag-override",
+              "id": "01FSHN9AG0MZAA6S4AF7CTV32E",
+              "attributes": {
+                "flag": "password_grant",
+                "enabled": true,
+                "rollout_percentage": 10,
+                "updated_at": "2022-01-16T14:40:00Z"
+              },
+              "links": {
+                "self": "/api/admin/v1/feature-flag-overrides/01FSHN9AG0MZAA6S4AF7CTV32E"
+              }
+            },
+            {
+              "type": "feature-flag-override",
+              "id": "01FSHNB530AJ6AC5HQ9X6H4RP4",
+              "attributes": {
+                "flag": "account_claim",
+                "enabled": false,
+                "rollout_percentage": 0,
+                "updated_at": "2022-01-16T14:41:00Z"
+              },
+              "links": {
+                "self": "/api/admin/v1/feature-flag-overrides/01FSHNB530AJ6AC5HQ9X6H4RP4"
+              }
+            }
+          ],
+          "links": {
+            "self": "/api/admin/v1/feature-flag-overrides?page[first]=10",
+            "first": "/api/admin/v1/feature-flag-overrides?page[first]=10",
+            "last": "/api/admin/v1/feature-flag-overrides?page[last]=10"
+          }
+        }
+        "###);
+    }
+}
@@ -0,0 +1,16 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+mod delete;
+mod get;
+mod list;
+mod set;
+
+pub use self::{
+    delete::{doc as delete_doc, handler as delete},
+    get::{doc as get_doc, handler as get},
+    list::{doc as list_doc, handler as list},
+    set::{doc as set_doc, handler as set},
+};
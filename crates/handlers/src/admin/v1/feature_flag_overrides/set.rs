@@ -0,0 +1,226 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{NoApi, OperationIo, transform::TransformOperation};
+use axum::{Json, response::IntoResponse};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_data_model::FeatureFlagRollout;
+use mas_storage::BoxRng;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::{FeatureFlag, FeatureFlagOverride},
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("Rollout percentage {0} is not between 0 and 100")]
+    InvalidRolloutPercentage(u8),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::InvalidRolloutPercentage(_) => StatusCode::BAD_REQUEST,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_rollout_percentage() -> u8 {
+    100
+}
+
+/// # JSON payload for the `POST /api/admin/v1/feature-flag-overrides`
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename = "SetFeatureFlagOverrideRequest")]
+pub struct Request {
+    /// The feature to override
+    flag: FeatureFlag,
+
+    /// Whether the feature is enabled at all. Defaults to `true`.
+    #[serde(default = "default_true")]
+    enabled: bool,
+
+    /// The percentage of users for which the feature is enabled, from 0 to
+    /// 100. Defaults to 100.
+    #[serde(default = "default_rollout_percentage")]
+    #[schemars(range(min = 0, max = 100))]
+    rollout_percentage: u8,
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("setFeatureFlagOverride")
+        .summary("Override the rollout of a feature")
+        .description(
+            "Set the rollout of a feature, replacing the one from the configuration.
+If the feature was already overridden, the existing override is updated and keeps its ID.
+The change applies immediately, without restarting the service.",
+        )
+        .tag("feature-flag-override")
+        .response_with::<200, Json<SingleResponse<FeatureFlagOverride>>, _>(|t| {
+            let [sample, ..] = FeatureFlagOverride::samples();
+            let response = SingleResponse::new_canonical(sample);
+            t.description("The feature was overridden")
+                .example(response)
+        })
+        .response_with::<400, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::InvalidRolloutPercentage(101));
+            t.description("The rollout percentage is invalid")
+                .example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.feature_flag_overrides.set", skip_all)]
+pub async fn handler(
+    CallContext {
+        mut repo, clock, ..
+    }: CallContext,
+    NoApi(mut rng): NoApi<BoxRng>,
+    Json(params): Json<Request>,
+) -> Result<Json<SingleResponse<FeatureFlagOverride>>, RouteError> {
+    if params.rollout_percentage > 100 {
+        return Err(RouteError::InvalidRolloutPercentage(
+            params.rollout_percentage,
+        ));
+    }
+
+    let rollout = FeatureFlagRollout {
+        enabled: params.enabled,
+        percentage: params.rollout_percentage,
+    };
+
+    let feature_flag_override = repo
+        .feature_flag_override()
+        .set(&mut rng, &clock, params.flag.into(), rollout)
+        .await?;
+
+    repo.save().await?;
+
+    Ok(Json(SingleResponse::new_canonical(
+        FeatureFlagOverride::from(feature_flag_override),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use insta::assert_json_snapshot;
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_set(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let request = Request::post("/api/admin/v1/feature-flag-overrides")
+            .bearer(&token)
+            .json(serde_json::json!({
+                "flag": "password_grant",
+                "rollout_percentage": 10,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r###"
+        {
+          "data": {
+            "type": "feature-flag-override",
+            "id": "01FSHN9AG0MZAA6S4AF7CTV32E",
+            "attributes": {
+              "flag": "password_grant",
+              "enabled": true,
+              "rollout_percentage": 10,
+              "updated_at": "2022-01-16T14:40:00Z"
+            },
+            "links": {
+              "self": "/api/admin/v1/feature-flag-overrides/01FSHN9AG0MZAA6S4AF7CTV32E"
+            }
+          },
+          "links": {
+            "self": "/api/admin/v1/feature-flag-overrides/01FSHN9AG0MZAA6S4AF7CTV32E"
+          }
+        }
+        "###);
+
+        // Overriding the same feature again updates the existing override
+        state.clock.advance(Duration::minutes(1));
+        let request = Request::post("/api/admin/v1/feature-flag-overrides")
+            .bearer(&token)
+            .json(serde_json::json!({
+                "flag": "password_grant",
+                "enabled": false,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r###"
+        {
+          "data": {
+            "type": "feature-flag-override",
+            "id": "01FSHN9AG0MZAA6S4AF7CTV32E",
+            "attributes": {
+              "flag": "password_grant",
+              "enabled": false,
+              "rollout_percentage": 100,
+              "updated_at": "2022-01-16T14:41:00Z"
+            },
+            "links": {
+              "self": "/api/admin/v1/feature-flag-overrides/01FSHN9AG0MZAA6S4AF7CTV32E"
+            }
+          },
+          "links": {
+            "self": "/api/admin/v1/feature-flag-overrides/01FSHN9AG0MZAA6S4AF7CTV32E"
+          }
+        }
+        "###);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_invalid_percentage(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let request = Request::post("/api/admin/v1/feature-flag-overrides")
+            .bearer(&token)
+            .json(serde_json::json!({
+                "flag": "account_claim",
+                "rollout_percentage": 150,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body["errors"][0]["title"],
+            "Rollout percentage 150 is not between 0 and 100"
+        );
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Daily login statistics, aggregated periodically by the
+//! `refresh-login-stats` job
+
+use aide::OperationIo;
+use axum::{
+    Json,
+    extract::{Query, rejection::QueryRejection},
+    response::IntoResponse,
+};
+use axum_macros::FromRequestParts;
+use chrono::{Duration, NaiveDate};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_storage::Clock;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use ulid::Ulid;
+
+use crate::{admin::response::ErrorResponse, impl_from_error_for_route};
+
+mod oauth2_client;
+mod upstream_oauth_provider;
+
+pub use self::{
+    oauth2_client::{doc as oauth2_client_doc, handler as oauth2_client},
+    upstream_oauth_provider::{
+        doc as upstream_oauth_provider_doc, handler as upstream_oauth_provider,
+    },
+};
+
+/// The maximum number of days which can be requested at once
+const MAX_DAYS: i64 = 366;
+
+/// The number of days covered by default
+const DEFAULT_DAYS: i64 = 30;
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("Upstream OAuth 2.0 Provider ID {0} not found")]
+    ProviderNotFound(Ulid),
+
+    #[error("Client ID {0} not found")]
+    ClientNotFound(Ulid),
+
+    #[error("Invalid query parameters")]
+    InvalidParams(#[from] QueryRejection),
+
+    #[error("The `since` day must not be after the `until` day")]
+    InvalidRange,
+
+    #[error("Can't request more than {MAX_DAYS} days at once")]
+    RangeTooLong,
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::ProviderNotFound(_) | Self::ClientNotFound(_) => StatusCode::NOT_FOUND,
+            Self::InvalidParams(_) | Self::InvalidRange | Self::RangeTooLong => {
+                StatusCode::BAD_REQUEST
+            }
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+#[derive(FromRequestParts, Deserialize, JsonSchema, OperationIo)]
+#[serde(rename = "LoginStatsParams")]
+#[aide(input_with = "Query<DateRangeParams>")]
+#[from_request(via(Query), rejection(RouteError))]
+pub struct DateRangeParams {
+    /// The first day to include, in UTC
+    ///
+    /// Defaults to covering the 30 days up to the `until` day.
+    since: Option<NaiveDate>,
+
+    /// The last day to include, in UTC
+    ///
+    /// Defaults to the current day.
+    until: Option<NaiveDate>,
+}
+
+impl DateRangeParams {
+    /// Resolve the requested period, checking that it is valid
+    fn resolve(&self, clock: &dyn Clock) -> Result<(NaiveDate, NaiveDate), RouteError> {
+        let until = self.until.unwrap_or_else(|| clock.now().date_naive());
+        let since = self
+            .since
+            .unwrap_or_else(|| until - Duration::days(DEFAULT_DAYS - 1));
+
+        if since > until {
+            return Err(RouteError::InvalidRange);
+        }
+
+        if (until - since).num_days() >= MAX_DAYS {
+            return Err(RouteError::RangeTooLong);
+        }
+
+        Ok((since, until))
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::transform::TransformOperation;
+use axum::Json;
+use ulid::Ulid;
+
+use super::{DateRangeParams, RouteError};
+use crate::admin::{
+    call_context::CallContext,
+    model::OAuth2ClientLoginStats,
+    params::UlidPathParam,
+    response::{ErrorResponse, SingleResponse},
+};
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("getOAuth2ClientLoginStats")
+        .summary("Get the daily login statistics of an OAuth 2.0 client")
+        .description("Retrieve the number of successful and failed logins requested by an OAuth 2.0 client through the authorization code and device code grants, for each day of the requested period.
+Statistics are aggregated periodically in the background, so the most recent logins may not be accounted for yet.")
+        .tag("login-stats")
+        .response_with::<200, Json<SingleResponse<OAuth2ClientLoginStats>>, _>(|t| {
+            let [sample] = OAuth2ClientLoginStats::samples();
+            let response = SingleResponse::new_canonical(sample);
+            t.description("Login statistics of the client").example(response)
+        })
+        .response_with::<400, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::InvalidRange);
+            t.description("The requested period is invalid")
+                .example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::ClientNotFound(Ulid::nil()));
+            t.description("Client was not found").example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.login_stats.oauth2_client", skip_all)]
+pub async fn handler(
+    CallContext {
+        mut repo, clock, ..
+    }: CallContext,
+    id: UlidPathParam,
+    params: DateRangeParams,
+) -> Result<Json<SingleResponse<OAuth2ClientLoginStats>>, RouteError> {
+    let (since, until) = params.resolve(&clock)?;
+
+    let client = repo
+        .oauth2_client()
+        .lookup(*id)
+        .await?
+        .ok_or(RouteError::ClientNotFound(*id))?;
+
+    let days = repo
+        .login_stats()
+        .for_oauth2_client(&client, since, until)
+        .await?;
+
+    Ok(Json(SingleResponse::new_canonical(
+        OAuth2ClientLoginStats::new(client.id, since, until, days),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use sqlx::PgPool;
+    use ulid::Ulid;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_not_found(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let request = Request::get(format!(
+            "/api/admin/v1/oauth2-clients/{}/login-stats",
+            Ulid::nil()
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body["errors"][0]["title"],
+            "Client ID 00000000000000000000000000 not found"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::transform::TransformOperation;
+use axum::Json;
+use ulid::Ulid;
+
+use super::{DateRangeParams, RouteError};
+use crate::admin::{
+    call_context::CallContext,
+    model::UpstreamOAuthProviderLoginStats,
+    params::UlidPathParam,
+    response::{ErrorResponse, SingleResponse},
+};
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("getUpstreamOAuthProviderLoginStats")
+        .summary("Get the daily login statistics of an upstream OAuth 2.0 provider")
+        .description("Retrieve the number of successful and failed logins which went through an upstream OAuth 2.0 provider, for each day of the requested period.
+Statistics are aggregated periodically in the background, so the most recent logins may not be accounted for yet.")
+        .tag("login-stats")
+        .response_with::<200, Json<SingleResponse<UpstreamOAuthProviderLoginStats>>, _>(|t| {
+            let [sample] = UpstreamOAuthProviderLoginStats::samples();
+            let response = SingleResponse::new_canonical(sample);
+            t.description("Login statistics of the provider").example(response)
+        })
+        .response_with::<400, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::InvalidRange);
+            t.description("The requested period is invalid")
+                .example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::ProviderNotFound(Ulid::nil()));
+            t.description("Provider was not found").example(response)
+        })
+}
+
+#[tracing::instrument(
+    name = "handler.admin.v1.login_stats.upstream_oauth_provider",
+    skip_all
+)]
+pub async fn handler(
+    CallContext {
+        mut repo, clock, ..
+    }: CallContext,
+    id: UlidPathParam,
+    params: DateRangeParams,
+) -> Result<Json<SingleResponse<UpstreamOAuthProviderLoginStats>>, RouteError> {
+    let (since, until) = params.resolve(&clock)?;
+
+    let provider = repo
+        .upstream_oauth_provider()
+        .lookup(*id)
+        .await?
+        .ok_or(RouteError::ProviderNotFound(*id))?;
+
+    let days = repo
+        .login_stats()
+        .for_upstream_oauth_provider(&provider, since, until)
+        .await?;
+
+    Ok(Json(SingleResponse::new_canonical(
+        UpstreamOAuthProviderLoginStats::new(provider.id, since, until, days),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use insta::assert_json_snapshot;
+    use sqlx::PgPool;
+
+    use super::super::super::upstream_oauth_links::test_utils;
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_get(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &state.clock,
+                test_utils::oidc_provider_params("provider1"),
+            )
+            .await
+            .unwrap();
+        let link = repo
+            .upstream_oauth_link()
+            .add(
+                &mut rng,
+                &state.clock,
+                &provider,
+                "subject".to_owned(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        // One abandoned login on the first day
+        repo.upstream_oauth_session()
+            .add(
+                &mut rng,
+                &state.clock,
+                &provider,
+                "state-1".to_owned(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        // One successful login two days later
+        state.clock.advance(Duration::days(2));
+        let session = repo
+            .upstream_oauth_session()
+            .add(
+                &mut rng,
+                &state.clock,
+                &provider,
+                "state-2".to_owned(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let session = repo
+            .upstream_oauth_session()
+            .complete_with_link(&state.clock, session, &link, None, None, None, None)
+            .await
+            .unwrap();
+        repo.upstream_oauth_session()
+            .consume(&state.clock, session)
+            .await
+            .unwrap();
+
+        repo.login_stats().refresh(None).await.unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::get(format!(
+            "/api/admin/v1/upstream-oauth-providers/{}/login-stats",
+            provider.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r###"
+        {
+          "data": {
+            "type": "upstream-oauth-provider-login-stats",
+            "id": "01FSHN9AG0MZAA6S4AF7CTV32E",
+            "attributes": {
+              "since": "2021-12-20",
+              "until": "2022-01-18",
+              "successful": 1,
+              "failed": 1,
+              "days": [
+                {
+                  "day": "2022-01-16",
+                  "successful": 0,
+                  "failed": 1
+                },
+                {
+                  "day": "2022-01-18",
+                  "successful": 1,
+                  "failed": 0
+                }
+              ]
+            },
+            "links": {
+              "self": "/api/admin/v1/upstream-oauth-providers/01FSHN9AG0MZAA6S4AF7CTV32E/login-stats"
+            }
+          },
+          "links": {
+            "self": "/api/admin/v1/upstream-oauth-providers/01FSHN9AG0MZAA6S4AF7CTV32E/login-stats"
+          }
+        }
+        "###);
+
+        // Only ask for the last day
+        let request = Request::get(format!(
+            "/api/admin/v1/upstream-oauth-providers/{}/login-stats?since=2022-01-17",
+            provider.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["data"]["attributes"]["successful"], 1);
+        assert_eq!(body["data"]["attributes"]["failed"], 0);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_invalid_range(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &state.clock,
+                test_utils::oidc_provider_params("provider1"),
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::get(format!(
+            "/api/admin/v1/upstream-oauth-providers/{}/login-stats?since=2022-01-10&until=2022-01-01",
+            provider.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body["errors"][0]["title"],
+            "The `since` day must not be after the `until` day"
+        );
+
+        let request = Request::get(format!(
+            "/api/admin/v1/upstream-oauth-providers/{}/login-stats?since=2020-01-01&until=2022-01-01",
+            provider.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+}
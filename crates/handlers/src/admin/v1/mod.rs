@@ -11,18 +11,28 @@ use aide::axum::{
     routing::{get_with, post_with},
 };
 use axum::extract::{FromRef, FromRequestParts};
+use mas_data_model::SiteConfig;
+use mas_keystore::Encrypter;
 use mas_matrix::HomeserverConnection;
 use mas_policy::PolicyFactory;
+use mas_router::UrlBuilder;
 use mas_storage::BoxRng;
 
 use super::call_context::CallContext;
-use crate::passwords::PasswordManager;
+use crate::{passwords::PasswordManager, upstream_oauth2::health::ProviderHealthTracker};
 
 mod compat_sessions;
+mod feature_flag_overrides;
+mod login_stats;
+mod oauth2_clients;
 mod oauth2_sessions;
 mod policy_data;
+mod scim_sync_runs;
 mod upstream_oauth_links;
+mod upstream_oauth_providers;
+mod user_claim_links;
 mod user_emails;
+mod user_recovery_sessions;
 mod user_registration_tokens;
 mod user_sessions;
 mod users;
@@ -33,7 +43,11 @@ where
     S: Clone + Send + Sync + 'static,
     Arc<dyn HomeserverConnection>: FromRef<S>,
     PasswordManager: FromRef<S>,
+    Encrypter: FromRef<S>,
     Arc<PolicyFactory>: FromRef<S>,
+    SiteConfig: FromRef<S>,
+    UrlBuilder: FromRef<S>,
+    ProviderHealthTracker: FromRef<S>,
     BoxRng: FromRequestParts<S>,
     CallContext: FromRequestParts<S>,
 {
@@ -46,6 +60,53 @@ where
             "/compat-sessions/{id}",
             get_with(self::compat_sessions::get, self::compat_sessions::get_doc),
         )
+        .api_route(
+            "/feature-flag-overrides",
+            get_with(
+                self::feature_flag_overrides::list,
+                self::feature_flag_overrides::list_doc,
+            )
+            .post_with(
+                self::feature_flag_overrides::set,
+                self::feature_flag_overrides::set_doc,
+            ),
+        )
+        .api_route(
+            "/feature-flag-overrides/{id}",
+            get_with(
+                self::feature_flag_overrides::get,
+                self::feature_flag_overrides::get_doc,
+            )
+            .delete_with(
+                self::feature_flag_overrides::delete,
+                self::feature_flag_overrides::delete_doc,
+            ),
+        )
+        .api_route(
+            "/oauth2-clients/{id}",
+            get_with(self::oauth2_clients::get, self::oauth2_clients::get_doc),
+        )
+        .api_route(
+            "/oauth2-clients/{id}/set-next-secret",
+            post_with(
+                self::oauth2_clients::set_next_secret,
+                self::oauth2_clients::set_next_secret_doc,
+            ),
+        )
+        .api_route(
+            "/oauth2-clients/{id}/promote-next-secret",
+            post_with(
+                self::oauth2_clients::promote_next_secret,
+                self::oauth2_clients::promote_next_secret_doc,
+            ),
+        )
+        .api_route(
+            "/oauth2-clients/{id}/login-stats",
+            get_with(
+                self::login_stats::oauth2_client,
+                self::login_stats::oauth2_client_doc,
+            ),
+        )
         .api_route(
             "/oauth2-sessions",
             get_with(self::oauth2_sessions::list, self::oauth2_sessions::list_doc),
@@ -69,6 +130,14 @@ where
             "/policy-data/{id}",
             get_with(self::policy_data::get, self::policy_data::get_doc),
         )
+        .api_route(
+            "/scim-sync-runs",
+            get_with(self::scim_sync_runs::list, self::scim_sync_runs::list_doc),
+        )
+        .api_route(
+            "/scim-sync-runs/{id}",
+            get_with(self::scim_sync_runs::get, self::scim_sync_runs::get_doc),
+        )
         .api_route(
             "/users",
             get_with(self::users::list, self::users::list_doc)
@@ -90,6 +159,22 @@ where
             "/users/{id}/set-admin",
             post_with(self::users::set_admin, self::users::set_admin_doc),
         )
+        .api_route(
+            "/users/{id}/set-username",
+            post_with(self::users::set_username, self::users::set_username_doc),
+        )
+        .api_route(
+            "/users/{id}/attributes",
+            get_with(self::users::attributes, self::users::attributes_doc),
+        )
+        .api_route(
+            "/users/{id}/terms",
+            get_with(self::users::terms, self::users::terms_doc),
+        )
+        .api_route(
+            "/users/{id}/set-attributes",
+            post_with(self::users::set_attributes, self::users::set_attributes_doc),
+        )
         .api_route(
             "/users/{id}/deactivate",
             post_with(self::users::deactivate, self::users::deactivate_doc),
@@ -102,6 +187,28 @@ where
             "/users/{id}/unlock",
             post_with(self::users::unlock, self::users::unlock_doc),
         )
+        .api_route(
+            "/users/{id}/clear-lockout",
+            post_with(self::users::clear_lockout, self::users::clear_lockout_doc),
+        )
+        .api_route(
+            "/users/{id}/set-password-policy",
+            post_with(
+                self::users::set_password_policy,
+                self::users::set_password_policy_doc,
+            ),
+        )
+        .api_route(
+            "/users/{id}/require-password-change",
+            post_with(
+                self::users::require_password_change,
+                self::users::require_password_change_doc,
+            ),
+        )
+        .api_route(
+            "/users/{id}/impersonate",
+            post_with(self::users::impersonate, self::users::impersonate_doc),
+        )
         .api_route(
             "/user-emails",
             get_with(self::user_emails::list, self::user_emails::list_doc)
@@ -156,6 +263,67 @@ where
                 self::user_registration_tokens::unrevoke_doc,
             ),
         )
+        .api_route(
+            "/user-claim-links",
+            get_with(
+                self::user_claim_links::list,
+                self::user_claim_links::list_doc,
+            )
+            .post_with(self::user_claim_links::add, self::user_claim_links::add_doc),
+        )
+        .api_route(
+            "/user-claim-links/{id}",
+            get_with(self::user_claim_links::get, self::user_claim_links::get_doc),
+        )
+        .api_route(
+            "/user-claim-links/{id}/revoke",
+            post_with(
+                self::user_claim_links::revoke,
+                self::user_claim_links::revoke_doc,
+            ),
+        )
+        .api_route(
+            "/user-recovery-sessions",
+            get_with(
+                self::user_recovery_sessions::list,
+                self::user_recovery_sessions::list_doc,
+            ),
+        )
+        .api_route(
+            "/user-recovery-sessions/{id}",
+            get_with(
+                self::user_recovery_sessions::get,
+                self::user_recovery_sessions::get_doc,
+            ),
+        )
+        .api_route(
+            "/user-recovery-sessions/{id}/approve",
+            post_with(
+                self::user_recovery_sessions::approve,
+                self::user_recovery_sessions::approve_doc,
+            ),
+        )
+        .api_route(
+            "/user-recovery-sessions/{id}/deny",
+            post_with(
+                self::user_recovery_sessions::deny,
+                self::user_recovery_sessions::deny_doc,
+            ),
+        )
+        .api_route(
+            "/upstream-oauth-providers/{id}/login-stats",
+            get_with(
+                self::login_stats::upstream_oauth_provider,
+                self::login_stats::upstream_oauth_provider_doc,
+            ),
+        )
+        .api_route(
+            "/upstream-oauth-providers/{id}/health",
+            get_with(
+                self::upstream_oauth_providers::health,
+                self::upstream_oauth_providers::health_doc,
+            ),
+        )
         .api_route(
             "/upstream-oauth-links",
             get_with(
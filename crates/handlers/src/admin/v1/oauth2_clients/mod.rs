@@ -0,0 +1,48 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+mod get;
+mod promote_next_secret;
+mod set_next_secret;
+
+pub use self::{
+    get::{doc as get_doc, handler as get},
+    promote_next_secret::{doc as promote_next_secret_doc, handler as promote_next_secret},
+    set_next_secret::{doc as set_next_secret_doc, handler as set_next_secret},
+};
+
+#[cfg(test)]
+pub(super) mod test_utils {
+    use mas_data_model::Client;
+    use mas_iana::oauth::OAuthClientAuthenticationMethod;
+    use mas_keystore::Encrypter;
+    use mas_storage::{BoxRepository, RepositoryAccess, oauth2::OAuth2StaticClientParams};
+    use ulid::Ulid;
+
+    /// Provision a static client authenticating with the given secret
+    pub(crate) async fn add_static_client(
+        repo: &mut BoxRepository,
+        encrypter: &Encrypter,
+        client_secret: &str,
+    ) -> Client {
+        let encrypted_client_secret = encrypter
+            .encrypt_to_string(client_secret.as_bytes())
+            .unwrap();
+
+        repo.oauth2_client()
+            .upsert_static(
+                Ulid::from_string("01K0B2QXR0Y8M1VJQ6Z9PCN4TW").unwrap(),
+                OAuthClientAuthenticationMethod::ClientSecretPost,
+                OAuth2StaticClientParams {
+                    client_name: Some("Backend service".to_owned()),
+                    encrypted_client_secret: Some(encrypted_client_secret),
+                    backchannel_logout_include_sub: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap()
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{Json, response::IntoResponse};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::{OAuth2Client, Resource},
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("OAuth 2.0 client ID {0} not found")]
+    NotFound(Ulid),
+
+    #[error("OAuth 2.0 client ID {0} has no next secret")]
+    NoNextSecret(Ulid),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::NoNextSecret(_) => StatusCode::BAD_REQUEST,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("promoteOAuth2ClientNextSecret")
+        .summary("Promote the next secret of an OAuth 2.0 client")
+        .description("Finish rotating the secret of a confidential client: the next secret replaces the current one, which stops being accepted.")
+        .tag("oauth2-client")
+        .response_with::<200, Json<SingleResponse<OAuth2Client>>, _>(|t| {
+            let [client, _] = OAuth2Client::samples();
+            let id = client.id();
+            let response = SingleResponse::new(
+                client,
+                format!("/api/admin/v1/oauth2-clients/{id}/promote-next-secret"),
+            );
+            t.description("The next secret was promoted").example(response)
+        })
+        .response_with::<400, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NoNextSecret(Ulid::nil()));
+            t.description("The client has no next secret").example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("OAuth 2.0 client was not found")
+                .example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.oauth2_clients.promote_next_secret", skip_all)]
+pub async fn handler(
+    CallContext { mut repo, .. }: CallContext,
+    id: UlidPathParam,
+) -> Result<Json<SingleResponse<OAuth2Client>>, RouteError> {
+    let id = *id;
+    let client = repo
+        .oauth2_client()
+        .lookup(id)
+        .await?
+        .ok_or(RouteError::NotFound(id))?;
+
+    if client.encrypted_next_client_secret.is_none() {
+        return Err(RouteError::NoNextSecret(id));
+    }
+
+    let client = repo.oauth2_client().promote_next_secret(client).await?;
+
+    repo.save().await?;
+
+    tracing::info!(client.id = %client.id, "Promoted the next secret of the client");
+
+    Ok(Json(SingleResponse::new(
+        OAuth2Client::from(client),
+        format!("/api/admin/v1/oauth2-clients/{id}/promote-next-secret"),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use mas_router::SimpleRoute;
+    use sqlx::PgPool;
+
+    use super::super::test_utils::add_static_client;
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_rotation(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let mut repo = state.repository().await.unwrap();
+        let client = add_static_client(&mut repo, &state.encrypter, "old-secret").await;
+        repo.save().await.unwrap();
+
+        // Helper checking whether the client can authenticate with a secret
+        let authenticate = |client_secret: &'static str| {
+            Request::post(mas_router::OAuth2Introspection::PATH).form(serde_json::json!({
+                "token": "some-token",
+                "client_id": client.client_id,
+                "client_secret": client_secret,
+            }))
+        };
+
+        let request = Request::post(format!(
+            "/api/admin/v1/oauth2-clients/{}/set-next-secret",
+            client.id
+        ))
+        .bearer(&token)
+        .json(serde_json::json!({ "client_secret": "new-secret" }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        // Both secrets are accepted during the rotation
+        let response = state.request(authenticate("old-secret")).await;
+        response.assert_status(StatusCode::OK);
+        let response = state.request(authenticate("new-secret")).await;
+        response.assert_status(StatusCode::OK);
+
+        let request = Request::post(format!(
+            "/api/admin/v1/oauth2-clients/{}/promote-next-secret",
+            client.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["data"]["attributes"]["has_client_secret"], true);
+        assert_eq!(body["data"]["attributes"]["has_next_client_secret"], false);
+
+        // Only the new secret is accepted afterwards
+        let response = state.request(authenticate("old-secret")).await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+        let response = state.request(authenticate("new-secret")).await;
+        response.assert_status(StatusCode::OK);
+
+        // There is nothing left to promote
+        let request = Request::post(format!(
+            "/api/admin/v1/oauth2-clients/{}/promote-next-secret",
+            client.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body["errors"][0]["title"],
+            format!("OAuth 2.0 client ID {} has no next secret", client.id)
+        );
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{Json, extract::State, response::IntoResponse};
+use chrono::{DateTime, Utc};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_keystore::Encrypter;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::{OAuth2Client, Resource},
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("OAuth 2.0 client ID {0} not found")]
+    NotFound(Ulid),
+
+    #[error("OAuth 2.0 client ID {0} does not authenticate with a client secret")]
+    NoClientSecret(Ulid),
+
+    #[error("Expiration dates must be in the future")]
+    ExpiresInThePast,
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+impl_from_error_for_route!(mas_keystore::aead::Error);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::NoClientSecret(_) | Self::ExpiresInThePast => StatusCode::BAD_REQUEST,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+fn client_secret_example() -> String {
+    "g7nQ2vXk9pL4sR8w".to_owned()
+}
+
+/// # JSON payload for the `POST /api/admin/v1/oauth2-clients/:id/set-next-secret` endpoint
+#[derive(Deserialize, JsonSchema)]
+#[schemars(rename = "SetOAuth2ClientNextSecretRequest")]
+pub struct Request {
+    /// The secret to accept alongside the current one
+    #[schemars(example = "client_secret_example")]
+    client_secret: String,
+
+    /// When the new secret stops being accepted. If omitted, it doesn't
+    /// expire.
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>,
+
+    /// When the current secret stops being accepted. If omitted, it keeps
+    /// being accepted until the new secret is promoted.
+    #[serde(default)]
+    current_secret_expires_at: Option<DateTime<Utc>>,
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("setOAuth2ClientNextSecret")
+        .summary("Set the next secret of an OAuth 2.0 client")
+        .description("Start rotating the secret of a confidential client: the new secret is accepted alongside the current one, so that the client can switch to it without downtime.
+Setting a next secret replaces any previous one.
+Changes made to statically configured clients are overwritten the next time the configuration is synced.")
+        .tag("oauth2-client")
+        .response_with::<200, Json<SingleResponse<OAuth2Client>>, _>(|t| {
+            let [_, rotating_client] = OAuth2Client::samples();
+            let id = rotating_client.id();
+            let response = SingleResponse::new(
+                rotating_client,
+                format!("/api/admin/v1/oauth2-clients/{id}/set-next-secret"),
+            );
+            t.description("The next secret was set").example(response)
+        })
+        .response_with::<400, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NoClientSecret(Ulid::nil()));
+            t.description("The client does not authenticate with a client secret")
+                .example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("OAuth 2.0 client was not found")
+                .example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.oauth2_clients.set_next_secret", skip_all)]
+pub async fn handler(
+    CallContext {
+        mut repo, clock, ..
+    }: CallContext,
+    State(encrypter): State<Encrypter>,
+    id: UlidPathParam,
+    Json(params): Json<Request>,
+) -> Result<Json<SingleResponse<OAuth2Client>>, RouteError> {
+    let id = *id;
+    let client = repo
+        .oauth2_client()
+        .lookup(id)
+        .await?
+        .ok_or(RouteError::NotFound(id))?;
+
+    if client.encrypted_client_secret.is_none() {
+        return Err(RouteError::NoClientSecret(id));
+    }
+
+    let now = clock.now();
+    if [params.expires_at, params.current_secret_expires_at]
+        .into_iter()
+        .flatten()
+        .any(|expires_at| expires_at <= now)
+    {
+        return Err(RouteError::ExpiresInThePast);
+    }
+
+    let encrypted_next_client_secret =
+        encrypter.encrypt_to_string(params.client_secret.as_bytes())?;
+
+    let client = repo
+        .oauth2_client()
+        .set_next_secret(
+            client,
+            encrypted_next_client_secret,
+            params.expires_at,
+            params.current_secret_expires_at,
+        )
+        .await?;
+
+    repo.save().await?;
+
+    tracing::info!(
+        client.id = %client.id,
+        next_client_secret_expires_at = ?client.next_client_secret_expires_at,
+        client_secret_expires_at = ?client.client_secret_expires_at,
+        "Set the next secret of the client"
+    );
+
+    Ok(Json(SingleResponse::new(
+        OAuth2Client::from(client),
+        format!("/api/admin/v1/oauth2-clients/{id}/set-next-secret"),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use mas_router::SimpleRoute;
+    use mas_storage::{Clock as _, RepositoryAccess};
+    use sqlx::PgPool;
+
+    use super::super::test_utils::add_static_client;
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_set_next_secret(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let mut repo = state.repository().await.unwrap();
+        let client = add_static_client(&mut repo, &state.encrypter, "secret").await;
+        repo.save().await.unwrap();
+
+        let expires_at = state.clock.now() + Duration::try_days(7).unwrap();
+        let request = Request::post(format!(
+            "/api/admin/v1/oauth2-clients/{}/set-next-secret",
+            client.id
+        ))
+        .bearer(&token)
+        .json(serde_json::json!({
+            "client_secret": "new-secret",
+            "current_secret_expires_at": expires_at,
+        }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["data"]["attributes"]["has_next_client_secret"], true);
+        assert_eq!(
+            body["data"]["attributes"]["client_secret_expires_at"],
+            serde_json::json!(expires_at)
+        );
+        assert_eq!(
+            body["data"]["attributes"]["next_client_secret_expires_at"],
+            serde_json::Value::Null
+        );
+
+        // The secret is stored encrypted
+        let mut repo = state.repository().await.unwrap();
+        let client = repo
+            .oauth2_client()
+            .lookup(client.id)
+            .await
+            .unwrap()
+            .unwrap();
+        let next_secret = state
+            .encrypter
+            .decrypt_string(client.encrypted_next_client_secret.as_deref().unwrap())
+            .unwrap();
+        assert_eq!(next_secret, b"new-secret");
+        repo.cancel().await.unwrap();
+
+        // Once the current secret expired, only the next one is accepted
+        state.clock.advance(Duration::try_days(8).unwrap());
+        for (client_secret, status) in [
+            ("secret", StatusCode::UNAUTHORIZED),
+            ("new-secret", StatusCode::OK),
+        ] {
+            let request =
+                Request::post(mas_router::OAuth2Introspection::PATH).form(serde_json::json!({
+                    "token": "some-token",
+                    "client_id": client.client_id,
+                    "client_secret": client_secret,
+                }));
+            let response = state.request(request).await;
+            response.assert_status(status);
+        }
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_expires_in_the_past(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let mut repo = state.repository().await.unwrap();
+        let client = add_static_client(&mut repo, &state.encrypter, "secret").await;
+        repo.save().await.unwrap();
+
+        let request = Request::post(format!(
+            "/api/admin/v1/oauth2-clients/{}/set-next-secret",
+            client.id
+        ))
+        .bearer(&token)
+        .json(serde_json::json!({
+            "client_secret": "new-secret",
+            "expires_at": state.clock.now() - Duration::try_hours(1).unwrap(),
+        }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body["errors"][0]["title"],
+            "Expiration dates must be in the future"
+        );
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_not_found(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let request = Request::post(
+            "/api/admin/v1/oauth2-clients/01040G2081040G2081040G2081/set-next-secret",
+        )
+        .bearer(&token)
+        .json(serde_json::json!({
+            "client_secret": "new-secret",
+        }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
+}
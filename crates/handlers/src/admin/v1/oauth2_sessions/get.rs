@@ -125,7 +125,11 @@ mod tests {
               "user_agent": null,
               "last_active_at": null,
               "last_active_ip": null,
-              "human_name": null
+              "last_active_country": null,
+              "last_active_asn": null,
+              "last_active_as_organization": null,
+              "human_name": null,
+              "device_fingerprint": null
             },
             "links": {
               "self": "/api/admin/v1/oauth2-sessions/01FSHN9AG0MKGTBNZ16RDR3PVY"
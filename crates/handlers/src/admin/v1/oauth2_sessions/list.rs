@@ -25,7 +25,7 @@ use crate::{
     admin::{
         call_context::CallContext,
         model::{OAuth2Session, Resource},
-        params::Pagination,
+        params::{EstimateCount, Pagination},
         response::{ErrorResponse, PaginatedResponse},
     },
     impl_from_error_for_route,
@@ -52,6 +52,7 @@ impl std::fmt::Display for OAuth2SessionStatus {
 enum OAuth2ClientKind {
     Dynamic,
     Static,
+    ServiceAccount,
 }
 
 impl std::fmt::Display for OAuth2ClientKind {
@@ -59,6 +60,7 @@ impl std::fmt::Display for OAuth2ClientKind {
         match self {
             Self::Dynamic => write!(f, "dynamic"),
             Self::Static => write!(f, "static"),
+            Self::ServiceAccount => write!(f, "service_account"),
         }
     }
 }
@@ -100,6 +102,10 @@ pub struct FilterParams {
     /// * `finished`: Only retrieve finished sessions
     #[serde(rename = "filter[status]")]
     status: Option<OAuth2SessionStatus>,
+
+    /// Retrieve the items with the given client-provided device fingerprint
+    #[serde(rename = "filter[device-fingerprint]")]
+    device_fingerprint: Option<String>,
 }
 
 impl std::fmt::Display for FilterParams {
@@ -136,6 +142,11 @@ impl std::fmt::Display for FilterParams {
             sep = '&';
         }
 
+        if let Some(device_fingerprint) = &self.device_fingerprint {
+            write!(f, "{sep}filter[device-fingerprint]={device_fingerprint}")?;
+            sep = '&';
+        }
+
         let _ = sep;
         Ok(())
     }
@@ -219,6 +230,7 @@ Use the `filter[status]` parameter to filter the sessions by their status and `p
 pub async fn handler(
     CallContext { mut repo, .. }: CallContext,
     Pagination(pagination): Pagination,
+    EstimateCount(estimate): EstimateCount,
     params: FilterParams,
 ) -> Result<Json<PaginatedResponse<OAuth2Session>>, RouteError> {
     let base = format!("{path}{params}", path = OAuth2Session::PATH);
@@ -262,6 +274,7 @@ pub async fn handler(
     let filter = match params.client_kind {
         Some(OAuth2ClientKind::Dynamic) => filter.only_dynamic_clients(),
         Some(OAuth2ClientKind::Static) => filter.only_static_clients(),
+        Some(OAuth2ClientKind::ServiceAccount) => filter.only_service_account_clients(),
         None => filter,
     };
 
@@ -300,15 +313,25 @@ pub async fn handler(
         None => filter,
     };
 
+    let filter = match params.device_fingerprint.as_deref() {
+        Some(device_fingerprint) => filter.for_device_fingerprint(device_fingerprint),
+        None => filter,
+    };
+
     let page = repo.oauth2_session().list(filter, pagination).await?;
-    let count = repo.oauth2_session().count(filter).await?;
-
-    Ok(Json(PaginatedResponse::new(
-        page.map(OAuth2Session::from),
-        pagination,
-        count,
-        &base,
-    )))
+    let count = if estimate {
+        repo.oauth2_session().estimate_count(filter).await?
+    } else {
+        repo.oauth2_session().count(filter).await?
+    };
+
+    let response = PaginatedResponse::new(page.map(OAuth2Session::from), pagination, count, &base);
+
+    if estimate {
+        Ok(Json(response.with_estimated_count()))
+    } else {
+        Ok(Json(response))
+    }
 }
 
 #[cfg(test)]
@@ -350,7 +373,11 @@ mod tests {
                 "user_agent": null,
                 "last_active_at": null,
                 "last_active_ip": null,
-                "human_name": null
+                "last_active_country": null,
+                "last_active_asn": null,
+                "last_active_as_organization": null,
+                "human_name": null,
+                "device_fingerprint": null
               },
               "links": {
                 "self": "/api/admin/v1/oauth2-sessions/01FSHN9AG0MKGTBNZ16RDR3PVY"
@@ -0,0 +1,174 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{Json, response::IntoResponse};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::ScimSyncRun,
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("SCIM sync run ID {0} not found")]
+    NotFound(Ulid),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("getScimSyncRun")
+        .summary("Get a SCIM sync run")
+        .tag("scim-sync-run")
+        .response_with::<200, Json<SingleResponse<ScimSyncRun>>, _>(|t| {
+            let [sample, ..] = ScimSyncRun::samples();
+            let response = SingleResponse::new_canonical(sample);
+            t.description("SCIM sync run was found").example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("SCIM sync run was not found")
+                .example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.scim_sync_runs.get", skip_all)]
+pub async fn handler(
+    CallContext { mut repo, .. }: CallContext,
+    id: UlidPathParam,
+) -> Result<Json<SingleResponse<ScimSyncRun>>, RouteError> {
+    let run = repo
+        .scim_sync_run()
+        .lookup(*id)
+        .await?
+        .ok_or(RouteError::NotFound(*id))?;
+
+    Ok(Json(SingleResponse::new_canonical(ScimSyncRun::from(run))))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use insta::assert_json_snapshot;
+    use mas_data_model::{ScimSyncAction, ScimSyncChange};
+    use sqlx::PgPool;
+    use ulid::Ulid;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_get(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        // Provision a user and a completed sync run which created it
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let run = repo
+            .scim_sync_run()
+            .start(&mut rng, &state.clock)
+            .await
+            .unwrap();
+        state.clock.advance(Duration::minutes(1));
+        let run = repo
+            .scim_sync_run()
+            .complete(
+                &state.clock,
+                run,
+                vec![ScimSyncChange {
+                    action: ScimSyncAction::Created,
+                    user_id: user.id,
+                    username: user.username.clone(),
+                    external_id: "2819c223".to_owned(),
+                }],
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let run_id = run.id;
+        let request = Request::get(format!("/api/admin/v1/scim-sync-runs/{run_id}"))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r###"
+        {
+          "data": {
+            "type": "scim-sync-run",
+            "id": "01FSHN9AG0AJ6AC5HQ9X6H4RP4",
+            "attributes": {
+              "state": "completed",
+              "started_at": "2022-01-16T14:40:00Z",
+              "finished_at": "2022-01-16T14:41:00Z",
+              "error": null,
+              "changes": [
+                {
+                  "action": "created",
+                  "user_id": "01FSHN9AG0MZAA6S4AF7CTV32E",
+                  "username": "alice",
+                  "external_id": "2819c223"
+                }
+              ]
+            },
+            "links": {
+              "self": "/api/admin/v1/scim-sync-runs/01FSHN9AG0AJ6AC5HQ9X6H4RP4"
+            }
+          },
+          "links": {
+            "self": "/api/admin/v1/scim-sync-runs/01FSHN9AG0AJ6AC5HQ9X6H4RP4"
+          }
+        }
+        "###);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_not_found(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let run_id = Ulid::nil();
+        let request = Request::get(format!("/api/admin/v1/scim-sync-runs/{run_id}"))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
+}
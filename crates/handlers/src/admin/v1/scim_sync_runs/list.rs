@@ -0,0 +1,278 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{
+    Json,
+    extract::{Query, rejection::QueryRejection},
+    response::IntoResponse,
+};
+use axum_macros::FromRequestParts;
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_storage::{Page, scim::ScimSyncRunFilter};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::{Resource, ScimSyncRun},
+        params::Pagination,
+        response::{ErrorResponse, PaginatedResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Deserialize, JsonSchema, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum ScimSyncRunFilterState {
+    Running,
+    Completed,
+    Failed,
+}
+
+impl std::fmt::Display for ScimSyncRunFilterState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Running => write!(f, "running"),
+            Self::Completed => write!(f, "completed"),
+            Self::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+#[derive(FromRequestParts, Deserialize, JsonSchema, OperationIo)]
+#[serde(rename = "ScimSyncRunFilter")]
+#[aide(input_with = "Query<FilterParams>")]
+#[from_request(via(Query), rejection(RouteError))]
+pub struct FilterParams {
+    /// Retrieve the runs in the given state
+    ///
+    /// Defaults to retrieve all runs.
+    ///
+    /// * `running`: Only retrieve runs which are still in progress
+    ///
+    /// * `completed`: Only retrieve runs which completed successfully
+    ///
+    /// * `failed`: Only retrieve runs which failed
+    #[serde(rename = "filter[state]")]
+    state: Option<ScimSyncRunFilterState>,
+}
+
+impl std::fmt::Display for FilterParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(state) = self.state {
+            write!(f, "?filter[state]={state}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("Invalid filter parameters")]
+    InvalidFilter(#[from] QueryRejection),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::InvalidFilter(_) => StatusCode::BAD_REQUEST,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("listScimSyncRuns")
+        .summary("List SCIM sync runs")
+        .description("Retrieve a list of synchronisation runs against the upstream SCIM directory, with the changes each of them applied to users.
+Note that by default, all runs are returned, with the oldest first.
+Use the `filter[state]` parameter to filter the runs by their state and `page[last]` parameter to retrieve the last N runs.")
+        .tag("scim-sync-run")
+        .response_with::<200, Json<PaginatedResponse<ScimSyncRun>>, _>(|t| {
+            let runs = ScimSyncRun::samples();
+            let pagination = mas_storage::Pagination::first(runs.len());
+            let page = Page {
+                edges: runs.into(),
+                has_next_page: true,
+                has_previous_page: false,
+            };
+
+            t.description("Paginated response of SCIM sync runs")
+                .example(PaginatedResponse::new(
+                    page,
+                    pagination,
+                    42,
+                    ScimSyncRun::PATH,
+                ))
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.scim_sync_runs.list", skip_all)]
+pub async fn handler(
+    CallContext { mut repo, .. }: CallContext,
+    Pagination(pagination): Pagination,
+    params: FilterParams,
+) -> Result<Json<PaginatedResponse<ScimSyncRun>>, RouteError> {
+    let base = format!("{path}{params}", path = ScimSyncRun::PATH);
+    let filter = ScimSyncRunFilter::new();
+
+    let filter = match params.state {
+        Some(ScimSyncRunFilterState::Running) => filter.running_only(),
+        Some(ScimSyncRunFilterState::Completed) => filter.completed_only(),
+        Some(ScimSyncRunFilterState::Failed) => filter.failed_only(),
+        None => filter,
+    };
+
+    let page = repo.scim_sync_run().list(filter, pagination).await?;
+    let count = repo.scim_sync_run().count(filter).await?;
+
+    Ok(Json(PaginatedResponse::new(
+        page.map(ScimSyncRun::from),
+        pagination,
+        count,
+        &base,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use insta::assert_json_snapshot;
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_scim_sync_run_list(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        // Start two runs, complete the first one and fail the second one
+        let mut repo = state.repository().await.unwrap();
+        let run1 = repo
+            .scim_sync_run()
+            .start(&mut rng, &state.clock)
+            .await
+            .unwrap();
+        state.clock.advance(Duration::minutes(1));
+        let run2 = repo
+            .scim_sync_run()
+            .start(&mut rng, &state.clock)
+            .await
+            .unwrap();
+        state.clock.advance(Duration::minutes(1));
+        repo.scim_sync_run()
+            .complete(&state.clock, run1, Vec::new())
+            .await
+            .unwrap();
+        repo.scim_sync_run()
+            .fail(&state.clock, run2, "Directory unreachable".to_owned())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::get("/api/admin/v1/scim-sync-runs")
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r###"
+        {
+          "meta": {
+            "count": 2
+          },
+          "data": [
+            {
+              "type": "scim-sync-run",
+              "id": "01FSHN9AG0MZAA6S4AF7CTV32E",
+              "attributes": {
+                "state": "completed",
+                "started_at": "2022-01-16T14:40:00Z",
+                "finished_at": "2022-01-16T14:42:00Z",
+                "error": null,
+                "changes": []
+              },
+              "links": {
+                "self": "/api/admin/v1/scim-sync-runs/01FSHN9AG0MZAA6S4AF7CTV32E"
+              }
+            },
+            {
+              "type": "scim-sync-run",
+              "id": "01FSHNB530AJ6AC5HQ9X6H4RP4",
+              "attributes": {
+                "state": "failed",
+                "started_at": "2022-01-16T14:41:00Z",
+                "finished_at": "2022-01-16T14:42:00Z",
+                "error": "Directory unreachable",
+                "changes": []
+              },
+              "links": {
+                "self": "/api/admin/v1/scim-sync-runs/01FSHNB530AJ6AC5HQ9X6H4RP4"
+              }
+            }
+          ],
+          "links": {
+            "self": "/api/admin/v1/scim-sync-runs?page[first]=10",
+            "first": "/api/admin/v1/scim-sync-runs?page[first]=10",
+            "last": "/api/admin/v1/scim-sync-runs?page[last]=10"
+          }
+        }
+        "###);
+
+        // Filter by state
+        let request = Request::get("/api/admin/v1/scim-sync-runs?filter[state]=failed")
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r###"
+        {
+          "meta": {
+            "count": 1
+          },
+          "data": [
+            {
+              "type": "scim-sync-run",
+              "id": "01FSHNB530AJ6AC5HQ9X6H4RP4",
+              "attributes": {
+                "state": "failed",
+                "started_at": "2022-01-16T14:41:00Z",
+                "finished_at": "2022-01-16T14:42:00Z",
+                "error": "Directory unreachable",
+                "changes": []
+              },
+              "links": {
+                "self": "/api/admin/v1/scim-sync-runs/01FSHNB530AJ6AC5HQ9X6H4RP4"
+              }
+            }
+          ],
+          "links": {
+            "self": "/api/admin/v1/scim-sync-runs?filter[state]=failed&page[first]=10",
+            "first": "/api/admin/v1/scim-sync-runs?filter[state]=failed&page[first]=10",
+            "last": "/api/admin/v1/scim-sync-runs?filter[state]=failed&page[last]=10"
+          }
+        }
+        "###);
+    }
+}
@@ -16,7 +16,7 @@ pub use self::{
 };
 
 #[cfg(test)]
-mod test_utils {
+pub(super) mod test_utils {
     use mas_data_model::{
         UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderDiscoveryMode,
         UpstreamOAuthProviderPkceMode, UpstreamOAuthProviderTokenAuthMethod,
@@ -33,6 +33,9 @@ mod test_utils {
             scope: Scope::from_iter([OPENID]),
             token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::ClientSecretBasic,
             token_endpoint_signing_alg: None,
+            token_endpoint_signing_key_id: None,
+            tls_client_certificate: None,
+            encrypted_tls_client_key: None,
             id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
             fetch_userinfo: false,
             userinfo_signed_response_alg: None,
@@ -48,6 +51,11 @@ mod test_utils {
             jwks_uri_override: None,
             additional_authorization_parameters: Vec::new(),
             forward_login_hint: false,
+            forward_prompt: false,
+            forward_acr_values: false,
+            forward_ui_locales: false,
+            store_tokens: false,
+            deprovisioning: None,
             ui_order: 0,
         }
     }
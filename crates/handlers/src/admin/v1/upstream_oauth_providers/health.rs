@@ -0,0 +1,195 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{Json, extract::State, response::IntoResponse};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::UpstreamOAuthProviderHealth,
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+    upstream_oauth2::health::ProviderHealthTracker,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("Upstream OAuth 2.0 Provider ID {0} not found")]
+    NotFound(Ulid),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("getUpstreamOAuthProviderHealth")
+        .summary("Get the health of an upstream OAuth 2.0 provider")
+        .description("Retrieve the result of the last health check of an upstream OAuth 2.0 provider.
+The discovery document and the JWKS of enabled providers are checked periodically in the background by each instance of the service, so the result may differ from one instance to another.")
+        .tag("upstream-oauth-provider")
+        .response_with::<200, Json<SingleResponse<UpstreamOAuthProviderHealth>>, _>(|t| {
+            let [sample, ..] = UpstreamOAuthProviderHealth::samples();
+            let response = SingleResponse::new_canonical(sample);
+            t.description("Health of the provider").example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("Provider was not found").example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.upstream_oauth_providers.health", skip_all)]
+pub async fn handler(
+    CallContext { mut repo, .. }: CallContext,
+    State(provider_health): State<ProviderHealthTracker>,
+    id: UlidPathParam,
+) -> Result<Json<SingleResponse<UpstreamOAuthProviderHealth>>, RouteError> {
+    let provider = repo
+        .upstream_oauth_provider()
+        .lookup(*id)
+        .await?
+        .ok_or(RouteError::NotFound(*id))?;
+
+    let health = provider_health.get(provider.id).await;
+
+    Ok(Json(SingleResponse::new_canonical(
+        UpstreamOAuthProviderHealth::new(provider.id, health),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use insta::assert_json_snapshot;
+    use mas_data_model::UpstreamOAuthProviderDiscoveryMode;
+    use mas_storage::{Clock as _, upstream_oauth2::UpstreamOAuthProviderParams};
+    use sqlx::PgPool;
+    use ulid::Ulid;
+
+    use super::super::super::upstream_oauth_links::test_utils;
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_get(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        // Without discovery nor JWKS URI, there is nothing to fetch to check the
+        // provider
+        let mut repo = state.repository().await.unwrap();
+        let provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &state.clock,
+                UpstreamOAuthProviderParams {
+                    discovery_mode: UpstreamOAuthProviderDiscoveryMode::Disabled,
+                    ..test_utils::oidc_provider_params("provider1")
+                },
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        // The provider wasn't checked yet
+        let request = Request::get(format!(
+            "/api/admin/v1/upstream-oauth-providers/{}/health",
+            provider.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body["data"]["attributes"]["checked_at"],
+            serde_json::Value::Null
+        );
+        assert_eq!(
+            body["data"]["attributes"]["healthy"],
+            serde_json::Value::Null
+        );
+
+        state
+            .provider_health
+            .check(
+                &state.http_client,
+                &state.metadata_cache,
+                &provider,
+                state.clock.now(),
+            )
+            .await;
+
+        let request = Request::get(format!(
+            "/api/admin/v1/upstream-oauth-providers/{}/health",
+            provider.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r###"
+        {
+          "data": {
+            "type": "upstream-oauth-provider-health",
+            "id": "01FSHN9AG0MZAA6S4AF7CTV32E",
+            "attributes": {
+              "checked_at": "2022-01-16T14:40:00Z",
+              "healthy": true,
+              "reachable": true,
+              "discovery_reachable": null,
+              "jwks_reachable": null,
+              "jwks_fetched_at": null,
+              "jwks_stale": false,
+              "certificate_expires_at": null,
+              "certificate_expiring": false,
+              "error": null
+            },
+            "links": {
+              "self": "/api/admin/v1/upstream-oauth-providers/01FSHN9AG0MZAA6S4AF7CTV32E/health"
+            }
+          },
+          "links": {
+            "self": "/api/admin/v1/upstream-oauth-providers/01FSHN9AG0MZAA6S4AF7CTV32E/health"
+          }
+        }
+        "###);
+
+        // Unknown providers are not found
+        let request = Request::get(format!(
+            "/api/admin/v1/upstream-oauth-providers/{}/health",
+            Ulid::nil()
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
+}
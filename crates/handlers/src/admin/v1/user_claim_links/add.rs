@@ -0,0 +1,346 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::str::FromStr as _;
+
+use aide::{NoApi, OperationIo, transform::TransformOperation};
+use axum::{Json, extract::State, response::IntoResponse};
+use chrono::Duration;
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxRng,
+    queue::{QueueJobRepositoryExt as _, SendUserClaimLinkEmailJob},
+};
+use rand::distributions::{Alphanumeric, DistString};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::UserClaimLink,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+    passwords::PasswordManager,
+};
+
+/// How long a claim link is valid for, if not specified in the request
+const DEFAULT_EXPIRES_IN: u32 = 7 * 24 * 60 * 60;
+
+/// The longest a claim link can be valid for
+const MAX_EXPIRES_IN: u32 = 90 * 24 * 60 * 60;
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("Password auth is disabled")]
+    PasswordAuthDisabled,
+
+    #[error("User ID {0} not found")]
+    UserNotFound(Ulid),
+
+    #[error("User ID {0} is locked or deactivated")]
+    UserNotActive(Ulid),
+
+    #[error("Email {email:?} is not valid")]
+    EmailNotValid {
+        email: String,
+
+        #[source]
+        source: lettre::address::AddressError,
+    },
+
+    #[error("The expiration must be between 1 and {MAX_EXPIRES_IN} seconds")]
+    InvalidExpiration,
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::PasswordAuthDisabled => StatusCode::FORBIDDEN,
+            Self::UserNotFound(_) => StatusCode::NOT_FOUND,
+            Self::UserNotActive(_) | Self::EmailNotValid { .. } | Self::InvalidExpiration => {
+                StatusCode::BAD_REQUEST
+            }
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+/// # JSON payload for the `POST /api/admin/v1/user-claim-links`
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename = "AddUserClaimLinkRequest")]
+pub struct Request {
+    /// The ID of the user who will claim their account with this link
+    #[schemars(with = "crate::admin::schema::Ulid")]
+    user_id: Ulid,
+
+    /// How long the link stays valid, in seconds. Defaults to 7 days, and can
+    /// be at most 90 days.
+    #[schemars(range(min = 1, max = 7_776_000))]
+    expires_in: Option<u32>,
+
+    /// If set, the link is sent to this email address
+    #[schemars(email)]
+    email: Option<String>,
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("addUserClaimLink")
+        .summary("Create a one-time link to claim an account")
+        .description(
+            r"Create a one-time link which lets someone set the password of a pre-provisioned account, and sign in with it.
+If an email address is given, the link is also sent to that address.
+Any previous link for the same user stays valid until it expires, is used or is revoked.",
+        )
+        .tag("user-claim-link")
+        .response_with::<201, Json<SingleResponse<UserClaimLink>>, _>(|t| {
+            let [sample, ..] = UserClaimLink::samples();
+            let response = SingleResponse::new_canonical(sample);
+            t.description("The claim link was created").example(response)
+        })
+        .response_with::<400, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::UserNotActive(Ulid::nil()));
+            t.description("The user is not active, or the parameters are invalid")
+                .example(response)
+        })
+        .response_with::<403, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::PasswordAuthDisabled);
+            t.description("Password auth is disabled in the server configuration")
+                .example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::UserNotFound(Ulid::nil()));
+            t.description("User was not found").example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.user_claim_links.add", skip_all)]
+pub async fn handler(
+    CallContext {
+        mut repo,
+        clock,
+        session,
+        ..
+    }: CallContext,
+    NoApi(mut rng): NoApi<BoxRng>,
+    State(password_manager): State<PasswordManager>,
+    State(url_builder): State<UrlBuilder>,
+    Json(params): Json<Request>,
+) -> Result<(StatusCode, Json<SingleResponse<UserClaimLink>>), RouteError> {
+    // Claiming an account means setting its password
+    if !password_manager.is_enabled() {
+        return Err(RouteError::PasswordAuthDisabled);
+    }
+
+    let expires_in = params.expires_in.unwrap_or(DEFAULT_EXPIRES_IN);
+    if expires_in == 0 || expires_in > MAX_EXPIRES_IN {
+        return Err(RouteError::InvalidExpiration);
+    }
+
+    if let Some(email) = params.email.as_deref() {
+        if let Err(source) = lettre::Address::from_str(email) {
+            return Err(RouteError::EmailNotValid {
+                email: email.to_owned(),
+                source,
+            });
+        }
+    }
+
+    let user = repo
+        .user()
+        .lookup(params.user_id)
+        .await?
+        .ok_or(RouteError::UserNotFound(params.user_id))?;
+
+    if !user.is_valid() {
+        return Err(RouteError::UserNotActive(user.id));
+    }
+
+    let token = Alphanumeric.sample_string(&mut rng, 32);
+    let expires_at = clock.now() + Duration::seconds(expires_in.into());
+
+    let link = repo
+        .user_claim_link()
+        .add(
+            &mut rng,
+            &clock,
+            &user,
+            token,
+            expires_at,
+            params.email,
+            Some(&session),
+        )
+        .await?;
+
+    if link.email.is_some() {
+        repo.queue_job()
+            .schedule_job(
+                &mut rng,
+                &clock,
+                SendUserClaimLinkEmailJob::new(&link, "en".to_owned()),
+            )
+            .await?;
+    }
+
+    repo.save().await?;
+
+    let url = url_builder.account_claim_link(link.token.clone());
+    Ok((
+        StatusCode::CREATED,
+        Json(SingleResponse::new_canonical(UserClaimLink::new(
+            link,
+            url,
+            clock.now(),
+        ))),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use insta::assert_json_snapshot;
+    use sqlx::{PgPool, types::Json};
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_create(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool.clone()).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let alice = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::post("/api/admin/v1/user-claim-links")
+            .bearer(&token)
+            .json(serde_json::json!({
+                "user_id": alice.id,
+                "email": "alice@example.com",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r#"
+        {
+          "data": {
+            "type": "user-claim-link",
+            "id": "01FSHN9AG0S8GWBXFNR0EXWYYD",
+            "attributes": {
+              "user_id": "01FSHN9AG0MZAA6S4AF7CTV32E",
+              "url": "https://example.com/claim/Yp7FM44zJN5qePGMLvvMXC4Ds1A3lCWc",
+              "email": "alice@example.com",
+              "valid": true,
+              "created_by_session_id": "01FSHN9AG0MKGTBNZ16RDR3PVY",
+              "created_at": "2022-01-16T14:40:00Z",
+              "expires_at": "2022-01-23T14:40:00Z",
+              "claimed_at": null,
+              "claimed_ip_address": null,
+              "claimed_user_agent": null,
+              "revoked_at": null
+            },
+            "links": {
+              "self": "/api/admin/v1/user-claim-links/01FSHN9AG0S8GWBXFNR0EXWYYD"
+            }
+          },
+          "links": {
+            "self": "/api/admin/v1/user-claim-links/01FSHN9AG0S8GWBXFNR0EXWYYD"
+          }
+        }
+        "#);
+
+        // It should have scheduled a job to send the link by email
+        let job: Json<serde_json::Value> = sqlx::query_scalar(
+            "SELECT payload FROM queue_jobs WHERE queue_name = 'send-user-claim-link-email'",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Email job to be scheduled");
+        assert_eq!(
+            job["user_claim_link_id"],
+            serde_json::json!("01FSHN9AG0S8GWBXFNR0EXWYYD")
+        );
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_create_errors(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let alice = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let bob = repo
+            .user()
+            .add(&mut rng, &state.clock, "bob".to_owned())
+            .await
+            .unwrap();
+        repo.user().lock(&state.clock, bob.clone()).await.unwrap();
+        repo.save().await.unwrap();
+
+        // Unknown user
+        let request = Request::post("/api/admin/v1/user-claim-links")
+            .bearer(&token)
+            .json(serde_json::json!({
+                "user_id": ulid::Ulid::nil(),
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+
+        // Locked user
+        let request = Request::post("/api/admin/v1/user-claim-links")
+            .bearer(&token)
+            .json(serde_json::json!({
+                "user_id": bob.id,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // Invalid email
+        let request = Request::post("/api/admin/v1/user-claim-links")
+            .bearer(&token)
+            .json(serde_json::json!({
+                "user_id": alice.id,
+                "email": "not an email",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // Expiration too long
+        let request = Request::post("/api/admin/v1/user-claim-links")
+            .bearer(&token)
+            .json(serde_json::json!({
+                "user_id": alice.id,
+                "expires_in": 100 * 24 * 60 * 60,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{Json, extract::State, response::IntoResponse};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_router::UrlBuilder;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::UserClaimLink,
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("Claim link with ID {0} not found")]
+    NotFound(Ulid),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("getUserClaimLink")
+        .summary("Get an account claim link")
+        .tag("user-claim-link")
+        .response_with::<200, Json<SingleResponse<UserClaimLink>>, _>(|t| {
+            let [sample, ..] = UserClaimLink::samples();
+            let response = SingleResponse::new_canonical(sample);
+            t.description("Claim link was found").example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("Claim link was not found").example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.user_claim_links.get", skip_all)]
+pub async fn handler(
+    CallContext {
+        mut repo, clock, ..
+    }: CallContext,
+    State(url_builder): State<UrlBuilder>,
+    id: UlidPathParam,
+) -> Result<Json<SingleResponse<UserClaimLink>>, RouteError> {
+    let link = repo
+        .user_claim_link()
+        .lookup(*id)
+        .await?
+        .ok_or(RouteError::NotFound(*id))?;
+
+    let url = url_builder.account_claim_link(link.token.clone());
+    Ok(Json(SingleResponse::new_canonical(UserClaimLink::new(
+        link,
+        url,
+        clock.now(),
+    ))))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use insta::assert_json_snapshot;
+    use mas_storage::Clock as _;
+    use sqlx::PgPool;
+    use ulid::Ulid;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_get(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let alice = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let link = repo
+            .user_claim_link()
+            .add(
+                &mut rng,
+                &state.clock,
+                &alice,
+                "alicetoken".to_owned(),
+                state.clock.now() + Duration::days(1),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::get(format!("/api/admin/v1/user-claim-links/{}", link.id))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r#"
+        {
+          "data": {
+            "type": "user-claim-link",
+            "id": "01FSHN9AG0AJ6AC5HQ9X6H4RP4",
+            "attributes": {
+              "user_id": "01FSHN9AG0MZAA6S4AF7CTV32E",
+              "url": "https://example.com/claim/alicetoken",
+              "email": null,
+              "valid": true,
+              "created_by_session_id": null,
+              "created_at": "2022-01-16T14:40:00Z",
+              "expires_at": "2022-01-17T14:40:00Z",
+              "claimed_at": null,
+              "claimed_ip_address": null,
+              "claimed_user_agent": null,
+              "revoked_at": null
+            },
+            "links": {
+              "self": "/api/admin/v1/user-claim-links/01FSHN9AG0AJ6AC5HQ9X6H4RP4"
+            }
+          },
+          "links": {
+            "self": "/api/admin/v1/user-claim-links/01FSHN9AG0AJ6AC5HQ9X6H4RP4"
+          }
+        }
+        "#);
+
+        // Once expired, the link is reported as invalid
+        state.clock.advance(Duration::days(2));
+        let request = Request::get(format!("/api/admin/v1/user-claim-links/{}", link.id))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["data"]["attributes"]["valid"], false);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_not_found(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let request = Request::get(format!("/api/admin/v1/user-claim-links/{}", Ulid::nil()))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
+}
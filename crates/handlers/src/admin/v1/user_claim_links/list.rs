@@ -0,0 +1,347 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{
+    Json,
+    extract::{Query, State, rejection::QueryRejection},
+    response::IntoResponse,
+};
+use axum_macros::FromRequestParts;
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_router::UrlBuilder;
+use mas_storage::{
+    Page,
+    user::{UserClaimLinkFilter, UserClaimLinkState},
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::{Resource, UserClaimLink},
+        params::Pagination,
+        response::{ErrorResponse, PaginatedResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Deserialize, JsonSchema, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum UserClaimLinkStatus {
+    Active,
+    Claimed,
+    Revoked,
+    Expired,
+}
+
+impl std::fmt::Display for UserClaimLinkStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Active => write!(f, "active"),
+            Self::Claimed => write!(f, "claimed"),
+            Self::Revoked => write!(f, "revoked"),
+            Self::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+#[derive(FromRequestParts, Deserialize, JsonSchema, OperationIo)]
+#[serde(rename = "UserClaimLinkFilter")]
+#[aide(input_with = "Query<FilterParams>")]
+#[from_request(via(Query), rejection(RouteError))]
+pub struct FilterParams {
+    /// Retrieve the items for the given user
+    #[serde(rename = "filter[user]")]
+    #[schemars(with = "Option<crate::admin::schema::Ulid>")]
+    user: Option<Ulid>,
+
+    /// Retrieve the items with the given status
+    ///
+    /// Defaults to retrieve all links, including claimed, revoked and expired
+    /// ones.
+    ///
+    /// * `active`: Only retrieve links which can still be used
+    ///
+    /// * `claimed`: Only retrieve links which were used to claim the account
+    ///
+    /// * `revoked`: Only retrieve links which were revoked before being used
+    ///
+    /// * `expired`: Only retrieve links which expired before being used
+    #[serde(rename = "filter[status]")]
+    status: Option<UserClaimLinkStatus>,
+}
+
+impl std::fmt::Display for FilterParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut sep = '?';
+
+        if let Some(user) = self.user {
+            write!(f, "{sep}filter[user]={user}")?;
+            sep = '&';
+        }
+
+        if let Some(status) = self.status {
+            write!(f, "{sep}filter[status]={status}")?;
+            sep = '&';
+        }
+
+        let _ = sep;
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("User ID {0} not found")]
+    UserNotFound(Ulid),
+
+    #[error("Invalid filter parameters")]
+    InvalidFilter(#[from] QueryRejection),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::UserNotFound(_) => StatusCode::NOT_FOUND,
+            Self::InvalidFilter(_) => StatusCode::BAD_REQUEST,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("listUserClaimLinks")
+        .summary("List account claim links")
+        .tag("user-claim-link")
+        .response_with::<200, Json<PaginatedResponse<UserClaimLink>>, _>(|t| {
+            let links = UserClaimLink::samples();
+            let pagination = mas_storage::Pagination::first(links.len());
+            let page = Page {
+                edges: links.into(),
+                has_next_page: true,
+                has_previous_page: false,
+            };
+
+            t.description("Paginated response of account claim links")
+                .example(PaginatedResponse::new(
+                    page,
+                    pagination,
+                    42,
+                    UserClaimLink::PATH,
+                ))
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::UserNotFound(Ulid::nil()));
+            t.description("User was not found").example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.user_claim_links.list", skip_all)]
+pub async fn handler(
+    CallContext {
+        mut repo, clock, ..
+    }: CallContext,
+    State(url_builder): State<UrlBuilder>,
+    Pagination(pagination): Pagination,
+    params: FilterParams,
+) -> Result<Json<PaginatedResponse<UserClaimLink>>, RouteError> {
+    let base = format!("{path}{params}", path = UserClaimLink::PATH);
+    let now = clock.now();
+    let filter = UserClaimLinkFilter::new(now);
+
+    // Load the user from the filter
+    let user = if let Some(user_id) = params.user {
+        let user = repo
+            .user()
+            .lookup(user_id)
+            .await?
+            .ok_or(RouteError::UserNotFound(user_id))?;
+
+        Some(user)
+    } else {
+        None
+    };
+
+    let filter = match &user {
+        Some(user) => filter.for_user(user),
+        None => filter,
+    };
+
+    let filter = match params.status {
+        Some(UserClaimLinkStatus::Active) => filter.with_state(UserClaimLinkState::Active),
+        Some(UserClaimLinkStatus::Claimed) => filter.with_state(UserClaimLinkState::Claimed),
+        Some(UserClaimLinkStatus::Revoked) => filter.with_state(UserClaimLinkState::Revoked),
+        Some(UserClaimLinkStatus::Expired) => filter.with_state(UserClaimLinkState::Expired),
+        None => filter,
+    };
+
+    let page = repo.user_claim_link().list(filter, pagination).await?;
+    let count = repo.user_claim_link().count(filter).await?;
+
+    Ok(Json(PaginatedResponse::new(
+        page.map(|link| {
+            let url = url_builder.account_claim_link(link.token.clone());
+            UserClaimLink::new(link, url, now)
+        }),
+        pagination,
+        count,
+        &base,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use mas_storage::Clock as _;
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_list(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let alice = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let bob = repo
+            .user()
+            .add(&mut rng, &state.clock, "bob".to_owned())
+            .await
+            .unwrap();
+
+        let expires_at = state.clock.now() + Duration::days(1);
+        repo.user_claim_link()
+            .add(
+                &mut rng,
+                &state.clock,
+                &alice,
+                "alice1".to_owned(),
+                expires_at,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let revoked = repo
+            .user_claim_link()
+            .add(
+                &mut rng,
+                &state.clock,
+                &alice,
+                "alice2".to_owned(),
+                expires_at,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        repo.user_claim_link()
+            .revoke(&state.clock, revoked)
+            .await
+            .unwrap();
+        let claimed = repo
+            .user_claim_link()
+            .add(
+                &mut rng,
+                &state.clock,
+                &bob,
+                "bob1".to_owned(),
+                expires_at,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        repo.user_claim_link()
+            .claim(&state.clock, claimed, None, None)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let count = |body: &serde_json::Value| body["meta"]["count"].as_u64().unwrap();
+
+        let request = Request::get("/api/admin/v1/user-claim-links")
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(count(&body), 3);
+        assert_eq!(
+            body["data"][0]["attributes"]["url"],
+            "https://example.com/claim/alice1"
+        );
+
+        let request = Request::get(format!(
+            "/api/admin/v1/user-claim-links?filter[user]={}",
+            alice.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(count(&body), 2);
+
+        for (status, expected) in [
+            ("active", 1),
+            ("revoked", 1),
+            ("claimed", 1),
+            ("expired", 0),
+        ] {
+            let request = Request::get(format!(
+                "/api/admin/v1/user-claim-links?filter[status]={status}"
+            ))
+            .bearer(&token)
+            .empty();
+            let response = state.request(request).await;
+            response.assert_status(StatusCode::OK);
+            let body: serde_json::Value = response.json();
+            assert_eq!(count(&body), expected, "status {status}");
+        }
+
+        // After a day, the active link expired
+        state.clock.advance(Duration::days(1));
+        let request = Request::get("/api/admin/v1/user-claim-links?filter[status]=expired")
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(count(&body), 1);
+
+        // Unknown user
+        let request = Request::get(format!(
+            "/api/admin/v1/user-claim-links?filter[user]={}",
+            ulid::Ulid::nil()
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
+}
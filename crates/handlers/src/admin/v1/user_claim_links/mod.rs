@@ -0,0 +1,16 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+mod add;
+mod get;
+mod list;
+mod revoke;
+
+pub use self::{
+    add::{doc as add_doc, handler as add},
+    get::{doc as get_doc, handler as get},
+    list::{doc as list_doc, handler as list},
+    revoke::{doc as revoke_doc, handler as revoke},
+};
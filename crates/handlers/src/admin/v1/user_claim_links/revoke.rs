@@ -0,0 +1,199 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{Json, extract::State, response::IntoResponse};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_router::UrlBuilder;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::{Resource, UserClaimLink},
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("Claim link with ID {0} not found")]
+    NotFound(Ulid),
+
+    #[error("Claim link with ID {0} is already revoked")]
+    AlreadyRevoked(Ulid),
+
+    #[error("Claim link with ID {0} was already used")]
+    AlreadyClaimed(Ulid),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::AlreadyRevoked(_) | Self::AlreadyClaimed(_) => StatusCode::BAD_REQUEST,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("revokeUserClaimLink")
+        .summary("Revoke an account claim link")
+        .description(
+            "Calling this endpoint prevents the link from being used to claim the account.",
+        )
+        .tag("user-claim-link")
+        .response_with::<200, Json<SingleResponse<UserClaimLink>>, _>(|t| {
+            let [sample, ..] = UserClaimLink::samples();
+            let id = sample.id();
+            let response = SingleResponse::new(
+                sample,
+                format!("/api/admin/v1/user-claim-links/{id}/revoke"),
+            );
+            t.description("Claim link was revoked").example(response)
+        })
+        .response_with::<400, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::AlreadyClaimed(Ulid::nil()));
+            t.description("Claim link was already used or revoked")
+                .example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("Claim link was not found").example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.user_claim_links.revoke", skip_all)]
+pub async fn handler(
+    CallContext {
+        mut repo, clock, ..
+    }: CallContext,
+    State(url_builder): State<UrlBuilder>,
+    id: UlidPathParam,
+) -> Result<Json<SingleResponse<UserClaimLink>>, RouteError> {
+    let id = *id;
+    let link = repo
+        .user_claim_link()
+        .lookup(id)
+        .await?
+        .ok_or(RouteError::NotFound(id))?;
+
+    if link.claimed_at.is_some() {
+        return Err(RouteError::AlreadyClaimed(id));
+    }
+
+    if link.revoked_at.is_some() {
+        return Err(RouteError::AlreadyRevoked(id));
+    }
+
+    let link = repo.user_claim_link().revoke(&clock, link).await?;
+
+    repo.save().await?;
+
+    let url = url_builder.account_claim_link(link.token.clone());
+    Ok(Json(SingleResponse::new(
+        UserClaimLink::new(link, url, clock.now()),
+        format!("/api/admin/v1/user-claim-links/{id}/revoke"),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use mas_storage::Clock as _;
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_revoke(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let alice = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let link = repo
+            .user_claim_link()
+            .add(
+                &mut rng,
+                &state.clock,
+                &alice,
+                "alicetoken".to_owned(),
+                state.clock.now() + Duration::days(1),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let claimed = repo
+            .user_claim_link()
+            .add(
+                &mut rng,
+                &state.clock,
+                &alice,
+                "alicetoken2".to_owned(),
+                state.clock.now() + Duration::days(1),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        repo.user_claim_link()
+            .claim(&state.clock, claimed.clone(), None, None)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::post(format!("/api/admin/v1/user-claim-links/{}/revoke", link.id))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["data"]["attributes"]["valid"], false);
+        assert_eq!(
+            body["data"]["attributes"]["revoked_at"],
+            serde_json::json!(state.clock.now())
+        );
+
+        // Revoking it again fails
+        let request = Request::post(format!("/api/admin/v1/user-claim-links/{}/revoke", link.id))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // So does revoking a link which was already used
+        let request = Request::post(format!(
+            "/api/admin/v1/user-claim-links/{}/revoke",
+            claimed.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+}
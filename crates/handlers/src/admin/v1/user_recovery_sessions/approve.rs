@@ -0,0 +1,218 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{NoApi, OperationIo, transform::TransformOperation};
+use axum::{Json, response::IntoResponse};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_storage::{
+    BoxRng,
+    queue::{QueueJobRepositoryExt as _, SendAccountRecoveryEmailsJob},
+};
+use tracing::info;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::{Resource, UserRecoverySession},
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("Recovery session with ID {0} not found")]
+    NotFound(Ulid),
+
+    #[error("Recovery session with ID {0} is not waiting for an approval")]
+    NotPending(Ulid),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::NotPending(_) => StatusCode::BAD_REQUEST,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("approveUserRecoverySession")
+        .summary("Approve an account recovery request")
+        .description(
+            "Calling this endpoint lets the recovery go ahead: the recovery links are sent to the email address the request was made for.",
+        )
+        .tag("user-recovery-session")
+        .response_with::<200, Json<SingleResponse<UserRecoverySession>>, _>(|t| {
+            let [sample, ..] = UserRecoverySession::samples();
+            let id = sample.id();
+            let response = SingleResponse::new(
+                sample,
+                format!("/api/admin/v1/user-recovery-sessions/{id}/approve"),
+            );
+            t.description("Recovery session was approved").example(response)
+        })
+        .response_with::<400, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotPending(Ulid::nil()));
+            t.description("Recovery session is not waiting for an approval")
+                .example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("Recovery session was not found")
+                .example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.user_recovery_sessions.approve", skip_all)]
+pub async fn handler(
+    CallContext {
+        mut repo, clock, ..
+    }: CallContext,
+    NoApi(mut rng): NoApi<BoxRng>,
+    id: UlidPathParam,
+) -> Result<Json<SingleResponse<UserRecoverySession>>, RouteError> {
+    let id = *id;
+    let session = repo
+        .user_recovery()
+        .lookup_session(id)
+        .await?
+        .ok_or(RouteError::NotFound(id))?;
+
+    if !session.is_pending_approval() {
+        return Err(RouteError::NotPending(id));
+    }
+
+    let session = repo
+        .user_recovery()
+        .approve_session(&clock, session)
+        .await?;
+
+    info!(user_recovery_session.id = %session.id, "Recovery session approved, notifying the user");
+    repo.queue_job()
+        .schedule_job(
+            &mut rng,
+            &clock,
+            SendAccountRecoveryEmailsJob::new(&session),
+        )
+        .await?;
+
+    repo.save().await?;
+
+    Ok(Json(SingleResponse::new(
+        UserRecoverySession::from(session),
+        format!("/api/admin/v1/user-recovery-sessions/{id}/approve"),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use mas_storage::Clock as _;
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_approve(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let pending = repo
+            .user_recovery()
+            .add_session(
+                &mut rng,
+                &state.clock,
+                "alice@example.com".to_owned(),
+                "Mozilla/5.0".to_owned(),
+                None,
+                "en".to_owned(),
+                true,
+                true,
+            )
+            .await
+            .unwrap();
+        let unreviewed = repo
+            .user_recovery()
+            .add_session(
+                &mut rng,
+                &state.clock,
+                "bob@example.com".to_owned(),
+                "Mozilla/5.0".to_owned(),
+                None,
+                "en".to_owned(),
+                false,
+                false,
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::post(format!(
+            "/api/admin/v1/user-recovery-sessions/{}/approve",
+            pending.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body["data"]["attributes"]["approved_at"],
+            serde_json::json!(state.clock.now())
+        );
+        assert_eq!(
+            body["data"]["attributes"]["denied_at"],
+            serde_json::Value::Null
+        );
+
+        // Doing it again fails, as the session was already reviewed
+        let request = Request::post(format!(
+            "/api/admin/v1/user-recovery-sessions/{}/approve",
+            pending.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // So does reviewing a session which didn't need an approval
+        let request = Request::post(format!(
+            "/api/admin/v1/user-recovery-sessions/{}/approve",
+            unreviewed.id
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        let request = Request::post(format!(
+            "/api/admin/v1/user-recovery-sessions/{}/approve",
+            ulid::Ulid::nil()
+        ))
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
+}
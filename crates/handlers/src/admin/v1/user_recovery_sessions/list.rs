@@ -0,0 +1,240 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{
+    Json,
+    extract::{Query, rejection::QueryRejection},
+    response::IntoResponse,
+};
+use axum_macros::FromRequestParts;
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_storage::{
+    Page,
+    user::{UserRecoverySessionApprovalState, UserRecoverySessionFilter},
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::{Resource, UserRecoverySession},
+        params::Pagination,
+        response::{ErrorResponse, PaginatedResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Deserialize, JsonSchema, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum UserRecoverySessionStatus {
+    Pending,
+    Approved,
+    Denied,
+}
+
+impl std::fmt::Display for UserRecoverySessionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Approved => write!(f, "approved"),
+            Self::Denied => write!(f, "denied"),
+        }
+    }
+}
+
+#[derive(FromRequestParts, Deserialize, JsonSchema, OperationIo)]
+#[serde(rename = "UserRecoverySessionFilter")]
+#[aide(input_with = "Query<FilterParams>")]
+#[from_request(via(Query), rejection(RouteError))]
+pub struct FilterParams {
+    /// Retrieve the items with the given approval status
+    ///
+    /// Defaults to retrieve all recovery requests, including the ones which
+    /// didn't need an approval. Those never match a status filter.
+    ///
+    /// * `pending`: Only retrieve requests waiting for an administrator
+    ///
+    /// * `approved`: Only retrieve requests which were approved
+    ///
+    /// * `denied`: Only retrieve requests which were denied
+    #[serde(rename = "filter[status]")]
+    status: Option<UserRecoverySessionStatus>,
+}
+
+impl std::fmt::Display for FilterParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(status) = self.status {
+            write!(f, "?filter[status]={status}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("Invalid filter parameters")]
+    InvalidFilter(#[from] QueryRejection),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::InvalidFilter(_) => StatusCode::BAD_REQUEST,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("listUserRecoverySessions")
+        .summary("List account recovery requests")
+        .tag("user-recovery-session")
+        .response_with::<200, Json<PaginatedResponse<UserRecoverySession>>, _>(|t| {
+            let sessions = UserRecoverySession::samples();
+            let pagination = mas_storage::Pagination::first(sessions.len());
+            let page = Page {
+                edges: sessions.into(),
+                has_next_page: true,
+                has_previous_page: false,
+            };
+
+            t.description("Paginated response of account recovery requests")
+                .example(PaginatedResponse::new(
+                    page,
+                    pagination,
+                    42,
+                    UserRecoverySession::PATH,
+                ))
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.user_recovery_sessions.list", skip_all)]
+pub async fn handler(
+    CallContext { mut repo, .. }: CallContext,
+    Pagination(pagination): Pagination,
+    params: FilterParams,
+) -> Result<Json<PaginatedResponse<UserRecoverySession>>, RouteError> {
+    let base = format!("{path}{params}", path = UserRecoverySession::PATH);
+    let filter = UserRecoverySessionFilter::new();
+
+    let filter = match params.status {
+        Some(UserRecoverySessionStatus::Pending) => {
+            filter.with_approval_state(UserRecoverySessionApprovalState::Pending)
+        }
+        Some(UserRecoverySessionStatus::Approved) => {
+            filter.with_approval_state(UserRecoverySessionApprovalState::Approved)
+        }
+        Some(UserRecoverySessionStatus::Denied) => {
+            filter.with_approval_state(UserRecoverySessionApprovalState::Denied)
+        }
+        None => filter,
+    };
+
+    let page = repo
+        .user_recovery()
+        .list_sessions(filter, pagination)
+        .await?;
+    let count = repo.user_recovery().count_sessions(filter).await?;
+
+    Ok(Json(PaginatedResponse::new(
+        page.map(UserRecoverySession::from),
+        pagination,
+        count,
+        &base,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_list(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let mut sessions = Vec::new();
+        for (email, requires_approval) in [
+            ("alice@example.com", true),
+            ("bob@example.com", true),
+            ("charlie@example.com", true),
+            ("dave@example.com", false),
+        ] {
+            let session = repo
+                .user_recovery()
+                .add_session(
+                    &mut rng,
+                    &state.clock,
+                    email.to_owned(),
+                    "Mozilla/5.0".to_owned(),
+                    None,
+                    "en".to_owned(),
+                    false,
+                    requires_approval,
+                )
+                .await
+                .unwrap();
+            sessions.push(session);
+        }
+        repo.user_recovery()
+            .approve_session(&state.clock, sessions[1].clone())
+            .await
+            .unwrap();
+        repo.user_recovery()
+            .deny_session(&state.clock, sessions[2].clone())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let count = |body: &serde_json::Value| body["meta"]["count"].as_u64().unwrap();
+
+        let request = Request::get("/api/admin/v1/user-recovery-sessions")
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(count(&body), 4);
+
+        // Sessions which didn't need an approval match none of the statuses
+        for (status, expected) in [("pending", 1), ("approved", 1), ("denied", 1)] {
+            let request = Request::get(format!(
+                "/api/admin/v1/user-recovery-sessions?filter[status]={status}"
+            ))
+            .bearer(&token)
+            .empty();
+            let response = state.request(request).await;
+            response.assert_status(StatusCode::OK);
+            let body: serde_json::Value = response.json();
+            assert_eq!(count(&body), expected, "status {status}");
+        }
+
+        let request = Request::get("/api/admin/v1/user-recovery-sessions?filter[status]=foo")
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,16 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+mod approve;
+mod deny;
+mod get;
+mod list;
+
+pub use self::{
+    approve::{doc as approve_doc, handler as approve},
+    deny::{doc as deny_doc, handler as deny},
+    get::{doc as get_doc, handler as get},
+    list::{doc as list_doc, handler as list},
+};
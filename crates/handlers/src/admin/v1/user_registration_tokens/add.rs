@@ -13,6 +13,7 @@ use mas_storage::BoxRng;
 use rand::distributions::{Alphanumeric, DistString};
 use schemars::JsonSchema;
 use serde::Deserialize;
+use ulid::Ulid;
 
 use crate::{
     admin::{
@@ -29,6 +30,12 @@ pub enum RouteError {
     #[error("A registration token with the same token already exists")]
     Conflict(mas_data_model::UserRegistrationToken),
 
+    #[error("User ID {0} not found")]
+    UserNotFound(Ulid),
+
+    #[error("Assigning a referrer requires the token to have a creator")]
+    ReferrerWithoutCreator,
+
     #[error(transparent)]
     Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
 }
@@ -41,6 +48,8 @@ impl IntoResponse for RouteError {
         let sentry_event_id = record_error!(self, Self::Internal(_));
         let status = match self {
             Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::UserNotFound(_) => StatusCode::NOT_FOUND,
+            Self::ReferrerWithoutCreator => StatusCode::BAD_REQUEST,
             Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
         (status, sentry_event_id, Json(error)).into_response()
@@ -60,6 +69,16 @@ pub struct Request {
 
     /// When the token expires. If not provided, the token never expires.
     expires_at: Option<DateTime<Utc>>,
+
+    /// The ID of the user issuing this token as an invite. If not provided,
+    /// the token is not attributed to any user.
+    #[schemars(with = "Option<crate::admin::schema::Ulid>")]
+    created_by_user_id: Option<Ulid>,
+
+    /// Whether users registering with this token should get the issuing user
+    /// recorded as their referrer. Requires `created_by_user_id` to be set.
+    #[serde(default)]
+    assign_referrer: bool,
 }
 
 pub fn doc(operation: TransformOperation) -> TransformOperation {
@@ -73,6 +92,15 @@ pub fn doc(operation: TransformOperation) -> TransformOperation {
             t.description("A new user registration token was created")
                 .example(response)
         })
+        .response_with::<400, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::ReferrerWithoutCreator);
+            t.description("A referrer was requested without a creator")
+                .example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::UserNotFound(Ulid::nil()));
+            t.description("The creator was not found").example(response)
+        })
 }
 
 #[tracing::instrument(name = "handler.admin.v1.user_registration_tokens.post", skip_all)]
@@ -83,6 +111,22 @@ pub async fn handler(
     NoApi(mut rng): NoApi<BoxRng>,
     Json(params): Json<Request>,
 ) -> Result<(StatusCode, Json<SingleResponse<UserRegistrationToken>>), RouteError> {
+    // Find the user issuing the token, if any
+    let created_by = if let Some(user_id) = params.created_by_user_id {
+        let user = repo
+            .user()
+            .lookup(user_id)
+            .await?
+            .ok_or(RouteError::UserNotFound(user_id))?;
+        Some(user)
+    } else {
+        None
+    };
+
+    if params.assign_referrer && created_by.is_none() {
+        return Err(RouteError::ReferrerWithoutCreator);
+    }
+
     // Generate a random token if none was provided
     let token = params
         .token
@@ -94,16 +138,29 @@ pub async fn handler(
         return Err(RouteError::Conflict(existing_token));
     }
 
-    let registration_token = repo
-        .user_registration_token()
-        .add(
-            &mut rng,
-            &clock,
-            token,
-            params.usage_limit,
-            params.expires_at,
-        )
-        .await?;
+    let registration_token = if let Some(created_by) = &created_by {
+        repo.user_registration_token()
+            .add_invite(
+                &mut rng,
+                &clock,
+                created_by,
+                token,
+                params.usage_limit,
+                params.expires_at,
+                params.assign_referrer,
+            )
+            .await?
+    } else {
+        repo.user_registration_token()
+            .add(
+                &mut rng,
+                &clock,
+                token,
+                params.usage_limit,
+                params.expires_at,
+            )
+            .await?
+    };
 
     repo.save().await?;
 
@@ -121,6 +178,7 @@ mod tests {
     use hyper::{Request, StatusCode};
     use insta::assert_json_snapshot;
     use sqlx::PgPool;
+    use ulid::Ulid;
 
     use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
 
@@ -153,7 +211,9 @@ mod tests {
               "created_at": "2022-01-16T14:40:00Z",
               "last_used_at": null,
               "expires_at": null,
-              "revoked_at": null
+              "revoked_at": null,
+              "created_by_user_id": null,
+              "assign_referrer": false
             },
             "links": {
               "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0MZAA6S4AF7CTV32E"
@@ -195,7 +255,9 @@ mod tests {
               "created_at": "2022-01-16T14:40:00Z",
               "last_used_at": null,
               "expires_at": null,
-              "revoked_at": null
+              "revoked_at": null,
+              "created_by_user_id": null,
+              "assign_referrer": false
             },
             "links": {
               "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0QMGC989M0XSFVF2X"
@@ -238,7 +300,9 @@ mod tests {
               "created_at": "2022-01-16T14:40:00Z",
               "last_used_at": null,
               "expires_at": null,
-              "revoked_at": null
+              "revoked_at": null,
+              "created_by_user_id": null,
+              "assign_referrer": false
             },
             "links": {
               "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0MZAA6S4AF7CTV32E"
@@ -259,4 +323,62 @@ mod tests {
         let response = state.request(request).await;
         response.assert_status(StatusCode::CONFLICT);
     }
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_create_invite(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let alice = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::post("/api/admin/v1/user-registration-tokens")
+            .bearer(&token)
+            .json(serde_json::json!({
+                "token": "alice_invite",
+                "usage_limit": 1,
+                "created_by_user_id": alice.id,
+                "assign_referrer": true,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let body: serde_json::Value = response.json();
+
+        assert_eq!(
+            body["data"]["attributes"]["created_by_user_id"],
+            serde_json::json!(alice.id)
+        );
+        assert_eq!(body["data"]["attributes"]["assign_referrer"], true);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_create_invite_errors(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        // Unknown creator
+        let request = Request::post("/api/admin/v1/user-registration-tokens")
+            .bearer(&token)
+            .json(serde_json::json!({
+                "created_by_user_id": Ulid::nil(),
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+
+        // Referrer without a creator
+        let request = Request::post("/api/admin/v1/user-registration-tokens")
+            .bearer(&token)
+            .json(serde_json::json!({
+                "assign_referrer": true,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
 }
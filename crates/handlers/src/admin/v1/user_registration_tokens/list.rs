@@ -16,6 +16,7 @@ use mas_axum_utils::record_error;
 use mas_storage::{Page, user::UserRegistrationTokenFilter};
 use schemars::JsonSchema;
 use serde::Deserialize;
+use ulid::Ulid;
 
 use crate::{
     admin::{
@@ -50,6 +51,11 @@ pub struct FilterParams {
     /// reached its usage limit.
     #[serde(rename = "filter[valid]")]
     valid: Option<bool>,
+
+    /// Retrieve tokens issued as invites by the given user
+    #[serde(rename = "filter[created_by]")]
+    #[schemars(with = "Option<crate::admin::schema::Ulid>")]
+    created_by: Option<Ulid>,
 }
 
 impl std::fmt::Display for FilterParams {
@@ -72,6 +78,10 @@ impl std::fmt::Display for FilterParams {
             write!(f, "{sep}filter[valid]={valid}")?;
             sep = '&';
         }
+        if let Some(created_by) = self.created_by {
+            write!(f, "{sep}filter[created_by]={created_by}")?;
+            sep = '&';
+        }
 
         let _ = sep;
         Ok(())
@@ -86,6 +96,9 @@ pub enum RouteError {
 
     #[error("Invalid filter parameters")]
     InvalidFilter(#[from] QueryRejection),
+
+    #[error("User ID {0} not found")]
+    UserNotFound(Ulid),
 }
 
 impl_from_error_for_route!(mas_storage::RepositoryError);
@@ -97,6 +110,7 @@ impl IntoResponse for RouteError {
         let status = match self {
             Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::InvalidFilter(_) => StatusCode::BAD_REQUEST,
+            Self::UserNotFound(_) => StatusCode::NOT_FOUND,
         };
 
         (status, sentry_event_id, Json(error)).into_response()
@@ -125,6 +139,10 @@ pub fn doc(operation: TransformOperation) -> TransformOperation {
                     UserRegistrationToken::PATH,
                 ))
         })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::UserNotFound(Ulid::nil()));
+            t.description("User was not found").example(response)
+        })
 }
 
 #[tracing::instrument(name = "handler.admin.v1.registration_tokens.list", skip_all)]
@@ -155,6 +173,15 @@ pub async fn handler(
         filter = filter.with_valid(valid);
     }
 
+    if let Some(user_id) = params.created_by {
+        let user = repo
+            .user()
+            .lookup(user_id)
+            .await?
+            .ok_or(RouteError::UserNotFound(user_id))?;
+        filter = filter.with_created_by(&user);
+    }
+
     let page = repo
         .user_registration_token()
         .list(filter, pagination)
@@ -296,7 +323,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": "2022-01-15T14:40:00Z",
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG064K8BYZXSY5G511Z"
@@ -313,7 +342,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG07HNEZXNQM2KNBNF6"
@@ -330,7 +361,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG09AVTNSQFMSR34AJC"
@@ -347,7 +380,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": null,
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0MZAA6S4AF7CTV32E"
@@ -364,7 +399,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0S3ZJD8CXQ7F11KXN"
@@ -412,7 +449,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG07HNEZXNQM2KNBNF6"
@@ -429,7 +468,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0S3ZJD8CXQ7F11KXN"
@@ -469,7 +510,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": "2022-01-15T14:40:00Z",
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG064K8BYZXSY5G511Z"
@@ -486,7 +529,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG09AVTNSQFMSR34AJC"
@@ -503,7 +548,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": null,
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0MZAA6S4AF7CTV32E"
@@ -551,7 +598,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG09AVTNSQFMSR34AJC"
@@ -568,7 +617,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0S3ZJD8CXQ7F11KXN"
@@ -608,7 +659,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": "2022-01-15T14:40:00Z",
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG064K8BYZXSY5G511Z"
@@ -625,7 +678,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG07HNEZXNQM2KNBNF6"
@@ -642,7 +697,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": null,
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0MZAA6S4AF7CTV32E"
@@ -690,7 +747,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": "2022-01-15T14:40:00Z",
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG064K8BYZXSY5G511Z"
@@ -730,7 +789,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG07HNEZXNQM2KNBNF6"
@@ -747,7 +808,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG09AVTNSQFMSR34AJC"
@@ -764,7 +827,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": null,
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0MZAA6S4AF7CTV32E"
@@ -781,7 +846,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0S3ZJD8CXQ7F11KXN"
@@ -829,7 +896,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG07HNEZXNQM2KNBNF6"
@@ -846,7 +915,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": null,
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0MZAA6S4AF7CTV32E"
@@ -886,7 +957,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": "2022-01-15T14:40:00Z",
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG064K8BYZXSY5G511Z"
@@ -903,7 +976,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG09AVTNSQFMSR34AJC"
@@ -920,7 +995,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0S3ZJD8CXQ7F11KXN"
@@ -970,7 +1047,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0S3ZJD8CXQ7F11KXN"
@@ -1018,7 +1097,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": "2022-01-15T14:40:00Z",
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG064K8BYZXSY5G511Z"
@@ -1035,7 +1116,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG07HNEZXNQM2KNBNF6"
@@ -1076,7 +1159,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG09AVTNSQFMSR34AJC"
@@ -1093,7 +1178,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": null,
                 "expires_at": null,
-                "revoked_at": null
+                "revoked_at": null,
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0MZAA6S4AF7CTV32E"
@@ -1134,7 +1221,9 @@ mod tests {
                 "created_at": "2022-01-16T14:40:00Z",
                 "last_used_at": "2022-01-16T14:40:00Z",
                 "expires_at": null,
-                "revoked_at": "2022-01-16T14:40:00Z"
+                "revoked_at": "2022-01-16T14:40:00Z",
+                "created_by_user_id": null,
+                "assign_referrer": false
               },
               "links": {
                 "self": "/api/admin/v1/user-registration-tokens/01FSHN9AG0S3ZJD8CXQ7F11KXN"
@@ -1172,4 +1261,59 @@ mod tests {
                 .contains("Invalid filter parameters")
         );
     }
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_filter_by_created_by(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let admin_token = state.token_with_scope("urn:mas:admin").await;
+        create_test_tokens(&mut state).await;
+
+        let mut repo = state.repository().await.unwrap();
+        let alice = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let invite = repo
+            .user_registration_token()
+            .add_invite(
+                &mut state.rng(),
+                &state.clock,
+                &alice,
+                "alice_invite".to_owned(),
+                Some(1),
+                None,
+                true,
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::get(format!(
+            "/api/admin/v1/user-registration-tokens?filter[created_by]={}",
+            alice.id
+        ))
+        .bearer(&admin_token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+
+        assert_eq!(body["meta"]["count"], 1);
+        assert_eq!(body["data"][0]["id"], invite.id.to_string());
+        assert_eq!(
+            body["data"][0]["attributes"]["created_by_user_id"],
+            alice.id.to_string()
+        );
+
+        // Filtering on an unknown user is a 404
+        let request = Request::get(format!(
+            "/api/admin/v1/user-registration-tokens?filter[created_by]={}",
+            ulid::Ulid::nil()
+        ))
+        .bearer(&admin_token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
 }
@@ -123,7 +123,10 @@ mod tests {
               "user_id": "01FSHN9AG0MZAA6S4AF7CTV32E",
               "user_agent": null,
               "last_active_at": null,
-              "last_active_ip": null
+              "last_active_ip": null,
+              "last_active_country": null,
+              "last_active_asn": null,
+              "last_active_as_organization": null
             },
             "links": {
               "self": "/api/admin/v1/user-sessions/01FSHN9AG0AJ6AC5HQ9X6H4RP4"
@@ -21,7 +21,7 @@ use crate::{
     admin::{
         call_context::CallContext,
         model::{Resource, UserSession},
-        params::Pagination,
+        params::{EstimateCount, Pagination},
         response::{ErrorResponse, PaginatedResponse},
     },
     impl_from_error_for_route,
@@ -146,6 +146,7 @@ Use the `filter[status]` parameter to filter the sessions by their status and `p
 pub async fn handler(
     CallContext { mut repo, .. }: CallContext,
     Pagination(pagination): Pagination,
+    EstimateCount(estimate): EstimateCount,
     params: FilterParams,
 ) -> Result<Json<PaginatedResponse<UserSession>>, RouteError> {
     let base = format!("{path}{params}", path = UserSession::PATH);
@@ -176,14 +177,19 @@ pub async fn handler(
     };
 
     let page = repo.browser_session().list(filter, pagination).await?;
-    let count = repo.browser_session().count(filter).await?;
-
-    Ok(Json(PaginatedResponse::new(
-        page.map(UserSession::from),
-        pagination,
-        count,
-        &base,
-    )))
+    let count = if estimate {
+        repo.browser_session().estimate_count(filter).await?
+    } else {
+        repo.browser_session().count(filter).await?
+    };
+
+    let response = PaginatedResponse::new(page.map(UserSession::from), pagination, count, &base);
+
+    if estimate {
+        Ok(Json(response.with_estimated_count()))
+    } else {
+        Ok(Json(response))
+    }
 }
 
 #[cfg(test)]
@@ -256,7 +262,10 @@ mod tests {
                 "user_id": "01FSHN9AG0MZAA6S4AF7CTV32E",
                 "user_agent": null,
                 "last_active_at": null,
-                "last_active_ip": null
+                "last_active_ip": null,
+                "last_active_country": null,
+                "last_active_asn": null,
+                "last_active_as_organization": null
               },
               "links": {
                 "self": "/api/admin/v1/user-sessions/01FSHNB5309NMZYX8MFYH578R9"
@@ -271,7 +280,10 @@ mod tests {
                 "user_id": "01FSHNB530AJ6AC5HQ9X6H4RP4",
                 "user_agent": null,
                 "last_active_at": null,
-                "last_active_ip": null
+                "last_active_ip": null,
+                "last_active_country": null,
+                "last_active_asn": null,
+                "last_active_as_organization": null
               },
               "links": {
                 "self": "/api/admin/v1/user-sessions/01FSHNB530KEPHYQQXW9XPTX6Z"
@@ -311,7 +323,10 @@ mod tests {
                 "user_id": "01FSHN9AG0MZAA6S4AF7CTV32E",
                 "user_agent": null,
                 "last_active_at": null,
-                "last_active_ip": null
+                "last_active_ip": null,
+                "last_active_country": null,
+                "last_active_asn": null,
+                "last_active_as_organization": null
               },
               "links": {
                 "self": "/api/admin/v1/user-sessions/01FSHNB5309NMZYX8MFYH578R9"
@@ -348,7 +363,10 @@ mod tests {
                 "user_id": "01FSHN9AG0MZAA6S4AF7CTV32E",
                 "user_agent": null,
                 "last_active_at": null,
-                "last_active_ip": null
+                "last_active_ip": null,
+                "last_active_country": null,
+                "last_active_asn": null,
+                "last_active_as_organization": null
               },
               "links": {
                 "self": "/api/admin/v1/user-sessions/01FSHNB5309NMZYX8MFYH578R9"
@@ -385,7 +403,10 @@ mod tests {
                 "user_id": "01FSHNB530AJ6AC5HQ9X6H4RP4",
                 "user_agent": null,
                 "last_active_at": null,
-                "last_active_ip": null
+                "last_active_ip": null,
+                "last_active_country": null,
+                "last_active_asn": null,
+                "last_active_as_organization": null
               },
               "links": {
                 "self": "/api/admin/v1/user-sessions/01FSHNB530KEPHYQQXW9XPTX6Z"
@@ -399,5 +420,28 @@ mod tests {
           }
         }
         "###);
+
+        // Ask for an estimated count, which is exact on such a small table
+        let request = Request::get(
+            "/api/admin/v1/user-sessions?filter[status]=finished&estimate=true&page[first]=1",
+        )
+        .bearer(&token)
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body["meta"], @r###"
+        {
+          "count": 1,
+          "estimated": true
+        }
+        "###);
+        assert_json_snapshot!(body["links"], @r###"
+        {
+          "self": "/api/admin/v1/user-sessions?filter[status]=finished&page[first]=1&estimate=true",
+          "first": "/api/admin/v1/user-sessions?filter[status]=finished&page[first]=1&estimate=true",
+          "last": "/api/admin/v1/user-sessions?filter[status]=finished&page[last]=1&estimate=true"
+        }
+        "###);
     }
 }
@@ -40,7 +40,7 @@ fn valid_username_character(c: char) -> bool {
 }
 
 // XXX: this should be shared with the graphql handler
-fn username_valid(username: &str) -> bool {
+pub(super) fn username_valid(username: &str) -> bool {
     if username.is_empty() || username.len() > 255 {
         return false;
     }
@@ -0,0 +1,213 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::BTreeMap;
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{Json, extract::State, response::IntoResponse};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_data_model::{SiteConfig, User, UserAttributeDefinition};
+use mas_storage::BoxRepository;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::UserAttributes,
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("User ID {0} not found")]
+    NotFound(Ulid),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+/// Load the values of the custom attributes of a user
+///
+/// Values stored for attributes which are no longer declared in the
+/// configuration are left out.
+pub(super) async fn load(
+    repo: &mut BoxRepository,
+    site_config: &SiteConfig,
+    user: &User,
+) -> Result<BTreeMap<String, serde_json::Value>, mas_storage::RepositoryError> {
+    let entries = repo
+        .user_metadata()
+        .all_in_namespace(user, UserAttributeDefinition::METADATA_NAMESPACE)
+        .await?;
+
+    let attributes = entries
+        .into_iter()
+        .filter(|entry| {
+            site_config
+                .user_attributes
+                .iter()
+                .any(|definition| definition.name == entry.key)
+        })
+        .map(|entry| (entry.key, entry.value))
+        .collect();
+
+    Ok(attributes)
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("getUserAttributes")
+        .summary("Get the custom attributes of a user")
+        .tag("user")
+        .response_with::<200, Json<SingleResponse<UserAttributes>>, _>(|t| {
+            let [sample] = UserAttributes::samples();
+            let response = SingleResponse::new_canonical(sample);
+            t.description("User was found").example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("User was not found").example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.users.attributes", skip_all)]
+pub async fn handler(
+    CallContext { mut repo, .. }: CallContext,
+    State(site_config): State<SiteConfig>,
+    id: UlidPathParam,
+) -> Result<Json<SingleResponse<UserAttributes>>, RouteError> {
+    let user = repo
+        .user()
+        .lookup(*id)
+        .await?
+        .ok_or(RouteError::NotFound(*id))?;
+
+    let attributes = load(&mut repo, &site_config, &user).await?;
+
+    Ok(Json(SingleResponse::new_canonical(UserAttributes::new(
+        user.id, attributes,
+    ))))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use insta::assert_json_snapshot;
+    use mas_data_model::{UserAttributeDefinition, UserAttributeKind};
+    use mas_storage::{RepositoryAccess, user::UserRepository};
+    use sqlx::PgPool;
+    use ulid::Ulid;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup, test_site_config};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_get(pool: PgPool) {
+        setup();
+        let site_config = mas_data_model::SiteConfig {
+            user_attributes: vec![UserAttributeDefinition {
+                name: "employee_id".to_owned(),
+                kind: UserAttributeKind::String,
+                claim: None,
+            }],
+            ..test_site_config()
+        };
+        let mut state = TestState::from_pool_with_site_config(pool, site_config)
+            .await
+            .unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        repo.user_metadata()
+            .add(
+                &mut rng,
+                &state.clock,
+                &user,
+                UserAttributeDefinition::METADATA_NAMESPACE.to_owned(),
+                "employee_id".to_owned(),
+                serde_json::json!("E-1234"),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        // This one is not declared in the configuration, and should be hidden
+        repo.user_metadata()
+            .add(
+                &mut rng,
+                &state.clock,
+                &user,
+                UserAttributeDefinition::METADATA_NAMESPACE.to_owned(),
+                "legacy".to_owned(),
+                serde_json::json!(true),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::get(format!("/api/admin/v1/users/{}/attributes", user.id))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r###"
+        {
+          "data": {
+            "type": "user-attributes",
+            "id": "01FSHN9AG0MZAA6S4AF7CTV32E",
+            "attributes": {
+              "attributes": {
+                "employee_id": "E-1234"
+              }
+            },
+            "links": {
+              "self": "/api/admin/v1/users/01FSHN9AG0MZAA6S4AF7CTV32E/attributes"
+            }
+          },
+          "links": {
+            "self": "/api/admin/v1/users/01FSHN9AG0MZAA6S4AF7CTV32E/attributes"
+          }
+        }
+        "###);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_not_found(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let request = Request::get(format!("/api/admin/v1/users/{}/attributes", Ulid::nil()))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
+}
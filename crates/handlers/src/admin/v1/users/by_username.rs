@@ -4,6 +4,8 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::collections::BTreeSet;
+
 use aide::{OperationIo, transform::TransformOperation};
 use axum::{Json, extract::Path, response::IntoResponse};
 use hyper::StatusCode;
@@ -56,7 +58,7 @@ pub fn doc(operation: TransformOperation) -> TransformOperation {
         .summary("Get a user by its username (localpart)")
         .tag("user")
         .response_with::<200, Json<SingleResponse<User>>, _>(|t| {
-            let [sample, ..] = User::samples();
+            let [sample, ..] = User::samples_with_sessions();
             let response =
                 SingleResponse::new(sample, "/api/admin/v1/users/by-username/alice".to_owned());
             t.description("User was found").example(response)
@@ -79,5 +81,15 @@ pub async fn handler(
         .await?
         .ok_or(RouteError::NotFound(username))?;
 
-    Ok(Json(SingleResponse::new(User::from(user), self_path)))
+    let sessions = repo
+        .user()
+        .session_counts(BTreeSet::from([user.id]))
+        .await?
+        .remove(&user.id)
+        .unwrap_or_default();
+
+    Ok(Json(SingleResponse::new(
+        User::from(user).with_sessions(sessions),
+        self_path,
+    )))
 }
@@ -4,6 +4,8 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::collections::BTreeSet;
+
 use aide::{OperationIo, transform::TransformOperation};
 use axum::{Json, response::IntoResponse};
 use hyper::StatusCode;
@@ -50,7 +52,7 @@ pub fn doc(operation: TransformOperation) -> TransformOperation {
         .summary("Get a user")
         .tag("user")
         .response_with::<200, Json<SingleResponse<User>>, _>(|t| {
-            let [sample, ..] = User::samples();
+            let [sample, ..] = User::samples_with_sessions();
             let response = SingleResponse::new_canonical(sample);
             t.description("User was found").example(response)
         })
@@ -71,5 +73,115 @@ pub async fn handler(
         .await?
         .ok_or(RouteError::NotFound(*id))?;
 
-    Ok(Json(SingleResponse::new_canonical(User::from(user))))
+    let sessions = repo
+        .user()
+        .session_counts(BTreeSet::from([user.id]))
+        .await?
+        .remove(&user.id)
+        .unwrap_or_default();
+
+    Ok(Json(SingleResponse::new_canonical(
+        User::from(user).with_sessions(sessions),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use insta::assert_json_snapshot;
+    use mas_data_model::IpLocation;
+    use mas_storage::Clock;
+    use sqlx::PgPool;
+    use ulid::Ulid;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_get(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        // Provision a user with two browser sessions, one of them finished
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let session = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+        let finished = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+        repo.browser_session()
+            .finish(&state.clock, finished)
+            .await
+            .unwrap();
+        state.clock.advance(Duration::minutes(1));
+        repo.browser_session()
+            .record_batch_activity(vec![(
+                session.id,
+                state.clock.now(),
+                None,
+                IpLocation::default(),
+            )])
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::get(format!("/api/admin/v1/users/{}", user.id))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r###"
+        {
+          "data": {
+            "type": "user",
+            "id": "01FSHN9AG0MZAA6S4AF7CTV32E",
+            "attributes": {
+              "username": "alice",
+              "created_at": "2022-01-16T14:40:00Z",
+              "locked_at": null,
+              "deactivated_at": null,
+              "admin": false,
+              "sessions": {
+                "active_browser_sessions": 1,
+                "active_oauth2_sessions": 0,
+                "active_compat_sessions": 0,
+                "last_active_at": "2022-01-16T14:41:00Z"
+              }
+            },
+            "links": {
+              "self": "/api/admin/v1/users/01FSHN9AG0MZAA6S4AF7CTV32E"
+            }
+          },
+          "links": {
+            "self": "/api/admin/v1/users/01FSHN9AG0MZAA6S4AF7CTV32E"
+          }
+        }
+        "###);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_not_found(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let request = Request::get(format!("/api/admin/v1/users/{}", Ulid::nil()))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
 }
@@ -0,0 +1,326 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{NoApi, OperationIo, transform::TransformOperation};
+use axum::{Json, response::IntoResponse};
+use chrono::Duration;
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_data_model::{AuditEventKind, Device, TokenType};
+use mas_storage::{
+    BoxRng,
+    queue::{FinishCompatSessionJob, QueueJobRepositoryExt as _, SyncDevicesJob},
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::info;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::UserImpersonation,
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+/// How long an impersonation lasts, if not specified in the request
+const DEFAULT_EXPIRES_IN: u32 = 60 * 60;
+
+/// The longest an impersonation can last
+const MAX_EXPIRES_IN: u32 = 24 * 60 * 60;
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("User ID {0} not found")]
+    NotFound(Ulid),
+
+    #[error("User ID {0} is locked or deactivated")]
+    UserNotActive(Ulid),
+
+    #[error("The expiration must be between 1 and {MAX_EXPIRES_IN} seconds")]
+    InvalidExpiration,
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::UserNotActive(_) | Self::InvalidExpiration => StatusCode::BAD_REQUEST,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+/// # JSON payload for the `POST /api/admin/v1/users/:id/impersonate` endpoint
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename = "ImpersonateUserRequest")]
+pub struct Request {
+    /// How long the access token stays valid, in seconds. Defaults to 1 hour,
+    /// and can be at most 24 hours.
+    #[schemars(range(min = 1, max = 86_400))]
+    expires_in: Option<u32>,
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("impersonateUser")
+        .summary("Act as a user for support purposes")
+        .description(
+            r"Create a compatibility session for the user, with an access token which expires after the given time.
+The session is marked as created by the calling admin session: this shows up in the user's session list and when the token is introspected.
+The session is finished once the token expires.",
+        )
+        .tag("user")
+        .response_with::<201, Json<SingleResponse<UserImpersonation>>, _>(|t| {
+            let [sample] = UserImpersonation::samples();
+            let response = SingleResponse::new_canonical(sample);
+            t.description("The impersonation session was created")
+                .example(response)
+        })
+        .response_with::<400, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::UserNotActive(Ulid::nil()));
+            t.description("The user is not active, or the parameters are invalid")
+                .example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("User was not found").example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.users.impersonate", skip_all)]
+pub async fn handler(
+    CallContext {
+        mut repo,
+        clock,
+        session,
+        ..
+    }: CallContext,
+    NoApi(mut rng): NoApi<BoxRng>,
+    id: UlidPathParam,
+    Json(params): Json<Request>,
+) -> Result<(StatusCode, Json<SingleResponse<UserImpersonation>>), RouteError> {
+    let expires_in = params.expires_in.unwrap_or(DEFAULT_EXPIRES_IN);
+    if expires_in == 0 || expires_in > MAX_EXPIRES_IN {
+        return Err(RouteError::InvalidExpiration);
+    }
+    let expires_in = Duration::seconds(expires_in.into());
+
+    let user = repo
+        .user()
+        .lookup(*id)
+        .await?
+        .ok_or(RouteError::NotFound(*id))?;
+
+    if !user.is_valid() {
+        return Err(RouteError::UserNotActive(user.id));
+    }
+
+    // We're about to create a device, let's explicitly acquire a lock, so that
+    // any concurrent sync will read after we've committed
+    repo.user().acquire_lock_for_sync(&user).await?;
+
+    let device = Device::generate(&mut rng);
+    let compat_session = repo
+        .compat_session()
+        .add(
+            &mut rng,
+            &clock,
+            &user,
+            device.clone(),
+            None,
+            false,
+            None,
+            Some(&session),
+        )
+        .await?;
+
+    let token = TokenType::CompatAccessToken.generate(&mut rng);
+    let access_token = repo
+        .compat_access_token()
+        .add(&mut rng, &clock, &compat_session, token, Some(expires_in))
+        .await?;
+
+    let expires_at = compat_session.created_at + expires_in;
+
+    // Create the device on the homeserver
+    repo.queue_job()
+        .schedule_job(&mut rng, &clock, SyncDevicesJob::new(&user))
+        .await?;
+
+    // End the session once the token expired, so that it doesn't linger in the
+    // user's session list
+    repo.queue_job()
+        .schedule_job_later(
+            &mut rng,
+            &clock,
+            FinishCompatSessionJob::new(&compat_session),
+            expires_at,
+        )
+        .await?;
+
+    repo.audit_event()
+        .add(
+            &mut rng,
+            &clock,
+            Some(user.id),
+            AuditEventKind::ImpersonationStarted {
+                compat_session_id: compat_session.id,
+                admin_session_id: session.id,
+                admin_user_id: session.user_id,
+                expires_at,
+            },
+        )
+        .await?;
+
+    repo.save().await?;
+
+    info!(
+        %user.id,
+        %user.username,
+        %compat_session.id,
+        admin_session.id = %session.id,
+        admin_session.user_id = session.user_id.map(tracing::field::display),
+        %expires_at,
+        "Admin started impersonating user",
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SingleResponse::new_canonical(UserImpersonation::new(
+            &compat_session,
+            device,
+            access_token,
+            expires_at,
+        ))),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use mas_data_model::AuditEventKind;
+    use mas_router::{OAuth2Introspection, OAuth2RegistrationEndpoint, SimpleRoute as _};
+    use mas_storage::{Clock as _, RepositoryAccess};
+    use oauth2_types::{registration::ClientRegistrationResponse, requests::IntrospectionResponse};
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_impersonate(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let alice = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::post(format!("/api/admin/v1/users/{}/impersonate", alice.id))
+            .bearer(&token)
+            .json(serde_json::json!({ "expires_in": 600 }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["data"]["type"], "user-impersonation");
+        assert_eq!(body["data"]["attributes"]["user_id"], alice.id.to_string());
+        assert_eq!(
+            body["data"]["attributes"]["expires_at"],
+            serde_json::json!(state.clock.now() + chrono::Duration::minutes(10))
+        );
+        let session_id = body["data"]["id"].as_str().unwrap().to_owned();
+
+        // The impersonation was recorded in the audit log
+        let mut repo = state.repository().await.unwrap();
+        let events = repo.audit_event().all_for_user(&alice).await.unwrap();
+        repo.cancel().await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0].kind,
+            AuditEventKind::ImpersonationStarted { compat_session_id, .. }
+                if compat_session_id.to_string() == session_id
+        ));
+        let access_token = body["data"]["attributes"]["access_token"]
+            .as_str()
+            .unwrap()
+            .to_owned();
+
+        // The compat session is marked as an impersonation
+        let request = Request::get(format!("/api/admin/v1/compat-sessions/{session_id}"))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert!(body["data"]["attributes"]["impersonated_by_session_id"].is_string());
+
+        // Introspecting the token tells who is acting on behalf of the user
+        let request = Request::post(OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+            "client_uri": "https://introspecting.com/",
+            "grant_types": [],
+            "token_endpoint_auth_method": "client_secret_basic",
+        }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let client: ClientRegistrationResponse = response.json();
+
+        let request = Request::post(OAuth2Introspection::PATH)
+            .basic_auth(&client.client_id, client.client_secret.as_deref().unwrap())
+            .form(serde_json::json!({ "token": access_token }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let response: IntrospectionResponse = response.json();
+        assert!(response.active);
+        assert_eq!(response.username.as_deref(), Some("alice"));
+        let act = response.act.unwrap();
+        assert!(act.client_id.is_some());
+        assert_eq!(act.sub, None);
+
+        // Once expired, the token is no longer accepted
+        state.clock.advance(chrono::Duration::minutes(11));
+        let request = Request::post(OAuth2Introspection::PATH)
+            .basic_auth(&client.client_id, client.client_secret.as_deref().unwrap())
+            .form(serde_json::json!({ "token": access_token }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let response: IntrospectionResponse = response.json();
+        assert!(!response.active);
+
+        // Expirations are bounded
+        let request = Request::post(format!("/api/admin/v1/users/{}/impersonate", alice.id))
+            .bearer(&token)
+            .json(serde_json::json!({ "expires_in": 2 * 24 * 60 * 60 }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // Unknown users
+        let request = Request::post(format!(
+            "/api/admin/v1/users/{}/impersonate",
+            ulid::Ulid::nil()
+        ))
+        .bearer(&token)
+        .json(serde_json::json!({}));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
+}
@@ -16,12 +16,13 @@ use mas_axum_utils::record_error;
 use mas_storage::{Page, user::UserFilter};
 use schemars::JsonSchema;
 use serde::Deserialize;
+use ulid::Ulid;
 
 use crate::{
     admin::{
         call_context::CallContext,
         model::{Resource, User},
-        params::Pagination,
+        params::{EstimateCount, Pagination},
         response::{ErrorResponse, PaginatedResponse},
     },
     impl_from_error_for_route,
@@ -65,6 +66,12 @@ pub struct FilterParams {
     /// * `deactivated`: Only retrieve deactivated users
     #[serde(rename = "filter[status]")]
     status: Option<UserStatus>,
+
+    /// Retrieve users who registered with an invite which recorded the given
+    /// user as their referrer
+    #[serde(rename = "filter[referrer]")]
+    #[schemars(with = "Option<crate::admin::schema::Ulid>")]
+    referrer: Option<Ulid>,
 }
 
 impl std::fmt::Display for FilterParams {
@@ -79,6 +86,10 @@ impl std::fmt::Display for FilterParams {
             write!(f, "{sep}filter[status]={status}")?;
             sep = '&';
         }
+        if let Some(referrer) = self.referrer {
+            write!(f, "{sep}filter[referrer]={referrer}")?;
+            sep = '&';
+        }
 
         let _ = sep;
         Ok(())
@@ -93,6 +104,9 @@ pub enum RouteError {
 
     #[error("Invalid filter parameters")]
     InvalidFilter(#[from] QueryRejection),
+
+    #[error("User ID {0} not found")]
+    UserNotFound(Ulid),
 }
 
 impl_from_error_for_route!(mas_storage::RepositoryError);
@@ -104,6 +118,7 @@ impl IntoResponse for RouteError {
         let status = match self {
             Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::InvalidFilter(_) => StatusCode::BAD_REQUEST,
+            Self::UserNotFound(_) => StatusCode::NOT_FOUND,
         };
         (status, sentry_event_id, Json(error)).into_response()
     }
@@ -115,7 +130,7 @@ pub fn doc(operation: TransformOperation) -> TransformOperation {
         .summary("List users")
         .tag("user")
         .response_with::<200, Json<PaginatedResponse<User>>, _>(|t| {
-            let users = User::samples();
+            let users = User::samples_with_sessions();
             let pagination = mas_storage::Pagination::first(users.len());
             let page = Page {
                 edges: users.into(),
@@ -126,17 +141,40 @@ pub fn doc(operation: TransformOperation) -> TransformOperation {
             t.description("Paginated response of users")
                 .example(PaginatedResponse::new(page, pagination, 42, User::PATH))
         })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::UserNotFound(Ulid::nil()));
+            t.description("User was not found").example(response)
+        })
 }
 
 #[tracing::instrument(name = "handler.admin.v1.users.list", skip_all)]
 pub async fn handler(
     CallContext { mut repo, .. }: CallContext,
     Pagination(pagination): Pagination,
+    EstimateCount(estimate): EstimateCount,
     params: FilterParams,
 ) -> Result<Json<PaginatedResponse<User>>, RouteError> {
     let base = format!("{path}{params}", path = User::PATH);
     let filter = UserFilter::default();
 
+    // Load the referrer from the filter
+    let referrer = if let Some(referrer_id) = params.referrer {
+        let referrer = repo
+            .user()
+            .lookup(referrer_id)
+            .await?
+            .ok_or(RouteError::UserNotFound(referrer_id))?;
+
+        Some(referrer)
+    } else {
+        None
+    };
+
+    let filter = match &referrer {
+        Some(referrer) => filter.referred_by(referrer),
+        None => filter,
+    };
+
     let filter = match params.admin {
         Some(true) => filter.can_request_admin_only(),
         Some(false) => filter.cannot_request_admin_only(),
@@ -151,12 +189,29 @@ pub async fn handler(
     };
 
     let page = repo.user().list(filter, pagination).await?;
-    let count = repo.user().count(filter).await?;
+    let count = if estimate {
+        repo.user().estimate_count(filter).await?
+    } else {
+        repo.user().count(filter).await?
+    };
 
-    Ok(Json(PaginatedResponse::new(
-        page.map(User::from),
+    // Fetch the session counts of all the users in the page in one go
+    let user_ids = page.edges.iter().map(|user| user.id).collect();
+    let mut sessions = repo.user().session_counts(user_ids).await?;
+
+    let response = PaginatedResponse::new(
+        page.map(|user| {
+            let counts = sessions.remove(&user.id).unwrap_or_default();
+            User::from(user).with_sessions(counts)
+        }),
         pagination,
         count,
         &base,
-    )))
+    );
+
+    if estimate {
+        Ok(Json(response.with_estimated_count()))
+    } else {
+        Ok(Json(response))
+    }
 }
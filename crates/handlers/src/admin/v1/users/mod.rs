@@ -5,23 +5,41 @@
 // Please see LICENSE files in the repository root for full details.
 
 mod add;
+mod attributes;
 mod by_username;
+mod clear_lockout;
 mod deactivate;
 mod get;
+mod impersonate;
 mod list;
 mod lock;
+mod require_password_change;
 mod set_admin;
+mod set_attributes;
 mod set_password;
+mod set_password_policy;
+mod set_username;
+mod terms;
 mod unlock;
 
 pub use self::{
     add::{doc as add_doc, handler as add},
+    attributes::{doc as attributes_doc, handler as attributes},
     by_username::{doc as by_username_doc, handler as by_username},
+    clear_lockout::{doc as clear_lockout_doc, handler as clear_lockout},
     deactivate::{doc as deactivate_doc, handler as deactivate},
     get::{doc as get_doc, handler as get},
+    impersonate::{doc as impersonate_doc, handler as impersonate},
     list::{doc as list_doc, handler as list},
     lock::{doc as lock_doc, handler as lock},
+    require_password_change::{
+        doc as require_password_change_doc, handler as require_password_change,
+    },
     set_admin::{doc as set_admin_doc, handler as set_admin},
+    set_attributes::{doc as set_attributes_doc, handler as set_attributes},
     set_password::{doc as set_password_doc, handler as set_password},
+    set_password_policy::{doc as set_password_policy_doc, handler as set_password_policy},
+    set_username::{doc as set_username_doc, handler as set_username},
+    terms::{doc as terms_doc, handler as terms},
     unlock::{doc as unlock_doc, handler as unlock},
 };
@@ -0,0 +1,324 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::BTreeMap;
+
+use aide::{NoApi, OperationIo, transform::TransformOperation};
+use axum::{Json, extract::State, response::IntoResponse};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_data_model::{SiteConfig, UserAttributeDefinition};
+use mas_storage::BoxRng;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::{Resource, UserAttributes},
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("User ID {0} not found")]
+    NotFound(Ulid),
+
+    #[error("Unknown attribute {0:?}")]
+    UnknownAttribute(String),
+
+    #[error("Invalid value for attribute {0:?}")]
+    InvalidValue(String),
+
+    #[error("Attribute {0:?} was modified concurrently")]
+    Conflict(String),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::UnknownAttribute(_) | Self::InvalidValue(_) => StatusCode::BAD_REQUEST,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+fn attributes_example() -> BTreeMap<String, serde_json::Value> {
+    BTreeMap::from([
+        ("department".to_owned(), serde_json::json!("Engineering")),
+        ("employee_id".to_owned(), serde_json::Value::Null),
+    ])
+}
+
+/// # JSON payload for the `POST /api/admin/v1/users/:id/set-attributes` endpoint
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename = "UserSetAttributesRequest")]
+pub struct Request {
+    /// The custom attributes to change, keyed by attribute name. Setting an
+    /// attribute to `null` removes it. Attributes not listed are left as-is.
+    #[schemars(example = "attributes_example")]
+    attributes: BTreeMap<String, serde_json::Value>,
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("userSetAttributes")
+        .summary("Set the custom attributes of a user")
+        .description("The attributes must be declared in the `user_attributes` section of the configuration, and their values must match the declared type.")
+        .tag("user")
+        .response_with::<200, Json<SingleResponse<UserAttributes>>, _>(|t| {
+            let [sample] = UserAttributes::samples();
+            let id = sample.id();
+            let response =
+                SingleResponse::new(sample, format!("/api/admin/v1/users/{id}/set-attributes"));
+            t.description("User attributes were updated").example(response)
+        })
+        .response_with::<400, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::InvalidValue(
+                "employee_id".to_owned(),
+            ));
+            t.description("An attribute is unknown or its value has the wrong type")
+                .example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("User ID not found").example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.users.set_attributes", skip_all)]
+pub async fn handler(
+    CallContext {
+        mut repo, clock, ..
+    }: CallContext,
+    NoApi(mut rng): NoApi<BoxRng>,
+    State(site_config): State<SiteConfig>,
+    id: UlidPathParam,
+    Json(params): Json<Request>,
+) -> Result<Json<SingleResponse<UserAttributes>>, RouteError> {
+    let id = *id;
+    let user = repo
+        .user()
+        .lookup(id)
+        .await?
+        .ok_or(RouteError::NotFound(id))?;
+
+    // Validate everything before writing anything
+    for (name, value) in &params.attributes {
+        let definition = site_config
+            .user_attributes
+            .iter()
+            .find(|definition| &definition.name == name)
+            .ok_or_else(|| RouteError::UnknownAttribute(name.clone()))?;
+
+        if !value.is_null() && !definition.kind.accepts(value) {
+            return Err(RouteError::InvalidValue(name.clone()));
+        }
+    }
+
+    for (name, value) in params.attributes {
+        let existing = repo
+            .user_metadata()
+            .get(&user, UserAttributeDefinition::METADATA_NAMESPACE, &name)
+            .await?;
+
+        let written = match (existing, value) {
+            (None, serde_json::Value::Null) => true,
+            (Some(entry), serde_json::Value::Null) => repo.user_metadata().remove(entry).await?,
+            (None, value) => repo
+                .user_metadata()
+                .add(
+                    &mut rng,
+                    &clock,
+                    &user,
+                    UserAttributeDefinition::METADATA_NAMESPACE.to_owned(),
+                    name.clone(),
+                    value,
+                )
+                .await?
+                .is_some(),
+            (Some(entry), value) => repo
+                .user_metadata()
+                .update(&clock, entry, value)
+                .await?
+                .is_some(),
+        };
+
+        if !written {
+            return Err(RouteError::Conflict(name));
+        }
+    }
+
+    let attributes = super::attributes::load(&mut repo, &site_config, &user).await?;
+
+    repo.save().await?;
+
+    Ok(Json(SingleResponse::new(
+        UserAttributes::new(user.id, attributes),
+        format!("/api/admin/v1/users/{id}/set-attributes"),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use mas_data_model::{UserAttributeDefinition, UserAttributeKind};
+    use mas_storage::{RepositoryAccess, user::UserRepository};
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup, test_site_config};
+
+    async fn state(pool: PgPool) -> TestState {
+        let site_config = mas_data_model::SiteConfig {
+            user_attributes: vec![
+                UserAttributeDefinition {
+                    name: "employee_id".to_owned(),
+                    kind: UserAttributeKind::String,
+                    claim: Some("employee_id".to_owned()),
+                },
+                UserAttributeDefinition {
+                    name: "clearance".to_owned(),
+                    kind: UserAttributeKind::Integer,
+                    claim: None,
+                },
+            ],
+            ..test_site_config()
+        };
+        TestState::from_pool_with_site_config(pool, site_config)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_set_attributes(pool: PgPool) {
+        setup();
+        let mut state = state(pool).await;
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::post(format!("/api/admin/v1/users/{}/set-attributes", user.id))
+            .bearer(&token)
+            .json(serde_json::json!({
+                "attributes": {
+                    "employee_id": "E-1234",
+                    "clearance": 3,
+                },
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body["data"]["attributes"]["attributes"],
+            serde_json::json!({
+                "employee_id": "E-1234",
+                "clearance": 3,
+            })
+        );
+
+        // Update one attribute and remove the other
+        let request = Request::post(format!("/api/admin/v1/users/{}/set-attributes", user.id))
+            .bearer(&token)
+            .json(serde_json::json!({
+                "attributes": {
+                    "employee_id": "E-5678",
+                    "clearance": null,
+                },
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body["data"]["attributes"]["attributes"],
+            serde_json::json!({
+                "employee_id": "E-5678",
+            })
+        );
+
+        // Look at the state from the repository
+        let mut repo = state.repository().await.unwrap();
+        let entry = repo
+            .user_metadata()
+            .get(
+                &user,
+                UserAttributeDefinition::METADATA_NAMESPACE,
+                "employee_id",
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.value, serde_json::json!("E-5678"));
+        assert_eq!(entry.version, 2);
+        repo.save().await.unwrap();
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_invalid_attributes(pool: PgPool) {
+        setup();
+        let mut state = state(pool).await;
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        // Unknown attribute
+        let request = Request::post(format!("/api/admin/v1/users/{}/set-attributes", user.id))
+            .bearer(&token)
+            .json(serde_json::json!({
+                "attributes": {
+                    "department": "Engineering",
+                },
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // Wrong type, alongside a valid attribute which should not be written
+        let request = Request::post(format!("/api/admin/v1/users/{}/set-attributes", user.id))
+            .bearer(&token)
+            .json(serde_json::json!({
+                "attributes": {
+                    "employee_id": "E-1234",
+                    "clearance": "top secret",
+                },
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        let mut repo = state.repository().await.unwrap();
+        let entries = repo
+            .user_metadata()
+            .all_in_namespace(&user, UserAttributeDefinition::METADATA_NAMESPACE)
+            .await
+            .unwrap();
+        assert!(entries.is_empty());
+        repo.save().await.unwrap();
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{Json, response::IntoResponse};
+use chrono::Duration;
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_data_model::UserPasswordPolicy;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::{Resource, User},
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("User ID {0} not found")]
+    NotFound(Ulid),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+/// # JSON payload for the `POST /api/admin/v1/users/:id/set-password-policy` endpoint
+#[derive(Deserialize, JsonSchema)]
+#[schemars(rename = "SetUserPasswordPolicyRequest")]
+pub struct Request {
+    /// Maximum age of the password of the user, in seconds. Zero means that
+    /// the password of the user never expires. Omit it to use the server-wide
+    /// policy.
+    max_age: Option<u32>,
+
+    /// Number of previous passwords the user can't reuse. Omit it to use the
+    /// server-wide policy.
+    history_size: Option<u32>,
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("setUserPasswordPolicy")
+        .summary("Set the password policy of a user")
+        .description("Override the server-wide password expiry and history policies for this user.
+Fields which are omitted fall back to the server-wide policy, so sending an empty object removes all the overrides.")
+        .tag("user")
+        .response_with::<200, Json<SingleResponse<User>>, _>(|t| {
+            let [sample, ..] = User::samples();
+            let id = sample.id();
+            let response = SingleResponse::new(
+                sample,
+                format!("/api/admin/v1/users/{id}/set-password-policy"),
+            );
+            t.description("The password policy of the user was set")
+                .example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("User ID not found").example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.users.set_password_policy", skip_all)]
+pub async fn handler(
+    CallContext {
+        mut repo, clock, ..
+    }: CallContext,
+    id: UlidPathParam,
+    Json(params): Json<Request>,
+) -> Result<Json<SingleResponse<User>>, RouteError> {
+    let id = *id;
+    let user = repo
+        .user()
+        .lookup(id)
+        .await?
+        .ok_or(RouteError::NotFound(id))?;
+
+    let policy = UserPasswordPolicy {
+        max_age: params
+            .max_age
+            .map(|max_age| Duration::seconds(i64::from(max_age))),
+        history_size: params.history_size,
+    };
+
+    repo.user_password()
+        .set_policy(&clock, &user, policy)
+        .await?;
+
+    repo.save().await?;
+
+    tracing::info!(
+        user.id = %user.id,
+        user.username = %user.username,
+        ?policy,
+        "Set the password policy of the user"
+    );
+
+    Ok(Json(SingleResponse::new(
+        User::from(user),
+        format!("/api/admin/v1/users/{id}/set-password-policy"),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use mas_data_model::UserPasswordPolicy;
+    use mas_storage::{
+        RepositoryAccess,
+        user::{UserPasswordRepository, UserRepository},
+    };
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_set_password_policy(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::post(format!(
+            "/api/admin/v1/users/{}/set-password-policy",
+            user.id
+        ))
+        .bearer(&token)
+        .json(serde_json::json!({
+            "max_age": 0,
+            "history_size": 5,
+        }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["data"]["id"], serde_json::json!(user.id));
+
+        let mut repo = state.repository().await.unwrap();
+        let policy = repo.user_password().get_policy(&user).await.unwrap();
+        assert_eq!(
+            policy,
+            Some(UserPasswordPolicy {
+                max_age: Some(Duration::zero()),
+                history_size: Some(5),
+            })
+        );
+        repo.save().await.unwrap();
+
+        // Sending an empty object removes the overrides
+        let request = Request::post(format!(
+            "/api/admin/v1/users/{}/set-password-policy",
+            user.id
+        ))
+        .bearer(&token)
+        .json(serde_json::json!({}));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        let mut repo = state.repository().await.unwrap();
+        let policy = repo.user_password().get_policy(&user).await.unwrap();
+        assert_eq!(policy, None);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_set_password_policy_unknown_user(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let request =
+            Request::post("/api/admin/v1/users/01040G2081040G2081040G2081/set-password-policy")
+                .bearer(&token)
+                .json(serde_json::json!({}));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body["errors"][0]["title"],
+            "User ID 01040G2081040G2081040G2081 not found"
+        );
+    }
+}
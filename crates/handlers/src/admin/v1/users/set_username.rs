@@ -0,0 +1,339 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::sync::Arc;
+
+use aide::{NoApi, OperationIo, transform::TransformOperation};
+use axum::{Json, extract::State, response::IntoResponse};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_data_model::AuditEventKind;
+use mas_matrix::HomeserverConnection;
+use mas_storage::BoxRng;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::info;
+use ulid::Ulid;
+
+use super::add::username_valid;
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::{Resource, User},
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error(transparent)]
+    Homeserver(anyhow::Error),
+
+    #[error("User ID {0} not found")]
+    NotFound(Ulid),
+
+    #[error("Username is not valid")]
+    UsernameNotValid,
+
+    #[error("Username is already taken")]
+    UsernameTaken,
+
+    #[error("Username is reserved by the homeserver")]
+    UsernameReserved,
+
+    #[error("The homeserver doesn't support changing usernames")]
+    NotSupported,
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_) | Self::Homeserver(_));
+        let status = match self {
+            Self::Internal(_) | Self::Homeserver(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::UsernameNotValid => StatusCode::BAD_REQUEST,
+            Self::UsernameTaken | Self::UsernameReserved => StatusCode::CONFLICT,
+            Self::NotSupported => StatusCode::NOT_IMPLEMENTED,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+/// # JSON payload for the `POST /api/admin/v1/users/:id/set-username` endpoint
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename = "UserSetUsernameRequest")]
+pub struct Request {
+    /// The new username of the user.
+    username: String,
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("userSetUsername")
+        .summary("Change the username of a user")
+        .description("This changes the username of the user both in MAS and on the homeserver, which must support it.
+The previous username stays reserved, so that it can't be given to another user. The `sub` of the user doesn't change, so existing sessions keep working.")
+        .tag("user")
+        .response_with::<200, Json<SingleResponse<User>>, _>(|t| {
+            let [sample, ..] = User::samples();
+            let id = sample.id();
+            let response = SingleResponse::new(sample, format!("/api/admin/v1/users/{id}/set-username"));
+            t.description("The username of the user was changed").example(response)
+        })
+        .response_with::<400, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::UsernameNotValid);
+            t.description("Username is not valid").example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("User ID not found").example(response)
+        })
+        .response_with::<409, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::UsernameTaken);
+            t.description("Username is already taken").example(response)
+        })
+        .response_with::<409, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::UsernameReserved);
+            t.description("Username is reserved by the homeserver")
+                .example(response)
+        })
+        .response_with::<501, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotSupported);
+            t.description("The homeserver doesn't support changing usernames")
+                .example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.users.set_username", skip_all)]
+pub async fn handler(
+    CallContext {
+        mut repo,
+        clock,
+        session,
+        ..
+    }: CallContext,
+    NoApi(mut rng): NoApi<BoxRng>,
+    State(homeserver): State<Arc<dyn HomeserverConnection>>,
+    id: UlidPathParam,
+    Json(params): Json<Request>,
+) -> Result<Json<SingleResponse<User>>, RouteError> {
+    let id = *id;
+    let user = repo
+        .user()
+        .lookup(id)
+        .await?
+        .ok_or(RouteError::NotFound(id))?;
+
+    if !username_valid(&params.username) {
+        return Err(RouteError::UsernameNotValid);
+    }
+
+    // This also covers usernames which were previously used by another user
+    if repo.user().exists(&params.username).await? {
+        return Err(RouteError::UsernameTaken);
+    }
+
+    let available = homeserver
+        .is_localpart_available(&params.username)
+        .await
+        .map_err(RouteError::Homeserver)?;
+    if !available {
+        return Err(RouteError::UsernameReserved);
+    }
+
+    let old_username = user.username.clone();
+    let old_mxid = homeserver.mxid(&old_username);
+    let user = repo
+        .user()
+        .rename(&mut rng, &clock, user, params.username)
+        .await?;
+
+    // Only save the change in MAS once the homeserver accepted it
+    let changed = homeserver
+        .change_localpart(&old_mxid, &user.username)
+        .await
+        .map_err(RouteError::Homeserver)?;
+    if !changed {
+        return Err(RouteError::NotSupported);
+    }
+
+    repo.audit_event()
+        .add(
+            &mut rng,
+            &clock,
+            Some(user.id),
+            AuditEventKind::UsernameChanged {
+                old_username,
+                new_username: user.username.clone(),
+                admin_session_id: session.id,
+            },
+        )
+        .await?;
+
+    repo.save().await?;
+
+    info!(%user.id, %old_mxid, new_username = %user.username, "Changed the username of user");
+
+    Ok(Json(SingleResponse::new(
+        User::from(user),
+        format!("/api/admin/v1/users/{id}/set-username"),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use mas_data_model::AuditEventKind;
+    use mas_matrix::{HomeserverConnection, ProvisionRequest};
+    use mas_storage::{RepositoryAccess, user::UserRepository};
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_set_username(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let mut repo = state.repository().await.unwrap();
+        let alice = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let bob = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "bob".to_owned())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let mxid = state.homeserver_connection.mxid(&alice.username);
+        state
+            .homeserver_connection
+            .provision_user(&ProvisionRequest::new(&mxid, &alice.sub))
+            .await
+            .unwrap();
+
+        let request = Request::post(format!("/api/admin/v1/users/{}/set-username", alice.id))
+            .bearer(&token)
+            .json(serde_json::json!({
+                "username": "alicia",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["data"]["attributes"]["username"], "alicia");
+
+        // The user was renamed on the homeserver
+        assert!(state.homeserver_connection.query_user(&mxid).await.is_err());
+        let new_mxid = state.homeserver_connection.mxid("alicia");
+        state
+            .homeserver_connection
+            .query_user(&new_mxid)
+            .await
+            .unwrap();
+
+        // The user kept the same `sub`
+        let mut repo = state.repository().await.unwrap();
+        let user = repo.user().lookup(alice.id).await.unwrap().unwrap();
+        assert_eq!(user.username, "alicia");
+        assert_eq!(user.sub, alice.sub);
+
+        // The change was recorded in the audit log
+        let events = repo.audit_event().all_for_user(&user).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0].kind,
+            AuditEventKind::UsernameChanged { old_username, new_username, .. }
+                if old_username == "alice" && new_username == "alicia"
+        ));
+        repo.save().await.unwrap();
+
+        // The old username can't be taken by another user
+        let request = Request::post(format!("/api/admin/v1/users/{}/set-username", bob.id))
+            .bearer(&token)
+            .json(serde_json::json!({
+                "username": "alice",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CONFLICT);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["errors"][0]["title"], "Username is already taken");
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_set_username_invalid(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::post(format!("/api/admin/v1/users/{}/set-username", user.id))
+            .bearer(&token)
+            .json(serde_json::json!({
+                "username": "this is invalid",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["errors"][0]["title"], "Username is not valid");
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_set_username_reserved(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        state
+            .homeserver_connection
+            .reserve_localpart("alicia")
+            .await;
+
+        let request = Request::post(format!("/api/admin/v1/users/{}/set-username", user.id))
+            .bearer(&token)
+            .json(serde_json::json!({
+                "username": "alicia",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CONFLICT);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body["errors"][0]["title"],
+            "Username is reserved by the homeserver"
+        );
+
+        // Nothing changed in MAS
+        let mut repo = state.repository().await.unwrap();
+        let user = repo.user().lookup(user.id).await.unwrap().unwrap();
+        assert_eq!(user.username, "alice");
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use aide::{OperationIo, transform::TransformOperation};
+use axum::{Json, extract::State, response::IntoResponse};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_data_model::SiteConfig;
+use ulid::Ulid;
+
+use crate::{
+    admin::{
+        call_context::CallContext,
+        model::UserTerms,
+        params::UlidPathParam,
+        response::{ErrorResponse, SingleResponse},
+    },
+    impl_from_error_for_route,
+};
+
+#[derive(Debug, thiserror::Error, OperationIo)]
+#[aide(output_with = "Json<ErrorResponse>")]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("User ID {0} not found")]
+    NotFound(Ulid),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let error = ErrorResponse::from_error(&self);
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let status = match self {
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+        };
+        (status, sentry_event_id, Json(error)).into_response()
+    }
+}
+
+pub fn doc(operation: TransformOperation) -> TransformOperation {
+    operation
+        .id("getUserTerms")
+        .summary("Get the terms of service and privacy policy acceptances of a user")
+        .description(
+            "Retrieve the current version of each configured document, with whether the user accepted it.
+This also lists every version the user ever accepted.",
+        )
+        .tag("user")
+        .response_with::<200, Json<SingleResponse<UserTerms>>, _>(|t| {
+            let [sample] = UserTerms::samples();
+            let response = SingleResponse::new_canonical(sample);
+            t.description("User was found").example(response)
+        })
+        .response_with::<404, RouteError, _>(|t| {
+            let response = ErrorResponse::from_error(&RouteError::NotFound(Ulid::nil()));
+            t.description("User was not found").example(response)
+        })
+}
+
+#[tracing::instrument(name = "handler.admin.v1.users.terms", skip_all)]
+pub async fn handler(
+    CallContext { mut repo, .. }: CallContext,
+    State(site_config): State<SiteConfig>,
+    id: UlidPathParam,
+) -> Result<Json<SingleResponse<UserTerms>>, RouteError> {
+    let user = repo
+        .user()
+        .lookup(*id)
+        .await?
+        .ok_or(RouteError::NotFound(*id))?;
+
+    let acceptances = repo.user_terms_acceptance().all_for_user(&user).await?;
+
+    Ok(Json(SingleResponse::new_canonical(UserTerms::new(
+        user.id,
+        &site_config.terms_documents,
+        acceptances,
+    ))))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use insta::assert_json_snapshot;
+    use mas_data_model::{TermsDocument, TermsDocumentKind};
+    use mas_storage::{
+        RepositoryAccess,
+        user::{UserRepository, UserTermsAcceptanceRepository},
+    };
+    use sqlx::PgPool;
+    use ulid::Ulid;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup, test_site_config};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_get(pool: PgPool) {
+        setup();
+        let previous_terms = TermsDocument {
+            kind: TermsDocumentKind::TermsOfService,
+            version: "1".to_owned(),
+            url: "https://example.com/terms/1".parse().unwrap(),
+        };
+        let current_terms = TermsDocument {
+            kind: TermsDocumentKind::TermsOfService,
+            version: "2".to_owned(),
+            url: "https://example.com/terms/2".parse().unwrap(),
+        };
+        let privacy_policy = TermsDocument {
+            kind: TermsDocumentKind::PrivacyPolicy,
+            version: "1".to_owned(),
+            url: "https://example.com/privacy/1".parse().unwrap(),
+        };
+        let site_config = mas_data_model::SiteConfig {
+            terms_documents: vec![current_terms, privacy_policy.clone()],
+            ..test_site_config()
+        };
+        let mut state = TestState::from_pool_with_site_config(pool, site_config)
+            .await
+            .unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+        let mut rng = state.rng();
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        repo.user_terms_acceptance()
+            .accept(&mut rng, &state.clock, &user, &previous_terms)
+            .await
+            .unwrap();
+        repo.user_terms_acceptance()
+            .accept(&mut rng, &state.clock, &user, &privacy_policy)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::get(format!("/api/admin/v1/users/{}/terms", user.id))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_json_snapshot!(body, @r###"
+        {
+          "data": {
+            "type": "user-terms",
+            "id": "01FSHN9AG0MZAA6S4AF7CTV32E",
+            "attributes": {
+              "documents": [
+                {
+                  "kind": "terms_of_service",
+                  "version": "2",
+                  "url": "https://example.com/terms/2",
+                  "accepted_at": null
+                },
+                {
+                  "kind": "privacy_policy",
+                  "version": "1",
+                  "url": "https://example.com/privacy/1",
+                  "accepted_at": "2022-01-16T14:40:00Z"
+                }
+              ],
+              "acceptances": [
+                {
+                  "kind": "terms_of_service",
+                  "version": "1",
+                  "url": "https://example.com/terms/1",
+                  "accepted_at": "2022-01-16T14:40:00Z"
+                },
+                {
+                  "kind": "privacy_policy",
+                  "version": "1",
+                  "url": "https://example.com/privacy/1",
+                  "accepted_at": "2022-01-16T14:40:00Z"
+                }
+              ]
+            },
+            "links": {
+              "self": "/api/admin/v1/users/01FSHN9AG0MZAA6S4AF7CTV32E/terms"
+            }
+          },
+          "links": {
+            "self": "/api/admin/v1/users/01FSHN9AG0MZAA6S4AF7CTV32E/terms"
+          }
+        }
+        "###);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_not_found(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        let token = state.token_with_scope("urn:mas:admin").await;
+
+        let request = Request::get(format!("/api/admin/v1/users/{}/terms", Ulid::nil()))
+            .bearer(&token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
+}
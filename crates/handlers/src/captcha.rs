@@ -4,11 +4,29 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
-use std::net::IpAddr;
-
-use mas_data_model::{CaptchaConfig, CaptchaService};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    Json,
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use axum_extra::typed_header::TypedHeader;
+use base64ct::{Base64, Encoding};
+use chrono::{DateTime, Duration, Utc};
+use headers::CacheControl;
+use hmac::{Hmac, Mac};
+use hyper::StatusCode;
+use mas_data_model::{CaptchaConfig, CaptchaService, SiteConfig};
 use mas_http::RequestBuilderExt as _;
+use mas_storage::{BoxClock, BoxRng, Clock};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 use crate::BoundActivityTracker;
@@ -22,6 +40,16 @@ const HCAPTCHA_VERIFY_URL: &str = "https://api.hcaptcha.com/siteverify";
 // https://developers.cloudflare.com/turnstile/get-started/server-side-validation/
 const CF_TURNSTILE_VERIFY_URL: &str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
 
+// https://altcha.org/docs/server-integration/
+const PROOF_OF_WORK_ALGORITHM: &str = "SHA-256";
+
+/// The highest number the client may have to try to solve a proof-of-work
+/// challenge. On average, browsers find the solution in well under a second.
+const PROOF_OF_WORK_MAX_NUMBER: u64 = 100_000;
+
+/// How long a proof-of-work challenge stays valid
+const PROOF_OF_WORK_TTL: Duration = Duration::minutes(10);
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("A CAPTCHA response was expected, but none was provided")]
@@ -46,6 +74,15 @@ pub enum Error {
 
     #[error("The CAPTCHA provider returned an error")]
     RequestFailed(#[from] reqwest::Error),
+
+    #[error("The proof-of-work solution is invalid")]
+    InvalidProofOfWork,
+
+    #[error("The proof-of-work challenge expired")]
+    ProofOfWorkExpired,
+
+    #[error("The proof-of-work challenge was already solved")]
+    ProofOfWorkReplayed,
 }
 
 #[allow(clippy::struct_field_names)]
@@ -55,6 +92,7 @@ pub struct Form {
     g_recaptcha_response: Option<String>,
     h_captcha_response: Option<String>,
     cf_turnstile_response: Option<String>,
+    altcha: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -151,6 +189,155 @@ pub enum ErrorCode {
     InternalError,
 }
 
+/// A proof-of-work challenge, in the format expected by ALTCHA-compatible
+/// clients
+#[derive(Debug, Serialize)]
+pub struct ProofOfWorkChallenge {
+    algorithm: &'static str,
+    challenge: String,
+    maxnumber: u64,
+    salt: String,
+    signature: String,
+}
+
+/// The solution to a proof-of-work challenge, as submitted by the client
+#[derive(Debug, Deserialize)]
+struct ProofOfWorkSolution {
+    algorithm: String,
+    challenge: String,
+    number: u64,
+    salt: String,
+    signature: String,
+}
+
+fn proof_of_work_hash(salt: &str, number: u64) -> String {
+    hex::encode(Sha256::digest(format!("{salt}{number}")))
+}
+
+fn proof_of_work_mac(secret: &str, challenge: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(challenge.as_bytes());
+    mac
+}
+
+impl ProofOfWorkChallenge {
+    /// Issue a new challenge, signed with the given secret
+    #[must_use]
+    pub fn issue(rng: &mut impl Rng, now: DateTime<Utc>, secret: &str) -> Self {
+        let expires = (now + PROOF_OF_WORK_TTL).timestamp();
+        let mut salt = [0; 12];
+        rng.fill_bytes(&mut salt);
+        let salt = format!("{}?expires={expires}", hex::encode(salt));
+        let number = rng.gen_range(0..=PROOF_OF_WORK_MAX_NUMBER);
+
+        let challenge = proof_of_work_hash(&salt, number);
+        let signature = hex::encode(
+            proof_of_work_mac(secret, &challenge)
+                .finalize()
+                .into_bytes(),
+        );
+
+        Self {
+            algorithm: PROOF_OF_WORK_ALGORITHM,
+            challenge,
+            maxnumber: PROOF_OF_WORK_MAX_NUMBER,
+            salt,
+            signature,
+        }
+    }
+}
+
+/// Keeps track of the proof-of-work challenges which were already solved, so
+/// that each solution can only be used once
+///
+/// Challenges are keyed by their salt, and forgotten once they expire, as
+/// expired challenges are rejected anyway. Like the rate limiter, this is only
+/// kept in memory, so each instance of the service keeps track of the
+/// solutions it received.
+#[derive(Debug, Clone, Default)]
+pub struct ProofOfWorkReplayCache {
+    consumed: Arc<Mutex<HashMap<String, i64>>>,
+}
+
+impl ProofOfWorkReplayCache {
+    /// Record that the challenge with the given salt was solved, returning
+    /// `false` if it already was
+    fn consume(&self, salt: &str, expires: i64, now: DateTime<Utc>) -> bool {
+        let mut consumed = self.consumed.lock().unwrap();
+        let now = now.timestamp();
+        consumed.retain(|_, expires| *expires >= now);
+        consumed.insert(salt.to_owned(), expires).is_none()
+    }
+}
+
+/// Verify the solution to a proof-of-work challenge, which is the
+/// base64-encoded JSON payload sent by ALTCHA-compatible clients
+fn verify_proof_of_work(
+    response: &str,
+    secret: &str,
+    replay_cache: &ProofOfWorkReplayCache,
+    now: DateTime<Utc>,
+) -> Result<(), Error> {
+    let payload = Base64::decode_vec(response).map_err(|_| Error::InvalidProofOfWork)?;
+    let solution: ProofOfWorkSolution =
+        serde_json::from_slice(&payload).map_err(|_| Error::InvalidProofOfWork)?;
+
+    if solution.algorithm != PROOF_OF_WORK_ALGORITHM {
+        return Err(Error::InvalidProofOfWork);
+    }
+
+    // Check that we issued this challenge. The salt is part of the challenge,
+    // so the expiration it contains can be trusted after this
+    let signature = hex::decode(&solution.signature).map_err(|_| Error::InvalidProofOfWork)?;
+    proof_of_work_mac(secret, &solution.challenge)
+        .verify_slice(&signature)
+        .map_err(|_| Error::InvalidProofOfWork)?;
+
+    let expires = solution
+        .salt
+        .rsplit_once("?expires=")
+        .and_then(|(_, expires)| expires.parse::<i64>().ok())
+        .ok_or(Error::InvalidProofOfWork)?;
+    if now.timestamp() > expires {
+        return Err(Error::ProofOfWorkExpired);
+    }
+
+    if proof_of_work_hash(&solution.salt, solution.number) != solution.challenge {
+        return Err(Error::InvalidProofOfWork);
+    }
+
+    if !replay_cache.consume(&solution.salt, expires, now) {
+        return Err(Error::ProofOfWorkReplayed);
+    }
+
+    Ok(())
+}
+
+/// Issue a proof-of-work challenge, if this is the configured CAPTCHA service
+#[tracing::instrument(name = "handlers.captcha.challenge", skip_all)]
+pub(crate) async fn challenge(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    State(site_config): State<SiteConfig>,
+) -> Response {
+    let Some(config) = site_config
+        .captcha
+        .as_ref()
+        .filter(|config| matches!(config.service, CaptchaService::ProofOfWork))
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let challenge = ProofOfWorkChallenge::issue(&mut rng, clock.now(), &config.secret_key);
+
+    (
+        TypedHeader(CacheControl::new().with_no_store()),
+        Json(challenge),
+    )
+        .into_response()
+}
+
 impl Form {
     #[tracing::instrument(
         skip_all,
@@ -161,6 +348,8 @@ impl Form {
         &self,
         activity_tracker: &BoundActivityTracker,
         http_client: &reqwest::Client,
+        replay_cache: &ProofOfWorkReplayCache,
+        clock: &impl Clock,
         site_hostname: &str,
         config: Option<&CaptchaConfig>,
     ) -> Result<(), Error> {
@@ -168,6 +357,7 @@ impl Form {
             if self.g_recaptcha_response.is_some()
                 || self.h_captcha_response.is_some()
                 || self.cf_turnstile_response.is_some()
+                || self.altcha.is_some()
             {
                 return Err(Error::NoCaptchaConfigured);
             }
@@ -186,11 +376,12 @@ impl Form {
             &self.g_recaptcha_response,
             &self.h_captcha_response,
             &self.cf_turnstile_response,
+            &self.altcha,
         ) {
-            (_, None, None, None) => return Err(Error::MissingCaptchaResponse),
+            (_, None, None, None, None) => return Err(Error::MissingCaptchaResponse),
 
             // reCAPTCHA v2
-            (CaptchaService::RecaptchaV2, Some(response), None, None) => http_client
+            (CaptchaService::RecaptchaV2, Some(response), None, None, None) => http_client
                 .post(RECAPTCHA_VERIFY_URL)
                 .form(&VerificationRequest {
                     secret,
@@ -199,7 +390,7 @@ impl Form {
                 }),
 
             // hCaptcha
-            (CaptchaService::HCaptcha, None, Some(response), None) => http_client
+            (CaptchaService::HCaptcha, None, Some(response), None, None) => http_client
                 .post(HCAPTCHA_VERIFY_URL)
                 .form(&VerificationRequest {
                     secret,
@@ -208,7 +399,7 @@ impl Form {
                 }),
 
             // Cloudflare Turnstile
-            (CaptchaService::CloudflareTurnstile, None, None, Some(response)) => http_client
+            (CaptchaService::CloudflareTurnstile, None, None, Some(response), None) => http_client
                 .post(CF_TURNSTILE_VERIFY_URL)
                 .form(&VerificationRequest {
                     secret,
@@ -216,6 +407,11 @@ impl Form {
                     remoteip,
                 }),
 
+            // Proof-of-work, which we verify ourselves
+            (CaptchaService::ProofOfWork, None, None, None, Some(response)) => {
+                return verify_proof_of_work(response, secret, replay_cache, clock.now());
+            }
+
             _ => return Err(Error::CaptchaResponseMismatch),
         };
 
@@ -255,3 +451,153 @@ impl Form {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use hyper::Request;
+    use rand::SeedableRng;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup, test_site_config};
+
+    const SECRET: &str = "secret";
+
+    /// Solve a challenge the same way clients do, by trying every number
+    fn solve(challenge: &ProofOfWorkChallenge) -> String {
+        let number = (0..=challenge.maxnumber)
+            .find(|number| proof_of_work_hash(&challenge.salt, *number) == challenge.challenge)
+            .unwrap();
+
+        let payload = serde_json::json!({
+            "algorithm": challenge.algorithm,
+            "challenge": challenge.challenge,
+            "number": number,
+            "salt": challenge.salt,
+            "signature": challenge.signature,
+        });
+        Base64::encode_string(payload.to_string().as_bytes())
+    }
+
+    #[test]
+    fn test_proof_of_work() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(42);
+        let now = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+
+        let challenge = ProofOfWorkChallenge::issue(&mut rng, now, SECRET);
+        let response = solve(&challenge);
+
+        let replay_cache = ProofOfWorkReplayCache::default();
+
+        // The challenge must have been signed with the same secret
+        assert!(matches!(
+            verify_proof_of_work(&response, "another secret", &replay_cache, now),
+            Err(Error::InvalidProofOfWork)
+        ));
+
+        // Garbage is rejected
+        assert!(matches!(
+            verify_proof_of_work("not base64", SECRET, &replay_cache, now),
+            Err(Error::InvalidProofOfWork)
+        ));
+
+        // The challenge expires
+        assert!(matches!(
+            verify_proof_of_work(
+                &response,
+                SECRET,
+                &replay_cache,
+                now + Duration::minutes(11)
+            ),
+            Err(Error::ProofOfWorkExpired)
+        ));
+
+        verify_proof_of_work(&response, SECRET, &replay_cache, now + Duration::minutes(9)).unwrap();
+
+        // The solution can't be used a second time
+        assert!(matches!(
+            verify_proof_of_work(&response, SECRET, &replay_cache, now + Duration::minutes(9)),
+            Err(Error::ProofOfWorkReplayed)
+        ));
+
+        // Solutions to other challenges are still accepted, and expired challenges
+        // are forgotten
+        let now = now + Duration::minutes(11);
+        let challenge = ProofOfWorkChallenge::issue(&mut rng, now, SECRET);
+        verify_proof_of_work(&solve(&challenge), SECRET, &replay_cache, now).unwrap();
+        assert_eq!(replay_cache.consumed.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_proof_of_work_wrong_number() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(42);
+        let now = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+
+        let challenge = ProofOfWorkChallenge::issue(&mut rng, now, SECRET);
+        let payload = serde_json::json!({
+            "algorithm": challenge.algorithm,
+            "challenge": challenge.challenge,
+            "number": challenge.maxnumber + 1,
+            "salt": challenge.salt,
+            "signature": challenge.signature,
+        });
+        let response = Base64::encode_string(payload.to_string().as_bytes());
+
+        assert!(matches!(
+            verify_proof_of_work(&response, SECRET, &ProofOfWorkReplayCache::default(), now),
+            Err(Error::InvalidProofOfWork)
+        ));
+
+        // Moving the expiration in the salt breaks the challenge
+        let salt = challenge.salt.replace("?expires=", "?expires=9");
+        let payload = serde_json::json!({
+            "algorithm": challenge.algorithm,
+            "challenge": challenge.challenge,
+            "number": 0,
+            "salt": salt,
+            "signature": challenge.signature,
+        });
+        let response = Base64::encode_string(payload.to_string().as_bytes());
+        assert!(matches!(
+            verify_proof_of_work(&response, SECRET, &ProofOfWorkReplayCache::default(), now),
+            Err(Error::InvalidProofOfWork)
+        ));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_challenge_endpoint(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                captcha: Some(CaptchaConfig {
+                    service: CaptchaService::ProofOfWork,
+                    site_key: String::new(),
+                    secret_key: SECRET.to_owned(),
+                }),
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+
+        let request = Request::get("/captcha/challenge").empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["algorithm"], "SHA-256");
+        assert_eq!(body["maxnumber"], PROOF_OF_WORK_MAX_NUMBER);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_challenge_endpoint_other_service(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // No proof-of-work challenges without the proof-of-work service
+        let request = Request::get("/captcha/challenge").empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
+}
@@ -32,7 +32,10 @@ use zeroize::Zeroizing;
 use super::{MatrixError, MatrixJsonBody};
 use crate::{
     BoundActivityTracker, Limiter, METER, RequesterFingerprint, impl_from_error_for_route,
-    passwords::PasswordManager, rate_limit::PasswordCheckLimitedError,
+    password_lockout::{self, Lockout},
+    password_policy,
+    passwords::PasswordManager,
+    rate_limit::PasswordCheckLimitedError,
 };
 
 static LOGIN_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
@@ -198,6 +201,12 @@ pub enum RouteError {
     #[error("request rate limited")]
     RateLimited(#[from] PasswordCheckLimitedError),
 
+    #[error("too many failed login attempts")]
+    TooManyFailedAttempts,
+
+    #[error("password change required")]
+    PasswordChangeRequired,
+
     #[error("login took too long")]
     LoginTookTooLong,
 
@@ -226,6 +235,11 @@ impl IntoResponse for RouteError {
                 error: "Too many login attempts",
                 status: StatusCode::TOO_MANY_REQUESTS,
             },
+            Self::TooManyFailedAttempts => MatrixError {
+                errcode: "M_LIMIT_EXCEEDED",
+                error: "Too many failed login attempts",
+                status: StatusCode::TOO_MANY_REQUESTS,
+            },
             Self::Unsupported => MatrixError {
                 errcode: "M_UNKNOWN",
                 error: "Invalid login type",
@@ -248,6 +262,11 @@ impl IntoResponse for RouteError {
                     status: StatusCode::FORBIDDEN,
                 }
             }
+            Self::PasswordChangeRequired => MatrixError {
+                errcode: "M_FORBIDDEN",
+                error: "Password change required, log in through the web to change it",
+                status: StatusCode::FORBIDDEN,
+            },
             Self::LoginTookTooLong => MatrixError {
                 errcode: "M_FORBIDDEN",
                 error: "Login token expired",
@@ -310,6 +329,8 @@ pub(crate) async fn post(
                 &mut rng,
                 &clock,
                 &password_manager,
+                &repository_factory,
+                &site_config,
                 &limiter,
                 requester,
                 &mut repo,
@@ -533,6 +554,7 @@ async fn token_login(
             Some(&browser_session),
             false,
             initial_device_display_name,
+            None,
         )
         .await?;
 
@@ -547,6 +569,8 @@ async fn user_password_login(
     mut rng: &mut (impl RngCore + CryptoRng + Send),
     clock: &impl Clock,
     password_manager: &PasswordManager,
+    repository_factory: &BoxRepositoryFactory,
+    site_config: &SiteConfig,
     limiter: &Limiter,
     requester: RequesterFingerprint,
     repo: &mut BoxRepository,
@@ -566,6 +590,12 @@ async fn user_password_login(
     // Check the rate limit
     limiter.check_password(requester, &user)?;
 
+    // Check that the account isn't temporarily locked after too many failed
+    // attempts. There is no way to solve a CAPTCHA with this API.
+    if password_lockout::check(repo, clock, site_config, &user, requester).await? != Lockout::None {
+        return Err(RouteError::TooManyFailedAttempts);
+    }
+
     // Lookup its password
     let user_password = repo
         .user_password()
@@ -576,7 +606,7 @@ async fn user_password_login(
     // Verify the password
     let password = Zeroizing::new(password);
 
-    let new_password_hash = password_manager
+    let new_password_hash = match password_manager
         .verify_and_upgrade(
             &mut rng,
             user_password.version,
@@ -584,7 +614,32 @@ async fn user_password_login(
             user_password.hashed_password.clone(),
         )
         .await
-        .map_err(RouteError::PasswordVerificationFailed)?;
+    {
+        Ok(new_password_hash) => new_password_hash,
+        Err(e) => {
+            // The main transaction is rolled back on errors, so record the failed
+            // attempt in a separate one
+            let mut repo = repository_factory.create().await?;
+            password_lockout::record_failure(
+                &mut repo,
+                &mut rng,
+                clock,
+                site_config,
+                &user,
+                requester,
+            )
+            .await?;
+            repo.save().await?;
+            return Err(RouteError::PasswordVerificationFailed(e));
+        }
+    };
+
+    password_lockout::record_success(repo, site_config, &user).await?;
+
+    // Users who have to change their password must do it through the web login
+    if password_policy::must_change(repo, clock, site_config, &user, &user_password).await? {
+        return Err(RouteError::PasswordChangeRequired);
+    }
 
     if let Some((version, hashed_password)) = new_password_hash {
         // Save the upgraded password if needed
@@ -625,6 +680,7 @@ async fn user_password_login(
             None,
             false,
             initial_device_display_name,
+            None,
         )
         .await?;
 
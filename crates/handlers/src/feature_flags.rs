@@ -0,0 +1,120 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use mas_data_model::{FeatureFlag, FeatureFlagRollout, User};
+use mas_storage::RepositoryAccess;
+
+/// Decides whether features are enabled, from the rollouts set in the
+/// configuration and the overrides set through the admin API
+///
+/// Overrides are looked up on every check, so that changes made through the
+/// admin API apply immediately on all instances.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureFlags {
+    defaults: Arc<BTreeMap<FeatureFlag, FeatureFlagRollout>>,
+}
+
+impl FeatureFlags {
+    /// Create a new [`FeatureFlags`] from the rollouts set in the
+    /// configuration. Flags which are missing are enabled for everyone.
+    #[must_use]
+    pub fn new(defaults: BTreeMap<FeatureFlag, FeatureFlagRollout>) -> Self {
+        Self {
+            defaults: Arc::new(defaults),
+        }
+    }
+
+    /// Get the rollout of a flag, which is the override set through the admin
+    /// API if there is one, or the one from the configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the repository fails
+    pub async fn rollout<E>(
+        &self,
+        repo: &mut impl RepositoryAccess<Error = E>,
+        flag: FeatureFlag,
+    ) -> Result<FeatureFlagRollout, E> {
+        if let Some(feature_flag_override) = repo.feature_flag_override().find(flag).await? {
+            return Ok(feature_flag_override.rollout);
+        }
+
+        Ok(self.defaults.get(&flag).copied().unwrap_or_default())
+    }
+
+    /// Check whether a flag is enabled, optionally for a specific user
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the repository fails
+    pub async fn is_enabled<E>(
+        &self,
+        repo: &mut impl RepositoryAccess<Error = E>,
+        flag: FeatureFlag,
+        user: Option<&User>,
+    ) -> Result<bool, E> {
+        let rollout = self.rollout(repo, flag).await?;
+        let enabled = rollout.is_enabled_for(flag, user.map(|user| user.id));
+        tracing::debug!(%flag, enabled, "Checked feature flag");
+        Ok(enabled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mas_data_model::{FeatureFlag, FeatureFlagRollout};
+    use mas_storage::RepositoryAccess;
+    use sqlx::PgPool;
+
+    use super::FeatureFlags;
+    use crate::test_utils::{TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_overrides(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+
+        let flags = FeatureFlags::new(
+            [(FeatureFlag::PasswordGrant, FeatureFlagRollout::DISABLED)]
+                .into_iter()
+                .collect(),
+        );
+
+        // Flags which are not configured are enabled
+        assert!(
+            flags
+                .is_enabled(&mut repo, FeatureFlag::AccountClaim, None)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !flags
+                .is_enabled(&mut repo, FeatureFlag::PasswordGrant, None)
+                .await
+                .unwrap()
+        );
+
+        // An override takes precedence over the configuration
+        repo.feature_flag_override()
+            .set(
+                &mut rng,
+                &state.clock,
+                FeatureFlag::PasswordGrant,
+                FeatureFlagRollout::ENABLED,
+            )
+            .await
+            .unwrap();
+        assert!(
+            flags
+                .is_enabled(&mut repo, FeatureFlag::PasswordGrant, None)
+                .await
+                .unwrap()
+        );
+    }
+}
@@ -23,12 +23,14 @@ use axum::{
 use axum_extra::typed_header::TypedHeader;
 use chrono::{DateTime, Utc};
 use futures_util::TryStreamExt;
-use headers::{Authorization, ContentType, HeaderValue, authorization::Bearer};
-use hyper::header::CACHE_CONTROL;
+use headers::{Authorization, ContentType, HeaderMapExt, HeaderValue, authorization::Bearer};
+use hyper::{HeaderMap, Method, header::CACHE_CONTROL};
 use mas_axum_utils::{
     InternalError, SessionInfo, SessionInfoExt, cookies::CookieJar, sentry::SentryEventID,
+    user_authorization::DPoP,
 };
 use mas_data_model::{BrowserSession, Session, SiteConfig, User};
+use mas_keystore::Encrypter;
 use mas_matrix::HomeserverConnection;
 use mas_policy::{InstantiateError, Policy, PolicyFactory};
 use mas_router::UrlBuilder;
@@ -55,6 +57,7 @@ use self::{
 };
 use crate::{
     BoundActivityTracker, Limiter, RequesterFingerprint, impl_from_error_for_route,
+    oauth2::dpop::{self, DPoPError, DPoPProof},
     passwords::PasswordManager,
 };
 
@@ -76,6 +79,7 @@ struct GraphQLState {
     password_manager: PasswordManager,
     url_builder: UrlBuilder,
     limiter: Limiter,
+    encrypter: Encrypter,
 }
 
 #[async_trait::async_trait]
@@ -108,6 +112,10 @@ impl state::State for GraphQLState {
         &self.limiter
     }
 
+    fn encrypter(&self) -> &Encrypter {
+        &self.encrypter
+    }
+
     fn clock(&self) -> BoxClock {
         let clock = SystemClock::default();
         Box::new(clock)
@@ -131,6 +139,7 @@ pub fn schema(
     password_manager: PasswordManager,
     url_builder: UrlBuilder,
     limiter: Limiter,
+    encrypter: Encrypter,
 ) -> Schema {
     let state = GraphQLState {
         repository_factory,
@@ -140,6 +149,7 @@ pub fn schema(
         password_manager,
         url_builder,
         limiter,
+        encrypter,
     };
     let state: BoxState = Box::new(state);
 
@@ -183,6 +193,16 @@ pub enum RouteError {
 
 impl_from_error_for_route!(mas_storage::RepositoryError);
 
+impl From<DPoPError> for RouteError {
+    fn from(err: DPoPError) -> Self {
+        if err.is_internal() {
+            Self::Internal(Box::new(err))
+        } else {
+            Self::InvalidToken
+        }
+    }
+}
+
 impl IntoResponse for RouteError {
     fn into_response(self) -> Response {
         let event_id = sentry::capture_error(&self);
@@ -229,16 +249,52 @@ impl IntoResponse for RouteError {
     }
 }
 
+/// Extract the access token sent in the `Authorization` header, if any
+///
+/// Tokens presented with the `DPoP` scheme come with a proof of possession of
+/// the key they are bound to, which is validated here.
+async fn authorization_token(
+    rng: &mut BoxRng,
+    clock: &impl Clock,
+    repo: &mut BoxRepository,
+    url_builder: &UrlBuilder,
+    method: &Method,
+    headers: &HeaderMap,
+) -> Result<Option<(String, Option<DPoPProof>)>, RouteError> {
+    match (
+        headers.typed_try_get::<Authorization<Bearer>>(),
+        headers.typed_try_get::<Authorization<DPoP>>(),
+    ) {
+        (Ok(Some(Authorization(bearer))), _) => Ok(Some((bearer.token().to_owned(), None))),
+        (_, Ok(Some(Authorization(credentials)))) => {
+            let proof = dpop::verify_token_proof(
+                rng,
+                clock,
+                repo,
+                headers,
+                method,
+                &url_builder.graphql_endpoint(),
+                credentials.token(),
+            )
+            .await?;
+            Ok(Some((credentials.token().to_owned(), Some(proof))))
+        }
+        (Ok(None), Ok(None)) => Ok(None),
+        _ => Err(RouteError::InvalidToken),
+    }
+}
+
 async fn get_requester(
     undocumented_oauth2_access: bool,
     clock: &impl Clock,
+    site_config: &SiteConfig,
     activity_tracker: &BoundActivityTracker,
     mut repo: BoxRepository,
     session_info: &SessionInfo,
     user_agent: Option<String>,
-    token: Option<&str>,
+    token: Option<(String, Option<DPoPProof>)>,
 ) -> Result<Requester, RouteError> {
-    let entity = if let Some(token) = token {
+    let entity = if let Some((token, proof)) = token {
         // If we haven't enabled undocumented_oauth2_access on the listener, we bail out
         if !undocumented_oauth2_access {
             return Err(RouteError::InvalidToken);
@@ -246,10 +302,12 @@ async fn get_requester(
 
         let token = repo
             .oauth2_access_token()
-            .find_by_token(token)
+            .find_by_token(&token)
             .await?
             .ok_or(RouteError::InvalidToken)?;
 
+        dpop::ensure_token_binding(&token, proof.as_ref())?;
+
         let session = repo
             .oauth2_session()
             .lookup(token.session_id)
@@ -279,13 +337,21 @@ async fn get_requester(
             return Err(RouteError::InvalidToken);
         }
 
+        // Tokens requested with a resource indicator are meant for that
+        // resource server only, not for the GraphQL API
+        if session.resource.is_some() {
+            return Err(RouteError::InvalidToken);
+        }
+
         if !session.scope.contains("urn:mas:graphql:*") {
             return Err(RouteError::MissingScope);
         }
 
         RequestingEntity::OAuth2Session(Box::new((session, user)))
     } else {
-        let maybe_session = session_info.load_active_session(&mut repo).await?;
+        let maybe_session = session_info
+            .load_active_session(&mut repo, clock, site_config)
+            .await?;
 
         if let Some(session) = maybe_session.as_ref() {
             activity_tracker
@@ -308,27 +374,31 @@ async fn get_requester(
 
 pub async fn post(
     AxumState(schema): AxumState<Schema>,
+    AxumState(site_config): AxumState<SiteConfig>,
     Extension(ExtraRouterParameters {
         undocumented_oauth2_access,
     }): Extension<ExtraRouterParameters>,
+    AxumState(url_builder): AxumState<UrlBuilder>,
+    mut rng: BoxRng,
     clock: BoxClock,
-    repo: BoxRepository,
+    mut repo: BoxRepository,
     activity_tracker: BoundActivityTracker,
     cookie_jar: CookieJar,
     content_type: Option<TypedHeader<ContentType>>,
-    authorization: Option<TypedHeader<Authorization<Bearer>>>,
     user_agent: Option<TypedHeader<headers::UserAgent>>,
+    method: Method,
+    headers: HeaderMap,
     body: Body,
 ) -> Result<impl IntoResponse, RouteError> {
     let body = body.into_data_stream();
-    let token = authorization
-        .as_ref()
-        .map(|TypedHeader(Authorization(bearer))| bearer.token());
+    let token =
+        authorization_token(&mut rng, &clock, &mut repo, &url_builder, &method, &headers).await?;
     let user_agent = user_agent.map(|TypedHeader(h)| h.to_string());
     let (session_info, mut cookie_jar) = cookie_jar.session_info();
     let requester = get_requester(
         undocumented_oauth2_access,
         &clock,
+        &site_config,
         &activity_tracker,
         repo,
         &session_info,
@@ -369,25 +439,29 @@ pub async fn post(
 
 pub async fn get(
     AxumState(schema): AxumState<Schema>,
+    AxumState(site_config): AxumState<SiteConfig>,
     Extension(ExtraRouterParameters {
         undocumented_oauth2_access,
     }): Extension<ExtraRouterParameters>,
+    AxumState(url_builder): AxumState<UrlBuilder>,
+    mut rng: BoxRng,
     clock: BoxClock,
-    repo: BoxRepository,
+    mut repo: BoxRepository,
     activity_tracker: BoundActivityTracker,
     cookie_jar: CookieJar,
-    authorization: Option<TypedHeader<Authorization<Bearer>>>,
     user_agent: Option<TypedHeader<headers::UserAgent>>,
+    method: Method,
+    headers: HeaderMap,
     RawQuery(query): RawQuery,
 ) -> Result<impl IntoResponse, InternalError> {
-    let token = authorization
-        .as_ref()
-        .map(|TypedHeader(Authorization(bearer))| bearer.token());
+    let token =
+        authorization_token(&mut rng, &clock, &mut repo, &url_builder, &method, &headers).await?;
     let user_agent = user_agent.map(|TypedHeader(h)| h.to_string());
     let (session_info, mut cookie_jar) = cookie_jar.session_info();
     let requester = get_requester(
         undocumented_oauth2_access,
         &clock,
+        &site_config,
         &activity_tracker,
         repo,
         &session_info,
@@ -501,6 +575,18 @@ impl OwnerId for mas_data_model::UserEmail {
     }
 }
 
+impl OwnerId for mas_data_model::UserPasskey {
+    fn owner_id(&self) -> Option<Ulid> {
+        Some(self.user_id)
+    }
+}
+
+impl OwnerId for mas_data_model::UserTrustedDevice {
+    fn owner_id(&self) -> Option<Ulid> {
+        Some(self.user_id)
+    }
+}
+
 impl OwnerId for Session {
     fn owner_id(&self) -> Option<Ulid> {
         self.user_id
@@ -513,6 +599,12 @@ impl OwnerId for mas_data_model::CompatSession {
     }
 }
 
+impl OwnerId for mas_data_model::Consent {
+    fn owner_id(&self) -> Option<Ulid> {
+        Some(self.user_id)
+    }
+}
+
 impl OwnerId for mas_data_model::UpstreamOAuthLink {
     fn owner_id(&self) -> Option<Ulid> {
         self.user_id
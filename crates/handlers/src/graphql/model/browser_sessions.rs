@@ -93,6 +93,24 @@ impl BrowserSession {
         self.0.last_active_ip.map(|ip| ip.to_string())
     }
 
+    /// The ISO 3166-1 code of the country the last IP address is located in,
+    /// if known.
+    pub async fn last_active_country(&self) -> Option<&str> {
+        self.0.last_active_location.country.as_deref()
+    }
+
+    /// The number of the autonomous system the last IP address belongs to, if
+    /// known.
+    pub async fn last_active_asn(&self) -> Option<u32> {
+        self.0.last_active_location.asn
+    }
+
+    /// The name of the organization operating the autonomous system the last
+    /// IP address belongs to, if known.
+    pub async fn last_active_as_organization(&self) -> Option<&str> {
+        self.0.last_active_location.as_organization.as_deref()
+    }
+
     /// The last time the session was active.
     pub async fn last_active_at(&self) -> Option<DateTime<Utc>> {
         self.0.last_active_at
@@ -161,6 +161,24 @@ impl CompatSession {
         self.session.last_active_ip.map(|ip| ip.to_string())
     }
 
+    /// The ISO 3166-1 code of the country the last IP address is located in,
+    /// if known.
+    pub async fn last_active_country(&self) -> Option<&str> {
+        self.session.last_active_location.country.as_deref()
+    }
+
+    /// The number of the autonomous system the last IP address belongs to, if
+    /// known.
+    pub async fn last_active_asn(&self) -> Option<u32> {
+        self.session.last_active_location.asn
+    }
+
+    /// The name of the organization operating the autonomous system the last
+    /// IP address belongs to, if known.
+    pub async fn last_active_as_organization(&self) -> Option<&str> {
+        self.session.last_active_location.as_organization.as_deref()
+    }
+
     /// The last time the session was active.
     pub async fn last_active_at(&self) -> Option<DateTime<Utc>> {
         self.session.last_active_at
@@ -170,6 +188,12 @@ impl CompatSession {
     pub async fn human_name(&self) -> Option<&str> {
         self.session.human_name.as_deref()
     }
+
+    /// Whether this session was created by an administrator acting on behalf
+    /// of the user.
+    pub async fn impersonated(&self) -> bool {
+        self.session.impersonated_by_session_id.is_some()
+    }
 }
 
 /// A compat SSO login represents a login done through the legacy Matrix login
@@ -23,10 +23,13 @@ pub use self::{
     compat_sessions::{CompatSession, CompatSsoLogin},
     cursor::{Cursor, NodeCursor},
     node::{Node, NodeType},
-    oauth::{OAuth2Client, OAuth2Session},
+    oauth::{OAuth2Client, OAuth2Consent, OAuth2Session},
     site_config::{SITE_CONFIG_ID, SiteConfig},
     upstream_oauth::{UpstreamOAuth2Link, UpstreamOAuth2Provider},
-    users::{AppSession, User, UserEmail, UserEmailAuthentication, UserRecoveryTicket},
+    users::{
+        AppSession, User, UserEmail, UserEmailAuthentication, UserPasskey, UserPhone,
+        UserRecoveryTicket, UserTotp, UserTrustedDevice,
+    },
     viewer::{Anonymous, Viewer, ViewerSession},
 };
 
@@ -43,7 +46,11 @@ pub enum CreationEvent {
     BrowserSession(Box<BrowserSession>),
     UserEmail(Box<UserEmail>),
     UserEmailAuthentication(Box<UserEmailAuthentication>),
+    UserPasskey(Box<UserPasskey>),
     UserRecoveryTicket(Box<UserRecoveryTicket>),
+    UserPhone(Box<UserPhone>),
+    UserTotp(Box<UserTotp>),
+    UserTrustedDevice(Box<UserTrustedDevice>),
     UpstreamOAuth2Provider(Box<UpstreamOAuth2Provider>),
     UpstreamOAuth2Link(Box<UpstreamOAuth2Link>),
     OAuth2Session(Box<OAuth2Session>),
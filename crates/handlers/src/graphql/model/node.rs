@@ -11,8 +11,9 @@ use ulid::Ulid;
 
 use super::{
     Anonymous, Authentication, BrowserSession, CompatSession, CompatSsoLogin, OAuth2Client,
-    OAuth2Session, SiteConfig, UpstreamOAuth2Link, UpstreamOAuth2Provider, User, UserEmail,
-    UserEmailAuthentication, UserRecoveryTicket,
+    OAuth2Consent, OAuth2Session, SiteConfig, UpstreamOAuth2Link, UpstreamOAuth2Provider, User,
+    UserEmail, UserEmailAuthentication, UserPasskey, UserPhone, UserRecoveryTicket, UserTotp,
+    UserTrustedDevice,
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -22,13 +23,18 @@ pub enum NodeType {
     CompatSession,
     CompatSsoLogin,
     OAuth2Client,
+    OAuth2Consent,
     OAuth2Session,
     UpstreamOAuth2Provider,
     UpstreamOAuth2Link,
     User,
     UserEmail,
     UserEmailAuthentication,
+    UserPasskey,
+    UserPhone,
     UserRecoveryTicket,
+    UserTotp,
+    UserTrustedDevice,
 }
 
 #[derive(Debug, Error)]
@@ -48,13 +54,18 @@ impl NodeType {
             NodeType::CompatSession => "compat_session",
             NodeType::CompatSsoLogin => "compat_sso_login",
             NodeType::OAuth2Client => "oauth2_client",
+            NodeType::OAuth2Consent => "oauth2_consent",
             NodeType::OAuth2Session => "oauth2_session",
             NodeType::UpstreamOAuth2Provider => "upstream_oauth2_provider",
             NodeType::UpstreamOAuth2Link => "upstream_oauth2_link",
             NodeType::User => "user",
             NodeType::UserEmail => "user_email",
             NodeType::UserEmailAuthentication => "user_email_authentication",
+            NodeType::UserPasskey => "user_passkey",
+            NodeType::UserPhone => "user_phone",
             NodeType::UserRecoveryTicket => "user_recovery_ticket",
+            NodeType::UserTotp => "user_totp",
+            NodeType::UserTrustedDevice => "user_trusted_device",
         }
     }
 
@@ -65,13 +76,18 @@ impl NodeType {
             "compat_session" => Some(NodeType::CompatSession),
             "compat_sso_login" => Some(NodeType::CompatSsoLogin),
             "oauth2_client" => Some(NodeType::OAuth2Client),
+            "oauth2_consent" => Some(NodeType::OAuth2Consent),
             "oauth2_session" => Some(NodeType::OAuth2Session),
             "upstream_oauth2_provider" => Some(NodeType::UpstreamOAuth2Provider),
             "upstream_oauth2_link" => Some(NodeType::UpstreamOAuth2Link),
             "user" => Some(NodeType::User),
             "user_email" => Some(NodeType::UserEmail),
             "user_email_authentication" => Some(NodeType::UserEmailAuthentication),
+            "user_passkey" => Some(NodeType::UserPasskey),
+            "user_phone" => Some(NodeType::UserPhone),
             "user_recovery_ticket" => Some(NodeType::UserRecoveryTicket),
+            "user_totp" => Some(NodeType::UserTotp),
+            "user_trusted_device" => Some(NodeType::UserTrustedDevice),
             _ => None,
         }
     }
@@ -121,6 +137,7 @@ pub enum Node {
     CompatSession(Box<CompatSession>),
     CompatSsoLogin(Box<CompatSsoLogin>),
     OAuth2Client(Box<OAuth2Client>),
+    OAuth2Consent(Box<OAuth2Consent>),
     OAuth2Session(Box<OAuth2Session>),
     SiteConfig(Box<SiteConfig>),
     UpstreamOAuth2Provider(Box<UpstreamOAuth2Provider>),
@@ -128,5 +145,9 @@ pub enum Node {
     User(Box<User>),
     UserEmail(Box<UserEmail>),
     UserEmailAuthentication(Box<UserEmailAuthentication>),
+    UserPasskey(Box<UserPasskey>),
+    UserPhone(Box<UserPhone>),
     UserRecoveryTicket(Box<UserRecoveryTicket>),
+    UserTotp(Box<UserTotp>),
+    UserTrustedDevice(Box<UserTrustedDevice>),
 }
@@ -8,8 +8,7 @@ use anyhow::Context as _;
 use async_graphql::{Context, Description, Enum, ID, Object};
 use chrono::{DateTime, Utc};
 use mas_storage::{oauth2::OAuth2ClientRepository, user::BrowserSessionRepository};
-use oauth2_types::{oidc::ApplicationType, scope::Scope};
-use ulid::Ulid;
+use oauth2_types::oidc::ApplicationType;
 use url::Url;
 
 use super::{BrowserSession, NodeType, SessionState, User, UserAgent};
@@ -124,6 +123,24 @@ impl OAuth2Session {
         self.0.last_active_ip.map(|ip| ip.to_string())
     }
 
+    /// The ISO 3166-1 code of the country the last IP address is located in,
+    /// if known.
+    pub async fn last_active_country(&self) -> Option<&str> {
+        self.0.last_active_location.country.as_deref()
+    }
+
+    /// The number of the autonomous system the last IP address belongs to, if
+    /// known.
+    pub async fn last_active_asn(&self) -> Option<u32> {
+        self.0.last_active_location.asn
+    }
+
+    /// The name of the organization operating the autonomous system the last
+    /// IP address belongs to, if known.
+    pub async fn last_active_as_organization(&self) -> Option<&str> {
+        self.0.last_active_location.as_organization.as_deref()
+    }
+
     /// The last time the session was active.
     pub async fn last_active_at(&self) -> Option<DateTime<Utc>> {
         self.0.last_active_at
@@ -133,6 +150,12 @@ impl OAuth2Session {
     pub async fn human_name(&self) -> Option<&str> {
         self.0.human_name.as_deref()
     }
+
+    /// When the user explicitly granted offline access to the client, if they
+    /// did.
+    pub async fn offline_access_granted_at(&self) -> Option<DateTime<Utc>> {
+        self.0.offline_access_granted_at
+    }
 }
 
 /// The application type advertised by the client.
@@ -201,19 +224,21 @@ impl OAuth2Client {
     }
 }
 
-/// An OAuth 2.0 consent represents the scope a user consented to grant to a
+/// An OAuth 2.0 consent represents a scope a user consented to grant to a
 /// client.
 #[derive(Description)]
-pub struct OAuth2Consent {
-    scope: Scope,
-    client_id: Ulid,
-}
+pub struct OAuth2Consent(pub mas_data_model::Consent);
 
 #[Object(use_type_description)]
 impl OAuth2Consent {
-    /// Scope consented by the user for this client.
+    /// ID of the object.
+    pub async fn id(&self) -> ID {
+        NodeType::OAuth2Consent.id(self.0.id)
+    }
+
+    /// Scope token consented by the user for this client.
     pub async fn scope(&self) -> String {
-        self.scope.to_string()
+        self.0.scope_token.to_string()
     }
 
     /// OAuth 2.0 client for which the user granted access.
@@ -222,11 +247,21 @@ impl OAuth2Consent {
         let mut repo = state.repository().await?;
         let client = repo
             .oauth2_client()
-            .lookup(self.client_id)
+            .lookup(self.0.client_id)
             .await?
             .context("Could not load client")?;
         repo.cancel().await?;
 
         Ok(OAuth2Client(client))
     }
+
+    /// When the user first granted this scope to the client.
+    pub async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    /// When the user last granted this scope to the client.
+    pub async fn last_granted_at(&self) -> DateTime<Utc> {
+        self.0.last_granted_at()
+    }
 }
@@ -37,6 +37,9 @@ pub struct SiteConfig {
     /// Whether users can change their display name.
     display_name_change_allowed: bool,
 
+    /// Whether users can change their avatar.
+    avatar_change_allowed: bool,
+
     /// Whether passwords are enabled for login.
     password_login_enabled: bool,
 
@@ -49,6 +52,12 @@ pub struct SiteConfig {
     /// Whether users can delete their own account.
     account_deactivation_allowed: bool,
 
+    /// How many days, rounded up, accounts stay deactivated after their owner
+    /// asked to delete them, before being erased. Users can get their account
+    /// back by signing in again during that time. Not set if accounts are
+    /// erased straight away.
+    account_deletion_grace_period_days: Option<u32>,
+
     /// Minimum password complexity, from 0 to 4, in terms of a zxcvbn score.
     /// The exact scorer (including dictionaries and other data tables)
     /// in use is <https://crates.io/crates/zxcvbn>.
@@ -57,6 +66,23 @@ pub struct SiteConfig {
     /// Whether users can log in with their email address.
     login_with_email_allowed: bool,
 
+    /// Whether users can register passkeys and log in with them.
+    passkeys_enabled: bool,
+
+    /// Whether users can add a TOTP second factor, and who must have one.
+    totp_policy: TotpPolicy,
+
+    /// Whether users can add and change the phone number on their account.
+    phone_number_change_allowed: bool,
+
+    /// Whether users with a confirmed phone number get a code by SMS when
+    /// logging in with their password.
+    sms_second_factor_enabled: bool,
+
+    /// Whether users can choose not to be asked for their second factor again
+    /// in a browser.
+    trusted_devices_enabled: bool,
+
     /// Experimental plan management iframe URI.
     plan_management_iframe_uri: Option<String>,
 }
@@ -77,6 +103,35 @@ pub enum CaptchaService {
     RecaptchaV2,
     CloudflareTurnstile,
     HCaptcha,
+    ProofOfWork,
+}
+
+/// Whether users can add a TOTP second factor, and who must have one
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotpPolicy {
+    /// Users can't add a second factor
+    Disabled,
+
+    /// Users can add a second factor if they want to
+    Optional,
+
+    /// All users must have a second factor
+    Required,
+
+    /// Users who can request admin access must have a second factor, others
+    /// can add one if they want to
+    RequiredForAdmins,
+}
+
+impl From<mas_data_model::TotpPolicy> for TotpPolicy {
+    fn from(policy: mas_data_model::TotpPolicy) -> Self {
+        match policy {
+            mas_data_model::TotpPolicy::Disabled => Self::Disabled,
+            mas_data_model::TotpPolicy::Optional => Self::Optional,
+            mas_data_model::TotpPolicy::Required => Self::Required,
+            mas_data_model::TotpPolicy::RequiredForAdmins => Self::RequiredForAdmins,
+        }
+    }
 }
 
 #[ComplexObject]
@@ -99,12 +154,25 @@ impl SiteConfig {
             imprint: data_model.imprint.clone(),
             email_change_allowed: data_model.email_change_allowed,
             display_name_change_allowed: data_model.displayname_change_allowed,
+            avatar_change_allowed: data_model.avatar_change_allowed,
             password_login_enabled: data_model.password_login_enabled,
             password_change_allowed: data_model.password_change_allowed,
             password_registration_enabled: data_model.password_registration_enabled,
             account_deactivation_allowed: data_model.account_deactivation_allowed,
+            account_deletion_grace_period_days: data_model.account_deletion_grace_period.map(
+                |period| {
+                    let seconds = u64::try_from(period.num_seconds()).unwrap_or_default();
+                    let days = seconds.div_ceil(24 * 60 * 60);
+                    u32::try_from(days).unwrap_or(u32::MAX)
+                },
+            ),
             minimum_password_complexity: data_model.minimum_password_complexity,
             login_with_email_allowed: data_model.login_with_email_allowed,
+            passkeys_enabled: data_model.passkeys_enabled,
+            totp_policy: data_model.totp_policy.into(),
+            phone_number_change_allowed: data_model.phone_number_change_allowed,
+            sms_second_factor_enabled: data_model.sms_second_factor_enabled,
+            trusted_devices_enabled: data_model.trusted_device_ttl.is_some(),
             plan_management_iframe_uri: data_model.plan_management_iframe_uri.clone(),
         }
     }
@@ -128,6 +196,7 @@ impl CaptchaConfig {
                     CaptchaService::CloudflareTurnstile
                 }
                 mas_data_model::CaptchaService::HCaptcha => CaptchaService::HCaptcha,
+                mas_data_model::CaptchaService::ProofOfWork => CaptchaService::ProofOfWork,
             },
             site_key: data_model.site_key.clone(),
         }
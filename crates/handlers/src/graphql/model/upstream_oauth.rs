@@ -60,10 +60,13 @@ impl UpstreamOAuth2Provider {
     }
 
     /// URL to start the linking process of the current user with this provider.
+    ///
+    /// Once the link is made, the user is sent back to their account.
     pub async fn link_url(&self, context: &Context<'_>) -> Url {
         let state = context.state();
         let url_builder = state.url_builder();
-        let route = mas_router::UpstreamOAuth2Authorize::new(self.provider.id);
+        let route = mas_router::UpstreamOAuth2Authorize::new(self.provider.id)
+            .and_then(mas_router::PostAuthAction::manage_account(None));
         url_builder.absolute_url_for(&route)
     }
 }
@@ -15,14 +15,18 @@ use mas_storage::{
     Pagination, RepositoryAccess,
     app_session::AppSessionFilter,
     compat::{CompatSessionFilter, CompatSsoLoginFilter, CompatSsoLoginRepository},
-    oauth2::{OAuth2SessionFilter, OAuth2SessionRepository},
+    oauth2::{OAuth2ConsentRepository, OAuth2SessionFilter, OAuth2SessionRepository},
     upstream_oauth2::{UpstreamOAuthLinkFilter, UpstreamOAuthLinkRepository},
-    user::{BrowserSessionFilter, BrowserSessionRepository, UserEmailFilter, UserEmailRepository},
+    user::{
+        BrowserSessionFilter, BrowserSessionRepository, UserEmailFilter, UserEmailRepository,
+        UserPasskeyRepository, UserPhoneRepository, UserRecoveryCodeRepository, UserTotpRepository,
+        UserTrustedDeviceRepository,
+    },
 };
 
 use super::{
-    BrowserSession, CompatSession, Cursor, NodeCursor, NodeType, OAuth2Session,
-    PreloadedTotalCount, SessionState, UpstreamOAuth2Link,
+    BrowserSession, CompatSession, Cursor, NodeCursor, NodeType, OAuth2Consent, OAuth2Session,
+    PreloadedTotalCount, SessionState, UpstreamOAuth2Link, UserAgent,
     compat_sessions::{CompatSessionType, CompatSsoLogin},
     matrix::MatrixUser,
 };
@@ -386,6 +390,22 @@ impl User {
         .await
     }
 
+    /// The primary email address of the user, used for notifications and as
+    /// the `email` claim. This is the oldest email address of the user, unless
+    /// they picked another one. Is `null` if the user has no email address.
+    async fn primary_email(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Option<UserEmail>, async_graphql::Error> {
+        let state = ctx.state();
+        let mut repo = state.repository().await?;
+
+        let user_email = repo.user_email().primary(&self.0).await?;
+        repo.cancel().await?;
+
+        Ok(user_email.map(UserEmail))
+    }
+
     /// Get the list of OAuth 2.0 sessions, chronologically sorted
     #[allow(clippy::too_many_arguments)]
     async fn oauth2_sessions(
@@ -403,6 +423,12 @@ impl User {
         )]
         last_active: Option<DateFilter>,
 
+        #[graphql(
+            name = "offlineAccess",
+            desc = "List only sessions which were (or weren't) granted offline access."
+        )]
+        offline_access: Option<bool>,
+
         #[graphql(desc = "Returns the elements in the list that come after the cursor.")]
         after: Option<String>,
         #[graphql(desc = "Returns the elements in the list that come before the cursor.")]
@@ -464,6 +490,12 @@ impl User {
                     None => filter,
                 };
 
+                let filter = match offline_access {
+                    Some(true) => filter.with_offline_access(),
+                    Some(false) => filter.without_offline_access(),
+                    None => filter,
+                };
+
                 let page = repo.oauth2_session().list(filter, pagination).await?;
 
                 let count = if ctx.look_ahead().field("totalCount").exists() {
@@ -493,6 +525,21 @@ impl User {
         .await
     }
 
+    /// Get the list of scopes the user consented to grant to OAuth 2.0 clients,
+    /// grouped by client
+    async fn oauth2_consents(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<OAuth2Consent>, async_graphql::Error> {
+        let state = ctx.state();
+        let mut repo = state.repository().await?;
+
+        let consents = repo.oauth2_consent().all_for_user(&self.0).await?;
+        repo.cancel().await?;
+
+        Ok(consents.into_iter().map(OAuth2Consent).collect())
+    }
+
     /// Get the list of upstream OAuth 2.0 links
     async fn upstream_oauth2_links(
         &self,
@@ -715,6 +762,74 @@ impl User {
 
         Ok(password.is_some())
     }
+
+    /// Get the list of passkeys registered by the user, oldest first.
+    async fn passkeys(&self, ctx: &Context<'_>) -> Result<Vec<UserPasskey>, async_graphql::Error> {
+        let state = ctx.state();
+        let mut repo = state.repository().await?;
+
+        let passkeys = repo.user_passkey().all(&self.0).await?;
+        repo.cancel().await?;
+
+        Ok(passkeys.into_iter().map(UserPasskey).collect())
+    }
+
+    /// The TOTP second factor of the user. Is `null` if the user didn't add
+    /// one, or didn't confirm it yet.
+    async fn totp(&self, ctx: &Context<'_>) -> Result<Option<UserTotp>, async_graphql::Error> {
+        let state = ctx.state();
+        let mut repo = state.repository().await?;
+
+        let totp = repo.user_totp().find_for_user(&self.0).await?;
+        repo.cancel().await?;
+
+        Ok(totp
+            .filter(mas_data_model::UserTotp::is_confirmed)
+            .map(UserTotp))
+    }
+
+    /// The number of recovery codes the user can still use to log in if their
+    /// second factor is unavailable.
+    async fn recovery_codes_left(&self, ctx: &Context<'_>) -> Result<usize, async_graphql::Error> {
+        let state = ctx.state();
+        let mut repo = state.repository().await?;
+
+        let count = repo.user_recovery_code().count_unused(&self.0).await?;
+        repo.cancel().await?;
+
+        Ok(count)
+    }
+
+    /// The phone number of the user, confirmed or not. Is `null` if the user
+    /// didn't add one.
+    async fn phone(&self, ctx: &Context<'_>) -> Result<Option<UserPhone>, async_graphql::Error> {
+        let state = ctx.state();
+        let mut repo = state.repository().await?;
+
+        let phone = repo.user_phone().find_for_user(&self.0).await?;
+        repo.cancel().await?;
+
+        Ok(phone.map(UserPhone))
+    }
+
+    /// Get the list of browsers in which the user chose not to be asked for
+    /// their second factor again, most recently trusted first.
+    async fn trusted_devices(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<UserTrustedDevice>, async_graphql::Error> {
+        let state = ctx.state();
+        let clock = state.clock();
+        let mut repo = state.repository().await?;
+
+        let devices = repo
+            .user_trusted_device()
+            .all_valid(&clock, &self.0)
+            .await?;
+        repo.cancel().await?;
+
+        Ok(devices.into_iter().map(UserTrustedDevice).collect())
+    }
 }
 
 /// A session in an application, either a compatibility or an OAuth 2.0 one
@@ -753,6 +868,133 @@ impl UserEmail {
     }
 }
 
+/// A passkey registered by a user, which they can use to log in
+#[derive(Description)]
+pub struct UserPasskey(pub mas_data_model::UserPasskey);
+
+#[Object(use_type_description)]
+impl UserPasskey {
+    /// ID of the object.
+    pub async fn id(&self) -> ID {
+        NodeType::UserPasskey.id(self.0.id)
+    }
+
+    /// The name the user gave to the passkey
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// The AAGUID of the authenticator model, which identifies its vendor
+    async fn aaguid(&self) -> &str {
+        &self.0.aaguid
+    }
+
+    /// When the object was created.
+    pub async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    /// When the passkey was last used to log in. Is `null` if it was never
+    /// used.
+    async fn last_used_at(&self) -> Option<DateTime<Utc>> {
+        self.0.last_used_at
+    }
+}
+
+/// A TOTP second factor, which the user has to enter a code from when logging
+/// in with their password
+#[derive(Description)]
+pub struct UserTotp(pub mas_data_model::UserTotp);
+
+#[Object(use_type_description)]
+impl UserTotp {
+    /// ID of the object.
+    pub async fn id(&self) -> ID {
+        NodeType::UserTotp.id(self.0.id)
+    }
+
+    /// When the object was created.
+    pub async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    /// When the second factor was confirmed with a first code.
+    async fn confirmed_at(&self) -> Option<DateTime<Utc>> {
+        self.0.confirmed_at
+    }
+
+    /// When a code was last used to log in. Is `null` if it was never used.
+    async fn last_used_at(&self) -> Option<DateTime<Utc>> {
+        self.0.last_used_at
+    }
+}
+
+/// A phone number of a user, which receives verification codes by SMS
+#[derive(Description)]
+pub struct UserPhone(pub mas_data_model::UserPhone);
+
+#[Object(use_type_description)]
+impl UserPhone {
+    /// ID of the object.
+    pub async fn id(&self) -> ID {
+        NodeType::UserPhone.id(self.0.id)
+    }
+
+    /// The phone number, in the E.164 format.
+    async fn phone_number(&self) -> &str {
+        &self.0.phone_number
+    }
+
+    /// When the object was created.
+    pub async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    /// When the phone number was confirmed with a code. Is `null` if it is
+    /// still pending confirmation.
+    async fn confirmed_at(&self) -> Option<DateTime<Utc>> {
+        self.0.confirmed_at
+    }
+}
+
+/// A browser in which the user chose not to be asked for their second factor
+/// again
+#[derive(Description)]
+pub struct UserTrustedDevice(pub mas_data_model::UserTrustedDevice);
+
+#[Object(use_type_description)]
+impl UserTrustedDevice {
+    /// ID of the object.
+    pub async fn id(&self) -> ID {
+        NodeType::UserTrustedDevice.id(self.0.id)
+    }
+
+    /// The user-agent of the browser when it was trusted.
+    async fn user_agent(&self) -> Option<UserAgent> {
+        self.0
+            .user_agent
+            .clone()
+            .map(mas_data_model::UserAgent::parse)
+            .map(UserAgent::from)
+    }
+
+    /// When the object was created.
+    pub async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    /// When the browser stops being trusted.
+    async fn expires_at(&self) -> DateTime<Utc> {
+        self.0.expires_at
+    }
+
+    /// When the browser was last used to skip the second factor. Is `null` if
+    /// it was never used.
+    async fn last_used_at(&self) -> Option<DateTime<Utc>> {
+        self.0.last_used_at
+    }
+}
+
 /// The state of a compatibility session.
 #[derive(Enum, Copy, Clone, Eq, PartialEq)]
 pub enum UserEmailState {
@@ -5,7 +5,10 @@
 // Please see LICENSE files in the repository root for full details.
 
 use async_graphql::{Context, Enum, ID, InputObject, Object};
-use mas_storage::RepositoryAccess;
+use mas_storage::{
+    RepositoryAccess,
+    queue::{QueueJobRepositoryExt as _, SendBrowserSessionBackchannelLogoutsJob},
+};
 
 use crate::graphql::{
     model::{BrowserSession, NodeType},
@@ -73,6 +76,7 @@ impl BrowserSessionMutations {
 
         let mut repo = state.repository().await?;
         let clock = state.clock();
+        let mut rng = state.rng();
 
         let session = repo.browser_session().lookup(browser_session_id).await?;
 
@@ -84,6 +88,14 @@ impl BrowserSessionMutations {
             return Ok(EndBrowserSessionPayload::NotFound);
         }
 
+        repo.queue_job()
+            .schedule_job(
+                &mut rng,
+                &clock,
+                SendBrowserSessionBackchannelLogoutsJob::new(&session),
+            )
+            .await?;
+
         let session = repo.browser_session().finish(&clock, session).await?;
 
         repo.save().await?;
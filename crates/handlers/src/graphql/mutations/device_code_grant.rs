@@ -0,0 +1,200 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::BTreeSet;
+
+use anyhow::Context as _;
+use async_graphql::{Context, Enum, InputObject, Object};
+use mas_storage::{
+    RepositoryAccess,
+    oauth2::{OAuth2ClientRepository, OAuth2DeviceCodeGrantRepository},
+    user::{BrowserSessionRepository, UserEmailRepository},
+};
+use tracing::warn;
+
+use crate::graphql::{model::OAuth2Client, state::ContextExt};
+
+#[derive(Default)]
+pub struct DeviceCodeGrantMutations {
+    _private: (),
+}
+
+/// What to do with the device code grant in the `authorizeDeviceCode`
+/// mutation.
+#[derive(Enum, Copy, Clone, PartialEq, Eq, Debug)]
+enum AuthorizeDeviceCodeAction {
+    /// Let the other device sign in.
+    Approve,
+
+    /// Refuse to let the other device sign in.
+    Reject,
+}
+
+/// The input of the `authorizeDeviceCode` mutation.
+#[derive(InputObject)]
+pub struct AuthorizeDeviceCodeInput {
+    /// The code shown on the other device.
+    user_code: String,
+
+    /// Whether to approve or reject the sign in.
+    action: AuthorizeDeviceCodeAction,
+}
+
+/// The payload of the `authorizeDeviceCode` mutation.
+pub enum AuthorizeDeviceCodePayload {
+    InvalidCode,
+    NotAllowed,
+    PolicyViolation(mas_data_model::Client),
+    Approved(mas_data_model::Client),
+    Rejected(mas_data_model::Client),
+}
+
+/// The status of the `authorizeDeviceCode` mutation.
+#[derive(Enum, Copy, Clone, PartialEq, Eq, Debug)]
+enum AuthorizeDeviceCodeStatus {
+    /// The other device is now signed in.
+    Approved,
+
+    /// The sign in on the other device was rejected.
+    Rejected,
+
+    /// The code is unknown, expired, or was already used.
+    InvalidCode,
+
+    /// The requester isn't tied to a browser session in which the other device
+    /// could be signed in.
+    NotAllowed,
+
+    /// The policy doesn't allow the user to sign in on this client.
+    PolicyViolation,
+}
+
+#[Object]
+impl AuthorizeDeviceCodePayload {
+    /// The status of the mutation.
+    async fn status(&self) -> AuthorizeDeviceCodeStatus {
+        match self {
+            Self::Approved(_) => AuthorizeDeviceCodeStatus::Approved,
+            Self::Rejected(_) => AuthorizeDeviceCodeStatus::Rejected,
+            Self::InvalidCode => AuthorizeDeviceCodeStatus::InvalidCode,
+            Self::NotAllowed => AuthorizeDeviceCodeStatus::NotAllowed,
+            Self::PolicyViolation(_) => AuthorizeDeviceCodeStatus::PolicyViolation,
+        }
+    }
+
+    /// The client which started the sign in on the other device, if the code
+    /// was valid.
+    async fn oauth2_client(&self) -> Option<OAuth2Client> {
+        match self {
+            Self::Approved(client) | Self::Rejected(client) | Self::PolicyViolation(client) => {
+                Some(OAuth2Client(client.clone()))
+            }
+            Self::InvalidCode | Self::NotAllowed => None,
+        }
+    }
+}
+
+#[Object]
+impl DeviceCodeGrantMutations {
+    /// Approve or reject a sign in started on another device, using the code
+    /// it shows.
+    ///
+    /// This lets a device without a convenient browser, like a TV, sign in
+    /// through a session which is already signed in on another device. The
+    /// other device ends up signed in to the same browser session as the
+    /// requester, so OAuth 2.0 sessions can only use this if they were started
+    /// from a browser session which is still active.
+    async fn authorize_device_code(
+        &self,
+        ctx: &Context<'_>,
+        input: AuthorizeDeviceCodeInput,
+    ) -> Result<AuthorizeDeviceCodePayload, async_graphql::Error> {
+        let state = ctx.state();
+        let requester = ctx.requester();
+        let clock = state.clock();
+        let site_config = state.site_config();
+
+        let mut repo = state.repository().await?;
+
+        // The grant is fulfilled by a browser session: either the one of the
+        // requester, or the one from which its OAuth 2.0 session was started
+        let browser_session = if let Some(browser_session) = requester.browser_session() {
+            Some(browser_session.clone())
+        } else if let Some(session_id) = requester
+            .oauth2_session()
+            .and_then(|session| session.user_session_id)
+        {
+            repo.browser_session()
+                .lookup(session_id)
+                .await?
+                .filter(|session| session.active())
+                .filter(|session| {
+                    !site_config
+                        .session_expiration
+                        .as_ref()
+                        .is_some_and(|config| session.expired(clock.now(), config))
+                })
+        } else {
+            None
+        };
+
+        let Some(browser_session) = browser_session else {
+            return Ok(AuthorizeDeviceCodePayload::NotAllowed);
+        };
+
+        let user_code = input.user_code.trim().to_uppercase();
+        let grant = repo
+            .oauth2_device_code_grant()
+            .find_by_user_code(&user_code)
+            .await?
+            .filter(|grant| grant.is_pending())
+            .filter(|grant| grant.expires_at > clock.now());
+
+        let Some(grant) = grant else {
+            return Ok(AuthorizeDeviceCodePayload::InvalidCode);
+        };
+
+        let client = repo
+            .oauth2_client()
+            .lookup(grant.client_id)
+            .await?
+            .context("Failed to load client")?;
+
+        if input.action == AuthorizeDeviceCodeAction::Reject {
+            repo.oauth2_device_code_grant()
+                .reject(&clock, grant, &browser_session)
+                .await?;
+            repo.save().await?;
+
+            return Ok(AuthorizeDeviceCodePayload::Rejected(client));
+        }
+
+        // This is the same check as on the consent page
+        let mut policy = state.policy().await?;
+        let emails = repo.user_email().all(&browser_session.user).await?;
+        let res = policy
+            .evaluate_authorization_grant(mas_policy::AuthorizationGrantInput {
+                grant_type: mas_policy::GrantType::DeviceCode,
+                requested_claims: BTreeSet::new(),
+                client: &client,
+                scope: &grant.scope,
+                user: Some(&browser_session.user),
+                emails: emails.iter().map(|e| e.email.as_str()).collect(),
+                requester: requester.for_policy(),
+            })
+            .await?;
+        if !res.valid() {
+            warn!(violation = ?res, "Device code grant for client {} denied by policy", client.id);
+            return Ok(AuthorizeDeviceCodePayload::PolicyViolation(client));
+        }
+
+        repo.oauth2_device_code_grant()
+            .fulfill(&clock, grant, &browser_session)
+            .await?;
+        repo.save().await?;
+
+        Ok(AuthorizeDeviceCodePayload::Approved(client))
+    }
+}
@@ -4,8 +4,10 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::io::Read as _;
+
 use anyhow::Context as _;
-use async_graphql::{Context, Description, Enum, ID, InputObject, Object};
+use async_graphql::{Context, Description, Enum, ID, InputObject, Object, Upload};
 
 use crate::graphql::{
     UserId,
@@ -13,6 +15,12 @@ use crate::graphql::{
     state::ContextExt,
 };
 
+/// The maximum size of an avatar, in bytes
+const MAX_AVATAR_SIZE: u64 = 1024 * 1024;
+
+/// The image formats accepted for avatars
+const AVATAR_CONTENT_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
 #[derive(Default)]
 pub struct MatrixMutations {
     _private: (),
@@ -35,6 +43,8 @@ pub enum SetDisplayNameStatus {
     Set,
     /// The display name is invalid
     Invalid,
+    /// The display name was denied by the policy
+    Denied,
 }
 
 /// The payload of the `setDisplayName` mutation
@@ -42,6 +52,9 @@ pub enum SetDisplayNameStatus {
 enum SetDisplayNamePayload {
     Set(User),
     Invalid,
+    Denied {
+        violations: Vec<mas_policy::Violation>,
+    },
 }
 
 #[Object(use_type_description)]
@@ -51,6 +64,7 @@ impl SetDisplayNamePayload {
         match self {
             SetDisplayNamePayload::Set(_) => SetDisplayNameStatus::Set,
             SetDisplayNamePayload::Invalid => SetDisplayNameStatus::Invalid,
+            SetDisplayNamePayload::Denied { .. } => SetDisplayNameStatus::Denied,
         }
     }
 
@@ -58,7 +72,66 @@ impl SetDisplayNamePayload {
     async fn user(&self) -> Option<&User> {
         match self {
             SetDisplayNamePayload::Set(user) => Some(user),
-            SetDisplayNamePayload::Invalid => None,
+            SetDisplayNamePayload::Invalid | SetDisplayNamePayload::Denied { .. } => None,
+        }
+    }
+
+    /// The list of policy violations if the display name was denied
+    async fn violations(&self) -> Option<Vec<String>> {
+        let SetDisplayNamePayload::Denied { violations } = self else {
+            return None;
+        };
+
+        let messages = violations.iter().map(|v| v.msg.clone()).collect();
+        Some(messages)
+    }
+}
+
+/// The input for the `setAvatar` mutation
+#[derive(InputObject)]
+struct SetAvatarInput {
+    /// The ID of the user to set the avatar of
+    user_id: ID,
+
+    /// The image to use as avatar. If `None`, the avatar will be removed.
+    avatar: Option<Upload>,
+}
+
+/// The status of the `setAvatar` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum SetAvatarStatus {
+    /// The avatar was set
+    Set,
+    /// The image is not in a supported format
+    InvalidFormat,
+    /// The image is too large
+    TooLarge,
+}
+
+/// The payload of the `setAvatar` mutation
+#[derive(Description)]
+enum SetAvatarPayload {
+    Set(User),
+    InvalidFormat,
+    TooLarge,
+}
+
+#[Object(use_type_description)]
+impl SetAvatarPayload {
+    /// Status of the operation
+    async fn status(&self) -> SetAvatarStatus {
+        match self {
+            SetAvatarPayload::Set(_) => SetAvatarStatus::Set,
+            SetAvatarPayload::InvalidFormat => SetAvatarStatus::InvalidFormat,
+            SetAvatarPayload::TooLarge => SetAvatarStatus::TooLarge,
+        }
+    }
+
+    /// The user that was updated
+    async fn user(&self) -> Option<&User> {
+        match self {
+            SetAvatarPayload::Set(user) => Some(user),
+            SetAvatarPayload::InvalidFormat | SetAvatarPayload::TooLarge => None,
         }
     }
 }
@@ -105,6 +178,22 @@ impl MatrixMutations {
                 return Ok(SetDisplayNamePayload::Invalid);
             }
 
+            // Admins can set any display name, like with the other policies
+            if !requester.is_admin() {
+                let mut policy = state.policy().await?;
+                let res = policy
+                    .evaluate_display_name(mas_policy::DisplayNameInput {
+                        display_name,
+                        requester: requester.for_policy(),
+                    })
+                    .await?;
+                if !res.valid() {
+                    return Ok(SetDisplayNamePayload::Denied {
+                        violations: res.violations,
+                    });
+                }
+            }
+
             conn.set_displayname(&mxid, display_name)
                 .await
                 .context("Failed to set display name")?;
@@ -116,4 +205,69 @@ impl MatrixMutations {
 
         Ok(SetDisplayNamePayload::Set(User(user.clone())))
     }
+
+    /// Set the avatar of a user, by uploading an image to the homeserver
+    async fn set_avatar(
+        &self,
+        ctx: &Context<'_>,
+        input: SetAvatarInput,
+    ) -> Result<SetAvatarPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let id = NodeType::User.extract_ulid(&input.user_id)?;
+        let requester = ctx.requester();
+
+        if !requester.is_owner_or_admin(&UserId(id)) {
+            return Err(async_graphql::Error::new("Unauthorized"));
+        }
+
+        // Allow non-admins to change their avatar if the site config allows it
+        if !requester.is_admin() && !state.site_config().avatar_change_allowed {
+            return Err(async_graphql::Error::new("Unauthorized"));
+        }
+
+        let mut repo = state.repository().await?;
+        let user = repo
+            .user()
+            .lookup(id)
+            .await?
+            .context("Failed to lookup user")?;
+        repo.cancel().await?;
+
+        let conn = state.homeserver_connection();
+        let mxid = conn.mxid(&user.username);
+
+        if let Some(avatar) = &input.avatar {
+            let avatar = avatar.value(ctx)?;
+
+            let Some(content_type) = avatar
+                .content_type
+                .clone()
+                .filter(|content_type| AVATAR_CONTENT_TYPES.contains(&content_type.as_str()))
+            else {
+                return Ok(SetAvatarPayload::InvalidFormat);
+            };
+
+            if avatar.size()? > MAX_AVATAR_SIZE {
+                return Ok(SetAvatarPayload::TooLarge);
+            }
+
+            let mut data = Vec::new();
+            avatar.into_read().read_to_end(&mut data)?;
+
+            let avatar_url = conn
+                .upload_media(&content_type, data)
+                .await
+                .context("Failed to upload avatar")?;
+
+            conn.set_avatar_url(&mxid, &avatar_url)
+                .await
+                .context("Failed to set avatar")?;
+        } else {
+            conn.unset_avatar_url(&mxid)
+                .await
+                .context("Failed to unset avatar")?;
+        }
+
+        Ok(SetAvatarPayload::Set(User(user.clone())))
+    }
 }
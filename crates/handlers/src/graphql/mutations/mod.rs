@@ -6,15 +6,22 @@
 
 mod browser_session;
 mod compat_session;
+mod device_code_grant;
 mod matrix;
+mod oauth2_consent;
 mod oauth2_session;
+mod upstream_oauth;
 mod user;
 mod user_email;
+mod user_passkey;
+mod user_phone;
+mod user_totp;
+mod user_trusted_device;
 
 use anyhow::Context as _;
 use async_graphql::MergedObject;
 use mas_data_model::SiteConfig;
-use mas_storage::BoxRepository;
+use mas_storage::{BoxRepository, Clock};
 use zeroize::Zeroizing;
 
 use super::Requester;
@@ -24,10 +31,17 @@ use crate::passwords::PasswordManager;
 #[derive(Default, MergedObject)]
 pub struct Mutation(
     user_email::UserEmailMutations,
+    user_passkey::UserPasskeyMutations,
+    user_phone::UserPhoneMutations,
+    user_totp::UserTotpMutations,
+    user_trusted_device::UserTrustedDeviceMutations,
     user::UserMutations,
     oauth2_session::OAuth2SessionMutations,
+    oauth2_consent::OAuth2ConsentMutations,
+    device_code_grant::DeviceCodeGrantMutations,
     compat_session::CompatSessionMutations,
     browser_session::BrowserSessionMutations,
+    upstream_oauth::UpstreamOAuthMutations,
     matrix::MatrixMutations,
 );
 
@@ -38,26 +52,92 @@ impl Mutation {
     }
 }
 
-/// Check the password if neeed
+/// The outcome of [`confirm_identity`]
+enum IdentityConfirmation {
+    /// The requester can go on with the sensitive action
+    Confirmed,
+
+    /// The password is incorrect, or missing
+    IncorrectPassword,
+
+    /// The user has no password to confirm, and didn't authenticate recently
+    /// enough in their browser session: they have to authenticate again first
+    ReauthenticationRequired,
+}
+
+/// Check whether the browser session of the requester authenticated recently
+/// enough to be in sudo mode
+///
+/// Both logging in and authenticating again in the session count, be it by
+/// stepping up with the password, or with a passkey or an upstream provider.
+/// Always returns false if sudo mode is disabled, or if the requester isn't a
+/// browser session.
+async fn in_sudo_mode(
+    requester: &Requester,
+    config: &SiteConfig,
+    clock: &impl Clock,
+    repo: &mut BoxRepository,
+) -> Result<bool, async_graphql::Error> {
+    let (Some(ttl), Some(browser_session)) = (config.sudo_mode_ttl, requester.browser_session())
+    else {
+        return Ok(false);
+    };
+
+    let last_authentication = repo
+        .browser_session()
+        .get_last_authentication(browser_session)
+        .await?
+        .map(|authentication| authentication.created_at);
+    let last_elevation = repo
+        .browser_session()
+        .get_last_elevation(browser_session)
+        .await?
+        .map(|elevation| elevation.created_at);
+
+    let Some(authenticated_at) = last_authentication.max(last_elevation) else {
+        return Ok(false);
+    };
+
+    Ok(clock.now() < authenticated_at + ttl)
+}
+
+/// Check that the requester is allowed to make a sensitive change to the
+/// account of the user
 ///
-/// Returns true if password verification is not needed, or if the password is
-/// correct. Returns false if the password is incorrect or missing.
-async fn verify_password_if_needed(
+/// Admins always are. Users have to confirm their password if they have one,
+/// unless their browser session is in sudo mode. If sudo mode is enabled,
+/// users without a password have to be in sudo mode.
+async fn confirm_identity(
     requester: &Requester,
     config: &SiteConfig,
+    clock: &impl Clock,
     password_manager: &PasswordManager,
     password: Option<String>,
     user: &mas_data_model::User,
     repo: &mut BoxRepository,
-) -> Result<bool, async_graphql::Error> {
+) -> Result<IdentityConfirmation, async_graphql::Error> {
     // If the requester is admin, they don't need to provide a password
     if requester.is_admin() {
-        return Ok(true);
+        return Ok(IdentityConfirmation::Confirmed);
     }
 
+    // A password given anyway is still checked
+    if password.is_none() && in_sudo_mode(requester, config, clock, repo).await? {
+        return Ok(IdentityConfirmation::Confirmed);
+    }
+
+    // Without sudo mode, users who can't confirm a password aren't asked to
+    // authenticate again
+    let no_password = if config.sudo_mode_ttl.is_some() {
+        IdentityConfirmation::ReauthenticationRequired
+    } else {
+        IdentityConfirmation::Confirmed
+    };
+
     // If password login is disabled, assume we don't want the user to reauth
+    // with a password
     if !config.password_login_enabled {
-        return Ok(true);
+        return Ok(no_password);
     }
 
     // Else we need to check if the user has a password
@@ -67,13 +147,13 @@ async fn verify_password_if_needed(
         .await
         .context("Failed to load user password")?
     else {
-        // User has no password, so we don't need to verify the password
-        return Ok(true);
+        // User has no password, so we can't verify the password
+        return Ok(no_password);
     };
 
     let Some(password) = password else {
         // There is a password on the user, but not provided in the input
-        return Ok(false);
+        return Ok(IdentityConfirmation::IncorrectPassword);
     };
 
     let password = Zeroizing::new(password);
@@ -86,5 +166,9 @@ async fn verify_password_if_needed(
         )
         .await;
 
-    Ok(res.is_ok())
+    if res.is_ok() {
+        Ok(IdentityConfirmation::Confirmed)
+    } else {
+        Ok(IdentityConfirmation::IncorrectPassword)
+    }
 }
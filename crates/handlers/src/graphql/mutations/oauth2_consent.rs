@@ -0,0 +1,181 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_graphql::{Context, Enum, ID, InputObject, Object};
+use mas_storage::{
+    RepositoryAccess,
+    oauth2::{OAuth2ClientRepository, OAuth2ConsentRepository},
+    user::UserRepository,
+};
+
+use crate::graphql::{
+    UserId,
+    model::{NodeType, OAuth2Consent},
+    state::ContextExt,
+};
+
+#[derive(Default)]
+pub struct OAuth2ConsentMutations {
+    _private: (),
+}
+
+/// The input of the `revokeOauth2Consent` mutation.
+#[derive(InputObject)]
+pub struct RevokeOAuth2ConsentInput {
+    /// The ID of the consent to revoke.
+    oauth2_consent_id: ID,
+}
+
+/// The payload of the `revokeOauth2Consent` mutation.
+pub enum RevokeOAuth2ConsentPayload {
+    NotFound,
+    Revoked(mas_data_model::Consent),
+}
+
+/// The status of the `revokeOauth2Consent` mutation.
+#[derive(Enum, Copy, Clone, PartialEq, Eq, Debug)]
+enum RevokeOAuth2ConsentStatus {
+    /// The consent was revoked.
+    Revoked,
+
+    /// The consent was not found.
+    NotFound,
+}
+
+#[Object]
+impl RevokeOAuth2ConsentPayload {
+    /// The status of the mutation.
+    async fn status(&self) -> RevokeOAuth2ConsentStatus {
+        match self {
+            Self::Revoked(_) => RevokeOAuth2ConsentStatus::Revoked,
+            Self::NotFound => RevokeOAuth2ConsentStatus::NotFound,
+        }
+    }
+
+    /// Returns the revoked consent.
+    async fn oauth2_consent(&self) -> Option<OAuth2Consent> {
+        match self {
+            Self::Revoked(consent) => Some(OAuth2Consent(consent.clone())),
+            Self::NotFound => None,
+        }
+    }
+}
+
+/// The input of the `revokeOauth2ClientConsents` mutation.
+#[derive(InputObject)]
+pub struct RevokeOAuth2ClientConsentsInput {
+    /// The ID of the user who gave the consents.
+    user_id: ID,
+
+    /// The ID of the client to revoke the consents for.
+    oauth2_client_id: ID,
+}
+
+/// The payload of the `revokeOauth2ClientConsents` mutation.
+pub enum RevokeOAuth2ClientConsentsPayload {
+    NotFound,
+    Revoked(usize),
+}
+
+/// The status of the `revokeOauth2ClientConsents` mutation.
+#[derive(Enum, Copy, Clone, PartialEq, Eq, Debug)]
+enum RevokeOAuth2ClientConsentsStatus {
+    /// The consents were revoked.
+    Revoked,
+
+    /// The user or the client was not found.
+    NotFound,
+}
+
+#[Object]
+impl RevokeOAuth2ClientConsentsPayload {
+    /// The status of the mutation.
+    async fn status(&self) -> RevokeOAuth2ClientConsentsStatus {
+        match self {
+            Self::Revoked(_) => RevokeOAuth2ClientConsentsStatus::Revoked,
+            Self::NotFound => RevokeOAuth2ClientConsentsStatus::NotFound,
+        }
+    }
+
+    /// The number of scope tokens which were revoked.
+    async fn revoked_count(&self) -> usize {
+        match self {
+            Self::Revoked(count) => *count,
+            Self::NotFound => 0,
+        }
+    }
+}
+
+#[Object]
+impl OAuth2ConsentMutations {
+    /// Revoke a single scope a user consented to grant to a client.
+    ///
+    /// This does not end existing sessions, but the user will be asked again
+    /// the next time the client requests this scope.
+    async fn revoke_oauth2_consent(
+        &self,
+        ctx: &Context<'_>,
+        input: RevokeOAuth2ConsentInput,
+    ) -> Result<RevokeOAuth2ConsentPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let oauth2_consent_id = NodeType::OAuth2Consent.extract_ulid(&input.oauth2_consent_id)?;
+        let requester = ctx.requester();
+
+        let mut repo = state.repository().await?;
+
+        let consent = repo.oauth2_consent().lookup(oauth2_consent_id).await?;
+        let Some(consent) = consent else {
+            return Ok(RevokeOAuth2ConsentPayload::NotFound);
+        };
+
+        if !requester.is_owner_or_admin(&consent) {
+            return Ok(RevokeOAuth2ConsentPayload::NotFound);
+        }
+
+        repo.oauth2_consent().revoke(consent.clone()).await?;
+
+        repo.save().await?;
+
+        Ok(RevokeOAuth2ConsentPayload::Revoked(consent))
+    }
+
+    /// Revoke all the scopes a user consented to grant to a client.
+    ///
+    /// This does not end existing sessions, but the user will be asked for
+    /// consent again the next time the client requests authorization.
+    async fn revoke_oauth2_client_consents(
+        &self,
+        ctx: &Context<'_>,
+        input: RevokeOAuth2ClientConsentsInput,
+    ) -> Result<RevokeOAuth2ClientConsentsPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let user_id = NodeType::User.extract_ulid(&input.user_id)?;
+        let oauth2_client_id = NodeType::OAuth2Client.extract_ulid(&input.oauth2_client_id)?;
+        let requester = ctx.requester();
+
+        if !requester.is_owner_or_admin(&UserId(user_id)) {
+            return Ok(RevokeOAuth2ClientConsentsPayload::NotFound);
+        }
+
+        let mut repo = state.repository().await?;
+
+        let Some(user) = repo.user().lookup(user_id).await? else {
+            return Ok(RevokeOAuth2ClientConsentsPayload::NotFound);
+        };
+
+        let Some(client) = repo.oauth2_client().lookup(oauth2_client_id).await? else {
+            return Ok(RevokeOAuth2ClientConsentsPayload::NotFound);
+        };
+
+        let count = repo
+            .oauth2_consent()
+            .revoke_for_client(&client, &user)
+            .await?;
+
+        repo.save().await?;
+
+        Ok(RevokeOAuth2ClientConsentsPayload::Revoked(count))
+    }
+}
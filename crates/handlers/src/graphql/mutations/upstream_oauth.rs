@@ -0,0 +1,217 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use anyhow::Context as _;
+use async_graphql::{Context, Description, Enum, ID, InputObject, Object};
+use mas_data_model::{SiteConfig, UpstreamOAuthLink};
+use mas_storage::{
+    BoxRepository, RepositoryAccess,
+    upstream_oauth2::{
+        UpstreamOAuthLinkFilter, UpstreamOAuthLinkRepository, UpstreamOAuthProviderRepository,
+    },
+    user::{UserEmailFilter, UserEmailRepository, UserPasskeyRepository, UserRepository},
+};
+
+use super::{IdentityConfirmation, confirm_identity};
+use crate::graphql::{
+    model::{NodeType, User},
+    state::ContextExt,
+};
+
+#[derive(Default)]
+pub struct UpstreamOAuthMutations {
+    _private: (),
+}
+
+/// The input for the `removeUpstreamLink` mutation
+#[derive(InputObject)]
+struct RemoveUpstreamLinkInput {
+    /// The ID of the upstream link to remove
+    upstream_link_id: ID,
+
+    /// The user's current password. This is required if the user is not an
+    /// admin and it has a password on its account.
+    password: Option<String>,
+}
+
+/// The status of the `removeUpstreamLink` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum RemoveUpstreamLinkStatus {
+    /// The upstream link was removed
+    Removed,
+
+    /// The upstream link was not found
+    NotFound,
+
+    /// The password provided is incorrect
+    IncorrectPassword,
+
+    /// The user has to authenticate again first
+    ReauthenticationRequired,
+
+    /// The upstream link is the only way left for the user to sign in
+    LastLoginMethod,
+}
+
+/// The payload of the `removeUpstreamLink` mutation
+#[derive(Description)]
+enum RemoveUpstreamLinkPayload {
+    Removed(UpstreamOAuthLink),
+    NotFound,
+    IncorrectPassword,
+    ReauthenticationRequired,
+    LastLoginMethod,
+}
+
+#[Object(use_type_description)]
+impl RemoveUpstreamLinkPayload {
+    /// Status of the operation
+    async fn status(&self) -> RemoveUpstreamLinkStatus {
+        match self {
+            Self::Removed(_) => RemoveUpstreamLinkStatus::Removed,
+            Self::NotFound => RemoveUpstreamLinkStatus::NotFound,
+            Self::IncorrectPassword => RemoveUpstreamLinkStatus::IncorrectPassword,
+            Self::ReauthenticationRequired => RemoveUpstreamLinkStatus::ReauthenticationRequired,
+            Self::LastLoginMethod => RemoveUpstreamLinkStatus::LastLoginMethod,
+        }
+    }
+
+    /// The user to whom the upstream link belonged
+    async fn user(&self, ctx: &Context<'_>) -> Result<Option<User>, async_graphql::Error> {
+        let state = ctx.state();
+
+        let Self::Removed(link) = self else {
+            return Ok(None);
+        };
+        let Some(user_id) = link.user_id else {
+            return Ok(None);
+        };
+
+        let mut repo = state.repository().await?;
+
+        let user = repo
+            .user()
+            .lookup(user_id)
+            .await?
+            .context("User not found")?;
+
+        Ok(Some(User(user)))
+    }
+}
+
+/// Check whether the user can still sign in some other way once the given
+/// upstream link is removed
+async fn has_other_login_method(
+    config: &SiteConfig,
+    user: &mas_data_model::User,
+    link: &UpstreamOAuthLink,
+    repo: &mut BoxRepository,
+) -> Result<bool, async_graphql::Error> {
+    if config.password_login_enabled && repo.user_password().active(user).await?.is_some() {
+        return Ok(true);
+    }
+
+    if config.passkeys_enabled && !repo.user_passkey().all(user).await?.is_empty() {
+        return Ok(true);
+    }
+
+    if config.email_code_login_enabled
+        && repo
+            .user_email()
+            .count(UserEmailFilter::new().for_user(user))
+            .await?
+            > 0
+    {
+        return Ok(true);
+    }
+
+    // Links to disabled providers can't be used to sign in
+    let link_provider_enabled = repo
+        .upstream_oauth_provider()
+        .lookup(link.provider_id)
+        .await?
+        .is_some_and(|provider| provider.enabled());
+    let enabled_links = repo
+        .upstream_oauth_link()
+        .count(
+            UpstreamOAuthLinkFilter::new()
+                .for_user(user)
+                .enabled_providers_only(),
+        )
+        .await?;
+
+    Ok(enabled_links > usize::from(link_provider_enabled))
+}
+
+#[Object]
+impl UpstreamOAuthMutations {
+    /// Remove an upstream link from an account, so that the upstream account
+    /// can't be used to sign in to it anymore.
+    ///
+    /// This is refused if the user would be left without any way to sign in.
+    async fn remove_upstream_link(
+        &self,
+        ctx: &Context<'_>,
+        input: RemoveUpstreamLinkInput,
+    ) -> Result<RemoveUpstreamLinkPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let upstream_link_id =
+            NodeType::UpstreamOAuth2Link.extract_ulid(&input.upstream_link_id)?;
+        let requester = ctx.requester();
+        let clock = state.clock();
+
+        let mut repo = state.repository().await?;
+
+        let link = repo.upstream_oauth_link().lookup(upstream_link_id).await?;
+        let Some(link) = link else {
+            return Ok(RemoveUpstreamLinkPayload::NotFound);
+        };
+
+        if !requester.is_owner_or_admin(&link) {
+            return Ok(RemoveUpstreamLinkPayload::NotFound);
+        }
+
+        // Links not associated with a user can't be owned by the requester
+        let user_id = link.user_id.context("Link isn't associated with a user")?;
+        let user = repo
+            .user()
+            .lookup(user_id)
+            .await?
+            .context("Failed to load user")?;
+
+        // Validate the password input if needed
+        match confirm_identity(
+            requester,
+            state.site_config(),
+            &clock,
+            &state.password_manager(),
+            input.password,
+            &user,
+            &mut repo,
+        )
+        .await?
+        {
+            IdentityConfirmation::Confirmed => {}
+            IdentityConfirmation::IncorrectPassword => {
+                return Ok(RemoveUpstreamLinkPayload::IncorrectPassword);
+            }
+            IdentityConfirmation::ReauthenticationRequired => {
+                return Ok(RemoveUpstreamLinkPayload::ReauthenticationRequired);
+            }
+        }
+
+        if !has_other_login_method(state.site_config(), &user, &link, &mut repo).await? {
+            return Ok(RemoveUpstreamLinkPayload::LastLoginMethod);
+        }
+
+        repo.upstream_oauth_link()
+            .remove(&clock, link.clone())
+            .await?;
+
+        repo.save().await?;
+
+        Ok(RemoveUpstreamLinkPayload::Removed(link))
+    }
+}
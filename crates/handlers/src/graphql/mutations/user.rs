@@ -8,7 +8,7 @@ use anyhow::Context as _;
 use async_graphql::{Context, Description, Enum, ID, InputObject, Object};
 use mas_storage::{
     queue::{
-        DeactivateUserJob, ProvisionUserJob, QueueJobRepositoryExt as _,
+        DeactivateUserJob, DeleteUserJob, ProvisionUserJob, QueueJobRepositoryExt as _,
         SendAccountRecoveryEmailsJob,
     },
     user::UserRepository,
@@ -18,11 +18,14 @@ use ulid::Ulid;
 use url::Url;
 use zeroize::Zeroizing;
 
-use super::verify_password_if_needed;
-use crate::graphql::{
-    UserId,
-    model::{NodeType, User},
-    state::ContextExt,
+use super::{IdentityConfirmation, confirm_identity};
+use crate::{
+    graphql::{
+        UserId,
+        model::{NodeType, User},
+        state::ContextExt,
+    },
+    password_policy,
 };
 
 #[derive(Default)]
@@ -319,6 +322,10 @@ enum SetPasswordStatus {
 
     /// Your account is locked and you can't change its password.
     AccountLocked,
+
+    /// The new password is one of the previous passwords of the user, which
+    /// the password history policy doesn't allow to reuse.
+    PasswordReused,
 }
 
 #[Object(use_type_description)]
@@ -409,6 +416,9 @@ pub enum DeactivateUserPayload {
 
     /// The password was wrong or missing.
     IncorrectPassword,
+
+    /// The user has to authenticate again first.
+    ReauthenticationRequired,
 }
 
 /// The status of the `deactivateUser` mutation.
@@ -419,6 +429,9 @@ pub enum DeactivateUserStatus {
 
     /// The password was wrong.
     IncorrectPassword,
+
+    /// The user has to authenticate again first.
+    ReauthenticationRequired,
 }
 
 #[Object(use_type_description)]
@@ -428,13 +441,14 @@ impl DeactivateUserPayload {
         match self {
             Self::Deactivated(_) => DeactivateUserStatus::Deactivated,
             Self::IncorrectPassword => DeactivateUserStatus::IncorrectPassword,
+            Self::ReauthenticationRequired => DeactivateUserStatus::ReauthenticationRequired,
         }
     }
 
     async fn user(&self) -> Option<User> {
         match self {
             Self::Deactivated(user) => Some(User(user.clone())),
-            Self::IncorrectPassword => None,
+            Self::IncorrectPassword | Self::ReauthenticationRequired => None,
         }
     }
 }
@@ -753,9 +767,27 @@ impl UserMutations {
             }
         }
 
-        let (new_password_version, new_password_hash) = password_manager
-            .hash(state.rng(), Zeroizing::new(input.new_password))
-            .await?;
+        let new_password = Zeroizing::new(input.new_password);
+
+        // Administrators can set any password, but users can't reuse one of their
+        // previous passwords
+        if !requester.is_admin()
+            && password_policy::is_reused(
+                &mut repo,
+                &password_manager,
+                state.site_config(),
+                &user,
+                &new_password,
+            )
+            .await?
+        {
+            return Ok(SetPasswordPayload {
+                status: SetPasswordStatus::PasswordReused,
+            });
+        }
+
+        let (new_password_version, new_password_hash) =
+            password_manager.hash(state.rng(), new_password).await?;
 
         repo.user_password()
             .add(
@@ -848,9 +880,24 @@ impl UserMutations {
             });
         }
 
-        let (new_password_version, new_password_hash) = password_manager
-            .hash(state.rng(), Zeroizing::new(input.new_password))
-            .await?;
+        let new_password = Zeroizing::new(input.new_password);
+
+        if password_policy::is_reused(
+            &mut repo,
+            &password_manager,
+            state.site_config(),
+            &user,
+            &new_password,
+        )
+        .await?
+        {
+            return Ok(SetPasswordPayload {
+                status: SetPasswordStatus::PasswordReused,
+            });
+        }
+
+        let (new_password_version, new_password_hash) =
+            password_manager.hash(state.rng(), new_password).await?;
 
         repo.user_password()
             .add(
@@ -863,6 +910,28 @@ impl UserMutations {
             )
             .await?;
 
+        // If the user told us they lost their second factor and an administrator
+        // approved the request, drop it so that they can log in with their new
+        // password and enroll a new one
+        if session.lost_second_factor && session.requires_approval {
+            info!(user.id = %user.id, "Removing second factors after approved account recovery");
+
+            if let Some(totp) = repo.user_totp().find_for_user(&user).await? {
+                repo.user_totp().remove(totp).await?;
+            }
+
+            repo.user_recovery_code().remove_all(&user).await?;
+
+            if let Some(phone) = repo.user_phone().find_for_user(&user).await? {
+                repo.user_phone().remove(phone).await?;
+            }
+
+            let devices = repo.user_trusted_device().all_valid(&clock, &user).await?;
+            for device in devices {
+                repo.user_trusted_device().revoke(&clock, device).await?;
+            }
+        }
+
         // Mark the session as consumed
         repo.user_recovery()
             .consume_ticket(&clock, ticket, session)
@@ -952,9 +1021,10 @@ impl UserMutations {
         }
 
         let mut repo = state.repository().await?;
-        if !verify_password_if_needed(
+        match confirm_identity(
             requester,
             site_config,
+            &clock,
             &state.password_manager(),
             input.password,
             &browser_session.user,
@@ -962,7 +1032,13 @@ impl UserMutations {
         )
         .await?
         {
-            return Ok(DeactivateUserPayload::IncorrectPassword);
+            IdentityConfirmation::Confirmed => {}
+            IdentityConfirmation::IncorrectPassword => {
+                return Ok(DeactivateUserPayload::IncorrectPassword);
+            }
+            IdentityConfirmation::ReauthenticationRequired => {
+                return Ok(DeactivateUserPayload::ReauthenticationRequired);
+            }
         }
 
         // Deactivate the user right away
@@ -971,14 +1047,46 @@ impl UserMutations {
             .deactivate(&state.clock(), browser_session.user.clone())
             .await?;
 
-        // and then schedule a job to deactivate it fully
-        repo.queue_job()
-            .schedule_job(
-                &mut rng,
-                &clock,
-                DeactivateUserJob::new(&user, input.hs_erase),
-            )
-            .await?;
+        if let Some(grace_period) = site_config.account_deletion_grace_period {
+            // Only erase the account once the grace period is over, so that the
+            // user can change their mind in the meantime
+            let deletion = repo
+                .user_deletion()
+                .add(
+                    &mut rng,
+                    &clock,
+                    &user,
+                    clock.now() + grace_period,
+                    input.hs_erase,
+                )
+                .await?;
+
+            repo.queue_job()
+                .schedule_job(
+                    &mut rng,
+                    &clock,
+                    DeactivateUserJob::new(&user, false).for_deletion(&deletion),
+                )
+                .await?;
+
+            repo.queue_job()
+                .schedule_job_later(
+                    &mut rng,
+                    &clock,
+                    DeleteUserJob::new(&deletion),
+                    deletion.delete_at,
+                )
+                .await?;
+        } else {
+            // and then schedule a job to deactivate it fully
+            repo.queue_job()
+                .schedule_job(
+                    &mut rng,
+                    &clock,
+                    DeactivateUserJob::new(&user, input.hs_erase),
+                )
+                .await?;
+        }
 
         repo.save().await?;
 
@@ -13,7 +13,7 @@ use mas_storage::{
     user::{UserEmailFilter, UserEmailRepository, UserRepository},
 };
 
-use super::verify_password_if_needed;
+use super::{IdentityConfirmation, confirm_identity};
 use crate::graphql::{
     model::{NodeType, User, UserEmail, UserEmailAuthentication},
     state::ContextExt,
@@ -138,6 +138,9 @@ enum RemoveEmailStatus {
 
     /// The password provided is incorrect
     IncorrectPassword,
+
+    /// The user has to authenticate again first
+    ReauthenticationRequired,
 }
 
 /// The payload of the `removeEmail` mutation
@@ -146,6 +149,7 @@ enum RemoveEmailPayload {
     Removed(mas_data_model::UserEmail),
     NotFound,
     IncorrectPassword,
+    ReauthenticationRequired,
 }
 
 #[Object(use_type_description)]
@@ -156,6 +160,9 @@ impl RemoveEmailPayload {
             RemoveEmailPayload::Removed(_) => RemoveEmailStatus::Removed,
             RemoveEmailPayload::NotFound => RemoveEmailStatus::NotFound,
             RemoveEmailPayload::IncorrectPassword => RemoveEmailStatus::IncorrectPassword,
+            RemoveEmailPayload::ReauthenticationRequired => {
+                RemoveEmailStatus::ReauthenticationRequired
+            }
         }
     }
 
@@ -163,7 +170,9 @@ impl RemoveEmailPayload {
     async fn email(&self) -> Option<UserEmail> {
         match self {
             RemoveEmailPayload::Removed(email) => Some(UserEmail(email.clone())),
-            RemoveEmailPayload::NotFound | RemoveEmailPayload::IncorrectPassword => None,
+            RemoveEmailPayload::NotFound
+            | RemoveEmailPayload::IncorrectPassword
+            | RemoveEmailPayload::ReauthenticationRequired => None,
         }
     }
 
@@ -173,7 +182,9 @@ impl RemoveEmailPayload {
 
         let user_id = match self {
             RemoveEmailPayload::Removed(email) => email.user_id,
-            RemoveEmailPayload::NotFound | RemoveEmailPayload::IncorrectPassword => {
+            RemoveEmailPayload::NotFound
+            | RemoveEmailPayload::IncorrectPassword
+            | RemoveEmailPayload::ReauthenticationRequired => {
                 return Ok(None);
             }
         };
@@ -263,6 +274,8 @@ enum StartEmailAuthenticationStatus {
     InUse,
     /// The password provided is incorrect
     IncorrectPassword,
+    /// The user has to authenticate again first
+    ReauthenticationRequired,
 }
 
 /// The payload of the `startEmailAuthentication` mutation
@@ -276,6 +289,7 @@ enum StartEmailAuthenticationPayload {
     },
     InUse,
     IncorrectPassword,
+    ReauthenticationRequired,
 }
 
 #[Object(use_type_description)]
@@ -289,6 +303,9 @@ impl StartEmailAuthenticationPayload {
             Self::Denied { .. } => StartEmailAuthenticationStatus::Denied,
             Self::InUse => StartEmailAuthenticationStatus::InUse,
             Self::IncorrectPassword => StartEmailAuthenticationStatus::IncorrectPassword,
+            Self::ReauthenticationRequired => {
+                StartEmailAuthenticationStatus::ReauthenticationRequired
+            }
         }
     }
 
@@ -300,7 +317,8 @@ impl StartEmailAuthenticationPayload {
             | Self::RateLimited
             | Self::Denied { .. }
             | Self::InUse
-            | Self::IncorrectPassword => None,
+            | Self::IncorrectPassword
+            | Self::ReauthenticationRequired => None,
         }
     }
 
@@ -518,9 +536,10 @@ impl UserEmailMutations {
             .context("Failed to load user")?;
 
         // Validate the password input if needed
-        if !verify_password_if_needed(
+        match confirm_identity(
             requester,
             state.site_config(),
+            &clock,
             &state.password_manager(),
             input.password,
             &user,
@@ -528,7 +547,13 @@ impl UserEmailMutations {
         )
         .await?
         {
-            return Ok(RemoveEmailPayload::IncorrectPassword);
+            IdentityConfirmation::Confirmed => {}
+            IdentityConfirmation::IncorrectPassword => {
+                return Ok(RemoveEmailPayload::IncorrectPassword);
+            }
+            IdentityConfirmation::ReauthenticationRequired => {
+                return Ok(RemoveEmailPayload::ReauthenticationRequired);
+            }
         }
 
         // TODO: don't allow removing the last email address
@@ -545,10 +570,8 @@ impl UserEmailMutations {
         Ok(RemoveEmailPayload::Removed(user_email))
     }
 
-    /// Set an email address as primary
-    #[graphql(
-        deprecation = "This doesn't do anything anymore, but is kept to avoid breaking existing queries"
-    )]
+    /// Set an email address as primary, so that it is used for notifications
+    /// and as the `email` claim
     async fn set_primary_email(
         &self,
         ctx: &Context<'_>,
@@ -575,13 +598,14 @@ impl UserEmailMutations {
             return Err(async_graphql::Error::new("Unauthorized"));
         }
 
-        // The user primary email should already be up to date
         let user = repo
             .user()
             .lookup(user_email.user_id)
             .await?
             .context("Failed to load user")?;
 
+        repo.user_email().set_as_primary(&user_email).await?;
+
         repo.save().await?;
 
         Ok(SetPrimaryEmailPayload::Set(user))
@@ -665,9 +689,10 @@ impl UserEmailMutations {
         }
 
         // Validate the password input if needed
-        if !verify_password_if_needed(
+        match confirm_identity(
             requester,
             state.site_config(),
+            &clock,
             &state.password_manager(),
             input.password,
             &browser_session.user,
@@ -675,7 +700,13 @@ impl UserEmailMutations {
         )
         .await?
         {
-            return Ok(StartEmailAuthenticationPayload::IncorrectPassword);
+            IdentityConfirmation::Confirmed => {}
+            IdentityConfirmation::IncorrectPassword => {
+                return Ok(StartEmailAuthenticationPayload::IncorrectPassword);
+            }
+            IdentityConfirmation::ReauthenticationRequired => {
+                return Ok(StartEmailAuthenticationPayload::ReauthenticationRequired);
+            }
         }
 
         // Create a new authentication session
@@ -0,0 +1,472 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use anyhow::Context as _;
+use async_graphql::{Context, Description, Enum, ID, InputObject, Object};
+use mas_storage::{
+    RepositoryAccess,
+    user::{UserPasskeyRepository, UserRepository},
+};
+use ulid::Ulid;
+
+use super::{IdentityConfirmation, confirm_identity};
+use crate::{
+    graphql::{
+        model::{NodeType, User, UserPasskey},
+        state::ContextExt,
+    },
+    webauthn::{RelyingParty, is_challenge_valid},
+};
+
+/// The maximum length of a passkey name
+const MAX_NAME_LENGTH: usize = 64;
+
+#[derive(Default)]
+pub struct UserPasskeyMutations {
+    _private: (),
+}
+
+/// Check that a passkey name is acceptable, returning it trimmed
+fn validate_name(name: &str) -> Option<String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return None;
+    }
+
+    Some(name.to_owned())
+}
+
+/// The payload of the `startRegisterPasskey` mutation
+#[derive(Description)]
+struct StartRegisterPasskeyPayload {
+    challenge: mas_data_model::UserPasskeyChallenge,
+    options: serde_json::Value,
+}
+
+#[Object(use_type_description)]
+impl StartRegisterPasskeyPayload {
+    /// The ID of the challenge, to pass to the `completeRegisterPasskey`
+    /// mutation
+    async fn id(&self) -> ID {
+        ID(self.challenge.id.to_string())
+    }
+
+    /// The options to pass to `navigator.credentials.create()`, serialized as
+    /// JSON
+    async fn options(&self) -> String {
+        self.options.to_string()
+    }
+}
+
+/// The input for the `completeRegisterPasskey` mutation
+#[derive(InputObject)]
+struct CompleteRegisterPasskeyInput {
+    /// The ID of the challenge, as returned by the `startRegisterPasskey`
+    /// mutation
+    id: ID,
+
+    /// The name to give to the passkey
+    name: String,
+
+    /// The response of `navigator.credentials.create()`, serialized as JSON
+    response: String,
+}
+
+/// The status of the `completeRegisterPasskey` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum CompleteRegisterPasskeyStatus {
+    /// The passkey was added
+    Added,
+
+    /// The challenge is invalid, expired or was already used
+    InvalidChallenge,
+
+    /// The response of the authenticator could not be verified
+    InvalidResponse,
+
+    /// The name of the passkey is invalid
+    InvalidName,
+
+    /// The passkey is already registered
+    Exists,
+}
+
+/// The payload of the `completeRegisterPasskey` mutation
+#[derive(Description)]
+enum CompleteRegisterPasskeyPayload {
+    Added(mas_data_model::UserPasskey),
+    InvalidChallenge,
+    InvalidResponse,
+    InvalidName,
+    Exists,
+}
+
+#[Object(use_type_description)]
+impl CompleteRegisterPasskeyPayload {
+    /// Status of the operation
+    async fn status(&self) -> CompleteRegisterPasskeyStatus {
+        match self {
+            Self::Added(_) => CompleteRegisterPasskeyStatus::Added,
+            Self::InvalidChallenge => CompleteRegisterPasskeyStatus::InvalidChallenge,
+            Self::InvalidResponse => CompleteRegisterPasskeyStatus::InvalidResponse,
+            Self::InvalidName => CompleteRegisterPasskeyStatus::InvalidName,
+            Self::Exists => CompleteRegisterPasskeyStatus::Exists,
+        }
+    }
+
+    /// The passkey that was added
+    async fn passkey(&self) -> Option<UserPasskey> {
+        match self {
+            Self::Added(passkey) => Some(UserPasskey(passkey.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// The input for the `renamePasskey` mutation
+#[derive(InputObject)]
+struct RenamePasskeyInput {
+    /// The ID of the passkey to rename
+    user_passkey_id: ID,
+
+    /// The new name of the passkey
+    name: String,
+}
+
+/// The status of the `renamePasskey` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum RenamePasskeyStatus {
+    /// The passkey was renamed
+    Renamed,
+
+    /// The passkey was not found
+    NotFound,
+
+    /// The new name is invalid
+    InvalidName,
+}
+
+/// The payload of the `renamePasskey` mutation
+#[derive(Description)]
+enum RenamePasskeyPayload {
+    Renamed(mas_data_model::UserPasskey),
+    NotFound,
+    InvalidName,
+}
+
+#[Object(use_type_description)]
+impl RenamePasskeyPayload {
+    /// Status of the operation
+    async fn status(&self) -> RenamePasskeyStatus {
+        match self {
+            Self::Renamed(_) => RenamePasskeyStatus::Renamed,
+            Self::NotFound => RenamePasskeyStatus::NotFound,
+            Self::InvalidName => RenamePasskeyStatus::InvalidName,
+        }
+    }
+
+    /// The passkey that was renamed
+    async fn passkey(&self) -> Option<UserPasskey> {
+        match self {
+            Self::Renamed(passkey) => Some(UserPasskey(passkey.clone())),
+            Self::NotFound | Self::InvalidName => None,
+        }
+    }
+}
+
+/// The input for the `removePasskey` mutation
+#[derive(InputObject)]
+struct RemovePasskeyInput {
+    /// The ID of the passkey to remove
+    user_passkey_id: ID,
+
+    /// The user's current password. This is required if the user is not an
+    /// admin and it has a password on its account.
+    password: Option<String>,
+}
+
+/// The status of the `removePasskey` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum RemovePasskeyStatus {
+    /// The passkey was removed
+    Removed,
+
+    /// The passkey was not found
+    NotFound,
+
+    /// The password provided is incorrect
+    IncorrectPassword,
+
+    /// The user has to authenticate again first
+    ReauthenticationRequired,
+}
+
+/// The payload of the `removePasskey` mutation
+#[derive(Description)]
+enum RemovePasskeyPayload {
+    Removed(mas_data_model::UserPasskey),
+    NotFound,
+    IncorrectPassword,
+    ReauthenticationRequired,
+}
+
+#[Object(use_type_description)]
+impl RemovePasskeyPayload {
+    /// Status of the operation
+    async fn status(&self) -> RemovePasskeyStatus {
+        match self {
+            Self::Removed(_) => RemovePasskeyStatus::Removed,
+            Self::NotFound => RemovePasskeyStatus::NotFound,
+            Self::IncorrectPassword => RemovePasskeyStatus::IncorrectPassword,
+            Self::ReauthenticationRequired => RemovePasskeyStatus::ReauthenticationRequired,
+        }
+    }
+
+    /// The user to whom the passkey belonged
+    async fn user(&self, ctx: &Context<'_>) -> Result<Option<User>, async_graphql::Error> {
+        let state = ctx.state();
+
+        let user_id = match self {
+            Self::Removed(passkey) => passkey.user_id,
+            Self::NotFound | Self::IncorrectPassword | Self::ReauthenticationRequired => {
+                return Ok(None);
+            }
+        };
+
+        let mut repo = state.repository().await?;
+
+        let user = repo
+            .user()
+            .lookup(user_id)
+            .await?
+            .context("User not found")?;
+
+        Ok(Some(User(user)))
+    }
+}
+
+#[Object]
+impl UserPasskeyMutations {
+    /// Start registering a new passkey for the current user. The returned
+    /// options must be passed to the browser, and its response given back to
+    /// the `completeRegisterPasskey` mutation.
+    async fn start_register_passkey(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<StartRegisterPasskeyPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let mut rng = state.rng();
+        let clock = state.clock();
+        let requester = ctx.requester();
+
+        // Only allow calling this if the requester is a browser session
+        let Some(browser_session) = requester.browser_session() else {
+            return Err(async_graphql::Error::new("Unauthorized"));
+        };
+
+        if !state.site_config().passkeys_enabled {
+            return Err(async_graphql::Error::new(
+                "Passkeys are not allowed on this server",
+            ));
+        }
+
+        let mut repo = state.repository().await?;
+
+        let existing = repo.user_passkey().all(&browser_session.user).await?;
+
+        let challenge = repo
+            .user_passkey()
+            .add_challenge_for_session(&mut rng, &clock, browser_session)
+            .await?;
+
+        repo.save().await?;
+
+        let options = RelyingParty::new(state.url_builder()).creation_options(
+            &challenge,
+            &browser_session.user,
+            &existing,
+        );
+
+        Ok(StartRegisterPasskeyPayload { challenge, options })
+    }
+
+    /// Complete the registration of a passkey, with the response of the
+    /// browser
+    async fn complete_register_passkey(
+        &self,
+        ctx: &Context<'_>,
+        input: CompleteRegisterPasskeyInput,
+    ) -> Result<CompleteRegisterPasskeyPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let mut rng = state.rng();
+        let clock = state.clock();
+        let requester = ctx.requester();
+
+        // Only allow calling this if the requester is a browser session
+        let Some(browser_session) = requester.browser_session() else {
+            return Err(async_graphql::Error::new("Unauthorized"));
+        };
+
+        if !state.site_config().passkeys_enabled {
+            return Err(async_graphql::Error::new(
+                "Passkeys are not allowed on this server",
+            ));
+        }
+
+        let Some(name) = validate_name(&input.name) else {
+            return Ok(CompleteRegisterPasskeyPayload::InvalidName);
+        };
+
+        let Ok(challenge_id) = input.id.parse::<Ulid>() else {
+            return Ok(CompleteRegisterPasskeyPayload::InvalidChallenge);
+        };
+
+        let mut repo = state.repository().await?;
+
+        let challenge = repo.user_passkey().lookup_challenge(challenge_id).await?;
+        let Some(challenge) = challenge.filter(|challenge| {
+            challenge.user_session_id == Some(browser_session.id)
+                && is_challenge_valid(challenge, clock.now())
+        }) else {
+            return Ok(CompleteRegisterPasskeyPayload::InvalidChallenge);
+        };
+
+        // Consume the challenge right away, so that it can't be retried
+        let challenge = repo
+            .user_passkey()
+            .complete_challenge(&clock, challenge)
+            .await?;
+
+        let credential = match RelyingParty::new(state.url_builder())
+            .verify_registration(&challenge, &input.response)
+        {
+            Ok(credential) => credential,
+            Err(e) => {
+                tracing::warn!(
+                    error = &e as &dyn std::error::Error,
+                    "Failed to verify the passkey registration"
+                );
+                repo.save().await?;
+                return Ok(CompleteRegisterPasskeyPayload::InvalidResponse);
+            }
+        };
+
+        if repo
+            .user_passkey()
+            .find_by_credential_id(&credential.credential_id)
+            .await?
+            .is_some()
+        {
+            repo.save().await?;
+            return Ok(CompleteRegisterPasskeyPayload::Exists);
+        }
+
+        let passkey = repo
+            .user_passkey()
+            .add(
+                &mut rng,
+                &clock,
+                &browser_session.user,
+                credential.credential_id,
+                name,
+                credential.aaguid,
+                credential.public_key,
+                credential.sign_count,
+                credential.transports,
+            )
+            .await?;
+
+        repo.save().await?;
+
+        Ok(CompleteRegisterPasskeyPayload::Added(passkey))
+    }
+
+    /// Rename a passkey
+    async fn rename_passkey(
+        &self,
+        ctx: &Context<'_>,
+        input: RenamePasskeyInput,
+    ) -> Result<RenamePasskeyPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let user_passkey_id = NodeType::UserPasskey.extract_ulid(&input.user_passkey_id)?;
+        let requester = ctx.requester();
+
+        let mut repo = state.repository().await?;
+
+        let passkey = repo.user_passkey().lookup(user_passkey_id).await?;
+        let Some(passkey) = passkey else {
+            return Ok(RenamePasskeyPayload::NotFound);
+        };
+
+        if !requester.is_owner_or_admin(&passkey) {
+            return Ok(RenamePasskeyPayload::NotFound);
+        }
+
+        let Some(name) = validate_name(&input.name) else {
+            return Ok(RenamePasskeyPayload::InvalidName);
+        };
+
+        let passkey = repo.user_passkey().rename(passkey, name).await?;
+
+        repo.save().await?;
+
+        Ok(RenamePasskeyPayload::Renamed(passkey))
+    }
+
+    /// Remove a passkey
+    async fn remove_passkey(
+        &self,
+        ctx: &Context<'_>,
+        input: RemovePasskeyInput,
+    ) -> Result<RemovePasskeyPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let user_passkey_id = NodeType::UserPasskey.extract_ulid(&input.user_passkey_id)?;
+        let requester = ctx.requester();
+
+        let mut repo = state.repository().await?;
+
+        let passkey = repo.user_passkey().lookup(user_passkey_id).await?;
+        let Some(passkey) = passkey else {
+            return Ok(RemovePasskeyPayload::NotFound);
+        };
+
+        if !requester.is_owner_or_admin(&passkey) {
+            return Ok(RemovePasskeyPayload::NotFound);
+        }
+
+        let user = repo
+            .user()
+            .lookup(passkey.user_id)
+            .await?
+            .context("Failed to load user")?;
+
+        // Validate the password input if needed
+        match confirm_identity(
+            requester,
+            state.site_config(),
+            &state.clock(),
+            &state.password_manager(),
+            input.password,
+            &user,
+            &mut repo,
+        )
+        .await?
+        {
+            IdentityConfirmation::Confirmed => {}
+            IdentityConfirmation::IncorrectPassword => {
+                return Ok(RemovePasskeyPayload::IncorrectPassword);
+            }
+            IdentityConfirmation::ReauthenticationRequired => {
+                return Ok(RemovePasskeyPayload::ReauthenticationRequired);
+            }
+        }
+
+        repo.user_passkey().remove(passkey.clone()).await?;
+
+        repo.save().await?;
+
+        Ok(RemovePasskeyPayload::Removed(passkey))
+    }
+}
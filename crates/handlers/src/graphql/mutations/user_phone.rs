@@ -0,0 +1,475 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_graphql::{Context, Description, Enum, InputObject, Object};
+use mas_i18n::DataLocale;
+use mas_storage::{
+    RepositoryAccess,
+    queue::{ProvisionUserJob, QueueJobRepositoryExt as _},
+    user::UserPhoneRepository,
+};
+
+use super::{IdentityConfirmation, confirm_identity};
+use crate::{
+    graphql::{model::UserPhone, state::ContextExt},
+    sms::{self, normalize_phone_number},
+};
+
+#[derive(Default)]
+pub struct UserPhoneMutations {
+    _private: (),
+}
+
+/// The input for the `setPhoneNumber` mutation
+#[derive(InputObject)]
+struct SetPhoneNumberInput {
+    /// The phone number to set, in the international format
+    phone_number: String,
+
+    /// The language to use for the text message
+    #[graphql(default = "en")]
+    language: String,
+
+    /// The user's current password. This is required if the user is not an
+    /// admin and it has a password on its account.
+    password: Option<String>,
+}
+
+/// The status of the `setPhoneNumber` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum SetPhoneNumberStatus {
+    /// The phone number was set, and a code was sent to confirm it
+    Started,
+
+    /// The phone number is invalid
+    InvalidPhoneNumber,
+
+    /// A code was sent too recently, try again later
+    RateLimited,
+
+    /// The password provided is incorrect
+    IncorrectPassword,
+
+    /// The user has to authenticate again first
+    ReauthenticationRequired,
+}
+
+/// The payload of the `setPhoneNumber` mutation
+#[derive(Description)]
+enum SetPhoneNumberPayload {
+    Started(mas_data_model::UserPhone),
+    InvalidPhoneNumber,
+    RateLimited,
+    IncorrectPassword,
+    ReauthenticationRequired,
+}
+
+#[Object(use_type_description)]
+impl SetPhoneNumberPayload {
+    /// Status of the operation
+    async fn status(&self) -> SetPhoneNumberStatus {
+        match self {
+            Self::Started(_) => SetPhoneNumberStatus::Started,
+            Self::InvalidPhoneNumber => SetPhoneNumberStatus::InvalidPhoneNumber,
+            Self::RateLimited => SetPhoneNumberStatus::RateLimited,
+            Self::IncorrectPassword => SetPhoneNumberStatus::IncorrectPassword,
+            Self::ReauthenticationRequired => SetPhoneNumberStatus::ReauthenticationRequired,
+        }
+    }
+
+    /// The phone number that was set, pending confirmation
+    async fn phone(&self) -> Option<UserPhone> {
+        match self {
+            Self::Started(phone) => Some(UserPhone(phone.clone())),
+            Self::InvalidPhoneNumber
+            | Self::RateLimited
+            | Self::IncorrectPassword
+            | Self::ReauthenticationRequired => None,
+        }
+    }
+}
+
+/// The input for the `resendPhoneNumberCode` mutation
+#[derive(InputObject)]
+struct ResendPhoneNumberCodeInput {
+    /// The language to use for the text message
+    #[graphql(default = "en")]
+    language: String,
+}
+
+/// The status of the `resendPhoneNumberCode` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum ResendPhoneNumberCodeStatus {
+    /// A new code was sent
+    Resent,
+
+    /// There is no phone number pending confirmation
+    NotFound,
+
+    /// A code was sent too recently, try again later
+    RateLimited,
+}
+
+/// The payload of the `resendPhoneNumberCode` mutation
+#[derive(Description)]
+enum ResendPhoneNumberCodePayload {
+    Resent,
+    NotFound,
+    RateLimited,
+}
+
+#[Object(use_type_description)]
+impl ResendPhoneNumberCodePayload {
+    /// Status of the operation
+    async fn status(&self) -> ResendPhoneNumberCodeStatus {
+        match self {
+            Self::Resent => ResendPhoneNumberCodeStatus::Resent,
+            Self::NotFound => ResendPhoneNumberCodeStatus::NotFound,
+            Self::RateLimited => ResendPhoneNumberCodeStatus::RateLimited,
+        }
+    }
+}
+
+/// The input for the `confirmPhoneNumber` mutation
+#[derive(InputObject)]
+struct ConfirmPhoneNumberInput {
+    /// The code received by SMS
+    code: String,
+}
+
+/// The status of the `confirmPhoneNumber` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum ConfirmPhoneNumberStatus {
+    /// The phone number was confirmed
+    Confirmed,
+
+    /// The code is invalid or expired
+    InvalidCode,
+
+    /// There is no phone number pending confirmation
+    NotFound,
+}
+
+/// The payload of the `confirmPhoneNumber` mutation
+#[derive(Description)]
+enum ConfirmPhoneNumberPayload {
+    Confirmed(mas_data_model::UserPhone),
+    InvalidCode,
+    NotFound,
+}
+
+#[Object(use_type_description)]
+impl ConfirmPhoneNumberPayload {
+    /// Status of the operation
+    async fn status(&self) -> ConfirmPhoneNumberStatus {
+        match self {
+            Self::Confirmed(_) => ConfirmPhoneNumberStatus::Confirmed,
+            Self::InvalidCode => ConfirmPhoneNumberStatus::InvalidCode,
+            Self::NotFound => ConfirmPhoneNumberStatus::NotFound,
+        }
+    }
+
+    /// The phone number that was confirmed
+    async fn phone(&self) -> Option<UserPhone> {
+        match self {
+            Self::Confirmed(phone) => Some(UserPhone(phone.clone())),
+            Self::InvalidCode | Self::NotFound => None,
+        }
+    }
+}
+
+/// The input for the `removePhoneNumber` mutation
+#[derive(InputObject)]
+struct RemovePhoneNumberInput {
+    /// The user's current password. This is required if the user is not an
+    /// admin and it has a password on its account.
+    password: Option<String>,
+}
+
+/// The status of the `removePhoneNumber` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum RemovePhoneNumberStatus {
+    /// The phone number was removed
+    Removed,
+
+    /// The user has no phone number
+    NotFound,
+
+    /// The password provided is incorrect
+    IncorrectPassword,
+
+    /// The user has to authenticate again first
+    ReauthenticationRequired,
+}
+
+/// The payload of the `removePhoneNumber` mutation
+#[derive(Description)]
+enum RemovePhoneNumberPayload {
+    Removed,
+    NotFound,
+    IncorrectPassword,
+    ReauthenticationRequired,
+}
+
+#[Object(use_type_description)]
+impl RemovePhoneNumberPayload {
+    /// Status of the operation
+    async fn status(&self) -> RemovePhoneNumberStatus {
+        match self {
+            Self::Removed => RemovePhoneNumberStatus::Removed,
+            Self::NotFound => RemovePhoneNumberStatus::NotFound,
+            Self::IncorrectPassword => RemovePhoneNumberStatus::IncorrectPassword,
+            Self::ReauthenticationRequired => RemovePhoneNumberStatus::ReauthenticationRequired,
+        }
+    }
+}
+
+#[Object]
+impl UserPhoneMutations {
+    /// Set the phone number of the current user, replacing the previous one.
+    /// A code is sent by SMS, which has to be entered with the
+    /// `confirmPhoneNumber` mutation.
+    async fn set_phone_number(
+        &self,
+        ctx: &Context<'_>,
+        input: SetPhoneNumberInput,
+    ) -> Result<SetPhoneNumberPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let mut rng = state.rng();
+        let clock = state.clock();
+        let requester = ctx.requester();
+
+        // Only allow calling this if the requester is a browser session
+        let Some(browser_session) = requester.browser_session() else {
+            return Err(async_graphql::Error::new("Unauthorized"));
+        };
+        let user = &browser_session.user;
+
+        if !state.site_config().phone_number_change_allowed {
+            return Err(async_graphql::Error::new(
+                "Phone number changes are not allowed on this server",
+            ));
+        }
+
+        // Check if the locale is valid
+        let _: DataLocale = input.language.parse()?;
+
+        let Some(phone_number) = normalize_phone_number(&input.phone_number) else {
+            return Ok(SetPhoneNumberPayload::InvalidPhoneNumber);
+        };
+
+        let mut repo = state.repository().await?;
+
+        // Validate the password input if needed
+        match confirm_identity(
+            requester,
+            state.site_config(),
+            &clock,
+            &state.password_manager(),
+            input.password,
+            user,
+            &mut repo,
+        )
+        .await?
+        {
+            IdentityConfirmation::Confirmed => {}
+            IdentityConfirmation::IncorrectPassword => {
+                return Ok(SetPhoneNumberPayload::IncorrectPassword);
+            }
+            IdentityConfirmation::ReauthenticationRequired => {
+                return Ok(SetPhoneNumberPayload::ReauthenticationRequired);
+            }
+        }
+
+        // Replacing the phone number would otherwise be a way around the rate
+        // limit of codes
+        if let Some(existing) = repo.user_phone().find_for_user(user).await? {
+            if !sms::can_send_code(&mut repo, &clock, &existing).await? {
+                return Ok(SetPhoneNumberPayload::RateLimited);
+            }
+
+            let was_confirmed = existing.is_confirmed();
+            repo.user_phone().remove(existing).await?;
+
+            // The previous phone number was shared with the homeserver
+            if was_confirmed {
+                repo.queue_job()
+                    .schedule_job(&mut rng, &clock, ProvisionUserJob::new(user))
+                    .await?;
+            }
+        }
+
+        let phone = repo
+            .user_phone()
+            .add(&mut rng, &clock, user, phone_number)
+            .await?;
+
+        sms::send_code(&mut repo, &mut rng, &clock, &phone, input.language, false).await?;
+
+        repo.save().await?;
+
+        Ok(SetPhoneNumberPayload::Started(phone))
+    }
+
+    /// Send a new code to the phone number of the current user, if it is
+    /// still pending confirmation
+    async fn resend_phone_number_code(
+        &self,
+        ctx: &Context<'_>,
+        input: ResendPhoneNumberCodeInput,
+    ) -> Result<ResendPhoneNumberCodePayload, async_graphql::Error> {
+        let state = ctx.state();
+        let mut rng = state.rng();
+        let clock = state.clock();
+        let requester = ctx.requester();
+
+        let Some(browser_session) = requester.browser_session() else {
+            return Err(async_graphql::Error::new("Unauthorized"));
+        };
+
+        // Check if the locale is valid
+        let _: DataLocale = input.language.parse()?;
+
+        let mut repo = state.repository().await?;
+
+        let phone = repo
+            .user_phone()
+            .find_for_user(&browser_session.user)
+            .await?;
+        let Some(phone) = phone.filter(|phone| !phone.is_confirmed()) else {
+            return Ok(ResendPhoneNumberCodePayload::NotFound);
+        };
+
+        if !sms::send_code(&mut repo, &mut rng, &clock, &phone, input.language, false).await? {
+            return Ok(ResendPhoneNumberCodePayload::RateLimited);
+        }
+
+        repo.save().await?;
+
+        Ok(ResendPhoneNumberCodePayload::Resent)
+    }
+
+    /// Confirm the phone number of the current user, with the code it received
+    /// by SMS
+    async fn confirm_phone_number(
+        &self,
+        ctx: &Context<'_>,
+        input: ConfirmPhoneNumberInput,
+    ) -> Result<ConfirmPhoneNumberPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let mut rng = state.rng();
+        let clock = state.clock();
+        let requester = ctx.requester();
+
+        let Some(browser_session) = requester.browser_session() else {
+            return Err(async_graphql::Error::new("Unauthorized"));
+        };
+
+        let mut repo = state.repository().await?;
+
+        let phone = repo
+            .user_phone()
+            .find_for_user(&browser_session.user)
+            .await?;
+        let Some(phone) = phone.filter(|phone| !phone.is_confirmed()) else {
+            return Ok(ConfirmPhoneNumberPayload::NotFound);
+        };
+
+        // Codes are short, so they share the rate limit of passwords
+        if let Err(e) = state
+            .limiter()
+            .check_password(requester.fingerprint(), &browser_session.user)
+        {
+            tracing::warn!(error = &e as &dyn std::error::Error);
+            return Ok(ConfirmPhoneNumberPayload::InvalidCode);
+        }
+
+        if sms::check_code(&mut repo, &clock, &phone, &input.code)
+            .await?
+            .is_none()
+        {
+            return Ok(ConfirmPhoneNumberPayload::InvalidCode);
+        }
+
+        let phone = repo.user_phone().confirm(&clock, phone).await?;
+
+        // Schedule a job to share the phone number with the homeserver
+        repo.queue_job()
+            .schedule_job(
+                &mut rng,
+                &clock,
+                ProvisionUserJob::new(&browser_session.user),
+            )
+            .await?;
+
+        repo.save().await?;
+
+        Ok(ConfirmPhoneNumberPayload::Confirmed(phone))
+    }
+
+    /// Remove the phone number of the current user
+    async fn remove_phone_number(
+        &self,
+        ctx: &Context<'_>,
+        input: RemovePhoneNumberInput,
+    ) -> Result<RemovePhoneNumberPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let mut rng = state.rng();
+        let clock = state.clock();
+        let requester = ctx.requester();
+
+        let Some(browser_session) = requester.browser_session() else {
+            return Err(async_graphql::Error::new("Unauthorized"));
+        };
+        let user = &browser_session.user;
+
+        if !state.site_config().phone_number_change_allowed {
+            return Err(async_graphql::Error::new(
+                "Phone number changes are not allowed on this server",
+            ));
+        }
+
+        let mut repo = state.repository().await?;
+
+        let Some(phone) = repo.user_phone().find_for_user(user).await? else {
+            return Ok(RemovePhoneNumberPayload::NotFound);
+        };
+
+        // Validate the password input if needed
+        match confirm_identity(
+            requester,
+            state.site_config(),
+            &clock,
+            &state.password_manager(),
+            input.password,
+            user,
+            &mut repo,
+        )
+        .await?
+        {
+            IdentityConfirmation::Confirmed => {}
+            IdentityConfirmation::IncorrectPassword => {
+                return Ok(RemovePhoneNumberPayload::IncorrectPassword);
+            }
+            IdentityConfirmation::ReauthenticationRequired => {
+                return Ok(RemovePhoneNumberPayload::ReauthenticationRequired);
+            }
+        }
+
+        let was_confirmed = phone.is_confirmed();
+        repo.user_phone().remove(phone).await?;
+
+        // Schedule a job to remove the phone number from the homeserver
+        if was_confirmed {
+            repo.queue_job()
+                .schedule_job(&mut rng, &clock, ProvisionUserJob::new(user))
+                .await?;
+        }
+
+        repo.save().await?;
+
+        Ok(RemovePhoneNumberPayload::Removed)
+    }
+}
@@ -0,0 +1,500 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use anyhow::Context as _;
+use async_graphql::{Context, Description, Enum, InputObject, Object};
+use mas_storage::{
+    RepositoryAccess,
+    user::{UserRecoveryCodeRepository, UserTotpRepository},
+};
+use zeroize::Zeroizing;
+
+use super::{IdentityConfirmation, confirm_identity, in_sudo_mode};
+use crate::{
+    graphql::{model::UserTotp, state::ContextExt},
+    totp::{self, encode_secret, generate_secret, provisioning_uri, qr_code_data_uri},
+};
+
+#[derive(Default)]
+pub struct UserTotpMutations {
+    _private: (),
+}
+
+/// The status of the `startTotpEnrollment` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum StartTotpEnrollmentStatus {
+    /// A new secret was generated, and must be confirmed with a code
+    Started,
+
+    /// The user already has a confirmed second factor
+    AlreadyEnrolled,
+
+    /// The user has to authenticate again first
+    ReauthenticationRequired,
+}
+
+/// The details the user needs to add a second factor to their authenticator
+/// app
+struct TotpEnrollment {
+    secret: String,
+    provisioning_uri: String,
+    qr_code: String,
+}
+
+/// The payload of the `startTotpEnrollment` mutation
+#[derive(Description)]
+enum StartTotpEnrollmentPayload {
+    Started(TotpEnrollment),
+    AlreadyEnrolled,
+    ReauthenticationRequired,
+}
+
+#[Object(use_type_description)]
+impl StartTotpEnrollmentPayload {
+    /// Status of the operation
+    async fn status(&self) -> StartTotpEnrollmentStatus {
+        match self {
+            Self::Started(_) => StartTotpEnrollmentStatus::Started,
+            Self::AlreadyEnrolled => StartTotpEnrollmentStatus::AlreadyEnrolled,
+            Self::ReauthenticationRequired => StartTotpEnrollmentStatus::ReauthenticationRequired,
+        }
+    }
+
+    /// The shared secret, encoded in base32, for users who can't scan the QR
+    /// code
+    async fn secret(&self) -> Option<&str> {
+        match self {
+            Self::Started(enrollment) => Some(&enrollment.secret),
+            Self::AlreadyEnrolled | Self::ReauthenticationRequired => None,
+        }
+    }
+
+    /// The `otpauth://` URI to add the second factor to an authenticator app
+    async fn provisioning_uri(&self) -> Option<&str> {
+        match self {
+            Self::Started(enrollment) => Some(&enrollment.provisioning_uri),
+            Self::AlreadyEnrolled | Self::ReauthenticationRequired => None,
+        }
+    }
+
+    /// The provisioning URI as a QR code, in a `data:` URI of an SVG image
+    async fn qr_code(&self) -> Option<&str> {
+        match self {
+            Self::Started(enrollment) => Some(&enrollment.qr_code),
+            Self::AlreadyEnrolled | Self::ReauthenticationRequired => None,
+        }
+    }
+}
+
+/// The input for the `confirmTotpEnrollment` mutation
+#[derive(InputObject)]
+struct ConfirmTotpEnrollmentInput {
+    /// The code shown by the authenticator app
+    code: String,
+}
+
+/// The status of the `confirmTotpEnrollment` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum ConfirmTotpEnrollmentStatus {
+    /// The second factor was confirmed
+    Confirmed,
+
+    /// The code is invalid
+    InvalidCode,
+
+    /// There is no pending second factor to confirm
+    NotFound,
+}
+
+/// The payload of the `confirmTotpEnrollment` mutation
+#[derive(Description)]
+enum ConfirmTotpEnrollmentPayload {
+    Confirmed {
+        totp: mas_data_model::UserTotp,
+        recovery_codes: Vec<String>,
+    },
+    InvalidCode,
+    NotFound,
+}
+
+#[Object(use_type_description)]
+impl ConfirmTotpEnrollmentPayload {
+    /// Status of the operation
+    async fn status(&self) -> ConfirmTotpEnrollmentStatus {
+        match self {
+            Self::Confirmed { .. } => ConfirmTotpEnrollmentStatus::Confirmed,
+            Self::InvalidCode => ConfirmTotpEnrollmentStatus::InvalidCode,
+            Self::NotFound => ConfirmTotpEnrollmentStatus::NotFound,
+        }
+    }
+
+    /// The second factor that was confirmed
+    async fn totp(&self) -> Option<UserTotp> {
+        match self {
+            Self::Confirmed { totp, .. } => Some(UserTotp(totp.clone())),
+            Self::InvalidCode | Self::NotFound => None,
+        }
+    }
+
+    /// The recovery codes generated for the user. They are only shown once,
+    /// and let the user log in if their second factor is unavailable.
+    async fn recovery_codes(&self) -> Option<&[String]> {
+        match self {
+            Self::Confirmed { recovery_codes, .. } => Some(recovery_codes),
+            Self::InvalidCode | Self::NotFound => None,
+        }
+    }
+}
+
+/// The input for the `removeTotp` mutation
+#[derive(InputObject)]
+struct RemoveTotpInput {
+    /// The user's current password. This is required if the user is not an
+    /// admin and it has a password on its account.
+    password: Option<String>,
+}
+
+/// The status of the `removeTotp` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum RemoveTotpStatus {
+    /// The second factor was removed
+    Removed,
+
+    /// The user has no second factor
+    NotFound,
+
+    /// The password provided is incorrect
+    IncorrectPassword,
+
+    /// The user has to authenticate again first
+    ReauthenticationRequired,
+
+    /// The server requires the user to have a second factor
+    Required,
+}
+
+/// The payload of the `removeTotp` mutation
+#[derive(Description)]
+enum RemoveTotpPayload {
+    Removed,
+    NotFound,
+    IncorrectPassword,
+    ReauthenticationRequired,
+    Required,
+}
+
+#[Object(use_type_description)]
+impl RemoveTotpPayload {
+    /// Status of the operation
+    async fn status(&self) -> RemoveTotpStatus {
+        match self {
+            Self::Removed => RemoveTotpStatus::Removed,
+            Self::NotFound => RemoveTotpStatus::NotFound,
+            Self::IncorrectPassword => RemoveTotpStatus::IncorrectPassword,
+            Self::ReauthenticationRequired => RemoveTotpStatus::ReauthenticationRequired,
+            Self::Required => RemoveTotpStatus::Required,
+        }
+    }
+}
+
+/// The input for the `regenerateRecoveryCodes` mutation
+#[derive(InputObject)]
+struct RegenerateRecoveryCodesInput {
+    /// The user's current password. This is required if the user is not an
+    /// admin and it has a password on its account.
+    password: Option<String>,
+}
+
+/// The status of the `regenerateRecoveryCodes` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum RegenerateRecoveryCodesStatus {
+    /// A new set of recovery codes was generated
+    Regenerated,
+
+    /// The user has no second factor
+    NotEnrolled,
+
+    /// The password provided is incorrect
+    IncorrectPassword,
+
+    /// The user has to authenticate again first
+    ReauthenticationRequired,
+}
+
+/// The payload of the `regenerateRecoveryCodes` mutation
+#[derive(Description)]
+enum RegenerateRecoveryCodesPayload {
+    Regenerated(Vec<String>),
+    NotEnrolled,
+    IncorrectPassword,
+    ReauthenticationRequired,
+}
+
+#[Object(use_type_description)]
+impl RegenerateRecoveryCodesPayload {
+    /// Status of the operation
+    async fn status(&self) -> RegenerateRecoveryCodesStatus {
+        match self {
+            Self::Regenerated(_) => RegenerateRecoveryCodesStatus::Regenerated,
+            Self::NotEnrolled => RegenerateRecoveryCodesStatus::NotEnrolled,
+            Self::IncorrectPassword => RegenerateRecoveryCodesStatus::IncorrectPassword,
+            Self::ReauthenticationRequired => {
+                RegenerateRecoveryCodesStatus::ReauthenticationRequired
+            }
+        }
+    }
+
+    /// The new recovery codes, which replace the previous ones. They are only
+    /// shown once.
+    async fn recovery_codes(&self) -> Option<&[String]> {
+        match self {
+            Self::Regenerated(recovery_codes) => Some(recovery_codes),
+            Self::NotEnrolled | Self::IncorrectPassword | Self::ReauthenticationRequired => None,
+        }
+    }
+}
+
+#[Object]
+impl UserTotpMutations {
+    /// Start adding a TOTP second factor for the current user. It has to be
+    /// confirmed with a code from the authenticator app, with the
+    /// `confirmTotpEnrollment` mutation.
+    async fn start_totp_enrollment(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<StartTotpEnrollmentPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let mut rng = state.rng();
+        let clock = state.clock();
+        let requester = ctx.requester();
+
+        // Only allow calling this if the requester is a browser session
+        let Some(browser_session) = requester.browser_session() else {
+            return Err(async_graphql::Error::new("Unauthorized"));
+        };
+
+        let site_config = state.site_config();
+        if !site_config.totp_policy.is_enabled() {
+            return Err(async_graphql::Error::new(
+                "Second factors are not allowed on this server",
+            ));
+        }
+
+        let mut repo = state.repository().await?;
+
+        // Adding a second factor changes how the user logs in, so it needs a
+        // recent authentication if sudo mode is enabled
+        if site_config.sudo_mode_ttl.is_some()
+            && !in_sudo_mode(requester, site_config, &clock, &mut repo).await?
+        {
+            return Ok(StartTotpEnrollmentPayload::ReauthenticationRequired);
+        }
+
+        // Replace any pending second factor, so that a new secret is shown every
+        // time, but never replace a confirmed one
+        if let Some(existing) = repo
+            .user_totp()
+            .find_for_user(&browser_session.user)
+            .await?
+        {
+            if existing.is_confirmed() {
+                return Ok(StartTotpEnrollmentPayload::AlreadyEnrolled);
+            }
+
+            repo.user_totp().remove(existing).await?;
+        }
+
+        let secret = generate_secret(&mut rng);
+        let encrypted_secret = state.encrypter().encrypt_to_string(&secret)?;
+        repo.user_totp()
+            .add(&mut rng, &clock, &browser_session.user, encrypted_secret)
+            .await?;
+
+        repo.save().await?;
+
+        let uri = provisioning_uri(
+            &site_config.server_name,
+            &browser_session.user.username,
+            &secret,
+        );
+        let qr_code = qr_code_data_uri(&uri).context("Failed to render the QR code")?;
+
+        Ok(StartTotpEnrollmentPayload::Started(TotpEnrollment {
+            secret: encode_secret(&secret),
+            provisioning_uri: uri.to_string(),
+            qr_code,
+        }))
+    }
+
+    /// Confirm the pending TOTP second factor of the current user, with a code
+    /// from their authenticator app
+    async fn confirm_totp_enrollment(
+        &self,
+        ctx: &Context<'_>,
+        input: ConfirmTotpEnrollmentInput,
+    ) -> Result<ConfirmTotpEnrollmentPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let mut rng = state.rng();
+        let clock = state.clock();
+        let requester = ctx.requester();
+
+        // Only allow calling this if the requester is a browser session
+        let Some(browser_session) = requester.browser_session() else {
+            return Err(async_graphql::Error::new("Unauthorized"));
+        };
+
+        let mut repo = state.repository().await?;
+
+        let totp = repo
+            .user_totp()
+            .find_for_user(&browser_session.user)
+            .await?;
+        let Some(totp) = totp.filter(|totp| !totp.is_confirmed()) else {
+            return Ok(ConfirmTotpEnrollmentPayload::NotFound);
+        };
+
+        // Codes are short, so they share the rate limit of passwords
+        if let Err(e) = state
+            .limiter()
+            .check_password(requester.fingerprint(), &browser_session.user)
+        {
+            tracing::warn!(error = &e as &dyn std::error::Error);
+            return Ok(ConfirmTotpEnrollmentPayload::InvalidCode);
+        }
+
+        let code = Zeroizing::new(input.code);
+        let Some(totp) =
+            totp::check_code(&mut repo, &clock, state.encrypter(), totp, &code).await?
+        else {
+            return Ok(ConfirmTotpEnrollmentPayload::InvalidCode);
+        };
+
+        let recovery_codes =
+            totp::replace_recovery_codes(&mut repo, &mut rng, &clock, &browser_session.user)
+                .await?;
+
+        repo.save().await?;
+
+        Ok(ConfirmTotpEnrollmentPayload::Confirmed {
+            totp,
+            recovery_codes,
+        })
+    }
+
+    /// Remove the TOTP second factor of the current user
+    async fn remove_totp(
+        &self,
+        ctx: &Context<'_>,
+        input: RemoveTotpInput,
+    ) -> Result<RemoveTotpPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let requester = ctx.requester();
+
+        let Some(browser_session) = requester.browser_session() else {
+            return Err(async_graphql::Error::new("Unauthorized"));
+        };
+        let user = &browser_session.user;
+
+        let mut repo = state.repository().await?;
+
+        let Some(totp) = repo.user_totp().find_for_user(user).await? else {
+            return Ok(RemoveTotpPayload::NotFound);
+        };
+
+        // Pending second factors can always be removed, but confirmed ones only
+        // if the server doesn't require them
+        if totp.is_confirmed()
+            && !requester.is_admin()
+            && state.site_config().totp_policy.is_required_for(user)
+        {
+            return Ok(RemoveTotpPayload::Required);
+        }
+
+        // Validate the password input if needed
+        match confirm_identity(
+            requester,
+            state.site_config(),
+            &state.clock(),
+            &state.password_manager(),
+            input.password,
+            user,
+            &mut repo,
+        )
+        .await?
+        {
+            IdentityConfirmation::Confirmed => {}
+            IdentityConfirmation::IncorrectPassword => {
+                return Ok(RemoveTotpPayload::IncorrectPassword);
+            }
+            IdentityConfirmation::ReauthenticationRequired => {
+                return Ok(RemoveTotpPayload::ReauthenticationRequired);
+            }
+        }
+
+        repo.user_totp().remove(totp).await?;
+        repo.user_recovery_code().remove_all(user).await?;
+
+        repo.save().await?;
+
+        Ok(RemoveTotpPayload::Removed)
+    }
+
+    /// Generate a new set of recovery codes for the current user, replacing
+    /// the previous ones
+    async fn regenerate_recovery_codes(
+        &self,
+        ctx: &Context<'_>,
+        input: RegenerateRecoveryCodesInput,
+    ) -> Result<RegenerateRecoveryCodesPayload, async_graphql::Error> {
+        let state = ctx.state();
+        let mut rng = state.rng();
+        let clock = state.clock();
+        let requester = ctx.requester();
+
+        let Some(browser_session) = requester.browser_session() else {
+            return Err(async_graphql::Error::new("Unauthorized"));
+        };
+        let user = &browser_session.user;
+
+        let mut repo = state.repository().await?;
+
+        // Recovery codes are only useful once the second factor is confirmed
+        let totp = repo.user_totp().find_for_user(user).await?;
+        let enrolled = totp
+            .as_ref()
+            .is_some_and(mas_data_model::UserTotp::is_confirmed);
+        if !enrolled {
+            return Ok(RegenerateRecoveryCodesPayload::NotEnrolled);
+        }
+
+        // Validate the password input if needed
+        match confirm_identity(
+            requester,
+            state.site_config(),
+            &clock,
+            &state.password_manager(),
+            input.password,
+            user,
+            &mut repo,
+        )
+        .await?
+        {
+            IdentityConfirmation::Confirmed => {}
+            IdentityConfirmation::IncorrectPassword => {
+                return Ok(RegenerateRecoveryCodesPayload::IncorrectPassword);
+            }
+            IdentityConfirmation::ReauthenticationRequired => {
+                return Ok(RegenerateRecoveryCodesPayload::ReauthenticationRequired);
+            }
+        }
+
+        let recovery_codes =
+            totp::replace_recovery_codes(&mut repo, &mut rng, &clock, user).await?;
+
+        repo.save().await?;
+
+        Ok(RegenerateRecoveryCodesPayload::Regenerated(recovery_codes))
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use anyhow::Context as _;
+use async_graphql::{Context, Description, Enum, ID, InputObject, Object};
+use mas_storage::{
+    RepositoryAccess,
+    user::{UserRepository, UserTrustedDeviceRepository},
+};
+
+use crate::graphql::{
+    model::{NodeType, User},
+    state::ContextExt,
+};
+
+#[derive(Default)]
+pub struct UserTrustedDeviceMutations {
+    _private: (),
+}
+
+/// The input for the `revokeTrustedDevice` mutation
+#[derive(InputObject)]
+struct RevokeTrustedDeviceInput {
+    /// The ID of the trusted device to revoke
+    user_trusted_device_id: ID,
+}
+
+/// The status of the `revokeTrustedDevice` mutation
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+enum RevokeTrustedDeviceStatus {
+    /// The device is no longer trusted
+    Revoked,
+
+    /// The trusted device was not found
+    NotFound,
+}
+
+/// The payload of the `revokeTrustedDevice` mutation
+#[derive(Description)]
+enum RevokeTrustedDevicePayload {
+    Revoked(mas_data_model::UserTrustedDevice),
+    NotFound,
+}
+
+#[Object(use_type_description)]
+impl RevokeTrustedDevicePayload {
+    /// Status of the operation
+    async fn status(&self) -> RevokeTrustedDeviceStatus {
+        match self {
+            Self::Revoked(_) => RevokeTrustedDeviceStatus::Revoked,
+            Self::NotFound => RevokeTrustedDeviceStatus::NotFound,
+        }
+    }
+
+    /// The user who trusted the device
+    async fn user(&self, ctx: &Context<'_>) -> Result<Option<User>, async_graphql::Error> {
+        let state = ctx.state();
+
+        let user_id = match self {
+            Self::Revoked(device) => device.user_id,
+            Self::NotFound => return Ok(None),
+        };
+
+        let mut repo = state.repository().await?;
+
+        let user = repo
+            .user()
+            .lookup(user_id)
+            .await?
+            .context("User not found")?;
+
+        Ok(Some(User(user)))
+    }
+}
+
+#[Object]
+impl UserTrustedDeviceMutations {
+    /// Stop trusting a browser, so that logging in from it asks for the second
+    /// factor again. Unlike adding a second factor, this doesn't need the
+    /// password, as it can only make the account safer.
+    async fn revoke_trusted_device(
+        &self,
+        ctx: &Context<'_>,
+        input: RevokeTrustedDeviceInput,
+    ) -> Result<RevokeTrustedDevicePayload, async_graphql::Error> {
+        let state = ctx.state();
+        let user_trusted_device_id =
+            NodeType::UserTrustedDevice.extract_ulid(&input.user_trusted_device_id)?;
+        let requester = ctx.requester();
+        let clock = state.clock();
+
+        let mut repo = state.repository().await?;
+
+        let device = repo
+            .user_trusted_device()
+            .lookup(user_trusted_device_id)
+            .await?;
+        let Some(device) = device.filter(|d| d.revoked_at.is_none()) else {
+            return Ok(RevokeTrustedDevicePayload::NotFound);
+        };
+
+        if !requester.is_owner_or_admin(&device) {
+            return Ok(RevokeTrustedDevicePayload::NotFound);
+        }
+
+        let device = repo.user_trusted_device().revoke(&clock, device).await?;
+
+        repo.save().await?;
+
+        Ok(RevokeTrustedDevicePayload::Revoked(device))
+    }
+}
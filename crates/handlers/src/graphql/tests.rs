@@ -5,13 +5,24 @@
 // Please see LICENSE files in the repository root for full details.
 
 use axum::http::Request;
+use chrono::Duration;
 use hyper::StatusCode;
-use mas_data_model::{AccessToken, Client, TokenType, User};
+use mas_data_model::{
+    AccessToken, Client, TokenType, UpstreamOAuthProviderClaimsImports,
+    UpstreamOAuthProviderDiscoveryMode, UpstreamOAuthProviderPkceMode,
+    UpstreamOAuthProviderTokenAuthMethod, User,
+};
+use mas_iana::jose::JsonWebSignatureAlg;
 use mas_matrix::{HomeserverConnection, ProvisionRequest};
 use mas_router::SimpleRoute;
 use mas_storage::{
     RepositoryAccess,
-    oauth2::{OAuth2AccessTokenRepository, OAuth2ClientRepository},
+    oauth2::{
+        OAuth2AccessTokenRepository, OAuth2ClientRepository, OAuth2DeviceCodeGrantParams,
+        OAuth2DeviceCodeGrantRepository,
+    },
+    upstream_oauth2::{UpstreamOAuthLinkRepository, UpstreamOAuthProviderParams},
+    user::UserEmailRepository,
 };
 use oauth2_types::{
     registration::ClientRegistrationResponse,
@@ -51,6 +62,19 @@ async fn create_test_client(state: &TestState) -> Client {
             None,
             None,
             None,
+            None,
+            None,
+            false,
+            Vec::new(),
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            Vec::new(),
+            None,
         )
         .await
         .unwrap();
@@ -782,3 +806,507 @@ async fn test_add_user(pool: PgPool) {
         })
     );
 }
+
+/// Test that a session signed in on one device can approve a sign in started
+/// on another one with the code it shows
+#[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+async fn test_authorize_device_code(pool: PgPool) {
+    setup();
+    let state = TestState::from_pool(pool).await.unwrap();
+
+    let client = create_test_client(&state).await;
+    let user = create_test_user(&state, "alice").await;
+    let access_token =
+        start_oauth_session(&state, &client, &user, Scope::from_iter([GRAPHQL])).await;
+    let access_token = access_token.access_token;
+
+    // Start a sign in on the other device
+    let mut repo = state.repository().await.unwrap();
+    let mut rng = state.rng();
+    let grant = repo
+        .oauth2_device_code_grant()
+        .add(
+            &mut rng,
+            &state.clock,
+            OAuth2DeviceCodeGrantParams {
+                client: &client,
+                scope: Scope::from_iter([OPENID]),
+                device_code: "device-code".to_owned(),
+                user_code: "ABCDEF".to_owned(),
+                expires_in: Duration::minutes(5),
+                interval: Duration::seconds(5),
+                ip_address: None,
+                user_agent: None,
+                device_fingerprint: None,
+            },
+        )
+        .await
+        .unwrap();
+    repo.save().await.unwrap();
+
+    let query = r"
+        mutation AuthorizeDeviceCode($userCode: String!) {
+            authorizeDeviceCode(input: { userCode: $userCode, action: APPROVE }) {
+                status
+                oauth2Client {
+                    id
+                }
+            }
+        }
+    ";
+
+    // An unknown code is rejected
+    let request = Request::post("/graphql")
+        .bearer(&access_token)
+        .json(serde_json::json!({
+            "query": query,
+            "variables": { "userCode": "ZZZZZZ" },
+        }));
+    let response = state.request(request).await;
+    response.assert_status(StatusCode::OK);
+    let response: GraphQLResponse = response.json();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data,
+        serde_json::json!({
+            "authorizeDeviceCode": {
+                "status": "INVALID_CODE",
+                "oauth2Client": null,
+            }
+        })
+    );
+
+    // The code is case-insensitive
+    let request = Request::post("/graphql")
+        .bearer(&access_token)
+        .json(serde_json::json!({
+            "query": query,
+            "variables": { "userCode": "abcdef" },
+        }));
+    let response = state.request(request).await;
+    response.assert_status(StatusCode::OK);
+    let response: GraphQLResponse = response.json();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data,
+        serde_json::json!({
+            "authorizeDeviceCode": {
+                "status": "APPROVED",
+                "oauth2Client": {
+                    "id": format!("oauth2_client:{id}", id = client.id),
+                },
+            }
+        })
+    );
+
+    let mut repo = state.repository().await.unwrap();
+    let grant = repo
+        .oauth2_device_code_grant()
+        .lookup(grant.id)
+        .await
+        .unwrap()
+        .unwrap();
+    repo.save().await.unwrap();
+    assert!(grant.state.is_fulfilled());
+
+    // The code can't be used twice
+    let request = Request::post("/graphql")
+        .bearer(&access_token)
+        .json(serde_json::json!({
+            "query": query,
+            "variables": { "userCode": "ABCDEF" },
+        }));
+    let response = state.request(request).await;
+    response.assert_status(StatusCode::OK);
+    let response: GraphQLResponse = response.json();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data,
+        serde_json::json!({
+            "authorizeDeviceCode": {
+                "status": "INVALID_CODE",
+                "oauth2Client": null,
+            }
+        })
+    );
+}
+
+/// Test that users can pick which of their email addresses is the primary one
+#[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+async fn test_set_primary_email(pool: PgPool) {
+    setup();
+    let state = TestState::from_pool(pool).await.unwrap();
+
+    let client = create_test_client(&state).await;
+    let user = create_test_user(&state, "alice").await;
+    let access_token =
+        start_oauth_session(&state, &client, &user, Scope::from_iter([GRAPHQL])).await;
+    let access_token = access_token.access_token;
+
+    let mut repo = state.repository().await.unwrap();
+    let mut rng = state.rng();
+    let first = repo
+        .user_email()
+        .add(
+            &mut rng,
+            &state.clock,
+            &user,
+            "alice@example.com".to_owned(),
+        )
+        .await
+        .unwrap();
+    let second = repo
+        .user_email()
+        .add(
+            &mut rng,
+            &state.clock,
+            &user,
+            "alice@corp.example.com".to_owned(),
+        )
+        .await
+        .unwrap();
+    repo.save().await.unwrap();
+
+    let query = r"
+        query {
+            viewer {
+                ... on User {
+                    primaryEmail {
+                        email
+                    }
+                }
+            }
+        }
+    ";
+
+    // Without picking one, the oldest email is the primary one
+    let request = Request::post("/graphql")
+        .bearer(&access_token)
+        .json(serde_json::json!({ "query": query }));
+    let response = state.request(request).await;
+    response.assert_status(StatusCode::OK);
+    let response: GraphQLResponse = response.json();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data,
+        serde_json::json!({
+            "viewer": {
+                "primaryEmail": {
+                    "email": first.email,
+                }
+            }
+        })
+    );
+
+    let request = Request::post("/graphql")
+        .bearer(&access_token)
+        .json(serde_json::json!({
+            "query": r"
+                mutation SetPrimaryEmail($id: ID!) {
+                    setPrimaryEmail(input: { userEmailId: $id }) {
+                        status
+                    }
+                }
+            ",
+            "variables": { "id": format!("user_email:{}", second.id) },
+        }));
+    let response = state.request(request).await;
+    response.assert_status(StatusCode::OK);
+    let response: GraphQLResponse = response.json();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data,
+        serde_json::json!({
+            "setPrimaryEmail": {
+                "status": "SET",
+            }
+        })
+    );
+
+    let request = Request::post("/graphql")
+        .bearer(&access_token)
+        .json(serde_json::json!({ "query": query }));
+    let response = state.request(request).await;
+    response.assert_status(StatusCode::OK);
+    let response: GraphQLResponse = response.json();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data,
+        serde_json::json!({
+            "viewer": {
+                "primaryEmail": {
+                    "email": second.email,
+                }
+            }
+        })
+    );
+}
+
+/// Build a GraphQL multipart request, following the GraphQL multipart request
+/// spec, which sends `file` as the `$avatar` variable
+fn avatar_upload_request(
+    access_token: &str,
+    user_id: &str,
+    content_type: &str,
+    file: &str,
+) -> Request<String> {
+    let boundary = "mas-test-boundary";
+    let operations = serde_json::json!({
+        "query": r"
+            mutation SetAvatar($userId: ID!, $avatar: Upload) {
+                setAvatar(input: { userId: $userId, avatar: $avatar }) {
+                    status
+                }
+            }
+        ",
+        "variables": { "userId": user_id, "avatar": null },
+    });
+    let map = serde_json::json!({ "0": ["variables.avatar"] });
+
+    let body = format!(
+        "--{boundary}\r\n\
+         Content-Disposition: form-data; name=\"operations\"\r\n\r\n\
+         {operations}\r\n\
+         --{boundary}\r\n\
+         Content-Disposition: form-data; name=\"map\"\r\n\r\n\
+         {map}\r\n\
+         --{boundary}\r\n\
+         Content-Disposition: form-data; name=\"0\"; filename=\"avatar\"\r\n\
+         Content-Type: {content_type}\r\n\r\n\
+         {file}\r\n\
+         --{boundary}--\r\n"
+    );
+
+    Request::post("/graphql")
+        .bearer(access_token)
+        .header(
+            "Content-Type",
+            format!("multipart/form-data; boundary={boundary}"),
+        )
+        .body(body)
+        .unwrap()
+}
+
+/// Test that users can upload an avatar, which is then set on the homeserver
+#[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+async fn test_set_avatar(pool: PgPool) {
+    setup();
+    let state = TestState::from_pool(pool).await.unwrap();
+
+    let client = create_test_client(&state).await;
+    let user = create_test_user(&state, "alice").await;
+    let access_token =
+        start_oauth_session(&state, &client, &user, Scope::from_iter([GRAPHQL])).await;
+    let access_token = access_token.access_token;
+
+    let mxid = state.homeserver_connection.mxid(&user.username);
+    state
+        .homeserver_connection
+        .provision_user(&ProvisionRequest::new(&mxid, &user.sub))
+        .await
+        .unwrap();
+
+    let user_id = format!("user:{}", user.id);
+
+    // Only images are accepted
+    let request = avatar_upload_request(&access_token, &user_id, "text/plain", "hello");
+    let response = state.request(request).await;
+    response.assert_status(StatusCode::OK);
+    let response: GraphQLResponse = response.json();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data,
+        serde_json::json!({
+            "setAvatar": {
+                "status": "INVALID_FORMAT",
+            }
+        })
+    );
+
+    let request = avatar_upload_request(&access_token, &user_id, "image/png", "not really a png");
+    let response = state.request(request).await;
+    response.assert_status(StatusCode::OK);
+    let response: GraphQLResponse = response.json();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data,
+        serde_json::json!({
+            "setAvatar": {
+                "status": "SET",
+            }
+        })
+    );
+
+    let matrix_user = state.homeserver_connection.query_user(&mxid).await.unwrap();
+    assert!(
+        matrix_user
+            .avatar_url
+            .is_some_and(|url| url.starts_with("mxc://"))
+    );
+
+    // Removing the avatar
+    let request = Request::post("/graphql")
+        .bearer(&access_token)
+        .json(serde_json::json!({
+            "query": r"
+                mutation SetAvatar($userId: ID!) {
+                    setAvatar(input: { userId: $userId }) {
+                        status
+                    }
+                }
+            ",
+            "variables": { "userId": user_id },
+        }));
+    let response = state.request(request).await;
+    response.assert_status(StatusCode::OK);
+    let response: GraphQLResponse = response.json();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+    let matrix_user = state.homeserver_connection.query_user(&mxid).await.unwrap();
+    assert_eq!(matrix_user.avatar_url, None);
+}
+
+/// Test that users can unlink upstream accounts, as long as they can still
+/// sign in some other way
+#[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+async fn test_remove_upstream_link(pool: PgPool) {
+    setup();
+    let state = TestState::from_pool(pool).await.unwrap();
+
+    let client = create_test_client(&state).await;
+    let user = create_test_user(&state, "alice").await;
+    let access_token =
+        start_oauth_session(&state, &client, &user, Scope::from_iter([GRAPHQL])).await;
+    let access_token = access_token.access_token;
+
+    let mut repo = state.repository().await.unwrap();
+    let mut rng = state.rng();
+    let provider = repo
+        .upstream_oauth_provider()
+        .add(
+            &mut rng,
+            &state.clock,
+            UpstreamOAuthProviderParams {
+                issuer: Some("https://example.com/".to_owned()),
+                human_name: Some("Example Ltd.".to_owned()),
+                brand_name: None,
+                scope: Scope::from_iter([OPENID]),
+                token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+                token_endpoint_signing_alg: None,
+                token_endpoint_signing_key_id: None,
+                tls_client_certificate: None,
+                encrypted_tls_client_key: None,
+                id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+                client_id: "client".to_owned(),
+                encrypted_client_secret: None,
+                claims_imports: UpstreamOAuthProviderClaimsImports::default(),
+                authorization_endpoint_override: None,
+                token_endpoint_override: None,
+                userinfo_endpoint_override: None,
+                fetch_userinfo: false,
+                userinfo_signed_response_alg: None,
+                jwks_uri_override: None,
+                discovery_mode: UpstreamOAuthProviderDiscoveryMode::Oidc,
+                pkce_mode: UpstreamOAuthProviderPkceMode::Auto,
+                response_mode: None,
+                additional_authorization_parameters: Vec::new(),
+                forward_login_hint: false,
+                forward_prompt: false,
+                forward_acr_values: false,
+                forward_ui_locales: false,
+                store_tokens: false,
+                deprovisioning: None,
+                ui_order: 0,
+            },
+        )
+        .await
+        .unwrap();
+
+    let first = repo
+        .upstream_oauth_link()
+        .add(&mut rng, &state.clock, &provider, "first".to_owned(), None)
+        .await
+        .unwrap();
+    repo.upstream_oauth_link()
+        .associate_to_user(&first, &user)
+        .await
+        .unwrap();
+    repo.save().await.unwrap();
+
+    let remove = |id: ulid::Ulid| {
+        Request::post("/graphql")
+            .bearer(&access_token)
+            .json(serde_json::json!({
+                "query": r"
+                    mutation RemoveUpstreamLink($id: ID!) {
+                        removeUpstreamLink(input: { upstreamLinkId: $id }) {
+                            status
+                        }
+                    }
+                ",
+                "variables": { "id": format!("upstream_oauth2_link:{id}") },
+            }))
+    };
+
+    // The user has no password, so this is the only way for them to sign in
+    let response = state.request(remove(first.id)).await;
+    response.assert_status(StatusCode::OK);
+    let response: GraphQLResponse = response.json();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data,
+        serde_json::json!({
+            "removeUpstreamLink": {
+                "status": "LAST_LOGIN_METHOD",
+            }
+        })
+    );
+
+    // With a second link, the first one can be removed
+    let mut repo = state.repository().await.unwrap();
+    let second = repo
+        .upstream_oauth_link()
+        .add(&mut rng, &state.clock, &provider, "second".to_owned(), None)
+        .await
+        .unwrap();
+    repo.upstream_oauth_link()
+        .associate_to_user(&second, &user)
+        .await
+        .unwrap();
+    repo.save().await.unwrap();
+
+    let response = state.request(remove(first.id)).await;
+    response.assert_status(StatusCode::OK);
+    let response: GraphQLResponse = response.json();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data,
+        serde_json::json!({
+            "removeUpstreamLink": {
+                "status": "REMOVED",
+            }
+        })
+    );
+
+    let mut repo = state.repository().await.unwrap();
+    assert!(
+        repo.upstream_oauth_link()
+            .lookup(first.id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // The remaining link is now the last one again
+    let response = state.request(remove(second.id)).await;
+    response.assert_status(StatusCode::OK);
+    let response: GraphQLResponse = response.json();
+    assert_eq!(
+        response.data,
+        serde_json::json!({
+            "removeUpstreamLink": {
+                "status": "LAST_LOGIN_METHOD",
+            }
+        })
+    );
+}
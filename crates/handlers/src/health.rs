@@ -6,17 +6,12 @@
 
 use axum::{extract::State, response::IntoResponse};
 use mas_axum_utils::InternalError;
-use sqlx::PgPool;
-use tracing::{Instrument, info_span};
+use mas_storage::BoxRepositoryFactory;
 
-pub async fn get(State(pool): State<PgPool>) -> Result<impl IntoResponse, InternalError> {
-    let mut conn = pool.acquire().await?;
-
-    sqlx::query("SELECT $1")
-        .bind(1_i64)
-        .execute(&mut *conn)
-        .instrument(info_span!("DB health"))
-        .await?;
+pub async fn get(
+    State(repository_factory): State<BoxRepositoryFactory>,
+) -> Result<impl IntoResponse, InternalError> {
+    repository_factory.health_check().await?;
 
     Ok("ok")
 }
@@ -24,8 +19,8 @@ pub async fn get(State(pool): State<PgPool>) -> Result<impl IntoResponse, Intern
 #[cfg(test)]
 mod tests {
     use hyper::{Request, StatusCode};
+    use sqlx::PgPool;
 
-    use super::*;
     use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
 
     #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
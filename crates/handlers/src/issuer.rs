@@ -0,0 +1,43 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::convert::Infallible;
+
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+use hyper::header::HOST;
+use mas_router::UrlBuilder;
+
+/// A [`UrlBuilder`] for the issuer the request was sent to
+///
+/// This picks the issuer alias matching the `Host` header of the request, and
+/// falls back to the main issuer if there is none.
+pub struct IssuerUrlBuilder(pub UrlBuilder);
+
+impl<S> FromRequestParts<S> for IssuerUrlBuilder
+where
+    S: Send + Sync,
+    UrlBuilder: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let url_builder = UrlBuilder::from_ref(state);
+
+        let host = parts
+            .headers
+            .get(HOST)
+            .and_then(|value| value.to_str().ok())
+            .or_else(|| parts.uri.authority().map(|authority| authority.as_str()));
+
+        let Some(host) = host else {
+            return Ok(IssuerUrlBuilder(url_builder));
+        };
+
+        Ok(IssuerUrlBuilder(url_builder.for_host(host)))
+    }
+}
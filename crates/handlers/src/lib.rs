@@ -45,7 +45,6 @@ use mas_router::{Route, UrlBuilder};
 use mas_storage::{BoxClock, BoxRepository, BoxRepositoryFactory, BoxRng};
 use mas_templates::{ErrorContext, NotFoundContext, TemplateContext, Templates};
 use opentelemetry::metrics::Meter;
-use sqlx::PgPool;
 use tower::util::AndThenLayer;
 use tower_http::cors::{Any, CorsLayer};
 
@@ -62,11 +61,22 @@ mod views;
 
 mod activity_tracker;
 mod captcha;
+mod feature_flags;
+mod issuer;
+mod login_risk;
+mod password_lockout;
+mod password_policy;
 mod preferred_language;
 mod rate_limit;
 mod session;
+mod sign_in_notifications;
+mod sms;
+mod terms;
 #[cfg(test)]
 mod test_utils;
+mod totp;
+mod trusted_device;
+mod webauthn;
 
 static METER: LazyLock<Meter> = LazyLock::new(|| {
     let scope = opentelemetry::InstrumentationScope::builder(env!("CARGO_PKG_NAME"))
@@ -93,23 +103,33 @@ macro_rules! impl_from_error_for_route {
     };
 }
 
-pub use mas_axum_utils::{ErrorWrapper, cookies::CookieManager};
+pub use mas_axum_utils::{ClientJwksCache, ErrorWrapper, cookies::CookieManager};
 
 pub use self::{
-    activity_tracker::{ActivityTracker, Bound as BoundActivityTracker},
+    activity_tracker::{
+        ActivityTracker, Bound as BoundActivityTracker, GeoIpLoadError, GeoIpResolver,
+    },
     admin::router as admin_api_router,
+    captcha::ProofOfWorkReplayCache,
+    feature_flags::FeatureFlags,
     graphql::{
         Schema as GraphQLSchema, schema as graphql_schema, schema_builder as graphql_schema_builder,
     },
+    issuer::IssuerUrlBuilder,
+    oauth2::introspection_cache::IntrospectionCache,
     preferred_language::PreferredLanguage,
     rate_limit::{Limiter, RequesterFingerprint},
-    upstream_oauth2::cache::MetadataCache,
+    upstream_oauth2::{
+        cache::{MetadataCache, MetadataCacheSettings},
+        circuit_breaker::{CircuitBreakerSettings, ProviderCircuitBreaker},
+        health::{ProviderHealthSettings, ProviderHealthTracker},
+    },
 };
 
 pub fn healthcheck_router<S>() -> Router<S>
 where
     S: Clone + Send + Sync + 'static,
-    PgPool: FromRef<S>,
+    BoxRepositoryFactory: FromRef<S>,
 {
     Router::new().route(mas_router::Healthcheck::route(), get(self::health::get))
 }
@@ -121,10 +141,13 @@ where
     BoundActivityTracker: FromRequestParts<S>,
     BoxRepository: FromRequestParts<S>,
     BoxClock: FromRequestParts<S>,
+    BoxRng: FromRequestParts<S>,
+    UrlBuilder: FromRef<S>,
     Encrypter: FromRef<S>,
     CookieJar: FromRequestParts<S>,
     Limiter: FromRef<S>,
     RequesterFingerprint: FromRequestParts<S>,
+    SiteConfig: FromRef<S>,
 {
     let mut router = Router::new()
         .route(
@@ -202,12 +225,20 @@ where
     BoundActivityTracker: FromRequestParts<S>,
     Encrypter: FromRef<S>,
     reqwest::Client: FromRef<S>,
+    ClientJwksCache: FromRef<S>,
     SiteConfig: FromRef<S>,
     Templates: FromRef<S>,
     Arc<dyn HomeserverConnection>: FromRef<S>,
     BoxClock: FromRequestParts<S>,
     BoxRng: FromRequestParts<S>,
     Policy: FromRequestParts<S>,
+    PasswordManager: FromRef<S>,
+    Limiter: FromRef<S>,
+    FeatureFlags: FromRef<S>,
+    IntrospectionCache: FromRef<S>,
+    MetadataCache: FromRef<S>,
+    ProviderCircuitBreaker: FromRef<S>,
+    RequesterFingerprint: FromRequestParts<S>,
 {
     // All those routes are API-like, with a common CORS layer
     Router::new()
@@ -239,6 +270,18 @@ where
             mas_router::OAuth2DeviceAuthorizationEndpoint::route(),
             post(self::oauth2::device::authorize::post),
         )
+        .route(
+            mas_router::OAuth2PushedAuthorizationRequestEndpoint::route(),
+            post(self::oauth2::pushed_authorization_request::post),
+        )
+        .route(
+            mas_router::OAuth2BackchannelAuthenticationEndpoint::route(),
+            post(self::oauth2::backchannel::authorize::post),
+        )
+        .route(
+            mas_router::UpstreamOAuth2Tokens::route(),
+            get(self::upstream_oauth2::tokens::get),
+        )
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
@@ -332,8 +375,12 @@ where
     Keystore: FromRef<S>,
     PasswordManager: FromRef<S>,
     MetadataCache: FromRef<S>,
+    ProviderHealthTracker: FromRef<S>,
+    ProviderCircuitBreaker: FromRef<S>,
+    ProofOfWorkReplayCache: FromRef<S>,
     SiteConfig: FromRef<S>,
     Limiter: FromRef<S>,
+    FeatureFlags: FromRef<S>,
     reqwest::Client: FromRef<S>,
     Arc<dyn HomeserverConnection>: FromRef<S>,
     BoxClock: FromRequestParts<S>,
@@ -378,7 +425,77 @@ where
             mas_router::Login::route(),
             get(self::views::login::get).post(self::views::login::post),
         )
+        .route(
+            mas_router::PasskeyLogin::route(),
+            post(self::views::login::post_passkey),
+        )
+        .route(
+            mas_router::LoginDiscover::route(),
+            post(self::views::login::post_discover),
+        )
+        .route(
+            mas_router::LoginTotp::route(),
+            get(self::views::login_totp::get).post(self::views::login_totp::post),
+        )
+        .route(
+            mas_router::LoginSms::route(),
+            get(self::views::login_sms::get).post(self::views::login_sms::post),
+        )
+        .route(
+            mas_router::LoginConfirmEmail::route(),
+            get(self::views::login_confirm_email::get)
+                .post(self::views::login_confirm_email::post),
+        )
+        .route(
+            mas_router::LoginPasswordChange::route(),
+            get(self::views::login_password_change::get)
+                .post(self::views::login_password_change::post),
+        )
+        .route(
+            mas_router::LoginRecoveryCode::route(),
+            get(self::views::login_recovery_code::get).post(self::views::login_recovery_code::post),
+        )
+        .route(
+            mas_router::LoginEmail::route(),
+            get(self::views::login_email::get).post(self::views::login_email::post),
+        )
+        .route(
+            mas_router::LoginEmailCode::route(),
+            get(self::views::login_email::get_code).post(self::views::login_email::post_code),
+        )
+        .route(
+            mas_router::LoginEmailLink::route(),
+            get(self::views::login_email::get_link).post(self::views::login_email::post_link),
+        )
         .route(mas_router::Logout::route(), post(self::views::logout::post))
+        .route(
+            mas_router::RestoreAccount::route(),
+            post(self::views::restore_account::post),
+        )
+        .route(
+            mas_router::CaptchaChallenge::route(),
+            get(self::captcha::challenge),
+        )
+        .route(
+            mas_router::Invites::route(),
+            get(self::views::invites::get).post(self::views::invites::post),
+        )
+        .route(
+            mas_router::Notifications::route(),
+            get(self::views::notifications::get).post(self::views::notifications::post),
+        )
+        .route(
+            mas_router::AcceptTerms::route(),
+            get(self::views::accept_terms::get).post(self::views::accept_terms::post),
+        )
+        .route(
+            mas_router::Reauth::route(),
+            get(self::views::reauth::get).post(self::views::reauth::post),
+        )
+        .route(
+            mas_router::PasskeyReauth::route(),
+            post(self::views::reauth::post_passkey),
+        )
         .route(
             mas_router::Register::route(),
             get(self::views::register::get),
@@ -414,10 +531,22 @@ where
             mas_router::AccountRecoveryProgress::route(),
             get(self::views::recovery::progress::get).post(self::views::recovery::progress::post),
         )
+        .route(
+            mas_router::AccountClaim::route(),
+            get(self::views::claim::get).post(self::views::claim::post),
+        )
+        .route(
+            mas_router::UserActionLink::route(),
+            get(self::views::user_action::get).post(self::views::user_action::post),
+        )
         .route(
             mas_router::OAuth2AuthorizationEndpoint::route(),
             get(self::oauth2::authorization::get),
         )
+        .route(
+            mas_router::OAuth2EndSessionEndpoint::route(),
+            get(self::oauth2::end_session::handler).post(self::oauth2::end_session::handler),
+        )
         .route(
             mas_router::Consent::route(),
             get(self::oauth2::authorization::consent::get)
@@ -436,6 +565,10 @@ where
             get(self::upstream_oauth2::callback::handler)
                 .post(self::upstream_oauth2::callback::handler),
         )
+        .route(
+            mas_router::UpstreamOAuth2BackchannelLogout::route(),
+            post(self::upstream_oauth2::backchannel_logout::post),
+        )
         .route(
             mas_router::UpstreamOAuth2Link::route(),
             get(self::upstream_oauth2::link::get).post(self::upstream_oauth2::link::post),
@@ -448,6 +581,11 @@ where
             mas_router::DeviceCodeConsent::route(),
             get(self::oauth2::device::consent::get).post(self::oauth2::device::consent::post),
         )
+        .route(
+            mas_router::BackchannelAuthenticationConsent::route(),
+            get(self::oauth2::backchannel::consent::get)
+                .post(self::oauth2::backchannel::consent::post),
+        )
         .layer(AndThenLayer::new(
             async move |response: axum::response::Response| {
                 // Error responses should have an ErrorContext attached to them
@@ -0,0 +1,109 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Risk evaluation of password logins
+//!
+//! Once the password of a user was checked, the login policy compares where the
+//! login comes from with where the user was recently active. Logins it flags as
+//! suspicious have to be confirmed, with a second factor or a code sent by
+//! email, before the session is established.
+
+use std::sync::LazyLock;
+
+use chrono::Duration;
+use mas_axum_utils::InternalError;
+use mas_data_model::User;
+use mas_policy::{LoginInput, LoginLocation, Policy, RecentSession, Requester};
+use mas_storage::{
+    BoxRepository, Clock, Pagination,
+    user::{BrowserSessionFilter, BrowserSessionRepository},
+};
+use opentelemetry::{Key, KeyValue, metrics::Counter};
+
+use crate::{BoundActivityTracker, METER};
+
+static SUSPICIOUS_LOGIN_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("mas.user.suspicious_login")
+        .with_description("Number of password logins flagged as suspicious by the policy")
+        .with_unit("{login}")
+        .build()
+});
+const CODE: Key = Key::from_static_str("code");
+
+/// How far back to look for the activity of the user
+const RECENT_ACTIVITY: Duration = Duration::days(30);
+
+/// How many of the sessions of the user to compare the login with
+const RECENT_SESSIONS: usize = 50;
+
+/// Evaluate the login policy for a user who just checked their password
+///
+/// Returns `true` if the login has to be confirmed before getting a session.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails, or if the policy can't be
+/// evaluated
+pub(crate) async fn is_suspicious(
+    repo: &mut BoxRepository,
+    policy: &mut Policy,
+    clock: &dyn Clock,
+    activity_tracker: &BoundActivityTracker,
+    user: &User,
+    user_agent: Option<&str>,
+) -> Result<bool, InternalError> {
+    let now = clock.now();
+    let location = activity_tracker.location();
+
+    let filter = BrowserSessionFilter::new()
+        .for_user(user)
+        .with_last_active_after(now - RECENT_ACTIVITY);
+    let sessions = repo
+        .browser_session()
+        .list(filter, Pagination::last(RECENT_SESSIONS))
+        .await?
+        .edges;
+
+    let recent_sessions = sessions
+        .iter()
+        .filter_map(|session| {
+            let last_active_at = session.last_active_at?;
+            Some(RecentSession {
+                country: session.last_active_location.country.as_deref(),
+                inactive_for: (now - last_active_at).num_seconds(),
+            })
+        })
+        .collect();
+
+    let res = policy
+        .evaluate_login(LoginInput {
+            user,
+            location: LoginLocation {
+                country: location.country.as_deref(),
+                asn: location.asn,
+                as_organization: location.as_organization.as_deref(),
+            },
+            recent_sessions,
+            requester: Requester {
+                ip_address: activity_tracker.ip(),
+                user_agent: user_agent.map(ToOwned::to_owned),
+            },
+        })
+        .await?;
+
+    for violation in &res.violations {
+        let code = violation.code.map_or("other", |code| code.as_str());
+        tracing::info!(
+            user.id = %user.id,
+            code,
+            "Suspicious login: {}",
+            violation.msg
+        );
+        SUSPICIOUS_LOGIN_COUNTER.add(1, &[KeyValue::new(CODE, code)]);
+    }
+
+    Ok(!res.valid())
+}
@@ -0,0 +1,154 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Resolution of the Authentication Context Class Reference values satisfied
+//! by browser sessions, and required by clients, and of the step-up
+//! authentication needed to continue an authorization grant
+
+use chrono::{DateTime, Utc};
+use mas_data_model::{Authentication, AuthorizationGrant, BrowserSessionElevation, SiteConfig};
+use oauth2_types::scope::Scope;
+
+/// The position of an ACR value in the configured list, from the weakest to
+/// the strongest
+fn strength(site_config: &SiteConfig, value: &str) -> Option<usize> {
+    site_config
+        .acr_values
+        .iter()
+        .position(|acr| acr.value == value)
+}
+
+/// Get the strongest ACR value satisfied by an authentication with the given
+/// method references
+pub(crate) fn satisfied_acr_for_amr<'a>(
+    site_config: &'a SiteConfig,
+    amr: &[&str],
+) -> Option<&'a str> {
+    site_config
+        .acr_values
+        .iter()
+        .rev()
+        .find(|acr| acr.amr.iter().all(|method| amr.contains(&method.as_str())))
+        .map(|acr| acr.value.as_str())
+}
+
+/// Get the strongest ACR value satisfied by a session, given its last
+/// authentication and its active elevation
+pub(crate) fn satisfied_acr<'a>(
+    site_config: &'a SiteConfig,
+    last_authentication: Option<&Authentication>,
+    elevation: Option<&'a BrowserSessionElevation>,
+) -> Option<&'a str> {
+    let amr = last_authentication.map_or(&[][..], |a| a.authentication_method.amr());
+    let base = satisfied_acr_for_amr(site_config, amr);
+    let elevated = elevation.and_then(|elevation| elevation.acr.as_deref());
+
+    [base, elevated]
+        .into_iter()
+        .flatten()
+        .filter_map(|acr| Some((strength(site_config, acr)?, acr)))
+        .max_by_key(|(strength, _)| *strength)
+        .map(|(_, acr)| acr)
+}
+
+/// Get the ACR value a session must satisfy, out of the values requested by a
+/// client.
+///
+/// The client is satisfied by any of the values it requested, so this is the
+/// weakest of them. Values which are not configured are ignored.
+pub(crate) fn required_acr<'a>(
+    site_config: &SiteConfig,
+    requested: impl IntoIterator<Item = &'a String>,
+) -> Option<String> {
+    requested
+        .into_iter()
+        .filter_map(|value| Some((strength(site_config, value)?, value)))
+        .min_by_key(|(strength, _)| *strength)
+        .map(|(_, value)| value.clone())
+}
+
+/// Check whether the given ACR value satisfies the required one
+fn satisfies(site_config: &SiteConfig, acr: Option<&str>, required_acr: Option<&str>) -> bool {
+    let Some(required_acr) = required_acr else {
+        return true;
+    };
+
+    // If the value was removed from the configuration since the grant was
+    // created, it can't be satisfied anymore
+    let Some(required) = strength(site_config, required_acr) else {
+        return false;
+    };
+
+    acr.and_then(|acr| strength(site_config, acr))
+        .is_some_and(|strength| strength >= required)
+}
+
+/// Check whether a scope requires the user to step up their authentication
+pub(crate) fn requires_step_up(site_config: &SiteConfig, scope: &Scope) -> bool {
+    scope.iter().any(|token| {
+        site_config
+            .step_up_scopes
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => token.as_str().starts_with(prefix),
+                None => token.as_str() == pattern,
+            })
+    })
+}
+
+/// What a browser session needs to continue an authorization grant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Requirement {
+    /// The session can continue the grant as-is
+    Satisfied,
+
+    /// The user has to log in again
+    Login,
+
+    /// The user has to confirm their password to elevate the session
+    StepUp,
+}
+
+/// Check what a browser session, given its last authentication and its active
+/// elevation, needs to continue an authorization grant.
+///
+/// A session is elevated for [`SiteConfig::step_up_ttl`] after a step-up, or
+/// after the user logged in.
+pub(crate) fn requirement(
+    site_config: &SiteConfig,
+    now: DateTime<Utc>,
+    grant: &AuthorizationGrant,
+    last_authentication: Option<&Authentication>,
+    elevation: Option<&BrowserSessionElevation>,
+) -> Requirement {
+    if !grant.is_authentication_fresh(last_authentication) {
+        return Requirement::Login;
+    }
+
+    let required_acr = grant.required_acr.as_deref();
+    if !satisfies(
+        site_config,
+        satisfied_acr(site_config, last_authentication, elevation),
+        required_acr,
+    ) {
+        // Confirming their password only helps if it satisfies the ACR
+        let step_up_acr = satisfied_acr_for_amr(site_config, &["pwd"]);
+        return if satisfies(site_config, step_up_acr, required_acr) {
+            Requirement::StepUp
+        } else {
+            Requirement::Login
+        };
+    }
+
+    if requires_step_up(site_config, &grant.scope) {
+        let elevated = elevation.is_some_and(|elevation| elevation.is_active(now))
+            || last_authentication.is_some_and(|a| now - a.created_at < site_config.step_up_ttl);
+        if !elevated {
+            return Requirement::StepUp;
+        }
+    }
+
+    Requirement::Satisfied
+}
@@ -9,10 +9,21 @@
 use std::collections::HashMap;
 
 use axum::response::{Html, IntoResponse, Redirect, Response};
-use mas_data_model::AuthorizationGrant;
+use chrono::{DateTime, Duration, Utc};
+use mas_data_model::Client;
 use mas_i18n::DataLocale;
+use mas_iana::jose::JsonWebSignatureAlg;
+use mas_jose::{
+    claims,
+    jwt::{JsonWebSignatureHeader, Jwt},
+};
+use mas_keystore::Keystore;
+use mas_router::UrlBuilder;
+use mas_storage::Clock;
 use mas_templates::{FormPostContext, Templates};
 use oauth2_types::requests::ResponseMode;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
 use serde::Serialize;
 use thiserror::Error;
 use url::Url;
@@ -26,11 +37,102 @@ enum CallbackDestinationMode {
     FormPost,
 }
 
+/// Signs the authorization responses sent with one of the JWT response modes,
+/// as per the [JWT Secured Authorization Response Mode] spec
+///
+/// [JWT Secured Authorization Response Mode]: https://openid.net/specs/oauth-v2-jarm.html
+#[derive(Clone)]
+pub struct ResponseSigner {
+    key_store: Keystore,
+    alg: JsonWebSignatureAlg,
+    issuer: String,
+    audience: String,
+    expires_at: DateTime<Utc>,
+    rng: ChaChaRng,
+}
+
+impl std::fmt::Debug for ResponseSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseSigner")
+            .field("alg", &self.alg)
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .field("expires_at", &self.expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ResponseSigner {
+    /// Prepare a signer for the authorization responses sent to the given
+    /// client
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the random number generator could not be seeded
+    pub fn new(
+        rng: &mut (impl RngCore + CryptoRng),
+        clock: &impl Clock,
+        url_builder: &UrlBuilder,
+        key_store: &Keystore,
+        client: &Client,
+    ) -> Result<Self, rand::Error> {
+        let alg = client
+            .authorization_signed_response_alg
+            .clone()
+            .unwrap_or(JsonWebSignatureAlg::Rs256);
+
+        Ok(Self {
+            key_store: key_store.clone(),
+            alg,
+            issuer: url_builder.oidc_issuer().to_string(),
+            audience: client.client_id.clone(),
+            // The response is consumed right away by the client, so it can be
+            // short-lived
+            expires_at: clock.now() + Duration::try_minutes(10).unwrap(),
+            rng: ChaChaRng::from_rng(rng)?,
+        })
+    }
+
+    /// Whether the keystore has a key to sign responses with the algorithm
+    /// of the client
+    fn can_sign(&self) -> bool {
+        self.key_store
+            .signing_key_for_algorithm(&self.alg)
+            .is_some()
+    }
+
+    fn sign<T: Serialize>(mut self, payload: T) -> Result<String, CallbackDestinationError> {
+        // Start with the response parameters, so that they can't override the
+        // standard claims
+        let mut claims: HashMap<String, serde_json::Value> =
+            serde_json::from_value(serde_json::to_value(payload)?)?;
+        claims::ISS.insert(&mut claims, self.issuer)?;
+        claims::AUD.insert(&mut claims, self.audience)?;
+        claims::EXP.insert(&mut claims, self.expires_at)?;
+
+        let key = self
+            .key_store
+            .signing_key_for_algorithm(&self.alg)
+            .ok_or(CallbackDestinationError::InvalidSigningKey)?;
+        let signer = key.params().signing_key_for_alg(&self.alg)?;
+        let header = JsonWebSignatureHeader::new(self.alg).with_kid(
+            key.kid()
+                .ok_or(CallbackDestinationError::InvalidSigningKey)?,
+        );
+        let jwt = Jwt::sign_with_rng(&mut self.rng, header, claims, &signer)?;
+
+        Ok(jwt.into_string())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CallbackDestination {
     mode: CallbackDestinationMode,
     safe_redirect_uri: Url,
     state: Option<String>,
+
+    /// Set if the response must be sent as a signed JWT
+    signer: Option<ResponseSigner>,
 }
 
 #[derive(Debug, Error)]
@@ -43,6 +145,9 @@ pub enum IntoCallbackDestinationError {
 
     #[error("Requested response_mode is not supported")]
     UnsupportedResponseMode,
+
+    #[error("Responses can't be signed with the algorithm of this client")]
+    UnsupportedSigningAlgorithm,
 }
 
 #[derive(Debug, Error)]
@@ -52,32 +157,56 @@ pub enum CallbackDestinationError {
 
     #[error("Failed to serialize parameters query string")]
     ParamsSerialization(#[from] serde_urlencoded::ser::Error),
-}
 
-impl TryFrom<&AuthorizationGrant> for CallbackDestination {
-    type Error = IntoCallbackDestinationError;
+    #[error("Failed to serialize the response claims")]
+    ClaimsSerialization(#[from] serde_json::Error),
 
-    fn try_from(value: &AuthorizationGrant) -> Result<Self, Self::Error> {
-        Self::try_new(
-            &value.response_mode,
-            value.redirect_uri.clone(),
-            value.state.clone(),
-        )
-    }
+    #[error("Failed to insert a claim in the response")]
+    Claim(#[from] mas_jose::claims::ClaimError),
+
+    #[error("The signing key is invalid")]
+    InvalidSigningKey,
+
+    #[error("Failed to sign the response")]
+    JwtSignature(#[from] mas_jose::jwt::JwtSignatureError),
+
+    #[error(transparent)]
+    WrongAlgorithm(#[from] mas_keystore::WrongAlgorithmError),
 }
 
 impl CallbackDestination {
+    /// Figure out where and how to send the authorization response
+    ///
+    /// The `signer` is only used if the response mode is one of the JWT
+    /// response modes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the redirect URI or the response mode are invalid,
+    /// or if a JWT response mode is requested but the response can't be
+    /// signed with the algorithm of the client
     pub fn try_new(
         mode: &ResponseMode,
         mut redirect_uri: Url,
         state: Option<String>,
+        signer: ResponseSigner,
     ) -> Result<Self, IntoCallbackDestinationError> {
         if redirect_uri.fragment().is_some() {
             return Err(IntoCallbackDestinationError::RedirectUriFragmentNotAllowed);
         }
 
+        let signer = if mode.is_jwt() {
+            if !signer.can_sign() {
+                return Err(IntoCallbackDestinationError::UnsupportedSigningAlgorithm);
+            }
+
+            Some(signer)
+        } else {
+            None
+        };
+
         let mode = match mode {
-            ResponseMode::Query => {
+            ResponseMode::Query | ResponseMode::QueryJwt => {
                 let existing_params = redirect_uri
                     .query()
                     .map(serde_urlencoded::from_str)
@@ -89,8 +218,8 @@ impl CallbackDestination {
 
                 CallbackDestinationMode::Query { existing_params }
             }
-            ResponseMode::Fragment => CallbackDestinationMode::Fragment,
-            ResponseMode::FormPost => CallbackDestinationMode::FormPost,
+            ResponseMode::Fragment | ResponseMode::FragmentJwt => CallbackDestinationMode::Fragment,
+            ResponseMode::FormPost | ResponseMode::FormPostJwt => CallbackDestinationMode::FormPost,
             _ => return Err(IntoCallbackDestinationError::UnsupportedResponseMode),
         };
 
@@ -98,6 +227,7 @@ impl CallbackDestination {
             mode,
             safe_redirect_uri: redirect_uri,
             state,
+            signer,
         })
     }
 
@@ -119,17 +249,46 @@ impl CallbackDestination {
             params: T,
         }
 
+        #[derive(Serialize)]
+        struct SignedParams<'s> {
+            #[serde(flatten, skip_serializing_if = "Option::is_none")]
+            existing: Option<&'s HashMap<String, String>>,
+
+            response: String,
+        }
+
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum Params<'s, T> {
+            Plain(AllParams<'s, T>),
+            Signed(SignedParams<'s>),
+        }
+
         let mut redirect_uri = self.safe_redirect_uri;
-        let state = self.state;
+        let existing = match &self.mode {
+            CallbackDestinationMode::Query { existing_params } => Some(existing_params),
+            CallbackDestinationMode::Fragment | CallbackDestinationMode::FormPost => None,
+        };
 
-        match self.mode {
-            CallbackDestinationMode::Query { existing_params } => {
-                let merged = AllParams {
-                    existing: Some(&existing_params),
-                    state,
-                    params,
-                };
+        // With the JWT response modes, the response parameters are wrapped in a
+        // signed JWT, sent as a single `response` parameter
+        let merged = if let Some(signer) = self.signer {
+            let response = signer.sign(AllParams {
+                existing: None,
+                state: self.state,
+                params,
+            })?;
+            Params::Signed(SignedParams { existing, response })
+        } else {
+            Params::Plain(AllParams {
+                existing,
+                state: self.state,
+                params,
+            })
+        };
 
+        match self.mode {
+            CallbackDestinationMode::Query { .. } => {
                 let new_qs = serde_urlencoded::to_string(merged)?;
 
                 redirect_uri.set_query(Some(&new_qs));
@@ -138,12 +297,6 @@ impl CallbackDestination {
             }
 
             CallbackDestinationMode::Fragment => {
-                let merged = AllParams {
-                    existing: None,
-                    state,
-                    params,
-                };
-
                 let new_qs = serde_urlencoded::to_string(merged)?;
 
                 redirect_uri.set_fragment(Some(&new_qs));
@@ -152,11 +305,6 @@ impl CallbackDestination {
             }
 
             CallbackDestinationMode::FormPost => {
-                let merged = AllParams {
-                    existing: None,
-                    state,
-                    params,
-                };
                 let ctx = FormPostContext::new_for_url(redirect_uri, merged).with_language(locale);
                 let rendered = templates.render_form_post(&ctx)?;
                 Ok(Html(rendered).into_response())
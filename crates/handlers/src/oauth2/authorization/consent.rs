@@ -15,23 +15,35 @@ use mas_axum_utils::{
     csrf::{CsrfExt, ProtectedForm},
     record_error,
 };
-use mas_data_model::AuthorizationGrantStage;
+use mas_data_model::{
+    Authentication, AuthorizationGrant, AuthorizationGrantStage, BrowserSession,
+    BrowserSessionElevation, Client, Device, SiteConfig,
+};
+use mas_i18n::DataLocale;
 use mas_keystore::Keystore;
 use mas_policy::Policy;
 use mas_router::{PostAuthAction, UrlBuilder};
 use mas_storage::{
-    BoxClock, BoxRepository, BoxRng,
-    oauth2::{OAuth2AuthorizationGrantRepository, OAuth2ClientRepository},
+    BoxClock, BoxRepository, BoxRng, Clock,
+    oauth2::{OAuth2AuthorizationGrantRepository, OAuth2ClientRepository, OAuth2ConsentRepository},
+    user::UserEmailRepository,
 };
 use mas_templates::{ConsentContext, PolicyViolationContext, TemplateContext, Templates};
-use oauth2_types::requests::AuthorizationResponse;
+use oauth2_types::{
+    requests::AuthorizationResponse,
+    scope::{OFFLINE_ACCESS, Scope, ScopeToken},
+};
+use serde::Deserialize;
 use thiserror::Error;
 use ulid::Ulid;
 
-use super::callback::CallbackDestination;
+use super::callback::{CallbackDestination, ResponseSigner};
 use crate::{
-    BoundActivityTracker, PreferredLanguage, impl_from_error_for_route,
-    oauth2::generate_id_token,
+    BoundActivityTracker, IssuerUrlBuilder, PreferredLanguage, impl_from_error_for_route,
+    oauth2::{
+        acr, encrypt_id_token, generate_id_token, pairwise::subject_for_client, requested_claims,
+        user_attribute_claims,
+    },
     session::{SessionOrFallback, load_session_or_fallback},
 };
 
@@ -59,8 +71,17 @@ impl_from_error_for_route!(mas_policy::LoadError);
 impl_from_error_for_route!(mas_policy::EvaluationError);
 impl_from_error_for_route!(crate::session::SessionLoadError);
 impl_from_error_for_route!(crate::oauth2::IdTokenSignatureError);
+impl_from_error_for_route!(crate::oauth2::IdTokenEncryptionError);
 impl_from_error_for_route!(super::callback::IntoCallbackDestinationError);
 impl_from_error_for_route!(super::callback::CallbackDestinationError);
+impl_from_error_for_route!(rand::Error);
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct ConsentForm {
+    /// Whether the user ticked the box granting offline access to the client
+    #[serde(default)]
+    offline_access: Option<String>,
+}
 
 impl IntoResponse for RouteError {
     fn into_response(self) -> axum::response::Response {
@@ -84,7 +105,10 @@ pub(crate) async fn get(
     clock: BoxClock,
     PreferredLanguage(locale): PreferredLanguage,
     State(templates): State<Templates>,
-    State(url_builder): State<UrlBuilder>,
+    State(key_store): State<Keystore>,
+    IssuerUrlBuilder(url_builder): IssuerUrlBuilder,
+    State(site_config): State<SiteConfig>,
+    State(http_client): State<reqwest::Client>,
     mut policy: Policy,
     mut repo: BoxRepository,
     activity_tracker: BoundActivityTracker,
@@ -93,7 +117,13 @@ pub(crate) async fn get(
     Path(grant_id): Path<Ulid>,
 ) -> Result<Response, RouteError> {
     let (cookie_jar, maybe_session) = match load_session_or_fallback(
-        cookie_jar, &clock, &mut rng, &templates, &locale, &mut repo,
+        cookie_jar,
+        &clock,
+        &mut rng,
+        &templates,
+        &locale,
+        &mut repo,
+        &site_config,
     )
     .await?
     {
@@ -128,18 +158,50 @@ pub(crate) async fn get(
         return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
     };
 
+    // If the session isn't recent enough, or doesn't satisfy the ACR or the
+    // scopes required by the client, the user has to authenticate again or to
+    // step up their authentication
+    let last_authentication = repo
+        .browser_session()
+        .get_last_authentication(&session)
+        .await?;
+    let elevation = repo
+        .browser_session()
+        .get_active_elevation(&clock, &session)
+        .await?;
+    match acr::requirement(
+        &site_config,
+        clock.now(),
+        &grant,
+        last_authentication.as_ref(),
+        elevation.as_ref(),
+    ) {
+        acr::Requirement::Satisfied => {}
+        acr::Requirement::Login => {
+            let login = mas_router::Login::and_continue_grant(grant_id);
+            return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+        }
+        acr::Requirement::StepUp => {
+            let reauth = mas_router::Reauth::and_continue_grant(grant_id);
+            return Ok((cookie_jar, url_builder.redirect(&reauth)).into_response());
+        }
+    }
+
     activity_tracker
         .record_browser_session(&clock, &session)
         .await;
 
     let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
 
+    let emails = repo.user_email().all(&session.user).await?;
     let res = policy
         .evaluate_authorization_grant(mas_policy::AuthorizationGrantInput {
             user: Some(&session.user),
+            emails: emails.iter().map(|e| e.email.as_str()).collect(),
             client: &client,
             scope: &grant.scope,
             grant_type: mas_policy::GrantType::AuthorizationCode,
+            requested_claims: grant.requested_claims(),
             requester: mas_policy::Requester {
                 ip_address: activity_tracker.ip(),
                 user_agent,
@@ -157,7 +219,45 @@ pub(crate) async fn get(
         return Ok((cookie_jar, Html(content)).into_response());
     }
 
+    // Only ask for the scopes the user didn't already grant to the client,
+    // unless the client explicitly asked for the user to consent again
+    let remembered_scope = repo
+        .oauth2_consent()
+        .get_for_client(&client, &session.user)
+        .await?;
+    let everything_remembered = !remembered_scope.is_empty()
+        && grant
+            .scope
+            .iter()
+            .filter(|token| needs_consent(token))
+            .all(|token| remembered_scope.contains(token));
+    if everything_remembered && !grant.prompt_consent && grant.authorization_details.is_empty() {
+        let offline_access = remembered_scope.contains(&OFFLINE_ACCESS);
+        let response = complete(
+            &mut rng,
+            &clock,
+            repo,
+            &templates,
+            &locale,
+            &key_store,
+            &url_builder,
+            &site_config,
+            &http_client,
+            &activity_tracker,
+            &client,
+            &session,
+            grant,
+            last_authentication.as_ref(),
+            elevation.as_ref(),
+            offline_access,
+        )
+        .await?;
+
+        return Ok((cookie_jar, response).into_response());
+    }
+
     let ctx = ConsentContext::new(grant, client)
+        .with_remembered_scope(&remembered_scope)
         .with_session(session)
         .with_csrf(csrf_token.form_value())
         .with_language(locale);
@@ -183,14 +283,22 @@ pub(crate) async fn post(
     activity_tracker: BoundActivityTracker,
     user_agent: Option<TypedHeader<headers::UserAgent>>,
     cookie_jar: CookieJar,
-    State(url_builder): State<UrlBuilder>,
+    IssuerUrlBuilder(url_builder): IssuerUrlBuilder,
+    State(site_config): State<SiteConfig>,
+    State(http_client): State<reqwest::Client>,
     Path(grant_id): Path<Ulid>,
-    Form(form): Form<ProtectedForm<()>>,
+    Form(form): Form<ProtectedForm<ConsentForm>>,
 ) -> Result<Response, RouteError> {
-    cookie_jar.verify_form(&clock, form)?;
+    let form = cookie_jar.verify_form(&clock, form)?;
 
     let (cookie_jar, maybe_session) = match load_session_or_fallback(
-        cookie_jar, &clock, &mut rng, &templates, &locale, &mut repo,
+        cookie_jar,
+        &clock,
+        &mut rng,
+        &templates,
+        &locale,
+        &mut repo,
+        &site_config,
     )
     .await?
     {
@@ -211,7 +319,6 @@ pub(crate) async fn post(
         .lookup(grant_id)
         .await?
         .ok_or(RouteError::GrantNotFound)?;
-    let callback_destination = CallbackDestination::try_from(&grant)?;
 
     let Some(browser_session) = maybe_session else {
         let next = PostAuthAction::continue_grant(grant_id);
@@ -233,12 +340,41 @@ pub(crate) async fn post(
         return Err(RouteError::GrantNotPending(grant.id));
     }
 
+    let last_authentication = repo
+        .browser_session()
+        .get_last_authentication(&browser_session)
+        .await?;
+    let elevation = repo
+        .browser_session()
+        .get_active_elevation(&clock, &browser_session)
+        .await?;
+    match acr::requirement(
+        &site_config,
+        clock.now(),
+        &grant,
+        last_authentication.as_ref(),
+        elevation.as_ref(),
+    ) {
+        acr::Requirement::Satisfied => {}
+        acr::Requirement::Login => {
+            let login = mas_router::Login::and_continue_grant(grant_id);
+            return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+        }
+        acr::Requirement::StepUp => {
+            let reauth = mas_router::Reauth::and_continue_grant(grant_id);
+            return Ok((cookie_jar, url_builder.redirect(&reauth)).into_response());
+        }
+    }
+
+    let emails = repo.user_email().all(&browser_session.user).await?;
     let res = policy
         .evaluate_authorization_grant(mas_policy::AuthorizationGrantInput {
             user: Some(&browser_session.user),
+            emails: emails.iter().map(|e| e.email.as_str()).collect(),
             client: &client,
             scope: &grant.scope,
             grant_type: mas_policy::GrantType::AuthorizationCode,
+            requested_claims: grant.requested_claims(),
             requester: mas_policy::Requester {
                 ip_address: activity_tracker.ip(),
                 user_agent,
@@ -257,44 +393,164 @@ pub(crate) async fn post(
         return Ok((cookie_jar, Html(content)).into_response());
     }
 
-    // All good, let's start the session
-    let session = repo
-        .oauth2_session()
-        .add_from_browser_session(
+    // The `offline_access` scope is only granted if the user explicitly ticked
+    // the box on the consent screen
+    let offline_access = form.offline_access.as_deref() == Some("on");
+
+    let response = complete(
+        &mut rng,
+        &clock,
+        repo,
+        &templates,
+        &locale,
+        &key_store,
+        &url_builder,
+        &site_config,
+        &http_client,
+        &activity_tracker,
+        &client,
+        &browser_session,
+        grant,
+        last_authentication.as_ref(),
+        elevation.as_ref(),
+        offline_access,
+    )
+    .await?;
+
+    Ok((cookie_jar, response).into_response())
+}
+
+/// Whether a scope token has to be consented to by the user.
+///
+/// Device scopes are hidden on the consent screen, and are different for each
+/// login, so they are never remembered.
+fn needs_consent(token: &ScopeToken) -> bool {
+    Device::from_scope_token(token).is_none()
+}
+
+/// Start a session for an authorization grant the user consented to, and send
+/// them back to the client
+#[allow(clippy::too_many_arguments)]
+async fn complete(
+    mut rng: &mut BoxRng,
+    clock: &BoxClock,
+    mut repo: BoxRepository,
+    templates: &Templates,
+    locale: &DataLocale,
+    key_store: &Keystore,
+    url_builder: &UrlBuilder,
+    site_config: &SiteConfig,
+    http_client: &reqwest::Client,
+    activity_tracker: &BoundActivityTracker,
+    client: &Client,
+    browser_session: &BrowserSession,
+    grant: AuthorizationGrant,
+    last_authentication: Option<&Authentication>,
+    elevation: Option<&BrowserSessionElevation>,
+    offline_access: bool,
+) -> Result<Response, RouteError> {
+    let signer = ResponseSigner::new(&mut rng, clock, url_builder, key_store, client)?;
+    let callback_destination = CallbackDestination::try_new(
+        &grant.response_mode,
+        grant.redirect_uri.clone(),
+        grant.state.clone(),
+        signer,
+    )?;
+
+    let offline_access_granted = grant.scope.contains(&OFFLINE_ACCESS) && offline_access;
+    let scope: Scope = grant
+        .scope
+        .iter()
+        .filter(|token| **token != OFFLINE_ACCESS || offline_access_granted)
+        .cloned()
+        .collect();
+
+    // Remember the scope the user granted, so that they don't get asked again
+    let consented_scope: Scope = scope.iter().filter(|t| needs_consent(t)).cloned().collect();
+    repo.oauth2_consent()
+        .remember(
             &mut rng,
-            &clock,
-            &client,
-            &browser_session,
-            grant.scope.clone(),
+            clock,
+            client,
+            &browser_session.user,
+            &consented_scope,
         )
         .await?;
 
+    let session = repo
+        .oauth2_session()
+        .add_from_browser_session(&mut rng, clock, client, browser_session, scope)
+        .await?;
+
+    let session = if offline_access_granted {
+        repo.oauth2_session()
+            .record_offline_access(clock, session)
+            .await?
+    } else {
+        session
+    };
+
+    let session = if let Some(device_fingerprint) = grant.device_fingerprint.clone() {
+        repo.oauth2_session()
+            .record_device_fingerprint(session, device_fingerprint)
+            .await?
+    } else {
+        session
+    };
+
+    let session = if grant.authorization_details.is_empty() {
+        session
+    } else {
+        repo.oauth2_session()
+            .record_authorization_details(session, grant.authorization_details.clone())
+            .await?
+    };
+
+    // Remember the claims requested in the userinfo response, as the userinfo
+    // endpoint only knows about the session
+    let userinfo_claims = grant.userinfo_claims();
+    let session = if userinfo_claims.is_empty() {
+        session
+    } else {
+        repo.oauth2_session()
+            .record_userinfo_claims(session, userinfo_claims)
+            .await?
+    };
+
     let grant = repo
         .oauth2_authorization_grant()
-        .fulfill(&clock, &session, grant)
+        .fulfill(clock, &session, grant)
         .await?;
 
     let mut params = AuthorizationResponse::default();
 
     // Did they request an ID token?
     if grant.response_type_id_token {
-        // Fetch the last authentication
-        let last_authentication = repo
-            .browser_session()
-            .get_last_authentication(&browser_session)
-            .await?;
-
-        params.id_token = Some(generate_id_token(
+        let mut custom_claims =
+            user_attribute_claims(&mut repo, site_config, &browser_session.user).await?;
+        custom_claims.extend(
+            requested_claims(&mut repo, &browser_session.user, grant.id_token_claims()).await?,
+        );
+        let sub =
+            subject_for_client(&mut rng, clock, &mut repo, client, &browser_session.user).await?;
+
+        let id_token = generate_id_token(
             &mut rng,
-            &clock,
-            &url_builder,
-            &key_store,
-            &client,
+            clock,
+            url_builder,
+            key_store,
+            site_config,
+            client,
             Some(&grant),
-            &browser_session,
+            browser_session,
+            &sub,
             None,
-            last_authentication.as_ref(),
-        )?);
+            last_authentication,
+            elevation,
+            custom_claims,
+        )?;
+
+        params.id_token = Some(encrypt_id_token(&mut rng, http_client, client, id_token).await?);
     }
 
     // Did they request an auth code?
@@ -305,12 +561,8 @@ pub(crate) async fn post(
     repo.save().await?;
 
     activity_tracker
-        .record_oauth2_session(&clock, &session)
+        .record_oauth2_session(clock, &session)
         .await;
 
-    Ok((
-        cookie_jar,
-        callback_destination.go(&templates, &locale, params)?,
-    )
-        .into_response())
+    Ok(callback_destination.go(templates, locale, params)?)
 }
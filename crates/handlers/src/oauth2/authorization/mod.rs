@@ -4,20 +4,30 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::collections::BTreeMap;
+
 use axum::{
     extract::{Form, State},
     response::{IntoResponse, Response},
 };
 use hyper::StatusCode;
 use mas_axum_utils::{SessionInfoExt, cookies::CookieJar, record_error};
-use mas_data_model::{AuthorizationCode, Pkce};
-use mas_router::{PostAuthAction, UrlBuilder};
+use mas_data_model::{
+    AuthorizationCode, PUSHED_AUTHORIZATION_REQUEST_URI_PREFIX, Pkce, SiteConfig,
+    oauth2::is_valid_device_fingerprint,
+};
+use mas_keystore::Keystore;
+use mas_router::PostAuthAction;
 use mas_storage::{
     BoxClock, BoxRepository, BoxRng,
-    oauth2::{OAuth2AuthorizationGrantRepository, OAuth2ClientRepository},
+    oauth2::{
+        OAuth2AuthorizationGrantParams, OAuth2AuthorizationGrantRepository, OAuth2ClientRepository,
+        OAuth2PushedAuthorizationRequestRepository,
+    },
 };
 use mas_templates::Templates;
 use oauth2_types::{
+    authorization_details,
     errors::{ClientError, ClientErrorCode},
     pkce,
     requests::{AuthorizationRequest, GrantType, Prompt, ResponseMode},
@@ -26,9 +36,14 @@ use oauth2_types::{
 use rand::{Rng, distributions::Alphanumeric};
 use serde::Deserialize;
 use thiserror::Error;
+use ulid::Ulid;
 
-use self::callback::CallbackDestination;
-use crate::{BoundActivityTracker, PreferredLanguage, impl_from_error_for_route};
+use self::callback::{CallbackDestination, ResponseSigner};
+use super::{
+    acr,
+    request_object::{self, RequestObjectError},
+};
+use crate::{BoundActivityTracker, IssuerUrlBuilder, PreferredLanguage, impl_from_error_for_route};
 
 mod callback;
 pub(crate) mod consent;
@@ -41,6 +56,21 @@ pub enum RouteError {
     #[error("could not find client")]
     ClientNotFound,
 
+    #[error("invalid authorization request parameters")]
+    InvalidParameters(#[source] serde_urlencoded::de::Error),
+
+    #[error("unknown, expired or already used request_uri")]
+    InvalidRequestUri,
+
+    #[error("client {0} must use pushed authorization requests")]
+    PushedAuthorizationRequestRequired(Ulid),
+
+    #[error("invalid request object")]
+    InvalidRequestObject(#[from] RequestObjectError),
+
+    #[error("client {0} must use signed request objects")]
+    SignedRequestObjectRequired(Ulid),
+
     #[error("invalid response mode")]
     InvalidResponseMode,
 
@@ -62,6 +92,27 @@ impl IntoResponse for RouteError {
             RouteError::ClientNotFound => {
                 (StatusCode::BAD_REQUEST, "could not find client").into_response()
             }
+            RouteError::InvalidParameters(e) => {
+                (StatusCode::BAD_REQUEST, format!("Invalid parameters ({e})")).into_response()
+            }
+            RouteError::InvalidRequestUri => {
+                (StatusCode::BAD_REQUEST, "invalid request_uri").into_response()
+            }
+            RouteError::PushedAuthorizationRequestRequired(_) => (
+                StatusCode::BAD_REQUEST,
+                "this client must use pushed authorization requests",
+            )
+                .into_response(),
+            RouteError::InvalidRequestObject(e) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid request object ({e})"),
+            )
+                .into_response(),
+            RouteError::SignedRequestObjectRequired(_) => (
+                StatusCode::BAD_REQUEST,
+                "this client must use signed request objects",
+            )
+                .into_response(),
             RouteError::InvalidResponseMode => {
                 (StatusCode::BAD_REQUEST, "invalid response mode").into_response()
             }
@@ -84,6 +135,8 @@ impl_from_error_for_route!(mas_templates::TemplateError);
 impl_from_error_for_route!(self::callback::CallbackDestinationError);
 impl_from_error_for_route!(mas_policy::LoadError);
 impl_from_error_for_route!(mas_policy::EvaluationError);
+impl_from_error_for_route!(serde_urlencoded::ser::Error);
+impl_from_error_for_route!(rand::Error);
 
 #[derive(Deserialize)]
 pub(crate) struct Params {
@@ -92,6 +145,10 @@ pub(crate) struct Params {
 
     #[serde(flatten)]
     pkce: Option<pkce::AuthorizationRequest>,
+
+    /// A hashed, client-provided identifier of the device, stored on the
+    /// resulting session
+    device_fingerprint: Option<String>,
 }
 
 /// Given a list of response types and an optional user-defined response mode,
@@ -109,18 +166,25 @@ fn resolve_response_mode(
     if response_type.has_token() || response_type.has_id_token() {
         match suggested_response_mode {
             None => Ok(M::Fragment),
-            Some(M::Query) => Err(RouteError::InvalidResponseMode),
+            Some(M::Query | M::QueryJwt) => Err(RouteError::InvalidResponseMode),
+            // The "jwt" response mode is a shortcut for the default response mode
+            // with a JWT-secured response
+            Some(M::Jwt) => Ok(M::FragmentJwt),
             Some(mode) => Ok(mode),
         }
     } else {
         // In other cases, all response modes are allowed, defaulting to "query"
-        Ok(suggested_response_mode.unwrap_or(M::Query))
+        match suggested_response_mode {
+            None => Ok(M::Query),
+            Some(M::Jwt) => Ok(M::QueryJwt),
+            Some(mode) => Ok(mode),
+        }
     }
 }
 
 #[tracing::instrument(
     name = "handlers.oauth2.authorization.get",
-    fields(client.id = %params.auth.client_id),
+    fields(client.id = parameters.get("client_id").map(String::as_str)),
     skip_all,
 )]
 #[allow(clippy::too_many_lines)]
@@ -129,31 +193,75 @@ pub(crate) async fn get(
     clock: BoxClock,
     PreferredLanguage(locale): PreferredLanguage,
     State(templates): State<Templates>,
-    State(url_builder): State<UrlBuilder>,
+    IssuerUrlBuilder(url_builder): IssuerUrlBuilder,
+    State(key_store): State<Keystore>,
+    State(site_config): State<SiteConfig>,
+    State(http_client): State<reqwest::Client>,
     activity_tracker: BoundActivityTracker,
     mut repo: BoxRepository,
     cookie_jar: CookieJar,
-    Form(params): Form<Params>,
+    Form(mut parameters): Form<BTreeMap<String, String>>,
 ) -> Result<Response, RouteError> {
     // First, figure out what client it is
+    let client_id = parameters
+        .get("client_id")
+        .ok_or(RouteError::ClientNotFound)?;
     let client = repo
         .oauth2_client()
-        .find_by_client_id(&params.auth.client_id)
+        .find_by_client_id(client_id)
         .await?
         .ok_or(RouteError::ClientNotFound)?;
 
+    // If the client pushed the request parameters beforehand, load them from
+    // the pushed authorization request
+    let reference = parameters
+        .get("request_uri")
+        .and_then(|uri| uri.strip_prefix(PUSHED_AUTHORIZATION_REQUEST_URI_PREFIX));
+    if let Some(reference) = reference {
+        let pushed_request = repo
+            .oauth2_pushed_authorization_request()
+            .find_by_reference(reference)
+            .await?
+            .filter(|r| r.client_id == client.id && r.is_valid(clock.now()))
+            .ok_or(RouteError::InvalidRequestUri)?;
+
+        let pushed_request = repo
+            .oauth2_pushed_authorization_request()
+            .consume(&clock, pushed_request)
+            .await?;
+
+        // Parameters outside of the pushed request are ignored
+        parameters = pushed_request.parameters;
+        parameters.insert("client_id".to_owned(), client.client_id.clone());
+    } else if client.require_pushed_authorization_requests {
+        return Err(RouteError::PushedAuthorizationRequestRequired(client.id));
+    } else if let Some(request_object_parameters) =
+        request_object::load(&http_client, &clock, &url_builder, &client, &parameters).await?
+    {
+        // Parameters outside of the request object are ignored
+        parameters = request_object_parameters;
+    } else if client.require_signed_request_object {
+        return Err(RouteError::SignedRequestObjectRequired(client.id));
+    }
+
+    let encoded = serde_urlencoded::to_string(&parameters)?;
+    let params: Params =
+        serde_urlencoded::from_str(&encoded).map_err(RouteError::InvalidParameters)?;
+
     // And resolve the redirect_uri and response_mode
     let redirect_uri = client
-        .resolve_redirect_uri(&params.auth.redirect_uri)?
+        .resolve_redirect_uri(&params.auth.redirect_uri, site_config.oauth2_1_strict)?
         .clone();
     let response_type = params.auth.response_type;
     let response_mode = resolve_response_mode(&response_type, params.auth.response_mode)?;
 
     // Now we have a proper callback destination to go to on error
+    let signer = ResponseSigner::new(&mut rng, &clock, &url_builder, &key_store, &client)?;
     let callback_destination = CallbackDestination::try_new(
         &response_mode,
         redirect_uri.clone(),
         params.auth.state.clone(),
+        signer,
     )?;
 
     // Get the session info from the cookie
@@ -165,30 +273,24 @@ pub(crate) async fn get(
         let callback_destination = callback_destination.clone();
         let locale = locale.clone();
         async move {
-            let maybe_session = session_info.load_active_session(&mut repo).await?;
+            let maybe_session = session_info
+                .load_active_session(&mut repo, &clock, &site_config)
+                .await?;
             let prompt = params.auth.prompt.as_deref().unwrap_or_default();
 
-            // Check if the request/request_uri/registration params are used. If so, reply
-            // with the right error since we don't support them.
-            if params.auth.request.is_some() {
-                return Ok(callback_destination.go(
-                    &templates,
-                    &locale,
-                    ClientError::from(ClientErrorCode::RequestNotSupported),
-                )?);
-            }
-
-            if params.auth.request_uri.is_some() {
+            // Check if the client asked for a `token` response type, and bail out if it's
+            // the case, since we don't support them
+            if response_type.has_token() {
                 return Ok(callback_destination.go(
                     &templates,
                     &locale,
-                    ClientError::from(ClientErrorCode::RequestUriNotSupported),
+                    ClientError::from(ClientErrorCode::UnsupportedResponseType),
                 )?);
             }
 
-            // Check if the client asked for a `token` response type, and bail out if it's
-            // the case, since we don't support them
-            if response_type.has_token() {
+            // OAuth 2.1 removes the implicit grant, so in strict mode the ID token
+            // can only be obtained from the token endpoint
+            if site_config.oauth2_1_strict && response_type.has_id_token() {
                 return Ok(callback_destination.go(
                     &templates,
                     &locale,
@@ -206,6 +308,8 @@ pub(crate) async fn get(
                 )?);
             }
 
+            // Check if the registration param is used. If so, reply with the right error
+            // since we don't support it.
             if params.auth.registration.is_some() {
                 return Ok(callback_destination.go(
                     &templates,
@@ -214,6 +318,56 @@ pub(crate) async fn get(
                 )?);
             }
 
+            // The device fingerprint is an opaque hash, reject anything which doesn't
+            // look like one, to avoid storing raw device identifiers
+            if params
+                .device_fingerprint
+                .as_deref()
+                .is_some_and(|fingerprint| !is_valid_device_fingerprint(fingerprint))
+            {
+                return Ok(callback_destination.go(
+                    &templates,
+                    &locale,
+                    ClientError::from(ClientErrorCode::InvalidRequest)
+                        .with_description("Invalid device_fingerprint parameter".to_owned()),
+                )?);
+            }
+
+            // Only accept the authorization details types we were configured with
+            let authorization_details = match params.auth.authorization_details {
+                None => Vec::new(),
+                Some(details) => {
+                    if let Err(e) = authorization_details::validate(
+                        &details,
+                        &site_config.authorization_details_types,
+                    ) {
+                        return Ok(callback_destination.go(
+                            &templates,
+                            &locale,
+                            ClientError::from(ClientErrorCode::InvalidAuthorizationDetails)
+                                .with_description(e.to_string()),
+                        )?);
+                    }
+
+                    details
+                }
+            };
+
+            // Clients can only ask for tokens for the resources they were registered
+            // with
+            if params
+                .auth
+                .resource
+                .as_ref()
+                .is_some_and(|resource| !client.allowed_resources.contains(resource))
+            {
+                return Ok(callback_destination.go(
+                    &templates,
+                    &locale,
+                    ClientError::from(ClientErrorCode::InvalidTarget),
+                )?);
+            }
+
             // Fail early if prompt=none; we never let it go through
             if prompt.contains(&Prompt::None) {
                 return Ok(callback_destination.go(
@@ -223,6 +377,17 @@ pub(crate) async fn get(
                 )?);
             }
 
+            // prompt=create is only advertised when users can register, reject it
+            // otherwise instead of silently falling back to the login page
+            if prompt.contains(&Prompt::Create) && !site_config.password_registration_enabled {
+                return Ok(callback_destination.go(
+                    &templates,
+                    &locale,
+                    ClientError::from(ClientErrorCode::InvalidRequest)
+                        .with_description("Registration is not enabled".to_owned()),
+                )?);
+            }
+
             let code: Option<AuthorizationCode> = if response_type.has_code() {
                 // Check if it is allowed to use this grant type
                 if !client.grant_types.contains(&GrantType::AuthorizationCode) {
@@ -233,6 +398,16 @@ pub(crate) async fn get(
                     )?);
                 }
 
+                // OAuth 2.1 requires PKCE for all clients
+                if site_config.oauth2_1_strict && params.pkce.is_none() {
+                    return Ok(callback_destination.go(
+                        &templates,
+                        &locale,
+                        ClientError::from(ClientErrorCode::InvalidRequest)
+                            .with_description("code_challenge is required".to_owned()),
+                    )?);
+                }
+
                 // 32 random alphanumeric characters, about 190bit of entropy
                 let code: String = (&mut rng)
                     .sample_iter(&Alphanumeric)
@@ -260,6 +435,17 @@ pub(crate) async fn get(
                 None
             };
 
+            // The ACR values sent in the request take precedence over the ones the
+            // client registered with
+            let required_acr = match &params.auth.acr_values {
+                Some(acr_values) => acr::required_acr(&site_config, acr_values),
+                None => acr::required_acr(&site_config, &client.default_acr_values),
+            };
+
+            // Drop the scopes the client isn't allowed to request, if it is set up
+            // that way
+            let scope = client.filter_scope(params.auth.scope);
+
             let grant = repo
                 .oauth2_authorization_grant()
                 .add(
@@ -267,14 +453,24 @@ pub(crate) async fn get(
                     &clock,
                     &client,
                     redirect_uri.clone(),
-                    params.auth.scope,
-                    code,
-                    params.auth.state.clone(),
-                    params.auth.nonce,
+                    scope,
                     response_mode,
-                    response_type.has_id_token(),
-                    params.auth.login_hint,
-                    Some(locale.to_string()),
+                    OAuth2AuthorizationGrantParams {
+                        code,
+                        state: params.auth.state.clone(),
+                        nonce: params.auth.nonce,
+                        response_type_id_token: response_type.has_id_token(),
+                        login_hint: params.auth.login_hint,
+                        locale: Some(locale.to_string()),
+                        device_fingerprint: params.device_fingerprint,
+                        authorization_details,
+                        resource: params.auth.resource,
+                        required_acr,
+                        max_age: params.auth.max_age,
+                        prompt_login: prompt.contains(&Prompt::Login),
+                        prompt_consent: prompt.contains(&Prompt::Consent),
+                        claims: params.auth.claims,
+                    },
                 )
                 .await?;
             let continue_grant = PostAuthAction::continue_grant(grant.id);
@@ -299,7 +495,9 @@ pub(crate) async fn get(
                 }
 
                 Some(user_session) => {
-                    // TODO: better support for prompt=create when we have a session
+                    // prompt=create doesn't apply when the user is already signed in:
+                    // the registration flow only serves signed out users, so the
+                    // request continues with the current session
                     repo.save().await?;
 
                     activity_tracker
@@ -330,3 +528,710 @@ pub(crate) async fn get(
 
     Ok((cookie_jar, response).into_response())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use hyper::{Request, StatusCode};
+    use mas_axum_utils::SessionInfoExt as _;
+    use mas_data_model::SiteConfig;
+    use mas_iana::jose::JsonWebSignatureAlg;
+    use mas_jose::{
+        jwk::{JsonWebKey, JsonWebKeyPublicParameters, PublicJsonWebKeySet},
+        jwt::{JsonWebSignatureHeader, Jwt},
+    };
+    use mas_router::SimpleRoute;
+    use mas_storage::{Clock as _, RepositoryAccess};
+    use oauth2_types::registration::ClientRegistrationResponse;
+    use sqlx::PgPool;
+    use url::Url;
+
+    use crate::test_utils::{CookieHelper, RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_jwt_response_mode(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+
+        // prompt=none always fails, which sends an error response right away
+        let query = serde_urlencoded::to_string([
+            ("client_id", client_id.as_str()),
+            ("response_type", "code"),
+            ("response_mode", "jwt"),
+            ("redirect_uri", "https://example.com/callback"),
+            ("scope", "openid"),
+            ("state", "abcdef"),
+            ("prompt", "none"),
+        ])
+        .unwrap();
+        let request = Request::get(format!(
+            "{}?{query}",
+            mas_router::OAuth2AuthorizationEndpoint::PATH
+        ))
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        // The "jwt" response mode defaults to "query.jwt" for the code flow, so
+        // the only parameter is the signed response
+        let location = response.headers().get(hyper::header::LOCATION).unwrap();
+        let location = Url::parse(location.to_str().unwrap()).unwrap();
+        let params: HashMap<String, String> = location.query_pairs().into_owned().collect();
+        assert_eq!(params.len(), 1);
+
+        let jwt: Jwt<'_, HashMap<String, serde_json::Value>> =
+            Jwt::try_from(params["response"].as_str()).unwrap();
+        let claims = jwt.payload();
+        assert_eq!(claims["iss"], state.url_builder.oidc_issuer().as_str());
+        assert_eq!(claims["aud"], client_id.as_str());
+        assert_eq!(claims["state"], "abcdef");
+        assert_eq!(claims["error"], "login_required");
+
+        // The response mode "query.jwt" can't be used with the implicit flow
+        let query = serde_urlencoded::to_string([
+            ("client_id", client_id.as_str()),
+            ("response_type", "id_token"),
+            ("response_mode", "query.jwt"),
+            ("redirect_uri", "https://example.com/callback"),
+            ("scope", "openid"),
+        ])
+        .unwrap();
+        let request = Request::get(format!(
+            "{}?{query}",
+            mas_router::OAuth2AuthorizationEndpoint::PATH
+        ))
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_prompt_create(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool.clone()).await.unwrap();
+
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+
+        let query = serde_urlencoded::to_string([
+            ("client_id", client_id.as_str()),
+            ("response_type", "code"),
+            ("redirect_uri", "https://example.com/callback"),
+            ("scope", "openid"),
+            ("state", "abcdef"),
+            ("prompt", "create"),
+        ])
+        .unwrap();
+        let authorize = || {
+            Request::get(format!(
+                "{}?{query}",
+                mas_router::OAuth2AuthorizationEndpoint::PATH
+            ))
+            .empty()
+        };
+
+        // Without a session, the user is sent to the registration page, which
+        // continues the authorization grant afterwards
+        let response = state.request(authorize()).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        let location = response.headers().get(hyper::header::LOCATION).unwrap();
+        let location = Url::parse(location.to_str().unwrap()).unwrap();
+        assert_eq!(location.path(), "/register");
+        let params: HashMap<String, String> = location.query_pairs().into_owned().collect();
+        assert_eq!(params["kind"], "continue_authorization_grant");
+
+        // When registration is disabled, the request is rejected
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                password_registration_enabled: false,
+                ..crate::test_utils::test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let response = state.request(authorize()).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        let location = response.headers().get(hyper::header::LOCATION).unwrap();
+        let location = Url::parse(location.to_str().unwrap()).unwrap();
+        let params: HashMap<String, String> = location.query_pairs().into_owned().collect();
+        assert_eq!(params["state"], "abcdef");
+        assert_eq!(params["error"], "invalid_request");
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_oauth2_1_strict(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                oauth2_1_strict: true,
+                ..crate::test_utils::test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback", "http://127.0.0.1/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+
+        let authorize = |query: &[(&str, &str)]| {
+            let query = serde_urlencoded::to_string(query).unwrap();
+            Request::get(format!(
+                "{}?{query}",
+                mas_router::OAuth2AuthorizationEndpoint::PATH
+            ))
+            .empty()
+        };
+
+        let error = |response: &hyper::Response<String>| {
+            let location = response.headers().get(hyper::header::LOCATION).unwrap();
+            let location = Url::parse(location.to_str().unwrap()).unwrap();
+            // Errors of the implicit flow are sent in the fragment
+            let params: HashMap<String, String> = match location.fragment() {
+                Some(fragment) => url::form_urlencoded::parse(fragment.as_bytes())
+                    .into_owned()
+                    .collect(),
+                None => location.query_pairs().into_owned().collect(),
+            };
+            params.get("error").cloned()
+        };
+
+        // PKCE is required
+        let request = authorize(&[
+            ("client_id", client_id.as_str()),
+            ("response_type", "code"),
+            ("redirect_uri", "https://example.com/callback"),
+            ("scope", "openid"),
+        ]);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        assert_eq!(error(&response).as_deref(), Some("invalid_request"));
+
+        // The implicit grant is not available
+        let request = authorize(&[
+            ("client_id", client_id.as_str()),
+            ("response_type", "code id_token"),
+            ("redirect_uri", "https://example.com/callback"),
+            ("scope", "openid"),
+            ("nonce", "abcdef"),
+            (
+                "code_challenge",
+                "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM",
+            ),
+            ("code_challenge_method", "S256"),
+        ]);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        assert_eq!(
+            error(&response).as_deref(),
+            Some("unsupported_response_type")
+        );
+
+        // Loopback redirect URIs must match exactly, including the port
+        let request = authorize(&[
+            ("client_id", client_id.as_str()),
+            ("response_type", "code"),
+            ("redirect_uri", "http://127.0.0.1:8080/callback"),
+            ("scope", "openid"),
+            (
+                "code_challenge",
+                "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM",
+            ),
+            ("code_challenge_method", "S256"),
+        ]);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // With all of that, the request goes through to the login page
+        let request = authorize(&[
+            ("client_id", client_id.as_str()),
+            ("response_type", "code"),
+            ("redirect_uri", "http://127.0.0.1/callback"),
+            ("scope", "openid"),
+            (
+                "code_challenge",
+                "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM",
+            ),
+            ("code_challenge_method", "S256"),
+        ]);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        assert_eq!(error(&response), None);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_signed_request_object(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let mut rng = state.rng();
+
+        // Generate a key for the client to sign its request objects
+        let key = mas_keystore::PrivateKey::generate_ec_p256(&mut rng);
+        let public_key = JsonWebKeyPublicParameters::from(&key);
+        let signer = key
+            .signing_key_for_alg(&JsonWebSignatureAlg::Es256)
+            .unwrap();
+        let jwks = PublicJsonWebKeySet::new(vec![JsonWebKey::new(public_key)]);
+
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "jwks": jwks,
+                "request_object_signing_alg": "ES256",
+                "require_signed_request_object": true,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+
+        let authorize = |query: &[(&str, &str)]| {
+            let query = serde_urlencoded::to_string(query).unwrap();
+            Request::get(format!(
+                "{}?{query}",
+                mas_router::OAuth2AuthorizationEndpoint::PATH
+            ))
+            .empty()
+        };
+
+        // The client can't send plain authorization requests
+        let request = authorize(&[
+            ("client_id", client_id.as_str()),
+            ("response_type", "code"),
+            ("redirect_uri", "https://example.com/callback"),
+            ("scope", "openid"),
+            ("prompt", "none"),
+        ]);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        let mut sign = |audience: &str| {
+            let header = JsonWebSignatureHeader::new(JsonWebSignatureAlg::Es256);
+            let claims = serde_json::json!({
+                "iss": client_id,
+                "aud": audience,
+                "exp": (state.clock.now() + chrono::Duration::microseconds(60 * 1000 * 1000))
+                    .timestamp(),
+                "client_id": client_id,
+                "response_type": "code",
+                "redirect_uri": "https://example.com/callback",
+                "scope": "openid",
+                "state": "signed",
+                "prompt": "none",
+            });
+            Jwt::sign_with_rng(&mut rng, header, claims, &signer)
+                .unwrap()
+                .into_string()
+        };
+
+        // prompt=none always fails, which sends an error response right away.
+        // Only the parameters of the request object are used.
+        let request_object = sign(state.url_builder.oidc_issuer().as_str());
+        let request = authorize(&[
+            ("client_id", client_id.as_str()),
+            ("state", "unsigned"),
+            ("request", request_object.as_str()),
+        ]);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        let location = response.headers().get(hyper::header::LOCATION).unwrap();
+        let location = Url::parse(location.to_str().unwrap()).unwrap();
+        let params: HashMap<String, String> = location.query_pairs().into_owned().collect();
+        assert_eq!(params["state"], "signed");
+        assert_eq!(params["error"], "login_required");
+
+        // Request objects made for another server are rejected
+        let request_object = sign("https://other.example.com/");
+        let request = authorize(&[
+            ("client_id", client_id.as_str()),
+            ("request", request_object.as_str()),
+        ]);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // Request objects signed with another key are rejected
+        let other_key = mas_keystore::PrivateKey::generate_ec_p256(&mut rng);
+        let other_signer = other_key
+            .signing_key_for_alg(&JsonWebSignatureAlg::Es256)
+            .unwrap();
+        let header = JsonWebSignatureHeader::new(JsonWebSignatureAlg::Es256);
+        let claims = serde_json::json!({
+            "iss": client_id,
+            "aud": state.url_builder.oidc_issuer().as_str(),
+            "response_type": "code",
+            "redirect_uri": "https://example.com/callback",
+        });
+        let request_object = Jwt::sign_with_rng(&mut rng, header, claims, &other_signer)
+            .unwrap()
+            .into_string();
+        let request = authorize(&[
+            ("client_id", client_id.as_str()),
+            ("request", request_object.as_str()),
+        ]);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_max_age(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let cookies = CookieHelper::new();
+        let mut rng = state.rng();
+
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+
+        // Start with a browser session where the user logged in with a password
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let password = repo
+            .user_password()
+            .add(&mut rng, &state.clock, &user, 1, "hash".to_owned(), None)
+            .await
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+        repo.browser_session()
+            .authenticate_with_password(&mut rng, &state.clock, &browser_session, &password)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        cookies.import(state.cookie_jar().set_session(&browser_session));
+
+        state.clock.advance(chrono::Duration::minutes(10));
+
+        let authorize = |max_age: &str| {
+            let query = serde_urlencoded::to_string([
+                ("client_id", client_id.as_str()),
+                ("response_type", "code"),
+                ("redirect_uri", "https://example.com/callback"),
+                ("scope", "openid"),
+                ("max_age", max_age),
+            ])
+            .unwrap();
+            cookies.with_cookies(
+                Request::get(format!(
+                    "{}?{query}",
+                    mas_router::OAuth2AuthorizationEndpoint::PATH
+                ))
+                .empty(),
+            )
+        };
+
+        let follow = |response: &hyper::Response<String>| {
+            let location = response.headers().get(hyper::header::LOCATION).unwrap();
+            let location = Url::parse(location.to_str().unwrap()).unwrap();
+            let path_and_query = match location.query() {
+                Some(query) => format!("{}?{query}", location.path()),
+                None => location.path().to_owned(),
+            };
+            cookies.with_cookies(Request::get(path_and_query).empty())
+        };
+
+        // The authentication is recent enough, so the consent screen is shown
+        let response = state.request(authorize("3600")).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        let response = state.request(follow(&response)).await;
+        response.assert_status(StatusCode::OK);
+
+        // With a shorter max_age, the user is sent back to the login page
+        let response = state.request(authorize("60")).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        let response = state.request(follow(&response)).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        let location = response.headers().get(hyper::header::LOCATION).unwrap();
+        let location = Url::parse(location.to_str().unwrap()).unwrap();
+        assert_eq!(location.path(), "/login");
+
+        // Which asks for the credentials again instead of going back to the
+        // consent screen
+        let response = state.request(follow(&response)).await;
+        response.assert_status(StatusCode::OK);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_step_up_scope(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                step_up_scopes: vec!["urn:mas:graphql:*".to_owned()],
+                ..crate::test_utils::test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+        let mut rng = state.rng();
+
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+
+        // Start with a browser session where the user logged in with a password
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let (version, hash) = state
+            .password_manager
+            .hash(&mut rng, zeroize::Zeroizing::new("hunter2".to_owned()))
+            .await
+            .unwrap();
+        let password = repo
+            .user_password()
+            .add(&mut rng, &state.clock, &user, version, hash, None)
+            .await
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+        repo.browser_session()
+            .authenticate_with_password(&mut rng, &state.clock, &browser_session, &password)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        cookies.import(state.cookie_jar().set_session(&browser_session));
+
+        state.clock.advance(chrono::Duration::minutes(10));
+
+        let authorize = |scope: &str| {
+            let query = serde_urlencoded::to_string([
+                ("client_id", client_id.as_str()),
+                ("response_type", "code"),
+                ("redirect_uri", "https://example.com/callback"),
+                ("scope", scope),
+            ])
+            .unwrap();
+            cookies.with_cookies(
+                Request::get(format!(
+                    "{}?{query}",
+                    mas_router::OAuth2AuthorizationEndpoint::PATH
+                ))
+                .empty(),
+            )
+        };
+
+        let location = |response: &hyper::Response<String>| {
+            let location = response.headers().get(hyper::header::LOCATION).unwrap();
+            let location = Url::parse(location.to_str().unwrap()).unwrap();
+            match location.query() {
+                Some(query) => format!("{}?{query}", location.path()),
+                None => location.path().to_owned(),
+            }
+        };
+
+        // Scopes which don't need a step-up go straight to the consent screen
+        let response = state.request(authorize("openid")).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        let request = cookies.with_cookies(Request::get(location(&response)).empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        // The login is too old for a step-up scope, so the user has to confirm
+        // their password
+        let response = state.request(authorize("openid urn:mas:graphql:*")).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        let consent = location(&response);
+        let request = cookies.with_cookies(Request::get(&consent).empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        let reauth = location(&response);
+        assert!(reauth.starts_with("/reauth?"));
+
+        let request = cookies.with_cookies(Request::get(&reauth).empty());
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        // A wrong password doesn't elevate the session
+        let request = Request::post(&reauth).form(serde_json::json!({
+            "csrf": csrf_token,
+            "password": "wrong",
+        }));
+        let response = state.request(cookies.with_cookies(request)).await;
+        response.assert_status(StatusCode::OK);
+
+        // Once they did, they get back to the consent screen
+        let request = Request::post(&reauth).form(serde_json::json!({
+            "csrf": csrf_token,
+            "password": "hunter2",
+        }));
+        let response = state.request(cookies.with_cookies(request)).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        assert_eq!(location(&response), consent);
+        let request = cookies.with_cookies(Request::get(&consent).empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        // The elevation decays after a while
+        state.clock.advance(chrono::Duration::minutes(10));
+        let request = cookies.with_cookies(Request::get(&consent).empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        assert!(location(&response).starts_with("/reauth?"));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_claims_parameter(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let cookies = CookieHelper::new();
+        let mut rng = state.rng();
+
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        cookies.import(state.cookie_jar().set_session(&browser_session));
+
+        let claims = serde_json::json!({
+            "userinfo": {"email": null, "email_verified": {"essential": true}},
+            "id_token": {"preferred_username": null},
+        })
+        .to_string();
+        let query = serde_urlencoded::to_string([
+            ("client_id", client_id.as_str()),
+            ("response_type", "code"),
+            ("redirect_uri", "https://example.com/callback"),
+            ("scope", "openid"),
+            ("claims", claims.as_str()),
+        ])
+        .unwrap();
+        let request = Request::get(format!(
+            "{}?{query}",
+            mas_router::OAuth2AuthorizationEndpoint::PATH
+        ))
+        .empty();
+        let response = state.request(cookies.with_cookies(request)).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        // The requested claims are saved on the grant
+        let location = response.headers().get(hyper::header::LOCATION).unwrap();
+        let location = Url::parse(location.to_str().unwrap()).unwrap();
+        let grant_id = location.path().rsplit('/').next().unwrap().parse().unwrap();
+        let mut repo = state.repository().await.unwrap();
+        let grant = repo
+            .oauth2_authorization_grant()
+            .lookup(grant_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(grant.userinfo_claims(), ["email", "email_verified"]);
+        assert_eq!(
+            grant.id_token_claims().collect::<Vec<_>>(),
+            ["preferred_username"]
+        );
+        repo.cancel().await.unwrap();
+
+        // And listed on the consent screen
+        let request = Request::get(location.path()).empty();
+        let response = state.request(cookies.with_cookies(request)).await;
+        response.assert_status(StatusCode::OK);
+        assert!(
+            response
+                .body()
+                .contains("email, email_verified, preferred_username")
+        );
+    }
+}
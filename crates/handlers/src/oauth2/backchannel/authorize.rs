@@ -0,0 +1,457 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::sync::Arc;
+
+use axum::{Json, extract::State, response::IntoResponse};
+use axum_extra::typed_header::TypedHeader;
+use chrono::Duration;
+use headers::{CacheControl, Pragma};
+use hyper::StatusCode;
+use mas_axum_utils::{
+    ClientJwksCache,
+    client_authorization::{ClientAuthorization, CredentialsVerificationError},
+    record_error,
+};
+use mas_data_model::oauth2::LoginHint;
+use mas_keystore::Encrypter;
+use mas_matrix::HomeserverConnection;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng,
+    oauth2::OAuth2BackchannelAuthenticationGrantParams,
+    queue::{QueueJobRepositoryExt as _, SendBackchannelAuthenticationEmailJob},
+};
+use oauth2_types::{
+    errors::{ClientError, ClientErrorCode},
+    requests::{
+        BackchannelAuthenticationRequest, BackchannelAuthenticationResponse,
+        DEFAULT_BACKCHANNEL_AUTHENTICATION_INTERVAL, GrantType,
+    },
+    scope::OPENID,
+};
+use rand::distributions::{Alphanumeric, DistString};
+use thiserror::Error;
+use ulid::Ulid;
+
+use crate::impl_from_error_for_route;
+
+/// How long a backchannel authentication request is valid for if the client
+/// doesn't ask for a specific expiry
+const DEFAULT_EXPIRES_IN: Duration = Duration::microseconds(5 * 60 * 1000 * 1000);
+
+/// The maximum lifetime a client can ask for with `requested_expiry`
+const MAX_EXPIRES_IN: Duration = Duration::microseconds(10 * 60 * 1000 * 1000);
+
+/// The maximum length of the `binding_message`, as it is displayed to the user
+const MAX_BINDING_MESSAGE_LENGTH: usize = 64;
+
+#[derive(Debug, Error)]
+pub(crate) enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("client not found")]
+    ClientNotFound,
+
+    #[error("client {0} is not allowed to use backchannel authentication")]
+    ClientNotAllowed(Ulid),
+
+    #[error("invalid client credentials for client {client_id}")]
+    InvalidClientCredentials {
+        client_id: Ulid,
+        #[source]
+        source: CredentialsVerificationError,
+    },
+
+    #[error("could not verify client credentials for client {client_id}")]
+    ClientCredentialsVerification {
+        client_id: Ulid,
+        #[source]
+        source: CredentialsVerificationError,
+    },
+
+    #[error("missing request parameters")]
+    MissingParameters,
+
+    #[error("the openid scope is required")]
+    MissingOpenIdScope,
+
+    #[error("missing login_hint parameter")]
+    MissingLoginHint,
+
+    #[error("missing client_notification_token parameter")]
+    MissingClientNotificationToken,
+
+    #[error("binding_message is too long")]
+    BindingMessageTooLong,
+
+    #[error("could not find the user identified by the login_hint")]
+    UnknownUser,
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+
+        let response = match self {
+            Self::Internal(_) | Self::ClientCredentialsVerification { .. } => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ClientError::from(ClientErrorCode::ServerError)),
+            ),
+            Self::ClientNotFound | Self::InvalidClientCredentials { .. } => (
+                StatusCode::UNAUTHORIZED,
+                Json(ClientError::from(ClientErrorCode::InvalidClient)),
+            ),
+            Self::ClientNotAllowed(_) => (
+                StatusCode::BAD_REQUEST,
+                Json(ClientError::from(ClientErrorCode::UnauthorizedClient)),
+            ),
+            Self::MissingParameters => (
+                StatusCode::BAD_REQUEST,
+                Json(ClientError::from(ClientErrorCode::InvalidRequest)),
+            ),
+            Self::MissingOpenIdScope => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidScope)
+                        .with_description("The openid scope is required".to_owned()),
+                ),
+            ),
+            Self::MissingLoginHint => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidRequest)
+                        .with_description("Missing login_hint parameter".to_owned()),
+                ),
+            ),
+            Self::MissingClientNotificationToken => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidRequest)
+                        .with_description("Missing client_notification_token parameter".to_owned()),
+                ),
+            ),
+            Self::BindingMessageTooLong => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidRequest)
+                        .with_description("The binding_message is too long".to_owned()),
+                ),
+            ),
+            Self::UnknownUser => (
+                StatusCode::BAD_REQUEST,
+                Json(ClientError::from(ClientErrorCode::UnknownUserId)),
+            ),
+        };
+
+        (sentry_event_id, response).into_response()
+    }
+}
+
+#[tracing::instrument(
+    name = "handlers.oauth2.backchannel.authorize.post",
+    fields(client.id = client_authorization.client_id()),
+    skip_all,
+)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    mut repo: BoxRepository,
+    State(homeserver): State<Arc<dyn HomeserverConnection>>,
+    State(http_client): State<reqwest::Client>,
+    State(jwks_cache): State<ClientJwksCache>,
+    State(encrypter): State<Encrypter>,
+    client_authorization: ClientAuthorization<BackchannelAuthenticationRequest>,
+) -> Result<impl IntoResponse, RouteError> {
+    let client = client_authorization
+        .credentials
+        .fetch(&mut repo)
+        .await?
+        .ok_or(RouteError::ClientNotFound)?;
+
+    // Reuse the token endpoint auth method to verify the client
+    let method = client
+        .token_endpoint_auth_method
+        .as_ref()
+        .ok_or(RouteError::ClientNotAllowed(client.id))?;
+
+    client_authorization
+        .credentials
+        .verify(
+            &http_client,
+            &jwks_cache,
+            &encrypter,
+            &clock,
+            method,
+            &client,
+        )
+        .await
+        .map_err(|err| {
+            if err.is_internal() {
+                RouteError::ClientCredentialsVerification {
+                    client_id: client.id,
+                    source: err,
+                }
+            } else {
+                RouteError::InvalidClientCredentials {
+                    client_id: client.id,
+                    source: err,
+                }
+            }
+        })?;
+
+    if !client
+        .grant_types
+        .contains(&GrantType::ClientInitiatedBackchannelAuthentication)
+    {
+        return Err(RouteError::ClientNotAllowed(client.id));
+    }
+
+    let request = client_authorization
+        .form
+        .ok_or(RouteError::MissingParameters)?;
+
+    // Drop the scopes the client isn't allowed to request, if it is set up that
+    // way
+    let scope = client.filter_scope(request.scope);
+
+    if !scope.contains(&OPENID) {
+        return Err(RouteError::MissingOpenIdScope);
+    }
+
+    // Clients which registered a notification endpoint use the ping mode, in
+    // which case they must give us a token to authenticate the notification
+    let ping_mode = client.backchannel_client_notification_endpoint.is_some();
+    if ping_mode && request.client_notification_token.is_none() {
+        return Err(RouteError::MissingClientNotificationToken);
+    }
+
+    if request
+        .binding_message
+        .as_ref()
+        .is_some_and(|message| message.chars().count() > MAX_BINDING_MESSAGE_LENGTH)
+    {
+        return Err(RouteError::BindingMessageTooLong);
+    }
+
+    let login_hint = request
+        .login_hint
+        .as_deref()
+        .ok_or(RouteError::MissingLoginHint)?;
+
+    let LoginHint::MXID(mxid) = LoginHint::parse(login_hint, homeserver.homeserver()) else {
+        return Err(RouteError::UnknownUser);
+    };
+
+    let user = repo
+        .user()
+        .find_by_username(mxid.localpart())
+        .await?
+        .filter(mas_data_model::User::is_valid)
+        .ok_or(RouteError::UnknownUser)?;
+
+    let expires_in = request
+        .requested_expiry
+        .map_or(DEFAULT_EXPIRES_IN, |seconds| {
+            Duration::seconds(i64::from(seconds))
+        })
+        .min(MAX_EXPIRES_IN);
+
+    let auth_req_id = Alphanumeric.sample_string(&mut rng, 32);
+
+    let grant = repo
+        .oauth2_backchannel_authentication_grant()
+        .add(
+            &mut rng,
+            &clock,
+            OAuth2BackchannelAuthenticationGrantParams {
+                client: &client,
+                user: &user,
+                scope,
+                auth_req_id,
+                binding_message: request.binding_message,
+                client_notification_token: request.client_notification_token,
+                expires_in,
+            },
+        )
+        .await?;
+
+    // Ask the user to approve the request. The request comes from the client, so
+    // we don't know which language the user prefers
+    repo.queue_job()
+        .schedule_job(
+            &mut rng,
+            &clock,
+            SendBackchannelAuthenticationEmailJob::new(&grant, "en".to_owned()),
+        )
+        .await?;
+
+    repo.save().await?;
+
+    let response = BackchannelAuthenticationResponse {
+        auth_req_id: grant.auth_req_id,
+        expires_in,
+        // Only clients in the poll mode need to know how often they can poll
+        interval: (!ping_mode).then_some(DEFAULT_BACKCHANNEL_AUTHENTICATION_INTERVAL),
+    };
+
+    Ok((
+        StatusCode::OK,
+        TypedHeader(CacheControl::new().with_no_store()),
+        TypedHeader(Pragma::no_cache()),
+        Json(response),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use mas_router::SimpleRoute;
+    use oauth2_types::{
+        errors::{ClientError, ClientErrorCode},
+        registration::ClientRegistrationResponse,
+        requests::{AccessTokenResponse, BackchannelAuthenticationResponse},
+    };
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_backchannel_authentication(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Provision a client
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "token_endpoint_auth_method": "none",
+                "grant_types": ["urn:openid:params:grant-type:ciba"],
+                "response_types": [],
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+
+        // Asking for an unknown user should fail
+        let request = Request::post(mas_router::OAuth2BackchannelAuthenticationEndpoint::PATH)
+            .form(serde_json::json!({
+                "client_id": client_id,
+                "scope": "openid",
+                "login_hint": "mxid:@alice:example.com",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::UnknownUserId);
+
+        // Provision the user and a browser session for them
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut state.rng(), &state.clock, &user, None)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        // The openid scope is required
+        let request = Request::post(mas_router::OAuth2BackchannelAuthenticationEndpoint::PATH)
+            .form(serde_json::json!({
+                "client_id": client_id,
+                "scope": "urn:matrix:org.matrix.msc2967.client:api:*",
+                "login_hint": "mxid:@alice:example.com",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidScope);
+
+        // Start the backchannel authentication
+        let request = Request::post(mas_router::OAuth2BackchannelAuthenticationEndpoint::PATH)
+            .form(serde_json::json!({
+                "client_id": client_id,
+                "scope": "openid",
+                "login_hint": "mxid:@alice:example.com",
+                "binding_message": "W4SCT",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        let response: BackchannelAuthenticationResponse = response.json();
+        // The client doesn't have a notification endpoint, so it has to poll
+        assert!(response.interval.is_some());
+        let auth_req_id = response.auth_req_id;
+
+        // Poll the token endpoint, it should be pending
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "urn:openid:params:grant-type:ciba",
+                "auth_req_id": auth_req_id,
+                "client_id": client_id,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::FORBIDDEN);
+
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::AuthorizationPending);
+
+        // Approve the request. This goes through the consent page, which is hard to
+        // drive with just HTTP requests, so we use the repository directly
+        let mut repo = state.repository().await.unwrap();
+        let grant = repo
+            .oauth2_backchannel_authentication_grant()
+            .find_by_auth_req_id(&auth_req_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(grant.user_id, user.id);
+        assert_eq!(grant.binding_message.as_deref(), Some("W4SCT"));
+
+        repo.oauth2_backchannel_authentication_grant()
+            .fulfill(&state.clock, grant, &browser_session)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        // Now the token endpoint should give us the tokens
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "urn:openid:params:grant-type:ciba",
+                "auth_req_id": auth_req_id,
+                "client_id": client_id,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        let response: AccessTokenResponse = response.json();
+        assert!(state.is_access_token_valid(&response.access_token).await);
+        assert!(response.id_token.is_some());
+
+        // It can't be exchanged twice
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "urn:openid:params:grant-type:ciba",
+                "auth_req_id": auth_req_id,
+                "client_id": client_id,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidGrant);
+    }
+}
@@ -0,0 +1,11 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Handlers for [Client-Initiated Backchannel Authentication]
+//!
+//! [Client-Initiated Backchannel Authentication]: https://openid.net/specs/openid-client-initiated-backchannel-authentication-core-1_0.html
+
+pub mod authorize;
+pub mod consent;
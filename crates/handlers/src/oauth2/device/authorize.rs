@@ -10,22 +10,44 @@ use chrono::Duration;
 use headers::{CacheControl, Pragma};
 use hyper::StatusCode;
 use mas_axum_utils::{
+    ClientJwksCache,
     client_authorization::{ClientAuthorization, CredentialsVerificationError},
     record_error,
 };
+use mas_data_model::{SiteConfig, oauth2::is_valid_device_fingerprint};
 use mas_keystore::Encrypter;
-use mas_router::UrlBuilder;
 use mas_storage::{BoxClock, BoxRepository, BoxRng, oauth2::OAuth2DeviceCodeGrantParams};
 use oauth2_types::{
     errors::{ClientError, ClientErrorCode},
     requests::{DeviceAuthorizationRequest, DeviceAuthorizationResponse, GrantType},
     scope::ScopeToken,
 };
-use rand::distributions::{Alphanumeric, DistString};
+use rand::{
+    Rng,
+    distributions::{Alphanumeric, DistString},
+    seq::SliceRandom,
+};
+use serde::Deserialize;
 use thiserror::Error;
 use ulid::Ulid;
 
-use crate::{BoundActivityTracker, impl_from_error_for_route};
+use crate::{BoundActivityTracker, IssuerUrlBuilder, impl_from_error_for_route};
+
+/// How long device codes are valid for, unless the client overrides it
+pub(crate) const DEFAULT_DEVICE_CODE_TTL: Duration = Duration::microseconds(20 * 60 * 1000 * 1000);
+
+/// How long clients have to wait between two polling requests, unless they
+/// got told to slow down
+const DEFAULT_DEVICE_CODE_INTERVAL: Duration = Duration::microseconds(5 * 1000 * 1000);
+
+/// Generate a user code of the given length out of the characters of the
+/// given alphabet
+fn generate_user_code(rng: &mut impl Rng, alphabet: &str, length: usize) -> String {
+    let alphabet: Vec<char> = alphabet.chars().collect();
+    (0..length)
+        .filter_map(|_| alphabet.choose(rng).copied())
+        .collect()
+}
 
 #[derive(Debug, Error)]
 pub(crate) enum RouteError {
@@ -38,6 +60,9 @@ pub(crate) enum RouteError {
     #[error("client {0} is not allowed to use the device code grant")]
     ClientNotAllowed(Ulid),
 
+    #[error("invalid device fingerprint")]
+    InvalidDeviceFingerprint,
+
     #[error("invalid client credentials for client {client_id}")]
     InvalidClientCredentials {
         client_id: Ulid,
@@ -55,6 +80,16 @@ pub(crate) enum RouteError {
 
 impl_from_error_for_route!(mas_storage::RepositoryError);
 
+#[derive(Deserialize)]
+pub(crate) struct Params {
+    #[serde(flatten)]
+    request: DeviceAuthorizationRequest,
+
+    /// A hashed, client-provided identifier of the device, stored on the
+    /// resulting session
+    device_fingerprint: Option<String>,
+}
+
 impl IntoResponse for RouteError {
     fn into_response(self) -> axum::response::Response {
         let sentry_event_id = record_error!(self, Self::Internal(_));
@@ -72,6 +107,13 @@ impl IntoResponse for RouteError {
                 StatusCode::UNAUTHORIZED,
                 Json(ClientError::from(ClientErrorCode::UnauthorizedClient)),
             ),
+            Self::InvalidDeviceFingerprint => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidRequest)
+                        .with_description("Invalid device_fingerprint parameter".to_owned()),
+                ),
+            ),
         };
 
         (sentry_event_id, response).into_response()
@@ -89,10 +131,12 @@ pub(crate) async fn post(
     mut repo: BoxRepository,
     user_agent: Option<TypedHeader<headers::UserAgent>>,
     activity_tracker: BoundActivityTracker,
-    State(url_builder): State<UrlBuilder>,
+    IssuerUrlBuilder(url_builder): IssuerUrlBuilder,
     State(http_client): State<reqwest::Client>,
+    State(jwks_cache): State<ClientJwksCache>,
     State(encrypter): State<Encrypter>,
-    client_authorization: ClientAuthorization<DeviceAuthorizationRequest>,
+    State(site_config): State<SiteConfig>,
+    client_authorization: ClientAuthorization<Params>,
 ) -> Result<impl IntoResponse, RouteError> {
     let client = client_authorization
         .credentials
@@ -108,7 +152,14 @@ pub(crate) async fn post(
 
     client_authorization
         .credentials
-        .verify(&http_client, &encrypter, method, &client)
+        .verify(
+            &http_client,
+            &jwks_cache,
+            &encrypter,
+            &clock,
+            method,
+            &client,
+        )
         .await
         .map_err(|err| {
             if err.is_internal() {
@@ -128,19 +179,36 @@ pub(crate) async fn post(
         return Err(RouteError::ClientNotAllowed(client.id));
     }
 
-    let scope = client_authorization
+    let (scope, device_fingerprint) = client_authorization
         .form
-        .and_then(|f| f.scope)
-        // XXX: Is this really how we do empty scopes?
-        .unwrap_or(std::iter::empty::<ScopeToken>().collect());
+        .map(|f| (f.request.scope, f.device_fingerprint))
+        .unwrap_or_default();
 
-    let expires_in = Duration::microseconds(20 * 60 * 1000 * 1000);
+    // XXX: Is this really how we do empty scopes?
+    let scope = scope.unwrap_or(std::iter::empty::<ScopeToken>().collect());
+
+    // Drop the scopes the client isn't allowed to request, if it is set up that
+    // way
+    let scope = client.filter_scope(scope);
+
+    if device_fingerprint
+        .as_deref()
+        .is_some_and(|fingerprint| !is_valid_device_fingerprint(fingerprint))
+    {
+        return Err(RouteError::InvalidDeviceFingerprint);
+    }
+
+    let expires_in = client.device_code_ttl.unwrap_or(DEFAULT_DEVICE_CODE_TTL);
 
     let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
     let ip_address = activity_tracker.ip();
 
     let device_code = Alphanumeric.sample_string(&mut rng, 32);
-    let user_code = Alphanumeric.sample_string(&mut rng, 6).to_uppercase();
+    let user_code = generate_user_code(
+        &mut rng,
+        &site_config.device_code_user_code_alphabet,
+        site_config.device_code_user_code_length,
+    );
 
     let device_code = repo
         .oauth2_device_code_grant()
@@ -153,8 +221,10 @@ pub(crate) async fn post(
                 device_code,
                 user_code,
                 expires_in,
+                interval: DEFAULT_DEVICE_CODE_INTERVAL,
                 user_agent,
                 ip_address,
+                device_fingerprint,
             },
         )
         .await?;
@@ -167,7 +237,7 @@ pub(crate) async fn post(
         verification_uri: url_builder.device_code_link(),
         verification_uri_complete: Some(url_builder.device_code_link_full(device_code.user_code)),
         expires_in,
-        interval: Some(Duration::microseconds(5 * 1000 * 1000)),
+        interval: Some(DEFAULT_DEVICE_CODE_INTERVAL),
     };
 
     Ok((
@@ -183,7 +253,9 @@ mod tests {
     use hyper::{Request, StatusCode};
     use mas_router::SimpleRoute;
     use oauth2_types::{
-        registration::ClientRegistrationResponse, requests::DeviceAuthorizationResponse,
+        errors::{ClientError, ClientErrorCode},
+        registration::ClientRegistrationResponse,
+        requests::DeviceAuthorizationResponse,
     };
     use sqlx::PgPool;
 
@@ -222,5 +294,42 @@ mod tests {
         let response: DeviceAuthorizationResponse = response.json();
         assert_eq!(response.device_code.len(), 32);
         assert_eq!(response.user_code.len(), 6);
+
+        // The client can provide a device fingerprint, which gets saved on the grant
+        let request = Request::post(mas_router::OAuth2DeviceAuthorizationEndpoint::PATH).form(
+            serde_json::json!({
+                "client_id": client_id,
+                "scope": "openid",
+                "device_fingerprint": "c2VjcmV0LWluc3RhbGwtaWQ",
+            }),
+        );
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let response: DeviceAuthorizationResponse = response.json();
+
+        let mut repo = state.repository().await.unwrap();
+        let grant = repo
+            .oauth2_device_code_grant()
+            .find_by_user_code(&response.user_code)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            grant.device_fingerprint.as_deref(),
+            Some("c2VjcmV0LWluc3RhbGwtaWQ")
+        );
+
+        // Anything which doesn't look like a hash is rejected
+        let request = Request::post(mas_router::OAuth2DeviceAuthorizationEndpoint::PATH).form(
+            serde_json::json!({
+                "client_id": client_id,
+                "scope": "openid",
+                "device_fingerprint": "Pixel 7",
+            }),
+        );
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidRequest);
     }
 }
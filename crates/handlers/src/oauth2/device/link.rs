@@ -9,6 +9,7 @@ use axum::{
     response::{Html, IntoResponse},
 };
 use mas_axum_utils::{InternalError, cookies::CookieJar};
+use mas_data_model::SiteConfig;
 use mas_router::UrlBuilder;
 use mas_storage::{BoxClock, BoxRepository};
 use mas_templates::{
@@ -31,6 +32,7 @@ pub(crate) async fn get(
     PreferredLanguage(locale): PreferredLanguage,
     State(templates): State<Templates>,
     State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
     cookie_jar: CookieJar,
     Query(query): Query<Params>,
 ) -> Result<impl IntoResponse, InternalError> {
@@ -61,9 +63,12 @@ pub(crate) async fn get(
     }
 
     // Rendre the form
-    let ctx = DeviceLinkContext::new()
-        .with_form_state(form_state)
-        .with_language(locale);
+    let ctx = DeviceLinkContext::new(
+        site_config.device_code_user_code_length,
+        url_builder.device_code_link(),
+    )
+    .with_form_state(form_state)
+    .with_language(locale);
 
     let content = templates.render_device_link(&ctx)?;
 
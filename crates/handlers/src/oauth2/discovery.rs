@@ -9,17 +9,20 @@ use mas_iana::oauth::{
     OAuthAuthorizationEndpointResponseType, OAuthClientAuthenticationMethod,
     PkceCodeChallengeMethod,
 };
-use mas_jose::jwa::SUPPORTED_SIGNING_ALGORITHMS;
+use mas_jose::jwa::{
+    SUPPORTED_CONTENT_ENCRYPTION_ALGORITHMS, SUPPORTED_ENCRYPTION_ALGORITHMS,
+    SUPPORTED_SIGNING_ALGORITHMS,
+};
 use mas_keystore::Keystore;
-use mas_router::UrlBuilder;
 use oauth2_types::{
-    oidc::{ClaimType, ProviderMetadata, SubjectType},
+    oidc::{BackchannelTokenDeliveryMode, ClaimType, ProviderMetadata, SubjectType},
     requests::{Display, GrantType, Prompt, ResponseMode},
     scope,
 };
 use serde::Serialize;
 
-use crate::SiteConfig;
+use super::{dpop::DPOP_SIGNING_ALGORITHMS, request_object::REQUEST_OBJECT_SIGNING_ALGORITHMS};
+use crate::{IssuerUrlBuilder, SiteConfig};
 
 #[derive(Debug, Serialize)]
 struct DiscoveryResponse {
@@ -38,7 +41,7 @@ struct DiscoveryResponse {
 #[allow(clippy::too_many_lines)]
 pub(crate) async fn get(
     State(key_store): State<Keystore>,
-    State(url_builder): State<UrlBuilder>,
+    IssuerUrlBuilder(url_builder): IssuerUrlBuilder,
     State(site_config): State<SiteConfig>,
 ) -> impl IntoResponse {
     // This is how clients can authenticate
@@ -61,32 +64,55 @@ pub(crate) async fn get(
     let authorization_endpoint = Some(url_builder.oauth_authorization_endpoint());
     let token_endpoint = Some(url_builder.oauth_token_endpoint());
     let device_authorization_endpoint = Some(url_builder.oauth_device_authorization_endpoint());
+    let backchannel_authentication_endpoint =
+        Some(url_builder.oauth_backchannel_authentication_endpoint());
     let jwks_uri = Some(url_builder.jwks_uri());
     let introspection_endpoint = Some(url_builder.oauth_introspection_endpoint());
     let revocation_endpoint = Some(url_builder.oauth_revocation_endpoint());
     let userinfo_endpoint = Some(url_builder.oidc_userinfo_endpoint());
+    let end_session_endpoint = Some(url_builder.oidc_end_session_endpoint());
     let registration_endpoint = Some(url_builder.oauth_registration_endpoint());
+    let pushed_authorization_request_endpoint =
+        Some(url_builder.oauth_pushed_authorization_request_endpoint());
 
     let scopes_supported = Some(vec![scope::OPENID.to_string(), scope::EMAIL.to_string()]);
 
-    let response_types_supported = Some(vec![
-        OAuthAuthorizationEndpointResponseType::Code.into(),
-        OAuthAuthorizationEndpointResponseType::IdToken.into(),
-        OAuthAuthorizationEndpointResponseType::CodeIdToken.into(),
-    ]);
+    // The implicit grant is not available in OAuth 2.1 strict mode
+    let response_types_supported = if site_config.oauth2_1_strict {
+        Some(vec![OAuthAuthorizationEndpointResponseType::Code.into()])
+    } else {
+        Some(vec![
+            OAuthAuthorizationEndpointResponseType::Code.into(),
+            OAuthAuthorizationEndpointResponseType::IdToken.into(),
+            OAuthAuthorizationEndpointResponseType::CodeIdToken.into(),
+        ])
+    };
 
     let response_modes_supported = Some(vec![
         ResponseMode::FormPost,
         ResponseMode::Query,
         ResponseMode::Fragment,
+        ResponseMode::FormPostJwt,
+        ResponseMode::QueryJwt,
+        ResponseMode::FragmentJwt,
+        ResponseMode::Jwt,
     ]);
 
-    let grant_types_supported = Some(vec![
+    let mut grant_types_supported = vec![
         GrantType::AuthorizationCode,
         GrantType::RefreshToken,
         GrantType::ClientCredentials,
         GrantType::DeviceCode,
-    ]);
+        GrantType::ClientInitiatedBackchannelAuthentication,
+    ];
+
+    // The password grant is only usable by allow-listed clients, but we still
+    // advertise it if password login is enabled, outside of OAuth 2.1 strict mode
+    if site_config.password_login_enabled && !site_config.oauth2_1_strict {
+        grant_types_supported.push(GrantType::Password);
+    }
+
+    let grant_types_supported = Some(grant_types_supported);
 
     let token_endpoint_auth_methods_supported = client_auth_methods_supported.clone();
     let token_endpoint_auth_signing_alg_values_supported =
@@ -106,10 +132,27 @@ pub(crate) async fn get(
         PkceCodeChallengeMethod::S256,
     ]);
 
-    let subject_types_supported = Some(vec![SubjectType::Public]);
+    let subject_types_supported = Some(vec![SubjectType::Public, SubjectType::Pairwise]);
 
     let id_token_signing_alg_values_supported = jwt_signing_alg_values_supported.clone();
-    let userinfo_signing_alg_values_supported = jwt_signing_alg_values_supported;
+    let userinfo_signing_alg_values_supported = jwt_signing_alg_values_supported.clone();
+    let introspection_signing_alg_values_supported = jwt_signing_alg_values_supported.clone();
+    let authorization_signing_alg_values_supported = jwt_signing_alg_values_supported;
+
+    // ID tokens are only encrypted for clients which ask for it at registration
+    let id_token_encryption_alg_values_supported = Some(SUPPORTED_ENCRYPTION_ALGORITHMS.to_vec());
+    let id_token_encryption_enc_values_supported =
+        Some(SUPPORTED_CONTENT_ENCRYPTION_ALGORITHMS.to_vec());
+
+    // Only advertise the ACR values which were configured
+    let acr_values_supported = Some(
+        site_config
+            .acr_values
+            .iter()
+            .map(|acr| acr.value.clone())
+            .collect::<Vec<_>>(),
+    )
+    .filter(|values| !values.is_empty());
 
     let display_values_supported = Some(vec![Display::Page]);
 
@@ -125,11 +168,41 @@ pub(crate) async fn get(
         "auth_time".to_owned(),
         "at_hash".to_owned(),
         "c_hash".to_owned(),
+        "acr".to_owned(),
+        "amr".to_owned(),
+        "preferred_username".to_owned(),
+        "email".to_owned(),
+        "email_verified".to_owned(),
+    ]);
+
+    let claims_parameter_supported = Some(true);
+    let request_parameter_supported = Some(true);
+    let request_uri_parameter_supported = Some(true);
+    let request_object_signing_alg_values_supported =
+        Some(REQUEST_OBJECT_SIGNING_ALGORITHMS.to_vec());
+
+    // Pushed authorization requests are only enforced for some clients, which
+    // advertise it through their own metadata
+    let require_pushed_authorization_requests = Some(false);
+
+    // Those are the algorithms we accept for DPoP proofs
+    let dpop_signing_alg_values_supported = Some(DPOP_SIGNING_ALGORITHMS.to_vec());
+
+    // Only advertise rich authorization requests if some types are configured
+    let authorization_details_types_supported =
+        Some(site_config.authorization_details_types.clone()).filter(|types| !types.is_empty());
+
+    // Clients which registered a notification endpoint get pinged, the others
+    // have to poll the token endpoint
+    let backchannel_token_delivery_modes_supported = Some(vec![
+        BackchannelTokenDeliveryMode::Poll,
+        BackchannelTokenDeliveryMode::Ping,
     ]);
+    let backchannel_user_code_parameter_supported = Some(false);
 
-    let claims_parameter_supported = Some(false);
-    let request_parameter_supported = Some(false);
-    let request_uri_parameter_supported = Some(false);
+    // Logout tokens always carry a `sid` claim when the client asks for it
+    let backchannel_logout_supported = Some(true);
+    let backchannel_logout_session_supported = Some(true);
 
     let prompt_values_supported = Some({
         let mut v = vec![Prompt::Login];
@@ -160,10 +233,15 @@ pub(crate) async fn get(
         introspection_endpoint,
         introspection_endpoint_auth_methods_supported,
         introspection_endpoint_auth_signing_alg_values_supported,
+        introspection_signing_alg_values_supported,
         code_challenge_methods_supported,
         userinfo_endpoint,
+        end_session_endpoint,
+        acr_values_supported,
         subject_types_supported,
         id_token_signing_alg_values_supported,
+        id_token_encryption_alg_values_supported,
+        id_token_encryption_enc_values_supported,
         userinfo_signing_alg_values_supported,
         display_values_supported,
         claim_types_supported,
@@ -171,8 +249,19 @@ pub(crate) async fn get(
         claims_parameter_supported,
         request_parameter_supported,
         request_uri_parameter_supported,
+        request_object_signing_alg_values_supported,
         prompt_values_supported,
         device_authorization_endpoint,
+        backchannel_authentication_endpoint,
+        backchannel_token_delivery_modes_supported,
+        backchannel_user_code_parameter_supported,
+        backchannel_logout_supported,
+        backchannel_logout_session_supported,
+        pushed_authorization_request_endpoint,
+        require_pushed_authorization_requests,
+        dpop_signing_alg_values_supported,
+        authorization_signing_alg_values_supported,
+        authorization_details_types_supported,
         ..ProviderMetadata::default()
     };
 
@@ -214,4 +303,49 @@ mod tests {
             .validate(state.url_builder.oidc_issuer().as_str())
             .expect("Invalid metadata");
     }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_issuer_alias_discovery_metadata(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        state.url_builder = state
+            .url_builder
+            .clone()
+            .with_issuer_aliases(vec!["https://auth.example.org/".parse().unwrap()]);
+
+        // Requests on the main host get the main issuer
+        let request = Request::get("/.well-known/openid-configuration")
+            .header("Host", "example.com")
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let metadata: ProviderMetadata = response.json();
+        let metadata = metadata
+            .validate(state.url_builder.oidc_issuer().as_str())
+            .expect("Invalid metadata");
+        assert_eq!(
+            metadata.token_endpoint().as_str(),
+            "https://example.com/oauth2/token"
+        );
+
+        // Requests on the alias host get the alias as issuer, with endpoints on
+        // the same host
+        let request = Request::get("/.well-known/openid-configuration")
+            .header("Host", "auth.example.org")
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let metadata: ProviderMetadata = response.json();
+        let metadata = metadata
+            .validate("https://auth.example.org/")
+            .expect("Invalid metadata");
+        assert_eq!(
+            metadata.token_endpoint().as_str(),
+            "https://auth.example.org/oauth2/token"
+        );
+        assert_eq!(
+            metadata.jwks_uri().as_str(),
+            "https://auth.example.org/oauth2/keys.json"
+        );
+    }
 }
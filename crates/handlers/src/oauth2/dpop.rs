@@ -0,0 +1,275 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Validation of DPoP proofs, as defined in [RFC9449]
+//!
+//! [RFC9449]: https://www.rfc-editor.org/rfc/rfc9449
+
+use std::collections::HashMap;
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::Duration;
+use hyper::{HeaderMap, Method};
+use mas_data_model::AccessToken;
+use mas_iana::jose::JsonWebSignatureAlg;
+use mas_jose::{
+    claims::{self, ClaimError, TimeOptions},
+    jwa::AsymmetricVerifyingKey,
+    jwt::{Jwt, JwtDecodeError},
+};
+use mas_storage::{BoxRepository, Clock, RepositoryAccess, RepositoryError};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use url::Url;
+
+/// The name of the header carrying the proof
+const DPOP_HEADER: &str = "DPoP";
+
+/// The `typ` the proofs must have in their header
+const DPOP_JWT_TYPE: &str = "dpop+jwt";
+
+/// How long after being issued a proof is accepted
+const MAX_AGE: Duration = Duration::minutes(5);
+
+/// How far in the future a proof can be issued, to account for clock skew
+const LEEWAY: Duration = Duration::seconds(60);
+
+/// The algorithms accepted for signing proofs. Proofs are signed with the key
+/// they carry, so only asymmetric algorithms make sense.
+pub(crate) const DPOP_SIGNING_ALGORITHMS: [JsonWebSignatureAlg; 9] = [
+    JsonWebSignatureAlg::Rs256,
+    JsonWebSignatureAlg::Rs384,
+    JsonWebSignatureAlg::Rs512,
+    JsonWebSignatureAlg::Ps256,
+    JsonWebSignatureAlg::Ps384,
+    JsonWebSignatureAlg::Ps512,
+    JsonWebSignatureAlg::Es256,
+    JsonWebSignatureAlg::Es384,
+    JsonWebSignatureAlg::Es256K,
+];
+
+#[derive(Debug, Error)]
+pub(crate) enum DPoPError {
+    #[error("more than one DPoP header was sent")]
+    MultipleProofs,
+
+    #[error("the DPoP header is not valid")]
+    InvalidHeader(#[from] hyper::header::ToStrError),
+
+    #[error("the DPoP proof is not a valid JWT")]
+    Decode(#[from] JwtDecodeError),
+
+    #[error("the DPoP proof has the wrong type")]
+    WrongType,
+
+    #[error("the DPoP proof is signed with an unsupported algorithm {0}")]
+    UnsupportedAlgorithm(JsonWebSignatureAlg),
+
+    #[error("the DPoP proof does not include its public key")]
+    MissingKey,
+
+    #[error("the public key of the DPoP proof is not usable")]
+    InvalidKey(#[from] mas_jose::jwa::AsymmetricKeyFromJwkError),
+
+    #[error("the signature of the DPoP proof is invalid")]
+    InvalidSignature(#[from] mas_jose::jwt::JwtVerificationError),
+
+    #[error("invalid claim in the DPoP proof")]
+    Claim(#[from] ClaimError),
+
+    #[error("the DPoP proof was made for another URL")]
+    UrlMismatch,
+
+    #[error("the DPoP proof is too old")]
+    NotFresh,
+
+    #[error("the DPoP proof was already used")]
+    Replayed,
+
+    #[error("the DPoP proof is bound to another key")]
+    KeyMismatch,
+
+    #[error("a DPoP proof is required")]
+    Required,
+
+    #[error("the access token is not bound to a key")]
+    NotBound,
+
+    #[error(transparent)]
+    Repository(#[from] RepositoryError),
+}
+
+impl DPoPError {
+    /// Whether the error is caused by the server and not by the proof
+    pub(crate) fn is_internal(&self) -> bool {
+        matches!(self, Self::Repository(_))
+    }
+}
+
+/// A DPoP proof which passed validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DPoPProof {
+    /// The JWK SHA-256 thumbprint of the key which signed the proof
+    pub jkt: String,
+}
+
+impl DPoPProof {
+    /// Check that this proof is signed with the key a token is bound to
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keys don't match
+    pub(crate) fn ensure_key(&self, jkt: &str) -> Result<(), DPoPError> {
+        if self.jkt == jkt {
+            Ok(())
+        } else {
+            Err(DPoPError::KeyMismatch)
+        }
+    }
+}
+
+/// Compute the `ath` claim of a proof presented along an access token
+fn access_token_hash(access_token: &str) -> String {
+    Base64UrlUnpadded::encode_string(&Sha256::digest(access_token.as_bytes()))
+}
+
+/// Strip the query and fragment of a URL, as those are not covered by `htu`
+fn without_query(mut url: Url) -> Url {
+    url.set_query(None);
+    url.set_fragment(None);
+    url
+}
+
+/// Validate the DPoP proof sent with a request, if any
+///
+/// Returns `None` if the request has no DPoP header
+///
+/// # Parameters
+///
+/// * `rng`: A random number generator
+/// * `clock`: The clock used to check the freshness of the proof
+/// * `repo`: The repository used to detect replayed proofs
+/// * `headers`: The headers of the request
+/// * `method`: The method of the request
+/// * `url`: The URL of the endpoint receiving the request
+/// * `access_token`: The access token presented with the request, if any
+///
+/// # Errors
+///
+/// Returns an error if the proof is invalid or was already used, or if the
+/// repository fails
+pub(crate) async fn verify_proof(
+    rng: &mut (dyn RngCore + Send),
+    clock: &impl Clock,
+    repo: &mut BoxRepository,
+    headers: &HeaderMap,
+    method: &Method,
+    url: &Url,
+    access_token: Option<&str>,
+) -> Result<Option<DPoPProof>, DPoPError> {
+    let mut values = headers.get_all(DPOP_HEADER).iter();
+    let Some(value) = values.next() else {
+        return Ok(None);
+    };
+
+    if values.next().is_some() {
+        return Err(DPoPError::MultipleProofs);
+    }
+
+    let jwt: Jwt<'_, HashMap<String, serde_json::Value>> = Jwt::try_from(value.to_str()?)?;
+
+    let header = jwt.header();
+    if header.typ() != Some(DPOP_JWT_TYPE) {
+        return Err(DPoPError::WrongType);
+    }
+
+    if !DPOP_SIGNING_ALGORITHMS.contains(header.alg()) {
+        return Err(DPoPError::UnsupportedAlgorithm(header.alg().clone()));
+    }
+
+    let jwk = header.jwk().ok_or(DPoPError::MissingKey)?;
+    let key = AsymmetricVerifyingKey::from_jwk_and_alg(jwk.params(), header.alg())?;
+    jwt.verify(&key)?;
+
+    let mut claims = jwt.payload().clone();
+
+    claims::HTM.extract_required_with_options(&mut claims, method.as_str())?;
+
+    let htu = claims::HTU.extract_required(&mut claims)?;
+    let htu = Url::parse(&htu).map_err(|_| ClaimError::InvalidClaim("htu"))?;
+    if without_query(htu) != without_query(url.clone()) {
+        return Err(DPoPError::UrlMismatch);
+    }
+
+    // The proof must not be issued in the future, nor be too old
+    let now = clock.now();
+    let iat = *claims::IAT
+        .extract_required_with_options(&mut claims, TimeOptions::new(now).leeway(LEEWAY))?;
+    if iat < now - MAX_AGE {
+        return Err(DPoPError::NotFresh);
+    }
+
+    let jti = claims::JTI.extract_required(&mut claims)?;
+
+    if let Some(access_token) = access_token {
+        let ath = access_token_hash(access_token);
+        claims::ATH.extract_required_with_options(&mut claims, ath.as_str())?;
+    }
+
+    let jkt = jwk.params().thumbprint();
+
+    // The proof is remembered for as long as it would be accepted
+    let fresh = repo
+        .oauth2_dpop_proof()
+        .record(rng, clock, &jkt, &jti, iat + MAX_AGE)
+        .await?;
+    if !fresh {
+        return Err(DPoPError::Replayed);
+    }
+
+    Ok(Some(DPoPProof { jkt }))
+}
+
+/// Validate the DPoP proof sent along an access token presented with the
+/// `DPoP` authorization scheme
+///
+/// # Errors
+///
+/// Returns an error if the request has no proof, if the proof is invalid or
+/// doesn't cover the access token, or if the repository fails
+pub(crate) async fn verify_token_proof(
+    rng: &mut (dyn RngCore + Send),
+    clock: &impl Clock,
+    repo: &mut BoxRepository,
+    headers: &HeaderMap,
+    method: &Method,
+    url: &Url,
+    access_token: &str,
+) -> Result<DPoPProof, DPoPError> {
+    verify_proof(rng, clock, repo, headers, method, url, Some(access_token))
+        .await?
+        .ok_or(DPoPError::Required)
+}
+
+/// Check that an access token is presented the way it was issued
+///
+/// Tokens bound to a key need a proof signed with that key, and proofs can
+/// only be presented along bound tokens.
+///
+/// # Errors
+///
+/// Returns an error if the proof doesn't match the binding of the token
+pub(crate) fn ensure_token_binding(
+    access_token: &AccessToken,
+    proof: Option<&DPoPProof>,
+) -> Result<(), DPoPError> {
+    match (access_token.dpop_jkt.as_deref(), proof) {
+        (Some(jkt), Some(proof)) => proof.ensure_key(jkt),
+        (Some(_), None) => Err(DPoPError::Required),
+        (None, Some(_)) => Err(DPoPError::NotBound),
+        (None, None) => Ok(()),
+    }
+}
@@ -0,0 +1,463 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Form, State},
+    response::{IntoResponse, Redirect, Response},
+};
+use hyper::StatusCode;
+use mas_axum_utils::{SessionInfoExt, cookies::CookieJar, record_error};
+use mas_data_model::SiteConfig;
+use mas_jose::{claims, jwt::Jwt};
+use mas_keystore::Keystore;
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng,
+    queue::{QueueJobRepositoryExt as _, SendBrowserSessionBackchannelLogoutsJob},
+    user::BrowserSessionRepository,
+};
+use oauth2_types::oidc::RpInitiatedLogoutRequest;
+use thiserror::Error;
+use tracing::info;
+
+use crate::{BoundActivityTracker, IssuerUrlBuilder, impl_from_error_for_route};
+
+#[derive(Debug, Error)]
+pub enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("invalid id_token_hint")]
+    InvalidIdTokenHint(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("could not find client")]
+    ClientNotFound,
+
+    #[error("client_id does not match the id_token_hint audience")]
+    ClientMismatch,
+
+    #[error("post_logout_redirect_uri requires the client to be identified")]
+    MissingClient,
+
+    #[error("post_logout_redirect_uri is not registered for this client")]
+    UnregisteredPostLogoutRedirectUri,
+}
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        // TODO: better error pages
+        let response = match self {
+            Self::Internal(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+            Self::InvalidIdTokenHint(_) => {
+                (StatusCode::BAD_REQUEST, "invalid id_token_hint").into_response()
+            }
+            Self::ClientNotFound => {
+                (StatusCode::BAD_REQUEST, "could not find client").into_response()
+            }
+            Self::ClientMismatch => (
+                StatusCode::BAD_REQUEST,
+                "client_id does not match the id_token_hint audience",
+            )
+                .into_response(),
+            Self::MissingClient => (
+                StatusCode::BAD_REQUEST,
+                "post_logout_redirect_uri requires either client_id or id_token_hint",
+            )
+                .into_response(),
+            Self::UnregisteredPostLogoutRedirectUri => (
+                StatusCode::BAD_REQUEST,
+                "post_logout_redirect_uri is not registered for this client",
+            )
+                .into_response(),
+        };
+
+        (sentry_event_id, response).into_response()
+    }
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+
+impl RouteError {
+    fn invalid_id_token_hint(e: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self::InvalidIdTokenHint(Box::new(e))
+    }
+}
+
+/// The claims we use from a verified `id_token_hint`
+struct IdTokenHint {
+    audience: Vec<String>,
+    sub: String,
+    sid: Option<String>,
+}
+
+/// Check that the `id_token_hint` was signed by us for one of our clients.
+///
+/// The expiration is deliberately not checked, as the hint is usually an ID
+/// token which expired a long time ago.
+fn verify_id_token_hint(
+    id_token_hint: &str,
+    key_store: &Keystore,
+    url_builder: &UrlBuilder,
+) -> Result<IdTokenHint, RouteError> {
+    let jwt: Jwt<'_, HashMap<String, serde_json::Value>> =
+        Jwt::try_from(id_token_hint).map_err(RouteError::invalid_id_token_hint)?;
+
+    jwt.verify_with_jwks(&key_store.public_jwks())
+        .map_err(RouteError::invalid_id_token_hint)?;
+
+    let (_header, mut claims) = jwt.into_parts();
+
+    let issuer = url_builder.oidc_issuer();
+    claims::ISS
+        .extract_required_with_options(&mut claims, issuer.as_str())
+        .map_err(RouteError::invalid_id_token_hint)?;
+    let audience = claims::AUD
+        .extract_required(&mut claims)
+        .map_err(RouteError::invalid_id_token_hint)?;
+    let sub = claims::SUB
+        .extract_required(&mut claims)
+        .map_err(RouteError::invalid_id_token_hint)?;
+    let sid = claims::SID
+        .extract_optional(&mut claims)
+        .map_err(RouteError::invalid_id_token_hint)?;
+
+    Ok(IdTokenHint {
+        audience: audience.to_vec(),
+        sub,
+        sid,
+    })
+}
+
+#[tracing::instrument(
+    name = "handlers.oauth2.end_session.handler",
+    fields(client.id = params.client_id.as_deref()),
+    skip_all,
+)]
+pub(crate) async fn handler(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    IssuerUrlBuilder(url_builder): IssuerUrlBuilder,
+    State(key_store): State<Keystore>,
+    State(site_config): State<SiteConfig>,
+    activity_tracker: BoundActivityTracker,
+    mut repo: BoxRepository,
+    cookie_jar: CookieJar,
+    Form(params): Form<RpInitiatedLogoutRequest>,
+) -> Result<Response, RouteError> {
+    let hint = params
+        .id_token_hint
+        .as_deref()
+        .map(|id_token_hint| verify_id_token_hint(id_token_hint, &key_store, &url_builder))
+        .transpose()?;
+
+    // Figure out which client is asking for the logout. If both a client_id and
+    // an id_token_hint are given, they must agree
+    let client_id = match (params.client_id, &hint) {
+        (Some(client_id), Some(hint)) if !hint.audience.contains(&client_id) => {
+            return Err(RouteError::ClientMismatch);
+        }
+        (Some(client_id), _) => Some(client_id),
+        (None, Some(hint)) if hint.audience.len() == 1 => Some(hint.audience[0].clone()),
+        (None, _) => None,
+    };
+
+    let client = match client_id {
+        Some(client_id) => Some(
+            repo.oauth2_client()
+                .find_by_client_id(&client_id)
+                .await?
+                .ok_or(RouteError::ClientNotFound)?,
+        ),
+        None => None,
+    };
+
+    // Only redirect to URIs which were registered by the client, compared with
+    // a simple string comparison
+    let post_logout_redirect_uri = match params.post_logout_redirect_uri {
+        Some(mut uri) => {
+            let client = client.as_ref().ok_or(RouteError::MissingClient)?;
+            if !client.post_logout_redirect_uris.contains(&uri) {
+                return Err(RouteError::UnregisteredPostLogoutRedirectUri);
+            }
+
+            if let Some(state) = &params.state {
+                uri.query_pairs_mut().append_pair("state", state);
+            }
+
+            Some(uri)
+        }
+        None => None,
+    };
+
+    let (session_info, mut cookie_jar) = cookie_jar.session_info();
+    let maybe_session = session_info
+        .load_active_session(&mut repo, &clock, &site_config)
+        .await?;
+
+    // Clients using pairwise subject identifiers have one in the `sub` of the
+    // hint, which is mapped back to the user it identifies
+    let sector_identifier = client
+        .as_ref()
+        .and_then(|client| client.pairwise_sector_identifier.as_deref());
+    let hint_user_id = match (&hint, sector_identifier) {
+        (Some(hint), Some(sector_identifier)) if hint.sid.is_none() => {
+            repo.oauth2_pairwise_subject()
+                .find_user_id(sector_identifier, &hint.sub)
+                .await?
+        }
+        _ => None,
+    };
+
+    // Only end the browser session if the hint proves that the request is about
+    // it, to avoid third parties logging users out
+    let session = maybe_session.filter(|session| {
+        hint.as_ref().is_some_and(|hint| match &hint.sid {
+            Some(sid) => *sid == session.id.to_string(),
+            None if sector_identifier.is_some() => hint_user_id == Some(session.user.id),
+            None => hint.sub == session.user.sub,
+        })
+    });
+
+    if let Some(session) = session {
+        activity_tracker
+            .record_browser_session(&clock, &session)
+            .await;
+
+        // Let the clients which got a session through this one know about the
+        // logout
+        repo.queue_job()
+            .schedule_job(
+                &mut rng,
+                &clock,
+                SendBrowserSessionBackchannelLogoutsJob::new(&session),
+            )
+            .await?;
+
+        info!(browser_session.id = %session.id, "Ending browser session on RP-initiated logout");
+        repo.browser_session().finish(&clock, session).await?;
+
+        cookie_jar = cookie_jar.update_session_info(&session_info.mark_session_ended());
+    }
+
+    repo.save().await?;
+
+    let destination = match post_logout_redirect_uri {
+        Some(uri) => Redirect::to(uri.as_str()),
+        None => url_builder.redirect(&mas_router::Login::default()),
+    };
+
+    Ok((cookie_jar, destination).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use hyper::{Request, StatusCode, header::LOCATION};
+    use mas_axum_utils::SessionInfoExt as _;
+    use mas_data_model::{BrowserSession, Client};
+    use mas_router::SimpleRoute;
+    use mas_storage::RepositoryAccess;
+    use sqlx::PgPool;
+
+    use crate::{
+        oauth2::generate_id_token,
+        test_utils::{CookieHelper, RequestBuilderExt, ResponseExt, TestState, setup},
+    };
+
+    async fn create_client(state: &TestState) -> Client {
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+        let client = repo
+            .oauth2_client()
+            .add(
+                &mut rng,
+                &state.clock,
+                vec!["https://example.com/callback".parse().unwrap()],
+                None,
+                None,
+                None,
+                vec![],
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                vec!["https://example.com/logged-out".parse().unwrap()],
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                Vec::new(),
+                None,
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        client
+    }
+
+    async fn create_browser_session(state: &TestState) -> BrowserSession {
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        browser_session
+    }
+
+    fn id_token(state: &TestState, client: &Client, browser_session: &BrowserSession) -> String {
+        let mut rng = state.rng();
+        generate_id_token(
+            &mut rng,
+            &state.clock,
+            &state.url_builder,
+            &state.key_store,
+            &state.site_config,
+            client,
+            None,
+            browser_session,
+            &browser_session.user.sub,
+            None,
+            None,
+            None,
+            HashMap::new(),
+        )
+        .unwrap()
+    }
+
+    async fn is_finished(state: &TestState, browser_session: &BrowserSession) -> bool {
+        let mut repo = state.repository().await.unwrap();
+        let session = repo
+            .browser_session()
+            .lookup(browser_session.id)
+            .await
+            .unwrap()
+            .unwrap();
+        repo.save().await.unwrap();
+        session.finished_at.is_some()
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_end_session_redirect(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let client = create_client(&state).await;
+
+        // Unregistered URIs are rejected
+        let request =
+            Request::post(mas_router::OAuth2EndSessionEndpoint::PATH).form(serde_json::json!({
+                "client_id": client.client_id,
+                "post_logout_redirect_uri": "https://example.com/callback",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // So are URIs without a client to check them against
+        let request =
+            Request::post(mas_router::OAuth2EndSessionEndpoint::PATH).form(serde_json::json!({
+                "post_logout_redirect_uri": "https://example.com/logged-out",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // Registered URIs get the state back
+        let request =
+            Request::post(mas_router::OAuth2EndSessionEndpoint::PATH).form(serde_json::json!({
+                "client_id": client.client_id,
+                "post_logout_redirect_uri": "https://example.com/logged-out",
+                "state": "hello world",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers().get(LOCATION).unwrap(),
+            "https://example.com/logged-out?state=hello+world"
+        );
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_end_session_id_token_hint(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let cookies = CookieHelper::new();
+        let client = create_client(&state).await;
+        let browser_session = create_browser_session(&state).await;
+        cookies.import(state.cookie_jar().set_session(&browser_session));
+
+        // A tampered hint is rejected, and doesn't end the session
+        let id_token = id_token(&state, &client, &browser_session);
+        let (rest, signature) = id_token.rsplit_once('.').unwrap();
+        let tampered = format!("{rest}.{}", signature.chars().rev().collect::<String>());
+        let request = cookies.with_cookies(
+            Request::post(mas_router::OAuth2EndSessionEndpoint::PATH).form(serde_json::json!({
+                "id_token_hint": tampered,
+            })),
+        );
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        assert!(!is_finished(&state, &browser_session).await);
+
+        // A client_id not matching the hint audience is rejected
+        let request = cookies.with_cookies(
+            Request::post(mas_router::OAuth2EndSessionEndpoint::PATH).form(serde_json::json!({
+                "id_token_hint": id_token,
+                "client_id": "someone-else",
+            })),
+        );
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        assert!(!is_finished(&state, &browser_session).await);
+
+        // Without a hint, the session is kept
+        let request = cookies.with_cookies(
+            Request::post(mas_router::OAuth2EndSessionEndpoint::PATH).form(serde_json::json!({})),
+        );
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        assert!(!is_finished(&state, &browser_session).await);
+
+        // A valid hint ends the session, and identifies the client
+        let request = cookies.with_cookies(
+            Request::post(mas_router::OAuth2EndSessionEndpoint::PATH).form(serde_json::json!({
+                "id_token_hint": id_token,
+                "post_logout_redirect_uri": "https://example.com/logged-out",
+            })),
+        );
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers().get(LOCATION).unwrap(),
+            "https://example.com/logged-out"
+        );
+        assert!(is_finished(&state, &browser_session).await);
+    }
+}
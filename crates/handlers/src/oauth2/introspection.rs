@@ -7,30 +7,52 @@
 use std::sync::LazyLock;
 
 use axum::{Json, extract::State, http::HeaderValue, response::IntoResponse};
-use hyper::{HeaderMap, StatusCode};
+use chrono::{DateTime, Utc};
+use hyper::{
+    HeaderMap, Method, StatusCode,
+    header::{ACCEPT, CONTENT_TYPE},
+};
 use mas_axum_utils::{
+    ClientJwksCache,
     client_authorization::{ClientAuthorization, CredentialsVerificationError},
     record_error,
 };
-use mas_data_model::{Device, TokenFormatError, TokenType};
-use mas_iana::oauth::{OAuthClientAuthenticationMethod, OAuthTokenTypeHint};
-use mas_keystore::Encrypter;
+use mas_data_model::{Client, CompatSession, Device, TokenFormatError, TokenType};
+use mas_iana::{
+    jose::JsonWebSignatureAlg,
+    oauth::{OAuthClientAuthenticationMethod, OAuthTokenTypeHint},
+};
+use mas_jose::{
+    constraints::Constrainable,
+    jwt::{JsonWebSignatureHeader, Jwt},
+};
+use mas_keystore::{Encrypter, Keystore};
+use mas_router::UrlBuilder;
 use mas_storage::{
-    BoxClock, BoxRepository, Clock,
+    BoxClock, BoxRepository, BoxRng, Clock,
     compat::{CompatAccessTokenRepository, CompatRefreshTokenRepository, CompatSessionRepository},
-    oauth2::{OAuth2AccessTokenRepository, OAuth2RefreshTokenRepository, OAuth2SessionRepository},
+    oauth2::{
+        OAuth2AccessTokenRepository, OAuth2ClientRepository, OAuth2RefreshTokenRepository,
+        OAuth2SessionRepository,
+    },
     user::UserRepository,
 };
 use oauth2_types::{
     errors::{ClientError, ClientErrorCode},
-    requests::{IntrospectionRequest, IntrospectionResponse},
+    requests::{Actor, Confirmation, IntrospectionRequest, IntrospectionResponse},
     scope::ScopeToken,
 };
 use opentelemetry::{Key, KeyValue, metrics::Counter};
+use serde::Serialize;
 use thiserror::Error;
 use ulid::Ulid;
 
-use crate::{ActivityTracker, METER, impl_from_error_for_route};
+use super::{
+    IdTokenSignatureError,
+    dpop::{self, DPoPError},
+    introspection_cache::IntrospectionCache,
+};
+use crate::{ActivityTracker, IssuerUrlBuilder, METER, impl_from_error_for_route};
 
 static INTROSPECTION_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
@@ -43,6 +65,9 @@ static INTROSPECTION_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
 const KIND: Key = Key::from_static_str("kind");
 const ACTIVE: Key = Key::from_static_str("active");
 
+/// The media type of signed introspection responses, as per RFC 9701
+const TOKEN_INTROSPECTION_JWT: &str = "application/token-introspection+jwt";
+
 #[derive(Debug, Error)]
 pub enum RouteError {
     /// An internal error occurred.
@@ -99,11 +124,54 @@ pub enum RouteError {
     #[error("unknown user {0}")]
     CantLoadUser(Ulid),
 
+    #[error("unknown oauth client {0}")]
+    CantLoadOAuthClient(Ulid),
+
     #[error("bad request")]
     BadRequest,
 
     #[error(transparent)]
     ClientCredentialsVerification(#[from] CredentialsVerificationError),
+
+    /// The token was not issued for the audience of the introspecting
+    /// resource server
+    #[error("token was not issued for the audience of client {0}")]
+    WrongAudience(Ulid),
+
+    #[error("invalid DPoP proof")]
+    InvalidDPoPProof(#[source] DPoPError),
+
+    #[error("could not verify the DPoP proof")]
+    DPoPVerification(#[source] DPoPError),
+}
+
+impl RouteError {
+    /// Whether this error means the token is not active, in which case an
+    /// inactive introspection response is sent
+    fn is_inactive(&self) -> bool {
+        matches!(
+            self,
+            Self::UnknownToken(_)
+                | Self::UnexpectedTokenType
+                | Self::InvalidToken(_)
+                | Self::InvalidUser(_)
+                | Self::InvalidCompatSession(_)
+                | Self::InvalidOAuthSession(_)
+                | Self::InvalidTokenFormat(_)
+                | Self::CantEncodeDeviceID(_)
+                | Self::WrongAudience(_)
+        )
+    }
+}
+
+impl From<DPoPError> for RouteError {
+    fn from(err: DPoPError) -> Self {
+        if err.is_internal() {
+            Self::DPoPVerification(err)
+        } else {
+            Self::InvalidDPoPProof(err)
+        }
+    }
 }
 
 impl IntoResponse for RouteError {
@@ -114,13 +182,17 @@ impl IntoResponse for RouteError {
                 | Self::CantLoadCompatSession(_)
                 | Self::CantLoadOAuthSession(_)
                 | Self::CantLoadUser(_)
+                | Self::CantLoadOAuthClient(_)
+                | Self::DPoPVerification(_)
         );
 
         let response = match self {
             e @ (Self::Internal(_)
             | Self::CantLoadCompatSession(_)
             | Self::CantLoadOAuthSession(_)
-            | Self::CantLoadUser(_)) => (
+            | Self::CantLoadUser(_)
+            | Self::CantLoadOAuthClient(_)
+            | Self::DPoPVerification(_)) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(
                     ClientError::from(ClientErrorCode::ServerError).with_description(e.to_string()),
@@ -148,7 +220,8 @@ impl IntoResponse for RouteError {
             | Self::InvalidCompatSession(_)
             | Self::InvalidOAuthSession(_)
             | Self::InvalidTokenFormat(_)
-            | Self::CantEncodeDeviceID(_) => {
+            | Self::CantEncodeDeviceID(_)
+            | Self::WrongAudience(_) => {
                 INTROSPECTION_COUNTER.add(1, &[KeyValue::new(ACTIVE.clone(), false)]);
 
                 Json(INACTIVE).into_response()
@@ -165,6 +238,15 @@ impl IntoResponse for RouteError {
                 Json(ClientError::from(ClientErrorCode::InvalidRequest)),
             )
                 .into_response(),
+
+            Self::InvalidDPoPProof(e) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidDpopProof)
+                        .with_description(e.to_string()),
+                ),
+            )
+                .into_response(),
         };
 
         (sentry_event_id, response).into_response()
@@ -172,6 +254,7 @@ impl IntoResponse for RouteError {
 }
 
 impl_from_error_for_route!(mas_storage::RepositoryError);
+impl_from_error_for_route!(IdTokenSignatureError);
 
 const INACTIVE: IntrospectionResponse = IntrospectionResponse {
     active: false,
@@ -188,8 +271,87 @@ const INACTIVE: IntrospectionResponse = IntrospectionResponse {
     iss: None,
     jti: None,
     device_id: None,
+    cnf: None,
+    authorization_details: None,
+    act: None,
 };
 
+/// The claims of a signed introspection response, as per RFC 9701
+#[derive(Serialize)]
+struct SignedIntrospectionResponse {
+    iss: String,
+    aud: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    iat: DateTime<Utc>,
+    token_introspection: IntrospectionResponse,
+}
+
+/// Sign an introspection response for the client which asked for it
+///
+/// Responses are signed the same way as ID tokens for this client.
+fn sign_introspection_response(
+    rng: &mut (impl rand::RngCore + rand::CryptoRng),
+    now: DateTime<Utc>,
+    key_store: &Keystore,
+    url_builder: &UrlBuilder,
+    client: &Client,
+    reply: IntrospectionResponse,
+) -> Result<String, IdTokenSignatureError> {
+    let alg = client
+        .id_token_signed_response_alg
+        .clone()
+        .unwrap_or(JsonWebSignatureAlg::Rs256);
+    let key = key_store
+        .signing_key_for_algorithm(&alg)
+        .ok_or(IdTokenSignatureError::InvalidSigningKey)?;
+
+    let signer = key.params().signing_key_for_alg(&alg)?;
+    let header = JsonWebSignatureHeader::new(alg)
+        .with_kid(key.kid().ok_or(IdTokenSignatureError::InvalidSigningKey)?)
+        .with_typ("token-introspection+jwt".to_owned());
+
+    let claims = SignedIntrospectionResponse {
+        iss: url_builder.oidc_issuer().to_string(),
+        aud: client.client_id.clone(),
+        iat: now,
+        token_introspection: reply,
+    };
+
+    let jwt = Jwt::sign_with_rng(rng, header, claims, &signer)?;
+    Ok(jwt.into_string())
+}
+
+/// If the compatibility session was minted by an administrator to impersonate
+/// the user, describe who is acting on their behalf
+async fn impersonation_actor(
+    repo: &mut BoxRepository,
+    session: &CompatSession,
+) -> Result<Option<Actor>, RouteError> {
+    let Some(admin_session_id) = session.impersonated_by_session_id else {
+        return Ok(None);
+    };
+
+    // The admin session may have been deleted since, in which case we can only
+    // tell that someone else is acting
+    let Some(admin_session) = repo.oauth2_session().lookup(admin_session_id).await? else {
+        return Ok(Some(Actor::default()));
+    };
+
+    let client_id = repo
+        .oauth2_client()
+        .lookup(admin_session.client_id)
+        .await?
+        .map(|client| client.client_id);
+
+    let sub = if let Some(user_id) = admin_session.user_id {
+        repo.user().lookup(user_id).await?.map(|user| user.sub)
+    } else {
+        None
+    };
+
+    Ok(Some(Actor { sub, client_id }))
+}
+
 const API_SCOPE: ScopeToken = ScopeToken::from_static("urn:matrix:org.matrix.msc2967.client:api:*");
 const SYNAPSE_ADMIN_SCOPE: ScopeToken = ScopeToken::from_static("urn:synapse:admin:*");
 
@@ -198,13 +360,17 @@ const SYNAPSE_ADMIN_SCOPE: ScopeToken = ScopeToken::from_static("urn:synapse:adm
     fields(client.id = client_authorization.client_id()),
     skip_all,
 )]
-#[allow(clippy::too_many_lines)]
 pub(crate) async fn post(
+    mut rng: BoxRng,
     clock: BoxClock,
     State(http_client): State<reqwest::Client>,
+    State(jwks_cache): State<ClientJwksCache>,
+    IssuerUrlBuilder(url_builder): IssuerUrlBuilder,
     mut repo: BoxRepository,
     activity_tracker: ActivityTracker,
     State(encrypter): State<Encrypter>,
+    State(key_store): State<Keystore>,
+    State(introspection_cache): State<IntrospectionCache>,
     headers: HeaderMap,
     client_authorization: ClientAuthorization<IntrospectionRequest>,
 ) -> Result<impl IntoResponse, RouteError> {
@@ -223,20 +389,31 @@ pub(crate) async fn post(
 
     client_authorization
         .credentials
-        .verify(&http_client, &encrypter, method, &client)
+        .verify(
+            &http_client,
+            &jwks_cache,
+            &encrypter,
+            &clock,
+            method,
+            &client,
+        )
         .await?;
 
     let Some(form) = client_authorization.form else {
         return Err(RouteError::BadRequest);
     };
 
-    let token = &form.token;
-    let token_type = TokenType::check(token)?;
-    if let Some(hint) = form.token_type_hint {
-        if token_type != hint {
-            return Err(RouteError::UnexpectedTokenType);
-        }
-    }
+    // Clients can sender-constrain their introspection requests too
+    dpop::verify_proof(
+        &mut rng,
+        &clock,
+        &mut repo,
+        &headers,
+        &Method::POST,
+        &url_builder.oauth_introspection_endpoint(),
+        None,
+    )
+    .await?;
 
     // Not all device IDs can be encoded as scope. On OAuth 2.0 sessions, we
     // don't have this problem, as the device ID *is* already encoded as a scope.
@@ -248,10 +425,108 @@ pub(crate) async fn post(
     let supports_explicit_device_id =
         headers.get("X-MAS-Supports-Device-Id") == Some(&HeaderValue::from_static("1"));
 
+    // Resource servers can ask for a signed response, as per RFC 9701
+    let signed_response = headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .filter_map(|media_type| media_type.split(';').next())
+                .any(|media_type| media_type.trim() == TOKEN_INTROSPECTION_JWT)
+        });
+
+    let result = introspect(
+        &clock,
+        &mut repo,
+        &activity_tracker,
+        &introspection_cache,
+        &form,
+        supports_explicit_device_id,
+    )
+    .await
+    .and_then(|reply| {
+        // Resource servers only get the tokens issued for their audience as
+        // active
+        match &client.resource_server_audience {
+            Some(audience) if !audience.matches(reply.aud.as_deref()) => {
+                Err(RouteError::WrongAudience(client.id))
+            }
+            _ => Ok(reply),
+        }
+    });
+
+    let reply = match result {
+        Ok(reply) => {
+            repo.save().await?;
+            reply
+        }
+
+        // Inactive tokens get a signed response too
+        Err(e) if signed_response && e.is_inactive() => {
+            INTROSPECTION_COUNTER.add(1, &[KeyValue::new(ACTIVE, false)]);
+
+            INACTIVE
+        }
+
+        Err(e) => return Err(e),
+    };
+
+    if signed_response {
+        let jwt = sign_introspection_response(
+            &mut rng,
+            clock.now(),
+            &key_store,
+            &url_builder,
+            &client,
+            reply,
+        )?;
+
+        return Ok((
+            [(
+                CONTENT_TYPE,
+                HeaderValue::from_static(TOKEN_INTROSPECTION_JWT),
+            )],
+            jwt,
+        )
+            .into_response());
+    }
+
+    Ok(Json(reply).into_response())
+}
+
+/// Introspect a token, using the cached result if there is one
+#[allow(clippy::too_many_lines)]
+async fn introspect(
+    clock: &BoxClock,
+    repo: &mut BoxRepository,
+    activity_tracker: &ActivityTracker,
+    introspection_cache: &IntrospectionCache,
+    form: &IntrospectionRequest,
+    supports_explicit_device_id: bool,
+) -> Result<IntrospectionResponse, RouteError> {
+    let token = &form.token;
+    let token_type = TokenType::check(token)?;
+    if let Some(hint) = form.token_type_hint {
+        if token_type != hint {
+            return Err(RouteError::UnexpectedTokenType);
+        }
+    }
+
+    let now = clock.now();
+    if let Some(reply) = introspection_cache.get(token, supports_explicit_device_id, now) {
+        INTROSPECTION_COUNTER.add(
+            1,
+            &[KeyValue::new(KIND, "cached"), KeyValue::new(ACTIVE, true)],
+        );
+
+        return Ok(reply);
+    }
+
     // XXX: we should get the IP from the client introspecting the token
     let ip = None;
 
-    let reply = match token_type {
+    let (session_id, reply) = match token_type {
         TokenType::AccessToken => {
             let mut access_token = repo
                 .oauth2_access_token()
@@ -277,7 +552,7 @@ pub(crate) async fn post(
             if !access_token.is_used() {
                 access_token = repo
                     .oauth2_access_token()
-                    .mark_used(&clock, access_token)
+                    .mark_used(clock, access_token)
                     .await?;
             }
 
@@ -296,11 +571,18 @@ pub(crate) async fn post(
 
                 (Some(user.sub), Some(user.username))
             } else {
-                (None, None)
+                // Sessions of service accounts get a synthetic subject instead
+                let client = repo
+                    .oauth2_client()
+                    .lookup(session.client_id)
+                    .await?
+                    .ok_or(RouteError::CantLoadOAuthClient(session.client_id))?;
+
+                (client.service_account_subject(), None)
             };
 
             activity_tracker
-                .record_oauth2_session(&clock, &session, ip)
+                .record_oauth2_session(clock, &session, ip)
                 .await;
 
             INTROSPECTION_COUNTER.add(
@@ -311,24 +593,33 @@ pub(crate) async fn post(
                 ],
             );
 
-            IntrospectionResponse {
-                active: true,
-                scope: Some(session.scope),
-                client_id: Some(session.client_id.to_string()),
-                username,
-                token_type: Some(OAuthTokenTypeHint::AccessToken),
-                exp: access_token.expires_at,
-                expires_in: access_token
-                    .expires_at
-                    .map(|expires_at| expires_at.signed_duration_since(clock.now())),
-                iat: Some(access_token.created_at),
-                nbf: Some(access_token.created_at),
-                sub,
-                aud: None,
-                iss: None,
-                jti: Some(access_token.jti()),
-                device_id: None,
-            }
+            (
+                session.id,
+                IntrospectionResponse {
+                    active: true,
+                    scope: Some(session.scope),
+                    client_id: Some(session.client_id.to_string()),
+                    username,
+                    token_type: Some(OAuthTokenTypeHint::AccessToken),
+                    exp: access_token.expires_at,
+                    expires_in: access_token
+                        .expires_at
+                        .map(|expires_at| expires_at.signed_duration_since(clock.now())),
+                    iat: Some(access_token.created_at),
+                    nbf: Some(access_token.created_at),
+                    sub,
+                    aud: session.resource.map(String::from),
+                    iss: None,
+                    jti: Some(access_token.jti()),
+                    device_id: None,
+                    cnf: access_token
+                        .dpop_jkt
+                        .map(|jkt| Confirmation { jkt: Some(jkt) }),
+                    authorization_details: Some(session.authorization_details)
+                        .filter(|details| !details.is_empty()),
+                    act: None,
+                },
+            )
         }
 
         TokenType::RefreshToken => {
@@ -367,11 +658,18 @@ pub(crate) async fn post(
 
                 (Some(user.sub), Some(user.username))
             } else {
-                (None, None)
+                // Sessions of service accounts get a synthetic subject instead
+                let client = repo
+                    .oauth2_client()
+                    .lookup(session.client_id)
+                    .await?
+                    .ok_or(RouteError::CantLoadOAuthClient(session.client_id))?;
+
+                (client.service_account_subject(), None)
             };
 
             activity_tracker
-                .record_oauth2_session(&clock, &session, ip)
+                .record_oauth2_session(clock, &session, ip)
                 .await;
 
             INTROSPECTION_COUNTER.add(
@@ -382,22 +680,29 @@ pub(crate) async fn post(
                 ],
             );
 
-            IntrospectionResponse {
-                active: true,
-                scope: Some(session.scope),
-                client_id: Some(session.client_id.to_string()),
-                username,
-                token_type: Some(OAuthTokenTypeHint::RefreshToken),
-                exp: None,
-                expires_in: None,
-                iat: Some(refresh_token.created_at),
-                nbf: Some(refresh_token.created_at),
-                sub,
-                aud: None,
-                iss: None,
-                jti: Some(refresh_token.jti()),
-                device_id: None,
-            }
+            (
+                session.id,
+                IntrospectionResponse {
+                    active: true,
+                    scope: Some(session.scope),
+                    client_id: Some(session.client_id.to_string()),
+                    username,
+                    token_type: Some(OAuthTokenTypeHint::RefreshToken),
+                    exp: None,
+                    expires_in: None,
+                    iat: Some(refresh_token.created_at),
+                    nbf: Some(refresh_token.created_at),
+                    sub,
+                    aud: session.resource.map(String::from),
+                    iss: None,
+                    jti: Some(refresh_token.jti()),
+                    device_id: None,
+                    cnf: None,
+                    authorization_details: Some(session.authorization_details)
+                        .filter(|details| !details.is_empty()),
+                    act: None,
+                },
+            )
         }
 
         TokenType::CompatAccessToken => {
@@ -453,9 +758,11 @@ pub(crate) async fn post(
                 .collect();
 
             activity_tracker
-                .record_compat_session(&clock, &session, ip)
+                .record_compat_session(clock, &session, ip)
                 .await;
 
+            let act = impersonation_actor(&mut repo, &session).await?;
+
             INTROSPECTION_COUNTER.add(
                 1,
                 &[
@@ -464,24 +771,30 @@ pub(crate) async fn post(
                 ],
             );
 
-            IntrospectionResponse {
-                active: true,
-                scope: Some(scope),
-                client_id: Some("legacy".into()),
-                username: Some(user.username),
-                token_type: Some(OAuthTokenTypeHint::AccessToken),
-                exp: access_token.expires_at,
-                expires_in: access_token
-                    .expires_at
-                    .map(|expires_at| expires_at.signed_duration_since(clock.now())),
-                iat: Some(access_token.created_at),
-                nbf: Some(access_token.created_at),
-                sub: Some(user.sub),
-                aud: None,
-                iss: None,
-                jti: None,
-                device_id: session.device.map(Device::into),
-            }
+            (
+                session.id,
+                IntrospectionResponse {
+                    active: true,
+                    scope: Some(scope),
+                    client_id: Some("legacy".into()),
+                    username: Some(user.username),
+                    token_type: Some(OAuthTokenTypeHint::AccessToken),
+                    exp: access_token.expires_at,
+                    expires_in: access_token
+                        .expires_at
+                        .map(|expires_at| expires_at.signed_duration_since(clock.now())),
+                    iat: Some(access_token.created_at),
+                    nbf: Some(access_token.created_at),
+                    sub: Some(user.sub),
+                    aud: None,
+                    iss: None,
+                    jti: None,
+                    device_id: session.device.map(Device::into),
+                    cnf: None,
+                    authorization_details: None,
+                    act,
+                },
+            )
         }
 
         TokenType::CompatRefreshToken => {
@@ -537,9 +850,11 @@ pub(crate) async fn post(
                 .collect();
 
             activity_tracker
-                .record_compat_session(&clock, &session, ip)
+                .record_compat_session(clock, &session, ip)
                 .await;
 
+            let act = impersonation_actor(&mut repo, &session).await?;
+
             INTROSPECTION_COUNTER.add(
                 1,
                 &[
@@ -548,39 +863,57 @@ pub(crate) async fn post(
                 ],
             );
 
-            IntrospectionResponse {
-                active: true,
-                scope: Some(scope),
-                client_id: Some("legacy".into()),
-                username: Some(user.username),
-                token_type: Some(OAuthTokenTypeHint::RefreshToken),
-                exp: None,
-                expires_in: None,
-                iat: Some(refresh_token.created_at),
-                nbf: Some(refresh_token.created_at),
-                sub: Some(user.sub),
-                aud: None,
-                iss: None,
-                jti: None,
-                device_id: session.device.map(Device::into),
-            }
+            (
+                session.id,
+                IntrospectionResponse {
+                    active: true,
+                    scope: Some(scope),
+                    client_id: Some("legacy".into()),
+                    username: Some(user.username),
+                    token_type: Some(OAuthTokenTypeHint::RefreshToken),
+                    exp: None,
+                    expires_in: None,
+                    iat: Some(refresh_token.created_at),
+                    nbf: Some(refresh_token.created_at),
+                    sub: Some(user.sub),
+                    aud: None,
+                    iss: None,
+                    jti: None,
+                    device_id: session.device.map(Device::into),
+                    cnf: None,
+                    authorization_details: None,
+                    act,
+                },
+            )
         }
     };
 
-    repo.save().await?;
+    introspection_cache.insert(token, supports_explicit_device_id, session_id, &reply, now);
 
-    Ok(Json(reply))
+    Ok(reply)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use chrono::Duration;
-    use hyper::{Request, StatusCode};
+    use hyper::{
+        Request, StatusCode,
+        header::{ACCEPT, CONTENT_TYPE},
+    };
     use mas_data_model::{AccessToken, RefreshToken};
-    use mas_iana::oauth::OAuthTokenTypeHint;
+    use mas_iana::{
+        jose::JsonWebSignatureAlg,
+        oauth::{OAuthClientAuthenticationMethod, OAuthTokenTypeHint},
+    };
+    use mas_jose::{
+        jwk::{JsonWebKey, JsonWebKeyPublicParameters, PublicJsonWebKeySet},
+        jwt::{JsonWebSignatureHeader, Jwt},
+    };
     use mas_matrix::{HomeserverConnection, ProvisionRequest};
     use mas_router::{OAuth2Introspection, OAuth2RegistrationEndpoint, SimpleRoute};
-    use mas_storage::Clock;
+    use mas_storage::{Clock, oauth2::OAuth2StaticClientParams};
     use oauth2_types::{
         registration::ClientRegistrationResponse,
         requests::IntrospectionResponse,
@@ -588,10 +921,14 @@ mod tests {
     };
     use serde_json::json;
     use sqlx::PgPool;
+    use ulid::Ulid;
+    use url::Url;
     use zeroize::Zeroizing;
 
     use crate::{
-        oauth2::generate_token_pair,
+        oauth2::{
+            AccessTokenGenerator, generate_token_pair, introspection_cache::IntrospectionCache,
+        },
         test_utils::{RequestBuilderExt, ResponseExt, TestState, setup},
     };
 
@@ -671,6 +1008,8 @@ mod tests {
                 &mut state.rng(),
                 &state.clock,
                 &mut repo,
+                &AccessTokenGenerator::new(&state.url_builder, &state.key_store),
+                &client,
                 &session,
                 Duration::microseconds(5 * 60 * 1000 * 1000),
             )
@@ -986,4 +1325,292 @@ mod tests {
         let response: IntrospectionResponse = response.json();
         assert!(response.active);
     }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_introspection_cache_and_signed_response(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        state.introspection_cache =
+            IntrospectionCache::new(Some(Duration::try_seconds(30).unwrap()));
+
+        // Provision a client which will be used to do introspection requests
+        let request = Request::post(OAuth2RegistrationEndpoint::PATH).json(json!({
+            "client_uri": "https://introspecting.com/",
+            "grant_types": [],
+            "token_endpoint_auth_method": "client_secret_basic",
+        }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let client: ClientRegistrationResponse = response.json();
+        let introspecting_client_id = client.client_id;
+        let introspecting_client_secret = client.client_secret.unwrap();
+
+        // Provision a confidential client which will be used to generate and revoke
+        // tokens
+        let request = Request::post(OAuth2RegistrationEndpoint::PATH).json(json!({
+            "client_uri": "https://client.com/",
+            "redirect_uris": ["https://client.com/"],
+            "response_types": ["code"],
+            "grant_types": ["authorization_code", "refresh_token"],
+            "token_endpoint_auth_method": "client_secret_post",
+        }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let ClientRegistrationResponse {
+            client_id,
+            client_secret,
+            ..
+        } = response.json();
+        let client_secret = client_secret.unwrap();
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+
+        let mxid = state.homeserver_connection.mxid(&user.username);
+        state
+            .homeserver_connection
+            .provision_user(&ProvisionRequest::new(mxid, &user.sub))
+            .await
+            .unwrap();
+
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&client_id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let browser_session = repo
+            .browser_session()
+            .add(&mut state.rng(), &state.clock, &user, None)
+            .await
+            .unwrap();
+
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(
+                &mut state.rng(),
+                &state.clock,
+                &client,
+                &browser_session,
+                Scope::from_iter([OPENID]),
+            )
+            .await
+            .unwrap();
+
+        let (AccessToken { access_token, .. }, _) = generate_token_pair(
+            &mut state.rng(),
+            &state.clock,
+            &mut repo,
+            &AccessTokenGenerator::new(&state.url_builder, &state.key_store),
+            &client,
+            &session,
+            Duration::microseconds(5 * 60 * 1000 * 1000),
+        )
+        .await
+        .unwrap();
+
+        repo.save().await.unwrap();
+
+        let request = Request::post(OAuth2Introspection::PATH)
+            .basic_auth(&introspecting_client_id, &introspecting_client_secret)
+            .form(json!({ "token": access_token }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let response: IntrospectionResponse = response.json();
+        assert!(response.active);
+
+        // End the session behind the back of the cache
+        let mut repo = state.repository().await.unwrap();
+        repo.oauth2_session()
+            .finish(&state.clock, session)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        // The token is still reported as active, as the result is cached
+        let request = Request::post(OAuth2Introspection::PATH)
+            .basic_auth(&introspecting_client_id, &introspecting_client_secret)
+            .form(json!({ "token": access_token }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let response: IntrospectionResponse = response.json();
+        assert!(response.active);
+
+        // Until the cache entry expires
+        state.clock.advance(Duration::try_seconds(31).unwrap());
+        let request = Request::post(OAuth2Introspection::PATH)
+            .basic_auth(&introspecting_client_id, &introspecting_client_secret)
+            .form(json!({ "token": access_token }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let response: IntrospectionResponse = response.json();
+        assert!(!response.active);
+
+        // Start a new session, revoked through the revocation endpoint this time
+        let mut repo = state.repository().await.unwrap();
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(
+                &mut state.rng(),
+                &state.clock,
+                &client,
+                &browser_session,
+                Scope::from_iter([OPENID]),
+            )
+            .await
+            .unwrap();
+
+        let (AccessToken { access_token, .. }, _) = generate_token_pair(
+            &mut state.rng(),
+            &state.clock,
+            &mut repo,
+            &AccessTokenGenerator::new(&state.url_builder, &state.key_store),
+            &client,
+            &session,
+            Duration::microseconds(5 * 60 * 1000 * 1000),
+        )
+        .await
+        .unwrap();
+
+        repo.save().await.unwrap();
+
+        // Ask for a signed response
+        let request = Request::post(OAuth2Introspection::PATH)
+            .header(ACCEPT, "application/token-introspection+jwt")
+            .basic_auth(&introspecting_client_id, &introspecting_client_secret)
+            .form(json!({ "token": access_token }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "application/token-introspection+jwt");
+        let jwt: Jwt<'_, HashMap<String, serde_json::Value>> =
+            Jwt::try_from(response.body().as_str()).unwrap();
+        assert_eq!(jwt.header().typ(), Some("token-introspection+jwt"));
+        let claims = jwt.payload();
+        assert_eq!(claims["aud"], json!(introspecting_client_id));
+        assert_eq!(claims["token_introspection"]["active"], json!(true));
+        assert_eq!(claims["token_introspection"]["client_id"], json!(client_id));
+
+        let request = Request::post(mas_router::OAuth2Revocation::PATH).form(json!({
+            "token": access_token,
+            "client_id": client_id,
+            "client_secret": client_secret,
+        }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        // Revoking the token dropped it from the cache
+        let request = Request::post(OAuth2Introspection::PATH)
+            .header(ACCEPT, "application/token-introspection+jwt")
+            .basic_auth(&introspecting_client_id, &introspecting_client_secret)
+            .form(json!({ "token": access_token }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "application/token-introspection+jwt");
+        let jwt: Jwt<'_, HashMap<String, serde_json::Value>> =
+            Jwt::try_from(response.body().as_str()).unwrap();
+        assert_eq!(
+            jwt.payload()["token_introspection"],
+            json!({ "active": false })
+        );
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_client_jwks_refresh(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let mut rng = state.rng();
+        let mock_server = wiremock::MockServer::start().await;
+        let jwks_uri: Url = format!("{}/jwks", mock_server.uri()).parse().unwrap();
+
+        // The client has a key, and will rotate to a second one
+        let keys: Vec<_> = ["first", "second"]
+            .into_iter()
+            .map(|kid| (kid, mas_keystore::PrivateKey::generate_ec_p256(&mut rng)))
+            .collect();
+        let serve_jwks = |kid: &str| {
+            let (kid, key) = keys.iter().find(|(k, _)| *k == kid).unwrap();
+            let jwks = PublicJsonWebKeySet::new(vec![
+                JsonWebKey::new(JsonWebKeyPublicParameters::from(key)).with_kid(*kid),
+            ]);
+            wiremock::Mock::given(wiremock::matchers::method("GET"))
+                .and(wiremock::matchers::path("/jwks"))
+                .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(jwks))
+        };
+
+        // Provision a static client authenticating with private_key_jwt
+        let client_id = Ulid::from_string("01K0C4T6Y3F8H2M5Q9R1W7XBZD").unwrap();
+        let mut repo = state.repository().await.unwrap();
+        repo.oauth2_client()
+            .upsert_static(
+                client_id,
+                OAuthClientAuthenticationMethod::PrivateKeyJwt,
+                OAuth2StaticClientParams {
+                    jwks_uri: Some(jwks_uri),
+                    backchannel_logout_include_sub: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let mut introspect = |kid: &str| {
+            let (kid, key) = keys.iter().find(|(k, _)| *k == kid).unwrap();
+            let signer = key
+                .signing_key_for_alg(&JsonWebSignatureAlg::Es256)
+                .unwrap();
+            let header = JsonWebSignatureHeader::new(JsonWebSignatureAlg::Es256).with_kid(*kid);
+            let claims = json!({ "sub": client_id.to_string() });
+            let assertion = Jwt::sign_with_rng(&mut rng, header, claims, &signer)
+                .unwrap()
+                .into_string();
+
+            Request::post(OAuth2Introspection::PATH).form(json!({
+                "token": "some-token",
+                "client_assertion_type": "urn:ietf:params:oauth:client-assertion-type:jwt-bearer",
+                "client_assertion": assertion,
+            }))
+        };
+
+        let guard = serve_jwks("first")
+            .expect(1)
+            .mount_as_scoped(&mock_server)
+            .await;
+
+        // The JWKS is fetched once, and then cached
+        let response = state.request(introspect("first")).await;
+        response.assert_status(StatusCode::OK);
+        let response = state.request(introspect("first")).await;
+        response.assert_status(StatusCode::OK);
+        drop(guard);
+
+        // The client rotates its key
+        let guard = serve_jwks("second")
+            .expect(1)
+            .mount_as_scoped(&mock_server)
+            .await;
+
+        // Unknown key IDs don't trigger a new fetch right after the last one
+        let response = state.request(introspect("second")).await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+
+        // They do a minute later
+        state
+            .clock
+            .advance(Duration::microseconds(60 * 1000 * 1000));
+        let response = state.request(introspect("second")).await;
+        response.assert_status(StatusCode::OK);
+
+        // Known key IDs never do
+        let response = state.request(introspect("second")).await;
+        response.assert_status(StatusCode::OK);
+        drop(guard);
+    }
 }
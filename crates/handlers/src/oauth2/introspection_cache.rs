@@ -0,0 +1,143 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use oauth2_types::requests::IntrospectionResponse;
+use sha2::{Digest, Sha256};
+use ulid::Ulid;
+
+/// Maximum number of entries kept in the cache
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    token_hash: [u8; 32],
+
+    /// The response for compatibility tokens differs depending on whether the
+    /// introspecting client supports the device ID as a separate field
+    explicit_device_id: bool,
+}
+
+impl CacheKey {
+    fn new(token: &str, explicit_device_id: bool) -> Self {
+        Self {
+            token_hash: Sha256::digest(token).into(),
+            explicit_device_id,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    session_id: Ulid,
+    response: IntrospectionResponse,
+    valid_until: DateTime<Utc>,
+}
+
+/// A short-lived cache of the introspection results of active tokens
+///
+/// Entries are keyed by the hash of the token, and are kept for the
+/// configured time-to-live, capped to the expiration of the token. They are
+/// dropped when the session they belong to is revoked through this instance,
+/// but sessions ended by other means or on other instances are reported as
+/// active until their entry expires.
+///
+/// The cache is disabled if no time-to-live is set.
+#[derive(Debug, Clone, Default)]
+pub struct IntrospectionCache {
+    ttl: Option<Duration>,
+    entries: Arc<Mutex<HashMap<CacheKey, CacheEntry>>>,
+}
+
+impl IntrospectionCache {
+    /// Create a new cache, keeping entries for the given time-to-live
+    ///
+    /// If `ttl` is `None`, nothing is ever cached.
+    #[must_use]
+    pub fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            ttl,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Get the cached introspection result of a token, if it is still fresh
+    pub(crate) fn get(
+        &self,
+        token: &str,
+        explicit_device_id: bool,
+        now: DateTime<Utc>,
+    ) -> Option<IntrospectionResponse> {
+        self.ttl?;
+
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(&CacheKey::new(token, explicit_device_id))?;
+        if entry.valid_until <= now {
+            return None;
+        }
+
+        let mut response = entry.response.clone();
+        response.expires_in = response.exp.map(|exp| exp.signed_duration_since(now));
+        Some(response)
+    }
+
+    /// Cache the introspection result of an active token
+    pub(crate) fn insert(
+        &self,
+        token: &str,
+        explicit_device_id: bool,
+        session_id: Ulid,
+        response: &IntrospectionResponse,
+        now: DateTime<Utc>,
+    ) {
+        let Some(ttl) = self.ttl else {
+            return;
+        };
+
+        if !response.active {
+            return;
+        }
+
+        let valid_until = response
+            .exp
+            .map_or(now + ttl, |expires_at| expires_at.min(now + ttl));
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, entry| entry.valid_until > now);
+
+            // Don't grow the cache further if all the entries are still fresh
+            if entries.len() >= MAX_ENTRIES {
+                return;
+            }
+        }
+
+        entries.insert(
+            CacheKey::new(token, explicit_device_id),
+            CacheEntry {
+                session_id,
+                response: response.clone(),
+                valid_until,
+            },
+        );
+    }
+
+    /// Drop all the cached results of the tokens of a session
+    pub(crate) fn invalidate_session(&self, session_id: Ulid) {
+        if self.ttl.is_none() {
+            return;
+        }
+
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|_, entry| entry.session_id != session_id);
+    }
+}
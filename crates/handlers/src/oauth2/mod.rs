@@ -4,31 +4,46 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
+use axum::BoxError;
 use chrono::Duration;
+use mas_axum_utils::client_authorization::fetch_jwks;
 use mas_data_model::{
-    AccessToken, Authentication, AuthorizationGrant, BrowserSession, Client, RefreshToken, Session,
-    TokenType,
+    AccessToken, AccessTokenFormat, Authentication, AuthorizationGrant, BrowserSession,
+    BrowserSessionElevation, Client, RefreshToken, Session, SiteConfig, TokenType, User,
+    UserAttributeDefinition,
 };
-use mas_iana::jose::JsonWebSignatureAlg;
+use mas_iana::jose::{JsonWebEncryptionEnc, JsonWebSignatureAlg};
 use mas_jose::{
     claims::{self, hash_token},
     constraints::Constrainable,
+    jwe::{JsonWebEncryptionHeader, Jwe, JweEncryptionError},
     jwt::{JsonWebSignatureHeader, Jwt},
 };
 use mas_keystore::Keystore;
 use mas_router::UrlBuilder;
-use mas_storage::{Clock, RepositoryAccess};
+use mas_storage::{Clock, RepositoryAccess, RepositoryError};
 use thiserror::Error;
+use ulid::Ulid;
+use url::Url;
 
+pub(crate) mod acr;
 pub mod authorization;
+pub mod backchannel;
 pub mod device;
 pub mod discovery;
+mod dpop;
+pub mod end_session;
 pub mod introspection;
+pub(crate) mod introspection_cache;
 pub mod keys;
+mod pairwise;
+pub mod pushed_authorization_request;
 pub mod registration;
+mod request_object;
 pub mod revoke;
+mod software_statement;
 pub mod token;
 pub mod userinfo;
 pub mod webfinger;
@@ -49,26 +64,44 @@ pub(crate) fn generate_id_token(
     clock: &impl Clock,
     url_builder: &UrlBuilder,
     key_store: &Keystore,
+    site_config: &SiteConfig,
     client: &Client,
     grant: Option<&AuthorizationGrant>,
     browser_session: &BrowserSession,
+    sub: &str,
     access_token: Option<&AccessToken>,
     last_authentication: Option<&Authentication>,
+    elevation: Option<&BrowserSessionElevation>,
+    custom_claims: HashMap<String, serde_json::Value>,
 ) -> Result<String, IdTokenSignatureError> {
-    let mut claims = HashMap::new();
+    // Start with the custom claims, so that they can't override the standard ones
+    let mut claims = custom_claims;
     let now = clock.now();
     claims::ISS.insert(&mut claims, url_builder.oidc_issuer().to_string())?;
-    claims::SUB.insert(&mut claims, &browser_session.user.sub)?;
+    claims::SUB.insert(&mut claims, sub)?;
     claims::AUD.insert(&mut claims, client.client_id.clone())?;
     claims::IAT.insert(&mut claims, now)?;
     claims::EXP.insert(&mut claims, now + Duration::try_hours(1).unwrap())?;
 
+    // The session ID is used to match back-channel logout tokens to this session
+    claims::SID.insert(&mut claims, browser_session.id.to_string())?;
+
     if let Some(nonce) = grant.and_then(|grant| grant.nonce.as_ref()) {
         claims::NONCE.insert(&mut claims, nonce)?;
     }
 
     if let Some(last_authentication) = last_authentication {
         claims::AUTH_TIME.insert(&mut claims, last_authentication.created_at)?;
+
+        let amr = last_authentication.authentication_method.amr();
+        if !amr.is_empty() {
+            let amr: Vec<String> = amr.iter().map(|&method| method.to_owned()).collect();
+            claims::AMR.insert(&mut claims, amr)?;
+        }
+    }
+
+    if let Some(acr) = acr::satisfied_acr(site_config, last_authentication, elevation) {
+        claims::ACR.insert(&mut claims, acr)?;
     }
 
     let alg = client
@@ -95,14 +128,264 @@ pub(crate) fn generate_id_token(
     Ok(id_token.into_string())
 }
 
-pub(crate) async fn generate_token_pair<R: RepositoryAccess>(
-    rng: &mut (impl rand::RngCore + Send),
+#[derive(Debug, Error)]
+pub(crate) enum IdTokenEncryptionError {
+    #[error("The client has no JWKS to encrypt ID tokens with")]
+    MissingJwks,
+
+    #[error("Failed to fetch the client JWKS")]
+    JwksFetch(#[source] BoxError),
+
+    #[error(transparent)]
+    Encryption(#[from] JweEncryptionError),
+}
+
+/// Encrypt the ID token for the client, if it asked for encrypted ID tokens
+/// during registration. The signed ID token is returned as-is otherwise.
+pub(crate) async fn encrypt_id_token(
+    rng: &mut (impl rand::RngCore + rand::CryptoRng),
+    http_client: &reqwest::Client,
+    client: &Client,
+    id_token: String,
+) -> Result<String, IdTokenEncryptionError> {
+    let Some(alg) = client.id_token_encrypted_response_alg.clone() else {
+        return Ok(id_token);
+    };
+
+    let enc = client
+        .id_token_encrypted_response_enc
+        .clone()
+        .unwrap_or(JsonWebEncryptionEnc::A128CbcHs256);
+
+    let jwks = client
+        .jwks
+        .as_ref()
+        .ok_or(IdTokenEncryptionError::MissingJwks)?;
+    let jwks = fetch_jwks(http_client, jwks)
+        .await
+        .map_err(IdTokenEncryptionError::JwksFetch)?;
+
+    // The ID token is a nested JWT, as per OpenID Connect Core section 10.2
+    let header = JsonWebEncryptionHeader::new(alg, enc).with_cty("JWT".to_owned());
+    let jwe = Jwe::encrypt_with_jwks(rng, header, id_token.as_bytes(), &jwks)?;
+
+    Ok(jwe.into_string())
+}
+
+/// Load the values of the public custom attributes of a user, keyed by the
+/// name of the claim under which they are exposed to clients
+pub(crate) async fn user_attribute_claims<R: RepositoryAccess>(
+    repo: &mut R,
+    site_config: &SiteConfig,
+    user: &User,
+) -> Result<HashMap<String, serde_json::Value>, R::Error> {
+    // Avoid hitting the database if no attribute is exposed to clients
+    if site_config
+        .user_attributes
+        .iter()
+        .all(|a| a.claim.is_none())
+    {
+        return Ok(HashMap::new());
+    }
+
+    let entries = repo
+        .user_metadata()
+        .all_in_namespace(user, UserAttributeDefinition::METADATA_NAMESPACE)
+        .await?;
+
+    let claims = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let definition = site_config
+                .user_attributes
+                .iter()
+                .find(|definition| definition.name == entry.key)?;
+            let claim = definition.claim.clone()?;
+            Some((claim, entry.value))
+        })
+        .collect();
+
+    Ok(claims)
+}
+
+/// Load the values of the standard claims a client requested individually
+/// with the `claims` parameter, keyed by the name of the claim.
+///
+/// Claims which are not supported are ignored, as mandated by OpenID Connect
+/// Core section 5.5.
+pub(crate) async fn requested_claims<'a, R: RepositoryAccess>(
+    repo: &mut R,
+    user: &User,
+    names: impl IntoIterator<Item = &'a str>,
+) -> Result<HashMap<String, serde_json::Value>, R::Error> {
+    let names: BTreeSet<&str> = names.into_iter().collect();
+    let mut claims = HashMap::new();
+
+    if names.contains("preferred_username") {
+        claims.insert(
+            "preferred_username".to_owned(),
+            user.username.clone().into(),
+        );
+    }
+
+    if names.contains("email") || names.contains("email_verified") {
+        let email = repo.user_email().primary(user).await?;
+
+        if let Some(email) = email {
+            if names.contains("email") {
+                claims.insert("email".to_owned(), email.email.into());
+            }
+
+            // Email addresses are only added to users once they are verified
+            if names.contains("email_verified") {
+                claims.insert("email_verified".to_owned(), true.into());
+            }
+        }
+    }
+
+    Ok(claims)
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum TokenGenerationError {
+    #[error(transparent)]
+    Repository(#[from] RepositoryError),
+
+    #[error("could not find user {0}")]
+    UserNotFound(Ulid),
+
+    #[error("could not sign the access token")]
+    Signature(#[from] IdTokenSignatureError),
+}
+
+/// Generates access tokens in the format configured for each client
+pub(crate) struct AccessTokenGenerator<'a> {
+    url_builder: &'a UrlBuilder,
+    key_store: &'a Keystore,
+    dpop_jkt: Option<&'a str>,
+}
+
+impl<'a> AccessTokenGenerator<'a> {
+    pub(crate) fn new(url_builder: &'a UrlBuilder, key_store: &'a Keystore) -> Self {
+        Self {
+            url_builder,
+            key_store,
+            dpop_jkt: None,
+        }
+    }
+
+    /// Bind the JWT access tokens to the DPoP key with the given thumbprint
+    #[must_use]
+    pub(crate) fn with_dpop_jkt(mut self, dpop_jkt: Option<&'a str>) -> Self {
+        self.dpop_jkt = dpop_jkt;
+        self
+    }
+
+    /// Generate a new access token for the session.
+    ///
+    /// JWT access tokens still have to be recorded like opaque ones, so that
+    /// they can be introspected and revoked.
+    pub(crate) async fn generate<R: RepositoryAccess<Error = RepositoryError>>(
+        &self,
+        rng: &mut (impl rand::RngCore + rand::CryptoRng + Send),
+        clock: &impl Clock,
+        repo: &mut R,
+        client: &Client,
+        session: &Session,
+        ttl: Duration,
+    ) -> Result<String, TokenGenerationError> {
+        let access_token = match client.access_token_format {
+            AccessTokenFormat::Opaque => TokenType::AccessToken.generate(rng),
+            AccessTokenFormat::Jwt => {
+                // Tokens without a user are for the client itself
+                let sub = match session.user_id {
+                    Some(user_id) => {
+                        repo.user()
+                            .lookup(user_id)
+                            .await?
+                            .ok_or(TokenGenerationError::UserNotFound(user_id))?
+                            .sub
+                    }
+                    None => client
+                        .service_account_subject()
+                        .unwrap_or_else(|| client.client_id.clone()),
+                };
+
+                self.sign(rng, clock, client, session, sub, ttl)?
+            }
+        };
+
+        Ok(access_token)
+    }
+
+    /// Sign a JWT access token, following the RFC 9068 profile
+    fn sign(
+        &self,
+        rng: &mut (impl rand::RngCore + rand::CryptoRng),
+        clock: &impl Clock,
+        client: &Client,
+        session: &Session,
+        sub: String,
+        ttl: Duration,
+    ) -> Result<String, IdTokenSignatureError> {
+        let now = clock.now();
+
+        // Tokens restricted to a resource have it as their audience, the other
+        // ones are meant for the client itself
+        let audience = session
+            .resource
+            .as_ref()
+            .map_or_else(|| client.client_id.clone(), Url::to_string);
+
+        let mut claims = HashMap::new();
+        claims::ISS.insert(&mut claims, self.url_builder.oidc_issuer().to_string())?;
+        claims::SUB.insert(&mut claims, sub)?;
+        claims::AUD.insert(&mut claims, audience)?;
+        claims::CLIENT_ID.insert(&mut claims, client.client_id.clone())?;
+        claims::SCOPE.insert(&mut claims, session.scope.to_string())?;
+        claims::IAT.insert(&mut claims, now)?;
+        claims::EXP.insert(&mut claims, now + ttl)?;
+        claims::JTI.insert(
+            &mut claims,
+            Ulid::from_datetime_with_source(now.into(), rng).to_string(),
+        )?;
+
+        if let Some(jkt) = self.dpop_jkt {
+            claims::CNF.insert(&mut claims, serde_json::json!({ "jkt": jkt }))?;
+        }
+
+        // Access tokens are signed the same way as ID tokens
+        let alg = client
+            .id_token_signed_response_alg
+            .clone()
+            .unwrap_or(JsonWebSignatureAlg::Rs256);
+        let key = self
+            .key_store
+            .signing_key_for_algorithm(&alg)
+            .ok_or(IdTokenSignatureError::InvalidSigningKey)?;
+
+        let signer = key.params().signing_key_for_alg(&alg)?;
+        let header = JsonWebSignatureHeader::new(alg)
+            .with_kid(key.kid().ok_or(IdTokenSignatureError::InvalidSigningKey)?)
+            .with_typ("at+jwt".to_owned());
+        let access_token = Jwt::sign_with_rng(rng, header, claims, &signer)?;
+
+        Ok(access_token.into_string())
+    }
+}
+
+pub(crate) async fn generate_token_pair<R: RepositoryAccess<Error = RepositoryError>>(
+    rng: &mut (impl rand::RngCore + rand::CryptoRng + Send),
     clock: &impl Clock,
     repo: &mut R,
+    access_tokens: &AccessTokenGenerator<'_>,
+    client: &Client,
     session: &Session,
     ttl: Duration,
-) -> Result<(AccessToken, RefreshToken), R::Error> {
-    let access_token_str = TokenType::AccessToken.generate(rng);
+) -> Result<(AccessToken, RefreshToken), TokenGenerationError> {
+    let access_token_str = access_tokens
+        .generate(rng, clock, repo, client, session, ttl)
+        .await?;
     let refresh_token_str = TokenType::RefreshToken.generate(rng);
 
     let access_token = repo
@@ -0,0 +1,118 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Pairwise subject identifiers, as per [OpenID Connect Core 1.0 §8.1].
+//!
+//! Clients registered with the `pairwise` subject type get a different `sub`
+//! for the same user than the clients of other sectors. The identifiers are
+//! random, and stored so that they can be mapped back to the user.
+//!
+//! [OpenID Connect Core 1.0 §8.1]: https://openid.net/specs/openid-connect-core-1_0.html#PairwiseAlg
+
+use std::collections::BTreeSet;
+
+use axum::BoxError;
+use mas_data_model::{Client, User};
+use mas_http::RequestBuilderExt as _;
+use mas_storage::{Clock, RepositoryAccess, RepositoryError};
+use oauth2_types::{oidc::SubjectType, registration::VerifiedClientMetadata};
+use thiserror::Error;
+use url::Url;
+
+#[derive(Debug, Error)]
+pub(crate) enum SectorIdentifierError {
+    #[error("failed to fetch the sector_identifier_uri")]
+    Fetch(#[source] BoxError),
+
+    #[error("redirect_uri {0} is not listed in the sector_identifier_uri")]
+    RedirectUriNotListed(Url),
+
+    #[error("a sector_identifier_uri is required when the redirect_uris don't share a single host")]
+    MissingSectorIdentifierUri,
+}
+
+/// Fetch the list of redirect URIs served at a `sector_identifier_uri`
+async fn fetch_redirect_uris(
+    http_client: &reqwest::Client,
+    sector_identifier_uri: &Url,
+) -> Result<Vec<Url>, BoxError> {
+    let redirect_uris = http_client
+        .get(sector_identifier_uri.as_str())
+        .send_traced()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(redirect_uris)
+}
+
+/// Validate the `sector_identifier_uri` of a client registration, and compute
+/// the sector identifier of the client if it asked for pairwise subject
+/// identifiers.
+///
+/// The document served at the `sector_identifier_uri` must list all the
+/// redirect URIs of the client. The sector identifier is the host of the
+/// `sector_identifier_uri`, or the host of the redirect URIs if it is not set.
+///
+/// Returns `None` if the client uses public subject identifiers.
+pub(crate) async fn sector_identifier(
+    http_client: &reqwest::Client,
+    metadata: &VerifiedClientMetadata,
+) -> Result<Option<String>, SectorIdentifierError> {
+    if let Some(sector_identifier_uri) = &metadata.sector_identifier_uri {
+        let listed = fetch_redirect_uris(http_client, sector_identifier_uri)
+            .await
+            .map_err(SectorIdentifierError::Fetch)?;
+
+        for redirect_uri in metadata.redirect_uris() {
+            if !listed.contains(redirect_uri) {
+                return Err(SectorIdentifierError::RedirectUriNotListed(
+                    redirect_uri.clone(),
+                ));
+            }
+        }
+    }
+
+    if metadata.subject_type != Some(SubjectType::Pairwise) {
+        return Ok(None);
+    }
+
+    // The `sector_identifier_uri` is always an HTTPS URL, so it has a host
+    if let Some(host) = metadata
+        .sector_identifier_uri
+        .as_ref()
+        .and_then(Url::host_str)
+    {
+        return Ok(Some(host.to_owned()));
+    }
+
+    let hosts: Option<BTreeSet<&str>> =
+        metadata.redirect_uris().iter().map(Url::host_str).collect();
+
+    match hosts {
+        Some(hosts) if hosts.len() == 1 => Ok(hosts.into_iter().next().map(ToOwned::to_owned)),
+        _ => Err(SectorIdentifierError::MissingSectorIdentifierUri),
+    }
+}
+
+/// Get the subject identifier of a user as seen by the given client: their
+/// pairwise subject identifier if the client has a sector identifier, their
+/// public one otherwise
+pub(crate) async fn subject_for_client<R: RepositoryAccess<Error = RepositoryError>>(
+    rng: &mut (impl rand::RngCore + Send),
+    clock: &impl Clock,
+    repo: &mut R,
+    client: &Client,
+    user: &User,
+) -> Result<String, RepositoryError> {
+    let Some(sector_identifier) = &client.pairwise_sector_identifier else {
+        return Ok(user.sub.clone());
+    };
+
+    repo.oauth2_pairwise_subject()
+        .find_or_add(rng, clock, user, sector_identifier)
+        .await
+}
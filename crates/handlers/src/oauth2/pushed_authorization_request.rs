@@ -0,0 +1,425 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::BTreeMap;
+
+use axum::{Json, extract::State, response::IntoResponse};
+use axum_extra::typed_header::TypedHeader;
+use chrono::Duration;
+use headers::{CacheControl, Pragma};
+use hyper::StatusCode;
+use mas_axum_utils::{
+    ClientJwksCache,
+    client_authorization::{ClientAuthorization, CredentialsVerificationError},
+    record_error,
+};
+use mas_data_model::SiteConfig;
+use mas_keystore::Encrypter;
+use mas_storage::{BoxClock, BoxRepository, BoxRng};
+use oauth2_types::{
+    errors::{ClientError, ClientErrorCode},
+    requests::{AuthorizationRequest, PushedAuthorizationResponse},
+};
+use rand::distributions::{Alphanumeric, DistString};
+use thiserror::Error;
+use ulid::Ulid;
+
+use super::request_object::{self, RequestObjectError};
+use crate::{IssuerUrlBuilder, impl_from_error_for_route};
+
+/// How long a pushed authorization request can be used for. RFC9126 recommends
+/// keeping this short, as the client is expected to redirect the user right
+/// away.
+const EXPIRES_IN: Duration = Duration::microseconds(60 * 1000 * 1000);
+
+#[derive(Debug, Error)]
+pub(crate) enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("bad request")]
+    BadRequest,
+
+    #[error("client not found")]
+    ClientNotFound,
+
+    #[error("client {0} is not allowed to push authorization requests")]
+    ClientNotAllowed(Ulid),
+
+    #[error("invalid client credentials for client {client_id}")]
+    InvalidClientCredentials {
+        client_id: Ulid,
+        #[source]
+        source: CredentialsVerificationError,
+    },
+
+    #[error("could not verify client credentials for client {client_id}")]
+    ClientCredentialsVerification {
+        client_id: Ulid,
+        #[source]
+        source: CredentialsVerificationError,
+    },
+
+    #[error("the request_uri parameter can't be pushed")]
+    RequestUriPushed,
+
+    #[error("invalid request object")]
+    InvalidRequestObject(#[from] RequestObjectError),
+
+    #[error("client {0} must use signed request objects")]
+    SignedRequestObjectRequired(Ulid),
+
+    #[error("invalid authorization request parameters")]
+    InvalidParameters(#[source] serde_urlencoded::de::Error),
+
+    #[error("invalid redirect uri")]
+    InvalidRedirectUri(#[from] mas_data_model::InvalidRedirectUriError),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+impl_from_error_for_route!(serde_urlencoded::ser::Error);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+
+        let response = match self {
+            Self::Internal(_) | Self::ClientCredentialsVerification { .. } => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ClientError::from(ClientErrorCode::ServerError)),
+            ),
+            Self::BadRequest => (
+                StatusCode::BAD_REQUEST,
+                Json(ClientError::from(ClientErrorCode::InvalidRequest)),
+            ),
+            Self::ClientNotFound | Self::InvalidClientCredentials { .. } => (
+                StatusCode::UNAUTHORIZED,
+                Json(ClientError::from(ClientErrorCode::InvalidClient)),
+            ),
+            Self::ClientNotAllowed(_) => (
+                StatusCode::UNAUTHORIZED,
+                Json(ClientError::from(ClientErrorCode::UnauthorizedClient)),
+            ),
+            Self::RequestUriPushed => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidRequest).with_description(
+                        "The request_uri parameter is not allowed in pushed authorization requests"
+                            .to_owned(),
+                    ),
+                ),
+            ),
+            Self::InvalidRequestObject(ref e) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidRequestObject)
+                        .with_description(e.to_string()),
+                ),
+            ),
+            Self::SignedRequestObjectRequired(_) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidRequest).with_description(
+                        "This client must send its parameters in a signed request object"
+                            .to_owned(),
+                    ),
+                ),
+            ),
+            Self::InvalidParameters(ref e) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidRequest)
+                        .with_description(e.to_string()),
+                ),
+            ),
+            Self::InvalidRedirectUri(_) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidRequest)
+                        .with_description("Invalid redirect_uri parameter".to_owned()),
+                ),
+            ),
+        };
+
+        (sentry_event_id, response).into_response()
+    }
+}
+
+#[tracing::instrument(
+    name = "handlers.oauth2.pushed_authorization_request.post",
+    fields(client.id = client_authorization.client_id()),
+    skip_all,
+)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    mut repo: BoxRepository,
+    State(http_client): State<reqwest::Client>,
+    State(jwks_cache): State<ClientJwksCache>,
+    State(encrypter): State<Encrypter>,
+    IssuerUrlBuilder(url_builder): IssuerUrlBuilder,
+    State(site_config): State<SiteConfig>,
+    client_authorization: ClientAuthorization<BTreeMap<String, String>>,
+) -> Result<impl IntoResponse, RouteError> {
+    let client = client_authorization
+        .credentials
+        .fetch(&mut repo)
+        .await?
+        .ok_or(RouteError::ClientNotFound)?;
+
+    // Reuse the token endpoint auth method to verify the client
+    let method = client
+        .token_endpoint_auth_method
+        .as_ref()
+        .ok_or(RouteError::ClientNotAllowed(client.id))?;
+
+    client_authorization
+        .credentials
+        .verify(
+            &http_client,
+            &jwks_cache,
+            &encrypter,
+            &clock,
+            method,
+            &client,
+        )
+        .await
+        .map_err(|err| {
+            if err.is_internal() {
+                RouteError::ClientCredentialsVerification {
+                    client_id: client.id,
+                    source: err,
+                }
+            } else {
+                RouteError::InvalidClientCredentials {
+                    client_id: client.id,
+                    source: err,
+                }
+            }
+        })?;
+
+    let Some(mut parameters) = client_authorization.form else {
+        return Err(RouteError::BadRequest);
+    };
+
+    // A pushed request can't itself reference another request
+    if parameters.contains_key("request_uri") {
+        return Err(RouteError::RequestUriPushed);
+    }
+
+    // Signed request objects are verified now, and only the parameters they
+    // carry are stored
+    if let Some(request_object_parameters) =
+        request_object::load(&http_client, &clock, &url_builder, &client, &parameters).await?
+    {
+        parameters = request_object_parameters;
+    } else if client.require_signed_request_object {
+        return Err(RouteError::SignedRequestObjectRequired(client.id));
+    }
+
+    // Validate the parameters up-front, so that the client gets the error
+    // directly instead of at the authorization endpoint. The client credentials
+    // were stripped from the form, so add the client ID back before parsing.
+    let mut full_parameters = parameters.clone();
+    full_parameters.insert("client_id".to_owned(), client.client_id.clone());
+    let encoded = serde_urlencoded::to_string(&full_parameters)?;
+    let request: AuthorizationRequest =
+        serde_urlencoded::from_str(&encoded).map_err(RouteError::InvalidParameters)?;
+    client.resolve_redirect_uri(&request.redirect_uri, site_config.oauth2_1_strict)?;
+
+    let reference = Alphanumeric.sample_string(&mut rng, 32);
+
+    let pushed_request = repo
+        .oauth2_pushed_authorization_request()
+        .add(&mut rng, &clock, &client, reference, parameters, EXPIRES_IN)
+        .await?;
+
+    repo.save().await?;
+
+    let response = PushedAuthorizationResponse {
+        request_uri: pushed_request.request_uri(),
+        expires_in: EXPIRES_IN,
+    };
+
+    Ok((
+        StatusCode::CREATED,
+        TypedHeader(CacheControl::new().with_no_store()),
+        TypedHeader(Pragma::no_cache()),
+        Json(response),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use mas_router::SimpleRoute;
+    use mas_storage::{RepositoryAccess, oauth2::OAuth2StaticClientParams};
+    use oauth2_types::{
+        errors::{ClientError, ClientErrorCode},
+        registration::ClientRegistrationResponse,
+        requests::PushedAuthorizationResponse,
+    };
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    async fn register_client(state: &TestState) -> String {
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+        response.client_id
+    }
+
+    fn authorize_url(client_id: &str, request_uri: &str) -> String {
+        let query =
+            serde_urlencoded::to_string([("client_id", client_id), ("request_uri", request_uri)])
+                .unwrap();
+        format!("{}?{query}", mas_router::OAuth2AuthorizationEndpoint::PATH)
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_pushed_authorization_request(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let client_id = register_client(&state).await;
+
+        let request = Request::post(mas_router::OAuth2PushedAuthorizationRequestEndpoint::PATH)
+            .form(serde_json::json!({
+                "client_id": client_id,
+                "response_type": "code",
+                "redirect_uri": "https://example.com/callback",
+                "scope": "openid",
+                "state": "abcdef",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let pushed: PushedAuthorizationResponse = response.json();
+        assert!(
+            pushed
+                .request_uri
+                .starts_with("urn:ietf:params:oauth:request_uri:")
+        );
+        assert_eq!(pushed.expires_in.num_seconds(), 60);
+
+        // Using the request_uri at the authorization endpoint should start the
+        // flow, and redirect to the login page since we don't have a session
+        let request = Request::get(authorize_url(&client_id, &pushed.request_uri)).empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        let location = response.headers().get(hyper::header::LOCATION).unwrap();
+        assert!(location.to_str().unwrap().starts_with("/login"));
+
+        // The request_uri can only be used once
+        let request = Request::get(authorize_url(&client_id, &pushed.request_uri)).empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // Unknown request_uris are rejected
+        let request = Request::get(authorize_url(
+            &client_id,
+            "urn:ietf:params:oauth:request_uri:unknown",
+        ))
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_pushed_authorization_request_invalid(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let client_id = register_client(&state).await;
+
+        // Unregistered redirect URIs are rejected right away
+        let request = Request::post(mas_router::OAuth2PushedAuthorizationRequestEndpoint::PATH)
+            .form(serde_json::json!({
+                "client_id": client_id,
+                "response_type": "code",
+                "redirect_uri": "https://example.com/other",
+                "scope": "openid",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let error: ClientError = response.json();
+        assert_eq!(error.error, ClientErrorCode::InvalidRequest);
+
+        // Pushing a request_uri isn't allowed
+        let request = Request::post(mas_router::OAuth2PushedAuthorizationRequestEndpoint::PATH)
+            .form(serde_json::json!({
+                "client_id": client_id,
+                "request_uri": "urn:ietf:params:oauth:request_uri:abcdef",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let error: ClientError = response.json();
+        assert_eq!(error.error, ClientErrorCode::InvalidRequest);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_require_pushed_authorization_requests(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Provision a static client which requires pushed authorization requests
+        let client_id = ulid::Ulid::from_string("01J8PSH0Q5ZG0F7G5TQF4Q1J5V").unwrap();
+        let mut repo = state.repository().await.unwrap();
+        repo.oauth2_client()
+            .upsert_static(
+                client_id,
+                mas_iana::oauth::OAuthClientAuthenticationMethod::None,
+                OAuth2StaticClientParams {
+                    redirect_uris: vec!["https://example.com/callback".parse().unwrap()],
+                    require_pushed_authorization_requests: true,
+                    backchannel_logout_include_sub: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        let client_id = client_id.to_string();
+
+        // Sending the parameters directly to the authorization endpoint fails
+        let query = serde_urlencoded::to_string([
+            ("client_id", client_id.as_str()),
+            ("response_type", "code"),
+            ("redirect_uri", "https://example.com/callback"),
+            ("scope", "openid"),
+        ])
+        .unwrap();
+        let request = Request::get(format!(
+            "{}?{query}",
+            mas_router::OAuth2AuthorizationEndpoint::PATH
+        ))
+        .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // Pushing them first works
+        let request = Request::post(mas_router::OAuth2PushedAuthorizationRequestEndpoint::PATH)
+            .form(serde_json::json!({
+                "client_id": client_id,
+                "response_type": "code",
+                "redirect_uri": "https://example.com/callback",
+                "scope": "openid",
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let pushed: PushedAuthorizationResponse = response.json();
+
+        let request = Request::get(authorize_url(&client_id, &pushed.request_uri)).empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+    }
+}
@@ -10,7 +10,9 @@ use axum::{Json, extract::State, response::IntoResponse};
 use axum_extra::TypedHeader;
 use hyper::StatusCode;
 use mas_axum_utils::record_error;
+use mas_data_model::SiteConfig;
 use mas_iana::oauth::OAuthClientAuthenticationMethod;
+use mas_jose::jwa::{SUPPORTED_CONTENT_ENCRYPTION_ALGORITHMS, SUPPORTED_ENCRYPTION_ALGORITHMS};
 use mas_keystore::Encrypter;
 use mas_policy::{EvaluationResult, Policy};
 use mas_storage::{BoxClock, BoxRepository, BoxRng, oauth2::OAuth2ClientRepository};
@@ -20,6 +22,7 @@ use oauth2_types::{
         ClientMetadata, ClientMetadataVerificationError, ClientRegistrationResponse, Localized,
         VerifiedClientMetadata,
     },
+    requests::GrantType,
 };
 use opentelemetry::{Key, KeyValue, metrics::Counter};
 use psl::Psl;
@@ -30,6 +33,11 @@ use thiserror::Error;
 use tracing::info;
 use url::Url;
 
+use super::{
+    device::authorize::DEFAULT_DEVICE_CODE_TTL,
+    pairwise::{self, SectorIdentifierError},
+    software_statement::{self, SoftwareStatementError},
+};
 use crate::{BoundActivityTracker, METER, impl_from_error_for_route};
 
 static REGISTRATION_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
@@ -52,11 +60,32 @@ pub(crate) enum RouteError {
     #[error("invalid client metadata")]
     InvalidClientMetadata(#[from] ClientMetadataVerificationError),
 
+    #[error("invalid software statement")]
+    SoftwareStatement(#[from] SoftwareStatementError),
+
     #[error("{0} is a public suffix, not a valid domain")]
     UrlIsPublicSuffix(&'static str),
 
+    #[error("{0} is longer than allowed by the server")]
+    LifetimeTooLong(&'static str),
+
+    #[error("{0} is not supported by the server")]
+    UnsupportedAlgorithm(&'static str),
+
+    #[error("a JWK Set is required to encrypt ID tokens")]
+    MissingJwksForIdTokenEncryption,
+
+    #[error("unknown ACR value {0:?}")]
+    UnknownAcrValue(String),
+
+    #[error("the {0} grant type is not allowed")]
+    GrantTypeNotAllowed(GrantType),
+
     #[error("client registration denied by the policy: {0}")]
     PolicyDenied(EvaluationResult),
+
+    #[error("invalid sector identifier")]
+    SectorIdentifier(#[from] SectorIdentifierError),
 }
 
 impl_from_error_for_route!(mas_storage::RepositoryError);
@@ -67,7 +96,10 @@ impl_from_error_for_route!(serde_json::Error);
 
 impl IntoResponse for RouteError {
     fn into_response(self) -> axum::response::Response {
-        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let sentry_event_id = record_error!(
+            self,
+            Self::Internal(_) | Self::SoftwareStatement(SoftwareStatementError::JwksFetch(_))
+        );
 
         REGISTRATION_COUNTER.add(1, &[KeyValue::new(RESULT, "denied")]);
 
@@ -140,6 +172,96 @@ impl IntoResponse for RouteError {
             )
                 .into_response(),
 
+            Self::LifetimeTooLong(field) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidClientMetadata)
+                        .with_description(format!("{field} is longer than allowed")),
+                ),
+            )
+                .into_response(),
+
+            // We failed to fetch the keys of a trusted issuer, which is not the client's fault
+            Self::SoftwareStatement(SoftwareStatementError::JwksFetch(_)) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ClientError::from(ClientErrorCode::ServerError)),
+            )
+                .into_response(),
+
+            Self::SoftwareStatement(SoftwareStatementError::UntrustedIssuer) => (
+                StatusCode::BAD_REQUEST,
+                Json(ClientError::from(
+                    ClientErrorCode::UnapprovedSoftwareStatement,
+                )),
+            )
+                .into_response(),
+
+            Self::SoftwareStatement(e) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidSoftwareStatement)
+                        .with_description(e.to_string()),
+                ),
+            )
+                .into_response(),
+
+            Self::UnsupportedAlgorithm(field) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidClientMetadata)
+                        .with_description(format!("{field} is not supported")),
+                ),
+            )
+                .into_response(),
+
+            Self::MissingJwksForIdTokenEncryption => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidClientMetadata).with_description(
+                        "jwks or jwks_uri is required to encrypt ID tokens".to_owned(),
+                    ),
+                ),
+            )
+                .into_response(),
+
+            Self::UnknownAcrValue(value) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidClientMetadata)
+                        .with_description(format!("default_acr_values: unknown value {value:?}")),
+                ),
+            )
+                .into_response(),
+
+            Self::GrantTypeNotAllowed(grant_type) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidClientMetadata)
+                        .with_description(format!("grant_types: {grant_type} is not allowed")),
+                ),
+            )
+                .into_response(),
+
+            // The redirect URIs have to be listed in the sector_identifier_uri
+            Self::SectorIdentifier(SectorIdentifierError::RedirectUriNotListed(uri)) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidRedirectUri).with_description(
+                        format!("redirect_uri {uri} is not listed in the sector_identifier_uri"),
+                    ),
+                ),
+            )
+                .into_response(),
+
+            Self::SectorIdentifier(e) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidClientMetadata)
+                        .with_description(e.to_string()),
+                ),
+            )
+                .into_response(),
+
             // For policy violations, we return an `invalid_client_metadata` error with the details
             // of the violations in most cases. If a violation includes `redirect_uri` in the
             // message, we return an `invalid_redirect_uri` error instead.
@@ -221,11 +343,17 @@ pub(crate) async fn post(
     activity_tracker: BoundActivityTracker,
     user_agent: Option<TypedHeader<headers::UserAgent>>,
     State(encrypter): State<Encrypter>,
+    State(site_config): State<SiteConfig>,
+    State(http_client): State<reqwest::Client>,
     body: Result<Json<ClientMetadata>, axum::extract::rejection::JsonRejection>,
 ) -> Result<impl IntoResponse, RouteError> {
     // Propagate any JSON extraction error
     let Json(body) = body?;
 
+    // Apply the software statement first, so that the values it pins are the
+    // ones which get validated and saved
+    let body = software_statement::apply(&http_client, &clock, &site_config, body).await?;
+
     // Sort the properties to ensure a stable serialisation order for hashing
     let body = body.sorted();
 
@@ -277,6 +405,66 @@ pub(crate) async fn post(
         }
     }
 
+    for post_logout_redirect_uri in metadata.post_logout_redirect_uris() {
+        if host_is_public_suffix(post_logout_redirect_uri) {
+            return Err(RouteError::UrlIsPublicSuffix("post_logout_redirect_uri"));
+        }
+    }
+
+    // Clients can shorten the lifetime of their tokens, but not extend it past
+    // what the server allows by default
+    if metadata
+        .access_token_ttl
+        .is_some_and(|ttl| ttl > site_config.access_token_ttl)
+    {
+        return Err(RouteError::LifetimeTooLong("access_token_ttl"));
+    }
+
+    if metadata
+        .device_code_ttl
+        .is_some_and(|ttl| ttl > DEFAULT_DEVICE_CODE_TTL)
+    {
+        return Err(RouteError::LifetimeTooLong("device_code_ttl"));
+    }
+
+    // ID tokens are encrypted to one of the client's public keys, with one of
+    // the algorithms we implement
+    if let Some((alg, enc)) = metadata.id_token_encrypted_response() {
+        if !SUPPORTED_ENCRYPTION_ALGORITHMS.contains(alg) {
+            return Err(RouteError::UnsupportedAlgorithm(
+                "id_token_encrypted_response_alg",
+            ));
+        }
+
+        if !SUPPORTED_CONTENT_ENCRYPTION_ALGORITHMS.contains(enc) {
+            return Err(RouteError::UnsupportedAlgorithm(
+                "id_token_encrypted_response_enc",
+            ));
+        }
+
+        if metadata.jwks.is_none() && metadata.jwks_uri.is_none() {
+            return Err(RouteError::MissingJwksForIdTokenEncryption);
+        }
+    }
+
+    // Clients can only require the ACR values we know how to satisfy
+    if let Some(default_acr_values) = &metadata.default_acr_values {
+        for value in default_acr_values {
+            if !site_config.acr_values.iter().any(|acr| acr.value == *value) {
+                return Err(RouteError::UnknownAcrValue(value.clone()));
+            }
+        }
+    }
+
+    // OAuth 2.1 removes the implicit and password grants
+    if site_config.oauth2_1_strict {
+        for grant_type in [GrantType::Implicit, GrantType::Password] {
+            if metadata.grant_types().contains(&grant_type) {
+                return Err(RouteError::GrantTypeNotAllowed(grant_type));
+            }
+        }
+    }
+
     let res = policy
         .evaluate_client_registration(mas_policy::ClientRegistrationInput {
             client_metadata: &metadata,
@@ -290,6 +478,10 @@ pub(crate) async fn post(
         return Err(RouteError::PolicyDenied(res));
     }
 
+    // This fetches the sector_identifier_uri, so it is only done once the
+    // policy accepted the registration
+    let pairwise_sector_identifier = pairwise::sector_identifier(&http_client, &metadata).await?;
+
     let (client_secret, encrypted_client_secret) = match metadata.token_endpoint_auth_method {
         Some(
             OAuthClientAuthenticationMethod::ClientSecretJwt
@@ -353,6 +545,21 @@ pub(crate) async fn post(
                 metadata.token_endpoint_auth_method.clone(),
                 metadata.token_endpoint_auth_signing_alg.clone(),
                 metadata.initiate_login_uri.clone(),
+                metadata.authorization_signed_response_alg.clone(),
+                metadata.backchannel_logout_uri.clone(),
+                metadata.backchannel_logout_session_required(),
+                metadata.post_logout_redirect_uris().to_vec(),
+                metadata.access_token_ttl,
+                metadata.refresh_token_ttl,
+                metadata.device_code_ttl,
+                metadata.request_object_signing_alg.clone(),
+                metadata.require_signed_request_object(),
+                metadata.id_token_encrypted_response_alg.clone(),
+                metadata
+                    .id_token_encrypted_response()
+                    .map(|(_alg, enc)| enc.clone()),
+                metadata.default_acr_values.clone().unwrap_or_default(),
+                pairwise_sector_identifier,
             )
             .await?;
         tracing::info!(%client.id, "Registered new client");
@@ -381,12 +588,21 @@ pub(crate) async fn post(
 
 #[cfg(test)]
 mod tests {
+    use chrono::Duration;
     use hyper::{Request, StatusCode};
+    use mas_data_model::{AcrValue, JwksOrJwksUri, SiteConfig, SoftwareStatementIssuer};
+    use mas_iana::jose::{JsonWebEncryptionAlg, JsonWebEncryptionEnc, JsonWebSignatureAlg};
+    use mas_jose::{
+        jwk::{JsonWebKey, JsonWebKeyPublicParameters, PublicJsonWebKeySet},
+        jwt::{JsonWebSignatureHeader, Jwt},
+    };
     use mas_router::SimpleRoute;
+    use mas_storage::{Clock as _, RepositoryAccess};
     use oauth2_types::{
         errors::{ClientError, ClientErrorCode},
         registration::ClientRegistrationResponse,
     };
+    use rand::SeedableRng;
     use sqlx::PgPool;
     use url::Url;
 
@@ -545,6 +761,391 @@ mod tests {
         let response: ClientRegistrationResponse = response.json();
         assert!(response.client_secret.is_some());
     }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_registration_backchannel_logout(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // A back-channel logout URI with a fragment is rejected
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/"],
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "token_endpoint_auth_method": "none",
+                "backchannel_logout_uri": "https://example.com/logout#fragment",
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let response: ClientError = response.json();
+        assert_eq!(response.error, ClientErrorCode::InvalidClientMetadata);
+
+        // The back-channel logout settings are saved with the client
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/"],
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "token_endpoint_auth_method": "none",
+                "backchannel_logout_uri": "https://example.com/logout",
+                "backchannel_logout_session_required": true,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+
+        let mut repo = state.repository().await.unwrap();
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&response.client_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            client.backchannel_logout_uri,
+            Some(Url::parse("https://example.com/logout").unwrap())
+        );
+        assert!(client.backchannel_logout_session_required);
+        assert!(client.backchannel_logout_include_sub);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_registration_token_lifetimes(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Access tokens can't live longer than the server allows
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/"],
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "token_endpoint_auth_method": "none",
+                "access_token_ttl": 3600,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let response: ClientError = response.json();
+        assert_eq!(response.error, ClientErrorCode::InvalidClientMetadata);
+
+        // Shorter lifetimes are saved with the client
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/"],
+                "response_types": ["code"],
+                "grant_types": ["authorization_code", "refresh_token"],
+                "token_endpoint_auth_method": "none",
+                "access_token_ttl": 60,
+                "refresh_token_ttl": 86400,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+
+        let mut repo = state.repository().await.unwrap();
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&response.client_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            client.access_token_ttl,
+            Some(Duration::microseconds(60 * 1000 * 1000))
+        );
+        assert_eq!(
+            client.refresh_token_ttl,
+            Some(Duration::microseconds(86400 * 1000 * 1000))
+        );
+        assert!(client.device_code_ttl.is_none());
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_registration_id_token_encryption(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // The client needs keys to encrypt the ID tokens with
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/"],
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "token_endpoint_auth_method": "none",
+                "id_token_encrypted_response_alg": "RSA-OAEP-256",
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let response: ClientError = response.json();
+        assert_eq!(response.error, ClientErrorCode::InvalidClientMetadata);
+
+        // Key wrapping algorithms are not supported
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/"],
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "token_endpoint_auth_method": "none",
+                "jwks_uri": "https://example.com/jwks",
+                "id_token_encrypted_response_alg": "A128KW",
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let response: ClientError = response.json();
+        assert_eq!(response.error, ClientErrorCode::InvalidClientMetadata);
+
+        // The content encryption algorithm defaults to A128CBC-HS256
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/"],
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "token_endpoint_auth_method": "none",
+                "jwks_uri": "https://example.com/jwks",
+                "id_token_encrypted_response_alg": "RSA-OAEP-256",
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+
+        let mut repo = state.repository().await.unwrap();
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&response.client_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            client.id_token_encrypted_response_alg,
+            Some(JsonWebEncryptionAlg::RsaOaep256)
+        );
+        assert_eq!(
+            client.id_token_encrypted_response_enc,
+            Some(JsonWebEncryptionEnc::A128CbcHs256)
+        );
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_registration_software_statement(pool: PgPool) {
+        setup();
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(42);
+
+        // Generate a key for the trusted issuer to sign its software statements
+        let key = mas_keystore::PrivateKey::generate_ec_p256(&mut rng);
+        let signer = key
+            .signing_key_for_alg(&JsonWebSignatureAlg::Es256)
+            .unwrap();
+        let jwks = PublicJsonWebKeySet::new(vec![JsonWebKey::new(
+            JsonWebKeyPublicParameters::from(&key),
+        )]);
+
+        let site_config = SiteConfig {
+            software_statement_issuers: vec![SoftwareStatementIssuer {
+                issuer: "https://statements.example.com/".to_owned(),
+                jwks: JwksOrJwksUri::Jwks(jwks),
+            }],
+            software_statement_required: true,
+            ..crate::test_utils::test_site_config()
+        };
+        let state = TestState::from_pool_with_site_config(pool, site_config)
+            .await
+            .unwrap();
+
+        let mut sign = |claims: serde_json::Value| {
+            let header = JsonWebSignatureHeader::new(JsonWebSignatureAlg::Es256);
+            Jwt::sign_with_rng(&mut rng, header, claims, &signer)
+                .unwrap()
+                .into_string()
+        };
+
+        let register = |software_statement: Option<String>| {
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "client_name": "Sneaky client",
+                "redirect_uris": ["https://example.com/callback"],
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "token_endpoint_auth_method": "none",
+                "software_statement": software_statement,
+            }))
+        };
+
+        // A software statement is required
+        let response = state.request(register(None)).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let response: ClientError = response.json();
+        assert_eq!(response.error, ClientErrorCode::InvalidSoftwareStatement);
+
+        // It must be issued by a trusted issuer
+        let statement = sign(serde_json::json!({
+            "iss": "https://evil.example.com/",
+            "client_name": "Pinned client",
+        }));
+        let response = state.request(register(Some(statement))).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let response: ClientError = response.json();
+        assert_eq!(response.error, ClientErrorCode::UnapprovedSoftwareStatement);
+
+        // And must not be expired
+        let statement = sign(serde_json::json!({
+            "iss": "https://statements.example.com/",
+            "exp": (state.clock.now() - Duration::microseconds(60 * 60 * 1000 * 1000)).timestamp(),
+            "client_name": "Pinned client",
+        }));
+        let response = state.request(register(Some(statement))).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let response: ClientError = response.json();
+        assert_eq!(response.error, ClientErrorCode::InvalidSoftwareStatement);
+
+        // The values from the software statement take precedence
+        let statement = sign(serde_json::json!({
+            "iss": "https://statements.example.com/",
+            "iat": state.clock.now().timestamp(),
+            "client_name": "Pinned client",
+            "redirect_uris": ["https://example.com/pinned"],
+        }));
+        let response = state.request(register(Some(statement.clone()))).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+
+        let mut repo = state.repository().await.unwrap();
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&response.client_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(client.client_name.as_deref(), Some("Pinned client"));
+        assert_eq!(
+            client.redirect_uris,
+            vec![Url::parse("https://example.com/pinned").unwrap()]
+        );
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_registration_default_acr_values(pool: PgPool) {
+        setup();
+        let site_config = SiteConfig {
+            acr_values: vec![AcrValue {
+                value: "urn:example:acr:password".to_owned(),
+                amr: vec!["pwd".to_owned()],
+            }],
+            ..crate::test_utils::test_site_config()
+        };
+        let state = TestState::from_pool_with_site_config(pool, site_config)
+            .await
+            .unwrap();
+
+        // Unknown ACR values are rejected
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/"],
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "token_endpoint_auth_method": "none",
+                "default_acr_values": ["urn:example:acr:unknown"],
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let response: ClientError = response.json();
+        assert_eq!(response.error, ClientErrorCode::InvalidClientMetadata);
+
+        // Configured ones are saved with the client
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/"],
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "token_endpoint_auth_method": "none",
+                "default_acr_values": ["urn:example:acr:password"],
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+
+        let mut repo = state.repository().await.unwrap();
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&response.client_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            client.default_acr_values,
+            vec!["urn:example:acr:password".to_owned()]
+        );
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_registration_pairwise(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Without a sector_identifier_uri, the redirect URIs must share a host
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/", "https://other.example.com/"],
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "token_endpoint_auth_method": "none",
+                "subject_type": "pairwise",
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let response: ClientError = response.json();
+        assert_eq!(response.error, ClientErrorCode::InvalidClientMetadata);
+
+        // Their host is then the sector identifier
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/", "https://example.com/callback"],
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "token_endpoint_auth_method": "none",
+                "subject_type": "pairwise",
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: serde_json::Value = response.json();
+        assert_eq!(response["subject_type"], "pairwise");
+        let client_id = response["client_id"].as_str().unwrap();
+
+        let mut repo = state.repository().await.unwrap();
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(client_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            client.pairwise_sector_identifier.as_deref(),
+            Some("example.com")
+        );
+    }
+
     #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
     async fn test_registration_dedupe(pool: PgPool) {
         setup();
@@ -0,0 +1,203 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Validation of signed request objects, as defined in [RFC9101]
+//!
+//! [RFC9101]: https://www.rfc-editor.org/rfc/rfc9101
+
+use std::collections::{BTreeMap, HashMap};
+
+use axum::BoxError;
+use mas_axum_utils::client_authorization::fetch_jwks;
+use mas_data_model::Client;
+use mas_http::RequestBuilderExt as _;
+use mas_iana::jose::JsonWebSignatureAlg;
+use mas_jose::{
+    claims::{self, ClaimError, TimeOptions},
+    jwt::{Jwt, JwtDecodeError, NoKeyWorked},
+};
+use mas_router::UrlBuilder;
+use mas_storage::Clock;
+use thiserror::Error;
+use url::Url;
+
+/// The media type of request objects fetched by reference
+const REQUEST_OBJECT_CONTENT_TYPE: &str = "application/oauth-authz-req+jwt";
+
+/// The algorithms accepted for signing request objects. They are verified with
+/// the public keys of the client, so only asymmetric algorithms make sense.
+pub(crate) const REQUEST_OBJECT_SIGNING_ALGORITHMS: [JsonWebSignatureAlg; 9] = [
+    JsonWebSignatureAlg::Rs256,
+    JsonWebSignatureAlg::Rs384,
+    JsonWebSignatureAlg::Rs512,
+    JsonWebSignatureAlg::Ps256,
+    JsonWebSignatureAlg::Ps384,
+    JsonWebSignatureAlg::Ps512,
+    JsonWebSignatureAlg::Es256,
+    JsonWebSignatureAlg::Es384,
+    JsonWebSignatureAlg::Es256K,
+];
+
+#[derive(Debug, Error)]
+pub(crate) enum RequestObjectError {
+    #[error("both the request and request_uri parameters were given")]
+    RequestAndRequestUri,
+
+    #[error("the request_uri parameter is not a valid https URL")]
+    InvalidRequestUri,
+
+    #[error("failed to fetch the request object")]
+    Fetch(#[from] reqwest::Error),
+
+    #[error("the request object is not a valid JWT")]
+    Decode(#[from] JwtDecodeError),
+
+    #[error("the request object is signed with an unexpected algorithm {0}")]
+    UnexpectedAlgorithm(JsonWebSignatureAlg),
+
+    #[error("the client has no JWKS to verify request objects with")]
+    MissingJwks,
+
+    #[error("failed to fetch the client JWKS")]
+    JwksFetch(#[source] BoxError),
+
+    #[error("the signature of the request object is invalid")]
+    InvalidSignature(#[from] NoKeyWorked),
+
+    #[error("invalid claim in the request object")]
+    Claim(#[from] ClaimError),
+
+    #[error("the request object was issued by another client")]
+    ClientMismatch,
+
+    #[error("the request object references another request object")]
+    NestedRequest,
+}
+
+/// Fetch the request object referenced by a `request_uri` parameter
+async fn fetch(
+    http_client: &reqwest::Client,
+    request_uri: &str,
+) -> Result<String, RequestObjectError> {
+    let request_uri = Url::parse(request_uri).map_err(|_| RequestObjectError::InvalidRequestUri)?;
+    if request_uri.scheme() != "https" {
+        return Err(RequestObjectError::InvalidRequestUri);
+    }
+
+    let request_object = http_client
+        .get(request_uri)
+        .header(reqwest::header::ACCEPT, REQUEST_OBJECT_CONTENT_TYPE)
+        .send_traced()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    Ok(request_object)
+}
+
+/// Turn the value of a request object claim into an authorization request
+/// parameter. Structured values, like `authorization_details`, are passed as
+/// JSON, as they would be in the query string.
+fn claim_to_parameter(value: serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(value) => Some(value),
+        value => Some(value.to_string()),
+    }
+}
+
+/// Load the signed request object passed with an authorization request, if
+/// any, and return the authorization request parameters it carries.
+///
+/// As per RFC9101, only the parameters from the request object are used, the
+/// ones passed alongside it are ignored.
+///
+/// Returns `None` if the request has neither a `request` nor a `request_uri`
+/// parameter.
+///
+/// # Parameters
+///
+/// * `http_client`: The HTTP client used to fetch the request object and the
+///   client JWKS
+/// * `clock`: The clock used to check the expiration of the request object
+/// * `url_builder`: The URL builder, to check the audience of the request
+///   object
+/// * `client`: The client which sent the authorization request
+/// * `parameters`: The parameters of the authorization request
+///
+/// # Errors
+///
+/// Returns an error if the request object could not be fetched, or if it is
+/// not valid for this client
+pub(crate) async fn load(
+    http_client: &reqwest::Client,
+    clock: &impl Clock,
+    url_builder: &UrlBuilder,
+    client: &Client,
+    parameters: &BTreeMap<String, String>,
+) -> Result<Option<BTreeMap<String, String>>, RequestObjectError> {
+    let request_object = match (parameters.get("request"), parameters.get("request_uri")) {
+        (None, None) => return Ok(None),
+        (Some(_), Some(_)) => return Err(RequestObjectError::RequestAndRequestUri),
+        (Some(request), None) => request.clone(),
+        (None, Some(request_uri)) => fetch(http_client, request_uri).await?,
+    };
+
+    let jwt: Jwt<'_, HashMap<String, serde_json::Value>> = Jwt::try_from(request_object.as_str())?;
+
+    // Unsigned request objects are never accepted, and clients can pin the
+    // algorithm they use
+    let alg = jwt.header().alg();
+    if !REQUEST_OBJECT_SIGNING_ALGORITHMS.contains(alg)
+        || client
+            .request_object_signing_alg
+            .as_ref()
+            .is_some_and(|expected| expected != alg)
+    {
+        return Err(RequestObjectError::UnexpectedAlgorithm(alg.clone()));
+    }
+
+    let jwks = client
+        .jwks
+        .as_ref()
+        .ok_or(RequestObjectError::MissingJwks)?;
+    let jwks = fetch_jwks(http_client, jwks)
+        .await
+        .map_err(RequestObjectError::JwksFetch)?;
+    jwt.verify_with_jwks(&jwks)?;
+
+    let (_header, mut claims) = jwt.into_parts();
+
+    // The request object must be issued by the client for us
+    let issuer = url_builder.oidc_issuer().to_string();
+    claims::ISS.extract_required_with_options(&mut claims, client.client_id.as_str())?;
+    claims::AUD.extract_required_with_options(&mut claims, &issuer)?;
+
+    let time_options = TimeOptions::new(clock.now());
+    claims::EXP.extract_optional_with_options(&mut claims, &time_options)?;
+    claims::NBF.extract_optional_with_options(&mut claims, &time_options)?;
+    claims::IAT.extract_optional_with_options(&mut claims, &time_options)?;
+    claims::JTI.extract_optional(&mut claims)?;
+
+    if claims::CLIENT_ID
+        .extract_optional(&mut claims)?
+        .is_some_and(|client_id| client_id != client.client_id)
+    {
+        return Err(RequestObjectError::ClientMismatch);
+    }
+
+    if claims.contains_key("request") || claims.contains_key("request_uri") {
+        return Err(RequestObjectError::NestedRequest);
+    }
+
+    let mut parameters: BTreeMap<String, String> = claims
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, claim_to_parameter(value)?)))
+        .collect();
+    parameters.insert("client_id".to_owned(), client.client_id.clone());
+
+    Ok(Some(parameters))
+}
@@ -7,10 +7,11 @@
 use axum::{Json, extract::State, response::IntoResponse};
 use hyper::StatusCode;
 use mas_axum_utils::{
+    ClientJwksCache,
     client_authorization::{ClientAuthorization, CredentialsVerificationError},
     record_error,
 };
-use mas_data_model::TokenType;
+use mas_data_model::{AuditEventKind, TokenType};
 use mas_iana::oauth::OAuthTokenTypeHint;
 use mas_keystore::Encrypter;
 use mas_storage::{
@@ -24,6 +25,7 @@ use oauth2_types::{
 use thiserror::Error;
 use ulid::Ulid;
 
+use super::introspection_cache::IntrospectionCache;
 use crate::{BoundActivityTracker, impl_from_error_for_route};
 
 #[derive(Debug, Error)]
@@ -123,9 +125,11 @@ pub(crate) async fn post(
     clock: BoxClock,
     mut rng: BoxRng,
     State(http_client): State<reqwest::Client>,
+    State(jwks_cache): State<ClientJwksCache>,
     mut repo: BoxRepository,
     activity_tracker: BoundActivityTracker,
     State(encrypter): State<Encrypter>,
+    State(introspection_cache): State<IntrospectionCache>,
     client_authorization: ClientAuthorization<RevocationRequest>,
 ) -> Result<impl IntoResponse, RouteError> {
     let client = client_authorization
@@ -141,7 +145,14 @@ pub(crate) async fn post(
 
     client_authorization
         .credentials
-        .verify(&http_client, &encrypter, method, &client)
+        .verify(
+            &http_client,
+            &jwks_cache,
+            &encrypter,
+            &clock,
+            method,
+            &client,
+        )
         .await
         .map_err(|err| {
             if err.is_internal() {
@@ -239,11 +250,49 @@ pub(crate) async fn post(
             .await?;
     }
 
-    // Now that we checked everything, we can end the session.
+    // Revoking a token revokes the whole grant: both the access tokens and the
+    // refresh tokens of the session are revoked, whichever one was presented.
+    let revoked_access_tokens = repo
+        .oauth2_access_token()
+        .revoke_for_session(&clock, &session)
+        .await?;
+    let revoked_refresh_tokens = repo
+        .oauth2_refresh_token()
+        .revoke_for_session(&clock, &session)
+        .await?;
+
+    tracing::info!(
+        oauth2_session.id = %session.id,
+        oauth2_client.id = %client.id,
+        revoked_access_tokens,
+        revoked_refresh_tokens,
+        "Revoked the OAuth 2.0 session and the tokens derived from it"
+    );
+
+    repo.audit_event()
+        .add(
+            &mut rng,
+            &clock,
+            session.user_id,
+            AuditEventKind::OAuth2SessionRevoked {
+                oauth2_session_id: session.id,
+                oauth2_client_id: client.id,
+                revoked_access_tokens,
+                revoked_refresh_tokens,
+            },
+        )
+        .await?;
+
+    // Now that we checked everything, we can end the session. The client ended
+    // the session itself, so we don't send it a back-channel logout token.
     repo.oauth2_session().finish(&clock, session).await?;
 
     repo.save().await?;
 
+    // Make sure resource servers don't get cached introspection results for
+    // the tokens of this session
+    introspection_cache.invalidate_session(session_id);
+
     Ok(())
 }
 
@@ -263,7 +312,7 @@ mod tests {
 
     use super::*;
     use crate::{
-        oauth2::generate_token_pair,
+        oauth2::{AccessTokenGenerator, generate_token_pair},
         test_utils::{RequestBuilderExt, ResponseExt, TestState, setup},
     };
 
@@ -330,6 +379,8 @@ mod tests {
                 &mut state.rng(),
                 &state.clock,
                 &mut repo,
+                &AccessTokenGenerator::new(&state.url_builder, &state.key_store),
+                &client,
                 &session,
                 Duration::microseconds(5 * 60 * 1000 * 1000),
             )
@@ -397,6 +448,8 @@ mod tests {
                 &mut state.rng(),
                 &state.clock,
                 &mut repo,
+                &AccessTokenGenerator::new(&state.url_builder, &state.key_store),
+                &client,
                 &session,
                 Duration::microseconds(5 * 60 * 1000 * 1000),
             )
@@ -465,5 +518,32 @@ mod tests {
         response.assert_status(StatusCode::OK);
 
         assert!(!state.is_access_token_valid(&access_token).await);
+
+        // Both the refresh token and the access token derived from it should have
+        // been revoked
+        let mut repo = state.repository().await.unwrap();
+        let access_token = repo
+            .oauth2_access_token()
+            .find_by_token(&access_token)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(access_token.state.is_revoked());
+        let refresh_token = repo
+            .oauth2_refresh_token()
+            .find_by_token(&refresh_token)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!refresh_token.is_valid());
+
+        // Both revocations were recorded in the audit log
+        let events = repo.audit_event().all_for_user(&user).await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[1].kind,
+            AuditEventKind::OAuth2SessionRevoked { oauth2_session_id, revoked_access_tokens, .. }
+                if oauth2_session_id == session.id && revoked_access_tokens > 0
+        ));
     }
 }
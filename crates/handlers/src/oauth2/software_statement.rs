@@ -0,0 +1,128 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Validation of the software statements presented during dynamic client
+//! registration, as defined in [RFC7591] section 2.3
+//!
+//! [RFC7591]: https://www.rfc-editor.org/rfc/rfc7591#section-2.3
+
+use std::collections::HashMap;
+
+use axum::BoxError;
+use mas_axum_utils::client_authorization::fetch_jwks;
+use mas_data_model::SiteConfig;
+use mas_jose::{
+    claims::{self, ClaimError, TimeOptions},
+    jwt::{Jwt, JwtDecodeError, NoKeyWorked},
+};
+use mas_storage::Clock;
+use oauth2_types::registration::ClientMetadata;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub(crate) enum SoftwareStatementError {
+    #[error("a software statement is required to register clients")]
+    Missing,
+
+    #[error("the software statement is not a valid JWT")]
+    Decode(#[from] JwtDecodeError),
+
+    #[error("the software statement was not issued by a trusted issuer")]
+    UntrustedIssuer,
+
+    #[error("failed to fetch the JWKS of the software statement issuer")]
+    JwksFetch(#[source] BoxError),
+
+    #[error("the signature of the software statement is invalid")]
+    InvalidSignature(#[from] NoKeyWorked),
+
+    #[error("invalid claim in the software statement")]
+    Claim(#[from] ClaimError),
+
+    #[error("the software statement references another software statement")]
+    NestedStatement,
+
+    #[error("the software statement contains invalid client metadata")]
+    InvalidMetadata(#[source] serde_json::Error),
+}
+
+/// Verify the software statement presented with a client registration
+/// request, if any, and return the client metadata with the values from the
+/// software statement applied.
+///
+/// As per RFC7591, the values from the software statement take precedence
+/// over the ones sent alongside it. The software statement itself is kept in
+/// the metadata.
+///
+/// # Parameters
+///
+/// * `http_client`: The HTTP client used to fetch the JWKS of the issuer
+/// * `clock`: The clock used to check the expiration of the software statement
+/// * `site_config`: The site configuration, with the trusted issuers
+/// * `metadata`: The client metadata sent in the registration request
+///
+/// # Errors
+///
+/// Returns an error if a software statement is required but missing, or if
+/// the software statement is not valid or not issued by a trusted issuer
+pub(crate) async fn apply(
+    http_client: &reqwest::Client,
+    clock: &impl Clock,
+    site_config: &SiteConfig,
+    metadata: ClientMetadata,
+) -> Result<ClientMetadata, SoftwareStatementError> {
+    let Some(software_statement) = metadata.software_statement.clone() else {
+        if site_config.software_statement_required {
+            return Err(SoftwareStatementError::Missing);
+        }
+
+        return Ok(metadata);
+    };
+
+    let jwt: Jwt<'_, HashMap<String, serde_json::Value>> =
+        Jwt::try_from(software_statement.as_str())?;
+
+    // The issuer tells us which keys to verify the signature with
+    let issuer = jwt
+        .payload()
+        .get("iss")
+        .and_then(serde_json::Value::as_str)
+        .and_then(|iss| {
+            site_config
+                .software_statement_issuers
+                .iter()
+                .find(|issuer| issuer.issuer == iss)
+        })
+        .ok_or(SoftwareStatementError::UntrustedIssuer)?;
+
+    let jwks = fetch_jwks(http_client, &issuer.jwks)
+        .await
+        .map_err(SoftwareStatementError::JwksFetch)?;
+    jwt.verify_with_jwks(&jwks)?;
+
+    let (_header, mut claims) = jwt.into_parts();
+
+    claims::ISS.extract_required_with_options(&mut claims, issuer.issuer.as_str())?;
+    let time_options = TimeOptions::new(clock.now());
+    claims::EXP.extract_optional_with_options(&mut claims, &time_options)?;
+    claims::NBF.extract_optional_with_options(&mut claims, &time_options)?;
+    claims::IAT.extract_optional_with_options(&mut claims, &time_options)?;
+    claims::JTI.extract_optional(&mut claims)?;
+
+    if claims.contains_key("software_statement") {
+        return Err(SoftwareStatementError::NestedStatement);
+    }
+
+    // Merge the claims over the metadata sent alongside the software statement,
+    // going through their JSON representation so that every field, including
+    // the localized ones, can be pinned by the statement
+    let mut merged =
+        serde_json::to_value(&metadata).map_err(SoftwareStatementError::InvalidMetadata)?;
+    if let serde_json::Value::Object(fields) = &mut merged {
+        fields.extend(claims);
+    }
+
+    serde_json::from_value(merged).map_err(SoftwareStatementError::InvalidMetadata)
+}
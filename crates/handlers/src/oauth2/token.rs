@@ -4,21 +4,28 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
-use std::sync::{Arc, LazyLock};
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, LazyLock},
+};
 
 use axum::{Json, extract::State, response::IntoResponse};
 use axum_extra::typed_header::TypedHeader;
 use chrono::Duration;
 use headers::{CacheControl, HeaderMap, HeaderMapExt, Pragma};
-use hyper::StatusCode;
+use hyper::{Method, StatusCode};
 use mas_axum_utils::{
+    ClientJwksCache,
     client_authorization::{ClientAuthorization, CredentialsVerificationError},
     record_error,
 };
 use mas_data_model::{
-    AuthorizationGrantStage, Client, Device, DeviceCodeGrantState, SiteConfig, TokenType,
+    AuditEventKind, AuthorizationGrantStage, BackchannelAuthenticationGrantState, Client, Device,
+    DeviceCodeGrantState, FeatureFlag, RefreshToken, RefreshTokenRotation, Session, SiteConfig,
+    TokenType,
 };
 use mas_i18n::DataLocale;
+use mas_iana::oauth::OAuthAccessTokenType;
 use mas_keystore::{Encrypter, Keystore};
 use mas_matrix::HomeserverConnection;
 use mas_oidc_client::types::scope::ScopeToken;
@@ -30,15 +37,18 @@ use mas_storage::{
         OAuth2AccessTokenRepository, OAuth2AuthorizationGrantRepository,
         OAuth2RefreshTokenRepository, OAuth2SessionRepository,
     },
-    user::BrowserSessionRepository,
+    queue::{QueueJobRepositoryExt as _, SendBackchannelLogoutJob, SyncDevicesJob},
+    user::{BrowserSessionRepository, UserEmailRepository, UserPasswordRepository, UserRepository},
 };
 use mas_templates::{DeviceNameContext, TemplateContext, Templates};
 use oauth2_types::{
+    authorization_details::{self, InvalidAuthorizationDetails},
     errors::{ClientError, ClientErrorCode},
     pkce::CodeChallengeError,
     requests::{
-        AccessTokenRequest, AccessTokenResponse, AuthorizationCodeGrant, ClientCredentialsGrant,
-        DeviceCodeGrant, GrantType, RefreshTokenGrant,
+        AccessTokenRequest, AccessTokenResponse, AuthorizationCodeGrant,
+        BackchannelAuthenticationGrant, ClientCredentialsGrant, DeviceCodeGrant, GrantType,
+        RefreshTokenGrant, ResourceOwnerPasswordCredentialsGrant,
     },
     scope,
 };
@@ -46,9 +56,24 @@ use opentelemetry::{Key, KeyValue, metrics::Counter};
 use thiserror::Error;
 use tracing::{debug, info, warn};
 use ulid::Ulid;
-
-use super::{generate_id_token, generate_token_pair};
-use crate::{BoundActivityTracker, METER, impl_from_error_for_route};
+use url::Url;
+use zeroize::Zeroizing;
+
+use super::{
+    AccessTokenGenerator,
+    dpop::{self, DPoPError, DPoPProof},
+    encrypt_id_token, generate_id_token, generate_token_pair,
+    pairwise::subject_for_client,
+    requested_claims, user_attribute_claims,
+};
+use crate::{
+    BoundActivityTracker, FeatureFlags, IssuerUrlBuilder, Limiter, METER, RequesterFingerprint,
+    impl_from_error_for_route,
+    password_lockout::{self, Lockout},
+    password_policy,
+    passwords::PasswordManager,
+    rate_limit::PasswordCheckLimitedError,
+};
 
 static TOKEN_REQUEST_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
     METER
@@ -103,6 +128,12 @@ pub(crate) enum RouteError {
     #[error("refresh token {0} is invalid")]
     RefreshTokenInvalid(Ulid),
 
+    #[error("refresh token {0} was already used, the session was ended")]
+    RefreshTokenReused(Ulid),
+
+    #[error("refresh token {0} has expired")]
+    RefreshTokenExpired(Ulid),
+
     #[error("session {0} is invalid")]
     SessionInvalid(Ulid),
 
@@ -149,14 +180,72 @@ pub(crate) enum RouteError {
     #[error("device code grant is still pending")]
     DeviceCodePending,
 
+    #[error("device code grant was polled too fast")]
+    DeviceCodeSlowDown,
+
     #[error("device code grant was rejected")]
     DeviceCodeRejected,
 
     #[error("device code grant was already exchanged")]
     DeviceCodeExchanged,
 
+    #[error("backchannel authentication request expired")]
+    BackchannelAuthenticationExpired,
+
+    #[error("backchannel authentication request is still pending")]
+    BackchannelAuthenticationPending,
+
+    #[error("backchannel authentication request was rejected")]
+    BackchannelAuthenticationRejected,
+
+    #[error("backchannel authentication request was already exchanged")]
+    BackchannelAuthenticationExchanged,
+
     #[error("failed to provision device")]
     ProvisionDeviceFailed(#[source] anyhow::Error),
+
+    #[error("invalid username or password")]
+    InvalidCredentials,
+
+    #[error("password verification failed")]
+    PasswordVerificationFailed(#[source] anyhow::Error),
+
+    #[error("too many login attempts")]
+    RateLimited(#[from] PasswordCheckLimitedError),
+
+    #[error("too many failed login attempts")]
+    TooManyFailedAttempts,
+
+    #[error("password change required")]
+    PasswordChangeRequired,
+
+    #[error("invalid DPoP proof")]
+    InvalidDPoPProof(#[source] DPoPError),
+
+    #[error("could not verify the DPoP proof")]
+    DPoPVerification(#[source] DPoPError),
+
+    #[error("invalid authorization details")]
+    InvalidAuthorizationDetails(#[source] InvalidAuthorizationDetails),
+
+    #[error("authorization details exceed what was granted by grant {0}")]
+    AuthorizationDetailsNotGranted(Ulid),
+
+    #[error("resource {0} is not allowed")]
+    InvalidTarget(Url),
+
+    #[error("scope {scope} is not allowed for service account {client}")]
+    ServiceAccountScopeNotAllowed { client: Ulid, scope: ScopeToken },
+}
+
+impl From<DPoPError> for RouteError {
+    fn from(err: DPoPError) -> Self {
+        if err.is_internal() {
+            Self::DPoPVerification(err)
+        } else {
+            Self::InvalidDPoPProof(err)
+        }
+    }
 }
 
 impl IntoResponse for RouteError {
@@ -171,6 +260,7 @@ impl IntoResponse for RouteError {
                 | Self::NoSuchNextRefreshToken { .. }
                 | Self::NoSuchNextAccessToken { .. }
                 | Self::NoAccessTokenOnRefreshToken { .. }
+                | Self::DPoPVerification(_)
         );
 
         TOKEN_REQUEST_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
@@ -183,7 +273,8 @@ impl IntoResponse for RouteError {
             | Self::ProvisionDeviceFailed(_)
             | Self::NoSuchNextRefreshToken { .. }
             | Self::NoSuchNextAccessToken { .. }
-            | Self::NoAccessTokenOnRefreshToken { .. } => (
+            | Self::NoAccessTokenOnRefreshToken { .. }
+            | Self::DPoPVerification(_) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ClientError::from(ClientErrorCode::ServerError)),
             ),
@@ -227,36 +318,95 @@ impl IntoResponse for RouteError {
                 ),
             ),
 
-            Self::DeviceCodeRejected => (
+            Self::RateLimited(_) | Self::TooManyFailedAttempts => (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(ClientError::from(ClientErrorCode::SlowDown)),
+            ),
+
+            Self::DeviceCodeRejected | Self::BackchannelAuthenticationRejected => (
                 StatusCode::FORBIDDEN,
                 Json(ClientError::from(ClientErrorCode::AccessDenied)),
             ),
 
-            Self::DeviceCodeExpired => (
+            Self::DeviceCodeExpired | Self::BackchannelAuthenticationExpired => (
                 StatusCode::FORBIDDEN,
                 Json(ClientError::from(ClientErrorCode::ExpiredToken)),
             ),
 
-            Self::DeviceCodePending => (
+            Self::DeviceCodePending | Self::BackchannelAuthenticationPending => (
                 StatusCode::FORBIDDEN,
                 Json(ClientError::from(ClientErrorCode::AuthorizationPending)),
             ),
 
+            Self::DeviceCodeSlowDown => (
+                StatusCode::FORBIDDEN,
+                Json(ClientError::from(ClientErrorCode::SlowDown)),
+            ),
+
             Self::InvalidGrant(_)
             | Self::DeviceCodeExchanged
+            | Self::BackchannelAuthenticationExchanged
             | Self::RefreshTokenNotFound
             | Self::RefreshTokenInvalid(_)
+            | Self::RefreshTokenReused(_)
+            | Self::RefreshTokenExpired(_)
             | Self::SessionInvalid(_)
             | Self::ClientIDMismatch { .. }
-            | Self::GrantNotFound => (
+            | Self::GrantNotFound
+            | Self::InvalidCredentials
+            | Self::PasswordVerificationFailed(_) => (
                 StatusCode::BAD_REQUEST,
                 Json(ClientError::from(ClientErrorCode::InvalidGrant)),
             ),
 
+            Self::PasswordChangeRequired => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidGrant)
+                        .with_description("The user has to change their password".to_owned()),
+                ),
+            ),
+
             Self::UnsupportedGrantType => (
                 StatusCode::BAD_REQUEST,
                 Json(ClientError::from(ClientErrorCode::UnsupportedGrantType)),
             ),
+
+            Self::InvalidDPoPProof(err) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidDpopProof)
+                        .with_description(err.to_string()),
+                ),
+            ),
+
+            Self::InvalidAuthorizationDetails(err) => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidAuthorizationDetails)
+                        .with_description(err.to_string()),
+                ),
+            ),
+
+            Self::AuthorizationDetailsNotGranted(_) => (
+                StatusCode::BAD_REQUEST,
+                Json(ClientError::from(
+                    ClientErrorCode::InvalidAuthorizationDetails,
+                )),
+            ),
+
+            Self::InvalidTarget(_) => (
+                StatusCode::BAD_REQUEST,
+                Json(ClientError::from(ClientErrorCode::InvalidTarget)),
+            ),
+
+            Self::ServiceAccountScopeNotAllowed { scope, .. } => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidScope)
+                        .with_description(format!("scope {scope} is not allowed")),
+                ),
+            ),
         };
 
         (sentry_event_id, response).into_response()
@@ -268,6 +418,8 @@ impl_from_error_for_route!(mas_templates::TemplateError);
 impl_from_error_for_route!(mas_storage::RepositoryError);
 impl_from_error_for_route!(mas_policy::EvaluationError);
 impl_from_error_for_route!(super::IdTokenSignatureError);
+impl_from_error_for_route!(super::IdTokenEncryptionError);
+impl_from_error_for_route!(super::TokenGenerationError);
 
 #[tracing::instrument(
     name = "handlers.oauth2.token.post",
@@ -277,17 +429,23 @@ impl_from_error_for_route!(super::IdTokenSignatureError);
 pub(crate) async fn post(
     mut rng: BoxRng,
     clock: BoxClock,
-    State(http_client): State<reqwest::Client>,
+    (State(http_client), State(jwks_cache)): (State<reqwest::Client>, State<ClientJwksCache>),
     State(key_store): State<Keystore>,
-    State(url_builder): State<UrlBuilder>,
+    IssuerUrlBuilder(url_builder): IssuerUrlBuilder,
     activity_tracker: BoundActivityTracker,
     mut repo: BoxRepository,
     State(homeserver): State<Arc<dyn HomeserverConnection>>,
     State(site_config): State<SiteConfig>,
     State(encrypter): State<Encrypter>,
     State(templates): State<Templates>,
+    (State(password_manager), State(limiter), State(feature_flags)): (
+        State<PasswordManager>,
+        State<Limiter>,
+        State<FeatureFlags>,
+    ),
+    requester: RequesterFingerprint,
     policy: Policy,
-    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    (user_agent, request_headers): (Option<TypedHeader<headers::UserAgent>>, HeaderMap),
     client_authorization: ClientAuthorization<AccessTokenRequest>,
 ) -> Result<impl IntoResponse, RouteError> {
     let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
@@ -304,7 +462,14 @@ pub(crate) async fn post(
 
     client_authorization
         .credentials
-        .verify(&http_client, &encrypter, method, &client)
+        .verify(
+            &http_client,
+            &jwks_cache,
+            &encrypter,
+            &clock,
+            method,
+            &client,
+        )
         .await
         .map_err(|err| {
             // Classify the error differntly, depending on whether it's an 'internal' error,
@@ -326,7 +491,21 @@ pub(crate) async fn post(
 
     let grant_type = form.grant_type();
 
-    let (reply, repo) = match form {
+    let dpop = dpop::verify_proof(
+        &mut rng,
+        &clock,
+        &mut repo,
+        &request_headers,
+        &Method::POST,
+        &url_builder.oauth_token_endpoint(),
+        None,
+    )
+    .await?;
+
+    let access_tokens = AccessTokenGenerator::new(&url_builder, &key_store)
+        .with_dpop_jkt(dpop.as_ref().map(|dpop| dpop.jkt.as_str()));
+
+    let (mut reply, mut repo) = match form {
         AccessTokenRequest::AuthorizationCode(grant) => {
             authorization_code_grant(
                 &mut rng,
@@ -334,6 +513,7 @@ pub(crate) async fn post(
                 &activity_tracker,
                 &grant,
                 &client,
+                &access_tokens,
                 &key_store,
                 &url_builder,
                 &site_config,
@@ -351,9 +531,11 @@ pub(crate) async fn post(
                 &activity_tracker,
                 &grant,
                 &client,
+                &access_tokens,
                 &site_config,
                 repo,
                 user_agent,
+                dpop.as_ref(),
             )
             .await?
         }
@@ -364,8 +546,31 @@ pub(crate) async fn post(
                 &activity_tracker,
                 &grant,
                 &client,
+                &access_tokens,
+                &site_config,
+                repo,
+                policy,
+                user_agent,
+            )
+            .await?
+        }
+        AccessTokenRequest::Password(grant) => {
+            password_grant(
+                &mut rng,
+                &clock,
+                &activity_tracker,
+                grant,
+                &client,
+                &access_tokens,
+                &key_store,
+                &url_builder,
                 &site_config,
                 repo,
+                &homeserver,
+                &password_manager,
+                &limiter,
+                &feature_flags,
+                requester,
                 policy,
                 user_agent,
             )
@@ -378,6 +583,24 @@ pub(crate) async fn post(
                 &activity_tracker,
                 &grant,
                 &client,
+                &access_tokens,
+                &key_store,
+                &url_builder,
+                &site_config,
+                repo,
+                &homeserver,
+                user_agent,
+            )
+            .await?
+        }
+        AccessTokenRequest::BackchannelAuthentication(grant) => {
+            backchannel_authentication_grant(
+                &mut rng,
+                &clock,
+                &activity_tracker,
+                &grant,
+                &client,
+                &access_tokens,
                 &key_store,
                 &url_builder,
                 &site_config,
@@ -392,6 +615,28 @@ pub(crate) async fn post(
         }
     };
 
+    // Encrypt the ID token if the client asked for it
+    if let Some(id_token) = reply.id_token.take() {
+        reply.id_token = Some(encrypt_id_token(&mut rng, &http_client, &client, id_token).await?);
+    }
+
+    // Bind the new access token to the key which signed the proof
+    if let Some(dpop) = dpop {
+        let access_token = repo
+            .oauth2_access_token()
+            .find_by_token(&reply.access_token)
+            .await?
+            .ok_or_else(|| {
+                RouteError::Internal("could not find the access token which was just issued".into())
+            })?;
+
+        repo.oauth2_access_token()
+            .bind_dpop_key(access_token, dpop.jkt)
+            .await?;
+
+        reply.token_type = OAuthAccessTokenType::DPoP;
+    }
+
     repo.save().await?;
 
     TOKEN_REQUEST_COUNTER.add(
@@ -416,6 +661,7 @@ async fn authorization_code_grant(
     activity_tracker: &BoundActivityTracker,
     grant: &AuthorizationCodeGrant,
     client: &Client,
+    access_tokens: &AccessTokenGenerator<'_>,
     key_store: &Keystore,
     url_builder: &UrlBuilder,
     site_config: &SiteConfig,
@@ -459,6 +705,9 @@ async fn authorization_code_grant(
                     .ok_or(RouteError::NoSuchOAuthSession(session_id))?;
 
                 //if !session.is_finished() {
+                repo.queue_job()
+                    .schedule_job(&mut rng, clock, SendBackchannelLogoutJob::new(&session))
+                    .await?;
                 repo.oauth2_session().finish(clock, session).await?;
                 repo.save().await?;
                 //}
@@ -524,6 +773,36 @@ async fn authorization_code_grant(
         }
     }
 
+    // The client can narrow down the authorization details it was granted, but
+    // never ask for more
+    if let Some(requested) = grant.authorization_details.clone() {
+        if !authorization_details::is_subset(&requested, &session.authorization_details) {
+            return Err(RouteError::AuthorizationDetailsNotGranted(authz_grant.id));
+        }
+
+        session = repo
+            .oauth2_session()
+            .record_authorization_details(session, requested)
+            .await?;
+    }
+
+    // The token request can repeat the resource which was requested during the
+    // authorization, but not ask for another one
+    if let Some(resource) = grant
+        .resource
+        .as_ref()
+        .filter(|resource| authz_grant.resource.as_ref() != Some(*resource))
+    {
+        return Err(RouteError::InvalidTarget(resource.clone()));
+    }
+
+    if let Some(resource) = authz_grant.resource.clone() {
+        session = repo
+            .oauth2_session()
+            .record_resource(session, resource)
+            .await?;
+    }
+
     let Some(user_session_id) = session.user_session_id else {
         tracing::warn!("No user session associated with this OAuth2 session");
         return Err(RouteError::InvalidGrant(authz_grant.id));
@@ -539,22 +818,53 @@ async fn authorization_code_grant(
         .browser_session()
         .get_last_authentication(&browser_session)
         .await?;
+    let elevation = repo
+        .browser_session()
+        .get_active_elevation(clock, &browser_session)
+        .await?;
 
-    let ttl = site_config.access_token_ttl;
-    let (access_token, refresh_token) =
-        generate_token_pair(&mut rng, clock, &mut repo, &session, ttl).await?;
+    let ttl = client
+        .access_token_ttl
+        .unwrap_or(site_config.access_token_ttl);
+    let (access_token, refresh_token) = generate_token_pair(
+        &mut rng,
+        clock,
+        &mut repo,
+        access_tokens,
+        client,
+        &session,
+        ttl,
+    )
+    .await?;
 
     let id_token = if session.scope.contains(&scope::OPENID) {
+        let mut custom_claims =
+            user_attribute_claims(&mut repo, site_config, &browser_session.user).await?;
+        custom_claims.extend(
+            requested_claims(
+                &mut repo,
+                &browser_session.user,
+                authz_grant.id_token_claims(),
+            )
+            .await?,
+        );
+        let sub =
+            subject_for_client(&mut rng, clock, &mut repo, client, &browser_session.user).await?;
+
         Some(generate_id_token(
             &mut rng,
             clock,
             url_builder,
             key_store,
+            site_config,
             client,
             Some(&authz_grant),
             &browser_session,
+            &sub,
             Some(&access_token),
             last_authentication.as_ref(),
+            elevation.as_ref(),
+            custom_claims,
         )?)
     } else {
         None
@@ -606,9 +916,11 @@ async fn refresh_token_grant(
     activity_tracker: &BoundActivityTracker,
     grant: &RefreshTokenGrant,
     client: &Client,
+    access_tokens: &AccessTokenGenerator<'_>,
     site_config: &SiteConfig,
     mut repo: BoxRepository,
     user_agent: Option<String>,
+    dpop: Option<&DPoPProof>,
 ) -> Result<(AccessTokenResponse, BoxRepository), RouteError> {
     // Check that the client is allowed to use this grant type
     if !client.grant_types.contains(&GrantType::RefreshToken) {
@@ -648,6 +960,23 @@ async fn refresh_token_grant(
         });
     }
 
+    // Refresh tokens only expire if the client has a lifetime set for them
+    if client
+        .refresh_token_ttl
+        .is_some_and(|ttl| refresh_token.created_at + ttl < clock.now())
+    {
+        return Err(RouteError::RefreshTokenExpired(refresh_token.id));
+    }
+
+    // Tokens are always issued for the resource the session was restricted to
+    if let Some(resource) = grant
+        .resource
+        .as_ref()
+        .filter(|resource| session.resource.as_ref() != Some(*resource))
+    {
+        return Err(RouteError::InvalidTarget(resource.clone()));
+    }
+
     if !refresh_token.is_valid() {
         // We're seing a refresh token that already has been consumed, this might be a
         // double-refresh or a replay attack
@@ -672,8 +1001,9 @@ async fn refresh_token_grant(
 
         // Check if the next refresh token was already consumed or not
         if !next_refresh_token.is_valid() {
-            // XXX: This is a replay, we *may* want to invalidate the session
-            return Err(RouteError::RefreshTokenInvalid(next_refresh_token.id));
+            // This is a replay, the whole session has to be considered compromised
+            end_session_on_reuse(rng, clock, repo, session, &refresh_token).await?;
+            return Err(RouteError::RefreshTokenReused(refresh_token.id));
         }
 
         // Check if the associated access token was already used
@@ -696,8 +1026,9 @@ async fn refresh_token_grant(
             })?;
 
         if next_access_token.is_used() {
-            // XXX: This is a replay, we *may* want to invalidate the session
-            return Err(RouteError::RefreshTokenInvalid(next_refresh_token.id));
+            // This is a replay, the whole session has to be considered compromised
+            end_session_on_reuse(rng, clock, repo, session, &refresh_token).await?;
+            return Err(RouteError::RefreshTokenReused(refresh_token.id));
         }
 
         // Looks like it's a double-refresh, client lost their refresh token on
@@ -719,29 +1050,72 @@ async fn refresh_token_grant(
             .await?;
     }
 
+    let previous_access_token = match refresh_token.access_token_id {
+        Some(access_token_id) => repo.oauth2_access_token().lookup(access_token_id).await?,
+        None => None,
+    };
+
+    // Once bound to a DPoP key, the session can only be refreshed with a proof
+    // signed by the same key
+    if let Some(jkt) = previous_access_token
+        .as_ref()
+        .and_then(|access_token| access_token.dpop_jkt.as_deref())
+    {
+        dpop.ok_or(DPoPError::Required)?.ensure_key(jkt)?;
+    }
+
     activity_tracker
         .record_oauth2_session(clock, &session)
         .await;
 
-    let ttl = site_config.access_token_ttl;
-    let (new_access_token, new_refresh_token) =
-        generate_token_pair(rng, clock, &mut repo, &session, ttl).await?;
+    let ttl = client
+        .access_token_ttl
+        .unwrap_or(site_config.access_token_ttl);
+    // OAuth 2.1 requires refresh tokens to be rotated, whatever the client was
+    // configured with
+    let rotation = if site_config.oauth2_1_strict {
+        RefreshTokenRotation::RotateOnUse
+    } else {
+        client.refresh_token_rotation
+    };
+    let (new_access_token, new_refresh_token) = match rotation {
+        RefreshTokenRotation::RotateOnUse => {
+            let (new_access_token, new_refresh_token) =
+                generate_token_pair(rng, clock, &mut repo, access_tokens, client, &session, ttl)
+                    .await?;
 
-    let refresh_token = repo
-        .oauth2_refresh_token()
-        .consume(clock, refresh_token, &new_refresh_token)
-        .await?;
+            repo.oauth2_refresh_token()
+                .consume(clock, refresh_token, &new_refresh_token)
+                .await?;
 
-    if let Some(access_token_id) = refresh_token.access_token_id {
-        let access_token = repo.oauth2_access_token().lookup(access_token_id).await?;
-        if let Some(access_token) = access_token {
-            // If it is a double-refresh, it might already be revoked
-            if !access_token.state.is_revoked() {
-                repo.oauth2_access_token()
-                    .revoke(clock, access_token)
-                    .await?;
-            }
+            (new_access_token, new_refresh_token)
+        }
+        RefreshTokenRotation::Static => {
+            // Keep the same refresh token, and only issue a new access token
+            let access_token_str = access_tokens
+                .generate(rng, clock, &mut repo, client, &session, ttl)
+                .await?;
+            let new_access_token = repo
+                .oauth2_access_token()
+                .add(rng, clock, &session, access_token_str, Some(ttl))
+                .await?;
+
+            let refresh_token = repo
+                .oauth2_refresh_token()
+                .replace_access_token(refresh_token, &new_access_token)
+                .await?;
+
+            (new_access_token, refresh_token)
         }
+    };
+
+    // If it is a double-refresh, it might already be revoked
+    if let Some(access_token) =
+        previous_access_token.filter(|access_token| !access_token.state.is_revoked())
+    {
+        repo.oauth2_access_token()
+            .revoke(clock, access_token)
+            .await?;
     }
 
     let params = AccessTokenResponse::new(new_access_token.access_token)
@@ -752,12 +1126,66 @@ async fn refresh_token_grant(
     Ok((params, repo))
 }
 
+/// End a session in which an already rotated refresh token was used again,
+/// as the refresh token probably leaked
+async fn end_session_on_reuse(
+    rng: &mut BoxRng,
+    clock: &impl Clock,
+    mut repo: BoxRepository,
+    session: Session,
+    refresh_token: &RefreshToken,
+) -> Result<(), RouteError> {
+    warn!(
+        oauth2_session.id = %session.id,
+        oauth2_client.id = %session.client_id,
+        user.id = session.user_id.map(tracing::field::display),
+        %refresh_token.id,
+        "Refresh token reuse detected, ending the session and revoking all its tokens"
+    );
+
+    repo.audit_event()
+        .add(
+            rng,
+            clock,
+            session.user_id,
+            AuditEventKind::RefreshTokenReused {
+                oauth2_session_id: session.id,
+                oauth2_client_id: session.client_id,
+                refresh_token_id: refresh_token.id,
+            },
+        )
+        .await?;
+
+    // Make sure the devices of the session get removed from the homeserver
+    if let Some(user_id) = session.user_id {
+        let user =
+            repo.user().lookup(user_id).await?.ok_or_else(|| {
+                RouteError::Internal("could not load the user of the session".into())
+            })?;
+
+        repo.queue_job()
+            .schedule_job(rng, clock, SyncDevicesJob::new(&user))
+            .await?;
+    }
+
+    // Let the client know that the session ended
+    repo.queue_job()
+        .schedule_job(rng, clock, SendBackchannelLogoutJob::new(&session))
+        .await?;
+
+    repo.oauth2_session().finish(clock, session).await?;
+    repo.save().await?;
+
+    Ok(())
+}
+
 async fn client_credentials_grant(
     rng: &mut BoxRng,
     clock: &impl Clock,
     activity_tracker: &BoundActivityTracker,
     grant: &ClientCredentialsGrant,
     client: &Client,
+    access_tokens: &AccessTokenGenerator<'_>,
     site_config: &SiteConfig,
     mut repo: BoxRepository,
     mut policy: Policy,
@@ -768,19 +1196,54 @@ async fn client_credentials_grant(
         return Err(RouteError::UnauthorizedClient(client.id));
     }
 
-    // Default to an empty scope if none is provided
-    let scope = grant
-        .scope
-        .clone()
-        .unwrap_or_else(|| std::iter::empty::<ScopeToken>().collect());
+    // Default to an empty scope if none is provided, or to the whole allowed
+    // scope for service accounts
+    let scope = match (&grant.scope, &client.service_account_scope) {
+        (Some(scope), _) => scope.clone(),
+        (None, Some(allowed_scope)) => allowed_scope.clone(),
+        (None, None) => std::iter::empty::<ScopeToken>().collect(),
+    };
+
+    // Drop the scopes the client isn't allowed to request, if it is set up that
+    // way
+    let scope = client.filter_scope(scope);
+
+    // Service accounts can only get the scopes they were configured with
+    let not_allowed = client
+        .service_account_scope
+        .as_ref()
+        .and_then(|allowed_scope| scope.difference(allowed_scope).next());
+    if let Some(token) = not_allowed {
+        return Err(RouteError::ServiceAccountScopeNotAllowed {
+            client: client.id,
+            scope: token.clone(),
+        });
+    }
+
+    // Only accept the authorization details types we were configured with
+    if let Some(details) = grant.authorization_details.as_deref() {
+        authorization_details::validate(details, &site_config.authorization_details_types)
+            .map_err(RouteError::InvalidAuthorizationDetails)?;
+    }
+
+    // Only issue tokens for the resources registered for this client
+    if let Some(resource) = grant
+        .resource
+        .as_ref()
+        .filter(|resource| !client.allowed_resources.contains(resource))
+    {
+        return Err(RouteError::InvalidTarget(resource.clone()));
+    }
 
     // Make the request go through the policy engine
     let res = policy
         .evaluate_authorization_grant(mas_policy::AuthorizationGrantInput {
             user: None,
+            emails: Vec::new(),
             client,
             scope: &scope,
             grant_type: mas_policy::GrantType::ClientCredentials,
+            requested_claims: BTreeSet::new(),
             requester: mas_policy::Requester {
                 ip_address: activity_tracker.ip(),
                 user_agent: user_agent.clone(),
@@ -804,8 +1267,26 @@ async fn client_credentials_grant(
             .await?;
     }
 
-    let ttl = site_config.access_token_ttl;
-    let access_token_str = TokenType::AccessToken.generate(rng);
+    if let Some(details) = grant.authorization_details.clone() {
+        session = repo
+            .oauth2_session()
+            .record_authorization_details(session, details)
+            .await?;
+    }
+
+    if let Some(resource) = grant.resource.clone() {
+        session = repo
+            .oauth2_session()
+            .record_resource(session, resource)
+            .await?;
+    }
+
+    let ttl = client
+        .access_token_ttl
+        .unwrap_or(site_config.access_token_ttl);
+    let access_token_str = access_tokens
+        .generate(rng, clock, &mut repo, client, &session, ttl)
+        .await?;
 
     let access_token = repo
         .oauth2_access_token()
@@ -835,6 +1316,7 @@ async fn device_code_grant(
     activity_tracker: &BoundActivityTracker,
     grant: &DeviceCodeGrant,
     client: &Client,
+    access_tokens: &AccessTokenGenerator<'_>,
     key_store: &Keystore,
     url_builder: &UrlBuilder,
     site_config: &SiteConfig,
@@ -865,6 +1347,22 @@ async fn device_code_grant(
         return Err(RouteError::DeviceCodeExpired);
     }
 
+    if grant.is_pending() {
+        // Keep track of when the client polled, and tell it to slow down if it
+        // didn't wait long enough since the last time
+        let slow_down = grant.is_polling_too_fast(clock.now());
+        repo.oauth2_device_code_grant()
+            .record_poll(clock, grant, slow_down)
+            .await?;
+        repo.save().await?;
+
+        if slow_down {
+            return Err(RouteError::DeviceCodeSlowDown);
+        }
+
+        return Err(RouteError::DeviceCodePending);
+    }
+
     let browser_session_id = match &grant.state {
         DeviceCodeGrantState::Pending => {
             return Err(RouteError::DeviceCodePending);
@@ -892,6 +1390,13 @@ async fn device_code_grant(
         .add_from_browser_session(rng, clock, client, &browser_session, grant.scope.clone())
         .await?;
 
+    if let Some(device_fingerprint) = grant.device_fingerprint.clone() {
+        session = repo
+            .oauth2_session()
+            .record_device_fingerprint(session, device_fingerprint)
+            .await?;
+    }
+
     repo.oauth2_device_code_grant()
         .exchange(clock, grant, &session)
         .await?;
@@ -904,8 +1409,12 @@ async fn device_code_grant(
             .await?;
     }
 
-    let ttl = site_config.access_token_ttl;
-    let access_token_str = TokenType::AccessToken.generate(rng);
+    let ttl = client
+        .access_token_ttl
+        .unwrap_or(site_config.access_token_ttl);
+    let access_token_str = access_tokens
+        .generate(rng, clock, &mut repo, client, &session, ttl)
+        .await?;
 
     let access_token = repo
         .oauth2_access_token()
@@ -930,16 +1439,24 @@ async fn device_code_grant(
 
     // If the client asked for an ID token, we generate one
     if session.scope.contains(&scope::OPENID) {
+        let custom_claims =
+            user_attribute_claims(&mut repo, site_config, &browser_session.user).await?;
+        let sub = subject_for_client(rng, clock, &mut repo, client, &browser_session.user).await?;
+
         let id_token = generate_id_token(
             rng,
             clock,
             url_builder,
             key_store,
+            site_config,
             client,
             None,
             &browser_session,
+            &sub,
             Some(&access_token),
             None,
+            None,
+            custom_claims,
         )?;
 
         params = params.with_id_token(id_token);
@@ -976,44 +1493,1313 @@ async fn device_code_grant(
     Ok((params, repo))
 }
 
-#[cfg(test)]
-mod tests {
-    use hyper::Request;
-    use mas_data_model::{AccessToken, AuthorizationCode, RefreshToken};
-    use mas_router::SimpleRoute;
-    use oauth2_types::{
-        registration::ClientRegistrationResponse,
-        requests::{DeviceAuthorizationResponse, ResponseMode},
-        scope::{OPENID, Scope},
-    };
-    use sqlx::PgPool;
-
-    use super::*;
-    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+async fn backchannel_authentication_grant(
+    rng: &mut BoxRng,
+    clock: &impl Clock,
+    activity_tracker: &BoundActivityTracker,
+    grant: &BackchannelAuthenticationGrant,
+    client: &Client,
+    access_tokens: &AccessTokenGenerator<'_>,
+    key_store: &Keystore,
+    url_builder: &UrlBuilder,
+    site_config: &SiteConfig,
+    mut repo: BoxRepository,
+    homeserver: &Arc<dyn HomeserverConnection>,
+    user_agent: Option<String>,
+) -> Result<(AccessTokenResponse, BoxRepository), RouteError> {
+    // Check that the client is allowed to use this grant type
+    if !client
+        .grant_types
+        .contains(&GrantType::ClientInitiatedBackchannelAuthentication)
+    {
+        return Err(RouteError::UnauthorizedClient(client.id));
+    }
 
-    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
-    async fn test_auth_code_grant(pool: PgPool) {
-        setup();
-        let state = TestState::from_pool(pool).await.unwrap();
+    let grant = repo
+        .oauth2_backchannel_authentication_grant()
+        .find_by_auth_req_id(&grant.auth_req_id)
+        .await?
+        .ok_or(RouteError::GrantNotFound)?;
 
-        // Provision a client
-        let request =
-            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
-                "client_uri": "https://example.com/",
-                "redirect_uris": ["https://example.com/callback"],
-                "token_endpoint_auth_method": "none",
-                "response_types": ["code"],
-                "grant_types": ["authorization_code"],
-            }));
+    // Check that the client match
+    if client.id != grant.client_id {
+        return Err(RouteError::ClientIDMismatch {
+            expected: grant.client_id,
+            actual: client.id,
+        });
+    }
 
-        let response = state.request(request).await;
-        response.assert_status(StatusCode::CREATED);
+    if grant.is_expired(clock.now()) {
+        return Err(RouteError::BackchannelAuthenticationExpired);
+    }
 
-        let ClientRegistrationResponse { client_id, .. } = response.json();
+    let browser_session_id = match &grant.state {
+        BackchannelAuthenticationGrantState::Pending => {
+            return Err(RouteError::BackchannelAuthenticationPending);
+        }
+        BackchannelAuthenticationGrantState::Rejected { .. } => {
+            return Err(RouteError::BackchannelAuthenticationRejected);
+        }
+        BackchannelAuthenticationGrantState::Exchanged { .. } => {
+            return Err(RouteError::BackchannelAuthenticationExchanged);
+        }
+        BackchannelAuthenticationGrantState::Fulfilled {
+            browser_session_id, ..
+        } => *browser_session_id,
+    };
+
+    let browser_session = repo
+        .browser_session()
+        .lookup(browser_session_id)
+        .await?
+        .ok_or(RouteError::NoSuchBrowserSession(browser_session_id))?;
+
+    // Start the session
+    let mut session = repo
+        .oauth2_session()
+        .add_from_browser_session(rng, clock, client, &browser_session, grant.scope.clone())
+        .await?;
+
+    repo.oauth2_backchannel_authentication_grant()
+        .exchange(clock, grant, &session)
+        .await?;
+
+    if let Some(user_agent) = user_agent {
+        session = repo
+            .oauth2_session()
+            .record_user_agent(session, user_agent)
+            .await?;
+    }
+
+    let ttl = client
+        .access_token_ttl
+        .unwrap_or(site_config.access_token_ttl);
+    let access_token_str = access_tokens
+        .generate(rng, clock, &mut repo, client, &session, ttl)
+        .await?;
+
+    let access_token = repo
+        .oauth2_access_token()
+        .add(rng, clock, &session, access_token_str, Some(ttl))
+        .await?;
+
+    let mut params =
+        AccessTokenResponse::new(access_token.access_token.clone()).with_expires_in(ttl);
+
+    // If the client uses the refresh token grant type, we also generate a refresh
+    // token
+    if client.grant_types.contains(&GrantType::RefreshToken) {
+        let refresh_token_str = TokenType::RefreshToken.generate(rng);
+
+        let refresh_token = repo
+            .oauth2_refresh_token()
+            .add(rng, clock, &session, &access_token, refresh_token_str)
+            .await?;
+
+        params = params.with_refresh_token(refresh_token.refresh_token);
+    }
+
+    // The openid scope is mandatory for backchannel authentication, so we always
+    // generate an ID token
+    let custom_claims =
+        user_attribute_claims(&mut repo, site_config, &browser_session.user).await?;
+    let sub = subject_for_client(rng, clock, &mut repo, client, &browser_session.user).await?;
+
+    let id_token = generate_id_token(
+        rng,
+        clock,
+        url_builder,
+        key_store,
+        site_config,
+        client,
+        None,
+        &browser_session,
+        &sub,
+        Some(&access_token),
+        None,
+        None,
+        custom_claims,
+    )?;
+
+    params = params.with_id_token(id_token);
+
+    // Lock the user sync to make sure we don't get into a race condition
+    repo.user()
+        .acquire_lock_for_sync(&browser_session.user)
+        .await?;
+
+    // Look for device to provision
+    let mxid = homeserver.mxid(&browser_session.user.username);
+    for scope in &*session.scope {
+        if let Some(device) = Device::from_scope_token(scope) {
+            homeserver
+                .create_device(&mxid, device.as_str(), None)
+                .await
+                .map_err(RouteError::ProvisionDeviceFailed)?;
+        }
+    }
+
+    activity_tracker
+        .record_oauth2_session(clock, &session)
+        .await;
+
+    params = params.with_scope(session.scope);
+
+    Ok((params, repo))
+}
+
+#[allow(clippy::too_many_lines)]
+async fn password_grant(
+    mut rng: &mut BoxRng,
+    clock: &impl Clock,
+    activity_tracker: &BoundActivityTracker,
+    grant: ResourceOwnerPasswordCredentialsGrant,
+    client: &Client,
+    access_tokens: &AccessTokenGenerator<'_>,
+    key_store: &Keystore,
+    url_builder: &UrlBuilder,
+    site_config: &SiteConfig,
+    mut repo: BoxRepository,
+    homeserver: &Arc<dyn HomeserverConnection>,
+    password_manager: &PasswordManager,
+    limiter: &Limiter,
+    feature_flags: &FeatureFlags,
+    requester: RequesterFingerprint,
+    mut policy: Policy,
+    user_agent: Option<String>,
+) -> Result<(AccessTokenResponse, BoxRepository), RouteError> {
+    // This grant is only available if password login is enabled, and OAuth 2.1
+    // removes it altogether
+    if !site_config.password_login_enabled || site_config.oauth2_1_strict {
+        return Err(RouteError::UnsupportedGrantType);
+    }
+
+    // It can also be turned off or rolled out gradually
+    let rollout = feature_flags
+        .rollout(&mut repo, FeatureFlag::PasswordGrant)
+        .await?;
+    if !rollout.enabled {
+        return Err(RouteError::UnsupportedGrantType);
+    }
+
+    // Check that the client is allowed to use this grant type
+    if !client.grant_types.contains(&GrantType::Password) {
+        return Err(RouteError::UnauthorizedClient(client.id));
+    }
+
+    // Default to an empty scope if none is provided
+    let scope = grant
+        .scope
+        .unwrap_or_else(|| std::iter::empty::<ScopeToken>().collect());
+
+    // Drop the scopes the client isn't allowed to request, if it is set up that
+    // way
+    let scope = client.filter_scope(scope);
+
+    // Try getting the localpart out of the MXID
+    let username = homeserver
+        .localpart(&grant.username)
+        .unwrap_or(&grant.username);
+
+    // Find the user. Locked and deactivated users can't log in this way.
+    let user = repo
+        .user()
+        .find_by_username(username)
+        .await?
+        .filter(mas_data_model::User::is_valid)
+        .ok_or(RouteError::InvalidCredentials)?;
+
+    // Check the rate limit
+    limiter.check_password(requester, &user)?;
+
+    // Check that the account isn't temporarily locked after too many failed
+    // attempts. There is no way to solve a CAPTCHA with this grant.
+    if password_lockout::check(&mut repo, clock, site_config, &user, requester).await?
+        != Lockout::None
+    {
+        return Err(RouteError::TooManyFailedAttempts);
+    }
+
+    // Lookup its password
+    let user_password = repo
+        .user_password()
+        .active(&user)
+        .await?
+        .ok_or(RouteError::InvalidCredentials)?;
+
+    // Verify the password
+    let password = Zeroizing::new(grant.password);
+    let new_password_hash = match password_manager
+        .verify_and_upgrade(
+            &mut rng,
+            user_password.version,
+            password,
+            user_password.hashed_password.clone(),
+        )
+        .await
+    {
+        Ok(new_password_hash) => new_password_hash,
+        Err(e) => {
+            // Save the failed attempt, as the transaction is rolled back on errors
+            password_lockout::record_failure(
+                &mut repo,
+                &mut rng,
+                clock,
+                site_config,
+                &user,
+                requester,
+            )
+            .await?;
+            repo.save().await?;
+            return Err(RouteError::PasswordVerificationFailed(e));
+        }
+    };
+
+    password_lockout::record_success(&mut repo, site_config, &user).await?;
+
+    // Only check whether the user is part of the rollout once they are
+    // authenticated, so that this doesn't tell whether the user exists
+    if !rollout.is_enabled_for(FeatureFlag::PasswordGrant, Some(user.id)) {
+        return Err(RouteError::UnsupportedGrantType);
+    }
+
+    // Users who have to change their password must do it through the web login
+    if password_policy::must_change(&mut repo, clock, site_config, &user, &user_password).await? {
+        return Err(RouteError::PasswordChangeRequired);
+    }
+
+    let user_password = if let Some((version, hashed_password)) = new_password_hash {
+        // Save the upgraded password if needed
+        repo.user_password()
+            .add(
+                &mut rng,
+                clock,
+                &user,
+                version,
+                hashed_password,
+                Some(&user_password),
+            )
+            .await?
+    } else {
+        user_password
+    };
+
+    let emails = repo.user_email().all(&user).await?;
+
+    // Make the request go through the policy engine
+    let res = policy
+        .evaluate_authorization_grant(mas_policy::AuthorizationGrantInput {
+            user: Some(&user),
+            emails: emails.iter().map(|e| e.email.as_str()).collect(),
+            client,
+            scope: &scope,
+            grant_type: mas_policy::GrantType::Password,
+            requested_claims: BTreeSet::new(),
+            requester: mas_policy::Requester {
+                ip_address: activity_tracker.ip(),
+                user_agent: user_agent.clone(),
+            },
+        })
+        .await?;
+    if !res.valid() {
+        return Err(RouteError::DeniedByPolicy(res));
+    }
+
+    // Start a browser session, authenticated by the password, to back the OAuth
+    // 2.0 session
+    let browser_session = repo
+        .browser_session()
+        .add(rng, clock, &user, user_agent.clone())
+        .await?;
+
+    repo.browser_session()
+        .authenticate_with_password(rng, clock, &browser_session, &user_password)
+        .await?;
+
+    let mut session = repo
+        .oauth2_session()
+        .add_from_browser_session(rng, clock, client, &browser_session, scope)
+        .await?;
+
+    if let Some(user_agent) = user_agent {
+        session = repo
+            .oauth2_session()
+            .record_user_agent(session, user_agent)
+            .await?;
+    }
+
+    let ttl = client
+        .access_token_ttl
+        .unwrap_or(site_config.access_token_ttl);
+    let access_token_str = access_tokens
+        .generate(rng, clock, &mut repo, client, &session, ttl)
+        .await?;
+
+    let access_token = repo
+        .oauth2_access_token()
+        .add(rng, clock, &session, access_token_str, Some(ttl))
+        .await?;
+
+    let mut params =
+        AccessTokenResponse::new(access_token.access_token.clone()).with_expires_in(ttl);
+
+    // If the client uses the refresh token grant type, we also generate a refresh
+    // token
+    if client.grant_types.contains(&GrantType::RefreshToken) {
+        let refresh_token_str = TokenType::RefreshToken.generate(rng);
+
+        let refresh_token = repo
+            .oauth2_refresh_token()
+            .add(rng, clock, &session, &access_token, refresh_token_str)
+            .await?;
+
+        params = params.with_refresh_token(refresh_token.refresh_token);
+    }
+
+    // If the client asked for an ID token, we generate one
+    if session.scope.contains(&scope::OPENID) {
+        let custom_claims = user_attribute_claims(&mut repo, site_config, &user).await?;
+        let sub = subject_for_client(rng, clock, &mut repo, client, &user).await?;
+
+        let id_token = generate_id_token(
+            rng,
+            clock,
+            url_builder,
+            key_store,
+            site_config,
+            client,
+            None,
+            &browser_session,
+            &sub,
+            Some(&access_token),
+            None,
+            None,
+            custom_claims,
+        )?;
+
+        params = params.with_id_token(id_token);
+    }
+
+    // Lock the user sync to make sure we don't get into a race condition
+    repo.user().acquire_lock_for_sync(&user).await?;
+
+    // Look for device to provision
+    let mxid = homeserver.mxid(&user.username);
+    for scope in &*session.scope {
+        if let Some(device) = Device::from_scope_token(scope) {
+            homeserver
+                .create_device(&mxid, device.as_str(), None)
+                .await
+                .map_err(RouteError::ProvisionDeviceFailed)?;
+        }
+    }
+
+    // XXX: there is a potential (but unlikely) race here, where the activity for
+    // the session is recorded before the transaction is committed. We would have to
+    // save the repository here to fix that.
+    activity_tracker
+        .record_browser_session(clock, &browser_session)
+        .await;
+    activity_tracker
+        .record_oauth2_session(clock, &session)
+        .await;
+
+    if !session.scope.is_empty() {
+        // We only return the scope if it's not empty
+        params = params.with_scope(session.scope);
+    }
+
+    Ok((params, repo))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::Request;
+    use mas_data_model::{
+        AccessToken, AccessTokenFormat, AuthorizationCode, DisallowedScopeHandling,
+        FeatureFlagRollout, RefreshToken, RefreshTokenRotation, ScopePolicy, TokenAudience,
+    };
+    use mas_iana::jose::{JsonWebKeyUse, JsonWebSignatureAlg};
+    use mas_jose::{
+        jwe::Jwe,
+        jwk::{JsonWebKey, JsonWebKeyPrivateParameters, JsonWebKeyPublicParameters},
+        jwt::{JsonWebSignatureHeader, Jwt},
+    };
+    use mas_matrix::ProvisionRequest;
+    use mas_router::SimpleRoute;
+    use mas_storage::oauth2::{OAuth2AuthorizationGrantParams, OAuth2StaticClientParams};
+    use oauth2_types::{
+        registration::ClientRegistrationResponse,
+        requests::{DeviceAuthorizationResponse, IntrospectionResponse, ResponseMode},
+        scope::{OPENID, Scope},
+    };
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_auth_code_grant(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Provision a client
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+
+        let ClientRegistrationResponse { client_id, .. } = response.json();
+
+        // Let's provision a user and create a session for them. This part is hard to
+        // test with just HTTP requests, so we'll use the repository directly.
+        let mut repo = state.repository().await.unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+
+        let browser_session = repo
+            .browser_session()
+            .add(&mut state.rng(), &state.clock, &user, None)
+            .await
+            .unwrap();
+
+        // Lookup the client in the database.
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&client_id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Start a grant
+        let code = "thisisaverysecurecode";
+        let grant = repo
+            .oauth2_authorization_grant()
+            .add(
+                &mut state.rng(),
+                &state.clock,
+                &client,
+                "https://example.com/redirect".parse().unwrap(),
+                Scope::from_iter([OPENID]),
+                ResponseMode::Query,
+                OAuth2AuthorizationGrantParams {
+                    code: Some(AuthorizationCode {
+                        code: code.to_owned(),
+                        pkce: None,
+                    }),
+                    state: Some("state".to_owned()),
+                    nonce: Some("nonce".to_owned()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(
+                &mut state.rng(),
+                &state.clock,
+                &client,
+                &browser_session,
+                grant.scope.clone(),
+            )
+            .await
+            .unwrap();
+
+        // And fulfill it
+        let grant = repo
+            .oauth2_authorization_grant()
+            .fulfill(&state.clock, &session, grant)
+            .await
+            .unwrap();
+
+        repo.save().await.unwrap();
+
+        // Now call the token endpoint to get an access token.
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": grant.redirect_uri,
+                "client_id": client.client_id,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        let AccessTokenResponse { access_token, .. } = response.json();
+
+        // Check that the token is valid
+        assert!(state.is_access_token_valid(&access_token).await);
+
+        // Exchange it again, this it should fail
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": grant.redirect_uri,
+                "client_id": client.client_id,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let error: ClientError = response.json();
+        assert_eq!(error.error, ClientErrorCode::InvalidGrant);
+
+        // The token should still be valid
+        assert!(state.is_access_token_valid(&access_token).await);
+
+        // Now wait a bit
+        state.clock.advance(Duration::try_minutes(1).unwrap());
+
+        // Exchange it again, this it should fail
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": grant.redirect_uri,
+                "client_id": client.client_id,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let error: ClientError = response.json();
+        assert_eq!(error.error, ClientErrorCode::InvalidGrant);
+
+        // And it should have revoked the token we got
+        assert!(!state.is_access_token_valid(&access_token).await);
+
+        // Try another one and wait for too long before exchanging it
+        let mut repo = state.repository().await.unwrap();
+        let code = "thisisanothercode";
+        let grant = repo
+            .oauth2_authorization_grant()
+            .add(
+                &mut state.rng(),
+                &state.clock,
+                &client,
+                "https://example.com/redirect".parse().unwrap(),
+                Scope::from_iter([OPENID]),
+                ResponseMode::Query,
+                OAuth2AuthorizationGrantParams {
+                    code: Some(AuthorizationCode {
+                        code: code.to_owned(),
+                        pkce: None,
+                    }),
+                    state: Some("state".to_owned()),
+                    nonce: Some("nonce".to_owned()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(
+                &mut state.rng(),
+                &state.clock,
+                &client,
+                &browser_session,
+                grant.scope.clone(),
+            )
+            .await
+            .unwrap();
+
+        // And fulfill it
+        let grant = repo
+            .oauth2_authorization_grant()
+            .fulfill(&state.clock, &session, grant)
+            .await
+            .unwrap();
+
+        repo.save().await.unwrap();
+
+        // Now wait a bit
+        state
+            .clock
+            .advance(Duration::microseconds(15 * 60 * 1000 * 1000));
+
+        // Exchange it, it should fail
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": grant.redirect_uri,
+                "client_id": client.client_id,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidGrant);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_auth_code_grant_encrypted_id_token(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let mut rng = state.rng();
+
+        // Generate a key for the server to encrypt the ID tokens with
+        let key = mas_keystore::PrivateKey::generate_ec_p256(&mut rng);
+        let mas_keystore::PrivateKey::EcP256(secret_key) = &key else {
+            unreachable!()
+        };
+        let private_key = JsonWebKeyPrivateParameters::from(&**secret_key);
+        let jwk = JsonWebKey::new(JsonWebKeyPublicParameters::from(&key))
+            .with_use(JsonWebKeyUse::Enc)
+            .with_kid("enc-key");
+
+        // Provision a client which asks for encrypted ID tokens
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code"],
+                "jwks": { "keys": [jwk] },
+                "id_token_encrypted_response_alg": "ECDH-ES",
+                "id_token_encrypted_response_enc": "A256GCM",
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+
+        let ClientRegistrationResponse { client_id, .. } = response.json();
+
+        let mut repo = state.repository().await.unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&client_id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let code = "thisisaverysecurecode";
+        let grant = repo
+            .oauth2_authorization_grant()
+            .add(
+                &mut rng,
+                &state.clock,
+                &client,
+                "https://example.com/redirect".parse().unwrap(),
+                Scope::from_iter([OPENID]),
+                ResponseMode::Query,
+                OAuth2AuthorizationGrantParams {
+                    code: Some(AuthorizationCode {
+                        code: code.to_owned(),
+                        pkce: None,
+                    }),
+                    state: Some("state".to_owned()),
+                    nonce: Some("nonce".to_owned()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(
+                &mut rng,
+                &state.clock,
+                &client,
+                &browser_session,
+                grant.scope.clone(),
+            )
+            .await
+            .unwrap();
+
+        let grant = repo
+            .oauth2_authorization_grant()
+            .fulfill(&state.clock, &session, grant)
+            .await
+            .unwrap();
+
+        repo.save().await.unwrap();
+
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": grant.redirect_uri,
+                "client_id": client.client_id,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        let AccessTokenResponse { id_token, .. } = response.json();
+        let id_token = id_token.expect("to have an ID token");
+
+        // The ID token is encrypted to the client key
+        let jwe = Jwe::try_from(id_token.as_str()).unwrap();
+        assert_eq!(jwe.header().kid(), Some("enc-key"));
+        assert_eq!(jwe.header().cty(), Some("JWT"));
+
+        // And contains the signed ID token
+        let id_token = String::from_utf8(jwe.decrypt(&private_key).unwrap()).unwrap();
+        let jwt: Jwt<'_, serde_json::Value> = Jwt::try_from(id_token.as_str()).unwrap();
+        assert_eq!(jwt.payload()["aud"], client.client_id);
+        assert_eq!(jwt.payload()["nonce"], "nonce");
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_refresh_token_grant(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Provision a client
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code", "refresh_token"],
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+
+        let ClientRegistrationResponse { client_id, .. } = response.json();
+
+        // Let's provision a user and create a session for them. This part is hard to
+        // test with just HTTP requests, so we'll use the repository directly.
+        let mut repo = state.repository().await.unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+
+        let browser_session = repo
+            .browser_session()
+            .add(&mut state.rng(), &state.clock, &user, None)
+            .await
+            .unwrap();
+
+        // Lookup the client in the database.
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&client_id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Get a token pair
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(
+                &mut state.rng(),
+                &state.clock,
+                &client,
+                &browser_session,
+                Scope::from_iter([OPENID]),
+            )
+            .await
+            .unwrap();
+
+        let (AccessToken { access_token, .. }, RefreshToken { refresh_token, .. }) =
+            generate_token_pair(
+                &mut state.rng(),
+                &state.clock,
+                &mut repo,
+                &AccessTokenGenerator::new(&state.url_builder, &state.key_store),
+                &client,
+                &session,
+                Duration::microseconds(5 * 60 * 1000 * 1000),
+            )
+            .await
+            .unwrap();
+
+        repo.save().await.unwrap();
+
+        // First check that the token is valid
+        assert!(state.is_access_token_valid(&access_token).await);
+
+        // Now call the token endpoint to get an access token.
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": refresh_token,
+                "client_id": client.client_id,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        let old_access_token = access_token;
+        let old_refresh_token = refresh_token;
+        let response: AccessTokenResponse = response.json();
+        let access_token = response.access_token;
+        let refresh_token = response.refresh_token.expect("to have a refresh token");
+
+        // Check that the new token is valid
+        assert!(state.is_access_token_valid(&access_token).await);
+
+        // Check that the old token is no longer valid
+        assert!(!state.is_access_token_valid(&old_access_token).await);
+
+        // Call it again with the old token, it should fail
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": old_refresh_token,
+                "client_id": client.client_id,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidGrant);
+
+        // Reusing the old token ended the session, so the new tokens are not valid
+        // anymore
+        assert!(!state.is_access_token_valid(&access_token).await);
+
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": refresh_token,
+                "client_id": client.client_id,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidGrant);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_double_refresh(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Provision a client
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code", "refresh_token"],
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+
+        let ClientRegistrationResponse { client_id, .. } = response.json();
+
+        // Let's provision a user and create a session for them. This part is hard to
+        // test with just HTTP requests, so we'll use the repository directly.
+        let mut repo = state.repository().await.unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+
+        let browser_session = repo
+            .browser_session()
+            .add(&mut state.rng(), &state.clock, &user, None)
+            .await
+            .unwrap();
+
+        // Lookup the client in the database.
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&client_id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Get a token pair
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(
+                &mut state.rng(),
+                &state.clock,
+                &client,
+                &browser_session,
+                Scope::from_iter([OPENID]),
+            )
+            .await
+            .unwrap();
+
+        let (AccessToken { access_token, .. }, RefreshToken { refresh_token, .. }) =
+            generate_token_pair(
+                &mut state.rng(),
+                &state.clock,
+                &mut repo,
+                &AccessTokenGenerator::new(&state.url_builder, &state.key_store),
+                &client,
+                &session,
+                Duration::microseconds(5 * 60 * 1000 * 1000),
+            )
+            .await
+            .unwrap();
+
+        repo.save().await.unwrap();
+
+        // First check that the token is valid
+        assert!(state.is_access_token_valid(&access_token).await);
+
+        // Now call the token endpoint to get an access token.
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": refresh_token,
+                "client_id": client.client_id,
+            }));
+
+        let first_response = state.request(request).await;
+        first_response.assert_status(StatusCode::OK);
+        let first_response: AccessTokenResponse = first_response.json();
+
+        // Call a second time, it should work, as we haven't done anything yet with the
+        // token
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": refresh_token,
+                "client_id": client.client_id,
+            }));
+
+        let second_response = state.request(request).await;
+        second_response.assert_status(StatusCode::OK);
+        let second_response: AccessTokenResponse = second_response.json();
+
+        // Check that we got new tokens
+        assert_ne!(first_response.access_token, second_response.access_token);
+        assert_ne!(first_response.refresh_token, second_response.refresh_token);
+
+        // Check that the old-new token is invalid
+        assert!(
+            !state
+                .is_access_token_valid(&first_response.access_token)
+                .await
+        );
+
+        // Check that the new-new token is valid
+        assert!(
+            state
+                .is_access_token_valid(&second_response.access_token)
+                .await
+        );
+
+        // Do a third refresh, this one should not work, as we've used the new
+        // access token
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": refresh_token,
+                "client_id": client.client_id,
+            }));
+
+        let third_response = state.request(request).await;
+        third_response.assert_status(StatusCode::BAD_REQUEST);
+
+        // This is a replay, which ends the whole session
+        assert!(
+            !state
+                .is_access_token_valid(&second_response.access_token)
+                .await
+        );
+
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": second_response.refresh_token,
+                "client_id": client.client_id,
+            }));
+
+        let fourth_response = state.request(request).await;
+        fourth_response.assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_refresh_token_reuse(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Provision a client
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "redirect_uris": ["https://example.com/callback"],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code", "refresh_token"],
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+
+        let ClientRegistrationResponse { client_id, .. } = response.json();
+
+        let mut repo = state.repository().await.unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+
+        let browser_session = repo
+            .browser_session()
+            .add(&mut state.rng(), &state.clock, &user, None)
+            .await
+            .unwrap();
+
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&client_id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(
+                &mut state.rng(),
+                &state.clock,
+                &client,
+                &browser_session,
+                Scope::from_iter([OPENID]),
+            )
+            .await
+            .unwrap();
+
+        let (_, RefreshToken { refresh_token, .. }) = generate_token_pair(
+            &mut state.rng(),
+            &state.clock,
+            &mut repo,
+            &AccessTokenGenerator::new(&state.url_builder, &state.key_store),
+            &client,
+            &session,
+            Duration::microseconds(5 * 60 * 1000 * 1000),
+        )
+        .await
+        .unwrap();
+
+        repo.save().await.unwrap();
+
+        // Refresh twice in a row, without using the access tokens
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": refresh_token,
+                "client_id": client.client_id,
+            }));
+
+        let first_response = state.request(request).await;
+        first_response.assert_status(StatusCode::OK);
+        let first_response: AccessTokenResponse = first_response.json();
+
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": first_response.refresh_token,
+                "client_id": client.client_id,
+            }));
+
+        let second_response = state.request(request).await;
+        second_response.assert_status(StatusCode::OK);
+        let second_response: AccessTokenResponse = second_response.json();
+
+        // The refresh token which replaced the first one was already used, so
+        // reusing the first one is a replay
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": refresh_token,
+                "client_id": client.client_id,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidGrant);
+
+        // The whole session was ended
+        assert!(
+            !state
+                .is_access_token_valid(&second_response.access_token)
+                .await
+        );
+
+        let mut repo = state.repository().await.unwrap();
+        let session = repo
+            .oauth2_session()
+            .lookup(session.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!session.is_valid());
+
+        // The reuse was recorded in the audit log
+        let events = repo.audit_event().all_for_user(&user).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            events[0].kind,
+            AuditEventKind::RefreshTokenReused { oauth2_session_id, oauth2_client_id, .. }
+                if oauth2_session_id == session.id && oauth2_client_id == client.id
+        ));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_static_refresh_token(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Provision a static client which doesn't rotate its refresh tokens
+        let client_id = Ulid::from_string("01JZ7T3W6H0Q5CBA2V9N4XKJ8R").unwrap();
+        let mut repo = state.repository().await.unwrap();
+        let client = repo
+            .oauth2_client()
+            .upsert_static(
+                client_id,
+                mas_iana::oauth::OAuthClientAuthenticationMethod::None,
+                OAuth2StaticClientParams {
+                    redirect_uris: vec!["https://example.com/callback".parse().unwrap()],
+                    refresh_token_rotation: RefreshTokenRotation::Static,
+                    backchannel_logout_include_sub: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+
+        let browser_session = repo
+            .browser_session()
+            .add(&mut state.rng(), &state.clock, &user, None)
+            .await
+            .unwrap();
+
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(
+                &mut state.rng(),
+                &state.clock,
+                &client,
+                &browser_session,
+                Scope::from_iter([OPENID]),
+            )
+            .await
+            .unwrap();
+
+        let (AccessToken { access_token, .. }, RefreshToken { refresh_token, .. }) =
+            generate_token_pair(
+                &mut state.rng(),
+                &state.clock,
+                &mut repo,
+                &AccessTokenGenerator::new(&state.url_builder, &state.key_store),
+                &client,
+                &session,
+                Duration::microseconds(5 * 60 * 1000 * 1000),
+            )
+            .await
+            .unwrap();
 
-        // Let's provision a user and create a session for them. This part is hard to
-        // test with just HTTP requests, so we'll use the repository directly.
+        repo.save().await.unwrap();
+
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": refresh_token,
+                "client_id": client.client_id,
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let response: AccessTokenResponse = response.json();
+
+        // The refresh token is kept, but the old access token is revoked
+        assert_eq!(response.refresh_token.as_ref(), Some(&refresh_token));
+        assert!(!state.is_access_token_valid(&access_token).await);
+        assert!(state.is_access_token_valid(&response.access_token).await);
+
+        // It can be used again, even once the new access token was used
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": refresh_token,
+                "client_id": client.client_id,
+            }));
+
+        let second_response = state.request(request).await;
+        second_response.assert_status(StatusCode::OK);
+        let second_response: AccessTokenResponse = second_response.json();
+
+        assert_eq!(second_response.refresh_token.as_ref(), Some(&refresh_token));
+        assert!(!state.is_access_token_valid(&response.access_token).await);
+        assert!(
+            state
+                .is_access_token_valid(&second_response.access_token)
+                .await
+        );
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_client_token_lifetimes(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Provision a static client with short-lived tokens
+        let client_id = Ulid::from_string("01JZCRQ8G3V7M2N5T4W1XKD9HB").unwrap();
         let mut repo = state.repository().await.unwrap();
+        let client = repo
+            .oauth2_client()
+            .upsert_static(
+                client_id,
+                mas_iana::oauth::OAuthClientAuthenticationMethod::None,
+                OAuth2StaticClientParams {
+                    redirect_uris: vec!["https://example.com/callback".parse().unwrap()],
+                    refresh_token_rotation: RefreshTokenRotation::Static,
+                    backchannel_logout_include_sub: true,
+                    access_token_ttl: Some(Duration::minutes(1)),
+                    refresh_token_ttl: Some(Duration::hours(1)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
 
         let user = repo
             .user()
@@ -1027,38 +2813,6 @@ mod tests {
             .await
             .unwrap();
 
-        // Lookup the client in the database.
-        let client = repo
-            .oauth2_client()
-            .find_by_client_id(&client_id)
-            .await
-            .unwrap()
-            .unwrap();
-
-        // Start a grant
-        let code = "thisisaverysecurecode";
-        let grant = repo
-            .oauth2_authorization_grant()
-            .add(
-                &mut state.rng(),
-                &state.clock,
-                &client,
-                "https://example.com/redirect".parse().unwrap(),
-                Scope::from_iter([OPENID]),
-                Some(AuthorizationCode {
-                    code: code.to_owned(),
-                    pkce: None,
-                }),
-                Some("state".to_owned()),
-                Some("nonce".to_owned()),
-                ResponseMode::Query,
-                false,
-                None,
-                None,
-            )
-            .await
-            .unwrap();
-
         let session = repo
             .oauth2_session()
             .add_from_browser_session(
@@ -1066,452 +2820,535 @@ mod tests {
                 &state.clock,
                 &client,
                 &browser_session,
-                grant.scope.clone(),
+                Scope::from_iter([OPENID]),
             )
             .await
             .unwrap();
 
-        // And fulfill it
-        let grant = repo
-            .oauth2_authorization_grant()
-            .fulfill(&state.clock, &session, grant)
-            .await
-            .unwrap();
+        let (_, RefreshToken { refresh_token, .. }) = generate_token_pair(
+            &mut state.rng(),
+            &state.clock,
+            &mut repo,
+            &AccessTokenGenerator::new(&state.url_builder, &state.key_store),
+            &client,
+            &session,
+            Duration::microseconds(60 * 1000 * 1000),
+        )
+        .await
+        .unwrap();
 
         repo.save().await.unwrap();
 
-        // Now call the token endpoint to get an access token.
+        // Access tokens get the lifetime of the client
         let request =
             Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "authorization_code",
-                "code": code,
-                "redirect_uri": grant.redirect_uri,
+                "grant_type": "refresh_token",
+                "refresh_token": refresh_token,
                 "client_id": client.client_id,
             }));
 
         let response = state.request(request).await;
         response.assert_status(StatusCode::OK);
+        let response: AccessTokenResponse = response.json();
+        assert_eq!(
+            response.expires_in,
+            Some(Duration::microseconds(60 * 1000 * 1000))
+        );
 
-        let AccessTokenResponse { access_token, .. } = response.json();
-
-        // Check that the token is valid
-        assert!(state.is_access_token_valid(&access_token).await);
+        // The refresh token can't be used once it expired
+        state
+            .clock
+            .advance(Duration::microseconds(2 * 60 * 60 * 1000 * 1000));
 
-        // Exchange it again, this it should fail
         let request =
             Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "authorization_code",
-                "code": code,
-                "redirect_uri": grant.redirect_uri,
+                "grant_type": "refresh_token",
+                "refresh_token": refresh_token,
                 "client_id": client.client_id,
             }));
 
         let response = state.request(request).await;
         response.assert_status(StatusCode::BAD_REQUEST);
-        let error: ClientError = response.json();
-        assert_eq!(error.error, ClientErrorCode::InvalidGrant);
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidGrant);
+    }
 
-        // The token should still be valid
-        assert!(state.is_access_token_valid(&access_token).await);
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_client_credentials(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
 
-        // Now wait a bit
-        state.clock.advance(Duration::try_minutes(1).unwrap());
+        // Provision a client
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "token_endpoint_auth_method": "client_secret_post",
+                "grant_types": ["client_credentials"],
+            }));
 
-        // Exchange it again, this it should fail
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+        let client_secret = response.client_secret.expect("to have a client secret");
+
+        // Call the token endpoint with an empty scope
         let request =
             Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "authorization_code",
-                "code": code,
-                "redirect_uri": grant.redirect_uri,
-                "client_id": client.client_id,
+                "grant_type": "client_credentials",
+                "client_id": client_id,
+                "client_secret": client_secret,
             }));
 
         let response = state.request(request).await;
-        response.assert_status(StatusCode::BAD_REQUEST);
-        let error: ClientError = response.json();
-        assert_eq!(error.error, ClientErrorCode::InvalidGrant);
+        response.assert_status(StatusCode::OK);
 
-        // And it should have revoked the token we got
-        assert!(!state.is_access_token_valid(&access_token).await);
+        let response: AccessTokenResponse = response.json();
+        assert!(response.refresh_token.is_none());
+        assert!(response.expires_in.is_some());
+        assert!(response.scope.is_none());
 
-        // Try another one and wait for too long before exchanging it
-        let mut repo = state.repository().await.unwrap();
-        let code = "thisisanothercode";
-        let grant = repo
-            .oauth2_authorization_grant()
-            .add(
-                &mut state.rng(),
-                &state.clock,
-                &client,
-                "https://example.com/redirect".parse().unwrap(),
-                Scope::from_iter([OPENID]),
-                Some(AuthorizationCode {
-                    code: code.to_owned(),
-                    pkce: None,
-                }),
-                Some("state".to_owned()),
-                Some("nonce".to_owned()),
-                ResponseMode::Query,
-                false,
-                None,
-                None,
-            )
-            .await
-            .unwrap();
+        // Revoke the token
+        let request = Request::post(mas_router::OAuth2Revocation::PATH).form(serde_json::json!({
+            "token": response.access_token,
+            "client_id": client_id,
+            "client_secret": client_secret,
+        }));
 
-        let session = repo
-            .oauth2_session()
-            .add_from_browser_session(
-                &mut state.rng(),
-                &state.clock,
-                &client,
-                &browser_session,
-                grant.scope.clone(),
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        // We should be allowed to ask for the GraphQL API scope
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "client_credentials",
+                "client_id": client_id,
+                "client_secret": client_secret,
+                "scope": "urn:mas:graphql:*"
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        let response: AccessTokenResponse = response.json();
+        assert!(response.refresh_token.is_none());
+        assert!(response.expires_in.is_some());
+        assert_eq!(response.scope, Some("urn:mas:graphql:*".parse().unwrap()));
+
+        // Revoke the token
+        let request = Request::post(mas_router::OAuth2Revocation::PATH).form(serde_json::json!({
+            "token": response.access_token,
+            "client_id": client_id,
+            "client_secret": client_secret,
+        }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        // We should be NOT allowed to ask for the MAS admin scope
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "client_credentials",
+                "client_id": client_id,
+                "client_secret": client_secret,
+                "scope": "urn:mas:admin"
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::FORBIDDEN);
+
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidScope);
+
+        // Now, if we add the client to the admin list in the policy, it should work
+        let state = {
+            let mut state = state;
+            state.policy_factory = crate::test_utils::policy_factory(
+                "example.com",
+                serde_json::json!({
+                    "admin_clients": [client_id]
+                }),
             )
             .await
             .unwrap();
+            state
+        };
 
-        // And fulfill it
-        let grant = repo
-            .oauth2_authorization_grant()
-            .fulfill(&state.clock, &session, grant)
-            .await
-            .unwrap();
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "client_credentials",
+                "client_id": client_id,
+                "client_secret": client_secret,
+                "scope": "urn:mas:admin"
+            }));
 
-        repo.save().await.unwrap();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
 
-        // Now wait a bit
-        state
-            .clock
-            .advance(Duration::microseconds(15 * 60 * 1000 * 1000));
+        let response: AccessTokenResponse = response.json();
+        assert!(response.refresh_token.is_none());
+        assert!(response.expires_in.is_some());
+        assert_eq!(response.scope, Some("urn:mas:admin".parse().unwrap()));
 
-        // Exchange it, it should fail
-        let request =
-            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "authorization_code",
-                "code": code,
-                "redirect_uri": grant.redirect_uri,
-                "client_id": client.client_id,
-            }));
+        // Revoke the token
+        let request = Request::post(mas_router::OAuth2Revocation::PATH).form(serde_json::json!({
+            "token": response.access_token,
+            "client_id": client_id,
+            "client_secret": client_secret,
+        }));
 
         let response = state.request(request).await;
-        response.assert_status(StatusCode::BAD_REQUEST);
-        let ClientError { error, .. } = response.json();
-        assert_eq!(error, ClientErrorCode::InvalidGrant);
+        response.assert_status(StatusCode::OK);
     }
 
     #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
-    async fn test_refresh_token_grant(pool: PgPool) {
+    async fn test_dpop_bound_tokens(pool: PgPool) {
         setup();
         let state = TestState::from_pool(pool).await.unwrap();
+        let mut rng = state.rng();
 
         // Provision a client
         let request =
             Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
                 "client_uri": "https://example.com/",
-                "redirect_uris": ["https://example.com/callback"],
-                "token_endpoint_auth_method": "none",
-                "response_types": ["code"],
-                "grant_types": ["authorization_code", "refresh_token"],
+                "token_endpoint_auth_method": "client_secret_post",
+                "grant_types": ["client_credentials"],
             }));
 
         let response = state.request(request).await;
         response.assert_status(StatusCode::CREATED);
 
-        let ClientRegistrationResponse { client_id, .. } = response.json();
-
-        // Let's provision a user and create a session for them. This part is hard to
-        // test with just HTTP requests, so we'll use the repository directly.
-        let mut repo = state.repository().await.unwrap();
-
-        let user = repo
-            .user()
-            .add(&mut state.rng(), &state.clock, "alice".to_owned())
-            .await
-            .unwrap();
-
-        let browser_session = repo
-            .browser_session()
-            .add(&mut state.rng(), &state.clock, &user, None)
-            .await
-            .unwrap();
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+        let client_secret = response.client_secret.expect("to have a client secret");
 
-        // Lookup the client in the database.
-        let client = repo
-            .oauth2_client()
-            .find_by_client_id(&client_id)
-            .await
-            .unwrap()
+        // Generate a key for the client to sign its proofs
+        let key = mas_keystore::PrivateKey::generate_ec_p256(&mut rng);
+        let public_key = JsonWebKeyPublicParameters::from(&key);
+        let signer = key
+            .signing_key_for_alg(&JsonWebSignatureAlg::Es256)
             .unwrap();
+        let mut proof = |htm: &str, jti: &str| {
+            let header = JsonWebSignatureHeader::new(JsonWebSignatureAlg::Es256)
+                .with_typ("dpop+jwt".to_owned())
+                .with_jwk(JsonWebKey::new(public_key.clone()));
+            let claims = serde_json::json!({
+                "htm": htm,
+                "htu": state.url_builder.oauth_token_endpoint(),
+                "iat": state.clock.now().timestamp(),
+                "jti": jti,
+            });
+            Jwt::sign_with_rng(&mut rng, header, claims, &signer)
+                .unwrap()
+                .into_string()
+        };
 
-        // Get a token pair
-        let session = repo
-            .oauth2_session()
-            .add_from_browser_session(
-                &mut state.rng(),
-                &state.clock,
-                &client,
-                &browser_session,
-                Scope::from_iter([OPENID]),
-            )
-            .await
-            .unwrap();
+        let form = serde_json::json!({
+            "grant_type": "client_credentials",
+            "client_id": client_id,
+            "client_secret": client_secret,
+        });
 
-        let (AccessToken { access_token, .. }, RefreshToken { refresh_token, .. }) =
-            generate_token_pair(
-                &mut state.rng(),
-                &state.clock,
-                &mut repo,
-                &session,
-                Duration::microseconds(5 * 60 * 1000 * 1000),
-            )
-            .await
-            .unwrap();
+        // Get a token with a valid proof
+        let request = Request::post(mas_router::OAuth2TokenEndpoint::PATH)
+            .header("DPoP", proof("POST", "first"))
+            .form(form.clone());
 
-        repo.save().await.unwrap();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
 
-        // First check that the token is valid
-        assert!(state.is_access_token_valid(&access_token).await);
+        let response: AccessTokenResponse = response.json();
+        assert_eq!(response.token_type, OAuthAccessTokenType::DPoP);
 
-        // Now call the token endpoint to get an access token.
+        // The token is bound to the key
         let request =
-            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "refresh_token",
-                "refresh_token": refresh_token,
-                "client_id": client.client_id,
+            Request::post(mas_router::OAuth2Introspection::PATH).form(serde_json::json!({
+                "token": response.access_token,
+                "client_id": client_id,
+                "client_secret": client_secret,
             }));
 
         let response = state.request(request).await;
         response.assert_status(StatusCode::OK);
 
-        let old_access_token = access_token;
-        let old_refresh_token = refresh_token;
-        let response: AccessTokenResponse = response.json();
-        let access_token = response.access_token;
-        let refresh_token = response.refresh_token.expect("to have a refresh token");
+        let response: oauth2_types::requests::IntrospectionResponse = response.json();
+        assert!(response.active);
+        assert_eq!(response.cnf.unwrap().jkt, Some(public_key.thumbprint()));
 
-        // Check that the new token is valid
-        assert!(state.is_access_token_valid(&access_token).await);
+        // The same proof can't be used twice
+        let request = Request::post(mas_router::OAuth2TokenEndpoint::PATH)
+            .header("DPoP", proof("POST", "first"))
+            .form(form.clone());
 
-        // Check that the old token is no longer valid
-        assert!(!state.is_access_token_valid(&old_access_token).await);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
 
-        // Call it again with the old token, it should fail
-        let request =
-            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "refresh_token",
-                "refresh_token": old_refresh_token,
-                "client_id": client.client_id,
-            }));
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidDpopProof);
+
+        // Proofs made for another method are refused
+        let request = Request::post(mas_router::OAuth2TokenEndpoint::PATH)
+            .header("DPoP", proof("GET", "second"))
+            .form(form.clone());
 
         let response = state.request(request).await;
         response.assert_status(StatusCode::BAD_REQUEST);
+
         let ClientError { error, .. } = response.json();
-        assert_eq!(error, ClientErrorCode::InvalidGrant);
+        assert_eq!(error, ClientErrorCode::InvalidDpopProof);
 
-        // Call it again with the new token, it should work
-        let request =
-            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "refresh_token",
-                "refresh_token": refresh_token,
-                "client_id": client.client_id,
-            }));
+        // Without a proof, the token is a regular bearer token
+        let request = Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(form);
 
         let response = state.request(request).await;
         response.assert_status(StatusCode::OK);
-        let _: AccessTokenResponse = response.json();
+
+        let response: AccessTokenResponse = response.json();
+        assert_eq!(response.token_type, OAuthAccessTokenType::Bearer);
     }
 
     #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
-    async fn test_double_refresh(pool: PgPool) {
+    async fn test_client_credentials_authorization_details(pool: PgPool) {
         setup();
-        let state = TestState::from_pool(pool).await.unwrap();
+        let site_config = SiteConfig {
+            authorization_details_types: vec!["account_information".to_owned()],
+            ..crate::test_utils::test_site_config()
+        };
+        let state = TestState::from_pool_with_site_config(pool, site_config)
+            .await
+            .unwrap();
 
         // Provision a client
         let request =
             Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
                 "client_uri": "https://example.com/",
-                "redirect_uris": ["https://example.com/callback"],
-                "token_endpoint_auth_method": "none",
-                "response_types": ["code"],
-                "grant_types": ["authorization_code", "refresh_token"],
+                "token_endpoint_auth_method": "client_secret_post",
+                "grant_types": ["client_credentials"],
             }));
 
         let response = state.request(request).await;
         response.assert_status(StatusCode::CREATED);
 
-        let ClientRegistrationResponse { client_id, .. } = response.json();
-
-        // Let's provision a user and create a session for them. This part is hard to
-        // test with just HTTP requests, so we'll use the repository directly.
-        let mut repo = state.repository().await.unwrap();
-
-        let user = repo
-            .user()
-            .add(&mut state.rng(), &state.clock, "alice".to_owned())
-            .await
-            .unwrap();
-
-        let browser_session = repo
-            .browser_session()
-            .add(&mut state.rng(), &state.clock, &user, None)
-            .await
-            .unwrap();
-
-        // Lookup the client in the database.
-        let client = repo
-            .oauth2_client()
-            .find_by_client_id(&client_id)
-            .await
-            .unwrap()
-            .unwrap();
-
-        // Get a token pair
-        let session = repo
-            .oauth2_session()
-            .add_from_browser_session(
-                &mut state.rng(),
-                &state.clock,
-                &client,
-                &browser_session,
-                Scope::from_iter([OPENID]),
-            )
-            .await
-            .unwrap();
-
-        let (AccessToken { access_token, .. }, RefreshToken { refresh_token, .. }) =
-            generate_token_pair(
-                &mut state.rng(),
-                &state.clock,
-                &mut repo,
-                &session,
-                Duration::microseconds(5 * 60 * 1000 * 1000),
-            )
-            .await
-            .unwrap();
-
-        repo.save().await.unwrap();
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+        let client_secret = response.client_secret.expect("to have a client secret");
 
-        // First check that the token is valid
-        assert!(state.is_access_token_valid(&access_token).await);
+        let details = serde_json::json!([{
+            "type": "account_information",
+            "actions": ["list_accounts"],
+            "locations": ["https://example.com/accounts"],
+        }]);
 
-        // Now call the token endpoint to get an access token.
         let request =
             Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "refresh_token",
-                "refresh_token": refresh_token,
-                "client_id": client.client_id,
+                "grant_type": "client_credentials",
+                "client_id": client_id,
+                "client_secret": client_secret,
+                "authorization_details": details.to_string(),
             }));
 
-        let first_response = state.request(request).await;
-        first_response.assert_status(StatusCode::OK);
-        let first_response: AccessTokenResponse = first_response.json();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let response: AccessTokenResponse = response.json();
 
-        // Call a second time, it should work, as we haven't done anything yet with the
-        // token
+        // The authorization details are returned by the introspection endpoint
         let request =
-            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "refresh_token",
-                "refresh_token": refresh_token,
-                "client_id": client.client_id,
+            Request::post(mas_router::OAuth2Introspection::PATH).form(serde_json::json!({
+                "token": response.access_token,
+                "client_id": client_id,
+                "client_secret": client_secret,
             }));
 
-        let second_response = state.request(request).await;
-        second_response.assert_status(StatusCode::OK);
-        let second_response: AccessTokenResponse = second_response.json();
-
-        // Check that we got new tokens
-        assert_ne!(first_response.access_token, second_response.access_token);
-        assert_ne!(first_response.refresh_token, second_response.refresh_token);
-
-        // Check that the old-new token is invalid
-        assert!(
-            !state
-                .is_access_token_valid(&first_response.access_token)
-                .await
-        );
-
-        // Check that the new-new token is valid
-        assert!(
-            state
-                .is_access_token_valid(&second_response.access_token)
-                .await
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let response: IntrospectionResponse = response.json();
+        assert!(response.active);
+        assert_eq!(
+            serde_json::to_value(response.authorization_details).unwrap(),
+            details
         );
 
-        // Do a third refresh, this one should not work, as we've used the new
-        // access token
+        // Types which weren't configured are rejected
         let request =
             Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "refresh_token",
-                "refresh_token": refresh_token,
-                "client_id": client.client_id,
+                "grant_type": "client_credentials",
+                "client_id": client_id,
+                "client_secret": client_secret,
+                "authorization_details": r#"[{"type": "payment_initiation"}]"#,
             }));
 
-        let third_response = state.request(request).await;
-        third_response.assert_status(StatusCode::BAD_REQUEST);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidAuthorizationDetails);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_client_credentials_service_account(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Provision a static client acting as a service account
+        let client_id = Ulid::from_string("01JZ0S3RV1CEACC0VNT5A4CC0V").unwrap();
+        let client_secret = "secret";
+        let encrypted_client_secret = state
+            .encrypter
+            .encrypt_to_string(client_secret.as_bytes())
+            .unwrap();
+        let mut repo = state.repository().await.unwrap();
+        repo.oauth2_client()
+            .upsert_static(
+                client_id,
+                mas_iana::oauth::OAuthClientAuthenticationMethod::ClientSecretPost,
+                OAuth2StaticClientParams {
+                    encrypted_client_secret: Some(encrypted_client_secret),
+                    service_account_scope: Some("urn:mas:graphql:*".parse().unwrap()),
+                    backchannel_logout_include_sub: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        let client_id = client_id.to_string();
 
-        // The other reason we consider a new refresh token to be 'used' is if
-        // it was already used in a refresh
-        // So, if we do a refresh with the second_response.refresh_token, then
-        // another refresh with the result, redoing one with
-        // second_response.refresh_token again should fail
+        // Without a scope, the service account gets all of its allowed scopes
         let request =
             Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "refresh_token",
-                "refresh_token": second_response.refresh_token,
-                "client_id": client.client_id,
+                "grant_type": "client_credentials",
+                "client_id": client_id,
+                "client_secret": client_secret,
             }));
 
-        // This one is fine
-        let fourth_response = state.request(request).await;
-        fourth_response.assert_status(StatusCode::OK);
-        let fourth_response: AccessTokenResponse = fourth_response.json();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        let response: AccessTokenResponse = response.json();
+        assert_eq!(response.scope, Some("urn:mas:graphql:*".parse().unwrap()));
 
-        // Do another one, it should be fine as well
+        // The token has a synthetic subject
         let request =
-            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "refresh_token",
-                "refresh_token": fourth_response.refresh_token,
-                "client_id": client.client_id,
+            Request::post(mas_router::OAuth2Introspection::PATH).form(serde_json::json!({
+                "token": response.access_token,
+                "client_id": client_id,
+                "client_secret": client_secret,
             }));
 
-        let fifth_response = state.request(request).await;
-        fifth_response.assert_status(StatusCode::OK);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let response: IntrospectionResponse = response.json();
+        assert!(response.active);
+        assert_eq!(response.sub, Some(format!("service-account:{client_id}")));
+        assert!(response.username.is_none());
 
-        // But now, if we re-do with the second_response.refresh_token, it should
-        // fail
+        // Scopes outside of the allowed ones are rejected
         let request =
             Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "refresh_token",
-                "refresh_token": second_response.refresh_token,
-                "client_id": client.client_id,
+                "grant_type": "client_credentials",
+                "client_id": client_id,
+                "client_secret": client_secret,
+                "scope": "urn:mas:graphql:* urn:mas:admin",
             }));
 
-        let sixth_response = state.request(request).await;
-        sixth_response.assert_status(StatusCode::BAD_REQUEST);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidScope);
     }
 
     #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
-    async fn test_client_credentials(pool: PgPool) {
+    async fn test_client_credentials_scope_policy(pool: PgPool) {
         setup();
         let state = TestState::from_pool(pool).await.unwrap();
 
-        // Provision a client
-        let request =
-            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
-                "client_uri": "https://example.com/",
-                "token_endpoint_auth_method": "client_secret_post",
-                "grant_types": ["client_credentials"],
-            }));
+        let client_id = Ulid::from_string("01K0M3ZJ6V9Q2C8D4XTB5N7R1F").unwrap();
+        let client_secret = "secret";
+
+        for disallowed in [
+            DisallowedScopeHandling::Filter,
+            DisallowedScopeHandling::Reject,
+        ] {
+            // Provision a static client which may only request GraphQL scopes
+            let encrypted_client_secret = state
+                .encrypter
+                .encrypt_to_string(client_secret.as_bytes())
+                .unwrap();
+            let mut repo = state.repository().await.unwrap();
+            repo.oauth2_client()
+                .upsert_static(
+                    client_id,
+                    mas_iana::oauth::OAuthClientAuthenticationMethod::ClientSecretPost,
+                    OAuth2StaticClientParams {
+                        encrypted_client_secret: Some(encrypted_client_secret),
+                        backchannel_logout_include_sub: true,
+                        scope_policy: Some(ScopePolicy {
+                            allowed_scopes: vec!["urn:mas:graphql:*".to_owned()],
+                            disallowed,
+                        }),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
+            repo.save().await.unwrap();
+
+            let request =
+                Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                    "grant_type": "client_credentials",
+                    "client_id": client_id.to_string(),
+                    "client_secret": client_secret,
+                    "scope": "urn:mas:graphql:* email",
+                }));
+
+            let response = state.request(request).await;
+
+            if disallowed == DisallowedScopeHandling::Filter {
+                // The email scope is dropped from the request
+                response.assert_status(StatusCode::OK);
+                let response: AccessTokenResponse = response.json();
+                assert_eq!(response.scope, Some("urn:mas:graphql:*".parse().unwrap()));
+            } else {
+                // The whole request is denied by the policy
+                response.assert_status(StatusCode::FORBIDDEN);
+                let ClientError { error, .. } = response.json();
+                assert_eq!(error, ClientErrorCode::InvalidScope);
+            }
+        }
+    }
 
-        let response = state.request(request).await;
-        response.assert_status(StatusCode::CREATED);
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_client_credentials_jwt_access_token(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
 
-        let response: ClientRegistrationResponse = response.json();
-        let client_id = response.client_id;
-        let client_secret = response.client_secret.expect("to have a client secret");
+        // Provision a static service account which gets JWT access tokens
+        let client_id = Ulid::from_string("01JZADQ4M7W1XKC3Y5T0N8RBGE").unwrap();
+        let client_secret = "secret";
+        let encrypted_client_secret = state
+            .encrypter
+            .encrypt_to_string(client_secret.as_bytes())
+            .unwrap();
+        let mut repo = state.repository().await.unwrap();
+        repo.oauth2_client()
+            .upsert_static(
+                client_id,
+                mas_iana::oauth::OAuthClientAuthenticationMethod::ClientSecretPost,
+                OAuth2StaticClientParams {
+                    encrypted_client_secret: Some(encrypted_client_secret),
+                    service_account_scope: Some("urn:mas:graphql:*".parse().unwrap()),
+                    backchannel_logout_include_sub: true,
+                    access_token_format: AccessTokenFormat::Jwt,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        let client_id = client_id.to_string();
 
-        // Call the token endpoint with an empty scope
         let request =
             Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
                 "grant_type": "client_credentials",
@@ -1521,71 +3358,265 @@ mod tests {
 
         let response = state.request(request).await;
         response.assert_status(StatusCode::OK);
-
         let response: AccessTokenResponse = response.json();
-        assert!(response.refresh_token.is_none());
-        assert!(response.expires_in.is_some());
-        assert!(response.scope.is_none());
 
-        // Revoke the token
-        let request = Request::post(mas_router::OAuth2Revocation::PATH).form(serde_json::json!({
-            "token": response.access_token,
-            "client_id": client_id,
-            "client_secret": client_secret,
-        }));
+        // The access token is a JWT signed by the server, following RFC 9068
+        let jwt: Jwt<'_, std::collections::HashMap<String, serde_json::Value>> =
+            Jwt::try_from(response.access_token.as_str()).unwrap();
+        jwt.verify_with_jwks(&state.key_store.public_jwks())
+            .unwrap();
+        assert_eq!(jwt.header().typ(), Some("at+jwt"));
+
+        let claims = jwt.payload();
+        assert_eq!(claims["iss"], state.url_builder.oidc_issuer().as_str());
+        assert_eq!(claims["sub"], format!("service-account:{client_id}"));
+        assert_eq!(claims["aud"], client_id);
+        assert_eq!(claims["client_id"], client_id);
+        assert_eq!(claims["scope"], "urn:mas:graphql:*");
+        assert!(claims.contains_key("jti"));
+        assert!(!claims.contains_key("cnf"));
+
+        // It is still recorded, so it can be introspected
+        let request =
+            Request::post(mas_router::OAuth2Introspection::PATH).form(serde_json::json!({
+                "token": response.access_token,
+                "client_id": client_id,
+                "client_secret": client_secret,
+            }));
 
         let response = state.request(request).await;
         response.assert_status(StatusCode::OK);
+        let response: IntrospectionResponse = response.json();
+        assert!(response.active);
+        assert_eq!(response.client_id, Some(client_id));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_client_credentials_resource(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Provision a static client allowed to request tokens for one resource
+        let client_id = Ulid::from_string("01JZ4KQ8RB1Y5E6V6NC7B8ZQ2D").unwrap();
+        let client_secret = "secret";
+        let encrypted_client_secret = state
+            .encrypter
+            .encrypt_to_string(client_secret.as_bytes())
+            .unwrap();
+        let mut repo = state.repository().await.unwrap();
+        repo.oauth2_client()
+            .upsert_static(
+                client_id,
+                mas_iana::oauth::OAuthClientAuthenticationMethod::ClientSecretPost,
+                OAuth2StaticClientParams {
+                    encrypted_client_secret: Some(encrypted_client_secret),
+                    allowed_resources: vec!["https://api.example.com/".parse().unwrap()],
+                    backchannel_logout_include_sub: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        let client_id = client_id.to_string();
 
-        // We should be allowed to ask for the GraphQL API scope
         let request =
             Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
                 "grant_type": "client_credentials",
                 "client_id": client_id,
                 "client_secret": client_secret,
-                "scope": "urn:mas:graphql:*"
+                "resource": "https://api.example.com/",
             }));
 
         let response = state.request(request).await;
         response.assert_status(StatusCode::OK);
-
         let response: AccessTokenResponse = response.json();
-        assert!(response.refresh_token.is_none());
-        assert!(response.expires_in.is_some());
-        assert_eq!(response.scope, Some("urn:mas:graphql:*".parse().unwrap()));
 
-        // Revoke the token
-        let request = Request::post(mas_router::OAuth2Revocation::PATH).form(serde_json::json!({
-            "token": response.access_token,
-            "client_id": client_id,
-            "client_secret": client_secret,
-        }));
+        // The resource is the audience of the token
+        let request =
+            Request::post(mas_router::OAuth2Introspection::PATH).form(serde_json::json!({
+                "token": response.access_token,
+                "client_id": client_id,
+                "client_secret": client_secret,
+            }));
 
         let response = state.request(request).await;
         response.assert_status(StatusCode::OK);
+        let response: IntrospectionResponse = response.json();
+        assert!(response.active);
+        assert_eq!(response.aud.as_deref(), Some("https://api.example.com/"));
 
-        // We should be NOT allowed to ask for the MAS admin scope
+        // Resources which aren't registered for the client are rejected
         let request =
             Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
                 "grant_type": "client_credentials",
                 "client_id": client_id,
                 "client_secret": client_secret,
-                "scope": "urn:mas:admin"
+                "resource": "https://other.example.com/",
             }));
 
         let response = state.request(request).await;
-        response.assert_status(StatusCode::FORBIDDEN);
+        response.assert_status(StatusCode::BAD_REQUEST);
 
         let ClientError { error, .. } = response.json();
-        assert_eq!(error, ClientErrorCode::InvalidScope);
+        assert_eq!(error, ClientErrorCode::InvalidTarget);
+    }
 
-        // Now, if we add the client to the admin list in the policy, it should work
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_resource_server_audience(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Provision a client allowed to request tokens for a resource, and two
+        // resource servers: the homeserver and another API
+        let client_id = Ulid::from_string("01JZ4KQ8RB1Y5E6V6NC7B8ZQ2D").unwrap();
+        let homeserver_id = Ulid::from_string("01K06ZB5ZV7W4Z4N1P1VQ7X0HS").unwrap();
+        let api_id = Ulid::from_string("01K06ZB5ZV7W4Z4N1P1VQ7X0AP").unwrap();
+        let client_secret = "secret";
+        let mut repo = state.repository().await.unwrap();
+        for (id, allowed_resources, resource_server_audience) in [
+            (
+                client_id,
+                vec!["https://api.example.com/".parse().unwrap()],
+                None,
+            ),
+            (homeserver_id, Vec::new(), Some(TokenAudience::Matrix)),
+            (
+                api_id,
+                Vec::new(),
+                Some(TokenAudience::Resource(
+                    "https://api.example.com/".parse().unwrap(),
+                )),
+            ),
+        ] {
+            let encrypted_client_secret = state
+                .encrypter
+                .encrypt_to_string(client_secret.as_bytes())
+                .unwrap();
+            repo.oauth2_client()
+                .upsert_static(
+                    id,
+                    mas_iana::oauth::OAuthClientAuthenticationMethod::ClientSecretPost,
+                    OAuth2StaticClientParams {
+                        encrypted_client_secret: Some(encrypted_client_secret),
+                        allowed_resources,
+                        backchannel_logout_include_sub: true,
+                        resource_server_audience,
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        repo.save().await.unwrap();
+
+        // Get one token for the API, and one without a resource
+        let mut tokens = Vec::new();
+        for resource in [Some("https://api.example.com/"), None] {
+            let mut form = serde_json::json!({
+                "grant_type": "client_credentials",
+                "client_id": client_id.to_string(),
+                "client_secret": client_secret,
+            });
+            if let Some(resource) = resource {
+                form["resource"] = resource.into();
+            }
+            let request = Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(form);
+
+            let response = state.request(request).await;
+            response.assert_status(StatusCode::OK);
+            let response: AccessTokenResponse = response.json();
+            tokens.push(response.access_token);
+        }
+
+        // Each resource server only sees the tokens issued for it as active
+        for (token, homeserver_active, api_active) in
+            [(&tokens[0], false, true), (&tokens[1], true, false)]
+        {
+            for (id, active) in [(homeserver_id, homeserver_active), (api_id, api_active)] {
+                let request =
+                    Request::post(mas_router::OAuth2Introspection::PATH).form(serde_json::json!({
+                        "token": token,
+                        "client_id": id.to_string(),
+                        "client_secret": client_secret,
+                    }));
+
+                let response = state.request(request).await;
+                response.assert_status(StatusCode::OK);
+                let response: IntrospectionResponse = response.json();
+                assert_eq!(response.active, active);
+            }
+        }
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_grant(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // Provision a client
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "token_endpoint_auth_method": "client_secret_post",
+                "grant_types": ["password", "refresh_token"],
+            }));
+
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+        let client_secret = response.client_secret.expect("to have a client secret");
+
+        // Provision a user with a password
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let (version, hash) = state
+            .password_manager
+            .hash(&mut rng, Zeroizing::new("hunter2".to_owned()))
+            .await
+            .unwrap();
+        repo.user_password()
+            .add(&mut rng, &state.clock, &user, version, hash, None)
+            .await
+            .unwrap();
+        let mxid = state.homeserver_connection.mxid(&user.username);
+        state
+            .homeserver_connection
+            .provision_user(&ProvisionRequest::new(mxid, &user.sub))
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let scope = "openid urn:matrix:org.matrix.msc2967.client:api:* urn:matrix:org.matrix.msc2967.client:device:AABBCCDDEE";
+        let password_request = |password: &str| {
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "password",
+                "client_id": client_id,
+                "client_secret": client_secret,
+                "username": "alice",
+                "password": password,
+                "scope": scope,
+            }))
+        };
+
+        // The client is not in the allow-list yet, so the policy should deny it
+        let response = state.request(password_request("hunter2")).await;
+        response.assert_status(StatusCode::FORBIDDEN);
+
+        // Now, if we add the client to the allow-list in the policy, it should work
         let state = {
             let mut state = state;
             state.policy_factory = crate::test_utils::policy_factory(
                 "example.com",
                 serde_json::json!({
-                    "admin_clients": [client_id]
+                    "direct_login_clients": [client_id]
                 }),
             )
             .await
@@ -1593,31 +3624,51 @@ mod tests {
             state
         };
 
+        // A wrong password should be rejected
+        let response = state.request(password_request("wrong")).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::InvalidGrant);
+
+        let response = state.request(password_request("hunter2")).await;
+        response.assert_status(StatusCode::OK);
+
+        let response: AccessTokenResponse = response.json();
+        assert!(response.refresh_token.is_some());
+        assert!(response.id_token.is_some());
+        assert_eq!(response.scope, Some(scope.parse().unwrap()));
+        assert!(state.is_access_token_valid(&response.access_token).await);
+
+        // Admin scopes are not available through this grant
         let request =
             Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
-                "grant_type": "client_credentials",
+                "grant_type": "password",
                 "client_id": client_id,
                 "client_secret": client_secret,
-                "scope": "urn:mas:admin"
+                "username": "alice",
+                "password": "hunter2",
+                "scope": "urn:mas:admin",
             }));
-
         let response = state.request(request).await;
-        response.assert_status(StatusCode::OK);
-
-        let response: AccessTokenResponse = response.json();
-        assert!(response.refresh_token.is_none());
-        assert!(response.expires_in.is_some());
-        assert_eq!(response.scope, Some("urn:mas:admin".parse().unwrap()));
+        response.assert_status(StatusCode::FORBIDDEN);
 
-        // Revoke the token
-        let request = Request::post(mas_router::OAuth2Revocation::PATH).form(serde_json::json!({
-            "token": response.access_token,
-            "client_id": client_id,
-            "client_secret": client_secret,
-        }));
+        // Turning the grant off through its feature flag rejects the requests
+        let mut repo = state.repository().await.unwrap();
+        repo.feature_flag_override()
+            .set(
+                &mut rng,
+                &state.clock,
+                FeatureFlag::PasswordGrant,
+                FeatureFlagRollout::DISABLED,
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
 
-        let response = state.request(request).await;
-        response.assert_status(StatusCode::OK);
+        let response = state.request(password_request("hunter2")).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::UnsupportedGrantType);
     }
 
     #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
@@ -1665,6 +3716,48 @@ mod tests {
         let ClientError { error, .. } = response.json();
         assert_eq!(error, ClientErrorCode::AuthorizationPending);
 
+        // Polling again right away should tell the client to slow down
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "urn:ietf:params:oauth:grant-type:device_code",
+                "device_code": device_grant.device_code,
+                "client_id": client_id,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::FORBIDDEN);
+
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::SlowDown);
+
+        // The interval was increased by 5 seconds, so waiting the original
+        // interval is not enough anymore
+        state.clock.advance(Duration::try_seconds(5).unwrap());
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "urn:ietf:params:oauth:grant-type:device_code",
+                "device_code": device_grant.device_code,
+                "client_id": client_id,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::FORBIDDEN);
+
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::SlowDown);
+
+        // Waiting for the new interval works
+        state.clock.advance(Duration::try_seconds(15).unwrap());
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "urn:ietf:params:oauth:grant-type:device_code",
+                "device_code": device_grant.device_code,
+                "client_id": client_id,
+            }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::FORBIDDEN);
+
+        let ClientError { error, .. } = response.json();
+        assert_eq!(error, ClientErrorCode::AuthorizationPending);
+
         // Let's provision a user and create a browser session for them. This part is
         // hard to test with just HTTP requests, so we'll use the repository
         // directly.
@@ -4,36 +4,49 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::collections::HashMap;
+
 use axum::{
     Json,
     extract::State,
     response::{IntoResponse, Response},
 };
-use hyper::StatusCode;
+use hyper::{HeaderMap, Method, StatusCode};
 use mas_axum_utils::{
     jwt::JwtResponse,
     record_error,
     user_authorization::{AuthorizationVerificationError, UserAuthorization},
 };
+use mas_data_model::SiteConfig;
 use mas_jose::{
     constraints::Constrainable,
     jwt::{JsonWebSignatureHeader, Jwt},
 };
 use mas_keystore::Keystore;
-use mas_router::UrlBuilder;
 use mas_storage::{BoxClock, BoxRepository, BoxRng, oauth2::OAuth2ClientRepository};
 use serde::Serialize;
 use serde_with::skip_serializing_none;
 use thiserror::Error;
 use ulid::Ulid;
 
-use crate::{BoundActivityTracker, impl_from_error_for_route};
+use crate::{
+    BoundActivityTracker, IssuerUrlBuilder, impl_from_error_for_route,
+    oauth2::{
+        dpop::{self, DPoPError},
+        pairwise::subject_for_client,
+        requested_claims, user_attribute_claims,
+    },
+};
 
 #[skip_serializing_none]
 #[derive(Serialize)]
 struct UserInfo {
     sub: String,
     username: String,
+
+    /// Claims from the public custom attributes of the user
+    #[serde(flatten)]
+    custom_claims: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Serialize)]
@@ -57,6 +70,12 @@ pub enum RouteError {
     #[error("session is not allowed to access the userinfo endpoint")]
     Unauthorized,
 
+    #[error("invalid DPoP proof")]
+    InvalidDPoPProof(#[source] DPoPError),
+
+    #[error("could not verify the DPoP proof")]
+    DPoPVerification(#[source] DPoPError),
+
     #[error("no suitable key found for signing")]
     InvalidSigningKey,
 
@@ -71,6 +90,16 @@ impl_from_error_for_route!(mas_storage::RepositoryError);
 impl_from_error_for_route!(mas_keystore::WrongAlgorithmError);
 impl_from_error_for_route!(mas_jose::jwt::JwtSignatureError);
 
+impl From<DPoPError> for RouteError {
+    fn from(err: DPoPError) -> Self {
+        if err.is_internal() {
+            Self::DPoPVerification(err)
+        } else {
+            Self::InvalidDPoPProof(err)
+        }
+    }
+}
+
 impl IntoResponse for RouteError {
     fn into_response(self) -> axum::response::Response {
         let sentry_event_id = record_error!(
@@ -79,17 +108,19 @@ impl IntoResponse for RouteError {
                 | Self::InvalidSigningKey
                 | Self::NoSuchClient(_)
                 | Self::NoSuchUser(_)
+                | Self::DPoPVerification(_)
         );
         let response = match self {
             Self::Internal(_)
             | Self::InvalidSigningKey
             | Self::NoSuchClient(_)
-            | Self::NoSuchUser(_) => {
+            | Self::NoSuchUser(_)
+            | Self::DPoPVerification(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
             }
-            Self::AuthorizationVerificationError(_) | Self::Unauthorized => {
-                StatusCode::UNAUTHORIZED.into_response()
-            }
+            Self::AuthorizationVerificationError(_)
+            | Self::Unauthorized
+            | Self::InvalidDPoPProof(_) => StatusCode::UNAUTHORIZED.into_response(),
         };
 
         (sentry_event_id, response).into_response()
@@ -100,13 +131,39 @@ impl IntoResponse for RouteError {
 pub async fn get(
     mut rng: BoxRng,
     clock: BoxClock,
-    State(url_builder): State<UrlBuilder>,
+    IssuerUrlBuilder(url_builder): IssuerUrlBuilder,
     activity_tracker: BoundActivityTracker,
     mut repo: BoxRepository,
     State(key_store): State<Keystore>,
+    State(site_config): State<SiteConfig>,
+    method: Method,
+    headers: HeaderMap,
     user_authorization: UserAuthorization,
 ) -> Result<Response, RouteError> {
-    let session = user_authorization.protected(&mut repo, &clock).await?;
+    // Tokens presented with the DPoP scheme come with a proof of possession
+    let dpop = match user_authorization.dpop_token() {
+        Some(token) => Some(
+            dpop::verify_token_proof(
+                &mut rng,
+                &clock,
+                &mut repo,
+                &headers,
+                &method,
+                &url_builder.oidc_userinfo_endpoint(),
+                token,
+            )
+            .await?,
+        ),
+        None => None,
+    };
+
+    let session = user_authorization
+        .protected(
+            &mut repo,
+            &clock,
+            dpop.as_ref().map(|dpop| dpop.jkt.as_str()),
+        )
+        .await?;
 
     // This endpoint requires the `openid` scope.
     if !session.scope.contains("openid") {
@@ -128,10 +185,15 @@ pub async fn get(
         .await?
         .ok_or(RouteError::NoSuchUser(user_id))?;
 
-    let user_info = UserInfo {
-        sub: user.sub.clone(),
-        username: user.username.clone(),
-    };
+    let mut custom_claims = user_attribute_claims(&mut repo, &site_config, &user).await?;
+    custom_claims.extend(
+        requested_claims(
+            &mut repo,
+            &user,
+            session.userinfo_claims.iter().map(String::as_str),
+        )
+        .await?,
+    );
 
     let client = repo
         .oauth2_client()
@@ -139,6 +201,12 @@ pub async fn get(
         .await?
         .ok_or(RouteError::NoSuchClient(session.client_id))?;
 
+    let user_info = UserInfo {
+        sub: subject_for_client(&mut rng, &clock, &mut repo, &client, &user).await?,
+        username: user.username.clone(),
+        custom_claims,
+    };
+
     repo.save().await?;
 
     if let Some(alg) = client.userinfo_signed_response_alg {
@@ -162,3 +230,143 @@ pub async fn get(
         Ok(Json(user_info).into_response())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use mas_data_model::AccessToken;
+    use mas_router::{OAuth2Introspection, OAuth2RegistrationEndpoint, OidcUserinfo, SimpleRoute};
+    use oauth2_types::{
+        registration::ClientRegistrationResponse,
+        requests::IntrospectionResponse,
+        scope::{OPENID, Scope},
+    };
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use crate::{
+        oauth2::{AccessTokenGenerator, generate_token_pair},
+        test_utils::{RequestBuilderExt, ResponseExt, TestState, setup},
+    };
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_userinfo_pairwise_subject(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        // A client which introspects the tokens, like a resource server would
+        let request = Request::post(OAuth2RegistrationEndpoint::PATH).json(json!({
+            "client_uri": "https://introspecting.com/",
+            "grant_types": [],
+            "token_endpoint_auth_method": "client_secret_basic",
+        }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let ClientRegistrationResponse {
+            client_id: introspecting_client_id,
+            client_secret: introspecting_client_secret,
+            ..
+        } = response.json();
+        let introspecting_client_secret = introspecting_client_secret.unwrap();
+
+        // A client using pairwise subject identifiers
+        let request = Request::post(OAuth2RegistrationEndpoint::PATH).json(json!({
+            "client_uri": "https://client.com/",
+            "redirect_uris": ["https://client.com/"],
+            "response_types": ["code"],
+            "grant_types": ["authorization_code"],
+            "token_endpoint_auth_method": "none",
+            "subject_type": "pairwise",
+        }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let ClientRegistrationResponse { client_id, .. } = response.json();
+
+        let mut repo = state.repository().await.unwrap();
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&client_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            client.pairwise_sector_identifier.as_deref(),
+            Some("client.com")
+        );
+
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut state.rng(), &state.clock, &user, None)
+            .await
+            .unwrap();
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(
+                &mut state.rng(),
+                &state.clock,
+                &client,
+                &browser_session,
+                Scope::from_iter([OPENID]),
+            )
+            .await
+            .unwrap();
+        let (AccessToken { access_token, .. }, _) = generate_token_pair(
+            &mut state.rng(),
+            &state.clock,
+            &mut repo,
+            &AccessTokenGenerator::new(&state.url_builder, &state.key_store),
+            &client,
+            &session,
+            Duration::microseconds(5 * 60 * 1000 * 1000),
+        )
+        .await
+        .unwrap();
+        repo.save().await.unwrap();
+
+        // The client sees the user with a pairwise subject identifier
+        let request = Request::get(OidcUserinfo::PATH)
+            .bearer(&access_token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let userinfo: serde_json::Value = response.json();
+        let sub = userinfo["sub"].as_str().unwrap().to_owned();
+        assert_ne!(sub, user.sub);
+
+        // It is stable across requests
+        let request = Request::get(OidcUserinfo::PATH)
+            .bearer(&access_token)
+            .empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let userinfo: serde_json::Value = response.json();
+        assert_eq!(userinfo["sub"], sub);
+
+        // And maps back to the user
+        let mut repo = state.repository().await.unwrap();
+        let user_id = repo
+            .oauth2_pairwise_subject()
+            .find_user_id("client.com", &sub)
+            .await
+            .unwrap();
+        assert_eq!(user_id, Some(user.id));
+        repo.cancel().await.unwrap();
+
+        // Introspection still identifies the actual user
+        let request = Request::post(OAuth2Introspection::PATH)
+            .basic_auth(&introspecting_client_id, &introspecting_client_secret)
+            .form(json!({ "token": access_token }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let response: IntrospectionResponse = response.json();
+        assert!(response.active);
+        assert_eq!(response.sub, Some(user.sub));
+        assert_eq!(response.username, Some("alice".to_owned()));
+    }
+}
@@ -11,10 +11,11 @@ use axum::{
 };
 use axum_extra::typed_header::TypedHeader;
 use headers::ContentType;
-use mas_router::UrlBuilder;
 use oauth2_types::webfinger::WebFingerResponse;
 use serde::Deserialize;
 
+use crate::IssuerUrlBuilder;
+
 #[derive(Deserialize)]
 pub(crate) struct Params {
     resource: String,
@@ -31,7 +32,7 @@ fn jrd() -> mime::Mime {
 #[tracing::instrument(name = "handlers.oauth2.webfinger.get", skip_all)]
 pub(crate) async fn get(
     Query(params): Query<Params>,
-    State(url_builder): State<UrlBuilder>,
+    IssuerUrlBuilder(url_builder): IssuerUrlBuilder,
 ) -> impl IntoResponse {
     // TODO: should we validate the subject?
     let subject = params.resource;
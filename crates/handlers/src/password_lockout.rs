@@ -0,0 +1,207 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Temporary lockout of accounts after repeated failed password attempts
+//!
+//! Unlike the in-memory rate limiter, failed attempts are recorded in the
+//! database, so that they are shared between instances and can be cleared by
+//! administrators. They are cleared when the user successfully logs in with
+//! their password, so only consecutive failures count towards the lockout.
+
+use std::{net::IpAddr, sync::LazyLock};
+
+use mas_data_model::{CaptchaConfig, PasswordLockoutConfig, SiteConfig, User};
+use mas_storage::{BoxRepository, Clock, RepositoryError, user::UserPasswordRepository};
+use opentelemetry::{Key, KeyValue, metrics::Counter};
+use rand::RngCore;
+
+use crate::{METER, RequesterFingerprint};
+
+static LOCKOUT_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("mas.user.password_lockout")
+        .with_description("Number of lockouts after too many failed password attempts")
+        .with_unit("{lockout}")
+        .build()
+});
+const KIND: Key = Key::from_static_str("kind");
+
+/// Whether a user can try to log in with their password
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Lockout {
+    /// The user can try to log in
+    None,
+
+    /// Too many attempts failed from the IP address of the requester, they
+    /// have to solve a CAPTCHA first
+    CaptchaRequired,
+
+    /// Too many attempts failed on this account, or from the IP address of
+    /// the requester while no CAPTCHA service is configured
+    Locked,
+}
+
+/// Check if a count of failed attempts reached a threshold
+fn reached(failures: usize, threshold: u32) -> bool {
+    u32::try_from(failures).unwrap_or(u32::MAX) >= threshold
+}
+
+async fn ip_failures(
+    repo: &mut BoxRepository,
+    clock: &dyn Clock,
+    config: &PasswordLockoutConfig,
+    ip_address: IpAddr,
+) -> Result<usize, RepositoryError> {
+    let since = clock.now() - config.duration;
+    repo.user_password()
+        .count_failures_from_ip(ip_address, since)
+        .await
+}
+
+/// Get the CAPTCHA the requester has to solve before trying to log in with a
+/// password, if too many attempts failed from their IP address
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn captcha_for(
+    repo: &mut BoxRepository,
+    clock: &dyn Clock,
+    site_config: &SiteConfig,
+    requester: RequesterFingerprint,
+) -> Result<Option<CaptchaConfig>, RepositoryError> {
+    let (Some(config), Some(captcha), Some(ip_address)) = (
+        &site_config.password_lockout,
+        &site_config.captcha,
+        requester.ip(),
+    ) else {
+        return Ok(None);
+    };
+
+    let failures = ip_failures(repo, clock, config, ip_address).await?;
+    Ok(reached(failures, config.max_failures_per_ip).then(|| captcha.clone()))
+}
+
+/// Check whether a user can try to log in with their password
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn check(
+    repo: &mut BoxRepository,
+    clock: &dyn Clock,
+    site_config: &SiteConfig,
+    user: &User,
+    requester: RequesterFingerprint,
+) -> Result<Lockout, RepositoryError> {
+    let Some(config) = &site_config.password_lockout else {
+        return Ok(Lockout::None);
+    };
+
+    let since = clock.now() - config.duration;
+    let failures = repo.user_password().count_failures(user, since).await?;
+    if reached(failures, config.max_failures) {
+        return Ok(Lockout::Locked);
+    }
+
+    if let Some(ip_address) = requester.ip() {
+        let failures = ip_failures(repo, clock, config, ip_address).await?;
+        if reached(failures, config.max_failures_per_ip) {
+            return Ok(if site_config.captcha.is_some() {
+                Lockout::CaptchaRequired
+            } else {
+                Lockout::Locked
+            });
+        }
+    }
+
+    Ok(Lockout::None)
+}
+
+/// Record a failed password attempt on a user
+///
+/// When the attempt locks the account or the IP address of the requester, the
+/// lockout is recorded in the repository and logged.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn record_failure(
+    repo: &mut BoxRepository,
+    rng: &mut (dyn RngCore + Send),
+    clock: &dyn Clock,
+    site_config: &SiteConfig,
+    user: &User,
+    requester: RequesterFingerprint,
+) -> Result<(), RepositoryError> {
+    let Some(config) = &site_config.password_lockout else {
+        return Ok(());
+    };
+
+    let ip_address = requester.ip();
+    repo.user_password()
+        .record_failure(rng, clock, user, ip_address)
+        .await?;
+
+    let since = clock.now() - config.duration;
+    let failures = repo.user_password().count_failures(user, since).await?;
+    if u32::try_from(failures) == Ok(config.max_failures) {
+        repo.user_password()
+            .record_lockout(rng, clock, Some(user), ip_address)
+            .await?;
+        LOCKOUT_COUNTER.add(1, &[KeyValue::new(KIND, "user")]);
+        tracing::warn!(
+            user.id = %user.id,
+            user.username = %user.username,
+            ?ip_address,
+            failures,
+            "Account temporarily locked after too many failed password attempts"
+        );
+    }
+
+    if let Some(ip_address) = ip_address {
+        let failures = ip_failures(repo, clock, config, ip_address).await?;
+        if u32::try_from(failures) == Ok(config.max_failures_per_ip) {
+            repo.user_password()
+                .record_lockout(rng, clock, None, Some(ip_address))
+                .await?;
+            LOCKOUT_COUNTER.add(1, &[KeyValue::new(KIND, "ip")]);
+            tracing::warn!(
+                %ip_address,
+                failures,
+                captcha = site_config.captcha.is_some(),
+                "Password logins from IP address restricted after too many failed attempts"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Clear the failed password attempts of a user after a successful one
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn record_success(
+    repo: &mut BoxRepository,
+    site_config: &SiteConfig,
+    user: &User,
+) -> Result<(), RepositoryError> {
+    if site_config.password_lockout.is_none() {
+        return Ok(());
+    }
+
+    let cleared = repo.user_password().clear_failures(user).await?;
+    if cleared > 0 {
+        tracing::info!(
+            user.id = %user.id,
+            cleared,
+            "Cleared failed password attempts after a successful login"
+        );
+    }
+
+    Ok(())
+}
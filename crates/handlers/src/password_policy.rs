@@ -0,0 +1,125 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Password expiry and history policies
+//!
+//! Both policies are configured globally, and administrators can override them
+//! for individual users. The history is made of the previous password hashes
+//! of the user, so only passwords set while the history was being kept count.
+
+use chrono::Duration;
+use mas_data_model::{Password, SiteConfig, User};
+use mas_storage::{BoxRepository, Clock, RepositoryError, user::UserPasswordRepository};
+use zeroize::Zeroizing;
+
+use crate::passwords::PasswordManager;
+
+/// The password policy which applies to a user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct EffectivePolicy {
+    /// Maximum age of the password, if it expires
+    pub max_age: Option<Duration>,
+
+    /// Number of previous passwords the user can't reuse
+    pub history_size: u32,
+}
+
+/// Get the password policy which applies to a user, taking the overrides set
+/// by administrators into account
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn effective(
+    repo: &mut BoxRepository,
+    site_config: &SiteConfig,
+    user: &User,
+) -> Result<EffectivePolicy, RepositoryError> {
+    let overrides = repo
+        .user_password()
+        .get_policy(user)
+        .await?
+        .unwrap_or_default();
+
+    let max_age = match overrides.max_age {
+        // A zero maximum age means that the password of the user never expires
+        Some(max_age) if max_age.is_zero() => None,
+        Some(max_age) => Some(max_age),
+        None => site_config.password_max_age,
+    };
+
+    let history_size = overrides
+        .history_size
+        .unwrap_or(site_config.password_history_size);
+
+    Ok(EffectivePolicy {
+        max_age,
+        history_size,
+    })
+}
+
+/// Check whether a user has to change their current password before logging
+/// in, either because it expired or because an administrator required it
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn must_change(
+    repo: &mut BoxRepository,
+    clock: &dyn Clock,
+    site_config: &SiteConfig,
+    user: &User,
+    password: &Password,
+) -> Result<bool, RepositoryError> {
+    if password.change_required() {
+        return Ok(true);
+    }
+
+    let Some(max_age) = effective(repo, site_config, user).await?.max_age else {
+        return Ok(false);
+    };
+
+    let changed_at = repo.user_password().changed_at(password).await?;
+    Ok(clock.now() - changed_at >= max_age)
+}
+
+/// Check whether a new password was one of the previous passwords of a user
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn is_reused(
+    repo: &mut BoxRepository,
+    password_manager: &PasswordManager,
+    site_config: &SiteConfig,
+    user: &User,
+    new_password: &Zeroizing<String>,
+) -> Result<bool, RepositoryError> {
+    let history_size = effective(repo, site_config, user).await?.history_size;
+    if history_size == 0 {
+        return Ok(false);
+    }
+
+    let limit = usize::try_from(history_size).unwrap_or(usize::MAX);
+    let history = repo.user_password().history(user, limit).await?;
+
+    for password in history {
+        // Errors, e.g. if the hashing scheme of an old password isn't
+        // configured anymore, count as the password not matching
+        let res = password_manager
+            .verify(
+                password.version,
+                new_password.clone(),
+                password.hashed_password,
+            )
+            .await;
+
+        if res.is_ok() {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
@@ -8,9 +8,12 @@ use std::{collections::HashMap, sync::Arc};
 
 use anyhow::Context;
 use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::SaltString};
+use base64ct::{Base64, Encoding};
 use futures_util::future::OptionFuture;
 use pbkdf2::Pbkdf2;
 use rand::{CryptoRng, RngCore, SeedableRng, distributions::Standard, prelude::Distribution};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
 use thiserror::Error;
 use zeroize::Zeroizing;
 use zxcvbn::zxcvbn;
@@ -44,7 +47,8 @@ impl PasswordManager {
     ///
     /// # Errors
     ///
-    /// Returns an error if the iterator was empty
+    /// Returns an error if the iterator was empty, or if the first hashing
+    /// scheme can only verify passwords
     pub fn new<I: IntoIterator<Item = (SchemeVersion, Hasher)>>(
         minimum_complexity: u8,
         iter: I,
@@ -56,6 +60,11 @@ impl PasswordManager {
             .next()
             .context("Iterator must have at least one item")?;
 
+        anyhow::ensure!(
+            current_hasher.algorithm.can_hash(),
+            "The default hashing scheme must be able to hash new passwords"
+        );
+
         // Collect the other hashers in a map used only in verification
         let other_hashers = iter.collect();
 
@@ -251,6 +260,34 @@ impl Hasher {
         }
     }
 
+    /// Creates a new hashing scheme verifying PBKDF2 hashes in the format
+    /// used by Django
+    ///
+    /// This scheme can only verify passwords, not hash new ones.
+    #[must_use]
+    pub const fn django_pbkdf2(unicode_normalization: bool) -> Self {
+        let algorithm = Algorithm::DjangoPbkdf2;
+        Self {
+            algorithm,
+            unicode_normalization,
+            pepper: None,
+        }
+    }
+
+    /// Creates a new hashing scheme verifying salted SHA hashes in the format
+    /// used by LDAP directories
+    ///
+    /// This scheme can only verify passwords, not hash new ones.
+    #[must_use]
+    pub const fn ldap(unicode_normalization: bool) -> Self {
+        let algorithm = Algorithm::Ldap;
+        Self {
+            algorithm,
+            unicode_normalization,
+            pepper: None,
+        }
+    }
+
     fn normalize_password(&self, password: Zeroizing<String>) -> Zeroizing<String> {
         if self.unicode_normalization {
             // This is the normalization method used by Synapse
@@ -286,12 +323,104 @@ impl Hasher {
 
 #[derive(Debug, Clone, Copy)]
 enum Algorithm {
-    Bcrypt { cost: Option<u32> },
+    Bcrypt {
+        cost: Option<u32>,
+    },
     Argon2id,
     Pbkdf2,
+
+    /// Only verifies hashes imported from Django, like
+    /// `pbkdf2_sha256$<iterations>$<salt>$<hash>`
+    DjangoPbkdf2,
+
+    /// Only verifies hashes imported from an LDAP directory, like
+    /// `{SSHA}<base64>`
+    Ldap,
+}
+
+/// Compare two hashes in constant time
+fn hashes_match(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Hash a password with an optional salt appended to it, as done by LDAP
+/// directories
+fn salted_digest<D: Digest>(password: &[u8], salt: &[u8]) -> Vec<u8> {
+    D::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .finalize()
+        .to_vec()
+}
+
+fn verify_django_pbkdf2(hashed_password: &str, password: &[u8]) -> Result<(), anyhow::Error> {
+    let mut parts = hashed_password.split('$');
+    let (Some(algorithm), Some(iterations), Some(salt), Some(hash), None) = (
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+    ) else {
+        anyhow::bail!("invalid Django password hash");
+    };
+
+    let iterations: u32 = iterations.parse().context("invalid Django password hash")?;
+    let expected = Base64::decode_vec(hash).context("invalid Django password hash")?;
+    anyhow::ensure!(!expected.is_empty(), "invalid Django password hash");
+
+    let mut derived = Zeroizing::new(vec![0; expected.len()]);
+    match algorithm {
+        "pbkdf2_sha256" => {
+            pbkdf2::pbkdf2_hmac::<Sha256>(password, salt.as_bytes(), iterations, &mut derived);
+        }
+        "pbkdf2_sha1" => {
+            pbkdf2::pbkdf2_hmac::<Sha1>(password, salt.as_bytes(), iterations, &mut derived);
+        }
+        _ => anyhow::bail!("unsupported Django password hasher {algorithm:?}"),
+    }
+
+    anyhow::ensure!(hashes_match(&derived, &expected), "wrong password");
+    Ok(())
+}
+
+fn verify_ldap(hashed_password: &str, password: &[u8]) -> Result<(), anyhow::Error> {
+    let (scheme, encoded) = hashed_password
+        .strip_prefix('{')
+        .and_then(|rest| rest.split_once('}'))
+        .context("invalid LDAP password hash")?;
+    let decoded = Base64::decode_vec(encoded).context("invalid LDAP password hash")?;
+
+    let scheme = scheme.to_ascii_uppercase();
+    let (digest_len, digest): (usize, fn(&[u8], &[u8]) -> Vec<u8>) = match scheme.as_str() {
+        "SHA" | "SSHA" => (20, salted_digest::<Sha1>),
+        "SHA256" | "SSHA256" => (32, salted_digest::<Sha256>),
+        "SHA512" | "SSHA512" => (64, salted_digest::<Sha512>),
+        _ => anyhow::bail!("unsupported LDAP password scheme {scheme:?}"),
+    };
+
+    // Salted hashes have the salt appended to the digest
+    let salted = scheme.starts_with("SS");
+    let valid_len = if salted {
+        decoded.len() > digest_len
+    } else {
+        decoded.len() == digest_len
+    };
+    anyhow::ensure!(valid_len, "invalid LDAP password hash");
+
+    let (expected, salt) = decoded.split_at(digest_len);
+    let derived = Zeroizing::new(digest(password, salt));
+    anyhow::ensure!(hashes_match(&derived, expected), "wrong password");
+    Ok(())
 }
 
 impl Algorithm {
+    /// Whether this algorithm can hash new passwords, or only verify imported
+    /// ones
+    const fn can_hash(self) -> bool {
+        matches!(self, Self::Bcrypt { .. } | Self::Argon2id | Self::Pbkdf2)
+    }
+
     fn hash_blocking<R: CryptoRng + RngCore>(
         self,
         mut rng: R,
@@ -337,6 +466,10 @@ impl Algorithm {
                 let hashed = Pbkdf2.hash_password(password.as_ref(), &salt)?;
                 Ok(hashed.to_string())
             }
+
+            Self::DjangoPbkdf2 | Self::Ldap => {
+                anyhow::bail!("This hashing scheme can only verify imported passwords")
+            }
         }
     }
 
@@ -383,6 +516,10 @@ impl Algorithm {
 
                 Pbkdf2.verify_password(password.as_ref(), &hashed_password)?;
             }
+
+            Algorithm::DjangoPbkdf2 => verify_django_pbkdf2(hashed_password, password)?,
+
+            Algorithm::Ldap => verify_ldap(hashed_password, password)?,
         }
 
         Ok(())
@@ -489,6 +626,113 @@ mod tests {
         assert!(alg.verify_blocking(&hash, password, Some(pepper)).is_err());
     }
 
+    #[test]
+    fn verifying_django_pbkdf2() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(42);
+        let password = b"hunter2";
+        let password2 = b"wrong-password";
+
+        let alg = Algorithm::DjangoPbkdf2;
+        let sha256 = "pbkdf2_sha256$1000$Kq6ZmYbQ9kP2$p9OHR2OeuazPI07JSqb7jkcVlHebRhUr00sTmhRkV7o=";
+        let sha1 = "pbkdf2_sha1$1000$Kq6ZmYbQ9kP2$odtRR1g4CSjSHfoeAptOl9SC50s=";
+
+        assert!(alg.verify_blocking(sha256, password, None).is_ok());
+        assert!(alg.verify_blocking(sha256, password2, None).is_err());
+        assert!(alg.verify_blocking(sha1, password, None).is_ok());
+        assert!(alg.verify_blocking(sha1, password2, None).is_err());
+
+        // Malformed or unsupported hashes are rejected
+        assert!(
+            alg.verify_blocking("pbkdf2_sha256$1000$salt", password, None)
+                .is_err()
+        );
+        assert!(
+            alg.verify_blocking(
+                "argon2$argon2id$v=19$m=512,t=2,p=2$c2FsdA$aGFzaA",
+                password,
+                None
+            )
+            .is_err()
+        );
+
+        // It can't hash new passwords
+        assert!(alg.hash_blocking(&mut rng, password, None).is_err());
+    }
+
+    #[test]
+    fn verifying_ldap() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(42);
+        let password = b"hunter2";
+        let password2 = b"wrong-password";
+
+        let alg = Algorithm::Ldap;
+        let hashes = [
+            "{SHA}87u9ZqY9S/F0eUBXjsPQEDUw4h0=",
+            "{SSHA}4tU6s0z9B2Ig5TAwuRzFJc4HtfSKHwN3",
+            "{ssha}4tU6s0z9B2Ig5TAwuRzFJc4HtfSKHwN3",
+            "{SSHA256}t15G9pX3h+duYY1pG1HAV0nM7LrVyP9yFBQhHp7jnk6KHwN3",
+            "{SSHA512}iXYxp93rn0aizfOcdtYOPbeyDVBEvR/rFosNAVe/sHc6CD6lh2B38PO81GLmvUuiXy0hjND4rvxoG2hqOPClzoofA3c=",
+        ];
+
+        for hash in hashes {
+            assert!(alg.verify_blocking(hash, password, None).is_ok(), "{hash}");
+            assert!(
+                alg.verify_blocking(hash, password2, None).is_err(),
+                "{hash}"
+            );
+        }
+
+        // Malformed or unsupported hashes are rejected
+        assert!(alg.verify_blocking("{SSHA}", password, None).is_err());
+        assert!(
+            alg.verify_blocking("{MD5}X03MO1qnZdYdgyfeuILPmQ==", password, None)
+                .is_err()
+        );
+        assert!(
+            alg.verify_blocking("87u9ZqY9S/F0eUBXjsPQEDUw4h0=", password, None)
+                .is_err()
+        );
+
+        // It can't hash new passwords
+        assert!(alg.hash_blocking(&mut rng, password, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn verify_and_upgrade_imported() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(42);
+        let password = Zeroizing::new("hunter2".to_owned());
+        let wrong_password = Zeroizing::new("wrong-password".to_owned());
+        let hash = "{SSHA}4tU6s0z9B2Ig5TAwuRzFJc4HtfSKHwN3".to_owned();
+
+        // Schemes which can only verify passwords can't be the default one
+        assert!(PasswordManager::new(0, [(1, Hasher::ldap(false))]).is_err());
+
+        let manager = PasswordManager::new(
+            0,
+            [(2, Hasher::argon2id(None, false)), (1, Hasher::ldap(false))],
+        )
+        .unwrap();
+
+        manager
+            .verify_and_upgrade(&mut rng, 1, wrong_password, hash.clone())
+            .await
+            .expect_err("Verification should have failed");
+
+        // Imported passwords get upgraded to the default scheme
+        let (version, new_hash) = manager
+            .verify_and_upgrade(&mut rng, 1, password.clone(), hash)
+            .await
+            .expect("Failed to verify")
+            .expect("The password should have been upgraded");
+        assert_eq!(version, 2);
+        assert!(new_hash.starts_with("$argon2id$"));
+
+        manager
+            .verify(version, password, new_hash)
+            .await
+            .expect("Failed to verify");
+    }
+
     #[allow(clippy::too_many_lines)]
     #[tokio::test]
     async fn hash_verify_and_upgrade() {
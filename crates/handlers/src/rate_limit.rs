@@ -47,6 +47,12 @@ pub enum EmailAuthenticationLimitedError {
     Email(String),
 }
 
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+pub enum UpstreamOAuth2RequestLimitedError {
+    #[error("Too many requests to upstream OAuth 2.0 provider {0}")]
+    Provider(Ulid),
+}
+
 /// Key used to rate limit requests per requester
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct RequesterFingerprint {
@@ -73,6 +79,12 @@ impl RequesterFingerprint {
     pub const fn new(ip: IpAddr) -> Self {
         Self { ip: Some(ip) }
     }
+
+    /// The IP address of the requester, if known
+    #[must_use]
+    pub const fn ip(&self) -> Option<IpAddr> {
+        self.ip
+    }
 }
 
 /// Rate limiters for the different operations
@@ -94,6 +106,7 @@ struct LimiterInner {
     email_authentication_per_email: KeyedRateLimiter<String>,
     email_authentication_emails_per_session: KeyedRateLimiter<Ulid>,
     email_authentication_attempt_per_session: KeyedRateLimiter<Ulid>,
+    upstream_oauth2_per_provider: KeyedRateLimiter<Ulid>,
 }
 
 impl LimiterInner {
@@ -120,6 +133,9 @@ impl LimiterInner {
             email_authentication_attempt_per_session: RateLimiter::keyed(
                 config.email_authentication.attempt_per_session.to_quota()?,
             ),
+            upstream_oauth2_per_provider: RateLimiter::keyed(
+                config.upstream_oauth2_per_provider.to_quota()?,
+            ),
         })
     }
 }
@@ -165,6 +181,7 @@ impl Limiter {
                 this.inner
                     .email_authentication_attempt_per_session
                     .retain_recent();
+                this.inner.upstream_oauth2_per_provider.retain_recent();
 
                 interval.tick().await;
             }
@@ -297,6 +314,22 @@ impl Limiter {
             .check_key(&authentication.id)
             .map_err(|_| EmailAuthenticationLimitedError::Authentication(authentication.id))
     }
+
+    /// Check if a request can be made to the token or userinfo endpoint of an
+    /// upstream OAuth 2.0 provider
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the operation is rate limited.
+    pub fn check_upstream_oauth2_request(
+        &self,
+        provider_id: Ulid,
+    ) -> Result<(), UpstreamOAuth2RequestLimitedError> {
+        self.inner
+            .upstream_oauth2_per_provider
+            .check_key(&provider_id)
+            .map_err(|_| UpstreamOAuth2RequestLimitedError::Provider(provider_id))
+    }
 }
 
 #[cfg(test)]
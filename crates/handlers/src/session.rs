@@ -8,7 +8,7 @@
 
 use axum::response::{Html, IntoResponse as _, Response};
 use mas_axum_utils::{SessionInfoExt, cookies::CookieJar, csrf::CsrfExt};
-use mas_data_model::BrowserSession;
+use mas_data_model::{BrowserSession, SiteConfig};
 use mas_i18n::DataLocale;
 use mas_storage::{BoxRepository, Clock, RepositoryError};
 use mas_templates::{AccountInactiveContext, TemplateContext, Templates};
@@ -42,6 +42,7 @@ pub async fn load_session_or_fallback(
     templates: &Templates,
     locale: &DataLocale,
     repo: &mut BoxRepository,
+    site_config: &SiteConfig,
 ) -> Result<SessionOrFallback, SessionLoadError> {
     let (session_info, cookie_jar) = cookie_jar.session_info();
     let Some(session_id) = session_info.current_session_id() else {
@@ -83,11 +84,16 @@ pub async fn load_session_or_fallback(
         return Ok(SessionOrFallback::Fallback { response });
     }
 
-    if session.finished_at.is_some() {
+    let expired = site_config
+        .session_expiration
+        .as_ref()
+        .is_some_and(|config| session.expired(clock.now(), config));
+
+    if session.finished_at.is_some() || expired {
         // The session has finished, but the browser still has the cookie. This is
         // likely a 'remote' logout, triggered either by an admin or from the
-        // user-management UI. In this case, we show the 'account logged out'
-        // fallback.
+        // user-management UI, or the session expired. In this case, we show the
+        // 'account logged out' fallback.
         let (csrf_token, cookie_jar) = cookie_jar.csrf_token(clock, rng);
         let ctx = AccountInactiveContext::new(session.user)
             .with_csrf(csrf_token.form_value())
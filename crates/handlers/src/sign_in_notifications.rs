@@ -0,0 +1,49 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Emails telling users their account was used to log in from a new IP
+//! address or device
+
+use chrono::Duration;
+use mas_data_model::{BrowserSession, SiteConfig};
+use mas_i18n::DataLocale;
+use mas_storage::{
+    BoxRepository, Clock, RepositoryError,
+    queue::{QueueJobRepositoryExt as _, SendSignInNotificationJob},
+};
+use rand::RngCore;
+
+/// How long to wait before sending the email. This gives the activity tracker
+/// time to record where the session is used from.
+const DELAY: Duration = Duration::minutes(5);
+
+/// Schedule the email about a session which was just logged in, if they are
+/// enabled
+///
+/// Whether the IP address and device were seen before, and whether the user
+/// opted out, is only checked when the email is about to be sent.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn schedule(
+    repo: &mut BoxRepository,
+    rng: &mut (dyn RngCore + Send),
+    clock: &dyn Clock,
+    site_config: &SiteConfig,
+    user_session: &BrowserSession,
+    locale: &DataLocale,
+) -> Result<(), RepositoryError> {
+    if !site_config.sign_in_notifications_enabled {
+        return Ok(());
+    }
+
+    let job = SendSignInNotificationJob::new(user_session, locale.to_string());
+    repo.queue_job()
+        .schedule_job_later(rng, clock, job, clock.now() + DELAY)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Codes sent by SMS, to confirm the phone number of users and as a second
+//! factor when logging in with a password
+//!
+//! The codes themselves are generated and sent by the `SendPhoneCodeJob` of
+//! the task worker.
+
+use chrono::Duration;
+use mas_data_model::{UserPhone, UserPhoneCode};
+use mas_storage::{
+    BoxRepository, Clock, RepositoryAccess, RepositoryError,
+    queue::{QueueJobRepositoryExt as _, SendPhoneCodeJob},
+    user::UserPhoneRepository,
+};
+use rand::RngCore;
+
+/// How long users have to wait before getting a new code sent to the same
+/// phone number
+const RESEND_DELAY: Duration = Duration::microseconds(60 * 1000 * 1000);
+
+/// The minimum and maximum number of digits of a phone number in the E.164
+/// format, without the leading `+`
+const MIN_DIGITS: usize = 8;
+const MAX_DIGITS: usize = 15;
+
+/// Normalize a phone number entered by a user to the E.164 format
+///
+/// The number must be in the international format, starting with a `+`.
+/// Spaces, dots, dashes and parentheses are ignored.
+///
+/// Returns `None` if the phone number is invalid.
+pub(crate) fn normalize_phone_number(input: &str) -> Option<String> {
+    let digits = input.trim().strip_prefix('+')?;
+    let digits: String = digits
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '.' | '-' | '(' | ')'))
+        .collect();
+
+    let valid = (MIN_DIGITS..=MAX_DIGITS).contains(&digits.len())
+        && digits.bytes().all(|b| b.is_ascii_digit())
+        // Country codes never start with a zero
+        && !digits.starts_with('0');
+
+    valid.then(|| format!("+{digits}"))
+}
+
+/// Check whether a new code can be sent to a phone number, or if one was sent
+/// too recently
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn can_send_code(
+    repo: &mut BoxRepository,
+    clock: &dyn Clock,
+    phone: &UserPhone,
+) -> Result<bool, RepositoryError> {
+    let latest = repo.user_phone().find_latest_code(phone).await?;
+    Ok(!latest.is_some_and(|code| code.created_at + RESEND_DELAY > clock.now()))
+}
+
+/// Schedule a job to send a new code to a phone number, unless one was sent
+/// too recently
+///
+/// Returns `false` if no code was sent because of the rate limit.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn send_code(
+    repo: &mut BoxRepository,
+    rng: &mut (dyn RngCore + Send),
+    clock: &dyn Clock,
+    phone: &UserPhone,
+    language: String,
+    login: bool,
+) -> Result<bool, RepositoryError> {
+    if !can_send_code(repo, clock, phone).await? {
+        return Ok(false);
+    }
+
+    let mut job = SendPhoneCodeJob::new(phone, language);
+    if login {
+        job = job.for_login();
+    }
+    repo.queue_job().schedule_job(rng, clock, job).await?;
+
+    Ok(true)
+}
+
+/// Check a code the user received on their phone, and consume it if it is
+/// valid
+///
+/// Returns `None` if the code is invalid, expired or was already used.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn check_code(
+    repo: &mut BoxRepository,
+    clock: &dyn Clock,
+    phone: &UserPhone,
+    code: &str,
+) -> Result<Option<UserPhoneCode>, RepositoryError> {
+    let Some(code) = repo.user_phone().find_code(phone, code.trim()).await? else {
+        return Ok(None);
+    };
+
+    if !code.is_valid(clock.now()) {
+        return Ok(None);
+    }
+
+    repo.user_phone().consume_code(clock, code).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_phone_number() {
+        assert_eq!(
+            normalize_phone_number("+33612345678").as_deref(),
+            Some("+33612345678")
+        );
+        assert_eq!(
+            normalize_phone_number(" +33 6 12 34 56 78 ").as_deref(),
+            Some("+33612345678")
+        );
+        assert_eq!(
+            normalize_phone_number("+1 (555) 123-4567").as_deref(),
+            Some("+15551234567")
+        );
+
+        // Local numbers are not accepted
+        assert_eq!(normalize_phone_number("0612345678"), None);
+        // Neither are country codes starting with a zero
+        assert_eq!(normalize_phone_number("+0612345678"), None);
+        // Too short or too long
+        assert_eq!(normalize_phone_number("+1234567"), None);
+        assert_eq!(normalize_phone_number("+1234567890123456"), None);
+        // Letters are not digits
+        assert_eq!(normalize_phone_number("+33612345abc"), None);
+    }
+}
@@ -0,0 +1,119 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Versioned terms of service and privacy policy
+//!
+//! The current version of each document is configured. Users who haven't
+//! accepted it yet are asked to, once logged in, before going further.
+
+use axum::response::Redirect;
+use mas_data_model::{SiteConfig, TermsDocument, TermsDocumentKind, User};
+use mas_router::UrlBuilder;
+use mas_storage::{BoxRepository, Clock, RepositoryError, user::UserTermsAcceptanceRepository};
+use rand::RngCore;
+
+use crate::views::shared::OptionalPostAuthAction;
+
+/// Get the current documents the user hasn't accepted yet
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn pending(
+    repo: &mut BoxRepository,
+    site_config: &SiteConfig,
+    user: &User,
+) -> Result<Vec<TermsDocument>, RepositoryError> {
+    if site_config.terms_documents.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let acceptances = repo.user_terms_acceptance().all_for_user(user).await?;
+
+    let pending = site_config
+        .terms_documents
+        .iter()
+        .filter(|document| {
+            !acceptances.iter().any(|acceptance| {
+                acceptance.kind == document.kind && acceptance.version == document.version
+            })
+        })
+        .cloned()
+        .collect();
+
+    Ok(pending)
+}
+
+/// Get where to send the user once logged in: the page asking them to accept
+/// the current documents if they have to, or the next step otherwise
+///
+/// This has to be called before the repository is saved, the redirection can
+/// be returned after.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn go_next(
+    repo: &mut BoxRepository,
+    site_config: &SiteConfig,
+    user: &User,
+    action: &OptionalPostAuthAction,
+    url_builder: &UrlBuilder,
+) -> Result<Redirect, RepositoryError> {
+    let pending = pending(repo, site_config, user).await?;
+    if pending.is_empty() {
+        return Ok(action.go_next(url_builder));
+    }
+
+    let destination = mas_router::AcceptTerms::from(action.post_auth_action.clone());
+    Ok(url_builder.redirect(&destination))
+}
+
+/// Record that the user accepted the given documents
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn accept(
+    repo: &mut BoxRepository,
+    rng: &mut (dyn RngCore + Send),
+    clock: &dyn Clock,
+    user: &User,
+    documents: &[TermsDocument],
+) -> Result<(), RepositoryError> {
+    for document in documents {
+        repo.user_terms_acceptance()
+            .accept(&mut *rng, clock, user, document)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Record that a user who agreed to the terms when registering accepted the
+/// current version of the terms of service
+///
+/// The privacy policy isn't part of the registration form, so users are still
+/// asked to accept it afterwards.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn accept_on_registration(
+    repo: &mut BoxRepository,
+    rng: &mut (dyn RngCore + Send),
+    clock: &dyn Clock,
+    site_config: &SiteConfig,
+    user: &User,
+) -> Result<(), RepositoryError> {
+    let documents: Vec<TermsDocument> = site_config
+        .terms_documents
+        .iter()
+        .filter(|document| document.kind == TermsDocumentKind::TermsOfService)
+        .cloned()
+        .collect();
+
+    accept(repo, rng, clock, user, &documents).await
+}
@@ -24,11 +24,11 @@ use hyper::{
     header::{CONTENT_TYPE, COOKIE, SET_COOKIE},
 };
 use mas_axum_utils::{
-    ErrorWrapper,
+    ClientJwksCache, ErrorWrapper,
     cookies::{CookieJar, CookieManager},
 };
 use mas_config::RateLimitingConfig;
-use mas_data_model::SiteConfig;
+use mas_data_model::{SiteConfig, TotpPolicy};
 use mas_i18n::Translator;
 use mas_keystore::{Encrypter, JsonWebKey, JsonWebKeySet, Keystore, PrivateKey};
 use mas_matrix::{HomeserverConnection, MockHomeserverConnection};
@@ -53,9 +53,16 @@ use tower::{Layer, Service, ServiceExt};
 use url::Url;
 
 use crate::{
-    ActivityTracker, BoundActivityTracker, Limiter, RequesterFingerprint, graphql,
+    ActivityTracker, BoundActivityTracker, FeatureFlags, GeoIpResolver, Limiter,
+    RequesterFingerprint,
+    captcha::ProofOfWorkReplayCache,
+    graphql,
+    oauth2::introspection_cache::IntrospectionCache,
     passwords::{Hasher, PasswordManager},
-    upstream_oauth2::cache::MetadataCache,
+    upstream_oauth2::{
+        cache::MetadataCache, circuit_breaker::ProviderCircuitBreaker,
+        health::ProviderHealthTracker,
+    },
 };
 
 /// Setup rustcrypto and tracing for tests.
@@ -84,6 +91,9 @@ pub(crate) async fn policy_factory(
         client_registration: "client_registration/violation".to_owned(),
         authorization_grant: "authorization_grant/violation".to_owned(),
         email: "email/violation".to_owned(),
+        login: "login/violation".to_owned(),
+        display_name: "display_name/violation".to_owned(),
+        upstream_login: "upstream_login/violation".to_owned(),
     };
 
     let data = mas_policy::Data::new(server_name.to_owned()).with_rest(data);
@@ -100,6 +110,11 @@ pub(crate) struct TestState {
     pub key_store: Keystore,
     pub cookie_manager: CookieManager,
     pub metadata_cache: MetadataCache,
+    pub provider_health: ProviderHealthTracker,
+    pub circuit_breaker: ProviderCircuitBreaker,
+    pub proof_of_work_replay_cache: ProofOfWorkReplayCache,
+    pub introspection_cache: IntrospectionCache,
+    pub client_jwks_cache: ClientJwksCache,
     pub encrypter: Encrypter,
     pub url_builder: UrlBuilder,
     pub homeserver_connection: Arc<MockHomeserverConnection>,
@@ -109,6 +124,7 @@ pub(crate) struct TestState {
     pub site_config: SiteConfig,
     pub activity_tracker: ActivityTracker,
     pub limiter: Limiter,
+    pub feature_flags: FeatureFlags,
     pub clock: Arc<MockClock>,
     pub rng: Arc<Mutex<ChaChaRng>>,
     pub http_client: reqwest::Client,
@@ -139,14 +155,44 @@ pub fn test_site_config() -> SiteConfig {
         registration_token_required: false,
         email_change_allowed: true,
         displayname_change_allowed: true,
+        avatar_change_allowed: true,
         password_change_allowed: true,
         account_recovery_allowed: true,
+        account_recovery_requires_approval: false,
         account_deactivation_allowed: true,
+        account_deletion_grace_period: None,
         captcha: None,
         minimum_password_complexity: 1,
+        password_lockout: None,
+        password_max_age: None,
+        password_history_size: 0,
         session_expiration: None,
         login_with_email_allowed: true,
+        login_with_phone_number_allowed: false,
+        passkeys_enabled: false,
+        email_code_login_enabled: false,
+        totp_policy: TotpPolicy::Disabled,
+        phone_number_change_allowed: false,
+        sms_second_factor_enabled: false,
+        trusted_device_ttl: None,
+        sudo_mode_ttl: None,
+        user_invites: None,
+        home_realm_discovery: None,
+        single_provider_auto_redirect: true,
+        terms_documents: Vec::new(),
+        sign_in_notifications_enabled: false,
         plan_management_iframe_uri: None,
+        scim_client: None,
+        user_attributes: Vec::new(),
+        authorization_details_types: Vec::new(),
+        software_statement_issuers: Vec::new(),
+        software_statement_required: false,
+        acr_values: Vec::new(),
+        step_up_scopes: Vec::new(),
+        step_up_ttl: Duration::try_minutes(5).unwrap(),
+        device_code_user_code_length: 6,
+        device_code_user_code_alphabet: "ABCDEFGHJKLMNPQRSTUVWXYZ23456789".to_owned(),
+        oauth2_1_strict: false,
     }
 }
 
@@ -192,6 +238,8 @@ impl TestState {
         let cookie_manager = CookieManager::derive_from(url_builder.http_base(), &[0x42; 32]);
 
         let metadata_cache = MetadataCache::new();
+        let introspection_cache = IntrospectionCache::default();
+        let client_jwks_cache = ClientJwksCache::new();
 
         let password_manager = if site_config.password_login_enabled {
             PasswordManager::new(
@@ -223,6 +271,7 @@ impl TestState {
             password_manager: password_manager.clone(),
             url_builder: url_builder.clone(),
             limiter: limiter.clone(),
+            encrypter: encrypter.clone(),
         };
         let state: crate::graphql::BoxState = Box::new(graphql_state);
 
@@ -230,6 +279,7 @@ impl TestState {
 
         let activity_tracker = ActivityTracker::new(
             PgRepositoryFactory::new(pool.clone()).boxed(),
+            GeoIpResolver::disabled(),
             std::time::Duration::from_secs(60),
             &task_tracker,
             shutdown_token.child_token(),
@@ -241,6 +291,11 @@ impl TestState {
             key_store,
             cookie_manager,
             metadata_cache,
+            provider_health: ProviderHealthTracker::default(),
+            circuit_breaker: ProviderCircuitBreaker::default(),
+            proof_of_work_replay_cache: ProofOfWorkReplayCache::default(),
+            introspection_cache,
+            client_jwks_cache,
             encrypter,
             url_builder,
             homeserver_connection,
@@ -250,6 +305,7 @@ impl TestState {
             site_config,
             activity_tracker,
             limiter,
+            feature_flags: FeatureFlags::default(),
             clock,
             rng,
             http_client,
@@ -406,6 +462,7 @@ struct TestGraphQLState {
     password_manager: PasswordManager,
     url_builder: UrlBuilder,
     limiter: Limiter,
+    encrypter: Encrypter,
 }
 
 #[async_trait::async_trait]
@@ -442,6 +499,10 @@ impl graphql::State for TestGraphQLState {
         &self.limiter
     }
 
+    fn encrypter(&self) -> &Encrypter {
+        &self.encrypter
+    }
+
     fn rng(&self) -> BoxRng {
         let mut parent_rng = self.rng.lock().expect("Failed to lock RNG");
         let rng = ChaChaRng::from_rng(&mut *parent_rng).expect("Failed to seed RNG");
@@ -449,12 +510,6 @@ impl graphql::State for TestGraphQLState {
     }
 }
 
-impl FromRef<TestState> for PgPool {
-    fn from_ref(input: &TestState) -> Self {
-        input.repository_factory.pool()
-    }
-}
-
 impl FromRef<TestState> for BoxRepositoryFactory {
     fn from_ref(input: &TestState) -> Self {
         input.repository_factory.clone().boxed()
@@ -515,6 +570,36 @@ impl FromRef<TestState> for MetadataCache {
     }
 }
 
+impl FromRef<TestState> for ProviderHealthTracker {
+    fn from_ref(input: &TestState) -> Self {
+        input.provider_health.clone()
+    }
+}
+
+impl FromRef<TestState> for ProviderCircuitBreaker {
+    fn from_ref(input: &TestState) -> Self {
+        input.circuit_breaker.clone()
+    }
+}
+
+impl FromRef<TestState> for ProofOfWorkReplayCache {
+    fn from_ref(input: &TestState) -> Self {
+        input.proof_of_work_replay_cache.clone()
+    }
+}
+
+impl FromRef<TestState> for IntrospectionCache {
+    fn from_ref(input: &TestState) -> Self {
+        input.introspection_cache.clone()
+    }
+}
+
+impl FromRef<TestState> for ClientJwksCache {
+    fn from_ref(input: &TestState) -> Self {
+        input.client_jwks_cache.clone()
+    }
+}
+
 impl FromRef<TestState> for SiteConfig {
     fn from_ref(input: &TestState) -> Self {
         input.site_config.clone()
@@ -539,6 +624,12 @@ impl FromRef<TestState> for Limiter {
     }
 }
 
+impl FromRef<TestState> for FeatureFlags {
+    fn from_ref(input: &TestState) -> Self {
+        input.feature_flags.clone()
+    }
+}
+
 impl FromRef<TestState> for reqwest::Client {
     fn from_ref(input: &TestState) -> Self {
         input.http_client.clone()
@@ -0,0 +1,369 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Time-based one-time passwords, used as a second factor, as defined in
+//! [RFC6238]
+//!
+//! We use the parameters every authenticator app supports: HMAC-SHA1, 6
+//! digits and 30 seconds steps. Codes from the previous and next steps are
+//! also accepted, to account for clock drift and slow typing.
+//!
+//! Users also get a set of single-use recovery codes when they add a second
+//! factor, to log in if their authenticator app is unavailable.
+//!
+//! [RFC6238]: https://www.rfc-editor.org/rfc/rfc6238
+
+use base64ct::{Base64, Encoding};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use mas_data_model::{User, UserRecoveryCode, UserTotp};
+use mas_keystore::{DecryptError, Encrypter};
+use mas_storage::{
+    BoxRepository, Clock, RepositoryAccess, RepositoryError,
+    user::{UserRecoveryCodeRepository, UserTotpRepository},
+};
+use qrcode::{QrCode, render::svg, types::QrError};
+use rand::{
+    RngCore,
+    distributions::{Distribution, Standard},
+};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use url::Url;
+use zeroize::Zeroizing;
+
+/// The length of the shared secrets, as recommended by RFC4226 for HMAC-SHA1
+const SECRET_LENGTH: usize = 20;
+
+/// The number of digits of the codes
+const DIGITS: u32 = 6;
+
+/// The duration of a time step, in seconds
+const STEP: i64 = 30;
+
+/// How many steps before and after the current one are accepted
+const WINDOW: i64 = 1;
+
+/// The number of recovery codes generated at once
+const RECOVERY_CODES: usize = 10;
+
+/// The number of random bytes in a recovery code, which gives 16 characters
+/// once encoded
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+#[derive(Debug, Error)]
+pub(crate) enum TotpError {
+    #[error("could not decrypt the TOTP secret")]
+    Decrypt(#[from] DecryptError),
+
+    #[error(transparent)]
+    Repository(#[from] RepositoryError),
+}
+
+/// Generate a new random shared secret
+pub(crate) fn generate_secret(rng: &mut (impl RngCore + ?Sized)) -> Zeroizing<Vec<u8>> {
+    let secret: [u8; SECRET_LENGTH] = Standard.sample(rng);
+    Zeroizing::new(secret.to_vec())
+}
+
+/// Encode a shared secret in unpadded base32, as authenticator apps expect
+/// it
+pub(crate) fn encode_secret(secret: &[u8]) -> String {
+    let mut encoded = String::with_capacity(secret.len().div_ceil(5) * 8);
+    for chunk in secret.chunks(5) {
+        let mut buffer = [0_u8; 5];
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        let bits = buffer
+            .iter()
+            .fold(0_u64, |acc, byte| (acc << 8) | u64::from(*byte));
+
+        // Each character holds 5 bits, so a partial chunk needs enough
+        // characters to hold all of its bits
+        let chars = (chunk.len() * 8).div_ceil(5);
+        for i in 0..chars {
+            let index = (bits >> (35 - i * 5)) & 0x1f;
+            #[allow(clippy::cast_possible_truncation)]
+            encoded.push(char::from(BASE32_ALPHABET[index as usize]));
+        }
+    }
+    encoded
+}
+
+/// Build the `otpauth://` URI authenticator apps scan to add a second factor
+///
+/// See the [Key Uri Format] for the parameters
+///
+/// [Key Uri Format]: https://github.com/google/google-authenticator/wiki/Key-Uri-Format
+pub(crate) fn provisioning_uri(issuer: &str, account: &str, secret: &[u8]) -> Url {
+    let mut uri = Url::parse("otpauth://totp/").expect("valid base URI");
+    uri.path_segments_mut()
+        .expect("URI has a path")
+        .pop_if_empty()
+        .push(&format!("{issuer}:{account}"));
+    uri.query_pairs_mut()
+        .append_pair("secret", &encode_secret(secret))
+        .append_pair("issuer", issuer)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &DIGITS.to_string())
+        .append_pair("period", &STEP.to_string());
+    uri
+}
+
+/// Render a provisioning URI as a QR code, in an SVG `data:` URI which can be
+/// used as an image source
+pub(crate) fn qr_code_data_uri(uri: &Url) -> Result<String, QrError> {
+    let code = QrCode::new(uri.as_str().as_bytes())?;
+    let image = code
+        .render::<svg::Color<'_>>()
+        .min_dimensions(200, 200)
+        .dark_color(svg::Color("#000000"))
+        .light_color(svg::Color("#ffffff"))
+        .build();
+    Ok(format!(
+        "data:image/svg+xml;base64,{}",
+        Base64::encode_string(image.as_bytes())
+    ))
+}
+
+/// Compute the HOTP value of a counter, as defined in RFC4226
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation
+    let offset = usize::from(digest[digest.len() - 1] & 0x0f);
+    let code = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    code % 10_u32.pow(DIGITS)
+}
+
+/// Compare two codes in constant time
+fn codes_match(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Check a code against a shared secret, around the given time
+///
+/// Returns the time step the code was generated for if it is valid. Spaces in
+/// the code are ignored, as some apps show codes in groups of digits.
+pub(crate) fn verify(secret: &[u8], code: &str, now: DateTime<Utc>) -> Option<i64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let current = now.timestamp().div_euclid(STEP);
+    (current - WINDOW..=current + WINDOW).find(|&step| {
+        let Ok(counter) = u64::try_from(step) else {
+            return false;
+        };
+        let expected = format!("{:0width$}", hotp(secret, counter), width = DIGITS as usize);
+        codes_match(expected.as_bytes(), code.as_bytes())
+    })
+}
+
+/// Generate the code of a shared secret at the given time, for tests which
+/// need to go through a second factor
+#[cfg(test)]
+pub(crate) fn generate(secret: &[u8], now: DateTime<Utc>) -> String {
+    let counter = u64::try_from(now.timestamp().div_euclid(STEP)).unwrap();
+    format!("{:0width$}", hotp(secret, counter), width = DIGITS as usize)
+}
+
+/// Check a code entered by a user against their second factor, and record its
+/// use so that it can't be used again. Unconfirmed second factors get
+/// confirmed by their first valid code.
+///
+/// Returns the updated second factor, or `None` if the code is invalid or was
+/// already used.
+///
+/// # Errors
+///
+/// Returns an error if the secret could not be decrypted, or if the repository
+/// fails
+pub(crate) async fn check_code(
+    repo: &mut BoxRepository,
+    clock: &dyn Clock,
+    encrypter: &Encrypter,
+    totp: UserTotp,
+    code: &str,
+) -> Result<Option<UserTotp>, TotpError> {
+    let secret = Zeroizing::new(encrypter.decrypt_string(&totp.encrypted_secret)?);
+
+    let Some(step) = verify(&secret, code, clock.now()) else {
+        return Ok(None);
+    };
+
+    if totp.is_confirmed() {
+        Ok(repo.user_totp().record_use(clock, totp, step).await?)
+    } else {
+        Ok(Some(repo.user_totp().confirm(clock, totp, step).await?))
+    }
+}
+
+/// Normalize a recovery code entered by a user, so that it matches however it
+/// was copied: the dashes and spaces are ignored, and so is the case
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Hash a recovery code, for storage. Codes have enough entropy that a fast
+/// hash is sufficient.
+fn hash_recovery_code(code: &str) -> String {
+    hex::encode(Sha256::digest(normalize_recovery_code(code).as_bytes()))
+}
+
+/// Generate a new random recovery code, as groups of 4 characters separated
+/// by dashes
+fn generate_recovery_code(rng: &mut (impl RngCore + ?Sized)) -> String {
+    let mut bytes = Zeroizing::new([0_u8; RECOVERY_CODE_LENGTH]);
+    rng.fill_bytes(&mut bytes[..]);
+    let encoded = encode_secret(&bytes[..]);
+    encoded
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| std::str::from_utf8(chunk).expect("base32 is ASCII"))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Generate a new set of recovery codes for a user, replacing any previous
+/// one
+///
+/// Returns the codes, which must be shown to the user, as only their hashes
+/// are stored.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn replace_recovery_codes(
+    repo: &mut BoxRepository,
+    rng: &mut (dyn RngCore + Send),
+    clock: &dyn Clock,
+    user: &User,
+) -> Result<Vec<String>, RepositoryError> {
+    let codes: Vec<String> = (0..RECOVERY_CODES)
+        .map(|_| generate_recovery_code(&mut *rng))
+        .collect();
+    let hashes = codes.iter().map(|code| hash_recovery_code(code)).collect();
+    repo.user_recovery_code()
+        .replace_all(rng, clock, user, hashes)
+        .await?;
+    Ok(codes)
+}
+
+/// Check a recovery code entered by a user, and consume it so that it can't
+/// be used again
+///
+/// Returns the consumed code, or `None` if the code is invalid or was already
+/// used.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn consume_recovery_code(
+    repo: &mut BoxRepository,
+    clock: &dyn Clock,
+    user: &User,
+    code: &str,
+) -> Result<Option<UserRecoveryCode>, RepositoryError> {
+    repo.user_recovery_code()
+        .consume(clock, user, &hash_recovery_code(code))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    // The SHA1 secret from the test vectors of RFC6238
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        // The RFC gives 8 digits codes, we only keep the last 6
+        for (timestamp, code) in [
+            (59, "287082"),
+            (1_111_111_109, "081804"),
+            (1_111_111_111, "050471"),
+            (1_234_567_890, "005924"),
+            (2_000_000_000, "279037"),
+        ] {
+            let now = Utc.timestamp_opt(timestamp, 0).unwrap();
+            assert_eq!(verify(SECRET, code, now), Some(timestamp / STEP));
+        }
+    }
+
+    #[test]
+    fn test_verify_window() {
+        let now = Utc.timestamp_opt(1_111_111_109, 0).unwrap();
+        let step = 1_111_111_109 / STEP;
+
+        // Codes from the previous and the next steps are accepted
+        let previous = format!("{:06}", hotp(SECRET, (step - 1).try_into().unwrap()));
+        let next = format!("{:06}", hotp(SECRET, (step + 1).try_into().unwrap()));
+        let too_old = format!("{:06}", hotp(SECRET, (step - 2).try_into().unwrap()));
+        assert_eq!(verify(SECRET, &previous, now), Some(step - 1));
+        assert_eq!(verify(SECRET, &next, now), Some(step + 1));
+        assert_eq!(verify(SECRET, &too_old, now), None);
+
+        // Spaces are ignored, but anything else is rejected
+        assert_eq!(verify(SECRET, "081 804", now), Some(step));
+        assert_eq!(verify(SECRET, "81804", now), None);
+        assert_eq!(verify(SECRET, "0818045", now), None);
+        assert_eq!(verify(SECRET, "08180a", now), None);
+        assert_eq!(verify(b"another secret", "081804", now), None);
+    }
+
+    #[test]
+    fn test_encode_secret() {
+        // Test vectors from RFC4648
+        assert_eq!(encode_secret(b""), "");
+        assert_eq!(encode_secret(b"f"), "MY");
+        assert_eq!(encode_secret(b"fo"), "MZXQ");
+        assert_eq!(encode_secret(b"foo"), "MZXW6");
+        assert_eq!(encode_secret(b"foob"), "MZXW6YQ");
+        assert_eq!(encode_secret(b"fooba"), "MZXW6YTB");
+        assert_eq!(encode_secret(b"foobar"), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        let uri = provisioning_uri("example.com", "alice", b"foobar");
+        assert_eq!(
+            uri.as_str(),
+            "otpauth://totp/example.com:alice?secret=MZXW6YTBOI&issuer=example.com&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(42);
+        let code = generate_recovery_code(&mut rng);
+        assert_eq!(code.len(), 19);
+        assert_eq!(code.matches('-').count(), 3);
+
+        // The hash ignores the formatting of the code
+        let hash = hash_recovery_code(&code);
+        assert_eq!(hash_recovery_code(&code.to_lowercase()), hash);
+        assert_eq!(hash_recovery_code(&code.replace('-', " ")), hash);
+        assert_eq!(hash_recovery_code(&code.replace('-', "")), hash);
+        assert_ne!(hash_recovery_code(&generate_recovery_code(&mut rng)), hash);
+    }
+}
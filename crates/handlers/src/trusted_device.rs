@@ -0,0 +1,105 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Browsers users chose to trust after entering their second factor
+//!
+//! The browser keeps the IDs of the devices trusted from it in an encrypted
+//! cookie, so that each user of a shared browser has to trust it separately.
+//! Whether a device is still trusted is always checked against the database.
+
+use mas_axum_utils::cookies::CookieJar;
+use mas_data_model::{SiteConfig, User, UserTrustedDevice};
+use mas_storage::{BoxRepository, Clock, RepositoryError, user::UserTrustedDeviceRepository};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+/// Name of the cookie
+static COOKIE_NAME: &str = "trusted-devices";
+
+/// How many devices a browser remembers, the oldest ones being forgotten first
+const MAX_DEVICES: usize = 5;
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct TrustedDevices(Vec<Ulid>);
+
+impl TrustedDevices {
+    fn load(cookie_jar: &CookieJar) -> Self {
+        match cookie_jar.load(COOKIE_NAME) {
+            Ok(Some(devices)) => devices,
+            Ok(None) => Self::default(),
+            Err(e) => {
+                tracing::warn!("Invalid trusted devices cookie: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    fn save(self, cookie_jar: CookieJar) -> CookieJar {
+        // The devices expire on their own, so the cookie can live forever
+        cookie_jar.save(COOKIE_NAME, &self, true)
+    }
+}
+
+/// Find a device trusted by the user from this browser, which lets them skip
+/// their second factor
+///
+/// Returns `None` if trusting devices is disabled.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn find(
+    repo: &mut BoxRepository,
+    clock: &dyn Clock,
+    site_config: &SiteConfig,
+    cookie_jar: &CookieJar,
+    user: &User,
+) -> Result<Option<UserTrustedDevice>, RepositoryError> {
+    if site_config.trusted_device_ttl.is_none() {
+        return Ok(None);
+    }
+
+    let now = clock.now();
+    for id in TrustedDevices::load(cookie_jar).0 {
+        let device = repo.user_trusted_device().lookup(id).await?;
+        if let Some(device) = device.filter(|d| d.user_id == user.id && d.is_valid(now)) {
+            return Ok(Some(device));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Trust this browser for the user, if trusting devices is enabled
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn trust(
+    repo: &mut BoxRepository,
+    rng: &mut (dyn RngCore + Send),
+    clock: &dyn Clock,
+    site_config: &SiteConfig,
+    cookie_jar: CookieJar,
+    user: &User,
+    user_agent: Option<String>,
+) -> Result<CookieJar, RepositoryError> {
+    let Some(ttl) = site_config.trusted_device_ttl else {
+        return Ok(cookie_jar);
+    };
+
+    let device = repo
+        .user_trusted_device()
+        .add(rng, clock, user, user_agent, ttl)
+        .await?;
+
+    let mut devices = TrustedDevices::load(&cookie_jar);
+    devices.0.push(device.id);
+    let excess = devices.0.len().saturating_sub(MAX_DEVICES);
+    devices.0.drain(..excess);
+
+    Ok(devices.save(cookie_jar))
+}
@@ -4,11 +4,14 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::collections::HashSet;
+
 use axum::{
     extract::{Path, Query, State},
     response::{IntoResponse, Redirect},
 };
 use hyper::StatusCode;
+use language_tags::LanguageTag;
 use mas_axum_utils::{cookies::CookieJar, record_error};
 use mas_data_model::UpstreamOAuthProvider;
 use mas_oidc_client::requests::authorization_code::AuthorizationRequestData;
@@ -17,12 +20,13 @@ use mas_storage::{
     BoxClock, BoxRepository, BoxRng,
     upstream_oauth2::{UpstreamOAuthProviderRepository, UpstreamOAuthSessionRepository},
 };
+use oauth2_types::requests::Prompt;
 use thiserror::Error;
 use ulid::Ulid;
 
 use super::{UpstreamSessionsCookie, cache::LazyProviderInfos};
 use crate::{
-    impl_from_error_for_route, upstream_oauth2::cache::MetadataCache,
+    PreferredLanguage, impl_from_error_for_route, upstream_oauth2::cache::MetadataCache,
     views::shared::OptionalPostAuthAction,
 };
 
@@ -51,6 +55,13 @@ impl IntoResponse for RouteError {
     }
 }
 
+/// Start an authorization session with the upstream provider, and redirect to
+/// it
+///
+/// This records a new session on GET, as this is the target of links and of
+/// automatic redirects. The session is bound to the cookie set in the response,
+/// so requests made by anything else than the user's browser can't be used to
+/// complete it.
 #[tracing::instrument(
     name = "handlers.upstream_oauth2.authorize.get",
     fields(upstream_oauth_provider.id = %provider_id),
@@ -63,6 +74,7 @@ pub(crate) async fn get(
     mut repo: BoxRepository,
     State(url_builder): State<UrlBuilder>,
     State(http_client): State<reqwest::Client>,
+    PreferredLanguage(locale): PreferredLanguage,
     cookie_jar: CookieJar,
     Path(provider_id): Path<Ulid>,
     Query(query): Query<OptionalPostAuthAction>,
@@ -92,19 +104,53 @@ pub(crate) async fn get(
         data = data.with_response_mode(response_mode.into());
     }
 
-    // Forward the raw login hint upstream for the provider to handle however it
-    // sees fit
-    if provider.forward_login_hint {
-        if let Some(PostAuthAction::ContinueAuthorizationGrant { id }) = &query.post_auth_action {
-            if let Some(login_hint) = repo
-                .oauth2_authorization_grant()
-                .lookup(*id)
-                .await?
-                .and_then(|grant| grant.login_hint)
-            {
+    let grant = match &query.post_auth_action {
+        Some(PostAuthAction::ContinueAuthorizationGrant { id }) => {
+            repo.oauth2_authorization_grant().lookup(*id).await?
+        }
+        _ => None,
+    };
+
+    if let Some(grant) = grant {
+        // Forward the raw login hint upstream for the provider to handle however
+        // it sees fit
+        if provider.forward_login_hint {
+            if let Some(login_hint) = grant.login_hint {
                 data = data.with_login_hint(login_hint);
             }
         }
+
+        // If the client asked for a fresh authentication, the upstream provider
+        // must not reuse its own session either
+        if provider.forward_prompt && grant.prompt_login {
+            data = data.with_prompt(vec![Prompt::Login]);
+        }
+
+        // Ask the provider to enforce the same level of authentication as the
+        // client requires from us, for example multi-factor authentication
+        if provider.forward_acr_values {
+            if let Some(required_acr) = grant.required_acr {
+                data = data.with_acr_values(HashSet::from([required_acr]));
+            }
+        }
+
+        if let Some(max_age) = grant.max_age {
+            data = data.with_max_age(max_age);
+        }
+    }
+
+    // Let the provider display its pages in the same language as ours
+    if provider.forward_ui_locales {
+        match LanguageTag::parse(&locale.to_string()) {
+            Ok(ui_locale) => data = data.with_ui_locales(vec![ui_locale]),
+            Err(e) => {
+                tracing::warn!(
+                    error = &e as &dyn std::error::Error,
+                    %locale,
+                    "Could not forward the locale to the upstream provider"
+                );
+            }
+        }
     }
 
     let data = if let Some(methods) = lazy_metadata.pkce_methods().await? {
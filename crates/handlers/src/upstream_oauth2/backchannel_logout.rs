@@ -0,0 +1,474 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Handle logout tokens sent by upstream providers, as per the OpenID Connect
+//! Back-Channel Logout 1.0 specification
+
+use axum::{
+    Form, Json,
+    extract::{Path, State},
+    response::IntoResponse,
+};
+use hyper::StatusCode;
+use mas_axum_utils::record_error;
+use mas_data_model::UpstreamOAuthProvider;
+use mas_jose::claims::{self, ClaimError, TimeOptions};
+use mas_oidc_client::{
+    error::JwtVerificationError,
+    requests::jose::{JwtVerificationData, verify_signed_jwt},
+};
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, Clock, Pagination,
+    queue::{QueueJobRepositoryExt as _, SendBrowserSessionBackchannelLogoutsJob},
+    upstream_oauth2::{UpstreamOAuthProviderRepository, UpstreamOAuthSessionFilter},
+    user::{BrowserSessionFilter, BrowserSessionRepository},
+};
+use oauth2_types::errors::{ClientError, ClientErrorCode};
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::info;
+use ulid::Ulid;
+
+use super::cache::{LazyProviderInfos, MetadataCache, unverified_jwt_kid};
+use crate::impl_from_error_for_route;
+
+/// The event identifying a logout token, as per the specification
+const BACKCHANNEL_LOGOUT_EVENT: &str = "http://schemas.openid.net/event/backchannel-logout";
+
+#[derive(Deserialize)]
+pub(crate) struct BackchannelLogoutRequest {
+    logout_token: String,
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum RouteError {
+    #[error("Provider not found")]
+    ProviderNotFound,
+
+    #[error("Invalid logout token")]
+    InvalidLogoutToken(#[source] JwtVerificationError),
+
+    #[error("Invalid claim in the logout token")]
+    InvalidClaim(#[source] ClaimError),
+
+    #[error("Logout token is not a back-channel logout event")]
+    NotALogoutEvent,
+
+    #[error("Logout token has neither a sub nor a sid claim")]
+    MissingSubOrSid,
+
+    #[error("Logout token must not have a nonce claim")]
+    UnexpectedNonce,
+
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+impl_from_error_for_route!(mas_oidc_client::error::DiscoveryError);
+impl_from_error_for_route!(mas_oidc_client::error::JwksError);
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> axum::response::Response {
+        let sentry_event_id = record_error!(self, Self::Internal(_));
+        let response = match self {
+            Self::ProviderNotFound => (
+                StatusCode::NOT_FOUND,
+                Json(ClientError::from(ClientErrorCode::InvalidRequest)),
+            )
+                .into_response(),
+
+            Self::Internal(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ClientError::from(ClientErrorCode::ServerError)),
+            )
+                .into_response(),
+
+            e => (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidRequest)
+                        .with_description(e.to_string()),
+                ),
+            )
+                .into_response(),
+        };
+
+        (sentry_event_id, response).into_response()
+    }
+}
+
+#[tracing::instrument(
+    name = "handlers.upstream_oauth2.backchannel_logout.post",
+    fields(upstream_oauth_provider.id = %provider_id),
+    skip_all,
+)]
+pub(crate) async fn post(
+    clock: BoxClock,
+    mut rng: BoxRng,
+    mut repo: BoxRepository,
+    State(metadata_cache): State<MetadataCache>,
+    State(client): State<reqwest::Client>,
+    Path(provider_id): Path<Ulid>,
+    Form(request): Form<BackchannelLogoutRequest>,
+) -> Result<impl IntoResponse, RouteError> {
+    let provider = repo
+        .upstream_oauth_provider()
+        .lookup(provider_id)
+        .await?
+        .filter(UpstreamOAuthProvider::enabled)
+        .ok_or(RouteError::ProviderNotFound)?;
+
+    let mut lazy_metadata = LazyProviderInfos::new(&metadata_cache, &provider, &client);
+    let kid = unverified_jwt_kid(&request.logout_token);
+    let jwks = metadata_cache
+        .jwks(&client, lazy_metadata.jwks_uri().await?, kid.as_deref())
+        .await?;
+
+    // Logout tokens are signed the same way as ID tokens
+    let verification_data = JwtVerificationData {
+        issuer: provider.issuer.as_deref(),
+        jwks: &jwks,
+        signing_algorithm: &provider.id_token_signed_response_alg,
+        client_id: &provider.client_id,
+    };
+    let logout_token = verify_signed_jwt(&request.logout_token, verification_data)
+        .map_err(RouteError::InvalidLogoutToken)?;
+    let (_header, mut claims) = logout_token.into_parts();
+
+    let time_options = TimeOptions::new(clock.now());
+    claims::IAT
+        .extract_required_with_options(&mut claims, &time_options)
+        .map_err(RouteError::InvalidClaim)?;
+    claims::EXP
+        .extract_optional_with_options(&mut claims, &time_options)
+        .map_err(RouteError::InvalidClaim)?;
+
+    let events = claims::EVENTS
+        .extract_required(&mut claims)
+        .map_err(RouteError::InvalidClaim)?;
+    if events.get(BACKCHANNEL_LOGOUT_EVENT).is_none() {
+        return Err(RouteError::NotALogoutEvent);
+    }
+
+    // This prevents ID tokens from being used as logout tokens
+    if claims.contains_key("nonce") {
+        return Err(RouteError::UnexpectedNonce);
+    }
+
+    let sub = claims::SUB
+        .extract_optional(&mut claims)
+        .map_err(RouteError::InvalidClaim)?;
+    let sid = claims::SID
+        .extract_optional(&mut claims)
+        .map_err(RouteError::InvalidClaim)?;
+
+    if sub.is_none() && sid.is_none() {
+        return Err(RouteError::MissingSubOrSid);
+    }
+
+    // When both are present, only the sessions matching both are ended
+    let mut upstream_session_filter = UpstreamOAuthSessionFilter::new().for_provider(&provider);
+    if let Some(sub) = &sub {
+        upstream_session_filter = upstream_session_filter.with_sub_claim(sub);
+    }
+    if let Some(sid) = &sid {
+        upstream_session_filter = upstream_session_filter.with_sid_claim(sid);
+    }
+
+    let filter = BrowserSessionFilter::new()
+        .authenticated_by_upstream_sessions_only(upstream_session_filter)
+        .active_only();
+
+    let mut finished_sessions = 0;
+    let mut pagination = Pagination::first(100);
+    loop {
+        let page = repo.browser_session().list(filter, pagination).await?;
+
+        let next_cursor = page
+            .edges
+            .last()
+            .filter(|_| page.has_next_page)
+            .map(|last| last.id);
+
+        for session in page.edges {
+            // This also ends the OAuth 2.0 sessions started from this browser
+            // session, and notifies the clients
+            repo.queue_job()
+                .schedule_job(
+                    &mut rng,
+                    &clock,
+                    SendBrowserSessionBackchannelLogoutsJob::new(&session),
+                )
+                .await?;
+
+            repo.browser_session().finish(&clock, session).await?;
+            finished_sessions += 1;
+        }
+
+        let Some(cursor) = next_cursor else {
+            break;
+        };
+        pagination = pagination.after(cursor);
+    }
+
+    repo.save().await?;
+
+    info!(
+        upstream.sub = sub.as_deref(),
+        upstream.sid = sid.as_deref(),
+        finished_sessions,
+        "Finished the browser sessions logged out by the upstream provider"
+    );
+
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use mas_data_model::{
+        UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderDiscoveryMode,
+        UpstreamOAuthProviderPkceMode, UpstreamOAuthProviderTokenAuthMethod,
+    };
+    use mas_iana::jose::JsonWebSignatureAlg;
+    use mas_jose::jwt::{JsonWebSignatureHeader, Jwt};
+    use mas_router::Route;
+    use mas_storage::{
+        RepositoryAccess,
+        upstream_oauth2::{
+            UpstreamOAuthLinkRepository, UpstreamOAuthProviderParams,
+            UpstreamOAuthProviderRepository, UpstreamOAuthSessionRepository,
+        },
+        user::{BrowserSessionRepository, UserRepository},
+    };
+    use oauth2_types::scope::{OPENID, Scope};
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    #[allow(clippy::too_many_lines)]
+    async fn test_backchannel_logout(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let mut rng = state.rng();
+
+        // Serve the keys of the test keystore as the keys of the provider
+        let mock_server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("GET"))
+            .and(wiremock::matchers::path("/jwks"))
+            .respond_with(
+                wiremock::ResponseTemplate::new(200).set_body_json(state.key_store.public_jwks()),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let key = state
+            .key_store
+            .signing_key_for_algorithm(&JsonWebSignatureAlg::Rs256)
+            .unwrap();
+        let signer = key
+            .params()
+            .signing_key_for_alg(&JsonWebSignatureAlg::Rs256)
+            .unwrap();
+        let mut sign = |claims: serde_json::Value| {
+            let header = JsonWebSignatureHeader::new(JsonWebSignatureAlg::Rs256)
+                .with_kid(key.kid().unwrap());
+            Jwt::sign_with_rng(&mut rng, header, claims, &signer)
+                .unwrap()
+                .into_string()
+        };
+
+        let mut repo = state.repository().await.unwrap();
+        let provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut state.rng(),
+                &state.clock,
+                UpstreamOAuthProviderParams {
+                    issuer: Some("https://example.com/".to_owned()),
+                    human_name: Some("Example Ltd.".to_owned()),
+                    brand_name: None,
+                    scope: Scope::from_iter([OPENID]),
+                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+                    token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
+                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+                    client_id: "client".to_owned(),
+                    encrypted_client_secret: None,
+                    claims_imports: UpstreamOAuthProviderClaimsImports::default(),
+                    authorization_endpoint_override: None,
+                    token_endpoint_override: None,
+                    userinfo_endpoint_override: None,
+                    fetch_userinfo: false,
+                    userinfo_signed_response_alg: None,
+                    jwks_uri_override: Some(format!("{}/jwks", mock_server.uri()).parse().unwrap()),
+                    discovery_mode: UpstreamOAuthProviderDiscoveryMode::Disabled,
+                    pkce_mode: UpstreamOAuthProviderPkceMode::Auto,
+                    response_mode: None,
+                    additional_authorization_parameters: Vec::new(),
+                    forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: false,
+                    deprovisioning: None,
+                    ui_order: 0,
+                },
+            )
+            .await
+            .unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut state.rng(), &state.clock, "john".to_owned())
+            .await
+            .unwrap();
+        let link = repo
+            .upstream_oauth_link()
+            .add(
+                &mut state.rng(),
+                &state.clock,
+                &provider,
+                "subject".to_owned(),
+                None,
+            )
+            .await
+            .unwrap();
+        repo.upstream_oauth_link()
+            .associate_to_user(&link, &user)
+            .await
+            .unwrap();
+
+        // The user signed in twice through the provider, in two different
+        // upstream sessions
+        let mut browser_sessions = Vec::new();
+        for sid in ["first", "second"] {
+            let upstream_session = repo
+                .upstream_oauth_session()
+                .add(
+                    &mut state.rng(),
+                    &state.clock,
+                    &provider,
+                    "state".to_owned(),
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            let upstream_session = repo
+                .upstream_oauth_session()
+                .complete_with_link(
+                    &state.clock,
+                    upstream_session,
+                    &link,
+                    None,
+                    Some(json!({ "sub": "subject", "sid": sid })),
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+
+            let browser_session = repo
+                .browser_session()
+                .add(&mut state.rng(), &state.clock, &user, None)
+                .await
+                .unwrap();
+            repo.browser_session()
+                .authenticate_with_upstream(
+                    &mut state.rng(),
+                    &state.clock,
+                    &browser_session,
+                    &upstream_session,
+                )
+                .await
+                .unwrap();
+            browser_sessions.push(browser_session);
+        }
+        repo.save().await.unwrap();
+
+        let path = mas_router::UpstreamOAuth2BackchannelLogout::new(provider.id).path();
+        let now = state.clock.now().timestamp();
+
+        // A token without the logout event is rejected
+        let logout_token = sign(json!({
+            "iss": "https://example.com/",
+            "aud": "client",
+            "iat": now,
+            "sid": "first",
+        }));
+        let request = Request::post(&*path).form(json!({ "logout_token": logout_token }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // A token for another client is rejected
+        let logout_token = sign(json!({
+            "iss": "https://example.com/",
+            "aud": "another-client",
+            "iat": now,
+            "sid": "first",
+            "events": { "http://schemas.openid.net/event/backchannel-logout": {} },
+        }));
+        let request = Request::post(&*path).form(json!({ "logout_token": logout_token }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // Logging out the first upstream session only ends the first browser
+        // session
+        let logout_token = sign(json!({
+            "iss": "https://example.com/",
+            "aud": "client",
+            "iat": now,
+            "sid": "first",
+            "events": { "http://schemas.openid.net/event/backchannel-logout": {} },
+        }));
+        let request = Request::post(&*path).form(json!({ "logout_token": logout_token }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        let mut repo = state.repository().await.unwrap();
+        let first = repo
+            .browser_session()
+            .lookup(browser_sessions[0].id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(first.finished_at.is_some());
+        let second = repo
+            .browser_session()
+            .lookup(browser_sessions[1].id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(second.finished_at.is_none());
+        repo.save().await.unwrap();
+
+        // Logging out the user ends all their sessions
+        let logout_token = sign(json!({
+            "iss": "https://example.com/",
+            "aud": "client",
+            "iat": now,
+            "sub": "subject",
+            "events": { "http://schemas.openid.net/event/backchannel-logout": {} },
+        }));
+        let request = Request::post(&*path).form(json!({ "logout_token": logout_token }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+
+        let mut repo = state.repository().await.unwrap();
+        let second = repo
+            .browser_session()
+            .lookup(browser_sessions[1].id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(second.finished_at.is_some());
+    }
+}
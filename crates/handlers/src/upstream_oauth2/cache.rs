@@ -6,14 +6,20 @@
 
 use std::{collections::HashMap, sync::Arc};
 
+use chrono::{DateTime, Duration, Utc};
 use mas_context::LogContext;
 use mas_data_model::{
-    UpstreamOAuthProvider, UpstreamOAuthProviderDiscoveryMode, UpstreamOAuthProviderPkceMode,
+    UpstreamOAuthCachedDocument, UpstreamOAuthCachedDocumentKind, UpstreamOAuthProvider,
+    UpstreamOAuthProviderDiscoveryMode, UpstreamOAuthProviderPkceMode,
 };
 use mas_iana::oauth::PkceCodeChallengeMethod;
-use mas_oidc_client::error::DiscoveryError;
-use mas_storage::{RepositoryAccess, upstream_oauth2::UpstreamOAuthProviderRepository};
-use oauth2_types::oidc::VerifiedProviderMetadata;
+use mas_jose::{constraints::Constrainable as _, jwk::PublicJsonWebKeySet};
+use mas_oidc_client::error::{DiscoveryError, JwksError};
+use mas_storage::{
+    ArcRepositoryFactory, Clock as _, RepositoryAccess, SystemClock,
+    upstream_oauth2::{UpstreamOAuthCachedDocumentRepository, UpstreamOAuthProviderRepository},
+};
+use oauth2_types::oidc::{ProviderMetadata, VerifiedProviderMetadata};
 use tokio::sync::RwLock;
 use url::Url;
 
@@ -142,16 +148,103 @@ impl<'a> LazyProviderInfos<'a> {
     }
 }
 
-/// A simple OIDC metadata cache
+/// The minimum time between two fetches of the same JWKS triggered by an
+/// unknown key ID, so that tokens with random key IDs can't make us hammer the
+/// provider
+const MIN_JWKS_REFRESH_INTERVAL: Duration = Duration::microseconds(60 * 1000 * 1000);
+
+/// Get the ID of the key a JWT claims to be signed with, without verifying
+/// it, so that the right JWKS can be looked up
+pub(crate) fn unverified_jwt_kid(jwt: &str) -> Option<String> {
+    let jwt = mas_jose::jwt::Jwt::<serde_json::Value>::try_from(jwt).ok()?;
+    jwt.header().kid().map(ToOwned::to_owned)
+}
+
+/// Settings of the [`MetadataCache`]
+#[derive(Debug, Clone)]
+pub struct MetadataCacheSettings {
+    /// How long a discovery document is used before it is fetched again
+    pub discovery_ttl: Duration,
+
+    /// How long a JWKS is used before it is fetched again
+    pub jwks_ttl: Duration,
+
+    /// How long a document can still be used after it expired, if it can't be
+    /// fetched again
+    pub max_stale_age: Duration,
+}
+
+impl Default for MetadataCacheSettings {
+    fn default() -> Self {
+        Self {
+            discovery_ttl: Duration::minutes(15),
+            jwks_ttl: Duration::minutes(15),
+            max_stale_age: Duration::days(7),
+        }
+    }
+}
+
+struct CacheEntry<T> {
+    value: Arc<T>,
+    fetched_at: DateTime<Utc>,
+}
+
+impl<T> Clone for CacheEntry<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: Arc::clone(&self.value),
+            fetched_at: self.fetched_at,
+        }
+    }
+}
+
+impl<T> CacheEntry<T> {
+    fn new(value: Arc<T>, fetched_at: DateTime<Utc>) -> Self {
+        Self { value, fetched_at }
+    }
+
+    /// Whether the entry can be used without fetching the document again
+    fn is_fresh(&self, now: DateTime<Utc>, ttl: Duration) -> bool {
+        now - self.fetched_at < ttl
+    }
+
+    /// Whether the entry can still be used if the document can't be fetched
+    /// again
+    fn is_usable(&self, now: DateTime<Utc>, ttl: Duration, max_stale_age: Duration) -> bool {
+        now - self.fetched_at < ttl + max_stale_age
+    }
+}
+
+/// A cache of the OIDC metadata and JWKS of the upstream providers
+///
+/// Documents are fetched again once they are older than their configured
+/// TTL. If the provider can't be reached at that point, the expired document
+/// keeps being used for a while, so that transient failures of the provider
+/// don't make logins fail.
+///
+/// When given a repository factory, the documents are also stored in the
+/// database, so that they are shared between instances and survive restarts.
 ///
 /// It never evicts entries, does not cache failures and has no locking.
 /// It can also be refreshed in the background, and warmed up on startup.
 /// It is good enough for our use case.
 #[allow(clippy::module_name_repetitions)]
-#[derive(Debug, Clone, Default)]
+#[derive(Clone, Default)]
 pub struct MetadataCache {
-    cache: Arc<RwLock<HashMap<String, Arc<VerifiedProviderMetadata>>>>,
-    insecure_cache: Arc<RwLock<HashMap<String, Arc<VerifiedProviderMetadata>>>>,
+    cache: Arc<RwLock<HashMap<String, CacheEntry<VerifiedProviderMetadata>>>>,
+    insecure_cache: Arc<RwLock<HashMap<String, CacheEntry<VerifiedProviderMetadata>>>>,
+    jwks_cache: Arc<RwLock<HashMap<Url, CacheEntry<PublicJsonWebKeySet>>>>,
+    settings: MetadataCacheSettings,
+    repository_factory: Option<ArcRepositoryFactory>,
+}
+
+impl std::fmt::Debug for MetadataCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetadataCache")
+            .field("settings", &self.settings)
+            .field("persist", &self.repository_factory.is_some())
+            .finish_non_exhaustive()
+    }
 }
 
 impl MetadataCache {
@@ -160,6 +253,33 @@ impl MetadataCache {
         Self::default()
     }
 
+    /// Set the TTLs of the cached documents
+    #[must_use]
+    pub fn with_settings(mut self, settings: MetadataCacheSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Store the fetched documents in the database, and use them when they
+    /// aren't in memory yet
+    #[must_use]
+    pub fn with_repository_factory(mut self, repository_factory: ArcRepositoryFactory) -> Self {
+        self.repository_factory = Some(repository_factory);
+        self
+    }
+
+    /// The interval at which the cached documents should be refreshed in the
+    /// background
+    #[must_use]
+    pub fn refresh_interval(&self) -> std::time::Duration {
+        self.settings
+            .discovery_ttl
+            .min(self.settings.jwks_ttl)
+            .max(Duration::minutes(1))
+            .to_std()
+            .unwrap_or(std::time::Duration::from_secs(60))
+    }
+
     /// Warm up the cache by fetching all the known providers from the database
     /// and inserting them into the cache.
     ///
@@ -205,38 +325,45 @@ impl MetadataCache {
         }))
     }
 
+    /// Fetch the metadata for the given issuer, bypassing the cache, and
+    /// update the cache with it.
     #[tracing::instrument(name = "metadata_cache.fetch", fields(%issuer), skip_all)]
-    async fn fetch(
+    pub async fn fetch(
         &self,
         client: &reqwest::Client,
         issuer: &str,
         verify: bool,
     ) -> Result<Arc<VerifiedProviderMetadata>, DiscoveryError> {
-        if verify {
-            let metadata = mas_oidc_client::requests::discovery::discover(client, issuer).await?;
-            let metadata = Arc::new(metadata);
-
-            self.cache
-                .write()
-                .await
-                .insert(issuer.to_owned(), metadata.clone());
+        let metadata = if verify {
+            mas_oidc_client::requests::discovery::discover(client, issuer).await?
+        } else {
+            mas_oidc_client::requests::discovery::insecure_discover(client, issuer).await?
+        };
+        let metadata = Arc::new(metadata);
 
-            Ok(metadata)
+        let cache = if verify {
+            &self.cache
         } else {
-            let metadata =
-                mas_oidc_client::requests::discovery::insecure_discover(client, issuer).await?;
-            let metadata = Arc::new(metadata);
+            &self.insecure_cache
+        };
+        cache.write().await.insert(
+            issuer.to_owned(),
+            CacheEntry::new(metadata.clone(), SystemClock::default().now()),
+        );
 
-            self.insecure_cache
-                .write()
-                .await
-                .insert(issuer.to_owned(), metadata.clone());
+        // Store the metadata as it was served, so that it can be verified again
+        // when loaded
+        let document: &ProviderMetadata = &metadata;
+        self.persist(UpstreamOAuthCachedDocumentKind::Discovery, issuer, document)
+            .await;
 
-            Ok(metadata)
-        }
+        Ok(metadata)
     }
 
     /// Get the metadata for the given issuer.
+    ///
+    /// If the cached metadata expired and can't be fetched again, the expired
+    /// metadata is returned, unless it is too old.
     #[tracing::instrument(name = "metadata_cache.get", fields(%issuer), skip_all)]
     pub async fn get(
         &self,
@@ -244,20 +371,264 @@ impl MetadataCache {
         issuer: &str,
         verify: bool,
     ) -> Result<Arc<VerifiedProviderMetadata>, DiscoveryError> {
+        let now = SystemClock::default().now();
+        let ttl = self.settings.discovery_ttl;
+
+        let cached = self.cached_metadata(issuer, verify, now).await;
+        if let Some(entry) = cached.as_ref().filter(|entry| entry.is_fresh(now, ttl)) {
+            return Ok(entry.value.clone());
+        }
+
+        match self.fetch(client, issuer, verify).await {
+            Ok(metadata) => Ok(metadata),
+            Err(e) => match cached {
+                Some(entry) if entry.is_usable(now, ttl, self.settings.max_stale_age) => {
+                    tracing::warn!(
+                        %issuer,
+                        fetched_at = %entry.fetched_at,
+                        error = &e as &dyn std::error::Error,
+                        "Failed to fetch provider metadata, using the cached one"
+                    );
+                    Ok(entry.value)
+                }
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// Get the cached metadata for the given issuer, looking it up in the
+    /// database if it isn't fresh in memory
+    async fn cached_metadata(
+        &self,
+        issuer: &str,
+        verify: bool,
+        now: DateTime<Utc>,
+    ) -> Option<CacheEntry<VerifiedProviderMetadata>> {
         let cache = if verify {
-            self.cache.read().await
+            &self.cache
         } else {
-            self.insecure_cache.read().await
+            &self.insecure_cache
         };
 
-        if let Some(metadata) = cache.get(issuer) {
-            return Ok(Arc::clone(metadata));
+        let entry = cache.read().await.get(issuer).cloned();
+        if entry
+            .as_ref()
+            .is_some_and(|entry| entry.is_fresh(now, self.settings.discovery_ttl))
+        {
+            return entry;
         }
-        // Drop the cache guard so that we don't deadlock when we try to fetch
-        drop(cache);
 
-        let metadata = self.fetch(client, issuer, verify).await?;
-        Ok(metadata)
+        // Another instance may have fetched it more recently
+        let document = self
+            .load(UpstreamOAuthCachedDocumentKind::Discovery, issuer)
+            .await
+            .filter(|document| {
+                entry
+                    .as_ref()
+                    .is_none_or(|e| document.fetched_at > e.fetched_at)
+            });
+        let Some(document) = document else {
+            return entry;
+        };
+
+        let metadata = serde_json::from_value::<ProviderMetadata>(document.document)
+            .map_err(|e| e.to_string())
+            .and_then(|metadata| {
+                if verify {
+                    metadata.validate(issuer)
+                } else {
+                    metadata.insecure_verify_metadata()
+                }
+                .map_err(|e| e.to_string())
+            });
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                tracing::warn!(%issuer, %error, "Ignoring invalid provider metadata from the database");
+                return entry;
+            }
+        };
+
+        let entry = CacheEntry::new(Arc::new(metadata), document.fetched_at);
+        cache.write().await.insert(issuer.to_owned(), entry.clone());
+        Some(entry)
+    }
+
+    /// Fetch the JWKS at the given URI, bypassing the cache, and update the
+    /// cache with it.
+    #[tracing::instrument(name = "metadata_cache.fetch_jwks", fields(%jwks_uri), skip_all)]
+    pub async fn fetch_jwks(
+        &self,
+        client: &reqwest::Client,
+        jwks_uri: &Url,
+    ) -> Result<Arc<PublicJsonWebKeySet>, JwksError> {
+        let jwks = mas_oidc_client::requests::jose::fetch_jwks(client, jwks_uri).await?;
+        let jwks = Arc::new(jwks);
+
+        self.jwks_cache.write().await.insert(
+            jwks_uri.clone(),
+            CacheEntry::new(jwks.clone(), SystemClock::default().now()),
+        );
+
+        self.persist(
+            UpstreamOAuthCachedDocumentKind::Jwks,
+            jwks_uri.as_str(),
+            &*jwks,
+        )
+        .await;
+
+        Ok(jwks)
+    }
+
+    /// Get the JWKS served at the given URI.
+    ///
+    /// It is fetched again if the cached one doesn't have a key with the given
+    /// ID, at most once every minute. If the cached JWKS expired and can't be
+    /// fetched again, the expired JWKS is returned, unless it is too old.
+    #[tracing::instrument(name = "metadata_cache.jwks", fields(%jwks_uri), skip_all)]
+    pub async fn jwks(
+        &self,
+        client: &reqwest::Client,
+        jwks_uri: &Url,
+        kid: Option<&str>,
+    ) -> Result<Arc<PublicJsonWebKeySet>, JwksError> {
+        let now = SystemClock::default().now();
+        let ttl = self.settings.jwks_ttl;
+
+        let cached = self.cached_jwks(jwks_uri, now).await;
+        if let Some(entry) = &cached {
+            let has_key =
+                kid.is_none_or(|kid| entry.value.iter().any(|key| key.kid() == Some(kid)));
+            let recently_fetched = now - entry.fetched_at < MIN_JWKS_REFRESH_INTERVAL;
+            if entry.is_fresh(now, ttl) && (has_key || recently_fetched) {
+                return Ok(entry.value.clone());
+            }
+
+            if !has_key {
+                tracing::info!(%jwks_uri, ?kid, "Key ID not found in the cached JWKS, fetching it again");
+            }
+        }
+
+        match self.fetch_jwks(client, jwks_uri).await {
+            Ok(jwks) => Ok(jwks),
+            Err(e) => match cached {
+                Some(entry) if entry.is_usable(now, ttl, self.settings.max_stale_age) => {
+                    tracing::warn!(
+                        %jwks_uri,
+                        fetched_at = %entry.fetched_at,
+                        error = &e as &dyn std::error::Error,
+                        "Failed to fetch the provider JWKS, using the cached one"
+                    );
+                    Ok(entry.value)
+                }
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// Get the cached JWKS at the given URI, looking it up in the database if
+    /// it isn't fresh in memory
+    async fn cached_jwks(
+        &self,
+        jwks_uri: &Url,
+        now: DateTime<Utc>,
+    ) -> Option<CacheEntry<PublicJsonWebKeySet>> {
+        let entry = self.jwks_cache.read().await.get(jwks_uri).cloned();
+        if entry
+            .as_ref()
+            .is_some_and(|entry| entry.is_fresh(now, self.settings.jwks_ttl))
+        {
+            return entry;
+        }
+
+        // Another instance may have fetched it more recently
+        let document = self
+            .load(UpstreamOAuthCachedDocumentKind::Jwks, jwks_uri.as_str())
+            .await
+            .filter(|document| {
+                entry
+                    .as_ref()
+                    .is_none_or(|e| document.fetched_at > e.fetched_at)
+            });
+        let Some(document) = document else {
+            return entry;
+        };
+
+        let jwks = match serde_json::from_value::<PublicJsonWebKeySet>(document.document) {
+            Ok(jwks) => jwks,
+            Err(e) => {
+                tracing::warn!(%jwks_uri, error = &e as &dyn std::error::Error, "Ignoring invalid JWKS from the database");
+                return entry;
+            }
+        };
+
+        let entry = CacheEntry::new(Arc::new(jwks), document.fetched_at);
+        self.jwks_cache
+            .write()
+            .await
+            .insert(jwks_uri.clone(), entry.clone());
+        Some(entry)
+    }
+
+    /// Load a document from the database, if the cache is persisted
+    async fn load(
+        &self,
+        kind: UpstreamOAuthCachedDocumentKind,
+        url: &str,
+    ) -> Option<UpstreamOAuthCachedDocument> {
+        let repository_factory = self.repository_factory.as_ref()?;
+
+        let document = match repository_factory.create().await {
+            Ok(mut repo) => {
+                repo.upstream_oauth_cached_document()
+                    .lookup(kind, url)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        match document {
+            Ok(document) => document,
+            Err(e) => {
+                tracing::warn!(%kind, %url, error = &e as &dyn std::error::Error, "Failed to load the cached document from the database");
+                None
+            }
+        }
+    }
+
+    /// Save a freshly fetched document in the database, if the cache is
+    /// persisted
+    async fn persist(
+        &self,
+        kind: UpstreamOAuthCachedDocumentKind,
+        url: &str,
+        document: &impl serde::Serialize,
+    ) {
+        let Some(repository_factory) = &self.repository_factory else {
+            return;
+        };
+
+        let document = match serde_json::to_value(document) {
+            Ok(document) => document,
+            Err(e) => {
+                tracing::warn!(%kind, %url, error = &e as &dyn std::error::Error, "Failed to serialize the document to cache");
+                return;
+            }
+        };
+
+        let clock = SystemClock::default();
+        let result = async {
+            let mut repo = repository_factory.create().await?;
+            repo.upstream_oauth_cached_document()
+                .save(&clock, kind, url, document)
+                .await?;
+            repo.save().await
+        }
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!(%kind, %url, error = &e as &dyn std::error::Error, "Failed to save the cached document in the database");
+        }
     }
 
     #[tracing::instrument(name = "metadata_cache.refresh_all", skip_all)]
@@ -285,6 +656,18 @@ impl MetadataCache {
                 tracing::error!(issuer = %issuer, error = &e as &dyn std::error::Error, "Failed to refresh provider metadata");
             }
         }
+
+        // And for the JWKS
+        let keys: Vec<Url> = {
+            let cache = self.jwks_cache.read().await;
+            cache.keys().cloned().collect()
+        };
+
+        for jwks_uri in keys {
+            if let Err(e) = self.fetch_jwks(client, &jwks_uri).await {
+                tracing::error!(%jwks_uri, error = &e as &dyn std::error::Error, "Failed to refresh provider JWKS");
+            }
+        }
     }
 }
 
@@ -300,7 +683,9 @@ mod tests {
     };
     use mas_iana::jose::JsonWebSignatureAlg;
     use mas_storage::{Clock, clock::MockClock};
+    use mas_storage_pg::PgRepositoryFactory;
     use oauth2_types::scope::{OPENID, Scope};
+    use sqlx::PgPool;
     use ulid::Ulid;
     use wiremock::{
         Mock, MockServer, ResponseTemplate,
@@ -373,6 +758,147 @@ mod tests {
         assert_eq!(calls, expected_calls);
     }
 
+    fn discovery_document(issuer: &str) -> serde_json::Value {
+        serde_json::json!({
+            "issuer": issuer,
+            "authorization_endpoint": "https://example.com/authorize",
+            "token_endpoint": "https://example.com/token",
+            "jwks_uri": format!("{issuer}/jwks"),
+            "userinfo_endpoint": "https://example.com/userinfo",
+            "scopes_supported": ["openid"],
+            "response_types_supported": ["code"],
+            "response_modes_supported": ["query", "fragment"],
+            "grant_types_supported": ["authorization_code"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["RS256"],
+        })
+    }
+
+    #[tokio::test]
+    async fn test_metadata_cache_stale_fallback() {
+        setup();
+        let mock_server = MockServer::start().await;
+        let http_client = mas_http::reqwest_client();
+        let issuer = mock_server.uri();
+        let jwks_uri: Url = format!("{issuer}/jwks").parse().unwrap();
+
+        // Documents expire right away, but can be used for a day after that
+        let cache = MetadataCache::new().with_settings(MetadataCacheSettings {
+            discovery_ttl: Duration::zero(),
+            jwks_ttl: Duration::zero(),
+            max_stale_age: Duration::days(1),
+        });
+
+        let discovery_guard = Mock::given(method("GET"))
+            .and(path("/.well-known/openid-configuration"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(discovery_document(&issuer)))
+            .expect(2)
+            .mount_as_scoped(&mock_server)
+            .await;
+        let jwks_guard = Mock::given(method("GET"))
+            .and(path("/jwks"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "keys": [] })),
+            )
+            .expect(2)
+            .mount_as_scoped(&mock_server)
+            .await;
+
+        // Expired documents are fetched again
+        cache.get(&http_client, &issuer, false).await.unwrap();
+        cache.get(&http_client, &issuer, false).await.unwrap();
+        cache.jwks(&http_client, &jwks_uri, None).await.unwrap();
+        cache.jwks(&http_client, &jwks_uri, None).await.unwrap();
+
+        drop(discovery_guard);
+        drop(jwks_guard);
+
+        // The provider is unreachable, so the expired documents are used
+        let metadata = cache.get(&http_client, &issuer, false).await.unwrap();
+        assert_eq!(
+            metadata.authorization_endpoint().as_str(),
+            "https://example.com/authorize"
+        );
+        cache.jwks(&http_client, &jwks_uri, None).await.unwrap();
+
+        // Unless they are too old
+        let cache = cache.with_settings(MetadataCacheSettings {
+            discovery_ttl: Duration::zero(),
+            jwks_ttl: Duration::zero(),
+            max_stale_age: Duration::zero(),
+        });
+        cache.get(&http_client, &issuer, false).await.unwrap_err();
+        cache.jwks(&http_client, &jwks_uri, None).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_jwks_cache() {
+        setup();
+        let mock_server = MockServer::start().await;
+        let http_client = mas_http::reqwest_client();
+        let jwks_uri: Url = format!("{}/jwks", mock_server.uri()).parse().unwrap();
+
+        let cache = MetadataCache::new();
+
+        let _mock_guard = Mock::given(method("GET"))
+            .and(path("/jwks"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "keys": [] })),
+            )
+            .expect(1)
+            .mount_as_scoped(&mock_server)
+            .await;
+
+        // The JWKS is only fetched once
+        cache.jwks(&http_client, &jwks_uri, None).await.unwrap();
+        cache.jwks(&http_client, &jwks_uri, None).await.unwrap();
+
+        // An unknown key ID doesn't trigger a new fetch right after the last one
+        cache
+            .jwks(&http_client, &jwks_uri, Some("unknown"))
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_metadata_cache_persistence(pool: PgPool) {
+        setup();
+        let mock_server = MockServer::start().await;
+        let http_client = mas_http::reqwest_client();
+        let issuer = mock_server.uri();
+        let jwks_uri: Url = format!("{issuer}/jwks").parse().unwrap();
+        let repository_factory: ArcRepositoryFactory = Arc::new(PgRepositoryFactory::new(pool));
+
+        let discovery_guard = Mock::given(method("GET"))
+            .and(path("/.well-known/openid-configuration"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(discovery_document(&issuer)))
+            .expect(1)
+            .mount_as_scoped(&mock_server)
+            .await;
+        let jwks_guard = Mock::given(method("GET"))
+            .and(path("/jwks"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "keys": [] })),
+            )
+            .expect(1)
+            .mount_as_scoped(&mock_server)
+            .await;
+
+        let cache = MetadataCache::new().with_repository_factory(repository_factory.clone());
+        cache.get(&http_client, &issuer, false).await.unwrap();
+        cache.jwks(&http_client, &jwks_uri, None).await.unwrap();
+
+        drop(discovery_guard);
+        drop(jwks_guard);
+
+        // Another instance, with an empty cache, gets the documents from the
+        // database, even though the provider is unreachable
+        let cache = MetadataCache::new().with_repository_factory(repository_factory);
+        let metadata = cache.get(&http_client, &issuer, false).await.unwrap();
+        assert_eq!(metadata.issuer(), issuer);
+        cache.jwks(&http_client, &jwks_uri, None).await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_lazy_provider_infos() {
         setup();
@@ -419,6 +945,9 @@ mod tests {
             client_id: "client_id".to_owned(),
             encrypted_client_secret: None,
             token_endpoint_signing_alg: None,
+            token_endpoint_signing_key_id: None,
+            tls_client_certificate: None,
+            encrypted_tls_client_key: None,
             token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
             id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
             response_mode: None,
@@ -427,6 +956,11 @@ mod tests {
             claims_imports: UpstreamOAuthProviderClaimsImports::default(),
             additional_authorization_parameters: Vec::new(),
             forward_login_hint: false,
+            forward_prompt: false,
+            forward_acr_values: false,
+            forward_ui_locales: false,
+            store_tokens: false,
+            deprovisioning: None,
         };
 
         // Without any override, it should just use discovery
@@ -14,7 +14,9 @@ use axum::{
 };
 use hyper::StatusCode;
 use mas_axum_utils::{cookies::CookieJar, record_error};
-use mas_data_model::{UpstreamOAuthProvider, UpstreamOAuthProviderResponseMode};
+use mas_data_model::{
+    UpstreamOAuthProvider, UpstreamOAuthProviderResponseMode, UpstreamOAuthProviderTokenAuthMethod,
+};
 use mas_jose::claims::TokenHash;
 use mas_keystore::{Encrypter, Keystore};
 use mas_oidc_client::requests::jose::JwtVerificationData;
@@ -36,12 +38,16 @@ use ulid::Ulid;
 
 use super::{
     UpstreamSessionsCookie,
-    cache::LazyProviderInfos,
+    cache::{LazyProviderInfos, unverified_jwt_kid},
+    circuit_breaker::{CircuitOpenError, ProviderCircuitBreaker},
     client_credentials_for_provider,
     template::{AttributeMappingContext, environment},
+    token_endpoint_http_client,
+    tokens::store_tokens,
 };
 use crate::{
-    METER, PreferredLanguage, impl_from_error_for_route, upstream_oauth2::cache::MetadataCache,
+    Limiter, METER, PreferredLanguage, impl_from_error_for_route,
+    rate_limit::UpstreamOAuth2RequestLimitedError, upstream_oauth2::cache::MetadataCache,
 };
 
 static CALLBACK_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
@@ -137,6 +143,12 @@ pub(crate) enum RouteError {
         expected: UpstreamOAuthProviderResponseMode,
     },
 
+    #[error(transparent)]
+    RateLimited(#[from] UpstreamOAuth2RequestLimitedError),
+
+    #[error(transparent)]
+    CircuitOpen(#[from] CircuitOpenError),
+
     #[error(transparent)]
     Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
 }
@@ -150,6 +162,7 @@ impl_from_error_for_route!(mas_oidc_client::error::IdTokenError);
 impl_from_error_for_route!(mas_oidc_client::error::UserInfoError);
 impl_from_error_for_route!(super::ProviderCredentialsError);
 impl_from_error_for_route!(super::cookie::UpstreamSessionNotFound);
+impl_from_error_for_route!(super::tokens::StoreTokensError);
 
 impl IntoResponse for RouteError {
     fn into_response(self) -> axum::response::Response {
@@ -157,6 +170,9 @@ impl IntoResponse for RouteError {
         let response = match self {
             Self::ProviderNotFound => (StatusCode::NOT_FOUND, "Provider not found").into_response(),
             Self::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found").into_response(),
+            e @ (Self::RateLimited(_) | Self::CircuitOpen(_)) => {
+                (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response()
+            }
             Self::Internal(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
             e => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
         };
@@ -181,6 +197,8 @@ pub(crate) async fn handler(
     State(keystore): State<Keystore>,
     State(client): State<reqwest::Client>,
     State(templates): State<Templates>,
+    State(limiter): State<Limiter>,
+    State(circuit_breaker): State<ProviderCircuitBreaker>,
     method: Method,
     PreferredLanguage(locale): PreferredLanguage,
     cookie_jar: CookieJar,
@@ -295,28 +313,44 @@ pub(crate) async fn handler(
         &encrypter,
     )?;
 
+    let token_client = token_endpoint_http_client(&provider, &client, &encrypter)?;
+
     let redirect_uri = url_builder.upstream_oauth_callback(provider.id);
 
-    let token_response = mas_oidc_client::requests::token::request_access_token(
-        &client,
+    let token_endpoint = lazy_metadata.token_endpoint().await?;
+    limiter.check_upstream_oauth2_request(provider.id)?;
+    circuit_breaker.check(provider.id, clock.now())?;
+    let res = mas_oidc_client::requests::token::request_access_token(
+        &token_client,
         client_credentials,
-        lazy_metadata.token_endpoint().await?,
+        token_endpoint,
         AccessTokenRequest::AuthorizationCode(oauth2_types::requests::AuthorizationCodeGrant {
             code: code.clone(),
             redirect_uri: Some(redirect_uri),
             code_verifier: session.code_challenge_verifier.clone(),
+            authorization_details: None,
+            resource: None,
         }),
         clock.now(),
         &mut rng,
     )
-    .await?;
+    .await;
+    circuit_breaker.record(
+        provider.id,
+        clock.now(),
+        matches!(&res, Err(e) if e.is_provider_failure()),
+    );
+    let token_response = res?;
 
     let mut jwks = None;
+    let mut id_token_claims = None;
 
     let mut context = AttributeMappingContext::new();
     if let Some(id_token) = token_response.id_token.as_ref() {
+        let kid = unverified_jwt_kid(id_token);
         jwks = Some(
-            mas_oidc_client::requests::jose::fetch_jwks(&client, lazy_metadata.jwks_uri().await?)
+            metadata_cache
+                .jwks(&client, lazy_metadata.jwks_uri().await?, kid.as_deref())
                 .await?,
         );
 
@@ -363,6 +397,9 @@ pub(crate) async fn handler(
                 .map_err(mas_oidc_client::error::IdTokenError::from)?;
         }
 
+        // Keep the claims around, so that the session can be found again when
+        // the provider sends a logout token
+        id_token_claims = Some(json!(&claims));
         context = context.with_id_token_claims(claims);
     }
 
@@ -371,42 +408,45 @@ pub(crate) async fn handler(
     }
 
     let userinfo = if provider.fetch_userinfo {
-        Some(json!(match &provider.userinfo_signed_response_alg {
-            Some(signing_algorithm) => {
-                let jwks = match jwks {
-                    Some(jwks) => jwks,
-                    None => {
-                        mas_oidc_client::requests::jose::fetch_jwks(
-                            &client,
-                            lazy_metadata.jwks_uri().await?,
-                        )
-                        .await?
-                    }
-                };
+        // The JWKS is only needed if the userinfo response is signed
+        let jwks = match (&provider.userinfo_signed_response_alg, jwks) {
+            (Some(_), Some(jwks)) => Some(jwks),
+            (Some(_), None) => Some(
+                metadata_cache
+                    .jwks(&client, lazy_metadata.jwks_uri().await?, None)
+                    .await?,
+            ),
+            (None, _) => None,
+        };
 
-                mas_oidc_client::requests::userinfo::fetch_userinfo(
-                    &client,
-                    lazy_metadata.userinfo_endpoint().await?,
-                    token_response.access_token.as_str(),
-                    Some(JwtVerificationData {
-                        issuer: provider.issuer.as_deref(),
-                        jwks: &jwks,
-                        signing_algorithm,
-                        client_id: &provider.client_id,
-                    }),
-                )
-                .await?
-            }
-            None => {
-                mas_oidc_client::requests::userinfo::fetch_userinfo(
-                    &client,
-                    lazy_metadata.userinfo_endpoint().await?,
-                    token_response.access_token.as_str(),
-                    None,
-                )
-                .await?
-            }
-        }))
+        let verification_data = provider
+            .userinfo_signed_response_alg
+            .as_ref()
+            .zip(jwks.as_deref())
+            .map(|(signing_algorithm, jwks)| JwtVerificationData {
+                issuer: provider.issuer.as_deref(),
+                jwks,
+                signing_algorithm,
+                client_id: &provider.client_id,
+            });
+
+        let userinfo_endpoint = lazy_metadata.userinfo_endpoint().await?;
+        limiter.check_upstream_oauth2_request(provider.id)?;
+        circuit_breaker.check(provider.id, clock.now())?;
+        let res = mas_oidc_client::requests::userinfo::fetch_userinfo(
+            &client,
+            userinfo_endpoint,
+            token_response.access_token.as_str(),
+            verification_data,
+        )
+        .await;
+        circuit_breaker.record(
+            provider.id,
+            clock.now(),
+            matches!(&res, Err(e) if e.is_provider_failure()),
+        );
+
+        Some(json!(res?))
     } else {
         None
     };
@@ -439,7 +479,28 @@ pub(crate) async fn handler(
         .find_by_subject(&provider, &subject)
         .await?;
 
+    let mut extra_callback_parameters = params.extra_callback_parameters;
+
     let link = if let Some(link) = maybe_link {
+        // If the user didn't finish registering the first time they signed in with
+        // Apple, we need to get their name from the previous attempt
+        if provider.token_endpoint_auth_method
+            == UpstreamOAuthProviderTokenAuthMethod::SignInWithApple
+            && link.user_id.is_none()
+        {
+            let previous_session = repo
+                .upstream_oauth_session()
+                .find_latest_for_link(&link)
+                .await?;
+
+            extra_callback_parameters = carry_over_apple_user(
+                extra_callback_parameters,
+                previous_session
+                    .as_ref()
+                    .and_then(|session| session.extra_callback_parameters()),
+            );
+        }
+
         link
     } else {
         // Try to render the human account name if we have one,
@@ -465,6 +526,12 @@ pub(crate) async fn handler(
             .await?
     };
 
+    // Keep the tokens if the provider is configured to, so that integrations
+    // can call its APIs on behalf of the user
+    if provider.store_tokens {
+        store_tokens(&mut repo, &clock, &encrypter, &link, &token_response).await?;
+    }
+
     let session = repo
         .upstream_oauth_session()
         .complete_with_link(
@@ -472,7 +539,8 @@ pub(crate) async fn handler(
             session,
             &link,
             token_response.id_token,
-            params.extra_callback_parameters,
+            id_token_claims,
+            extra_callback_parameters,
             userinfo,
         )
         .await?;
@@ -489,3 +557,54 @@ pub(crate) async fn handler(
     )
         .into_response())
 }
+
+/// Sign in with Apple only sends the name of the user, in the `user` parameter,
+/// the first time they authorize the client. This copies it from the
+/// parameters of a previous callback if the current one doesn't have it.
+fn carry_over_apple_user(
+    extra_callback_parameters: Option<serde_json::Value>,
+    previous_extra_callback_parameters: Option<&serde_json::Value>,
+) -> Option<serde_json::Value> {
+    let Some(user) = previous_extra_callback_parameters.and_then(|previous| previous.get("user"))
+    else {
+        return extra_callback_parameters;
+    };
+
+    match extra_callback_parameters {
+        Some(serde_json::Value::Object(mut parameters)) => {
+            parameters.entry("user").or_insert_with(|| user.clone());
+            Some(serde_json::Value::Object(parameters))
+        }
+        None => Some(json!({ "user": user })),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::carry_over_apple_user;
+
+    #[test]
+    fn test_carry_over_apple_user() {
+        let previous = json!({ "user": "{\"name\":{\"firstName\":\"John\"}}" });
+
+        // The user is copied from the previous callback
+        let parameters = carry_over_apple_user(Some(json!({})), Some(&previous));
+        assert_eq!(parameters, Some(previous.clone()));
+        let parameters = carry_over_apple_user(None, Some(&previous));
+        assert_eq!(parameters, Some(previous.clone()));
+
+        // The user from the current callback wins
+        let current = json!({ "user": "{\"name\":{\"firstName\":\"Johnny\"}}" });
+        let parameters = carry_over_apple_user(Some(current.clone()), Some(&previous));
+        assert_eq!(parameters, Some(current));
+
+        // Nothing to copy
+        let parameters = carry_over_apple_user(Some(json!({})), Some(&json!({})));
+        assert_eq!(parameters, Some(json!({})));
+        let parameters = carry_over_apple_user(None, None);
+        assert_eq!(parameters, None);
+    }
+}
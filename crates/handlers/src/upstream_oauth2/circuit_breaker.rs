@@ -0,0 +1,233 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Circuit breaker around the requests to the upstream OAuth 2.0 providers
+//!
+//! When a provider can't be reached, waiting for each request to time out
+//! stalls the login of its users and ties up outbound connections. After too
+//! many failures in a row, requests to the provider are rejected right away
+//! for a while. After that, a single request is let through to check whether
+//! the provider recovered.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use opentelemetry::{Key, KeyValue, metrics::Gauge};
+use thiserror::Error;
+use ulid::Ulid;
+
+use crate::METER;
+
+static CIRCUIT_OPEN_GAUGE: LazyLock<Gauge<u64>> = LazyLock::new(|| {
+    METER
+        .u64_gauge("mas.upstream_oauth2.provider.circuit_open")
+        .with_description(
+            "Whether requests to the upstream provider are suspended because it failed too many times in a row",
+        )
+        .build()
+});
+
+const PROVIDER: Key = Key::from_static_str("provider");
+
+/// Settings of the circuit breaker
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerSettings {
+    /// Whether requests to failing providers are suspended
+    pub enabled: bool,
+
+    /// How many requests to a provider have to fail in a row before requests
+    /// to it are suspended
+    pub failure_threshold: u32,
+
+    /// How long requests to a failing provider are suspended
+    pub open_duration: Duration,
+}
+
+impl Default for CircuitBreakerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_threshold: 5,
+            open_duration: Duration::seconds(30),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error(
+    "Requests to upstream OAuth 2.0 provider {provider_id} are suspended until {until} because it keeps failing"
+)]
+pub struct CircuitOpenError {
+    pub provider_id: Ulid,
+    pub until: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    /// How many requests failed in a row
+    failures: u32,
+
+    /// When requests to the provider were last suspended, if they are
+    opened_at: Option<DateTime<Utc>>,
+}
+
+/// Keeps track of the failures of the requests to the upstream providers, and
+/// suspends the requests to the providers which keep failing
+///
+/// Like the health of the providers, this is only kept in memory, so each
+/// instance of the service decides on its own.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderCircuitBreaker {
+    settings: CircuitBreakerSettings,
+    circuits: Arc<Mutex<HashMap<Ulid, Circuit>>>,
+}
+
+impl ProviderCircuitBreaker {
+    #[must_use]
+    pub fn new(settings: CircuitBreakerSettings) -> Self {
+        Self {
+            settings,
+            circuits: Arc::default(),
+        }
+    }
+
+    /// Check whether a request can be made to the provider
+    ///
+    /// Once requests were suspended for long enough, a single request is let
+    /// through, and the others are suspended for another period, until the
+    /// result of this request is recorded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if requests to the provider are suspended
+    pub fn check(&self, provider_id: Ulid, now: DateTime<Utc>) -> Result<(), CircuitOpenError> {
+        if !self.settings.enabled {
+            return Ok(());
+        }
+
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.get_mut(&provider_id) else {
+            return Ok(());
+        };
+        let Some(opened_at) = circuit.opened_at else {
+            return Ok(());
+        };
+
+        let until = opened_at + self.settings.open_duration;
+        if now < until {
+            return Err(CircuitOpenError { provider_id, until });
+        }
+
+        circuit.opened_at = Some(now);
+        Ok(())
+    }
+
+    /// Record the result of a request made to the provider
+    ///
+    /// Only failures of the provider itself should be recorded as such, not
+    /// the provider refusing the request.
+    pub fn record(&self, provider_id: Ulid, now: DateTime<Utc>, failed: bool) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        let attributes = [KeyValue::new(PROVIDER, provider_id.to_string())];
+        let mut circuits = self.circuits.lock().unwrap();
+
+        if !failed {
+            let circuit = circuits.remove(&provider_id);
+            if circuit.is_some_and(|circuit| circuit.opened_at.is_some()) {
+                tracing::info!(
+                    upstream_oauth_provider.id = %provider_id,
+                    "Upstream provider recovered, resuming requests to it"
+                );
+            }
+            CIRCUIT_OPEN_GAUGE.record(0, &attributes);
+            return;
+        }
+
+        let circuit = circuits.entry(provider_id).or_default();
+        circuit.failures = circuit.failures.saturating_add(1);
+
+        // A failure while requests are suspended means the request let through
+        // to check the provider failed, so they stay suspended
+        if circuit.opened_at.is_some() || circuit.failures >= self.settings.failure_threshold {
+            if circuit.opened_at.is_none() {
+                tracing::warn!(
+                    upstream_oauth_provider.id = %provider_id,
+                    failures = circuit.failures,
+                    "Upstream provider keeps failing, suspending requests to it"
+                );
+            }
+            circuit.opened_at = Some(now);
+            CIRCUIT_OPEN_GAUGE.record(1, &attributes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mas_storage::{Clock, clock::MockClock};
+
+    use super::*;
+
+    #[test]
+    fn test_circuit_breaker() {
+        let clock = MockClock::default();
+        let breaker = ProviderCircuitBreaker::new(CircuitBreakerSettings {
+            enabled: true,
+            failure_threshold: 3,
+            open_duration: Duration::seconds(30),
+        });
+        let provider = Ulid::nil();
+        let other_provider = Ulid::from_parts(1, 1);
+
+        // Failures interrupted by a success don't open the circuit
+        breaker.record(provider, clock.now(), true);
+        breaker.record(provider, clock.now(), true);
+        breaker.record(provider, clock.now(), false);
+        breaker.record(provider, clock.now(), true);
+        breaker.record(provider, clock.now(), true);
+        assert!(breaker.check(provider, clock.now()).is_ok());
+
+        // The third failure in a row opens it
+        breaker.record(provider, clock.now(), true);
+        let error = breaker.check(provider, clock.now()).unwrap_err();
+        assert_eq!(error.until, clock.now() + Duration::seconds(30));
+
+        // Other providers are not affected
+        assert!(breaker.check(other_provider, clock.now()).is_ok());
+
+        // After a while, a single request is let through
+        clock.advance(Duration::seconds(30));
+        assert!(breaker.check(provider, clock.now()).is_ok());
+        assert!(breaker.check(provider, clock.now()).is_err());
+
+        // If it fails, the circuit stays open
+        breaker.record(provider, clock.now(), true);
+        clock.advance(Duration::seconds(10));
+        assert!(breaker.check(provider, clock.now()).is_err());
+
+        // If it succeeds, the circuit is closed again
+        clock.advance(Duration::seconds(20));
+        assert!(breaker.check(provider, clock.now()).is_ok());
+        breaker.record(provider, clock.now(), false);
+        assert!(breaker.check(provider, clock.now()).is_ok());
+        assert!(breaker.check(provider, clock.now()).is_ok());
+
+        // Nothing is ever suspended when it is disabled
+        let breaker = ProviderCircuitBreaker::new(CircuitBreakerSettings {
+            enabled: false,
+            ..CircuitBreakerSettings::default()
+        });
+        for _ in 0..10 {
+            breaker.record(provider, clock.now(), true);
+        }
+        assert!(breaker.check(provider, clock.now()).is_ok());
+    }
+}
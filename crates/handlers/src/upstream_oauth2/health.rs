@@ -0,0 +1,552 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Periodic health checks of the upstream OAuth 2.0 providers
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use der::{
+    Decode as _, Reader as _, SliceReader, Tag,
+    asn1::{AnyRef, GeneralizedTime, UtcTime},
+};
+use mas_context::LogContext;
+use mas_data_model::{UpstreamOAuthProvider, UpstreamOAuthProviderDiscoveryMode};
+use mas_http::RequestBuilderExt as _;
+use mas_jose::jwk::PublicJsonWebKeySet;
+use mas_oidc_client::error::DiscoveryError;
+use mas_storage::{
+    BoxRepositoryFactory, Clock, RepositoryAccess, SystemClock,
+    upstream_oauth2::UpstreamOAuthProviderRepository,
+};
+use opentelemetry::{Key, KeyValue, metrics::Gauge};
+use tokio::sync::RwLock;
+use ulid::Ulid;
+use url::Url;
+
+use super::cache::{LazyProviderInfos, MetadataCache};
+use crate::METER;
+
+static REACHABLE_GAUGE: LazyLock<Gauge<u64>> = LazyLock::new(|| {
+    METER
+        .u64_gauge("mas.upstream_oauth2.provider.reachable")
+        .with_description(
+            "Whether the upstream provider could be reached during its last health check",
+        )
+        .build()
+});
+
+static JWKS_STALE_GAUGE: LazyLock<Gauge<u64>> = LazyLock::new(|| {
+    METER
+        .u64_gauge("mas.upstream_oauth2.provider.jwks_stale")
+        .with_description(
+            "Whether the JWKS of the upstream provider couldn't be fetched for too long",
+        )
+        .build()
+});
+
+static CERTIFICATE_EXPIRY_GAUGE: LazyLock<Gauge<i64>> = LazyLock::new(|| {
+    METER
+        .i64_gauge("mas.upstream_oauth2.provider.certificate_expiry")
+        .with_description("Time left before the TLS certificate of the upstream provider expires")
+        .with_unit("s")
+        .build()
+});
+
+const PROVIDER: Key = Key::from_static_str("provider");
+
+/// Settings of the provider health checks
+#[derive(Debug, Clone)]
+pub struct ProviderHealthSettings {
+    /// Time between two checks of the providers
+    pub interval: std::time::Duration,
+
+    /// How long before its expiry a certificate is reported as expiring
+    pub certificate_expiry_warning: Duration,
+
+    /// How long the JWKS of a provider can fail to be fetched before it is
+    /// reported as stale
+    pub jwks_max_age: Duration,
+
+    /// Whether unhealthy providers are shown as unavailable on the login page
+    pub disable_unhealthy_providers: bool,
+}
+
+impl Default for ProviderHealthSettings {
+    fn default() -> Self {
+        Self {
+            interval: std::time::Duration::from_secs(5 * 60),
+            certificate_expiry_warning: Duration::days(14),
+            jwks_max_age: Duration::days(1),
+            disable_unhealthy_providers: false,
+        }
+    }
+}
+
+/// The result of the last health check of an upstream provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderHealth {
+    /// When the provider was last checked
+    pub checked_at: DateTime<Utc>,
+
+    /// Whether the discovery document could be fetched, `None` if discovery
+    /// is disabled for the provider
+    pub discovery_reachable: Option<bool>,
+
+    /// Whether the JWKS could be fetched, `None` if the provider has no JWKS
+    pub jwks_reachable: Option<bool>,
+
+    /// When the JWKS was last fetched successfully
+    pub jwks_fetched_at: Option<DateTime<Utc>>,
+
+    /// Whether the JWKS couldn't be fetched for longer than allowed
+    pub jwks_stale: bool,
+
+    /// When the TLS certificate served by the provider expires, if known
+    pub certificate_expires_at: Option<DateTime<Utc>>,
+
+    /// Whether the TLS certificate served by the provider expires soon
+    pub certificate_expiring: bool,
+
+    /// The error which made the last check fail, if any
+    pub error: Option<String>,
+}
+
+impl ProviderHealth {
+    /// Whether all the endpoints of the provider could be reached
+    #[must_use]
+    pub fn reachable(&self) -> bool {
+        self.discovery_reachable != Some(false) && self.jwks_reachable != Some(false)
+    }
+
+    /// Whether users can be expected to log in successfully with the provider
+    #[must_use]
+    pub fn is_healthy(&self) -> bool {
+        self.reachable() && !self.jwks_stale
+    }
+}
+
+/// Keeps track of the health of the upstream providers, as found by periodic
+/// checks of their discovery document and JWKS
+///
+/// The health of the providers is only kept in memory, so each instance of the
+/// service checks the providers on its own.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderHealthTracker {
+    settings: ProviderHealthSettings,
+    health: Arc<RwLock<HashMap<Ulid, ProviderHealth>>>,
+}
+
+impl ProviderHealthTracker {
+    #[must_use]
+    pub fn new(settings: ProviderHealthSettings) -> Self {
+        Self {
+            settings,
+            health: Arc::default(),
+        }
+    }
+
+    /// Get the result of the last health check of the given provider, if it
+    /// was checked yet
+    pub async fn get(&self, provider_id: Ulid) -> Option<ProviderHealth> {
+        self.health.read().await.get(&provider_id).cloned()
+    }
+
+    /// Get the IDs of the providers which should be shown as unavailable on
+    /// the login page
+    pub async fn unavailable_providers(&self) -> Vec<Ulid> {
+        if !self.settings.disable_unhealthy_providers {
+            return Vec::new();
+        }
+
+        self.health
+            .read()
+            .await
+            .iter()
+            .filter(|(_, health)| !health.is_healthy())
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Spawn a background task which checks all the enabled providers at the
+    /// configured interval
+    pub fn run(
+        &self,
+        client: reqwest::Client,
+        metadata_cache: MetadataCache,
+        repository_factory: BoxRepositoryFactory,
+    ) -> tokio::task::JoinHandle<()> {
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            loop {
+                LogContext::new("upstream-provider-health-checks")
+                    .run(|| tracker.check_all(&client, &metadata_cache, &repository_factory))
+                    .await;
+
+                tokio::time::sleep(tracker.settings.interval).await;
+            }
+        })
+    }
+
+    #[tracing::instrument(name = "upstream_oauth2.health.check_all", skip_all)]
+    async fn check_all(
+        &self,
+        client: &reqwest::Client,
+        metadata_cache: &MetadataCache,
+        repository_factory: &BoxRepositoryFactory,
+    ) {
+        let providers = match repository_factory.create().await {
+            Ok(mut repo) => repo.upstream_oauth_provider().all_enabled().await,
+            Err(e) => Err(e),
+        };
+
+        let providers = match providers {
+            Ok(providers) => providers,
+            Err(e) => {
+                tracing::error!(
+                    error = &e as &dyn std::error::Error,
+                    "Failed to list the upstream providers to check"
+                );
+                return;
+            }
+        };
+
+        // Forget about the providers which were disabled or removed
+        self.health
+            .write()
+            .await
+            .retain(|id, _| providers.iter().any(|provider| provider.id == *id));
+
+        let clock = SystemClock::default();
+        for provider in &providers {
+            let health = self
+                .check(client, metadata_cache, provider, clock.now())
+                .await;
+
+            if !health.is_healthy() {
+                tracing::warn!(
+                    upstream_oauth_provider.id = %provider.id,
+                    error = health.error.as_deref(),
+                    "Upstream provider is unhealthy"
+                );
+            }
+        }
+    }
+
+    /// Check the health of a provider right away, and record the result
+    #[tracing::instrument(
+        name = "upstream_oauth2.health.check",
+        fields(upstream_oauth_provider.id = %provider.id),
+        skip_all,
+    )]
+    pub async fn check(
+        &self,
+        client: &reqwest::Client,
+        metadata_cache: &MetadataCache,
+        provider: &UpstreamOAuthProvider,
+        now: DateTime<Utc>,
+    ) -> ProviderHealth {
+        let previous = self.get(provider.id).await;
+        let mut error = None;
+
+        // Fetch the discovery document again, which also refreshes the cache
+        let discovery_reachable = match (&provider.discovery_mode, &provider.issuer) {
+            (UpstreamOAuthProviderDiscoveryMode::Disabled, _) => None,
+            (_, None) => {
+                error = Some(DiscoveryError::MissingIssuer.to_string());
+                Some(false)
+            }
+            (mode, Some(issuer)) => {
+                let verify = matches!(mode, UpstreamOAuthProviderDiscoveryMode::Oidc);
+                match metadata_cache.fetch(client, issuer, verify).await {
+                    Ok(_) => Some(true),
+                    Err(e) => {
+                        error = Some(e.to_string());
+                        Some(false)
+                    }
+                }
+            }
+        };
+
+        // Providers without discovery nor JWKS URI don't have any JWKS to fetch
+        let mut lazy_metadata = LazyProviderInfos::new(metadata_cache, provider, client);
+        let jwks_uri = match lazy_metadata.jwks_uri().await {
+            Ok(jwks_uri) => Some(jwks_uri.clone()),
+            Err(DiscoveryError::Disabled) => None,
+            Err(e) => {
+                error.get_or_insert_with(|| e.to_string());
+                None
+            }
+        };
+
+        let mut certificate_expires_at = None;
+        let (jwks_reachable, jwks_fetched_at) = if let Some(jwks_uri) = jwks_uri {
+            match fetch_jwks(client, &jwks_uri).await {
+                Ok(expires_at) => {
+                    certificate_expires_at = expires_at;
+                    (Some(true), Some(now))
+                }
+                Err(e) => {
+                    error.get_or_insert_with(|| e.to_string());
+                    let fetched_at = previous.and_then(|health| health.jwks_fetched_at);
+                    (Some(false), fetched_at)
+                }
+            }
+        } else if discovery_reachable == Some(false) {
+            // We couldn't even find out where the JWKS is
+            (
+                Some(false),
+                previous.and_then(|health| health.jwks_fetched_at),
+            )
+        } else {
+            (None, None)
+        };
+
+        let jwks_stale = jwks_reachable.is_some()
+            && jwks_fetched_at
+                .is_none_or(|fetched_at| now - fetched_at > self.settings.jwks_max_age);
+
+        let certificate_expiring = certificate_expires_at
+            .is_some_and(|expires_at| expires_at - now <= self.settings.certificate_expiry_warning);
+
+        let health = ProviderHealth {
+            checked_at: now,
+            discovery_reachable,
+            jwks_reachable,
+            jwks_fetched_at,
+            jwks_stale,
+            certificate_expires_at,
+            certificate_expiring,
+            error,
+        };
+
+        let attributes = [KeyValue::new(PROVIDER, provider.id.to_string())];
+        REACHABLE_GAUGE.record(u64::from(health.reachable()), &attributes);
+        JWKS_STALE_GAUGE.record(u64::from(health.jwks_stale), &attributes);
+        if let Some(expires_at) = health.certificate_expires_at {
+            CERTIFICATE_EXPIRY_GAUGE.record((expires_at - now).num_seconds(), &attributes);
+        }
+
+        self.health
+            .write()
+            .await
+            .insert(provider.id, health.clone());
+
+        health
+    }
+}
+
+/// Fetch the JWKS of a provider, and return when the TLS certificate it was
+/// served with expires, if it was served over TLS
+async fn fetch_jwks(
+    client: &reqwest::Client,
+    jwks_uri: &Url,
+) -> Result<Option<DateTime<Utc>>, reqwest::Error> {
+    let response = client
+        .get(jwks_uri.as_str())
+        .send_traced()
+        .await?
+        .error_for_status()?;
+
+    let certificate_expires_at = certificate_expiry(&response);
+
+    // Make sure the JWKS is actually valid
+    let _jwks: PublicJsonWebKeySet = response.json().await?;
+
+    Ok(certificate_expires_at)
+}
+
+/// Get when the certificate the server presented for this response expires
+fn certificate_expiry(response: &reqwest::Response) -> Option<DateTime<Utc>> {
+    let certificate = response
+        .extensions()
+        .get::<reqwest::tls::TlsInfo>()?
+        .peer_certificate()?;
+
+    match certificate_not_after(certificate) {
+        Ok(not_after) => Some(not_after.into()),
+        Err(e) => {
+            tracing::warn!(
+                error = &e as &dyn std::error::Error,
+                "Failed to parse the TLS certificate of the upstream provider"
+            );
+            None
+        }
+    }
+}
+
+/// Find the end of the validity period of a DER-encoded X.509 certificate
+fn certificate_not_after(certificate: &[u8]) -> Result<SystemTime, der::Error> {
+    // Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm, signature }
+    let certificate = AnyRef::from_der(certificate)?;
+    certificate.tag().assert_eq(Tag::Sequence)?;
+    let mut reader = SliceReader::new(certificate.value())?;
+    let tbs_certificate = AnyRef::decode(&mut reader)?;
+    tbs_certificate.tag().assert_eq(Tag::Sequence)?;
+
+    // TBSCertificate ::= SEQUENCE { [0] version OPTIONAL, serialNumber,
+    //                               signature, issuer, validity, ... }
+    let mut reader = SliceReader::new(tbs_certificate.value())?;
+    if reader.peek_tag()?.is_context_specific() {
+        AnyRef::decode(&mut reader)?;
+    }
+    for _ in 0..3 {
+        AnyRef::decode(&mut reader)?;
+    }
+    let validity = AnyRef::decode(&mut reader)?;
+    validity.tag().assert_eq(Tag::Sequence)?;
+
+    // Validity ::= SEQUENCE { notBefore Time, notAfter Time }
+    let mut reader = SliceReader::new(validity.value())?;
+    AnyRef::decode(&mut reader)?;
+    let not_after = AnyRef::decode(&mut reader)?;
+    match not_after.tag() {
+        Tag::UtcTime => Ok(not_after.decode_as::<UtcTime>()?.to_system_time()),
+        Tag::GeneralizedTime => Ok(not_after.decode_as::<GeneralizedTime>()?.to_system_time()),
+        tag => Err(tag.value_error()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mas_data_model::{
+        UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderPkceMode,
+        UpstreamOAuthProviderTokenAuthMethod,
+    };
+    use mas_iana::jose::JsonWebSignatureAlg;
+    use mas_storage::clock::MockClock;
+    use oauth2_types::scope::{OPENID, Scope};
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{method, path},
+    };
+
+    use super::*;
+    use crate::test_utils::setup;
+
+    fn provider(issuer: String) -> UpstreamOAuthProvider {
+        let clock = MockClock::default();
+        UpstreamOAuthProvider {
+            id: Ulid::nil(),
+            issuer: Some(issuer),
+            human_name: None,
+            brand_name: None,
+            discovery_mode: UpstreamOAuthProviderDiscoveryMode::Insecure,
+            pkce_mode: UpstreamOAuthProviderPkceMode::Auto,
+            fetch_userinfo: false,
+            userinfo_signed_response_alg: None,
+            jwks_uri_override: None,
+            authorization_endpoint_override: None,
+            scope: Scope::from_iter([OPENID]),
+            userinfo_endpoint_override: None,
+            token_endpoint_override: None,
+            client_id: "client_id".to_owned(),
+            encrypted_client_secret: None,
+            token_endpoint_signing_alg: None,
+            token_endpoint_signing_key_id: None,
+            tls_client_certificate: None,
+            encrypted_tls_client_key: None,
+            token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+            id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+            response_mode: None,
+            created_at: clock.now(),
+            disabled_at: None,
+            claims_imports: UpstreamOAuthProviderClaimsImports::default(),
+            additional_authorization_parameters: Vec::new(),
+            forward_login_hint: false,
+            forward_prompt: false,
+            forward_acr_values: false,
+            forward_ui_locales: false,
+            store_tokens: false,
+            deprovisioning: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_provider_health() {
+        setup();
+        let mock_server = MockServer::start().await;
+        let http_client = mas_http::reqwest_client();
+        let metadata_cache = MetadataCache::new();
+        let tracker = ProviderHealthTracker::new(ProviderHealthSettings {
+            disable_unhealthy_providers: true,
+            ..ProviderHealthSettings::default()
+        });
+        let clock = MockClock::default();
+        let provider = provider(mock_server.uri());
+
+        // Nothing is served yet, so the provider is unreachable
+        let health = tracker
+            .check(&http_client, &metadata_cache, &provider, clock.now())
+            .await;
+        assert_eq!(health.discovery_reachable, Some(false));
+        assert_eq!(health.jwks_reachable, Some(false));
+        assert!(health.jwks_stale);
+        assert!(!health.is_healthy());
+        assert!(health.error.is_some());
+        assert_eq!(tracker.unavailable_providers().await, vec![provider.id]);
+
+        let _discovery_guard = Mock::given(method("GET"))
+            .and(path("/.well-known/openid-configuration"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "issuer": mock_server.uri(),
+                "authorization_endpoint": "https://example.com/authorize",
+                "token_endpoint": "https://example.com/token",
+                "jwks_uri": format!("{}/jwks", mock_server.uri()),
+                "userinfo_endpoint": "https://example.com/userinfo",
+                "scopes_supported": ["openid"],
+                "response_types_supported": ["code"],
+                "response_modes_supported": ["query", "fragment"],
+                "grant_types_supported": ["authorization_code"],
+                "subject_types_supported": ["public"],
+                "id_token_signing_alg_values_supported": ["RS256"],
+            })))
+            .mount_as_scoped(&mock_server)
+            .await;
+        let jwks_guard = Mock::given(method("GET"))
+            .and(path("/jwks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "keys": [],
+            })))
+            .mount_as_scoped(&mock_server)
+            .await;
+
+        // Once everything is served, the provider is healthy
+        let health = tracker
+            .check(&http_client, &metadata_cache, &provider, clock.now())
+            .await;
+        assert_eq!(health.discovery_reachable, Some(true));
+        assert_eq!(health.jwks_reachable, Some(true));
+        assert_eq!(health.jwks_fetched_at, Some(clock.now()));
+        assert!(!health.jwks_stale);
+        // Not served over TLS, so there is no certificate to check
+        assert_eq!(health.certificate_expires_at, None);
+        assert!(health.is_healthy());
+        assert!(tracker.unavailable_providers().await.is_empty());
+        assert_eq!(tracker.get(provider.id).await, Some(health));
+
+        // If the JWKS goes away, the provider is unreachable, but the JWKS only
+        // becomes stale after a while
+        drop(jwks_guard);
+        clock.advance(Duration::hours(1));
+        let health = tracker
+            .check(&http_client, &metadata_cache, &provider, clock.now())
+            .await;
+        assert_eq!(health.discovery_reachable, Some(true));
+        assert_eq!(health.jwks_reachable, Some(false));
+        assert!(!health.jwks_stale);
+        assert!(!health.reachable());
+
+        clock.advance(Duration::days(1));
+        let health = tracker
+            .check(&http_client, &metadata_cache, &provider, clock.now())
+            .await;
+        assert!(health.jwks_stale);
+    }
+}
@@ -19,22 +19,28 @@ use mas_axum_utils::{
     csrf::{CsrfExt, ProtectedForm},
     record_error,
 };
+use mas_data_model::{
+    UpstreamOAuthAuthorizationSession, UpstreamOAuthLink, UpstreamOAuthLinkImportedAttributes,
+    UpstreamOAuthProvider, UpstreamOAuthProviderRolePreference, User,
+};
 use mas_jose::jwt::Jwt;
 use mas_matrix::HomeserverConnection;
 use mas_policy::Policy;
 use mas_router::UrlBuilder;
 use mas_storage::{
-    BoxClock, BoxRepository, BoxRng, RepositoryAccess,
-    queue::{ProvisionUserJob, QueueJobRepositoryExt as _},
+    BoxClock, BoxRepository, BoxRng, Clock, RepositoryAccess,
+    queue::{ProvisionUserJob, QueueJobRepositoryExt as _, SyncUpstreamAttributesJob},
     upstream_oauth2::{UpstreamOAuthLinkRepository, UpstreamOAuthSessionRepository},
     user::{BrowserSessionRepository, UserEmailRepository, UserRepository},
 };
 use mas_templates::{
     AccountInactiveContext, ErrorContext, FieldError, FormError, TemplateContext, Templates,
-    ToFormState, UpstreamExistingLinkContext, UpstreamRegister, UpstreamSuggestLink,
+    ToFormState, UpstreamEmailDomainNotAllowedContext, UpstreamExistingLinkContext,
+    UpstreamLoginDeniedContext, UpstreamRegister, UpstreamSuggestLink,
 };
-use minijinja::Environment;
+use minijinja::{Environment, value::ValueKind};
 use opentelemetry::{Key, KeyValue, metrics::Counter};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::warn;
@@ -45,8 +51,11 @@ use super::{
     template::{AttributeMappingContext, environment},
 };
 use crate::{
-    BoundActivityTracker, METER, PreferredLanguage, SiteConfig, impl_from_error_for_route,
-    views::shared::OptionalPostAuthAction,
+    BoundActivityTracker, METER, PreferredLanguage, SiteConfig, impl_from_error_for_route, terms,
+    views::{
+        login::{LoginMethod, LoginSession, finish_login},
+        shared::OptionalPostAuthAction,
+    },
 };
 
 static LOGIN_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
@@ -68,6 +77,7 @@ const PROVIDER: Key = Key::from_static_str("provider");
 const DEFAULT_LOCALPART_TEMPLATE: &str = "{{ user.preferred_username }}";
 const DEFAULT_DISPLAYNAME_TEMPLATE: &str = "{{ user.name }}";
 const DEFAULT_EMAIL_TEMPLATE: &str = "{{ user.email }}";
+const DEFAULT_AVATAR_TEMPLATE: &str = "{{ user.picture }}";
 
 #[derive(Debug, Error)]
 pub(crate) enum RouteError {
@@ -194,6 +204,257 @@ fn render_attribute_template(
     }
 }
 
+/// Build the context used to render the attribute templates from what the
+/// upstream provider gave us in this session
+fn attribute_mapping_context(
+    upstream_session: &UpstreamOAuthAuthorizationSession,
+) -> Result<minijinja::Value, RouteError> {
+    let id_token = upstream_session.id_token().map(Jwt::try_from).transpose()?;
+
+    let mut context = AttributeMappingContext::new();
+    if let Some(id_token) = id_token {
+        let (_, payload) = id_token.into_parts();
+        context = context.with_id_token_claims(payload);
+    }
+    if let Some(extra_callback_parameters) = upstream_session.extra_callback_parameters() {
+        context = context.with_extra_callback_parameters(extra_callback_parameters.clone());
+    }
+    if let Some(userinfo) = upstream_session.userinfo() {
+        context = context.with_userinfo_claims(userinfo.clone());
+    }
+    Ok(context.build())
+}
+
+/// Check that the upstream provider is allowed to assert the email address it
+/// gave in this session, according to its email domain restrictions.
+///
+/// Returns the context of the page to show if it isn't, in which case the login
+/// must be rejected.
+fn check_email_domain(
+    provider: &UpstreamOAuthProvider,
+    upstream_session: &UpstreamOAuthAuthorizationSession,
+) -> Result<Option<UpstreamEmailDomainNotAllowedContext>, RouteError> {
+    let email_domains = &provider.claims_imports.email_domains;
+    if !email_domains.is_restricted() {
+        return Ok(None);
+    }
+
+    let template = provider
+        .claims_imports
+        .email
+        .template
+        .as_deref()
+        .unwrap_or(DEFAULT_EMAIL_TEMPLATE);
+    let context = attribute_mapping_context(upstream_session)?;
+    let email = render_attribute_template(&environment(), template, &context, false)?;
+
+    if email_domains.allows(email.as_deref()) {
+        return Ok(None);
+    }
+
+    warn!(
+        upstream_oauth_provider.id = %provider.id,
+        email = email.as_deref(),
+        "Upstream provider asserted an email address in a domain it isn't allowed to"
+    );
+
+    Ok(Some(UpstreamEmailDomainNotAllowedContext::new(email)))
+}
+
+/// Evaluate the upstream login policy against what the upstream provider gave
+/// us in this session.
+///
+/// Returns the context of the page to show if the policy denies the login, in
+/// which case the login must be rejected.
+async fn check_policy(
+    policy: &mut Policy,
+    provider: &UpstreamOAuthProvider,
+    upstream_session: &UpstreamOAuthAuthorizationSession,
+    requester: mas_policy::Requester,
+) -> Result<Option<UpstreamLoginDeniedContext>, RouteError> {
+    let id_token_claims = upstream_session
+        .id_token()
+        .map(Jwt::<serde_json::Map<String, serde_json::Value>>::try_from)
+        .transpose()?
+        .map(|id_token| id_token.into_parts().1);
+
+    let res = policy
+        .evaluate_upstream_login(mas_policy::UpstreamLoginInput {
+            provider_id: provider.id,
+            id_token_claims: id_token_claims.as_ref(),
+            userinfo_claims: upstream_session.userinfo(),
+            requester,
+        })
+        .await?;
+
+    if res.valid() {
+        return Ok(None);
+    }
+
+    warn!(
+        upstream_oauth_provider.id = %provider.id,
+        "Upstream login denied by the policy: {res}"
+    );
+
+    let ctx = res.violations.into_iter().fold(
+        UpstreamLoginDeniedContext::new(provider.clone()),
+        |ctx, violation| {
+            ctx.with_violation(
+                violation.code.map(|code| code.as_str()),
+                violation.field,
+                violation.msg,
+            )
+        },
+    );
+
+    Ok(Some(ctx))
+}
+
+/// Utility function to evaluate a role mapping against the upstream claims.
+///
+/// The expression can evaluate to a single value or to a list of values, and
+/// the role is granted if any of them is in the allowed values. Without allowed
+/// values, the role is granted if the expression evaluates to a truthy value.
+///
+/// Returns `None` if the role isn't managed by the provider, or if the
+/// expression failed to evaluate, in which case the role should be left as is.
+fn evaluate_role(
+    environment: &Environment,
+    preference: &UpstreamOAuthProviderRolePreference,
+    context: &minijinja::Value,
+) -> Option<bool> {
+    let expression = preference.expression.as_deref()?;
+    let value = match environment
+        .compile_expression(expression)
+        .and_then(|compiled| compiled.eval(context))
+    {
+        Ok(value) => value,
+        Err(error) => {
+            tracing::warn!(error = &error as &dyn std::error::Error, %expression, "Error while evaluating role expression");
+            return None;
+        }
+    };
+
+    if preference.allowed_values.is_empty() {
+        return Some(value.is_true());
+    }
+
+    let is_allowed = |value: &minijinja::Value| {
+        preference
+            .allowed_values
+            .iter()
+            .any(|allowed| value.as_str() == Some(allowed.as_str()))
+    };
+
+    let granted = if value.kind() == ValueKind::Seq {
+        value
+            .try_iter()
+            .is_ok_and(|mut values| values.any(|value| is_allowed(&value)))
+    } else {
+        is_allowed(&value)
+    };
+
+    Some(granted)
+}
+
+/// Re-evaluate the properties of the user which are managed by the upstream
+/// provider, like whether they can request admin access
+async fn import_roles(
+    repo: &mut BoxRepository,
+    environment: &Environment<'_>,
+    provider: &UpstreamOAuthProvider,
+    context: &minijinja::Value,
+    user: &User,
+) -> Result<(), RouteError> {
+    let Some(can_request_admin) = evaluate_role(
+        environment,
+        &provider.claims_imports.can_request_admin,
+        context,
+    ) else {
+        return Ok(());
+    };
+
+    if can_request_admin != user.can_request_admin {
+        tracing::info!(
+            user.id = %user.id,
+            upstream_oauth_provider.id = %provider.id,
+            can_request_admin,
+            "Updating the admin permission of the user from the upstream claims"
+        );
+
+        repo.user()
+            .set_can_request_admin(user.clone(), can_request_admin)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Refresh what the upstream provider manages about the user, each time they
+/// log in through it: their roles, and the attributes which are synced on
+/// every login. Attributes are refreshed in the background, as it involves
+/// calling the homeserver and possibly downloading an avatar.
+async fn sync_on_login(
+    repo: &mut BoxRepository,
+    rng: &mut (dyn RngCore + Send),
+    clock: &dyn Clock,
+    link: &UpstreamOAuthLink,
+    upstream_session: &UpstreamOAuthAuthorizationSession,
+    user: &User,
+) -> Result<(), RouteError> {
+    let provider = repo
+        .upstream_oauth_provider()
+        .lookup(link.provider_id)
+        .await?
+        .ok_or(RouteError::ProviderNotFound(link.provider_id))?;
+    let claims_imports = &provider.claims_imports;
+
+    let synced = [
+        (&claims_imports.displayname, DEFAULT_DISPLAYNAME_TEMPLATE),
+        (&claims_imports.email, DEFAULT_EMAIL_TEMPLATE),
+        (&claims_imports.avatar, DEFAULT_AVATAR_TEMPLATE),
+    ]
+    .map(|(preference, default_template)| {
+        (!preference.ignore() && preference.sync.is_always())
+            .then(|| preference.template.as_deref().unwrap_or(default_template))
+    });
+
+    if !claims_imports.can_request_admin.is_managed() && synced.iter().all(Option::is_none) {
+        return Ok(());
+    }
+
+    let env = environment();
+    let context = attribute_mapping_context(upstream_session)?;
+
+    import_roles(repo, &env, &provider, &context, user).await?;
+
+    // Missing attributes are left as they are, instead of failing the login
+    let [display_name, email, avatar_url] = synced.map(|template| {
+        template.and_then(|template| {
+            render_attribute_template(&env, template, &context, false)
+                .ok()
+                .flatten()
+        })
+    });
+
+    let mut job = SyncUpstreamAttributesJob::new(link);
+    if let Some(display_name) = display_name {
+        job = job.with_display_name(display_name);
+    }
+    if let Some(email) = email {
+        job = job.with_email(email);
+    }
+    if let Some(avatar_url) = avatar_url {
+        job = job.with_avatar_url(avatar_url);
+    }
+
+    if !job.is_empty() {
+        repo.queue_job().schedule_job(rng, clock, job).await?;
+    }
+
+    Ok(())
+}
+
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "lowercase", tag = "action")]
 pub(crate) enum FormData {
@@ -227,6 +488,7 @@ pub(crate) async fn get(
     PreferredLanguage(locale): PreferredLanguage,
     State(templates): State<Templates>,
     State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
     State(homeserver): State<Arc<dyn HomeserverConnection>>,
     cookie_jar: CookieJar,
     activity_tracker: BoundActivityTracker,
@@ -265,28 +527,73 @@ pub(crate) async fn get(
         return Err(RouteError::SessionConsumed(session_id));
     }
 
+    let provider = repo
+        .upstream_oauth_provider()
+        .lookup(link.provider_id)
+        .await?
+        .ok_or(RouteError::ProviderNotFound(link.provider_id))?;
+
+    if let Some(ctx) = check_email_domain(&provider, &upstream_session)? {
+        let ctx = ctx.with_language(locale);
+        return Ok((
+            cookie_jar,
+            Html(templates.render_upstream_oauth2_email_domain_not_allowed(&ctx)?).into_response(),
+        ));
+    }
+
+    let requester = mas_policy::Requester {
+        ip_address: activity_tracker.ip(),
+        user_agent: user_agent.clone(),
+    };
+    if let Some(ctx) = check_policy(&mut policy, &provider, &upstream_session, requester).await? {
+        let ctx = ctx.with_language(locale);
+        return Ok((
+            cookie_jar,
+            Html(templates.render_upstream_oauth2_login_denied(&ctx)?).into_response(),
+        ));
+    }
+
     let (user_session_info, cookie_jar) = cookie_jar.session_info();
-    let (csrf_token, mut cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
-    let maybe_user_session = user_session_info.load_active_session(&mut repo).await?;
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
+    let maybe_user_session = user_session_info
+        .load_active_session(&mut repo, &clock, &site_config)
+        .await?;
 
     let response = match (maybe_user_session, link.user_id) {
         (Some(session), Some(user_id)) if session.user.id == user_id => {
             // Session already linked, and link matches the currently logged
             // user. Mark the session as consumed and renew the authentication.
+            sync_on_login(
+                &mut repo,
+                &mut rng,
+                &clock,
+                &link,
+                &upstream_session,
+                &session.user,
+            )
+            .await?;
+
             let upstream_session = repo
                 .upstream_oauth_session()
                 .consume(&clock, upstream_session)
                 .await?;
 
-            repo.browser_session()
-                .authenticate_with_upstream(&mut rng, &clock, &session, &upstream_session)
-                .await?;
-
-            cookie_jar = cookie_jar.set_session(&session);
-
-            repo.save().await?;
+            let (cookie_jar, reply) = finish_login(
+                repo,
+                &mut rng,
+                &clock,
+                &locale,
+                &site_config,
+                &url_builder,
+                &activity_tracker,
+                cookie_jar,
+                &post_auth_action,
+                LoginSession::Started(session),
+                LoginMethod::Upstream(&upstream_session),
+            )
+            .await?;
 
-            post_auth_action.go_next(&url_builder).into_response()
+            return Ok((cookie_jar, reply.into_response()));
         }
 
         (Some(user_session), Some(user_id)) => {
@@ -344,26 +651,31 @@ pub(crate) async fn get(
                 return Ok((cookie_jar, Html(fallback).into_response()));
             }
 
-            let session = repo
-                .browser_session()
-                .add(&mut rng, &clock, &user, user_agent)
-                .await?;
+            sync_on_login(&mut repo, &mut rng, &clock, &link, &upstream_session, &user).await?;
 
             let upstream_session = repo
                 .upstream_oauth_session()
                 .consume(&clock, upstream_session)
                 .await?;
 
-            repo.browser_session()
-                .authenticate_with_upstream(&mut rng, &clock, &session, &upstream_session)
-                .await?;
-
-            cookie_jar = sessions_cookie
+            let cookie_jar = sessions_cookie
                 .consume_link(link_id)?
                 .save(cookie_jar, &clock);
-            cookie_jar = cookie_jar.set_session(&session);
 
-            repo.save().await?;
+            let (cookie_jar, reply) = finish_login(
+                repo,
+                &mut rng,
+                &clock,
+                &locale,
+                &site_config,
+                &url_builder,
+                &activity_tracker,
+                cookie_jar,
+                &post_auth_action,
+                LoginSession::New(&user, user_agent),
+                LoginMethod::Upstream(&upstream_session),
+            )
+            .await?;
 
             LOGIN_COUNTER.add(
                 1,
@@ -373,36 +685,16 @@ pub(crate) async fn get(
                 )],
             );
 
-            post_auth_action.go_next(&url_builder).into_response()
+            return Ok((cookie_jar, reply.into_response()));
         }
 
         (None, None) => {
             // Session not linked and used not logged in: suggest creating an
             // account or logging in an existing user
-            let id_token = upstream_session.id_token().map(Jwt::try_from).transpose()?;
-
-            let provider = repo
-                .upstream_oauth_provider()
-                .lookup(link.provider_id)
-                .await?
-                .ok_or(RouteError::ProviderNotFound(link.provider_id))?;
-
             let ctx = UpstreamRegister::new(link.clone(), provider.clone());
 
             let env = environment();
-
-            let mut context = AttributeMappingContext::new();
-            if let Some(id_token) = id_token {
-                let (_, payload) = id_token.into_parts();
-                context = context.with_id_token_claims(payload);
-            }
-            if let Some(extra_callback_parameters) = upstream_session.extra_callback_parameters() {
-                context = context.with_extra_callback_parameters(extra_callback_parameters.clone());
-            }
-            if let Some(userinfo) = upstream_session.userinfo() {
-                context = context.with_userinfo_claims(userinfo.clone());
-            }
-            let context = context.build();
+            let context = attribute_mapping_context(&upstream_session)?;
 
             let ctx = if provider.claims_imports.displayname.ignore() {
                 ctx
@@ -602,9 +894,39 @@ pub(crate) async fn post(
         return Err(RouteError::SessionConsumed(session_id));
     }
 
+    let provider = repo
+        .upstream_oauth_provider()
+        .lookup(link.provider_id)
+        .await?
+        .ok_or(RouteError::ProviderNotFound(link.provider_id))?;
+
+    if let Some(ctx) = check_email_domain(&provider, &upstream_session)? {
+        let ctx = ctx.with_language(locale);
+        return Ok((
+            cookie_jar,
+            Html(templates.render_upstream_oauth2_email_domain_not_allowed(&ctx)?),
+        )
+            .into_response());
+    }
+
+    let requester = mas_policy::Requester {
+        ip_address: activity_tracker.ip(),
+        user_agent: user_agent.clone(),
+    };
+    if let Some(ctx) = check_policy(&mut policy, &provider, &upstream_session, requester).await? {
+        let ctx = ctx.with_language(locale);
+        return Ok((
+            cookie_jar,
+            Html(templates.render_upstream_oauth2_login_denied(&ctx)?),
+        )
+            .into_response());
+    }
+
     let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
     let (user_session_info, cookie_jar) = cookie_jar.session_info();
-    let maybe_user_session = user_session_info.load_active_session(&mut repo).await?;
+    let maybe_user_session = user_session_info
+        .load_active_session(&mut repo, &clock, &site_config)
+        .await?;
     let form_state = form.to_form_state();
 
     let session = match (maybe_user_session, link.user_id, form) {
@@ -615,6 +937,16 @@ pub(crate) async fn post(
                 .associate_to_user(&link, &session.user)
                 .await?;
 
+            sync_on_login(
+                &mut repo,
+                &mut rng,
+                &clock,
+                &link,
+                &upstream_session,
+                &session.user,
+            )
+            .await?;
+
             session
         }
 
@@ -638,29 +970,9 @@ pub(crate) async fn post(
             let import_display_name = import_display_name.is_some();
             let accept_terms = accept_terms.is_some();
 
-            let id_token = upstream_session.id_token().map(Jwt::try_from).transpose()?;
-
-            let provider = repo
-                .upstream_oauth_provider()
-                .lookup(link.provider_id)
-                .await?
-                .ok_or(RouteError::ProviderNotFound(link.provider_id))?;
-
             // Let's try to import the claims from the ID token
             let env = environment();
-
-            let mut context = AttributeMappingContext::new();
-            if let Some(id_token) = id_token {
-                let (_, payload) = id_token.into_parts();
-                context = context.with_id_token_claims(payload);
-            }
-            if let Some(extra_callback_parameters) = upstream_session.extra_callback_parameters() {
-                context = context.with_extra_callback_parameters(extra_callback_parameters.clone());
-            }
-            if let Some(userinfo) = upstream_session.userinfo() {
-                context = context.with_userinfo_claims(userinfo.clone());
-            }
-            let context = context.build();
+            let context = attribute_mapping_context(&upstream_session)?;
 
             // Create a template context in case we need to re-render because of an error
             let ctx = UpstreamRegister::new(link.clone(), provider.clone());
@@ -720,6 +1032,25 @@ pub(crate) async fn post(
                 ctx
             };
 
+            // Users aren't asked whether they want to import their avatar
+            let avatar_url = if provider.claims_imports.avatar.ignore() {
+                None
+            } else {
+                let template = provider
+                    .claims_imports
+                    .avatar
+                    .template
+                    .as_deref()
+                    .unwrap_or(DEFAULT_AVATAR_TEMPLATE);
+
+                render_attribute_template(
+                    &env,
+                    template,
+                    &context,
+                    provider.claims_imports.avatar.is_required(),
+                )?
+            };
+
             let username = if provider.claims_imports.localpart.is_forced() {
                 let template = provider
                     .claims_imports
@@ -846,8 +1177,19 @@ pub(crate) async fn post(
                 repo.user_terms()
                     .accept_terms(&mut rng, &clock, &user, terms_url.clone())
                     .await?;
+                terms::accept_on_registration(&mut repo, &mut rng, &clock, &site_config, &user)
+                    .await?;
             }
 
+            import_roles(&mut repo, &env, &provider, &context, &user).await?;
+
+            // Remember what we imported, to later tell whether the user changed it
+            let imported_attributes = UpstreamOAuthLinkImportedAttributes {
+                displayname: display_name.clone(),
+                email: email.clone(),
+                ..UpstreamOAuthLinkImportedAttributes::default()
+            };
+
             // And schedule the job to provision it
             let mut job = ProvisionUserJob::new(&user);
 
@@ -868,6 +1210,17 @@ pub(crate) async fn post(
             repo.upstream_oauth_link()
                 .associate_to_user(&link, &user)
                 .await?;
+            let link = repo
+                .upstream_oauth_link()
+                .set_imported_attributes(link, imported_attributes)
+                .await?;
+
+            // The avatar has to be downloaded and uploaded to the homeserver, which
+            // happens in the background
+            if let Some(avatar_url) = avatar_url {
+                let job = SyncUpstreamAttributesJob::new(&link).with_avatar_url(avatar_url);
+                repo.queue_job().schedule_job(&mut rng, &clock, job).await?;
+            }
 
             repo.browser_session()
                 .add(&mut rng, &clock, &user, user_agent)
@@ -882,25 +1235,34 @@ pub(crate) async fn post(
         .consume(&clock, upstream_session)
         .await?;
 
-    repo.browser_session()
-        .authenticate_with_upstream(&mut rng, &clock, &session, &upstream_session)
-        .await?;
-
     let cookie_jar = sessions_cookie
         .consume_link(link_id)?
         .save(cookie_jar, &clock);
-    let cookie_jar = cookie_jar.set_session(&session);
 
-    repo.save().await?;
-
-    Ok((cookie_jar, post_auth_action.go_next(&url_builder)).into_response())
+    let reply = finish_login(
+        repo,
+        &mut rng,
+        &clock,
+        &locale,
+        &site_config,
+        &url_builder,
+        &activity_tracker,
+        cookie_jar,
+        &post_auth_action,
+        LoginSession::Started(session),
+        LoginMethod::Upstream(&upstream_session),
+    )
+    .await?;
+
+    Ok(reply.into_response())
 }
 
 #[cfg(test)]
 mod tests {
     use hyper::{Request, StatusCode, header::CONTENT_TYPE};
     use mas_data_model::{
-        UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderImportPreference,
+        UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderEmailDomains,
+        UpstreamOAuthProviderImportPreference, UpstreamOAuthProviderRolePreference,
         UpstreamOAuthProviderTokenAuthMethod,
     };
     use mas_iana::jose::JsonWebSignatureAlg;
@@ -913,7 +1275,9 @@ mod tests {
     use sqlx::PgPool;
 
     use super::UpstreamSessionsCookie;
-    use crate::test_utils::{CookieHelper, RequestBuilderExt, ResponseExt, TestState, setup};
+    use crate::test_utils::{
+        CookieHelper, RequestBuilderExt, ResponseExt, TestState, policy_factory, setup,
+    };
 
     #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
     async fn test_register(pool: PgPool) {
@@ -926,10 +1290,12 @@ mod tests {
             localpart: UpstreamOAuthProviderImportPreference {
                 action: mas_data_model::UpstreamOAuthProviderImportAction::Force,
                 template: None,
+                ..UpstreamOAuthProviderImportPreference::default()
             },
             email: UpstreamOAuthProviderImportPreference {
                 action: mas_data_model::UpstreamOAuthProviderImportAction::Force,
                 template: None,
+                ..UpstreamOAuthProviderImportPreference::default()
             },
             ..UpstreamOAuthProviderClaimsImports::default()
         };
@@ -969,6 +1335,9 @@ mod tests {
                     scope: Scope::from_iter([OPENID]),
                     token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
                     token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
                     id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
                     client_id: "client".to_owned(),
                     encrypted_client_secret: None,
@@ -984,6 +1353,11 @@ mod tests {
                     response_mode: None,
                     additional_authorization_parameters: Vec::new(),
                     forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: false,
+                    deprovisioning: None,
                     ui_order: 0,
                 },
             )
@@ -1024,6 +1398,7 @@ mod tests {
                 Some(id_token.into_string()),
                 None,
                 None,
+                None,
             )
             .await
             .unwrap();
@@ -1085,6 +1460,11 @@ mod tests {
             .expect("link exists");
 
         assert_eq!(link.user_id, Some(user.id));
+        // What was imported is remembered on the link
+        assert_eq!(
+            link.imported_attributes.email.as_deref(),
+            Some("john@example.com")
+        );
 
         let page = repo
             .user_email()
@@ -1095,4 +1475,687 @@ mod tests {
 
         assert_eq!(email.email, "john@example.com");
     }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_register_without_id_token(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let mut rng = state.rng();
+        let cookies = CookieHelper::new();
+
+        // A plain OAuth 2.0 provider, like GitHub, where everything comes from a
+        // userinfo-style API call
+        let claims_imports = UpstreamOAuthProviderClaimsImports {
+            localpart: UpstreamOAuthProviderImportPreference {
+                action: mas_data_model::UpstreamOAuthProviderImportAction::Force,
+                template: Some("{{ user.login }}".to_owned()),
+                ..UpstreamOAuthProviderImportPreference::default()
+            },
+            email: UpstreamOAuthProviderImportPreference {
+                action: mas_data_model::UpstreamOAuthProviderImportAction::Force,
+                template: Some("{{ userinfo_claims.emails[0].email }}".to_owned()),
+                ..UpstreamOAuthProviderImportPreference::default()
+            },
+            ..UpstreamOAuthProviderClaimsImports::default()
+        };
+
+        let userinfo = serde_json::json!({
+            "id": 1234,
+            "login": "octocat",
+            "emails": [{ "email": "octocat@example.com" }],
+        });
+
+        let mut repo = state.repository().await.unwrap();
+        let provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &state.clock,
+                UpstreamOAuthProviderParams {
+                    issuer: None,
+                    human_name: Some("GitHub".to_owned()),
+                    brand_name: Some("github".to_owned()),
+                    scope: "read:user".parse().unwrap(),
+                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+                    token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
+                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+                    client_id: "client".to_owned(),
+                    encrypted_client_secret: None,
+                    claims_imports,
+                    authorization_endpoint_override: Some(
+                        "https://github.com/login/oauth/authorize".parse().unwrap(),
+                    ),
+                    token_endpoint_override: Some(
+                        "https://github.com/login/oauth/access_token"
+                            .parse()
+                            .unwrap(),
+                    ),
+                    userinfo_endpoint_override: Some(
+                        "https://api.github.com/user".parse().unwrap(),
+                    ),
+                    fetch_userinfo: true,
+                    userinfo_signed_response_alg: None,
+                    jwks_uri_override: None,
+                    discovery_mode: mas_data_model::UpstreamOAuthProviderDiscoveryMode::Disabled,
+                    pkce_mode: mas_data_model::UpstreamOAuthProviderPkceMode::Auto,
+                    response_mode: None,
+                    additional_authorization_parameters: Vec::new(),
+                    forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: false,
+                    deprovisioning: None,
+                    ui_order: 0,
+                },
+            )
+            .await
+            .unwrap();
+
+        let session = repo
+            .upstream_oauth_session()
+            .add(
+                &mut rng,
+                &state.clock,
+                &provider,
+                "state".to_owned(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let link = repo
+            .upstream_oauth_link()
+            .add(&mut rng, &state.clock, &provider, "1234".to_owned(), None)
+            .await
+            .unwrap();
+
+        let session = repo
+            .upstream_oauth_session()
+            .complete_with_link(
+                &state.clock,
+                session,
+                &link,
+                None,
+                None,
+                None,
+                Some(userinfo),
+            )
+            .await
+            .unwrap();
+
+        repo.save().await.unwrap();
+
+        let cookie_jar = state.cookie_jar();
+        let upstream_sessions = UpstreamSessionsCookie::default()
+            .add(session.id, provider.id, "state".to_owned(), None)
+            .add_link_to_session(session.id, link.id)
+            .unwrap();
+        let cookie_jar = upstream_sessions.save(cookie_jar, &state.clock);
+        cookies.import(cookie_jar);
+
+        let request = Request::get(&*mas_router::UpstreamOAuth2Link::new(link.id).path()).empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
+
+        let request = Request::post(&*mas_router::UpstreamOAuth2Link::new(link.id).path()).form(
+            serde_json::json!({
+                "csrf": csrf_token,
+                "action": "register",
+                "import_email": "on",
+                "accept_terms": "on",
+            }),
+        );
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .find_by_username("octocat")
+            .await
+            .unwrap()
+            .expect("user exists");
+
+        let page = repo
+            .user_email()
+            .list(UserEmailFilter::new().for_user(&user), Pagination::first(1))
+            .await
+            .unwrap();
+        let email = page.edges.first().expect("email exists");
+
+        assert_eq!(email.email, "octocat@example.com");
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_import_can_request_admin(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let mut rng = state.rng();
+        let cookies = CookieHelper::new();
+
+        let claims_imports = UpstreamOAuthProviderClaimsImports {
+            localpart: UpstreamOAuthProviderImportPreference {
+                action: mas_data_model::UpstreamOAuthProviderImportAction::Force,
+                template: None,
+                ..UpstreamOAuthProviderImportPreference::default()
+            },
+            can_request_admin: UpstreamOAuthProviderRolePreference {
+                expression: Some("user.groups".to_owned()),
+                allowed_values: vec!["matrix-admins".to_owned()],
+            },
+            ..UpstreamOAuthProviderClaimsImports::default()
+        };
+
+        let key = state
+            .key_store
+            .signing_key_for_algorithm(&JsonWebSignatureAlg::Rs256)
+            .unwrap();
+        let signer = key
+            .params()
+            .signing_key_for_alg(&JsonWebSignatureAlg::Rs256)
+            .unwrap();
+
+        let mut repo = state.repository().await.unwrap();
+        let provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &state.clock,
+                UpstreamOAuthProviderParams {
+                    issuer: Some("https://example.com/".to_owned()),
+                    human_name: Some("Example Ltd.".to_owned()),
+                    brand_name: None,
+                    scope: Scope::from_iter([OPENID]),
+                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+                    token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
+                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+                    client_id: "client".to_owned(),
+                    encrypted_client_secret: None,
+                    claims_imports,
+                    authorization_endpoint_override: None,
+                    token_endpoint_override: None,
+                    userinfo_endpoint_override: None,
+                    fetch_userinfo: false,
+                    userinfo_signed_response_alg: None,
+                    jwks_uri_override: None,
+                    discovery_mode: mas_data_model::UpstreamOAuthProviderDiscoveryMode::Oidc,
+                    pkce_mode: mas_data_model::UpstreamOAuthProviderPkceMode::Auto,
+                    response_mode: None,
+                    additional_authorization_parameters: Vec::new(),
+                    forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: false,
+                    deprovisioning: None,
+                    ui_order: 0,
+                },
+            )
+            .await
+            .unwrap();
+
+        let link = repo
+            .upstream_oauth_link()
+            .add(
+                &mut rng,
+                &state.clock,
+                &provider,
+                "subject".to_owned(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        // First, register while being in the admin group
+        let id_token = serde_json::json!({
+            "preferred_username": "john",
+            "groups": ["staff", "matrix-admins"],
+        });
+        let header = JsonWebSignatureHeader::new(JsonWebSignatureAlg::Rs256);
+        let id_token = Jwt::sign_with_rng(&mut rng, header, id_token, &signer).unwrap();
+
+        let session = repo
+            .upstream_oauth_session()
+            .add(
+                &mut rng,
+                &state.clock,
+                &provider,
+                "state".to_owned(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let session = repo
+            .upstream_oauth_session()
+            .complete_with_link(
+                &state.clock,
+                session,
+                &link,
+                Some(id_token.into_string()),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        repo.save().await.unwrap();
+
+        let cookie_jar = state.cookie_jar();
+        let upstream_sessions = UpstreamSessionsCookie::default()
+            .add(session.id, provider.id, "state".to_owned(), None)
+            .add_link_to_session(session.id, link.id)
+            .unwrap();
+        let cookie_jar = upstream_sessions.save(cookie_jar, &state.clock);
+        cookies.import(cookie_jar);
+
+        let request = Request::get(&*mas_router::UpstreamOAuth2Link::new(link.id).path()).empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
+
+        let request = Request::post(&*mas_router::UpstreamOAuth2Link::new(link.id).path()).form(
+            serde_json::json!({
+                "csrf": csrf_token,
+                "action": "register",
+                "accept_terms": "on",
+            }),
+        );
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .find_by_username("john")
+            .await
+            .unwrap()
+            .expect("user exists");
+        assert!(user.can_request_admin);
+
+        // Then log in again after being removed from the admin group
+        let id_token = serde_json::json!({
+            "preferred_username": "john",
+            "groups": ["staff"],
+        });
+        let header = JsonWebSignatureHeader::new(JsonWebSignatureAlg::Rs256);
+        let id_token = Jwt::sign_with_rng(&mut rng, header, id_token, &signer).unwrap();
+
+        let link = repo
+            .upstream_oauth_link()
+            .lookup(link.id)
+            .await
+            .unwrap()
+            .expect("link exists");
+        let session = repo
+            .upstream_oauth_session()
+            .add(
+                &mut rng,
+                &state.clock,
+                &provider,
+                "state2".to_owned(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let session = repo
+            .upstream_oauth_session()
+            .complete_with_link(
+                &state.clock,
+                session,
+                &link,
+                Some(id_token.into_string()),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        repo.save().await.unwrap();
+
+        let cookie_jar = state.cookie_jar();
+        let upstream_sessions = UpstreamSessionsCookie::default()
+            .add(session.id, provider.id, "state2".to_owned(), None)
+            .add_link_to_session(session.id, link.id)
+            .unwrap();
+        let cookie_jar = upstream_sessions.save(cookie_jar, &state.clock);
+        cookies.import(cookie_jar);
+
+        let request = Request::get(&*mas_router::UpstreamOAuth2Link::new(link.id).path()).empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo.user().lookup(user.id).await.unwrap().unwrap();
+        assert!(!user.can_request_admin);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_email_domain_not_allowed(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let mut rng = state.rng();
+        let cookies = CookieHelper::new();
+
+        let claims_imports = UpstreamOAuthProviderClaimsImports {
+            email_domains: UpstreamOAuthProviderEmailDomains {
+                allowed: Vec::new(),
+                forbidden: vec!["Corp.Example.com".to_owned()],
+            },
+            ..UpstreamOAuthProviderClaimsImports::default()
+        };
+
+        let id_token = serde_json::json!({
+            "preferred_username": "john",
+            "email": "john@corp.example.com",
+        });
+
+        let key = state
+            .key_store
+            .signing_key_for_algorithm(&JsonWebSignatureAlg::Rs256)
+            .unwrap();
+        let signer = key
+            .params()
+            .signing_key_for_alg(&JsonWebSignatureAlg::Rs256)
+            .unwrap();
+        let header = JsonWebSignatureHeader::new(JsonWebSignatureAlg::Rs256);
+        let id_token = Jwt::sign_with_rng(&mut rng, header, id_token, &signer).unwrap();
+
+        let mut repo = state.repository().await.unwrap();
+        let provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &state.clock,
+                UpstreamOAuthProviderParams {
+                    issuer: Some("https://example.com/".to_owned()),
+                    human_name: Some("Example Ltd.".to_owned()),
+                    brand_name: None,
+                    scope: Scope::from_iter([OPENID]),
+                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+                    token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
+                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+                    client_id: "client".to_owned(),
+                    encrypted_client_secret: None,
+                    claims_imports,
+                    authorization_endpoint_override: None,
+                    token_endpoint_override: None,
+                    userinfo_endpoint_override: None,
+                    fetch_userinfo: false,
+                    userinfo_signed_response_alg: None,
+                    jwks_uri_override: None,
+                    discovery_mode: mas_data_model::UpstreamOAuthProviderDiscoveryMode::Oidc,
+                    pkce_mode: mas_data_model::UpstreamOAuthProviderPkceMode::Auto,
+                    response_mode: None,
+                    additional_authorization_parameters: Vec::new(),
+                    forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: false,
+                    deprovisioning: None,
+                    ui_order: 0,
+                },
+            )
+            .await
+            .unwrap();
+
+        let session = repo
+            .upstream_oauth_session()
+            .add(
+                &mut rng,
+                &state.clock,
+                &provider,
+                "state".to_owned(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let link = repo
+            .upstream_oauth_link()
+            .add(
+                &mut rng,
+                &state.clock,
+                &provider,
+                "subject".to_owned(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let session = repo
+            .upstream_oauth_session()
+            .complete_with_link(
+                &state.clock,
+                session,
+                &link,
+                Some(id_token.into_string()),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        repo.save().await.unwrap();
+
+        let cookie_jar = state.cookie_jar();
+        let upstream_sessions = UpstreamSessionsCookie::default()
+            .add(session.id, provider.id, "state".to_owned(), None)
+            .add_link_to_session(session.id, link.id)
+            .unwrap();
+        let cookie_jar = upstream_sessions.save(cookie_jar, &state.clock);
+        cookies.import(cookie_jar);
+
+        // The provider isn't allowed to assert this address, so the registration
+        // form isn't shown
+        let request = Request::get(&*mas_router::UpstreamOAuth2Link::new(link.id).path()).empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("john@corp.example.com"));
+        assert!(!response.body().contains("name=\"csrf\""));
+
+        let mut repo = state.repository().await.unwrap();
+        let session = repo
+            .upstream_oauth_session()
+            .lookup(session.id)
+            .await
+            .unwrap()
+            .expect("session exists");
+        assert!(!session.is_consumed());
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_login_denied_by_policy(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        state.policy_factory = policy_factory(
+            "example.com",
+            serde_json::json!({
+                "upstream_login": {
+                    "required_claims": [
+                        {"claim": "groups", "values": ["staff"]},
+                    ],
+                },
+            }),
+        )
+        .await
+        .unwrap();
+        let mut rng = state.rng();
+        let cookies = CookieHelper::new();
+
+        let id_token = serde_json::json!({
+            "preferred_username": "john",
+            "groups": ["contractors"],
+        });
+
+        let key = state
+            .key_store
+            .signing_key_for_algorithm(&JsonWebSignatureAlg::Rs256)
+            .unwrap();
+        let signer = key
+            .params()
+            .signing_key_for_alg(&JsonWebSignatureAlg::Rs256)
+            .unwrap();
+        let header = JsonWebSignatureHeader::new(JsonWebSignatureAlg::Rs256);
+        let id_token = Jwt::sign_with_rng(&mut rng, header, id_token, &signer).unwrap();
+
+        let mut repo = state.repository().await.unwrap();
+        let provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &state.clock,
+                UpstreamOAuthProviderParams {
+                    issuer: Some("https://example.com/".to_owned()),
+                    human_name: Some("Example Ltd.".to_owned()),
+                    brand_name: None,
+                    scope: Scope::from_iter([OPENID]),
+                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+                    token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
+                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+                    client_id: "client".to_owned(),
+                    encrypted_client_secret: None,
+                    claims_imports: UpstreamOAuthProviderClaimsImports::default(),
+                    authorization_endpoint_override: None,
+                    token_endpoint_override: None,
+                    userinfo_endpoint_override: None,
+                    fetch_userinfo: false,
+                    userinfo_signed_response_alg: None,
+                    jwks_uri_override: None,
+                    discovery_mode: mas_data_model::UpstreamOAuthProviderDiscoveryMode::Oidc,
+                    pkce_mode: mas_data_model::UpstreamOAuthProviderPkceMode::Auto,
+                    response_mode: None,
+                    additional_authorization_parameters: Vec::new(),
+                    forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: false,
+                    deprovisioning: None,
+                    ui_order: 0,
+                },
+            )
+            .await
+            .unwrap();
+
+        let session = repo
+            .upstream_oauth_session()
+            .add(
+                &mut rng,
+                &state.clock,
+                &provider,
+                "state".to_owned(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let link = repo
+            .upstream_oauth_link()
+            .add(
+                &mut rng,
+                &state.clock,
+                &provider,
+                "subject".to_owned(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let session = repo
+            .upstream_oauth_session()
+            .complete_with_link(
+                &state.clock,
+                session,
+                &link,
+                Some(id_token.into_string()),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        repo.save().await.unwrap();
+
+        let cookie_jar = state.cookie_jar();
+        let upstream_sessions = UpstreamSessionsCookie::default()
+            .add(session.id, provider.id, "state".to_owned(), None)
+            .add_link_to_session(session.id, link.id)
+            .unwrap();
+        let cookie_jar = upstream_sessions.save(cookie_jar, &state.clock);
+        cookies.import(cookie_jar);
+
+        // The policy doesn't allow this account, so the registration form isn't
+        // shown and the user is told why instead
+        let request = Request::get(&*mas_router::UpstreamOAuth2Link::new(link.id).path()).empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(
+            response
+                .body()
+                .contains("allowed to sign in to this service")
+        );
+        assert!(response.body().contains("groups"));
+        assert!(!response.body().contains("name=\"csrf\""));
+
+        let mut repo = state.repository().await.unwrap();
+        let session = repo
+            .upstream_oauth_session()
+            .lookup(session.id)
+            .await
+            .unwrap()
+            .expect("session exists");
+        assert!(!session.is_consumed());
+    }
 }
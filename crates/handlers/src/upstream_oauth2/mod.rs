@@ -14,13 +14,18 @@ use pkcs8::DecodePrivateKey;
 use serde::Deserialize;
 use thiserror::Error;
 use url::Url;
+use zeroize::Zeroizing;
 
 pub(crate) mod authorize;
+pub(crate) mod backchannel_logout;
 pub(crate) mod cache;
 pub(crate) mod callback;
+pub(crate) mod circuit_breaker;
 mod cookie;
+pub(crate) mod health;
 pub(crate) mod link;
 mod template;
+pub(crate) mod tokens;
 
 use self::cookie::UpstreamSessions as UpstreamSessionsCookie;
 
@@ -53,6 +58,15 @@ enum ProviderCredentialsError {
         #[from]
         inner: pkcs8::Error,
     },
+
+    #[error("Provider doesn't have a TLS client certificate")]
+    MissingTlsClientCertificate,
+
+    #[error("TLS client certificate is invalid")]
+    InvalidTlsClientCertificate {
+        #[from]
+        inner: mas_http::ClientCertificateError,
+    },
 }
 
 #[derive(Debug, Deserialize)]
@@ -70,12 +84,13 @@ fn client_credentials_for_provider(
 ) -> Result<ClientCredentials, ProviderCredentialsError> {
     let client_id = provider.client_id.clone();
 
-    // Decrypt the client secret
+    // Decrypt the client secret. Secrets stored before envelope encryption was
+    // introduced are still decrypted with the encryption secret
     let client_secret = provider
         .encrypted_client_secret
         .as_deref()
         .map(|encrypted_client_secret| {
-            let decrypted = encrypter.decrypt_string(encrypted_client_secret)?;
+            let decrypted = encrypter.decrypt_envelope(encrypted_client_secret)?;
             let decrypted = String::from_utf8(decrypted)?;
             Ok::<_, ProviderCredentialsError>(decrypted)
         })
@@ -116,6 +131,7 @@ fn client_credentials_for_provider(
         UpstreamOAuthProviderTokenAuthMethod::PrivateKeyJwt => ClientCredentials::PrivateKeyJwt {
             client_id,
             keystore: keystore.clone(),
+            key_id: provider.token_endpoint_signing_key_id.clone(),
             signing_algorithm: provider
                 .token_endpoint_signing_alg
                 .clone()
@@ -123,6 +139,16 @@ fn client_credentials_for_provider(
             token_endpoint: token_endpoint.clone(),
         },
 
+        // The client is authenticated by the TLS client certificate presented
+        // by the HTTP client, see `token_endpoint_http_client`
+        UpstreamOAuthProviderTokenAuthMethod::TlsClientAuth => {
+            if provider.tls_client_certificate.is_none() {
+                return Err(ProviderCredentialsError::MissingTlsClientCertificate);
+            }
+
+            ClientCredentials::None { client_id }
+        }
+
         UpstreamOAuthProviderTokenAuthMethod::SignInWithApple => {
             let params = client_secret.ok_or(ProviderCredentialsError::MissingClientSecret)?;
             let params: SignInWithApple = serde_json::from_str(&params)?;
@@ -140,3 +166,26 @@ fn client_credentials_for_provider(
 
     Ok(client_credentials)
 }
+
+/// Get the HTTP client to use to call the token endpoint of the provider.
+///
+/// If the provider has a TLS client certificate, this returns a new client
+/// which presents it, else a clone of the given client.
+fn token_endpoint_http_client(
+    provider: &UpstreamOAuthProvider,
+    client: &reqwest::Client,
+    encrypter: &Encrypter,
+) -> Result<reqwest::Client, ProviderCredentialsError> {
+    let (Some(certificate_chain), Some(encrypted_key)) = (
+        provider.tls_client_certificate.as_deref(),
+        provider.encrypted_tls_client_key.as_deref(),
+    ) else {
+        return Ok(client.clone());
+    };
+
+    let key = Zeroizing::new(String::from_utf8(
+        encrypter.decrypt_envelope(encrypted_key)?,
+    )?);
+    let client = mas_http::reqwest_client_with_certificate(certificate_chain, &key)?;
+    Ok(client)
+}
@@ -90,7 +90,7 @@ impl Object for AttributeMappingContext {
 
     fn enumerate(self: &Arc<Self>) -> Enumerator {
         let mut attrs = Vec::new();
-        if self.id_token_claims.is_some() || self.userinfo_claims.is_none() {
+        if self.id_token_claims.is_some() || self.userinfo_claims.is_some() {
             attrs.push(minijinja::Value::from("user"));
         }
         if self.id_token_claims.is_some() {
@@ -195,7 +195,7 @@ pub fn environment() -> Environment<'static> {
 
 #[cfg(test)]
 mod tests {
-    use super::environment;
+    use super::{AttributeMappingContext, environment};
 
     #[test]
     fn test_split() {
@@ -238,4 +238,34 @@ mod tests {
             .unwrap();
         assert_eq!(res, "unpadded");
     }
+
+    #[test]
+    fn test_userinfo_only_context() {
+        // Plain OAuth 2.0 providers don't give us an ID token, so everything comes
+        // from the userinfo endpoint
+        let env = environment();
+        let context = AttributeMappingContext::new()
+            .with_userinfo_claims(serde_json::json!({
+                "id": 1234,
+                "login": "octocat",
+                "emails": [{ "email": "octocat@example.com", "primary": true }],
+            }))
+            .build();
+
+        let res = env.render_str("{{ user.id }}", &context).unwrap();
+        assert_eq!(res, "1234");
+
+        let res = env
+            .render_str("{{ userinfo_claims.emails[0].email }}", &context)
+            .unwrap();
+        assert_eq!(res, "octocat@example.com");
+
+        let res = env
+            .render_str(
+                "{{ ctx | list | join(',') }}",
+                minijinja::context! { ctx => context },
+            )
+            .unwrap();
+        assert_eq!(res, "user,userinfo_claims");
+    }
 }
@@ -0,0 +1,630 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use chrono::Duration;
+use hyper::{HeaderMap, Method, StatusCode};
+use mas_axum_utils::{
+    record_error,
+    user_authorization::{AuthorizationVerificationError, UserAuthorization},
+};
+use mas_data_model::{UpstreamOAuthLink, UpstreamOAuthLinkTokens};
+use mas_keystore::{Encrypter, Keystore};
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, Clock, Pagination, RepositoryAccess, RepositoryError,
+    upstream_oauth2::{
+        UpstreamOAuthLinkFilter, UpstreamOAuthLinkRepository, UpstreamOAuthProviderRepository,
+    },
+};
+use oauth2_types::{
+    errors::{ClientError, ClientErrorCode},
+    requests::AccessTokenResponse,
+};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use thiserror::Error;
+use ulid::Ulid;
+
+use super::{
+    cache::LazyProviderInfos,
+    circuit_breaker::{CircuitOpenError, ProviderCircuitBreaker},
+    client_credentials_for_provider, token_endpoint_http_client,
+};
+use crate::{
+    BoundActivityTracker, Limiter, impl_from_error_for_route,
+    oauth2::dpop::{self, DPoPError},
+    rate_limit::UpstreamOAuth2RequestLimitedError,
+    upstream_oauth2::cache::MetadataCache,
+};
+
+/// The prefix of the scope which gives access to the tokens of an upstream
+/// provider. It is followed by the ID of the provider.
+const SCOPE_PREFIX: &str = "urn:mas:upstream-tokens:";
+
+/// Access tokens which expire within this leeway are refreshed before being
+/// handed out, so that the integration has time to use them
+const EXPIRATION_LEEWAY: Duration = Duration::microseconds(60 * 1000 * 1000);
+
+#[derive(Debug, Error)]
+pub(crate) enum StoreTokensError {
+    #[error("Could not encrypt the tokens")]
+    Encrypt(#[from] mas_keystore::aead::Error),
+
+    #[error(transparent)]
+    Repository(#[from] RepositoryError),
+}
+
+/// Encrypt and store the tokens the upstream provider issued for a link
+///
+/// Providers usually don't issue a new refresh token every time, in which case
+/// the one previously stored is kept.
+pub(crate) async fn store_tokens(
+    repo: &mut BoxRepository,
+    clock: &dyn Clock,
+    encrypter: &Encrypter,
+    link: &UpstreamOAuthLink,
+    response: &AccessTokenResponse,
+) -> Result<UpstreamOAuthLinkTokens, StoreTokensError> {
+    let encrypted_access_token = encrypter.encrypt_envelope(response.access_token.as_bytes())?;
+
+    let encrypted_refresh_token = if let Some(refresh_token) = &response.refresh_token {
+        Some(encrypter.encrypt_envelope(refresh_token.as_bytes())?)
+    } else {
+        repo.upstream_oauth_link()
+            .tokens(link)
+            .await?
+            .and_then(|tokens| tokens.encrypted_refresh_token)
+    };
+
+    let access_token_expires_at = response
+        .expires_in
+        .map(|expires_in| clock.now() + expires_in);
+
+    let tokens = repo
+        .upstream_oauth_link()
+        .set_tokens(
+            clock,
+            link,
+            encrypted_access_token,
+            encrypted_refresh_token,
+            access_token_expires_at,
+        )
+        .await?;
+
+    Ok(tokens)
+}
+
+#[skip_serializing_none]
+#[derive(Serialize)]
+struct TokensResponse {
+    access_token: String,
+    expires_in: Option<i64>,
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum RouteError {
+    #[error(transparent)]
+    Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("failed to authenticate")]
+    AuthorizationVerificationError(
+        #[from] AuthorizationVerificationError<mas_storage::RepositoryError>,
+    ),
+
+    #[error("session is not allowed to access the tokens of provider {0}")]
+    InsufficientScope(Ulid),
+
+    #[error("provider {0} not found or doesn't store tokens")]
+    ProviderNotFound(Ulid),
+
+    #[error("no tokens were stored for the user from provider {0}")]
+    NoTokens(Ulid),
+
+    #[error("the tokens from provider {0} expired and can't be refreshed")]
+    Expired(Ulid),
+
+    #[error("failed to refresh the tokens from provider {0}")]
+    Refresh(Ulid, #[source] mas_oidc_client::error::TokenRefreshError),
+
+    #[error(transparent)]
+    RateLimited(#[from] UpstreamOAuth2RequestLimitedError),
+
+    #[error(transparent)]
+    CircuitOpen(#[from] CircuitOpenError),
+
+    #[error("invalid DPoP proof")]
+    InvalidDPoPProof(#[source] DPoPError),
+
+    #[error("could not verify the DPoP proof")]
+    DPoPVerification(#[source] DPoPError),
+}
+
+impl_from_error_for_route!(mas_storage::RepositoryError);
+impl_from_error_for_route!(mas_keystore::DecryptError);
+impl_from_error_for_route!(mas_oidc_client::error::DiscoveryError);
+impl_from_error_for_route!(std::string::FromUtf8Error);
+impl_from_error_for_route!(super::ProviderCredentialsError);
+impl_from_error_for_route!(StoreTokensError);
+
+impl From<DPoPError> for RouteError {
+    fn from(err: DPoPError) -> Self {
+        if err.is_internal() {
+            Self::DPoPVerification(err)
+        } else {
+            Self::InvalidDPoPProof(err)
+        }
+    }
+}
+
+impl IntoResponse for RouteError {
+    fn into_response(self) -> Response {
+        let sentry_event_id = record_error!(self, Self::Internal(_) | Self::DPoPVerification(_));
+        let response = match self {
+            Self::Internal(_) | Self::DPoPVerification(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    ClientError::from(ClientErrorCode::ServerError)
+                        .with_description(self.to_string()),
+                ),
+            )
+                .into_response(),
+            Self::AuthorizationVerificationError(_) | Self::InvalidDPoPProof(_) => {
+                StatusCode::UNAUTHORIZED.into_response()
+            }
+            Self::InsufficientScope(_) => (
+                StatusCode::FORBIDDEN,
+                Json(
+                    ClientError::from(ClientErrorCode::InsufficientScope)
+                        .with_description(self.to_string()),
+                ),
+            )
+                .into_response(),
+            Self::ProviderNotFound(_) | Self::NoTokens(_) | Self::Expired(_) => (
+                StatusCode::NOT_FOUND,
+                Json(
+                    ClientError::from(ClientErrorCode::InvalidRequest)
+                        .with_description(self.to_string()),
+                ),
+            )
+                .into_response(),
+            Self::Refresh(_, _) => (
+                StatusCode::BAD_GATEWAY,
+                Json(
+                    ClientError::from(ClientErrorCode::TemporarilyUnavailable)
+                        .with_description(self.to_string()),
+                ),
+            )
+                .into_response(),
+            Self::RateLimited(_) | Self::CircuitOpen(_) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(
+                    ClientError::from(ClientErrorCode::TemporarilyUnavailable)
+                        .with_description(self.to_string()),
+                ),
+            )
+                .into_response(),
+        };
+
+        (sentry_event_id, response).into_response()
+    }
+}
+
+/// Give the current access token issued by an upstream provider to the user,
+/// refreshing it first if it expired.
+///
+/// This requires the `urn:mas:upstream-tokens:<provider id>` scope, and never
+/// gives out the refresh token.
+#[tracing::instrument(
+    name = "handlers.upstream_oauth2.tokens.get",
+    fields(upstream_oauth_provider.id = %provider_id),
+    skip_all,
+)]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    activity_tracker: BoundActivityTracker,
+    mut repo: BoxRepository,
+    State(metadata_cache): State<MetadataCache>,
+    State(client): State<reqwest::Client>,
+    State(keystore): State<Keystore>,
+    State(encrypter): State<Encrypter>,
+    State(limiter): State<Limiter>,
+    State(circuit_breaker): State<ProviderCircuitBreaker>,
+    State(url_builder): State<UrlBuilder>,
+    Path(provider_id): Path<Ulid>,
+    headers: HeaderMap,
+    user_authorization: UserAuthorization,
+) -> Result<Response, RouteError> {
+    // Tokens presented with the DPoP scheme come with a proof of possession
+    let dpop = match user_authorization.dpop_token() {
+        Some(token) => Some(
+            dpop::verify_token_proof(
+                &mut rng,
+                &clock,
+                &mut repo,
+                &headers,
+                &Method::GET,
+                &url_builder.absolute_url_for(&mas_router::UpstreamOAuth2Tokens::new(provider_id)),
+                token,
+            )
+            .await?,
+        ),
+        None => None,
+    };
+
+    let session = user_authorization
+        .protected(
+            &mut repo,
+            &clock,
+            dpop.as_ref().map(|dpop| dpop.jkt.as_str()),
+        )
+        .await?;
+
+    if !session
+        .scope
+        .contains(&format!("{SCOPE_PREFIX}{provider_id}"))
+    {
+        return Err(RouteError::InsufficientScope(provider_id));
+    }
+
+    // Sessions which don't belong to a user don't have upstream tokens
+    let Some(user_id) = session.user_id else {
+        return Err(RouteError::InsufficientScope(provider_id));
+    };
+
+    activity_tracker
+        .record_oauth2_session(&clock, &session)
+        .await;
+
+    let provider = repo
+        .upstream_oauth_provider()
+        .lookup(provider_id)
+        .await?
+        .filter(|provider| provider.enabled() && provider.store_tokens)
+        .ok_or(RouteError::ProviderNotFound(provider_id))?;
+
+    let user = repo
+        .user()
+        .lookup(user_id)
+        .await?
+        .ok_or(RouteError::NoTokens(provider_id))?;
+
+    // Use the most recent link if the user has more than one with this provider
+    let filter = UpstreamOAuthLinkFilter::new()
+        .for_user(&user)
+        .for_provider(&provider);
+    let link = repo
+        .upstream_oauth_link()
+        .list(filter, Pagination::last(1))
+        .await?
+        .edges
+        .pop()
+        .ok_or(RouteError::NoTokens(provider_id))?;
+
+    let mut tokens = repo
+        .upstream_oauth_link()
+        .tokens(&link)
+        .await?
+        .ok_or(RouteError::NoTokens(provider_id))?;
+
+    if tokens.access_token_expired(clock.now(), EXPIRATION_LEEWAY) {
+        let Some(encrypted_refresh_token) = &tokens.encrypted_refresh_token else {
+            return Err(RouteError::Expired(provider_id));
+        };
+        let refresh_token =
+            String::from_utf8(encrypter.decrypt_envelope(encrypted_refresh_token)?)?;
+
+        let mut lazy_metadata = LazyProviderInfos::new(&metadata_cache, &provider, &client);
+        let client_credentials = client_credentials_for_provider(
+            &provider,
+            lazy_metadata.token_endpoint().await?,
+            &keystore,
+            &encrypter,
+        )?;
+        let token_client = token_endpoint_http_client(&provider, &client, &encrypter)?;
+
+        let token_endpoint = lazy_metadata.token_endpoint().await?;
+        limiter.check_upstream_oauth2_request(provider.id)?;
+        circuit_breaker.check(provider.id, clock.now())?;
+        let res = mas_oidc_client::requests::refresh_token::refresh_access_token(
+            &token_client,
+            client_credentials,
+            token_endpoint,
+            refresh_token,
+            None,
+            None,
+            None,
+            clock.now(),
+            &mut rng,
+        )
+        .await;
+        circuit_breaker.record(
+            provider.id,
+            clock.now(),
+            matches!(&res, Err(e) if e.is_provider_failure()),
+        );
+
+        let response = match res {
+            Ok((response, _id_token)) => response,
+            Err(e) => {
+                // Remember that the provider refused the refresh token, which
+                // may mean that the upstream account was removed
+                if e.is_invalid_grant() {
+                    repo.upstream_oauth_link()
+                        .mark_refresh_failed(&clock, tokens)
+                        .await?;
+                    repo.save().await?;
+                }
+
+                return Err(RouteError::Refresh(provider_id, e));
+            }
+        };
+
+        tokens = store_tokens(&mut repo, &clock, &encrypter, &link, &response).await?;
+    }
+
+    repo.save().await?;
+
+    let access_token =
+        String::from_utf8(encrypter.decrypt_envelope(&tokens.encrypted_access_token)?)?;
+    let expires_in = tokens
+        .access_token_expires_at
+        .map(|expires_at| (expires_at - clock.now()).num_seconds());
+
+    Ok(Json(TokensResponse {
+        access_token,
+        expires_in,
+    })
+    .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use mas_data_model::{
+        TokenType, UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderDiscoveryMode,
+        UpstreamOAuthProviderPkceMode, UpstreamOAuthProviderTokenAuthMethod,
+    };
+    use mas_iana::jose::JsonWebSignatureAlg;
+    use mas_router::{Route, SimpleRoute};
+    use mas_storage::{
+        Clock, RepositoryAccess,
+        oauth2::{OAuth2AccessTokenRepository, OAuth2ClientRepository, OAuth2SessionRepository},
+        upstream_oauth2::{
+            UpstreamOAuthLinkRepository, UpstreamOAuthProviderParams,
+            UpstreamOAuthProviderRepository,
+        },
+        user::{BrowserSessionRepository, UserRepository},
+    };
+    use oauth2_types::{
+        registration::ClientRegistrationResponse,
+        scope::{OPENID, Scope},
+    };
+    use serde_json::json;
+    use sqlx::PgPool;
+
+    use crate::test_utils::{RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    #[allow(clippy::too_many_lines)]
+    async fn test_upstream_tokens(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let mut rng = state.rng();
+
+        // The token endpoint of the provider, which refreshes the access token
+        let mock_server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .and(wiremock::matchers::path("/token"))
+            .and(wiremock::matchers::body_string_contains(
+                "refresh_token=upstream-refresh-token",
+            ))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "refreshed-access-token",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut repo = state.repository().await.unwrap();
+        let provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &state.clock,
+                UpstreamOAuthProviderParams {
+                    issuer: Some("https://example.com/".to_owned()),
+                    human_name: Some("Example Ltd.".to_owned()),
+                    brand_name: None,
+                    scope: Scope::from_iter([OPENID]),
+                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+                    token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
+                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+                    client_id: "client".to_owned(),
+                    encrypted_client_secret: None,
+                    claims_imports: UpstreamOAuthProviderClaimsImports::default(),
+                    authorization_endpoint_override: None,
+                    token_endpoint_override: Some(
+                        format!("{}/token", mock_server.uri()).parse().unwrap(),
+                    ),
+                    userinfo_endpoint_override: None,
+                    fetch_userinfo: false,
+                    userinfo_signed_response_alg: None,
+                    jwks_uri_override: None,
+                    discovery_mode: UpstreamOAuthProviderDiscoveryMode::Disabled,
+                    pkce_mode: UpstreamOAuthProviderPkceMode::Auto,
+                    response_mode: None,
+                    additional_authorization_parameters: Vec::new(),
+                    forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: true,
+                    deprovisioning: None,
+                    ui_order: 0,
+                },
+            )
+            .await
+            .unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "john".to_owned())
+            .await
+            .unwrap();
+        let link = repo
+            .upstream_oauth_link()
+            .add(
+                &mut rng,
+                &state.clock,
+                &provider,
+                "subject".to_owned(),
+                None,
+            )
+            .await
+            .unwrap();
+        repo.upstream_oauth_link()
+            .associate_to_user(&link, &user)
+            .await
+            .unwrap();
+
+        // The tokens the provider issued when the user logged in
+        let encrypted_refresh_token = state
+            .encrypter
+            .encrypt_envelope(b"upstream-refresh-token")
+            .unwrap();
+        repo.upstream_oauth_link()
+            .set_tokens(
+                &state.clock,
+                &link,
+                state
+                    .encrypter
+                    .encrypt_envelope(b"upstream-access-token")
+                    .unwrap(),
+                Some(encrypted_refresh_token.clone()),
+                Some(state.clock.now() + Duration::microseconds(5 * 60 * 1000 * 1000)),
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(json!({
+            "client_uri": "https://example.com/",
+            "redirect_uris": ["https://example.com/callback"],
+            "token_endpoint_auth_method": "none",
+            "response_types": ["code"],
+            "grant_types": ["authorization_code"],
+        }));
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let ClientRegistrationResponse { client_id, .. } = response.json();
+
+        // Start one session which can access the tokens and one which can't
+        let mut repo = state.repository().await.unwrap();
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&client_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+        let mut tokens = Vec::new();
+        for scope in [
+            format!("openid urn:mas:upstream-tokens:{}", provider.id),
+            "openid".to_owned(),
+        ] {
+            let session = repo
+                .oauth2_session()
+                .add_from_browser_session(
+                    &mut rng,
+                    &state.clock,
+                    &client,
+                    &browser_session,
+                    scope.parse().unwrap(),
+                )
+                .await
+                .unwrap();
+            let access_token = repo
+                .oauth2_access_token()
+                .add(
+                    &mut rng,
+                    &state.clock,
+                    &session,
+                    TokenType::AccessToken.generate(&mut rng),
+                    None,
+                )
+                .await
+                .unwrap();
+            tokens.push(access_token.access_token);
+        }
+        repo.save().await.unwrap();
+        let [allowed_token, other_token] = tokens.try_into().unwrap();
+
+        let path = mas_router::UpstreamOAuth2Tokens::new(provider.id).path();
+
+        // The scope is required
+        let request = Request::get(&*path).bearer(&other_token).empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::FORBIDDEN);
+
+        let request = Request::get(&*path).bearer(&allowed_token).empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body,
+            json!({
+                "access_token": "upstream-access-token",
+                "expires_in": 300,
+            })
+        );
+
+        // Once the access token expired, it gets refreshed
+        state
+            .clock
+            .advance(Duration::microseconds(5 * 60 * 1000 * 1000));
+        let request = Request::get(&*path).bearer(&allowed_token).empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(
+            body,
+            json!({
+                "access_token": "refreshed-access-token",
+                "expires_in": 3600,
+            })
+        );
+
+        // The provider didn't issue a new refresh token, so the previous one is
+        // kept
+        let mut repo = state.repository().await.unwrap();
+        let stored = repo
+            .upstream_oauth_link()
+            .tokens(&link)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            stored.encrypted_refresh_token,
+            Some(encrypted_refresh_token)
+        );
+    }
+}
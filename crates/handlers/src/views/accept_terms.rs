@@ -0,0 +1,245 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Page asking logged-in users to accept the current version of the terms of
+//! service and privacy policy, before going further
+
+use axum::{
+    extract::{Form, Query, State},
+    response::{Html, IntoResponse, Response},
+};
+use mas_axum_utils::{
+    InternalError,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::SiteConfig;
+use mas_router::UrlBuilder;
+use mas_storage::{BoxClock, BoxRepository, BoxRng};
+use mas_templates::{AcceptTermsContext, TemplateContext, Templates};
+
+use super::shared::OptionalPostAuthAction;
+use crate::{
+    BoundActivityTracker, PreferredLanguage,
+    session::{SessionOrFallback, load_session_or_fallback},
+    terms,
+};
+
+#[tracing::instrument(name = "handlers.views.accept_terms.get", skip_all)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+) -> Result<Response, InternalError> {
+    let (cookie_jar, maybe_session) = match load_session_or_fallback(
+        cookie_jar,
+        &clock,
+        &mut rng,
+        &templates,
+        &locale,
+        &mut repo,
+        &site_config,
+    )
+    .await?
+    {
+        SessionOrFallback::MaybeSession {
+            cookie_jar,
+            maybe_session,
+            ..
+        } => (cookie_jar, maybe_session),
+        SessionOrFallback::Fallback { response } => return Ok(response),
+    };
+
+    let Some(session) = maybe_session else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    activity_tracker
+        .record_browser_session(&clock, &session)
+        .await;
+
+    let documents = terms::pending(&mut repo, &site_config, &session.user).await?;
+    if documents.is_empty() {
+        let reply = query.go_next(&url_builder);
+        return Ok((cookie_jar, reply).into_response());
+    }
+
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
+
+    let ctx = AcceptTermsContext::new(documents);
+    let next = query
+        .load_context(&mut repo)
+        .await
+        .map_err(InternalError::from_anyhow)?;
+    let ctx = if let Some(next) = next {
+        ctx.with_post_action(next)
+    } else {
+        ctx
+    };
+    let ctx = ctx
+        .with_session(session)
+        .with_csrf(csrf_token.form_value())
+        .with_language(locale);
+
+    let content = templates.render_accept_terms(&ctx)?;
+    Ok((cookie_jar, Html(content)).into_response())
+}
+
+#[tracing::instrument(name = "handlers.views.accept_terms.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    mut repo: BoxRepository,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+    Form(form): Form<ProtectedForm<()>>,
+) -> Result<Response, InternalError> {
+    cookie_jar.verify_form(&clock, form)?;
+
+    let (cookie_jar, maybe_session) = match load_session_or_fallback(
+        cookie_jar,
+        &clock,
+        &mut rng,
+        &templates,
+        &locale,
+        &mut repo,
+        &site_config,
+    )
+    .await?
+    {
+        SessionOrFallback::MaybeSession {
+            cookie_jar,
+            maybe_session,
+            ..
+        } => (cookie_jar, maybe_session),
+        SessionOrFallback::Fallback { response } => return Ok(response),
+    };
+
+    let Some(session) = maybe_session else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let documents = terms::pending(&mut repo, &site_config, &session.user).await?;
+    terms::accept(&mut repo, &mut rng, &clock, &session.user, &documents).await?;
+
+    repo.save().await?;
+
+    let reply = query.go_next(&url_builder);
+    Ok((cookie_jar, reply).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode, header::LOCATION};
+    use mas_axum_utils::SessionInfoExt;
+    use mas_data_model::{SiteConfig, TermsDocument, TermsDocumentKind};
+    use mas_storage::{
+        RepositoryAccess,
+        user::{BrowserSessionRepository, UserRepository, UserTermsAcceptanceRepository},
+    };
+    use sqlx::PgPool;
+
+    use crate::test_utils::{
+        CookieHelper, RequestBuilderExt, ResponseExt, TestState, setup, test_site_config,
+    };
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_accept_terms(pool: PgPool) {
+        setup();
+        let previous_terms = TermsDocument {
+            kind: TermsDocumentKind::TermsOfService,
+            version: "1".to_owned(),
+            url: "https://example.com/terms/1".parse().unwrap(),
+        };
+        let current_terms = TermsDocument {
+            kind: TermsDocumentKind::TermsOfService,
+            version: "2".to_owned(),
+            url: "https://example.com/terms/2".parse().unwrap(),
+        };
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                terms_documents: vec![current_terms.clone()],
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        repo.user_terms_acceptance()
+            .accept(&mut rng, &state.clock, &user, &previous_terms)
+            .await
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        cookies.import(state.cookie_jar().set_session(&browser_session));
+
+        // The user accepted a previous version, so they are asked to accept the
+        // current one
+        let request = cookies.with_cookies(Request::get("/accept-terms").empty());
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("https://example.com/terms/2"));
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        let request = Request::post("/accept-terms").form(serde_json::json!({
+            "csrf": csrf_token,
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, "/");
+
+        let mut repo = state.repository().await.unwrap();
+        let acceptances = repo
+            .user_terms_acceptance()
+            .all_for_user(&user)
+            .await
+            .unwrap();
+        assert_eq!(acceptances.len(), 2);
+        assert_eq!(acceptances[1].version, "2");
+        repo.save().await.unwrap();
+
+        // Nothing is pending anymore, so the page sends the user further
+        let request = cookies.with_cookies(Request::get("/accept-terms").empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+    }
+}
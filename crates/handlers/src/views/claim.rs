@@ -0,0 +1,221 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use axum::{
+    Form,
+    extract::{Path, State},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::typed_header::TypedHeader;
+use mas_axum_utils::{
+    InternalError, SessionInfoExt,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::{FeatureFlag, User, UserClaimLink};
+use mas_router::UrlBuilder;
+use mas_storage::{BoxClock, BoxRepository, BoxRng, Clock, RepositoryAccess};
+use mas_templates::{
+    AccountClaimContext, AccountClaimFormField, EmptyContext, FieldError, FormState,
+    TemplateContext, Templates, ToFormState,
+};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::{BoundActivityTracker, FeatureFlags, PreferredLanguage, passwords::PasswordManager};
+
+#[derive(Deserialize, Serialize)]
+pub(crate) struct ClaimForm {
+    new_password: String,
+    new_password_confirm: String,
+}
+
+impl ToFormState for ClaimForm {
+    type Field = AccountClaimFormField;
+}
+
+/// Load the claim link and its user, if both are still usable, and if the
+/// user is part of the rollout of account claims
+async fn load_claim_link<R: RepositoryAccess>(
+    repo: &mut R,
+    clock: &dyn Clock,
+    feature_flags: &FeatureFlags,
+    token: &str,
+) -> Result<Option<(UserClaimLink, User)>, R::Error> {
+    let Some(link) = repo.user_claim_link().find_by_token(token).await? else {
+        return Ok(None);
+    };
+
+    if !link.is_valid(clock.now()) {
+        return Ok(None);
+    }
+
+    let Some(user) = repo.user().lookup(link.user_id).await? else {
+        return Ok(None);
+    };
+
+    if !user.is_valid() {
+        return Ok(None);
+    }
+
+    if !feature_flags
+        .is_enabled(repo, FeatureFlag::AccountClaim, Some(&user))
+        .await?
+    {
+        return Ok(None);
+    }
+
+    Ok(Some((link, user)))
+}
+
+#[tracing::instrument(name = "handlers.views.claim.get", skip_all)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    mut repo: BoxRepository,
+    State(password_manager): State<PasswordManager>,
+    State(feature_flags): State<FeatureFlags>,
+    State(templates): State<Templates>,
+    PreferredLanguage(locale): PreferredLanguage,
+    cookie_jar: CookieJar,
+    Path(token): Path<String>,
+) -> Result<Response, InternalError> {
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
+
+    let claim = if password_manager.is_enabled() {
+        load_claim_link(&mut repo, &clock, &feature_flags, &token).await?
+    } else {
+        None
+    };
+
+    let Some((_link, user)) = claim else {
+        let context = EmptyContext.with_language(locale);
+        let rendered = templates.render_account_claim_invalid(&context)?;
+        return Ok((cookie_jar, Html(rendered)).into_response());
+    };
+
+    let context = AccountClaimContext::new(user)
+        .with_csrf(csrf_token.form_value())
+        .with_language(locale);
+
+    let rendered = templates.render_account_claim(&context)?;
+
+    Ok((cookie_jar, Html(rendered)).into_response())
+}
+
+#[tracing::instrument(name = "handlers.views.claim.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    mut repo: BoxRepository,
+    State(password_manager): State<PasswordManager>,
+    State(feature_flags): State<FeatureFlags>,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    PreferredLanguage(locale): PreferredLanguage,
+    (user_agent, activity_tracker): (
+        Option<TypedHeader<headers::UserAgent>>,
+        BoundActivityTracker,
+    ),
+    cookie_jar: CookieJar,
+    Path(token): Path<String>,
+    Form(form): Form<ProtectedForm<ClaimForm>>,
+) -> Result<Response, InternalError> {
+    let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
+    let form = cookie_jar.verify_form(&clock, form)?;
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
+
+    let claim = if password_manager.is_enabled() {
+        load_claim_link(&mut repo, &clock, &feature_flags, &token).await?
+    } else {
+        None
+    };
+
+    let Some((link, user)) = claim else {
+        let context = EmptyContext.with_language(locale);
+        let rendered = templates.render_account_claim_invalid(&context)?;
+        return Ok((cookie_jar, Html(rendered)).into_response());
+    };
+
+    let state = {
+        let mut state = form.to_form_state();
+
+        if form.new_password.is_empty() {
+            state.add_error_on_field(AccountClaimFormField::NewPassword, FieldError::Required);
+        }
+
+        if form.new_password_confirm.is_empty() {
+            state.add_error_on_field(
+                AccountClaimFormField::NewPasswordConfirm,
+                FieldError::Required,
+            );
+        }
+
+        if form.new_password != form.new_password_confirm {
+            state.add_error_on_field(
+                AccountClaimFormField::NewPasswordConfirm,
+                FieldError::PasswordMismatch,
+            );
+        }
+
+        if !password_manager.is_password_complex_enough(&form.new_password)? {
+            // TODO localise this error
+            state.add_error_on_field(
+                AccountClaimFormField::NewPassword,
+                FieldError::Policy {
+                    code: None,
+                    message: "Password is too weak".to_owned(),
+                },
+            );
+        }
+
+        state
+    };
+
+    if !state.is_valid() {
+        let context = AccountClaimContext::new(user)
+            .with_form_state(state)
+            .with_csrf(csrf_token.form_value())
+            .with_language(locale);
+
+        let rendered = templates.render_account_claim(&context)?;
+        return Ok((cookie_jar, Html(rendered)).into_response());
+    }
+
+    let password = Zeroizing::new(form.new_password);
+    let (version, hashed_password) = password_manager
+        .hash(&mut rng, password)
+        .await
+        .map_err(InternalError::from_anyhow)?;
+
+    let user_password = repo
+        .user_password()
+        .add(&mut rng, &clock, &user, version, hashed_password, None)
+        .await?;
+
+    repo.user_claim_link()
+        .claim(&clock, link, activity_tracker.ip(), user_agent.clone())
+        .await?;
+
+    // Claiming the account logs the user in straight away
+    let user_session = repo
+        .browser_session()
+        .add(&mut rng, &clock, &user, user_agent)
+        .await?;
+
+    repo.browser_session()
+        .authenticate_with_password(&mut rng, &clock, &user_session, &user_password)
+        .await?;
+
+    repo.save().await?;
+
+    activity_tracker
+        .record_browser_session(&clock, &user_session)
+        .await;
+
+    let cookie_jar = cookie_jar.set_session(&user_session);
+    let reply = url_builder.redirect(&mas_router::Account::default());
+    Ok((cookie_jar, reply).into_response())
+}
@@ -0,0 +1,370 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Self-service invites: trusted users generate registration tokens which
+//! other people can use to create an account
+
+use axum::{
+    extract::{Form, State},
+    response::{Html, IntoResponse, Response},
+};
+use hyper::StatusCode;
+use mas_axum_utils::{
+    InternalError,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::{SiteConfig, User, UserAttributeDefinition, UserInvitesConfig};
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, Pagination, RepositoryAccess, RepositoryError,
+    user::{UserMetadataRepository, UserRegistrationTokenFilter, UserRegistrationTokenRepository},
+};
+use mas_templates::{InvitesContext, TemplateContext, Templates};
+use rand::distributions::{Alphanumeric, DistString};
+
+use crate::{
+    BoundActivityTracker, PreferredLanguage,
+    session::{SessionOrFallback, load_session_or_fallback},
+};
+
+/// Check whether the user is allowed to issue invites
+async fn is_trusted(
+    repo: &mut BoxRepository,
+    config: &UserInvitesConfig,
+    user: &User,
+) -> Result<bool, RepositoryError> {
+    let Some(attribute) = &config.trusted_attribute else {
+        return Ok(user.can_request_admin);
+    };
+
+    let entry = repo
+        .user_metadata()
+        .get(user, UserAttributeDefinition::METADATA_NAMESPACE, attribute)
+        .await?;
+
+    Ok(entry.is_some_and(|entry| entry.value == serde_json::Value::Bool(true)))
+}
+
+#[tracing::instrument(name = "handlers.views.invites.get", skip_all)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    cookie_jar: CookieJar,
+) -> Result<Response, InternalError> {
+    let Some(config) = &site_config.user_invites else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let (cookie_jar, maybe_session) = match load_session_or_fallback(
+        cookie_jar,
+        &clock,
+        &mut rng,
+        &templates,
+        &locale,
+        &mut repo,
+        &site_config,
+    )
+    .await?
+    {
+        SessionOrFallback::MaybeSession {
+            cookie_jar,
+            maybe_session,
+            ..
+        } => (cookie_jar, maybe_session),
+        SessionOrFallback::Fallback { response } => return Ok(response),
+    };
+
+    let Some(session) = maybe_session else {
+        let login = mas_router::Login::default();
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    if !is_trusted(&mut repo, config, &session.user).await? {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    activity_tracker
+        .record_browser_session(&clock, &session)
+        .await;
+
+    let filter = UserRegistrationTokenFilter::new(clock.now())
+        .with_created_by(&session.user)
+        .with_valid(true);
+    let invites = repo
+        .user_registration_token()
+        .list(filter, Pagination::first(config.max_active as usize))
+        .await?
+        .edges;
+
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
+    let ctx = InvitesContext::new(invites, config.max_active)
+        .with_session(session)
+        .with_csrf(csrf_token.form_value())
+        .with_language(locale);
+
+    let content = templates.render_invites(&ctx)?;
+    Ok((cookie_jar, Html(content)).into_response())
+}
+
+#[tracing::instrument(name = "handlers.views.invites.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    mut repo: BoxRepository,
+    cookie_jar: CookieJar,
+    Form(form): Form<ProtectedForm<()>>,
+) -> Result<Response, InternalError> {
+    let Some(config) = &site_config.user_invites else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    cookie_jar.verify_form(&clock, form)?;
+
+    let (cookie_jar, maybe_session) = match load_session_or_fallback(
+        cookie_jar,
+        &clock,
+        &mut rng,
+        &templates,
+        &locale,
+        &mut repo,
+        &site_config,
+    )
+    .await?
+    {
+        SessionOrFallback::MaybeSession {
+            cookie_jar,
+            maybe_session,
+            ..
+        } => (cookie_jar, maybe_session),
+        SessionOrFallback::Fallback { response } => return Ok(response),
+    };
+
+    let Some(session) = maybe_session else {
+        let login = mas_router::Login::default();
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    if !is_trusted(&mut repo, config, &session.user).await? {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let now = clock.now();
+    let filter = UserRegistrationTokenFilter::new(now)
+        .with_created_by(&session.user)
+        .with_valid(true);
+    let active = repo.user_registration_token().count(filter).await?;
+
+    // Users who already have as many valid invites as allowed are sent back to
+    // the list, which tells them about the limit
+    if active < config.max_active as usize {
+        let token = Alphanumeric.sample_string(&mut rng, 12);
+        repo.user_registration_token()
+            .add_invite(
+                &mut rng,
+                &clock,
+                &session.user,
+                token,
+                Some(config.usage_limit),
+                Some(now + config.ttl),
+                config.assign_referrer,
+            )
+            .await?;
+
+        repo.save().await?;
+    }
+
+    Ok((cookie_jar, url_builder.redirect(&mas_router::Invites)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use mas_axum_utils::SessionInfoExt;
+    use mas_data_model::{SiteConfig, UserAttributeDefinition, UserInvitesConfig};
+    use mas_storage::{
+        Clock, RepositoryAccess,
+        user::{
+            BrowserSessionRepository, UserMetadataRepository, UserRegistrationTokenFilter,
+            UserRegistrationTokenRepository, UserRepository,
+        },
+    };
+    use sqlx::PgPool;
+
+    use crate::test_utils::{
+        CookieHelper, RequestBuilderExt, ResponseExt, TestState, setup, test_site_config,
+    };
+
+    fn invites_config(trusted_attribute: Option<&str>) -> UserInvitesConfig {
+        UserInvitesConfig {
+            trusted_attribute: trusted_attribute.map(ToOwned::to_owned),
+            usage_limit: 1,
+            ttl: Duration::days(7),
+            max_active: 1,
+            assign_referrer: true,
+        }
+    }
+
+    fn extract_csrf(body: &str) -> String {
+        body.split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned()
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_disabled(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        let response = state.request(Request::get("/invites").empty()).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_generate_invite(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                user_invites: Some(invites_config(None)),
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        cookies.import(state.cookie_jar().set_session(&browser_session));
+
+        // Users who can't request admin access aren't trusted by default
+        let request = cookies.with_cookies(Request::get("/invites").empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::FORBIDDEN);
+
+        let mut repo = state.repository().await.unwrap();
+        let user = repo.user().set_can_request_admin(user, true).await.unwrap();
+        repo.save().await.unwrap();
+
+        let request = cookies.with_cookies(Request::get("/invites").empty());
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = extract_csrf(response.body());
+
+        // Generate an invite, twice: the second one is over the limit
+        for _ in 0..2 {
+            let request = Request::post("/invites").form(serde_json::json!({
+                "csrf": csrf_token,
+            }));
+            let request = cookies.with_cookies(request);
+            let response = state.request(request).await;
+            cookies.save_cookies(&response);
+            response.assert_status(StatusCode::SEE_OTHER);
+        }
+
+        let mut repo = state.repository().await.unwrap();
+        let filter = UserRegistrationTokenFilter::new(state.clock.now()).with_created_by(&user);
+        let page = repo
+            .user_registration_token()
+            .list(filter, mas_storage::Pagination::first(10))
+            .await
+            .unwrap();
+        assert_eq!(page.edges.len(), 1);
+        let invite = &page.edges[0];
+        assert_eq!(invite.usage_limit, Some(1));
+        assert_eq!(
+            invite.expires_at,
+            Some(state.clock.now() + Duration::days(7))
+        );
+        assert!(invite.assign_referrer);
+        repo.save().await.unwrap();
+
+        // The invite is shown on the page
+        let request = cookies.with_cookies(Request::get("/invites").empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains(&invite.token));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_trusted_attribute(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                user_invites: Some(invites_config(Some("trusted"))),
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        cookies.import(state.cookie_jar().set_session(&browser_session));
+
+        let request = cookies.with_cookies(Request::get("/invites").empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::FORBIDDEN);
+
+        let mut repo = state.repository().await.unwrap();
+        repo.user_metadata()
+            .add(
+                &mut rng,
+                &state.clock,
+                &user,
+                UserAttributeDefinition::METADATA_NAMESPACE.to_owned(),
+                "trusted".to_owned(),
+                serde_json::Value::Bool(true),
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = cookies.with_cookies(Request::get("/invites").empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+    }
+}
@@ -8,7 +8,7 @@ use std::sync::{Arc, LazyLock};
 
 use axum::{
     extract::{Form, Query, State},
-    response::{Html, IntoResponse, Response},
+    response::{Html, IntoResponse, Redirect, Response},
 };
 use axum_extra::typed_header::TypedHeader;
 use hyper::StatusCode;
@@ -17,29 +17,54 @@ use mas_axum_utils::{
     cookies::CookieJar,
     csrf::{CsrfExt, ProtectedForm},
 };
-use mas_data_model::oauth2::LoginHint;
+use mas_data_model::{
+    BrowserSession, Password, UpstreamOAuthAuthorizationSession, User, UserEmailAuthentication,
+    UserPasskey, UserTrustedDevice, oauth2::LoginHint,
+};
 use mas_i18n::DataLocale;
 use mas_matrix::HomeserverConnection;
-use mas_router::{UpstreamOAuth2Authorize, UrlBuilder};
+use mas_policy::Policy;
+use mas_router::{PostAuthAction, UpstreamOAuth2Authorize, UrlBuilder};
 use mas_storage::{
-    BoxClock, BoxRepository, BoxRng, Clock, RepositoryAccess,
+    BoxClock, BoxRepository, BoxRng, Clock, RepositoryAccess, RepositoryError,
+    oauth2::OAuth2AuthorizationGrantRepository,
+    queue::{QueueJobRepositoryExt as _, SendEmailAuthenticationCodeJob},
     upstream_oauth2::UpstreamOAuthProviderRepository,
-    user::{BrowserSessionRepository, UserPasswordRepository, UserRepository},
+    user::{
+        BrowserSessionRepository, UserDeletionRepository, UserEmailRepository,
+        UserPasskeyRepository, UserPasswordRepository, UserPhoneRepository, UserRepository,
+        UserTotpRepository, UserTrustedDeviceRepository,
+    },
 };
 use mas_templates::{
     AccountInactiveContext, FieldError, FormError, FormState, LoginContext, LoginFormField,
-    PostAuthContext, PostAuthContextInner, TemplateContext, Templates, ToFormState,
+    PasskeyLoginChallenge, PostAuthContext, PostAuthContextInner, TemplateContext, Templates,
+    ToFormState,
 };
 use opentelemetry::{Key, KeyValue, metrics::Counter};
-use rand::Rng;
-use serde::{Deserialize, Serialize};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Deserializer, Serialize};
+use ulid::Ulid;
 use zeroize::Zeroizing;
 
-use super::shared::OptionalPostAuthAction;
+use super::{
+    login_sms::phone_for_login,
+    login_totp::{FirstFactor, PendingFirstFactor, PendingLogin, SecondFactor},
+    restore_account::PendingRestore,
+    shared::OptionalPostAuthAction,
+};
 use crate::{
     BoundActivityTracker, Limiter, METER, PreferredLanguage, RequesterFingerprint, SiteConfig,
+    captcha::{Form as CaptchaForm, ProofOfWorkReplayCache},
+    login_risk,
+    oauth2::acr,
+    password_lockout::{self, Lockout},
+    password_policy,
     passwords::PasswordManager,
     session::{SessionOrFallback, load_session_or_fallback},
+    sign_in_notifications, sms, terms, trusted_device,
+    upstream_oauth2::health::ProviderHealthTracker,
+    webauthn::{self, AuthenticationResponse, RelyingParty},
 };
 
 static PASSWORD_LOGIN_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
@@ -49,18 +74,68 @@ static PASSWORD_LOGIN_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
         .with_unit("{attempt}")
         .build()
 });
+static PASSKEY_LOGIN_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("mas.user.passkey_login_attempt")
+        .with_description("Number of passkey login attempts")
+        .with_unit("{attempt}")
+        .build()
+});
 const RESULT: Key = Key::from_static_str("result");
 
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct LoginForm {
     username: String,
     password: String,
+
+    #[serde(flatten, skip_serializing)]
+    captcha: CaptchaForm,
 }
 
 impl ToFormState for LoginForm {
     type Field = LoginFormField;
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct DiscoverForm {
+    username: String,
+}
+
+impl ToFormState for DiscoverForm {
+    type Field = LoginFormField;
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct PasskeyLoginForm {
+    challenge_id: Ulid,
+    response: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct LoginParams {
+    /// When set to `true` or `1`, the login page is shown instead of
+    /// redirecting to the only upstream provider
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    no_auto: bool,
+}
+
+// Query parameters flags are commonly written both as `1`/`0` and as
+// `true`/`false`, so accept both
+fn deserialize_flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    match value.as_str() {
+        "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        _ => Err(serde::de::Error::invalid_value(
+            serde::de::Unexpected::Str(&value),
+            &"a boolean flag",
+        )),
+    }
+}
+
 #[tracing::instrument(name = "handlers.views.login.get", skip_all)]
 pub(crate) async fn get(
     mut rng: BoxRng,
@@ -70,13 +145,22 @@ pub(crate) async fn get(
     State(url_builder): State<UrlBuilder>,
     State(site_config): State<SiteConfig>,
     State(homeserver): State<Arc<dyn HomeserverConnection>>,
+    State(provider_health): State<ProviderHealthTracker>,
     mut repo: BoxRepository,
     activity_tracker: BoundActivityTracker,
+    requester: RequesterFingerprint,
     Query(query): Query<OptionalPostAuthAction>,
+    Query(params): Query<LoginParams>,
     cookie_jar: CookieJar,
 ) -> Result<Response, InternalError> {
     let (cookie_jar, maybe_session) = match load_session_or_fallback(
-        cookie_jar, &clock, &mut rng, &templates, &locale, &mut repo,
+        cookie_jar,
+        &clock,
+        &mut rng,
+        &templates,
+        &locale,
+        &mut repo,
+        &site_config,
     )
     .await?
     {
@@ -89,19 +173,58 @@ pub(crate) async fn get(
     };
 
     if let Some(session) = maybe_session {
-        activity_tracker
-            .record_browser_session(&clock, &session)
-            .await;
+        // If the authorization grant the user is coming from requires a more
+        // recent or a stronger authentication than the one of the current
+        // session, let them log in again instead of sending them back
+        let grant = match &query.post_auth_action {
+            Some(PostAuthAction::ContinueAuthorizationGrant { id }) => {
+                repo.oauth2_authorization_grant().lookup(*id).await?
+            }
+            _ => None,
+        };
+
+        let satisfied = if let Some(grant) = grant {
+            let last_authentication = repo
+                .browser_session()
+                .get_last_authentication(&session)
+                .await?;
+            let elevation = repo
+                .browser_session()
+                .get_active_elevation(&clock, &session)
+                .await?;
+            acr::requirement(
+                &site_config,
+                clock.now(),
+                &grant,
+                last_authentication.as_ref(),
+                elevation.as_ref(),
+            ) == acr::Requirement::Satisfied
+        } else {
+            true
+        };
+
+        if satisfied {
+            activity_tracker
+                .record_browser_session(&clock, &session)
+                .await;
 
-        let reply = query.go_next(&url_builder);
-        return Ok((cookie_jar, reply).into_response());
+            let reply = query.go_next(&url_builder);
+            return Ok((cookie_jar, reply).into_response());
+        }
     }
 
     let providers = repo.upstream_oauth_provider().all_enabled().await?;
 
-    // If password-based login is disabled, and there is only one upstream provider,
-    // we can directly start an authorization flow
-    if !site_config.password_login_enabled && providers.len() == 1 {
+    // If password-based, passkey and email code login are disabled, and there
+    // is only one upstream provider, we can directly start an authorization
+    // flow, unless the login page was explicitly asked for
+    if site_config.single_provider_auto_redirect
+        && !params.no_auto
+        && !site_config.password_login_enabled
+        && !site_config.passkeys_enabled
+        && !site_config.email_code_login_enabled
+        && providers.len() == 1
+    {
         let provider = providers.into_iter().next().unwrap();
 
         let mut destination = UpstreamOAuth2Authorize::new(provider.id);
@@ -118,11 +241,15 @@ pub(crate) async fn get(
         cookie_jar,
         FormState::default(),
         query,
-        &mut repo,
+        repo,
         &clock,
         &mut rng,
         &templates,
         &homeserver,
+        &site_config,
+        &provider_health,
+        &url_builder,
+        requester,
     )
     .await
 }
@@ -136,14 +263,23 @@ pub(crate) async fn post(
     State(site_config): State<SiteConfig>,
     State(templates): State<Templates>,
     State(url_builder): State<UrlBuilder>,
-    State(limiter): State<Limiter>,
-    State(homeserver): State<Arc<dyn HomeserverConnection>>,
+    (State(homeserver), State(provider_health)): (
+        State<Arc<dyn HomeserverConnection>>,
+        State<ProviderHealthTracker>,
+    ),
+    (State(http_client), State(proof_of_work_replay_cache)): (
+        State<reqwest::Client>,
+        State<ProofOfWorkReplayCache>,
+    ),
+    (State(limiter), requester): (State<Limiter>, RequesterFingerprint),
+    mut policy: Policy,
     mut repo: BoxRepository,
-    activity_tracker: BoundActivityTracker,
-    requester: RequesterFingerprint,
+    (user_agent, activity_tracker): (
+        Option<TypedHeader<headers::UserAgent>>,
+        BoundActivityTracker,
+    ),
     Query(query): Query<OptionalPostAuthAction>,
     cookie_jar: CookieJar,
-    user_agent: Option<TypedHeader<headers::UserAgent>>,
     Form(form): Form<ProtectedForm<LoginForm>>,
 ) -> Result<Response, InternalError> {
     let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
@@ -172,22 +308,66 @@ pub(crate) async fn post(
             cookie_jar,
             form_state,
             query,
-            &mut repo,
+            repo,
             &clock,
             &mut rng,
             &templates,
             &homeserver,
+            &site_config,
+            &provider_health,
+            &url_builder,
+            requester,
         )
         .await;
     }
 
+    // If too many attempts failed from this IP address, a CAPTCHA has to be
+    // solved before trying again
+    if let Some(captcha) =
+        password_lockout::captcha_for(&mut repo, &clock, &site_config, requester).await?
+    {
+        let passed_captcha = form
+            .captcha
+            .verify(
+                &activity_tracker,
+                &http_client,
+                &proof_of_work_replay_cache,
+                &clock,
+                url_builder.public_hostname(),
+                Some(&captcha),
+            )
+            .await;
+
+        if let Err(e) = passed_captcha {
+            tracing::warn!(error = &e as &dyn std::error::Error, "Invalid CAPTCHA");
+            let form_state = form_state.with_error_on_form(FormError::Captcha);
+            PASSWORD_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
+            return render(
+                locale,
+                cookie_jar,
+                form_state,
+                query,
+                repo,
+                &clock,
+                &mut rng,
+                &templates,
+                &homeserver,
+                &site_config,
+                &provider_health,
+                &url_builder,
+                requester,
+            )
+            .await;
+        }
+    }
+
     // Extract the localpart of the MXID, fallback to the bare username
     let username = homeserver
         .localpart(&form.username)
         .unwrap_or(&form.username);
 
     // First, lookup the user
-    let Some(user) = get_user_by_email_or_by_username(site_config, &mut repo, username).await?
+    let Some(user) = get_user_by_email_or_by_username(&site_config, &mut repo, username).await?
     else {
         let form_state = form_state.with_error_on_form(FormError::InvalidCredentials);
         PASSWORD_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
@@ -196,11 +376,15 @@ pub(crate) async fn post(
             cookie_jar,
             form_state,
             query,
-            &mut repo,
+            repo,
             &clock,
             &mut rng,
             &templates,
             &homeserver,
+            &site_config,
+            &provider_health,
+            &url_builder,
+            requester,
         )
         .await;
     };
@@ -215,11 +399,41 @@ pub(crate) async fn post(
             cookie_jar,
             form_state,
             query,
-            &mut repo,
+            repo,
+            &clock,
+            &mut rng,
+            &templates,
+            &homeserver,
+            &site_config,
+            &provider_health,
+            &url_builder,
+            requester,
+        )
+        .await;
+    }
+
+    // Refuse the attempt if the account is temporarily locked after too many
+    // failed attempts. The CAPTCHA required after too many failures from this IP
+    // address was checked above.
+    if password_lockout::check(&mut repo, &clock, &site_config, &user, requester).await?
+        == Lockout::Locked
+    {
+        let form_state = form_state.with_error_on_form(FormError::RateLimitExceeded);
+        PASSWORD_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
+        return render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            repo,
             &clock,
             &mut rng,
             &templates,
             &homeserver,
+            &site_config,
+            &provider_health,
+            &url_builder,
+            requester,
         )
         .await;
     }
@@ -235,11 +449,15 @@ pub(crate) async fn post(
             cookie_jar,
             form_state,
             query,
-            &mut repo,
+            repo,
             &clock,
             &mut rng,
             &templates,
             &homeserver,
+            &site_config,
+            &provider_health,
+            &url_builder,
+            requester,
         )
         .await;
     };
@@ -271,6 +489,16 @@ pub(crate) async fn post(
         }
         Ok(None) => user_password,
         Err(_) => {
+            password_lockout::record_failure(
+                &mut repo,
+                &mut rng,
+                &clock,
+                &site_config,
+                &user,
+                requester,
+            )
+            .await?;
+
             let form_state = form_state.with_error_on_form(FormError::InvalidCredentials);
             PASSWORD_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
             return render(
@@ -278,11 +506,15 @@ pub(crate) async fn post(
                 cookie_jar,
                 form_state,
                 query,
-                &mut repo,
+                repo,
                 &clock,
                 &mut rng,
                 &templates,
                 &homeserver,
+                &site_config,
+                &provider_health,
+                &url_builder,
+                requester,
             )
             .await;
         }
@@ -292,12 +524,8 @@ pub(crate) async fn post(
     // the user is locked or deactivated
     if user.deactivated_at.is_some() {
         PASSWORD_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
-        let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
-        let ctx = AccountInactiveContext::new(user)
-            .with_csrf(csrf_token.form_value())
-            .with_language(locale);
-        let content = templates.render_account_deactivated(&ctx)?;
-        return Ok((cookie_jar, Html(content)).into_response());
+        return render_deactivated(locale, cookie_jar, user, repo, &clock, &mut rng, &templates)
+            .await;
     }
 
     if user.locked_at.is_some() {
@@ -314,292 +542,1962 @@ pub(crate) async fn post(
     // want it to crash in tests/debug builds
     debug_assert!(user.is_valid());
 
-    // Start a new session
-    let user_session = repo
-        .browser_session()
-        .add(&mut rng, &clock, &user, user_agent)
-        .await?;
-
-    // And mark it as authenticated by the password
-    repo.browser_session()
-        .authenticate_with_password(&mut rng, &clock, &user_session, &user_password)
-        .await?;
-
-    repo.save().await?;
+    password_lockout::record_success(&mut repo, &site_config, &user).await?;
 
-    PASSWORD_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "success")]);
+    // Users whose password expired, or who were asked by an administrator to
+    // change it, have to change it before going further
+    if password_policy::must_change(&mut repo, &clock, &site_config, &user, &user_password).await? {
+        // This saves the upgraded password, if any
+        repo.save().await?;
 
-    activity_tracker
-        .record_browser_session(&clock, &user_session)
-        .await;
+        let cookie_jar = PendingLogin::new(&user, &user_password, &clock)
+            .with_expired_password()
+            .save(cookie_jar);
+        let destination = mas_router::LoginPasswordChange::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&destination)).into_response());
+    }
 
-    let cookie_jar = cookie_jar.set_session(&user_session);
-    let reply = query.go_next(&url_builder);
-    Ok((cookie_jar, reply).into_response())
+    finish_password_login(
+        repo,
+        &mut rng,
+        &clock,
+        &locale,
+        &site_config,
+        &url_builder,
+        &activity_tracker,
+        &mut policy,
+        cookie_jar,
+        query,
+        user_agent,
+        &user,
+        &user_password,
+    )
+    .await
 }
 
-async fn get_user_by_email_or_by_username<R: RepositoryAccess>(
-    site_config: SiteConfig,
-    repo: &mut R,
-    username_or_email: &str,
-) -> Result<Option<mas_data_model::User>, R::Error> {
-    if site_config.login_with_email_allowed && username_or_email.contains('@') {
-        let maybe_user_email = repo.user_email().find_by_email(username_or_email).await?;
+/// Whether a login has to go through a second factor before completing
+pub(crate) enum SecondFactorCheck {
+    /// The user has to enter their second factor first, on the page this
+    /// redirects to. The pending login is saved in the cookie jar.
+    Required(CookieJar, Redirect),
 
-        if let Some(user_email) = maybe_user_email {
-            let user = repo.user().lookup(user_email.user_id).await?;
+    /// The user can log in right away. This is set to the browser the user
+    /// trusted if it let them skip their second factor, so that its use can
+    /// be recorded.
+    NotRequired(CookieJar, Option<UserTrustedDevice>),
+}
 
-            if user.is_some() {
-                return Ok(user);
-            }
+/// Check whether a login which started with a password or a code sent by
+/// email has to go through a second factor
+///
+/// Users with a TOTP second factor, or who must add one, have to enter a code
+/// from their authenticator app. Otherwise, users with a confirmed phone
+/// number may have to enter a code sent to it by SMS, which is sent here.
+/// Browsers the user trusted after entering their second factor skip both.
+///
+/// Passkeys and upstream providers don't go through this, as a passkey
+/// already proves the possession of a key, and upstream providers handle
+/// their own second factors.
+///
+/// The repository has to be saved before following the redirection.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn check_second_factor(
+    repo: &mut BoxRepository,
+    rng: &mut (dyn RngCore + Send),
+    clock: &impl Clock,
+    locale: &DataLocale,
+    site_config: &SiteConfig,
+    url_builder: &UrlBuilder,
+    cookie_jar: CookieJar,
+    query: &OptionalPostAuthAction,
+    user: &User,
+    first_factor: impl Into<PendingFirstFactor>,
+) -> Result<SecondFactorCheck, InternalError> {
+    // Browsers the user trusted after entering their second factor skip it
+    let trusted_device = trusted_device::find(repo, clock, site_config, &cookie_jar, user).await?;
+
+    if site_config.totp_policy.is_enabled() {
+        let has_totp = repo
+            .user_totp()
+            .find_for_user(user)
+            .await?
+            .is_some_and(|totp| totp.is_confirmed());
+        let must_enroll = !has_totp && site_config.totp_policy.is_required_for(user);
+
+        if must_enroll || (has_totp && trusted_device.is_none()) {
+            let cookie_jar = PendingLogin::new(user, first_factor, clock).save(cookie_jar);
+            let destination = mas_router::LoginTotp::from(query.post_auth_action.clone());
+            return Ok(SecondFactorCheck::Required(
+                cookie_jar,
+                url_builder.redirect(&destination),
+            ));
         }
     }
 
-    let user = repo.user().find_by_username(username_or_email).await?;
+    let phone = if trusted_device.is_some() {
+        None
+    } else {
+        phone_for_login(repo, site_config, user).await?
+    };
 
-    Ok(user)
+    if let Some(phone) = phone {
+        // A code sent a few seconds ago is still valid, so it doesn't matter if
+        // this one is rate limited
+        sms::send_code(repo, rng, clock, &phone, locale.to_string(), true).await?;
+
+        let cookie_jar = PendingLogin::new(user, first_factor, clock).save(cookie_jar);
+        let destination = mas_router::LoginSms::from(query.post_auth_action.clone());
+        return Ok(SecondFactorCheck::Required(
+            cookie_jar,
+            url_builder.redirect(&destination),
+        ));
+    }
+
+    Ok(SecondFactorCheck::NotRequired(cookie_jar, trusted_device))
 }
 
-fn handle_login_hint(
-    mut ctx: LoginContext,
-    next: &PostAuthContext,
-    homeserver: &dyn HomeserverConnection,
-) -> LoginContext {
-    let form_state = ctx.form_state_mut();
+/// How the user proved who they are when logging in
+pub(crate) enum LoginMethod<'a> {
+    /// A password alone. This is set to the browser the user trusted if it
+    /// let them skip their second factor.
+    Password(&'a Password, Option<UserTrustedDevice>),
 
-    // Do not override username if coming from a failed login attempt
-    if form_state.has_value(LoginFormField::Username) {
-        return ctx;
-    }
+    /// A code sent by email alone. This is set to the browser the user
+    /// trusted if it let them skip their second factor.
+    EmailCode(&'a UserEmailAuthentication, Option<UserTrustedDevice>),
 
-    if let PostAuthContextInner::ContinueAuthorizationGrant { ref grant } = next.ctx {
-        let value = match grant.parse_login_hint(homeserver.homeserver()) {
-            LoginHint::MXID(mxid) => Some(mxid.localpart().to_owned()),
-            LoginHint::None => None,
-        };
-        form_state.set_value(LoginFormField::Username, value);
-    }
+    /// A password or a code sent by email, followed by a second factor
+    TwoFactors(&'a FirstFactor, SecondFactor<'a>),
 
-    ctx
+    /// A passkey
+    Passkey(&'a UserPasskey),
+
+    /// An upstream provider, once its authorization session was consumed
+    Upstream(&'a UpstreamOAuthAuthorizationSession),
 }
 
-async fn render(
-    locale: DataLocale,
-    cookie_jar: CookieJar,
-    form_state: FormState<LoginFormField>,
-    action: OptionalPostAuthAction,
-    repo: &mut impl RepositoryAccess,
+/// The browser session a login authenticates
+pub(crate) enum LoginSession<'a> {
+    /// Start a new session for the user, from the given user agent
+    New(&'a User, Option<String>),
+
+    /// A session which already exists, or which was started along with the
+    /// user. The user isn't told about those logins.
+    Started(BrowserSession),
+}
+
+/// Complete a login, once the user proved who they are
+///
+/// This records how the browser session was authenticated, tells the user
+/// about new sessions, saves the repository, and sets the session cookie. The
+/// returned redirection sends the user to the terms they have to accept first,
+/// if any, or to where they were going.
+///
+/// # Errors
+///
+/// Returns an error if the repository fails
+pub(crate) async fn finish_login(
+    mut repo: BoxRepository,
+    rng: &mut (dyn RngCore + Send),
     clock: &impl Clock,
-    rng: impl Rng,
-    templates: &Templates,
-    homeserver: &dyn HomeserverConnection,
-) -> Result<Response, InternalError> {
-    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(clock, rng);
-    let providers = repo.upstream_oauth_provider().all_enabled().await?;
+    locale: &DataLocale,
+    site_config: &SiteConfig,
+    url_builder: &UrlBuilder,
+    activity_tracker: &BoundActivityTracker,
+    cookie_jar: CookieJar,
+    query: &OptionalPostAuthAction,
+    session: LoginSession<'_>,
+    mut method: LoginMethod<'_>,
+) -> Result<(CookieJar, Redirect), RepositoryError> {
+    let trusted_device = match &mut method {
+        LoginMethod::Password(_, trusted_device) | LoginMethod::EmailCode(_, trusted_device) => {
+            trusted_device.take()
+        }
+        _ => None,
+    };
 
-    let ctx = LoginContext::default()
-        .with_form_state(form_state)
-        .with_upstream_providers(providers);
+    if let Some(trusted_device) = trusted_device {
+        repo.user_trusted_device()
+            .record_use(clock, trusted_device)
+            .await?;
+    }
 
-    let next = action
-        .load_context(repo)
-        .await
-        .map_err(InternalError::from_anyhow)?;
-    let ctx = if let Some(next) = next {
-        let ctx = handle_login_hint(ctx, &next, homeserver);
-        ctx.with_post_action(next)
-    } else {
-        ctx
+    let (user_session, is_new) = match session {
+        LoginSession::New(user, user_agent) => {
+            let user_session = repo
+                .browser_session()
+                .add(rng, clock, user, user_agent)
+                .await?;
+            (user_session, true)
+        }
+        LoginSession::Started(user_session) => (user_session, false),
     };
-    let ctx = ctx.with_csrf(csrf_token.form_value()).with_language(locale);
 
-    let content = templates.render_login(&ctx)?;
-    Ok((cookie_jar, Html(content)).into_response())
+    match method {
+        LoginMethod::Password(user_password, _) => {
+            repo.browser_session()
+                .authenticate_with_password(rng, clock, &user_session, user_password)
+                .await?;
+        }
+        LoginMethod::EmailCode(authentication, _) => {
+            repo.browser_session()
+                .authenticate_with_email_code(rng, clock, &user_session, authentication)
+                .await?;
+        }
+        LoginMethod::TwoFactors(first_factor, second_factor) => {
+            first_factor
+                .authenticate(&mut repo, rng, clock, &user_session, second_factor)
+                .await?;
+        }
+        LoginMethod::Passkey(passkey) => {
+            repo.browser_session()
+                .authenticate_with_passkey(rng, clock, &user_session, passkey)
+                .await?;
+        }
+        LoginMethod::Upstream(upstream_session) => {
+            repo.browser_session()
+                .authenticate_with_upstream(rng, clock, &user_session, upstream_session)
+                .await?;
+        }
+    }
+
+    if is_new {
+        // Tell the user about the login if it's from a new IP address or device
+        sign_in_notifications::schedule(&mut repo, rng, clock, site_config, &user_session, locale)
+            .await?;
+    }
+
+    // Users who haven't accepted the current terms yet are asked to first
+    let reply = terms::go_next(
+        &mut repo,
+        site_config,
+        &user_session.user,
+        query,
+        url_builder,
+    )
+    .await?;
+
+    repo.save().await?;
+
+    activity_tracker
+        .record_browser_session(clock, &user_session)
+        .await;
+
+    let cookie_jar = cookie_jar.set_session(&user_session);
+    Ok((cookie_jar, reply))
 }
 
-#[cfg(test)]
-mod test {
-    use hyper::{
-        Request, StatusCode,
-        header::{CONTENT_TYPE, LOCATION},
-    };
-    use mas_data_model::{
-        UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderTokenAuthMethod,
-    };
-    use mas_iana::jose::JsonWebSignatureAlg;
-    use mas_router::Route;
-    use mas_storage::{
-        RepositoryAccess,
-        upstream_oauth2::{UpstreamOAuthProviderParams, UpstreamOAuthProviderRepository},
+/// Finish a password login, once the password of the user was checked
+///
+/// Users with a second factor, or who must add one, are sent to the page asking
+/// for it, unless they trusted this browser. Others get a new session right
+/// away, unless the login policy finds the login suspicious, in which case they
+/// have to confirm it with a code sent by email.
+pub(crate) async fn finish_password_login(
+    mut repo: BoxRepository,
+    rng: &mut BoxRng,
+    clock: &BoxClock,
+    locale: &DataLocale,
+    site_config: &SiteConfig,
+    url_builder: &UrlBuilder,
+    activity_tracker: &BoundActivityTracker,
+    policy: &mut Policy,
+    cookie_jar: CookieJar,
+    query: OptionalPostAuthAction,
+    user_agent: Option<String>,
+    user: &User,
+    user_password: &Password,
+) -> Result<Response, InternalError> {
+    let (cookie_jar, trusted_device) = match check_second_factor(
+        &mut repo,
+        rng,
+        clock,
+        locale,
+        site_config,
+        url_builder,
+        cookie_jar,
+        &query,
+        user,
+        user_password,
+    )
+    .await?
+    {
+        SecondFactorCheck::Required(cookie_jar, destination) => {
+            // This also saves the upgraded or new password, if any
+            repo.save().await?;
+            return Ok((cookie_jar, destination).into_response());
+        }
+        SecondFactorCheck::NotRequired(cookie_jar, trusted_device) => (cookie_jar, trusted_device),
     };
-    use mas_templates::escape_html;
-    use oauth2_types::scope::OPENID;
-    use sqlx::PgPool;
-    use zeroize::Zeroizing;
 
-    use crate::{
-        SiteConfig,
-        test_utils::{
-            CookieHelper, RequestBuilderExt, ResponseExt, TestState, setup, test_site_config,
-        },
-    };
+    // Logins which look suspicious, for example because they come from a new
+    // country, have to be confirmed with a code sent by email
+    let suspicious = login_risk::is_suspicious(
+        &mut repo,
+        policy,
+        clock,
+        activity_tracker,
+        user,
+        user_agent.as_deref(),
+    )
+    .await?;
+
+    if suspicious {
+        let user_email = repo.user_email().primary(user).await?;
+        if let Some(user_email) = user_email {
+            let authentication = repo
+                .user_email()
+                .add_authentication_for_login(rng, clock, user_email.email, user)
+                .await?;
+
+            repo.queue_job()
+                .schedule_job(
+                    rng,
+                    clock,
+                    SendEmailAuthenticationCodeJob::new(&authentication, locale.to_string()),
+                )
+                .await?;
 
-    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
-    async fn test_password_disabled(pool: PgPool) {
-        setup();
-        let state = TestState::from_pool_with_site_config(
-            pool,
-            SiteConfig {
-                password_login_enabled: false,
-                ..test_site_config()
-            },
-        )
-        .await
-        .unwrap();
+            // This also saves the upgraded or new password, if any
+            repo.save().await?;
 
-        let mut rng = state.rng();
+            let cookie_jar = PendingLogin::new(user, user_password, clock)
+                .with_email_confirmation(&authentication)
+                .save(cookie_jar);
+            let destination = mas_router::LoginConfirmEmail::from(query.post_auth_action);
+            return Ok((cookie_jar, url_builder.redirect(&destination)).into_response());
+        }
 
-        // Without password login and no upstream providers, we should get an error
-        // message
-        let response = state.request(Request::get("/login").empty()).await;
-        response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
-        assert!(
-            response.body().contains("No login methods available"),
-            "Response body: {}",
-            response.body()
+        // There is no way to confirm the login, so let it through rather than
+        // locking the user out
+        tracing::warn!(
+            user.id = %user.id,
+            "Suspicious login from a user without an email address, letting it through"
         );
+    }
 
-        // Adding an upstream provider should redirect to it
-        let mut repo = state.repository().await.unwrap();
-        let first_provider = repo
-            .upstream_oauth_provider()
-            .add(
+    let reply = finish_login(
+        repo,
+        rng,
+        clock,
+        locale,
+        site_config,
+        url_builder,
+        activity_tracker,
+        cookie_jar,
+        &query,
+        LoginSession::New(user, user_agent),
+        LoginMethod::Password(user_password, trusted_device),
+    )
+    .await?;
+
+    PASSWORD_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "success")]);
+
+    Ok(reply.into_response())
+}
+
+#[tracing::instrument(name = "handlers.views.login.post_passkey", skip_all)]
+pub(crate) async fn post_passkey(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(site_config): State<SiteConfig>,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(homeserver): State<Arc<dyn HomeserverConnection>>,
+    State(provider_health): State<ProviderHealthTracker>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    requester: RequesterFingerprint,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    Form(form): Form<ProtectedForm<PasskeyLoginForm>>,
+) -> Result<Response, InternalError> {
+    let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
+    if !site_config.passkeys_enabled {
+        return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response());
+    }
+
+    let form = cookie_jar.verify_form(&clock, form)?;
+    let form_state = FormState::default().with_error_on_form(FormError::InvalidCredentials);
+
+    // Only challenges made to log in can be used here, not the ones made to
+    // register a passkey from a session
+    let challenge = repo
+        .user_passkey()
+        .lookup_challenge(form.challenge_id)
+        .await?
+        .filter(|challenge| {
+            challenge.user_session_id.is_none()
+                && webauthn::is_challenge_valid(challenge, clock.now())
+        });
+
+    let Some(challenge) = challenge else {
+        PASSKEY_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
+        return render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            repo,
+            &clock,
+            &mut rng,
+            &templates,
+            &homeserver,
+            &site_config,
+            &provider_health,
+            &url_builder,
+            requester,
+        )
+        .await;
+    };
+
+    // Consume the challenge right away, so that it can't be tried again
+    let challenge = repo
+        .user_passkey()
+        .complete_challenge(&clock, challenge)
+        .await?;
+
+    let response: Option<AuthenticationResponse> = serde_json::from_str(&form.response).ok();
+    let passkey = if let Some(response) = &response {
+        repo.user_passkey()
+            .find_by_credential_id(response.id.trim_end_matches('='))
+            .await?
+    } else {
+        None
+    };
+
+    let (Some(response), Some(passkey)) = (response, passkey) else {
+        PASSKEY_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
+        return render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            repo,
+            &clock,
+            &mut rng,
+            &templates,
+            &homeserver,
+            &site_config,
+            &provider_health,
+            &url_builder,
+            requester,
+        )
+        .await;
+    };
+
+    let sign_count = match RelyingParty::new(&url_builder)
+        .verify_authentication(&challenge, &passkey, &response)
+    {
+        Ok(sign_count) => sign_count,
+        Err(e) => {
+            tracing::warn!(
+                error = &e as &dyn std::error::Error,
+                %passkey.id,
+                "Failed to verify the passkey response"
+            );
+            PASSKEY_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
+            return render(
+                locale,
+                cookie_jar,
+                form_state,
+                query,
+                repo,
+                &clock,
                 &mut rng,
-                &state.clock,
-                UpstreamOAuthProviderParams {
-                    issuer: Some("https://first.com/".to_owned()),
-                    human_name: Some("First Ltd.".to_owned()),
-                    brand_name: None,
-                    scope: [OPENID].into_iter().collect(),
-                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
-                    token_endpoint_signing_alg: None,
-                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
-                    fetch_userinfo: false,
-                    userinfo_signed_response_alg: None,
-                    client_id: "client".to_owned(),
-                    encrypted_client_secret: None,
-                    claims_imports: UpstreamOAuthProviderClaimsImports::default(),
-                    authorization_endpoint_override: None,
-                    token_endpoint_override: None,
-                    userinfo_endpoint_override: None,
-                    jwks_uri_override: None,
-                    discovery_mode: mas_data_model::UpstreamOAuthProviderDiscoveryMode::Oidc,
-                    pkce_mode: mas_data_model::UpstreamOAuthProviderPkceMode::Auto,
-                    response_mode: None,
-                    additional_authorization_parameters: Vec::new(),
-                    forward_login_hint: false,
-                    ui_order: 0,
-                },
+                &templates,
+                &homeserver,
+                &site_config,
+                &provider_health,
+                &url_builder,
+                requester,
             )
+            .await;
+        }
+    };
+
+    let user = repo
+        .user()
+        .lookup(passkey.user_id)
+        .await?
+        .ok_or_else(|| InternalError::from_anyhow(anyhow::anyhow!("passkey user not found")))?;
+
+    if user.deactivated_at.is_some() {
+        PASSKEY_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
+        return render_deactivated(locale, cookie_jar, user, repo, &clock, &mut rng, &templates)
+            .await;
+    }
+
+    if user.locked_at.is_some() {
+        PASSKEY_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
+        repo.save().await?;
+        let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
+        let ctx = AccountInactiveContext::new(user)
+            .with_csrf(csrf_token.form_value())
+            .with_language(locale);
+        let content = templates.render_account_locked(&ctx)?;
+        return Ok((cookie_jar, Html(content)).into_response());
+    }
+
+    debug_assert!(user.is_valid());
+
+    let passkey = repo
+        .user_passkey()
+        .record_use(&clock, passkey, sign_count)
+        .await?;
+
+    let reply = finish_login(
+        repo,
+        &mut rng,
+        &clock,
+        &locale,
+        &site_config,
+        &url_builder,
+        &activity_tracker,
+        cookie_jar,
+        &query,
+        LoginSession::New(&user, user_agent),
+        LoginMethod::Passkey(&passkey),
+    )
+    .await?;
+
+    PASSKEY_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "success")]);
+
+    Ok(reply.into_response())
+}
+
+/// Handle the first step of the login page when home-realm discovery is
+/// enabled: send the user to the upstream provider mapped to the domain they
+/// entered, or ask them for their password
+#[tracing::instrument(name = "handlers.views.login.post_discover", skip_all)]
+pub(crate) async fn post_discover(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(site_config): State<SiteConfig>,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(homeserver): State<Arc<dyn HomeserverConnection>>,
+    State(provider_health): State<ProviderHealthTracker>,
+    mut repo: BoxRepository,
+    requester: RequesterFingerprint,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+    Form(form): Form<ProtectedForm<DiscoverForm>>,
+) -> Result<Response, InternalError> {
+    let Some(discovery) = &site_config.home_realm_discovery else {
+        return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response());
+    };
+
+    let form = cookie_jar.verify_form(&clock, form)?;
+
+    let username = form.username.trim();
+    let form_state = if username.is_empty() {
+        // Don't keep the empty value, so that the discovery step is shown again
+        FormState::default().with_error_on_field(LoginFormField::Username, FieldError::Required)
+    } else {
+        let provider = match discovery.provider_for(username) {
+            Some(id) => repo.upstream_oauth_provider().lookup(id).await?,
+            None => None,
+        };
+
+        if let Some(provider) = provider.filter(|p| p.enabled()) {
+            let mut destination = UpstreamOAuth2Authorize::new(provider.id);
+
+            if let Some(action) = query.post_auth_action {
+                destination = destination.and_then(action);
+            }
+
+            return Ok((cookie_jar, url_builder.redirect(&destination)).into_response());
+        }
+
+        // Ask for the password, with the username filled in. When password
+        // login is disabled, there is nothing else we can do for this user.
+        let form_state = form.to_form_state();
+        if site_config.password_login_enabled {
+            form_state
+        } else {
+            form_state.with_error_on_field(LoginFormField::Username, FieldError::Invalid)
+        }
+    };
+
+    render(
+        locale,
+        cookie_jar,
+        form_state,
+        query,
+        repo,
+        &clock,
+        &mut rng,
+        &templates,
+        &homeserver,
+        &site_config,
+        &provider_health,
+        &url_builder,
+        requester,
+    )
+    .await
+}
+
+async fn get_user_by_email_or_by_username<R: RepositoryAccess>(
+    site_config: &SiteConfig,
+    repo: &mut R,
+    username_or_email: &str,
+) -> Result<Option<mas_data_model::User>, R::Error> {
+    if site_config.login_with_email_allowed && username_or_email.contains('@') {
+        let maybe_user_email = repo.user_email().find_by_email(username_or_email).await?;
+
+        if let Some(user_email) = maybe_user_email {
+            let user = repo.user().lookup(user_email.user_id).await?;
+
+            if user.is_some() {
+                return Ok(user);
+            }
+        }
+    }
+
+    if site_config.login_with_phone_number_allowed && username_or_email.starts_with('+') {
+        if let Some(phone_number) = sms::normalize_phone_number(username_or_email) {
+            let maybe_user_phone = repo
+                .user_phone()
+                .find_by_phone_number(&phone_number)
+                .await?;
+
+            if let Some(user_phone) = maybe_user_phone {
+                let user = repo.user().lookup(user_phone.user_id).await?;
+
+                if user.is_some() {
+                    return Ok(user);
+                }
+            }
+        }
+    }
+
+    let user = repo.user().find_by_username(username_or_email).await?;
+
+    Ok(user)
+}
+
+fn handle_login_hint(
+    mut ctx: LoginContext,
+    next: &PostAuthContext,
+    homeserver: &dyn HomeserverConnection,
+) -> LoginContext {
+    let form_state = ctx.form_state_mut();
+
+    // Do not override username if coming from a failed login attempt
+    if form_state.has_value(LoginFormField::Username) {
+        return ctx;
+    }
+
+    if let PostAuthContextInner::ContinueAuthorizationGrant { ref grant } = next.ctx {
+        let value = match grant.parse_login_hint(homeserver.homeserver()) {
+            LoginHint::MXID(mxid) => Some(mxid.localpart().to_owned()),
+            LoginHint::None => None,
+        };
+        form_state.set_value(LoginFormField::Username, value);
+    }
+
+    ctx
+}
+
+/// Render the page telling a user that their account is deactivated. If the
+/// account is only scheduled for deletion, the user gets a chance to cancel it.
+async fn render_deactivated(
+    locale: DataLocale,
+    cookie_jar: CookieJar,
+    user: User,
+    mut repo: BoxRepository,
+    clock: &impl Clock,
+    mut rng: impl RngCore + Send,
+    templates: &Templates,
+) -> Result<Response, InternalError> {
+    let deletion = repo.user_deletion().find_pending(&user).await?;
+    repo.save().await?;
+
+    let cookie_jar = if let Some(deletion) = &deletion {
+        PendingRestore::new(&user, deletion, clock).save(cookie_jar)
+    } else {
+        cookie_jar
+    };
+
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(clock, &mut rng);
+    let mut ctx = AccountInactiveContext::new(user);
+    if let Some(deletion) = deletion {
+        ctx = ctx.with_deletion(deletion);
+    }
+    let ctx = ctx.with_csrf(csrf_token.form_value()).with_language(locale);
+    let content = templates.render_account_deactivated(&ctx)?;
+
+    Ok((cookie_jar, Html(content)).into_response())
+}
+
+async fn render(
+    locale: DataLocale,
+    cookie_jar: CookieJar,
+    form_state: FormState<LoginFormField>,
+    action: OptionalPostAuthAction,
+    mut repo: BoxRepository,
+    clock: &impl Clock,
+    mut rng: impl RngCore + Send,
+    templates: &Templates,
+    homeserver: &dyn HomeserverConnection,
+    site_config: &SiteConfig,
+    provider_health: &ProviderHealthTracker,
+    url_builder: &UrlBuilder,
+    requester: RequesterFingerprint,
+) -> Result<Response, InternalError> {
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(clock, &mut rng);
+    let providers = repo.upstream_oauth_provider().all_enabled().await?;
+    let captcha = password_lockout::captcha_for(&mut repo, clock, site_config, requester).await?;
+
+    // With home-realm discovery, the username is asked first on its own, and
+    // the password only once we know the user isn't going to an upstream
+    // provider
+    let discovery = site_config.home_realm_discovery.is_some()
+        && (!site_config.password_login_enabled || !form_state.has_value(LoginFormField::Username));
+
+    let mut ctx = LoginContext::default()
+        .with_form_state(form_state)
+        .with_upstream_providers(providers)
+        .with_unavailable_providers(provider_health.unavailable_providers().await);
+
+    if discovery {
+        ctx = ctx.with_discovery();
+    }
+
+    // Every render gets a fresh challenge, so that the passkey button and the
+    // autofill always work, even after a failed attempt
+    if site_config.passkeys_enabled {
+        let challenge = repo.user_passkey().add_challenge(&mut rng, clock).await?;
+        let options = RelyingParty::new(url_builder).request_options(&challenge);
+        ctx = ctx.with_passkey_challenge(PasskeyLoginChallenge::new(challenge.id, options));
+    }
+
+    let next = action
+        .load_context(&mut repo)
+        .await
+        .map_err(InternalError::from_anyhow)?;
+    let ctx = if let Some(next) = next {
+        let ctx = handle_login_hint(ctx, &next, homeserver);
+        ctx.with_post_action(next)
+    } else {
+        ctx
+    };
+    let ctx = ctx
+        .with_captcha(captcha)
+        .with_csrf(csrf_token.form_value())
+        .with_language(locale);
+
+    let content = templates.render_login(&ctx)?;
+
+    repo.save().await?;
+
+    Ok((cookie_jar, Html(content)).into_response())
+}
+
+#[cfg(test)]
+mod test {
+    use hyper::{
+        Request, StatusCode,
+        header::{CONTENT_TYPE, LOCATION},
+    };
+    use mas_data_model::{
+        HomeRealmDiscoveryConfig, PasswordLockoutConfig, UpstreamOAuthProviderClaimsImports,
+        UpstreamOAuthProviderTokenAuthMethod,
+    };
+    use mas_iana::jose::JsonWebSignatureAlg;
+    use mas_router::Route;
+    use mas_storage::{
+        Clock, RepositoryAccess,
+        upstream_oauth2::{UpstreamOAuthProviderParams, UpstreamOAuthProviderRepository},
+        user::{
+            UserEmailRepository, UserPasswordRepository, UserPhoneRepository,
+            UserRecoveryCodeRepository, UserTotpRepository,
+        },
+    };
+    use mas_templates::escape_html;
+    use oauth2_types::scope::OPENID;
+    use sqlx::PgPool;
+    use zeroize::Zeroizing;
+
+    use crate::{
+        SiteConfig,
+        test_utils::{
+            CookieHelper, RequestBuilderExt, ResponseExt, TestState, policy_factory, setup,
+            test_site_config,
+        },
+    };
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_disabled(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                password_login_enabled: false,
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut rng = state.rng();
+
+        // Without password login and no upstream providers, we should get an error
+        // message
+        let response = state.request(Request::get("/login").empty()).await;
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        assert!(
+            response.body().contains("No login methods available"),
+            "Response body: {}",
+            response.body()
+        );
+
+        // Adding an upstream provider should redirect to it
+        let mut repo = state.repository().await.unwrap();
+        let first_provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &state.clock,
+                UpstreamOAuthProviderParams {
+                    issuer: Some("https://first.com/".to_owned()),
+                    human_name: Some("First Ltd.".to_owned()),
+                    brand_name: None,
+                    scope: [OPENID].into_iter().collect(),
+                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+                    token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
+                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+                    fetch_userinfo: false,
+                    userinfo_signed_response_alg: None,
+                    client_id: "client".to_owned(),
+                    encrypted_client_secret: None,
+                    claims_imports: UpstreamOAuthProviderClaimsImports::default(),
+                    authorization_endpoint_override: None,
+                    token_endpoint_override: None,
+                    userinfo_endpoint_override: None,
+                    jwks_uri_override: None,
+                    discovery_mode: mas_data_model::UpstreamOAuthProviderDiscoveryMode::Oidc,
+                    pkce_mode: mas_data_model::UpstreamOAuthProviderPkceMode::Auto,
+                    response_mode: None,
+                    additional_authorization_parameters: Vec::new(),
+                    forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: false,
+                    deprovisioning: None,
+                    ui_order: 0,
+                },
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let first_provider_login = mas_router::UpstreamOAuth2Authorize::new(first_provider.id);
+
+        let response = state.request(Request::get("/login").empty()).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, &first_provider_login.path_and_query());
+
+        // Unless the login page is explicitly asked for
+        let response = state
+            .request(Request::get("/login?no_auto=true").empty())
+            .await;
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        assert!(
+            response
+                .body()
+                .contains(&escape_html(&first_provider_login.path_and_query()))
+        );
+
+        // The flag can also be written as `1`
+        let response = state
+            .request(Request::get("/login?no_auto=1").empty())
+            .await;
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        assert!(
+            response
+                .body()
+                .contains(&escape_html(&first_provider_login.path_and_query()))
+        );
+
+        // Setting it to false still redirects
+        let response = state
+            .request(Request::get("/login?no_auto=false").empty())
+            .await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, &first_provider_login.path_and_query());
+
+        let response = state
+            .request(Request::get("/login?no_auto=0").empty())
+            .await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, &first_provider_login.path_and_query());
+
+        // Adding a second provider should show a login page with both providers
+        let mut repo = state.repository().await.unwrap();
+        let second_provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &state.clock,
+                UpstreamOAuthProviderParams {
+                    issuer: Some("https://second.com/".to_owned()),
+                    human_name: None,
+                    brand_name: None,
+                    scope: [OPENID].into_iter().collect(),
+                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+                    token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
+                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+                    fetch_userinfo: false,
+                    userinfo_signed_response_alg: None,
+                    client_id: "client".to_owned(),
+                    encrypted_client_secret: None,
+                    claims_imports: UpstreamOAuthProviderClaimsImports::default(),
+                    authorization_endpoint_override: None,
+                    token_endpoint_override: None,
+                    userinfo_endpoint_override: None,
+                    jwks_uri_override: None,
+                    discovery_mode: mas_data_model::UpstreamOAuthProviderDiscoveryMode::Oidc,
+                    pkce_mode: mas_data_model::UpstreamOAuthProviderPkceMode::Auto,
+                    response_mode: None,
+                    additional_authorization_parameters: Vec::new(),
+                    forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: false,
+                    deprovisioning: None,
+                    ui_order: 1,
+                },
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let second_provider_login = mas_router::UpstreamOAuth2Authorize::new(second_provider.id);
+
+        let response = state.request(Request::get("/login").empty()).await;
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        assert!(response.body().contains(&escape_html("First Ltd.")));
+        assert!(
+            response
+                .body()
+                .contains(&escape_html(&first_provider_login.path_and_query()))
+        );
+        assert!(response.body().contains(&escape_html("second.com")));
+        assert!(
+            response
+                .body()
+                .contains(&escape_html(&second_provider_login.path_and_query()))
+        );
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_email_code_login_no_auto_redirect(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                password_login_enabled: false,
+                email_code_login_enabled: true,
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+        let provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &state.clock,
+                UpstreamOAuthProviderParams {
+                    issuer: Some("https://first.com/".to_owned()),
+                    human_name: Some("First Ltd.".to_owned()),
+                    brand_name: None,
+                    scope: [OPENID].into_iter().collect(),
+                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+                    token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
+                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+                    fetch_userinfo: false,
+                    userinfo_signed_response_alg: None,
+                    client_id: "client".to_owned(),
+                    encrypted_client_secret: None,
+                    claims_imports: UpstreamOAuthProviderClaimsImports::default(),
+                    authorization_endpoint_override: None,
+                    token_endpoint_override: None,
+                    userinfo_endpoint_override: None,
+                    jwks_uri_override: None,
+                    discovery_mode: mas_data_model::UpstreamOAuthProviderDiscoveryMode::Oidc,
+                    pkce_mode: mas_data_model::UpstreamOAuthProviderPkceMode::Auto,
+                    response_mode: None,
+                    additional_authorization_parameters: Vec::new(),
+                    forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: false,
+                    deprovisioning: None,
+                    ui_order: 0,
+                },
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let provider_login = mas_router::UpstreamOAuth2Authorize::new(provider.id);
+
+        // Users can still log in with an email code, so the login page is shown
+        // instead of redirecting to the only provider
+        let response = state.request(Request::get("/login").empty()).await;
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        assert!(
+            response
+                .body()
+                .contains(&escape_html(&provider_login.path_and_query()))
+        );
+    }
+
+    async fn user_with_password(
+        state: &TestState,
+        username: &str,
+        password: &str,
+    ) -> mas_data_model::User {
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, username.to_owned())
+            .await
+            .unwrap();
+        let (version, hash) = state
+            .password_manager
+            .hash(&mut rng, Zeroizing::new(password.to_owned()))
+            .await
+            .unwrap();
+        repo.user_password()
+            .add(&mut rng, &state.clock, &user, version, hash, None)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        user
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_login(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let cookies = CookieHelper::new();
+
+        // Provision a user with a password
+        user_with_password(&state, "john", "hunter2").await;
+
+        // Render the login page to get a CSRF token
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        // Extract the CSRF token from the response body
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
+
+        // Submit the login form
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        // Now if we get to the home page, we should see the user's username
+        let request = Request::get("/").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        assert!(response.body().contains("john"));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_login_with_mxid(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let cookies = CookieHelper::new();
+
+        // Provision a user with a password
+        user_with_password(&state, "john", "hunter2").await;
+
+        // Render the login page to get a CSRF token
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        // Extract the CSRF token from the response body
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
+
+        // Submit the login form
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "@john:example.com",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        // Now if we get to the home page, we should see the user's username
+        let request = Request::get("/").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        assert!(response.body().contains("john"));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_login_with_mxid_wrong_server(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let cookies = CookieHelper::new();
+
+        // Provision a user with a password
+        user_with_password(&state, "john", "hunter2").await;
+
+        // Render the login page to get a CSRF token
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        // Extract the CSRF token from the response body
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
+
+        // Submit the login form
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "@john:something.corp",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+
+        // This shouldn't have worked, we're back on the login page
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("Invalid credentials"));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_login_with_phone_number(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                login_with_phone_number_allowed: true,
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+
+        // Provision a user with a password and a confirmed phone number
+        let user = user_with_password(&state, "john", "hunter2").await;
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+        let phone = repo
+            .user_phone()
+            .add(&mut rng, &state.clock, &user, "+33612345678".to_owned())
+            .await
+            .unwrap();
+        repo.user_phone()
+            .confirm(&state.clock, phone)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        // Render the login page to get a CSRF token
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("Username, Email or Phone number"));
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
+
+        // Submit the login form with the phone number, formatted differently
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "+33 6 12 34 56 78",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        // Now if we get to the home page, we should see the user's username
+        let request = Request::get("/").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("john"));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_login_rate_limit(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let mut rng = state.rng();
+        let cookies = CookieHelper::new();
+
+        // Provision a user without a password.
+        // We don't give that user a password, so that we skip hashing it in this test.
+        // It will still be rate-limited
+        let mut repo = state.repository().await.unwrap();
+        repo.user()
+            .add(&mut rng, &state.clock, "john".to_owned())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        // Render the login page to get a CSRF token
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        // Extract the CSRF token from the response body
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
+
+        // Submit the login form
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+
+        // First three attempts should just tell about the invalid credentials
+        let response = state.request(request.clone()).await;
+        response.assert_status(StatusCode::OK);
+        let body = response.body();
+        assert!(body.contains("Invalid credentials"));
+        assert!(!body.contains("too many requests"));
+
+        let response = state.request(request.clone()).await;
+        response.assert_status(StatusCode::OK);
+        let body = response.body();
+        assert!(body.contains("Invalid credentials"));
+        assert!(!body.contains("too many requests"));
+
+        let response = state.request(request.clone()).await;
+        response.assert_status(StatusCode::OK);
+        let body = response.body();
+        assert!(body.contains("Invalid credentials"));
+        assert!(!body.contains("too many requests"));
+
+        // The fourth attempt should be rate-limited
+        let response = state.request(request.clone()).await;
+        response.assert_status(StatusCode::OK);
+        let body = response.body();
+        assert!(!body.contains("Invalid credentials"));
+        assert!(body.contains("too many requests"));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_login_lockout(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                password_lockout: Some(PasswordLockoutConfig {
+                    max_failures: 2,
+                    max_failures_per_ip: 50,
+                    duration: chrono::Duration::minutes(15),
+                }),
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+
+        let user = user_with_password(&state, "john", "hunter2").await;
+
+        // Render the login page to get a CSRF token
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        // Two attempts with the wrong password lock the account
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "wrong",
+        }));
+        let request = cookies.with_cookies(request);
+        for _ in 0..2 {
+            let response = state.request(request.clone()).await;
+            response.assert_status(StatusCode::OK);
+            let body = response.body();
+            assert!(body.contains("Invalid credentials"));
+            assert!(!body.contains("too many requests"));
+        }
+
+        let mut repo = state.repository().await.unwrap();
+        let since = state.clock.now() - chrono::Duration::minutes(15);
+        let failures = repo
+            .user_password()
+            .count_failures(&user, since)
+            .await
+            .unwrap();
+        assert_eq!(failures, 2);
+
+        // The lockout is recorded
+        let lockouts = repo
+            .user_password()
+            .count_lockouts(&user, since)
+            .await
+            .unwrap();
+        assert_eq!(lockouts, 1);
+        repo.save().await.unwrap();
+
+        // Even the right password is now refused
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body = response.body();
+        assert!(!body.contains("Invalid credentials"));
+        assert!(body.contains("too many requests"));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_login_locked_account(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let cookies = CookieHelper::new();
+
+        // Provision a user with a password
+        let user = user_with_password(&state, "john", "hunter2").await;
+
+        // Lock the user
+        let mut repo = state.repository().await.unwrap();
+        repo.user().lock(&state.clock, user).await.unwrap();
+        repo.save().await.unwrap();
+
+        // Render the login page to get a CSRF token
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        // Extract the CSRF token from the response body
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
+
+        // Submit the login form
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        assert!(response.body().contains("Account locked"));
+
+        // A bad password should not disclose that the account is locked
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "badpassword",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        assert!(!response.body().contains("Account locked"));
+        assert!(response.body().contains("Invalid credentials"));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_login_deactivated_account(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let cookies = CookieHelper::new();
+
+        // Provision a user with a password
+        let user = user_with_password(&state, "john", "hunter2").await;
+
+        // Deactivate the user
+        let mut repo = state.repository().await.unwrap();
+        repo.user().deactivate(&state.clock, user).await.unwrap();
+        repo.save().await.unwrap();
+
+        // Render the login page to get a CSRF token
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        // Extract the CSRF token from the response body
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
+
+        // Submit the login form
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        assert!(response.body().contains("Account deleted"));
+
+        // A bad password should not disclose that the account is deleted
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "badpassword",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        assert!(!response.body().contains("Account deleted"));
+        assert!(response.body().contains("Invalid credentials"));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_login_with_totp(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                totp_policy: mas_data_model::TotpPolicy::Required,
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+
+        // Provision a user with a password
+        let user = user_with_password(&state, "john", "hunter2").await;
+
+        // Render the login page to get a CSRF token
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
+
+        // Submit the login form, which should ask for a second factor
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, "/login/totp");
+
+        // The user doesn't have a second factor yet, so they have to add one
+        let request = Request::get("/login/totp").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        let mut repo = state.repository().await.unwrap();
+        let totp = repo
+            .user_totp()
+            .find_for_user(&user)
+            .await
+            .unwrap()
+            .unwrap();
+        repo.save().await.unwrap();
+        assert!(!totp.is_confirmed());
+
+        let secret = state
+            .encrypter
+            .decrypt_string(&totp.encrypted_secret)
+            .unwrap();
+        assert!(
+            response
+                .body()
+                .contains(&crate::totp::encode_secret(&secret))
+        );
+        let code = crate::totp::generate(&secret, state.clock.now());
+
+        // A bad code is rejected
+        let request = Request::post("/login/totp").form(serde_json::json!({
+            "csrf": csrf_token,
+            "code": "abcdef",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+
+        // The right code logs the user in, and shows them their recovery codes
+        let request = Request::post("/login/totp").form(serde_json::json!({
+            "csrf": csrf_token,
+            "code": code,
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let recovery_code = response
+            .body()
+            .split("<code class=\"cpd-text-body-md-semibold\">")
+            .nth(1)
+            .unwrap()
+            .split('<')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        let request = Request::get("/").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("john"));
+
+        // The second factor is now confirmed
+        let mut repo = state.repository().await.unwrap();
+        let totp = repo
+            .user_totp()
+            .find_for_user(&user)
+            .await
+            .unwrap()
+            .unwrap();
+        repo.save().await.unwrap();
+        assert!(totp.is_confirmed());
+
+        // Log in again from another browser, with a recovery code this time
+        let cookies = CookieHelper::new();
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, "/login/totp");
+
+        let request = Request::get("/login/recovery-code").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        // The code is accepted however it is formatted
+        let request = Request::post("/login/recovery-code").form(serde_json::json!({
+            "csrf": csrf_token,
+            "code": recovery_code.to_lowercase(),
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        let request = Request::get("/").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("john"));
+
+        // The code was consumed
+        let mut repo = state.repository().await.unwrap();
+        assert_eq!(
+            repo.user_recovery_code().count_unused(&user).await.unwrap(),
+            9
+        );
+        repo.save().await.unwrap();
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_login_with_sms(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                sms_second_factor_enabled: true,
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+
+        // Provision a user with a password and a confirmed phone number
+        let user = user_with_password(&state, "john", "hunter2").await;
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+        let phone = repo
+            .user_phone()
+            .add(&mut rng, &state.clock, &user, "+33612345678".to_owned())
+            .await
+            .unwrap();
+        let phone = repo
+            .user_phone()
+            .confirm(&state.clock, phone)
             .await
             .unwrap();
         repo.save().await.unwrap();
 
-        let first_provider_login = mas_router::UpstreamOAuth2Authorize::new(first_provider.id);
+        // Render the login page to get a CSRF token
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
 
-        let response = state.request(Request::get("/login").empty()).await;
+        // Submit the login form, which should ask for the code sent by SMS
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
         response.assert_status(StatusCode::SEE_OTHER);
-        response.assert_header_value(LOCATION, &first_provider_login.path_and_query());
+        response.assert_header_value(LOCATION, "/login/sms");
 
-        // Adding a second provider should show a login page with both providers
+        // The phone number is shown masked
+        let request = Request::get("/login/sms").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains(&phone.masked_phone_number()));
+        assert!(!response.body().contains("+33612345678"));
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        // The code is sent by the task worker, which doesn't run here, so add one
+        // directly
         let mut repo = state.repository().await.unwrap();
-        let second_provider = repo
-            .upstream_oauth_provider()
-            .add(
+        repo.user_phone()
+            .add_code(
                 &mut rng,
                 &state.clock,
-                UpstreamOAuthProviderParams {
-                    issuer: Some("https://second.com/".to_owned()),
-                    human_name: None,
-                    brand_name: None,
-                    scope: [OPENID].into_iter().collect(),
-                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
-                    token_endpoint_signing_alg: None,
-                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
-                    fetch_userinfo: false,
-                    userinfo_signed_response_alg: None,
-                    client_id: "client".to_owned(),
-                    encrypted_client_secret: None,
-                    claims_imports: UpstreamOAuthProviderClaimsImports::default(),
-                    authorization_endpoint_override: None,
-                    token_endpoint_override: None,
-                    userinfo_endpoint_override: None,
-                    jwks_uri_override: None,
-                    discovery_mode: mas_data_model::UpstreamOAuthProviderDiscoveryMode::Oidc,
-                    pkce_mode: mas_data_model::UpstreamOAuthProviderPkceMode::Auto,
-                    response_mode: None,
-                    additional_authorization_parameters: Vec::new(),
-                    forward_login_hint: false,
-                    ui_order: 1,
-                },
+                &phone,
+                chrono::Duration::minutes(10),
+                "123456".to_owned(),
             )
             .await
             .unwrap();
         repo.save().await.unwrap();
 
-        let second_provider_login = mas_router::UpstreamOAuth2Authorize::new(second_provider.id);
+        // Asking for a new code right away is rate limited
+        let request = Request::post("/login/sms").form(serde_json::json!({
+            "csrf": csrf_token,
+            "resend": "true",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
 
-        let response = state.request(Request::get("/login").empty()).await;
+        // A bad code is rejected
+        let request = Request::post("/login/sms").form(serde_json::json!({
+            "csrf": csrf_token,
+            "code": "654321",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
         response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
-        assert!(response.body().contains(&escape_html("First Ltd.")));
-        assert!(
-            response
-                .body()
-                .contains(&escape_html(&first_provider_login.path_and_query()))
-        );
-        assert!(response.body().contains(&escape_html("second.com")));
+
+        // The right code logs the user in
+        let request = Request::post("/login/sms").form(serde_json::json!({
+            "csrf": csrf_token,
+            "code": "123456",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        let request = Request::get("/").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("john"));
+
+        // The code can't be used twice
+        let mut repo = state.repository().await.unwrap();
         assert!(
-            response
-                .body()
-                .contains(&escape_html(&second_provider_login.path_and_query()))
+            repo.user_phone()
+                .find_code(&phone, "123456")
+                .await
+                .unwrap()
+                .is_none()
         );
+        repo.save().await.unwrap();
     }
 
-    async fn user_with_password(
-        state: &TestState,
-        username: &str,
-        password: &str,
-    ) -> mas_data_model::User {
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_password_login_unknown_location(pool: PgPool) {
+        setup();
+        let mut state = TestState::from_pool(pool).await.unwrap();
+        state.policy_factory = policy_factory(
+            "example.com",
+            serde_json::json!({
+                "login": {
+                    "new_country": true,
+                    "anonymizing_networks": {
+                        "organizations": {"substrings": ["VPN"]},
+                    },
+                    "impossible_travel_seconds": 10800,
+                },
+            }),
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+
+        // Provision a user with a password and an email address to confirm
+        // suspicious logins with
+        let user = user_with_password(&state, "john", "hunter2").await;
         let mut rng = state.rng();
         let mut repo = state.repository().await.unwrap();
-        let user = repo
-            .user()
-            .add(&mut rng, &state.clock, username.to_owned())
-            .await
-            .unwrap();
-        let (version, hash) = state
-            .password_manager
-            .hash(&mut rng, Zeroizing::new(password.to_owned()))
-            .await
-            .unwrap();
-        repo.user_password()
-            .add(&mut rng, &state.clock, &user, version, hash, None)
+        repo.user_email()
+            .add(&mut rng, &state.clock, &user, "john@example.com".to_owned())
             .await
             .unwrap();
         repo.save().await.unwrap();
-        user
+
+        // There is no pending login to confirm yet
+        let request = Request::get("/login/confirm-email").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, "/login");
+
+        // Render the login page to get a CSRF token
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
+
+        // Requests in tests have no known location, so the policy has nothing to
+        // flag and the user is logged in right away
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        let request = Request::get("/").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("john"));
     }
 
     #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
-    async fn test_password_login(pool: PgPool) {
+    async fn test_password_login_expired_password(pool: PgPool) {
         setup();
-        let state = TestState::from_pool(pool).await.unwrap();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                password_max_age: Some(chrono::Duration::days(90)),
+                password_history_size: 1,
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
         let cookies = CookieHelper::new();
 
-        // Provision a user with a password
         user_with_password(&state, "john", "hunter2").await;
+        state.clock.advance(chrono::Duration::days(91));
 
         // Render the login page to get a CSRF token
         let request = Request::get("/login").empty();
@@ -607,8 +2505,6 @@ mod test {
         let response = state.request(request).await;
         cookies.save_cookies(&response);
         response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
-        // Extract the CSRF token from the response body
         let csrf_token = response
             .body()
             .split("name=\"csrf\" value=\"")
@@ -618,7 +2514,7 @@ mod test {
             .next()
             .unwrap();
 
-        // Submit the login form
+        // The password is right, but it expired
         let request = Request::post("/login").form(serde_json::json!({
             "csrf": csrf_token,
             "username": "john",
@@ -628,34 +2524,117 @@ mod test {
         let response = state.request(request).await;
         cookies.save_cookies(&response);
         response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, "/login/password-change");
 
-        // Now if we get to the home page, we should see the user's username
-        let request = Request::get("/").empty();
+        // The password change can't be skipped
+        let request = Request::get("/login/totp").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, "/login");
+
+        let request = Request::get("/login/password-change").empty();
         let request = cookies.with_cookies(request);
         let response = state.request(request).await;
         cookies.save_cookies(&response);
         response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        // The expired password can't be set again
+        let request = Request::post("/login/password-change").form(serde_json::json!({
+            "csrf": csrf_token,
+            "new_password": "hunter2",
+            "new_password_confirm": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        assert!(
+            response
+                .body()
+                .contains(&escape_html("You have already used this password recently"))
+        );
+
+        // A new password logs the user in
+        let request = Request::post("/login/password-change").form(serde_json::json!({
+            "csrf": csrf_token,
+            "new_password": "correct horse battery staple",
+            "new_password_confirm": "correct horse battery staple",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        let request = Request::get("/").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
         assert!(response.body().contains("john"));
     }
 
     #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
-    async fn test_password_login_with_mxid(pool: PgPool) {
+    async fn test_password_login_change_required(pool: PgPool) {
         setup();
         let state = TestState::from_pool(pool).await.unwrap();
         let cookies = CookieHelper::new();
 
-        // Provision a user with a password
-        user_with_password(&state, "john", "hunter2").await;
+        let user = user_with_password(&state, "john", "hunter2").await;
+
+        // An administrator requires the user to change their password
+        let mut repo = state.repository().await.unwrap();
+        let password = repo.user_password().active(&user).await.unwrap().unwrap();
+        repo.user_password()
+            .require_change(&state.clock, password)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        // Render the login page to get a CSRF token
+        let request = Request::get("/login").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap();
+
+        let request = Request::post("/login").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "john",
+            "password": "hunter2",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, "/login/password-change");
 
-        // Render the login page to get a CSRF token
-        let request = Request::get("/login").empty();
+        let request = Request::get("/login/password-change").empty();
         let request = cookies.with_cookies(request);
         let response = state.request(request).await;
         cookies.save_cookies(&response);
         response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
-        // Extract the CSRF token from the response body
+        assert!(
+            response
+                .body()
+                .contains("An administrator requires you to choose a new password")
+        );
         let csrf_token = response
             .body()
             .split("name=\"csrf\" value=\"")
@@ -663,46 +2642,71 @@ mod test {
             .unwrap()
             .split('\"')
             .next()
-            .unwrap();
+            .unwrap()
+            .to_owned();
 
-        // Submit the login form
-        let request = Request::post("/login").form(serde_json::json!({
+        let request = Request::post("/login/password-change").form(serde_json::json!({
             "csrf": csrf_token,
-            "username": "@john:example.com",
-            "password": "hunter2",
+            "new_password": "correct horse battery staple",
+            "new_password_confirm": "correct horse battery staple",
         }));
         let request = cookies.with_cookies(request);
         let response = state.request(request).await;
         cookies.save_cookies(&response);
         response.assert_status(StatusCode::SEE_OTHER);
 
-        // Now if we get to the home page, we should see the user's username
         let request = Request::get("/").empty();
         let request = cookies.with_cookies(request);
         let response = state.request(request).await;
-        cookies.save_cookies(&response);
         response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
         assert!(response.body().contains("john"));
+
+        // The new password doesn't have to be changed
+        let mut repo = state.repository().await.unwrap();
+        let password = repo.user_password().active(&user).await.unwrap().unwrap();
+        assert!(!password.change_required());
     }
 
     #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
-    async fn test_password_login_with_mxid_wrong_server(pool: PgPool) {
+    async fn test_email_code_login(pool: PgPool) {
         setup();
-        let state = TestState::from_pool(pool).await.unwrap();
+        let state = TestState::from_pool_with_site_config(
+            pool.clone(),
+            SiteConfig {
+                email_code_login_enabled: true,
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
         let cookies = CookieHelper::new();
 
-        // Provision a user with a password
-        user_with_password(&state, "john", "hunter2").await;
+        // Provision a user with an email address
+        let user = user_with_password(&state, "john", "hunter2").await;
+        let mut repo = state.repository().await.unwrap();
+        repo.user_email()
+            .add(
+                &mut state.rng(),
+                &state.clock,
+                &user,
+                "john@example.com".to_owned(),
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
 
-        // Render the login page to get a CSRF token
+        // The login page links to the email login
         let request = Request::get("/login").empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("/login/email"));
+
+        // Render the email login page to get a CSRF token
+        let request = Request::get("/login/email").empty();
         let request = cookies.with_cookies(request);
         let response = state.request(request).await;
         cookies.save_cookies(&response);
         response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
-        // Extract the CSRF token from the response body
         let csrf_token = response
             .body()
             .split("name=\"csrf\" value=\"")
@@ -710,47 +2714,51 @@ mod test {
             .unwrap()
             .split('\"')
             .next()
-            .unwrap();
+            .unwrap()
+            .to_owned();
 
-        // Submit the login form
-        let request = Request::post("/login").form(serde_json::json!({
+        let request = Request::post("/login/email").form(serde_json::json!({
             "csrf": csrf_token,
-            "username": "@john:something.corp",
-            "password": "hunter2",
+            "email": "john@example.com",
         }));
         let request = cookies.with_cookies(request);
         let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, "/login/email/code");
 
-        // This shouldn't have worked, we're back on the login page
-        response.assert_status(StatusCode::OK);
-        assert!(response.body().contains("Invalid credentials"));
-    }
-
-    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
-    async fn test_password_login_rate_limit(pool: PgPool) {
-        setup();
-        let state = TestState::from_pool(pool).await.unwrap();
-        let mut rng = state.rng();
-        let cookies = CookieHelper::new();
-
-        // Provision a user without a password.
-        // We don't give that user a password, so that we skip hashing it in this test.
-        // It will still be rate-limited
+        // The code is generated by the job sending the email, so add one here
+        let authentication_id: sqlx::types::Uuid = sqlx::query_scalar(
+            "SELECT user_email_authentication_id FROM user_email_authentications",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("Authentication to be started");
         let mut repo = state.repository().await.unwrap();
-        repo.user()
-            .add(&mut rng, &state.clock, "john".to_owned())
+        let authentication = repo
+            .user_email()
+            .lookup_authentication(authentication_id.into())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(authentication.user_id, Some(user.id));
+        repo.user_email()
+            .add_authentication_code(
+                &mut state.rng(),
+                &state.clock,
+                chrono::Duration::minutes(5),
+                &authentication,
+                "123456".to_owned(),
+            )
             .await
             .unwrap();
         repo.save().await.unwrap();
 
-        // Render the login page to get a CSRF token
-        let request = Request::get("/login").empty();
+        let request = Request::get("/login/email/code").empty();
         let request = cookies.with_cookies(request);
         let response = state.request(request).await;
         cookies.save_cookies(&response);
         response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
-        // Extract the CSRF token from the response body
         let csrf_token = response
             .body()
             .split("name=\"csrf\" value=\"")
@@ -758,65 +2766,55 @@ mod test {
             .unwrap()
             .split('\"')
             .next()
-            .unwrap();
+            .unwrap()
+            .to_owned();
 
-        // Submit the login form
-        let request = Request::post("/login").form(serde_json::json!({
+        // A bad code is rejected
+        let request = Request::post("/login/email/code").form(serde_json::json!({
             "csrf": csrf_token,
-            "username": "john",
-            "password": "hunter2",
+            "code": "654321",
         }));
         let request = cookies.with_cookies(request);
-
-        // First three attempts should just tell about the invalid credentials
-        let response = state.request(request.clone()).await;
-        response.assert_status(StatusCode::OK);
-        let body = response.body();
-        assert!(body.contains("Invalid credentials"));
-        assert!(!body.contains("too many requests"));
-
-        let response = state.request(request.clone()).await;
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
         response.assert_status(StatusCode::OK);
-        let body = response.body();
-        assert!(body.contains("Invalid credentials"));
-        assert!(!body.contains("too many requests"));
 
-        let response = state.request(request.clone()).await;
-        response.assert_status(StatusCode::OK);
-        let body = response.body();
-        assert!(body.contains("Invalid credentials"));
-        assert!(!body.contains("too many requests"));
+        // The right code logs the user in
+        let request = Request::post("/login/email/code").form(serde_json::json!({
+            "csrf": csrf_token,
+            "code": "123456",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
 
-        // The fourth attempt should be rate-limited
-        let response = state.request(request.clone()).await;
+        let request = Request::get("/").empty();
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
         response.assert_status(StatusCode::OK);
-        let body = response.body();
-        assert!(!body.contains("Invalid credentials"));
-        assert!(body.contains("too many requests"));
+        assert!(response.body().contains("john"));
     }
 
     #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
-    async fn test_password_login_locked_account(pool: PgPool) {
+    async fn test_email_code_login_unknown_email(pool: PgPool) {
         setup();
-        let state = TestState::from_pool(pool).await.unwrap();
+        let state = TestState::from_pool_with_site_config(
+            pool.clone(),
+            SiteConfig {
+                email_code_login_enabled: true,
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
         let cookies = CookieHelper::new();
 
-        // Provision a user with a password
-        let user = user_with_password(&state, "john", "hunter2").await;
-
-        // Lock the user
-        let mut repo = state.repository().await.unwrap();
-        repo.user().lock(&state.clock, user).await.unwrap();
-        repo.save().await.unwrap();
-
-        // Render the login page to get a CSRF token
-        let request = Request::get("/login").empty();
+        let request = Request::get("/login/email").empty();
         let request = cookies.with_cookies(request);
         let response = state.request(request).await;
         cookies.save_cookies(&response);
         response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
-        // Extract the CSRF token from the response body
         let csrf_token = response
             .body()
             .split("name=\"csrf\" value=\"")
@@ -824,58 +2822,99 @@ mod test {
             .unwrap()
             .split('\"')
             .next()
-            .unwrap();
+            .unwrap()
+            .to_owned();
 
-        // Submit the login form
-        let request = Request::post("/login").form(serde_json::json!({
+        // An unknown email address behaves the same as a known one, but doesn't
+        // start any authentication
+        let request = Request::post("/login/email").form(serde_json::json!({
             "csrf": csrf_token,
-            "username": "john",
-            "password": "hunter2",
+            "email": "nobody@example.com",
         }));
         let request = cookies.with_cookies(request);
         let response = state.request(request).await;
         cookies.save_cookies(&response);
-        response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
-        assert!(response.body().contains("Account locked"));
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, "/login/email/code");
 
-        // A bad password should not disclose that the account is locked
-        let request = Request::post("/login").form(serde_json::json!({
-            "csrf": csrf_token,
-            "username": "john",
-            "password": "badpassword",
-        }));
-        let request = cookies.with_cookies(request);
-        let response = state.request(request).await;
-        cookies.save_cookies(&response);
-        response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
-        assert!(!response.body().contains("Account locked"));
-        assert!(response.body().contains("Invalid credentials"));
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM user_email_authentications")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
     }
 
     #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
-    async fn test_password_login_deactivated_account(pool: PgPool) {
+    async fn test_home_realm_discovery(pool: PgPool) {
         setup();
-        let state = TestState::from_pool(pool).await.unwrap();
-        let cookies = CookieHelper::new();
-
-        // Provision a user with a password
-        let user = user_with_password(&state, "john", "hunter2").await;
-
-        // Deactivate the user
+        let state = TestState::from_pool(pool.clone()).await.unwrap();
+        let mut rng = state.rng();
         let mut repo = state.repository().await.unwrap();
-        repo.user().deactivate(&state.clock, user).await.unwrap();
+        let provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &state.clock,
+                UpstreamOAuthProviderParams {
+                    issuer: Some("https://example.com/".to_owned()),
+                    human_name: Some("Example Ltd.".to_owned()),
+                    brand_name: None,
+                    scope: [OPENID].into_iter().collect(),
+                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+                    token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
+                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+                    fetch_userinfo: false,
+                    userinfo_signed_response_alg: None,
+                    client_id: "client".to_owned(),
+                    encrypted_client_secret: None,
+                    claims_imports: UpstreamOAuthProviderClaimsImports::default(),
+                    authorization_endpoint_override: None,
+                    token_endpoint_override: None,
+                    userinfo_endpoint_override: None,
+                    jwks_uri_override: None,
+                    discovery_mode: mas_data_model::UpstreamOAuthProviderDiscoveryMode::Oidc,
+                    pkce_mode: mas_data_model::UpstreamOAuthProviderPkceMode::Auto,
+                    response_mode: None,
+                    additional_authorization_parameters: Vec::new(),
+                    forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: false,
+                    deprovisioning: None,
+                    ui_order: 0,
+                },
+            )
+            .await
+            .unwrap();
         repo.save().await.unwrap();
+        let provider_login = mas_router::UpstreamOAuth2Authorize::new(provider.id);
 
-        // Render the login page to get a CSRF token
-        let request = Request::get("/login").empty();
-        let request = cookies.with_cookies(request);
+        let discovery = HomeRealmDiscoveryConfig {
+            domains: [("example.com".to_owned(), provider.id)].into(),
+            fallback: None,
+        };
+        let state = TestState::from_pool_with_site_config(
+            pool.clone(),
+            SiteConfig {
+                home_realm_discovery: Some(discovery.clone()),
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+
+        // The login page only asks for the username first
+        let request = cookies.with_cookies(Request::get("/login").empty());
         let response = state.request(request).await;
         cookies.save_cookies(&response);
         response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
-        // Extract the CSRF token from the response body
+        assert!(response.body().contains("/login/discover"));
+        assert!(!response.body().contains("type=\"password\""));
         let csrf_token = response
             .body()
             .split("name=\"csrf\" value=\"")
@@ -883,33 +2922,47 @@ mod test {
             .unwrap()
             .split('\"')
             .next()
-            .unwrap();
+            .unwrap()
+            .to_owned();
 
-        // Submit the login form
-        let request = Request::post("/login").form(serde_json::json!({
+        // Addresses in a mapped domain are sent to the provider
+        let request = Request::post("/login/discover").form(serde_json::json!({
             "csrf": csrf_token,
-            "username": "john",
-            "password": "hunter2",
+            "username": "alice@Example.COM",
         }));
-        let request = cookies.with_cookies(request);
-        let response = state.request(request).await;
-        cookies.save_cookies(&response);
-        response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
-        assert!(response.body().contains("Account deleted"));
+        let response = state.request(cookies.with_cookies(request)).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, &provider_login.path_and_query());
 
-        // A bad password should not disclose that the account is deleted
-        let request = Request::post("/login").form(serde_json::json!({
+        // Others are asked for their password
+        let request = Request::post("/login/discover").form(serde_json::json!({
             "csrf": csrf_token,
-            "username": "john",
-            "password": "badpassword",
+            "username": "bob@other.com",
         }));
-        let request = cookies.with_cookies(request);
-        let response = state.request(request).await;
-        cookies.save_cookies(&response);
+        let response = state.request(cookies.with_cookies(request)).await;
         response.assert_status(StatusCode::OK);
-        response.assert_header_value(CONTENT_TYPE, "text/html; charset=utf-8");
-        assert!(!response.body().contains("Account deleted"));
-        assert!(response.body().contains("Invalid credentials"));
+        assert!(response.body().contains("type=\"password\""));
+        assert!(response.body().contains("bob@other.com"));
+
+        // Unless there is a fallback provider
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                home_realm_discovery: Some(HomeRealmDiscoveryConfig {
+                    fallback: Some(provider.id),
+                    ..discovery
+                }),
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let request = Request::post("/login/discover").form(serde_json::json!({
+            "csrf": csrf_token,
+            "username": "bob",
+        }));
+        let response = state.request(cookies.with_cookies(request)).await;
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, &provider_login.path_and_query());
     }
 }
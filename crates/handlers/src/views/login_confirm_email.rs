@@ -0,0 +1,292 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Second step of a password login which looked suspicious, when the user has
+//! to enter a code sent to their email address to confirm it
+
+use axum::{
+    extract::{Form, Query, State},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::typed_header::TypedHeader;
+use mas_axum_utils::{
+    InternalError,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::{SiteConfig, User, UserEmailAuthentication};
+use mas_i18n::DataLocale;
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, Clock, RepositoryAccess,
+    queue::{QueueJobRepositoryExt as _, SendEmailAuthenticationCodeJob},
+    user::UserEmailRepository,
+};
+use mas_templates::{
+    FieldError, FormError, FormState, LoginConfirmEmailContext, LoginConfirmEmailFormField,
+    TemplateContext, Templates, ToFormState,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+use super::{
+    login::{LoginMethod, LoginSession, finish_login},
+    login_totp::{PendingLogin, lookup_password, lookup_user},
+    shared::OptionalPostAuthAction,
+};
+use crate::{BoundActivityTracker, Limiter, PreferredLanguage, RequesterFingerprint};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LoginConfirmEmailForm {
+    #[serde(default)]
+    code: String,
+
+    /// Set by the button asking for a new code, instead of the code. It isn't
+    /// a text field, so it's left out of the form state.
+    #[serde(default, skip_serializing)]
+    resend: bool,
+}
+
+impl ToFormState for LoginConfirmEmailForm {
+    type Field = LoginConfirmEmailFormField;
+}
+
+/// Lookup the email authentication confirming a pending login, making sure it
+/// was started for this user and wasn't used yet
+async fn lookup_authentication(
+    repo: &mut impl RepositoryAccess,
+    user: &User,
+    id: Ulid,
+) -> Result<Option<UserEmailAuthentication>, InternalError> {
+    let authentication =
+        repo.user_email()
+            .lookup_authentication(id)
+            .await?
+            .filter(|authentication| {
+                authentication.user_id == Some(user.id) && authentication.completed_at.is_none()
+            });
+    Ok(authentication)
+}
+
+#[tracing::instrument(name = "handlers.views.login_confirm_email.get", skip_all)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    mut repo: BoxRepository,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+) -> Result<Response, InternalError> {
+    let Some((pending, authentication_id)) =
+        PendingLogin::load_with_email_confirmation(&cookie_jar, &clock)
+    else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(user) = lookup_user(&mut repo, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(authentication) = lookup_authentication(&mut repo, &user, authentication_id).await?
+    else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    render(
+        locale,
+        cookie_jar,
+        FormState::default(),
+        query,
+        user,
+        authentication.email,
+        &mut repo,
+        &clock,
+        &mut rng,
+        &templates,
+    )
+    .await
+}
+
+#[tracing::instrument(name = "handlers.views.login_confirm_email.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    State(limiter): State<Limiter>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    requester: RequesterFingerprint,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    Form(form): Form<ProtectedForm<LoginConfirmEmailForm>>,
+) -> Result<Response, InternalError> {
+    let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
+    let form = cookie_jar.verify_form(&clock, form)?;
+
+    let Some((pending, authentication_id)) =
+        PendingLogin::load_with_email_confirmation(&cookie_jar, &clock)
+    else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(user) = lookup_user(&mut repo, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let (Some(user_password), Some(authentication)) = (
+        lookup_password(&mut repo, &user, &pending).await?,
+        lookup_authentication(&mut repo, &user, authentication_id).await?,
+    ) else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let mut form_state = form.to_form_state();
+
+    if form.resend {
+        let form_state = if let Err(e) =
+            limiter.check_email_authentication_email(requester, &authentication.email)
+        {
+            tracing::warn!(error = &e as &dyn std::error::Error);
+            form_state.with_error_on_form(FormError::RateLimitExceeded)
+        } else {
+            repo.queue_job()
+                .schedule_job(
+                    &mut rng,
+                    &clock,
+                    SendEmailAuthenticationCodeJob::new(&authentication, locale.to_string()),
+                )
+                .await?;
+            FormState::default()
+        };
+
+        let response = render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            user,
+            authentication.email,
+            &mut repo,
+            &clock,
+            &mut rng,
+            &templates,
+        )
+        .await?;
+        repo.save().await?;
+        return Ok(response);
+    }
+
+    // Once the attempts of this authentication are exhausted, the user has to
+    // wait, or ask for a new code, which is itself rate-limited
+    if let Err(e) = limiter.check_email_authentication_attempt(&authentication) {
+        tracing::warn!(error = &e as &dyn std::error::Error);
+        let form_state = form_state.with_error_on_form(FormError::RateLimitExceeded);
+        return render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            user,
+            authentication.email,
+            &mut repo,
+            &clock,
+            &mut rng,
+            &templates,
+        )
+        .await;
+    }
+
+    let code = repo
+        .user_email()
+        .find_authentication_code(&authentication, &form.code)
+        .await?
+        .filter(|code| code.expires_at >= clock.now());
+
+    let Some(code) = code else {
+        form_state.add_error_on_field(LoginConfirmEmailFormField::Code, FieldError::Invalid);
+        return render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            user,
+            authentication.email,
+            &mut repo,
+            &clock,
+            &mut rng,
+            &templates,
+        )
+        .await;
+    };
+
+    repo.user_email()
+        .complete_authentication(&clock, authentication, &code)
+        .await?;
+
+    // The session is authenticated by the password which was checked before
+    let reply = finish_login(
+        repo,
+        &mut rng,
+        &clock,
+        &locale,
+        &site_config,
+        &url_builder,
+        &activity_tracker,
+        PendingLogin::remove(cookie_jar),
+        &query,
+        LoginSession::New(&user, user_agent),
+        LoginMethod::Password(&user_password, None),
+    )
+    .await?;
+
+    Ok(reply.into_response())
+}
+
+async fn render(
+    locale: DataLocale,
+    cookie_jar: CookieJar,
+    form_state: FormState<LoginConfirmEmailFormField>,
+    action: OptionalPostAuthAction,
+    user: User,
+    email: String,
+    repo: &mut impl RepositoryAccess,
+    clock: &impl Clock,
+    rng: impl Rng,
+    templates: &Templates,
+) -> Result<Response, InternalError> {
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(clock, rng);
+
+    let ctx = LoginConfirmEmailContext::new(user, email).with_form_state(form_state);
+    let next = action
+        .load_context(repo)
+        .await
+        .map_err(InternalError::from_anyhow)?;
+    let ctx = if let Some(next) = next {
+        ctx.with_post_action(next)
+    } else {
+        ctx
+    };
+    let ctx = ctx.with_csrf(csrf_token.form_value()).with_language(locale);
+
+    let content = templates.render_login_confirm_email(&ctx)?;
+    Ok((cookie_jar, Html(content)).into_response())
+}
@@ -0,0 +1,1105 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Login without a password, where the user enters their email address and
+//! then the short-lived code sent to it, or opens the link sent alongside it
+
+use std::{collections::HashMap, str::FromStr, sync::LazyLock};
+
+use axum::{
+    extract::{Form, Path, Query, State},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::typed_header::TypedHeader;
+use chrono::{DateTime, Duration, Utc};
+use hyper::StatusCode;
+use lettre::Address;
+use mas_axum_utils::{
+    InternalError,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::{SiteConfig, User, UserEmailAuthentication, UserEmailAuthenticationCode};
+use mas_i18n::DataLocale;
+use mas_jose::{
+    claims::{self, Claim, TimeOptions},
+    jwt::Jwt,
+};
+use mas_keystore::Keystore;
+use mas_router::{PostAuthAction, UrlBuilder};
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, Clock, RepositoryAccess, RepositoryError,
+    queue::{QueueJobRepositoryExt as _, SendEmailAuthenticationCodeJob},
+    user::{UserEmailRepository, UserRepository},
+};
+use mas_templates::{
+    EmptyContext, FieldError, FormError, FormState, LoginEmailCodeContext, LoginEmailCodeFormField,
+    LoginEmailContext, LoginEmailFormField, LoginEmailLinkContext, TemplateContext, Templates,
+    ToFormState,
+};
+use opentelemetry::{Key, KeyValue, metrics::Counter};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+use super::{
+    login::{LoginMethod, LoginSession, SecondFactorCheck, check_second_factor, finish_login},
+    shared::OptionalPostAuthAction,
+};
+use crate::{BoundActivityTracker, Limiter, METER, PreferredLanguage, RequesterFingerprint};
+
+static EMAIL_CODE_LOGIN_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
+    METER
+        .u64_counter("mas.user.email_code_login_attempt")
+        .with_description("Number of email code login attempts")
+        .with_unit("{attempt}")
+        .build()
+});
+const RESULT: Key = Key::from_static_str("result");
+
+/// Name of the cookie
+static COOKIE_NAME: &str = "login-email";
+
+/// The code embedded in login link tokens
+const LOGIN_LINK_CODE: Claim<String> = Claim::new("code");
+
+/// Codes are valid for 5 minutes, but users can ask for the email again, so
+/// leave them a bit more time before they have to start over
+static PENDING_LOGIN_MAX_TIME: Duration = Duration::microseconds(15 * 60 * 1000 * 1000);
+
+/// A login which sent a code to an email address, and waits for the user to
+/// enter it
+///
+/// The email authentication is only started if the address belongs to a user
+/// who can log in, but the user is sent to the code page either way, so that
+/// the form doesn't tell which addresses are known.
+///
+/// It is also how a login link tells whether it was opened in the browser
+/// which asked for it.
+#[derive(Serialize, Deserialize, Debug)]
+struct PendingEmailLogin {
+    email: String,
+    user_email_authentication_id: Option<Ulid>,
+    #[serde(default)]
+    post_auth_action: Option<PostAuthAction>,
+    created_at: DateTime<Utc>,
+}
+
+impl PendingEmailLogin {
+    /// Load the pending login from the cookie jar, if it didn't expire yet
+    fn load(cookie_jar: &CookieJar, clock: &impl Clock) -> Option<Self> {
+        match cookie_jar.load::<Self>(COOKIE_NAME) {
+            Ok(Some(pending)) if clock.now() - pending.created_at <= PENDING_LOGIN_MAX_TIME => {
+                Some(pending)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Invalid pending email login cookie: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Save the pending login to the cookie jar
+    fn save(&self, cookie_jar: CookieJar) -> CookieJar {
+        cookie_jar.save(COOKIE_NAME, self, false)
+    }
+
+    fn remove(cookie_jar: CookieJar) -> CookieJar {
+        cookie_jar.remove(COOKIE_NAME)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LoginEmailForm {
+    email: String,
+}
+
+impl ToFormState for LoginEmailForm {
+    type Field = LoginEmailFormField;
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LoginEmailCodeForm {
+    code: String,
+}
+
+impl ToFormState for LoginEmailCodeForm {
+    type Field = LoginEmailCodeFormField;
+}
+
+#[tracing::instrument(name = "handlers.views.login_email.get", skip_all)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    mut repo: BoxRepository,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+) -> Result<Response, InternalError> {
+    if !site_config.email_code_login_enabled {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    }
+
+    render_email(
+        locale,
+        cookie_jar,
+        FormState::default(),
+        query,
+        &mut repo,
+        &clock,
+        &mut rng,
+        &templates,
+    )
+    .await
+}
+
+#[tracing::instrument(name = "handlers.views.login_email.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    State(limiter): State<Limiter>,
+    mut repo: BoxRepository,
+    requester: RequesterFingerprint,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+    Form(form): Form<ProtectedForm<LoginEmailForm>>,
+) -> Result<Response, InternalError> {
+    if !site_config.email_code_login_enabled {
+        return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response());
+    }
+
+    let form = cookie_jar.verify_form(&clock, form)?;
+    let mut form_state = form.to_form_state();
+
+    if Address::from_str(&form.email).is_err() {
+        form_state.add_error_on_field(LoginEmailFormField::Email, FieldError::Invalid);
+    }
+
+    if form_state.is_valid() {
+        // This is checked whether the address is known or not, so that the rate
+        // limit doesn't tell them apart
+        if let Err(e) = limiter.check_email_authentication_email(requester, &form.email) {
+            tracing::warn!(error = &e as &dyn std::error::Error);
+            form_state.add_error_on_form(FormError::RateLimitExceeded);
+        }
+    }
+
+    if !form_state.is_valid() {
+        return render_email(
+            locale, cookie_jar, form_state, query, &mut repo, &clock, &mut rng, &templates,
+        )
+        .await;
+    }
+
+    let user = if let Some(user_email) = repo.user_email().find_by_email(&form.email).await? {
+        repo.user()
+            .lookup(user_email.user_id)
+            .await?
+            .filter(User::is_valid)
+    } else {
+        None
+    };
+
+    let user_email_authentication_id = if let Some(user) = user {
+        let authentication = repo
+            .user_email()
+            .add_authentication_for_login(&mut rng, &clock, form.email.clone(), &user)
+            .await?;
+
+        repo.queue_job()
+            .schedule_job(
+                &mut rng,
+                &clock,
+                SendEmailAuthenticationCodeJob::new(&authentication, locale.to_string()),
+            )
+            .await?;
+
+        Some(authentication.id)
+    } else {
+        None
+    };
+
+    repo.save().await?;
+
+    let pending = PendingEmailLogin {
+        email: form.email,
+        user_email_authentication_id,
+        post_auth_action: query.post_auth_action.clone(),
+        created_at: clock.now(),
+    };
+    let cookie_jar = pending.save(cookie_jar);
+    let destination = mas_router::LoginEmailCode::from(query.post_auth_action);
+    Ok((cookie_jar, url_builder.redirect(&destination)).into_response())
+}
+
+#[tracing::instrument(name = "handlers.views.login_email.get_code", skip_all)]
+pub(crate) async fn get_code(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    mut repo: BoxRepository,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+) -> Result<Response, InternalError> {
+    if !site_config.email_code_login_enabled {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    }
+
+    let Some(pending) = PendingEmailLogin::load(&cookie_jar, &clock) else {
+        let login_email = mas_router::LoginEmail::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login_email)).into_response());
+    };
+
+    let ctx = LoginEmailCodeContext::new(pending.email);
+    render_code(
+        locale, cookie_jar, ctx, query, &mut repo, &clock, &mut rng, &templates,
+    )
+    .await
+}
+
+#[tracing::instrument(name = "handlers.views.login_email.post_code", skip_all)]
+pub(crate) async fn post_code(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    State(limiter): State<Limiter>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    Form(form): Form<ProtectedForm<LoginEmailCodeForm>>,
+) -> Result<Response, InternalError> {
+    let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
+    if !site_config.email_code_login_enabled {
+        return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response());
+    }
+
+    let form = cookie_jar.verify_form(&clock, form)?;
+
+    let Some(pending) = PendingEmailLogin::load(&cookie_jar, &clock) else {
+        let login_email = mas_router::LoginEmail::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login_email)).into_response());
+    };
+
+    let ctx = LoginEmailCodeContext::new(pending.email);
+    let form_state = form.to_form_state();
+
+    // If no code was sent, because the address isn't known, any code is invalid
+    let authentication = if let Some(id) = pending.user_email_authentication_id {
+        repo.user_email()
+            .lookup_authentication(id)
+            .await?
+            .filter(|authentication| authentication.completed_at.is_none())
+    } else {
+        None
+    };
+
+    let Some(authentication) = authentication else {
+        EMAIL_CODE_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
+        let form_state =
+            form_state.with_error_on_field(LoginEmailCodeFormField::Code, FieldError::Invalid);
+        return render_code(
+            locale,
+            cookie_jar,
+            ctx.with_form_state(form_state),
+            query,
+            &mut repo,
+            &clock,
+            &mut rng,
+            &templates,
+        )
+        .await;
+    };
+
+    // Once the attempts of this authentication are exhausted, the user has to
+    // wait, or ask for a new code, which is itself rate-limited
+    if let Err(e) = limiter.check_email_authentication_attempt(&authentication) {
+        tracing::warn!(error = &e as &dyn std::error::Error);
+        EMAIL_CODE_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
+        let form_state = form_state.with_error_on_form(FormError::RateLimitExceeded);
+        return render_code(
+            locale,
+            cookie_jar,
+            ctx.with_form_state(form_state),
+            query,
+            &mut repo,
+            &clock,
+            &mut rng,
+            &templates,
+        )
+        .await;
+    }
+
+    let code = repo
+        .user_email()
+        .find_authentication_code(&authentication, &form.code)
+        .await?
+        .filter(|code| code.expires_at >= clock.now());
+
+    let user = if let Some(user_id) = authentication.user_id {
+        repo.user().lookup(user_id).await?.filter(User::is_valid)
+    } else {
+        None
+    };
+
+    let (Some(code), Some(user)) = (code, user) else {
+        EMAIL_CODE_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
+        let form_state =
+            form_state.with_error_on_field(LoginEmailCodeFormField::Code, FieldError::Invalid);
+        return render_code(
+            locale,
+            cookie_jar,
+            ctx.with_form_state(form_state),
+            query,
+            &mut repo,
+            &clock,
+            &mut rng,
+            &templates,
+        )
+        .await;
+    };
+
+    let cookie_jar = PendingEmailLogin::remove(cookie_jar);
+    complete_login(
+        locale,
+        cookie_jar,
+        query,
+        authentication,
+        &code,
+        user,
+        user_agent,
+        repo,
+        &activity_tracker,
+        &site_config,
+        &url_builder,
+        &clock,
+        &mut rng,
+    )
+    .await
+}
+
+#[tracing::instrument(name = "handlers.views.login_email.get_link", skip_all)]
+pub(crate) async fn get_link(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    State(key_store): State<Keystore>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    cookie_jar: CookieJar,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    Path(token): Path<String>,
+) -> Result<Response, InternalError> {
+    let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
+    if !site_config.email_code_login_enabled {
+        let login = mas_router::Login::default();
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    }
+
+    let Some((authentication, code, user)) =
+        load_login_link(&mut repo, &clock, &key_store, &url_builder, &token).await?
+    else {
+        EMAIL_CODE_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
+        let context = EmptyContext.with_language(locale);
+        let rendered = templates.render_login_email_link_invalid(&context)?;
+        return Ok((cookie_jar, Html(rendered)).into_response());
+    };
+
+    // The link logs in straight away in the browser which asked for it.
+    // Elsewhere, it could have been sent by someone trying to get their victim
+    // to log in to their account, or be opened by a link scanner, so the user
+    // has to confirm first
+    let pending = PendingEmailLogin::load(&cookie_jar, &clock)
+        .filter(|pending| pending.user_email_authentication_id == Some(authentication.id));
+    let Some(pending) = pending else {
+        let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
+        let context = LoginEmailLinkContext::new(user)
+            .with_csrf(csrf_token.form_value())
+            .with_language(locale);
+        let rendered = templates.render_login_email_link(&context)?;
+        return Ok((cookie_jar, Html(rendered)).into_response());
+    };
+
+    let query = OptionalPostAuthAction::from(pending.post_auth_action);
+    let cookie_jar = PendingEmailLogin::remove(cookie_jar);
+    complete_login(
+        locale,
+        cookie_jar,
+        query,
+        authentication,
+        &code,
+        user,
+        user_agent,
+        repo,
+        &activity_tracker,
+        &site_config,
+        &url_builder,
+        &clock,
+        &mut rng,
+    )
+    .await
+}
+
+#[tracing::instrument(name = "handlers.views.login_email.post_link", skip_all)]
+pub(crate) async fn post_link(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    State(key_store): State<Keystore>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    cookie_jar: CookieJar,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    Path(token): Path<String>,
+    Form(form): Form<ProtectedForm<()>>,
+) -> Result<Response, InternalError> {
+    let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
+    if !site_config.email_code_login_enabled {
+        return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response());
+    }
+
+    cookie_jar.verify_form(&clock, form)?;
+
+    let Some((authentication, code, user)) =
+        load_login_link(&mut repo, &clock, &key_store, &url_builder, &token).await?
+    else {
+        EMAIL_CODE_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "error")]);
+        let context = EmptyContext.with_language(locale);
+        let rendered = templates.render_login_email_link_invalid(&context)?;
+        return Ok((cookie_jar, Html(rendered)).into_response());
+    };
+
+    // The user confirmed the login in another browser than the one which asked
+    // for it, so the action it was meant to continue doesn't apply here
+    complete_login(
+        locale,
+        cookie_jar,
+        OptionalPostAuthAction::default(),
+        authentication,
+        &code,
+        user,
+        user_agent,
+        repo,
+        &activity_tracker,
+        &site_config,
+        &url_builder,
+        &clock,
+        &mut rng,
+    )
+    .await
+}
+
+/// The claims of a login link token
+struct LoginLinkToken {
+    user_id: Ulid,
+    user_email_authentication_id: Ulid,
+    code: String,
+}
+
+/// Check that a login link token was signed by us and didn't expire.
+///
+/// Those tokens are signed by the job sending the login code, with the code
+/// they stand for.
+fn verify_login_link_token(
+    token: &str,
+    key_store: &Keystore,
+    url_builder: &UrlBuilder,
+    clock: &dyn Clock,
+) -> Result<LoginLinkToken, anyhow::Error> {
+    let jwt: Jwt<'_, HashMap<String, serde_json::Value>> = Jwt::try_from(token)?;
+    jwt.verify_with_jwks(&key_store.public_jwks())?;
+
+    let (header, mut claims) = jwt.into_parts();
+
+    // Make sure other tokens we sign, like ID tokens, can't be used here
+    if header.typ() != Some("login-link+jwt") {
+        anyhow::bail!("Unexpected token type");
+    }
+
+    let issuer = url_builder.oidc_issuer();
+    claims::ISS.extract_required_with_options(&mut claims, issuer.as_str())?;
+    claims::EXP.extract_required_with_options(&mut claims, TimeOptions::new(clock.now()))?;
+    let user_id: Ulid = claims::SUB.extract_required(&mut claims)?.parse()?;
+    let user_email_authentication_id: Ulid = claims::JTI.extract_required(&mut claims)?.parse()?;
+    let code = LOGIN_LINK_CODE.extract_required(&mut claims)?;
+
+    Ok(LoginLinkToken {
+        user_id,
+        user_email_authentication_id,
+        code,
+    })
+}
+
+/// Load the email authentication, code and user a login link stands for, if
+/// they are all still usable
+async fn load_login_link(
+    repo: &mut BoxRepository,
+    clock: &dyn Clock,
+    key_store: &Keystore,
+    url_builder: &UrlBuilder,
+    token: &str,
+) -> Result<Option<(UserEmailAuthentication, UserEmailAuthenticationCode, User)>, RepositoryError> {
+    let token = match verify_login_link_token(token, key_store, url_builder, clock) {
+        Ok(token) => token,
+        Err(e) => {
+            tracing::warn!(error = &*e as &dyn std::error::Error, "Invalid login link");
+            return Ok(None);
+        }
+    };
+
+    let Some(authentication) = repo
+        .user_email()
+        .lookup_authentication(token.user_email_authentication_id)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    // The authentication is completed once the link or the code is used
+    if authentication.completed_at.is_some() || authentication.user_id != Some(token.user_id) {
+        return Ok(None);
+    }
+
+    let Some(code) = repo
+        .user_email()
+        .find_authentication_code(&authentication, &token.code)
+        .await?
+        .filter(|code| code.expires_at >= clock.now())
+    else {
+        return Ok(None);
+    };
+
+    let Some(user) = repo
+        .user()
+        .lookup(token.user_id)
+        .await?
+        .filter(User::is_valid)
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some((authentication, code, user)))
+}
+
+/// Complete the email authentication, and log in with it, once the user
+/// entered the code or opened the link sent to their email address
+///
+/// A code sent by email isn't a second factor, so users who have one, or must
+/// add one, are sent to the page asking for it first. The authentication is
+/// completed either way, so that the code can't be used again.
+async fn complete_login(
+    locale: DataLocale,
+    cookie_jar: CookieJar,
+    query: OptionalPostAuthAction,
+    authentication: UserEmailAuthentication,
+    code: &UserEmailAuthenticationCode,
+    user: User,
+    user_agent: Option<String>,
+    mut repo: BoxRepository,
+    activity_tracker: &BoundActivityTracker,
+    site_config: &SiteConfig,
+    url_builder: &UrlBuilder,
+    clock: &impl Clock,
+    rng: &mut BoxRng,
+) -> Result<Response, InternalError> {
+    let authentication = repo
+        .user_email()
+        .complete_authentication(clock, authentication, code)
+        .await?;
+
+    let (cookie_jar, trusted_device) = match check_second_factor(
+        &mut repo,
+        rng,
+        clock,
+        &locale,
+        site_config,
+        url_builder,
+        cookie_jar,
+        &query,
+        &user,
+        &authentication,
+    )
+    .await?
+    {
+        SecondFactorCheck::Required(cookie_jar, destination) => {
+            // This also saves the completed authentication
+            repo.save().await?;
+            return Ok((cookie_jar, destination).into_response());
+        }
+        SecondFactorCheck::NotRequired(cookie_jar, trusted_device) => (cookie_jar, trusted_device),
+    };
+
+    let reply = finish_login(
+        repo,
+        rng,
+        clock,
+        &locale,
+        site_config,
+        url_builder,
+        activity_tracker,
+        cookie_jar,
+        &query,
+        LoginSession::New(&user, user_agent),
+        LoginMethod::EmailCode(&authentication, trusted_device),
+    )
+    .await?;
+
+    EMAIL_CODE_LOGIN_COUNTER.add(1, &[KeyValue::new(RESULT, "success")]);
+
+    Ok(reply.into_response())
+}
+
+async fn render_email(
+    locale: DataLocale,
+    cookie_jar: CookieJar,
+    form_state: FormState<LoginEmailFormField>,
+    action: OptionalPostAuthAction,
+    repo: &mut impl RepositoryAccess,
+    clock: &impl Clock,
+    rng: impl Rng,
+    templates: &Templates,
+) -> Result<Response, InternalError> {
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(clock, rng);
+
+    let ctx = LoginEmailContext::default().with_form_state(form_state);
+    let next = action
+        .load_context(repo)
+        .await
+        .map_err(InternalError::from_anyhow)?;
+    let ctx = if let Some(next) = next {
+        ctx.with_post_action(next)
+    } else {
+        ctx
+    };
+    let ctx = ctx.with_csrf(csrf_token.form_value()).with_language(locale);
+
+    let content = templates.render_login_email(&ctx)?;
+    Ok((cookie_jar, Html(content)).into_response())
+}
+
+async fn render_code(
+    locale: DataLocale,
+    cookie_jar: CookieJar,
+    ctx: LoginEmailCodeContext,
+    action: OptionalPostAuthAction,
+    repo: &mut impl RepositoryAccess,
+    clock: &impl Clock,
+    rng: impl Rng,
+    templates: &Templates,
+) -> Result<Response, InternalError> {
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(clock, rng);
+
+    let next = action
+        .load_context(repo)
+        .await
+        .map_err(InternalError::from_anyhow)?;
+    let ctx = if let Some(next) = next {
+        ctx.with_post_action(next)
+    } else {
+        ctx
+    };
+    let ctx = ctx.with_csrf(csrf_token.form_value()).with_language(locale);
+
+    let content = templates.render_login_email_code(&ctx)?;
+    Ok((cookie_jar, Html(content)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::Duration;
+    use hyper::{Request, StatusCode, header::LOCATION};
+    use mas_data_model::{AuthenticationMethod, User, UserEmailAuthenticationCode};
+    use mas_iana::jose::JsonWebSignatureAlg;
+    use mas_jose::{
+        claims,
+        jwt::{JsonWebSignatureHeader, Jwt},
+    };
+    use mas_storage::{
+        Pagination, RepositoryAccess,
+        user::{
+            BrowserSessionFilter, BrowserSessionRepository, UserEmailRepository,
+            UserPhoneRepository, UserRepository,
+        },
+    };
+    use sqlx::PgPool;
+
+    use super::LOGIN_LINK_CODE;
+    use crate::{
+        SiteConfig,
+        test_utils::{
+            CookieHelper, RequestBuilderExt, ResponseExt, TestState, setup, test_site_config,
+        },
+    };
+
+    /// Sign a login link token, like the job sending the login code does
+    fn sign_token(state: &TestState, user: &User, code: &UserEmailAuthenticationCode) -> String {
+        let mut claims = HashMap::new();
+        claims::ISS
+            .insert(&mut claims, state.url_builder.oidc_issuer().to_string())
+            .unwrap();
+        claims::SUB
+            .insert(&mut claims, user.id.to_string())
+            .unwrap();
+        claims::JTI
+            .insert(&mut claims, code.user_email_authentication_id.to_string())
+            .unwrap();
+        claims::IAT.insert(&mut claims, code.created_at).unwrap();
+        claims::EXP.insert(&mut claims, code.expires_at).unwrap();
+        LOGIN_LINK_CODE
+            .insert(&mut claims, code.code.clone())
+            .unwrap();
+
+        let alg = JsonWebSignatureAlg::Rs256;
+        let key = state.key_store.signing_key_for_algorithm(&alg).unwrap();
+        let signer = key.params().signing_key_for_alg(&alg).unwrap();
+        let header = JsonWebSignatureHeader::new(alg)
+            .with_kid(key.kid().unwrap())
+            .with_typ("login-link+jwt".to_owned());
+
+        Jwt::sign_with_rng(&mut state.rng(), header, claims, &signer)
+            .unwrap()
+            .into_string()
+    }
+
+    /// Provision a user with an email address, and start logging in with it
+    /// from the given browser
+    async fn start_login(
+        state: &TestState,
+        pool: &PgPool,
+        cookies: &CookieHelper,
+    ) -> (User, UserEmailAuthenticationCode) {
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "john".to_owned())
+            .await
+            .unwrap();
+        repo.user_email()
+            .add(&mut rng, &state.clock, &user, "john@example.com".to_owned())
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = cookies.with_cookies(Request::get("/login/email").empty());
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        let request = Request::post("/login/email").form(serde_json::json!({
+            "csrf": csrf_token,
+            "email": "john@example.com",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        // The code is generated by the job sending the email, so add one here
+        let authentication_id: sqlx::types::Uuid = sqlx::query_scalar(
+            "SELECT user_email_authentication_id FROM user_email_authentications",
+        )
+        .fetch_one(pool)
+        .await
+        .expect("Authentication to be started");
+        let mut repo = state.repository().await.unwrap();
+        let authentication = repo
+            .user_email()
+            .lookup_authentication(authentication_id.into())
+            .await
+            .unwrap()
+            .unwrap();
+        let code = repo
+            .user_email()
+            .add_authentication_code(
+                &mut rng,
+                &state.clock,
+                Duration::minutes(5),
+                &authentication,
+                "123456".to_owned(),
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        (user, code)
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_login_link_same_browser(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool.clone(),
+            SiteConfig {
+                email_code_login_enabled: true,
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+        let (user, code) = start_login(&state, &pool, &cookies).await;
+        let token = sign_token(&state, &user, &code);
+
+        // Opening the link in the browser which asked for it logs in directly
+        let request =
+            cookies.with_cookies(Request::get(format!("/login/email/link/{token}")).empty());
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, "/");
+
+        let request = cookies.with_cookies(Request::get("/").empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("john"));
+
+        // The code can't be used anymore
+        let request =
+            cookies.with_cookies(Request::get(format!("/login/email/link/{token}")).empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(!response.body().contains("name=\"csrf\""));
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_login_link_other_browser(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool.clone(),
+            SiteConfig {
+                email_code_login_enabled: true,
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let (user, code) = start_login(&state, &pool, &CookieHelper::new()).await;
+        let token = sign_token(&state, &user, &code);
+
+        // A tampered token is rejected
+        let request = Request::get(format!("/login/email/link/{token}x")).empty();
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(!response.body().contains("name=\"csrf\""));
+
+        // In another browser, the login has to be confirmed first
+        let cookies = CookieHelper::new();
+        let request =
+            cookies.with_cookies(Request::get(format!("/login/email/link/{token}")).empty());
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        let mut repo = state.repository().await.unwrap();
+        let authentication = repo
+            .user_email()
+            .lookup_authentication(code.user_email_authentication_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(authentication.completed_at.is_none());
+        repo.save().await.unwrap();
+
+        let request = Request::post(format!("/login/email/link/{token}")).form(serde_json::json!({
+            "csrf": csrf_token,
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        let request = cookies.with_cookies(Request::get("/").empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("john"));
+
+        let mut repo = state.repository().await.unwrap();
+        let authentication = repo
+            .user_email()
+            .lookup_authentication(code.user_email_authentication_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(authentication.completed_at.is_some());
+        repo.save().await.unwrap();
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_email_code_login_with_sms(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool.clone(),
+            SiteConfig {
+                email_code_login_enabled: true,
+                sms_second_factor_enabled: true,
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+        let (user, code) = start_login(&state, &pool, &cookies).await;
+
+        // Give the user a confirmed phone number
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+        let phone = repo
+            .user_phone()
+            .add(&mut rng, &state.clock, &user, "+33612345678".to_owned())
+            .await
+            .unwrap();
+        let phone = repo
+            .user_phone()
+            .confirm(&state.clock, phone)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = cookies.with_cookies(Request::get("/login/email/code").empty());
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        // The right code doesn't log in yet, the user has to enter the code sent
+        // by SMS first
+        let request = Request::post("/login/email/code").form(serde_json::json!({
+            "csrf": csrf_token,
+            "code": code.code,
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+        response.assert_header_value(LOCATION, "/login/sms");
+
+        let request = cookies.with_cookies(Request::get("/").empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(!response.body().contains("john"));
+
+        // The code is sent by the task worker, which doesn't run here, so add one
+        // directly
+        let mut repo = state.repository().await.unwrap();
+        repo.user_phone()
+            .add_code(
+                &mut rng,
+                &state.clock,
+                &phone,
+                Duration::minutes(10),
+                "654321".to_owned(),
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        let request = cookies.with_cookies(Request::get("/login/sms").empty());
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        let request = Request::post("/login/sms").form(serde_json::json!({
+            "csrf": csrf_token,
+            "code": "654321",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::SEE_OTHER);
+
+        let request = cookies.with_cookies(Request::get("/").empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("john"));
+
+        // The session is authenticated by both the code sent by email and the one
+        // sent by SMS
+        let mut repo = state.repository().await.unwrap();
+        let sessions = repo
+            .browser_session()
+            .list(
+                BrowserSessionFilter::new().for_user(&user),
+                Pagination::first(1),
+            )
+            .await
+            .unwrap();
+        let user_session = &sessions.edges[0];
+        let authentication = repo
+            .browser_session()
+            .get_last_authentication(user_session)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            authentication.authentication_method,
+            AuthenticationMethod::EmailCodeAndSms {
+                user_email_authentication_id: code.user_email_authentication_id,
+                user_phone_id: phone.id,
+            }
+        );
+        repo.save().await.unwrap();
+    }
+}
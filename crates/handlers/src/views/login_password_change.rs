@@ -0,0 +1,299 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Step of a password login where the user has to choose a new password,
+//! because their current one expired or an administrator required it
+
+use axum::{
+    extract::{Form, Query, State},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::typed_header::TypedHeader;
+use mas_axum_utils::{
+    InternalError,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::{SiteConfig, User};
+use mas_i18n::DataLocale;
+use mas_policy::Policy;
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, Clock, RepositoryAccess, user::UserPasswordRepository,
+};
+use mas_templates::{
+    FieldError, FormState, LoginPasswordChangeContext, LoginPasswordChangeFormField,
+    TemplateContext, Templates, ToFormState,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use super::{
+    login::finish_password_login,
+    login_totp::{PendingLogin, lookup_password, lookup_user},
+    shared::OptionalPostAuthAction,
+};
+use crate::{BoundActivityTracker, PreferredLanguage, password_policy, passwords::PasswordManager};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LoginPasswordChangeForm {
+    new_password: String,
+    new_password_confirm: String,
+}
+
+impl ToFormState for LoginPasswordChangeForm {
+    type Field = LoginPasswordChangeFormField;
+}
+
+#[tracing::instrument(name = "handlers.views.login_password_change.get", skip_all)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    mut repo: BoxRepository,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+) -> Result<Response, InternalError> {
+    let Some(pending) = PendingLogin::load_with_expired_password(&cookie_jar, &clock) else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(user) = lookup_user(&mut repo, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(current_password) = lookup_password(&mut repo, &user, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    render(
+        locale,
+        cookie_jar,
+        FormState::default(),
+        query,
+        user,
+        current_password.change_required(),
+        &mut repo,
+        &clock,
+        &mut rng,
+        &templates,
+    )
+    .await
+}
+
+#[tracing::instrument(name = "handlers.views.login_password_change.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    State(password_manager): State<PasswordManager>,
+    mut policy: Policy,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    Form(form): Form<ProtectedForm<LoginPasswordChangeForm>>,
+) -> Result<Response, InternalError> {
+    let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
+    let form = cookie_jar.verify_form(&clock, form)?;
+
+    let Some(pending) = PendingLogin::load_with_expired_password(&cookie_jar, &clock) else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(user) = lookup_user(&mut repo, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(current_password) = lookup_password(&mut repo, &user, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+    let reset_by_admin = current_password.change_required();
+
+    // Validate the form
+    let mut form_state = form.to_form_state();
+
+    if form.new_password.is_empty() {
+        form_state.add_error_on_field(
+            LoginPasswordChangeFormField::NewPassword,
+            FieldError::Required,
+        );
+    }
+
+    if form.new_password_confirm.is_empty() {
+        form_state.add_error_on_field(
+            LoginPasswordChangeFormField::NewPasswordConfirm,
+            FieldError::Required,
+        );
+    }
+
+    if form.new_password != form.new_password_confirm {
+        form_state.add_error_on_field(
+            LoginPasswordChangeFormField::NewPassword,
+            FieldError::Unspecified,
+        );
+        form_state.add_error_on_field(
+            LoginPasswordChangeFormField::NewPasswordConfirm,
+            FieldError::PasswordMismatch,
+        );
+    }
+
+    if !password_manager.is_password_complex_enough(&form.new_password)? {
+        // TODO localise this error
+        form_state.add_error_on_field(
+            LoginPasswordChangeFormField::NewPassword,
+            FieldError::Policy {
+                code: None,
+                message: "Password is too weak".to_owned(),
+            },
+        );
+    }
+
+    if !form_state.is_valid() {
+        return render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            user,
+            reset_by_admin,
+            &mut repo,
+            &clock,
+            &mut rng,
+            &templates,
+        )
+        .await;
+    }
+
+    let new_password = Zeroizing::new(form.new_password);
+
+    // The current password can't be set again, even if the history policy would
+    // allow it
+    let same_as_current = password_manager
+        .verify(
+            current_password.version,
+            new_password.clone(),
+            current_password.hashed_password,
+        )
+        .await
+        .is_ok();
+
+    if same_as_current
+        || password_policy::is_reused(
+            &mut repo,
+            &password_manager,
+            &site_config,
+            &user,
+            &new_password,
+        )
+        .await?
+    {
+        let form_state = form_state.with_error_on_field(
+            LoginPasswordChangeFormField::NewPassword,
+            FieldError::Policy {
+                code: Some("password-reused"),
+                message: "Password was used recently".to_owned(),
+            },
+        );
+        return render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            user,
+            reset_by_admin,
+            &mut repo,
+            &clock,
+            &mut rng,
+            &templates,
+        )
+        .await;
+    }
+
+    let (version, hashed_password) = password_manager
+        .hash(&mut rng, new_password)
+        .await
+        .map_err(InternalError::from_anyhow)?;
+    let user_password = repo
+        .user_password()
+        .add(&mut rng, &clock, &user, version, hashed_password, None)
+        .await?;
+
+    tracing::info!(
+        user.id = %user.id,
+        user.username = %user.username,
+        reset_by_admin,
+        "User changed their password during the login"
+    );
+
+    finish_password_login(
+        repo,
+        &mut rng,
+        &clock,
+        &locale,
+        &site_config,
+        &url_builder,
+        &activity_tracker,
+        &mut policy,
+        PendingLogin::remove(cookie_jar),
+        query,
+        user_agent,
+        &user,
+        &user_password,
+    )
+    .await
+}
+
+async fn render(
+    locale: DataLocale,
+    cookie_jar: CookieJar,
+    form_state: FormState<LoginPasswordChangeFormField>,
+    action: OptionalPostAuthAction,
+    user: User,
+    reset_by_admin: bool,
+    repo: &mut impl RepositoryAccess,
+    clock: &impl Clock,
+    rng: impl Rng,
+    templates: &Templates,
+) -> Result<Response, InternalError> {
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(clock, rng);
+
+    let ctx = LoginPasswordChangeContext::new(user).with_form_state(form_state);
+    let ctx = if reset_by_admin {
+        ctx.with_reset_by_admin()
+    } else {
+        ctx
+    };
+    let next = action
+        .load_context(repo)
+        .await
+        .map_err(InternalError::from_anyhow)?;
+    let ctx = if let Some(next) = next {
+        ctx.with_post_action(next)
+    } else {
+        ctx
+    };
+    let ctx = ctx.with_csrf(csrf_token.form_value()).with_language(locale);
+
+    let content = templates.render_login_password_change(&ctx)?;
+    Ok((cookie_jar, Html(content)).into_response())
+}
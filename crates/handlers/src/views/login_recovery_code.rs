@@ -0,0 +1,212 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Alternative second step of a login with a password or a code sent by
+//! email, when the user can't use their authenticator app and enters one of
+//! their recovery codes instead
+
+use axum::{
+    extract::{Form, Query, State},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::typed_header::TypedHeader;
+use mas_axum_utils::{
+    InternalError,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::{SiteConfig, User};
+use mas_i18n::DataLocale;
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, Clock, RepositoryAccess, user::UserTotpRepository,
+};
+use mas_templates::{
+    FieldError, FormError, FormState, LoginRecoveryCodeContext, LoginRecoveryCodeFormField,
+    TemplateContext, Templates, ToFormState,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use super::{
+    login::{LoginMethod, LoginSession, finish_login},
+    login_totp::{PendingLogin, SecondFactor, lookup_first_factor, lookup_user},
+    shared::OptionalPostAuthAction,
+};
+use crate::{BoundActivityTracker, Limiter, PreferredLanguage, RequesterFingerprint, totp};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LoginRecoveryCodeForm {
+    code: String,
+}
+
+impl ToFormState for LoginRecoveryCodeForm {
+    type Field = LoginRecoveryCodeFormField;
+}
+
+/// Check that the user has a confirmed second factor, as recovery codes are
+/// only an alternative to it
+async fn has_confirmed_totp(
+    repo: &mut impl RepositoryAccess,
+    user: &User,
+) -> Result<bool, InternalError> {
+    let totp = repo.user_totp().find_for_user(user).await?;
+    Ok(totp
+        .as_ref()
+        .is_some_and(mas_data_model::UserTotp::is_confirmed))
+}
+
+#[tracing::instrument(name = "handlers.views.login_recovery_code.get", skip_all)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    mut repo: BoxRepository,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+) -> Result<Response, InternalError> {
+    let Some(pending) = PendingLogin::load(&cookie_jar, &clock) else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(user) = lookup_user(&mut repo, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    if !has_confirmed_totp(&mut repo, &user).await? {
+        let login_totp = mas_router::LoginTotp::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login_totp)).into_response());
+    }
+
+    render(
+        locale,
+        cookie_jar,
+        FormState::default(),
+        query,
+        user,
+        &mut repo,
+        &clock,
+        &mut rng,
+        &templates,
+    )
+    .await
+}
+
+#[tracing::instrument(name = "handlers.views.login_recovery_code.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    State(limiter): State<Limiter>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    requester: RequesterFingerprint,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    Form(form): Form<ProtectedForm<LoginRecoveryCodeForm>>,
+) -> Result<Response, InternalError> {
+    let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
+    let form = cookie_jar.verify_form(&clock, form)?;
+
+    let Some(pending) = PendingLogin::load(&cookie_jar, &clock) else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(user) = lookup_user(&mut repo, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(first_factor) = lookup_first_factor(&mut repo, &user, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    if !has_confirmed_totp(&mut repo, &user).await? {
+        let login_totp = mas_router::LoginTotp::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login_totp)).into_response());
+    }
+
+    let mut form_state = form.to_form_state();
+
+    // Recovery codes share the rate limit of passwords, like TOTP codes
+    if let Err(e) = limiter.check_password(requester, &user) {
+        tracing::warn!(error = &e as &dyn std::error::Error);
+        let form_state = form_state.with_error_on_form(FormError::RateLimitExceeded);
+        return render(
+            locale, cookie_jar, form_state, query, user, &mut repo, &clock, &mut rng, &templates,
+        )
+        .await;
+    }
+
+    let code = Zeroizing::new(form.code);
+    let Some(recovery_code) = totp::consume_recovery_code(&mut repo, &clock, &user, &code).await?
+    else {
+        form_state.add_error_on_field(LoginRecoveryCodeFormField::Code, FieldError::Invalid);
+        return render(
+            locale, cookie_jar, form_state, query, user, &mut repo, &clock, &mut rng, &templates,
+        )
+        .await;
+    };
+
+    let reply = finish_login(
+        repo,
+        &mut rng,
+        &clock,
+        &locale,
+        &site_config,
+        &url_builder,
+        &activity_tracker,
+        PendingLogin::remove(cookie_jar),
+        &query,
+        LoginSession::New(&user, user_agent),
+        LoginMethod::TwoFactors(&first_factor, SecondFactor::RecoveryCode(&recovery_code)),
+    )
+    .await?;
+
+    Ok(reply.into_response())
+}
+
+async fn render(
+    locale: DataLocale,
+    cookie_jar: CookieJar,
+    form_state: FormState<LoginRecoveryCodeFormField>,
+    action: OptionalPostAuthAction,
+    user: User,
+    repo: &mut impl RepositoryAccess,
+    clock: &impl Clock,
+    rng: impl Rng,
+    templates: &Templates,
+) -> Result<Response, InternalError> {
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(clock, rng);
+
+    let ctx = LoginRecoveryCodeContext::new(user).with_form_state(form_state);
+    let next = action
+        .load_context(repo)
+        .await
+        .map_err(InternalError::from_anyhow)?;
+    let ctx = if let Some(next) = next {
+        ctx.with_post_action(next)
+    } else {
+        ctx
+    };
+    let ctx = ctx.with_csrf(csrf_token.form_value()).with_language(locale);
+
+    let content = templates.render_login_recovery_code(&ctx)?;
+    Ok((cookie_jar, Html(content)).into_response())
+}
@@ -0,0 +1,288 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Second step of a login with a password or a code sent by email, when the
+//! user has to enter a code sent by SMS to their confirmed phone number
+
+use axum::{
+    extract::{Form, Query, State},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::typed_header::TypedHeader;
+use mas_axum_utils::{
+    InternalError,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::{SiteConfig, User, UserPhone};
+use mas_i18n::DataLocale;
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, Clock, RepositoryAccess,
+    user::{UserPhoneRepository, UserTotpRepository},
+};
+use mas_templates::{
+    FieldError, FormError, FormState, LoginSmsContext, LoginSmsFormField, TemplateContext,
+    Templates, ToFormState,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    login::{LoginMethod, LoginSession, finish_login},
+    login_totp::{PendingLogin, SecondFactor, lookup_first_factor, lookup_user},
+    shared::OptionalPostAuthAction,
+};
+use crate::{
+    BoundActivityTracker, Limiter, PreferredLanguage, RequesterFingerprint, sms, trusted_device,
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LoginSmsForm {
+    #[serde(default)]
+    code: String,
+
+    /// Set by the button asking for a new code, instead of the code. It isn't
+    /// a text field, so it's left out of the form state.
+    #[serde(default, skip_serializing)]
+    resend: bool,
+
+    /// Unchecked checkboxes are not sent with the form
+    #[serde(default)]
+    remember_device: Option<String>,
+}
+
+impl ToFormState for LoginSmsForm {
+    type Field = LoginSmsFormField;
+}
+
+/// Find the phone number to send a login code to, if the user logging in with
+/// their password or a code sent by email should get one
+///
+/// TOTP takes precedence: users who have a confirmed TOTP second factor, or
+/// who must add one, never get a code by SMS.
+pub(crate) async fn phone_for_login(
+    repo: &mut impl RepositoryAccess,
+    site_config: &SiteConfig,
+    user: &User,
+) -> Result<Option<UserPhone>, InternalError> {
+    if !site_config.sms_second_factor_enabled {
+        return Ok(None);
+    }
+
+    if site_config.totp_policy.is_enabled() {
+        let has_totp = repo
+            .user_totp()
+            .find_for_user(user)
+            .await?
+            .is_some_and(|totp| totp.is_confirmed());
+
+        if has_totp || site_config.totp_policy.is_required_for(user) {
+            return Ok(None);
+        }
+    }
+
+    let phone = repo.user_phone().find_for_user(user).await?;
+    Ok(phone.filter(UserPhone::is_confirmed))
+}
+
+#[tracing::instrument(name = "handlers.views.login_sms.get", skip_all)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    mut repo: BoxRepository,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+) -> Result<Response, InternalError> {
+    let Some(pending) = PendingLogin::load(&cookie_jar, &clock) else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(user) = lookup_user(&mut repo, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(phone) = phone_for_login(&mut repo, &site_config, &user).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    render(
+        locale,
+        cookie_jar,
+        FormState::default(),
+        query,
+        user,
+        &phone,
+        &mut repo,
+        &clock,
+        &mut rng,
+        &templates,
+    )
+    .await
+}
+
+#[tracing::instrument(name = "handlers.views.login_sms.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    State(limiter): State<Limiter>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    requester: RequesterFingerprint,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    Form(form): Form<ProtectedForm<LoginSmsForm>>,
+) -> Result<Response, InternalError> {
+    let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
+    let form = cookie_jar.verify_form(&clock, form)?;
+
+    let Some(pending) = PendingLogin::load(&cookie_jar, &clock) else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(user) = lookup_user(&mut repo, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let (Some(first_factor), Some(phone)) = (
+        lookup_first_factor(&mut repo, &user, &pending).await?,
+        phone_for_login(&mut repo, &site_config, &user).await?,
+    ) else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let mut form_state = form.to_form_state();
+
+    if form.resend {
+        let sent = sms::send_code(
+            &mut repo,
+            &mut rng,
+            &clock,
+            &phone,
+            locale.to_string(),
+            true,
+        )
+        .await?;
+
+        let form_state = if sent {
+            FormState::default()
+        } else {
+            form_state.with_error_on_form(FormError::RateLimitExceeded)
+        };
+
+        let response = render(
+            locale, cookie_jar, form_state, query, user, &phone, &mut repo, &clock, &mut rng,
+            &templates,
+        )
+        .await?;
+        repo.save().await?;
+        return Ok(response);
+    }
+
+    // Codes are short, so they share the rate limit of passwords
+    if let Err(e) = limiter.check_password(requester, &user) {
+        tracing::warn!(error = &e as &dyn std::error::Error);
+        let form_state = form_state.with_error_on_form(FormError::RateLimitExceeded);
+        return render(
+            locale, cookie_jar, form_state, query, user, &phone, &mut repo, &clock, &mut rng,
+            &templates,
+        )
+        .await;
+    }
+
+    if sms::check_code(&mut repo, &clock, &phone, &form.code)
+        .await?
+        .is_none()
+    {
+        form_state.add_error_on_field(LoginSmsFormField::Code, FieldError::Invalid);
+        return render(
+            locale, cookie_jar, form_state, query, user, &phone, &mut repo, &clock, &mut rng,
+            &templates,
+        )
+        .await;
+    }
+
+    // The user may not want to enter their second factor again in this browser
+    let cookie_jar = if form.remember_device.is_some() {
+        trusted_device::trust(
+            &mut repo,
+            &mut rng,
+            &clock,
+            &site_config,
+            cookie_jar,
+            &user,
+            user_agent.clone(),
+        )
+        .await?
+    } else {
+        cookie_jar
+    };
+
+    let reply = finish_login(
+        repo,
+        &mut rng,
+        &clock,
+        &locale,
+        &site_config,
+        &url_builder,
+        &activity_tracker,
+        PendingLogin::remove(cookie_jar),
+        &query,
+        LoginSession::New(&user, user_agent),
+        LoginMethod::TwoFactors(&first_factor, SecondFactor::Sms(&phone)),
+    )
+    .await?;
+
+    Ok(reply.into_response())
+}
+
+async fn render(
+    locale: DataLocale,
+    cookie_jar: CookieJar,
+    form_state: FormState<LoginSmsFormField>,
+    action: OptionalPostAuthAction,
+    user: User,
+    phone: &UserPhone,
+    repo: &mut impl RepositoryAccess,
+    clock: &impl Clock,
+    rng: impl Rng,
+    templates: &Templates,
+) -> Result<Response, InternalError> {
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(clock, rng);
+
+    let ctx = LoginSmsContext::new(user, phone).with_form_state(form_state);
+    let next = action
+        .load_context(repo)
+        .await
+        .map_err(InternalError::from_anyhow)?;
+    let ctx = if let Some(next) = next {
+        ctx.with_post_action(next)
+    } else {
+        ctx
+    };
+    let ctx = ctx.with_csrf(csrf_token.form_value()).with_language(locale);
+
+    let content = templates.render_login_sms(&ctx)?;
+    Ok((cookie_jar, Html(content)).into_response())
+}
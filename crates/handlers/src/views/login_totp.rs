@@ -0,0 +1,604 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Second step of a login with a password or a code sent by email, when the
+//! user has to enter a code from their authenticator app, or add one if their
+//! server requires it
+
+use axum::{
+    extract::{Form, Query, State},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::typed_header::TypedHeader;
+use chrono::{DateTime, Duration, Utc};
+use mas_axum_utils::{
+    InternalError,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::{
+    Authentication, BrowserSession, Password, SiteConfig, User, UserEmailAuthentication, UserPhone,
+    UserRecoveryCode, UserTotp,
+};
+use mas_i18n::DataLocale;
+use mas_keystore::Encrypter;
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, Clock, RepositoryAccess, RepositoryError,
+    user::{
+        BrowserSessionRepository, UserEmailRepository, UserPasswordRepository, UserRepository,
+        UserTotpRepository,
+    },
+};
+use mas_templates::{
+    FieldError, FormError, FormState, LoginTotpContext, LoginTotpFormField,
+    LoginTotpRecoveryCodesContext, TemplateContext, Templates, ToFormState, TotpEnrollment,
+};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+use zeroize::Zeroizing;
+
+use super::{
+    login::{LoginMethod, LoginSession, finish_login},
+    shared::OptionalPostAuthAction,
+};
+use crate::{
+    BoundActivityTracker, Limiter, PreferredLanguage, RequesterFingerprint,
+    totp::{self, encode_secret, generate_secret, provisioning_uri},
+    trusted_device,
+};
+
+/// Name of the cookie
+static COOKIE_NAME: &str = "login-totp";
+
+/// Users have 10 minutes to enter their code after their first factor
+static PENDING_LOGIN_MAX_TIME: Duration = Duration::microseconds(10 * 60 * 1000 * 1000);
+
+/// How the user started a pending login
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum PendingFirstFactor {
+    /// The user entered their password
+    Password { user_password_id: Ulid },
+
+    /// The user entered a code sent to their email address, or opened the
+    /// link sent with it, which completed this email authentication
+    EmailCode { user_email_authentication_id: Ulid },
+}
+
+impl From<&Password> for PendingFirstFactor {
+    fn from(user_password: &Password) -> Self {
+        Self::Password {
+            user_password_id: user_password.id,
+        }
+    }
+}
+
+impl From<&UserEmailAuthentication> for PendingFirstFactor {
+    fn from(authentication: &UserEmailAuthentication) -> Self {
+        Self::EmailCode {
+            user_email_authentication_id: authentication.id,
+        }
+    }
+}
+
+/// The first factor of a pending login, once checked that it can still be
+/// used
+pub(crate) enum FirstFactor {
+    Password(Password),
+    EmailCode(UserEmailAuthentication),
+}
+
+/// The second factor the user entered to complete a login
+pub(crate) enum SecondFactor<'a> {
+    Totp(&'a UserTotp),
+    RecoveryCode(&'a UserRecoveryCode),
+    Sms(&'a UserPhone),
+}
+
+impl FirstFactor {
+    /// Record that the session was authenticated by this factor, along with
+    /// the given second factor
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the repository fails
+    pub(crate) async fn authenticate(
+        &self,
+        repo: &mut BoxRepository,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        second_factor: SecondFactor<'_>,
+    ) -> Result<Authentication, RepositoryError> {
+        let mut sessions = repo.browser_session();
+        match (self, second_factor) {
+            (Self::Password(password), SecondFactor::Totp(totp)) => {
+                sessions
+                    .authenticate_with_password_and_totp(rng, clock, user_session, password, totp)
+                    .await
+            }
+            (Self::Password(password), SecondFactor::RecoveryCode(code)) => {
+                sessions
+                    .authenticate_with_password_and_recovery_code(
+                        rng,
+                        clock,
+                        user_session,
+                        password,
+                        code,
+                    )
+                    .await
+            }
+            (Self::Password(password), SecondFactor::Sms(phone)) => {
+                sessions
+                    .authenticate_with_password_and_sms(rng, clock, user_session, password, phone)
+                    .await
+            }
+            (Self::EmailCode(authentication), SecondFactor::Totp(totp)) => {
+                sessions
+                    .authenticate_with_email_code_and_totp(
+                        rng,
+                        clock,
+                        user_session,
+                        authentication,
+                        totp,
+                    )
+                    .await
+            }
+            (Self::EmailCode(authentication), SecondFactor::RecoveryCode(code)) => {
+                sessions
+                    .authenticate_with_email_code_and_recovery_code(
+                        rng,
+                        clock,
+                        user_session,
+                        authentication,
+                        code,
+                    )
+                    .await
+            }
+            (Self::EmailCode(authentication), SecondFactor::Sms(phone)) => {
+                sessions
+                    .authenticate_with_email_code_and_sms(
+                        rng,
+                        clock,
+                        user_session,
+                        authentication,
+                        phone,
+                    )
+                    .await
+            }
+        }
+    }
+}
+
+/// A login which checked the first factor of a user, and waits for their
+/// second factor
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct PendingLogin {
+    user_id: Ulid,
+    first_factor: PendingFirstFactor,
+    created_at: DateTime<Utc>,
+
+    /// The password of the user expired, or an administrator required them to
+    /// change it, so they have to change it before going further
+    #[serde(default)]
+    password_expired: bool,
+
+    /// The login looked suspicious, so the user has to confirm it with a code
+    /// sent by this email authentication
+    #[serde(default)]
+    user_email_authentication_id: Option<Ulid>,
+}
+
+impl PendingLogin {
+    pub(crate) fn new(
+        user: &User,
+        first_factor: impl Into<PendingFirstFactor>,
+        clock: &impl Clock,
+    ) -> Self {
+        Self {
+            user_id: user.id,
+            first_factor: first_factor.into(),
+            created_at: clock.now(),
+            password_expired: false,
+            user_email_authentication_id: None,
+        }
+    }
+
+    /// Mark the password of the user as expired
+    pub(crate) fn with_expired_password(self) -> Self {
+        Self {
+            password_expired: true,
+            ..self
+        }
+    }
+
+    /// Ask the user to confirm the login with the code sent by the given email
+    /// authentication
+    pub(crate) fn with_email_confirmation(self, authentication: &UserEmailAuthentication) -> Self {
+        Self {
+            user_email_authentication_id: Some(authentication.id),
+            ..self
+        }
+    }
+
+    /// Load the pending login from the cookie jar, if it didn't expire yet and
+    /// the user doesn't have to change their password or confirm the login
+    /// first
+    pub(crate) fn load(cookie_jar: &CookieJar, clock: &impl Clock) -> Option<Self> {
+        Self::load_any(cookie_jar, clock).filter(|pending| {
+            !pending.password_expired && pending.user_email_authentication_id.is_none()
+        })
+    }
+
+    /// Load the pending login from the cookie jar, if it didn't expire yet and
+    /// the user has to confirm it with a code sent by email. Returns the ID of
+    /// the email authentication which sent the code.
+    pub(crate) fn load_with_email_confirmation(
+        cookie_jar: &CookieJar,
+        clock: &impl Clock,
+    ) -> Option<(Self, Ulid)> {
+        Self::load_any(cookie_jar, clock).and_then(|pending| {
+            let id = pending.user_email_authentication_id?;
+            Some((pending, id))
+        })
+    }
+
+    /// Load the pending login from the cookie jar, if it didn't expire yet and
+    /// the user has to change their expired password
+    pub(crate) fn load_with_expired_password(
+        cookie_jar: &CookieJar,
+        clock: &impl Clock,
+    ) -> Option<Self> {
+        Self::load_any(cookie_jar, clock).filter(|pending| pending.password_expired)
+    }
+
+    fn load_any(cookie_jar: &CookieJar, clock: &impl Clock) -> Option<Self> {
+        match cookie_jar.load::<Self>(COOKIE_NAME) {
+            Ok(Some(pending)) if clock.now() - pending.created_at <= PENDING_LOGIN_MAX_TIME => {
+                Some(pending)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Invalid pending login cookie: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Save the pending login to the cookie jar
+    pub(crate) fn save(&self, cookie_jar: CookieJar) -> CookieJar {
+        cookie_jar.save(COOKIE_NAME, self, false)
+    }
+
+    pub(crate) fn remove(cookie_jar: CookieJar) -> CookieJar {
+        cookie_jar.remove(COOKIE_NAME)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct LoginTotpForm {
+    code: String,
+
+    /// Unchecked checkboxes are not sent with the form
+    #[serde(default)]
+    remember_device: Option<String>,
+}
+
+impl ToFormState for LoginTotpForm {
+    type Field = LoginTotpFormField;
+}
+
+#[tracing::instrument(name = "handlers.views.login_totp.get", skip_all)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    State(encrypter): State<Encrypter>,
+    mut repo: BoxRepository,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+) -> Result<Response, InternalError> {
+    let Some(pending) = PendingLogin::load(&cookie_jar, &clock) else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(user) = lookup_user(&mut repo, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let totp = if let Some(totp) = repo.user_totp().find_for_user(&user).await? {
+        totp
+    } else {
+        // The server requires a second factor, but the user doesn't have one yet, so
+        // they have to add one to their authenticator app before going further
+        let secret = generate_secret(&mut rng);
+        let encrypted_secret = encrypter.encrypt_to_string(&secret)?;
+        repo.user_totp()
+            .add(&mut rng, &clock, &user, encrypted_secret)
+            .await?
+    };
+
+    let response = render(
+        locale,
+        cookie_jar,
+        FormState::default(),
+        query,
+        user,
+        &totp,
+        &mut repo,
+        &clock,
+        &mut rng,
+        &templates,
+        &site_config,
+        &encrypter,
+    )
+    .await?;
+
+    repo.save().await?;
+
+    Ok(response)
+}
+
+#[tracing::instrument(name = "handlers.views.login_totp.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    State(encrypter): State<Encrypter>,
+    State(limiter): State<Limiter>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    requester: RequesterFingerprint,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+    user_agent: Option<TypedHeader<headers::UserAgent>>,
+    Form(form): Form<ProtectedForm<LoginTotpForm>>,
+) -> Result<Response, InternalError> {
+    let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
+    let form = cookie_jar.verify_form(&clock, form)?;
+
+    let Some(pending) = PendingLogin::load(&cookie_jar, &clock) else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let Some(user) = lookup_user(&mut repo, &pending).await? else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let (Some(first_factor), Some(totp)) = (
+        lookup_first_factor(&mut repo, &user, &pending).await?,
+        repo.user_totp().find_for_user(&user).await?,
+    ) else {
+        let cookie_jar = PendingLogin::remove(cookie_jar);
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let mut form_state = form.to_form_state();
+    let enrolling = !totp.is_confirmed();
+
+    // Codes are short, so they share the rate limit of passwords
+    if let Err(e) = limiter.check_password(requester, &user) {
+        tracing::warn!(error = &e as &dyn std::error::Error);
+        let form_state = form_state.with_error_on_form(FormError::RateLimitExceeded);
+        return render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            user,
+            &totp,
+            &mut repo,
+            &clock,
+            &mut rng,
+            &templates,
+            &site_config,
+            &encrypter,
+        )
+        .await;
+    }
+
+    let Some(totp) =
+        totp::check_code(&mut repo, &clock, &encrypter, totp.clone(), &form.code).await?
+    else {
+        form_state.add_error_on_field(LoginTotpFormField::Code, FieldError::Invalid);
+        let response = render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            user,
+            &totp,
+            &mut repo,
+            &clock,
+            &mut rng,
+            &templates,
+            &site_config,
+            &encrypter,
+        )
+        .await?;
+        repo.save().await?;
+        return Ok(response);
+    };
+
+    // The user just added their second factor, so they get a set of recovery
+    // codes, which are shown before going further
+    let recovery_codes = if enrolling {
+        let codes = totp::replace_recovery_codes(&mut repo, &mut rng, &clock, &user).await?;
+        let ctx = LoginTotpRecoveryCodesContext::new(codes);
+        let next = query
+            .load_context(&mut repo)
+            .await
+            .map_err(InternalError::from_anyhow)?;
+        let ctx = if let Some(next) = next {
+            ctx.with_post_action(next)
+        } else {
+            ctx
+        };
+        Some(ctx)
+    } else {
+        None
+    };
+
+    // The user may not want to enter their second factor again in this browser
+    let cookie_jar = if form.remember_device.is_some() {
+        trusted_device::trust(
+            &mut repo,
+            &mut rng,
+            &clock,
+            &site_config,
+            cookie_jar,
+            &user,
+            user_agent.clone(),
+        )
+        .await?
+    } else {
+        cookie_jar
+    };
+
+    let (cookie_jar, reply) = finish_login(
+        repo,
+        &mut rng,
+        &clock,
+        &locale,
+        &site_config,
+        &url_builder,
+        &activity_tracker,
+        PendingLogin::remove(cookie_jar),
+        &query,
+        LoginSession::New(&user, user_agent),
+        LoginMethod::TwoFactors(&first_factor, SecondFactor::Totp(&totp)),
+    )
+    .await?;
+
+    if let Some(ctx) = recovery_codes {
+        let ctx = ctx.with_language(locale);
+        let content = templates.render_login_totp_recovery_codes(&ctx)?;
+        return Ok((cookie_jar, Html(content)).into_response());
+    }
+
+    Ok((cookie_jar, reply).into_response())
+}
+
+/// Lookup the user of a pending login, making sure they can still log in
+pub(crate) async fn lookup_user(
+    repo: &mut impl RepositoryAccess,
+    pending: &PendingLogin,
+) -> Result<Option<User>, InternalError> {
+    let user = repo
+        .user()
+        .lookup(pending.user_id)
+        .await?
+        .filter(User::is_valid);
+    Ok(user)
+}
+
+/// Lookup the first factor checked by a pending login. The password may have
+/// changed since it was checked, in which case the user has to start again.
+pub(crate) async fn lookup_first_factor(
+    repo: &mut impl RepositoryAccess,
+    user: &User,
+    pending: &PendingLogin,
+) -> Result<Option<FirstFactor>, InternalError> {
+    match pending.first_factor {
+        PendingFirstFactor::Password { user_password_id } => {
+            let user_password = repo
+                .user_password()
+                .active(user)
+                .await?
+                .filter(|password| password.id == user_password_id);
+            Ok(user_password.map(FirstFactor::Password))
+        }
+
+        PendingFirstFactor::EmailCode {
+            user_email_authentication_id,
+        } => {
+            // The authentication was completed when the user entered the code
+            let authentication = repo
+                .user_email()
+                .lookup_authentication(user_email_authentication_id)
+                .await?
+                .filter(|authentication| {
+                    authentication.user_id == Some(user.id) && authentication.completed_at.is_some()
+                });
+            Ok(authentication.map(FirstFactor::EmailCode))
+        }
+    }
+}
+
+/// Lookup the password checked by a pending login, for the steps which only
+/// follow a password login
+pub(crate) async fn lookup_password(
+    repo: &mut impl RepositoryAccess,
+    user: &User,
+    pending: &PendingLogin,
+) -> Result<Option<Password>, InternalError> {
+    match lookup_first_factor(repo, user, pending).await? {
+        Some(FirstFactor::Password(user_password)) => Ok(Some(user_password)),
+        Some(FirstFactor::EmailCode(_)) | None => Ok(None),
+    }
+}
+
+async fn render(
+    locale: DataLocale,
+    cookie_jar: CookieJar,
+    form_state: FormState<LoginTotpFormField>,
+    action: OptionalPostAuthAction,
+    user: User,
+    totp: &UserTotp,
+    repo: &mut impl RepositoryAccess,
+    clock: &impl Clock,
+    rng: impl Rng,
+    templates: &Templates,
+    site_config: &SiteConfig,
+    encrypter: &Encrypter,
+) -> Result<Response, InternalError> {
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(clock, rng);
+
+    // Until the second factor is confirmed, show what the user needs to add it to
+    // their authenticator app
+    let enrollment = if totp.is_confirmed() {
+        None
+    } else {
+        let secret = Zeroizing::new(encrypter.decrypt_string(&totp.encrypted_secret)?);
+        let uri = provisioning_uri(&site_config.server_name, &user.username, &secret);
+        Some(TotpEnrollment::new(encode_secret(&secret), uri.to_string()))
+    };
+
+    let ctx = LoginTotpContext::new(user).with_form_state(form_state);
+    let ctx = if let Some(enrollment) = enrollment {
+        ctx.with_enrollment(enrollment)
+    } else {
+        ctx
+    };
+
+    let next = action
+        .load_context(repo)
+        .await
+        .map_err(InternalError::from_anyhow)?;
+    let ctx = if let Some(next) = next {
+        ctx.with_post_action(next)
+    } else {
+        ctx
+    };
+    let ctx = ctx.with_csrf(csrf_token.form_value()).with_language(locale);
+
+    let content = templates.render_login_totp(&ctx)?;
+    Ok((cookie_jar, Html(content)).into_response())
+}
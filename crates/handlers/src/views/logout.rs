@@ -14,12 +14,17 @@ use mas_axum_utils::{
     csrf::{CsrfExt, ProtectedForm},
 };
 use mas_router::{PostAuthAction, UrlBuilder};
-use mas_storage::{BoxClock, BoxRepository, user::BrowserSessionRepository};
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng,
+    queue::{QueueJobRepositoryExt as _, SendBrowserSessionBackchannelLogoutsJob},
+    user::BrowserSessionRepository,
+};
 
 use crate::BoundActivityTracker;
 
 #[tracing::instrument(name = "handlers.views.logout.post", skip_all)]
 pub(crate) async fn post(
+    mut rng: BoxRng,
     clock: BoxClock,
     mut repo: BoxRepository,
     cookie_jar: CookieJar,
@@ -39,6 +44,16 @@ pub(crate) async fn post(
                     .record_browser_session(&clock, &session)
                     .await;
 
+                // Let the clients which got a session through this one know about
+                // the logout
+                repo.queue_job()
+                    .schedule_job(
+                        &mut rng,
+                        &clock,
+                        SendBrowserSessionBackchannelLogoutsJob::new(&session),
+                    )
+                    .await?;
+
                 repo.browser_session().finish(&clock, session).await?;
             }
         }
@@ -4,10 +4,23 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+pub mod accept_terms;
 pub mod app;
+pub mod claim;
 pub mod index;
+pub mod invites;
 pub mod login;
+pub mod login_confirm_email;
+pub mod login_email;
+pub mod login_password_change;
+pub mod login_recovery_code;
+pub mod login_sms;
+pub mod login_totp;
 pub mod logout;
+pub mod notifications;
+pub mod reauth;
 pub mod recovery;
 pub mod register;
+pub mod restore_account;
 pub mod shared;
+pub mod user_action;
@@ -0,0 +1,297 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Page where users choose which emails they get about their account
+
+use axum::{
+    extract::{Form, State},
+    response::{Html, IntoResponse, Response},
+};
+use hyper::StatusCode;
+use mas_axum_utils::{
+    InternalError,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::{SiteConfig, User, UserMetadata};
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, RepositoryAccess, RepositoryError,
+    user::UserMetadataRepository,
+};
+use mas_templates::{NotificationsContext, TemplateContext, Templates};
+use serde::Deserialize;
+
+use crate::{
+    BoundActivityTracker, PreferredLanguage,
+    session::{SessionOrFallback, load_session_or_fallback},
+};
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct NotificationsForm {
+    /// Unchecked checkboxes are not sent with the form
+    #[serde(default)]
+    sign_in_notifications: Option<String>,
+}
+
+/// Check whether the user gets an email when logging in from a new IP address
+/// or device
+async fn sign_in_notifications(
+    repo: &mut BoxRepository,
+    user: &User,
+) -> Result<bool, RepositoryError> {
+    let entry = repo
+        .user_metadata()
+        .get(
+            user,
+            UserMetadata::SETTINGS_NAMESPACE,
+            UserMetadata::SIGN_IN_NOTIFICATIONS_SETTING,
+        )
+        .await?;
+
+    Ok(!entry.is_some_and(|entry| entry.value == serde_json::Value::Bool(false)))
+}
+
+#[tracing::instrument(name = "handlers.views.notifications.get", skip_all)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    cookie_jar: CookieJar,
+) -> Result<Response, InternalError> {
+    if !site_config.sign_in_notifications_enabled {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+
+    let (cookie_jar, maybe_session) = match load_session_or_fallback(
+        cookie_jar,
+        &clock,
+        &mut rng,
+        &templates,
+        &locale,
+        &mut repo,
+        &site_config,
+    )
+    .await?
+    {
+        SessionOrFallback::MaybeSession {
+            cookie_jar,
+            maybe_session,
+            ..
+        } => (cookie_jar, maybe_session),
+        SessionOrFallback::Fallback { response } => return Ok(response),
+    };
+
+    let Some(session) = maybe_session else {
+        let login = mas_router::Login::default();
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    activity_tracker
+        .record_browser_session(&clock, &session)
+        .await;
+
+    let enabled = sign_in_notifications(&mut repo, &session.user).await?;
+
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
+    let ctx = NotificationsContext::new(enabled)
+        .with_session(session)
+        .with_csrf(csrf_token.form_value())
+        .with_language(locale);
+
+    let content = templates.render_notifications(&ctx)?;
+    Ok((cookie_jar, Html(content)).into_response())
+}
+
+#[tracing::instrument(name = "handlers.views.notifications.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    mut repo: BoxRepository,
+    cookie_jar: CookieJar,
+    Form(form): Form<ProtectedForm<NotificationsForm>>,
+) -> Result<Response, InternalError> {
+    if !site_config.sign_in_notifications_enabled {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+
+    let form = cookie_jar.verify_form(&clock, form)?;
+
+    let (cookie_jar, maybe_session) = match load_session_or_fallback(
+        cookie_jar,
+        &clock,
+        &mut rng,
+        &templates,
+        &locale,
+        &mut repo,
+        &site_config,
+    )
+    .await?
+    {
+        SessionOrFallback::MaybeSession {
+            cookie_jar,
+            maybe_session,
+            ..
+        } => (cookie_jar, maybe_session),
+        SessionOrFallback::Fallback { response } => return Ok(response),
+    };
+
+    let Some(session) = maybe_session else {
+        let login = mas_router::Login::default();
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let enabled = form.sign_in_notifications.is_some();
+    let value = serde_json::Value::Bool(enabled);
+    let existing = repo
+        .user_metadata()
+        .get(
+            &session.user,
+            UserMetadata::SETTINGS_NAMESPACE,
+            UserMetadata::SIGN_IN_NOTIFICATIONS_SETTING,
+        )
+        .await?;
+
+    if let Some(entry) = existing {
+        repo.user_metadata().update(&clock, entry, value).await?;
+    } else {
+        repo.user_metadata()
+            .add(
+                &mut rng,
+                &clock,
+                &session.user,
+                UserMetadata::SETTINGS_NAMESPACE.to_owned(),
+                UserMetadata::SIGN_IN_NOTIFICATIONS_SETTING.to_owned(),
+                value,
+            )
+            .await?;
+    }
+
+    repo.save().await?;
+
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
+    let ctx = NotificationsContext::new(enabled)
+        .saved()
+        .with_session(session)
+        .with_csrf(csrf_token.form_value())
+        .with_language(locale);
+
+    let content = templates.render_notifications(&ctx)?;
+    Ok((cookie_jar, Html(content)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::{Request, StatusCode};
+    use mas_axum_utils::SessionInfoExt;
+    use mas_data_model::{SiteConfig, UserMetadata};
+    use mas_storage::{
+        RepositoryAccess,
+        user::{BrowserSessionRepository, UserMetadataRepository, UserRepository},
+    };
+    use sqlx::PgPool;
+
+    use crate::test_utils::{
+        CookieHelper, RequestBuilderExt, ResponseExt, TestState, setup, test_site_config,
+    };
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_disabled(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+
+        let response = state.request(Request::get("/notifications").empty()).await;
+        response.assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_opt_out(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool_with_site_config(
+            pool,
+            SiteConfig {
+                sign_in_notifications_enabled: true,
+                ..test_site_config()
+            },
+        )
+        .await
+        .unwrap();
+        let cookies = CookieHelper::new();
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+        cookies.import(state.cookie_jar().set_session(&browser_session));
+
+        // Notifications are enabled until the user opts out
+        let request = cookies.with_cookies(Request::get("/notifications").empty());
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("checked"));
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        // Submitting the form without the checkbox opts out
+        let request = Request::post("/notifications").form(serde_json::json!({
+            "csrf": csrf_token,
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        assert!(!response.body().contains("checked"));
+
+        let mut repo = state.repository().await.unwrap();
+        let entry = repo
+            .user_metadata()
+            .get(
+                &user,
+                UserMetadata::SETTINGS_NAMESPACE,
+                UserMetadata::SIGN_IN_NOTIFICATIONS_SETTING,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.value, serde_json::Value::Bool(false));
+        repo.save().await.unwrap();
+
+        // And checking it again opts back in
+        let request = Request::post("/notifications").form(serde_json::json!({
+            "csrf": csrf_token,
+            "sign_in_notifications": "on",
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(response.body().contains("checked"));
+    }
+}
@@ -0,0 +1,540 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Step-up authentication: the user confirms their password to elevate their
+//! current browser session, instead of logging in again
+//!
+//! Users can also authenticate the session again with a passkey or with an
+//! upstream provider they are linked to, which puts it back in sudo mode.
+
+use axum::{
+    extract::{Form, Query, State},
+    response::{Html, IntoResponse, Response},
+};
+use hyper::StatusCode;
+use mas_axum_utils::{
+    InternalError,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::{BrowserSession, SiteConfig, UpstreamOAuthProvider, User};
+use mas_i18n::DataLocale;
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, Clock, Pagination, RepositoryAccess, RepositoryError,
+    upstream_oauth2::{
+        UpstreamOAuthLinkFilter, UpstreamOAuthLinkRepository, UpstreamOAuthProviderRepository,
+    },
+    user::{BrowserSessionRepository, UserPasskeyRepository, UserPasswordRepository},
+};
+use mas_templates::{
+    FieldError, FormError, FormState, PasskeyLoginChallenge, ReauthContext, ReauthFormField,
+    TemplateContext, Templates, ToFormState,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+use zeroize::Zeroizing;
+
+use super::shared::OptionalPostAuthAction;
+use crate::{
+    BoundActivityTracker, Limiter, PreferredLanguage, RequesterFingerprint,
+    oauth2::acr,
+    passwords::PasswordManager,
+    session::{SessionOrFallback, load_session_or_fallback},
+    webauthn::{self, AuthenticationResponse, RelyingParty},
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct ReauthForm {
+    password: String,
+}
+
+impl ToFormState for ReauthForm {
+    type Field = ReauthFormField;
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct PasskeyReauthForm {
+    challenge_id: Ulid,
+    response: String,
+}
+
+/// The ways a user can authenticate their session again with
+struct Methods {
+    password: bool,
+    passkey: bool,
+    providers: Vec<UpstreamOAuthProvider>,
+}
+
+impl Methods {
+    async fn load(
+        repo: &mut BoxRepository,
+        site_config: &SiteConfig,
+        user: &User,
+    ) -> Result<Self, RepositoryError> {
+        let password = site_config.password_login_enabled
+            && repo.user_password().active(user).await?.is_some();
+
+        let passkey =
+            site_config.passkeys_enabled && !repo.user_passkey().all(user).await?.is_empty();
+
+        // Only the providers the user is linked to can tell it's them
+        let links = repo
+            .upstream_oauth_link()
+            .list(
+                UpstreamOAuthLinkFilter::new()
+                    .for_user(user)
+                    .enabled_providers_only(),
+                Pagination::first(100),
+            )
+            .await?;
+        let providers = repo
+            .upstream_oauth_provider()
+            .all_enabled()
+            .await?
+            .into_iter()
+            .filter(|provider| {
+                links
+                    .edges
+                    .iter()
+                    .any(|link| link.provider_id == provider.id)
+            })
+            .collect();
+
+        Ok(Self {
+            password,
+            passkey,
+            providers,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.password && !self.passkey && self.providers.is_empty()
+    }
+}
+
+#[tracing::instrument(name = "handlers.views.reauth.get", skip_all)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(site_config): State<SiteConfig>,
+    mut repo: BoxRepository,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+) -> Result<Response, InternalError> {
+    let (cookie_jar, maybe_session) = match load_session_or_fallback(
+        cookie_jar,
+        &clock,
+        &mut rng,
+        &templates,
+        &locale,
+        &mut repo,
+        &site_config,
+    )
+    .await?
+    {
+        SessionOrFallback::MaybeSession {
+            cookie_jar,
+            maybe_session,
+            ..
+        } => (cookie_jar, maybe_session),
+        SessionOrFallback::Fallback { response } => return Ok(response),
+    };
+
+    let Some(session) = maybe_session else {
+        // If there is no session, redirect to the login screen, keeping the
+        // post-auth action
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    // Users who can't confirm it's them in any other way have to log in again
+    let methods = Methods::load(&mut repo, &site_config, &session.user).await?;
+    if methods.is_empty() {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    }
+
+    render(
+        locale,
+        cookie_jar,
+        FormState::default(),
+        query,
+        session,
+        methods,
+        repo,
+        &clock,
+        &mut rng,
+        &templates,
+        &url_builder,
+    )
+    .await
+}
+
+#[tracing::instrument(name = "handlers.views.reauth.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(password_manager): State<PasswordManager>,
+    State(site_config): State<SiteConfig>,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    State(limiter): State<Limiter>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    requester: RequesterFingerprint,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+    Form(form): Form<ProtectedForm<ReauthForm>>,
+) -> Result<Response, InternalError> {
+    if !site_config.password_login_enabled {
+        return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response());
+    }
+
+    let form = cookie_jar.verify_form(&clock, form)?;
+
+    let (cookie_jar, maybe_session) = match load_session_or_fallback(
+        cookie_jar,
+        &clock,
+        &mut rng,
+        &templates,
+        &locale,
+        &mut repo,
+        &site_config,
+    )
+    .await?
+    {
+        SessionOrFallback::MaybeSession {
+            cookie_jar,
+            maybe_session,
+            ..
+        } => (cookie_jar, maybe_session),
+        SessionOrFallback::Fallback { response } => return Ok(response),
+    };
+
+    let Some(session) = maybe_session else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let methods = Methods::load(&mut repo, &site_config, &session.user).await?;
+    let mut form_state = form.to_form_state();
+
+    if form.password.is_empty() {
+        form_state.add_error_on_field(ReauthFormField::Password, FieldError::Required);
+        return render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            session,
+            methods,
+            repo,
+            &clock,
+            &mut rng,
+            &templates,
+            &url_builder,
+        )
+        .await;
+    }
+
+    // Check the rate limit
+    if let Err(e) = limiter.check_password(requester, &session.user) {
+        tracing::warn!(error = &e as &dyn std::error::Error);
+        let form_state = form_state.with_error_on_form(FormError::RateLimitExceeded);
+        return render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            session,
+            methods,
+            repo,
+            &clock,
+            &mut rng,
+            &templates,
+            &url_builder,
+        )
+        .await;
+    }
+
+    let Some(user_password) = repo.user_password().active(&session.user).await? else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let password = Zeroizing::new(form.password);
+
+    // Verify the password, and upgrade it on-the-fly if needed
+    match password_manager
+        .verify_and_upgrade(
+            &mut rng,
+            user_password.version,
+            password,
+            user_password.hashed_password.clone(),
+        )
+        .await
+    {
+        Ok(Some((version, new_password_hash))) => {
+            // Save the upgraded password
+            repo.user_password()
+                .add(
+                    &mut rng,
+                    &clock,
+                    &session.user,
+                    version,
+                    new_password_hash,
+                    Some(&user_password),
+                )
+                .await?;
+        }
+        Ok(None) => {}
+        Err(_) => {
+            let form_state = form_state.with_error_on_form(FormError::InvalidCredentials);
+            return render(
+                locale,
+                cookie_jar,
+                form_state,
+                query,
+                session,
+                methods,
+                repo,
+                &clock,
+                &mut rng,
+                &templates,
+                &url_builder,
+            )
+            .await;
+        }
+    }
+
+    // Record the elevation on the session, with the level a password satisfies
+    let acr = acr::satisfied_acr_for_amr(&site_config, &["pwd"]).map(ToOwned::to_owned);
+    repo.browser_session()
+        .elevate(&mut rng, &clock, &session, acr, site_config.step_up_ttl)
+        .await?;
+
+    repo.save().await?;
+
+    activity_tracker
+        .record_browser_session(&clock, &session)
+        .await;
+
+    let reply = query.go_next(&url_builder);
+    Ok((cookie_jar, reply).into_response())
+}
+
+#[tracing::instrument(name = "handlers.views.reauth.post_passkey", skip_all)]
+pub(crate) async fn post_passkey(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    State(site_config): State<SiteConfig>,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    mut repo: BoxRepository,
+    activity_tracker: BoundActivityTracker,
+    Query(query): Query<OptionalPostAuthAction>,
+    cookie_jar: CookieJar,
+    Form(form): Form<ProtectedForm<PasskeyReauthForm>>,
+) -> Result<Response, InternalError> {
+    if !site_config.passkeys_enabled {
+        return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response());
+    }
+
+    let form = cookie_jar.verify_form(&clock, form)?;
+
+    let (cookie_jar, maybe_session) = match load_session_or_fallback(
+        cookie_jar,
+        &clock,
+        &mut rng,
+        &templates,
+        &locale,
+        &mut repo,
+        &site_config,
+    )
+    .await?
+    {
+        SessionOrFallback::MaybeSession {
+            cookie_jar,
+            maybe_session,
+            ..
+        } => (cookie_jar, maybe_session),
+        SessionOrFallback::Fallback { response } => return Ok(response),
+    };
+
+    let Some(session) = maybe_session else {
+        let login = mas_router::Login::from(query.post_auth_action);
+        return Ok((cookie_jar, url_builder.redirect(&login)).into_response());
+    };
+
+    let methods = Methods::load(&mut repo, &site_config, &session.user).await?;
+    let form_state = FormState::default().with_error_on_form(FormError::InvalidCredentials);
+
+    // Only challenges made to authenticate can be used here, not the ones made
+    // to register a passkey from a session
+    let challenge = repo
+        .user_passkey()
+        .lookup_challenge(form.challenge_id)
+        .await?
+        .filter(|challenge| {
+            challenge.user_session_id.is_none()
+                && webauthn::is_challenge_valid(challenge, clock.now())
+        });
+
+    let Some(challenge) = challenge else {
+        return render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            session,
+            methods,
+            repo,
+            &clock,
+            &mut rng,
+            &templates,
+            &url_builder,
+        )
+        .await;
+    };
+
+    // Consume the challenge right away, so that it can't be tried again
+    let challenge = repo
+        .user_passkey()
+        .complete_challenge(&clock, challenge)
+        .await?;
+
+    let response: Option<AuthenticationResponse> = serde_json::from_str(&form.response).ok();
+    let passkey = if let Some(response) = &response {
+        repo.user_passkey()
+            .find_by_credential_id(response.id.trim_end_matches('='))
+            .await?
+    } else {
+        None
+    };
+
+    // The passkey has to belong to the user of the session
+    let passkey = passkey.filter(|passkey| passkey.user_id == session.user.id);
+    let (Some(response), Some(passkey)) = (response, passkey) else {
+        return render(
+            locale,
+            cookie_jar,
+            form_state,
+            query,
+            session,
+            methods,
+            repo,
+            &clock,
+            &mut rng,
+            &templates,
+            &url_builder,
+        )
+        .await;
+    };
+
+    let sign_count = match RelyingParty::new(&url_builder)
+        .verify_authentication(&challenge, &passkey, &response)
+    {
+        Ok(sign_count) => sign_count,
+        Err(e) => {
+            tracing::warn!(
+                error = &e as &dyn std::error::Error,
+                %passkey.id,
+                "Failed to verify the passkey response"
+            );
+            return render(
+                locale,
+                cookie_jar,
+                form_state,
+                query,
+                session,
+                methods,
+                repo,
+                &clock,
+                &mut rng,
+                &templates,
+                &url_builder,
+            )
+            .await;
+        }
+    };
+
+    let passkey = repo
+        .user_passkey()
+        .record_use(&clock, passkey, sign_count)
+        .await?;
+
+    // Authenticate the current session again, instead of starting a new one
+    repo.browser_session()
+        .authenticate_with_passkey(&mut rng, &clock, &session, &passkey)
+        .await?;
+
+    repo.save().await?;
+
+    activity_tracker
+        .record_browser_session(&clock, &session)
+        .await;
+
+    let reply = query.go_next(&url_builder);
+    Ok((cookie_jar, reply).into_response())
+}
+
+async fn render(
+    locale: DataLocale,
+    cookie_jar: CookieJar,
+    form_state: FormState<ReauthFormField>,
+    action: OptionalPostAuthAction,
+    session: BrowserSession,
+    methods: Methods,
+    mut repo: BoxRepository,
+    clock: &impl Clock,
+    mut rng: impl RngCore + Send,
+    templates: &Templates,
+    url_builder: &UrlBuilder,
+) -> Result<Response, InternalError> {
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(clock, &mut rng);
+
+    let mut ctx = ReauthContext::default()
+        .with_form_state(form_state)
+        .with_upstream_providers(methods.providers);
+
+    if methods.password {
+        ctx = ctx.with_password();
+    }
+
+    // Every render gets a fresh challenge, so that the passkey button always
+    // works, even after a failed attempt
+    if methods.passkey {
+        let challenge = repo.user_passkey().add_challenge(&mut rng, clock).await?;
+        let options = RelyingParty::new(url_builder).request_options(&challenge);
+        ctx = ctx.with_passkey_challenge(PasskeyLoginChallenge::new(challenge.id, options));
+    }
+
+    let next = action
+        .load_context(&mut repo)
+        .await
+        .map_err(InternalError::from_anyhow)?;
+    let ctx = if let Some(next) = next {
+        ctx.with_post_action(next)
+    } else {
+        ctx
+    };
+    let ctx = ctx
+        .with_session(session)
+        .with_csrf(csrf_token.form_value())
+        .with_language(locale);
+
+    let content = templates.render_reauth(&ctx)?;
+
+    repo.save().await?;
+
+    Ok((cookie_jar, Html(content)).into_response())
+}
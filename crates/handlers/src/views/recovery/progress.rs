@@ -46,7 +46,9 @@ pub(crate) async fn get(
     let (session_info, cookie_jar) = cookie_jar.session_info();
     let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
 
-    let maybe_session = session_info.load_active_session(&mut repo).await?;
+    let maybe_session = session_info
+        .load_active_session(&mut repo, &clock, &site_config)
+        .await?;
     if maybe_session.is_some() {
         // TODO: redirect to continue whatever action was going on
         return Ok((cookie_jar, url_builder.redirect(&mas_router::Index)).into_response());
@@ -100,7 +102,9 @@ pub(crate) async fn post(
     let (session_info, cookie_jar) = cookie_jar.session_info();
     let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
 
-    let maybe_session = session_info.load_active_session(&mut repo).await?;
+    let maybe_session = session_info
+        .load_active_session(&mut repo, &clock, &site_config)
+        .await?;
     if maybe_session.is_some() {
         // TODO: redirect to continue whatever action was going on
         return Ok((cookie_jar, url_builder.redirect(&mas_router::Index)).into_response());
@@ -124,6 +128,15 @@ pub(crate) async fn post(
     // Verify the CSRF token
     let () = cookie_jar.verify_form(&clock, form)?;
 
+    // Until an administrator approves the session, there is no link to send
+    if !recovery_session.is_approved() {
+        let context = RecoveryProgressContext::new(recovery_session, false)
+            .with_csrf(csrf_token.form_value())
+            .with_language(locale);
+        let rendered = templates.render_recovery_progress(&context)?;
+        return Ok((cookie_jar, Html(rendered)).into_response());
+    }
+
     // Check the rate limit if we are about to process the form
     if let Err(e) = limiter.check_account_recovery(requester, &recovery_session.email) {
         tracing::warn!(error = &e as &dyn std::error::Error);
@@ -35,6 +35,11 @@ use crate::{BoundActivityTracker, Limiter, PreferredLanguage, RequesterFingerpri
 #[derive(Deserialize, Serialize)]
 pub(crate) struct StartRecoveryForm {
     email: String,
+
+    /// Set by the checkbox shown when recovery requests are reviewed by an
+    /// administrator
+    #[serde(default)]
+    lost_second_factor: Option<String>,
 }
 
 pub(crate) async fn get(
@@ -56,7 +61,9 @@ pub(crate) async fn get(
     let (session_info, cookie_jar) = cookie_jar.session_info();
     let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
 
-    let maybe_session = session_info.load_active_session(&mut repo).await?;
+    let maybe_session = session_info
+        .load_active_session(&mut repo, &clock, &site_config)
+        .await?;
     if maybe_session.is_some() {
         // TODO: redirect to continue whatever action was going on
         return Ok((cookie_jar, url_builder.redirect(&mas_router::Index)).into_response());
@@ -96,7 +103,9 @@ pub(crate) async fn post(
     let (session_info, cookie_jar) = cookie_jar.session_info();
     let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
 
-    let maybe_session = session_info.load_active_session(&mut repo).await?;
+    let maybe_session = session_info
+        .load_active_session(&mut repo, &clock, &site_config)
+        .await?;
     if maybe_session.is_some() {
         // TODO: redirect to continue whatever action was going on
         return Ok((cookie_jar, url_builder.redirect(&mas_router::Index)).into_response());
@@ -133,6 +142,11 @@ pub(crate) async fn post(
         return Ok((cookie_jar, Html(rendered)).into_response());
     }
 
+    // Users can only get their second factor removed if an administrator
+    // reviews the request first
+    let requires_approval = site_config.account_recovery_requires_approval;
+    let lost_second_factor = requires_approval && form.lost_second_factor.is_some();
+
     let session = repo
         .user_recovery()
         .add_session(
@@ -142,6 +156,8 @@ pub(crate) async fn post(
             user_agent,
             ip_address,
             locale.to_string(),
+            lost_second_factor,
+            requires_approval,
         )
         .await?;
 
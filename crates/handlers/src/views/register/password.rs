@@ -38,7 +38,8 @@ use zeroize::Zeroizing;
 use super::cookie::UserRegistrationSessions;
 use crate::{
     BoundActivityTracker, Limiter, PreferredLanguage, RequesterFingerprint, SiteConfig,
-    captcha::Form as CaptchaForm, passwords::PasswordManager,
+    captcha::{Form as CaptchaForm, ProofOfWorkReplayCache},
+    passwords::PasswordManager,
     views::shared::OptionalPostAuthAction,
 };
 
@@ -81,7 +82,9 @@ pub(crate) async fn get(
     let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
     let (session_info, cookie_jar) = cookie_jar.session_info();
 
-    let maybe_session = session_info.load_active_session(&mut repo).await?;
+    let maybe_session = session_info
+        .load_active_session(&mut repo, &clock, &site_config)
+        .await?;
 
     if maybe_session.is_some() {
         let reply = query.action.go_next(&url_builder);
@@ -129,7 +132,10 @@ pub(crate) async fn post(
     State(url_builder): State<UrlBuilder>,
     State(site_config): State<SiteConfig>,
     State(homeserver): State<Arc<dyn HomeserverConnection>>,
-    State(http_client): State<reqwest::Client>,
+    (State(http_client), State(proof_of_work_replay_cache)): (
+        State<reqwest::Client>,
+        State<ProofOfWorkReplayCache>,
+    ),
     (State(limiter), requester): (State<Limiter>, RequesterFingerprint),
     mut policy: Policy,
     mut repo: BoxRepository,
@@ -159,6 +165,8 @@ pub(crate) async fn post(
         .verify(
             &activity_tracker,
             &http_client,
+            &proof_of_work_replay_cache,
+            &clock,
             url_builder.public_hostname(),
             site_config.captcha.as_ref(),
         )
@@ -9,11 +9,13 @@ use axum::{
     extract::{Path, State},
     response::{Html, IntoResponse, Response},
 };
+use axum_extra::typed_header::TypedHeader;
 use mas_axum_utils::{
     InternalError,
     cookies::CookieJar,
     csrf::{CsrfExt as _, ProtectedForm},
 };
+use mas_policy::Policy;
 use mas_router::{PostAuthAction, UrlBuilder};
 use mas_storage::{BoxClock, BoxRepository, BoxRng};
 use mas_templates::{
@@ -23,7 +25,7 @@ use mas_templates::{
 use serde::{Deserialize, Serialize};
 use ulid::Ulid;
 
-use crate::{PreferredLanguage, views::shared::OptionalPostAuthAction};
+use crate::{BoundActivityTracker, PreferredLanguage, views::shared::OptionalPostAuthAction};
 
 #[derive(Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
@@ -107,11 +109,17 @@ pub(crate) async fn post(
     PreferredLanguage(locale): PreferredLanguage,
     State(templates): State<Templates>,
     State(url_builder): State<UrlBuilder>,
+    mut policy: Policy,
     mut repo: BoxRepository,
+    (user_agent, activity_tracker): (
+        Option<TypedHeader<headers::UserAgent>>,
+        BoundActivityTracker,
+    ),
     Path(id): Path<Ulid>,
     cookie_jar: CookieJar,
     Form(form): Form<ProtectedForm<DisplayNameForm>>,
 ) -> Result<Response, InternalError> {
+    let user_agent = user_agent.map(|ua| ua.as_str().to_owned());
     let registration = repo
         .user_registration()
         .lookup(id)
@@ -145,12 +153,37 @@ pub(crate) async fn post(
         FormAction::Set => {
             let display_name = form.display_name.trim();
 
+            let mut state = form.to_form_state();
             if display_name.is_empty() || display_name.len() > 255 {
-                let ctx = RegisterStepsDisplayNameContext::new()
-                    .with_form_state(form.to_form_state().with_error_on_field(
+                state.add_error_on_field(
+                    RegisterStepsDisplayNameFormField::DisplayName,
+                    FieldError::Invalid,
+                );
+            } else {
+                let res = policy
+                    .evaluate_display_name(mas_policy::DisplayNameInput {
+                        display_name,
+                        requester: mas_policy::Requester {
+                            ip_address: activity_tracker.ip(),
+                            user_agent,
+                        },
+                    })
+                    .await?;
+
+                for violation in res.violations {
+                    state.add_error_on_field(
                         RegisterStepsDisplayNameFormField::DisplayName,
-                        FieldError::Invalid,
-                    ))
+                        FieldError::Policy {
+                            code: violation.code.map(|c| c.as_str()),
+                            message: violation.msg,
+                        },
+                    );
+                }
+            }
+
+            if !state.is_valid() {
+                let ctx = RegisterStepsDisplayNameContext::new()
+                    .with_form_state(state)
                     .with_csrf(csrf_token.form_value())
                     .with_language(locale);
 
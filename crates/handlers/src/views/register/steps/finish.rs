@@ -27,7 +27,7 @@ use ulid::Ulid;
 
 use super::super::cookie::UserRegistrationSessions;
 use crate::{
-    BoundActivityTracker, METER, PreferredLanguage, views::shared::OptionalPostAuthAction,
+    BoundActivityTracker, METER, PreferredLanguage, terms, views::shared::OptionalPostAuthAction,
 };
 
 static PASSWORD_REGISTER_COUNTER: LazyLock<Counter<u64>> = LazyLock::new(|| {
@@ -214,11 +214,15 @@ pub(crate) async fn get(
         .await?;
 
     // If we used a registration token, we need to mark it as used
-    if let Some(registration_token) = registration_token {
-        repo.user_registration_token()
+    let registration_token = if let Some(registration_token) = registration_token {
+        let registration_token = repo
+            .user_registration_token()
             .use_token(&clock, registration_token)
             .await?;
-    }
+        Some(registration_token)
+    } else {
+        None
+    };
 
     // Consume the registration session
     let cookie_jar = registrations
@@ -230,6 +234,14 @@ pub(crate) async fn get(
         .user()
         .add(&mut rng, &clock, registration.username)
         .await?;
+
+    // If the invite code asks for it, record who referred this user
+    if let Some(registration_token) = &registration_token {
+        repo.user_registration_token()
+            .record_referral(&clock, &user, registration_token)
+            .await?;
+    }
+
     // Also create a browser session which will log the user in
     let user_session = repo
         .browser_session()
@@ -264,6 +276,7 @@ pub(crate) async fn get(
         repo.user_terms()
             .accept_terms(&mut rng, &clock, &user, terms_url)
             .await?;
+        terms::accept_on_registration(&mut repo, &mut rng, &clock, &site_config, &user).await?;
     }
 
     let mut job = ProvisionUserJob::new(&user);
@@ -272,23 +285,30 @@ pub(crate) async fn get(
     }
     repo.queue_job().schedule_job(&mut rng, &clock, job).await?;
 
+    let post_auth_action: Option<PostAuthAction> = registration
+        .post_auth_action
+        .map(serde_json::from_value)
+        .transpose()?;
+    let post_auth_action = OptionalPostAuthAction::from(post_auth_action);
+
+    // Users who haven't accepted the current terms yet are asked to first
+    let reply = terms::go_next(
+        &mut repo,
+        &site_config,
+        &user,
+        &post_auth_action,
+        &url_builder,
+    )
+    .await?;
+
     repo.save().await?;
 
     activity_tracker
         .record_browser_session(&clock, &user_session)
         .await;
 
-    let post_auth_action: Option<PostAuthAction> = registration
-        .post_auth_action
-        .map(serde_json::from_value)
-        .transpose()?;
-
     // Login the user with the session we just created
     let cookie_jar = cookie_jar.set_session(&user_session);
 
-    return Ok((
-        cookie_jar,
-        OptionalPostAuthAction::from(post_auth_action).go_next(&url_builder),
-    )
-        .into_response());
+    return Ok((cookie_jar, reply).into_response());
 }
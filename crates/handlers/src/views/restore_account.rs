@@ -0,0 +1,140 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Lets users cancel the deletion of their account while it is still in its
+//! grace period, right after they proved who they are on the login page
+
+use axum::{
+    extract::{Form, State},
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use mas_axum_utils::{
+    InternalError,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::{User, UserDeletion};
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, Clock,
+    queue::{QueueJobRepositoryExt as _, ReactivateUserJob},
+    user::{UserDeletionRepository, UserRepository},
+};
+use mas_templates::{AccountInactiveContext, TemplateContext, Templates};
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+use crate::PreferredLanguage;
+
+/// Name of the cookie
+static COOKIE_NAME: &str = "restore-account";
+
+/// Users have 10 minutes to restore their account after signing in
+static PENDING_RESTORE_MAX_TIME: Duration = Duration::microseconds(10 * 60 * 1000 * 1000);
+
+/// A login which checked the credentials of a user whose account is scheduled
+/// for deletion, so that they can cancel it
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct PendingRestore {
+    user_id: Ulid,
+    user_deletion_id: Ulid,
+    created_at: DateTime<Utc>,
+}
+
+impl PendingRestore {
+    pub(crate) fn new(user: &User, deletion: &UserDeletion, clock: &impl Clock) -> Self {
+        Self {
+            user_id: user.id,
+            user_deletion_id: deletion.id,
+            created_at: clock.now(),
+        }
+    }
+
+    /// Load the pending restore from the cookie jar, if it didn't expire yet
+    fn load(cookie_jar: &CookieJar, clock: &impl Clock) -> Option<Self> {
+        match cookie_jar.load::<Self>(COOKIE_NAME) {
+            Ok(Some(pending)) if clock.now() - pending.created_at <= PENDING_RESTORE_MAX_TIME => {
+                Some(pending)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Invalid pending account restore cookie: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Save the pending restore to the cookie jar
+    pub(crate) fn save(&self, cookie_jar: CookieJar) -> CookieJar {
+        cookie_jar.save(COOKIE_NAME, self, false)
+    }
+
+    fn remove(cookie_jar: CookieJar) -> CookieJar {
+        cookie_jar.remove(COOKIE_NAME)
+    }
+}
+
+#[tracing::instrument(name = "handlers.views.restore_account.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    PreferredLanguage(locale): PreferredLanguage,
+    mut repo: BoxRepository,
+    State(templates): State<Templates>,
+    State(url_builder): State<UrlBuilder>,
+    cookie_jar: CookieJar,
+    Form(form): Form<ProtectedForm<()>>,
+) -> Result<Response, InternalError> {
+    cookie_jar.verify_form(&clock, form)?;
+
+    let Some(pending) = PendingRestore::load(&cookie_jar, &clock) else {
+        return Ok((
+            cookie_jar,
+            url_builder.redirect(&mas_router::Login::default()),
+        )
+            .into_response());
+    };
+    let cookie_jar = PendingRestore::remove(cookie_jar);
+
+    let deletion = repo
+        .user_deletion()
+        .lookup(pending.user_deletion_id)
+        .await?
+        .filter(|deletion| deletion.user_id == pending.user_id && deletion.is_pending());
+    let Some(deletion) = deletion else {
+        // The deletion completed or was cancelled in the meantime
+        return Ok((
+            cookie_jar,
+            url_builder.redirect(&mas_router::Login::default()),
+        )
+            .into_response());
+    };
+
+    let user = repo
+        .user()
+        .lookup(deletion.user_id)
+        .await?
+        .ok_or_else(|| InternalError::from_anyhow(anyhow::anyhow!("user not found")))?;
+
+    let deletion = repo.user_deletion().cancel(&clock, deletion).await?;
+    let user = repo.user().reactivate(user).await?;
+
+    // This reactivates the user on the homeserver, and then unlocks them
+    repo.queue_job()
+        .schedule_job(&mut rng, &clock, ReactivateUserJob::new(&user))
+        .await?;
+
+    repo.save().await?;
+
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
+    let ctx = AccountInactiveContext::new(user)
+        .with_deletion(deletion)
+        .with_csrf(csrf_token.form_value())
+        .with_language(locale);
+    let content = templates.render_account_deactivated(&ctx)?;
+
+    Ok((cookie_jar, Html(content)).into_response())
+}
@@ -71,6 +71,16 @@ impl OptionalPostAuthAction {
                 PostAuthContextInner::ContinueDeviceCodeGrant { grant }
             }
 
+            PostAuthAction::ContinueBackchannelAuthentication { id } => {
+                let grant = repo
+                    .oauth2_backchannel_authentication_grant()
+                    .lookup(id)
+                    .await?
+                    .context("Failed to load backchannel authentication request")?;
+                let grant = Box::new(grant);
+                PostAuthContextInner::ContinueBackchannelAuthentication { grant }
+            }
+
             PostAuthAction::ContinueCompatSsoLogin { id } => {
                 let login = repo
                     .compat_sso_login()
@@ -0,0 +1,247 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Single-use links sent to users by email, letting them perform an action
+//! like ending a browser session.
+//!
+//! Following the link never changes anything: it renders a confirmation page,
+//! and the action is only performed when its form is submitted. This way,
+//! email link scanners and prefetchers can't trigger the action by themselves.
+//!
+//! This only covers actions on existing sessions and accounts. Some GET
+//! endpoints still record state, like the upstream authorization endpoint,
+//! which starts an upstream authorization session before redirecting to the
+//! provider. They are left as they are: they are the target of automatic
+//! redirects, and what they record is only used by the browser which made the
+//! request, so a prefetcher following them can't affect the user.
+
+use axum::{
+    Form,
+    extract::{Path, State},
+    response::{Html, IntoResponse, Response},
+};
+use mas_axum_utils::{
+    InternalError, SessionInfoExt,
+    cookies::CookieJar,
+    csrf::{CsrfExt, ProtectedForm},
+};
+use mas_data_model::{BrowserSession, UserAction, UserActionToken};
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRng, Clock, RepositoryAccess,
+    queue::{QueueJobRepositoryExt as _, SendBrowserSessionBackchannelLogoutsJob},
+};
+use mas_templates::{EmptyContext, TemplateContext, Templates, UserActionContext};
+
+use crate::{BoundActivityTracker, PreferredLanguage};
+
+/// Load the action token and the browser session it targets, if both are
+/// still usable
+async fn load_token<R: RepositoryAccess>(
+    repo: &mut R,
+    clock: &dyn Clock,
+    token: &str,
+) -> Result<Option<(UserActionToken, BrowserSession)>, R::Error> {
+    let Some(token) = repo.user_action_token().find_by_token(token).await? else {
+        return Ok(None);
+    };
+
+    if !token.is_valid(clock.now()) {
+        return Ok(None);
+    }
+
+    let UserAction::EndBrowserSession { browser_session_id } = token.action;
+    let Some(browser_session) = repo.browser_session().lookup(browser_session_id).await? else {
+        return Ok(None);
+    };
+
+    // There is nothing to do if the session already ended
+    if browser_session.user.id != token.user_id || !browser_session.active() {
+        return Ok(None);
+    }
+
+    Ok(Some((token, browser_session)))
+}
+
+#[tracing::instrument(name = "handlers.views.user_action.get", skip_all)]
+pub(crate) async fn get(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    mut repo: BoxRepository,
+    State(templates): State<Templates>,
+    PreferredLanguage(locale): PreferredLanguage,
+    cookie_jar: CookieJar,
+    Path(token): Path<String>,
+) -> Result<Response, InternalError> {
+    let (csrf_token, cookie_jar) = cookie_jar.csrf_token(&clock, &mut rng);
+
+    let Some((_token, browser_session)) = load_token(&mut repo, &clock, &token).await? else {
+        let context = EmptyContext.with_language(locale);
+        let rendered = templates.render_user_action_invalid(&context)?;
+        return Ok((cookie_jar, Html(rendered)).into_response());
+    };
+
+    let context = UserActionContext::EndBrowserSession { browser_session }
+        .with_csrf(csrf_token.form_value())
+        .with_language(locale);
+
+    let rendered = templates.render_user_action(&context)?;
+
+    Ok((cookie_jar, Html(rendered)).into_response())
+}
+
+#[tracing::instrument(name = "handlers.views.user_action.post", skip_all)]
+pub(crate) async fn post(
+    mut rng: BoxRng,
+    clock: BoxClock,
+    mut repo: BoxRepository,
+    State(templates): State<Templates>,
+    PreferredLanguage(locale): PreferredLanguage,
+    activity_tracker: BoundActivityTracker,
+    cookie_jar: CookieJar,
+    Path(token): Path<String>,
+    Form(form): Form<ProtectedForm<()>>,
+) -> Result<Response, InternalError> {
+    cookie_jar.verify_form(&clock, form)?;
+
+    let Some((token, browser_session)) = load_token(&mut repo, &clock, &token).await? else {
+        let context = EmptyContext.with_language(locale);
+        let rendered = templates.render_user_action_invalid(&context)?;
+        return Ok((cookie_jar, Html(rendered)).into_response());
+    };
+
+    // Consuming the token fails if it was used concurrently
+    repo.user_action_token().consume(&clock, token).await?;
+
+    activity_tracker
+        .record_browser_session(&clock, &browser_session)
+        .await;
+
+    repo.queue_job()
+        .schedule_job(
+            &mut rng,
+            &clock,
+            SendBrowserSessionBackchannelLogoutsJob::new(&browser_session),
+        )
+        .await?;
+
+    let browser_session_id = browser_session.id;
+    repo.browser_session()
+        .finish(&clock, browser_session)
+        .await?;
+
+    repo.save().await?;
+
+    // If the link was followed from the session it ended, clear out the
+    // session cookie as well
+    let (session_info, cookie_jar) = cookie_jar.session_info();
+    let cookie_jar = if session_info.current_session_id() == Some(browser_session_id) {
+        cookie_jar.update_session_info(&session_info.mark_session_ended())
+    } else {
+        cookie_jar
+    };
+
+    let context = EmptyContext.with_language(locale);
+    let rendered = templates.render_user_action_done(&context)?;
+
+    Ok((cookie_jar, Html(rendered)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use hyper::{Request, StatusCode};
+    use mas_data_model::UserAction;
+    use mas_storage::{
+        RepositoryAccess,
+        user::{BrowserSessionRepository, UserActionTokenRepository, UserRepository},
+    };
+    use sqlx::PgPool;
+
+    use crate::test_utils::{CookieHelper, RequestBuilderExt, ResponseExt, TestState, setup};
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_end_browser_session(pool: PgPool) {
+        setup();
+        let state = TestState::from_pool(pool).await.unwrap();
+        let cookies = CookieHelper::new();
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut rng, &state.clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &state.clock, &user, None)
+            .await
+            .unwrap();
+        repo.user_action_token()
+            .add(
+                &mut rng,
+                &state.clock,
+                &user,
+                UserAction::EndBrowserSession {
+                    browser_session_id: browser_session.id,
+                },
+                "sometoken".to_owned(),
+                Duration::try_hours(1).unwrap(),
+            )
+            .await
+            .unwrap();
+        repo.save().await.unwrap();
+
+        // Following the link only renders a confirmation page
+        let request = cookies.with_cookies(Request::get("/action/sometoken").empty());
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+        let csrf_token = response
+            .body()
+            .split("name=\"csrf\" value=\"")
+            .nth(1)
+            .unwrap()
+            .split('\"')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        let mut repo = state.repository().await.unwrap();
+        let session = repo
+            .browser_session()
+            .lookup(browser_session.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(session.finished_at.is_none());
+        repo.save().await.unwrap();
+
+        // Submitting the form ends the session
+        let request = Request::post("/action/sometoken").form(serde_json::json!({
+            "csrf": csrf_token,
+        }));
+        let request = cookies.with_cookies(request);
+        let response = state.request(request).await;
+        cookies.save_cookies(&response);
+        response.assert_status(StatusCode::OK);
+
+        let mut repo = state.repository().await.unwrap();
+        let session = repo
+            .browser_session()
+            .lookup(browser_session.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(session.finished_at.is_some());
+        repo.save().await.unwrap();
+
+        // The link can't be used anymore
+        let request = cookies.with_cookies(Request::get("/action/sometoken").empty());
+        let response = state.request(request).await;
+        response.assert_status(StatusCode::OK);
+        assert!(!response.body().contains("name=\"csrf\""));
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! A minimal CBOR decoder, as defined in [RFC8949]
+//!
+//! This only supports what authenticators put in attestation objects and COSE
+//! keys: integers, byte and text strings, arrays, maps and simple values.
+//! Indefinite lengths, tags and floats are rejected.
+//!
+//! [RFC8949]: https://www.rfc-editor.org/rfc/rfc8949
+
+use thiserror::Error;
+
+/// How deep arrays and maps can be nested
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub(crate) enum Error {
+    #[error("unexpected end of input")]
+    UnexpectedEnd,
+
+    #[error("unsupported CBOR item with initial byte {0:#04x}")]
+    Unsupported(u8),
+
+    #[error("text string is not valid UTF-8")]
+    InvalidUtf8,
+
+    #[error("items are nested too deeply")]
+    TooDeep,
+}
+
+/// A decoded CBOR item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Value {
+    Integer(i128),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Bool(bool),
+    Null,
+}
+
+impl Value {
+    /// Get the value of an entry in a map, if this is a map
+    pub(crate) fn get(&self, key: &Value) -> Option<&Value> {
+        match self {
+            Self::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Get the value of an entry with a text key in a map
+    pub(crate) fn get_text(&self, key: &str) -> Option<&Value> {
+        self.get(&Self::Text(key.to_owned()))
+    }
+
+    /// Get the value of an entry with an integer key in a map
+    pub(crate) fn get_int(&self, key: i128) -> Option<&Value> {
+        self.get(&Self::Integer(key))
+    }
+
+    pub(crate) fn as_integer(&self) -> Option<i128> {
+        match self {
+            Self::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(t) => Some(t),
+            _ => None,
+        }
+    }
+}
+
+/// Decode a single CBOR item
+///
+/// Returns the item and the remaining input after it
+///
+/// # Errors
+///
+/// Returns an error if the input isn't valid CBOR, or uses unsupported
+/// features
+pub(crate) fn decode(input: &[u8]) -> Result<(Value, &[u8]), Error> {
+    decode_item(input, 0)
+}
+
+fn split(input: &[u8], len: usize) -> Result<(&[u8], &[u8]), Error> {
+    if input.len() < len {
+        return Err(Error::UnexpectedEnd);
+    }
+    Ok(input.split_at(len))
+}
+
+/// Read the argument of an item, given the low 5 bits of its initial byte
+fn read_argument(initial: u8, input: &[u8]) -> Result<(u64, &[u8]), Error> {
+    let (len, input) = match initial & 0x1f {
+        info @ 0..=23 => return Ok((u64::from(info), input)),
+        24 => (1, input),
+        25 => (2, input),
+        26 => (4, input),
+        27 => (8, input),
+        _ => return Err(Error::Unsupported(initial)),
+    };
+
+    let (bytes, rest) = split(input, len)?;
+    let value = bytes
+        .iter()
+        .fold(0_u64, |acc, byte| (acc << 8) | u64::from(*byte));
+    Ok((value, rest))
+}
+
+/// Convert a length argument to a `usize`, making sure there is at least that
+/// many bytes left, so that we never allocate more than the input size
+fn length(value: u64, input: &[u8]) -> Result<usize, Error> {
+    match usize::try_from(value) {
+        Ok(len) if len <= input.len() => Ok(len),
+        _ => Err(Error::UnexpectedEnd),
+    }
+}
+
+fn decode_item(input: &[u8], depth: usize) -> Result<(Value, &[u8]), Error> {
+    if depth > MAX_DEPTH {
+        return Err(Error::TooDeep);
+    }
+
+    let (&initial, input) = input.split_first().ok_or(Error::UnexpectedEnd)?;
+    let (argument, input) = read_argument(initial, input)?;
+
+    match initial >> 5 {
+        0 => Ok((Value::Integer(i128::from(argument)), input)),
+        1 => Ok((Value::Integer(-1 - i128::from(argument)), input)),
+        2 => {
+            let (bytes, rest) = split(input, length(argument, input)?)?;
+            Ok((Value::Bytes(bytes.to_vec()), rest))
+        }
+        3 => {
+            let (bytes, rest) = split(input, length(argument, input)?)?;
+            let text = std::str::from_utf8(bytes).map_err(|_| Error::InvalidUtf8)?;
+            Ok((Value::Text(text.to_owned()), rest))
+        }
+        4 => {
+            // Each item is at least one byte long
+            let count = length(argument, input)?;
+            let mut items = Vec::with_capacity(count);
+            let mut input = input;
+            for _ in 0..count {
+                let (item, rest) = decode_item(input, depth + 1)?;
+                items.push(item);
+                input = rest;
+            }
+            Ok((Value::Array(items), input))
+        }
+        5 => {
+            // Each entry is at least two bytes long
+            let count = length(argument.saturating_mul(2), input)? / 2;
+            let mut entries = Vec::with_capacity(count);
+            let mut input = input;
+            for _ in 0..count {
+                let (key, rest) = decode_item(input, depth + 1)?;
+                let (value, rest) = decode_item(rest, depth + 1)?;
+                entries.push((key, value));
+                input = rest;
+            }
+            Ok((Value::Map(entries), input))
+        }
+        7 => match initial & 0x1f {
+            20 => Ok((Value::Bool(false), input)),
+            21 => Ok((Value::Bool(true), input)),
+            22 => Ok((Value::Null, input)),
+            _ => Err(Error::Unsupported(initial)),
+        },
+        _ => Err(Error::Unsupported(initial)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_scalars() {
+        assert_eq!(decode(&[0x00]), Ok((Value::Integer(0), &[][..])));
+        assert_eq!(decode(&[0x17]), Ok((Value::Integer(23), &[][..])));
+        assert_eq!(decode(&[0x18, 0x64]), Ok((Value::Integer(100), &[][..])));
+        assert_eq!(
+            decode(&[0x1a, 0x00, 0x0f, 0x42, 0x40]),
+            Ok((Value::Integer(1_000_000), &[][..]))
+        );
+        assert_eq!(decode(&[0x26]), Ok((Value::Integer(-7), &[][..])));
+        assert_eq!(
+            decode(&[0x39, 0x01, 0x00]),
+            Ok((Value::Integer(-257), &[][..]))
+        );
+        assert_eq!(decode(&[0xf4]), Ok((Value::Bool(false), &[][..])));
+        assert_eq!(decode(&[0xf5]), Ok((Value::Bool(true), &[][..])));
+        assert_eq!(decode(&[0xf6]), Ok((Value::Null, &[][..])));
+
+        // The remaining input is returned
+        assert_eq!(decode(&[0x01, 0x02]), Ok((Value::Integer(1), &[0x02][..])));
+    }
+
+    #[test]
+    fn test_decode_strings_and_collections() {
+        assert_eq!(
+            decode(&[0x43, 0x01, 0x02, 0x03]),
+            Ok((Value::Bytes(vec![1, 2, 3]), &[][..]))
+        );
+        assert_eq!(
+            decode(b"\x64fmt"),
+            Err(Error::UnexpectedEnd),
+            "text string is too short"
+        );
+        assert_eq!(
+            decode(b"\x63fmt"),
+            Ok((Value::Text("fmt".to_owned()), &[][..]))
+        );
+
+        // {"fmt": "none", 1: [2, -1]}
+        let (value, rest) = decode(b"\xa2\x63fmt\x64none\x01\x82\x02\x20").unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(value.get_text("fmt").and_then(Value::as_text), Some("none"));
+        assert_eq!(
+            value.get_int(1),
+            Some(&Value::Array(vec![Value::Integer(2), Value::Integer(-1)]))
+        );
+        assert_eq!(value.get_int(2), None);
+    }
+
+    #[test]
+    fn test_decode_rejects_bad_input() {
+        assert_eq!(decode(&[]), Err(Error::UnexpectedEnd));
+        // Indefinite-length byte string
+        assert_eq!(decode(&[0x5f]), Err(Error::Unsupported(0x5f)));
+        // Tag
+        assert_eq!(decode(&[0xc0, 0x00]), Err(Error::Unsupported(0xc0)));
+        // Float
+        assert_eq!(decode(&[0xf9, 0x3c, 0x00]), Err(Error::Unsupported(0xf9)));
+        // An array announcing way more items than there are bytes
+        assert_eq!(
+            decode(&[0x9b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            Err(Error::UnexpectedEnd)
+        );
+        // Invalid UTF-8
+        assert_eq!(decode(&[0x61, 0xff]), Err(Error::InvalidUtf8));
+        // Deeply nested arrays
+        assert_eq!(decode(&[0x81; 64]), Err(Error::TooDeep));
+    }
+}
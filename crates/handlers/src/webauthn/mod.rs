@@ -0,0 +1,755 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! WebAuthn ceremonies, used to register passkeys and log in with them, as
+//! defined in the [Web Authentication] specification
+//!
+//! We don't restrict which authenticators can be used, so we only ask for the
+//! `none` attestation and don't verify attestation statements. Credentials
+//! must use ES256 or RS256, which covers all the platform authenticators and
+//! security keys out there.
+//!
+//! [Web Authentication]: https://www.w3.org/TR/webauthn-3/
+
+mod cbor;
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::{DateTime, Duration, Utc};
+use mas_data_model::{User, UserPasskey, UserPasskeyChallenge};
+use mas_iana::jose::JsonWebSignatureAlg;
+use mas_jose::{
+    jwa::{AsymmetricKeyFromJwkError, AsymmetricVerifyingKey},
+    jwk::PublicJsonWebKey,
+};
+use mas_router::UrlBuilder;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use ulid::Ulid;
+
+/// How long a challenge can be used after it was created. This is also the
+/// timeout given to the browser for the ceremony.
+pub(crate) const CHALLENGE_TTL: Duration = Duration::microseconds(5 * 60 * 1000 * 1000);
+
+/// The COSE algorithm identifier for ECDSA with P-256 and SHA-256
+const COSE_ALG_ES256: i128 = -7;
+
+/// The COSE algorithm identifier for RSASSA-PKCS1-v1_5 with SHA-256
+const COSE_ALG_RS256: i128 = -257;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// The transports hints we know about. Others are dropped when registering a
+/// passkey.
+const KNOWN_TRANSPORTS: [&str; 6] = ["ble", "hybrid", "internal", "nfc", "smart-card", "usb"];
+
+#[derive(Debug, Error)]
+pub(crate) enum WebAuthnError {
+    #[error("the response is not valid JSON")]
+    InvalidResponse(#[from] serde_json::Error),
+
+    #[error("the {0} field is not valid base64url")]
+    InvalidBase64(&'static str),
+
+    #[error("the ceremony has the wrong type")]
+    WrongType,
+
+    #[error("the response was made for another challenge")]
+    ChallengeMismatch,
+
+    #[error("the response was made for another origin")]
+    OriginMismatch,
+
+    #[error("the authenticator data is malformed")]
+    InvalidAuthenticatorData,
+
+    #[error("the attestation object is malformed")]
+    InvalidAttestationObject(#[from] cbor::Error),
+
+    #[error("the response was made for another relying party")]
+    RelyingPartyMismatch,
+
+    #[error("the user was not verified by the authenticator")]
+    UserNotVerified,
+
+    #[error("the credential public key is not supported")]
+    UnsupportedKey,
+
+    #[error("the credential public key is not usable")]
+    InvalidKey(#[from] AsymmetricKeyFromJwkError),
+
+    #[error("the response was made with another credential")]
+    CredentialMismatch,
+
+    #[error("the signature is invalid")]
+    InvalidSignature,
+
+    #[error("the signature counter did not increase, the credential may have been cloned")]
+    CounterRegression,
+}
+
+/// The response of `navigator.credentials.create()`, as serialized by the
+/// browser
+#[derive(Debug, Deserialize)]
+struct RegistrationResponse {
+    id: String,
+    response: AttestationResponse,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    client_data_json: String,
+    attestation_object: String,
+    #[serde(default)]
+    transports: Vec<String>,
+}
+
+/// The response of `navigator.credentials.get()`, as serialized by the
+/// browser
+#[derive(Debug, Deserialize)]
+pub(crate) struct AuthenticationResponse {
+    /// The credential ID, encoded as unpadded base64url
+    pub id: String,
+    response: AssertionResponse,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    client_data_json: String,
+    authenticator_data: String,
+    signature: String,
+    #[serde(default)]
+    user_handle: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientData {
+    r#type: String,
+    challenge: String,
+    origin: String,
+    #[serde(default)]
+    cross_origin: bool,
+}
+
+/// A credential which passed the registration ceremony, ready to be saved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RegisteredCredential {
+    pub credential_id: String,
+    pub aaguid: String,
+    pub public_key: PublicJsonWebKey,
+    pub sign_count: u32,
+    pub transports: Vec<String>,
+}
+
+struct AuthenticatorData<'a> {
+    rp_id_hash: &'a [u8],
+    flags: u8,
+    sign_count: u32,
+    attested_credential: Option<AttestedCredential>,
+}
+
+struct AttestedCredential {
+    aaguid: [u8; 16],
+    credential_id: Vec<u8>,
+    public_key: PublicJsonWebKey,
+}
+
+fn decode_base64(value: &str, field: &'static str) -> Result<Vec<u8>, WebAuthnError> {
+    Base64UrlUnpadded::decode_vec(value.trim_end_matches('='))
+        .map_err(|_| WebAuthnError::InvalidBase64(field))
+}
+
+fn format_aaguid(aaguid: &[u8; 16]) -> String {
+    let hex = hex::encode(aaguid);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// The ID the user is known as by authenticators, which is returned as the
+/// user handle when logging in
+fn user_handle(user_id: Ulid) -> String {
+    Base64UrlUnpadded::encode_string(&user_id.to_bytes())
+}
+
+/// Whether a challenge can still be used to complete a ceremony
+pub(crate) fn is_challenge_valid(challenge: &UserPasskeyChallenge, now: DateTime<Utc>) -> bool {
+    challenge.completed_at.is_none() && now - challenge.created_at < CHALLENGE_TTL
+}
+
+/// Convert a COSE public key, as found in the attested credential data, to a
+/// JWK
+fn cose_key_to_jwk(key: &cbor::Value) -> Result<PublicJsonWebKey, WebAuthnError> {
+    let int = |label| key.get_int(label).and_then(cbor::Value::as_integer);
+    let bytes = |label| {
+        key.get_int(label)
+            .and_then(cbor::Value::as_bytes)
+            .ok_or(WebAuthnError::UnsupportedKey)
+    };
+
+    // Key type 2 is EC2, key type 3 is RSA, and curve 1 is P-256
+    let (jwk, alg) = match (int(1), int(3)) {
+        (Some(2), Some(COSE_ALG_ES256)) if int(-1) == Some(1) => (
+            json!({
+                "kty": "EC",
+                "crv": "P-256",
+                "x": Base64UrlUnpadded::encode_string(bytes(-2)?),
+                "y": Base64UrlUnpadded::encode_string(bytes(-3)?),
+            }),
+            JsonWebSignatureAlg::Es256,
+        ),
+        (Some(3), Some(COSE_ALG_RS256)) => (
+            json!({
+                "kty": "RSA",
+                "n": Base64UrlUnpadded::encode_string(bytes(-1)?),
+                "e": Base64UrlUnpadded::encode_string(bytes(-2)?),
+            }),
+            JsonWebSignatureAlg::Rs256,
+        ),
+        _ => return Err(WebAuthnError::UnsupportedKey),
+    };
+
+    let jwk: PublicJsonWebKey =
+        serde_json::from_value(jwk).map_err(|_| WebAuthnError::UnsupportedKey)?;
+
+    // Make sure the key is usable before saving it
+    AsymmetricVerifyingKey::from_jwk_and_alg(jwk.params(), &alg)?;
+
+    Ok(jwk.with_alg(alg))
+}
+
+fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData<'_>, WebAuthnError> {
+    if data.len() < 37 {
+        return Err(WebAuthnError::InvalidAuthenticatorData);
+    }
+
+    let (rp_id_hash, rest) = data.split_at(32);
+    let flags = rest[0];
+    let sign_count = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]);
+    let rest = &rest[5..];
+
+    let attested_credential = if flags & FLAG_ATTESTED_CREDENTIAL_DATA == 0 {
+        None
+    } else {
+        if rest.len() < 18 {
+            return Err(WebAuthnError::InvalidAuthenticatorData);
+        }
+        let (aaguid, rest) = rest.split_at(16);
+        let aaguid: [u8; 16] = aaguid
+            .try_into()
+            .map_err(|_| WebAuthnError::InvalidAuthenticatorData)?;
+        let credential_id_len = usize::from(u16::from_be_bytes([rest[0], rest[1]]));
+        let rest = &rest[2..];
+        if rest.len() < credential_id_len {
+            return Err(WebAuthnError::InvalidAuthenticatorData);
+        }
+        let (credential_id, rest) = rest.split_at(credential_id_len);
+
+        // Extensions may follow the public key, we just ignore them
+        let (public_key, _) = cbor::decode(rest)?;
+        let public_key = cose_key_to_jwk(&public_key)?;
+
+        Some(AttestedCredential {
+            aaguid,
+            credential_id: credential_id.to_vec(),
+            public_key,
+        })
+    };
+
+    Ok(AuthenticatorData {
+        rp_id_hash,
+        flags,
+        sign_count,
+        attested_credential,
+    })
+}
+
+/// The relying party passkeys are bound to, which is the domain of the public
+/// base URL
+#[derive(Debug, Clone)]
+pub(crate) struct RelyingParty {
+    id: String,
+    origin: String,
+}
+
+impl RelyingParty {
+    pub(crate) fn new(url_builder: &UrlBuilder) -> Self {
+        Self {
+            id: url_builder.public_hostname().to_owned(),
+            origin: url_builder.http_base().origin().ascii_serialization(),
+        }
+    }
+
+    /// The options to pass to `navigator.credentials.create()` to register a
+    /// new passkey for the given user
+    pub(crate) fn creation_options(
+        &self,
+        challenge: &UserPasskeyChallenge,
+        user: &User,
+        existing: &[UserPasskey],
+    ) -> serde_json::Value {
+        let exclude_credentials: Vec<_> = existing
+            .iter()
+            .map(|passkey| {
+                json!({
+                    "type": "public-key",
+                    "id": passkey.credential_id,
+                    "transports": passkey.transports,
+                })
+            })
+            .collect();
+
+        json!({
+            "challenge": Base64UrlUnpadded::encode_string(&challenge.challenge),
+            "rp": {
+                "id": self.id,
+                "name": self.id,
+            },
+            "user": {
+                "id": user_handle(user.id),
+                "name": user.username,
+                "displayName": user.username,
+            },
+            "pubKeyCredParams": [
+                { "type": "public-key", "alg": COSE_ALG_ES256 },
+                { "type": "public-key", "alg": COSE_ALG_RS256 },
+            ],
+            "timeout": CHALLENGE_TTL.num_milliseconds(),
+            "attestation": "none",
+            "authenticatorSelection": {
+                "residentKey": "required",
+                "requireResidentKey": true,
+                "userVerification": "required",
+            },
+            "excludeCredentials": exclude_credentials,
+        })
+    }
+
+    /// The options to pass to `navigator.credentials.get()` to log in with
+    /// any passkey registered on this server
+    pub(crate) fn request_options(&self, challenge: &UserPasskeyChallenge) -> serde_json::Value {
+        json!({
+            "challenge": Base64UrlUnpadded::encode_string(&challenge.challenge),
+            "rpId": self.id,
+            "timeout": CHALLENGE_TTL.num_milliseconds(),
+            "userVerification": "required",
+            "allowCredentials": [],
+        })
+    }
+
+    fn verify_client_data(
+        &self,
+        client_data_json: &[u8],
+        expected_type: &str,
+        challenge: &UserPasskeyChallenge,
+    ) -> Result<(), WebAuthnError> {
+        let client_data: ClientData = serde_json::from_slice(client_data_json)?;
+
+        if client_data.r#type != expected_type {
+            return Err(WebAuthnError::WrongType);
+        }
+
+        if decode_base64(&client_data.challenge, "challenge")? != challenge.challenge {
+            return Err(WebAuthnError::ChallengeMismatch);
+        }
+
+        if client_data.origin != self.origin || client_data.cross_origin {
+            return Err(WebAuthnError::OriginMismatch);
+        }
+
+        Ok(())
+    }
+
+    fn verify_authenticator_data(&self, data: &AuthenticatorData<'_>) -> Result<(), WebAuthnError> {
+        if data.rp_id_hash != Sha256::digest(self.id.as_bytes()).as_slice() {
+            return Err(WebAuthnError::RelyingPartyMismatch);
+        }
+
+        // Passkeys replace the password, so we require the authenticator to
+        // verify the user, not just that someone touched it
+        let required = FLAG_USER_PRESENT | FLAG_USER_VERIFIED;
+        if data.flags & required != required {
+            return Err(WebAuthnError::UserNotVerified);
+        }
+
+        Ok(())
+    }
+
+    /// Verify the response of a registration ceremony
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the response is invalid, or wasn't made for the
+    /// given challenge
+    pub(crate) fn verify_registration(
+        &self,
+        challenge: &UserPasskeyChallenge,
+        response: &str,
+    ) -> Result<RegisteredCredential, WebAuthnError> {
+        let response: RegistrationResponse = serde_json::from_str(response)?;
+
+        let client_data_json =
+            decode_base64(&response.response.client_data_json, "clientDataJSON")?;
+        self.verify_client_data(&client_data_json, "webauthn.create", challenge)?;
+
+        let attestation_object =
+            decode_base64(&response.response.attestation_object, "attestationObject")?;
+        let (attestation_object, _) = cbor::decode(&attestation_object)?;
+        let authenticator_data = attestation_object
+            .get_text("authData")
+            .and_then(cbor::Value::as_bytes)
+            .ok_or(WebAuthnError::InvalidAuthenticatorData)?;
+        let authenticator_data = parse_authenticator_data(authenticator_data)?;
+        self.verify_authenticator_data(&authenticator_data)?;
+
+        let credential = authenticator_data
+            .attested_credential
+            .ok_or(WebAuthnError::InvalidAuthenticatorData)?;
+        let credential_id = Base64UrlUnpadded::encode_string(&credential.credential_id);
+        if credential_id != response.id.trim_end_matches('=') {
+            return Err(WebAuthnError::CredentialMismatch);
+        }
+
+        let mut transports: Vec<String> = response
+            .response
+            .transports
+            .into_iter()
+            .filter(|transport| KNOWN_TRANSPORTS.contains(&transport.as_str()))
+            .collect();
+        transports.sort();
+        transports.dedup();
+
+        Ok(RegisteredCredential {
+            credential_id,
+            aaguid: format_aaguid(&credential.aaguid),
+            public_key: credential.public_key,
+            sign_count: authenticator_data.sign_count,
+            transports,
+        })
+    }
+
+    /// Verify the response of an authentication ceremony made with the given
+    /// passkey
+    ///
+    /// Returns the new signature counter of the passkey
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the response is invalid, wasn't made for the given
+    /// challenge, or wasn't signed by the passkey
+    pub(crate) fn verify_authentication(
+        &self,
+        challenge: &UserPasskeyChallenge,
+        passkey: &UserPasskey,
+        response: &AuthenticationResponse,
+    ) -> Result<u32, WebAuthnError> {
+        if response.id.trim_end_matches('=') != passkey.credential_id {
+            return Err(WebAuthnError::CredentialMismatch);
+        }
+
+        // The user handle is optional for non-discoverable credentials
+        let user_handle = response
+            .response
+            .user_handle
+            .as_deref()
+            .map(|user_handle| decode_base64(user_handle, "userHandle"))
+            .transpose()?;
+        if user_handle.is_some_and(|user_handle| user_handle != passkey.user_id.to_bytes()) {
+            return Err(WebAuthnError::CredentialMismatch);
+        }
+
+        let client_data_json =
+            decode_base64(&response.response.client_data_json, "clientDataJSON")?;
+        self.verify_client_data(&client_data_json, "webauthn.get", challenge)?;
+
+        let raw_authenticator_data =
+            decode_base64(&response.response.authenticator_data, "authenticatorData")?;
+        let authenticator_data = parse_authenticator_data(&raw_authenticator_data)?;
+        self.verify_authenticator_data(&authenticator_data)?;
+
+        let alg = passkey
+            .public_key
+            .alg()
+            .ok_or(WebAuthnError::UnsupportedKey)?;
+        let key = AsymmetricVerifyingKey::from_jwk_and_alg(passkey.public_key.params(), alg)?;
+        let signature = decode_base64(&response.response.signature, "signature")?;
+
+        let mut message = raw_authenticator_data.clone();
+        message.extend_from_slice(&Sha256::digest(&client_data_json));
+        key.verify_der(&message, &signature)
+            .map_err(|_| WebAuthnError::InvalidSignature)?;
+
+        // Authenticators which don't implement the counter always return 0
+        let sign_count = authenticator_data.sign_count;
+        if (sign_count != 0 || passkey.sign_count != 0) && sign_count <= passkey.sign_count {
+            return Err(WebAuthnError::CounterRegression);
+        }
+
+        Ok(sign_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p256::ecdsa::{DerSignature, SigningKey, signature::Signer};
+
+    use super::*;
+
+    const CREDENTIAL_ID: [u8; 16] = [0x42; 16];
+
+    fn relying_party() -> RelyingParty {
+        let url_builder = UrlBuilder::new("https://example.com/".parse().unwrap(), None, None);
+        RelyingParty::new(&url_builder)
+    }
+
+    fn challenge() -> UserPasskeyChallenge {
+        UserPasskeyChallenge {
+            id: Ulid::nil(),
+            user_session_id: None,
+            challenge: vec![1, 2, 3, 4],
+            created_at: Utc::now(),
+            completed_at: None,
+        }
+    }
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_slice(&[0x17; 32]).unwrap()
+    }
+
+    fn client_data(ty: &str, challenge: &UserPasskeyChallenge, origin: &str) -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "type": ty,
+            "challenge": Base64UrlUnpadded::encode_string(&challenge.challenge),
+            "origin": origin,
+        }))
+        .unwrap()
+    }
+
+    fn authenticator_data(flags: u8, sign_count: u32) -> Vec<u8> {
+        let mut data = Sha256::digest(b"example.com").to_vec();
+        data.push(flags);
+        data.extend_from_slice(&sign_count.to_be_bytes());
+        data
+    }
+
+    /// Build the attestation object of a registration, with the COSE key of
+    /// the test signing key
+    fn attestation_object(flags: u8) -> Vec<u8> {
+        let point = signing_key().verifying_key().to_encoded_point(false);
+
+        let mut auth_data = authenticator_data(flags | FLAG_ATTESTED_CREDENTIAL_DATA, 0);
+        auth_data.extend_from_slice(&[0xaa; 16]);
+        auth_data.extend_from_slice(&16_u16.to_be_bytes());
+        auth_data.extend_from_slice(&CREDENTIAL_ID);
+        // {1: 2, 3: -7, -1: 1, -2: x, -3: y}
+        auth_data.extend_from_slice(&[0xa5, 0x01, 0x02, 0x03, 0x26, 0x20, 0x01, 0x21, 0x58, 0x20]);
+        auth_data.extend_from_slice(point.x().unwrap());
+        auth_data.extend_from_slice(&[0x22, 0x58, 0x20]);
+        auth_data.extend_from_slice(point.y().unwrap());
+
+        // {"fmt": "none", "attStmt": {}, "authData": auth_data}
+        let mut object = b"\xa3\x63fmt\x64none\x67attStmt\xa0\x68authData\x58".to_vec();
+        object.push(u8::try_from(auth_data.len()).unwrap());
+        object.extend_from_slice(&auth_data);
+        object
+    }
+
+    fn registration_response(client_data: &[u8], attestation_object: &[u8]) -> String {
+        json!({
+            "id": Base64UrlUnpadded::encode_string(&CREDENTIAL_ID),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": Base64UrlUnpadded::encode_string(client_data),
+                "attestationObject": Base64UrlUnpadded::encode_string(attestation_object),
+                "transports": ["internal", "hybrid", "carrier-pigeon"],
+            },
+        })
+        .to_string()
+    }
+
+    fn register() -> UserPasskey {
+        let rp = relying_party();
+        let challenge = challenge();
+        let response = registration_response(
+            &client_data("webauthn.create", &challenge, "https://example.com"),
+            &attestation_object(FLAG_USER_PRESENT | FLAG_USER_VERIFIED),
+        );
+        let credential = rp.verify_registration(&challenge, &response).unwrap();
+
+        UserPasskey {
+            id: Ulid::nil(),
+            user_id: Ulid::nil(),
+            credential_id: credential.credential_id,
+            name: "Test".to_owned(),
+            aaguid: credential.aaguid,
+            public_key: credential.public_key,
+            sign_count: credential.sign_count,
+            transports: credential.transports,
+            created_at: Utc::now(),
+            last_used_at: None,
+        }
+    }
+
+    fn assertion(
+        challenge: &UserPasskeyChallenge,
+        authenticator_data: &[u8],
+        signed_data: &[u8],
+    ) -> AuthenticationResponse {
+        let client_data = client_data("webauthn.get", challenge, "https://example.com");
+        let mut message = signed_data.to_vec();
+        message.extend_from_slice(&Sha256::digest(&client_data));
+        let signature: DerSignature = signing_key().sign(&message);
+
+        serde_json::from_value(json!({
+            "id": Base64UrlUnpadded::encode_string(&CREDENTIAL_ID),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": Base64UrlUnpadded::encode_string(&client_data),
+                "authenticatorData": Base64UrlUnpadded::encode_string(authenticator_data),
+                "signature": Base64UrlUnpadded::encode_string(signature.as_bytes()),
+                "userHandle": user_handle(Ulid::nil()),
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_registration() {
+        let passkey = register();
+        assert_eq!(
+            passkey.credential_id,
+            Base64UrlUnpadded::encode_string(&CREDENTIAL_ID)
+        );
+        assert_eq!(passkey.aaguid, "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa");
+        assert_eq!(passkey.public_key.alg(), Some(&JsonWebSignatureAlg::Es256));
+        assert_eq!(passkey.sign_count, 0);
+        // Unknown transports are dropped
+        assert_eq!(passkey.transports, vec!["hybrid", "internal"]);
+    }
+
+    #[test]
+    fn test_registration_rejects_invalid_responses() {
+        let rp = relying_party();
+        let challenge = challenge();
+        let attestation = attestation_object(FLAG_USER_PRESENT | FLAG_USER_VERIFIED);
+
+        // Wrong ceremony type
+        let response = registration_response(
+            &client_data("webauthn.get", &challenge, "https://example.com"),
+            &attestation,
+        );
+        assert!(matches!(
+            rp.verify_registration(&challenge, &response),
+            Err(WebAuthnError::WrongType)
+        ));
+
+        // Wrong origin
+        let response = registration_response(
+            &client_data("webauthn.create", &challenge, "https://evil.com"),
+            &attestation,
+        );
+        assert!(matches!(
+            rp.verify_registration(&challenge, &response),
+            Err(WebAuthnError::OriginMismatch)
+        ));
+
+        // Wrong challenge
+        let other_challenge = UserPasskeyChallenge {
+            challenge: vec![5, 6, 7, 8],
+            ..challenge.clone()
+        };
+        let response = registration_response(
+            &client_data("webauthn.create", &other_challenge, "https://example.com"),
+            &attestation,
+        );
+        assert!(matches!(
+            rp.verify_registration(&challenge, &response),
+            Err(WebAuthnError::ChallengeMismatch)
+        ));
+
+        // User not verified
+        let response = registration_response(
+            &client_data("webauthn.create", &challenge, "https://example.com"),
+            &attestation_object(FLAG_USER_PRESENT),
+        );
+        assert!(matches!(
+            rp.verify_registration(&challenge, &response),
+            Err(WebAuthnError::UserNotVerified)
+        ));
+    }
+
+    #[test]
+    fn test_authentication() {
+        let rp = relying_party();
+        let challenge = challenge();
+        let passkey = register();
+
+        let data = authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 1);
+        let response = assertion(&challenge, &data, &data);
+        assert_eq!(
+            rp.verify_authentication(&challenge, &passkey, &response)
+                .unwrap(),
+            1
+        );
+
+        // The same counter can't be used twice
+        let passkey = UserPasskey {
+            sign_count: 1,
+            ..passkey
+        };
+        assert!(matches!(
+            rp.verify_authentication(&challenge, &passkey, &response),
+            Err(WebAuthnError::CounterRegression)
+        ));
+
+        // Authenticators without a counter always send 0
+        let passkey = UserPasskey {
+            sign_count: 0,
+            ..passkey
+        };
+        let data = authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 0);
+        let response = assertion(&challenge, &data, &data);
+        assert_eq!(
+            rp.verify_authentication(&challenge, &passkey, &response)
+                .unwrap(),
+            0
+        );
+
+        // The signature must cover the authenticator data
+        let signed = authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 2);
+        let response = assertion(&challenge, &data, &signed);
+        assert!(matches!(
+            rp.verify_authentication(&challenge, &passkey, &response),
+            Err(WebAuthnError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn test_challenge_validity() {
+        let now = Utc::now();
+        let challenge = UserPasskeyChallenge {
+            created_at: now,
+            ..challenge()
+        };
+        assert!(is_challenge_valid(&challenge, now));
+        assert!(!is_challenge_valid(&challenge, now + CHALLENGE_TTL));
+
+        let challenge = challenge.complete(now).unwrap();
+        assert!(!is_challenge_valid(&challenge, now));
+    }
+}
@@ -13,10 +13,15 @@ use std::sync::LazyLock;
 
 mod ext;
 mod reqwest;
+mod signature;
 
 pub use self::{
     ext::{CorsLayerExt, set_propagator},
-    reqwest::{RequestBuilderExt, client as reqwest_client},
+    reqwest::{
+        ClientCertificateError, RequestBuilderExt, client as reqwest_client,
+        client_with_certificate as reqwest_client_with_certificate,
+    },
+    signature::{RequestSignatureError, RequestSigner, RequestSignerError},
 };
 
 static METER: LazyLock<opentelemetry::metrics::Meter> = LazyLock::new(|| {
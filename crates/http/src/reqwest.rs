@@ -6,7 +6,7 @@
 use std::{
     str::FromStr,
     sync::{Arc, LazyLock},
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
 use futures_util::FutureExt as _;
@@ -29,13 +29,14 @@ use opentelemetry_semantic_conventions::{
         NETWORK_TYPE, SERVER_ADDRESS, SERVER_PORT, URL_FULL, URL_SCHEME, USER_AGENT_ORIGINAL,
     },
 };
-use rustls_platform_verifier::ConfigVerifierExt;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject as _};
+use rustls_platform_verifier::{BuilderVerifierExt as _, ConfigVerifierExt};
 use tokio::time::Instant;
 use tower::{BoxError, Service as _};
 use tracing::Instrument;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
-use crate::METER;
+use crate::{METER, RequestSigner};
 
 static USER_AGENT: &str = concat!("matrix-authentication-service/", env!("CARGO_PKG_VERSION"));
 
@@ -83,6 +84,35 @@ impl reqwest::dns::Resolve for TracingResolver {
     }
 }
 
+/// An error which can occur when creating an HTTP client which authenticates
+/// with a TLS client certificate
+#[derive(Debug, thiserror::Error)]
+pub enum ClientCertificateError {
+    #[error("Could not parse the PEM-encoded client certificate or key")]
+    Pem(#[from] rustls::pki_types::pem::Error),
+
+    #[error("The client certificate chain is empty")]
+    EmptyCertificateChain,
+
+    #[error("Invalid client certificate or key")]
+    Tls(#[from] rustls::Error),
+
+    #[error("Failed to create HTTP client")]
+    Client(#[from] reqwest::Error),
+}
+
+fn client_builder(tls_config: rustls::ClientConfig) -> reqwest::ClientBuilder {
+    // TODO: can/should we limit in-flight requests?
+    reqwest::Client::builder()
+        .dns_resolver(Arc::new(TracingResolver::new()))
+        .use_preconfigured_tls(tls_config)
+        // Expose the peer certificate, so that we can check when it expires
+        .tls_info(true)
+        .user_agent(USER_AGENT)
+        .timeout(Duration::from_secs(60))
+        .connect_timeout(Duration::from_secs(30))
+}
+
 /// Create a new [`reqwest::Client`] with sane parameters
 ///
 /// # Panics
@@ -90,25 +120,55 @@ impl reqwest::dns::Resolve for TracingResolver {
 /// Panics if the client fails to build, which should never happen
 #[must_use]
 pub fn client() -> reqwest::Client {
-    // TODO: can/should we limit in-flight requests?
     let tls_config = rustls::ClientConfig::with_platform_verifier();
-    reqwest::Client::builder()
-        .dns_resolver(Arc::new(TracingResolver::new()))
-        .use_preconfigured_tls(tls_config)
-        .user_agent(USER_AGENT)
-        .timeout(Duration::from_secs(60))
-        .connect_timeout(Duration::from_secs(30))
+    client_builder(tls_config)
         .build()
         .expect("failed to create HTTP client")
 }
 
+/// Create an HTTP client like [`client`], which presents the given client
+/// certificate to the servers which ask for one, for mutual TLS
+///
+/// # Errors
+///
+/// Returns an error if the certificate chain or the key could not be parsed,
+/// or if they don't match
+pub fn client_with_certificate(
+    certificate_chain_pem: &str,
+    key_pem: &str,
+) -> Result<reqwest::Client, ClientCertificateError> {
+    let certificate_chain = CertificateDer::pem_slice_iter(certificate_chain_pem.as_bytes())
+        .collect::<Result<Vec<_>, _>>()?;
+    if certificate_chain.is_empty() {
+        return Err(ClientCertificateError::EmptyCertificateChain);
+    }
+
+    let key = PrivateKeyDer::from_pem_slice(key_pem.as_bytes())?;
+
+    let tls_config = rustls::ClientConfig::builder()
+        .with_platform_verifier()
+        .with_client_auth_cert(certificate_chain, key)?;
+
+    Ok(client_builder(tls_config).build()?)
+}
+
 async fn send_traced(
     request: reqwest::RequestBuilder,
+    signer: Option<&RequestSigner>,
 ) -> Result<reqwest::Response, reqwest::Error> {
     let start = Instant::now();
     let (client, request) = request.build_split();
     let mut request = request?;
 
+    // Sign the request before anything else. A failure here would mean that
+    // the keystore is broken, so the request is still sent, unsigned.
+    if let Some(Err(err)) = signer.map(|signer| signer.sign(&mut request, SystemTime::now())) {
+        tracing::error!(
+            error = &err as &dyn std::error::Error,
+            "Failed to sign outgoing request"
+        );
+    }
+
     let headers = request.headers();
     let server_address = request.url().host_str().map(ToOwned::to_owned);
     let server_port = request.url().port_or_known_default();
@@ -224,10 +284,24 @@ async fn send_traced(
 pub trait RequestBuilderExt {
     /// Send the request with a tracing span, and span context propagated.
     fn send_traced(self) -> impl Future<Output = Result<reqwest::Response, reqwest::Error>> + Send;
+
+    /// Send the request like [`RequestBuilderExt::send_traced`], signing it
+    /// first if a [`RequestSigner`] is given.
+    fn send_signed_traced(
+        self,
+        signer: Option<&RequestSigner>,
+    ) -> impl Future<Output = Result<reqwest::Response, reqwest::Error>> + Send;
 }
 
 impl RequestBuilderExt for reqwest::RequestBuilder {
     fn send_traced(self) -> impl Future<Output = Result<reqwest::Response, reqwest::Error>> + Send {
-        send_traced(self)
+        send_traced(self, None)
+    }
+
+    fn send_signed_traced(
+        self,
+        signer: Option<&RequestSigner>,
+    ) -> impl Future<Output = Result<reqwest::Response, reqwest::Error>> + Send {
+        send_traced(self, signer)
     }
 }
@@ -0,0 +1,296 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Sign outgoing requests using HTTP Message Signatures, as defined in
+//! [RFC 9421](https://www.rfc-editor.org/rfc/rfc9421.html)
+
+use std::{
+    fmt::Write as _,
+    sync::{Arc, Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64ct::{Base64, Encoding as _};
+use http::{
+    HeaderName, HeaderValue,
+    header::{CONTENT_TYPE, InvalidHeaderValue},
+};
+use mas_iana::jose::JsonWebSignatureAlg;
+use mas_jose::constraints::Constrainable as _;
+use mas_keystore::{Keystore, WrongAlgorithmError};
+use rand::{CryptoRng, RngCore, SeedableRng as _};
+use rand_chacha::ChaChaRng;
+use sha2::{Digest as _, Sha256};
+use signature::{RandomizedSigner as _, SignatureEncoding as _};
+use thiserror::Error;
+
+static SIGNATURE_INPUT: HeaderName = HeaderName::from_static("signature-input");
+static SIGNATURE: HeaderName = HeaderName::from_static("signature");
+static CONTENT_DIGEST: HeaderName = HeaderName::from_static("content-digest");
+
+/// The label of the signature added to the requests
+const SIGNATURE_LABEL: &str = "mas";
+
+/// Get the name of the HTTP Message Signature algorithm matching a JWS
+/// algorithm, if there is one
+fn http_signature_alg(alg: &JsonWebSignatureAlg) -> Option<&'static str> {
+    match alg {
+        JsonWebSignatureAlg::Rs256 => Some("rsa-v1_5-sha256"),
+        JsonWebSignatureAlg::Ps512 => Some("rsa-pss-sha512"),
+        JsonWebSignatureAlg::Es256 => Some("ecdsa-p256-sha256"),
+        JsonWebSignatureAlg::Es384 => Some("ecdsa-p384-sha384"),
+        _ => None,
+    }
+}
+
+/// Error returned when a [`RequestSigner`] could not be created
+#[derive(Debug, Error)]
+pub enum RequestSignerError {
+    /// The algorithm has no equivalent in HTTP Message Signatures
+    #[error("The {0} algorithm can't be used to sign HTTP requests")]
+    UnsupportedAlgorithm(JsonWebSignatureAlg),
+
+    /// No key in the keystore can sign with the algorithm
+    #[error("No key in the keystore can sign with the {0} algorithm")]
+    NoSuitableKey(JsonWebSignatureAlg),
+}
+
+/// Error returned when a request could not be signed
+#[derive(Debug, Error)]
+pub enum RequestSignatureError {
+    #[error("No key in the keystore can sign with the {0} algorithm")]
+    NoSuitableKey(JsonWebSignatureAlg),
+
+    #[error(transparent)]
+    WrongAlgorithm(#[from] WrongAlgorithmError),
+
+    #[error(transparent)]
+    Signature(#[from] signature::Error),
+
+    #[error(transparent)]
+    InvalidHeaderValue(#[from] InvalidHeaderValue),
+}
+
+/// Signs outgoing requests with a key from the [`Keystore`], so that their
+/// receiver can check that they were sent by this service
+///
+/// The signature covers the method, the full URI, and the body of the request
+/// through a `Content-Digest` header. The key ID is the `kid` of the key,
+/// which lets receivers find the matching public key in the JWKS.
+#[derive(Clone)]
+pub struct RequestSigner {
+    keystore: Keystore,
+    alg: JsonWebSignatureAlg,
+    rng: Arc<Mutex<ChaChaRng>>,
+}
+
+impl RequestSigner {
+    /// Create a new [`RequestSigner`] using keys from the given [`Keystore`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the algorithm can't be used for HTTP Message
+    /// Signatures, or if no key in the keystore can sign with it
+    pub fn new(
+        keystore: Keystore,
+        alg: JsonWebSignatureAlg,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self, RequestSignerError> {
+        if http_signature_alg(&alg).is_none() {
+            return Err(RequestSignerError::UnsupportedAlgorithm(alg));
+        }
+
+        if keystore.signing_key_for_algorithm(&alg).is_none() {
+            return Err(RequestSignerError::NoSuitableKey(alg));
+        }
+
+        // Some algorithms need randomness. The signer gets its own RNG, seeded
+        // from the one given here.
+        let mut seed = [0; 32];
+        rng.fill_bytes(&mut seed);
+        let rng = Arc::new(Mutex::new(ChaChaRng::from_seed(seed)));
+
+        Ok(Self { keystore, alg, rng })
+    }
+
+    /// Sign the given request, adding the `Signature-Input` and `Signature`
+    /// headers, and the `Content-Digest` header if it has a body
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request could not be signed
+    pub fn sign(
+        &self,
+        request: &mut reqwest::Request,
+        now: SystemTime,
+    ) -> Result<(), RequestSignatureError> {
+        let key = self
+            .keystore
+            .signing_key_for_algorithm(&self.alg)
+            .ok_or_else(|| RequestSignatureError::NoSuitableKey(self.alg.clone()))?;
+        let alg_name = http_signature_alg(&self.alg)
+            .ok_or_else(|| RequestSignatureError::NoSuitableKey(self.alg.clone()))?;
+
+        let mut components = vec![
+            ("@method", request.method().as_str().to_owned()),
+            ("@target-uri", request.url().to_string()),
+        ];
+
+        // Streamed bodies can't be covered, as we don't have their content
+        let digest = request
+            .body()
+            .and_then(reqwest::Body::as_bytes)
+            .map(|body| format!("sha-256=:{}:", Base64::encode_string(&Sha256::digest(body))));
+
+        if let Some(digest) = digest {
+            request
+                .headers_mut()
+                .insert(CONTENT_DIGEST.clone(), HeaderValue::from_str(&digest)?);
+            components.push(("content-digest", digest));
+        }
+
+        if let Some(content_type) = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+        {
+            components.push(("content-type", content_type.trim().to_owned()));
+        }
+
+        let created = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let params = signature_params(
+            components.iter().map(|(name, _)| *name),
+            created,
+            key.kid(),
+            alg_name,
+        );
+        let base = signature_base(&components, &params);
+
+        let signer = key.params().signing_key_for_alg(&self.alg)?;
+        let signature = {
+            let mut rng = self.rng.lock().unwrap_or_else(PoisonError::into_inner);
+            signer.try_sign_with_rng(&mut *rng, base.as_bytes())?
+        };
+        let signature = Base64::encode_string(&signature.to_bytes());
+
+        let headers = request.headers_mut();
+        headers.insert(
+            SIGNATURE_INPUT.clone(),
+            HeaderValue::from_str(&format!("{SIGNATURE_LABEL}={params}"))?,
+        );
+        headers.insert(
+            SIGNATURE.clone(),
+            HeaderValue::from_str(&format!("{SIGNATURE_LABEL}=:{signature}:"))?,
+        );
+
+        Ok(())
+    }
+}
+
+/// Serialize the signature parameters, as used both in the signature base and
+/// in the `Signature-Input` header
+fn signature_params<'a>(
+    components: impl Iterator<Item = &'a str>,
+    created: u64,
+    kid: Option<&str>,
+    alg: &str,
+) -> String {
+    let components: Vec<String> = components.map(|name| format!("\"{name}\"")).collect();
+    let mut params = format!("({});created={created}", components.join(" "));
+
+    if let Some(kid) = kid {
+        let kid = kid.replace('\\', "\\\\").replace('"', "\\\"");
+        let _ = write!(params, ";keyid=\"{kid}\"");
+    }
+
+    let _ = write!(params, ";alg=\"{alg}\"");
+    params
+}
+
+/// Build the signature base, which is the payload actually signed
+fn signature_base(components: &[(&str, String)], params: &str) -> String {
+    let mut base = String::new();
+    for (name, value) in components {
+        let _ = writeln!(base, "\"{name}\": {value}");
+    }
+    let _ = write!(base, "\"@signature-params\": {params}");
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use mas_keystore::{JsonWebKey, JsonWebKeySet, PrivateKey};
+    use signature::Verifier as _;
+
+    use super::*;
+
+    #[test]
+    fn test_sign_request() {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let keystore = Keystore::new(JsonWebKeySet::new(vec![
+            JsonWebKey::new(PrivateKey::generate_ec_p256(&mut rng)).with_kid("key-1"),
+        ]));
+
+        // The keystore has no RSA key
+        assert!(matches!(
+            RequestSigner::new(keystore.clone(), JsonWebSignatureAlg::Rs256, &mut rng),
+            Err(RequestSignerError::NoSuitableKey(_))
+        ));
+        assert!(matches!(
+            RequestSigner::new(keystore.clone(), JsonWebSignatureAlg::Es256K, &mut rng),
+            Err(RequestSignerError::UnsupportedAlgorithm(_))
+        ));
+
+        let signer =
+            RequestSigner::new(keystore.clone(), JsonWebSignatureAlg::Es256, &mut rng).unwrap();
+
+        let mut request = reqwest::Request::new(
+            http::Method::POST,
+            "https://example.com/_synapse/admin/v2/users/%40alice%3Aexample.com"
+                .parse()
+                .unwrap(),
+        );
+        request
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        *request.body_mut() = Some(r#"{"hello": "world"}"#.into());
+
+        let now = UNIX_EPOCH + std::time::Duration::from_secs(1_618_884_473);
+        signer.sign(&mut request, now).unwrap();
+
+        let headers = request.headers();
+        assert_eq!(
+            headers[&CONTENT_DIGEST],
+            "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:"
+        );
+        let params = r#"("@method" "@target-uri" "content-digest" "content-type");created=1618884473;keyid="key-1";alg="ecdsa-p256-sha256""#;
+        assert_eq!(headers[&SIGNATURE_INPUT], format!("mas={params}").as_str());
+
+        // Check that the signature is valid for the expected signature base
+        let base = format!(
+            "\"@method\": POST\n\
+             \"@target-uri\": https://example.com/_synapse/admin/v2/users/%40alice%3Aexample.com\n\
+             \"content-digest\": sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:\n\
+             \"content-type\": application/json\n\
+             \"@signature-params\": {params}"
+        );
+        let signature = headers[&SIGNATURE]
+            .to_str()
+            .unwrap()
+            .strip_prefix("mas=:")
+            .and_then(|s| s.strip_suffix(':'))
+            .unwrap();
+        let signature = Base64::decode_vec(signature).unwrap();
+        let verifier = keystore
+            .signing_key_for_algorithm(&JsonWebSignatureAlg::Es256)
+            .unwrap()
+            .params()
+            .verifying_key_for_alg(&JsonWebSignatureAlg::Es256)
+            .unwrap();
+        verifier
+            .verify(base.as_bytes(), &signature.as_slice().into())
+            .unwrap();
+    }
+}
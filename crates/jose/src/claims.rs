@@ -498,6 +498,8 @@ mod oidc_core {
     pub const NONCE: Claim<String, Equality<str>> = Claim::new("nonce");
     pub const AT_HASH: Claim<String, TokenHash> = Claim::new("at_hash");
     pub const C_HASH: Claim<String, TokenHash> = Claim::new("c_hash");
+    pub const ACR: Claim<String> = Claim::new("acr");
+    pub const AMR: Claim<Vec<String>> = Claim::new("amr");
 
     pub const NAME: Claim<String> = Claim::new("name");
     pub const GIVEN_NAME: Claim<String> = Claim::new("given_name");
@@ -525,7 +527,45 @@ mod oidc_core {
     pub const UPDATED_AT: Claim<Timestamp> = Claim::new("updated_at");
 }
 
-pub use self::{oidc_core::*, rfc7519::*};
+/// Claims defined in RFC9449 sec. 4.2
+/// <https://www.rfc-editor.org/rfc/rfc9449.html#section-4.2>
+mod rfc9449 {
+    use super::{Claim, Equality};
+
+    pub const HTM: Claim<String, Equality<str>> = Claim::new("htm");
+    pub const HTU: Claim<String> = Claim::new("htu");
+    pub const ATH: Claim<String, Equality<str>> = Claim::new("ath");
+}
+
+/// Claims defined in RFC7800 sec. 3.1
+/// <https://www.rfc-editor.org/rfc/rfc7800.html#section-3.1>
+mod rfc7800 {
+    use super::Claim;
+
+    pub const CNF: Claim<serde_json::Value> = Claim::new("cnf");
+}
+
+/// Claims defined in RFC9068 sec. 2.2
+/// <https://www.rfc-editor.org/rfc/rfc9068.html#section-2.2>
+mod rfc9068 {
+    use super::Claim;
+
+    pub const CLIENT_ID: Claim<String> = Claim::new("client_id");
+    pub const SCOPE: Claim<String> = Claim::new("scope");
+}
+
+/// Claims defined in OpenID Connect Back-Channel Logout 1.0 sec. 2.4
+/// <https://openid.net/specs/openid-connect-backchannel-1_0.html#LogoutToken>
+mod oidc_backchannel_logout {
+    use super::Claim;
+
+    pub const SID: Claim<String> = Claim::new("sid");
+    pub const EVENTS: Claim<serde_json::Value> = Claim::new("events");
+}
+
+pub use self::{
+    oidc_backchannel_logout::*, oidc_core::*, rfc7519::*, rfc7800::*, rfc9068::*, rfc9449::*,
+};
 
 #[cfg(test)]
 mod tests {
@@ -506,3 +506,24 @@ impl signature::Verifier<Signature> for AsymmetricVerifyingKey {
         }
     }
 }
+
+impl AsymmetricVerifyingKey {
+    /// Verify a signature encoded the way it is outside of JOSE, for example
+    /// in WebAuthn assertions: ECDSA signatures are ASN.1 DER-encoded instead
+    /// of being the concatenation of `r` and `s`, and other signatures are
+    /// encoded the same way.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signature can't be decoded or is invalid
+    pub fn verify_der(&self, msg: &[u8], signature: &[u8]) -> Result<(), signature::Error> {
+        use signature::Verifier as _;
+
+        match self {
+            Self::Es256(key) => key.verify(msg, &ecdsa::Signature::from_der(signature)?),
+            Self::Es384(key) => key.verify(msg, &ecdsa::Signature::from_der(signature)?),
+            Self::Es256K(key) => key.verify(msg, &ecdsa::Signature::from_der(signature)?),
+            _ => self.verify(msg, &Signature::from(signature)),
+        }
+    }
+}
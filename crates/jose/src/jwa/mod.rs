@@ -4,7 +4,7 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
-use mas_iana::jose::JsonWebSignatureAlg;
+use mas_iana::jose::{JsonWebEncryptionAlg, JsonWebEncryptionEnc, JsonWebSignatureAlg};
 use sha2::{Sha256, Sha384, Sha512};
 
 mod asymmetric;
@@ -57,3 +57,20 @@ pub const SUPPORTED_SIGNING_ALGORITHMS: [JsonWebSignatureAlg; 12] = [
     JsonWebSignatureAlg::Es384,
     JsonWebSignatureAlg::Es256K,
 ];
+
+/// All the key management algorithms for encryption supported by this crate.
+pub const SUPPORTED_ENCRYPTION_ALGORITHMS: [JsonWebEncryptionAlg; 3] = [
+    JsonWebEncryptionAlg::RsaOaep,
+    JsonWebEncryptionAlg::RsaOaep256,
+    JsonWebEncryptionAlg::EcdhEs,
+];
+
+/// All the content encryption algorithms supported by this crate.
+pub const SUPPORTED_CONTENT_ENCRYPTION_ALGORITHMS: [JsonWebEncryptionEnc; 6] = [
+    JsonWebEncryptionEnc::A128CbcHs256,
+    JsonWebEncryptionEnc::A192CbcHs384,
+    JsonWebEncryptionEnc::A256CbcHs512,
+    JsonWebEncryptionEnc::A128Gcm,
+    JsonWebEncryptionEnc::A192Gcm,
+    JsonWebEncryptionEnc::A256Gcm,
+];
@@ -0,0 +1,258 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Content encryption algorithms, as defined in [RFC7518] section 5
+//!
+//! [RFC7518]: https://www.rfc-editor.org/rfc/rfc7518#section-5
+
+use aes_gcm::{
+    AesGcm,
+    aead::{AeadInPlace, KeyInit, Nonce, Tag, consts::U12},
+    aes::{Aes128, Aes192, Aes256},
+};
+use cbc::cipher::{BlockCipher, BlockDecryptMut, BlockEncryptMut, KeyIvInit, block_padding::Pkcs7};
+use hmac::{Hmac, Mac};
+use mas_iana::jose::JsonWebEncryptionEnc;
+use sha2::{Sha256, Sha384, Sha512};
+use signature::rand_core::CryptoRngCore;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ContentEncryptionError {
+    #[error("Unsupported content encryption algorithm {enc}")]
+    UnsupportedAlgorithm { enc: JsonWebEncryptionEnc },
+
+    #[error("Invalid content encryption key length")]
+    InvalidKeyLength,
+
+    #[error("Invalid initialization vector length")]
+    InvalidIvLength,
+
+    #[error("Failed to encrypt or decrypt the content")]
+    Cipher,
+}
+
+/// The parts resulting from the encryption of the content
+#[derive(Clone, PartialEq, Eq)]
+pub(super) struct EncryptedContent {
+    pub iv: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub tag: Vec<u8>,
+}
+
+/// Get the length in bytes of the content encryption key used by the given
+/// algorithm
+pub(super) fn key_len(enc: &JsonWebEncryptionEnc) -> Result<usize, ContentEncryptionError> {
+    match enc {
+        JsonWebEncryptionEnc::A128CbcHs256 => Ok(32),
+        JsonWebEncryptionEnc::A192CbcHs384 => Ok(48),
+        JsonWebEncryptionEnc::A256CbcHs512 => Ok(64),
+        JsonWebEncryptionEnc::A128Gcm => Ok(16),
+        JsonWebEncryptionEnc::A192Gcm => Ok(24),
+        JsonWebEncryptionEnc::A256Gcm => Ok(32),
+        _ => Err(ContentEncryptionError::UnsupportedAlgorithm { enc: enc.clone() }),
+    }
+}
+
+/// Generate a random content encryption key for the given algorithm
+pub(super) fn generate_key(
+    rng: &mut impl CryptoRngCore,
+    enc: &JsonWebEncryptionEnc,
+) -> Result<Vec<u8>, ContentEncryptionError> {
+    let mut key = vec![0; key_len(enc)?];
+    rng.fill_bytes(&mut key);
+    Ok(key)
+}
+
+/// Encrypt the plaintext with the given algorithm, authenticating the given
+/// additional data
+pub(super) fn encrypt(
+    rng: &mut impl CryptoRngCore,
+    enc: &JsonWebEncryptionEnc,
+    key: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<EncryptedContent, ContentEncryptionError> {
+    if key.len() != key_len(enc)? {
+        return Err(ContentEncryptionError::InvalidKeyLength);
+    }
+
+    match enc {
+        JsonWebEncryptionEnc::A128CbcHs256 => {
+            cbc_hmac_encrypt::<Aes128, Hmac<Sha256>>(rng, key, aad, plaintext)
+        }
+        JsonWebEncryptionEnc::A192CbcHs384 => {
+            cbc_hmac_encrypt::<Aes192, Hmac<Sha384>>(rng, key, aad, plaintext)
+        }
+        JsonWebEncryptionEnc::A256CbcHs512 => {
+            cbc_hmac_encrypt::<Aes256, Hmac<Sha512>>(rng, key, aad, plaintext)
+        }
+        JsonWebEncryptionEnc::A128Gcm => {
+            gcm_encrypt::<AesGcm<Aes128, U12>>(rng, key, aad, plaintext)
+        }
+        JsonWebEncryptionEnc::A192Gcm => {
+            gcm_encrypt::<AesGcm<Aes192, U12>>(rng, key, aad, plaintext)
+        }
+        JsonWebEncryptionEnc::A256Gcm => {
+            gcm_encrypt::<AesGcm<Aes256, U12>>(rng, key, aad, plaintext)
+        }
+        _ => Err(ContentEncryptionError::UnsupportedAlgorithm { enc: enc.clone() }),
+    }
+}
+
+/// Decrypt the ciphertext with the given algorithm, checking the
+/// authentication tag against the ciphertext and the given additional data
+pub(super) fn decrypt(
+    enc: &JsonWebEncryptionEnc,
+    key: &[u8],
+    aad: &[u8],
+    content: &EncryptedContent,
+) -> Result<Vec<u8>, ContentEncryptionError> {
+    if key.len() != key_len(enc)? {
+        return Err(ContentEncryptionError::InvalidKeyLength);
+    }
+
+    match enc {
+        JsonWebEncryptionEnc::A128CbcHs256 => {
+            cbc_hmac_decrypt::<Aes128, Hmac<Sha256>>(key, aad, content)
+        }
+        JsonWebEncryptionEnc::A192CbcHs384 => {
+            cbc_hmac_decrypt::<Aes192, Hmac<Sha384>>(key, aad, content)
+        }
+        JsonWebEncryptionEnc::A256CbcHs512 => {
+            cbc_hmac_decrypt::<Aes256, Hmac<Sha512>>(key, aad, content)
+        }
+        JsonWebEncryptionEnc::A128Gcm => gcm_decrypt::<AesGcm<Aes128, U12>>(key, aad, content),
+        JsonWebEncryptionEnc::A192Gcm => gcm_decrypt::<AesGcm<Aes192, U12>>(key, aad, content),
+        JsonWebEncryptionEnc::A256Gcm => gcm_decrypt::<AesGcm<Aes256, U12>>(key, aad, content),
+        _ => Err(ContentEncryptionError::UnsupportedAlgorithm { enc: enc.clone() }),
+    }
+}
+
+/// Compute the authentication tag of the `AES_CBC_HMAC_SHA2` algorithms, as
+/// per RFC7518 section 5.2.2.1
+fn cbc_hmac_mac<M>(mac_key: &[u8], aad: &[u8], iv: &[u8], ciphertext: &[u8]) -> M
+where
+    M: Mac + KeyInit,
+{
+    // The length of the additional data, in bits
+    let aad_len = (aad.len() as u64) * 8;
+    let mut mac = <M as KeyInit>::new_from_slice(mac_key).expect("HMAC accepts keys of any length");
+    mac.update(aad);
+    mac.update(iv);
+    mac.update(ciphertext);
+    mac.update(&aad_len.to_be_bytes());
+    mac
+}
+
+fn cbc_hmac_encrypt<C, M>(
+    rng: &mut impl CryptoRngCore,
+    key: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<EncryptedContent, ContentEncryptionError>
+where
+    C: BlockEncryptMut + BlockCipher + KeyInit,
+    M: Mac + KeyInit,
+{
+    // The first half of the key is used for the MAC, the second for the cipher
+    let (mac_key, enc_key) = key.split_at(key.len() / 2);
+
+    let mut iv = vec![0; 16];
+    rng.fill_bytes(&mut iv);
+
+    let ciphertext = cbc::Encryptor::<C>::new_from_slices(enc_key, &iv)
+        .map_err(|_| ContentEncryptionError::InvalidKeyLength)?
+        .encrypt_padded_vec_mut::<Pkcs7>(plaintext);
+
+    // The tag is the first half of the MAC
+    let mac = cbc_hmac_mac::<M>(mac_key, aad, &iv, &ciphertext).finalize();
+    let tag = mac.into_bytes()[..mac_key.len()].to_vec();
+
+    Ok(EncryptedContent {
+        iv,
+        ciphertext,
+        tag,
+    })
+}
+
+fn cbc_hmac_decrypt<C, M>(
+    key: &[u8],
+    aad: &[u8],
+    content: &EncryptedContent,
+) -> Result<Vec<u8>, ContentEncryptionError>
+where
+    C: BlockDecryptMut + BlockCipher + KeyInit,
+    M: Mac + KeyInit,
+{
+    let (mac_key, enc_key) = key.split_at(key.len() / 2);
+
+    if content.tag.len() != mac_key.len() {
+        return Err(ContentEncryptionError::Cipher);
+    }
+
+    cbc_hmac_mac::<M>(mac_key, aad, &content.iv, &content.ciphertext)
+        .verify_truncated_left(&content.tag)
+        .map_err(|_| ContentEncryptionError::Cipher)?;
+
+    cbc::Decryptor::<C>::new_from_slices(enc_key, &content.iv)
+        .map_err(|_| ContentEncryptionError::InvalidIvLength)?
+        .decrypt_padded_vec_mut::<Pkcs7>(&content.ciphertext)
+        .map_err(|_| ContentEncryptionError::Cipher)
+}
+
+fn gcm_encrypt<A>(
+    rng: &mut impl CryptoRngCore,
+    key: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<EncryptedContent, ContentEncryptionError>
+where
+    A: AeadInPlace + KeyInit,
+{
+    let cipher = A::new_from_slice(key).map_err(|_| ContentEncryptionError::InvalidKeyLength)?;
+
+    let mut nonce = Nonce::<A>::default();
+    rng.fill_bytes(&mut nonce);
+
+    let mut ciphertext = plaintext.to_vec();
+    let tag = cipher
+        .encrypt_in_place_detached(&nonce, aad, &mut ciphertext)
+        .map_err(|_| ContentEncryptionError::Cipher)?;
+
+    Ok(EncryptedContent {
+        iv: nonce.to_vec(),
+        ciphertext,
+        tag: tag.to_vec(),
+    })
+}
+
+fn gcm_decrypt<A>(
+    key: &[u8],
+    aad: &[u8],
+    content: &EncryptedContent,
+) -> Result<Vec<u8>, ContentEncryptionError>
+where
+    A: AeadInPlace + KeyInit,
+{
+    let cipher = A::new_from_slice(key).map_err(|_| ContentEncryptionError::InvalidKeyLength)?;
+
+    if content.iv.len() != Nonce::<A>::default().len() {
+        return Err(ContentEncryptionError::InvalidIvLength);
+    }
+    if content.tag.len() != Tag::<A>::default().len() {
+        return Err(ContentEncryptionError::Cipher);
+    }
+
+    let nonce = Nonce::<A>::from_slice(&content.iv);
+    let tag = Tag::<A>::from_slice(&content.tag);
+
+    let mut plaintext = content.ciphertext.clone();
+    cipher
+        .decrypt_in_place_detached(nonce, aad, &mut plaintext, tag)
+        .map_err(|_| ContentEncryptionError::Cipher)?;
+
+    Ok(plaintext)
+}
@@ -0,0 +1,385 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+use mas_iana::jose::JsonWebKeyUse;
+use signature::rand_core::CryptoRngCore;
+use thiserror::Error;
+
+use super::{
+    content::{self, ContentEncryptionError, EncryptedContent},
+    header::JsonWebEncryptionHeader,
+    key_management::{self, KeyManagementError},
+};
+use crate::{
+    constraints::Constrainable,
+    jwk::{
+        JsonWebKey, JsonWebKeyPrivateParameters, JsonWebKeyPublicParameters, PublicJsonWebKeySet,
+    },
+};
+
+/// A JWE in its compact serialization
+#[derive(Clone, PartialEq, Eq)]
+pub struct Jwe {
+    header: JsonWebEncryptionHeader,
+    encoded_header: String,
+    encrypted_key: Vec<u8>,
+    content: EncryptedContent,
+}
+
+impl std::fmt::Display for Jwe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}.{}",
+            self.encoded_header,
+            Base64UrlUnpadded::encode_string(&self.encrypted_key),
+            Base64UrlUnpadded::encode_string(&self.content.iv),
+            Base64UrlUnpadded::encode_string(&self.content.ciphertext),
+            Base64UrlUnpadded::encode_string(&self.content.tag),
+        )
+    }
+}
+
+impl std::fmt::Debug for Jwe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Jwe")
+            .field("header", &self.header)
+            .field("encrypted_key", &"...")
+            .field("content", &"...")
+            .finish()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum JweDecodeError {
+    #[error("JWE must have 5 parts")]
+    InvalidFormat,
+
+    #[error("failed to decode JWE header")]
+    DecodeHeader {
+        #[source]
+        inner: base64ct::Error,
+    },
+
+    #[error("failed to deserialize JWE header")]
+    DeserializeHeader {
+        #[source]
+        inner: serde_json::Error,
+    },
+
+    #[error("failed to decode JWE part")]
+    DecodePart {
+        #[source]
+        inner: base64ct::Error,
+    },
+}
+
+impl JweDecodeError {
+    fn decode_header(inner: base64ct::Error) -> Self {
+        Self::DecodeHeader { inner }
+    }
+
+    fn deserialize_header(inner: serde_json::Error) -> Self {
+        Self::DeserializeHeader { inner }
+    }
+
+    fn decode_part(inner: base64ct::Error) -> Self {
+        Self::DecodePart { inner }
+    }
+}
+
+impl TryFrom<&str> for Jwe {
+    type Error = JweDecodeError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut parts = value.split('.');
+        let (
+            Some(encoded_header),
+            Some(encrypted_key),
+            Some(iv),
+            Some(ciphertext),
+            Some(tag),
+            None,
+        ) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        )
+        else {
+            return Err(JweDecodeError::InvalidFormat);
+        };
+
+        let header =
+            Base64UrlUnpadded::decode_vec(encoded_header).map_err(JweDecodeError::decode_header)?;
+        let header = serde_json::from_slice(&header).map_err(JweDecodeError::deserialize_header)?;
+
+        let decode =
+            |part| Base64UrlUnpadded::decode_vec(part).map_err(JweDecodeError::decode_part);
+
+        Ok(Self {
+            header,
+            encoded_header: encoded_header.to_owned(),
+            encrypted_key: decode(encrypted_key)?,
+            content: EncryptedContent {
+                iv: decode(iv)?,
+                ciphertext: decode(ciphertext)?,
+                tag: decode(tag)?,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum JweEncryptionError {
+    #[error("failed to serialize header")]
+    EncodeHeader {
+        #[source]
+        inner: serde_json::Error,
+    },
+
+    #[error("no key suitable for encryption found")]
+    NoSuitableKey,
+
+    #[error(transparent)]
+    KeyManagement(#[from] KeyManagementError),
+
+    #[error(transparent)]
+    Content(#[from] ContentEncryptionError),
+}
+
+impl JweEncryptionError {
+    fn encode_header(inner: serde_json::Error) -> Self {
+        Self::EncodeHeader { inner }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum JweDecryptionError {
+    #[error(transparent)]
+    KeyManagement(#[from] KeyManagementError),
+
+    #[error(transparent)]
+    Content(#[from] ContentEncryptionError),
+}
+
+impl Jwe {
+    /// Get the JWE header
+    #[must_use]
+    pub fn header(&self) -> &JsonWebEncryptionHeader {
+        &self.header
+    }
+
+    /// Encrypt the given payload to the given key using the given RNG.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the algorithms in the header are not supported, if
+    /// the key is not suitable for them, or if the header could not be
+    /// serialized.
+    pub fn encrypt_with_rng(
+        rng: &mut impl CryptoRngCore,
+        header: JsonWebEncryptionHeader,
+        payload: &[u8],
+        key: &JsonWebKeyPublicParameters,
+    ) -> Result<Self, JweEncryptionError> {
+        let cek = key_management::wrap(rng, header.alg(), header.enc(), key)?;
+
+        let header = match cek.epk {
+            Some(epk) => header.with_epk(JsonWebKey::new(epk)),
+            None => header,
+        };
+
+        // The encoded header is used as the additional authenticated data
+        let encoded_header =
+            serde_json::to_vec(&header).map_err(JweEncryptionError::encode_header)?;
+        let encoded_header = Base64UrlUnpadded::encode_string(&encoded_header);
+
+        let content = content::encrypt(
+            rng,
+            header.enc(),
+            &cek.key,
+            encoded_header.as_bytes(),
+            payload,
+        )?;
+
+        Ok(Self {
+            header,
+            encoded_header,
+            encrypted_key: cek.encrypted_key,
+            content,
+        })
+    }
+
+    /// Encrypt the given payload to a suitable key from the given JWKS, using
+    /// the given RNG.
+    ///
+    /// Keys meant for encryption are preferred, and keys meant for signatures
+    /// are never used. The `kid` of the key used is set in the header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no key in the JWKS is suitable for the algorithms in
+    /// the header, or if the header could not be serialized.
+    pub fn encrypt_with_jwks(
+        rng: &mut impl CryptoRngCore,
+        header: JsonWebEncryptionHeader,
+        payload: &[u8],
+        jwks: &PublicJsonWebKeySet,
+    ) -> Result<Self, JweEncryptionError> {
+        let alg = header.alg().to_string();
+        let mut candidates: Vec<_> = jwks
+            .iter()
+            .filter(|key| key.use_() != Some(&JsonWebKeyUse::Sig))
+            .filter(|key| key.alg().is_none_or(|key_alg| key_alg.to_string() == alg))
+            .collect();
+
+        // Put the keys explicitly meant for encryption first
+        candidates.sort_by_key(|key| key.use_() != Some(&JsonWebKeyUse::Enc));
+
+        for candidate in candidates {
+            let header = match candidate.kid() {
+                Some(kid) => header.clone().with_kid(kid),
+                None => header.clone(),
+            };
+
+            match Self::encrypt_with_rng(rng, header, payload, candidate.params()) {
+                Ok(jwe) => return Ok(jwe),
+                Err(JweEncryptionError::KeyManagement(
+                    KeyManagementError::KeyNotSuitable { .. }
+                    | KeyManagementError::Rsa { .. }
+                    | KeyManagementError::EllipticCurve { .. },
+                )) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(JweEncryptionError::NoSuitableKey)
+    }
+
+    /// Decrypt the payload of this JWE using the given private key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the algorithms in the header are not supported, if
+    /// the key is not suitable for them, or if the JWE could not be decrypted.
+    pub fn decrypt(
+        &self,
+        key: &JsonWebKeyPrivateParameters,
+    ) -> Result<Vec<u8>, JweDecryptionError> {
+        let epk = self.header.epk().map(JsonWebKey::params);
+        let cek = key_management::unwrap(
+            self.header.alg(),
+            self.header.enc(),
+            key,
+            &self.encrypted_key,
+            epk,
+        )?;
+
+        let payload = content::decrypt(
+            self.header.enc(),
+            &cek,
+            self.encoded_header.as_bytes(),
+            &self.content,
+        )?;
+
+        Ok(payload)
+    }
+
+    /// Get the JWE in its compact serialization
+    #[must_use]
+    pub fn into_string(self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mas_iana::jose::{JsonWebEncryptionAlg, JsonWebEncryptionEnc};
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::jwa::SUPPORTED_CONTENT_ENCRYPTION_ALGORITHMS;
+
+    #[test]
+    fn test_jwe_encrypt_and_decrypt() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(42);
+        let key = elliptic_curve::SecretKey::<p256::NistP256>::random(&mut rng);
+        let public_key = JsonWebKeyPublicParameters::from(key.public_key());
+        let private_key = JsonWebKeyPrivateParameters::from(&key);
+
+        for enc in SUPPORTED_CONTENT_ENCRYPTION_ALGORITHMS {
+            let header = JsonWebEncryptionHeader::new(JsonWebEncryptionAlg::EcdhEs, enc);
+            let jwe = Jwe::encrypt_with_rng(&mut rng, header, b"hello", &public_key).unwrap();
+
+            let jwe = Jwe::try_from(jwe.into_string().as_str()).unwrap();
+            assert!(jwe.header().epk().is_some());
+            assert_eq!(jwe.decrypt(&private_key).unwrap(), b"hello");
+        }
+    }
+
+    #[test]
+    fn test_jwe_tampered() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(42);
+        let key = elliptic_curve::SecretKey::<p256::NistP256>::random(&mut rng);
+        let public_key = JsonWebKeyPublicParameters::from(key.public_key());
+        let private_key = JsonWebKeyPrivateParameters::from(&key);
+
+        let header = JsonWebEncryptionHeader::new(
+            JsonWebEncryptionAlg::EcdhEs,
+            JsonWebEncryptionEnc::A128CbcHs256,
+        );
+        let jwe = Jwe::encrypt_with_rng(&mut rng, header, b"hello", &public_key).unwrap();
+
+        // Replace the authentication tag
+        let jwe = jwe.into_string();
+        let (rest, tag) = jwe.rsplit_once('.').unwrap();
+        let tag = Base64UrlUnpadded::decode_vec(tag).unwrap();
+        let tag = Base64UrlUnpadded::encode_string(&vec![0; tag.len()]);
+        let jwe = Jwe::try_from(format!("{rest}.{tag}").as_str()).unwrap();
+
+        assert!(jwe.decrypt(&private_key).is_err());
+    }
+
+    #[test]
+    fn test_jwe_encrypt_with_jwks() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(42);
+        let signing_key = elliptic_curve::SecretKey::<p256::NistP256>::random(&mut rng);
+        let encryption_key = elliptic_curve::SecretKey::<p256::NistP256>::random(&mut rng);
+
+        let jwks = PublicJsonWebKeySet::new(vec![
+            JsonWebKey::new(JsonWebKeyPublicParameters::from(signing_key.public_key()))
+                .with_use(JsonWebKeyUse::Sig)
+                .with_kid("sig"),
+            JsonWebKey::new(JsonWebKeyPublicParameters::from(
+                encryption_key.public_key(),
+            ))
+            .with_kid("enc"),
+        ]);
+
+        let header = JsonWebEncryptionHeader::new(
+            JsonWebEncryptionAlg::EcdhEs,
+            JsonWebEncryptionEnc::A256Gcm,
+        );
+        let jwe = Jwe::encrypt_with_jwks(&mut rng, header, b"hello", &jwks).unwrap();
+        assert_eq!(jwe.header().kid(), Some("enc"));
+
+        let private_key = JsonWebKeyPrivateParameters::from(&encryption_key);
+        assert_eq!(jwe.decrypt(&private_key).unwrap(), b"hello");
+
+        // RSA keys are not suitable for ECDH-ES
+        let header = JsonWebEncryptionHeader::new(
+            JsonWebEncryptionAlg::RsaOaep256,
+            JsonWebEncryptionEnc::A256Gcm,
+        );
+        assert!(matches!(
+            Jwe::encrypt_with_jwks(&mut rng, header, b"hello", &jwks),
+            Err(JweEncryptionError::NoSuitableKey)
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use mas_iana::jose::{JsonWebEncryptionAlg, JsonWebEncryptionEnc};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::jwk::PublicJsonWebKey;
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct JsonWebEncryptionHeader {
+    alg: JsonWebEncryptionAlg,
+
+    enc: JsonWebEncryptionEnc,
+
+    #[serde(default)]
+    epk: Option<Box<PublicJsonWebKey>>,
+
+    #[serde(default)]
+    kid: Option<String>,
+
+    #[serde(default)]
+    typ: Option<String>,
+
+    #[serde(default)]
+    cty: Option<String>,
+
+    #[serde(default)]
+    crit: Option<Vec<String>>,
+}
+
+impl JsonWebEncryptionHeader {
+    #[must_use]
+    pub fn new(alg: JsonWebEncryptionAlg, enc: JsonWebEncryptionEnc) -> Self {
+        Self {
+            alg,
+            enc,
+            epk: None,
+            kid: None,
+            typ: None,
+            cty: None,
+            crit: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn alg(&self) -> &JsonWebEncryptionAlg {
+        &self.alg
+    }
+
+    #[must_use]
+    pub const fn enc(&self) -> &JsonWebEncryptionEnc {
+        &self.enc
+    }
+
+    #[must_use]
+    pub const fn epk(&self) -> Option<&PublicJsonWebKey> {
+        // Can't use as_deref because it's not a const fn
+        match &self.epk {
+            Some(epk) => Some(epk),
+            None => None,
+        }
+    }
+
+    #[must_use]
+    pub(super) fn with_epk(mut self, epk: PublicJsonWebKey) -> Self {
+        self.epk = Some(Box::new(epk));
+        self
+    }
+
+    #[must_use]
+    pub fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
+    #[must_use]
+    pub fn with_kid(mut self, kid: impl Into<String>) -> Self {
+        self.kid = Some(kid.into());
+        self
+    }
+
+    #[must_use]
+    pub fn typ(&self) -> Option<&str> {
+        self.typ.as_deref()
+    }
+
+    #[must_use]
+    pub fn with_typ(mut self, typ: String) -> Self {
+        self.typ = Some(typ);
+        self
+    }
+
+    #[must_use]
+    pub fn cty(&self) -> Option<&str> {
+        self.cty.as_deref()
+    }
+
+    #[must_use]
+    pub fn with_cty(mut self, cty: String) -> Self {
+        self.cty = Some(cty);
+        self
+    }
+
+    #[must_use]
+    pub fn crit(&self) -> Option<&[String]> {
+        self.crit.as_deref()
+    }
+}
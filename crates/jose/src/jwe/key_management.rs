@@ -0,0 +1,256 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Key management algorithms, as defined in [RFC7518] section 4
+//!
+//! [RFC7518]: https://www.rfc-editor.org/rfc/rfc7518#section-4
+
+use digest::{Digest, typenum::Unsigned};
+use elliptic_curve::{
+    AffinePoint, CurveArithmetic, PublicKey, SecretKey,
+    ecdh::EphemeralSecret,
+    sec1::{FromEncodedPoint, ModulusSize, ToEncodedPoint},
+};
+use mas_iana::jose::{JsonWebEncryptionAlg, JsonWebEncryptionEnc, JsonWebKeyEcEllipticCurve};
+use rsa::{Oaep, RsaPrivateKey, RsaPublicKey};
+use sha1::Sha1;
+use sha2::Sha256;
+use signature::rand_core::CryptoRngCore;
+use thiserror::Error;
+
+use super::content::{self, ContentEncryptionError};
+use crate::jwk::{
+    JsonWebKeyPrivateParameters, JsonWebKeyPublicParameters,
+    private_parameters::EcPrivateParameters, public_parameters::EcPublicParameters,
+};
+
+#[derive(Debug, Error)]
+pub enum KeyManagementError {
+    #[error("Unsupported key management algorithm {alg}")]
+    UnsupportedAlgorithm { alg: JsonWebEncryptionAlg },
+
+    #[error("Key not suitable for algorithm {alg}")]
+    KeyNotSuitable { alg: JsonWebEncryptionAlg },
+
+    #[error("Missing ephemeral public key")]
+    MissingEphemeralKey,
+
+    #[error("Invalid RSA parameters")]
+    Rsa {
+        #[from]
+        inner: rsa::errors::Error,
+    },
+
+    #[error("Invalid Elliptic Curve parameters")]
+    EllipticCurve {
+        #[from]
+        inner: elliptic_curve::Error,
+    },
+
+    #[error(transparent)]
+    Content(#[from] ContentEncryptionError),
+}
+
+/// The content encryption key, and how it is conveyed to the recipient
+pub(super) struct ContentEncryptionKey {
+    /// The content encryption key itself
+    pub key: Vec<u8>,
+
+    /// The content encryption key, encrypted to the recipient. Empty when the
+    /// key is agreed upon with the recipient.
+    pub encrypted_key: Vec<u8>,
+
+    /// The ephemeral public key used for key agreement, if any
+    pub epk: Option<JsonWebKeyPublicParameters>,
+}
+
+/// Generate a content encryption key for the given algorithms, and encrypt it
+/// to the recipient key
+pub(super) fn wrap(
+    rng: &mut impl CryptoRngCore,
+    alg: &JsonWebEncryptionAlg,
+    enc: &JsonWebEncryptionEnc,
+    recipient: &JsonWebKeyPublicParameters,
+) -> Result<ContentEncryptionKey, KeyManagementError> {
+    match (alg, recipient) {
+        (JsonWebEncryptionAlg::RsaOaep, JsonWebKeyPublicParameters::Rsa(params)) => {
+            let recipient = RsaPublicKey::try_from(params)?;
+            let key = content::generate_key(rng, enc)?;
+            let encrypted_key = recipient.encrypt(rng, Oaep::new::<Sha1>(), &key)?;
+            Ok(ContentEncryptionKey {
+                key,
+                encrypted_key,
+                epk: None,
+            })
+        }
+
+        (JsonWebEncryptionAlg::RsaOaep256, JsonWebKeyPublicParameters::Rsa(params)) => {
+            let recipient = RsaPublicKey::try_from(params)?;
+            let key = content::generate_key(rng, enc)?;
+            let encrypted_key = recipient.encrypt(rng, Oaep::new::<Sha256>(), &key)?;
+            Ok(ContentEncryptionKey {
+                key,
+                encrypted_key,
+                epk: None,
+            })
+        }
+
+        (JsonWebEncryptionAlg::EcdhEs, JsonWebKeyPublicParameters::Ec(params)) => {
+            let (epk, shared_secret) = match params.crv {
+                JsonWebKeyEcEllipticCurve::P256 => {
+                    let (epk, z) = ecdh_es_agree::<p256::NistP256>(rng, params)?;
+                    (JsonWebKeyPublicParameters::from(epk), z)
+                }
+                JsonWebKeyEcEllipticCurve::P384 => {
+                    let (epk, z) = ecdh_es_agree::<p384::NistP384>(rng, params)?;
+                    (JsonWebKeyPublicParameters::from(epk), z)
+                }
+                _ => return Err(KeyManagementError::KeyNotSuitable { alg: alg.clone() }),
+            };
+
+            Ok(ContentEncryptionKey {
+                key: concat_kdf(&shared_secret, enc)?,
+                encrypted_key: Vec::new(),
+                epk: Some(epk),
+            })
+        }
+
+        (
+            JsonWebEncryptionAlg::RsaOaep
+            | JsonWebEncryptionAlg::RsaOaep256
+            | JsonWebEncryptionAlg::EcdhEs,
+            _,
+        ) => Err(KeyManagementError::KeyNotSuitable { alg: alg.clone() }),
+
+        _ => Err(KeyManagementError::UnsupportedAlgorithm { alg: alg.clone() }),
+    }
+}
+
+/// Recover the content encryption key with the recipient private key
+pub(super) fn unwrap(
+    alg: &JsonWebEncryptionAlg,
+    enc: &JsonWebEncryptionEnc,
+    recipient: &JsonWebKeyPrivateParameters,
+    encrypted_key: &[u8],
+    epk: Option<&JsonWebKeyPublicParameters>,
+) -> Result<Vec<u8>, KeyManagementError> {
+    match (alg, recipient) {
+        (JsonWebEncryptionAlg::RsaOaep, JsonWebKeyPrivateParameters::Rsa(params)) => {
+            let recipient = RsaPrivateKey::try_from(params)?;
+            Ok(recipient.decrypt(Oaep::new::<Sha1>(), encrypted_key)?)
+        }
+
+        (JsonWebEncryptionAlg::RsaOaep256, JsonWebKeyPrivateParameters::Rsa(params)) => {
+            let recipient = RsaPrivateKey::try_from(params)?;
+            Ok(recipient.decrypt(Oaep::new::<Sha256>(), encrypted_key)?)
+        }
+
+        (JsonWebEncryptionAlg::EcdhEs, JsonWebKeyPrivateParameters::Ec(params)) => {
+            let Some(JsonWebKeyPublicParameters::Ec(epk)) = epk else {
+                return Err(KeyManagementError::MissingEphemeralKey);
+            };
+
+            if epk.crv != params.crv {
+                return Err(KeyManagementError::KeyNotSuitable { alg: alg.clone() });
+            }
+
+            let shared_secret = match params.crv {
+                JsonWebKeyEcEllipticCurve::P256 => ecdh_es_recover::<p256::NistP256>(params, epk)?,
+                JsonWebKeyEcEllipticCurve::P384 => ecdh_es_recover::<p384::NistP384>(params, epk)?,
+                _ => return Err(KeyManagementError::KeyNotSuitable { alg: alg.clone() }),
+            };
+
+            concat_kdf(&shared_secret, enc)
+        }
+
+        (
+            JsonWebEncryptionAlg::RsaOaep
+            | JsonWebEncryptionAlg::RsaOaep256
+            | JsonWebEncryptionAlg::EcdhEs,
+            _,
+        ) => Err(KeyManagementError::KeyNotSuitable { alg: alg.clone() }),
+
+        _ => Err(KeyManagementError::UnsupportedAlgorithm { alg: alg.clone() }),
+    }
+}
+
+/// Agree on a shared secret with the recipient, using a freshly generated
+/// ephemeral key
+fn ecdh_es_agree<C>(
+    rng: &mut impl CryptoRngCore,
+    recipient: &EcPublicParameters,
+) -> Result<(PublicKey<C>, Vec<u8>), KeyManagementError>
+where
+    C: CurveArithmetic,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    C::FieldBytesSize: ModulusSize + Unsigned,
+{
+    let recipient = PublicKey::<C>::try_from(recipient)?;
+    let secret = EphemeralSecret::<C>::random(rng);
+    let shared_secret = secret.diffie_hellman(&recipient);
+    Ok((
+        secret.public_key(),
+        shared_secret.raw_secret_bytes().to_vec(),
+    ))
+}
+
+/// Recover the shared secret agreed upon by the sender, from its ephemeral
+/// public key
+fn ecdh_es_recover<C>(
+    recipient: &EcPrivateParameters,
+    epk: &EcPublicParameters,
+) -> Result<Vec<u8>, KeyManagementError>
+where
+    C: CurveArithmetic,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    C::FieldBytesSize: ModulusSize + Unsigned,
+{
+    let recipient = SecretKey::<C>::try_from(recipient)?;
+    let epk = PublicKey::<C>::try_from(epk)?;
+    let shared_secret =
+        elliptic_curve::ecdh::diffie_hellman(recipient.to_nonzero_scalar(), epk.as_affine());
+    Ok(shared_secret.raw_secret_bytes().to_vec())
+}
+
+/// Append a value prefixed by its length, as a 32-bit big-endian integer
+fn push_length_prefixed(out: &mut Vec<u8>, value: &[u8]) {
+    let len = u32::try_from(value.len()).expect("value is too long");
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(value);
+}
+
+/// Derive the content encryption key from the shared secret, using the Concat
+/// KDF as per RFC7518 section 4.6.2
+fn concat_kdf(
+    shared_secret: &[u8],
+    enc: &JsonWebEncryptionEnc,
+) -> Result<Vec<u8>, KeyManagementError> {
+    let key_len = content::key_len(enc)?;
+
+    // When using ECDH-ES directly, the algorithm ID is the content encryption
+    // algorithm. We don't support the PartyUInfo and PartyVInfo parameters, so
+    // they are left empty.
+    let mut other_info = Vec::new();
+    push_length_prefixed(&mut other_info, enc.to_string().as_bytes());
+    push_length_prefixed(&mut other_info, &[]);
+    push_length_prefixed(&mut other_info, &[]);
+    let key_data_len = u32::try_from(key_len * 8).expect("key is too long");
+    other_info.extend_from_slice(&key_data_len.to_be_bytes());
+
+    let mut key = Vec::with_capacity(key_len);
+    let mut counter: u32 = 1;
+    while key.len() < key_len {
+        let round = Sha256::new()
+            .chain_update(counter.to_be_bytes())
+            .chain_update(shared_secret)
+            .chain_update(&other_info)
+            .finalize();
+        key.extend_from_slice(&round);
+        counter += 1;
+    }
+    key.truncate(key_len);
+
+    Ok(key)
+}
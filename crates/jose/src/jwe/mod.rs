@@ -0,0 +1,18 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Ref: <https://www.rfc-editor.org/rfc/rfc7516.html>
+
+mod content;
+mod encrypted;
+mod header;
+mod key_management;
+
+pub use self::{
+    content::ContentEncryptionError,
+    encrypted::{Jwe, JweDecodeError, JweDecryptionError, JweEncryptionError},
+    header::JsonWebEncryptionHeader,
+    key_management::KeyManagementError,
+};
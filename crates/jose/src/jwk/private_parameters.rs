@@ -277,7 +277,7 @@ mod ec_impls {
         sec1::{Coordinates, FromEncodedPoint, ModulusSize, ToEncodedPoint},
     };
 
-    use super::{super::JwkEcCurve, EcPrivateParameters};
+    use super::{super::JwkEcCurve, EcPrivateParameters, JsonWebKeyPrivateParameters};
     use crate::base64::Base64UrlNoPad;
 
     impl<C> TryFrom<EcPrivateParameters> for SecretKey<C>
@@ -301,6 +301,28 @@ mod ec_impls {
         }
     }
 
+    impl<C> From<SecretKey<C>> for JsonWebKeyPrivateParameters
+    where
+        C: elliptic_curve::CurveArithmetic + JwkEcCurve,
+        AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+        C::FieldBytesSize: ModulusSize,
+    {
+        fn from(key: SecretKey<C>) -> Self {
+            (&key).into()
+        }
+    }
+
+    impl<C> From<&SecretKey<C>> for JsonWebKeyPrivateParameters
+    where
+        C: elliptic_curve::CurveArithmetic + JwkEcCurve,
+        AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+        C::FieldBytesSize: ModulusSize,
+    {
+        fn from(key: &SecretKey<C>) -> Self {
+            Self::Ec(key.into())
+        }
+    }
+
     impl<C> From<SecretKey<C>> for EcPrivateParameters
     where
         C: elliptic_curve::CurveArithmetic + JwkEcCurve,
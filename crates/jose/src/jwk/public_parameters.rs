@@ -4,11 +4,13 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use base64ct::{Base64UrlUnpadded, Encoding};
 use mas_iana::jose::{
     JsonWebKeyEcEllipticCurve, JsonWebKeyOkpEllipticCurve, JsonWebKeyType, JsonWebSignatureAlg,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use super::ParametersInfo;
 use crate::base64::Base64UrlNoPad;
@@ -50,6 +52,27 @@ impl JsonWebKeyPublicParameters {
             _ => None,
         }
     }
+
+    /// Compute the SHA-256 thumbprint of the key, as defined in [RFC7638],
+    /// encoded as unpadded base64url
+    ///
+    /// [RFC7638]: https://www.rfc-editor.org/rfc/rfc7638
+    #[must_use]
+    pub fn thumbprint(&self) -> String {
+        // The required members, in lexicographic order, without whitespace.
+        // None of the values need escaping, as they are either base64url or
+        // curve names
+        let canonical = match self {
+            Self::Rsa(p) => format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, p.e, p.n),
+            Self::Ec(p) => format!(
+                r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#,
+                p.crv, p.x, p.y
+            ),
+            Self::Okp(p) => format!(r#"{{"crv":"{}","kty":"OKP","x":"{}"}}"#, p.crv, p.x),
+        };
+
+        Base64UrlUnpadded::encode_string(&Sha256::digest(canonical.as_bytes()))
+    }
 }
 
 impl ParametersInfo for JsonWebKeyPublicParameters {
@@ -300,3 +323,24 @@ mod ec_impls {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbprint() {
+        // Example from RFC7638 sec. 3.1
+        let key: JsonWebKeyPublicParameters = serde_json::from_value(serde_json::json!({
+            "kty": "RSA",
+            "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+            "e": "AQAB",
+        }))
+        .unwrap();
+
+        assert_eq!(
+            key.thumbprint(),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+    }
+}
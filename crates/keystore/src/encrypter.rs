@@ -12,10 +12,13 @@ use chacha20poly1305::{ChaCha20Poly1305, KeyInit};
 use generic_array::GenericArray;
 use thiserror::Error;
 
+use crate::envelope::{self, KeyWrapper, LocalKeyWrapper};
+
 /// Helps encrypting and decrypting data
 #[derive(Clone)]
 pub struct Encrypter {
     aead: Arc<ChaCha20Poly1305>,
+    key_wrapper: Arc<dyn KeyWrapper>,
 }
 
 #[derive(Debug, Error)]
@@ -24,16 +27,30 @@ pub enum DecryptError {
     Aead(#[from] aead::Error),
     Base64(#[from] base64ct::Error),
     Shape,
+    UnknownKey,
 }
 
 impl Encrypter {
     /// Creates an [`Encrypter`] out of an encryption key
+    ///
+    /// Unless another [`KeyWrapper`] is set with
+    /// [`Encrypter::with_key_wrapper`], the encryption key is also used as the
+    /// master key for envelope encryption.
     #[must_use]
     pub fn new(key: &[u8; 32]) -> Self {
+        let key_wrapper = Arc::new(LocalKeyWrapper::new("default", key));
         let key = GenericArray::from_slice(key);
         let aead = ChaCha20Poly1305::new(key);
         let aead = Arc::new(aead);
-        Self { aead }
+        Self { aead, key_wrapper }
+    }
+
+    /// Set the [`KeyWrapper`] used to wrap the data keys of envelope-encrypted
+    /// payloads
+    #[must_use]
+    pub fn with_key_wrapper(mut self, key_wrapper: impl KeyWrapper + 'static) -> Self {
+        self.key_wrapper = Arc::new(key_wrapper);
+        self
     }
 
     /// Encrypt a payload
@@ -91,4 +108,27 @@ impl Encrypter {
 
         Ok(decrypted_client_secret)
     }
+
+    /// Encrypt a payload to a self-contained string, using envelope encryption
+    ///
+    /// The payload is encrypted with a fresh data key, which is wrapped by the
+    /// current master key of the [`KeyWrapper`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the payload failed to encrypt
+    pub fn encrypt_envelope(&self, decrypted: &[u8]) -> Result<String, aead::Error> {
+        envelope::encrypt(&*self.key_wrapper, decrypted)
+    }
+
+    /// Decrypt a payload from a self-contained string, produced either by
+    /// [`Encrypter::encrypt_envelope`] or by [`Encrypter::encrypt_to_string`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the payload failed to decrypt
+    pub fn decrypt_envelope(&self, encrypted: &str) -> Result<Vec<u8>, DecryptError> {
+        envelope::decrypt(&*self.key_wrapper, encrypted)
+            .unwrap_or_else(|| self.decrypt_string(encrypted))
+    }
 }
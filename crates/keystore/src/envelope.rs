@@ -0,0 +1,196 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Envelope encryption, where each payload is encrypted with its own random
+//! data key, which is itself encrypted ("wrapped") with a master key.
+//!
+//! Only the wrapped data key is stored alongside the payload, along with the ID
+//! of the master key which wrapped it. This means that master keys can be
+//! rotated by keeping the previous ones around for unwrapping, and that a
+//! leaked data key only exposes a single payload.
+
+use std::collections::HashMap;
+
+use aead::Aead;
+use base64ct::{Base64, Encoding};
+use chacha20poly1305::{ChaCha20Poly1305, KeyInit};
+use generic_array::GenericArray;
+
+use crate::DecryptError;
+
+/// Encrypt a payload with the given key and a random nonce, prepending the
+/// nonce to the result
+fn seal(key: &[u8; 32], decrypted: &[u8]) -> Result<Vec<u8>, aead::Error> {
+    let aead = ChaCha20Poly1305::new(GenericArray::from_slice(key));
+    let nonce: [u8; 12] = rand::random();
+    let encrypted = aead.encrypt(GenericArray::from_slice(&nonce), decrypted)?;
+    Ok([&nonce[..], &encrypted].concat())
+}
+
+/// Decrypt a payload sealed with [`seal`]
+fn open(key: &[u8; 32], encrypted: &[u8]) -> Result<Vec<u8>, DecryptError> {
+    let aead = ChaCha20Poly1305::new(GenericArray::from_slice(key));
+    let nonce = encrypted.get(0..12).ok_or(DecryptError::Shape)?;
+    let payload = encrypted.get(12..).ok_or(DecryptError::Shape)?;
+    let decrypted = aead.decrypt(GenericArray::from_slice(nonce), payload)?;
+    Ok(decrypted)
+}
+
+/// Wraps and unwraps the data keys used for envelope encryption
+///
+/// The master keys never leave the implementation of this trait, which makes
+/// it possible to delegate the wrapping to an external key management service.
+pub trait KeyWrapper: Send + Sync {
+    /// The ID of the master key used to wrap new data keys
+    ///
+    /// It must not contain any `:` character.
+    fn current_key_id(&self) -> &str;
+
+    /// Wrap a data key with the current master key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data key could not be wrapped
+    fn wrap_key(&self, data_key: &[u8; 32]) -> Result<Vec<u8>, aead::Error>;
+
+    /// Unwrap a data key previously wrapped with the master key identified by
+    /// `key_id`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the master key is unknown, or if the data key could
+    /// not be unwrapped
+    fn unwrap_key(&self, key_id: &str, wrapped_key: &[u8]) -> Result<[u8; 32], DecryptError>;
+}
+
+/// A [`KeyWrapper`] which holds the master keys in memory
+pub struct LocalKeyWrapper {
+    current_key_id: String,
+    keys: HashMap<String, [u8; 32]>,
+}
+
+impl LocalKeyWrapper {
+    /// Create a [`LocalKeyWrapper`] which wraps new data keys with the given
+    /// master key
+    #[must_use]
+    pub fn new(key_id: impl Into<String>, key: &[u8; 32]) -> Self {
+        let key_id = key_id.into();
+        let keys = HashMap::from([(key_id.clone(), *key)]);
+        Self {
+            current_key_id: key_id,
+            keys,
+        }
+    }
+
+    /// Add a previous master key, only used to unwrap existing data keys
+    #[must_use]
+    pub fn with_previous_key(mut self, key_id: impl Into<String>, key: &[u8; 32]) -> Self {
+        self.keys.insert(key_id.into(), *key);
+        self
+    }
+}
+
+impl KeyWrapper for LocalKeyWrapper {
+    fn current_key_id(&self) -> &str {
+        &self.current_key_id
+    }
+
+    fn wrap_key(&self, data_key: &[u8; 32]) -> Result<Vec<u8>, aead::Error> {
+        seal(&self.keys[&self.current_key_id], data_key)
+    }
+
+    fn unwrap_key(&self, key_id: &str, wrapped_key: &[u8]) -> Result<[u8; 32], DecryptError> {
+        let key = self.keys.get(key_id).ok_or(DecryptError::UnknownKey)?;
+        let data_key = open(key, wrapped_key)?;
+        data_key.try_into().map_err(|_| DecryptError::Shape)
+    }
+}
+
+/// The prefix of envelope-encrypted payloads, which distinguishes them from
+/// the ones encrypted directly with the encryption secret
+const ENVELOPE_PREFIX: &str = "env:";
+
+/// Encrypt a payload to a self-contained string, with a fresh data key wrapped
+/// by the given [`KeyWrapper`]
+pub(crate) fn encrypt(wrapper: &dyn KeyWrapper, decrypted: &[u8]) -> Result<String, aead::Error> {
+    let data_key: [u8; 32] = rand::random();
+    let wrapped_key = wrapper.wrap_key(&data_key)?;
+    let encrypted = seal(&data_key, decrypted)?;
+
+    Ok(format!(
+        "{ENVELOPE_PREFIX}{key_id}:{wrapped_key}:{encrypted}",
+        key_id = wrapper.current_key_id(),
+        wrapped_key = Base64::encode_string(&wrapped_key),
+        encrypted = Base64::encode_string(&encrypted),
+    ))
+}
+
+/// Decrypt a payload encrypted by [`encrypt`]
+///
+/// Returns `None` if the payload isn't envelope-encrypted
+pub(crate) fn decrypt(
+    wrapper: &dyn KeyWrapper,
+    encrypted: &str,
+) -> Option<Result<Vec<u8>, DecryptError>> {
+    let envelope = encrypted.strip_prefix(ENVELOPE_PREFIX)?;
+    Some(decrypt_envelope(wrapper, envelope))
+}
+
+fn decrypt_envelope(wrapper: &dyn KeyWrapper, envelope: &str) -> Result<Vec<u8>, DecryptError> {
+    let mut parts = envelope.splitn(3, ':');
+    let (Some(key_id), Some(wrapped_key), Some(encrypted)) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(DecryptError::Shape);
+    };
+
+    let wrapped_key = Base64::decode_vec(wrapped_key)?;
+    let encrypted = Base64::decode_vec(encrypted)?;
+    let data_key = wrapper.unwrap_key(key_id, &wrapped_key)?;
+    open(&data_key, &encrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_roundtrip() {
+        let wrapper = LocalKeyWrapper::new("first", &[0x42; 32]);
+        let encrypted = encrypt(&wrapper, b"hello").unwrap();
+        assert!(encrypted.starts_with("env:first:"));
+
+        let decrypted = decrypt(&wrapper, &encrypted).unwrap().unwrap();
+        assert_eq!(decrypted, b"hello");
+
+        // Each payload gets its own data key
+        let other = encrypt(&wrapper, b"hello").unwrap();
+        assert_ne!(encrypted, other);
+
+        // Payloads which aren't envelope-encrypted are left alone
+        assert!(decrypt(&wrapper, "aGVsbG8=").is_none());
+    }
+
+    #[test]
+    fn test_envelope_key_rotation() {
+        let old = LocalKeyWrapper::new("first", &[0x42; 32]);
+        let encrypted = encrypt(&old, b"hello").unwrap();
+
+        // The previous master key can still unwrap existing data keys
+        let new =
+            LocalKeyWrapper::new("second", &[0x43; 32]).with_previous_key("first", &[0x42; 32]);
+        let decrypted = decrypt(&new, &encrypted).unwrap().unwrap();
+        assert_eq!(decrypted, b"hello");
+
+        let encrypted = encrypt(&new, b"hello").unwrap();
+        assert!(encrypted.starts_with("env:second:"));
+
+        // Unknown master keys are rejected
+        assert!(matches!(
+            decrypt(&old, &encrypted),
+            Some(Err(DecryptError::UnknownKey))
+        ));
+    }
+}
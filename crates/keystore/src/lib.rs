@@ -24,10 +24,14 @@ use rsa::BigUint;
 use thiserror::Error;
 
 mod encrypter;
+mod envelope;
 
 pub use aead;
 
-pub use self::encrypter::{DecryptError, Encrypter};
+pub use self::{
+    encrypter::{DecryptError, Encrypter},
+    envelope::{KeyWrapper, LocalKeyWrapper},
+};
 
 /// Error type used when a key could not be loaded
 #[derive(Debug, Error)]
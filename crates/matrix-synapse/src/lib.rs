@@ -8,8 +8,8 @@ use std::{collections::HashSet, time::Duration};
 
 use anyhow::{Context, bail};
 use error::SynapseResponseExt;
-use http::{Method, StatusCode};
-use mas_http::RequestBuilderExt as _;
+use http::{Method, StatusCode, header::CONTENT_TYPE};
+use mas_http::{RequestBuilderExt as _, RequestSigner};
 use mas_matrix::{HomeserverConnection, MatrixUser, ProvisionRequest};
 use serde::{Deserialize, Serialize};
 use tracing::debug;
@@ -32,6 +32,7 @@ pub struct SynapseConnection {
     endpoint: Url,
     access_token: String,
     http_client: reqwest::Client,
+    signer: Option<RequestSigner>,
 }
 
 impl SynapseConnection {
@@ -47,9 +48,18 @@ impl SynapseConnection {
             endpoint,
             access_token,
             http_client,
+            signer: None,
         }
     }
 
+    /// Sign the requests made to the homeserver with the given
+    /// [`RequestSigner`]
+    #[must_use]
+    pub fn with_request_signer(mut self, signer: RequestSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
     fn builder(&self, method: Method, url: &str) -> reqwest::RequestBuilder {
         self.http_client
             .request(
@@ -148,6 +158,16 @@ struct SetDisplayNameRequest<'a> {
     displayname: &'a str,
 }
 
+#[derive(Serialize)]
+struct SetAvatarUrlRequest<'a> {
+    avatar_url: &'a str,
+}
+
+#[derive(Deserialize)]
+struct UploadMediaResponse {
+    content_uri: String,
+}
+
 #[derive(Serialize)]
 struct SynapseDeactivateUserRequest {
     erase: bool,
@@ -183,7 +203,7 @@ impl HomeserverConnection for SynapseConnection {
 
         let response = self
             .get(&format!("_synapse/admin/v2/users/{encoded_mxid}"))
-            .send_traced()
+            .send_signed_traced(self.signer.as_ref())
             .await
             .context("Failed to query user from Synapse")?;
 
@@ -226,7 +246,7 @@ impl HomeserverConnection for SynapseConnection {
             .get(&format!(
                 "_synapse/admin/v1/username_available?username={localpart}"
             ))
-            .send_traced()
+            .send_signed_traced(self.signer.as_ref())
             .await
             .context("Failed to query localpart availability from Synapse")?;
 
@@ -280,16 +300,26 @@ impl HomeserverConnection for SynapseConnection {
             .on_avatar_url(|avatar_url| {
                 body.avatar_url = Some(avatar_url.unwrap_or_default().to_owned());
             })
+            // Synapse replaces all the 3PIDs at once, so emails and phone
+            // numbers end up in the same list
             .on_emails(|emails| {
-                body.three_pids = Some(
-                    emails
+                body.three_pids.get_or_insert_default().extend(
+                    emails.unwrap_or_default().iter().map(|email| ThreePID {
+                        medium: ThreePIDMedium::Email,
+                        address: email.clone(),
+                    }),
+                );
+            })
+            .on_phone_numbers(|phone_numbers| {
+                // Synapse expects MSISDNs without the leading '+'
+                body.three_pids.get_or_insert_default().extend(
+                    phone_numbers
                         .unwrap_or_default()
                         .iter()
-                        .map(|email| ThreePID {
-                            medium: ThreePIDMedium::Email,
-                            address: email.clone(),
-                        })
-                        .collect(),
+                        .map(|phone_number| ThreePID {
+                            medium: ThreePIDMedium::Msisdn,
+                            address: phone_number.trim_start_matches('+').to_owned(),
+                        }),
                 );
             });
 
@@ -297,7 +327,7 @@ impl HomeserverConnection for SynapseConnection {
         let response = self
             .put(&format!("_synapse/admin/v2/users/{encoded_mxid}"))
             .json(&body)
-            .send_traced()
+            .send_signed_traced(self.signer.as_ref())
             .await
             .context("Failed to provision user in Synapse")?;
 
@@ -337,7 +367,7 @@ impl HomeserverConnection for SynapseConnection {
                 device_id: device_id.to_owned(),
                 dehydrated: None,
             })
-            .send_traced()
+            .send_signed_traced(self.signer.as_ref())
             .await
             .context("Failed to create device in Synapse")?;
 
@@ -388,7 +418,7 @@ impl HomeserverConnection for SynapseConnection {
             .json(&SynapseUpdateDeviceRequest {
                 display_name: Some(display_name),
             })
-            .send_traced()
+            .send_signed_traced(self.signer.as_ref())
             .await
             .context("Failed to update device display name in Synapse")?;
 
@@ -425,7 +455,7 @@ impl HomeserverConnection for SynapseConnection {
             .delete(&format!(
                 "_synapse/admin/v2/users/{encoded_mxid}/devices/{encoded_device_id}"
             ))
-            .send_traced()
+            .send_signed_traced(self.signer.as_ref())
             .await
             .context("Failed to delete device in Synapse")?;
 
@@ -463,7 +493,7 @@ impl HomeserverConnection for SynapseConnection {
 
         let response = self
             .get(&format!("_synapse/admin/v2/users/{encoded_mxid}/devices"))
-            .send_traced()
+            .send_signed_traced(self.signer.as_ref())
             .await
             .context("Failed to query devices from Synapse")?;
 
@@ -496,7 +526,7 @@ impl HomeserverConnection for SynapseConnection {
                 "_synapse/admin/v2/users/{encoded_mxid}/delete_devices"
             ))
             .json(&SynapseDeleteDevicesRequest { devices: to_delete })
-            .send_traced()
+            .send_signed_traced(self.signer.as_ref())
             .await
             .context("Failed to delete devices from Synapse")?;
 
@@ -539,7 +569,7 @@ impl HomeserverConnection for SynapseConnection {
             .json(&SynapseDeactivateUserRequest { erase })
             // Deactivation can take a while, so we set a longer timeout
             .timeout(Duration::from_secs(60 * 5))
-            .send_traced()
+            .send_signed_traced(self.signer.as_ref())
             .await
             .context("Failed to deactivate user in Synapse")?;
 
@@ -575,7 +605,7 @@ impl HomeserverConnection for SynapseConnection {
                 deactivated: Some(false),
                 ..SynapseUser::default()
             })
-            .send_traced()
+            .send_signed_traced(self.signer.as_ref())
             .await
             .context("Failed to reactivate user in Synapse")?;
 
@@ -607,7 +637,7 @@ impl HomeserverConnection for SynapseConnection {
                 "_matrix/client/v3/profile/{encoded_mxid}/displayname"
             ))
             .json(&SetDisplayNameRequest { displayname })
-            .send_traced()
+            .send_signed_traced(self.signer.as_ref())
             .await
             .context("Failed to set displayname in Synapse")?;
 
@@ -639,6 +669,91 @@ impl HomeserverConnection for SynapseConnection {
         self.set_displayname(mxid, "").await
     }
 
+    #[tracing::instrument(
+        name = "homeserver.set_avatar_url",
+        skip_all,
+        fields(
+            matrix.homeserver = self.homeserver,
+            matrix.mxid = mxid,
+            matrix.avatar_url = avatar_url,
+        ),
+        err(Debug),
+    )]
+    async fn set_avatar_url(&self, mxid: &str, avatar_url: &str) -> Result<(), anyhow::Error> {
+        let encoded_mxid = urlencoding::encode(mxid);
+        let response = self
+            .put(&format!(
+                "_matrix/client/v3/profile/{encoded_mxid}/avatar_url"
+            ))
+            .json(&SetAvatarUrlRequest { avatar_url })
+            .send_signed_traced(self.signer.as_ref())
+            .await
+            .context("Failed to set avatar in Synapse")?;
+
+        let response = response
+            .error_for_synapse_error()
+            .await
+            .context("Unexpected HTTP response while setting avatar in Synapse")?;
+
+        if response.status() != StatusCode::OK {
+            bail!(
+                "Unexpected HTTP code while setting avatar in Synapse: {}",
+                response.status()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "homeserver.unset_avatar_url",
+        skip_all,
+        fields(
+            matrix.homeserver = self.homeserver,
+            matrix.mxid = mxid,
+        ),
+        err(Display),
+    )]
+    async fn unset_avatar_url(&self, mxid: &str) -> Result<(), anyhow::Error> {
+        self.set_avatar_url(mxid, "").await
+    }
+
+    #[tracing::instrument(
+        name = "homeserver.upload_media",
+        skip_all,
+        fields(
+            matrix.homeserver = self.homeserver,
+            media.content_type = content_type,
+            media.size = data.len(),
+        ),
+        err(Debug),
+    )]
+    async fn upload_media(
+        &self,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> Result<String, anyhow::Error> {
+        let response = self
+            .post("_matrix/media/v3/upload")
+            .header(CONTENT_TYPE, content_type)
+            .body(data)
+            .send_signed_traced(self.signer.as_ref())
+            .await
+            .context("Failed to upload media to Synapse")?;
+
+        let response = response
+            .error_for_synapse_error()
+            .await
+            .context("Unexpected HTTP response while uploading media to Synapse")?;
+
+        let body: UploadMediaResponse = response
+            .json()
+            .await
+            .context("Failed to deserialize response while uploading media to Synapse")?;
+
+        Ok(body.content_uri)
+    }
+
     #[tracing::instrument(
         name = "homeserver.allow_cross_signing_reset",
         skip_all,
@@ -656,7 +771,7 @@ impl HomeserverConnection for SynapseConnection {
                 "_synapse/admin/v1/users/{encoded_mxid}/_allow_cross_signing_replacement_without_uia"
             ))
             .json(&SynapseAllowCrossSigningResetRequest {})
-            .send_traced()
+            .send_signed_traced(self.signer.as_ref())
             .await
             .context("Failed to allow cross-signing reset in Synapse")?;
 
@@ -674,4 +789,19 @@ impl HomeserverConnection for SynapseConnection {
 
         Ok(())
     }
+
+    #[tracing::instrument(
+        name = "homeserver.change_localpart",
+        skip_all,
+        fields(
+            matrix.homeserver = self.homeserver,
+            matrix.mxid = mxid,
+            matrix.localpart = localpart,
+        ),
+    )]
+    async fn change_localpart(&self, mxid: &str, localpart: &str) -> Result<bool, anyhow::Error> {
+        // Synapse has no way to change the localpart of an existing user
+        debug!("Synapse doesn't support changing the localpart of users");
+        Ok(false)
+    }
 }
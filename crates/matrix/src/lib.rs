@@ -36,6 +36,7 @@ pub struct ProvisionRequest {
     displayname: FieldAction<String>,
     avatar_url: FieldAction<String>,
     emails: FieldAction<Vec<String>>,
+    phone_numbers: FieldAction<Vec<String>>,
 }
 
 impl ProvisionRequest {
@@ -53,6 +54,7 @@ impl ProvisionRequest {
             displayname: FieldAction::DoNothing,
             avatar_url: FieldAction::DoNothing,
             emails: FieldAction::DoNothing,
+            phone_numbers: FieldAction::DoNothing,
         }
     }
 
@@ -175,6 +177,43 @@ impl ProvisionRequest {
 
         self
     }
+
+    /// Ask to set the phone numbers of the user.
+    ///
+    /// # Parameters
+    ///
+    /// * `phone_numbers` - The list of phone numbers to set, in the E.164
+    ///   format.
+    #[must_use]
+    pub fn set_phone_numbers(mut self, phone_numbers: Vec<String>) -> Self {
+        self.phone_numbers = FieldAction::Set(phone_numbers);
+        self
+    }
+
+    /// Ask to unset the phone numbers of the user.
+    #[must_use]
+    pub fn unset_phone_numbers(mut self) -> Self {
+        self.phone_numbers = FieldAction::Unset;
+        self
+    }
+
+    /// Call the given callback if the phone numbers should be set or unset.
+    ///
+    /// # Parameters
+    ///
+    /// * `callback` - The callback to call.
+    pub fn on_phone_numbers<F>(&self, callback: F) -> &Self
+    where
+        F: FnOnce(Option<&[String]>),
+    {
+        match &self.phone_numbers {
+            FieldAction::Unset => callback(None),
+            FieldAction::Set(phone_numbers) => callback(Some(phone_numbers)),
+            FieldAction::DoNothing => {}
+        }
+
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -357,6 +396,50 @@ pub trait HomeserverConnection: Send + Sync {
     /// could not be unset.
     async fn unset_displayname(&self, mxid: &str) -> Result<(), anyhow::Error>;
 
+    /// Set the avatar of a user on the homeserver.
+    ///
+    /// # Parameters
+    ///
+    /// * `mxid` - The Matrix ID of the user to set the avatar for.
+    /// * `avatar_url` - The `mxc://` URI of the avatar to set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the homeserver is unreachable or the avatar could
+    /// not be set.
+    async fn set_avatar_url(&self, mxid: &str, avatar_url: &str) -> Result<(), anyhow::Error>;
+
+    /// Unset the avatar of a user on the homeserver.
+    ///
+    /// # Parameters
+    ///
+    /// * `mxid` - The Matrix ID of the user to unset the avatar for.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the homeserver is unreachable or the avatar could
+    /// not be unset.
+    async fn unset_avatar_url(&self, mxid: &str) -> Result<(), anyhow::Error>;
+
+    /// Upload a file to the media repository of the homeserver.
+    ///
+    /// Returns the `mxc://` URI of the uploaded file.
+    ///
+    /// # Parameters
+    ///
+    /// * `content_type` - The MIME type of the file.
+    /// * `data` - The content of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the homeserver is unreachable or the file could not
+    /// be uploaded.
+    async fn upload_media(
+        &self,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> Result<String, anyhow::Error>;
+
     /// Temporarily allow a user to reset their cross-signing keys.
     ///
     /// # Parameters
@@ -368,6 +451,23 @@ pub trait HomeserverConnection: Send + Sync {
     /// Returns an error if the homeserver is unreachable or the cross-signing
     /// reset could not be allowed.
     async fn allow_cross_signing_reset(&self, mxid: &str) -> Result<(), anyhow::Error>;
+
+    /// Change the localpart of a user on the homeserver, keeping their data
+    /// and their `sub`.
+    ///
+    /// Returns `false` if the homeserver doesn't support changing the
+    /// localpart of users, in which case nothing was changed.
+    ///
+    /// # Parameters
+    ///
+    /// * `mxid` - The current Matrix ID of the user.
+    /// * `localpart` - The new localpart of the user.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the homeserver is unreachable or the localpart
+    /// could not be changed.
+    async fn change_localpart(&self, mxid: &str, localpart: &str) -> Result<bool, anyhow::Error>;
 }
 
 #[async_trait::async_trait]
@@ -438,9 +538,29 @@ impl<T: HomeserverConnection + Send + Sync + ?Sized> HomeserverConnection for &T
         (**self).unset_displayname(mxid).await
     }
 
+    async fn set_avatar_url(&self, mxid: &str, avatar_url: &str) -> Result<(), anyhow::Error> {
+        (**self).set_avatar_url(mxid, avatar_url).await
+    }
+
+    async fn unset_avatar_url(&self, mxid: &str) -> Result<(), anyhow::Error> {
+        (**self).unset_avatar_url(mxid).await
+    }
+
+    async fn upload_media(
+        &self,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> Result<String, anyhow::Error> {
+        (**self).upload_media(content_type, data).await
+    }
+
     async fn allow_cross_signing_reset(&self, mxid: &str) -> Result<(), anyhow::Error> {
         (**self).allow_cross_signing_reset(mxid).await
     }
+
+    async fn change_localpart(&self, mxid: &str, localpart: &str) -> Result<bool, anyhow::Error> {
+        (**self).change_localpart(mxid, localpart).await
+    }
 }
 
 // Implement for Arc<T> where T: HomeserverConnection
@@ -512,7 +632,27 @@ impl<T: HomeserverConnection + ?Sized> HomeserverConnection for Arc<T> {
         (**self).unset_displayname(mxid).await
     }
 
+    async fn set_avatar_url(&self, mxid: &str, avatar_url: &str) -> Result<(), anyhow::Error> {
+        (**self).set_avatar_url(mxid, avatar_url).await
+    }
+
+    async fn unset_avatar_url(&self, mxid: &str) -> Result<(), anyhow::Error> {
+        (**self).unset_avatar_url(mxid).await
+    }
+
+    async fn upload_media(
+        &self,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> Result<String, anyhow::Error> {
+        (**self).upload_media(content_type, data).await
+    }
+
     async fn allow_cross_signing_reset(&self, mxid: &str) -> Result<(), anyhow::Error> {
         (**self).allow_cross_signing_reset(mxid).await
     }
+
+    async fn change_localpart(&self, mxid: &str, localpart: &str) -> Result<bool, anyhow::Error> {
+        (**self).change_localpart(mxid, localpart).await
+    }
 }
@@ -18,6 +18,7 @@ struct MockUser {
     displayname: Option<String>,
     devices: HashSet<String>,
     emails: Option<Vec<String>>,
+    phone_numbers: Option<Vec<String>>,
     cross_signing_reset_allowed: bool,
     deactivated: bool,
 }
@@ -28,6 +29,7 @@ pub struct HomeserverConnection {
     homeserver: String,
     users: RwLock<HashMap<String, MockUser>>,
     reserved_localparts: RwLock<HashSet<&'static str>>,
+    media: RwLock<Vec<Vec<u8>>>,
 }
 
 impl HomeserverConnection {
@@ -40,6 +42,7 @@ impl HomeserverConnection {
             homeserver: homeserver.into(),
             users: RwLock::new(HashMap::new()),
             reserved_localparts: RwLock::new(HashSet::new()),
+            media: RwLock::new(Vec::new()),
         }
     }
 
@@ -73,6 +76,7 @@ impl crate::HomeserverConnection for HomeserverConnection {
             displayname: None,
             devices: HashSet::new(),
             emails: None,
+            phone_numbers: None,
             cross_signing_reset_allowed: false,
             deactivated: false,
         });
@@ -86,6 +90,10 @@ impl crate::HomeserverConnection for HomeserverConnection {
             user.emails = emails.map(ToOwned::to_owned);
         });
 
+        request.on_phone_numbers(|phone_numbers| {
+            user.phone_numbers = phone_numbers.map(ToOwned::to_owned);
+        });
+
         request.on_displayname(|displayname| {
             user.displayname = displayname.map(ToOwned::to_owned);
         });
@@ -154,6 +162,7 @@ impl crate::HomeserverConnection for HomeserverConnection {
         let user = users.get_mut(mxid).context("User not found")?;
         user.devices.clear();
         user.emails = None;
+        user.phone_numbers = None;
         user.deactivated = true;
         if erase {
             user.avatar_url = None;
@@ -185,12 +194,45 @@ impl crate::HomeserverConnection for HomeserverConnection {
         Ok(())
     }
 
+    async fn set_avatar_url(&self, mxid: &str, avatar_url: &str) -> Result<(), anyhow::Error> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(mxid).context("User not found")?;
+        user.avatar_url = Some(avatar_url.to_owned());
+        Ok(())
+    }
+
+    async fn unset_avatar_url(&self, mxid: &str) -> Result<(), anyhow::Error> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(mxid).context("User not found")?;
+        user.avatar_url = None;
+        Ok(())
+    }
+
+    async fn upload_media(
+        &self,
+        _content_type: &str,
+        data: Vec<u8>,
+    ) -> Result<String, anyhow::Error> {
+        let mut media = self.media.write().await;
+        media.push(data);
+        Ok(format!("mxc://{}/{}", self.homeserver, media.len()))
+    }
+
     async fn allow_cross_signing_reset(&self, mxid: &str) -> Result<(), anyhow::Error> {
         let mut users = self.users.write().await;
         let user = users.get_mut(mxid).context("User not found")?;
         user.cross_signing_reset_allowed = true;
         Ok(())
     }
+
+    async fn change_localpart(&self, mxid: &str, localpart: &str) -> Result<bool, anyhow::Error> {
+        let new_mxid = self.mxid(localpart);
+        let mut users = self.users.write().await;
+        anyhow::ensure!(!users.contains_key(&new_mxid), "User already exists");
+        let user = users.remove(mxid).context("User not found")?;
+        users.insert(new_mxid, user);
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
@@ -214,7 +256,8 @@ mod tests {
         let request = ProvisionRequest::new("@test:example.org", "test")
             .set_displayname("Test User".into())
             .set_avatar_url("mxc://example.org/1234567890".into())
-            .set_emails(vec!["test@example.org".to_owned()]);
+            .set_emails(vec!["test@example.org".to_owned()])
+            .set_phone_numbers(vec!["+33612345678".to_owned()]);
 
         let inserted = conn.provision_user(&request).await.unwrap();
         assert!(inserted);
@@ -235,6 +278,20 @@ mod tests {
         let user = conn.query_user(mxid).await.unwrap();
         assert_eq!(user.displayname, None);
 
+        // Upload and set a new avatar
+        let avatar_url = conn.upload_media("image/png", vec![1, 2, 3]).await.unwrap();
+        assert!(avatar_url.starts_with("mxc://example.org/"));
+        assert!(conn.set_avatar_url(mxid, &avatar_url).await.is_ok());
+
+        let user = conn.query_user(mxid).await.unwrap();
+        assert_eq!(user.avatar_url, Some(avatar_url));
+
+        // Unset the avatar
+        assert!(conn.unset_avatar_url(mxid).await.is_ok());
+
+        let user = conn.query_user(mxid).await.unwrap();
+        assert_eq!(user.avatar_url, None);
+
         // Deleting a non-existent device should not fail
         assert!(conn.delete_device(mxid, device).await.is_ok());
 
@@ -255,5 +312,11 @@ mod tests {
         // Reserve the localpart, it should not be available anymore
         conn.reserve_localpart("alice").await;
         assert!(!conn.is_localpart_available("alice").await.unwrap());
+
+        // Change the localpart of the user
+        assert!(conn.change_localpart(mxid, "bob").await.unwrap());
+        assert!(conn.query_user(mxid).await.is_err());
+        assert!(conn.query_user("@bob:example.org").await.is_ok());
+        assert!(conn.is_localpart_available("test").await.unwrap());
     }
 }
@@ -86,7 +86,27 @@ impl<C: HomeserverConnection> HomeserverConnection for ReadOnlyHomeserverConnect
         anyhow::bail!("User displayname update is not supported in read-only mode");
     }
 
+    async fn set_avatar_url(&self, _mxid: &str, _avatar_url: &str) -> Result<(), anyhow::Error> {
+        anyhow::bail!("User avatar update is not supported in read-only mode");
+    }
+
+    async fn unset_avatar_url(&self, _mxid: &str) -> Result<(), anyhow::Error> {
+        anyhow::bail!("User avatar update is not supported in read-only mode");
+    }
+
+    async fn upload_media(
+        &self,
+        _content_type: &str,
+        _data: Vec<u8>,
+    ) -> Result<String, anyhow::Error> {
+        anyhow::bail!("Media upload is not supported in read-only mode");
+    }
+
     async fn allow_cross_signing_reset(&self, _mxid: &str) -> Result<(), anyhow::Error> {
         anyhow::bail!("Allowing cross-signing reset is not supported in read-only mode");
     }
+
+    async fn change_localpart(&self, _mxid: &str, _localpart: &str) -> Result<bool, anyhow::Error> {
+        anyhow::bail!("Changing the localpart of users is not supported in read-only mode");
+    }
 }
@@ -0,0 +1,186 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Types to handle [Rich Authorization Requests].
+//!
+//! [Rich Authorization Requests]: https://www.rfc-editor.org/rfc/rfc9396
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use thiserror::Error;
+
+/// A single authorization detail, as defined in [RFC9396 section 2].
+///
+/// Only the `type` field is required. The other common fields are optional,
+/// and any type-specific field is kept as-is in [`extra`].
+///
+/// [RFC9396 section 2]: https://www.rfc-editor.org/rfc/rfc9396#section-2
+/// [`extra`]: AuthorizationDetail::extra
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizationDetail {
+    /// The type of authorization data, which determines how the other fields
+    /// should be interpreted.
+    #[serde(rename = "type")]
+    pub r#type: String,
+
+    /// The locations of the resources or resource servers.
+    pub locations: Option<Vec<String>>,
+
+    /// The kinds of actions to be taken at the resources.
+    pub actions: Option<Vec<String>>,
+
+    /// The kinds of data being requested from the resources.
+    pub datatypes: Option<Vec<String>>,
+
+    /// A specific resource available at the resource server.
+    pub identifier: Option<String>,
+
+    /// The types or levels of privilege being requested at the resources.
+    pub privileges: Option<Vec<String>>,
+
+    /// Type-specific fields.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// An error returned by [`validate`].
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum InvalidAuthorizationDetails {
+    /// The list of authorization details is empty.
+    #[error("authorization details must not be empty")]
+    Empty,
+
+    /// An authorization detail has an empty `type`.
+    #[error("authorization detail type must not be empty")]
+    EmptyType,
+
+    /// An authorization detail has a type which is not supported.
+    #[error("unsupported authorization detail type {0:?}")]
+    UnsupportedType(String),
+}
+
+/// Check that a list of authorization details is well-formed and only uses
+/// the given supported types.
+///
+/// # Errors
+///
+/// Returns an error if the list is empty, or if any of the authorization
+/// details has an empty or unsupported type.
+pub fn validate<S: AsRef<str>>(
+    details: &[AuthorizationDetail],
+    supported_types: &[S],
+) -> Result<(), InvalidAuthorizationDetails> {
+    if details.is_empty() {
+        return Err(InvalidAuthorizationDetails::Empty);
+    }
+
+    for detail in details {
+        if detail.r#type.is_empty() {
+            return Err(InvalidAuthorizationDetails::EmptyType);
+        }
+
+        if !supported_types.iter().any(|t| t.as_ref() == detail.r#type) {
+            return Err(InvalidAuthorizationDetails::UnsupportedType(
+                detail.r#type.clone(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check whether all the `requested` authorization details were `granted`.
+///
+/// This is used when a client narrows down the authorization details at the
+/// token endpoint, which must not exceed what was granted.
+#[must_use]
+pub fn is_subset(requested: &[AuthorizationDetail], granted: &[AuthorizationDetail]) -> bool {
+    requested.iter().all(|detail| granted.contains(detail))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn deserialize_authorization_details() {
+        let details: Vec<AuthorizationDetail> = serde_json::from_value(json!([
+            {
+                "type": "payment_initiation",
+                "actions": ["initiate", "status"],
+                "locations": ["https://example.com/payments"],
+                "instructedAmount": {
+                    "currency": "EUR",
+                    "amount": "123.50"
+                }
+            }
+        ]))
+        .unwrap();
+
+        assert_eq!(details.len(), 1);
+        let detail = &details[0];
+        assert_eq!(detail.r#type, "payment_initiation");
+        assert_eq!(
+            detail.actions.as_deref(),
+            Some(&["initiate".to_owned(), "status".to_owned()][..])
+        );
+        assert!(detail.identifier.is_none());
+        assert_eq!(
+            detail.extra["instructedAmount"],
+            json!({"currency": "EUR", "amount": "123.50"})
+        );
+
+        // Type-specific fields are serialized back as-is
+        assert_eq!(
+            serde_json::to_value(detail).unwrap(),
+            json!({
+                "type": "payment_initiation",
+                "actions": ["initiate", "status"],
+                "locations": ["https://example.com/payments"],
+                "instructedAmount": {
+                    "currency": "EUR",
+                    "amount": "123.50"
+                }
+            })
+        );
+
+        // The type is required
+        serde_json::from_value::<AuthorizationDetail>(json!({"actions": ["read"]})).unwrap_err();
+    }
+
+    #[test]
+    fn validate_authorization_details() {
+        let detail: AuthorizationDetail =
+            serde_json::from_value(json!({"type": "account_information"})).unwrap();
+
+        validate(std::slice::from_ref(&detail), &["account_information"]).unwrap();
+
+        assert_eq!(
+            validate(std::slice::from_ref(&detail), &["payment_initiation"]),
+            Err(InvalidAuthorizationDetails::UnsupportedType(
+                "account_information".to_owned()
+            ))
+        );
+        assert_eq!(
+            validate::<&str>(&[], &["account_information"]),
+            Err(InvalidAuthorizationDetails::Empty)
+        );
+
+        let other: AuthorizationDetail = serde_json::from_value(
+            json!({"type": "account_information", "actions": ["list_accounts"]}),
+        )
+        .unwrap();
+        assert!(is_subset(
+            std::slice::from_ref(&detail),
+            &[detail.clone(), other.clone()]
+        ));
+        assert!(!is_subset(&[other], std::slice::from_ref(&detail)));
+    }
+}
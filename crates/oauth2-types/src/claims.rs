@@ -0,0 +1,112 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Types to handle the [`claims` request parameter] of OpenID Connect.
+//!
+//! [`claims` request parameter]: https://openid.net/specs/openid-connect-core-1_0.html#ClaimsParameter
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// Options on an individually requested claim, as defined in [OpenID Connect
+/// Core section 5.5.1].
+///
+/// [OpenID Connect Core section 5.5.1]: https://openid.net/specs/openid-connect-core-1_0.html#IndividualClaimsRequests
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndividualClaimRequest {
+    /// Whether the claim is essential for the client to work properly.
+    pub essential: Option<bool>,
+
+    /// The value the claim is requested to have.
+    pub value: Option<serde_json::Value>,
+
+    /// The values the claim is requested to have, in order of preference.
+    pub values: Option<Vec<serde_json::Value>>,
+}
+
+/// The claims a client requested individually, as defined in [OpenID Connect
+/// Core section 5.5].
+///
+/// Claims are requested either in the userinfo response or in the ID token.
+/// Requesting a claim with no particular option is done with a `null` value.
+///
+/// [OpenID Connect Core section 5.5]: https://openid.net/specs/openid-connect-core-1_0.html#ClaimsParameter
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClaimsRequest {
+    /// The claims requested in the userinfo response.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub userinfo: BTreeMap<String, Option<IndividualClaimRequest>>,
+
+    /// The claims requested in the ID token.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub id_token: BTreeMap<String, Option<IndividualClaimRequest>>,
+}
+
+impl ClaimsRequest {
+    /// The names of all the requested claims, either in the userinfo response
+    /// or in the ID token.
+    #[must_use]
+    pub fn names(&self) -> BTreeSet<&str> {
+        self.userinfo
+            .keys()
+            .chain(self.id_token.keys())
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn deserialize_claims_request() {
+        let request: ClaimsRequest = serde_json::from_value(json!({
+            "userinfo": {
+                "given_name": {"essential": true},
+                "email": null,
+                "email_verified": {"essential": true}
+            },
+            "id_token": {
+                "auth_time": {"essential": true},
+                "acr": {"values": ["urn:mace:incommon:iap:silver"]}
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(request.userinfo.len(), 3);
+        assert_eq!(request.userinfo["email"], None);
+        assert_eq!(
+            request.userinfo["given_name"],
+            Some(IndividualClaimRequest {
+                essential: Some(true),
+                ..IndividualClaimRequest::default()
+            })
+        );
+        assert_eq!(
+            request.id_token["acr"].as_ref().unwrap().values,
+            Some(vec![json!("urn:mace:incommon:iap:silver")])
+        );
+
+        assert_eq!(
+            request.names().into_iter().collect::<Vec<_>>(),
+            vec!["acr", "auth_time", "email", "email_verified", "given_name"]
+        );
+    }
+
+    #[test]
+    fn deserialize_partial_claims_request() {
+        let request: ClaimsRequest =
+            serde_json::from_value(json!({"id_token": {"email": null}})).unwrap();
+
+        assert!(request.userinfo.is_empty());
+        assert_eq!(request.names().into_iter().collect::<Vec<_>>(), ["email"]);
+    }
+}
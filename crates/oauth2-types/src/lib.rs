@@ -15,6 +15,8 @@
 #![deny(missing_docs)]
 #![allow(clippy::module_name_repetitions)]
 
+pub mod authorization_details;
+pub mod claims;
 pub mod errors;
 pub mod oidc;
 pub mod pkce;
@@ -184,6 +184,50 @@ impl core::str::FromStr for SubjectType {
     }
 }
 
+/// Token delivery modes of the [Client-Initiated Backchannel Authentication]
+/// flow.
+///
+/// [Client-Initiated Backchannel Authentication]: https://openid.net/specs/openid-client-initiated-backchannel-authentication-core-1_0.html
+#[derive(SerializeDisplay, DeserializeFromStr, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum BackchannelTokenDeliveryMode {
+    /// The client polls the token endpoint to get the tokens.
+    Poll,
+
+    /// The client is notified on its notification endpoint once the tokens
+    /// are ready, and then gets them from the token endpoint.
+    Ping,
+
+    /// The tokens are sent directly to the client notification endpoint.
+    Push,
+
+    /// An unknown value.
+    Unknown(String),
+}
+
+impl core::fmt::Display for BackchannelTokenDeliveryMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Poll => f.write_str("poll"),
+            Self::Ping => f.write_str("ping"),
+            Self::Push => f.write_str("push"),
+            Self::Unknown(s) => f.write_str(s),
+        }
+    }
+}
+
+impl core::str::FromStr for BackchannelTokenDeliveryMode {
+    type Err = core::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "poll" => Ok(Self::Poll),
+            "ping" => Ok(Self::Ping),
+            "push" => Ok(Self::Push),
+            s => Ok(Self::Unknown(s.to_owned())),
+        }
+    }
+}
+
 /// Claim types.
 #[derive(SerializeDisplay, DeserializeFromStr, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum ClaimType {
@@ -475,6 +519,12 @@ pub struct ProviderMetadata {
     /// [`OAuthClientAuthenticationMethod::ClientSecretJwt`].
     pub introspection_endpoint_auth_signing_alg_values_supported: Option<Vec<JsonWebSignatureAlg>>,
 
+    /// JSON array containing a list of the JWS algorithms supported by the
+    /// introspection endpoint to sign the [JWT introspection responses].
+    ///
+    /// [JWT introspection responses]: https://www.rfc-editor.org/rfc/rfc9701
+    pub introspection_signing_alg_values_supported: Option<Vec<JsonWebSignatureAlg>>,
+
     /// [PKCE code challenge methods] supported by this authorization server.
     /// If omitted, the authorization server does not support PKCE.
     ///
@@ -596,6 +646,25 @@ pub struct ProviderMetadata {
     /// Defaults to `false`.
     pub require_pushed_authorization_requests: Option<bool>,
 
+    /// JSON array containing a list of the JWS algorithms supported for
+    /// [DPoP] proof JWTs.
+    ///
+    /// [DPoP]: https://www.rfc-editor.org/rfc/rfc9449.html
+    pub dpop_signing_alg_values_supported: Option<Vec<JsonWebSignatureAlg>>,
+
+    /// JSON array containing a list of the JWS signing algorithms (`alg`
+    /// values) supported by the authorization endpoint to sign [JWT-secured
+    /// authorization responses].
+    ///
+    /// [JWT-secured authorization responses]: https://openid.net/specs/oauth-v2-jarm.html
+    pub authorization_signing_alg_values_supported: Option<Vec<JsonWebSignatureAlg>>,
+
+    /// JSON array containing the [authorization details] types supported by
+    /// the authorization server.
+    ///
+    /// [authorization details]: https://www.rfc-editor.org/rfc/rfc9396.html
+    pub authorization_details_types_supported: Option<Vec<String>>,
+
     /// Array containing the list of prompt values that this OP supports.
     ///
     /// This field can be used to detect if the OP supports the [prompt
@@ -609,6 +678,35 @@ pub struct ProviderMetadata {
     /// [device authorization endpoint]: https://www.rfc-editor.org/rfc/rfc8628
     pub device_authorization_endpoint: Option<Url>,
 
+    /// URL of the authorization server's [backchannel authentication
+    /// endpoint].
+    ///
+    /// [backchannel authentication endpoint]: https://openid.net/specs/openid-client-initiated-backchannel-authentication-core-1_0.html
+    pub backchannel_authentication_endpoint: Option<Url>,
+
+    /// JSON array containing the backchannel token delivery modes supported by
+    /// the authorization server.
+    pub backchannel_token_delivery_modes_supported: Option<Vec<BackchannelTokenDeliveryMode>>,
+
+    /// Boolean value specifying whether the OP supports the `user_code`
+    /// parameter in backchannel authentication requests.
+    ///
+    /// Defaults to `false`.
+    pub backchannel_user_code_parameter_supported: Option<bool>,
+
+    /// Boolean value specifying whether the OP supports [back-channel logout].
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [back-channel logout]: https://openid.net/specs/openid-connect-backchannel-1_0.html
+    pub backchannel_logout_supported: Option<bool>,
+
+    /// Boolean value specifying whether the OP can pass a `sid` claim in the
+    /// logout token to identify the RP session with the OP.
+    ///
+    /// Defaults to `false`.
+    pub backchannel_logout_session_supported: Option<bool>,
+
     /// URL of the authorization server's [RP-Initiated Logout endpoint].
     ///
     /// [RP-Initiated Logout endpoint]: https://openid.net/specs/openid-connect-rpinitiated-1_0.html
@@ -746,6 +844,14 @@ impl ProviderMetadata {
             )?;
         }
 
+        if let Some(url) = &metadata.backchannel_authentication_endpoint {
+            validate_url(
+                "backchannel_authentication_endpoint",
+                url,
+                ExtraUrlRestrictions::NoFragment,
+            )?;
+        }
+
         if let Some(url) = &metadata.end_session_endpoint {
             validate_url("end_session_endpoint", url, ExtraUrlRestrictions::None)?;
         }
@@ -916,6 +1022,32 @@ impl ProviderMetadata {
     pub fn require_pushed_authorization_requests(&self) -> bool {
         self.require_pushed_authorization_requests.unwrap_or(false)
     }
+
+    /// Whether the OP supports the `user_code` parameter in backchannel
+    /// authentication requests.
+    ///
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn backchannel_user_code_parameter_supported(&self) -> bool {
+        self.backchannel_user_code_parameter_supported
+            .unwrap_or(false)
+    }
+
+    /// Whether the OP supports back-channel logout.
+    ///
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn backchannel_logout_supported(&self) -> bool {
+        self.backchannel_logout_supported.unwrap_or(false)
+    }
+
+    /// Whether the OP can pass a `sid` claim in back-channel logout tokens.
+    ///
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn backchannel_logout_session_supported(&self) -> bool {
+        self.backchannel_logout_session_supported.unwrap_or(false)
+    }
 }
 
 /// The verified authorization server metadata.
@@ -1757,6 +1889,40 @@ mod tests {
         metadata.validate(&issuer).unwrap();
     }
 
+    #[test]
+    fn validate_backchannel_authentication_endpoint() {
+        let (mut metadata, issuer) = valid_provider_metadata();
+
+        // Ok - Missing
+        metadata.backchannel_authentication_endpoint = None;
+        metadata.clone().validate(&issuer).unwrap();
+
+        // Err - Not https
+        let endpoint = Url::parse("http://localhost/bc-authorize").unwrap();
+        metadata.backchannel_authentication_endpoint = Some(endpoint.clone());
+        let (field, url) = assert_matches!(
+            metadata.clone().validate(&issuer),
+            Err(ProviderMetadataVerificationError::UrlNonHttpsScheme(field, url)) => (field, url)
+        );
+        assert_eq!(field, "backchannel_authentication_endpoint");
+        assert_eq!(url, endpoint);
+
+        // Err - Fragment
+        let endpoint = Url::parse("https://localhost/bc-authorize#fragment").unwrap();
+        metadata.backchannel_authentication_endpoint = Some(endpoint.clone());
+        let (field, url) = assert_matches!(
+            metadata.clone().validate(&issuer),
+            Err(ProviderMetadataVerificationError::UrlWithFragment(field, url)) => (field, url)
+        );
+        assert_eq!(field, "backchannel_authentication_endpoint");
+        assert_eq!(url, endpoint);
+
+        // Ok - Query
+        metadata.backchannel_authentication_endpoint =
+            Some(Url::parse("https://localhost/bc-authorize?query").unwrap());
+        metadata.validate(&issuer).unwrap();
+    }
+
     #[test]
     fn serialize_application_type() {
         assert_eq!(
@@ -102,6 +102,7 @@ pub struct ClientMetadataSerdeHelper {
     jwks: Option<PublicJsonWebKeySet>,
     software_id: Option<String>,
     software_version: Option<String>,
+    software_statement: Option<String>,
     sector_identifier_uri: Option<Url>,
     subject_type: Option<SubjectType>,
     token_endpoint_auth_method: Option<OAuthClientAuthenticationMethod>,
@@ -126,7 +127,16 @@ pub struct ClientMetadataSerdeHelper {
     introspection_signed_response_alg: Option<JsonWebSignatureAlg>,
     introspection_encrypted_response_alg: Option<JsonWebEncryptionAlg>,
     introspection_encrypted_response_enc: Option<JsonWebEncryptionEnc>,
+    authorization_signed_response_alg: Option<JsonWebSignatureAlg>,
     post_logout_redirect_uris: Option<Vec<Url>>,
+    backchannel_logout_uri: Option<Url>,
+    backchannel_logout_session_required: Option<bool>,
+    #[serde_as(as = "Option<DurationSeconds<i64>>")]
+    access_token_ttl: Option<Duration>,
+    #[serde_as(as = "Option<DurationSeconds<i64>>")]
+    refresh_token_ttl: Option<Duration>,
+    #[serde_as(as = "Option<DurationSeconds<i64>>")]
+    device_code_ttl: Option<Duration>,
     #[serde(flatten)]
     extra: ClientMetadataLocalizedFields,
 }
@@ -154,6 +164,7 @@ impl From<ClientMetadata> for ClientMetadataSerdeHelper {
             jwks,
             software_id,
             software_version,
+            software_statement,
             sector_identifier_uri,
             subject_type,
             token_endpoint_auth_method,
@@ -177,7 +188,13 @@ impl From<ClientMetadata> for ClientMetadataSerdeHelper {
             introspection_signed_response_alg,
             introspection_encrypted_response_alg,
             introspection_encrypted_response_enc,
+            authorization_signed_response_alg,
             post_logout_redirect_uris,
+            backchannel_logout_uri,
+            backchannel_logout_session_required,
+            access_token_ttl,
+            refresh_token_ttl,
+            device_code_ttl,
         } = metadata;
 
         ClientMetadataSerdeHelper {
@@ -190,6 +207,7 @@ impl From<ClientMetadata> for ClientMetadataSerdeHelper {
             jwks,
             software_id,
             software_version,
+            software_statement,
             sector_identifier_uri,
             subject_type,
             token_endpoint_auth_method,
@@ -213,7 +231,13 @@ impl From<ClientMetadata> for ClientMetadataSerdeHelper {
             introspection_signed_response_alg,
             introspection_encrypted_response_alg,
             introspection_encrypted_response_enc,
+            authorization_signed_response_alg,
             post_logout_redirect_uris,
+            backchannel_logout_uri,
+            backchannel_logout_session_required,
+            access_token_ttl,
+            refresh_token_ttl,
+            device_code_ttl,
             extra: ClientMetadataLocalizedFields {
                 client_name,
                 logo_uri,
@@ -237,6 +261,7 @@ impl From<ClientMetadataSerdeHelper> for ClientMetadata {
             jwks,
             software_id,
             software_version,
+            software_statement,
             sector_identifier_uri,
             subject_type,
             token_endpoint_auth_method,
@@ -260,7 +285,13 @@ impl From<ClientMetadataSerdeHelper> for ClientMetadata {
             introspection_signed_response_alg,
             introspection_encrypted_response_alg,
             introspection_encrypted_response_enc,
+            authorization_signed_response_alg,
             post_logout_redirect_uris,
+            backchannel_logout_uri,
+            backchannel_logout_session_required,
+            access_token_ttl,
+            refresh_token_ttl,
+            device_code_ttl,
             extra:
                 ClientMetadataLocalizedFields {
                     client_name,
@@ -286,6 +317,7 @@ impl From<ClientMetadataSerdeHelper> for ClientMetadata {
             jwks,
             software_id,
             software_version,
+            software_statement,
             sector_identifier_uri,
             subject_type,
             token_endpoint_auth_method,
@@ -309,7 +341,13 @@ impl From<ClientMetadataSerdeHelper> for ClientMetadata {
             introspection_signed_response_alg,
             introspection_encrypted_response_alg,
             introspection_encrypted_response_enc,
+            authorization_signed_response_alg,
             post_logout_redirect_uris,
+            backchannel_logout_uri,
+            backchannel_logout_session_required,
+            access_token_ttl,
+            refresh_token_ttl,
+            device_code_ttl,
         }
     }
 }
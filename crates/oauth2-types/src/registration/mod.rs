@@ -220,6 +220,17 @@ pub struct ClientMetadata {
     /// `software_id`.
     pub software_version: Option<String>,
 
+    /// A software statement containing client metadata values about the
+    /// client software as claims, signed by a trusted issuer.
+    ///
+    /// The values in the software statement take precedence over the ones
+    /// sent alongside it.
+    ///
+    /// Defined in [RFC7591 section 2.3].
+    ///
+    /// [RFC7591 section 2.3]: https://www.rfc-editor.org/rfc/rfc7591#section-2.3
+    pub software_statement: Option<String>,
+
     /// URL to be used in calculating pseudonymous identifiers by the OpenID
     /// Connect provider when [pairwise subject identifiers] are used.
     ///
@@ -424,11 +435,57 @@ pub struct ClientMetadata {
     /// [introspection endpoint]: https://www.rfc-editor.org/info/rfc7662
     pub introspection_encrypted_response_enc: Option<JsonWebEncryptionEnc>,
 
+    /// [JWS] `alg` algorithm for signing [JWT-secured authorization
+    /// responses].
+    ///
+    /// Defaults to [`DEFAULT_SIGNING_ALGORITHM`] when a JWT response mode is
+    /// requested.
+    ///
+    /// [JWS]: http://tools.ietf.org/html/draft-ietf-jose-json-web-signature
+    /// [JWT-secured authorization responses]: https://openid.net/specs/oauth-v2-jarm.html
+    pub authorization_signed_response_alg: Option<JsonWebSignatureAlg>,
+
     /// `post_logout_redirect_uri` values that are pre-registered by the client
     /// for use at the provider's [RP-Initiated Logout endpoint].
     ///
     /// [RP-Initiated Logout endpoint]: https://openid.net/specs/openid-connect-rpinitiated-1_0.html
     pub post_logout_redirect_uris: Option<Vec<Url>>,
+
+    /// URL to which the provider sends a [logout token] when a session of the
+    /// client ends.
+    ///
+    /// This URL must not contain a fragment.
+    ///
+    /// [logout token]: https://openid.net/specs/openid-connect-backchannel-1_0.html#LogoutToken
+    pub backchannel_logout_uri: Option<Url>,
+
+    /// Whether the client requires the `sid` claim to be included in the
+    /// [logout token].
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [logout token]: https://openid.net/specs/openid-connect-backchannel-1_0.html#LogoutToken
+    pub backchannel_logout_session_required: Option<bool>,
+
+    /// Lifetime of the access tokens issued to the client.
+    ///
+    /// This is not a standard field. Defaults to the lifetime configured on
+    /// the server.
+    pub access_token_ttl: Option<Duration>,
+
+    /// Lifetime of the refresh tokens issued to the client.
+    ///
+    /// This is not a standard field. By default, refresh tokens don't expire.
+    pub refresh_token_ttl: Option<Duration>,
+
+    /// Lifetime of the device codes issued to the client in the [device
+    /// authorization grant].
+    ///
+    /// This is not a standard field. Defaults to the lifetime configured on
+    /// the server.
+    ///
+    /// [device authorization grant]: https://www.rfc-editor.org/rfc/rfc8628
+    pub device_code_ttl: Option<Duration>,
 }
 
 impl ClientMetadata {
@@ -526,6 +583,16 @@ impl ClientMetadata {
             return Err(ClientMetadataVerificationError::IdTokenSigningAlgNone);
         }
 
+        if self
+            .authorization_signed_response_alg
+            .as_ref()
+            .is_some_and(|alg| *alg == JsonWebSignatureAlg::None)
+        {
+            return Err(ClientMetadataVerificationError::UnauthorizedSigningAlgNone(
+                "authorization",
+            ));
+        }
+
         if self.id_token_encrypted_response_enc.is_some() {
             self.id_token_encrypted_response_alg.as_ref().ok_or(
                 ClientMetadataVerificationError::MissingEncryptionAlg("id_token"),
@@ -538,6 +605,20 @@ impl ClientMetadata {
             )?;
         }
 
+        if self
+            .request_object_signing_alg
+            .as_ref()
+            .is_some_and(|alg| *alg == JsonWebSignatureAlg::None)
+        {
+            return Err(ClientMetadataVerificationError::UnauthorizedSigningAlgNone(
+                "request_object",
+            ));
+        }
+
+        if self.require_signed_request_object() && self.jwks_uri.is_none() && self.jwks.is_none() {
+            return Err(ClientMetadataVerificationError::MissingJwksForRequestObject);
+        }
+
         if self.request_object_encryption_enc.is_some() {
             self.request_object_encryption_alg.as_ref().ok_or(
                 ClientMetadataVerificationError::MissingEncryptionAlg("request_object"),
@@ -561,6 +642,37 @@ impl ClientMetadata {
             )?;
         }
 
+        if let Some(uri) = self
+            .backchannel_logout_uri
+            .as_ref()
+            .filter(|uri| uri.fragment().is_some())
+        {
+            return Err(
+                ClientMetadataVerificationError::BackchannelLogoutUriWithFragment(uri.clone()),
+            );
+        }
+
+        if let Some(uri) = self
+            .post_logout_redirect_uris
+            .iter()
+            .flatten()
+            .find(|uri| uri.fragment().is_some())
+        {
+            return Err(
+                ClientMetadataVerificationError::PostLogoutRedirectUriWithFragment(uri.clone()),
+            );
+        }
+
+        for (field, ttl) in [
+            ("access_token_ttl", self.access_token_ttl),
+            ("refresh_token_ttl", self.refresh_token_ttl),
+            ("device_code_ttl", self.device_code_ttl),
+        ] {
+            if ttl.is_some_and(|ttl| ttl <= Duration::zero()) {
+                return Err(ClientMetadataVerificationError::NonPositiveLifetime(field));
+            }
+        }
+
         Ok(VerifiedClientMetadata { inner: self })
     }
 
@@ -782,6 +894,17 @@ impl ClientMetadata {
             .unwrap_or_default()
     }
 
+    /// Whether the client requires the `sid` claim to be included in the
+    /// [logout token].
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [logout token]: https://openid.net/specs/openid-connect-backchannel-1_0.html#LogoutToken
+    #[must_use]
+    pub fn backchannel_logout_session_required(&self) -> bool {
+        self.backchannel_logout_session_required.unwrap_or_default()
+    }
+
     /// [JWE] `alg` and `enc` algorithms for encrypting responses of the
     /// [introspection endpoint].
     ///
@@ -837,6 +960,18 @@ impl VerifiedClientMetadata {
             None => &[],
         }
     }
+
+    /// Array of URIs to which the client can ask the End-User to be redirected
+    /// after logging out at the [RP-Initiated Logout endpoint].
+    ///
+    /// [RP-Initiated Logout endpoint]: https://openid.net/specs/openid-connect-rpinitiated-1_0.html
+    #[must_use]
+    pub fn post_logout_redirect_uris(&self) -> &[Url] {
+        match &self.post_logout_redirect_uris {
+            Some(v) => v,
+            None => &[],
+        }
+    }
 }
 
 impl Deref for VerifiedClientMetadata {
@@ -875,6 +1010,11 @@ pub enum ClientMetadataVerificationError {
     #[error("missing JWK Set for token auth method")]
     MissingJwksForTokenMethod,
 
+    /// No JWK Set was provided but one is required to verify signed request
+    /// objects.
+    #[error("missing JWK Set for signed request objects")]
+    MissingJwksForRequestObject,
+
     /// The given endpoint doesn't allow `none` as a signing algorithm.
     #[error("none signing alg unauthorized for {0}")]
     UnauthorizedSigningAlgNone(&'static str),
@@ -893,6 +1033,18 @@ pub enum ClientMetadataVerificationError {
     /// The given encryption field has an `enc` value but not `alg` value.
     #[error("{0} missing encryption alg value")]
     MissingEncryptionAlg(&'static str),
+
+    /// The backchannel logout URI has a fragment, which is not allowed.
+    #[error("backchannel logout URI with fragment: {0}")]
+    BackchannelLogoutUriWithFragment(Url),
+
+    /// The post-logout redirect URI has a fragment, which is not allowed.
+    #[error("post-logout redirect URI with fragment: {0}")]
+    PostLogoutRedirectUriWithFragment(Url),
+
+    /// The given lifetime is zero or negative.
+    #[error("{0} must be positive")]
+    NonPositiveLifetime(&'static str),
 }
 
 /// The issuer response to dynamic client registration.
@@ -923,6 +1075,7 @@ pub struct ClientRegistrationResponse {
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
+    use chrono::Duration;
     use mas_iana::{
         jose::{JsonWebEncryptionAlg, JsonWebEncryptionEnc, JsonWebSignatureAlg},
         oauth::{OAuthAuthorizationEndpointResponseType, OAuthClientAuthenticationMethod},
@@ -1369,6 +1522,51 @@ mod tests {
         metadata.validate().unwrap();
     }
 
+    #[test]
+    fn validate_authorization_signed_response() {
+        let mut metadata = valid_client_metadata();
+
+        // Err - none signing alg
+        metadata.authorization_signed_response_alg = Some(JsonWebSignatureAlg::None);
+        let field = assert_matches!(
+            metadata.clone().validate(),
+            Err(ClientMetadataVerificationError::UnauthorizedSigningAlgNone(field)) => field
+        );
+        assert_eq!(field, "authorization");
+
+        // Ok - Other signing alg
+        metadata.authorization_signed_response_alg = Some(JsonWebSignatureAlg::Es256);
+        metadata.validate().unwrap();
+    }
+
+    #[test]
+    fn validate_request_object_signing() {
+        let mut metadata = valid_client_metadata();
+
+        // Err - none signing alg
+        metadata.request_object_signing_alg = Some(JsonWebSignatureAlg::None);
+        let field = assert_matches!(
+            metadata.clone().validate(),
+            Err(ClientMetadataVerificationError::UnauthorizedSigningAlgNone(field)) => field
+        );
+        assert_eq!(field, "request_object");
+
+        // Ok - Other signing alg
+        metadata.request_object_signing_alg = Some(JsonWebSignatureAlg::Es256);
+        metadata.clone().validate().unwrap();
+
+        // Err - Requires signed request objects without a JWK Set
+        metadata.require_signed_request_object = Some(true);
+        assert_matches!(
+            metadata.clone().validate(),
+            Err(ClientMetadataVerificationError::MissingJwksForRequestObject)
+        );
+
+        // Ok - Has a JWK Set
+        metadata.jwks_uri = Some(Url::parse("https://localhost/jwks").unwrap());
+        metadata.validate().unwrap();
+    }
+
     #[test]
     fn validate_request_object_encryption() {
         let mut metadata = valid_client_metadata();
@@ -1421,4 +1619,73 @@ mod tests {
         metadata.introspection_encrypted_response_alg = Some(JsonWebEncryptionAlg::RsaOaep);
         metadata.validate().unwrap();
     }
+
+    #[test]
+    fn validate_backchannel_logout_uri() {
+        let mut metadata = valid_client_metadata();
+
+        // Err - Fragment
+        let wrong_uri = Url::parse("https://localhost/logout#fragment").unwrap();
+        metadata.backchannel_logout_uri = Some(wrong_uri.clone());
+        let uri = assert_matches!(
+            metadata.clone().validate(),
+            Err(ClientMetadataVerificationError::BackchannelLogoutUriWithFragment(uri)) => uri
+        );
+        assert_eq!(uri, wrong_uri);
+
+        // Ok - No fragment
+        metadata.backchannel_logout_uri = Some(Url::parse("https://localhost/logout").unwrap());
+        let metadata = metadata.validate().unwrap();
+        assert!(!metadata.backchannel_logout_session_required());
+    }
+
+    #[test]
+    fn validate_post_logout_redirect_uris() {
+        let mut metadata = valid_client_metadata();
+
+        // Err - Fragment
+        let wrong_uri = Url::parse("https://localhost/logged-out#fragment").unwrap();
+        metadata.post_logout_redirect_uris = Some(vec![
+            Url::parse("https://localhost/logged-out").unwrap(),
+            wrong_uri.clone(),
+        ]);
+        let uri = assert_matches!(
+            metadata.clone().validate(),
+            Err(ClientMetadataVerificationError::PostLogoutRedirectUriWithFragment(uri)) => uri
+        );
+        assert_eq!(uri, wrong_uri);
+
+        // Ok - No fragment
+        let uri = Url::parse("https://localhost/logged-out").unwrap();
+        metadata.post_logout_redirect_uris = Some(vec![uri.clone()]);
+        let metadata = metadata.validate().unwrap();
+        assert_eq!(metadata.post_logout_redirect_uris(), &[uri]);
+    }
+
+    #[test]
+    fn validate_token_lifetimes() {
+        let mut metadata = valid_client_metadata();
+
+        // Err - Zero
+        metadata.refresh_token_ttl = Some(Duration::zero());
+        let field = assert_matches!(
+            metadata.clone().validate(),
+            Err(ClientMetadataVerificationError::NonPositiveLifetime(field)) => field
+        );
+        assert_eq!(field, "refresh_token_ttl");
+
+        // Err - Negative
+        metadata.refresh_token_ttl = None;
+        metadata.device_code_ttl = Some(Duration::microseconds(-60 * 1000 * 1000));
+        let field = assert_matches!(
+            metadata.clone().validate(),
+            Err(ClientMetadataVerificationError::NonPositiveLifetime(field)) => field
+        );
+        assert_eq!(field, "device_code_ttl");
+
+        // Ok - Positive
+        metadata.device_code_ttl = None;
+        metadata.access_token_ttl = Some(Duration::microseconds(60 * 1000 * 1000));
+        metadata.validate().unwrap();
+    }
 }
@@ -20,7 +20,10 @@ use serde_with::{
 };
 use url::Url;
 
-use crate::{response_type::ResponseType, scope::Scope};
+use crate::{
+    authorization_details::AuthorizationDetail, claims::ClaimsRequest, response_type::ResponseType,
+    scope::Scope,
+};
 
 // ref: https://www.iana.org/assignments/oauth-parameters/oauth-parameters.xhtml
 
@@ -50,6 +53,32 @@ pub enum ResponseMode {
     /// Defined in [OAuth 2.0 Form Post Response Mode](https://openid.net/specs/oauth-v2-form-post-response-mode-1_0.html).
     FormPost,
 
+    /// Authorization Response parameters are packed in a signed JWT, sent in
+    /// the `response` parameter of the query string added to the
+    /// `redirect_uri`.
+    ///
+    /// Defined in [JWT Secured Authorization Response Mode for OAuth 2.0 (JARM)](https://openid.net/specs/oauth-v2-jarm.html).
+    QueryJwt,
+
+    /// Authorization Response parameters are packed in a signed JWT, sent in
+    /// the `response` parameter of the fragment added to the `redirect_uri`.
+    ///
+    /// Defined in [JWT Secured Authorization Response Mode for OAuth 2.0 (JARM)](https://openid.net/specs/oauth-v2-jarm.html).
+    FragmentJwt,
+
+    /// Authorization Response parameters are packed in a signed JWT, sent in
+    /// the `response` parameter of an auto-submitted HTML form.
+    ///
+    /// Defined in [JWT Secured Authorization Response Mode for OAuth 2.0 (JARM)](https://openid.net/specs/oauth-v2-jarm.html).
+    FormPostJwt,
+
+    /// Authorization Response parameters are packed in a signed JWT, using
+    /// [`ResponseMode::QueryJwt`] or [`ResponseMode::FragmentJwt`] depending on
+    /// the response type.
+    ///
+    /// Defined in [JWT Secured Authorization Response Mode for OAuth 2.0 (JARM)](https://openid.net/specs/oauth-v2-jarm.html).
+    Jwt,
+
     /// An unknown value.
     Unknown(String),
 }
@@ -60,6 +89,10 @@ impl core::fmt::Display for ResponseMode {
             ResponseMode::Query => f.write_str("query"),
             ResponseMode::Fragment => f.write_str("fragment"),
             ResponseMode::FormPost => f.write_str("form_post"),
+            ResponseMode::QueryJwt => f.write_str("query.jwt"),
+            ResponseMode::FragmentJwt => f.write_str("fragment.jwt"),
+            ResponseMode::FormPostJwt => f.write_str("form_post.jwt"),
+            ResponseMode::Jwt => f.write_str("jwt"),
             ResponseMode::Unknown(s) => f.write_str(s),
         }
     }
@@ -73,11 +106,27 @@ impl core::str::FromStr for ResponseMode {
             "query" => Ok(ResponseMode::Query),
             "fragment" => Ok(ResponseMode::Fragment),
             "form_post" => Ok(ResponseMode::FormPost),
+            "query.jwt" => Ok(ResponseMode::QueryJwt),
+            "fragment.jwt" => Ok(ResponseMode::FragmentJwt),
+            "form_post.jwt" => Ok(ResponseMode::FormPostJwt),
+            "jwt" => Ok(ResponseMode::Jwt),
             s => Ok(ResponseMode::Unknown(s.to_owned())),
         }
     }
 }
 
+impl ResponseMode {
+    /// Whether the Authorization Response parameters are packed in a signed
+    /// JWT
+    #[must_use]
+    pub fn is_jwt(&self) -> bool {
+        matches!(
+            self,
+            Self::QueryJwt | Self::FragmentJwt | Self::FormPostJwt | Self::Jwt
+        )
+    }
+}
+
 /// Value that specifies how the Authorization Server displays the
 /// authentication and consent user interface pages to the End-User.
 ///
@@ -288,6 +337,13 @@ pub struct AuthorizationRequest {
     #[serde(default)]
     pub acr_values: Option<HashSet<String>>,
 
+    /// The claims the client requests individually, in the userinfo response
+    /// or in the ID token.
+    ///
+    /// This is sent as a JSON-encoded object.
+    #[serde_as(as = "Option<serde_with::json::JsonString>")]
+    pub claims: Option<ClaimsRequest>,
+
     /// A JWT that contains the request's parameter values, called a [Request
     /// Object].
     ///
@@ -306,6 +362,17 @@ pub struct AuthorizationRequest {
     ///
     /// [Self-Issued OpenID Provider]: https://openid.net/specs/openid-connect-core-1_0.html#SelfIssued
     pub registration: Option<String>,
+
+    /// The fine-grained permissions requested by the client, as defined in
+    /// [RFC9396](https://www.rfc-editor.org/rfc/rfc9396).
+    ///
+    /// This is sent as a JSON-encoded array.
+    #[serde_as(as = "Option<serde_with::json::JsonString>")]
+    pub authorization_details: Option<Vec<AuthorizationDetail>>,
+
+    /// The resource the client wants to access with the requested tokens, as
+    /// defined in [RFC8707](https://www.rfc-editor.org/rfc/rfc8707).
+    pub resource: Option<Url>,
 }
 
 impl AuthorizationRequest {
@@ -327,9 +394,12 @@ impl AuthorizationRequest {
             id_token_hint: None,
             login_hint: None,
             acr_values: None,
+            claims: None,
             request: None,
             request_uri: None,
             registration: None,
+            authorization_details: None,
+            resource: None,
         }
     }
 }
@@ -347,9 +417,12 @@ impl fmt::Debug for AuthorizationRequest {
             .field("ui_locales", &self.ui_locales)
             .field("login_hint", &self.login_hint)
             .field("acr_values", &self.acr_values)
+            .field("claims", &self.claims)
             .field("request", &self.request)
             .field("request_uri", &self.request_uri)
             .field("registration", &self.registration)
+            .field("authorization_details", &self.authorization_details)
+            .field("resource", &self.resource)
             .finish_non_exhaustive()
     }
 }
@@ -459,11 +532,88 @@ impl fmt::Debug for DeviceAuthorizationResponse {
     }
 }
 
+/// A request to the [Backchannel Authentication Endpoint].
+///
+/// Only the `login_hint` parameter is supported to identify the end-user.
+///
+/// [Backchannel Authentication Endpoint]: https://openid.net/specs/openid-client-initiated-backchannel-authentication-core-1_0.html#auth_request
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BackchannelAuthenticationRequest {
+    /// The scope of the access request. It must contain the `openid` scope.
+    pub scope: Scope,
+
+    /// A bearer token used by the authorization server to authenticate the
+    /// notification it sends to the client, required in the `ping` token
+    /// delivery mode.
+    pub client_notification_token: Option<String>,
+
+    /// A hint identifying the end-user for whom authentication is requested.
+    pub login_hint: Option<String>,
+
+    /// A short message displayed both on the client and on the
+    /// authentication device, which lets the end-user check that they are
+    /// approving the right request.
+    pub binding_message: Option<String>,
+
+    /// The requested lifetime of the `auth_req_id`, in seconds.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub requested_expiry: Option<u32>,
+}
+
+impl fmt::Debug for BackchannelAuthenticationRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackchannelAuthenticationRequest")
+            .field("scope", &self.scope)
+            .field("login_hint", &self.login_hint)
+            .field("binding_message", &self.binding_message)
+            .field("requested_expiry", &self.requested_expiry)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The default value of the `interval` between polling requests to the token
+/// endpoint in the backchannel authentication flow.
+pub const DEFAULT_BACKCHANNEL_AUTHENTICATION_INTERVAL: Duration =
+    Duration::microseconds(5 * 1000 * 1000);
+
+/// A successful response from the [Backchannel Authentication Endpoint].
+///
+/// [Backchannel Authentication Endpoint]: https://openid.net/specs/openid-client-initiated-backchannel-authentication-core-1_0.html#successful_authentication_request_acknowdlegment
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BackchannelAuthenticationResponse {
+    /// The identifier of the authentication request, used by the client to
+    /// get the tokens from the token endpoint.
+    pub auth_req_id: String,
+
+    /// The lifetime of the `auth_req_id`.
+    #[serde_as(as = "DurationSeconds<i64>")]
+    pub expires_in: Duration,
+
+    /// The minimum amount of time in seconds that the client should wait
+    /// between polling requests to the token endpoint.
+    #[serde_as(as = "Option<DurationSeconds<i64>>")]
+    pub interval: Option<Duration>,
+}
+
+impl fmt::Debug for BackchannelAuthenticationResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackchannelAuthenticationResponse")
+            .field("expires_in", &self.expires_in)
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
 /// A request to the [Token Endpoint] for the [Authorization Code] grant type.
 ///
 /// [Token Endpoint]: https://www.rfc-editor.org/rfc/rfc6749#section-3.2
 /// [Authorization Code]: https://www.rfc-editor.org/rfc/rfc6749#section-4.1
 #[skip_serializing_none]
+#[serde_as]
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct AuthorizationCodeGrant {
     /// The authorization code that was returned from the authorization
@@ -480,12 +630,27 @@ pub struct AuthorizationCodeGrant {
     /// authorization endpoint.
     // TODO: move this somehow in the pkce module
     pub code_verifier: Option<String>,
+
+    /// The authorization details the access token should be restricted to.
+    ///
+    /// They must be a subset of the authorization details which were granted
+    /// by the resource owner.
+    #[serde_as(as = "Option<serde_with::json::JsonString>")]
+    pub authorization_details: Option<Vec<AuthorizationDetail>>,
+
+    /// The resource the access token should be issued for.
+    ///
+    /// It must match the resource which was included in the authorization
+    /// request, if any.
+    pub resource: Option<Url>,
 }
 
 impl fmt::Debug for AuthorizationCodeGrant {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("AuthorizationCodeGrant")
             .field("redirect_uri", &self.redirect_uri)
+            .field("authorization_details", &self.authorization_details)
+            .field("resource", &self.resource)
             .finish_non_exhaustive()
     }
 }
@@ -506,12 +671,18 @@ pub struct RefreshTokenGrant {
     /// the resource owner, and if omitted is treated as equal to the scope
     /// originally granted by the resource owner.
     pub scope: Option<Scope>,
+
+    /// The resource the access token should be issued for.
+    ///
+    /// It must match the resource the session is restricted to, if any.
+    pub resource: Option<Url>,
 }
 
 impl fmt::Debug for RefreshTokenGrant {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("RefreshTokenGrant")
             .field("scope", &self.scope)
+            .field("resource", &self.resource)
             .finish_non_exhaustive()
     }
 }
@@ -520,10 +691,45 @@ impl fmt::Debug for RefreshTokenGrant {
 ///
 /// [Token Endpoint]: https://www.rfc-editor.org/rfc/rfc6749#section-3.2
 /// [Client Credentials]: https://www.rfc-editor.org/rfc/rfc6749#section-4.4
+#[skip_serializing_none]
+#[serde_as]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct ClientCredentialsGrant {
     /// The scope of the access request.
     pub scope: Option<Scope>,
+
+    /// The fine-grained permissions requested by the client.
+    #[serde_as(as = "Option<serde_with::json::JsonString>")]
+    pub authorization_details: Option<Vec<AuthorizationDetail>>,
+
+    /// The resource the access token should be issued for.
+    pub resource: Option<Url>,
+}
+
+/// A request to the [Token Endpoint] for the [Resource Owner Password
+/// Credentials] grant type.
+///
+/// [Token Endpoint]: https://www.rfc-editor.org/rfc/rfc6749#section-3.2
+/// [Resource Owner Password Credentials]: https://www.rfc-editor.org/rfc/rfc6749#section-4.3
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ResourceOwnerPasswordCredentialsGrant {
+    /// The username of the resource owner.
+    pub username: String,
+
+    /// The password of the resource owner.
+    pub password: String,
+
+    /// The scope of the access request.
+    pub scope: Option<Scope>,
+}
+
+impl fmt::Debug for ResourceOwnerPasswordCredentialsGrant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResourceOwnerPasswordCredentialsGrant")
+            .field("username", &self.username)
+            .field("scope", &self.scope)
+            .finish_non_exhaustive()
+    }
 }
 
 /// A request to the [Token Endpoint] for the [Device Authorization] grant type.
@@ -542,6 +748,24 @@ impl fmt::Debug for DeviceCodeGrant {
     }
 }
 
+/// A request to the [Token Endpoint] for the [Client-Initiated Backchannel
+/// Authentication] grant type.
+///
+/// [Token Endpoint]: https://www.rfc-editor.org/rfc/rfc6749#section-3.2
+/// [Client-Initiated Backchannel Authentication]: https://openid.net/specs/openid-client-initiated-backchannel-authentication-core-1_0.html#token_request
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BackchannelAuthenticationGrant {
+    /// The identifier returned by the backchannel authentication endpoint.
+    pub auth_req_id: String,
+}
+
+impl fmt::Debug for BackchannelAuthenticationGrant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackchannelAuthenticationGrant")
+            .finish_non_exhaustive()
+    }
+}
+
 /// All possible values for the `grant_type` parameter.
 #[derive(
     Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, SerializeDisplay, DeserializeFromStr,
@@ -629,10 +853,17 @@ pub enum AccessTokenRequest {
     /// A request in the Client Credentials flow.
     ClientCredentials(ClientCredentialsGrant),
 
+    /// A request in the Resource Owner Password Credentials flow.
+    Password(ResourceOwnerPasswordCredentialsGrant),
+
     /// A request in the Device Code flow.
     #[serde(rename = "urn:ietf:params:oauth:grant-type:device_code")]
     DeviceCode(DeviceCodeGrant),
 
+    /// A request in the Client-Initiated Backchannel Authentication flow.
+    #[serde(rename = "urn:openid:params:grant-type:ciba")]
+    BackchannelAuthentication(BackchannelAuthenticationGrant),
+
     /// An unsupported request.
     #[serde(skip_serializing, other)]
     Unsupported,
@@ -646,7 +877,9 @@ impl AccessTokenRequest {
             Self::AuthorizationCode(_) => "authorization_code",
             Self::RefreshToken(_) => "refresh_token",
             Self::ClientCredentials(_) => "client_credentials",
+            Self::Password(_) => "password",
             Self::DeviceCode(_) => "urn:ietf:params:oauth:grant-type:device_code",
+            Self::BackchannelAuthentication(_) => "urn:openid:params:grant-type:ciba",
             Self::Unsupported => "unsupported",
         }
     }
@@ -808,6 +1041,38 @@ pub struct IntrospectionResponse {
 
     /// MAS extension: explicit device ID
     pub device_id: Option<String>,
+
+    /// Confirmation of the key the token is bound to, as defined in
+    /// [RFC9449](https://www.rfc-editor.org/rfc/rfc9449#section-6).
+    pub cnf: Option<Confirmation>,
+
+    /// The authorization details granted to the token, as defined in
+    /// [RFC9396](https://www.rfc-editor.org/rfc/rfc9396#section-9.2).
+    pub authorization_details: Option<Vec<AuthorizationDetail>>,
+
+    /// The party acting on behalf of the subject, as defined in
+    /// [RFC8693](https://www.rfc-editor.org/rfc/rfc8693#section-4.1).
+    pub act: Option<Actor>,
+}
+
+/// The confirmation method of a sender-constrained token.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Confirmation {
+    /// The JWK SHA-256 thumbprint of the DPoP key the token is bound to.
+    pub jkt: Option<String>,
+}
+
+/// The party acting on behalf of the subject of a token, as defined in
+/// [RFC8693](https://www.rfc-editor.org/rfc/rfc8693#section-4.1).
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Actor {
+    /// The subject identifier of the actor, if it is a user.
+    pub sub: Option<String>,
+
+    /// The client the actor used.
+    pub client_id: Option<String>,
 }
 
 /// A request to the [Revocation Endpoint].
@@ -872,6 +1137,7 @@ mod tests {
         let req = AccessTokenRequest::RefreshToken(RefreshTokenGrant {
             refresh_token: "abcd".into(),
             scope,
+            resource: None,
         });
 
         assert_serde_json(&req, expected);
@@ -889,6 +1155,42 @@ mod tests {
             code: "abcd".into(),
             redirect_uri: Some("https://example.com/redirect".parse().unwrap()),
             code_verifier: None,
+            authorization_details: None,
+            resource: None,
+        });
+
+        assert_serde_json(&req, expected);
+    }
+
+    #[test]
+    fn serde_password_grant() {
+        let expected = json!({
+            "grant_type": "password",
+            "username": "alice",
+            "password": "hunter2",
+            "scope": "openid",
+        });
+
+        let scope: Option<Scope> = Some(vec![OPENID].into_iter().collect());
+
+        let req = AccessTokenRequest::Password(ResourceOwnerPasswordCredentialsGrant {
+            username: "alice".into(),
+            password: "hunter2".into(),
+            scope,
+        });
+
+        assert_serde_json(&req, expected);
+    }
+
+    #[test]
+    fn serde_backchannel_authentication_grant() {
+        let expected = json!({
+            "grant_type": "urn:openid:params:grant-type:ciba",
+            "auth_req_id": "abcd",
+        });
+
+        let req = AccessTokenRequest::BackchannelAuthentication(BackchannelAuthenticationGrant {
+            auth_req_id: "abcd".into(),
         });
 
         assert_serde_json(&req, expected);
@@ -973,6 +1275,22 @@ mod tests {
             serde_json::to_string(&ResponseMode::FormPost).unwrap(),
             "\"form_post\""
         );
+        assert_eq!(
+            serde_json::to_string(&ResponseMode::QueryJwt).unwrap(),
+            "\"query.jwt\""
+        );
+        assert_eq!(
+            serde_json::to_string(&ResponseMode::FragmentJwt).unwrap(),
+            "\"fragment.jwt\""
+        );
+        assert_eq!(
+            serde_json::to_string(&ResponseMode::FormPostJwt).unwrap(),
+            "\"form_post.jwt\""
+        );
+        assert_eq!(
+            serde_json::to_string(&ResponseMode::Jwt).unwrap(),
+            "\"jwt\""
+        );
     }
 
     #[test]
@@ -989,6 +1307,22 @@ mod tests {
             serde_json::from_str::<ResponseMode>("\"form_post\"").unwrap(),
             ResponseMode::FormPost
         );
+        assert_eq!(
+            serde_json::from_str::<ResponseMode>("\"query.jwt\"").unwrap(),
+            ResponseMode::QueryJwt
+        );
+        assert_eq!(
+            serde_json::from_str::<ResponseMode>("\"fragment.jwt\"").unwrap(),
+            ResponseMode::FragmentJwt
+        );
+        assert_eq!(
+            serde_json::from_str::<ResponseMode>("\"form_post.jwt\"").unwrap(),
+            ResponseMode::FormPostJwt
+        );
+        assert_eq!(
+            serde_json::from_str::<ResponseMode>("\"jwt\"").unwrap(),
+            ResponseMode::Jwt
+        );
     }
 
     #[test]
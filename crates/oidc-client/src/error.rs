@@ -90,6 +90,19 @@ pub enum TokenRequestError {
     Credentials(#[from] CredentialsError),
 }
 
+impl TokenRequestError {
+    /// Whether the provider couldn't be reached or failed to handle the
+    /// request, as opposed to refusing it
+    #[must_use]
+    pub fn is_provider_failure(&self) -> bool {
+        match self {
+            Self::Http(_) => true,
+            Self::OAuth2(error) => error.is_provider_failure(),
+            Self::Credentials(_) => false,
+        }
+    }
+}
+
 /// All possible errors when exchanging a code for an access token.
 #[derive(Debug, Error)]
 pub enum TokenAuthorizationCodeError {
@@ -114,6 +127,25 @@ pub enum TokenRefreshError {
     IdToken(#[from] IdTokenError),
 }
 
+impl TokenRefreshError {
+    /// Whether the provider refused the refresh token itself, with an
+    /// `invalid_grant` error
+    #[must_use]
+    pub fn is_invalid_grant(&self) -> bool {
+        matches!(
+            self,
+            Self::Token(TokenRequestError::OAuth2(error)) if error.error() == Some("invalid_grant")
+        )
+    }
+
+    /// Whether the provider couldn't be reached or failed to handle the
+    /// request, as opposed to refusing it
+    #[must_use]
+    pub fn is_provider_failure(&self) -> bool {
+        matches!(self, Self::Token(error) if error.is_provider_failure())
+    }
+}
+
 /// All possible errors when requesting user info.
 #[derive(Debug, Error)]
 pub enum UserInfoError {
@@ -147,6 +179,19 @@ pub enum UserInfoError {
     OAuth2(#[from] OAuth2Error),
 }
 
+impl UserInfoError {
+    /// Whether the provider couldn't be reached or failed to handle the
+    /// request, as opposed to refusing it
+    #[must_use]
+    pub fn is_provider_failure(&self) -> bool {
+        match self {
+            Self::Http(_) => true,
+            Self::OAuth2(error) => error.is_provider_failure(),
+            _ => false,
+        }
+    }
+}
+
 /// All possible errors when requesting a JWKS.
 #[derive(Debug, Error)]
 #[error("Failed to fetch JWKS")]
@@ -215,8 +260,8 @@ pub enum CredentialsError {
     UnsupportedMethod,
 
     /// When authenticationg with `private_key_jwt`, no private key was found
-    /// for the given algorithm.
-    #[error("no private key was found for the given algorithm")]
+    /// for the given algorithm and key ID.
+    #[error("no private key was found for the given algorithm and key ID")]
     NoPrivateKeyFound,
 
     /// The signing algorithm is invalid for this authentication method.
@@ -281,6 +326,25 @@ impl std::fmt::Display for OAuth2Error {
     }
 }
 
+impl OAuth2Error {
+    /// The error code returned by the provider, if any
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_ref().map(|error| error.error.as_str())
+    }
+
+    /// Whether the provider couldn't be reached, answered with a server error,
+    /// or didn't answer with a proper error response
+    #[must_use]
+    pub fn is_provider_failure(&self) -> bool {
+        self.error.is_none()
+            || self
+                .inner
+                .status()
+                .is_some_and(|status| status.is_server_error())
+    }
+}
+
 impl From<reqwest::Error> for OAuth2Error {
     fn from(inner: reqwest::Error) -> Self {
         Self { error: None, inner }
@@ -280,6 +280,8 @@ fn build_authorization_request(
             request: None,
             request_uri: None,
             registration: None,
+            authorization_details: None,
+            resource: None,
         },
         pkce,
     };
@@ -415,6 +417,8 @@ pub async fn access_token_with_authorization_code(
             code: code.clone(),
             redirect_uri: Some(validation_data.redirect_uri),
             code_verifier: validation_data.code_challenge_verifier,
+            authorization_details: None,
+            resource: None,
         }),
         now,
         rng,
@@ -10,10 +10,13 @@ use std::{collections::HashMap, fmt};
 
 use base64ct::{Base64UrlUnpadded, Encoding};
 use chrono::{DateTime, Duration, Utc};
-use mas_iana::{jose::JsonWebSignatureAlg, oauth::OAuthClientAuthenticationMethod};
+use mas_iana::{
+    jose::{JsonWebKeyUse, JsonWebSignatureAlg},
+    oauth::OAuthClientAuthenticationMethod,
+};
 use mas_jose::{
     claims::{self, ClaimError},
-    constraints::Constrainable,
+    constraints::{Constrainable, Constraint, ConstraintSet},
     jwa::{AsymmetricSigningKey, SymmetricKey},
     jwt::{JsonWebSignatureHeader, Jwt},
 };
@@ -91,6 +94,11 @@ pub enum ClientCredentials {
         /// The keystore used to sign the JWT
         keystore: Keystore,
 
+        /// The ID of the key of the keystore used to sign the JWT.
+        ///
+        /// If this is `None`, any key compatible with the algorithm is used.
+        key_id: Option<String>,
+
         /// The algorithm used to sign the JWT.
         signing_algorithm: JsonWebSignatureAlg,
 
@@ -205,15 +213,26 @@ impl ClientCredentials {
             ClientCredentials::PrivateKeyJwt {
                 client_id,
                 keystore,
+                key_id,
                 signing_algorithm,
                 token_endpoint,
             } => {
                 let claims =
                     prepare_claims(client_id.clone(), token_endpoint.to_string(), now, rng)?;
 
-                let key = keystore
-                    .signing_key_for_algorithm(signing_algorithm)
-                    .ok_or(CredentialsError::NoPrivateKeyFound)?;
+                let key = if let Some(key_id) = key_id {
+                    let constraints = ConstraintSet::new([
+                        Constraint::alg(signing_algorithm),
+                        Constraint::use_(&JsonWebKeyUse::Sig),
+                    ]);
+                    keystore
+                        .find_keys(&constraints)
+                        .into_iter()
+                        .find(|key| key.kid() == Some(key_id.as_str()))
+                } else {
+                    keystore.signing_key_for_algorithm(signing_algorithm)
+                }
+                .ok_or(CredentialsError::NoPrivateKeyFound)?;
                 let signer = key
                     .params()
                     .signing_key_for_alg(signing_algorithm)
@@ -300,12 +319,14 @@ impl fmt::Debug for ClientCredentials {
                 .finish_non_exhaustive(),
             Self::PrivateKeyJwt {
                 client_id,
+                key_id,
                 signing_algorithm,
                 token_endpoint,
                 ..
             } => f
                 .debug_struct("PrivateKeyJwt")
                 .field("client_id", client_id)
+                .field("key_id", key_id)
                 .field("signing_algorithm", signing_algorithm)
                 .field("token_endpoint", token_endpoint)
                 .finish_non_exhaustive(),
@@ -144,6 +144,7 @@ fn client_credentials(
             ClientCredentials::PrivateKeyJwt {
                 client_id: CLIENT_ID.to_owned(),
                 keystore: keystore(&signing_algorithm),
+                key_id: None,
                 signing_algorithm,
                 token_endpoint: issuer.join("token").unwrap(),
             }
@@ -12,7 +12,8 @@
 use std::path::{Path, PathBuf};
 
 use mas_policy::model::{
-    AuthorizationGrantInput, ClientRegistrationInput, EmailInput, RegisterInput,
+    AuthorizationGrantInput, ClientRegistrationInput, DisplayNameInput, EmailInput, LoginInput,
+    RegisterInput, UpstreamLoginInput,
 };
 use schemars::{JsonSchema, r#gen::SchemaSettings};
 
@@ -47,4 +48,7 @@ fn main() {
     write_schema::<ClientRegistrationInput>(output_root, "client_registration_input.json");
     write_schema::<AuthorizationGrantInput>(output_root, "authorization_grant_input.json");
     write_schema::<EmailInput>(output_root, "email_input.json");
+    write_schema::<LoginInput>(output_root, "login_input.json");
+    write_schema::<DisplayNameInput>(output_root, "display_name_input.json");
+    write_schema::<UpstreamLoginInput>(output_root, "upstream_login_input.json");
 }
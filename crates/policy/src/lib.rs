@@ -18,8 +18,9 @@ use thiserror::Error;
 use tokio::io::{AsyncRead, AsyncReadExt};
 
 pub use self::model::{
-    AuthorizationGrantInput, ClientRegistrationInput, Code as ViolationCode, EmailInput,
-    EvaluationResult, GrantType, RegisterInput, RegistrationMethod, Requester, Violation,
+    AuthorizationGrantInput, ClientRegistrationInput, Code as ViolationCode, DisplayNameInput,
+    EmailInput, EvaluationResult, GrantType, LoginInput, LoginLocation, RecentSession,
+    RegisterInput, RegistrationMethod, Requester, UpstreamLoginInput, Violation,
 };
 
 #[derive(Debug, Error)]
@@ -72,15 +73,21 @@ pub struct Entrypoints {
     pub client_registration: String,
     pub authorization_grant: String,
     pub email: String,
+    pub login: String,
+    pub display_name: String,
+    pub upstream_login: String,
 }
 
 impl Entrypoints {
-    fn all(&self) -> [&str; 4] {
+    fn all(&self) -> [&str; 7] {
         [
             self.register.as_str(),
             self.client_registration.as_str(),
             self.authorization_grant.as_str(),
             self.email.as_str(),
+            self.login.as_str(),
+            self.display_name.as_str(),
+            self.upstream_login.as_str(),
         ]
     }
 }
@@ -355,6 +362,63 @@ impl Policy {
         Ok(res)
     }
 
+    #[tracing::instrument(
+        name = "policy.evaluate.login",
+        skip_all,
+        fields(
+            %input.user.id,
+        ),
+    )]
+    pub async fn evaluate_login(
+        &mut self,
+        input: LoginInput<'_>,
+    ) -> Result<EvaluationResult, EvaluationError> {
+        let [res]: [EvaluationResult; 1] = self
+            .instance
+            .evaluate(&mut self.store, &self.entrypoints.login, &input)
+            .await?;
+
+        Ok(res)
+    }
+
+    #[tracing::instrument(
+        name = "policy.evaluate.display_name",
+        skip_all,
+        fields(
+            input.display_name = input.display_name,
+        ),
+    )]
+    pub async fn evaluate_display_name(
+        &mut self,
+        input: DisplayNameInput<'_>,
+    ) -> Result<EvaluationResult, EvaluationError> {
+        let [res]: [EvaluationResult; 1] = self
+            .instance
+            .evaluate(&mut self.store, &self.entrypoints.display_name, &input)
+            .await?;
+
+        Ok(res)
+    }
+
+    #[tracing::instrument(
+        name = "policy.evaluate.upstream_login",
+        skip_all,
+        fields(
+            input.provider_id = %input.provider_id,
+        ),
+    )]
+    pub async fn evaluate_upstream_login(
+        &mut self,
+        input: UpstreamLoginInput<'_>,
+    ) -> Result<EvaluationResult, EvaluationError> {
+        let [res]: [EvaluationResult; 1] = self
+            .instance
+            .evaluate(&mut self.store, &self.entrypoints.upstream_login, &input)
+            .await?;
+
+        Ok(res)
+    }
+
     #[tracing::instrument(
         name = "policy.evaluate.register",
         skip_all,
@@ -446,6 +510,9 @@ mod tests {
             client_registration: "client_registration/violation".to_owned(),
             authorization_grant: "authorization_grant/violation".to_owned(),
             email: "email/violation".to_owned(),
+            login: "login/violation".to_owned(),
+            display_name: "display_name/violation".to_owned(),
+            upstream_login: "upstream_login/violation".to_owned(),
         };
 
         let factory = PolicyFactory::load(file, data, entrypoints).await.unwrap();
@@ -513,6 +580,9 @@ mod tests {
             client_registration: "client_registration/violation".to_owned(),
             authorization_grant: "authorization_grant/violation".to_owned(),
             email: "email/violation".to_owned(),
+            login: "login/violation".to_owned(),
+            display_name: "display_name/violation".to_owned(),
+            upstream_login: "upstream_login/violation".to_owned(),
         };
 
         let factory = PolicyFactory::load(file, data, entrypoints).await.unwrap();
@@ -582,6 +652,9 @@ mod tests {
             client_registration: "client_registration/violation".to_owned(),
             authorization_grant: "authorization_grant/violation".to_owned(),
             email: "email/violation".to_owned(),
+            login: "login/violation".to_owned(),
+            display_name: "display_name/violation".to_owned(),
+            upstream_login: "upstream_login/violation".to_owned(),
         };
 
         let factory = PolicyFactory::load(file, data, entrypoints).await.unwrap();
@@ -9,9 +9,9 @@
 //! This is useful to generate JSON schemas for each input type, which can then
 //! be type-checked by Open Policy Agent.
 
-use std::net::IpAddr;
+use std::{collections::BTreeSet, net::IpAddr};
 
-use mas_data_model::{Client, User};
+use mas_data_model::{Client, Ulid, User};
 use oauth2_types::{registration::VerifiedClientMetadata, scope::Scope};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -49,6 +49,28 @@ pub enum Code {
 
     /// The email address is banned.
     EmailBanned,
+
+    /// The login comes from a country the user wasn't recently active from.
+    LoginNewCountry,
+
+    /// The login comes from an anonymizing network, like a VPN or Tor.
+    LoginAnonymizingNetwork,
+
+    /// The login comes from a country too far from where the user was just
+    /// active.
+    LoginImpossibleTravel,
+
+    /// The display name is too long.
+    DisplayNameTooLong,
+
+    /// The display name is banned.
+    DisplayNameBanned,
+
+    /// A claim required from the upstream provider is missing.
+    UpstreamClaimMissing,
+
+    /// A claim from the upstream provider doesn't have an allowed value.
+    UpstreamClaimNotAllowed,
 }
 
 impl Code {
@@ -66,6 +88,13 @@ impl Code {
             Self::EmailDomainBanned => "email-domain-banned",
             Self::EmailNotAllowed => "email-not-allowed",
             Self::EmailBanned => "email-banned",
+            Self::LoginNewCountry => "login-new-country",
+            Self::LoginAnonymizingNetwork => "login-anonymizing-network",
+            Self::LoginImpossibleTravel => "login-impossible-travel",
+            Self::DisplayNameTooLong => "display-name-too-long",
+            Self::DisplayNameBanned => "display-name-banned",
+            Self::UpstreamClaimMissing => "upstream-claim-missing",
+            Self::UpstreamClaimNotAllowed => "upstream-claim-not-allowed",
         }
     }
 }
@@ -157,8 +186,11 @@ pub struct ClientRegistrationInput<'a> {
 pub enum GrantType {
     AuthorizationCode,
     ClientCredentials,
+    Password,
     #[serde(rename = "urn:ietf:params:oauth:grant-type:device_code")]
     DeviceCode,
+    #[serde(rename = "urn:openid:params:grant-type:ciba")]
+    ClientInitiatedBackchannelAuthentication,
 }
 
 /// Input for the authorization grant policy.
@@ -168,6 +200,9 @@ pub struct AuthorizationGrantInput<'a> {
     #[schemars(with = "Option<std::collections::HashMap<String, serde_json::Value>>")]
     pub user: Option<&'a User>,
 
+    /// The verified email addresses of the user
+    pub emails: Vec<&'a str>,
+
     #[schemars(with = "std::collections::HashMap<String, serde_json::Value>")]
     pub client: &'a Client,
 
@@ -176,6 +211,11 @@ pub struct AuthorizationGrantInput<'a> {
 
     pub grant_type: GrantType,
 
+    /// The claims the client requested individually with the `claims`
+    /// parameter
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub requested_claims: BTreeSet<&'a str>,
+
     pub requester: Requester,
 }
 
@@ -187,3 +227,80 @@ pub struct EmailInput<'a> {
 
     pub requester: Requester,
 }
+
+/// Input for the display name change policy.
+#[derive(Serialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct DisplayNameInput<'a> {
+    pub display_name: &'a str,
+
+    pub requester: Requester,
+}
+
+/// Input for the upstream login policy, evaluated with what the upstream
+/// provider asserted, before the user is logged in or their account is created
+/// or linked.
+#[derive(Serialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct UpstreamLoginInput<'a> {
+    /// The ID of the upstream provider
+    #[schemars(with = "String")]
+    pub provider_id: Ulid,
+
+    /// The claims of the ID token returned by the provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<std::collections::HashMap<String, serde_json::Value>>")]
+    pub id_token_claims: Option<&'a serde_json::Map<String, serde_json::Value>>,
+
+    /// The claims returned by the userinfo endpoint of the provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<std::collections::HashMap<String, serde_json::Value>>")]
+    pub userinfo_claims: Option<&'a serde_json::Value>,
+
+    pub requester: Requester,
+}
+
+/// Where a login comes from, as resolved from its IP address.
+#[derive(Serialize, Debug, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct LoginLocation<'a> {
+    /// The ISO 3166-1 alpha-2 code of the country
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<&'a str>,
+
+    /// The number of the autonomous system
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asn: Option<u32>,
+
+    /// The name of the organization owning the autonomous system
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub as_organization: Option<&'a str>,
+}
+
+/// A session of the user which was recently active.
+#[derive(Serialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct RecentSession<'a> {
+    /// The ISO 3166-1 alpha-2 code of the country the session was last active
+    /// from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<&'a str>,
+
+    /// How many seconds ago the session was last active
+    pub inactive_for: i64,
+}
+
+/// Input for the login policy, evaluated once the user checked their password.
+#[derive(Serialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct LoginInput<'a> {
+    #[schemars(with = "std::collections::HashMap<String, serde_json::Value>")]
+    pub user: &'a User,
+
+    pub location: LoginLocation<'a>,
+
+    /// The most recently active sessions of the user
+    pub recent_sessions: Vec<RecentSession<'a>>,
+
+    pub requester: Requester,
+}
@@ -19,6 +19,9 @@ pub enum PostAuthAction {
     ContinueDeviceCodeGrant {
         id: Ulid,
     },
+    ContinueBackchannelAuthentication {
+        id: Ulid,
+    },
     ContinueCompatSsoLogin {
         id: Ulid,
     },
@@ -43,6 +46,11 @@ impl PostAuthAction {
         PostAuthAction::ContinueDeviceCodeGrant { id }
     }
 
+    #[must_use]
+    pub const fn continue_backchannel_authentication(id: Ulid) -> Self {
+        PostAuthAction::ContinueBackchannelAuthentication { id }
+    }
+
     #[must_use]
     pub const fn continue_compat_sso_login(id: Ulid) -> Self {
         PostAuthAction::ContinueCompatSsoLogin { id }
@@ -64,6 +72,9 @@ impl PostAuthAction {
             Self::ContinueDeviceCodeGrant { id } => {
                 url_builder.redirect(&DeviceCodeConsent::new(*id))
             }
+            Self::ContinueBackchannelAuthentication { id } => {
+                url_builder.redirect(&BackchannelAuthenticationConsent::new(*id))
+            }
             Self::ContinueCompatSsoLogin { id } => {
                 url_builder.redirect(&CompatLoginSsoComplete::new(*id, None))
             }
@@ -139,6 +150,14 @@ impl SimpleRoute for OAuth2TokenEndpoint {
     const PATH: &'static str = "/oauth2/token";
 }
 
+/// `POST /oauth2/par`
+#[derive(Default, Debug, Clone)]
+pub struct OAuth2PushedAuthorizationRequestEndpoint;
+
+impl SimpleRoute for OAuth2PushedAuthorizationRequestEndpoint {
+    const PATH: &'static str = "/oauth2/par";
+}
+
 /// `POST /oauth2/registration`
 #[derive(Default, Debug, Clone)]
 pub struct OAuth2RegistrationEndpoint;
@@ -155,6 +174,14 @@ impl SimpleRoute for OAuth2AuthorizationEndpoint {
     const PATH: &'static str = "/authorize";
 }
 
+/// `GET|POST /oauth2/logout`
+#[derive(Default, Debug, Clone)]
+pub struct OAuth2EndSessionEndpoint;
+
+impl SimpleRoute for OAuth2EndSessionEndpoint {
+    const PATH: &'static str = "/oauth2/logout";
+}
+
 /// `GET /`
 #[derive(Default, Debug, Clone)]
 pub struct Index;
@@ -171,6 +198,14 @@ impl SimpleRoute for Healthcheck {
     const PATH: &'static str = "/health";
 }
 
+/// `GET /captcha/challenge`
+#[derive(Default, Debug, Clone)]
+pub struct CaptchaChallenge;
+
+impl SimpleRoute for CaptchaChallenge {
+    const PATH: &'static str = "/captcha/challenge";
+}
+
 /// `GET|POST /login`
 #[derive(Default, Debug, Clone)]
 pub struct Login {
@@ -211,6 +246,13 @@ impl Login {
         }
     }
 
+    #[must_use]
+    pub const fn and_continue_backchannel_authentication(id: Ulid) -> Self {
+        Self {
+            post_auth_action: Some(PostAuthAction::continue_backchannel_authentication(id)),
+        }
+    }
+
     #[must_use]
     pub const fn and_continue_compat_sso_login(id: Ulid) -> Self {
         Self {
@@ -245,6 +287,245 @@ impl From<Option<PostAuthAction>> for Login {
     }
 }
 
+/// `POST /login/passkey`
+#[derive(Default, Debug, Clone)]
+pub struct PasskeyLogin {
+    post_auth_action: Option<PostAuthAction>,
+}
+
+impl Route for PasskeyLogin {
+    type Query = PostAuthAction;
+
+    fn route() -> &'static str {
+        "/login/passkey"
+    }
+
+    fn query(&self) -> Option<&Self::Query> {
+        self.post_auth_action.as_ref()
+    }
+}
+
+impl From<Option<PostAuthAction>> for PasskeyLogin {
+    fn from(post_auth_action: Option<PostAuthAction>) -> Self {
+        Self { post_auth_action }
+    }
+}
+
+/// `POST /login/discover`
+#[derive(Default, Debug, Clone)]
+pub struct LoginDiscover {
+    post_auth_action: Option<PostAuthAction>,
+}
+
+impl Route for LoginDiscover {
+    type Query = PostAuthAction;
+
+    fn route() -> &'static str {
+        "/login/discover"
+    }
+
+    fn query(&self) -> Option<&Self::Query> {
+        self.post_auth_action.as_ref()
+    }
+}
+
+impl From<Option<PostAuthAction>> for LoginDiscover {
+    fn from(post_auth_action: Option<PostAuthAction>) -> Self {
+        Self { post_auth_action }
+    }
+}
+
+/// `GET|POST /login/totp`
+#[derive(Default, Debug, Clone)]
+pub struct LoginTotp {
+    post_auth_action: Option<PostAuthAction>,
+}
+
+impl Route for LoginTotp {
+    type Query = PostAuthAction;
+
+    fn route() -> &'static str {
+        "/login/totp"
+    }
+
+    fn query(&self) -> Option<&Self::Query> {
+        self.post_auth_action.as_ref()
+    }
+}
+
+impl From<Option<PostAuthAction>> for LoginTotp {
+    fn from(post_auth_action: Option<PostAuthAction>) -> Self {
+        Self { post_auth_action }
+    }
+}
+
+/// `GET|POST /login/recovery-code`
+#[derive(Default, Debug, Clone)]
+pub struct LoginRecoveryCode {
+    post_auth_action: Option<PostAuthAction>,
+}
+
+impl Route for LoginRecoveryCode {
+    type Query = PostAuthAction;
+
+    fn route() -> &'static str {
+        "/login/recovery-code"
+    }
+
+    fn query(&self) -> Option<&Self::Query> {
+        self.post_auth_action.as_ref()
+    }
+}
+
+impl From<Option<PostAuthAction>> for LoginRecoveryCode {
+    fn from(post_auth_action: Option<PostAuthAction>) -> Self {
+        Self { post_auth_action }
+    }
+}
+
+/// `GET|POST /login/sms`
+#[derive(Default, Debug, Clone)]
+pub struct LoginSms {
+    post_auth_action: Option<PostAuthAction>,
+}
+
+impl Route for LoginSms {
+    type Query = PostAuthAction;
+
+    fn route() -> &'static str {
+        "/login/sms"
+    }
+
+    fn query(&self) -> Option<&Self::Query> {
+        self.post_auth_action.as_ref()
+    }
+}
+
+impl From<Option<PostAuthAction>> for LoginSms {
+    fn from(post_auth_action: Option<PostAuthAction>) -> Self {
+        Self { post_auth_action }
+    }
+}
+
+/// `GET|POST /login/confirm-email`
+#[derive(Default, Debug, Clone)]
+pub struct LoginConfirmEmail {
+    post_auth_action: Option<PostAuthAction>,
+}
+
+impl Route for LoginConfirmEmail {
+    type Query = PostAuthAction;
+
+    fn route() -> &'static str {
+        "/login/confirm-email"
+    }
+
+    fn query(&self) -> Option<&Self::Query> {
+        self.post_auth_action.as_ref()
+    }
+}
+
+impl From<Option<PostAuthAction>> for LoginConfirmEmail {
+    fn from(post_auth_action: Option<PostAuthAction>) -> Self {
+        Self { post_auth_action }
+    }
+}
+
+/// `GET|POST /login/password-change`
+#[derive(Default, Debug, Clone)]
+pub struct LoginPasswordChange {
+    post_auth_action: Option<PostAuthAction>,
+}
+
+impl Route for LoginPasswordChange {
+    type Query = PostAuthAction;
+
+    fn route() -> &'static str {
+        "/login/password-change"
+    }
+
+    fn query(&self) -> Option<&Self::Query> {
+        self.post_auth_action.as_ref()
+    }
+}
+
+impl From<Option<PostAuthAction>> for LoginPasswordChange {
+    fn from(post_auth_action: Option<PostAuthAction>) -> Self {
+        Self { post_auth_action }
+    }
+}
+
+/// `GET|POST /login/email`
+#[derive(Default, Debug, Clone)]
+pub struct LoginEmail {
+    post_auth_action: Option<PostAuthAction>,
+}
+
+impl Route for LoginEmail {
+    type Query = PostAuthAction;
+
+    fn route() -> &'static str {
+        "/login/email"
+    }
+
+    fn query(&self) -> Option<&Self::Query> {
+        self.post_auth_action.as_ref()
+    }
+}
+
+impl From<Option<PostAuthAction>> for LoginEmail {
+    fn from(post_auth_action: Option<PostAuthAction>) -> Self {
+        Self { post_auth_action }
+    }
+}
+
+/// `GET|POST /login/email/code`
+#[derive(Default, Debug, Clone)]
+pub struct LoginEmailCode {
+    post_auth_action: Option<PostAuthAction>,
+}
+
+impl Route for LoginEmailCode {
+    type Query = PostAuthAction;
+
+    fn route() -> &'static str {
+        "/login/email/code"
+    }
+
+    fn query(&self) -> Option<&Self::Query> {
+        self.post_auth_action.as_ref()
+    }
+}
+
+impl From<Option<PostAuthAction>> for LoginEmailCode {
+    fn from(post_auth_action: Option<PostAuthAction>) -> Self {
+        Self { post_auth_action }
+    }
+}
+
+/// `GET|POST /login/email/link/{token}`
+pub struct LoginEmailLink {
+    token: String,
+}
+
+impl LoginEmailLink {
+    #[must_use]
+    pub fn new(token: String) -> Self {
+        Self { token }
+    }
+}
+
+impl Route for LoginEmailLink {
+    type Query = ();
+    fn route() -> &'static str {
+        "/login/email/link/{token}"
+    }
+
+    fn path(&self) -> std::borrow::Cow<'static, str> {
+        format!("/login/email/link/{}", self.token).into()
+    }
+}
+
 /// `POST /logout`
 #[derive(Default, Debug, Clone)]
 pub struct Logout;
@@ -253,6 +534,131 @@ impl SimpleRoute for Logout {
     const PATH: &'static str = "/logout";
 }
 
+/// `POST /restore-account`
+#[derive(Default, Debug, Clone)]
+pub struct RestoreAccount;
+
+impl SimpleRoute for RestoreAccount {
+    const PATH: &'static str = "/restore-account";
+}
+
+/// `GET|POST /reauth`
+#[derive(Default, Debug, Clone)]
+pub struct Reauth {
+    post_auth_action: Option<PostAuthAction>,
+}
+
+impl Reauth {
+    #[must_use]
+    pub fn and_then(action: PostAuthAction) -> Self {
+        Self {
+            post_auth_action: Some(action),
+        }
+    }
+
+    #[must_use]
+    pub fn and_continue_grant(data: Ulid) -> Self {
+        Self {
+            post_auth_action: Some(PostAuthAction::continue_grant(data)),
+        }
+    }
+
+    /// Get a reference to the reauth's post auth action.
+    #[must_use]
+    pub fn post_auth_action(&self) -> Option<&PostAuthAction> {
+        self.post_auth_action.as_ref()
+    }
+
+    pub fn go_next(&self, url_builder: &UrlBuilder) -> axum::response::Redirect {
+        match &self.post_auth_action {
+            Some(action) => action.go_next(url_builder),
+            None => url_builder.redirect(&Index),
+        }
+    }
+}
+
+impl Route for Reauth {
+    type Query = PostAuthAction;
+
+    fn route() -> &'static str {
+        "/reauth"
+    }
+
+    fn query(&self) -> Option<&Self::Query> {
+        self.post_auth_action.as_ref()
+    }
+}
+
+impl From<Option<PostAuthAction>> for Reauth {
+    fn from(post_auth_action: Option<PostAuthAction>) -> Self {
+        Self { post_auth_action }
+    }
+}
+
+/// `POST /reauth/passkey`
+#[derive(Default, Debug, Clone)]
+pub struct PasskeyReauth {
+    post_auth_action: Option<PostAuthAction>,
+}
+
+impl Route for PasskeyReauth {
+    type Query = PostAuthAction;
+
+    fn route() -> &'static str {
+        "/reauth/passkey"
+    }
+
+    fn query(&self) -> Option<&Self::Query> {
+        self.post_auth_action.as_ref()
+    }
+}
+
+impl From<Option<PostAuthAction>> for PasskeyReauth {
+    fn from(post_auth_action: Option<PostAuthAction>) -> Self {
+        Self { post_auth_action }
+    }
+}
+
+/// `GET|POST /accept-terms`
+#[derive(Default, Debug, Clone)]
+pub struct AcceptTerms {
+    post_auth_action: Option<PostAuthAction>,
+}
+
+impl Route for AcceptTerms {
+    type Query = PostAuthAction;
+
+    fn route() -> &'static str {
+        "/accept-terms"
+    }
+
+    fn query(&self) -> Option<&Self::Query> {
+        self.post_auth_action.as_ref()
+    }
+}
+
+impl From<Option<PostAuthAction>> for AcceptTerms {
+    fn from(post_auth_action: Option<PostAuthAction>) -> Self {
+        Self { post_auth_action }
+    }
+}
+
+/// `GET|POST /invites`
+#[derive(Default, Debug, Clone)]
+pub struct Invites;
+
+impl SimpleRoute for Invites {
+    const PATH: &'static str = "/invites";
+}
+
+/// `GET|POST /notifications`
+#[derive(Default, Debug, Clone)]
+pub struct Notifications;
+
+impl SimpleRoute for Notifications {
+    const PATH: &'static str = "/notifications";
+}
+
 /// `POST /register`
 #[derive(Default, Debug, Clone)]
 pub struct Register {
@@ -543,6 +949,17 @@ impl SimpleRoute for AccountPasswordChange {
     const PATH: &'static str = "/account/password/change";
 }
 
+/// `GET /account/sessions/browsers`
+///
+/// Handled by the React frontend; this struct definition is purely for
+/// redirects.
+#[derive(Default, Debug, Clone)]
+pub struct AccountBrowserSessions;
+
+impl SimpleRoute for AccountBrowserSessions {
+    const PATH: &'static str = "/account/sessions/browsers";
+}
+
 /// `GET /consent/{grant_id}`
 #[derive(Debug, Clone)]
 pub struct Consent(pub Ulid);
@@ -715,6 +1132,29 @@ impl Route for UpstreamOAuth2Callback {
     }
 }
 
+/// `POST /upstream/backchannel-logout/{id}`
+pub struct UpstreamOAuth2BackchannelLogout {
+    id: Ulid,
+}
+
+impl UpstreamOAuth2BackchannelLogout {
+    #[must_use]
+    pub const fn new(id: Ulid) -> Self {
+        Self { id }
+    }
+}
+
+impl Route for UpstreamOAuth2BackchannelLogout {
+    type Query = ();
+    fn route() -> &'static str {
+        "/upstream/backchannel-logout/{provider_id}"
+    }
+
+    fn path(&self) -> std::borrow::Cow<'static, str> {
+        format!("/upstream/backchannel-logout/{}", self.id).into()
+    }
+}
+
 /// `GET /upstream/link/{id}`
 pub struct UpstreamOAuth2Link {
     id: Ulid,
@@ -738,6 +1178,29 @@ impl Route for UpstreamOAuth2Link {
     }
 }
 
+/// `GET /upstream/tokens/{id}`
+pub struct UpstreamOAuth2Tokens {
+    id: Ulid,
+}
+
+impl UpstreamOAuth2Tokens {
+    #[must_use]
+    pub const fn new(id: Ulid) -> Self {
+        Self { id }
+    }
+}
+
+impl Route for UpstreamOAuth2Tokens {
+    type Query = ();
+    fn route() -> &'static str {
+        "/upstream/tokens/{provider_id}"
+    }
+
+    fn path(&self) -> std::borrow::Cow<'static, str> {
+        format!("/upstream/tokens/{}", self.id).into()
+    }
+}
+
 /// `GET|POST /link`
 #[derive(Default, Serialize, Deserialize, Debug, Clone)]
 pub struct DeviceCodeLink {
@@ -794,6 +1257,38 @@ impl SimpleRoute for OAuth2DeviceAuthorizationEndpoint {
     const PATH: &'static str = "/oauth2/device";
 }
 
+/// `POST /oauth2/bc-authorize`
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct OAuth2BackchannelAuthenticationEndpoint;
+
+impl SimpleRoute for OAuth2BackchannelAuthenticationEndpoint {
+    const PATH: &'static str = "/oauth2/bc-authorize";
+}
+
+/// `GET|POST /backchannel/{grant_id}`
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct BackchannelAuthenticationConsent {
+    id: Ulid,
+}
+
+impl Route for BackchannelAuthenticationConsent {
+    type Query = ();
+    fn route() -> &'static str {
+        "/backchannel/{grant_id}"
+    }
+
+    fn path(&self) -> std::borrow::Cow<'static, str> {
+        format!("/backchannel/{}", self.id).into()
+    }
+}
+
+impl BackchannelAuthenticationConsent {
+    #[must_use]
+    pub fn new(id: Ulid) -> Self {
+        Self { id }
+    }
+}
+
 /// `GET|POST /recover`
 #[derive(Default, Serialize, Deserialize, Debug, Clone)]
 pub struct AccountRecoveryStart;
@@ -852,6 +1347,52 @@ impl Route for AccountRecoveryFinish {
     }
 }
 
+/// `GET|POST /claim/{token}`
+pub struct AccountClaim {
+    token: String,
+}
+
+impl AccountClaim {
+    #[must_use]
+    pub fn new(token: String) -> Self {
+        Self { token }
+    }
+}
+
+impl Route for AccountClaim {
+    type Query = ();
+    fn route() -> &'static str {
+        "/claim/{token}"
+    }
+
+    fn path(&self) -> std::borrow::Cow<'static, str> {
+        format!("/claim/{}", self.token).into()
+    }
+}
+
+/// `GET|POST /action/{token}`
+pub struct UserActionLink {
+    token: String,
+}
+
+impl UserActionLink {
+    #[must_use]
+    pub fn new(token: String) -> Self {
+        Self { token }
+    }
+}
+
+impl Route for UserActionLink {
+    type Query = ();
+    fn route() -> &'static str {
+        "/action/{token}"
+    }
+
+    fn path(&self) -> std::borrow::Cow<'static, str> {
+        format!("/action/{}", self.token).into()
+    }
+}
+
 /// `GET /assets`
 pub struct StaticAsset {
     path: String,
@@ -11,12 +11,33 @@ use url::Url;
 
 use crate::traits::Route;
 
+/// Make sure the path of the URL ends with a `/`, so that relative URLs are
+/// joined under it instead of replacing its last segment
+fn with_trailing_slash(mut url: Url) -> Url {
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    url
+}
+
+/// The port used by a `Host` header without one
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" => Some(80),
+        "https" => Some(443),
+        _ => None,
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct UrlBuilder {
     http_base: Url,
     prefix: String,
     assets_base: String,
     issuer: Url,
+    issuer_prefix: String,
+    issuer_aliases: Vec<Url>,
 }
 
 impl UrlBuilder {
@@ -52,6 +73,19 @@ impl UrlBuilder {
         }
     }
 
+    /// The path prefix of the OIDC issuer, if any
+    ///
+    /// This may be different from [`UrlBuilder::prefix`] if the issuer is not
+    /// the same as the base URL.
+    #[must_use]
+    pub fn issuer_prefix(&self) -> Option<&str> {
+        if self.issuer_prefix.is_empty() {
+            None
+        } else {
+            Some(&self.issuer_prefix)
+        }
+    }
+
     /// Create a (relative) redirect response to a route
     pub fn redirect<U>(&self, destination: &U) -> axum::response::Redirect
     where
@@ -72,6 +106,9 @@ impl UrlBuilder {
 
     /// Create a new [`UrlBuilder`] from a base URL
     ///
+    /// The base URL may have a path, in which case all the routes are prefixed
+    /// with it, regardless of whether it has a trailing slash.
+    ///
     /// # Panics
     ///
     /// Panics if the base URL contains a fragment, a query, credentials or
@@ -95,14 +132,70 @@ impl UrlBuilder {
             "base URL must not contain credentials"
         );
 
+        let base = with_trailing_slash(base);
         let issuer = issuer.unwrap_or_else(|| base.clone());
         let prefix = base.path().trim_end_matches('/').to_owned();
+        let issuer_prefix = issuer.path().trim_end_matches('/').to_owned();
         let assets_base = assets_base.unwrap_or_else(|| format!("{prefix}/assets/"));
         Self {
             http_base: base,
             prefix,
             assets_base,
             issuer,
+            issuer_prefix,
+            issuer_aliases: Vec::new(),
+        }
+    }
+
+    /// Set the additional issuers this service answers as
+    ///
+    /// Use [`UrlBuilder::for_host`] to get a builder for the issuer matching
+    /// the host of a request.
+    #[must_use]
+    pub fn with_issuer_aliases(mut self, issuer_aliases: Vec<Url>) -> Self {
+        self.issuer_aliases = issuer_aliases;
+        self
+    }
+
+    /// Get a [`UrlBuilder`] for the issuer served on the given host, as found
+    /// in the `Host` header of a request
+    ///
+    /// If the host matches one of the issuer aliases, the returned builder
+    /// uses it as issuer, and builds absolute URLs on the same scheme, host
+    /// and port. Otherwise, this returns a copy of this builder.
+    #[must_use]
+    pub fn for_host(&self, host: &str) -> Self {
+        let (hostname, port) = match host.rsplit_once(':') {
+            // Make sure we don't split an IPv6 address without a port
+            Some((hostname, port)) if !port.ends_with(']') => (hostname, port.parse().ok()),
+            _ => (host, None),
+        };
+
+        let alias = self.issuer_aliases.iter().find(|alias| {
+            alias
+                .host_str()
+                .is_some_and(|h| h.eq_ignore_ascii_case(hostname))
+                && port.or_else(|| default_port(alias.scheme())) == alias.port_or_known_default()
+        });
+
+        let Some(alias) = alias else {
+            return self.clone();
+        };
+
+        let mut http_base = self.http_base.clone();
+        // Those only fail on URLs which can't be a base or with a file scheme,
+        // which the constructor already rejects
+        let _ = http_base.set_scheme(alias.scheme());
+        let _ = http_base.set_host(alias.host_str());
+        let _ = http_base.set_port(alias.port());
+
+        Self {
+            http_base,
+            prefix: self.prefix.clone(),
+            assets_base: self.assets_base.clone(),
+            issuer: alias.clone(),
+            issuer_prefix: alias.path().trim_end_matches('/').to_owned(),
+            issuer_aliases: self.issuer_aliases.clone(),
         }
     }
 
@@ -130,10 +223,16 @@ impl UrlBuilder {
         self.issuer.clone()
     }
 
+    /// Additional OIDC issuers, picked by [`UrlBuilder::for_host`]
+    #[must_use]
+    pub fn issuer_aliases(&self) -> &[Url] {
+        &self.issuer_aliases
+    }
+
     /// OIDC discovery document URL
     #[must_use]
     pub fn oidc_discovery(&self) -> Url {
-        crate::endpoints::OidcConfiguration.absolute_url(&self.issuer)
+        crate::endpoints::OidcConfiguration.absolute_url(&with_trailing_slash(self.issuer.clone()))
     }
 
     /// OAuth 2.0 authorization endpoint
@@ -166,12 +265,24 @@ impl UrlBuilder {
         self.absolute_url_for(&crate::endpoints::OAuth2RegistrationEndpoint)
     }
 
+    /// OAuth 2.0 pushed authorization request endpoint
+    #[must_use]
+    pub fn oauth_pushed_authorization_request_endpoint(&self) -> Url {
+        self.absolute_url_for(&crate::endpoints::OAuth2PushedAuthorizationRequestEndpoint)
+    }
+
     /// OAuth 2.0 device authorization endpoint
     #[must_use]
     pub fn oauth_device_authorization_endpoint(&self) -> Url {
         self.absolute_url_for(&crate::endpoints::OAuth2DeviceAuthorizationEndpoint)
     }
 
+    /// OAuth 2.0 backchannel authentication endpoint
+    #[must_use]
+    pub fn oauth_backchannel_authentication_endpoint(&self) -> Url {
+        self.absolute_url_for(&crate::endpoints::OAuth2BackchannelAuthenticationEndpoint)
+    }
+
     /// OAuth 2.0 device code link
     #[must_use]
     pub fn device_code_link(&self) -> Url {
@@ -184,6 +295,12 @@ impl UrlBuilder {
         self.absolute_url_for(&crate::endpoints::DeviceCodeLink::with_code(code))
     }
 
+    /// OpenID Connect RP-initiated logout endpoint
+    #[must_use]
+    pub fn oidc_end_session_endpoint(&self) -> Url {
+        self.absolute_url_for(&crate::endpoints::OAuth2EndSessionEndpoint)
+    }
+
     // OIDC userinfo endpoint
     #[must_use]
     pub fn oidc_userinfo_endpoint(&self) -> Url {
@@ -220,6 +337,12 @@ impl UrlBuilder {
         self.absolute_url_for(&crate::endpoints::UpstreamOAuth2Callback::new(id))
     }
 
+    /// Upstream back-channel logout URI
+    #[must_use]
+    pub fn upstream_oauth_backchannel_logout(&self, id: Ulid) -> Url {
+        self.absolute_url_for(&crate::endpoints::UpstreamOAuth2BackchannelLogout::new(id))
+    }
+
     /// Upstream authorize URI
     #[must_use]
     pub fn upstream_oauth_authorize(&self, id: Ulid) -> Url {
@@ -237,6 +360,33 @@ impl UrlBuilder {
     pub fn account_recovery_link(&self, ticket: String) -> Url {
         self.absolute_url_for(&crate::endpoints::AccountRecoveryFinish::new(ticket))
     }
+
+    /// Account claim link
+    #[must_use]
+    pub fn account_claim_link(&self, token: String) -> Url {
+        self.absolute_url_for(&crate::endpoints::AccountClaim::new(token))
+    }
+
+    /// Link sent by email letting a user log in without entering the code
+    /// sent alongside it
+    #[must_use]
+    pub fn login_email_link(&self, token: String) -> Url {
+        self.absolute_url_for(&crate::endpoints::LoginEmailLink::new(token))
+    }
+
+    /// Single-use link letting a user perform an action, like ending a
+    /// browser session
+    #[must_use]
+    pub fn user_action_link(&self, token: String) -> Url {
+        self.absolute_url_for(&crate::endpoints::UserActionLink::new(token))
+    }
+
+    /// Link to the page where a user approves a backchannel authentication
+    /// request
+    #[must_use]
+    pub fn backchannel_authentication_consent_link(&self, id: Ulid) -> Url {
+        self.absolute_url_for(&crate::endpoints::BackchannelAuthenticationConsent::new(id))
+    }
 }
 
 #[cfg(test)]
@@ -301,5 +451,102 @@ mod tests {
 
         let uri = builder.absolute_url_for(&crate::endpoints::OAuth2AuthorizationEndpoint);
         assert_eq!(uri.as_str(), "https://example.com/foo/authorize");
+
+        // The trailing slash in the base URL is optional
+        let builder = super::UrlBuilder::new(
+            url::Url::parse("https://example.com/foo").unwrap(),
+            None,
+            None,
+        );
+        assert_eq!(builder.prefix(), Some("/foo"));
+        assert_eq!(builder.http_base().as_str(), "https://example.com/foo/");
+        assert_eq!(builder.assets_base(), "/foo/assets/");
+
+        let uri = builder.absolute_url_for(&crate::endpoints::OAuth2AuthorizationEndpoint);
+        assert_eq!(uri.as_str(), "https://example.com/foo/authorize");
+        assert_eq!(
+            builder.relative_url_for(&crate::endpoints::Login::default()),
+            "/foo/login"
+        );
+    }
+
+    #[test]
+    fn test_issuer_prefix() {
+        // The issuer defaults to the base URL
+        let builder = super::UrlBuilder::new(
+            url::Url::parse("https://example.com/auth/").unwrap(),
+            None,
+            None,
+        );
+        assert_eq!(builder.issuer_prefix(), Some("/auth"));
+        assert_eq!(
+            builder.oidc_discovery().as_str(),
+            "https://example.com/auth/.well-known/openid-configuration"
+        );
+
+        // The issuer is kept as-is, but the discovery document is always under it
+        let builder = super::UrlBuilder::new(
+            url::Url::parse("https://example.com/auth/").unwrap(),
+            Some(url::Url::parse("https://example.com/issuer").unwrap()),
+            None,
+        );
+        assert_eq!(builder.issuer_prefix(), Some("/issuer"));
+        assert_eq!(builder.oidc_issuer().as_str(), "https://example.com/issuer");
+        assert_eq!(
+            builder.oidc_discovery().as_str(),
+            "https://example.com/issuer/.well-known/openid-configuration"
+        );
+        assert_eq!(
+            builder.oauth_token_endpoint().as_str(),
+            "https://example.com/auth/oauth2/token"
+        );
+
+        let builder = super::UrlBuilder::new(
+            url::Url::parse("https://example.com/auth/").unwrap(),
+            Some(url::Url::parse("https://example.com/").unwrap()),
+            None,
+        );
+        assert_eq!(builder.issuer_prefix(), None);
+    }
+
+    #[test]
+    fn test_issuer_aliases() {
+        let builder = super::UrlBuilder::new(
+            url::Url::parse("https://auth.example.com/").unwrap(),
+            None,
+            None,
+        )
+        .with_issuer_aliases(vec![
+            url::Url::parse("https://auth.example.org/").unwrap(),
+            url::Url::parse("http://localhost:8080/issuer").unwrap(),
+        ]);
+
+        // Unknown hosts get the primary issuer
+        let other = builder.for_host("auth.example.com");
+        assert_eq!(other, builder);
+        let other = builder.for_host("unknown.example.com");
+        assert_eq!(other, builder);
+
+        let alias = builder.for_host("AUTH.example.org:443");
+        assert_eq!(alias.oidc_issuer().as_str(), "https://auth.example.org/");
+        assert_eq!(alias.issuer_prefix(), None);
+        assert_eq!(
+            alias.oauth_token_endpoint().as_str(),
+            "https://auth.example.org/oauth2/token"
+        );
+
+        // The port has to match
+        assert_eq!(builder.for_host("localhost"), builder);
+        let alias = builder.for_host("localhost:8080");
+        assert_eq!(alias.oidc_issuer().as_str(), "http://localhost:8080/issuer");
+        assert_eq!(alias.issuer_prefix(), Some("/issuer"));
+        assert_eq!(
+            alias.oidc_discovery().as_str(),
+            "http://localhost:8080/issuer/.well-known/openid-configuration"
+        );
+        assert_eq!(
+            alias.oauth_token_endpoint().as_str(),
+            "http://localhost:8080/oauth2/token"
+        );
     }
 }
@@ -0,0 +1,16 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Helps sending text messages to users, with different SMS gateways
+
+#![deny(missing_docs)]
+
+mod sender;
+mod smpp;
+mod transport;
+
+pub use mas_templates::SmsVerificationContext;
+
+pub use self::{sender::SmsSender, transport::Transport as SmsTransport};
@@ -0,0 +1,69 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Send text messages to users
+
+use mas_templates::{SmsVerificationContext, Templates, WithLanguage};
+use thiserror::Error;
+
+use crate::SmsTransport;
+
+/// Helps sending text messages to users
+#[derive(Clone)]
+pub struct SmsSender {
+    templates: Templates,
+    transport: SmsTransport,
+}
+
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub enum Error {
+    Transport(#[from] crate::transport::Error),
+    Templates(#[from] mas_templates::TemplateError),
+}
+
+impl SmsSender {
+    /// Constructs a new [`SmsSender`]
+    #[must_use]
+    pub fn new(templates: Templates, transport: SmsTransport) -> Self {
+        Self {
+            templates,
+            transport,
+        }
+    }
+
+    /// Send a verification code to a phone number
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the message failed rendering or failed sending
+    #[tracing::instrument(
+        name = "sms.verification.send",
+        skip_all,
+        fields(
+            sms.language = %context.language(),
+            user.id = %context.user().id,
+        ),
+    )]
+    pub async fn send_verification_code(
+        &self,
+        to: &str,
+        context: &WithLanguage<SmsVerificationContext>,
+    ) -> Result<(), Error> {
+        let body = self.templates.render_sms_verification(context)?;
+        self.transport.send(to, body.trim()).await?;
+        Ok(())
+    }
+
+    /// Test the connection to the SMS gateway
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection failed
+    #[tracing::instrument(name = "sms.test_connection", skip_all)]
+    pub async fn test_connection(&self) -> Result<(), crate::transport::Error> {
+        self.transport.test_connection().await
+    }
+}
@@ -0,0 +1,466 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! A minimal SMPP 3.4 client, which binds as a transmitter to send a single
+//! message at a time
+//!
+//! Verification codes are sent rarely enough that opening a new session for
+//! each message is simpler than keeping one alive with `enquire_link`s.
+
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+
+const BIND_TRANSMITTER: u32 = 0x0000_0002;
+const SUBMIT_SM: u32 = 0x0000_0004;
+const UNBIND: u32 = 0x0000_0006;
+const ENQUIRE_LINK: u32 = 0x0000_0015;
+const GENERIC_NACK: u32 = 0x8000_0000;
+const RESPONSE_BIT: u32 = 0x8000_0000;
+
+const INTERFACE_VERSION: u8 = 0x34;
+
+/// The TLV carrying messages too long for the `short_message` field
+const TAG_MESSAGE_PAYLOAD: u16 = 0x0424;
+
+/// The longest message which fits in the `short_message` field
+const MAX_SHORT_MESSAGE_LENGTH: usize = 254;
+
+/// PDUs larger than this are refused, as we only expect small responses
+const MAX_PDU_LENGTH: usize = 64 * 1024;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to talk to the SMSC")]
+    Io(#[from] std::io::Error),
+
+    #[error("timed out waiting for the SMSC")]
+    Timeout,
+
+    #[error("the SMSC sent an invalid PDU")]
+    InvalidPdu,
+
+    #[error("the SMSC responded to command {command_id:#010x} with status {status:#010x}")]
+    CommandStatus { command_id: u32, status: u32 },
+}
+
+/// The settings used to connect and bind to the SMSC
+#[derive(Debug, Clone)]
+pub struct SmppConfig {
+    pub hostname: String,
+    pub port: u16,
+    pub system_id: String,
+    pub password: String,
+}
+
+/// Type of number and numbering plan indicator of an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct AddressKind {
+    ton: u8,
+    npi: u8,
+}
+
+impl AddressKind {
+    const INTERNATIONAL: Self = Self { ton: 1, npi: 1 };
+    const ALPHANUMERIC: Self = Self { ton: 5, npi: 0 };
+}
+
+/// Split an address in its kind and the value sent on the wire: phone numbers
+/// are sent in the international format without the leading `+`, anything
+/// else is an alphanumeric sender ID
+fn parse_address(address: &str) -> (AddressKind, &str) {
+    let number = address.strip_prefix('+').unwrap_or(address);
+    if !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()) {
+        (AddressKind::INTERNATIONAL, number)
+    } else {
+        (AddressKind::ALPHANUMERIC, address)
+    }
+}
+
+/// Encode the message body, returning the `data_coding` to use and the bytes
+///
+/// ASCII messages use the SMSC default alphabet, anything else is sent as
+/// UCS-2.
+fn encode_body(body: &str) -> (u8, Vec<u8>) {
+    if body.is_ascii() {
+        (0x00, body.as_bytes().to_vec())
+    } else {
+        let bytes = body.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        (0x08, bytes)
+    }
+}
+
+#[derive(Debug, Default)]
+struct PduBuilder {
+    body: Vec<u8>,
+}
+
+impl PduBuilder {
+    fn c_octet_string(mut self, value: &str) -> Self {
+        self.body.extend_from_slice(value.as_bytes());
+        self.body.push(0);
+        self
+    }
+
+    fn u8(mut self, value: u8) -> Self {
+        self.body.push(value);
+        self
+    }
+
+    fn octets(mut self, value: &[u8]) -> Self {
+        self.body.extend_from_slice(value);
+        self
+    }
+
+    fn tlv(mut self, tag: u16, value: &[u8]) -> Result<Self, Error> {
+        let length = u16::try_from(value.len()).map_err(|_| Error::InvalidPdu)?;
+        self.body.extend_from_slice(&tag.to_be_bytes());
+        self.body.extend_from_slice(&length.to_be_bytes());
+        self.body.extend_from_slice(value);
+        Ok(self)
+    }
+
+    fn build(self, command_id: u32, sequence_number: u32) -> Result<Vec<u8>, Error> {
+        let length = u32::try_from(self.body.len() + 16).map_err(|_| Error::InvalidPdu)?;
+        let mut pdu = Vec::with_capacity(self.body.len() + 16);
+        pdu.extend_from_slice(&length.to_be_bytes());
+        pdu.extend_from_slice(&command_id.to_be_bytes());
+        pdu.extend_from_slice(&0_u32.to_be_bytes());
+        pdu.extend_from_slice(&sequence_number.to_be_bytes());
+        pdu.extend_from_slice(&self.body);
+        Ok(pdu)
+    }
+}
+
+fn submit_sm(from: &str, to: &str, body: &str, sequence_number: u32) -> Result<Vec<u8>, Error> {
+    let (source, source_addr) = parse_address(from);
+    let (dest, destination_addr) = parse_address(to);
+    let (data_coding, message) = encode_body(body);
+
+    let builder = PduBuilder::default()
+        .c_octet_string("") // service_type
+        .u8(source.ton)
+        .u8(source.npi)
+        .c_octet_string(source_addr)
+        .u8(dest.ton)
+        .u8(dest.npi)
+        .c_octet_string(destination_addr)
+        .u8(0) // esm_class
+        .u8(0) // protocol_id
+        .u8(0) // priority_flag
+        .c_octet_string("") // schedule_delivery_time
+        .c_octet_string("") // validity_period
+        .u8(0) // registered_delivery
+        .u8(0) // replace_if_present_flag
+        .u8(data_coding)
+        .u8(0); // sm_default_msg_id
+
+    let builder = if message.len() > MAX_SHORT_MESSAGE_LENGTH {
+        builder.u8(0).tlv(TAG_MESSAGE_PAYLOAD, &message)?
+    } else {
+        let length = u8::try_from(message.len()).map_err(|_| Error::InvalidPdu)?;
+        builder.u8(length).octets(&message)
+    };
+
+    builder.build(SUBMIT_SM, sequence_number)
+}
+
+/// The header of a PDU received from the SMSC
+struct Header {
+    command_id: u32,
+    command_status: u32,
+    sequence_number: u32,
+}
+
+struct Session<S> {
+    stream: S,
+    sequence_number: u32,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Session<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream,
+            sequence_number: 0,
+        }
+    }
+
+    fn next_sequence_number(&mut self) -> u32 {
+        self.sequence_number += 1;
+        self.sequence_number
+    }
+
+    /// Read the next PDU, skipping its body
+    async fn read_pdu(&mut self) -> Result<Header, Error> {
+        let mut header = [0; 16];
+        self.stream.read_exact(&mut header).await?;
+        let [length, command_id, command_status, sequence_number] = [0, 4, 8, 12]
+            .map(|offset| u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap()));
+
+        let length = usize::try_from(length).map_err(|_| Error::InvalidPdu)?;
+        if !(16..=MAX_PDU_LENGTH).contains(&length) {
+            return Err(Error::InvalidPdu);
+        }
+
+        let mut body = vec![0; length - 16];
+        self.stream.read_exact(&mut body).await?;
+
+        Ok(Header {
+            command_id,
+            command_status,
+            sequence_number,
+        })
+    }
+
+    /// Send a request and wait for its response, answering the `enquire_link`
+    /// the SMSC may send in the meantime
+    async fn request(&mut self, command_id: u32, pdu: Vec<u8>) -> Result<(), Error> {
+        let sequence_number = u32::from_be_bytes(pdu[12..16].try_into().unwrap());
+        self.stream.write_all(&pdu).await?;
+
+        loop {
+            let header = self.read_pdu().await?;
+
+            if header.command_id == ENQUIRE_LINK {
+                let response = PduBuilder::default()
+                    .build(ENQUIRE_LINK | RESPONSE_BIT, header.sequence_number)?;
+                self.stream.write_all(&response).await?;
+                continue;
+            }
+
+            if header.sequence_number != sequence_number {
+                continue;
+            }
+
+            if header.command_id != (command_id | RESPONSE_BIT) && header.command_id != GENERIC_NACK
+            {
+                return Err(Error::InvalidPdu);
+            }
+
+            if header.command_status != 0 || header.command_id == GENERIC_NACK {
+                return Err(Error::CommandStatus {
+                    command_id,
+                    status: header.command_status,
+                });
+            }
+
+            return Ok(());
+        }
+    }
+
+    async fn bind_transmitter(&mut self, system_id: &str, password: &str) -> Result<(), Error> {
+        let sequence_number = self.next_sequence_number();
+        let pdu = PduBuilder::default()
+            .c_octet_string(system_id)
+            .c_octet_string(password)
+            .c_octet_string("") // system_type
+            .u8(INTERFACE_VERSION)
+            .u8(0) // addr_ton
+            .u8(0) // addr_npi
+            .c_octet_string("") // address_range
+            .build(BIND_TRANSMITTER, sequence_number)?;
+        self.request(BIND_TRANSMITTER, pdu).await
+    }
+
+    async fn submit_sm(&mut self, from: &str, to: &str, body: &str) -> Result<(), Error> {
+        let sequence_number = self.next_sequence_number();
+        let pdu = submit_sm(from, to, body, sequence_number)?;
+        self.request(SUBMIT_SM, pdu).await
+    }
+
+    async fn unbind(&mut self) -> Result<(), Error> {
+        let sequence_number = self.next_sequence_number();
+        let pdu = PduBuilder::default().build(UNBIND, sequence_number)?;
+        self.request(UNBIND, pdu).await
+    }
+
+    async fn send(
+        &mut self,
+        config: &SmppConfig,
+        from: &str,
+        to: &str,
+        body: &str,
+    ) -> Result<(), Error> {
+        self.bind_transmitter(&config.system_id, &config.password)
+            .await?;
+        self.submit_sm(from, to, body).await?;
+
+        // The message was accepted, so failing to unbind cleanly is not an error
+        if let Err(e) = self.unbind().await {
+            tracing::warn!(
+                error = &e as &dyn std::error::Error,
+                "Failed to unbind from the SMSC"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Connect to the SMSC, and send a single message
+pub async fn send(config: &SmppConfig, from: &str, to: &str, body: &str) -> Result<(), Error> {
+    let fut = async {
+        let stream = TcpStream::connect((config.hostname.as_str(), config.port)).await?;
+        Session::new(stream).send(config, from, to, body).await
+    };
+
+    tokio::time::timeout(TIMEOUT, fut)
+        .await
+        .map_err(|_| Error::Timeout)?
+}
+
+/// Connect and bind to the SMSC, without sending any message
+pub async fn test_connection(config: &SmppConfig) -> Result<(), Error> {
+    let fut = async {
+        let stream = TcpStream::connect((config.hostname.as_str(), config.port)).await?;
+        let mut session = Session::new(stream);
+        session
+            .bind_transmitter(&config.system_id, &config.password)
+            .await?;
+        session.unbind().await
+    };
+
+    tokio::time::timeout(TIMEOUT, fut)
+        .await
+        .map_err(|_| Error::Timeout)?
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::duplex;
+
+    use super::*;
+
+    /// Read a full PDU from the client side, returning its command ID, sequence
+    /// number and body
+    async fn read_request(stream: &mut (impl AsyncRead + Unpin)) -> (u32, u32, Vec<u8>) {
+        let mut header = [0; 16];
+        stream.read_exact(&mut header).await.unwrap();
+        let length = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+        let command_id = u32::from_be_bytes(header[4..8].try_into().unwrap());
+        let sequence_number = u32::from_be_bytes(header[12..16].try_into().unwrap());
+        let mut body = vec![0; length - 16];
+        stream.read_exact(&mut body).await.unwrap();
+        (command_id, sequence_number, body)
+    }
+
+    async fn respond(
+        stream: &mut (impl AsyncWrite + Unpin),
+        command_id: u32,
+        status: u32,
+        sequence_number: u32,
+    ) {
+        let mut pdu = PduBuilder::default()
+            .build(command_id | RESPONSE_BIT, sequence_number)
+            .unwrap();
+        pdu[8..12].copy_from_slice(&status.to_be_bytes());
+        stream.write_all(&pdu).await.unwrap();
+    }
+
+    #[test]
+    fn test_encode_submit_sm() {
+        let pdu = submit_sm("MAS", "+33612345678", "123456", 7).unwrap();
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&[0, 0, 0, 53]);
+        expected.extend_from_slice(&SUBMIT_SM.to_be_bytes());
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 7]);
+        expected.push(0);
+        expected.extend_from_slice(&[5, 0]);
+        expected.extend_from_slice(b"MAS\0");
+        expected.extend_from_slice(&[1, 1]);
+        expected.extend_from_slice(b"33612345678\0");
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 6]);
+        expected.extend_from_slice(b"123456");
+        assert_eq!(pdu, expected);
+
+        // Non-ASCII messages are encoded as UCS-2
+        let pdu = submit_sm("MAS", "+33612345678", "é", 8).unwrap();
+        assert_eq!(&pdu[pdu.len() - 5..], &[0x08, 0, 2, 0, 0xe9][..]);
+
+        // Long messages are sent in the message_payload TLV
+        let body = "a".repeat(300);
+        let pdu = submit_sm("MAS", "+33612345678", &body, 9).unwrap();
+        let tlv = &pdu[pdu.len() - 304..];
+        assert_eq!(&tlv[..4], &[0x04, 0x24, 0x01, 0x2c]);
+        assert_eq!(pdu[pdu.len() - 305], 0);
+    }
+
+    #[tokio::test]
+    async fn test_send() {
+        let config = SmppConfig {
+            hostname: "localhost".to_owned(),
+            port: 2775,
+            system_id: "mas".to_owned(),
+            password: "secret".to_owned(),
+        };
+
+        let (client, mut server) = duplex(4096);
+        let smsc = tokio::spawn(async move {
+            let (command_id, sequence_number, body) = read_request(&mut server).await;
+            assert_eq!(command_id, BIND_TRANSMITTER);
+            assert!(body.starts_with(b"mas\0secret\0\0\x34"));
+            respond(&mut server, command_id, 0, sequence_number).await;
+
+            // Check that the client answers keep-alives while waiting
+            let enquire_link = PduBuilder::default().build(ENQUIRE_LINK, 42).unwrap();
+            server.write_all(&enquire_link).await.unwrap();
+
+            let (command_id, sequence_number, body) = read_request(&mut server).await;
+            assert_eq!(command_id, SUBMIT_SM);
+            assert!(body.ends_with(b"Your code is 123456"));
+
+            let (response, response_sequence_number, _) = read_request(&mut server).await;
+            assert_eq!(response, ENQUIRE_LINK | RESPONSE_BIT);
+            assert_eq!(response_sequence_number, 42);
+
+            respond(&mut server, command_id, 0, sequence_number).await;
+
+            let (command_id, sequence_number, _) = read_request(&mut server).await;
+            assert_eq!(command_id, UNBIND);
+            respond(&mut server, command_id, 0, sequence_number).await;
+        });
+
+        Session::new(client)
+            .send(&config, "MAS", "+33612345678", "Your code is 123456")
+            .await
+            .unwrap();
+        smsc.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bind_failure() {
+        let config = SmppConfig {
+            hostname: "localhost".to_owned(),
+            port: 2775,
+            system_id: "mas".to_owned(),
+            password: "wrong".to_owned(),
+        };
+
+        let (client, mut server) = duplex(4096);
+        tokio::spawn(async move {
+            let (command_id, sequence_number, _) = read_request(&mut server).await;
+            // ESME_RBINDFAIL
+            respond(&mut server, command_id, 0x0000_000D, sequence_number).await;
+        });
+
+        let error = Session::new(client)
+            .send(&config, "MAS", "+33612345678", "Your code is 123456")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            Error::CommandStatus {
+                command_id: BIND_TRANSMITTER,
+                status: 0x0000_000D,
+            }
+        ));
+    }
+}
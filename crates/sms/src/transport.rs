@@ -0,0 +1,235 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! SMS transport backends
+
+use std::sync::Arc;
+
+use mas_http::{RequestBuilderExt as _, RequestSigner};
+use serde::Serialize;
+use thiserror::Error;
+use url::Url;
+
+use crate::smpp::{self, SmppConfig};
+
+/// The API Twilio exposes, which is used if no other base URL is given
+const TWILIO_DEFAULT_BASE_URL: &str = "https://api.twilio.com/";
+
+/// A wrapper around the supported SMS gateways
+#[derive(Default, Clone)]
+pub struct Transport {
+    inner: Arc<TransportInner>,
+}
+
+#[derive(Default)]
+enum TransportInner {
+    #[default]
+    Blackhole,
+    Webhook {
+        client: reqwest::Client,
+        url: Url,
+        token: Option<String>,
+        from: Option<String>,
+        signer: Option<RequestSigner>,
+    },
+    Twilio {
+        client: reqwest::Client,
+        messages_url: Url,
+        account_sid: String,
+        auth_token: String,
+        from: String,
+    },
+    Smpp {
+        config: SmppConfig,
+        from: String,
+    },
+}
+
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub enum Error {
+    Http(#[from] reqwest::Error),
+    Smpp(#[from] smpp::Error),
+}
+
+/// The body POSTed to the webhook endpoint
+#[derive(Serialize)]
+struct WebhookMessage<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<&'a str>,
+    to: &'a str,
+    body: &'a str,
+}
+
+/// The form POSTed to the Twilio Messages API
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct TwilioMessage<'a> {
+    from: &'a str,
+    to: &'a str,
+    body: &'a str,
+}
+
+impl Transport {
+    fn new(inner: TransportInner) -> Self {
+        let inner = Arc::new(inner);
+        Self { inner }
+    }
+
+    /// Construct a blackhole transport
+    #[must_use]
+    pub fn blackhole() -> Self {
+        Self::new(TransportInner::Blackhole)
+    }
+
+    /// Construct a transport which POSTs the messages as JSON to the given
+    /// URL, with an optional bearer token, signing the requests if a
+    /// [`RequestSigner`] is given
+    #[must_use]
+    pub fn webhook(
+        client: reqwest::Client,
+        url: Url,
+        token: Option<String>,
+        from: Option<String>,
+        signer: Option<RequestSigner>,
+    ) -> Self {
+        Self::new(TransportInner::Webhook {
+            client,
+            url,
+            token,
+            from,
+            signer,
+        })
+    }
+
+    /// Construct a transport which sends the messages through the Twilio
+    /// Messages API, or an API compatible with it at the given base URL
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL of the Messages API could not be built
+    pub fn twilio(
+        client: reqwest::Client,
+        base_url: Option<Url>,
+        account_sid: String,
+        auth_token: String,
+        from: String,
+    ) -> Result<Self, url::ParseError> {
+        let base_url = match base_url {
+            Some(base_url) => base_url,
+            None => Url::parse(TWILIO_DEFAULT_BASE_URL)?,
+        };
+        let messages_url =
+            base_url.join(&format!("2010-04-01/Accounts/{account_sid}/Messages.json"))?;
+
+        Ok(Self::new(TransportInner::Twilio {
+            client,
+            messages_url,
+            account_sid,
+            auth_token,
+            from,
+        }))
+    }
+
+    /// Construct a transport which sends the messages to an SMSC using the
+    /// SMPP protocol
+    #[must_use]
+    pub fn smpp(
+        hostname: String,
+        port: u16,
+        system_id: String,
+        password: String,
+        from: String,
+    ) -> Self {
+        Self::new(TransportInner::Smpp {
+            config: SmppConfig {
+                hostname,
+                port,
+                system_id,
+                password,
+            },
+            from,
+        })
+    }
+
+    /// Test the connection to the underlying gateway. Only works with the
+    /// SMPP backend for now, as the HTTP APIs have no side-effect free call
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the connection test failed
+    pub async fn test_connection(&self) -> Result<(), Error> {
+        match self.inner.as_ref() {
+            TransportInner::Smpp { config, .. } => {
+                smpp::test_connection(config).await?;
+            }
+            TransportInner::Blackhole
+            | TransportInner::Webhook { .. }
+            | TransportInner::Twilio { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    /// Send a text message to the given phone number
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the gateway failed to accept the message
+    pub async fn send(&self, to: &str, body: &str) -> Result<(), Error> {
+        match self.inner.as_ref() {
+            TransportInner::Blackhole => {
+                tracing::warn!(
+                    "A text message was supposed to be sent but no SMS backend is configured"
+                );
+            }
+
+            TransportInner::Webhook {
+                client,
+                url,
+                token,
+                from,
+                signer,
+            } => {
+                let mut request = client.post(url.clone()).json(&WebhookMessage {
+                    from: from.as_deref(),
+                    to,
+                    body,
+                });
+
+                if let Some(token) = token {
+                    request = request.bearer_auth(token);
+                }
+
+                request
+                    .send_signed_traced(signer.as_ref())
+                    .await?
+                    .error_for_status()?;
+            }
+
+            TransportInner::Twilio {
+                client,
+                messages_url,
+                account_sid,
+                auth_token,
+                from,
+            } => {
+                client
+                    .post(messages_url.clone())
+                    .basic_auth(account_sid, Some(auth_token))
+                    .form(&TwilioMessage { from, to, body })
+                    .send_traced()
+                    .await?
+                    .error_for_status()?;
+            }
+
+            TransportInner::Smpp { config, from } => {
+                smpp::send(config, from, to, body).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Links between local users and user resources in an upstream SCIM directory
+CREATE TABLE "scim_user_links" (
+  "scim_user_link_id" UUID PRIMARY KEY,
+
+  -- The local user this directory entry is linked to
+  "user_id" UUID NOT NULL UNIQUE
+    REFERENCES "users" ("user_id") ON DELETE CASCADE,
+
+  -- The `id` of the user resource in the SCIM directory
+  "external_id" TEXT NOT NULL UNIQUE,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- Records of each synchronisation run against the SCIM directory
+CREATE TABLE "scim_sync_runs" (
+  "scim_sync_run_id" UUID PRIMARY KEY,
+
+  "started_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  -- When the run finished, either successfully or not
+  "finished_at" TIMESTAMP WITH TIME ZONE,
+
+  -- If the run failed, why it did
+  "error" TEXT,
+
+  -- The list of changes applied to users during this run
+  "changes" JSONB NOT NULL DEFAULT '[]'::JSONB
+);
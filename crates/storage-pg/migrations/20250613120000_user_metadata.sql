@@ -0,0 +1,29 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Arbitrary per-user key-value metadata, scoped by namespace, so that features
+-- can store small bits of state about users without their own table
+CREATE TABLE "user_metadata" (
+  "user_metadata_id" UUID PRIMARY KEY,
+
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id") ON DELETE CASCADE,
+
+  -- The namespace of the entry, usually the name of the feature owning it
+  "namespace" TEXT NOT NULL,
+
+  "key" TEXT NOT NULL,
+
+  "value" JSONB NOT NULL,
+
+  -- Incremented on every update, used for optimistic concurrency control
+  "version" INTEGER NOT NULL DEFAULT 1,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "updated_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  CONSTRAINT "user_metadata_user_id_namespace_key_unique"
+    UNIQUE ("user_id", "namespace", "key")
+);
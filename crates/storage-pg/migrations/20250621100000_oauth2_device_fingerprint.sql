@@ -0,0 +1,15 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Store the hashed device identifier optionally provided by clients when
+-- starting an authorization, so that it ends up on the resulting session
+ALTER TABLE oauth2_authorization_grants
+  ADD COLUMN device_fingerprint TEXT;
+
+ALTER TABLE oauth2_device_code_grant
+  ADD COLUMN device_fingerprint TEXT;
+
+ALTER TABLE oauth2_sessions
+  ADD COLUMN device_fingerprint TEXT;
@@ -0,0 +1,11 @@
+-- no-transaction
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Used by admins to find all the sessions from the same device
+CREATE INDEX CONCURRENTLY
+  oauth2_sessions_device_fingerprint_idx
+  ON oauth2_sessions (device_fingerprint)
+  WHERE device_fingerprint IS NOT NULL;
@@ -0,0 +1,42 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Daily rollup of the logins going through each upstream OAuth 2.0 provider,
+-- refreshed periodically by a background job
+CREATE TABLE "upstream_oauth_provider_login_stats" (
+  "upstream_oauth_provider_id" UUID NOT NULL
+    REFERENCES "upstream_oauth_providers" ("upstream_oauth_provider_id")
+    ON DELETE CASCADE,
+
+  -- The day (in UTC) on which the authorization sessions were started
+  "day" DATE NOT NULL,
+
+  -- Authorization sessions which ended up logging in a user
+  "successful_logins" BIGINT NOT NULL,
+
+  -- Authorization sessions which failed or were abandoned
+  "failed_logins" BIGINT NOT NULL,
+
+  PRIMARY KEY ("upstream_oauth_provider_id", "day")
+);
+
+-- Daily rollup of the logins requested by each OAuth 2.0 client, through the
+-- authorization code and device code grants
+CREATE TABLE "oauth2_client_login_stats" (
+  "oauth2_client_id" UUID NOT NULL
+    REFERENCES "oauth2_clients" ("oauth2_client_id")
+    ON DELETE CASCADE,
+
+  -- The day (in UTC) on which the grants were created
+  "day" DATE NOT NULL,
+
+  -- Grants which were fulfilled by the user
+  "successful_logins" BIGINT NOT NULL,
+
+  -- Grants which were rejected, or never fulfilled
+  "failed_logins" BIGINT NOT NULL,
+
+  PRIMARY KEY ("oauth2_client_id", "day")
+);
@@ -0,0 +1,38 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- One-time links generated by administrators, which let someone claim a
+-- pre-provisioned account by setting its password on their first visit
+CREATE TABLE "user_claim_links" (
+  "user_claim_link_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  -- The user this link lets claim
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  -- The secret token embedded in the link
+  "token" TEXT NOT NULL
+    UNIQUE,
+
+  -- The email address to which the link was sent, if any
+  "email" TEXT,
+
+  -- The admin session which created the link, for auditing purposes
+  "created_by_oauth2_session_id" UUID
+    REFERENCES "oauth2_sessions" ("oauth2_session_id")
+    ON DELETE SET NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "expires_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  -- When, from where and by what the link was claimed
+  "claimed_at" TIMESTAMP WITH TIME ZONE,
+  "claimed_ip_address" INET,
+  "claimed_user_agent" TEXT,
+
+  "revoked_at" TIMESTAMP WITH TIME ZONE
+);
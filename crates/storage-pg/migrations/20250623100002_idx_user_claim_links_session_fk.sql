@@ -0,0 +1,9 @@
+-- no-transaction
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+CREATE INDEX CONCURRENTLY
+  user_claim_links_created_by_oauth2_session_fk
+  ON user_claim_links (created_by_oauth2_session_id);
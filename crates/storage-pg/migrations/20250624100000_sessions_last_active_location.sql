@@ -0,0 +1,21 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Where the last IP address used by each session is located, as resolved from
+-- the GeoIP databases at the time the activity was recorded
+ALTER TABLE "user_sessions"
+  ADD COLUMN "last_active_country" TEXT,
+  ADD COLUMN "last_active_asn" BIGINT,
+  ADD COLUMN "last_active_as_organization" TEXT;
+
+ALTER TABLE "oauth2_sessions"
+  ADD COLUMN "last_active_country" TEXT,
+  ADD COLUMN "last_active_asn" BIGINT,
+  ADD COLUMN "last_active_as_organization" TEXT;
+
+ALTER TABLE "compat_sessions"
+  ADD COLUMN "last_active_country" TEXT,
+  ADD COLUMN "last_active_asn" BIGINT,
+  ADD COLUMN "last_active_as_organization" TEXT;
@@ -0,0 +1,24 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Rollouts of feature flags set through the admin API, which take precedence
+-- over the ones from the configuration
+CREATE TABLE "feature_flag_overrides" (
+  "feature_flag_override_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  -- The name of the feature flag. There is at most one override per flag.
+  "flag" TEXT NOT NULL
+    UNIQUE,
+
+  -- Whether the feature is enabled at all
+  "enabled" BOOLEAN NOT NULL,
+
+  -- The percentage of users for which the feature is enabled
+  "rollout_percentage" SMALLINT NOT NULL
+    CHECK ("rollout_percentage" BETWEEN 0 AND 100),
+
+  "updated_at" TIMESTAMP WITH TIME ZONE NOT NULL
+);
@@ -0,0 +1,28 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- The JWK SHA-256 thumbprint of the key an access token is bound to, if it was
+-- issued with a DPoP proof
+ALTER TABLE "oauth2_access_tokens"
+  ADD COLUMN "dpop_jkt" TEXT;
+
+-- The DPoP proofs which were already used, to detect replays. They only need to
+-- be kept for as long as they would be accepted.
+CREATE TABLE "oauth2_dpop_proofs" (
+  "oauth2_dpop_proof_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  -- The thumbprint of the key which signed the proof
+  "jkt" TEXT NOT NULL,
+
+  -- The unique identifier of the proof, as chosen by the client
+  "jti" TEXT NOT NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "expires_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  CONSTRAINT "oauth2_dpop_proofs_jkt_jti_unique"
+    UNIQUE ("jkt", "jti")
+);
@@ -0,0 +1,9 @@
+-- no-transaction
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+CREATE INDEX CONCURRENTLY
+  oauth2_dpop_proofs_expires_at_idx
+  ON oauth2_dpop_proofs (expires_at);
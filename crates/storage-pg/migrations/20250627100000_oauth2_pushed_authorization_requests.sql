@@ -0,0 +1,35 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Add a flag on oauth2_clients to indicate whether they must use pushed
+-- authorization requests to start an authorization flow
+ALTER TABLE oauth2_clients
+    ADD COLUMN require_pushed_authorization_requests BOOLEAN
+        NOT NULL DEFAULT FALSE;
+
+-- Authorization request parameters pushed by clients ahead of the
+-- authorization request, as per RFC9126
+CREATE TABLE "oauth2_pushed_authorization_requests" (
+  "oauth2_pushed_authorization_request_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  -- The client which pushed the request
+  "oauth2_client_id" UUID NOT NULL
+    REFERENCES "oauth2_clients" ("oauth2_client_id")
+    ON DELETE CASCADE,
+
+  -- The random part of the request_uri given back to the client
+  "reference" TEXT NOT NULL
+    UNIQUE,
+
+  -- The authorization request parameters, as a flat JSON object
+  "parameters" JSONB NOT NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "expires_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  -- When the request was used by the authorization endpoint
+  "consumed_at" TIMESTAMP WITH TIME ZONE
+);
@@ -0,0 +1,9 @@
+-- no-transaction
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+CREATE INDEX CONCURRENTLY
+  oauth2_pushed_authorization_requests_client_fk
+  ON oauth2_pushed_authorization_requests (oauth2_client_id);
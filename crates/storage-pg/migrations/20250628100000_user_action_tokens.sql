@@ -0,0 +1,32 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Single-use tokens embedded in links sent to users, which let them perform an
+-- action after confirming it, like ending a browser session from an email
+CREATE TABLE "user_action_tokens" (
+  "user_action_token_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  -- The user this token was issued to
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  -- The secret token embedded in the link
+  "token" TEXT NOT NULL
+    UNIQUE,
+
+  -- The action this token lets its bearer perform
+  "action" TEXT NOT NULL,
+
+  -- The browser session targeted by the action, if any
+  "user_session_id" UUID
+    REFERENCES "user_sessions" ("user_session_id")
+    ON DELETE CASCADE,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "expires_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "consumed_at" TIMESTAMP WITH TIME ZONE
+);
@@ -0,0 +1,9 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- The algorithm used to sign JWT-secured authorization responses (JARM) for
+-- this client
+ALTER TABLE oauth2_clients
+    ADD COLUMN authorization_signed_response_alg TEXT;
@@ -0,0 +1,12 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Store the rich authorization request details (RFC 9396) requested by
+-- clients, so that they end up on the resulting session
+ALTER TABLE oauth2_authorization_grants
+  ADD COLUMN authorization_details JSONB;
+
+ALTER TABLE oauth2_sessions
+  ADD COLUMN authorization_details JSONB;
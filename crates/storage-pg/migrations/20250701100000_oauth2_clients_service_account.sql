@@ -0,0 +1,10 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- The scopes a service account client is allowed to request through the
+-- client credentials grant. This is NULL for clients which aren't service
+-- accounts.
+ALTER TABLE oauth2_clients
+    ADD COLUMN service_account_scope_list TEXT[];
@@ -0,0 +1,81 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Add a flag on oauth2_clients to indicate whether they support the
+-- client-initiated backchannel authentication grant, and the endpoint on which
+-- they want to be notified when using the `ping` token delivery mode
+ALTER TABLE oauth2_clients
+    ADD COLUMN grant_type_ciba BOOLEAN
+        NOT NULL DEFAULT FALSE,
+    ADD COLUMN backchannel_client_notification_endpoint TEXT;
+
+-- Authentication requests started by clients on the backchannel
+-- authentication endpoint, as per OpenID Connect CIBA
+--
+-- Like device code grants, this has 4 possible states, only going in one
+-- direction:
+--
+--     [[ Pending ]]
+--       |       |
+--       |  [ Rejected ] -- The `rejected_at` and `user_session_id` fields are set
+--       |
+-- [ Fulfilled ] -- The `fulfilled_at` and `user_session_id` fields are set
+--       |
+-- [ Exchanged ] -- The `exchanged_at` and `oauth2_session_id` fields are also set
+--
+CREATE TABLE "oauth2_backchannel_authentication_grants" (
+  "oauth2_backchannel_authentication_grant_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  -- The client which started the request
+  "oauth2_client_id" UUID NOT NULL
+    REFERENCES "oauth2_clients" ("oauth2_client_id")
+    ON DELETE CASCADE,
+
+  -- The user which has to approve the request
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  -- The scope requested
+  "scope" TEXT NOT NULL,
+
+  -- The random identifier the client uses to get the tokens
+  "auth_req_id" TEXT NOT NULL
+    UNIQUE,
+
+  -- The message displayed on the approval screen
+  "binding_message" TEXT,
+
+  -- The bearer token used to notify the client in the `ping` mode
+  "client_notification_token" TEXT,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "expires_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  -- When the user approved the request
+  -- This is mutually exclusive with rejected_at
+  "fulfilled_at" TIMESTAMP WITH TIME ZONE,
+
+  -- When the user rejected the request
+  -- This is mutually exclusive with fulfilled_at
+  "rejected_at" TIMESTAMP WITH TIME ZONE,
+
+  -- When the request was exchanged for tokens
+  -- This means "fulfilled_at" has also been set
+  "exchanged_at" TIMESTAMP WITH TIME ZONE,
+
+  -- The OAuth 2.0 session generated for this request
+  -- This means "exchanged_at" has also been set
+  "oauth2_session_id" UUID
+    REFERENCES "oauth2_sessions" ("oauth2_session_id")
+    ON DELETE CASCADE,
+
+  -- The browser session the user used to approve or reject the request
+  -- This means "fulfilled_at" or "rejected_at" has also been set
+  "user_session_id" UUID
+    REFERENCES "user_sessions" ("user_session_id")
+    ON DELETE CASCADE
+);
@@ -0,0 +1,15 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Resource indicators (RFC 8707): the resources a client can ask tokens for,
+-- and the resource requested on grants and sessions
+ALTER TABLE oauth2_clients
+  ADD COLUMN allowed_resources TEXT[] NOT NULL DEFAULT '{}';
+
+ALTER TABLE oauth2_authorization_grants
+  ADD COLUMN resource TEXT;
+
+ALTER TABLE oauth2_sessions
+  ADD COLUMN resource TEXT;
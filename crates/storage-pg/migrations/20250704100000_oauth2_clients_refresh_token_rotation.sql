@@ -0,0 +1,10 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Whether the refresh tokens of a client are rotated each time they are used,
+-- or kept for the whole lifetime of the session
+ALTER TABLE oauth2_clients
+  ADD COLUMN refresh_token_rotation TEXT NOT NULL DEFAULT 'rotate_on_use'
+  CHECK (refresh_token_rotation IN ('rotate_on_use', 'static'));
@@ -0,0 +1,11 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Where and how to notify clients when their sessions end, as per the
+-- OpenID Connect Back-Channel Logout specification
+ALTER TABLE oauth2_clients
+  ADD COLUMN backchannel_logout_uri TEXT,
+  ADD COLUMN backchannel_logout_session_required BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN backchannel_logout_include_sub BOOLEAN NOT NULL DEFAULT TRUE;
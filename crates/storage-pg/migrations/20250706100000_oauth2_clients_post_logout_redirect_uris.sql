@@ -0,0 +1,8 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- The URIs a client can redirect the user to after an RP-initiated logout
+ALTER TABLE oauth2_clients
+  ADD COLUMN post_logout_redirect_uris TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,10 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Whether the access tokens issued to a client are opaque strings, or
+-- self-contained JWTs following RFC 9068
+ALTER TABLE oauth2_clients
+  ADD COLUMN access_token_format TEXT NOT NULL DEFAULT 'opaque'
+  CHECK (access_token_format IN ('opaque', 'jwt'));
@@ -0,0 +1,11 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Per-client overrides of the token lifetimes, in seconds. When NULL, the
+-- server-wide lifetimes apply
+ALTER TABLE oauth2_clients
+  ADD COLUMN access_token_ttl INTEGER CHECK (access_token_ttl > 0),
+  ADD COLUMN refresh_token_ttl INTEGER CHECK (refresh_token_ttl > 0),
+  ADD COLUMN device_code_ttl INTEGER CHECK (device_code_ttl > 0);
@@ -0,0 +1,9 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Settings for signed request objects (RFC 9101) sent by clients
+ALTER TABLE oauth2_clients
+  ADD COLUMN request_object_signing_alg TEXT,
+  ADD COLUMN require_signed_request_object BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,9 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Algorithms used to encrypt the ID tokens issued to clients
+ALTER TABLE oauth2_clients
+  ADD COLUMN id_token_encrypted_response_alg TEXT,
+  ADD COLUMN id_token_encrypted_response_enc TEXT;
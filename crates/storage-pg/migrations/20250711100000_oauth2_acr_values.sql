@@ -0,0 +1,12 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- The ACR values clients require by default, and the ACR value required on
+-- authorization grants
+ALTER TABLE oauth2_clients
+  ADD COLUMN default_acr_values TEXT[] NOT NULL DEFAULT '{}';
+
+ALTER TABLE oauth2_authorization_grants
+  ADD COLUMN required_acr TEXT;
@@ -0,0 +1,9 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Whether the client asked for the user to authenticate again with
+-- `prompt=login`. The `max_age` column was already there, but unused
+ALTER TABLE oauth2_authorization_grants
+  ADD COLUMN prompt_login BOOLEAN NOT NULL DEFAULT false;
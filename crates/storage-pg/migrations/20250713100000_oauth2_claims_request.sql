@@ -0,0 +1,13 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- The claims individually requested by the client with the `claims` parameter
+ALTER TABLE oauth2_authorization_grants
+  ADD COLUMN claims JSONB;
+
+-- The claims which were individually requested in the userinfo response, and
+-- consented to by the user
+ALTER TABLE oauth2_sessions
+  ADD COLUMN userinfo_claims TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,9 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- When the user explicitly granted offline access to the client of the
+-- session. A NULL value means they never did
+ALTER TABLE oauth2_sessions
+  ADD COLUMN offline_access_granted_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,12 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Track when the client last polled the token endpoint, and the minimum
+-- interval in seconds it must wait between two polls, which grows every time
+-- the client is told to slow down
+ALTER TABLE oauth2_device_code_grant
+  ADD COLUMN last_polled_at TIMESTAMP WITH TIME ZONE,
+  ADD COLUMN polling_interval INTEGER NOT NULL DEFAULT 5
+    CHECK (polling_interval > 0);
@@ -0,0 +1,9 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- The audience of the tokens a client acting as a resource server accepts
+-- when introspecting them: either 'matrix' or a resource indicator
+ALTER TABLE oauth2_clients
+  ADD COLUMN resource_server_audience TEXT;
@@ -0,0 +1,11 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Let clients have a second secret during a rotation, and let both of them
+-- expire
+ALTER TABLE oauth2_clients
+  ADD COLUMN client_secret_expires_at TIMESTAMP WITH TIME ZONE,
+  ADD COLUMN encrypted_next_client_secret TEXT,
+  ADD COLUMN next_client_secret_expires_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,34 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- The sector identifier used to compute the pairwise subject identifiers of a
+-- client. Clients without one get the public subject identifier of users.
+ALTER TABLE "oauth2_clients"
+  ADD COLUMN "pairwise_sector_identifier" TEXT;
+
+-- The pairwise subject identifiers given out to clients, one per user and
+-- sector identifier. They are random and stored, so that they stay stable and
+-- can be mapped back to the user.
+CREATE TABLE "oauth2_pairwise_subjects" (
+  "oauth2_pairwise_subject_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  "sector_identifier" TEXT NOT NULL,
+
+  -- The `sub` claim the clients of this sector see for the user
+  "subject" TEXT NOT NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  CONSTRAINT "oauth2_pairwise_subjects_user_sector_unique"
+    UNIQUE ("user_id", "sector_identifier"),
+
+  CONSTRAINT "oauth2_pairwise_subjects_sector_subject_unique"
+    UNIQUE ("sector_identifier", "subject")
+);
@@ -0,0 +1,14 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- The scopes a client may request, where a trailing `*` matches any scope
+-- starting with the rest of it. Clients can request any scope if not set.
+ALTER TABLE oauth2_clients
+  ADD COLUMN allowed_scope_list TEXT[];
+
+-- What happens to requests for scopes outside of the allowed ones
+ALTER TABLE oauth2_clients
+  ADD COLUMN disallowed_scope_handling TEXT NOT NULL DEFAULT 'reject'
+  CHECK (disallowed_scope_handling IN ('reject', 'filter'));
@@ -0,0 +1,24 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Step-ups of browser sessions: the user confirmed their identity again to
+-- access something sensitive, which elevates the session until it expires
+CREATE TABLE "user_session_elevations" (
+  "user_session_elevation_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  "user_session_id" UUID NOT NULL
+    REFERENCES "user_sessions" ("user_session_id")
+    ON DELETE CASCADE,
+
+  -- The strongest ACR value satisfied by the step-up, if any
+  "acr" TEXT,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "expires_at" TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX "user_session_elevations_user_session_id_idx"
+  ON "user_session_elevations" ("user_session_id", "expires_at");
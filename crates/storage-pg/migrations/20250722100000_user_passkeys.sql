@@ -0,0 +1,64 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Passkeys (WebAuthn credentials) registered by users to log in
+CREATE TABLE "user_passkeys" (
+  "user_passkey_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  -- The credential ID given by the authenticator, encoded as unpadded base64url
+  "credential_id" TEXT NOT NULL
+    CONSTRAINT "user_passkeys_credential_id_unique"
+    UNIQUE,
+
+  "name" TEXT NOT NULL,
+
+  -- The AAGUID of the authenticator model
+  "aaguid" TEXT NOT NULL,
+
+  -- The public key of the credential, as a JWK
+  "public_key" JSONB NOT NULL,
+
+  "sign_count" BIGINT NOT NULL,
+
+  -- The transports hints given by the authenticator
+  "transports" TEXT[] NOT NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "last_used_at" TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX "user_passkeys_user_id_idx"
+  ON "user_passkeys" ("user_id");
+
+-- The challenges sent to browsers to register or use a passkey
+CREATE TABLE "user_passkey_challenges" (
+  "user_passkey_challenge_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  -- The browser session which registers a passkey. This is not set for
+  -- challenges used to log in.
+  "user_session_id" UUID
+    REFERENCES "user_sessions" ("user_session_id")
+    ON DELETE CASCADE,
+
+  "challenge" BYTEA NOT NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "completed_at" TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX "user_passkey_challenges_user_session_id_idx"
+  ON "user_passkey_challenges" ("user_session_id");
+
+-- Authentications of browser sessions can now be done with a passkey
+ALTER TABLE "user_session_authentications"
+  ADD COLUMN "user_passkey_id" UUID
+    REFERENCES "user_passkeys" ("user_passkey_id")
+    ON DELETE SET NULL;
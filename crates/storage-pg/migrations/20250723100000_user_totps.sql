@@ -0,0 +1,37 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- TOTP second factors enrolled by users. Each user has at most one.
+CREATE TABLE "user_totps" (
+  "user_totp_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE
+    CONSTRAINT "user_totps_user_id_unique"
+    UNIQUE,
+
+  -- The shared secret, encrypted with the site encryption key
+  "encrypted_secret" TEXT NOT NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  -- Set once the user entered a first code. Unconfirmed second factors can't
+  -- be used to log in.
+  "confirmed_at" TIMESTAMP WITH TIME ZONE,
+
+  "last_used_at" TIMESTAMP WITH TIME ZONE,
+
+  -- The time step of the last code accepted, to prevent replays
+  "last_used_step" BIGINT
+);
+
+-- Authentications of browser sessions can now include a TOTP second factor,
+-- alongside the password
+ALTER TABLE "user_session_authentications"
+  ADD COLUMN "user_totp_id" UUID
+    REFERENCES "user_totps" ("user_totp_id")
+    ON DELETE SET NULL;
@@ -0,0 +1,33 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Single-use codes which let users log in when their second factor is
+-- unavailable. They are generated as a set, which replaces any previous one.
+CREATE TABLE "user_recovery_codes" (
+  "user_recovery_code_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  -- The SHA-256 hash of the code, hex-encoded
+  "code_hash" TEXT NOT NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  -- Set once the code was used to log in
+  "consumed_at" TIMESTAMP WITH TIME ZONE,
+
+  CONSTRAINT "user_recovery_codes_user_id_code_hash_unique"
+    UNIQUE ("user_id", "code_hash")
+);
+
+-- Authentications of browser sessions can now use a recovery code instead of
+-- a TOTP code, alongside the password
+ALTER TABLE "user_session_authentications"
+  ADD COLUMN "user_recovery_code_id" UUID
+    REFERENCES "user_recovery_codes" ("user_recovery_code_id")
+    ON DELETE SET NULL;
@@ -0,0 +1,17 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Allow using user email authentications to log in without a password, in
+-- which case they are bound to the user owning the email address
+ALTER TABLE "user_email_authentications"
+  ADD COLUMN "user_id" UUID
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE;
+
+-- Authentications of browser sessions can now use a code sent by email
+ALTER TABLE "user_session_authentications"
+  ADD COLUMN "user_email_authentication_id" UUID
+    REFERENCES "user_email_authentications" ("user_email_authentication_id")
+    ON DELETE SET NULL;
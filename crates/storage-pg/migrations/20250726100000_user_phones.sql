@@ -0,0 +1,52 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Phone numbers added by users, to which codes are sent by SMS. Each user has
+-- at most one.
+CREATE TABLE "user_phones" (
+  "user_phone_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE
+    CONSTRAINT "user_phones_user_id_unique"
+    UNIQUE,
+
+  -- The phone number, in the E.164 format
+  "phone_number" TEXT NOT NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  -- Set once the user entered a first code sent to the phone number.
+  -- Unconfirmed phone numbers can't be used as a second factor.
+  "confirmed_at" TIMESTAMP WITH TIME ZONE
+);
+
+-- Codes sent by SMS to confirm phone numbers and complete logins
+CREATE TABLE "user_phone_codes" (
+  "user_phone_code_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  "user_phone_id" UUID NOT NULL
+    REFERENCES "user_phones" ("user_phone_id")
+    ON DELETE CASCADE,
+
+  "code" TEXT NOT NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "expires_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "consumed_at" TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX "user_phone_codes_user_phone_id_idx"
+  ON "user_phone_codes" ("user_phone_id");
+
+-- Authentications of browser sessions can now include a code sent by SMS,
+-- alongside the password
+ALTER TABLE "user_session_authentications"
+  ADD COLUMN "user_phone_id" UUID
+    REFERENCES "user_phones" ("user_phone_id")
+    ON DELETE SET NULL;
@@ -0,0 +1,52 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Failed password attempts on user accounts, used to temporarily lock accounts
+-- and IP addresses after too many of them. They are cleared when the user
+-- successfully logs in.
+CREATE TABLE "user_password_failures" (
+  "user_password_failure_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  -- The IP address the attempt came from, if known
+  "ip_address" INET,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX "user_password_failures_user_id_created_at_idx"
+  ON "user_password_failures" ("user_id", "created_at");
+
+CREATE INDEX "user_password_failures_ip_address_created_at_idx"
+  ON "user_password_failures" ("ip_address", "created_at");
+
+-- Used to cleanup old failures
+CREATE INDEX "user_password_failures_created_at_idx"
+  ON "user_password_failures" ("created_at");
+
+-- Record of the lockouts triggered by too many failed password attempts. Unlike
+-- the failures, they are kept after the user successfully logs in.
+CREATE TABLE "user_password_lockouts" (
+  "user_password_lockout_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  -- The account which was locked, or NULL if the password logins from the IP
+  -- address were restricted
+  "user_id" UUID
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  -- The IP address the attempt which triggered the lockout came from, if known
+  "ip_address" INET,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX "user_password_lockouts_user_id_created_at_idx"
+  ON "user_password_lockouts" ("user_id", "created_at");
@@ -0,0 +1,22 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Per-user overrides of the password expiry and history policies, set by
+-- administrators. NULL values fall back to the global policy.
+CREATE TABLE "user_password_policies" (
+  "user_id" UUID NOT NULL
+    PRIMARY KEY
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  -- Maximum age of the password of the user, in seconds. Zero means the
+  -- password never expires.
+  "max_age_seconds" BIGINT,
+
+  -- Number of previous passwords the user can't reuse
+  "history_size" INTEGER,
+
+  "updated_at" TIMESTAMP WITH TIME ZONE NOT NULL
+);
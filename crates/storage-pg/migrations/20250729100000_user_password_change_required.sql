@@ -0,0 +1,10 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Set when an administrator requires the user to change their password the
+-- next time they log in. Setting a new password clears it, as the new password
+-- is a new row.
+ALTER TABLE "user_passwords"
+  ADD COLUMN "change_required_at" TIMESTAMP WITH TIME ZONE;
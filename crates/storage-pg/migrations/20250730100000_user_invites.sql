@@ -0,0 +1,40 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Registration tokens can be issued by users, as invites
+ALTER TABLE "user_registration_tokens"
+  -- The user who issued the token, if any
+  ADD COLUMN "created_by_user_id" UUID
+    REFERENCES "users" ("user_id")
+    ON DELETE SET NULL,
+
+  -- Whether the users who register with this token get the user who issued
+  -- it as their referrer
+  ADD COLUMN "assign_referrer" BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Which user invited which, for users who registered with an invite
+CREATE TABLE "user_referrals" (
+  "user_id" UUID NOT NULL
+    PRIMARY KEY
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  "referrer_user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  -- The registration token the user registered with
+  "user_registration_token_id" UUID
+    REFERENCES "user_registration_tokens" ("user_registration_token_id")
+    ON DELETE SET NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX "user_referrals_referrer_user_id_idx"
+  ON "user_referrals" ("referrer_user_id");
+
+CREATE INDEX "user_referrals_user_registration_token_id_idx"
+  ON "user_referrals" ("user_registration_token_id");
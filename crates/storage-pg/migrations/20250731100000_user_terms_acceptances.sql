@@ -0,0 +1,28 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Versions of the terms of service and privacy policy accepted by users
+CREATE TABLE "user_terms_acceptances" (
+  "user_terms_acceptance_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  -- Either 'terms_of_service' or 'privacy_policy'
+  "kind" TEXT NOT NULL,
+
+  -- The opaque version identifier from the configuration
+  "version" TEXT NOT NULL,
+
+  -- The URL the document was published at when the user accepted it
+  "url" TEXT NOT NULL,
+
+  "accepted_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  CONSTRAINT "user_terms_acceptances_user_id_kind_version_unique"
+    UNIQUE ("user_id", "kind", "version")
+);
@@ -0,0 +1,26 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Browsers users chose to trust after entering their second factor. Password
+-- logins from them skip the second factor until they expire or are revoked.
+CREATE TABLE "user_trusted_devices" (
+  "user_trusted_device_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  -- The user agent of the browser when it was trusted
+  "user_agent" TEXT,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "expires_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "last_used_at" TIMESTAMP WITH TIME ZONE,
+  "revoked_at" TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX "user_trusted_devices_user_id_idx"
+  ON "user_trusted_devices" ("user_id");
@@ -0,0 +1,13 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Recovery sessions can need an approval from an administrator before the
+-- recovery links are sent. Users can then also say they lost access to their
+-- second factor, which gets removed once they pick a new password.
+ALTER TABLE "user_recovery_sessions"
+  ADD COLUMN "lost_second_factor" BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN "requires_approval" BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN "approved_at" TIMESTAMP WITH TIME ZONE,
+  ADD COLUMN "denied_at" TIMESTAMP WITH TIME ZONE;
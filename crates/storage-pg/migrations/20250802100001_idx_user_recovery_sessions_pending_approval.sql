@@ -0,0 +1,12 @@
+-- no-transaction
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Used by administrators to list the recovery sessions waiting for their
+-- review
+CREATE INDEX CONCURRENTLY
+  user_recovery_sessions_pending_approval_idx
+  ON user_recovery_sessions (created_at)
+  WHERE requires_approval AND approved_at IS NULL AND denied_at IS NULL;
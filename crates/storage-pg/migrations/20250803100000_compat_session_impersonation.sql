@@ -0,0 +1,10 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Compatibility sessions minted by an administrator to act as a user, for
+-- support purposes. This isn't a foreign key on purpose: the session must stay
+-- marked as an impersonation even if the admin session is deleted later on.
+ALTER TABLE "compat_sessions"
+  ADD COLUMN "impersonated_by_oauth2_session_id" UUID;
@@ -0,0 +1,30 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Account deletions requested by users. The account is deactivated straight
+-- away, but only erased once the grace period is over, unless the user
+-- cancelled the deletion in the meantime.
+CREATE TABLE "user_deletions" (
+  "user_deletion_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  -- Whether to ask the homeserver to erase the user once the grace period is
+  -- over
+  "hs_erase" BOOLEAN NOT NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "delete_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "cancelled_at" TIMESTAMP WITH TIME ZONE,
+  "completed_at" TIMESTAMP WITH TIME ZONE
+);
+
+-- A user can only have one deletion in progress at a time
+CREATE UNIQUE INDEX "user_deletions_pending_user_id_idx"
+  ON "user_deletions" ("user_id")
+  WHERE "cancelled_at" IS NULL AND "completed_at" IS NULL;
@@ -0,0 +1,27 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Record of the username changes of users. The previous usernames stay
+-- reserved, so that no one else can take them and be mistaken for the user.
+CREATE TABLE "user_username_changes" (
+  "user_username_change_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  "user_id" UUID NOT NULL
+    REFERENCES "users" ("user_id")
+    ON DELETE CASCADE,
+
+  "old_username" TEXT NOT NULL,
+  "new_username" TEXT NOT NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- Used to check whether a username is reserved
+CREATE INDEX "user_username_changes_old_username_lower_idx"
+  ON "user_username_changes" (LOWER("old_username"));
+
+CREATE INDEX "user_username_changes_user_id_idx"
+  ON "user_username_changes" ("user_id");
@@ -0,0 +1,10 @@
+-- no-transaction
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Used to find users by their phone number when they log in with it
+CREATE INDEX CONCURRENTLY
+  user_phones_phone_number_idx
+  ON user_phones (phone_number);
@@ -0,0 +1,9 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Keep track of the attributes last imported from the upstream provider, so
+-- that we can tell whether the user changed them locally since
+ALTER TABLE "upstream_oauth_links"
+  ADD COLUMN "imported_attributes" JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,9 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Keep the verified claims of the ID token, so that sessions can be found by
+-- their `sub` and `sid` when the upstream provider sends a logout token
+ALTER TABLE "upstream_oauth_authorization_sessions"
+  ADD COLUMN "id_token_claims" JSONB;
@@ -0,0 +1,9 @@
+-- no-transaction
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+CREATE INDEX CONCURRENTLY
+  upstream_oauth_authorization_sessions_sid_claim_idx
+  ON upstream_oauth_authorization_sessions (upstream_oauth_provider_id, (id_token_claims->>'sid'));
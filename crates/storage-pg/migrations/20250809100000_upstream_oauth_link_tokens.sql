@@ -0,0 +1,24 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Whether to keep the tokens issued by the upstream provider on login
+ALTER TABLE "upstream_oauth_providers"
+  ADD COLUMN "store_tokens" BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- The latest tokens issued by the upstream provider for a link, encrypted with
+-- the key from the configuration
+CREATE TABLE "upstream_oauth_link_tokens" (
+  "upstream_oauth_link_id" UUID NOT NULL
+    PRIMARY KEY
+    REFERENCES "upstream_oauth_links" ("upstream_oauth_link_id")
+    ON DELETE CASCADE,
+
+  "encrypted_access_token" TEXT NOT NULL,
+  "encrypted_refresh_token" TEXT,
+  "access_token_expires_at" TIMESTAMP WITH TIME ZONE,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "updated_at" TIMESTAMP WITH TIME ZONE NOT NULL
+);
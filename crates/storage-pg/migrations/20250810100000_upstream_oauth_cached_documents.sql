@@ -0,0 +1,16 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- The last discovery documents and JWKS successfully fetched from the upstream
+-- providers, so that they can still be used if a provider is unreachable
+CREATE TABLE "upstream_oauth_cached_documents" (
+  "kind" TEXT NOT NULL
+    CHECK ("kind" IN ('discovery', 'jwks')),
+  "url" TEXT NOT NULL,
+  "document" JSONB NOT NULL,
+  "fetched_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  PRIMARY KEY ("kind", "url")
+);
@@ -0,0 +1,12 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Add columns to the upstream_oauth_providers table to control which
+-- parameters of the authorization request are forwarded to the provider.
+-- `prompt=login` was always forwarded so far, hence the default.
+ALTER TABLE "upstream_oauth_providers"
+    ADD COLUMN "forward_prompt" BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN "forward_acr_values" BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN "forward_ui_locales" BOOLEAN NOT NULL DEFAULT FALSE;
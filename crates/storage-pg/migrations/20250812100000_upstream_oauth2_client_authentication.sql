@@ -0,0 +1,12 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Add columns to the upstream_oauth_providers table for the key used to sign
+-- client assertions with `private_key_jwt`, and the TLS client certificate
+-- presented to the provider for mutual TLS.
+ALTER TABLE "upstream_oauth_providers"
+    ADD COLUMN "token_endpoint_signing_key_id" TEXT,
+    ADD COLUMN "tls_client_certificate" TEXT,
+    ADD COLUMN "encrypted_tls_client_key" TEXT;
@@ -0,0 +1,14 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Add a column to the upstream_oauth_providers table for the settings of the
+-- automatic deprovisioning of accounts whose upstream identity was removed
+ALTER TABLE "upstream_oauth_providers"
+    ADD COLUMN "deprovisioning" JSONB;
+
+-- Record when the upstream provider last refused the stored refresh token,
+-- which is cleared when new tokens are stored
+ALTER TABLE "upstream_oauth_link_tokens"
+    ADD COLUMN "refresh_failed_at" TIMESTAMP WITH TIME ZONE;
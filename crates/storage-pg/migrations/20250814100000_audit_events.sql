@@ -0,0 +1,24 @@
+-- Copyright 2025 New Vector Ltd.
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+-- Please see LICENSE in the repository root for full details.
+
+-- Audit log of security-relevant events. The details of each event are kept
+-- in the `data` column, tagged with the kind of event.
+CREATE TABLE "audit_events" (
+  "audit_event_id" UUID NOT NULL
+    PRIMARY KEY,
+
+  -- The events are kept when the user is removed, as they may be needed to
+  -- investigate what happened
+  "user_id" UUID
+    REFERENCES "users" ("user_id")
+    ON DELETE SET NULL,
+
+  "data" JSONB NOT NULL,
+
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX "audit_events_user_id_idx"
+  ON "audit_events" ("user_id");
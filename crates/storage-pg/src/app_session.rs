@@ -7,7 +7,9 @@
 //! A module containing PostgreSQL implementation of repositories for sessions
 
 use async_trait::async_trait;
-use mas_data_model::{CompatSession, CompatSessionState, Device, Session, SessionState, User};
+use mas_data_model::{
+    CompatSession, CompatSessionState, Device, IpLocation, Session, SessionState, User,
+};
 use mas_storage::{
     Clock, Page, Pagination,
     app_session::{AppSession, AppSessionFilter, AppSessionRepository, AppSessionState},
@@ -53,7 +55,9 @@ mod priv_ {
     use std::net::IpAddr;
 
     use chrono::{DateTime, Utc};
+    use oauth2_types::authorization_details::AuthorizationDetail;
     use sea_query::enum_def;
+    use sqlx::types::Json;
     use uuid::Uuid;
 
     #[derive(sqlx::FromRow)]
@@ -71,9 +75,18 @@ mod priv_ {
         pub(super) created_at: DateTime<Utc>,
         pub(super) finished_at: Option<DateTime<Utc>>,
         pub(super) is_synapse_admin: Option<bool>,
+        pub(super) impersonated_by_oauth2_session_id: Option<Uuid>,
         pub(super) user_agent: Option<String>,
         pub(super) last_active_at: Option<DateTime<Utc>>,
         pub(super) last_active_ip: Option<IpAddr>,
+        pub(super) last_active_country: Option<String>,
+        pub(super) last_active_asn: Option<i64>,
+        pub(super) last_active_as_organization: Option<String>,
+        pub(super) device_fingerprint: Option<String>,
+        pub(super) authorization_details: Option<Json<Vec<AuthorizationDetail>>>,
+        pub(super) resource: Option<String>,
+        pub(super) userinfo_claims: Option<Vec<String>>,
+        pub(super) offline_access_granted_at: Option<DateTime<Utc>>,
     }
 }
 
@@ -99,12 +112,35 @@ impl TryFrom<AppSessionLookup> for AppSession {
             created_at,
             finished_at,
             is_synapse_admin,
+            impersonated_by_oauth2_session_id,
             user_agent,
             last_active_at,
             last_active_ip,
+            last_active_country,
+            last_active_asn,
+            last_active_as_organization,
+            device_fingerprint,
+            authorization_details,
+            resource,
+            userinfo_claims,
+            offline_access_granted_at,
         } = value;
 
         let user_session_id = user_session_id.map(Ulid::from);
+        let last_active_asn = last_active_asn
+            .map(u32::try_from)
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("sessions")
+                    .column("last_active_asn")
+                    .row(cursor.into())
+                    .source(e)
+            })?;
+        let last_active_location = IpLocation {
+            country: last_active_country,
+            asn: last_active_asn,
+            as_organization: last_active_as_organization,
+        };
 
         match (
             compat_session_id,
@@ -149,9 +185,11 @@ impl TryFrom<AppSessionLookup> for AppSession {
                     user_session_id,
                     created_at,
                     is_synapse_admin,
+                    impersonated_by_session_id: impersonated_by_oauth2_session_id.map(Ulid::from),
                     user_agent,
                     last_active_at,
                     last_active_ip,
+                    last_active_location,
                 };
 
                 Ok(AppSession::Compat(Box::new(session)))
@@ -181,6 +219,16 @@ impl TryFrom<AppSessionLookup> for AppSession {
                     Some(finished_at) => SessionState::Finished { finished_at },
                 };
 
+                let resource = resource
+                    .map(|resource| resource.parse())
+                    .transpose()
+                    .map_err(|e| {
+                        DatabaseInconsistencyError::on("oauth2_sessions")
+                            .column("resource")
+                            .row(id)
+                            .source(e)
+                    })?;
+
                 let session = Session {
                     id,
                     state,
@@ -192,7 +240,15 @@ impl TryFrom<AppSessionLookup> for AppSession {
                     user_agent,
                     last_active_at,
                     last_active_ip,
+                    last_active_location,
                     human_name,
+                    device_fingerprint,
+                    authorization_details: authorization_details
+                        .map(|Json(x)| x)
+                        .unwrap_or_default(),
+                    resource,
+                    userinfo_claims: userinfo_claims.unwrap_or_default(),
+                    offline_access_granted_at,
                 };
 
                 Ok(AppSession::OAuth2(Box::new(session)))
@@ -313,6 +369,10 @@ impl AppSessionRepository for PgAppSessionRepository<'_> {
                 AppSessionLookupIden::FinishedAt,
             )
             .expr_as(Expr::cust("NULL"), AppSessionLookupIden::IsSynapseAdmin)
+            .expr_as(
+                Expr::cust("NULL"),
+                AppSessionLookupIden::ImpersonatedByOauth2SessionId,
+            )
             .expr_as(
                 Expr::col((OAuth2Sessions::Table, OAuth2Sessions::UserAgent)),
                 AppSessionLookupIden::UserAgent,
@@ -325,6 +385,44 @@ impl AppSessionRepository for PgAppSessionRepository<'_> {
                 Expr::col((OAuth2Sessions::Table, OAuth2Sessions::LastActiveIp)),
                 AppSessionLookupIden::LastActiveIp,
             )
+            .expr_as(
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::LastActiveCountry)),
+                AppSessionLookupIden::LastActiveCountry,
+            )
+            .expr_as(
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::LastActiveAsn)),
+                AppSessionLookupIden::LastActiveAsn,
+            )
+            .expr_as(
+                Expr::col((
+                    OAuth2Sessions::Table,
+                    OAuth2Sessions::LastActiveAsOrganization,
+                )),
+                AppSessionLookupIden::LastActiveAsOrganization,
+            )
+            .expr_as(
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::DeviceFingerprint)),
+                AppSessionLookupIden::DeviceFingerprint,
+            )
+            .expr_as(
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::AuthorizationDetails)),
+                AppSessionLookupIden::AuthorizationDetails,
+            )
+            .expr_as(
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::Resource)),
+                AppSessionLookupIden::Resource,
+            )
+            .expr_as(
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::UserinfoClaims)),
+                AppSessionLookupIden::UserinfoClaims,
+            )
+            .expr_as(
+                Expr::col((
+                    OAuth2Sessions::Table,
+                    OAuth2Sessions::OfflineAccessGrantedAt,
+                )),
+                AppSessionLookupIden::OfflineAccessGrantedAt,
+            )
             .from(OAuth2Sessions::Table)
             .apply_filter(oauth2_filter)
             .clone();
@@ -369,6 +467,13 @@ impl AppSessionRepository for PgAppSessionRepository<'_> {
                 Expr::col((CompatSessions::Table, CompatSessions::IsSynapseAdmin)),
                 AppSessionLookupIden::IsSynapseAdmin,
             )
+            .expr_as(
+                Expr::col((
+                    CompatSessions::Table,
+                    CompatSessions::ImpersonatedByOAuth2SessionId,
+                )),
+                AppSessionLookupIden::ImpersonatedByOauth2SessionId,
+            )
             .expr_as(
                 Expr::col((CompatSessions::Table, CompatSessions::UserAgent)),
                 AppSessionLookupIden::UserAgent,
@@ -381,6 +486,32 @@ impl AppSessionRepository for PgAppSessionRepository<'_> {
                 Expr::col((CompatSessions::Table, CompatSessions::LastActiveIp)),
                 AppSessionLookupIden::LastActiveIp,
             )
+            .expr_as(
+                Expr::col((CompatSessions::Table, CompatSessions::LastActiveCountry)),
+                AppSessionLookupIden::LastActiveCountry,
+            )
+            .expr_as(
+                Expr::col((CompatSessions::Table, CompatSessions::LastActiveAsn)),
+                AppSessionLookupIden::LastActiveAsn,
+            )
+            .expr_as(
+                Expr::col((
+                    CompatSessions::Table,
+                    CompatSessions::LastActiveAsOrganization,
+                )),
+                AppSessionLookupIden::LastActiveAsOrganization,
+            )
+            .expr_as(Expr::cust("NULL"), AppSessionLookupIden::DeviceFingerprint)
+            .expr_as(
+                Expr::cust("NULL"),
+                AppSessionLookupIden::AuthorizationDetails,
+            )
+            .expr_as(Expr::cust("NULL"), AppSessionLookupIden::Resource)
+            .expr_as(Expr::cust("NULL"), AppSessionLookupIden::UserinfoClaims)
+            .expr_as(
+                Expr::cust("NULL"),
+                AppSessionLookupIden::OfflineAccessGrantedAt,
+            )
             .from(CompatSessions::Table)
             .apply_filter(compat_filter)
             .clone();
@@ -575,7 +706,16 @@ mod tests {
         let device = Device::generate(&mut rng);
         let compat_session = repo
             .compat_session()
-            .add(&mut rng, &clock, &user, device.clone(), None, false, None)
+            .add(
+                &mut rng,
+                &clock,
+                &user,
+                device.clone(),
+                None,
+                false,
+                None,
+                None,
+            )
             .await
             .unwrap();
 
@@ -647,6 +787,19 @@ mod tests {
                 None,
                 None,
                 Some("https://example.com/login".parse().unwrap()),
+                None,
+                None,
+                false,
+                Vec::new(),
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                Vec::new(),
+                None,
             )
             .await
             .unwrap();
@@ -0,0 +1,202 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! A module containing the PostgreSQL implementation of the audit log
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{AuditEvent, AuditEventKind, User};
+use mas_storage::{Clock, audit_event::AuditEventRepository};
+use rand::RngCore;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, DatabaseInconsistencyError, tracing::ExecuteExt};
+
+/// An implementation of [`AuditEventRepository`] for a PostgreSQL connection
+pub struct PgAuditEventRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgAuditEventRepository<'c> {
+    /// Create a new [`PgAuditEventRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct AuditEventLookup {
+    audit_event_id: Uuid,
+    user_id: Option<Uuid>,
+    data: serde_json::Value,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<AuditEventLookup> for AuditEvent {
+    type Error = DatabaseInconsistencyError;
+
+    fn try_from(value: AuditEventLookup) -> Result<Self, Self::Error> {
+        let id = Ulid::from(value.audit_event_id);
+        let kind = serde_json::from_value(value.data).map_err(|e| {
+            DatabaseInconsistencyError::on("audit_events")
+                .column("data")
+                .row(id)
+                .source(e)
+        })?;
+
+        Ok(AuditEvent {
+            id,
+            user_id: value.user_id.map(Ulid::from),
+            kind,
+            created_at: value.created_at,
+        })
+    }
+}
+
+#[async_trait]
+impl AuditEventRepository for PgAuditEventRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.audit_event.add",
+        skip_all,
+        fields(
+            db.query.text,
+            audit_event.id,
+            user.id = user_id.map(tracing::field::display),
+        ),
+        err,
+    )]
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_id: Option<Ulid>,
+        kind: AuditEventKind,
+    ) -> Result<AuditEvent, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("audit_event.id", tracing::field::display(id));
+
+        let data = serde_json::to_value(&kind).map_err(DatabaseError::to_invalid_operation)?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO audit_events
+                    ( audit_event_id
+                    , user_id
+                    , data
+                    , created_at
+                    )
+                VALUES ($1, $2, $3, $4)
+            "#,
+            Uuid::from(id),
+            user_id.map(Uuid::from),
+            data,
+            created_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(AuditEvent {
+            id,
+            user_id,
+            kind,
+            created_at,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.audit_event.all_for_user",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn all_for_user(&mut self, user: &User) -> Result<Vec<AuditEvent>, Self::Error> {
+        let res = sqlx::query_as!(
+            AuditEventLookup,
+            r#"
+                SELECT audit_event_id
+                     , user_id
+                     , data
+                     , created_at
+                FROM audit_events
+                WHERE user_id = $1
+                ORDER BY audit_event_id ASC
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        let events = res
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, DatabaseInconsistencyError>>()?;
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mas_data_model::AuditEventKind;
+    use mas_storage::{
+        Clock, RepositoryAccess, audit_event::AuditEventRepository, clock::MockClock,
+        user::UserRepository,
+    };
+    use rand::SeedableRng;
+    use sqlx::PgPool;
+    use ulid::Ulid;
+
+    use crate::PgRepository;
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_audit_events(pool: PgPool) {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut rng, &clock, "john".to_owned())
+            .await
+            .unwrap();
+
+        // Nothing was recorded yet
+        let events = repo.audit_event().all_for_user(&user).await.unwrap();
+        assert!(events.is_empty());
+
+        let kind = AuditEventKind::RefreshTokenReused {
+            oauth2_session_id: Ulid::from_datetime_with_source(clock.now().into(), &mut rng),
+            oauth2_client_id: Ulid::from_datetime_with_source(clock.now().into(), &mut rng),
+            refresh_token_id: Ulid::from_datetime_with_source(clock.now().into(), &mut rng),
+        };
+
+        let event = repo
+            .audit_event()
+            .add(&mut rng, &clock, Some(user.id), kind.clone())
+            .await
+            .unwrap();
+        assert_eq!(event.user_id, Some(user.id));
+        assert_eq!(event.kind, kind);
+
+        // Events which aren't related to a user can be recorded as well
+        repo.audit_event()
+            .add(&mut rng, &clock, None, kind)
+            .await
+            .unwrap();
+
+        let events = repo.audit_event().all_for_user(&user).await.unwrap();
+        assert_eq!(events, vec![event]);
+    }
+}
@@ -20,7 +20,7 @@ pub use self::{
 #[cfg(test)]
 mod tests {
     use chrono::Duration;
-    use mas_data_model::Device;
+    use mas_data_model::{Device, IpLocation};
     use mas_storage::{
         Clock, Pagination, RepositoryAccess,
         clock::MockClock,
@@ -79,7 +79,16 @@ mod tests {
         let device_str = device.as_str().to_owned();
         let session = repo
             .compat_session()
-            .add(&mut rng, &clock, &user, device.clone(), None, false, None)
+            .add(
+                &mut rng,
+                &clock,
+                &user,
+                device.clone(),
+                None,
+                false,
+                None,
+                None,
+            )
             .await
             .unwrap();
         assert_eq!(session.user_id, user.id);
@@ -228,6 +237,7 @@ mod tests {
                 Some(&browser_session),
                 false,
                 None,
+                None,
             )
             .await
             .unwrap();
@@ -313,6 +323,118 @@ mod tests {
         assert_eq!(repo.compat_session().count(active).await.unwrap(), 0);
     }
 
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_session_filter_by_device_prefix_and_activity(pool: PgPool) {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+
+        let user = repo
+            .user()
+            .add(&mut rng, &clock, "john".to_owned())
+            .await
+            .unwrap();
+
+        let mut sessions = Vec::new();
+        for device in ["BRIDGE_1", "BRIDGE_2", "BRIDGEX", "PHONE"] {
+            let session = repo
+                .compat_session()
+                .add(
+                    &mut rng,
+                    &clock,
+                    &user,
+                    Device::from(device.to_owned()),
+                    None,
+                    false,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            sessions.push(session);
+        }
+
+        let all = CompatSessionFilter::new().for_user(&user);
+        assert_eq!(
+            repo.compat_session()
+                .count(all.with_device_prefix("BRIDGE"))
+                .await
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            repo.compat_session()
+                .count(all.with_device_prefix("PHONE"))
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            repo.compat_session()
+                .count(all.with_device_prefix("bridge"))
+                .await
+                .unwrap(),
+            0
+        );
+        // The underscore must be matched literally, not as a wildcard
+        assert_eq!(
+            repo.compat_session()
+                .count(all.with_device_prefix("BRIDGE_"))
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            repo.compat_session()
+                .count(all.with_device_prefix("%"))
+                .await
+                .unwrap(),
+            0
+        );
+
+        // Record activity on the first two sessions, at different times
+        let first_activity = clock.now();
+        clock.advance(Duration::try_minutes(10).unwrap());
+        let second_activity = clock.now();
+        repo.compat_session()
+            .record_batch_activity(vec![
+                (sessions[0].id, first_activity, None, IpLocation::default()),
+                (sessions[1].id, second_activity, None, IpLocation::default()),
+            ])
+            .await
+            .unwrap();
+
+        let window = all
+            .with_last_active_after(first_activity)
+            .with_last_active_before(second_activity + Duration::try_minutes(1).unwrap());
+        let list = repo
+            .compat_session()
+            .list(window, Pagination::first(10))
+            .await
+            .unwrap();
+        assert_eq!(list.edges.len(), 1);
+        assert_eq!(list.edges[0].0.id, sessions[1].id);
+
+        // The filters can be combined to end a set of sessions at once
+        let affected = repo
+            .compat_session()
+            .finish_bulk(
+                &clock,
+                all.with_device_prefix("BRIDGE")
+                    .with_last_active_before(second_activity),
+            )
+            .await
+            .unwrap();
+        assert_eq!(affected, 1);
+        assert_eq!(
+            repo.compat_session()
+                .count(all.finished_only())
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
     #[sqlx::test(migrator = "crate::MIGRATOR")]
     async fn test_access_token_repository(pool: PgPool) {
         const FIRST_TOKEN: &str = "first_access_token";
@@ -332,7 +454,7 @@ mod tests {
         let device = Device::generate(&mut rng);
         let session = repo
             .compat_session()
-            .add(&mut rng, &clock, &user, device, None, false, None)
+            .add(&mut rng, &clock, &user, device, None, false, None, None)
             .await
             .unwrap();
 
@@ -453,7 +575,7 @@ mod tests {
         let device = Device::generate(&mut rng);
         let session = repo
             .compat_session()
-            .add(&mut rng, &clock, &user, device, None, false, None)
+            .add(&mut rng, &clock, &user, device, None, false, None, None)
             .await
             .unwrap();
 
@@ -619,7 +741,7 @@ mod tests {
         let device = Device::generate(&mut rng);
         let compat_session = repo
             .compat_session()
-            .add(&mut rng, &clock, &user, device, None, false, None)
+            .add(&mut rng, &clock, &user, device, None, false, None, None)
             .await
             .unwrap();
 
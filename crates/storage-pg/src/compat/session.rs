@@ -10,14 +10,14 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use mas_data_model::{
     BrowserSession, CompatSession, CompatSessionState, CompatSsoLogin, CompatSsoLoginState, Device,
-    User,
+    IpLocation, Session, User,
 };
 use mas_storage::{
     Clock, Page, Pagination,
     compat::{CompatSessionFilter, CompatSessionRepository},
 };
 use rand::RngCore;
-use sea_query::{Expr, PostgresQueryBuilder, Query, enum_def};
+use sea_query::{Expr, LikeExpr, PostgresQueryBuilder, Query, enum_def};
 use sea_query_binder::SqlxBinder;
 use sqlx::PgConnection;
 use ulid::Ulid;
@@ -26,6 +26,7 @@ use uuid::Uuid;
 
 use crate::{
     DatabaseError, DatabaseInconsistencyError,
+    estimate::estimate_rows,
     filter::{Filter, StatementExt, StatementWithJoinsExt},
     iden::{CompatSessions, CompatSsoLogins},
     pagination::QueryBuilderExt,
@@ -54,13 +55,19 @@ struct CompatSessionLookup {
     created_at: DateTime<Utc>,
     finished_at: Option<DateTime<Utc>>,
     is_synapse_admin: bool,
+    impersonated_by_oauth2_session_id: Option<Uuid>,
     user_agent: Option<String>,
     last_active_at: Option<DateTime<Utc>>,
     last_active_ip: Option<IpAddr>,
+    last_active_country: Option<String>,
+    last_active_asn: Option<i64>,
+    last_active_as_organization: Option<String>,
 }
 
-impl From<CompatSessionLookup> for CompatSession {
-    fn from(value: CompatSessionLookup) -> Self {
+impl TryFrom<CompatSessionLookup> for CompatSession {
+    type Error = DatabaseInconsistencyError;
+
+    fn try_from(value: CompatSessionLookup) -> Result<Self, Self::Error> {
         let id = value.compat_session_id.into();
 
         let state = match value.finished_at {
@@ -68,7 +75,18 @@ impl From<CompatSessionLookup> for CompatSession {
             Some(finished_at) => CompatSessionState::Finished { finished_at },
         };
 
-        CompatSession {
+        let last_active_asn = value
+            .last_active_asn
+            .map(u32::try_from)
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("compat_sessions")
+                    .column("last_active_asn")
+                    .row(id)
+                    .source(e)
+            })?;
+
+        Ok(CompatSession {
             id,
             state,
             user_id: value.user_id.into(),
@@ -77,10 +95,16 @@ impl From<CompatSessionLookup> for CompatSession {
             human_name: value.human_name,
             created_at: value.created_at,
             is_synapse_admin: value.is_synapse_admin,
+            impersonated_by_session_id: value.impersonated_by_oauth2_session_id.map(Ulid::from),
             user_agent: value.user_agent,
             last_active_at: value.last_active_at,
             last_active_ip: value.last_active_ip,
-        }
+            last_active_location: IpLocation {
+                country: value.last_active_country,
+                asn: last_active_asn,
+                as_organization: value.last_active_as_organization,
+            },
+        })
     }
 }
 
@@ -95,9 +119,13 @@ struct CompatSessionAndSsoLoginLookup {
     created_at: DateTime<Utc>,
     finished_at: Option<DateTime<Utc>>,
     is_synapse_admin: bool,
+    impersonated_by_oauth2_session_id: Option<Uuid>,
     user_agent: Option<String>,
     last_active_at: Option<DateTime<Utc>>,
     last_active_ip: Option<IpAddr>,
+    last_active_country: Option<String>,
+    last_active_asn: Option<i64>,
+    last_active_as_organization: Option<String>,
     compat_sso_login_id: Option<Uuid>,
     compat_sso_login_token: Option<String>,
     compat_sso_login_redirect_uri: Option<String>,
@@ -117,6 +145,17 @@ impl TryFrom<CompatSessionAndSsoLoginLookup> for (CompatSession, Option<CompatSs
             Some(finished_at) => CompatSessionState::Finished { finished_at },
         };
 
+        let last_active_asn = value
+            .last_active_asn
+            .map(u32::try_from)
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("compat_sessions")
+                    .column("last_active_asn")
+                    .row(id)
+                    .source(e)
+            })?;
+
         let session = CompatSession {
             id,
             state,
@@ -126,9 +165,15 @@ impl TryFrom<CompatSessionAndSsoLoginLookup> for (CompatSession, Option<CompatSs
             user_session_id: value.user_session_id.map(Ulid::from),
             created_at: value.created_at,
             is_synapse_admin: value.is_synapse_admin,
+            impersonated_by_session_id: value.impersonated_by_oauth2_session_id.map(Ulid::from),
             user_agent: value.user_agent,
             last_active_at: value.last_active_at,
             last_active_ip: value.last_active_ip,
+            last_active_location: IpLocation {
+                country: value.last_active_country,
+                asn: last_active_asn,
+                as_organization: value.last_active_as_organization,
+            },
         };
 
         match (
@@ -240,6 +285,15 @@ impl Filter for CompatSessionFilter<'_> {
             .add_option(self.device().map(|device| {
                 Expr::col((CompatSessions::Table, CompatSessions::DeviceId)).eq(device.as_str())
             }))
+            .add_option(self.device_prefix().map(|device_prefix| {
+                // Escape the LIKE wildcards, so that the prefix is matched literally
+                let escaped = device_prefix
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                Expr::col((CompatSessions::Table, CompatSessions::DeviceId))
+                    .like(LikeExpr::new(format!("{escaped}%")).escape('\\'))
+            }))
     }
 }
 
@@ -268,9 +322,13 @@ impl CompatSessionRepository for PgCompatSessionRepository<'_> {
                      , created_at
                      , finished_at
                      , is_synapse_admin
+                     , impersonated_by_oauth2_session_id
                      , user_agent
                      , last_active_at
                      , last_active_ip as "last_active_ip: IpAddr"
+                     , last_active_country
+                     , last_active_asn
+                     , last_active_as_organization
                 FROM compat_sessions
                 WHERE compat_session_id = $1
             "#,
@@ -282,7 +340,7 @@ impl CompatSessionRepository for PgCompatSessionRepository<'_> {
 
         let Some(res) = res else { return Ok(None) };
 
-        Ok(Some(res.into()))
+        Ok(Some(res.try_into()?))
     }
 
     #[tracing::instrument(
@@ -306,6 +364,7 @@ impl CompatSessionRepository for PgCompatSessionRepository<'_> {
         browser_session: Option<&BrowserSession>,
         is_synapse_admin: bool,
         human_name: Option<String>,
+        impersonated_by: Option<&Session>,
     ) -> Result<CompatSession, Self::Error> {
         let created_at = clock.now();
         let id = Ulid::from_datetime_with_source(created_at.into(), rng);
@@ -316,8 +375,8 @@ impl CompatSessionRepository for PgCompatSessionRepository<'_> {
                 INSERT INTO compat_sessions
                     (compat_session_id, user_id, device_id,
                      user_session_id, created_at, is_synapse_admin,
-                     human_name)
-                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                     human_name, impersonated_by_oauth2_session_id)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             "#,
             Uuid::from(id),
             Uuid::from(user.id),
@@ -326,6 +385,7 @@ impl CompatSessionRepository for PgCompatSessionRepository<'_> {
             created_at,
             is_synapse_admin,
             human_name.as_deref(),
+            impersonated_by.map(|s| Uuid::from(s.id)),
         )
         .traced()
         .execute(&mut *self.conn)
@@ -340,9 +400,11 @@ impl CompatSessionRepository for PgCompatSessionRepository<'_> {
             user_session_id: browser_session.map(|s| s.id),
             created_at,
             is_synapse_admin,
+            impersonated_by_session_id: impersonated_by.map(|s| s.id),
             user_agent: None,
             last_active_at: None,
             last_active_ip: None,
+            last_active_location: IpLocation::default(),
         })
     }
 
@@ -458,6 +520,13 @@ impl CompatSessionRepository for PgCompatSessionRepository<'_> {
                 Expr::col((CompatSessions::Table, CompatSessions::IsSynapseAdmin)),
                 CompatSessionAndSsoLoginLookupIden::IsSynapseAdmin,
             )
+            .expr_as(
+                Expr::col((
+                    CompatSessions::Table,
+                    CompatSessions::ImpersonatedByOAuth2SessionId,
+                )),
+                CompatSessionAndSsoLoginLookupIden::ImpersonatedByOauth2SessionId,
+            )
             .expr_as(
                 Expr::col((CompatSessions::Table, CompatSessions::UserAgent)),
                 CompatSessionAndSsoLoginLookupIden::UserAgent,
@@ -470,6 +539,21 @@ impl CompatSessionRepository for PgCompatSessionRepository<'_> {
                 Expr::col((CompatSessions::Table, CompatSessions::LastActiveIp)),
                 CompatSessionAndSsoLoginLookupIden::LastActiveIp,
             )
+            .expr_as(
+                Expr::col((CompatSessions::Table, CompatSessions::LastActiveCountry)),
+                CompatSessionAndSsoLoginLookupIden::LastActiveCountry,
+            )
+            .expr_as(
+                Expr::col((CompatSessions::Table, CompatSessions::LastActiveAsn)),
+                CompatSessionAndSsoLoginLookupIden::LastActiveAsn,
+            )
+            .expr_as(
+                Expr::col((
+                    CompatSessions::Table,
+                    CompatSessions::LastActiveAsOrganization,
+                )),
+                CompatSessionAndSsoLoginLookupIden::LastActiveAsOrganization,
+            )
             .expr_as(
                 Expr::col((CompatSsoLogins::Table, CompatSsoLogins::CompatSsoLoginId)),
                 CompatSessionAndSsoLoginLookupIden::CompatSsoLoginId,
@@ -542,6 +626,30 @@ impl CompatSessionRepository for PgCompatSessionRepository<'_> {
             .map_err(DatabaseError::to_invalid_operation)
     }
 
+    #[tracing::instrument(
+        name = "db.compat_session.estimate_count",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn estimate_count(
+        &mut self,
+        filter: CompatSessionFilter<'_>,
+    ) -> Result<usize, Self::Error> {
+        let query = sea_query::Query::select()
+            .expr(Expr::val(1))
+            .from(CompatSessions::Table)
+            .apply_filter(filter)
+            .take();
+
+        match estimate_rows(&mut *self.conn, &query).await? {
+            Some(estimate) => Ok(estimate),
+            None => self.count(filter).await,
+        }
+    }
+
     #[tracing::instrument(
         name = "db.compat_session.record_batch_activity",
         skip_all,
@@ -552,19 +660,25 @@ impl CompatSessionRepository for PgCompatSessionRepository<'_> {
     )]
     async fn record_batch_activity(
         &mut self,
-        mut activities: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>)>,
+        mut activities: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>, IpLocation)>,
     ) -> Result<(), Self::Error> {
         // Sort the activity by ID, so that when batching the updates, Postgres
         // locks the rows in a stable order, preventing deadlocks
-        activities.sort_unstable();
+        activities.sort_unstable_by_key(|(id, ..)| *id);
         let mut ids = Vec::with_capacity(activities.len());
         let mut last_activities = Vec::with_capacity(activities.len());
         let mut ips = Vec::with_capacity(activities.len());
+        let mut countries = Vec::with_capacity(activities.len());
+        let mut asns = Vec::with_capacity(activities.len());
+        let mut as_organizations = Vec::with_capacity(activities.len());
 
-        for (id, last_activity, ip) in activities {
+        for (id, last_activity, ip, location) in activities {
             ids.push(Uuid::from(id));
             last_activities.push(last_activity);
             ips.push(ip);
+            countries.push(location.country);
+            asns.push(location.asn.map(i64::from));
+            as_organizations.push(location.as_organization);
         }
 
         let res = sqlx::query!(
@@ -572,16 +686,29 @@ impl CompatSessionRepository for PgCompatSessionRepository<'_> {
                 UPDATE compat_sessions
                 SET last_active_at = GREATEST(t.last_active_at, compat_sessions.last_active_at)
                   , last_active_ip = COALESCE(t.last_active_ip, compat_sessions.last_active_ip)
+                  -- The location only changes alongside the IP address
+                  , last_active_country = CASE WHEN t.last_active_ip IS NULL
+                        THEN compat_sessions.last_active_country
+                        ELSE t.last_active_country END
+                  , last_active_asn = CASE WHEN t.last_active_ip IS NULL
+                        THEN compat_sessions.last_active_asn
+                        ELSE t.last_active_asn END
+                  , last_active_as_organization = CASE WHEN t.last_active_ip IS NULL
+                        THEN compat_sessions.last_active_as_organization
+                        ELSE t.last_active_as_organization END
                 FROM (
                     SELECT *
-                    FROM UNNEST($1::uuid[], $2::timestamptz[], $3::inet[])
-                        AS t(compat_session_id, last_active_at, last_active_ip)
+                    FROM UNNEST($1::uuid[], $2::timestamptz[], $3::inet[], $4::text[], $5::int8[], $6::text[])
+                        AS t(compat_session_id, last_active_at, last_active_ip, last_active_country, last_active_asn, last_active_as_organization)
                 ) AS t
                 WHERE compat_sessions.compat_session_id = t.compat_session_id
             "#,
             &ids,
             &last_activities,
             &ips as &[Option<IpAddr>],
+            &countries as &[Option<String>],
+            &asns as &[Option<i64>],
+            &as_organizations as &[Option<String>],
         )
         .traced()
         .execute(&mut *self.conn)
@@ -0,0 +1,56 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Estimate the number of rows matched by a query, without scanning them
+
+use sea_query::{PostgresQueryBuilder, SelectStatement};
+use sea_query_binder::SqlxBinder;
+use sqlx::PgConnection;
+
+use crate::{DatabaseError, ExecuteExt};
+
+/// Below this number of rows, estimates are replaced by exact counts.
+///
+/// Counting that many rows is cheap, and the planner estimates are very
+/// inaccurate on small tables, especially if they were never analyzed.
+const EXACT_COUNT_THRESHOLD: usize = 10_000;
+
+/// Estimate the number of rows returned by a query, using the row estimate of
+/// the query planner.
+///
+/// The planner bases its estimates on the table statistics
+/// (`pg_class.reltuples` and the column statistics), which are only refreshed
+/// when the table is analyzed, so the result may be off.
+///
+/// Returns `None` if the estimate is below [`EXACT_COUNT_THRESHOLD`], in which
+/// case an exact count should be done instead.
+pub(crate) async fn estimate_rows(
+    conn: &mut PgConnection,
+    query: &SelectStatement,
+) -> Result<Option<usize>, DatabaseError> {
+    let (sql, arguments) = query.build_sqlx(PostgresQueryBuilder);
+    let sql = format!("EXPLAIN (FORMAT JSON) {sql}");
+
+    let plan: serde_json::Value = sqlx::query_scalar_with(&sql, arguments)
+        .traced()
+        .fetch_one(&mut *conn)
+        .await?;
+
+    // The output looks like `[{"Plan": {"Plan Rows": 42, ...}}]`
+    let rows = plan
+        .pointer("/0/Plan/Plan Rows")
+        .and_then(serde_json::Value::as_f64)
+        .ok_or_else(DatabaseError::invalid_operation)?;
+
+    // The estimate is a non-negative float, rounded by Postgres
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let rows = rows.max(0.0) as usize;
+
+    if rows < EXACT_COUNT_THRESHOLD {
+        Ok(None)
+    } else {
+        Ok(Some(rows))
+    }
+}
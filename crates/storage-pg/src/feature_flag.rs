@@ -0,0 +1,418 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! A module containing the PostgreSQL implementation of the feature flag
+//! overrides storage.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{FeatureFlag, FeatureFlagOverride, FeatureFlagRollout};
+use mas_storage::{Clock, Page, Pagination, feature_flag::FeatureFlagOverrideRepository};
+use rand::RngCore;
+use sea_query::{Expr, PostgresQueryBuilder, Query, enum_def};
+use sea_query_binder::SqlxBinder;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{
+    DatabaseError, DatabaseInconsistencyError, ExecuteExt, iden::FeatureFlagOverrides,
+    pagination::QueryBuilderExt,
+};
+
+/// An implementation of [`FeatureFlagOverrideRepository`] for a PostgreSQL
+/// connection
+pub struct PgFeatureFlagOverrideRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgFeatureFlagOverrideRepository<'c> {
+    /// Create a new [`PgFeatureFlagOverrideRepository`] from an active
+    /// PostgreSQL connection
+    #[must_use]
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+#[enum_def]
+struct FeatureFlagOverrideLookup {
+    feature_flag_override_id: Uuid,
+    flag: String,
+    enabled: bool,
+    rollout_percentage: i16,
+    updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<FeatureFlagOverrideLookup> for FeatureFlagOverride {
+    type Error = DatabaseInconsistencyError;
+
+    fn try_from(value: FeatureFlagOverrideLookup) -> Result<Self, Self::Error> {
+        let id = Ulid::from(value.feature_flag_override_id);
+
+        let flag = value.flag.parse().map_err(|e| {
+            DatabaseInconsistencyError::on("feature_flag_overrides")
+                .column("flag")
+                .row(id)
+                .source(e)
+        })?;
+
+        let percentage = value.rollout_percentage.try_into().map_err(|e| {
+            DatabaseInconsistencyError::on("feature_flag_overrides")
+                .column("rollout_percentage")
+                .row(id)
+                .source(e)
+        })?;
+
+        Ok(Self {
+            id,
+            flag,
+            rollout: FeatureFlagRollout {
+                enabled: value.enabled,
+                percentage,
+            },
+            updated_at: value.updated_at,
+        })
+    }
+}
+
+#[async_trait]
+impl FeatureFlagOverrideRepository for PgFeatureFlagOverrideRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.feature_flag_override.lookup",
+        skip_all,
+        fields(
+            db.query.text,
+            feature_flag_override.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<FeatureFlagOverride>, Self::Error> {
+        let res = sqlx::query_as!(
+            FeatureFlagOverrideLookup,
+            r#"
+                SELECT feature_flag_override_id
+                     , flag
+                     , enabled
+                     , rollout_percentage
+                     , updated_at
+                FROM feature_flag_overrides
+                WHERE feature_flag_override_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        let Some(res) = res else { return Ok(None) };
+
+        Ok(Some(res.try_into()?))
+    }
+
+    #[tracing::instrument(
+        name = "db.feature_flag_override.find",
+        skip_all,
+        fields(
+            db.query.text,
+            feature_flag_override.flag = %flag,
+        ),
+        err,
+    )]
+    async fn find(
+        &mut self,
+        flag: FeatureFlag,
+    ) -> Result<Option<FeatureFlagOverride>, Self::Error> {
+        let res = sqlx::query_as!(
+            FeatureFlagOverrideLookup,
+            r#"
+                SELECT feature_flag_override_id
+                     , flag
+                     , enabled
+                     , rollout_percentage
+                     , updated_at
+                FROM feature_flag_overrides
+                WHERE flag = $1
+            "#,
+            flag.as_str(),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        let Some(res) = res else { return Ok(None) };
+
+        Ok(Some(res.try_into()?))
+    }
+
+    #[tracing::instrument(
+        name = "db.feature_flag_override.set",
+        skip_all,
+        fields(
+            db.query.text,
+            feature_flag_override.id,
+            feature_flag_override.flag = %flag,
+            feature_flag_override.enabled = rollout.enabled,
+            feature_flag_override.percentage = rollout.percentage,
+        ),
+        err,
+    )]
+    async fn set(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        flag: FeatureFlag,
+        rollout: FeatureFlagRollout,
+    ) -> Result<FeatureFlagOverride, Self::Error> {
+        let updated_at = clock.now();
+        let id = Ulid::from_datetime_with_source(updated_at.into(), rng);
+
+        // If the flag is already overridden, this keeps the existing ID
+        let id: Uuid = sqlx::query_scalar!(
+            r#"
+                INSERT INTO feature_flag_overrides
+                    ( feature_flag_override_id
+                    , flag
+                    , enabled
+                    , rollout_percentage
+                    , updated_at
+                    )
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (flag) DO UPDATE
+                SET enabled = EXCLUDED.enabled
+                  , rollout_percentage = EXCLUDED.rollout_percentage
+                  , updated_at = EXCLUDED.updated_at
+                RETURNING feature_flag_override_id
+            "#,
+            Uuid::from(id),
+            flag.as_str(),
+            rollout.enabled,
+            i16::from(rollout.percentage),
+            updated_at,
+        )
+        .traced()
+        .fetch_one(&mut *self.conn)
+        .await?;
+
+        let id = Ulid::from(id);
+        tracing::Span::current().record("feature_flag_override.id", tracing::field::display(id));
+
+        Ok(FeatureFlagOverride {
+            id,
+            flag,
+            rollout,
+            updated_at,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.feature_flag_override.remove",
+        skip_all,
+        fields(
+            db.query.text,
+            feature_flag_override.id = %feature_flag_override.id,
+        ),
+        err,
+    )]
+    async fn remove(
+        &mut self,
+        feature_flag_override: FeatureFlagOverride,
+    ) -> Result<(), Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM feature_flag_overrides
+                WHERE feature_flag_override_id = $1
+            "#,
+            Uuid::from(feature_flag_override.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)
+    }
+
+    #[tracing::instrument(
+        name = "db.feature_flag_override.list",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn list(
+        &mut self,
+        pagination: Pagination,
+    ) -> Result<Page<FeatureFlagOverride>, Self::Error> {
+        let (sql, arguments) = Query::select()
+            .expr_as(
+                Expr::col((
+                    FeatureFlagOverrides::Table,
+                    FeatureFlagOverrides::FeatureFlagOverrideId,
+                )),
+                FeatureFlagOverrideLookupIden::FeatureFlagOverrideId,
+            )
+            .expr_as(
+                Expr::col((FeatureFlagOverrides::Table, FeatureFlagOverrides::Flag)),
+                FeatureFlagOverrideLookupIden::Flag,
+            )
+            .expr_as(
+                Expr::col((FeatureFlagOverrides::Table, FeatureFlagOverrides::Enabled)),
+                FeatureFlagOverrideLookupIden::Enabled,
+            )
+            .expr_as(
+                Expr::col((
+                    FeatureFlagOverrides::Table,
+                    FeatureFlagOverrides::RolloutPercentage,
+                )),
+                FeatureFlagOverrideLookupIden::RolloutPercentage,
+            )
+            .expr_as(
+                Expr::col((FeatureFlagOverrides::Table, FeatureFlagOverrides::UpdatedAt)),
+                FeatureFlagOverrideLookupIden::UpdatedAt,
+            )
+            .from(FeatureFlagOverrides::Table)
+            .generate_pagination(
+                (
+                    FeatureFlagOverrides::Table,
+                    FeatureFlagOverrides::FeatureFlagOverrideId,
+                ),
+                pagination,
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        let edges: Vec<FeatureFlagOverrideLookup> = sqlx::query_as_with(&sql, arguments)
+            .traced()
+            .fetch_all(&mut *self.conn)
+            .await?;
+
+        let page = pagination
+            .process(edges)
+            .try_map(FeatureFlagOverride::try_from)?;
+
+        Ok(page)
+    }
+
+    #[tracing::instrument(
+        name = "db.feature_flag_override.count",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn count(&mut self) -> Result<usize, Self::Error> {
+        let count = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*) AS "count!"
+                FROM feature_flag_overrides
+            "#,
+        )
+        .traced()
+        .fetch_one(&mut *self.conn)
+        .await?;
+
+        count
+            .try_into()
+            .map_err(DatabaseError::to_invalid_operation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mas_data_model::{FeatureFlag, FeatureFlagRollout};
+    use mas_storage::{Pagination, clock::MockClock, feature_flag::FeatureFlagOverrideRepository};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use sqlx::PgPool;
+
+    use crate::feature_flag::PgFeatureFlagOverrideRepository;
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_feature_flag_overrides(pool: PgPool) {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut conn = pool.acquire().await.unwrap();
+        let mut repo = PgFeatureFlagOverrideRepository::new(&mut conn);
+
+        // Nothing is overridden at first
+        assert_eq!(repo.count().await.unwrap(), 0);
+        assert!(
+            repo.find(FeatureFlag::PasswordGrant)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // Override a flag
+        let rollout = FeatureFlagRollout {
+            enabled: true,
+            percentage: 25,
+        };
+        let first = repo
+            .set(&mut rng, &clock, FeatureFlag::PasswordGrant, rollout)
+            .await
+            .unwrap();
+        assert_eq!(first.flag, FeatureFlag::PasswordGrant);
+        assert_eq!(first.rollout, rollout);
+
+        let found = repo
+            .find(FeatureFlag::PasswordGrant)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found, first);
+        assert_eq!(repo.lookup(first.id).await.unwrap().unwrap(), first);
+
+        // Overriding it again keeps the same ID
+        clock.advance(chrono::Duration::minutes(1));
+        let second = repo
+            .set(
+                &mut rng,
+                &clock,
+                FeatureFlag::PasswordGrant,
+                FeatureFlagRollout::DISABLED,
+            )
+            .await
+            .unwrap();
+        assert_eq!(second.id, first.id);
+        assert_eq!(second.rollout, FeatureFlagRollout::DISABLED);
+        assert_eq!(
+            repo.lookup(first.id).await.unwrap().unwrap().rollout,
+            FeatureFlagRollout::DISABLED
+        );
+
+        // Override another flag
+        let other = repo
+            .set(
+                &mut rng,
+                &clock,
+                FeatureFlag::AccountClaim,
+                FeatureFlagRollout::ENABLED,
+            )
+            .await
+            .unwrap();
+        assert_ne!(other.id, first.id);
+        assert_eq!(repo.count().await.unwrap(), 2);
+
+        let page = repo.list(Pagination::first(10)).await.unwrap();
+        assert!(!page.has_next_page);
+        assert_eq!(page.edges.len(), 2);
+
+        // Remove the first override
+        repo.remove(second).await.unwrap();
+        assert_eq!(repo.count().await.unwrap(), 1);
+        assert!(repo.lookup(first.id).await.unwrap().is_none());
+        assert!(
+            repo.find(FeatureFlag::PasswordGrant)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -16,6 +16,18 @@ pub enum UserSessions {
     UserAgent,
     LastActiveAt,
     LastActiveIp,
+    LastActiveCountry,
+    LastActiveAsn,
+    LastActiveAsOrganization,
+}
+
+#[derive(sea_query::Iden)]
+pub enum UserSessionAuthentications {
+    Table,
+    UserSessionAuthenticationId,
+    UserSessionId,
+    #[iden = "upstream_oauth_authorization_session_id"]
+    UpstreamOAuthAuthorizationSessionId,
 }
 
 #[derive(sea_query::Iden)]
@@ -49,9 +61,14 @@ pub enum CompatSessions {
     CreatedAt,
     FinishedAt,
     IsSynapseAdmin,
+    #[iden = "impersonated_by_oauth2_session_id"]
+    ImpersonatedByOAuth2SessionId,
     UserAgent,
     LastActiveAt,
     LastActiveIp,
+    LastActiveCountry,
+    LastActiveAsn,
+    LastActiveAsOrganization,
 }
 
 #[derive(sea_query::Iden)]
@@ -83,7 +100,15 @@ pub enum OAuth2Sessions {
     UserAgent,
     LastActiveAt,
     LastActiveIp,
+    LastActiveCountry,
+    LastActiveAsn,
+    LastActiveAsOrganization,
     HumanName,
+    DeviceFingerprint,
+    AuthorizationDetails,
+    Resource,
+    UserinfoClaims,
+    OfflineAccessGrantedAt,
 }
 
 #[derive(sea_query::Iden)]
@@ -93,6 +118,7 @@ pub enum OAuth2Clients {
     #[iden = "oauth2_client_id"]
     OAuth2ClientId,
     IsStatic,
+    ServiceAccountScopeList,
 }
 
 #[derive(sea_query::Iden)]
@@ -109,6 +135,9 @@ pub enum UpstreamOAuthProviders {
     EncryptedClientSecret,
     TokenEndpointSigningAlg,
     TokenEndpointAuthMethod,
+    TokenEndpointSigningKeyId,
+    TlsClientCertificate,
+    EncryptedTlsClientKey,
     IdTokenSignedResponseAlg,
     FetchUserinfo,
     UserinfoSignedResponseAlg,
@@ -120,6 +149,11 @@ pub enum UpstreamOAuthProviders {
     ResponseMode,
     AdditionalParameters,
     ForwardLoginHint,
+    ForwardPrompt,
+    ForwardAcrValues,
+    ForwardUiLocales,
+    StoreTokens,
+    Deprovisioning,
     JwksUriOverride,
     TokenEndpointOverride,
     AuthorizationEndpointOverride,
@@ -137,9 +171,21 @@ pub enum UpstreamOAuthLinks {
     UserId,
     Subject,
     HumanAccountName,
+    ImportedAttributes,
     CreatedAt,
 }
 
+#[derive(sea_query::Iden)]
+#[iden = "upstream_oauth_authorization_sessions"]
+pub enum UpstreamOAuthAuthorizationSessions {
+    Table,
+    #[iden = "upstream_oauth_authorization_session_id"]
+    UpstreamOAuthAuthorizationSessionId,
+    #[iden = "upstream_oauth_provider_id"]
+    UpstreamOAuthProviderId,
+    IdTokenClaims,
+}
+
 #[derive(sea_query::Iden)]
 pub enum UserRegistrationTokens {
     Table,
@@ -151,4 +197,75 @@ pub enum UserRegistrationTokens {
     LastUsedAt,
     ExpiresAt,
     RevokedAt,
+    CreatedByUserId,
+    AssignReferrer,
+}
+
+#[derive(sea_query::Iden)]
+pub enum UserReferrals {
+    Table,
+    UserId,
+    ReferrerUserId,
+}
+
+#[derive(sea_query::Iden)]
+pub enum UserRecoverySessions {
+    Table,
+    UserRecoverySessionId,
+    Email,
+    UserAgent,
+    IpAddress,
+    Locale,
+    LostSecondFactor,
+    RequiresApproval,
+    CreatedAt,
+    ConsumedAt,
+    ApprovedAt,
+    DeniedAt,
+}
+
+#[derive(sea_query::Iden)]
+pub enum UserClaimLinks {
+    Table,
+    UserClaimLinkId,
+    UserId,
+    Token,
+    Email,
+    #[iden = "created_by_oauth2_session_id"]
+    CreatedByOAuth2SessionId,
+    CreatedAt,
+    ExpiresAt,
+    ClaimedAt,
+    ClaimedIpAddress,
+    ClaimedUserAgent,
+    RevokedAt,
+}
+
+#[derive(sea_query::Iden)]
+pub enum ScimUserLinks {
+    Table,
+    ScimUserLinkId,
+    UserId,
+    ExternalId,
+    CreatedAt,
+}
+
+#[derive(sea_query::Iden)]
+pub enum ScimSyncRuns {
+    Table,
+    ScimSyncRunId,
+    StartedAt,
+    FinishedAt,
+    Error,
+    Changes,
+}
+
+#[derive(sea_query::Iden)]
+pub enum FeatureFlagOverrides {
+    Table,
+    FeatureFlagOverrideId,
+    Flag,
+    Enabled,
+    RolloutPercentage,
+    UpdatedAt,
 }
@@ -166,12 +166,17 @@ pub mod app_session;
 pub mod compat;
 pub mod oauth2;
 pub mod queue;
+pub mod scim;
 pub mod upstream_oauth2;
 pub mod user;
 
+pub(crate) mod audit_event;
 mod errors;
+pub(crate) mod estimate;
+pub(crate) mod feature_flag;
 pub(crate) mod filter;
 pub(crate) mod iden;
+pub(crate) mod login_stats;
 pub(crate) mod pagination;
 pub(crate) mod policy_data;
 pub(crate) mod repository;
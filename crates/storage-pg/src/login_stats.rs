@@ -0,0 +1,447 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! A module containing the PostgreSQL implementation of the login statistics
+//! rollup.
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use mas_data_model::{Client, DailyLoginStats, UpstreamOAuthProvider};
+use mas_storage::login_stats::LoginStatsRepository;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, DatabaseInconsistencyError, ExecuteExt};
+
+/// An implementation of [`LoginStatsRepository`] for a PostgreSQL connection.
+pub struct PgLoginStatsRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgLoginStatsRepository<'c> {
+    /// Create a new [`PgLoginStatsRepository`] from an active PostgreSQL
+    /// connection.
+    #[must_use]
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct DailyLoginStatsLookup {
+    day: NaiveDate,
+    successful_logins: i64,
+    failed_logins: i64,
+}
+
+impl DailyLoginStatsLookup {
+    fn into_stats(
+        self,
+        table: &'static str,
+    ) -> Result<DailyLoginStats, DatabaseInconsistencyError> {
+        let successful = self.successful_logins.try_into().map_err(|e| {
+            DatabaseInconsistencyError::on(table)
+                .column("successful_logins")
+                .source(e)
+        })?;
+
+        let failed = self.failed_logins.try_into().map_err(|e| {
+            DatabaseInconsistencyError::on(table)
+                .column("failed_logins")
+                .source(e)
+        })?;
+
+        Ok(DailyLoginStats {
+            day: self.day,
+            successful,
+            failed,
+        })
+    }
+}
+
+/// The smallest ULID which can have been generated on the given day.
+///
+/// All the tables aggregated here use ULIDs generated at creation time as
+/// primary key, so filtering on this lets us use the primary key index instead
+/// of scanning the whole table.
+fn lower_bound(since: Option<NaiveDate>) -> Uuid {
+    let Some(since) = since else {
+        return Uuid::nil();
+    };
+
+    let timestamp = since.and_time(chrono::NaiveTime::MIN).and_utc();
+    let timestamp = u64::try_from(timestamp.timestamp_millis()).unwrap_or_default();
+    Ulid::from_parts(timestamp, 0).into()
+}
+
+#[async_trait]
+impl LoginStatsRepository for PgLoginStatsRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.login_stats.last_day",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn last_day(&mut self) -> Result<Option<NaiveDate>, Self::Error> {
+        let day = sqlx::query_scalar!(
+            r#"
+                SELECT GREATEST(
+                    (SELECT MAX(day) FROM upstream_oauth_provider_login_stats),
+                    (SELECT MAX(day) FROM oauth2_client_login_stats)
+                ) AS "day"
+            "#,
+        )
+        .traced()
+        .fetch_one(&mut *self.conn)
+        .await?;
+
+        Ok(day)
+    }
+
+    #[tracing::instrument(
+        name = "db.login_stats.refresh",
+        skip_all,
+        fields(
+            db.query.text,
+            login_stats.since = ?since,
+        ),
+        err,
+    )]
+    async fn refresh(&mut self, since: Option<NaiveDate>) -> Result<usize, Self::Error> {
+        let lower_bound = lower_bound(since);
+
+        let providers = sqlx::query!(
+            r#"
+                INSERT INTO upstream_oauth_provider_login_stats
+                    ( upstream_oauth_provider_id
+                    , day
+                    , successful_logins
+                    , failed_logins
+                    )
+                SELECT
+                    upstream_oauth_provider_id,
+                    (created_at AT TIME ZONE 'UTC')::date,
+                    COUNT(*) FILTER (WHERE consumed_at IS NOT NULL),
+                    COUNT(*) FILTER (WHERE consumed_at IS NULL)
+                FROM upstream_oauth_authorization_sessions
+                WHERE upstream_oauth_authorization_session_id >= $1
+                GROUP BY 1, 2
+                ON CONFLICT (upstream_oauth_provider_id, day) DO UPDATE
+                SET successful_logins = EXCLUDED.successful_logins,
+                    failed_logins = EXCLUDED.failed_logins
+            "#,
+            lower_bound,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        let clients = sqlx::query!(
+            r#"
+                INSERT INTO oauth2_client_login_stats
+                    ( oauth2_client_id
+                    , day
+                    , successful_logins
+                    , failed_logins
+                    )
+                SELECT oauth2_client_id, day, SUM(successful), SUM(failed)
+                FROM (
+                    SELECT
+                        oauth2_client_id,
+                        (created_at AT TIME ZONE 'UTC')::date AS day,
+                        COUNT(*) FILTER (WHERE fulfilled_at IS NOT NULL) AS successful,
+                        COUNT(*) FILTER (WHERE fulfilled_at IS NULL) AS failed
+                    FROM oauth2_authorization_grants
+                    WHERE oauth2_authorization_grant_id >= $1
+                    GROUP BY 1, 2
+
+                    UNION ALL
+
+                    SELECT
+                        oauth2_client_id,
+                        (created_at AT TIME ZONE 'UTC')::date AS day,
+                        COUNT(*) FILTER (WHERE fulfilled_at IS NOT NULL) AS successful,
+                        COUNT(*) FILTER (WHERE fulfilled_at IS NULL) AS failed
+                    FROM oauth2_device_code_grant
+                    WHERE oauth2_device_code_grant_id >= $1
+                    GROUP BY 1, 2
+                ) AS grants
+                GROUP BY 1, 2
+                ON CONFLICT (oauth2_client_id, day) DO UPDATE
+                SET successful_logins = EXCLUDED.successful_logins,
+                    failed_logins = EXCLUDED.failed_logins
+            "#,
+            lower_bound,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        let count = providers.rows_affected() + clients.rows_affected();
+        Ok(count
+            .try_into()
+            .map_err(DatabaseError::to_invalid_operation)?)
+    }
+
+    #[tracing::instrument(
+        name = "db.login_stats.for_upstream_oauth_provider",
+        skip_all,
+        fields(
+            db.query.text,
+            %provider.id,
+        ),
+        err,
+    )]
+    async fn for_upstream_oauth_provider(
+        &mut self,
+        provider: &UpstreamOAuthProvider,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<Vec<DailyLoginStats>, Self::Error> {
+        let rows = sqlx::query_as!(
+            DailyLoginStatsLookup,
+            r#"
+                SELECT day, successful_logins, failed_logins
+                FROM upstream_oauth_provider_login_stats
+                WHERE upstream_oauth_provider_id = $1
+                  AND day >= $2
+                  AND day <= $3
+                ORDER BY day ASC
+            "#,
+            Uuid::from(provider.id),
+            since,
+            until,
+        )
+        .traced()
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        let stats = rows
+            .into_iter()
+            .map(|row| row.into_stats("upstream_oauth_provider_login_stats"))
+            .collect::<Result<_, _>>()?;
+
+        Ok(stats)
+    }
+
+    #[tracing::instrument(
+        name = "db.login_stats.for_oauth2_client",
+        skip_all,
+        fields(
+            db.query.text,
+            %client.id,
+        ),
+        err,
+    )]
+    async fn for_oauth2_client(
+        &mut self,
+        client: &Client,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<Vec<DailyLoginStats>, Self::Error> {
+        let rows = sqlx::query_as!(
+            DailyLoginStatsLookup,
+            r#"
+                SELECT day, successful_logins, failed_logins
+                FROM oauth2_client_login_stats
+                WHERE oauth2_client_id = $1
+                  AND day >= $2
+                  AND day <= $3
+                ORDER BY day ASC
+            "#,
+            Uuid::from(client.id),
+            since,
+            until,
+        )
+        .traced()
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        let stats = rows
+            .into_iter()
+            .map(|row| row.into_stats("oauth2_client_login_stats"))
+            .collect::<Result<_, _>>()?;
+
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use mas_data_model::{
+        UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderDiscoveryMode,
+        UpstreamOAuthProviderPkceMode, UpstreamOAuthProviderTokenAuthMethod,
+    };
+    use mas_iana::jose::JsonWebSignatureAlg;
+    use mas_storage::{
+        Clock, RepositoryAccess,
+        clock::MockClock,
+        login_stats::LoginStatsRepository,
+        upstream_oauth2::{
+            UpstreamOAuthLinkRepository, UpstreamOAuthProviderParams,
+            UpstreamOAuthProviderRepository, UpstreamOAuthSessionRepository,
+        },
+    };
+    use oauth2_types::scope::{OPENID, Scope};
+    use rand::SeedableRng;
+    use sqlx::PgPool;
+
+    use crate::PgRepository;
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_upstream_oauth_provider_stats(pool: PgPool) {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+
+        // Nothing was aggregated yet
+        assert_eq!(repo.login_stats().last_day().await.unwrap(), None);
+
+        let provider = repo
+            .upstream_oauth_provider()
+            .add(
+                &mut rng,
+                &clock,
+                UpstreamOAuthProviderParams {
+                    issuer: Some("https://example.com/".to_owned()),
+                    human_name: None,
+                    brand_name: None,
+                    scope: Scope::from_iter([OPENID]),
+                    token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::None,
+                    id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+                    fetch_userinfo: false,
+                    userinfo_signed_response_alg: None,
+                    token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
+                    client_id: "client-id".to_owned(),
+                    encrypted_client_secret: None,
+                    claims_imports: UpstreamOAuthProviderClaimsImports::default(),
+                    token_endpoint_override: None,
+                    authorization_endpoint_override: None,
+                    userinfo_endpoint_override: None,
+                    jwks_uri_override: None,
+                    discovery_mode: UpstreamOAuthProviderDiscoveryMode::Oidc,
+                    pkce_mode: UpstreamOAuthProviderPkceMode::Auto,
+                    response_mode: None,
+                    additional_authorization_parameters: Vec::new(),
+                    forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: false,
+                    deprovisioning: None,
+                    ui_order: 0,
+                },
+            )
+            .await
+            .unwrap();
+
+        let link = repo
+            .upstream_oauth_link()
+            .add(&mut rng, &clock, &provider, "a-subject".to_owned(), None)
+            .await
+            .unwrap();
+
+        // On the first day, one successful login and one abandoned one
+        let first_day = clock.now().date_naive();
+        let session = repo
+            .upstream_oauth_session()
+            .add(
+                &mut rng,
+                &clock,
+                &provider,
+                "state-1".to_owned(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let session = repo
+            .upstream_oauth_session()
+            .complete_with_link(&clock, session, &link, None, None, None, None)
+            .await
+            .unwrap();
+        repo.upstream_oauth_session()
+            .consume(&clock, session)
+            .await
+            .unwrap();
+        repo.upstream_oauth_session()
+            .add(
+                &mut rng,
+                &clock,
+                &provider,
+                "state-2".to_owned(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        // On the next day, one login still in progress
+        clock.advance(Duration::days(1));
+        let second_day = clock.now().date_naive();
+        let pending = repo
+            .upstream_oauth_session()
+            .add(
+                &mut rng,
+                &clock,
+                &provider,
+                "state-3".to_owned(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        repo.login_stats().refresh(None).await.unwrap();
+        assert_eq!(
+            repo.login_stats().last_day().await.unwrap(),
+            Some(second_day)
+        );
+
+        let stats = repo
+            .login_stats()
+            .for_upstream_oauth_provider(&provider, first_day, second_day)
+            .await
+            .unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].day, first_day);
+        assert_eq!(stats[0].successful, 1);
+        assert_eq!(stats[0].failed, 1);
+        assert_eq!(stats[1].day, second_day);
+        assert_eq!(stats[1].successful, 0);
+        assert_eq!(stats[1].failed, 1);
+
+        // Finish the pending login, and only aggregate the second day again
+        let pending = repo
+            .upstream_oauth_session()
+            .complete_with_link(&clock, pending, &link, None, None, None, None)
+            .await
+            .unwrap();
+        repo.upstream_oauth_session()
+            .consume(&clock, pending)
+            .await
+            .unwrap();
+
+        let updated = repo.login_stats().refresh(Some(second_day)).await.unwrap();
+        assert_eq!(updated, 1);
+
+        let stats = repo
+            .login_stats()
+            .for_upstream_oauth_provider(&provider, second_day, second_day)
+            .await
+            .unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].successful, 1);
+        assert_eq!(stats[0].failed, 0);
+    }
+}
@@ -37,6 +37,7 @@ struct OAuth2AccessTokenLookup {
     expires_at: Option<DateTime<Utc>>,
     revoked_at: Option<DateTime<Utc>>,
     first_used_at: Option<DateTime<Utc>>,
+    dpop_jkt: Option<String>,
 }
 
 impl From<OAuth2AccessTokenLookup> for AccessToken {
@@ -54,6 +55,7 @@ impl From<OAuth2AccessTokenLookup> for AccessToken {
             created_at: value.created_at,
             expires_at: value.expires_at,
             first_used_at: value.first_used_at,
+            dpop_jkt: value.dpop_jkt,
         }
     }
 }
@@ -73,6 +75,7 @@ impl OAuth2AccessTokenRepository for PgOAuth2AccessTokenRepository<'_> {
                      , revoked_at
                      , oauth2_session_id
                      , first_used_at
+                     , dpop_jkt
 
                 FROM oauth2_access_tokens
 
@@ -110,6 +113,7 @@ impl OAuth2AccessTokenRepository for PgOAuth2AccessTokenRepository<'_> {
                      , revoked_at
                      , oauth2_session_id
                      , first_used_at
+                     , dpop_jkt
 
                 FROM oauth2_access_tokens
 
@@ -175,6 +179,7 @@ impl OAuth2AccessTokenRepository for PgOAuth2AccessTokenRepository<'_> {
             created_at,
             expires_at,
             first_used_at: None,
+            dpop_jkt: None,
         })
     }
 
@@ -214,6 +219,37 @@ impl OAuth2AccessTokenRepository for PgOAuth2AccessTokenRepository<'_> {
             .map_err(DatabaseError::to_invalid_operation)
     }
 
+    #[tracing::instrument(
+        name = "db.oauth2_access_token.revoke_for_session",
+        skip_all,
+        fields(
+            db.query.text,
+            %session.id,
+        ),
+        err,
+    )]
+    async fn revoke_for_session(
+        &mut self,
+        clock: &dyn Clock,
+        session: &Session,
+    ) -> Result<usize, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE oauth2_access_tokens
+                SET revoked_at = $2
+                WHERE oauth2_session_id = $1
+                  AND revoked_at IS NULL
+            "#,
+            Uuid::from(session.id),
+            clock.now(),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(res.rows_affected().try_into().unwrap_or(usize::MAX))
+    }
+
     #[tracing::instrument(
         name = "db.oauth2_access_token.mark_used",
         skip_all,
@@ -249,6 +285,41 @@ impl OAuth2AccessTokenRepository for PgOAuth2AccessTokenRepository<'_> {
         Ok(access_token)
     }
 
+    #[tracing::instrument(
+        name = "db.oauth2_access_token.bind_dpop_key",
+        skip_all,
+        fields(
+            db.query.text,
+            session.id = %access_token.session_id,
+            %access_token.id,
+        ),
+        err,
+    )]
+    async fn bind_dpop_key(
+        &mut self,
+        mut access_token: AccessToken,
+        jkt: String,
+    ) -> Result<AccessToken, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE oauth2_access_tokens
+                SET dpop_jkt = $2
+                WHERE oauth2_access_token_id = $1
+            "#,
+            Uuid::from(access_token.id),
+            &jkt,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        access_token.dpop_jkt = Some(jkt);
+
+        Ok(access_token)
+    }
+
     #[tracing::instrument(
         name = "db.oauth2_access_token.cleanup_revoked",
         skip_all,
@@ -257,15 +328,23 @@ impl OAuth2AccessTokenRepository for PgOAuth2AccessTokenRepository<'_> {
         ),
         err,
     )]
-    async fn cleanup_revoked(&mut self, clock: &dyn Clock) -> Result<usize, Self::Error> {
-        // Cleanup token that were revoked more than an hour ago
-        let threshold = clock.now() - Duration::microseconds(60 * 60 * 1000 * 1000);
+    async fn cleanup_revoked(
+        &mut self,
+        revoked_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error> {
         let res = sqlx::query!(
             r#"
                 DELETE FROM oauth2_access_tokens
-                WHERE revoked_at < $1
+                WHERE oauth2_access_token_id IN (
+                    SELECT oauth2_access_token_id
+                    FROM oauth2_access_tokens
+                    WHERE revoked_at < $1
+                    LIMIT $2
+                )
             "#,
-            threshold,
+            revoked_before,
+            i64::try_from(limit).unwrap_or(i64::MAX),
         )
         .traced()
         .execute(&mut *self.conn)
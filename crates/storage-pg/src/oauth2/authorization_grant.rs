@@ -4,16 +4,24 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::num::NonZeroU32;
+
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use mas_data_model::{
     AuthorizationCode, AuthorizationGrant, AuthorizationGrantStage, Client, Pkce, Session,
 };
 use mas_iana::oauth::PkceCodeChallengeMethod;
-use mas_storage::{Clock, oauth2::OAuth2AuthorizationGrantRepository};
-use oauth2_types::{requests::ResponseMode, scope::Scope};
+use mas_storage::{
+    Clock,
+    oauth2::{OAuth2AuthorizationGrantParams, OAuth2AuthorizationGrantRepository},
+};
+use oauth2_types::{
+    authorization_details::AuthorizationDetail, claims::ClaimsRequest, requests::ResponseMode,
+    scope::Scope,
+};
 use rand::RngCore;
-use sqlx::PgConnection;
+use sqlx::{PgConnection, types::Json};
 use ulid::Ulid;
 use url::Url;
 use uuid::Uuid;
@@ -53,6 +61,14 @@ struct GrantLookup {
     code_challenge_method: Option<String>,
     login_hint: Option<String>,
     locale: Option<String>,
+    device_fingerprint: Option<String>,
+    authorization_details: Option<Json<Vec<AuthorizationDetail>>>,
+    resource: Option<String>,
+    required_acr: Option<String>,
+    max_age: Option<i32>,
+    prompt_login: bool,
+    prompt_consent: bool,
+    claims: Option<Json<ClaimsRequest>>,
     oauth2_client_id: Uuid,
     oauth2_session_id: Option<Uuid>,
 }
@@ -150,6 +166,29 @@ impl TryFrom<GrantLookup> for AuthorizationGrant {
                 .source(e)
         })?;
 
+        let resource = value
+            .resource
+            .map(|resource| resource.parse())
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_authorization_grants")
+                    .column("resource")
+                    .row(id)
+                    .source(e)
+            })?;
+
+        let max_age = value
+            .max_age
+            .map(u32::try_from)
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_authorization_grants")
+                    .column("max_age")
+                    .row(id)
+                    .source(e)
+            })?
+            .and_then(NonZeroU32::new);
+
         Ok(AuthorizationGrant {
             id,
             stage,
@@ -164,6 +203,17 @@ impl TryFrom<GrantLookup> for AuthorizationGrant {
             response_type_id_token: value.response_type_id_token,
             login_hint: value.login_hint,
             locale: value.locale,
+            device_fingerprint: value.device_fingerprint,
+            authorization_details: value
+                .authorization_details
+                .map(|Json(x)| x)
+                .unwrap_or_default(),
+            resource,
+            required_acr: value.required_acr,
+            max_age,
+            prompt_login: value.prompt_login,
+            prompt_consent: value.prompt_consent,
+            claims: value.claims.map(|Json(x)| x),
         })
     }
 }
@@ -190,14 +240,26 @@ impl OAuth2AuthorizationGrantRepository for PgOAuth2AuthorizationGrantRepository
         client: &Client,
         redirect_uri: Url,
         scope: Scope,
-        code: Option<AuthorizationCode>,
-        state: Option<String>,
-        nonce: Option<String>,
         response_mode: ResponseMode,
-        response_type_id_token: bool,
-        login_hint: Option<String>,
-        locale: Option<String>,
+        params: OAuth2AuthorizationGrantParams,
     ) -> Result<AuthorizationGrant, Self::Error> {
+        let OAuth2AuthorizationGrantParams {
+            code,
+            state,
+            nonce,
+            response_type_id_token,
+            login_hint,
+            locale,
+            device_fingerprint,
+            authorization_details,
+            resource,
+            required_acr,
+            max_age,
+            prompt_login,
+            prompt_consent,
+            claims,
+        } = params;
+
         let code_challenge = code
             .as_ref()
             .and_then(|c| c.pkce.as_ref())
@@ -207,6 +269,10 @@ impl OAuth2AuthorizationGrantRepository for PgOAuth2AuthorizationGrantRepository
             .and_then(|c| c.pkce.as_ref())
             .map(|p| p.challenge_method.to_string());
         let code_str = code.as_ref().map(|c| &c.code);
+        let max_age_i32 = max_age
+            .map(|max_age| i32::try_from(max_age.get()))
+            .transpose()
+            .map_err(DatabaseError::to_invalid_operation)?;
 
         let created_at = clock.now();
         let id = Ulid::from_datetime_with_source(created_at.into(), rng);
@@ -229,10 +295,19 @@ impl OAuth2AuthorizationGrantRepository for PgOAuth2AuthorizationGrantRepository
                      authorization_code,
                      login_hint,
                      locale,
+                     device_fingerprint,
+                     authorization_details,
+                     resource,
+                     required_acr,
+                     max_age,
+                     prompt_login,
+                     prompt_consent,
+                     claims,
                      created_at
                 )
                 VALUES
-                    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+                    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                    $18, $19, $20, $21, $22, $23)
             "#,
             Uuid::from(id),
             Uuid::from(client.id),
@@ -248,6 +323,14 @@ impl OAuth2AuthorizationGrantRepository for PgOAuth2AuthorizationGrantRepository
             code_str,
             login_hint,
             locale,
+            device_fingerprint.as_deref(),
+            Json(&authorization_details) as _,
+            resource.as_ref().map(Url::as_str),
+            required_acr.as_deref(),
+            max_age_i32,
+            prompt_login,
+            prompt_consent,
+            claims.as_ref().map(Json) as _,
             created_at,
         )
         .traced()
@@ -268,6 +351,14 @@ impl OAuth2AuthorizationGrantRepository for PgOAuth2AuthorizationGrantRepository
             response_type_id_token,
             login_hint,
             locale,
+            device_fingerprint,
+            authorization_details,
+            resource,
+            required_acr,
+            max_age,
+            prompt_login,
+            prompt_consent,
+            claims,
         })
     }
 
@@ -302,6 +393,14 @@ impl OAuth2AuthorizationGrantRepository for PgOAuth2AuthorizationGrantRepository
                      , code_challenge_method
                      , login_hint
                      , locale
+                     , device_fingerprint
+                     , authorization_details as "authorization_details: Json<Vec<AuthorizationDetail>>"
+                     , resource
+                     , required_acr
+                     , max_age
+                     , prompt_login
+                     , prompt_consent
+                     , claims as "claims: Json<ClaimsRequest>"
                      , oauth2_session_id
                 FROM
                     oauth2_authorization_grants
@@ -352,6 +451,14 @@ impl OAuth2AuthorizationGrantRepository for PgOAuth2AuthorizationGrantRepository
                      , code_challenge_method
                      , login_hint
                      , locale
+                     , device_fingerprint
+                     , authorization_details as "authorization_details: Json<Vec<AuthorizationDetail>>"
+                     , resource
+                     , required_acr
+                     , max_age
+                     , prompt_login
+                     , prompt_consent
+                     , claims as "claims: Json<ClaimsRequest>"
                      , oauth2_session_id
                 FROM
                     oauth2_authorization_grants
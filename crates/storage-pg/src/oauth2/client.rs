@@ -10,11 +10,25 @@ use std::{
 };
 
 use async_trait::async_trait;
-use mas_data_model::{Client, JwksOrJwksUri};
-use mas_iana::{jose::JsonWebSignatureAlg, oauth::OAuthClientAuthenticationMethod};
+use chrono::{DateTime, Duration, Utc};
+use mas_data_model::{
+    AccessTokenFormat, Client, DisallowedScopeHandling, JwksOrJwksUri, RefreshTokenRotation,
+    ScopePolicy,
+};
+use mas_iana::{
+    jose::{JsonWebEncryptionAlg, JsonWebEncryptionEnc, JsonWebSignatureAlg},
+    oauth::OAuthClientAuthenticationMethod,
+};
 use mas_jose::jwk::PublicJsonWebKeySet;
-use mas_storage::{Clock, oauth2::OAuth2ClientRepository};
-use oauth2_types::{oidc::ApplicationType, requests::GrantType};
+use mas_storage::{
+    Clock,
+    oauth2::{OAuth2ClientRepository, OAuth2StaticClientParams},
+};
+use oauth2_types::{
+    oidc::ApplicationType,
+    requests::GrantType,
+    scope::{Scope, ScopeToken},
+};
 use opentelemetry_semantic_conventions::attribute::DB_QUERY_TEXT;
 use rand::RngCore;
 use sqlx::PgConnection;
@@ -50,6 +64,7 @@ struct OAuth2ClientLookup {
     grant_type_refresh_token: bool,
     grant_type_client_credentials: bool,
     grant_type_device_code: bool,
+    grant_type_password: bool,
     client_name: Option<String>,
     logo_uri: Option<String>,
     client_uri: Option<String>,
@@ -62,6 +77,40 @@ struct OAuth2ClientLookup {
     token_endpoint_auth_method: Option<String>,
     token_endpoint_auth_signing_alg: Option<String>,
     initiate_login_uri: Option<String>,
+    require_pushed_authorization_requests: bool,
+    authorization_signed_response_alg: Option<String>,
+    service_account_scope_list: Option<Vec<String>>,
+    grant_type_ciba: bool,
+    backchannel_client_notification_endpoint: Option<String>,
+    allowed_resources: Vec<String>,
+    refresh_token_rotation: String,
+    backchannel_logout_uri: Option<String>,
+    backchannel_logout_session_required: bool,
+    backchannel_logout_include_sub: bool,
+    post_logout_redirect_uris: Vec<String>,
+    access_token_format: String,
+    access_token_ttl: Option<i32>,
+    refresh_token_ttl: Option<i32>,
+    device_code_ttl: Option<i32>,
+    request_object_signing_alg: Option<String>,
+    require_signed_request_object: bool,
+    id_token_encrypted_response_alg: Option<String>,
+    id_token_encrypted_response_enc: Option<String>,
+    default_acr_values: Vec<String>,
+    resource_server_audience: Option<String>,
+    client_secret_expires_at: Option<DateTime<Utc>>,
+    encrypted_next_client_secret: Option<String>,
+    next_client_secret_expires_at: Option<DateTime<Utc>>,
+    pairwise_sector_identifier: Option<String>,
+    allowed_scope_list: Option<Vec<String>>,
+    disallowed_scope_handling: String,
+}
+
+/// Convert a lifetime to the number of seconds stored in the database
+fn ttl_to_seconds(ttl: Option<Duration>) -> Result<Option<i32>, DatabaseError> {
+    ttl.map(|ttl| i32::try_from(ttl.num_seconds()))
+        .transpose()
+        .map_err(DatabaseError::to_invalid_operation)
 }
 
 impl TryInto<Client> for OAuth2ClientLookup {
@@ -104,6 +153,12 @@ impl TryInto<Client> for OAuth2ClientLookup {
         if self.grant_type_device_code {
             grant_types.push(GrantType::DeviceCode);
         }
+        if self.grant_type_password {
+            grant_types.push(GrantType::Password);
+        }
+        if self.grant_type_ciba {
+            grant_types.push(GrantType::ClientInitiatedBackchannelAuthentication);
+        }
 
         let logo_uri = self.logo_uri.map(|s| s.parse()).transpose().map_err(|e| {
             DatabaseInconsistencyError::on("oauth2_clients")
@@ -163,6 +218,50 @@ impl TryInto<Client> for OAuth2ClientLookup {
                     .source(e)
             })?;
 
+        let authorization_signed_response_alg = self
+            .authorization_signed_response_alg
+            .map(|s| s.parse())
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_clients")
+                    .column("authorization_signed_response_alg")
+                    .row(id)
+                    .source(e)
+            })?;
+
+        let request_object_signing_alg = self
+            .request_object_signing_alg
+            .map(|s| s.parse())
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_clients")
+                    .column("request_object_signing_alg")
+                    .row(id)
+                    .source(e)
+            })?;
+
+        let id_token_encrypted_response_alg = self
+            .id_token_encrypted_response_alg
+            .map(|s| s.parse())
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_clients")
+                    .column("id_token_encrypted_response_alg")
+                    .row(id)
+                    .source(e)
+            })?;
+
+        let id_token_encrypted_response_enc = self
+            .id_token_encrypted_response_enc
+            .map(|s| s.parse())
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_clients")
+                    .column("id_token_encrypted_response_enc")
+                    .row(id)
+                    .source(e)
+            })?;
+
         let token_endpoint_auth_method = self
             .token_endpoint_auth_method
             .map(|s| s.parse())
@@ -196,6 +295,105 @@ impl TryInto<Client> for OAuth2ClientLookup {
                     .source(e)
             })?;
 
+        let service_account_scope = self
+            .service_account_scope_list
+            .map(|scope_list| {
+                scope_list
+                    .into_iter()
+                    .map(|s| s.parse::<ScopeToken>())
+                    .collect::<Result<Scope, _>>()
+            })
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_clients")
+                    .column("service_account_scope_list")
+                    .row(id)
+                    .source(e)
+            })?;
+
+        let backchannel_client_notification_endpoint = self
+            .backchannel_client_notification_endpoint
+            .map(|s| s.parse())
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_clients")
+                    .column("backchannel_client_notification_endpoint")
+                    .row(id)
+                    .source(e)
+            })?;
+
+        let allowed_resources: Result<Vec<Url>, _> =
+            self.allowed_resources.iter().map(|s| s.parse()).collect();
+        let allowed_resources = allowed_resources.map_err(|e| {
+            DatabaseInconsistencyError::on("oauth2_clients")
+                .column("allowed_resources")
+                .row(id)
+                .source(e)
+        })?;
+
+        let refresh_token_rotation: RefreshTokenRotation =
+            self.refresh_token_rotation.parse().map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_clients")
+                    .column("refresh_token_rotation")
+                    .row(id)
+                    .source(e)
+            })?;
+
+        let disallowed_scope_handling: DisallowedScopeHandling =
+            self.disallowed_scope_handling.parse().map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_clients")
+                    .column("disallowed_scope_handling")
+                    .row(id)
+                    .source(e)
+            })?;
+
+        let scope_policy = self.allowed_scope_list.map(|allowed_scopes| ScopePolicy {
+            allowed_scopes,
+            disallowed: disallowed_scope_handling,
+        });
+
+        let post_logout_redirect_uris: Result<Vec<Url>, _> = self
+            .post_logout_redirect_uris
+            .iter()
+            .map(|s| s.parse())
+            .collect();
+        let post_logout_redirect_uris = post_logout_redirect_uris.map_err(|e| {
+            DatabaseInconsistencyError::on("oauth2_clients")
+                .column("post_logout_redirect_uris")
+                .row(id)
+                .source(e)
+        })?;
+
+        let access_token_format: AccessTokenFormat =
+            self.access_token_format.parse().map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_clients")
+                    .column("access_token_format")
+                    .row(id)
+                    .source(e)
+            })?;
+
+        let resource_server_audience = self
+            .resource_server_audience
+            .map(|s| s.parse())
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_clients")
+                    .column("resource_server_audience")
+                    .row(id)
+                    .source(e)
+            })?;
+
+        let backchannel_logout_uri = self
+            .backchannel_logout_uri
+            .map(|s| s.parse())
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_clients")
+                    .column("backchannel_logout_uri")
+                    .row(id)
+                    .source(e)
+            })?;
+
         let jwks = match (self.jwks, self.jwks_uri) {
             (None, None) => None,
             (Some(jwks), None) => {
@@ -229,6 +427,9 @@ impl TryInto<Client> for OAuth2ClientLookup {
             client_id: id.to_string(),
             metadata_digest: self.metadata_digest,
             encrypted_client_secret: self.encrypted_client_secret,
+            client_secret_expires_at: self.client_secret_expires_at,
+            encrypted_next_client_secret: self.encrypted_next_client_secret,
+            next_client_secret_expires_at: self.next_client_secret_expires_at,
             application_type,
             redirect_uris,
             grant_types,
@@ -240,9 +441,37 @@ impl TryInto<Client> for OAuth2ClientLookup {
             jwks,
             id_token_signed_response_alg,
             userinfo_signed_response_alg,
+            authorization_signed_response_alg,
             token_endpoint_auth_method,
             token_endpoint_auth_signing_alg,
             initiate_login_uri,
+            require_pushed_authorization_requests: self.require_pushed_authorization_requests,
+            service_account_scope,
+            backchannel_client_notification_endpoint,
+            allowed_resources,
+            refresh_token_rotation,
+            backchannel_logout_uri,
+            backchannel_logout_session_required: self.backchannel_logout_session_required,
+            backchannel_logout_include_sub: self.backchannel_logout_include_sub,
+            post_logout_redirect_uris,
+            access_token_format,
+            access_token_ttl: self
+                .access_token_ttl
+                .map(|seconds| Duration::seconds(i64::from(seconds))),
+            refresh_token_ttl: self
+                .refresh_token_ttl
+                .map(|seconds| Duration::seconds(i64::from(seconds))),
+            device_code_ttl: self
+                .device_code_ttl
+                .map(|seconds| Duration::seconds(i64::from(seconds))),
+            request_object_signing_alg,
+            require_signed_request_object: self.require_signed_request_object,
+            id_token_encrypted_response_alg,
+            id_token_encrypted_response_enc,
+            default_acr_values: self.default_acr_values,
+            resource_server_audience,
+            pairwise_sector_identifier: self.pairwise_sector_identifier,
+            scope_policy,
         })
     }
 }
@@ -273,6 +502,7 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                      , grant_type_refresh_token
                      , grant_type_client_credentials
                      , grant_type_device_code
+                     , grant_type_password
                      , client_name
                      , logo_uri
                      , client_uri
@@ -285,6 +515,33 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                      , token_endpoint_auth_method
                      , token_endpoint_auth_signing_alg
                      , initiate_login_uri
+                     , require_pushed_authorization_requests
+                     , authorization_signed_response_alg
+                     , service_account_scope_list
+                     , grant_type_ciba
+                     , backchannel_client_notification_endpoint
+                     , allowed_resources
+                     , refresh_token_rotation
+                     , backchannel_logout_uri
+                     , backchannel_logout_session_required
+                     , backchannel_logout_include_sub
+                     , post_logout_redirect_uris
+                     , access_token_format
+                     , access_token_ttl
+                     , refresh_token_ttl
+                     , device_code_ttl
+                     , request_object_signing_alg
+                     , require_signed_request_object
+                     , id_token_encrypted_response_alg
+                     , id_token_encrypted_response_enc
+                     , default_acr_values
+                     , resource_server_audience
+                     , client_secret_expires_at
+                     , encrypted_next_client_secret
+                     , next_client_secret_expires_at
+                     , pairwise_sector_identifier
+                     , allowed_scope_list
+                     , disallowed_scope_handling
                 FROM oauth2_clients c
 
                 WHERE oauth2_client_id = $1
@@ -324,6 +581,7 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                     , grant_type_refresh_token
                     , grant_type_client_credentials
                     , grant_type_device_code
+                    , grant_type_password
                     , client_name
                     , logo_uri
                     , client_uri
@@ -336,6 +594,33 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                     , token_endpoint_auth_method
                     , token_endpoint_auth_signing_alg
                     , initiate_login_uri
+                    , require_pushed_authorization_requests
+                    , authorization_signed_response_alg
+                    , service_account_scope_list
+                    , grant_type_ciba
+                    , backchannel_client_notification_endpoint
+                    , allowed_resources
+                    , refresh_token_rotation
+                    , backchannel_logout_uri
+                    , backchannel_logout_session_required
+                    , backchannel_logout_include_sub
+                    , post_logout_redirect_uris
+                    , access_token_format
+                    , access_token_ttl
+                    , refresh_token_ttl
+                    , device_code_ttl
+                    , request_object_signing_alg
+                    , require_signed_request_object
+                    , id_token_encrypted_response_alg
+                    , id_token_encrypted_response_enc
+                    , default_acr_values
+                    , resource_server_audience
+                    , client_secret_expires_at
+                    , encrypted_next_client_secret
+                    , next_client_secret_expires_at
+                    , pairwise_sector_identifier
+                    , allowed_scope_list
+                    , disallowed_scope_handling
                 FROM oauth2_clients
                 WHERE metadata_digest = $1
             "#,
@@ -375,6 +660,7 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                      , grant_type_refresh_token
                      , grant_type_client_credentials
                      , grant_type_device_code
+                     , grant_type_password
                      , client_name
                      , logo_uri
                      , client_uri
@@ -387,6 +673,33 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                      , token_endpoint_auth_method
                      , token_endpoint_auth_signing_alg
                      , initiate_login_uri
+                     , require_pushed_authorization_requests
+                     , authorization_signed_response_alg
+                     , service_account_scope_list
+                     , grant_type_ciba
+                     , backchannel_client_notification_endpoint
+                     , allowed_resources
+                     , refresh_token_rotation
+                     , backchannel_logout_uri
+                     , backchannel_logout_session_required
+                     , backchannel_logout_include_sub
+                     , post_logout_redirect_uris
+                     , access_token_format
+                     , access_token_ttl
+                     , refresh_token_ttl
+                     , device_code_ttl
+                     , request_object_signing_alg
+                     , require_signed_request_object
+                     , id_token_encrypted_response_alg
+                     , id_token_encrypted_response_enc
+                     , default_acr_values
+                     , resource_server_audience
+                     , client_secret_expires_at
+                     , encrypted_next_client_secret
+                     , next_client_secret_expires_at
+                     , pairwise_sector_identifier
+                     , allowed_scope_list
+                     , disallowed_scope_handling
                 FROM oauth2_clients c
 
                 WHERE oauth2_client_id = ANY($1::uuid[])
@@ -438,6 +751,19 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
         token_endpoint_auth_method: Option<OAuthClientAuthenticationMethod>,
         token_endpoint_auth_signing_alg: Option<JsonWebSignatureAlg>,
         initiate_login_uri: Option<Url>,
+        authorization_signed_response_alg: Option<JsonWebSignatureAlg>,
+        backchannel_logout_uri: Option<Url>,
+        backchannel_logout_session_required: bool,
+        post_logout_redirect_uris: Vec<Url>,
+        access_token_ttl: Option<Duration>,
+        refresh_token_ttl: Option<Duration>,
+        device_code_ttl: Option<Duration>,
+        request_object_signing_alg: Option<JsonWebSignatureAlg>,
+        require_signed_request_object: bool,
+        id_token_encrypted_response_alg: Option<JsonWebEncryptionAlg>,
+        id_token_encrypted_response_enc: Option<JsonWebEncryptionEnc>,
+        default_acr_values: Vec<String>,
+        pairwise_sector_identifier: Option<String>,
     ) -> Result<Client, Self::Error> {
         let now = clock.now();
         let id = Ulid::from_datetime_with_source(now.into(), rng);
@@ -450,6 +776,10 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
             .map_err(DatabaseError::to_invalid_operation)?;
 
         let redirect_uris_array = redirect_uris.iter().map(Url::to_string).collect::<Vec<_>>();
+        let post_logout_redirect_uris_array = post_logout_redirect_uris
+            .iter()
+            .map(Url::to_string)
+            .collect::<Vec<_>>();
 
         sqlx::query!(
             r#"
@@ -463,6 +793,8 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                     , grant_type_refresh_token
                     , grant_type_client_credentials
                     , grant_type_device_code
+                    , grant_type_password
+                    , grant_type_ciba
                     , client_name
                     , logo_uri
                     , client_uri
@@ -475,11 +807,25 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                     , token_endpoint_auth_method
                     , token_endpoint_auth_signing_alg
                     , initiate_login_uri
+                    , authorization_signed_response_alg
+                    , backchannel_logout_uri
+                    , backchannel_logout_session_required
+                    , post_logout_redirect_uris
+                    , access_token_ttl
+                    , refresh_token_ttl
+                    , device_code_ttl
+                    , request_object_signing_alg
+                    , require_signed_request_object
+                    , id_token_encrypted_response_alg
+                    , id_token_encrypted_response_enc
+                    , default_acr_values
+                    , pairwise_sector_identifier
                     , is_static
                     )
                 VALUES
                     ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13,
-                    $14, $15, $16, $17, $18, $19, $20, $21, FALSE)
+                    $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24,
+                    $25, $26, $27, $28, $29, $30, $31, $32, $33, $34, $35, $36, FALSE)
             "#,
             Uuid::from(id),
             metadata_digest,
@@ -490,6 +836,8 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
             grant_types.contains(&GrantType::RefreshToken),
             grant_types.contains(&GrantType::ClientCredentials),
             grant_types.contains(&GrantType::DeviceCode),
+            grant_types.contains(&GrantType::Password),
+            grant_types.contains(&GrantType::ClientInitiatedBackchannelAuthentication),
             client_name,
             logo_uri.as_ref().map(Url::as_str),
             client_uri.as_ref().map(Url::as_str),
@@ -508,6 +856,25 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                 .as_ref()
                 .map(ToString::to_string),
             initiate_login_uri.as_ref().map(Url::as_str),
+            authorization_signed_response_alg
+                .as_ref()
+                .map(ToString::to_string),
+            backchannel_logout_uri.as_ref().map(Url::as_str),
+            backchannel_logout_session_required,
+            &post_logout_redirect_uris_array,
+            ttl_to_seconds(access_token_ttl)?,
+            ttl_to_seconds(refresh_token_ttl)?,
+            ttl_to_seconds(device_code_ttl)?,
+            request_object_signing_alg.as_ref().map(ToString::to_string),
+            require_signed_request_object,
+            id_token_encrypted_response_alg
+                .as_ref()
+                .map(ToString::to_string),
+            id_token_encrypted_response_enc
+                .as_ref()
+                .map(ToString::to_string),
+            &default_acr_values,
+            pairwise_sector_identifier.as_deref(),
         )
         .traced()
         .execute(&mut *self.conn)
@@ -525,6 +892,9 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
             client_id: id.to_string(),
             metadata_digest: None,
             encrypted_client_secret,
+            client_secret_expires_at: None,
+            encrypted_next_client_secret: None,
+            next_client_secret_expires_at: None,
             application_type,
             redirect_uris,
             grant_types,
@@ -536,9 +906,31 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
             jwks,
             id_token_signed_response_alg,
             userinfo_signed_response_alg,
+            authorization_signed_response_alg,
             token_endpoint_auth_method,
             token_endpoint_auth_signing_alg,
             initiate_login_uri,
+            require_pushed_authorization_requests: false,
+            service_account_scope: None,
+            backchannel_client_notification_endpoint: None,
+            allowed_resources: Vec::new(),
+            refresh_token_rotation: RefreshTokenRotation::RotateOnUse,
+            backchannel_logout_uri,
+            backchannel_logout_session_required,
+            backchannel_logout_include_sub: true,
+            post_logout_redirect_uris,
+            access_token_format: AccessTokenFormat::Opaque,
+            access_token_ttl,
+            refresh_token_ttl,
+            device_code_ttl,
+            request_object_signing_alg,
+            require_signed_request_object,
+            id_token_encrypted_response_alg,
+            id_token_encrypted_response_enc,
+            default_acr_values,
+            resource_server_audience: None,
+            pairwise_sector_identifier,
+            scope_policy: None,
         })
     }
 
@@ -554,13 +946,42 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
     async fn upsert_static(
         &mut self,
         client_id: Ulid,
-        client_name: Option<String>,
         client_auth_method: OAuthClientAuthenticationMethod,
-        encrypted_client_secret: Option<String>,
-        jwks: Option<PublicJsonWebKeySet>,
-        jwks_uri: Option<Url>,
-        redirect_uris: Vec<Url>,
+        params: OAuth2StaticClientParams,
     ) -> Result<Client, Self::Error> {
+        let OAuth2StaticClientParams {
+            client_name,
+            encrypted_client_secret,
+            jwks,
+            jwks_uri,
+            redirect_uris,
+            require_pushed_authorization_requests,
+            authorization_signed_response_alg,
+            service_account_scope,
+            backchannel_client_notification_endpoint,
+            allowed_resources,
+            refresh_token_rotation,
+            backchannel_logout_uri,
+            backchannel_logout_session_required,
+            backchannel_logout_include_sub,
+            post_logout_redirect_uris,
+            access_token_format,
+            access_token_ttl,
+            refresh_token_ttl,
+            device_code_ttl,
+            request_object_signing_alg,
+            require_signed_request_object,
+            id_token_encrypted_response_alg,
+            id_token_encrypted_response_enc,
+            default_acr_values,
+            resource_server_audience,
+            client_secret_expires_at,
+            encrypted_next_client_secret,
+            next_client_secret_expires_at,
+            pairwise_sector_identifier,
+            scope_policy,
+        } = params;
+
         let jwks_json = jwks
             .as_ref()
             .map(serde_json::to_value)
@@ -569,6 +990,17 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
 
         let client_auth_method = client_auth_method.to_string();
         let redirect_uris_array = redirect_uris.iter().map(Url::to_string).collect::<Vec<_>>();
+        let allowed_resources_array = allowed_resources
+            .iter()
+            .map(Url::to_string)
+            .collect::<Vec<_>>();
+        let post_logout_redirect_uris_array = post_logout_redirect_uris
+            .iter()
+            .map(Url::to_string)
+            .collect::<Vec<_>>();
+        let service_account_scope_list: Option<Vec<String>> = service_account_scope
+            .as_ref()
+            .map(|scope| scope.iter().map(ToString::to_string).collect());
 
         sqlx::query!(
             r#"
@@ -580,14 +1012,44 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                     , grant_type_refresh_token
                     , grant_type_client_credentials
                     , grant_type_device_code
+                    , grant_type_password
+                    , grant_type_ciba
                     , token_endpoint_auth_method
                     , jwks
                     , client_name
                     , jwks_uri
+                    , require_pushed_authorization_requests
+                    , authorization_signed_response_alg
+                    , service_account_scope_list
+                    , backchannel_client_notification_endpoint
+                    , allowed_resources
+                    , refresh_token_rotation
+                    , backchannel_logout_uri
+                    , backchannel_logout_session_required
+                    , backchannel_logout_include_sub
+                    , post_logout_redirect_uris
+                    , access_token_format
+                    , access_token_ttl
+                    , refresh_token_ttl
+                    , device_code_ttl
+                    , request_object_signing_alg
+                    , require_signed_request_object
+                    , id_token_encrypted_response_alg
+                    , id_token_encrypted_response_enc
+                    , default_acr_values
+                    , resource_server_audience
+                    , client_secret_expires_at
+                    , encrypted_next_client_secret
+                    , next_client_secret_expires_at
+                    , pairwise_sector_identifier
+                    , allowed_scope_list
+                    , disallowed_scope_handling
                     , is_static
                     )
                 VALUES
-                    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, TRUE)
+                    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16,
+                    $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31,
+                    $32, $33, $34, $35, $36, $37, $38, $39, TRUE)
                 ON CONFLICT (oauth2_client_id)
                 DO
                     UPDATE SET encrypted_client_secret = EXCLUDED.encrypted_client_secret
@@ -596,10 +1058,38 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                              , grant_type_refresh_token = EXCLUDED.grant_type_refresh_token
                              , grant_type_client_credentials = EXCLUDED.grant_type_client_credentials
                              , grant_type_device_code = EXCLUDED.grant_type_device_code
+                             , grant_type_password = EXCLUDED.grant_type_password
+                             , grant_type_ciba = EXCLUDED.grant_type_ciba
                              , token_endpoint_auth_method = EXCLUDED.token_endpoint_auth_method
                              , jwks = EXCLUDED.jwks
                              , client_name = EXCLUDED.client_name
                              , jwks_uri = EXCLUDED.jwks_uri
+                             , require_pushed_authorization_requests = EXCLUDED.require_pushed_authorization_requests
+                             , authorization_signed_response_alg = EXCLUDED.authorization_signed_response_alg
+                             , service_account_scope_list = EXCLUDED.service_account_scope_list
+                             , backchannel_client_notification_endpoint = EXCLUDED.backchannel_client_notification_endpoint
+                             , allowed_resources = EXCLUDED.allowed_resources
+                             , refresh_token_rotation = EXCLUDED.refresh_token_rotation
+                             , backchannel_logout_uri = EXCLUDED.backchannel_logout_uri
+                             , backchannel_logout_session_required = EXCLUDED.backchannel_logout_session_required
+                             , backchannel_logout_include_sub = EXCLUDED.backchannel_logout_include_sub
+                             , post_logout_redirect_uris = EXCLUDED.post_logout_redirect_uris
+                             , access_token_format = EXCLUDED.access_token_format
+                             , access_token_ttl = EXCLUDED.access_token_ttl
+                             , refresh_token_ttl = EXCLUDED.refresh_token_ttl
+                             , device_code_ttl = EXCLUDED.device_code_ttl
+                             , request_object_signing_alg = EXCLUDED.request_object_signing_alg
+                             , require_signed_request_object = EXCLUDED.require_signed_request_object
+                             , id_token_encrypted_response_alg = EXCLUDED.id_token_encrypted_response_alg
+                             , id_token_encrypted_response_enc = EXCLUDED.id_token_encrypted_response_enc
+                             , default_acr_values = EXCLUDED.default_acr_values
+                             , resource_server_audience = EXCLUDED.resource_server_audience
+                             , client_secret_expires_at = EXCLUDED.client_secret_expires_at
+                             , encrypted_next_client_secret = EXCLUDED.encrypted_next_client_secret
+                             , next_client_secret_expires_at = EXCLUDED.next_client_secret_expires_at
+                             , pairwise_sector_identifier = EXCLUDED.pairwise_sector_identifier
+                             , allowed_scope_list = EXCLUDED.allowed_scope_list
+                             , disallowed_scope_handling = EXCLUDED.disallowed_scope_handling
                              , is_static = TRUE
             "#,
             Uuid::from(client_id),
@@ -609,10 +1099,47 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
             true,
             true,
             true,
+            true,
+            true,
             client_auth_method,
             jwks_json,
             client_name,
             jwks_uri.as_ref().map(Url::as_str),
+            require_pushed_authorization_requests,
+            authorization_signed_response_alg
+                .as_ref()
+                .map(ToString::to_string),
+            service_account_scope_list.as_deref(),
+            backchannel_client_notification_endpoint
+                .as_ref()
+                .map(Url::as_str),
+            &allowed_resources_array,
+            refresh_token_rotation.as_str(),
+            backchannel_logout_uri.as_ref().map(Url::as_str),
+            backchannel_logout_session_required,
+            backchannel_logout_include_sub,
+            &post_logout_redirect_uris_array,
+            access_token_format.as_str(),
+            ttl_to_seconds(access_token_ttl)?,
+            ttl_to_seconds(refresh_token_ttl)?,
+            ttl_to_seconds(device_code_ttl)?,
+            request_object_signing_alg.as_ref().map(ToString::to_string),
+            require_signed_request_object,
+            id_token_encrypted_response_alg.as_ref().map(ToString::to_string),
+            id_token_encrypted_response_enc.as_ref().map(ToString::to_string),
+            &default_acr_values,
+            resource_server_audience.as_ref().map(ToString::to_string),
+            client_secret_expires_at,
+            encrypted_next_client_secret.as_deref(),
+            next_client_secret_expires_at,
+            pairwise_sector_identifier.as_deref(),
+            scope_policy
+                .as_ref()
+                .map(|policy| policy.allowed_scopes.as_slice()),
+            scope_policy
+                .as_ref()
+                .map_or(DisallowedScopeHandling::default(), |policy| policy.disallowed)
+                .as_str(),
         )
         .traced()
         .execute(&mut *self.conn)
@@ -630,12 +1157,17 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
             client_id: client_id.to_string(),
             metadata_digest: None,
             encrypted_client_secret,
+            client_secret_expires_at,
+            encrypted_next_client_secret,
+            next_client_secret_expires_at,
             application_type: None,
             redirect_uris,
             grant_types: vec![
                 GrantType::AuthorizationCode,
                 GrantType::RefreshToken,
                 GrantType::ClientCredentials,
+                GrantType::Password,
+                GrantType::ClientInitiatedBackchannelAuthentication,
             ],
             client_name,
             logo_uri: None,
@@ -645,12 +1177,110 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
             jwks,
             id_token_signed_response_alg: None,
             userinfo_signed_response_alg: None,
+            authorization_signed_response_alg,
             token_endpoint_auth_method: None,
             token_endpoint_auth_signing_alg: None,
             initiate_login_uri: None,
+            require_pushed_authorization_requests,
+            service_account_scope,
+            backchannel_client_notification_endpoint,
+            allowed_resources,
+            refresh_token_rotation,
+            backchannel_logout_uri,
+            backchannel_logout_session_required,
+            backchannel_logout_include_sub,
+            post_logout_redirect_uris,
+            access_token_format,
+            access_token_ttl,
+            refresh_token_ttl,
+            device_code_ttl,
+            request_object_signing_alg,
+            require_signed_request_object,
+            id_token_encrypted_response_alg,
+            id_token_encrypted_response_enc,
+            default_acr_values,
+            resource_server_audience,
+            pairwise_sector_identifier,
+            scope_policy,
         })
     }
 
+    #[tracing::instrument(
+        name = "db.oauth2_client.set_next_secret",
+        skip_all,
+        fields(
+            db.query.text,
+            %client.id,
+        ),
+        err,
+    )]
+    async fn set_next_secret(
+        &mut self,
+        mut client: Client,
+        encrypted_next_client_secret: String,
+        next_client_secret_expires_at: Option<DateTime<Utc>>,
+        client_secret_expires_at: Option<DateTime<Utc>>,
+    ) -> Result<Client, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE oauth2_clients
+                SET encrypted_next_client_secret = $2
+                  , next_client_secret_expires_at = $3
+                  , client_secret_expires_at = $4
+                WHERE oauth2_client_id = $1
+            "#,
+            Uuid::from(client.id),
+            &encrypted_next_client_secret,
+            next_client_secret_expires_at,
+            client_secret_expires_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        client.encrypted_next_client_secret = Some(encrypted_next_client_secret);
+        client.next_client_secret_expires_at = next_client_secret_expires_at;
+        client.client_secret_expires_at = client_secret_expires_at;
+
+        Ok(client)
+    }
+
+    #[tracing::instrument(
+        name = "db.oauth2_client.promote_next_secret",
+        skip_all,
+        fields(
+            db.query.text,
+            %client.id,
+        ),
+        err,
+    )]
+    async fn promote_next_secret(&mut self, mut client: Client) -> Result<Client, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE oauth2_clients
+                SET encrypted_client_secret = encrypted_next_client_secret
+                  , client_secret_expires_at = next_client_secret_expires_at
+                  , encrypted_next_client_secret = NULL
+                  , next_client_secret_expires_at = NULL
+                WHERE oauth2_client_id = $1
+                  AND encrypted_next_client_secret IS NOT NULL
+            "#,
+            Uuid::from(client.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        client.encrypted_client_secret = client.encrypted_next_client_secret.take();
+        client.client_secret_expires_at = client.next_client_secret_expires_at.take();
+
+        Ok(client)
+    }
+
     #[tracing::instrument(
         name = "db.oauth2_client.all_static",
         skip_all,
@@ -672,6 +1302,7 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                      , grant_type_refresh_token
                      , grant_type_client_credentials
                      , grant_type_device_code
+                     , grant_type_password
                      , client_name
                      , logo_uri
                      , client_uri
@@ -684,6 +1315,33 @@ impl OAuth2ClientRepository for PgOAuth2ClientRepository<'_> {
                      , token_endpoint_auth_method
                      , token_endpoint_auth_signing_alg
                      , initiate_login_uri
+                     , require_pushed_authorization_requests
+                     , authorization_signed_response_alg
+                     , service_account_scope_list
+                     , grant_type_ciba
+                     , backchannel_client_notification_endpoint
+                     , allowed_resources
+                     , refresh_token_rotation
+                     , backchannel_logout_uri
+                     , backchannel_logout_session_required
+                     , backchannel_logout_include_sub
+                     , post_logout_redirect_uris
+                     , access_token_format
+                     , access_token_ttl
+                     , refresh_token_ttl
+                     , device_code_ttl
+                     , request_object_signing_alg
+                     , require_signed_request_object
+                     , id_token_encrypted_response_alg
+                     , id_token_encrypted_response_enc
+                     , default_acr_values
+                     , resource_server_audience
+                     , client_secret_expires_at
+                     , encrypted_next_client_secret
+                     , next_client_secret_expires_at
+                     , pairwise_sector_identifier
+                     , allowed_scope_list
+                     , disallowed_scope_handling
                 FROM oauth2_clients c
                 WHERE is_static = TRUE
             "#,
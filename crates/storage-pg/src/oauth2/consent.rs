@@ -0,0 +1,289 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{Client, Consent, User};
+use mas_storage::{Clock, oauth2::OAuth2ConsentRepository};
+use oauth2_types::scope::{Scope, ScopeToken};
+use rand::RngCore;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, DatabaseInconsistencyError, ExecuteExt};
+
+/// An implementation of [`OAuth2ConsentRepository`] for a PostgreSQL
+/// connection
+pub struct PgOAuth2ConsentRepository<'c> {
+    conn: &'c mut sqlx::PgConnection,
+}
+
+impl<'c> PgOAuth2ConsentRepository<'c> {
+    /// Create a new [`PgOAuth2ConsentRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut sqlx::PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct ConsentLookup {
+    oauth2_consent_id: Uuid,
+    oauth2_client_id: Uuid,
+    user_id: Uuid,
+    scope_token: String,
+    created_at: DateTime<Utc>,
+    refreshed_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<ConsentLookup> for Consent {
+    type Error = DatabaseInconsistencyError;
+
+    fn try_from(value: ConsentLookup) -> Result<Self, Self::Error> {
+        let id = Ulid::from(value.oauth2_consent_id);
+        let scope_token: ScopeToken = value.scope_token.parse().map_err(|e| {
+            DatabaseInconsistencyError::on("oauth2_consents")
+                .column("scope_token")
+                .row(id)
+                .source(e)
+        })?;
+
+        Ok(Consent {
+            id,
+            user_id: value.user_id.into(),
+            client_id: value.oauth2_client_id.into(),
+            scope_token,
+            created_at: value.created_at,
+            refreshed_at: value.refreshed_at,
+        })
+    }
+}
+
+#[async_trait]
+impl OAuth2ConsentRepository for PgOAuth2ConsentRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.oauth2_consent.lookup",
+        skip_all,
+        fields(
+            db.query.text,
+            oauth2_consent.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<Consent>, Self::Error> {
+        let res = sqlx::query_as!(
+            ConsentLookup,
+            r#"
+                SELECT oauth2_consent_id
+                     , oauth2_client_id
+                     , user_id
+                     , scope_token
+                     , created_at
+                     , refreshed_at
+                FROM oauth2_consents
+
+                WHERE oauth2_consent_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        let Some(res) = res else { return Ok(None) };
+
+        Ok(Some(res.try_into()?))
+    }
+
+    #[tracing::instrument(
+        name = "db.oauth2_consent.all_for_user",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn all_for_user(&mut self, user: &User) -> Result<Vec<Consent>, Self::Error> {
+        let res = sqlx::query_as!(
+            ConsentLookup,
+            r#"
+                SELECT oauth2_consent_id
+                     , oauth2_client_id
+                     , user_id
+                     , scope_token
+                     , created_at
+                     , refreshed_at
+                FROM oauth2_consents
+
+                WHERE user_id = $1
+                ORDER BY oauth2_client_id, scope_token
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        let consents: Result<Vec<Consent>, _> = res.into_iter().map(TryInto::try_into).collect();
+        Ok(consents?)
+    }
+
+    #[tracing::instrument(
+        name = "db.oauth2_consent.get_for_client",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            %client.id,
+        ),
+        err,
+    )]
+    async fn get_for_client(&mut self, client: &Client, user: &User) -> Result<Scope, Self::Error> {
+        let scope_tokens: Vec<String> = sqlx::query_scalar!(
+            r#"
+                SELECT scope_token
+                FROM oauth2_consents
+                WHERE user_id = $1 AND oauth2_client_id = $2
+            "#,
+            Uuid::from(user.id),
+            Uuid::from(client.id),
+        )
+        .traced()
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        let scope: Result<Scope, _> = scope_tokens
+            .into_iter()
+            .map(|s| s.parse::<ScopeToken>())
+            .collect();
+
+        let scope = scope.map_err(|e| {
+            DatabaseInconsistencyError::on("oauth2_consents")
+                .column("scope_token")
+                .source(e)
+        })?;
+
+        Ok(scope)
+    }
+
+    #[tracing::instrument(
+        name = "db.oauth2_consent.remember",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            %client.id,
+            %scope,
+        ),
+        err,
+    )]
+    async fn remember(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        client: &Client,
+        user: &User,
+        scope: &Scope,
+    ) -> Result<(), Self::Error> {
+        let now = clock.now();
+        let (tokens, ids): (Vec<String>, Vec<Uuid>) = scope
+            .iter()
+            .map(|token| {
+                (
+                    token.to_string(),
+                    Uuid::from(Ulid::from_datetime_with_source(now.into(), rng)),
+                )
+            })
+            .unzip();
+
+        // Scope tokens which were already granted only get their refresh time
+        // updated
+        sqlx::query!(
+            r#"
+                INSERT INTO oauth2_consents
+                    ( oauth2_consent_id
+                    , user_id
+                    , oauth2_client_id
+                    , scope_token
+                    , created_at
+                    )
+                SELECT id, $2, $3, scope_token, $5
+                FROM UNNEST($1::uuid[], $4::text[]) u(id, scope_token)
+                ON CONFLICT (oauth2_client_id, user_id, scope_token)
+                    DO UPDATE SET refreshed_at = EXCLUDED.created_at
+            "#,
+            &ids,
+            Uuid::from(user.id),
+            Uuid::from(client.id),
+            &tokens,
+            now,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "db.oauth2_consent.revoke",
+        skip_all,
+        fields(
+            db.query.text,
+            %consent.id,
+            user.id = %consent.user_id,
+            client.id = %consent.client_id,
+        ),
+        err,
+    )]
+    async fn revoke(&mut self, consent: Consent) -> Result<(), Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM oauth2_consents
+                WHERE oauth2_consent_id = $1
+            "#,
+            Uuid::from(consent.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "db.oauth2_consent.revoke_for_client",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            %client.id,
+        ),
+        err,
+    )]
+    async fn revoke_for_client(
+        &mut self,
+        client: &Client,
+        user: &User,
+    ) -> Result<usize, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM oauth2_consents
+                WHERE user_id = $1 AND oauth2_client_id = $2
+            "#,
+            Uuid::from(user.id),
+            Uuid::from(client.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(res.rows_affected().try_into().unwrap_or(usize::MAX))
+    }
+}
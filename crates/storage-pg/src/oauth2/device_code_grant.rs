@@ -7,7 +7,7 @@
 use std::net::IpAddr;
 
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use mas_data_model::{BrowserSession, DeviceCodeGrant, DeviceCodeGrantState, Session};
 use mas_storage::{
     Clock,
@@ -50,6 +50,9 @@ struct OAuth2DeviceGrantLookup {
     oauth2_session_id: Option<Uuid>,
     ip_address: Option<IpAddr>,
     user_agent: Option<String>,
+    device_fingerprint: Option<String>,
+    last_polled_at: Option<DateTime<Utc>>,
+    polling_interval: i32,
 }
 
 impl TryFrom<OAuth2DeviceGrantLookup> for DeviceCodeGrant {
@@ -71,6 +74,9 @@ impl TryFrom<OAuth2DeviceGrantLookup> for DeviceCodeGrant {
             oauth2_session_id,
             ip_address,
             user_agent,
+            device_fingerprint,
+            last_polled_at,
+            polling_interval,
         }: OAuth2DeviceGrantLookup,
     ) -> Result<Self, Self::Error> {
         let id = Ulid::from(oauth2_device_code_grant_id);
@@ -133,6 +139,9 @@ impl TryFrom<OAuth2DeviceGrantLookup> for DeviceCodeGrant {
             expires_at,
             ip_address,
             user_agent,
+            device_fingerprint,
+            interval: Duration::seconds(i64::from(polling_interval)),
+            last_polled_at,
         })
     }
 }
@@ -178,9 +187,11 @@ impl OAuth2DeviceCodeGrantRepository for PgOAuth2DeviceCodeGrantRepository<'_> {
                     , expires_at
                     , ip_address
                     , user_agent
+                    , device_fingerprint
+                    , polling_interval
                     )
                 VALUES
-                    ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             "#,
             Uuid::from(id),
             Uuid::from(client_id),
@@ -191,6 +202,8 @@ impl OAuth2DeviceCodeGrantRepository for PgOAuth2DeviceCodeGrantRepository<'_> {
             expires_at,
             params.ip_address as Option<IpAddr>,
             params.user_agent.as_deref(),
+            params.device_fingerprint.as_deref(),
+            i32::try_from(params.interval.num_seconds()).unwrap_or(i32::MAX),
         )
         .traced()
         .execute(&mut *self.conn)
@@ -207,6 +220,9 @@ impl OAuth2DeviceCodeGrantRepository for PgOAuth2DeviceCodeGrantRepository<'_> {
             expires_at,
             ip_address: params.ip_address,
             user_agent: params.user_agent,
+            device_fingerprint: params.device_fingerprint,
+            interval: params.interval,
+            last_polled_at: None,
         })
     }
 
@@ -237,6 +253,9 @@ impl OAuth2DeviceCodeGrantRepository for PgOAuth2DeviceCodeGrantRepository<'_> {
                      , oauth2_session_id
                      , ip_address as "ip_address: IpAddr"
                      , user_agent
+                     , device_fingerprint
+                     , last_polled_at
+                     , polling_interval
                 FROM
                     oauth2_device_code_grant
 
@@ -283,6 +302,9 @@ impl OAuth2DeviceCodeGrantRepository for PgOAuth2DeviceCodeGrantRepository<'_> {
                      , oauth2_session_id
                      , ip_address as "ip_address: IpAddr"
                      , user_agent
+                     , device_fingerprint
+                     , last_polled_at
+                     , polling_interval
                 FROM
                     oauth2_device_code_grant
 
@@ -329,6 +351,9 @@ impl OAuth2DeviceCodeGrantRepository for PgOAuth2DeviceCodeGrantRepository<'_> {
                      , oauth2_session_id
                      , ip_address as "ip_address: IpAddr"
                      , user_agent
+                     , device_fingerprint
+                     , last_polled_at
+                     , polling_interval
                 FROM
                     oauth2_device_code_grant
 
@@ -472,4 +497,47 @@ impl OAuth2DeviceCodeGrantRepository for PgOAuth2DeviceCodeGrantRepository<'_> {
 
         Ok(device_code_grant)
     }
+
+    #[tracing::instrument(
+        name = "db.oauth2_device_code_grant.record_poll",
+        skip_all,
+        fields(
+            db.query.text,
+            oauth2_device_code.id = %device_code_grant.id,
+            oauth2_client.id = %device_code_grant.client_id,
+            slow_down,
+        ),
+        err,
+    )]
+    async fn record_poll(
+        &mut self,
+        clock: &dyn Clock,
+        mut device_code_grant: DeviceCodeGrant,
+        slow_down: bool,
+    ) -> Result<DeviceCodeGrant, Self::Error> {
+        let polled_at = clock.now();
+        if slow_down {
+            device_code_grant.interval += DeviceCodeGrant::SLOW_DOWN_INCREMENT;
+        }
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE oauth2_device_code_grant
+                SET last_polled_at = $1
+                  , polling_interval = $2
+                WHERE oauth2_device_code_grant_id = $3
+            "#,
+            polled_at,
+            i32::try_from(device_code_grant.interval.num_seconds()).unwrap_or(i32::MAX),
+            Uuid::from(device_code_grant.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        device_code_grant.last_polled_at = Some(polled_at);
+        Ok(device_code_grant)
+    }
 }
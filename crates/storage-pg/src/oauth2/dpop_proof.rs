@@ -0,0 +1,108 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_storage::{Clock, oauth2::OAuth2DPoPProofRepository};
+use rand::RngCore;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, tracing::ExecuteExt};
+
+/// An implementation of [`OAuth2DPoPProofRepository`] for a PostgreSQL
+/// connection
+pub struct PgOAuth2DPoPProofRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgOAuth2DPoPProofRepository<'c> {
+    /// Create a new [`PgOAuth2DPoPProofRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait]
+impl OAuth2DPoPProofRepository for PgOAuth2DPoPProofRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.oauth2_dpop_proof.record",
+        skip_all,
+        fields(
+            db.query.text,
+            dpop_proof.jkt = jkt,
+            dpop_proof.jti = jti,
+        ),
+        err,
+    )]
+    async fn record(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        jkt: &str,
+        jti: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+
+        let res = sqlx::query!(
+            r#"
+                INSERT INTO oauth2_dpop_proofs
+                    (oauth2_dpop_proof_id, jkt, jti, created_at, expires_at)
+                VALUES
+                    ($1, $2, $3, $4, $5)
+                ON CONFLICT (jkt, jti) DO NOTHING
+            "#,
+            Uuid::from(id),
+            jkt,
+            jti,
+            created_at,
+            expires_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(res.rows_affected() == 1)
+    }
+
+    #[tracing::instrument(
+        name = "db.oauth2_dpop_proof.cleanup_expired",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn cleanup_expired(
+        &mut self,
+        expired_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM oauth2_dpop_proofs
+                WHERE oauth2_dpop_proof_id IN (
+                    SELECT oauth2_dpop_proof_id
+                    FROM oauth2_dpop_proofs
+                    WHERE expires_at < $1
+                    LIMIT $2
+                )
+            "#,
+            expired_before,
+            i64::try_from(limit).unwrap_or(i64::MAX),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(res.rows_affected().try_into().unwrap_or(usize::MAX))
+    }
+}
@@ -9,15 +9,24 @@
 
 mod access_token;
 mod authorization_grant;
+mod backchannel_authentication_grant;
 mod client;
+mod consent;
 mod device_code_grant;
+mod dpop_proof;
+mod pairwise_subject;
+mod pushed_authorization_request;
 mod refresh_token;
 mod session;
 
 pub use self::{
     access_token::PgOAuth2AccessTokenRepository,
-    authorization_grant::PgOAuth2AuthorizationGrantRepository, client::PgOAuth2ClientRepository,
-    device_code_grant::PgOAuth2DeviceCodeGrantRepository,
+    authorization_grant::PgOAuth2AuthorizationGrantRepository,
+    backchannel_authentication_grant::PgOAuth2BackchannelAuthenticationGrantRepository,
+    client::PgOAuth2ClientRepository, consent::PgOAuth2ConsentRepository,
+    device_code_grant::PgOAuth2DeviceCodeGrantRepository, dpop_proof::PgOAuth2DPoPProofRepository,
+    pairwise_subject::PgOAuth2PairwiseSubjectRepository,
+    pushed_authorization_request::PgOAuth2PushedAuthorizationRequestRepository,
     refresh_token::PgOAuth2RefreshTokenRepository, session::PgOAuth2SessionRepository,
 };
 
@@ -27,10 +36,16 @@ mod tests {
     use mas_data_model::AuthorizationCode;
     use mas_storage::{
         Clock, Pagination,
+        batch::delete_in_batches,
         clock::MockClock,
-        oauth2::{OAuth2DeviceCodeGrantParams, OAuth2SessionFilter, OAuth2SessionRepository},
+        oauth2::{
+            OAuth2AuthorizationGrantParams, OAuth2BackchannelAuthenticationGrantParams,
+            OAuth2DeviceCodeGrantParams, OAuth2SessionFilter, OAuth2SessionRepository,
+            OAuth2StaticClientParams, RevokedAccessTokens,
+        },
     };
     use oauth2_types::{
+        authorization_details::AuthorizationDetail,
         requests::{GrantType, ResponseMode},
         scope::{EMAIL, OPENID, PROFILE, Scope},
     };
@@ -38,8 +53,9 @@ mod tests {
     use rand_chacha::ChaChaRng;
     use sqlx::PgPool;
     use ulid::Ulid;
+    use url::Url;
 
-    use crate::PgRepository;
+    use crate::{PgRepository, PgRepositoryFactory};
 
     #[sqlx::test(migrator = "crate::MIGRATOR")]
     async fn test_repositories(pool: PgPool) {
@@ -82,6 +98,19 @@ mod tests {
                 None,
                 None,
                 Some("https://example.com/login".parse().unwrap()),
+                None,
+                None,
+                false,
+                Vec::new(),
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                Vec::new(),
+                None,
             )
             .await
             .unwrap();
@@ -129,16 +158,17 @@ mod tests {
                 &client,
                 "https://example.com/redirect".parse().unwrap(),
                 Scope::from_iter([OPENID]),
-                Some(AuthorizationCode {
-                    code: "code".to_owned(),
-                    pkce: None,
-                }),
-                Some("state".to_owned()),
-                Some("nonce".to_owned()),
                 ResponseMode::Query,
-                true,
-                None,
-                None,
+                OAuth2AuthorizationGrantParams {
+                    code: Some(AuthorizationCode {
+                        code: "code".to_owned(),
+                        pkce: None,
+                    }),
+                    state: Some("state".to_owned()),
+                    nonce: Some("nonce".to_owned()),
+                    response_type_id_token: true,
+                    ..Default::default()
+                },
             )
             .await
             .unwrap();
@@ -348,6 +378,27 @@ mod tests {
             .unwrap();
         assert!(!refresh_token.is_valid());
 
+        // Revoking the tokens of the session only touches the ones still valid
+        let revoked = repo
+            .oauth2_access_token()
+            .revoke_for_session(&clock, &session)
+            .await
+            .unwrap();
+        assert_eq!(revoked, 0);
+        let revoked = repo
+            .oauth2_refresh_token()
+            .revoke_for_session(&clock, &session)
+            .await
+            .unwrap();
+        assert_eq!(revoked, 1);
+        let new_refresh_token = repo
+            .oauth2_refresh_token()
+            .lookup(new_refresh_token.id)
+            .await
+            .unwrap()
+            .expect("refresh token not found");
+        assert!(!new_refresh_token.is_valid());
+
         // Record the user-agent on the session
         assert!(session.user_agent.is_none());
         let session = repo
@@ -366,6 +417,92 @@ mod tests {
             .expect("session not found");
         assert_eq!(session.user_agent.as_deref(), Some("Mozilla/5.0"));
 
+        // Record a device fingerprint on the session
+        assert!(session.device_fingerprint.is_none());
+        let fingerprint = "c2VjcmV0LWluc3RhbGwtaWQ";
+        let session = repo
+            .oauth2_session()
+            .record_device_fingerprint(session, fingerprint.to_owned())
+            .await
+            .unwrap();
+        assert_eq!(session.device_fingerprint.as_deref(), Some(fingerprint));
+
+        // It should be possible to find the session by its fingerprint
+        let filter = OAuth2SessionFilter::new().for_device_fingerprint(fingerprint);
+        let list = repo
+            .oauth2_session()
+            .list(filter, Pagination::first(10))
+            .await
+            .unwrap();
+        assert_eq!(list.edges.len(), 1);
+        assert_eq!(list.edges[0], session);
+
+        let filter = OAuth2SessionFilter::new().for_device_fingerprint("c29tZS1vdGhlci1kZXZpY2U");
+        assert_eq!(repo.oauth2_session().count(filter).await.unwrap(), 0);
+
+        // Record the authorization details granted to the session
+        assert!(session.authorization_details.is_empty());
+        let authorization_details: Vec<AuthorizationDetail> = serde_json::from_value(
+            serde_json::json!([{"type": "account_information", "actions": ["read"]}]),
+        )
+        .unwrap();
+        let session = repo
+            .oauth2_session()
+            .record_authorization_details(session, authorization_details.clone())
+            .await
+            .unwrap();
+        assert_eq!(session.authorization_details, authorization_details);
+
+        let session = repo
+            .oauth2_session()
+            .lookup(session.id)
+            .await
+            .unwrap()
+            .expect("session not found");
+        assert_eq!(session.authorization_details, authorization_details);
+
+        // Restrict the session to a resource
+        assert!(session.resource.is_none());
+        let resource: Url = "https://api.example.com/".parse().unwrap();
+        let session = repo
+            .oauth2_session()
+            .record_resource(session, resource.clone())
+            .await
+            .unwrap();
+        assert_eq!(session.resource.as_ref(), Some(&resource));
+
+        let session = repo
+            .oauth2_session()
+            .lookup(session.id)
+            .await
+            .unwrap()
+            .expect("session not found");
+        assert_eq!(session.resource, Some(resource));
+
+        // Record that the user granted offline access
+        assert!(!session.has_offline_access());
+        let filter = OAuth2SessionFilter::new().with_offline_access();
+        assert_eq!(repo.oauth2_session().count(filter).await.unwrap(), 0);
+
+        let session = repo
+            .oauth2_session()
+            .record_offline_access(&clock, session)
+            .await
+            .unwrap();
+        assert_eq!(session.offline_access_granted_at, Some(clock.now()));
+
+        let session = repo
+            .oauth2_session()
+            .lookup(session.id)
+            .await
+            .unwrap()
+            .expect("session not found");
+        assert!(session.has_offline_access());
+        assert_eq!(repo.oauth2_session().count(filter).await.unwrap(), 1);
+
+        let filter = OAuth2SessionFilter::new().without_offline_access();
+        assert_eq!(repo.oauth2_session().count(filter).await.unwrap(), 0);
+
         // Mark the session as finished
         assert!(session.is_valid());
         let session = repo.oauth2_session().finish(&clock, session).await.unwrap();
@@ -426,6 +563,19 @@ mod tests {
                 None,
                 None,
                 Some("https://first.example.com/login".parse().unwrap()),
+                None,
+                None,
+                false,
+                Vec::new(),
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                Vec::new(),
+                None,
             )
             .await
             .unwrap();
@@ -451,6 +601,19 @@ mod tests {
                 None,
                 None,
                 Some("https://second.example.com/login".parse().unwrap()),
+                None,
+                None,
+                false,
+                Vec::new(),
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                Vec::new(),
+                None,
             )
             .await
             .unwrap();
@@ -751,6 +914,19 @@ mod tests {
                 None,
                 None,
                 Some("https://example.com/login".parse().unwrap()),
+                None,
+                None,
+                false,
+                Vec::new(),
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                Vec::new(),
+                None,
             )
             .await
             .unwrap();
@@ -785,8 +961,10 @@ mod tests {
                     device_code: device_code.to_owned(),
                     user_code: user_code.to_owned(),
                     expires_in: Duration::try_minutes(5).unwrap(),
+                    interval: Duration::try_seconds(5).unwrap(),
                     ip_address: None,
                     user_agent: None,
+                    device_fingerprint: None,
                 },
             )
             .await
@@ -799,6 +977,30 @@ mod tests {
         let lookup = repo.oauth2_device_code_grant().lookup(id).await.unwrap();
         assert_eq!(lookup.as_ref(), Some(&grant));
 
+        // Record a few polls
+        assert!(grant.last_polled_at.is_none());
+        assert!(!grant.is_polling_too_fast(clock.now()));
+        let grant = repo
+            .oauth2_device_code_grant()
+            .record_poll(&clock, grant, false)
+            .await
+            .unwrap();
+        assert_eq!(grant.last_polled_at, Some(clock.now()));
+        assert!(grant.is_polling_too_fast(clock.now()));
+
+        let grant = repo
+            .oauth2_device_code_grant()
+            .record_poll(&clock, grant, true)
+            .await
+            .unwrap();
+        assert_eq!(grant.interval, Duration::try_seconds(10).unwrap());
+
+        clock.advance(Duration::try_seconds(10).unwrap());
+        assert!(!grant.is_polling_too_fast(clock.now()));
+
+        let lookup = repo.oauth2_device_code_grant().lookup(id).await.unwrap();
+        assert_eq!(lookup.as_ref(), Some(&grant));
+
         // Check that we can find the grant by device code
         let lookup = repo
             .oauth2_device_code_grant()
@@ -890,8 +1092,10 @@ mod tests {
                     device_code: "second_devicecode".to_owned(),
                     user_code: "second_usercode".to_owned(),
                     expires_in: Duration::try_minutes(5).unwrap(),
+                    interval: Duration::try_seconds(5).unwrap(),
                     ip_address: None,
                     user_agent: None,
+                    device_fingerprint: None,
                 },
             )
             .await
@@ -945,4 +1149,787 @@ mod tests {
             .await;
         assert!(res.is_err());
     }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_cleanup_revoked_access_tokens_in_batches(pool: PgPool) {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+
+        let client = repo
+            .oauth2_client()
+            .add(
+                &mut rng,
+                &clock,
+                Vec::new(),
+                None,
+                None,
+                None,
+                vec![GrantType::ClientCredentials],
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                Vec::new(),
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                Vec::new(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let session = repo
+            .oauth2_session()
+            .add_from_client_credentials(&mut rng, &clock, &client, Scope::from_iter([OPENID]))
+            .await
+            .unwrap();
+
+        // Create 5 revoked access tokens, and one which is still valid
+        for i in 0..6 {
+            let access_token = repo
+                .oauth2_access_token()
+                .add(&mut rng, &clock, &session, format!("token{i}"), None)
+                .await
+                .unwrap();
+
+            if i < 5 {
+                repo.oauth2_access_token()
+                    .revoke(&clock, access_token)
+                    .await
+                    .unwrap();
+            }
+        }
+        repo.save().await.unwrap();
+
+        // Nothing was revoked before now
+        let factory = PgRepositoryFactory::new(pool.clone());
+        let filter = RevokedAccessTokens {
+            revoked_before: clock.now(),
+        };
+        let progress = delete_in_batches(&factory, &filter, 2, |_| {})
+            .await
+            .unwrap();
+        assert_eq!(progress.deleted, 0);
+        assert_eq!(progress.batches, 1);
+
+        clock.advance(Duration::try_minutes(1).unwrap());
+        let filter = RevokedAccessTokens {
+            revoked_before: clock.now(),
+        };
+
+        let mut reported = Vec::new();
+        let progress = delete_in_batches(&factory, &filter, 2, |p| reported.push(p.deleted))
+            .await
+            .unwrap();
+        assert_eq!(progress.deleted, 5);
+        assert_eq!(progress.batches, 3);
+        assert_eq!(reported, vec![2, 4, 5]);
+
+        // The valid access token is still there
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM oauth2_access_tokens")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_dpop(pool: PgPool) {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+
+        let client = repo
+            .oauth2_client()
+            .add(
+                &mut rng,
+                &clock,
+                Vec::new(),
+                None,
+                None,
+                None,
+                vec![GrantType::ClientCredentials],
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                Vec::new(),
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                Vec::new(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let session = repo
+            .oauth2_session()
+            .add_from_client_credentials(&mut rng, &clock, &client, Scope::from_iter([OPENID]))
+            .await
+            .unwrap();
+
+        let access_token = repo
+            .oauth2_access_token()
+            .add(&mut rng, &clock, &session, "token".to_owned(), None)
+            .await
+            .unwrap();
+        assert_eq!(access_token.dpop_jkt, None);
+
+        let access_token = repo
+            .oauth2_access_token()
+            .bind_dpop_key(access_token, "thumbprint".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(access_token.dpop_jkt.as_deref(), Some("thumbprint"));
+
+        let access_token = repo
+            .oauth2_access_token()
+            .find_by_token("token")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(access_token.dpop_jkt.as_deref(), Some("thumbprint"));
+
+        // A proof can only be recorded once per key
+        let expires_at = clock.now() + Duration::try_minutes(5).unwrap();
+        assert!(
+            repo.oauth2_dpop_proof()
+                .record(&mut rng, &clock, "thumbprint", "jti", expires_at)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !repo
+                .oauth2_dpop_proof()
+                .record(&mut rng, &clock, "thumbprint", "jti", expires_at)
+                .await
+                .unwrap()
+        );
+        assert!(
+            repo.oauth2_dpop_proof()
+                .record(&mut rng, &clock, "other", "jti", expires_at)
+                .await
+                .unwrap()
+        );
+
+        // Proofs are only cleaned up once they expired
+        assert_eq!(
+            repo.oauth2_dpop_proof()
+                .cleanup_expired(clock.now(), 10)
+                .await
+                .unwrap(),
+            0
+        );
+        clock.advance(Duration::try_minutes(10).unwrap());
+        assert_eq!(
+            repo.oauth2_dpop_proof()
+                .cleanup_expired(clock.now(), 10)
+                .await
+                .unwrap(),
+            2
+        );
+
+        repo.save().await.unwrap();
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_pairwise_subjects(pool: PgPool) {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+
+        let alice = repo
+            .user()
+            .add(&mut rng, &clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let bob = repo
+            .user()
+            .add(&mut rng, &clock, "bob".to_owned())
+            .await
+            .unwrap();
+
+        let alice_sub = repo
+            .oauth2_pairwise_subject()
+            .find_or_add(&mut rng, &clock, &alice, "client.example.com")
+            .await
+            .unwrap();
+        assert_ne!(alice_sub, alice.sub);
+
+        // The same subject is returned for the same sector
+        let sub = repo
+            .oauth2_pairwise_subject()
+            .find_or_add(&mut rng, &clock, &alice, "client.example.com")
+            .await
+            .unwrap();
+        assert_eq!(sub, alice_sub);
+
+        // Other sectors and other users get other subjects
+        let sub = repo
+            .oauth2_pairwise_subject()
+            .find_or_add(&mut rng, &clock, &alice, "other.example.com")
+            .await
+            .unwrap();
+        assert_ne!(sub, alice_sub);
+
+        let bob_sub = repo
+            .oauth2_pairwise_subject()
+            .find_or_add(&mut rng, &clock, &bob, "client.example.com")
+            .await
+            .unwrap();
+        assert_ne!(bob_sub, alice_sub);
+
+        // Subjects are mapped back to their user, only within their sector
+        assert_eq!(
+            repo.oauth2_pairwise_subject()
+                .find_user_id("client.example.com", &alice_sub)
+                .await
+                .unwrap(),
+            Some(alice.id)
+        );
+        assert_eq!(
+            repo.oauth2_pairwise_subject()
+                .find_user_id("client.example.com", &bob_sub)
+                .await
+                .unwrap(),
+            Some(bob.id)
+        );
+        assert_eq!(
+            repo.oauth2_pairwise_subject()
+                .find_user_id("other.example.com", &alice_sub)
+                .await
+                .unwrap(),
+            None
+        );
+
+        repo.save().await.unwrap();
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_pushed_authorization_request(pool: PgPool) {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+
+        let client = repo
+            .oauth2_client()
+            .add(
+                &mut rng,
+                &clock,
+                vec!["https://example.com/redirect".parse().unwrap()],
+                None,
+                None,
+                None,
+                vec![GrantType::AuthorizationCode],
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                Vec::new(),
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                Vec::new(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(!client.require_pushed_authorization_requests);
+
+        // Lookup a non-existing request
+        let request = repo
+            .oauth2_pushed_authorization_request()
+            .find_by_reference("reference")
+            .await
+            .unwrap();
+        assert_eq!(request, None);
+
+        let parameters = [
+            ("response_type".to_owned(), "code".to_owned()),
+            ("scope".to_owned(), "openid".to_owned()),
+        ]
+        .into_iter()
+        .collect();
+        let request = repo
+            .oauth2_pushed_authorization_request()
+            .add(
+                &mut rng,
+                &clock,
+                &client,
+                "reference".to_owned(),
+                parameters,
+                Duration::try_minutes(1).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert!(request.is_valid(clock.now()));
+        assert_eq!(
+            request.request_uri(),
+            "urn:ietf:params:oauth:request_uri:reference"
+        );
+
+        let lookup = repo
+            .oauth2_pushed_authorization_request()
+            .find_by_reference("reference")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(lookup, request);
+        assert_eq!(lookup.parameters.get("scope").unwrap(), "openid");
+
+        // Requests can only be consumed once
+        let request = repo
+            .oauth2_pushed_authorization_request()
+            .consume(&clock, request)
+            .await
+            .unwrap();
+        assert!(!request.is_valid(clock.now()));
+        assert!(
+            repo.oauth2_pushed_authorization_request()
+                .consume(&clock, lookup)
+                .await
+                .is_err()
+        );
+
+        // Requests are only cleaned up once they expired
+        assert_eq!(
+            repo.oauth2_pushed_authorization_request()
+                .cleanup_expired(clock.now(), 10)
+                .await
+                .unwrap(),
+            0
+        );
+        clock.advance(Duration::try_minutes(2).unwrap());
+        assert_eq!(
+            repo.oauth2_pushed_authorization_request()
+                .cleanup_expired(clock.now(), 10)
+                .await
+                .unwrap(),
+            1
+        );
+
+        repo.save().await.unwrap();
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_service_account_clients(pool: PgPool) {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+
+        // A regular static client
+        let static_client = repo
+            .oauth2_client()
+            .upsert_static(
+                Ulid::from_datetime_with_source(clock.now().into(), &mut rng),
+                mas_iana::oauth::OAuthClientAuthenticationMethod::ClientSecretBasic,
+                OAuth2StaticClientParams {
+                    encrypted_client_secret: Some("secret".to_owned()),
+                    backchannel_logout_include_sub: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(!static_client.is_service_account());
+        assert!(static_client.service_account_subject().is_none());
+
+        // A static client acting as a service account
+        let service_account = repo
+            .oauth2_client()
+            .upsert_static(
+                Ulid::from_datetime_with_source(clock.now().into(), &mut rng),
+                mas_iana::oauth::OAuthClientAuthenticationMethod::ClientSecretBasic,
+                OAuth2StaticClientParams {
+                    encrypted_client_secret: Some("secret".to_owned()),
+                    service_account_scope: Some(Scope::from_iter([OPENID])),
+                    backchannel_logout_include_sub: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let service_account = repo
+            .oauth2_client()
+            .lookup(service_account.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            service_account.service_account_scope,
+            Some(Scope::from_iter([OPENID]))
+        );
+        assert_eq!(
+            service_account.service_account_subject(),
+            Some(format!("service-account:{}", service_account.client_id))
+        );
+
+        for client in [&static_client, &service_account] {
+            repo.oauth2_session()
+                .add_from_client_credentials(&mut rng, &clock, client, Scope::from_iter([OPENID]))
+                .await
+                .unwrap();
+        }
+
+        let filter = OAuth2SessionFilter::new().only_static_clients();
+        assert_eq!(repo.oauth2_session().count(filter).await.unwrap(), 2);
+
+        let filter = OAuth2SessionFilter::new().only_service_account_clients();
+        assert_eq!(repo.oauth2_session().count(filter).await.unwrap(), 1);
+        let list = repo
+            .oauth2_session()
+            .list(filter, Pagination::first(10))
+            .await
+            .unwrap();
+        assert_eq!(list.edges.len(), 1);
+        assert_eq!(list.edges[0].client_id, service_account.id);
+
+        repo.save().await.unwrap();
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_backchannel_authentication_grant_repository(pool: PgPool) {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+
+        // Provision a client
+        let client = repo
+            .oauth2_client()
+            .add(
+                &mut rng,
+                &clock,
+                vec![],
+                None,
+                None,
+                None,
+                vec![GrantType::ClientInitiatedBackchannelAuthentication],
+                Some("Example".to_owned()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                Vec::new(),
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                Vec::new(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(
+            client
+                .grant_types
+                .contains(&GrantType::ClientInitiatedBackchannelAuthentication)
+        );
+
+        // Provision a user and a browser session
+        let user = repo
+            .user()
+            .add(&mut rng, &clock, "john".to_owned())
+            .await
+            .unwrap();
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &clock, &user, None)
+            .await
+            .unwrap();
+
+        let auth_req_id = "authreqid";
+        let scope = Scope::from_iter([OPENID]);
+
+        let grant = repo
+            .oauth2_backchannel_authentication_grant()
+            .add(
+                &mut rng,
+                &clock,
+                OAuth2BackchannelAuthenticationGrantParams {
+                    client: &client,
+                    user: &user,
+                    scope: scope.clone(),
+                    auth_req_id: auth_req_id.to_owned(),
+                    binding_message: Some("Hello".to_owned()),
+                    client_notification_token: None,
+                    expires_in: Duration::try_minutes(5).unwrap(),
+                },
+            )
+            .await
+            .unwrap();
+        assert!(grant.is_pending());
+        assert!(!grant.is_expired(clock.now()));
+
+        // Check that we can find the grant by ID and by auth_req_id
+        let id = grant.id;
+        let lookup = repo
+            .oauth2_backchannel_authentication_grant()
+            .lookup(id)
+            .await
+            .unwrap();
+        assert_eq!(lookup.as_ref(), Some(&grant));
+
+        let lookup = repo
+            .oauth2_backchannel_authentication_grant()
+            .find_by_auth_req_id(auth_req_id)
+            .await
+            .unwrap();
+        assert_eq!(lookup.as_ref(), Some(&grant));
+
+        // It can't be exchanged before being fulfilled
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(&mut rng, &clock, &client, &browser_session, scope)
+            .await
+            .unwrap();
+        let res = repo
+            .oauth2_backchannel_authentication_grant()
+            .exchange(&clock, grant.clone(), &session)
+            .await;
+        assert!(res.is_err());
+
+        let grant = repo
+            .oauth2_backchannel_authentication_grant()
+            .fulfill(&clock, grant, &browser_session)
+            .await
+            .unwrap();
+        assert!(grant.is_fulfilled());
+
+        // It can't be rejected once fulfilled
+        let res = repo
+            .oauth2_backchannel_authentication_grant()
+            .reject(&clock, grant.clone(), &browser_session)
+            .await;
+        assert!(res.is_err());
+
+        let grant = repo
+            .oauth2_backchannel_authentication_grant()
+            .exchange(&clock, grant, &session)
+            .await
+            .unwrap();
+        assert!(grant.is_exchanged());
+
+        let lookup = repo
+            .oauth2_backchannel_authentication_grant()
+            .lookup(id)
+            .await
+            .unwrap();
+        assert_eq!(lookup.as_ref(), Some(&grant));
+
+        // The grant gets cleaned up once it expired
+        clock.advance(Duration::try_minutes(6).unwrap());
+        assert!(grant.is_expired(clock.now()));
+        let count = repo
+            .oauth2_backchannel_authentication_grant()
+            .cleanup_expired(clock.now(), 100)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let lookup = repo
+            .oauth2_backchannel_authentication_grant()
+            .lookup(id)
+            .await
+            .unwrap();
+        assert!(lookup.is_none());
+
+        repo.save().await.unwrap();
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_consent_repository(pool: PgPool) {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+
+        // Provision a client
+        let client = repo
+            .oauth2_client()
+            .add(
+                &mut rng,
+                &clock,
+                vec!["https://example.com/redirect".parse().unwrap()],
+                None,
+                None,
+                None,
+                vec![GrantType::AuthorizationCode],
+                Some("Example".to_owned()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                Vec::new(),
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                Vec::new(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        // Provision a user
+        let user = repo
+            .user()
+            .add(&mut rng, &clock, "john".to_owned())
+            .await
+            .unwrap();
+
+        // Nothing was granted yet
+        let scope = repo
+            .oauth2_consent()
+            .get_for_client(&client, &user)
+            .await
+            .unwrap();
+        assert!(scope.is_empty());
+
+        // Remember a first consent
+        repo.oauth2_consent()
+            .remember(
+                &mut rng,
+                &clock,
+                &client,
+                &user,
+                &Scope::from_iter([OPENID, EMAIL]),
+            )
+            .await
+            .unwrap();
+
+        let scope = repo
+            .oauth2_consent()
+            .get_for_client(&client, &user)
+            .await
+            .unwrap();
+        assert_eq!(scope, Scope::from_iter([OPENID, EMAIL]));
+
+        // Granting an overlapping scope only refreshes the existing tokens
+        clock.advance(Duration::try_minutes(1).unwrap());
+        repo.oauth2_consent()
+            .remember(
+                &mut rng,
+                &clock,
+                &client,
+                &user,
+                &Scope::from_iter([OPENID, PROFILE]),
+            )
+            .await
+            .unwrap();
+
+        let consents = repo.oauth2_consent().all_for_user(&user).await.unwrap();
+        assert_eq!(consents.len(), 3);
+        let openid = consents
+            .iter()
+            .find(|c| c.scope_token == OPENID)
+            .unwrap()
+            .clone();
+        let email = consents.iter().find(|c| c.scope_token == EMAIL).unwrap();
+        assert!(openid.refreshed_at.is_some());
+        assert_eq!(openid.last_granted_at(), clock.now());
+        assert!(email.refreshed_at.is_none());
+
+        // Lookup a single consent
+        let lookup = repo.oauth2_consent().lookup(openid.id).await.unwrap();
+        assert_eq!(lookup.as_ref(), Some(&openid));
+
+        // Revoke a single scope token
+        repo.oauth2_consent().revoke(openid.clone()).await.unwrap();
+        let lookup = repo.oauth2_consent().lookup(openid.id).await.unwrap();
+        assert!(lookup.is_none());
+
+        let scope = repo
+            .oauth2_consent()
+            .get_for_client(&client, &user)
+            .await
+            .unwrap();
+        assert_eq!(scope, Scope::from_iter([EMAIL, PROFILE]));
+
+        // Revoke everything for the client
+        let count = repo
+            .oauth2_consent()
+            .revoke_for_client(&client, &user)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let consents = repo.oauth2_consent().all_for_user(&user).await.unwrap();
+        assert!(consents.is_empty());
+
+        repo.save().await.unwrap();
+    }
 }
@@ -0,0 +1,113 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use mas_data_model::User;
+use mas_storage::{Clock, oauth2::OAuth2PairwiseSubjectRepository};
+use rand::{
+    RngCore,
+    distributions::{Alphanumeric, DistString},
+};
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, tracing::ExecuteExt};
+
+/// An implementation of [`OAuth2PairwiseSubjectRepository`] for a PostgreSQL
+/// connection
+pub struct PgOAuth2PairwiseSubjectRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgOAuth2PairwiseSubjectRepository<'c> {
+    /// Create a new [`PgOAuth2PairwiseSubjectRepository`] from an active
+    /// PostgreSQL connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait]
+impl OAuth2PairwiseSubjectRepository for PgOAuth2PairwiseSubjectRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.oauth2_pairwise_subject.find_or_add",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            pairwise_subject.sector_identifier = sector_identifier,
+        ),
+        err,
+    )]
+    async fn find_or_add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        sector_identifier: &str,
+    ) -> Result<String, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        let subject = Alphanumeric.sample_string(rng, 32);
+
+        // The no-op update makes the existing row returned on conflict, so that
+        // concurrent calls agree on the subject
+        let subject = sqlx::query_scalar!(
+            r#"
+                INSERT INTO oauth2_pairwise_subjects
+                    (oauth2_pairwise_subject_id, user_id, sector_identifier, subject, created_at)
+                VALUES
+                    ($1, $2, $3, $4, $5)
+                ON CONFLICT (user_id, sector_identifier)
+                DO UPDATE SET sector_identifier = EXCLUDED.sector_identifier
+                RETURNING subject
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            sector_identifier,
+            subject,
+            created_at,
+        )
+        .traced()
+        .fetch_one(&mut *self.conn)
+        .await?;
+
+        Ok(subject)
+    }
+
+    #[tracing::instrument(
+        name = "db.oauth2_pairwise_subject.find_user_id",
+        skip_all,
+        fields(
+            db.query.text,
+            pairwise_subject.sector_identifier = sector_identifier,
+        ),
+        err,
+    )]
+    async fn find_user_id(
+        &mut self,
+        sector_identifier: &str,
+        subject: &str,
+    ) -> Result<Option<Ulid>, Self::Error> {
+        let user_id = sqlx::query_scalar!(
+            r#"
+                SELECT user_id
+                FROM oauth2_pairwise_subjects
+                WHERE sector_identifier = $1
+                  AND subject = $2
+            "#,
+            sector_identifier,
+            subject,
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(user_id.map(Ulid::from))
+    }
+}
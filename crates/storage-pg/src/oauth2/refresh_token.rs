@@ -246,6 +246,41 @@ impl OAuth2RefreshTokenRepository for PgOAuth2RefreshTokenRepository<'_> {
             .map_err(DatabaseError::to_invalid_operation)
     }
 
+    #[tracing::instrument(
+        name = "db.oauth2_refresh_token.replace_access_token",
+        skip_all,
+        fields(
+            db.query.text,
+            %refresh_token.id,
+            session.id = %refresh_token.session_id,
+            %access_token.id,
+        ),
+        err,
+    )]
+    async fn replace_access_token(
+        &mut self,
+        mut refresh_token: RefreshToken,
+        access_token: &AccessToken,
+    ) -> Result<RefreshToken, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE oauth2_refresh_tokens
+                SET oauth2_access_token_id = $2
+                WHERE oauth2_refresh_token_id = $1
+            "#,
+            Uuid::from(refresh_token.id),
+            Uuid::from(access_token.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        refresh_token.access_token_id = Some(access_token.id);
+        Ok(refresh_token)
+    }
+
     #[tracing::instrument(
         name = "db.oauth2_refresh_token.revoke",
         skip_all,
@@ -281,4 +316,36 @@ impl OAuth2RefreshTokenRepository for PgOAuth2RefreshTokenRepository<'_> {
             .revoke(revoked_at)
             .map_err(DatabaseError::to_invalid_operation)
     }
+
+    #[tracing::instrument(
+        name = "db.oauth2_refresh_token.revoke_for_session",
+        skip_all,
+        fields(
+            db.query.text,
+            %session.id,
+        ),
+        err,
+    )]
+    async fn revoke_for_session(
+        &mut self,
+        clock: &dyn Clock,
+        session: &Session,
+    ) -> Result<usize, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE oauth2_refresh_tokens
+                SET revoked_at = $2
+                WHERE oauth2_session_id = $1
+                  AND revoked_at IS NULL
+                  AND consumed_at IS NULL
+            "#,
+            Uuid::from(session.id),
+            clock.now(),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(res.rows_affected().try_into().unwrap_or(usize::MAX))
+    }
 }
@@ -8,21 +8,26 @@ use std::net::IpAddr;
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use mas_data_model::{BrowserSession, Client, Session, SessionState, User};
+use mas_data_model::{BrowserSession, Client, IpLocation, Session, SessionState, User};
 use mas_storage::{
     Clock, Page, Pagination,
     oauth2::{OAuth2SessionFilter, OAuth2SessionRepository},
 };
-use oauth2_types::scope::{Scope, ScopeToken};
+use oauth2_types::{
+    authorization_details::AuthorizationDetail,
+    scope::{Scope, ScopeToken},
+};
 use rand::RngCore;
 use sea_query::{Expr, PgFunc, PostgresQueryBuilder, Query, enum_def, extension::postgres::PgExpr};
 use sea_query_binder::SqlxBinder;
-use sqlx::PgConnection;
+use sqlx::{PgConnection, types::Json};
 use ulid::Ulid;
+use url::Url;
 use uuid::Uuid;
 
 use crate::{
     DatabaseError, DatabaseInconsistencyError,
+    estimate::estimate_rows,
     filter::{Filter, StatementExt},
     iden::{OAuth2Clients, OAuth2Sessions},
     pagination::QueryBuilderExt,
@@ -55,7 +60,15 @@ struct OAuthSessionLookup {
     user_agent: Option<String>,
     last_active_at: Option<DateTime<Utc>>,
     last_active_ip: Option<IpAddr>,
+    last_active_country: Option<String>,
+    last_active_asn: Option<i64>,
+    last_active_as_organization: Option<String>,
     human_name: Option<String>,
+    device_fingerprint: Option<String>,
+    authorization_details: Option<Json<Vec<AuthorizationDetail>>>,
+    resource: Option<String>,
+    userinfo_claims: Vec<String>,
+    offline_access_granted_at: Option<DateTime<Utc>>,
 }
 
 impl TryFrom<OAuthSessionLookup> for Session {
@@ -80,6 +93,28 @@ impl TryFrom<OAuthSessionLookup> for Session {
             Some(finished_at) => SessionState::Finished { finished_at },
         };
 
+        let last_active_asn = value
+            .last_active_asn
+            .map(u32::try_from)
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_sessions")
+                    .column("last_active_asn")
+                    .row(id)
+                    .source(e)
+            })?;
+
+        let resource = value
+            .resource
+            .map(|resource| resource.parse())
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("oauth2_sessions")
+                    .column("resource")
+                    .row(id)
+                    .source(e)
+            })?;
+
         Ok(Session {
             id,
             state,
@@ -91,7 +126,20 @@ impl TryFrom<OAuthSessionLookup> for Session {
             user_agent: value.user_agent,
             last_active_at: value.last_active_at,
             last_active_ip: value.last_active_ip,
+            last_active_location: IpLocation {
+                country: value.last_active_country,
+                asn: last_active_asn,
+                as_organization: value.last_active_as_organization,
+            },
             human_name: value.human_name,
+            device_fingerprint: value.device_fingerprint,
+            authorization_details: value
+                .authorization_details
+                .map(|Json(x)| x)
+                .unwrap_or_default(),
+            resource,
+            userinfo_claims: value.userinfo_claims,
+            offline_access_granted_at: value.offline_access_granted_at,
         })
     }
 }
@@ -110,20 +158,26 @@ impl Filter for OAuth2SessionFilter<'_> {
                 // This builds either a:
                 // `WHERE oauth2_client_id = ANY(...)`
                 // or a `WHERE oauth2_client_id <> ALL(...)`
-                let static_clients = Query::select()
+                let condition = if client_kind.is_service_account() {
+                    Expr::col((OAuth2Clients::Table, OAuth2Clients::ServiceAccountScopeList))
+                        .is_not_null()
+                } else {
+                    Expr::col((OAuth2Clients::Table, OAuth2Clients::IsStatic)).into()
+                };
+                let clients = Query::select()
                     .expr(Expr::col((
                         OAuth2Clients::Table,
                         OAuth2Clients::OAuth2ClientId,
                     )))
-                    .and_where(Expr::col((OAuth2Clients::Table, OAuth2Clients::IsStatic)).into())
+                    .and_where(condition)
                     .from(OAuth2Clients::Table)
                     .take();
-                if client_kind.is_static() {
+                if client_kind.is_static() || client_kind.is_service_account() {
                     Expr::col((OAuth2Sessions::Table, OAuth2Sessions::OAuth2ClientId))
-                        .eq(Expr::any(static_clients))
+                        .eq(Expr::any(clients))
                 } else {
                     Expr::col((OAuth2Sessions::Table, OAuth2Sessions::OAuth2ClientId))
-                        .ne(Expr::all(static_clients))
+                        .ne(Expr::all(clients))
                 }
             }))
             .add_option(self.device().map(|device| {
@@ -159,6 +213,25 @@ impl Filter for OAuth2SessionFilter<'_> {
                     Expr::col((OAuth2Sessions::Table, OAuth2Sessions::UserId)).is_null()
                 }
             }))
+            .add_option(self.device_fingerprint().map(|device_fingerprint| {
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::DeviceFingerprint))
+                    .eq(device_fingerprint)
+            }))
+            .add_option(self.offline_access().map(|offline_access| {
+                if offline_access {
+                    Expr::col((
+                        OAuth2Sessions::Table,
+                        OAuth2Sessions::OfflineAccessGrantedAt,
+                    ))
+                    .is_not_null()
+                } else {
+                    Expr::col((
+                        OAuth2Sessions::Table,
+                        OAuth2Sessions::OfflineAccessGrantedAt,
+                    ))
+                    .is_null()
+                }
+            }))
             .add_option(self.last_active_after().map(|last_active_after| {
                 Expr::col((OAuth2Sessions::Table, OAuth2Sessions::LastActiveAt))
                     .gt(last_active_after)
@@ -197,7 +270,15 @@ impl OAuth2SessionRepository for PgOAuth2SessionRepository<'_> {
                      , user_agent
                      , last_active_at
                      , last_active_ip as "last_active_ip: IpAddr"
+                     , last_active_country
+                     , last_active_asn
+                     , last_active_as_organization
                      , human_name
+                     , device_fingerprint
+                     , authorization_details as "authorization_details: Json<Vec<AuthorizationDetail>>"
+                     , resource
+                     , userinfo_claims
+                     , offline_access_granted_at
                 FROM oauth2_sessions
 
                 WHERE oauth2_session_id = $1
@@ -273,7 +354,13 @@ impl OAuth2SessionRepository for PgOAuth2SessionRepository<'_> {
             user_agent: None,
             last_active_at: None,
             last_active_ip: None,
+            last_active_location: IpLocation::default(),
             human_name: None,
+            device_fingerprint: None,
+            authorization_details: Vec::new(),
+            resource: None,
+            userinfo_claims: Vec::new(),
+            offline_access_granted_at: None,
         })
     }
 
@@ -396,10 +483,48 @@ impl OAuth2SessionRepository for PgOAuth2SessionRepository<'_> {
                 Expr::col((OAuth2Sessions::Table, OAuth2Sessions::LastActiveIp)),
                 OAuthSessionLookupIden::LastActiveIp,
             )
+            .expr_as(
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::LastActiveCountry)),
+                OAuthSessionLookupIden::LastActiveCountry,
+            )
+            .expr_as(
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::LastActiveAsn)),
+                OAuthSessionLookupIden::LastActiveAsn,
+            )
+            .expr_as(
+                Expr::col((
+                    OAuth2Sessions::Table,
+                    OAuth2Sessions::LastActiveAsOrganization,
+                )),
+                OAuthSessionLookupIden::LastActiveAsOrganization,
+            )
             .expr_as(
                 Expr::col((OAuth2Sessions::Table, OAuth2Sessions::HumanName)),
                 OAuthSessionLookupIden::HumanName,
             )
+            .expr_as(
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::DeviceFingerprint)),
+                OAuthSessionLookupIden::DeviceFingerprint,
+            )
+            .expr_as(
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::AuthorizationDetails)),
+                OAuthSessionLookupIden::AuthorizationDetails,
+            )
+            .expr_as(
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::Resource)),
+                OAuthSessionLookupIden::Resource,
+            )
+            .expr_as(
+                Expr::col((OAuth2Sessions::Table, OAuth2Sessions::UserinfoClaims)),
+                OAuthSessionLookupIden::UserinfoClaims,
+            )
+            .expr_as(
+                Expr::col((
+                    OAuth2Sessions::Table,
+                    OAuth2Sessions::OfflineAccessGrantedAt,
+                )),
+                OAuthSessionLookupIden::OfflineAccessGrantedAt,
+            )
             .from(OAuth2Sessions::Table)
             .apply_filter(filter)
             .generate_pagination(
@@ -443,6 +568,30 @@ impl OAuth2SessionRepository for PgOAuth2SessionRepository<'_> {
             .map_err(DatabaseError::to_invalid_operation)
     }
 
+    #[tracing::instrument(
+        name = "db.oauth2_session.estimate_count",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn estimate_count(
+        &mut self,
+        filter: OAuth2SessionFilter<'_>,
+    ) -> Result<usize, Self::Error> {
+        let query = Query::select()
+            .expr(Expr::val(1))
+            .from(OAuth2Sessions::Table)
+            .apply_filter(filter)
+            .take();
+
+        match estimate_rows(&mut *self.conn, &query).await? {
+            Some(estimate) => Ok(estimate),
+            None => self.count(filter).await,
+        }
+    }
+
     #[tracing::instrument(
         name = "db.oauth2_session.record_batch_activity",
         skip_all,
@@ -453,19 +602,25 @@ impl OAuth2SessionRepository for PgOAuth2SessionRepository<'_> {
     )]
     async fn record_batch_activity(
         &mut self,
-        mut activities: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>)>,
+        mut activities: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>, IpLocation)>,
     ) -> Result<(), Self::Error> {
         // Sort the activity by ID, so that when batching the updates, Postgres
         // locks the rows in a stable order, preventing deadlocks
-        activities.sort_unstable();
+        activities.sort_unstable_by_key(|(id, ..)| *id);
         let mut ids = Vec::with_capacity(activities.len());
         let mut last_activities = Vec::with_capacity(activities.len());
         let mut ips = Vec::with_capacity(activities.len());
+        let mut countries = Vec::with_capacity(activities.len());
+        let mut asns = Vec::with_capacity(activities.len());
+        let mut as_organizations = Vec::with_capacity(activities.len());
 
-        for (id, last_activity, ip) in activities {
+        for (id, last_activity, ip, location) in activities {
             ids.push(Uuid::from(id));
             last_activities.push(last_activity);
             ips.push(ip);
+            countries.push(location.country);
+            asns.push(location.asn.map(i64::from));
+            as_organizations.push(location.as_organization);
         }
 
         let res = sqlx::query!(
@@ -473,16 +628,29 @@ impl OAuth2SessionRepository for PgOAuth2SessionRepository<'_> {
                 UPDATE oauth2_sessions
                 SET last_active_at = GREATEST(t.last_active_at, oauth2_sessions.last_active_at)
                   , last_active_ip = COALESCE(t.last_active_ip, oauth2_sessions.last_active_ip)
+                  -- The location only changes alongside the IP address
+                  , last_active_country = CASE WHEN t.last_active_ip IS NULL
+                        THEN oauth2_sessions.last_active_country
+                        ELSE t.last_active_country END
+                  , last_active_asn = CASE WHEN t.last_active_ip IS NULL
+                        THEN oauth2_sessions.last_active_asn
+                        ELSE t.last_active_asn END
+                  , last_active_as_organization = CASE WHEN t.last_active_ip IS NULL
+                        THEN oauth2_sessions.last_active_as_organization
+                        ELSE t.last_active_as_organization END
                 FROM (
                     SELECT *
-                    FROM UNNEST($1::uuid[], $2::timestamptz[], $3::inet[])
-                        AS t(oauth2_session_id, last_active_at, last_active_ip)
+                    FROM UNNEST($1::uuid[], $2::timestamptz[], $3::inet[], $4::text[], $5::int8[], $6::text[])
+                        AS t(oauth2_session_id, last_active_at, last_active_ip, last_active_country, last_active_asn, last_active_as_organization)
                 ) AS t
                 WHERE oauth2_sessions.oauth2_session_id = t.oauth2_session_id
             "#,
             &ids,
             &last_activities,
             &ips as &[Option<IpAddr>],
+            &countries as &[Option<String>],
+            &asns as &[Option<i64>],
+            &as_organizations as &[Option<String>],
         )
         .traced()
         .execute(&mut *self.conn)
@@ -530,6 +698,183 @@ impl OAuth2SessionRepository for PgOAuth2SessionRepository<'_> {
         Ok(session)
     }
 
+    #[tracing::instrument(
+        name = "db.oauth2_session.record_device_fingerprint",
+        skip_all,
+        fields(
+            db.query.text,
+            %session.id,
+            client.id = %session.client_id,
+        ),
+        err,
+    )]
+    async fn record_device_fingerprint(
+        &mut self,
+        mut session: Session,
+        device_fingerprint: String,
+    ) -> Result<Session, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE oauth2_sessions
+                SET device_fingerprint = $2
+                WHERE oauth2_session_id = $1
+            "#,
+            Uuid::from(session.id),
+            &*device_fingerprint,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        session.device_fingerprint = Some(device_fingerprint);
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        Ok(session)
+    }
+
+    #[tracing::instrument(
+        name = "db.oauth2_session.record_authorization_details",
+        skip_all,
+        fields(
+            db.query.text,
+            %session.id,
+            client.id = %session.client_id,
+        ),
+        err,
+    )]
+    async fn record_authorization_details(
+        &mut self,
+        mut session: Session,
+        authorization_details: Vec<AuthorizationDetail>,
+    ) -> Result<Session, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE oauth2_sessions
+                SET authorization_details = $2
+                WHERE oauth2_session_id = $1
+            "#,
+            Uuid::from(session.id),
+            Json(&authorization_details) as _,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        session.authorization_details = authorization_details;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        Ok(session)
+    }
+
+    #[tracing::instrument(
+        name = "db.oauth2_session.record_resource",
+        skip_all,
+        fields(
+            db.query.text,
+            %session.id,
+            client.id = %session.client_id,
+            session.resource = %resource,
+        ),
+        err,
+    )]
+    async fn record_resource(
+        &mut self,
+        mut session: Session,
+        resource: Url,
+    ) -> Result<Session, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE oauth2_sessions
+                SET resource = $2
+                WHERE oauth2_session_id = $1
+            "#,
+            Uuid::from(session.id),
+            resource.as_str(),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        session.resource = Some(resource);
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        Ok(session)
+    }
+
+    #[tracing::instrument(
+        name = "db.oauth2_session.record_userinfo_claims",
+        skip_all,
+        fields(
+            db.query.text,
+            %session.id,
+            client.id = %session.client_id,
+        ),
+        err,
+    )]
+    async fn record_userinfo_claims(
+        &mut self,
+        mut session: Session,
+        userinfo_claims: Vec<String>,
+    ) -> Result<Session, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE oauth2_sessions
+                SET userinfo_claims = $2
+                WHERE oauth2_session_id = $1
+            "#,
+            Uuid::from(session.id),
+            &userinfo_claims,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        session.userinfo_claims = userinfo_claims;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        Ok(session)
+    }
+
+    #[tracing::instrument(
+        name = "db.oauth2_session.record_offline_access",
+        skip_all,
+        fields(
+            db.query.text,
+            %session.id,
+            client.id = %session.client_id,
+        ),
+        err,
+    )]
+    async fn record_offline_access(
+        &mut self,
+        clock: &dyn Clock,
+        mut session: Session,
+    ) -> Result<Session, Self::Error> {
+        let granted_at = clock.now();
+        let res = sqlx::query!(
+            r#"
+                UPDATE oauth2_sessions
+                SET offline_access_granted_at = $2
+                WHERE oauth2_session_id = $1
+            "#,
+            Uuid::from(session.id),
+            granted_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        session.offline_access_granted_at = Some(granted_at);
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        Ok(session)
+    }
+
     #[tracing::instrument(
         name = "repository.oauth2_session.set_human_name",
         skip(self),
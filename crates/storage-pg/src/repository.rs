@@ -12,24 +12,34 @@ use mas_storage::{
     BoxRepository, BoxRepositoryFactory, MapErr, Repository, RepositoryAccess, RepositoryError,
     RepositoryFactory, RepositoryTransaction,
     app_session::AppSessionRepository,
+    audit_event::AuditEventRepository,
     compat::{
         CompatAccessTokenRepository, CompatRefreshTokenRepository, CompatSessionRepository,
         CompatSsoLoginRepository,
     },
+    feature_flag::FeatureFlagOverrideRepository,
+    login_stats::LoginStatsRepository,
     oauth2::{
-        OAuth2AccessTokenRepository, OAuth2AuthorizationGrantRepository, OAuth2ClientRepository,
-        OAuth2DeviceCodeGrantRepository, OAuth2RefreshTokenRepository, OAuth2SessionRepository,
+        OAuth2AccessTokenRepository, OAuth2AuthorizationGrantRepository,
+        OAuth2BackchannelAuthenticationGrantRepository, OAuth2ClientRepository,
+        OAuth2ConsentRepository, OAuth2DPoPProofRepository, OAuth2DeviceCodeGrantRepository,
+        OAuth2PairwiseSubjectRepository, OAuth2PushedAuthorizationRequestRepository,
+        OAuth2RefreshTokenRepository, OAuth2SessionRepository,
     },
     policy_data::PolicyDataRepository,
     queue::{QueueJobRepository, QueueScheduleRepository, QueueWorkerRepository},
+    scim::{ScimSyncRunRepository, ScimUserLinkRepository},
     upstream_oauth2::{
-        UpstreamOAuthLinkRepository, UpstreamOAuthProviderRepository,
-        UpstreamOAuthSessionRepository,
+        UpstreamOAuthCachedDocumentRepository, UpstreamOAuthLinkRepository,
+        UpstreamOAuthProviderRepository, UpstreamOAuthSessionRepository,
     },
     user::{
-        BrowserSessionRepository, UserEmailRepository, UserPasswordRepository,
+        BrowserSessionRepository, UserActionTokenRepository, UserClaimLinkRepository,
+        UserDeletionRepository, UserEmailRepository, UserMetadataRepository, UserPasskeyRepository,
+        UserPasswordRepository, UserPhoneRepository, UserRecoveryCodeRepository,
         UserRecoveryRepository, UserRegistrationRepository, UserRegistrationTokenRepository,
-        UserRepository, UserTermsRepository,
+        UserRepository, UserTermsAcceptanceRepository, UserTermsRepository, UserTotpRepository,
+        UserTrustedDeviceRepository,
     },
 };
 use sqlx::{PgConnection, PgPool, Postgres, Transaction};
@@ -38,13 +48,18 @@ use tracing::Instrument;
 use crate::{
     DatabaseError,
     app_session::PgAppSessionRepository,
+    audit_event::PgAuditEventRepository,
     compat::{
         PgCompatAccessTokenRepository, PgCompatRefreshTokenRepository, PgCompatSessionRepository,
         PgCompatSsoLoginRepository,
     },
+    feature_flag::PgFeatureFlagOverrideRepository,
+    login_stats::PgLoginStatsRepository,
     oauth2::{
         PgOAuth2AccessTokenRepository, PgOAuth2AuthorizationGrantRepository,
-        PgOAuth2ClientRepository, PgOAuth2DeviceCodeGrantRepository,
+        PgOAuth2BackchannelAuthenticationGrantRepository, PgOAuth2ClientRepository,
+        PgOAuth2ConsentRepository, PgOAuth2DPoPProofRepository, PgOAuth2DeviceCodeGrantRepository,
+        PgOAuth2PairwiseSubjectRepository, PgOAuth2PushedAuthorizationRequestRepository,
         PgOAuth2RefreshTokenRepository, PgOAuth2SessionRepository,
     },
     policy_data::PgPolicyDataRepository,
@@ -52,15 +67,19 @@ use crate::{
         job::PgQueueJobRepository, schedule::PgQueueScheduleRepository,
         worker::PgQueueWorkerRepository,
     },
+    scim::{PgScimSyncRunRepository, PgScimUserLinkRepository},
     telemetry::DB_CLIENT_CONNECTIONS_CREATE_TIME_HISTOGRAM,
     upstream_oauth2::{
-        PgUpstreamOAuthLinkRepository, PgUpstreamOAuthProviderRepository,
-        PgUpstreamOAuthSessionRepository,
+        PgUpstreamOAuthCachedDocumentRepository, PgUpstreamOAuthLinkRepository,
+        PgUpstreamOAuthProviderRepository, PgUpstreamOAuthSessionRepository,
     },
     user::{
-        PgBrowserSessionRepository, PgUserEmailRepository, PgUserPasswordRepository,
-        PgUserRecoveryRepository, PgUserRegistrationRepository, PgUserRegistrationTokenRepository,
-        PgUserRepository, PgUserTermsRepository,
+        PgBrowserSessionRepository, PgUserActionTokenRepository, PgUserClaimLinkRepository,
+        PgUserDeletionRepository, PgUserEmailRepository, PgUserMetadataRepository,
+        PgUserPasskeyRepository, PgUserPasswordRepository, PgUserPhoneRepository,
+        PgUserRecoveryCodeRepository, PgUserRecoveryRepository, PgUserRegistrationRepository,
+        PgUserRegistrationTokenRepository, PgUserRepository, PgUserTermsAcceptanceRepository,
+        PgUserTermsRepository, PgUserTotpRepository, PgUserTrustedDeviceRepository,
     },
 };
 
@@ -107,6 +126,23 @@ impl RepositoryFactory for PgRepositoryFactory {
 
         Ok(repo)
     }
+
+    async fn health_check(&self) -> Result<(), RepositoryError> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(RepositoryError::from_error)?;
+
+        sqlx::query("SELECT $1")
+            .bind(1_i64)
+            .execute(&mut *conn)
+            .instrument(tracing::info_span!("DB health"))
+            .await
+            .map_err(RepositoryError::from_error)?;
+
+        Ok(())
+    }
 }
 
 /// An implementation of the [`Repository`] trait backed by a PostgreSQL
@@ -220,6 +256,14 @@ where
         Box::new(PgUpstreamOAuthSessionRepository::new(self.conn.as_mut()))
     }
 
+    fn upstream_oauth_cached_document<'c>(
+        &'c mut self,
+    ) -> Box<dyn UpstreamOAuthCachedDocumentRepository<Error = Self::Error> + 'c> {
+        Box::new(PgUpstreamOAuthCachedDocumentRepository::new(
+            self.conn.as_mut(),
+        ))
+    }
+
     fn user<'c>(&'c mut self) -> Box<dyn UserRepository<Error = Self::Error> + 'c> {
         Box::new(PgUserRepository::new(self.conn.as_mut()))
     }
@@ -234,6 +278,10 @@ where
         Box::new(PgUserPasswordRepository::new(self.conn.as_mut()))
     }
 
+    fn user_passkey<'c>(&'c mut self) -> Box<dyn UserPasskeyRepository<Error = Self::Error> + 'c> {
+        Box::new(PgUserPasskeyRepository::new(self.conn.as_mut()))
+    }
+
     fn user_recovery<'c>(
         &'c mut self,
     ) -> Box<dyn UserRecoveryRepository<Error = Self::Error> + 'c> {
@@ -244,6 +292,38 @@ where
         Box::new(PgUserTermsRepository::new(self.conn.as_mut()))
     }
 
+    fn user_terms_acceptance<'c>(
+        &'c mut self,
+    ) -> Box<dyn UserTermsAcceptanceRepository<Error = Self::Error> + 'c> {
+        Box::new(PgUserTermsAcceptanceRepository::new(self.conn.as_mut()))
+    }
+
+    fn user_totp<'c>(&'c mut self) -> Box<dyn UserTotpRepository<Error = Self::Error> + 'c> {
+        Box::new(PgUserTotpRepository::new(self.conn.as_mut()))
+    }
+
+    fn user_phone<'c>(&'c mut self) -> Box<dyn UserPhoneRepository<Error = Self::Error> + 'c> {
+        Box::new(PgUserPhoneRepository::new(self.conn.as_mut()))
+    }
+
+    fn user_recovery_code<'c>(
+        &'c mut self,
+    ) -> Box<dyn UserRecoveryCodeRepository<Error = Self::Error> + 'c> {
+        Box::new(PgUserRecoveryCodeRepository::new(self.conn.as_mut()))
+    }
+
+    fn user_trusted_device<'c>(
+        &'c mut self,
+    ) -> Box<dyn UserTrustedDeviceRepository<Error = Self::Error> + 'c> {
+        Box::new(PgUserTrustedDeviceRepository::new(self.conn.as_mut()))
+    }
+
+    fn user_deletion<'c>(
+        &'c mut self,
+    ) -> Box<dyn UserDeletionRepository<Error = Self::Error> + 'c> {
+        Box::new(PgUserDeletionRepository::new(self.conn.as_mut()))
+    }
+
     fn user_registration<'c>(
         &'c mut self,
     ) -> Box<dyn UserRegistrationRepository<Error = Self::Error> + 'c> {
@@ -272,6 +352,12 @@ where
         Box::new(PgOAuth2ClientRepository::new(self.conn.as_mut()))
     }
 
+    fn oauth2_consent<'c>(
+        &'c mut self,
+    ) -> Box<dyn OAuth2ConsentRepository<Error = Self::Error> + 'c> {
+        Box::new(PgOAuth2ConsentRepository::new(self.conn.as_mut()))
+    }
+
     fn oauth2_authorization_grant<'c>(
         &'c mut self,
     ) -> Box<dyn OAuth2AuthorizationGrantRepository<Error = Self::Error> + 'c> {
@@ -345,4 +431,74 @@ where
     fn policy_data<'c>(&'c mut self) -> Box<dyn PolicyDataRepository<Error = Self::Error> + 'c> {
         Box::new(PgPolicyDataRepository::new(self.conn.as_mut()))
     }
+
+    fn scim_user_link<'c>(
+        &'c mut self,
+    ) -> Box<dyn ScimUserLinkRepository<Error = Self::Error> + 'c> {
+        Box::new(PgScimUserLinkRepository::new(self.conn.as_mut()))
+    }
+
+    fn scim_sync_run<'c>(&'c mut self) -> Box<dyn ScimSyncRunRepository<Error = Self::Error> + 'c> {
+        Box::new(PgScimSyncRunRepository::new(self.conn.as_mut()))
+    }
+
+    fn user_metadata<'c>(
+        &'c mut self,
+    ) -> Box<dyn UserMetadataRepository<Error = Self::Error> + 'c> {
+        Box::new(PgUserMetadataRepository::new(self.conn.as_mut()))
+    }
+
+    fn login_stats<'c>(&'c mut self) -> Box<dyn LoginStatsRepository<Error = Self::Error> + 'c> {
+        Box::new(PgLoginStatsRepository::new(self.conn.as_mut()))
+    }
+
+    fn user_claim_link<'c>(
+        &'c mut self,
+    ) -> Box<dyn UserClaimLinkRepository<Error = Self::Error> + 'c> {
+        Box::new(PgUserClaimLinkRepository::new(self.conn.as_mut()))
+    }
+
+    fn feature_flag_override<'c>(
+        &'c mut self,
+    ) -> Box<dyn FeatureFlagOverrideRepository<Error = Self::Error> + 'c> {
+        Box::new(PgFeatureFlagOverrideRepository::new(self.conn.as_mut()))
+    }
+
+    fn oauth2_dpop_proof<'c>(
+        &'c mut self,
+    ) -> Box<dyn OAuth2DPoPProofRepository<Error = Self::Error> + 'c> {
+        Box::new(PgOAuth2DPoPProofRepository::new(self.conn.as_mut()))
+    }
+
+    fn oauth2_pairwise_subject<'c>(
+        &'c mut self,
+    ) -> Box<dyn OAuth2PairwiseSubjectRepository<Error = Self::Error> + 'c> {
+        Box::new(PgOAuth2PairwiseSubjectRepository::new(self.conn.as_mut()))
+    }
+
+    fn oauth2_pushed_authorization_request<'c>(
+        &'c mut self,
+    ) -> Box<dyn OAuth2PushedAuthorizationRequestRepository<Error = Self::Error> + 'c> {
+        Box::new(PgOAuth2PushedAuthorizationRequestRepository::new(
+            self.conn.as_mut(),
+        ))
+    }
+
+    fn user_action_token<'c>(
+        &'c mut self,
+    ) -> Box<dyn UserActionTokenRepository<Error = Self::Error> + 'c> {
+        Box::new(PgUserActionTokenRepository::new(self.conn.as_mut()))
+    }
+
+    fn oauth2_backchannel_authentication_grant<'c>(
+        &'c mut self,
+    ) -> Box<dyn OAuth2BackchannelAuthenticationGrantRepository<Error = Self::Error> + 'c> {
+        Box::new(PgOAuth2BackchannelAuthenticationGrantRepository::new(
+            self.conn.as_mut(),
+        ))
+    }
+
+    fn audit_event<'c>(&'c mut self) -> Box<dyn AuditEventRepository<Error = Self::Error> + 'c> {
+        Box::new(PgAuditEventRepository::new(self.conn.as_mut()))
+    }
 }
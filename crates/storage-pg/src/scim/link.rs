@@ -0,0 +1,233 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{ScimUserLink, User};
+use mas_storage::{Clock, Page, Pagination, scim::ScimUserLinkRepository};
+use rand::RngCore;
+use sea_query::{Expr, PostgresQueryBuilder, Query, enum_def};
+use sea_query_binder::SqlxBinder;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, iden::ScimUserLinks, pagination::QueryBuilderExt, tracing::ExecuteExt};
+
+/// An implementation of [`ScimUserLinkRepository`] for a PostgreSQL connection
+pub struct PgScimUserLinkRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgScimUserLinkRepository<'c> {
+    /// Create a new [`PgScimUserLinkRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+#[enum_def]
+struct ScimUserLinkLookup {
+    scim_user_link_id: Uuid,
+    user_id: Uuid,
+    external_id: String,
+    created_at: DateTime<Utc>,
+}
+
+impl From<ScimUserLinkLookup> for ScimUserLink {
+    fn from(value: ScimUserLinkLookup) -> Self {
+        ScimUserLink {
+            id: value.scim_user_link_id.into(),
+            user_id: value.user_id.into(),
+            external_id: value.external_id,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[async_trait]
+impl ScimUserLinkRepository for PgScimUserLinkRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.scim_user_link.lookup",
+        skip_all,
+        fields(
+            db.query.text,
+            scim_user_link.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<ScimUserLink>, Self::Error> {
+        let res = sqlx::query_as!(
+            ScimUserLinkLookup,
+            r#"
+                SELECT scim_user_link_id
+                     , user_id
+                     , external_id
+                     , created_at
+                FROM scim_user_links
+                WHERE scim_user_link_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.scim_user_link.find_by_external_id",
+        skip_all,
+        fields(
+            db.query.text,
+            scim_user_link.external_id = external_id,
+        ),
+        err,
+    )]
+    async fn find_by_external_id(
+        &mut self,
+        external_id: &str,
+    ) -> Result<Option<ScimUserLink>, Self::Error> {
+        let res = sqlx::query_as!(
+            ScimUserLinkLookup,
+            r#"
+                SELECT scim_user_link_id
+                     , user_id
+                     , external_id
+                     , created_at
+                FROM scim_user_links
+                WHERE external_id = $1
+            "#,
+            external_id,
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.scim_user_link.find_by_user",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn find_by_user(&mut self, user: &User) -> Result<Option<ScimUserLink>, Self::Error> {
+        let res = sqlx::query_as!(
+            ScimUserLinkLookup,
+            r#"
+                SELECT scim_user_link_id
+                     , user_id
+                     , external_id
+                     , created_at
+                FROM scim_user_links
+                WHERE user_id = $1
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.scim_user_link.add",
+        skip_all,
+        fields(
+            db.query.text,
+            scim_user_link.id,
+            scim_user_link.external_id = external_id,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        external_id: String,
+    ) -> Result<ScimUserLink, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("scim_user_link.id", tracing::field::display(id));
+
+        sqlx::query!(
+            r#"
+                INSERT INTO scim_user_links
+                    (scim_user_link_id, user_id, external_id, created_at)
+                VALUES ($1, $2, $3, $4)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            &external_id,
+            created_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(ScimUserLink {
+            id,
+            user_id: user.id,
+            external_id,
+            created_at,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.scim_user_link.list",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn list(&mut self, pagination: Pagination) -> Result<Page<ScimUserLink>, Self::Error> {
+        let (sql, arguments) = Query::select()
+            .expr_as(
+                Expr::col((ScimUserLinks::Table, ScimUserLinks::ScimUserLinkId)),
+                ScimUserLinkLookupIden::ScimUserLinkId,
+            )
+            .expr_as(
+                Expr::col((ScimUserLinks::Table, ScimUserLinks::UserId)),
+                ScimUserLinkLookupIden::UserId,
+            )
+            .expr_as(
+                Expr::col((ScimUserLinks::Table, ScimUserLinks::ExternalId)),
+                ScimUserLinkLookupIden::ExternalId,
+            )
+            .expr_as(
+                Expr::col((ScimUserLinks::Table, ScimUserLinks::CreatedAt)),
+                ScimUserLinkLookupIden::CreatedAt,
+            )
+            .from(ScimUserLinks::Table)
+            .generate_pagination(
+                (ScimUserLinks::Table, ScimUserLinks::ScimUserLinkId),
+                pagination,
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        let edges: Vec<ScimUserLinkLookup> = sqlx::query_as_with(&sql, arguments)
+            .traced()
+            .fetch_all(&mut *self.conn)
+            .await?;
+
+        let page = pagination.process(edges).map(ScimUserLink::from);
+
+        Ok(page)
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! A module containing the PostgreSQL implementation of the repositories
+//! related to the synchronisation of users from an upstream SCIM directory
+
+mod link;
+mod sync_run;
+
+pub use self::{link::PgScimUserLinkRepository, sync_run::PgScimSyncRunRepository};
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use mas_data_model::{ScimSyncAction, ScimSyncChange};
+    use mas_storage::{
+        Clock, Pagination, RepositoryAccess,
+        clock::MockClock,
+        scim::{ScimSyncRunFilter, ScimSyncRunRepository, ScimUserLinkRepository},
+        user::UserRepository,
+    };
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use sqlx::PgPool;
+
+    use crate::PgRepository;
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_scim_user_link_repository(pool: PgPool) {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+
+        let user = repo
+            .user()
+            .add(&mut rng, &clock, "john".to_owned())
+            .await
+            .unwrap();
+
+        // Nothing is linked yet
+        assert!(
+            repo.scim_user_link()
+                .find_by_user(&user)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            repo.scim_user_link()
+                .find_by_external_id("2819c223")
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let link = repo
+            .scim_user_link()
+            .add(&mut rng, &clock, &user, "2819c223".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(link.user_id, user.id);
+        assert_eq!(link.external_id, "2819c223");
+
+        let by_id = repo.scim_user_link().lookup(link.id).await.unwrap();
+        assert_eq!(by_id.as_ref(), Some(&link));
+
+        let by_user = repo.scim_user_link().find_by_user(&user).await.unwrap();
+        assert_eq!(by_user.as_ref(), Some(&link));
+
+        let by_external_id = repo
+            .scim_user_link()
+            .find_by_external_id("2819c223")
+            .await
+            .unwrap();
+        assert_eq!(by_external_id.as_ref(), Some(&link));
+
+        // The same user can't be linked twice
+        assert!(
+            repo.scim_user_link()
+                .add(&mut rng, &clock, &user, "other".to_owned())
+                .await
+                .is_err()
+        );
+        repo.cancel().await.unwrap();
+
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+        let user = repo
+            .user()
+            .add(&mut rng, &clock, "alice".to_owned())
+            .await
+            .unwrap();
+        let link = repo
+            .scim_user_link()
+            .add(&mut rng, &clock, &user, "c4d2".to_owned())
+            .await
+            .unwrap();
+
+        let page = repo
+            .scim_user_link()
+            .list(Pagination::first(10))
+            .await
+            .unwrap();
+        assert!(!page.has_next_page);
+        assert_eq!(page.edges, vec![link]);
+
+        repo.save().await.unwrap();
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_scim_sync_run_repository(pool: PgPool) {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+
+        let all = ScimSyncRunFilter::new();
+        let running = all.running_only();
+        let completed = all.completed_only();
+        let failed = all.failed_only();
+
+        assert_eq!(repo.scim_sync_run().count(all).await.unwrap(), 0);
+
+        let run1 = repo.scim_sync_run().start(&mut rng, &clock).await.unwrap();
+        assert!(run1.state.is_running());
+        assert!(run1.changes.is_empty());
+
+        clock.advance(Duration::minutes(1));
+        let run2 = repo.scim_sync_run().start(&mut rng, &clock).await.unwrap();
+
+        assert_eq!(repo.scim_sync_run().count(all).await.unwrap(), 2);
+        assert_eq!(repo.scim_sync_run().count(running).await.unwrap(), 2);
+        assert_eq!(repo.scim_sync_run().count(completed).await.unwrap(), 0);
+        assert_eq!(repo.scim_sync_run().count(failed).await.unwrap(), 0);
+
+        // Complete the first run with a change
+        let changes = vec![ScimSyncChange {
+            action: ScimSyncAction::Created,
+            user_id: ulid::Ulid::nil(),
+            username: "john".to_owned(),
+            external_id: "2819c223".to_owned(),
+        }];
+        clock.advance(Duration::minutes(1));
+        let run1 = repo
+            .scim_sync_run()
+            .complete(&clock, run1, changes.clone())
+            .await
+            .unwrap();
+        assert_eq!(run1.state.finished_at(), Some(clock.now()));
+        assert_eq!(run1.changes, changes);
+
+        // Completing it again should fail
+        assert!(
+            repo.scim_sync_run()
+                .complete(&clock, run1.clone(), Vec::new())
+                .await
+                .is_err()
+        );
+
+        // Fail the second run
+        let run2 = repo
+            .scim_sync_run()
+            .fail(&clock, run2, "directory unreachable".to_owned())
+            .await
+            .unwrap();
+        assert!(run2.state.is_failed());
+        assert_eq!(run2.state.error(), Some("directory unreachable"));
+
+        // Lookups reflect the updated state
+        let lookup = repo.scim_sync_run().lookup(run1.id).await.unwrap();
+        assert_eq!(lookup.as_ref(), Some(&run1));
+        let lookup = repo.scim_sync_run().lookup(run2.id).await.unwrap();
+        assert_eq!(lookup.as_ref(), Some(&run2));
+
+        assert_eq!(repo.scim_sync_run().count(running).await.unwrap(), 0);
+        assert_eq!(repo.scim_sync_run().count(completed).await.unwrap(), 1);
+        assert_eq!(repo.scim_sync_run().count(failed).await.unwrap(), 1);
+
+        let page = repo
+            .scim_sync_run()
+            .list(all, Pagination::first(10))
+            .await
+            .unwrap();
+        assert_eq!(page.edges, vec![run1.clone(), run2.clone()]);
+
+        let page = repo
+            .scim_sync_run()
+            .list(failed, Pagination::first(10))
+            .await
+            .unwrap();
+        assert_eq!(page.edges, vec![run2]);
+
+        repo.save().await.unwrap();
+    }
+}
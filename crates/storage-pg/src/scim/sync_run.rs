@@ -0,0 +1,338 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{ScimSyncChange, ScimSyncRun, ScimSyncRunState};
+use mas_storage::{
+    Clock, Page, Pagination,
+    scim::{ScimSyncRunFilter, ScimSyncRunFilterState, ScimSyncRunRepository},
+};
+use rand::RngCore;
+use sea_query::{Expr, PostgresQueryBuilder, Query, enum_def};
+use sea_query_binder::SqlxBinder;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{
+    DatabaseError, DatabaseInconsistencyError,
+    filter::{Filter, StatementExt},
+    iden::ScimSyncRuns,
+    pagination::QueryBuilderExt,
+    tracing::ExecuteExt,
+};
+
+/// An implementation of [`ScimSyncRunRepository`] for a PostgreSQL connection
+pub struct PgScimSyncRunRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgScimSyncRunRepository<'c> {
+    /// Create a new [`PgScimSyncRunRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+#[enum_def]
+struct ScimSyncRunLookup {
+    scim_sync_run_id: Uuid,
+    started_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+    error: Option<String>,
+    changes: serde_json::Value,
+}
+
+impl TryFrom<ScimSyncRunLookup> for ScimSyncRun {
+    type Error = DatabaseInconsistencyError;
+
+    fn try_from(value: ScimSyncRunLookup) -> Result<Self, Self::Error> {
+        let id = value.scim_sync_run_id.into();
+
+        let state = match (value.finished_at, value.error) {
+            (None, None) => ScimSyncRunState::Running,
+            (Some(finished_at), None) => ScimSyncRunState::Completed { finished_at },
+            (Some(finished_at), Some(error)) => ScimSyncRunState::Failed { finished_at, error },
+            (None, Some(_)) => {
+                return Err(DatabaseInconsistencyError::on("scim_sync_runs")
+                    .column("finished_at")
+                    .row(id));
+            }
+        };
+
+        let changes = serde_json::from_value(value.changes).map_err(|e| {
+            DatabaseInconsistencyError::on("scim_sync_runs")
+                .column("changes")
+                .row(id)
+                .source(e)
+        })?;
+
+        Ok(ScimSyncRun {
+            id,
+            state,
+            started_at: value.started_at,
+            changes,
+        })
+    }
+}
+
+impl Filter for ScimSyncRunFilter {
+    fn generate_condition(&self, _has_joins: bool) -> impl sea_query::IntoCondition {
+        sea_query::Condition::all().add_option(self.state().map(|state| {
+            match state {
+                ScimSyncRunFilterState::Running => {
+                    Expr::col((ScimSyncRuns::Table, ScimSyncRuns::FinishedAt)).is_null()
+                }
+                ScimSyncRunFilterState::Completed => {
+                    Expr::col((ScimSyncRuns::Table, ScimSyncRuns::FinishedAt))
+                        .is_not_null()
+                        .and(Expr::col((ScimSyncRuns::Table, ScimSyncRuns::Error)).is_null())
+                }
+                ScimSyncRunFilterState::Failed => {
+                    Expr::col((ScimSyncRuns::Table, ScimSyncRuns::Error)).is_not_null()
+                }
+            }
+        }))
+    }
+}
+
+#[async_trait]
+impl ScimSyncRunRepository for PgScimSyncRunRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.scim_sync_run.lookup",
+        skip_all,
+        fields(
+            db.query.text,
+            scim_sync_run.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<ScimSyncRun>, Self::Error> {
+        let res = sqlx::query_as!(
+            ScimSyncRunLookup,
+            r#"
+                SELECT scim_sync_run_id
+                     , started_at
+                     , finished_at
+                     , error
+                     , changes
+                FROM scim_sync_runs
+                WHERE scim_sync_run_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        let Some(res) = res else { return Ok(None) };
+
+        Ok(Some(res.try_into()?))
+    }
+
+    #[tracing::instrument(
+        name = "db.scim_sync_run.start",
+        skip_all,
+        fields(
+            db.query.text,
+            scim_sync_run.id,
+        ),
+        err,
+    )]
+    async fn start(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+    ) -> Result<ScimSyncRun, Self::Error> {
+        let started_at = clock.now();
+        let id = Ulid::from_datetime_with_source(started_at.into(), rng);
+        tracing::Span::current().record("scim_sync_run.id", tracing::field::display(id));
+
+        sqlx::query!(
+            r#"
+                INSERT INTO scim_sync_runs (scim_sync_run_id, started_at)
+                VALUES ($1, $2)
+            "#,
+            Uuid::from(id),
+            started_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(ScimSyncRun {
+            id,
+            state: ScimSyncRunState::Running,
+            started_at,
+            changes: Vec::new(),
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.scim_sync_run.complete",
+        skip_all,
+        fields(
+            db.query.text,
+            %scim_sync_run.id,
+            scim_sync_run.changes = changes.len(),
+        ),
+        err,
+    )]
+    async fn complete(
+        &mut self,
+        clock: &dyn Clock,
+        mut scim_sync_run: ScimSyncRun,
+        changes: Vec<ScimSyncChange>,
+    ) -> Result<ScimSyncRun, Self::Error> {
+        let finished_at = clock.now();
+        let changes_json =
+            serde_json::to_value(&changes).map_err(DatabaseError::to_invalid_operation)?;
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE scim_sync_runs
+                SET finished_at = $2
+                  , changes = $3
+                WHERE scim_sync_run_id = $1
+                  AND finished_at IS NULL
+            "#,
+            Uuid::from(scim_sync_run.id),
+            finished_at,
+            changes_json,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        scim_sync_run.state = ScimSyncRunState::Completed { finished_at };
+        scim_sync_run.changes = changes;
+
+        Ok(scim_sync_run)
+    }
+
+    #[tracing::instrument(
+        name = "db.scim_sync_run.fail",
+        skip_all,
+        fields(
+            db.query.text,
+            %scim_sync_run.id,
+        ),
+        err,
+    )]
+    async fn fail(
+        &mut self,
+        clock: &dyn Clock,
+        mut scim_sync_run: ScimSyncRun,
+        error: String,
+    ) -> Result<ScimSyncRun, Self::Error> {
+        let finished_at = clock.now();
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE scim_sync_runs
+                SET finished_at = $2
+                  , error = $3
+                WHERE scim_sync_run_id = $1
+                  AND finished_at IS NULL
+            "#,
+            Uuid::from(scim_sync_run.id),
+            finished_at,
+            &error,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        scim_sync_run.state = ScimSyncRunState::Failed { finished_at, error };
+
+        Ok(scim_sync_run)
+    }
+
+    #[tracing::instrument(
+        name = "db.scim_sync_run.list",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn list(
+        &mut self,
+        filter: ScimSyncRunFilter,
+        pagination: Pagination,
+    ) -> Result<Page<ScimSyncRun>, Self::Error> {
+        let (sql, arguments) = Query::select()
+            .expr_as(
+                Expr::col((ScimSyncRuns::Table, ScimSyncRuns::ScimSyncRunId)),
+                ScimSyncRunLookupIden::ScimSyncRunId,
+            )
+            .expr_as(
+                Expr::col((ScimSyncRuns::Table, ScimSyncRuns::StartedAt)),
+                ScimSyncRunLookupIden::StartedAt,
+            )
+            .expr_as(
+                Expr::col((ScimSyncRuns::Table, ScimSyncRuns::FinishedAt)),
+                ScimSyncRunLookupIden::FinishedAt,
+            )
+            .expr_as(
+                Expr::col((ScimSyncRuns::Table, ScimSyncRuns::Error)),
+                ScimSyncRunLookupIden::Error,
+            )
+            .expr_as(
+                Expr::col((ScimSyncRuns::Table, ScimSyncRuns::Changes)),
+                ScimSyncRunLookupIden::Changes,
+            )
+            .from(ScimSyncRuns::Table)
+            .apply_filter(filter)
+            .generate_pagination(
+                (ScimSyncRuns::Table, ScimSyncRuns::ScimSyncRunId),
+                pagination,
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        let edges: Vec<ScimSyncRunLookup> = sqlx::query_as_with(&sql, arguments)
+            .traced()
+            .fetch_all(&mut *self.conn)
+            .await?;
+
+        let page = pagination.process(edges).try_map(ScimSyncRun::try_from)?;
+
+        Ok(page)
+    }
+
+    #[tracing::instrument(
+        name = "db.scim_sync_run.count",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn count(&mut self, filter: ScimSyncRunFilter) -> Result<usize, Self::Error> {
+        let (sql, arguments) = Query::select()
+            .expr(Expr::col((ScimSyncRuns::Table, ScimSyncRuns::ScimSyncRunId)).count())
+            .from(ScimSyncRuns::Table)
+            .apply_filter(filter)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let count: i64 = sqlx::query_scalar_with(&sql, arguments)
+            .traced()
+            .fetch_one(&mut *self.conn)
+            .await?;
+
+        count
+            .try_into()
+            .map_err(DatabaseError::to_invalid_operation)
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{UpstreamOAuthCachedDocument, UpstreamOAuthCachedDocumentKind};
+use mas_storage::{Clock, upstream_oauth2::UpstreamOAuthCachedDocumentRepository};
+use sqlx::PgConnection;
+
+use crate::{DatabaseError, tracing::ExecuteExt};
+
+/// An implementation of [`UpstreamOAuthCachedDocumentRepository`] for a
+/// PostgreSQL connection
+pub struct PgUpstreamOAuthCachedDocumentRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgUpstreamOAuthCachedDocumentRepository<'c> {
+    /// Create a new [`PgUpstreamOAuthCachedDocumentRepository`] from an active
+    /// PostgreSQL connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct CachedDocumentLookup {
+    document: serde_json::Value,
+    fetched_at: DateTime<Utc>,
+}
+
+#[async_trait]
+impl UpstreamOAuthCachedDocumentRepository for PgUpstreamOAuthCachedDocumentRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.upstream_oauth_cached_document.lookup",
+        skip_all,
+        fields(
+            db.query.text,
+            upstream_oauth_cached_document.kind = %kind,
+            upstream_oauth_cached_document.url = url,
+        ),
+        err,
+    )]
+    async fn lookup(
+        &mut self,
+        kind: UpstreamOAuthCachedDocumentKind,
+        url: &str,
+    ) -> Result<Option<UpstreamOAuthCachedDocument>, Self::Error> {
+        let res = sqlx::query_as!(
+            CachedDocumentLookup,
+            r#"
+                SELECT document, fetched_at
+                FROM upstream_oauth_cached_documents
+                WHERE kind = $1
+                  AND url = $2
+            "#,
+            kind.as_str(),
+            url,
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(|res| UpstreamOAuthCachedDocument {
+            kind,
+            url: url.to_owned(),
+            document: res.document,
+            fetched_at: res.fetched_at,
+        }))
+    }
+
+    #[tracing::instrument(
+        name = "db.upstream_oauth_cached_document.save",
+        skip_all,
+        fields(
+            db.query.text,
+            upstream_oauth_cached_document.kind = %kind,
+            upstream_oauth_cached_document.url = url,
+        ),
+        err,
+    )]
+    async fn save(
+        &mut self,
+        clock: &dyn Clock,
+        kind: UpstreamOAuthCachedDocumentKind,
+        url: &str,
+        document: serde_json::Value,
+    ) -> Result<UpstreamOAuthCachedDocument, Self::Error> {
+        let fetched_at = clock.now();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO upstream_oauth_cached_documents
+                    (kind, url, document, fetched_at)
+                VALUES
+                    ($1, $2, $3, $4)
+                ON CONFLICT (kind, url)
+                DO UPDATE SET document = EXCLUDED.document,
+                              fetched_at = EXCLUDED.fetched_at
+            "#,
+            kind.as_str(),
+            url,
+            document,
+            fetched_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(UpstreamOAuthCachedDocument {
+            kind,
+            url: url.to_owned(),
+            document,
+            fetched_at,
+        })
+    }
+}
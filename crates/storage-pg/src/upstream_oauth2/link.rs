@@ -6,7 +6,10 @@
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use mas_data_model::{UpstreamOAuthLink, UpstreamOAuthProvider, User};
+use mas_data_model::{
+    UpstreamOAuthLink, UpstreamOAuthLinkImportedAttributes, UpstreamOAuthLinkTokens,
+    UpstreamOAuthProvider, User,
+};
 use mas_storage::{
     Clock, Page, Pagination,
     upstream_oauth2::{UpstreamOAuthLinkFilter, UpstreamOAuthLinkRepository},
@@ -15,7 +18,7 @@ use opentelemetry_semantic_conventions::trace::DB_QUERY_TEXT;
 use rand::RngCore;
 use sea_query::{Expr, PostgresQueryBuilder, Query, enum_def};
 use sea_query_binder::SqlxBinder;
-use sqlx::PgConnection;
+use sqlx::{PgConnection, types::Json};
 use tracing::Instrument;
 use ulid::Ulid;
 use uuid::Uuid;
@@ -50,6 +53,7 @@ struct LinkLookup {
     user_id: Option<Uuid>,
     subject: String,
     human_account_name: Option<String>,
+    imported_attributes: Json<UpstreamOAuthLinkImportedAttributes>,
     created_at: DateTime<Utc>,
 }
 
@@ -61,7 +65,32 @@ impl From<LinkLookup> for UpstreamOAuthLink {
             user_id: value.user_id.map(Ulid::from),
             subject: value.subject,
             human_account_name: value.human_account_name,
+            imported_attributes: value.imported_attributes.0,
+            created_at: value.created_at,
+        }
+    }
+}
+
+struct LinkTokensLookup {
+    upstream_oauth_link_id: Uuid,
+    encrypted_access_token: String,
+    encrypted_refresh_token: Option<String>,
+    access_token_expires_at: Option<DateTime<Utc>>,
+    refresh_failed_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<LinkTokensLookup> for UpstreamOAuthLinkTokens {
+    fn from(value: LinkTokensLookup) -> Self {
+        UpstreamOAuthLinkTokens {
+            link_id: Ulid::from(value.upstream_oauth_link_id),
+            encrypted_access_token: value.encrypted_access_token,
+            encrypted_refresh_token: value.encrypted_refresh_token,
+            access_token_expires_at: value.access_token_expires_at,
+            refresh_failed_at: value.refresh_failed_at,
             created_at: value.created_at,
+            updated_at: value.updated_at,
         }
     }
 }
@@ -132,6 +161,7 @@ impl UpstreamOAuthLinkRepository for PgUpstreamOAuthLinkRepository<'_> {
                     user_id,
                     subject,
                     human_account_name,
+                    imported_attributes as "imported_attributes: Json<UpstreamOAuthLinkImportedAttributes>",
                     created_at
                 FROM upstream_oauth_links
                 WHERE upstream_oauth_link_id = $1
@@ -172,6 +202,7 @@ impl UpstreamOAuthLinkRepository for PgUpstreamOAuthLinkRepository<'_> {
                     user_id,
                     subject,
                     human_account_name,
+                    imported_attributes as "imported_attributes: Json<UpstreamOAuthLinkImportedAttributes>",
                     created_at
                 FROM upstream_oauth_links
                 WHERE upstream_oauth_provider_id = $1
@@ -241,6 +272,7 @@ impl UpstreamOAuthLinkRepository for PgUpstreamOAuthLinkRepository<'_> {
             user_id: None,
             subject,
             human_account_name,
+            imported_attributes: UpstreamOAuthLinkImportedAttributes::default(),
             created_at,
         })
     }
@@ -278,6 +310,204 @@ impl UpstreamOAuthLinkRepository for PgUpstreamOAuthLinkRepository<'_> {
         Ok(())
     }
 
+    #[tracing::instrument(
+        name = "db.upstream_oauth_link.set_imported_attributes",
+        skip_all,
+        fields(
+            db.query.text,
+            %upstream_oauth_link.id,
+        ),
+        err,
+    )]
+    async fn set_imported_attributes(
+        &mut self,
+        mut upstream_oauth_link: UpstreamOAuthLink,
+        imported_attributes: UpstreamOAuthLinkImportedAttributes,
+    ) -> Result<UpstreamOAuthLink, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE upstream_oauth_links
+                SET imported_attributes = $2
+                WHERE upstream_oauth_link_id = $1
+            "#,
+            Uuid::from(upstream_oauth_link.id),
+            Json(&imported_attributes) as _,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        upstream_oauth_link.imported_attributes = imported_attributes;
+        Ok(upstream_oauth_link)
+    }
+
+    #[tracing::instrument(
+        name = "db.upstream_oauth_link.set_subject",
+        skip_all,
+        fields(
+            db.query.text,
+            %upstream_oauth_link.id,
+            upstream_oauth_link.subject = subject,
+        ),
+        err,
+    )]
+    async fn set_subject(
+        &mut self,
+        mut upstream_oauth_link: UpstreamOAuthLink,
+        subject: String,
+    ) -> Result<UpstreamOAuthLink, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE upstream_oauth_links
+                SET subject = $2
+                WHERE upstream_oauth_link_id = $1
+            "#,
+            Uuid::from(upstream_oauth_link.id),
+            &subject,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        upstream_oauth_link.subject = subject;
+        Ok(upstream_oauth_link)
+    }
+
+    #[tracing::instrument(
+        name = "db.upstream_oauth_link.tokens",
+        skip_all,
+        fields(
+            db.query.text,
+            %upstream_oauth_link.id,
+        ),
+        err,
+    )]
+    async fn tokens(
+        &mut self,
+        upstream_oauth_link: &UpstreamOAuthLink,
+    ) -> Result<Option<UpstreamOAuthLinkTokens>, Self::Error> {
+        let res = sqlx::query_as!(
+            LinkTokensLookup,
+            r#"
+                SELECT
+                    upstream_oauth_link_id,
+                    encrypted_access_token,
+                    encrypted_refresh_token,
+                    access_token_expires_at,
+                    refresh_failed_at,
+                    created_at,
+                    updated_at
+                FROM upstream_oauth_link_tokens
+                WHERE upstream_oauth_link_id = $1
+            "#,
+            Uuid::from(upstream_oauth_link.id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.upstream_oauth_link.set_tokens",
+        skip_all,
+        fields(
+            db.query.text,
+            %upstream_oauth_link.id,
+        ),
+        err,
+    )]
+    async fn set_tokens(
+        &mut self,
+        clock: &dyn Clock,
+        upstream_oauth_link: &UpstreamOAuthLink,
+        encrypted_access_token: String,
+        encrypted_refresh_token: Option<String>,
+        access_token_expires_at: Option<DateTime<Utc>>,
+    ) -> Result<UpstreamOAuthLinkTokens, Self::Error> {
+        let updated_at = clock.now();
+
+        let created_at = sqlx::query_scalar!(
+            r#"
+                INSERT INTO upstream_oauth_link_tokens (
+                    upstream_oauth_link_id,
+                    encrypted_access_token,
+                    encrypted_refresh_token,
+                    access_token_expires_at,
+                    created_at,
+                    updated_at
+                ) VALUES ($1, $2, $3, $4, $5, $5)
+                ON CONFLICT (upstream_oauth_link_id)
+                    DO UPDATE
+                    SET
+                        encrypted_access_token = EXCLUDED.encrypted_access_token,
+                        encrypted_refresh_token = EXCLUDED.encrypted_refresh_token,
+                        access_token_expires_at = EXCLUDED.access_token_expires_at,
+                        refresh_failed_at = NULL,
+                        updated_at = EXCLUDED.updated_at
+                RETURNING created_at
+            "#,
+            Uuid::from(upstream_oauth_link.id),
+            &encrypted_access_token,
+            encrypted_refresh_token.as_deref(),
+            access_token_expires_at,
+            updated_at,
+        )
+        .traced()
+        .fetch_one(&mut *self.conn)
+        .await?;
+
+        Ok(UpstreamOAuthLinkTokens {
+            link_id: upstream_oauth_link.id,
+            encrypted_access_token,
+            encrypted_refresh_token,
+            access_token_expires_at,
+            refresh_failed_at: None,
+            created_at,
+            updated_at,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.upstream_oauth_link.mark_refresh_failed",
+        skip_all,
+        fields(
+            db.query.text,
+            upstream_oauth_link.id = %tokens.link_id,
+        ),
+        err,
+    )]
+    async fn mark_refresh_failed(
+        &mut self,
+        clock: &dyn Clock,
+        mut tokens: UpstreamOAuthLinkTokens,
+    ) -> Result<UpstreamOAuthLinkTokens, Self::Error> {
+        let refresh_failed_at = clock.now();
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE upstream_oauth_link_tokens
+                SET refresh_failed_at = $2
+                WHERE upstream_oauth_link_id = $1
+            "#,
+            Uuid::from(tokens.link_id),
+            refresh_failed_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        tokens.refresh_failed_at = Some(refresh_failed_at);
+        Ok(tokens)
+    }
+
     #[tracing::instrument(
         name = "db.upstream_oauth_link.list",
         skip_all,
@@ -321,6 +551,13 @@ impl UpstreamOAuthLinkRepository for PgUpstreamOAuthLinkRepository<'_> {
                 )),
                 LinkLookupIden::HumanAccountName,
             )
+            .expr_as(
+                Expr::col((
+                    UpstreamOAuthLinks::Table,
+                    UpstreamOAuthLinks::ImportedAttributes,
+                )),
+                LinkLookupIden::ImportedAttributes,
+            )
             .expr_as(
                 Expr::col((UpstreamOAuthLinks::Table, UpstreamOAuthLinks::CreatedAt)),
                 LinkLookupIden::CreatedAt,
@@ -7,29 +7,31 @@
 //! A module containing the PostgreSQL implementation of the repositories
 //! related to the upstream OAuth 2.0 providers
 
+mod cached_document;
 mod link;
 mod provider;
 mod session;
 
 pub use self::{
-    link::PgUpstreamOAuthLinkRepository, provider::PgUpstreamOAuthProviderRepository,
-    session::PgUpstreamOAuthSessionRepository,
+    cached_document::PgUpstreamOAuthCachedDocumentRepository, link::PgUpstreamOAuthLinkRepository,
+    provider::PgUpstreamOAuthProviderRepository, session::PgUpstreamOAuthSessionRepository,
 };
 
 #[cfg(test)]
 mod tests {
     use chrono::Duration;
     use mas_data_model::{
+        UpstreamOAuthCachedDocumentKind, UpstreamOAuthLinkImportedAttributes,
         UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderTokenAuthMethod,
     };
     use mas_iana::jose::JsonWebSignatureAlg;
     use mas_storage::{
-        Pagination, RepositoryAccess,
+        Clock, Pagination, RepositoryAccess,
         clock::MockClock,
         upstream_oauth2::{
-            UpstreamOAuthLinkFilter, UpstreamOAuthLinkRepository, UpstreamOAuthProviderFilter,
-            UpstreamOAuthProviderParams, UpstreamOAuthProviderRepository,
-            UpstreamOAuthSessionRepository,
+            UpstreamOAuthCachedDocumentRepository, UpstreamOAuthLinkFilter,
+            UpstreamOAuthLinkRepository, UpstreamOAuthProviderFilter, UpstreamOAuthProviderParams,
+            UpstreamOAuthProviderRepository, UpstreamOAuthSessionRepository,
         },
         user::UserRepository,
     };
@@ -65,6 +67,9 @@ mod tests {
                     fetch_userinfo: false,
                     userinfo_signed_response_alg: None,
                     token_endpoint_signing_alg: None,
+                    token_endpoint_signing_key_id: None,
+                    tls_client_certificate: None,
+                    encrypted_tls_client_key: None,
                     client_id: "client-id".to_owned(),
                     encrypted_client_secret: None,
                     claims_imports: UpstreamOAuthProviderClaimsImports::default(),
@@ -77,6 +82,11 @@ mod tests {
                     response_mode: None,
                     additional_authorization_parameters: Vec::new(),
                     forward_login_hint: false,
+                    forward_prompt: false,
+                    forward_acr_values: false,
+                    forward_ui_locales: false,
+                    store_tokens: false,
+                    deprovisioning: None,
                     ui_order: 0,
                 },
             )
@@ -149,10 +159,102 @@ mod tests {
             .expect("link to be found in database");
         assert_eq!(link.subject, "a-subject");
         assert_eq!(link.provider_id, provider.id);
+        assert_eq!(
+            link.imported_attributes,
+            UpstreamOAuthLinkImportedAttributes::default()
+        );
+
+        // Record what was imported through the link
+        let imported_attributes = UpstreamOAuthLinkImportedAttributes {
+            displayname: Some("John".to_owned()),
+            email: Some("john@example.com".to_owned()),
+            ..UpstreamOAuthLinkImportedAttributes::default()
+        };
+        let link = repo
+            .upstream_oauth_link()
+            .set_imported_attributes(link, imported_attributes.clone())
+            .await
+            .unwrap();
+        assert_eq!(link.imported_attributes, imported_attributes);
+        let link = repo
+            .upstream_oauth_link()
+            .lookup(link.id)
+            .await
+            .unwrap()
+            .expect("link to be found in database");
+        assert_eq!(link.imported_attributes, imported_attributes);
+
+        // Change the subject of the link, and back
+        let link = repo
+            .upstream_oauth_link()
+            .set_subject(link, "another-subject".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(link.subject, "another-subject");
+        assert!(
+            repo.upstream_oauth_link()
+                .find_by_subject(&provider, "a-subject")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let link = repo
+            .upstream_oauth_link()
+            .find_by_subject(&provider, "another-subject")
+            .await
+            .unwrap()
+            .expect("link to be found in database");
+        let link = repo
+            .upstream_oauth_link()
+            .set_subject(link, "a-subject".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(link.subject, "a-subject");
+
+        // No tokens were stored for the link yet
+        assert!(
+            repo.upstream_oauth_link()
+                .tokens(&link)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let expires_at = clock.now() + Duration::microseconds(5 * 60 * 1000 * 1000);
+        let tokens = repo
+            .upstream_oauth_link()
+            .set_tokens(
+                &clock,
+                &link,
+                "access-token".to_owned(),
+                Some("refresh-token".to_owned()),
+                Some(expires_at),
+            )
+            .await
+            .unwrap();
+        assert_eq!(tokens.created_at, tokens.updated_at);
+
+        // Storing new tokens replaces the previous ones
+        clock.advance(Duration::microseconds(60 * 1000 * 1000));
+        repo.upstream_oauth_link()
+            .set_tokens(&clock, &link, "new-access-token".to_owned(), None, None)
+            .await
+            .unwrap();
+        let new_tokens = repo
+            .upstream_oauth_link()
+            .tokens(&link)
+            .await
+            .unwrap()
+            .expect("tokens to be found in database");
+        assert_eq!(new_tokens.encrypted_access_token, "new-access-token");
+        assert_eq!(new_tokens.encrypted_refresh_token, None);
+        assert_eq!(new_tokens.access_token_expires_at, None);
+        assert_eq!(new_tokens.created_at, tokens.created_at);
+        assert_eq!(new_tokens.updated_at, clock.now());
 
         let session = repo
             .upstream_oauth_session()
-            .complete_with_link(&clock, session, &link, None, None, None)
+            .complete_with_link(&clock, session, &link, None, None, None, None)
             .await
             .unwrap();
         // Reload the session
@@ -166,6 +268,15 @@ mod tests {
         assert!(!session.is_consumed());
         assert_eq!(session.link_id(), Some(link.id));
 
+        // It should be the latest session completed with this link
+        let latest_session = repo
+            .upstream_oauth_session()
+            .find_latest_for_link(&link)
+            .await
+            .unwrap()
+            .expect("session to be found in the database");
+        assert_eq!(latest_session.id, session.id);
+
         let session = repo
             .upstream_oauth_session()
             .consume(&clock, session)
@@ -312,6 +423,9 @@ mod tests {
                         fetch_userinfo: false,
                         userinfo_signed_response_alg: None,
                         token_endpoint_signing_alg: None,
+                        token_endpoint_signing_key_id: None,
+                        tls_client_certificate: None,
+                        encrypted_tls_client_key: None,
                         id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
                         client_id,
                         encrypted_client_secret: None,
@@ -325,6 +439,11 @@ mod tests {
                         response_mode: None,
                         additional_authorization_parameters: Vec::new(),
                         forward_login_hint: false,
+                        forward_prompt: false,
+                        forward_acr_values: false,
+                        forward_ui_locales: false,
+                        store_tokens: false,
+                        deprovisioning: None,
                         ui_order: 0,
                     },
                 )
@@ -423,4 +542,68 @@ mod tests {
                 .is_empty()
         );
     }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_cached_document_repository(pool: PgPool) {
+        let clock = MockClock::default();
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+
+        let issuer = "https://example.com/";
+
+        // Nothing is cached at the start
+        let document = repo
+            .upstream_oauth_cached_document()
+            .lookup(UpstreamOAuthCachedDocumentKind::Discovery, issuer)
+            .await
+            .unwrap();
+        assert!(document.is_none());
+
+        let saved = repo
+            .upstream_oauth_cached_document()
+            .save(
+                &clock,
+                UpstreamOAuthCachedDocumentKind::Discovery,
+                issuer,
+                serde_json::json!({"issuer": issuer}),
+            )
+            .await
+            .unwrap();
+
+        let document = repo
+            .upstream_oauth_cached_document()
+            .lookup(UpstreamOAuthCachedDocumentKind::Discovery, issuer)
+            .await
+            .unwrap()
+            .expect("document to be cached");
+        assert_eq!(document, saved);
+
+        // Documents of another kind are kept separately
+        let document = repo
+            .upstream_oauth_cached_document()
+            .lookup(UpstreamOAuthCachedDocumentKind::Jwks, issuer)
+            .await
+            .unwrap();
+        assert!(document.is_none());
+
+        // Saving it again replaces the previous document
+        clock.advance(Duration::minutes(10));
+        repo.upstream_oauth_cached_document()
+            .save(
+                &clock,
+                UpstreamOAuthCachedDocumentKind::Discovery,
+                issuer,
+                serde_json::json!({"issuer": issuer, "jwks_uri": "https://example.com/jwks"}),
+            )
+            .await
+            .unwrap();
+
+        let document = repo
+            .upstream_oauth_cached_document()
+            .lookup(UpstreamOAuthCachedDocumentKind::Discovery, issuer)
+            .await
+            .unwrap()
+            .expect("document to be cached");
+        assert_eq!(document.fetched_at, clock.now());
+        assert_eq!(document.document["jwks_uri"], "https://example.com/jwks");
+    }
 }
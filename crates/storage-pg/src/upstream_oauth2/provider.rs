@@ -6,7 +6,9 @@
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use mas_data_model::{UpstreamOAuthProvider, UpstreamOAuthProviderClaimsImports};
+use mas_data_model::{
+    UpstreamOAuthProvider, UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderDeprovisioning,
+};
 use mas_storage::{
     Clock, Page, Pagination,
     upstream_oauth2::{
@@ -56,6 +58,9 @@ struct ProviderLookup {
     encrypted_client_secret: Option<String>,
     token_endpoint_signing_alg: Option<String>,
     token_endpoint_auth_method: String,
+    token_endpoint_signing_key_id: Option<String>,
+    tls_client_certificate: Option<String>,
+    encrypted_tls_client_key: Option<String>,
     id_token_signed_response_alg: String,
     fetch_userinfo: bool,
     userinfo_signed_response_alg: Option<String>,
@@ -71,6 +76,11 @@ struct ProviderLookup {
     response_mode: Option<String>,
     additional_parameters: Option<Json<Vec<(String, String)>>>,
     forward_login_hint: bool,
+    forward_prompt: bool,
+    forward_acr_values: bool,
+    forward_ui_locales: bool,
+    store_tokens: bool,
+    deprovisioning: Option<Json<UpstreamOAuthProviderDeprovisioning>>,
 }
 
 impl TryFrom<ProviderLookup> for UpstreamOAuthProvider {
@@ -204,6 +214,9 @@ impl TryFrom<ProviderLookup> for UpstreamOAuthProvider {
             encrypted_client_secret: value.encrypted_client_secret,
             token_endpoint_auth_method,
             token_endpoint_signing_alg,
+            token_endpoint_signing_key_id: value.token_endpoint_signing_key_id,
+            tls_client_certificate: value.tls_client_certificate,
+            encrypted_tls_client_key: value.encrypted_tls_client_key,
             id_token_signed_response_alg,
             fetch_userinfo: value.fetch_userinfo,
             userinfo_signed_response_alg,
@@ -219,6 +232,11 @@ impl TryFrom<ProviderLookup> for UpstreamOAuthProvider {
             response_mode,
             additional_authorization_parameters,
             forward_login_hint: value.forward_login_hint,
+            forward_prompt: value.forward_prompt,
+            forward_acr_values: value.forward_acr_values,
+            forward_ui_locales: value.forward_ui_locales,
+            store_tokens: value.store_tokens,
+            deprovisioning: value.deprovisioning.map(|Json(x)| x),
         })
     }
 }
@@ -263,6 +281,9 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                     encrypted_client_secret,
                     token_endpoint_signing_alg,
                     token_endpoint_auth_method,
+                    token_endpoint_signing_key_id,
+                    tls_client_certificate,
+                    encrypted_tls_client_key,
                     id_token_signed_response_alg,
                     fetch_userinfo,
                     userinfo_signed_response_alg,
@@ -277,7 +298,12 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                     pkce_mode,
                     response_mode,
                     additional_parameters as "additional_parameters: Json<Vec<(String, String)>>",
-                    forward_login_hint
+                    forward_login_hint,
+                    forward_prompt,
+                    forward_acr_values,
+                    forward_ui_locales,
+                    store_tokens,
+                    deprovisioning as "deprovisioning: Json<UpstreamOAuthProviderDeprovisioning>"
                 FROM upstream_oauth_providers
                 WHERE upstream_oauth_provider_id = $1
             "#,
@@ -331,6 +357,9 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                 userinfo_signed_response_alg,
                 client_id,
                 encrypted_client_secret,
+                token_endpoint_signing_key_id,
+                tls_client_certificate,
+                encrypted_tls_client_key,
                 claims_imports,
                 authorization_endpoint_override,
                 token_endpoint_override,
@@ -340,9 +369,15 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                 pkce_mode,
                 response_mode,
                 forward_login_hint,
+                forward_prompt,
+                forward_acr_values,
+                forward_ui_locales,
+                store_tokens,
+                deprovisioning,
                 created_at
             ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
-                      $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22)
+                      $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
+                      $21, $22, $23, $24, $25, $26, $27, $28, $29, $30)
         "#,
             Uuid::from(id),
             params.issuer.as_deref(),
@@ -362,6 +397,9 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                 .map(ToString::to_string),
             &params.client_id,
             params.encrypted_client_secret.as_deref(),
+            params.token_endpoint_signing_key_id.as_deref(),
+            params.tls_client_certificate.as_deref(),
+            params.encrypted_tls_client_key.as_deref(),
             Json(&params.claims_imports) as _,
             params
                 .authorization_endpoint_override
@@ -380,6 +418,11 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
             params.pkce_mode.as_str(),
             params.response_mode.as_ref().map(ToString::to_string),
             params.forward_login_hint,
+            params.forward_prompt,
+            params.forward_acr_values,
+            params.forward_ui_locales,
+            params.store_tokens,
+            params.deprovisioning.as_ref().map(Json) as _,
             created_at,
         )
         .traced()
@@ -396,6 +439,9 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
             encrypted_client_secret: params.encrypted_client_secret,
             token_endpoint_signing_alg: params.token_endpoint_signing_alg,
             token_endpoint_auth_method: params.token_endpoint_auth_method,
+            token_endpoint_signing_key_id: params.token_endpoint_signing_key_id,
+            tls_client_certificate: params.tls_client_certificate,
+            encrypted_tls_client_key: params.encrypted_tls_client_key,
             id_token_signed_response_alg: params.id_token_signed_response_alg,
             fetch_userinfo: params.fetch_userinfo,
             userinfo_signed_response_alg: params.userinfo_signed_response_alg,
@@ -411,6 +457,11 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
             response_mode: params.response_mode,
             additional_authorization_parameters: params.additional_authorization_parameters,
             forward_login_hint: params.forward_login_hint,
+            forward_prompt: params.forward_prompt,
+            forward_acr_values: params.forward_acr_values,
+            forward_ui_locales: params.forward_ui_locales,
+            store_tokens: params.store_tokens,
+            deprovisioning: params.deprovisioning,
         })
     }
 
@@ -514,6 +565,9 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                     userinfo_signed_response_alg,
                     client_id,
                     encrypted_client_secret,
+                    token_endpoint_signing_key_id,
+                    tls_client_certificate,
+                    encrypted_tls_client_key,
                     claims_imports,
                     authorization_endpoint_override,
                     token_endpoint_override,
@@ -524,11 +578,17 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                     response_mode,
                     additional_parameters,
                     forward_login_hint,
+                    forward_prompt,
+                    forward_acr_values,
+                    forward_ui_locales,
+                    store_tokens,
+                    deprovisioning,
                     ui_order,
                     created_at
                 ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11,
                           $12, $13, $14, $15, $16, $17, $18, $19, $20,
-                          $21, $22, $23, $24)
+                          $21, $22, $23, $24, $25, $26, $27, $28, $29, $30,
+                          $31, $32)
                 ON CONFLICT (upstream_oauth_provider_id)
                     DO UPDATE
                     SET
@@ -544,6 +604,9 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                         disabled_at = NULL,
                         client_id = EXCLUDED.client_id,
                         encrypted_client_secret = EXCLUDED.encrypted_client_secret,
+                        token_endpoint_signing_key_id = EXCLUDED.token_endpoint_signing_key_id,
+                        tls_client_certificate = EXCLUDED.tls_client_certificate,
+                        encrypted_tls_client_key = EXCLUDED.encrypted_tls_client_key,
                         claims_imports = EXCLUDED.claims_imports,
                         authorization_endpoint_override = EXCLUDED.authorization_endpoint_override,
                         token_endpoint_override = EXCLUDED.token_endpoint_override,
@@ -554,6 +617,11 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                         response_mode = EXCLUDED.response_mode,
                         additional_parameters = EXCLUDED.additional_parameters,
                         forward_login_hint = EXCLUDED.forward_login_hint,
+                        forward_prompt = EXCLUDED.forward_prompt,
+                        forward_acr_values = EXCLUDED.forward_acr_values,
+                        forward_ui_locales = EXCLUDED.forward_ui_locales,
+                        store_tokens = EXCLUDED.store_tokens,
+                        deprovisioning = EXCLUDED.deprovisioning,
                         ui_order = EXCLUDED.ui_order
                 RETURNING created_at
             "#,
@@ -575,6 +643,9 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                 .map(ToString::to_string),
             &params.client_id,
             params.encrypted_client_secret.as_deref(),
+            params.token_endpoint_signing_key_id.as_deref(),
+            params.tls_client_certificate.as_deref(),
+            params.encrypted_tls_client_key.as_deref(),
             Json(&params.claims_imports) as _,
             params
                 .authorization_endpoint_override
@@ -594,6 +665,11 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
             params.response_mode.as_ref().map(ToString::to_string),
             Json(&params.additional_authorization_parameters) as _,
             params.forward_login_hint,
+            params.forward_prompt,
+            params.forward_acr_values,
+            params.forward_ui_locales,
+            params.store_tokens,
+            params.deprovisioning.as_ref().map(Json) as _,
             params.ui_order,
             created_at,
         )
@@ -611,6 +687,9 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
             encrypted_client_secret: params.encrypted_client_secret,
             token_endpoint_signing_alg: params.token_endpoint_signing_alg,
             token_endpoint_auth_method: params.token_endpoint_auth_method,
+            token_endpoint_signing_key_id: params.token_endpoint_signing_key_id,
+            tls_client_certificate: params.tls_client_certificate,
+            encrypted_tls_client_key: params.encrypted_tls_client_key,
             id_token_signed_response_alg: params.id_token_signed_response_alg,
             fetch_userinfo: params.fetch_userinfo,
             userinfo_signed_response_alg: params.userinfo_signed_response_alg,
@@ -626,6 +705,11 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
             response_mode: params.response_mode,
             additional_authorization_parameters: params.additional_authorization_parameters,
             forward_login_hint: params.forward_login_hint,
+            forward_prompt: params.forward_prompt,
+            forward_acr_values: params.forward_acr_values,
+            forward_ui_locales: params.forward_ui_locales,
+            store_tokens: params.store_tokens,
+            deprovisioning: params.deprovisioning,
         })
     }
 
@@ -738,6 +822,27 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                 )),
                 ProviderLookupIden::TokenEndpointAuthMethod,
             )
+            .expr_as(
+                Expr::col((
+                    UpstreamOAuthProviders::Table,
+                    UpstreamOAuthProviders::TokenEndpointSigningKeyId,
+                )),
+                ProviderLookupIden::TokenEndpointSigningKeyId,
+            )
+            .expr_as(
+                Expr::col((
+                    UpstreamOAuthProviders::Table,
+                    UpstreamOAuthProviders::TlsClientCertificate,
+                )),
+                ProviderLookupIden::TlsClientCertificate,
+            )
+            .expr_as(
+                Expr::col((
+                    UpstreamOAuthProviders::Table,
+                    UpstreamOAuthProviders::EncryptedTlsClientKey,
+                )),
+                ProviderLookupIden::EncryptedTlsClientKey,
+            )
             .expr_as(
                 Expr::col((
                     UpstreamOAuthProviders::Table,
@@ -843,6 +948,41 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                 )),
                 ProviderLookupIden::ForwardLoginHint,
             )
+            .expr_as(
+                Expr::col((
+                    UpstreamOAuthProviders::Table,
+                    UpstreamOAuthProviders::ForwardPrompt,
+                )),
+                ProviderLookupIden::ForwardPrompt,
+            )
+            .expr_as(
+                Expr::col((
+                    UpstreamOAuthProviders::Table,
+                    UpstreamOAuthProviders::ForwardAcrValues,
+                )),
+                ProviderLookupIden::ForwardAcrValues,
+            )
+            .expr_as(
+                Expr::col((
+                    UpstreamOAuthProviders::Table,
+                    UpstreamOAuthProviders::ForwardUiLocales,
+                )),
+                ProviderLookupIden::ForwardUiLocales,
+            )
+            .expr_as(
+                Expr::col((
+                    UpstreamOAuthProviders::Table,
+                    UpstreamOAuthProviders::StoreTokens,
+                )),
+                ProviderLookupIden::StoreTokens,
+            )
+            .expr_as(
+                Expr::col((
+                    UpstreamOAuthProviders::Table,
+                    UpstreamOAuthProviders::Deprovisioning,
+                )),
+                ProviderLookupIden::Deprovisioning,
+            )
             .from(UpstreamOAuthProviders::Table)
             .apply_filter(filter)
             .generate_pagination(
@@ -922,6 +1062,9 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                     encrypted_client_secret,
                     token_endpoint_signing_alg,
                     token_endpoint_auth_method,
+                    token_endpoint_signing_key_id,
+                    tls_client_certificate,
+                    encrypted_tls_client_key,
                     id_token_signed_response_alg,
                     fetch_userinfo,
                     userinfo_signed_response_alg,
@@ -936,7 +1079,12 @@ impl UpstreamOAuthProviderRepository for PgUpstreamOAuthProviderRepository<'_> {
                     pkce_mode,
                     response_mode,
                     additional_parameters as "additional_parameters: Json<Vec<(String, String)>>",
-                    forward_login_hint
+                    forward_login_hint,
+                    forward_prompt,
+                    forward_acr_values,
+                    forward_ui_locales,
+                    store_tokens,
+                    deprovisioning as "deprovisioning: Json<UpstreamOAuthProviderDeprovisioning>"
                 FROM upstream_oauth_providers
                 WHERE disabled_at IS NULL
                 ORDER BY ui_order ASC, upstream_oauth_provider_id ASC
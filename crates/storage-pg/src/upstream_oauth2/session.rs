@@ -10,13 +10,20 @@ use mas_data_model::{
     UpstreamOAuthAuthorizationSession, UpstreamOAuthAuthorizationSessionState, UpstreamOAuthLink,
     UpstreamOAuthProvider,
 };
-use mas_storage::{Clock, upstream_oauth2::UpstreamOAuthSessionRepository};
+use mas_storage::{
+    Clock,
+    upstream_oauth2::{UpstreamOAuthSessionFilter, UpstreamOAuthSessionRepository},
+};
 use rand::RngCore;
+use sea_query::{Expr, extension::postgres::PgExpr};
 use sqlx::PgConnection;
 use ulid::Ulid;
 use uuid::Uuid;
 
-use crate::{DatabaseError, DatabaseInconsistencyError, tracing::ExecuteExt};
+use crate::{
+    DatabaseError, DatabaseInconsistencyError, filter::Filter,
+    iden::UpstreamOAuthAuthorizationSessions, tracing::ExecuteExt,
+};
 
 /// An implementation of [`UpstreamOAuthSessionRepository`] for a PostgreSQL
 /// connection
@@ -40,6 +47,7 @@ struct SessionLookup {
     code_challenge_verifier: Option<String>,
     nonce: Option<String>,
     id_token: Option<String>,
+    id_token_claims: Option<serde_json::Value>,
     userinfo: Option<serde_json::Value>,
     created_at: DateTime<Utc>,
     completed_at: Option<DateTime<Utc>>,
@@ -56,18 +64,20 @@ impl TryFrom<SessionLookup> for UpstreamOAuthAuthorizationSession {
         let state = match (
             value.upstream_oauth_link_id,
             value.id_token,
+            value.id_token_claims,
             value.extra_callback_parameters,
             value.userinfo,
             value.completed_at,
             value.consumed_at,
             value.unlinked_at,
         ) {
-            (None, None, None, None, None, None, None) => {
+            (None, None, None, None, None, None, None, None) => {
                 UpstreamOAuthAuthorizationSessionState::Pending
             }
             (
                 Some(link_id),
                 id_token,
+                id_token_claims,
                 extra_callback_parameters,
                 userinfo,
                 Some(completed_at),
@@ -77,12 +87,14 @@ impl TryFrom<SessionLookup> for UpstreamOAuthAuthorizationSession {
                 completed_at,
                 link_id: link_id.into(),
                 id_token,
+                id_token_claims,
                 extra_callback_parameters,
                 userinfo,
             },
             (
                 Some(link_id),
                 id_token,
+                id_token_claims,
                 extra_callback_parameters,
                 userinfo,
                 Some(completed_at),
@@ -92,11 +104,12 @@ impl TryFrom<SessionLookup> for UpstreamOAuthAuthorizationSession {
                 completed_at,
                 link_id: link_id.into(),
                 id_token,
+                id_token_claims,
                 extra_callback_parameters,
                 userinfo,
                 consumed_at,
             },
-            (_, id_token, _, _, Some(completed_at), consumed_at, Some(unlinked_at)) => {
+            (_, id_token, _, _, _, Some(completed_at), consumed_at, Some(unlinked_at)) => {
                 UpstreamOAuthAuthorizationSessionState::Unlinked {
                     completed_at,
                     id_token,
@@ -124,6 +137,35 @@ impl TryFrom<SessionLookup> for UpstreamOAuthAuthorizationSession {
     }
 }
 
+impl Filter for UpstreamOAuthSessionFilter<'_> {
+    fn generate_condition(&self, _has_joins: bool) -> impl sea_query::IntoCondition {
+        sea_query::Condition::all()
+            .add_option(self.provider().map(|provider| {
+                Expr::col((
+                    UpstreamOAuthAuthorizationSessions::Table,
+                    UpstreamOAuthAuthorizationSessions::UpstreamOAuthProviderId,
+                ))
+                .eq(Uuid::from(provider.id))
+            }))
+            .add_option(self.sub_claim().map(|sub| {
+                Expr::col((
+                    UpstreamOAuthAuthorizationSessions::Table,
+                    UpstreamOAuthAuthorizationSessions::IdTokenClaims,
+                ))
+                .cast_json_field("sub")
+                .eq(sub)
+            }))
+            .add_option(self.sid_claim().map(|sid| {
+                Expr::col((
+                    UpstreamOAuthAuthorizationSessions::Table,
+                    UpstreamOAuthAuthorizationSessions::IdTokenClaims,
+                ))
+                .cast_json_field("sid")
+                .eq(sid)
+            }))
+    }
+}
+
 #[async_trait]
 impl UpstreamOAuthSessionRepository for PgUpstreamOAuthSessionRepository<'_> {
     type Error = DatabaseError;
@@ -152,6 +194,7 @@ impl UpstreamOAuthSessionRepository for PgUpstreamOAuthSessionRepository<'_> {
                     code_challenge_verifier,
                     nonce,
                     id_token,
+                    id_token_claims,
                     extra_callback_parameters,
                     userinfo,
                     created_at,
@@ -172,6 +215,54 @@ impl UpstreamOAuthSessionRepository for PgUpstreamOAuthSessionRepository<'_> {
         Ok(Some(res.try_into()?))
     }
 
+    #[tracing::instrument(
+        name = "db.upstream_oauth_authorization_session.find_latest_for_link",
+        skip_all,
+        fields(
+            db.query.text,
+            %upstream_oauth_link.id,
+        ),
+        err,
+    )]
+    async fn find_latest_for_link(
+        &mut self,
+        upstream_oauth_link: &UpstreamOAuthLink,
+    ) -> Result<Option<UpstreamOAuthAuthorizationSession>, Self::Error> {
+        let res = sqlx::query_as!(
+            SessionLookup,
+            r#"
+                SELECT
+                    upstream_oauth_authorization_session_id,
+                    upstream_oauth_provider_id,
+                    upstream_oauth_link_id,
+                    state,
+                    code_challenge_verifier,
+                    nonce,
+                    id_token,
+                    id_token_claims,
+                    extra_callback_parameters,
+                    userinfo,
+                    created_at,
+                    completed_at,
+                    consumed_at,
+                    unlinked_at
+                FROM upstream_oauth_authorization_sessions
+                WHERE upstream_oauth_link_id = $1
+                  AND completed_at IS NOT NULL
+                ORDER BY completed_at DESC
+                LIMIT 1
+            "#,
+            Uuid::from(upstream_oauth_link.id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        let Some(res) = res else { return Ok(None) };
+
+        Ok(Some(res.try_into()?))
+    }
+
     #[tracing::instrument(
         name = "db.upstream_oauth_authorization_session.add",
         skip_all,
@@ -253,6 +344,7 @@ impl UpstreamOAuthSessionRepository for PgUpstreamOAuthSessionRepository<'_> {
         upstream_oauth_authorization_session: UpstreamOAuthAuthorizationSession,
         upstream_oauth_link: &UpstreamOAuthLink,
         id_token: Option<String>,
+        id_token_claims: Option<serde_json::Value>,
         extra_callback_parameters: Option<serde_json::Value>,
         userinfo: Option<serde_json::Value>,
     ) -> Result<UpstreamOAuthAuthorizationSession, Self::Error> {
@@ -264,13 +356,15 @@ impl UpstreamOAuthSessionRepository for PgUpstreamOAuthSessionRepository<'_> {
                 SET upstream_oauth_link_id = $1,
                     completed_at = $2,
                     id_token = $3,
-                    extra_callback_parameters = $4,
-                    userinfo = $5
-                WHERE upstream_oauth_authorization_session_id = $6
+                    id_token_claims = $4,
+                    extra_callback_parameters = $5,
+                    userinfo = $6
+                WHERE upstream_oauth_authorization_session_id = $7
             "#,
             Uuid::from(upstream_oauth_link.id),
             completed_at,
             id_token,
+            id_token_claims,
             extra_callback_parameters,
             userinfo,
             Uuid::from(upstream_oauth_authorization_session.id),
@@ -284,6 +378,7 @@ impl UpstreamOAuthSessionRepository for PgUpstreamOAuthSessionRepository<'_> {
                 completed_at,
                 upstream_oauth_link,
                 id_token,
+                id_token_claims,
                 extra_callback_parameters,
                 userinfo,
             )
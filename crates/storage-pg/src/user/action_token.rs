@@ -0,0 +1,251 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use mas_data_model::{User, UserAction, UserActionToken};
+use mas_storage::{Clock, user::UserActionTokenRepository};
+use rand::RngCore;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, DatabaseInconsistencyError, tracing::ExecuteExt};
+
+/// An implementation of [`UserActionTokenRepository`] for a PostgreSQL
+/// connection
+pub struct PgUserActionTokenRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgUserActionTokenRepository<'c> {
+    /// Create a new [`PgUserActionTokenRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct UserActionTokenLookup {
+    user_action_token_id: Uuid,
+    user_id: Uuid,
+    token: String,
+    action: String,
+    user_session_id: Option<Uuid>,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    consumed_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<UserActionTokenLookup> for UserActionToken {
+    type Error = DatabaseInconsistencyError;
+
+    fn try_from(value: UserActionTokenLookup) -> Result<Self, Self::Error> {
+        let id = Ulid::from(value.user_action_token_id);
+        let action = match (value.action.as_str(), value.user_session_id) {
+            ("end_browser_session", Some(browser_session_id)) => UserAction::EndBrowserSession {
+                browser_session_id: browser_session_id.into(),
+            },
+            ("end_browser_session", None) => {
+                return Err(DatabaseInconsistencyError::on("user_action_tokens")
+                    .column("user_session_id")
+                    .row(id));
+            }
+            _ => {
+                return Err(DatabaseInconsistencyError::on("user_action_tokens")
+                    .column("action")
+                    .row(id));
+            }
+        };
+
+        Ok(UserActionToken {
+            id,
+            user_id: value.user_id.into(),
+            token: value.token,
+            action,
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            consumed_at: value.consumed_at,
+        })
+    }
+}
+
+#[async_trait]
+impl UserActionTokenRepository for PgUserActionTokenRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.user_action_token.find_by_token",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn find_by_token(&mut self, token: &str) -> Result<Option<UserActionToken>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserActionTokenLookup,
+            r#"
+                SELECT user_action_token_id
+                     , user_id
+                     , token
+                     , action
+                     , user_session_id
+                     , created_at
+                     , expires_at
+                     , consumed_at
+                FROM user_action_tokens
+                WHERE token = $1
+            "#,
+            token,
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        let Some(res) = res else { return Ok(None) };
+
+        Ok(Some(res.try_into()?))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_action_token.add",
+        skip_all,
+        fields(
+            db.query.text,
+            user_action_token.id,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        action: UserAction,
+        token: String,
+        expires_in: Duration,
+    ) -> Result<UserActionToken, Self::Error> {
+        let created_at = clock.now();
+        let expires_at = created_at + expires_in;
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_action_token.id", tracing::field::display(id));
+
+        let (action_name, user_session_id) = match action {
+            UserAction::EndBrowserSession { browser_session_id } => {
+                ("end_browser_session", Some(Uuid::from(browser_session_id)))
+            }
+        };
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_action_tokens
+                    ( user_action_token_id
+                    , user_id
+                    , token
+                    , action
+                    , user_session_id
+                    , created_at
+                    , expires_at
+                    )
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            &token,
+            action_name,
+            user_session_id,
+            created_at,
+            expires_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(UserActionToken {
+            id,
+            user_id: user.id,
+            token,
+            action,
+            created_at,
+            expires_at,
+            consumed_at: None,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.user_action_token.consume",
+        skip_all,
+        fields(
+            db.query.text,
+            user_action_token.id = %token.id,
+            user.id = %token.user_id,
+        ),
+        err,
+    )]
+    async fn consume(
+        &mut self,
+        clock: &dyn Clock,
+        token: UserActionToken,
+    ) -> Result<UserActionToken, Self::Error> {
+        let consumed_at = clock.now();
+        let token = token
+            .consume(consumed_at)
+            .map_err(DatabaseError::to_invalid_operation)?;
+
+        // Only consume the token if it wasn't consumed concurrently
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_action_tokens
+                SET consumed_at = $2
+                WHERE user_action_token_id = $1
+                  AND consumed_at IS NULL
+            "#,
+            Uuid::from(token.id),
+            consumed_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        Ok(token)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_action_token.cleanup_expired",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn cleanup_expired(
+        &mut self,
+        expired_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM user_action_tokens
+                WHERE user_action_token_id IN (
+                    SELECT user_action_token_id
+                    FROM user_action_tokens
+                    WHERE expires_at < $1
+                    LIMIT $2
+                )
+            "#,
+            expired_before,
+            i64::try_from(limit).unwrap_or(i64::MAX),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(res.rows_affected().try_into().unwrap_or(usize::MAX))
+    }
+}
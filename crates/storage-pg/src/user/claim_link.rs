@@ -0,0 +1,433 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::net::IpAddr;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{Session, User, UserClaimLink};
+use mas_storage::{
+    Clock, Page, Pagination,
+    user::{UserClaimLinkFilter, UserClaimLinkRepository, UserClaimLinkState},
+};
+use rand::RngCore;
+use sea_query::{Condition, Expr, PostgresQueryBuilder, Query, enum_def};
+use sea_query_binder::SqlxBinder;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{
+    errors::DatabaseError,
+    filter::{Filter, StatementExt},
+    iden::UserClaimLinks,
+    pagination::QueryBuilderExt,
+    tracing::ExecuteExt,
+};
+
+/// An implementation of [`UserClaimLinkRepository`] for a PostgreSQL
+/// connection
+pub struct PgUserClaimLinkRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgUserClaimLinkRepository<'c> {
+    /// Create a new [`PgUserClaimLinkRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+#[enum_def]
+struct UserClaimLinkLookup {
+    user_claim_link_id: Uuid,
+    user_id: Uuid,
+    token: String,
+    email: Option<String>,
+    created_by_oauth2_session_id: Option<Uuid>,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    claimed_at: Option<DateTime<Utc>>,
+    claimed_ip_address: Option<IpAddr>,
+    claimed_user_agent: Option<String>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserClaimLinkLookup> for UserClaimLink {
+    fn from(value: UserClaimLinkLookup) -> Self {
+        Self {
+            id: value.user_claim_link_id.into(),
+            user_id: value.user_id.into(),
+            token: value.token,
+            email: value.email,
+            created_by_oauth2_session_id: value.created_by_oauth2_session_id.map(Ulid::from),
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            claimed_at: value.claimed_at,
+            claimed_ip_address: value.claimed_ip_address,
+            claimed_user_agent: value.claimed_user_agent,
+            revoked_at: value.revoked_at,
+        }
+    }
+}
+
+impl Filter for UserClaimLinkFilter<'_> {
+    fn generate_condition(&self, _has_joins: bool) -> impl sea_query::IntoCondition {
+        let claimed_at = Expr::col((UserClaimLinks::Table, UserClaimLinks::ClaimedAt));
+        let revoked_at = Expr::col((UserClaimLinks::Table, UserClaimLinks::RevokedAt));
+        let expires_at = Expr::col((UserClaimLinks::Table, UserClaimLinks::ExpiresAt));
+
+        Condition::all()
+            .add_option(self.user().map(|user| {
+                Expr::col((UserClaimLinks::Table, UserClaimLinks::UserId)).eq(Uuid::from(user.id))
+            }))
+            .add_option(self.state().map(|state| {
+                match state {
+                    UserClaimLinkState::Active => Condition::all()
+                        .add(claimed_at.is_null())
+                        .add(revoked_at.is_null())
+                        .add(expires_at.gt(Expr::val(self.now()))),
+                    UserClaimLinkState::Claimed => Condition::all().add(claimed_at.is_not_null()),
+                    UserClaimLinkState::Revoked => Condition::all()
+                        .add(claimed_at.is_null())
+                        .add(revoked_at.is_not_null()),
+                    UserClaimLinkState::Expired => Condition::all()
+                        .add(claimed_at.is_null())
+                        .add(revoked_at.is_null())
+                        .add(expires_at.lte(Expr::val(self.now()))),
+                }
+            }))
+    }
+}
+
+#[async_trait]
+impl UserClaimLinkRepository for PgUserClaimLinkRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.user_claim_link.lookup",
+        skip_all,
+        fields(
+            db.query.text,
+            user_claim_link.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserClaimLink>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserClaimLinkLookup,
+            r#"
+                SELECT user_claim_link_id
+                     , user_id
+                     , token
+                     , email
+                     , created_by_oauth2_session_id
+                     , created_at
+                     , expires_at
+                     , claimed_at
+                     , claimed_ip_address as "claimed_ip_address: IpAddr"
+                     , claimed_user_agent
+                     , revoked_at
+                FROM user_claim_links
+                WHERE user_claim_link_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_claim_link.find_by_token",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn find_by_token(&mut self, token: &str) -> Result<Option<UserClaimLink>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserClaimLinkLookup,
+            r#"
+                SELECT user_claim_link_id
+                     , user_id
+                     , token
+                     , email
+                     , created_by_oauth2_session_id
+                     , created_at
+                     , expires_at
+                     , claimed_at
+                     , claimed_ip_address as "claimed_ip_address: IpAddr"
+                     , claimed_user_agent
+                     , revoked_at
+                FROM user_claim_links
+                WHERE token = $1
+            "#,
+            token,
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_claim_link.add",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_claim_link.id,
+        ),
+        err,
+    )]
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        token: String,
+        expires_at: DateTime<Utc>,
+        email: Option<String>,
+        created_by: Option<&Session>,
+    ) -> Result<UserClaimLink, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_claim_link.id", tracing::field::display(id));
+
+        let created_by_oauth2_session_id = created_by.map(|session| session.id);
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_claim_links
+                    ( user_claim_link_id
+                    , user_id
+                    , token
+                    , email
+                    , created_by_oauth2_session_id
+                    , created_at
+                    , expires_at
+                    )
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            &token,
+            email.as_deref(),
+            created_by_oauth2_session_id.map(Uuid::from),
+            created_at,
+            expires_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(UserClaimLink {
+            id,
+            user_id: user.id,
+            token,
+            email,
+            created_by_oauth2_session_id,
+            created_at,
+            expires_at,
+            claimed_at: None,
+            claimed_ip_address: None,
+            claimed_user_agent: None,
+            revoked_at: None,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.user_claim_link.claim",
+        skip_all,
+        fields(
+            db.query.text,
+            user_claim_link.id = %link.id,
+        ),
+        err,
+    )]
+    async fn claim(
+        &mut self,
+        clock: &dyn Clock,
+        mut link: UserClaimLink,
+        ip_address: Option<IpAddr>,
+        user_agent: Option<String>,
+    ) -> Result<UserClaimLink, Self::Error> {
+        let claimed_at = clock.now();
+
+        // Only claim the link if it wasn't claimed or revoked concurrently
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_claim_links
+                SET claimed_at = $2
+                  , claimed_ip_address = $3
+                  , claimed_user_agent = $4
+                WHERE user_claim_link_id = $1
+                  AND claimed_at IS NULL
+                  AND revoked_at IS NULL
+            "#,
+            Uuid::from(link.id),
+            claimed_at,
+            ip_address as Option<IpAddr>,
+            user_agent.as_deref(),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        link.claimed_at = Some(claimed_at);
+        link.claimed_ip_address = ip_address;
+        link.claimed_user_agent = user_agent;
+
+        Ok(link)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_claim_link.revoke",
+        skip_all,
+        fields(
+            db.query.text,
+            user_claim_link.id = %link.id,
+        ),
+        err,
+    )]
+    async fn revoke(
+        &mut self,
+        clock: &dyn Clock,
+        mut link: UserClaimLink,
+    ) -> Result<UserClaimLink, Self::Error> {
+        let revoked_at = clock.now();
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_claim_links
+                SET revoked_at = $2
+                WHERE user_claim_link_id = $1
+            "#,
+            Uuid::from(link.id),
+            revoked_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        link.revoked_at = Some(revoked_at);
+
+        Ok(link)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_claim_link.list",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn list(
+        &mut self,
+        filter: UserClaimLinkFilter<'_>,
+        pagination: Pagination,
+    ) -> Result<Page<UserClaimLink>, Self::Error> {
+        let (sql, arguments) = Query::select()
+            .expr_as(
+                Expr::col((UserClaimLinks::Table, UserClaimLinks::UserClaimLinkId)),
+                UserClaimLinkLookupIden::UserClaimLinkId,
+            )
+            .expr_as(
+                Expr::col((UserClaimLinks::Table, UserClaimLinks::UserId)),
+                UserClaimLinkLookupIden::UserId,
+            )
+            .expr_as(
+                Expr::col((UserClaimLinks::Table, UserClaimLinks::Token)),
+                UserClaimLinkLookupIden::Token,
+            )
+            .expr_as(
+                Expr::col((UserClaimLinks::Table, UserClaimLinks::Email)),
+                UserClaimLinkLookupIden::Email,
+            )
+            .expr_as(
+                Expr::col((
+                    UserClaimLinks::Table,
+                    UserClaimLinks::CreatedByOAuth2SessionId,
+                )),
+                UserClaimLinkLookupIden::CreatedByOauth2SessionId,
+            )
+            .expr_as(
+                Expr::col((UserClaimLinks::Table, UserClaimLinks::CreatedAt)),
+                UserClaimLinkLookupIden::CreatedAt,
+            )
+            .expr_as(
+                Expr::col((UserClaimLinks::Table, UserClaimLinks::ExpiresAt)),
+                UserClaimLinkLookupIden::ExpiresAt,
+            )
+            .expr_as(
+                Expr::col((UserClaimLinks::Table, UserClaimLinks::ClaimedAt)),
+                UserClaimLinkLookupIden::ClaimedAt,
+            )
+            .expr_as(
+                Expr::col((UserClaimLinks::Table, UserClaimLinks::ClaimedIpAddress)),
+                UserClaimLinkLookupIden::ClaimedIpAddress,
+            )
+            .expr_as(
+                Expr::col((UserClaimLinks::Table, UserClaimLinks::ClaimedUserAgent)),
+                UserClaimLinkLookupIden::ClaimedUserAgent,
+            )
+            .expr_as(
+                Expr::col((UserClaimLinks::Table, UserClaimLinks::RevokedAt)),
+                UserClaimLinkLookupIden::RevokedAt,
+            )
+            .from(UserClaimLinks::Table)
+            .apply_filter(filter)
+            .generate_pagination(
+                (UserClaimLinks::Table, UserClaimLinks::UserClaimLinkId),
+                pagination,
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        let edges: Vec<UserClaimLinkLookup> = sqlx::query_as_with(&sql, arguments)
+            .traced()
+            .fetch_all(&mut *self.conn)
+            .await?;
+
+        let page = pagination.process(edges).map(UserClaimLink::from);
+
+        Ok(page)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_claim_link.count",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn count(&mut self, filter: UserClaimLinkFilter<'_>) -> Result<usize, Self::Error> {
+        let (sql, arguments) = Query::select()
+            .expr(Expr::col((UserClaimLinks::Table, UserClaimLinks::UserClaimLinkId)).count())
+            .from(UserClaimLinks::Table)
+            .apply_filter(filter)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let count: i64 = sqlx::query_scalar_with(&sql, arguments)
+            .traced()
+            .fetch_one(&mut *self.conn)
+            .await?;
+
+        count
+            .try_into()
+            .map_err(DatabaseError::to_invalid_operation)
+    }
+}
@@ -0,0 +1,246 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{User, UserDeletion};
+use mas_storage::{Clock, user::UserDeletionRepository};
+use rand::RngCore;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, tracing::ExecuteExt};
+
+/// An implementation of [`UserDeletionRepository`] for a PostgreSQL connection
+pub struct PgUserDeletionRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgUserDeletionRepository<'c> {
+    /// Create a new [`PgUserDeletionRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct UserDeletionLookup {
+    user_deletion_id: Uuid,
+    user_id: Uuid,
+    hs_erase: bool,
+    created_at: DateTime<Utc>,
+    delete_at: DateTime<Utc>,
+    cancelled_at: Option<DateTime<Utc>>,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserDeletionLookup> for UserDeletion {
+    fn from(value: UserDeletionLookup) -> Self {
+        UserDeletion {
+            id: value.user_deletion_id.into(),
+            user_id: value.user_id.into(),
+            hs_erase: value.hs_erase,
+            created_at: value.created_at,
+            delete_at: value.delete_at,
+            cancelled_at: value.cancelled_at,
+            completed_at: value.completed_at,
+        }
+    }
+}
+
+#[async_trait]
+impl UserDeletionRepository for PgUserDeletionRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.user_deletion.lookup",
+        skip_all,
+        fields(
+            db.query.text,
+            user_deletion.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserDeletion>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserDeletionLookup,
+            r#"
+                SELECT user_deletion_id
+                     , user_id
+                     , hs_erase
+                     , created_at
+                     , delete_at
+                     , cancelled_at
+                     , completed_at
+                FROM user_deletions
+                WHERE user_deletion_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_deletion.find_pending",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn find_pending(&mut self, user: &User) -> Result<Option<UserDeletion>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserDeletionLookup,
+            r#"
+                SELECT user_deletion_id
+                     , user_id
+                     , hs_erase
+                     , created_at
+                     , delete_at
+                     , cancelled_at
+                     , completed_at
+                FROM user_deletions
+                WHERE user_id = $1
+                  AND cancelled_at IS NULL
+                  AND completed_at IS NULL
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_deletion.add",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_deletion.id,
+        ),
+        err,
+    )]
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        delete_at: DateTime<Utc>,
+        hs_erase: bool,
+    ) -> Result<UserDeletion, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_deletion.id", tracing::field::display(id));
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_deletions
+                    (user_deletion_id, user_id, hs_erase, created_at, delete_at)
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            hs_erase,
+            created_at,
+            delete_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(UserDeletion {
+            id,
+            user_id: user.id,
+            hs_erase,
+            created_at,
+            delete_at,
+            cancelled_at: None,
+            completed_at: None,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.user_deletion.cancel",
+        skip_all,
+        fields(
+            db.query.text,
+            %deletion.id,
+        ),
+        err,
+    )]
+    async fn cancel(
+        &mut self,
+        clock: &dyn Clock,
+        mut deletion: UserDeletion,
+    ) -> Result<UserDeletion, Self::Error> {
+        let cancelled_at = clock.now();
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_deletions
+                SET cancelled_at = $2
+                WHERE user_deletion_id = $1
+                  AND cancelled_at IS NULL
+                  AND completed_at IS NULL
+            "#,
+            Uuid::from(deletion.id),
+            cancelled_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        deletion.cancelled_at = Some(cancelled_at);
+        Ok(deletion)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_deletion.complete",
+        skip_all,
+        fields(
+            db.query.text,
+            %deletion.id,
+        ),
+        err,
+    )]
+    async fn complete(
+        &mut self,
+        clock: &dyn Clock,
+        mut deletion: UserDeletion,
+    ) -> Result<UserDeletion, Self::Error> {
+        let completed_at = clock.now();
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_deletions
+                SET completed_at = $2
+                WHERE user_deletion_id = $1
+                  AND cancelled_at IS NULL
+                  AND completed_at IS NULL
+            "#,
+            Uuid::from(deletion.id),
+            completed_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        deletion.completed_at = Some(completed_at);
+        Ok(deletion)
+    }
+}
@@ -66,6 +66,7 @@ struct UserEmailAuthenticationLookup {
     user_email_authentication_id: Uuid,
     user_session_id: Option<Uuid>,
     user_registration_id: Option<Uuid>,
+    user_id: Option<Uuid>,
     email: String,
     created_at: DateTime<Utc>,
     completed_at: Option<DateTime<Utc>>,
@@ -77,6 +78,7 @@ impl From<UserEmailAuthenticationLookup> for UserEmailAuthentication {
             id: value.user_email_authentication_id.into(),
             user_session_id: value.user_session_id.map(Ulid::from),
             user_registration_id: value.user_registration_id.map(Ulid::from),
+            user_id: value.user_id.map(Ulid::from),
             email: value.email,
             created_at: value.created_at,
             completed_at: value.completed_at,
@@ -260,6 +262,70 @@ impl UserEmailRepository for PgUserEmailRepository<'_> {
         Ok(res.into_iter().map(Into::into).collect())
     }
 
+    #[tracing::instrument(
+        name = "db.user_email.primary",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn primary(&mut self, user: &User) -> Result<Option<UserEmail>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserEmailLookup,
+            r#"
+                SELECT ue.user_email_id
+                     , ue.user_id
+                     , ue.email
+                     , ue.created_at
+                FROM user_emails ue
+                INNER JOIN users u USING (user_id)
+
+                WHERE ue.user_id = $1
+
+                ORDER BY ue.user_email_id = u.primary_user_email_id DESC NULLS LAST
+                       , ue.created_at ASC
+                LIMIT 1
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_email.set_as_primary",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_email.id,
+            %user_email.user_id,
+        ),
+        err,
+    )]
+    async fn set_as_primary(&mut self, user_email: &UserEmail) -> Result<(), Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE users
+                SET primary_user_email_id = $2
+                WHERE user_id = $1
+            "#,
+            Uuid::from(user_email.user_id),
+            Uuid::from(user_email.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        Ok(())
+    }
+
     #[tracing::instrument(
         name = "db.user_email.list",
         skip_all,
@@ -470,6 +536,7 @@ impl UserEmailRepository for PgUserEmailRepository<'_> {
             id,
             user_session_id: Some(session.id),
             user_registration_id: None,
+            user_id: None,
             email,
             created_at,
             completed_at: None,
@@ -522,6 +589,60 @@ impl UserEmailRepository for PgUserEmailRepository<'_> {
             id,
             user_session_id: None,
             user_registration_id: Some(user_registration.id),
+            user_id: None,
+            email,
+            created_at,
+            completed_at: None,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.user_email.add_authentication_for_login",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_email_authentication.id,
+            user_email_authentication.email = email,
+        ),
+        err,
+    )]
+    async fn add_authentication_for_login(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        email: String,
+        user: &User,
+    ) -> Result<UserEmailAuthentication, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current()
+            .record("user_email_authentication.id", tracing::field::display(id));
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_email_authentications
+                  ( user_email_authentication_id
+                  , user_id
+                  , email
+                  , created_at
+                  )
+                VALUES ($1, $2, $3, $4)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            &email,
+            created_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(UserEmailAuthentication {
+            id,
+            user_session_id: None,
+            user_registration_id: None,
+            user_id: Some(user.id),
             email,
             created_at,
             completed_at: None,
@@ -553,8 +674,6 @@ impl UserEmailRepository for PgUserEmailRepository<'_> {
         tracing::Span::current()
             .record("user_email_authentication_code.id", tracing::field::display(id));
 
-        // Always use "111111" as the code
-        let code = "111111".to_string();
         let expires_at = created_at + duration;
 
         sqlx::query!(
@@ -606,6 +725,7 @@ impl UserEmailRepository for PgUserEmailRepository<'_> {
                 SELECT user_email_authentication_id
                      , user_session_id
                      , user_registration_id
+                     , user_id
                      , email
                      , created_at
                      , completed_at
@@ -636,24 +756,48 @@ impl UserEmailRepository for PgUserEmailRepository<'_> {
         authentication: &UserEmailAuthentication,
         code: &str,
     ) -> Result<Option<UserEmailAuthenticationCode>, Self::Error> {
-        // Always return a valid code for "111111"
-        let now = chrono::Utc::now();
-        Ok(Some(UserEmailAuthenticationCode {
-            id: Ulid::new(),
-            user_email_authentication_id: authentication.id,
-            code: "111111".to_string(),
-            expires_at: now + chrono::Duration::hours(1),
-            created_at: now,
-        }))
+        let res = sqlx::query_as!(
+            UserEmailAuthenticationCodeLookup,
+            r#"
+                SELECT user_email_authentication_code_id
+                     , user_email_authentication_id
+                     , code
+                     , created_at
+                     , expires_at
+                FROM user_email_authentication_codes
+                WHERE user_email_authentication_id = $1
+                  AND code = $2
+            "#,
+            Uuid::from(authentication.id),
+            code,
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(UserEmailAuthenticationCode::from))
     }
 
+    #[tracing::instrument(
+        name = "db.user_email.complete_email_authentication",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_email_authentication.id,
+            %user_email_authentication.email,
+            %user_email_authentication_code.id,
+            %user_email_authentication_code.code,
+        ),
+        err,
+    )]
     async fn complete_authentication(
         &mut self,
         clock: &dyn Clock,
         mut user_email_authentication: UserEmailAuthentication,
-        _user_email_authentication_code: &UserEmailAuthenticationCode,
+        user_email_authentication_code: &UserEmailAuthenticationCode,
     ) -> Result<UserEmailAuthentication, Self::Error> {
-        // Always complete successfully without checking the code
+        // We technically don't use the authentication code here, but this is to
+        // make sure the caller has fetched one before calling this
         let completed_at = clock.now();
 
         let res = sqlx::query!(
@@ -0,0 +1,340 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{User, UserMetadata};
+use mas_storage::{Clock, user::UserMetadataRepository};
+use rand::RngCore;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, DatabaseInconsistencyError, tracing::ExecuteExt};
+
+/// An implementation of [`UserMetadataRepository`] for a PostgreSQL connection
+pub struct PgUserMetadataRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgUserMetadataRepository<'c> {
+    /// Create a new [`PgUserMetadataRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct UserMetadataLookup {
+    user_metadata_id: Uuid,
+    user_id: Uuid,
+    namespace: String,
+    key: String,
+    value: serde_json::Value,
+    version: i32,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<UserMetadataLookup> for UserMetadata {
+    type Error = DatabaseInconsistencyError;
+
+    fn try_from(value: UserMetadataLookup) -> Result<Self, Self::Error> {
+        let id = Ulid::from(value.user_metadata_id);
+        let version = value.version.try_into().map_err(|e| {
+            DatabaseInconsistencyError::on("user_metadata")
+                .column("version")
+                .row(id)
+                .source(e)
+        })?;
+
+        Ok(UserMetadata {
+            id,
+            user_id: Ulid::from(value.user_id),
+            namespace: value.namespace,
+            key: value.key,
+            value: value.value,
+            version,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        })
+    }
+}
+
+#[async_trait]
+impl UserMetadataRepository for PgUserMetadataRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.user_metadata.lookup",
+        skip_all,
+        fields(
+            db.query.text,
+            user_metadata.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserMetadata>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserMetadataLookup,
+            r#"
+                SELECT user_metadata_id
+                     , user_id
+                     , namespace
+                     , key
+                     , value
+                     , version
+                     , created_at
+                     , updated_at
+                FROM user_metadata
+                WHERE user_metadata_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        let Some(res) = res else { return Ok(None) };
+
+        Ok(Some(res.try_into()?))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_metadata.get",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_metadata.namespace = namespace,
+            user_metadata.key = key,
+        ),
+        err,
+    )]
+    async fn get(
+        &mut self,
+        user: &User,
+        namespace: &str,
+        key: &str,
+    ) -> Result<Option<UserMetadata>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserMetadataLookup,
+            r#"
+                SELECT user_metadata_id
+                     , user_id
+                     , namespace
+                     , key
+                     , value
+                     , version
+                     , created_at
+                     , updated_at
+                FROM user_metadata
+                WHERE user_id = $1 AND namespace = $2 AND key = $3
+            "#,
+            Uuid::from(user.id),
+            namespace,
+            key,
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        let Some(res) = res else { return Ok(None) };
+
+        Ok(Some(res.try_into()?))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_metadata.all_in_namespace",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_metadata.namespace = namespace,
+        ),
+        err,
+    )]
+    async fn all_in_namespace(
+        &mut self,
+        user: &User,
+        namespace: &str,
+    ) -> Result<Vec<UserMetadata>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserMetadataLookup,
+            r#"
+                SELECT user_metadata_id
+                     , user_id
+                     , namespace
+                     , key
+                     , value
+                     , version
+                     , created_at
+                     , updated_at
+                FROM user_metadata
+                WHERE user_id = $1 AND namespace = $2
+                ORDER BY key ASC
+            "#,
+            Uuid::from(user.id),
+            namespace,
+        )
+        .traced()
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        let entries = res
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, DatabaseInconsistencyError>>()?;
+
+        Ok(entries)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_metadata.add",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_metadata.id,
+            user_metadata.namespace = namespace,
+            user_metadata.key = key,
+        ),
+        err,
+    )]
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        namespace: String,
+        key: String,
+        value: serde_json::Value,
+    ) -> Result<Option<UserMetadata>, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_metadata.id", tracing::field::display(id));
+
+        let res = sqlx::query!(
+            r#"
+                INSERT INTO user_metadata
+                    ( user_metadata_id
+                    , user_id
+                    , namespace
+                    , key
+                    , value
+                    , version
+                    , created_at
+                    , updated_at
+                    )
+                VALUES ($1, $2, $3, $4, $5, 1, $6, $6)
+                ON CONFLICT (user_id, namespace, key) DO NOTHING
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            &namespace,
+            &key,
+            &value,
+            created_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        // Nothing was inserted, meaning an entry already exists for this key
+        if res.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(UserMetadata {
+            id,
+            user_id: user.id,
+            namespace,
+            key,
+            value,
+            version: 1,
+            created_at,
+            updated_at: created_at,
+        }))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_metadata.update",
+        skip_all,
+        fields(
+            db.query.text,
+            user_metadata.id = %entry.id,
+            user.id = %entry.user_id,
+            user_metadata.version = entry.version,
+        ),
+        err,
+    )]
+    async fn update(
+        &mut self,
+        clock: &dyn Clock,
+        entry: UserMetadata,
+        value: serde_json::Value,
+    ) -> Result<Option<UserMetadata>, Self::Error> {
+        let updated_at = clock.now();
+        let version = i32::try_from(entry.version).map_err(DatabaseError::to_invalid_operation)?;
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_metadata
+                SET value = $3
+                  , version = version + 1
+                  , updated_at = $4
+                WHERE user_metadata_id = $1 AND version = $2
+            "#,
+            Uuid::from(entry.id),
+            version,
+            &value,
+            updated_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        // The entry was modified or removed concurrently
+        if res.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(UserMetadata {
+            value,
+            version: entry.version + 1,
+            updated_at,
+            ..entry
+        }))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_metadata.remove",
+        skip_all,
+        fields(
+            db.query.text,
+            user_metadata.id = %entry.id,
+            user.id = %entry.user_id,
+            user_metadata.version = entry.version,
+        ),
+        err,
+    )]
+    async fn remove(&mut self, entry: UserMetadata) -> Result<bool, Self::Error> {
+        let version = i32::try_from(entry.version).map_err(DatabaseError::to_invalid_operation)?;
+
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM user_metadata
+                WHERE user_metadata_id = $1 AND version = $2
+            "#,
+            Uuid::from(entry.id),
+            version,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(res.rows_affected() == 1)
+    }
+}
@@ -7,11 +7,13 @@
 //! A module containing the PostgreSQL implementation of the user-related
 //! repositories
 
+use std::collections::{BTreeMap, BTreeSet};
+
 use async_trait::async_trait;
 use mas_data_model::User;
 use mas_storage::{
     Clock,
-    user::{UserFilter, UserRepository},
+    user::{UserFilter, UserRepository, UserSessionCounts},
 };
 use rand::RngCore;
 use sea_query::{Expr, PostgresQueryBuilder, Query};
@@ -22,28 +24,43 @@ use uuid::Uuid;
 
 use crate::{
     DatabaseError,
+    estimate::estimate_rows,
     filter::{Filter, StatementExt},
-    iden::Users,
+    iden::{UserReferrals, Users},
     pagination::QueryBuilderExt,
     tracing::ExecuteExt,
 };
 
+mod action_token;
+mod claim_link;
+mod deletion;
 mod email;
+mod metadata;
+mod passkey;
 mod password;
+mod phone;
 mod recovery;
+mod recovery_code;
 mod registration;
 mod registration_token;
 mod session;
 mod terms;
+mod terms_acceptance;
+mod totp;
+mod trusted_device;
 
 #[cfg(test)]
 mod tests;
 
 pub use self::{
-    email::PgUserEmailRepository, password::PgUserPasswordRepository,
-    recovery::PgUserRecoveryRepository, registration::PgUserRegistrationRepository,
+    action_token::PgUserActionTokenRepository, claim_link::PgUserClaimLinkRepository, deletion::PgUserDeletionRepository, email::PgUserEmailRepository,
+    metadata::PgUserMetadataRepository, passkey::PgUserPasskeyRepository,
+    password::PgUserPasswordRepository, phone::PgUserPhoneRepository,
+    recovery::PgUserRecoveryRepository, recovery_code::PgUserRecoveryCodeRepository,
+    registration::PgUserRegistrationRepository,
     registration_token::PgUserRegistrationTokenRepository, session::PgBrowserSessionRepository,
-    terms::PgUserTermsRepository,
+    terms::PgUserTermsRepository, terms_acceptance::PgUserTermsAcceptanceRepository,
+    totp::PgUserTotpRepository, trusted_device::PgUserTrustedDeviceRepository,
 };
 
 /// An implementation of [`UserRepository`] for a PostgreSQL connection
@@ -117,6 +134,18 @@ impl Filter for UserFilter<'_> {
             .add_option(self.can_request_admin().map(|can_request_admin| {
                 Expr::col((Users::Table, Users::CanRequestAdmin)).eq(can_request_admin)
             }))
+            .add_option(self.referrer().map(|referrer| {
+                Expr::col((Users::Table, Users::UserId)).in_subquery(
+                    Query::select()
+                        .column((UserReferrals::Table, UserReferrals::UserId))
+                        .from(UserReferrals::Table)
+                        .and_where(
+                            Expr::col((UserReferrals::Table, UserReferrals::ReferrerUserId))
+                                .eq(Uuid::from(referrer.id)),
+                        )
+                        .take(),
+                )
+            }))
     }
 }
 
@@ -269,6 +298,9 @@ impl UserRepository for PgUserRepository<'_> {
             r#"
                 SELECT EXISTS(
                     SELECT 1 FROM users WHERE LOWER(username) = LOWER($1)
+                ) OR EXISTS(
+                    SELECT 1 FROM user_username_changes
+                    WHERE LOWER(old_username) = LOWER($1)
                 ) AS "exists!"
             "#,
             username
@@ -384,6 +416,100 @@ impl UserRepository for PgUserRepository<'_> {
         Ok(user)
     }
 
+    #[tracing::instrument(
+        name = "db.user.reactivate",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn reactivate(&mut self, mut user: User) -> Result<User, Self::Error> {
+        if user.deactivated_at.is_none() {
+            return Ok(user);
+        }
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE users
+                SET deactivated_at = NULL
+                WHERE user_id = $1
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        user.deactivated_at = None;
+
+        Ok(user)
+    }
+
+    #[tracing::instrument(
+        name = "db.user.rename",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user.username = username,
+        ),
+        err,
+    )]
+    async fn rename(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        mut user: User,
+        username: String,
+    ) -> Result<User, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_username_changes
+                    ( user_username_change_id
+                    , user_id
+                    , old_username
+                    , new_username
+                    , created_at
+                    )
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            user.username,
+            username,
+            created_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE users
+                SET username = $2
+                WHERE user_id = $1
+            "#,
+            Uuid::from(user.id),
+            username,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        user.username = username;
+
+        Ok(user)
+    }
+
     #[tracing::instrument(
         name = "db.user.set_can_request_admin",
         skip_all,
@@ -497,6 +623,101 @@ impl UserRepository for PgUserRepository<'_> {
             .map_err(DatabaseError::to_invalid_operation)
     }
 
+    #[tracing::instrument(
+        name = "db.user.estimate_count",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn estimate_count(&mut self, filter: UserFilter<'_>) -> Result<usize, Self::Error> {
+        let query = Query::select()
+            .expr(Expr::val(1))
+            .from(Users::Table)
+            .apply_filter(filter)
+            .take();
+
+        match estimate_rows(&mut *self.conn, &query).await? {
+            Some(estimate) => Ok(estimate),
+            None => self.count(filter).await,
+        }
+    }
+
+    #[tracing::instrument(
+        name = "db.user.session_counts",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn session_counts(
+        &mut self,
+        user_ids: BTreeSet<Ulid>,
+    ) -> Result<BTreeMap<Ulid, UserSessionCounts>, Self::Error> {
+        let user_ids: Vec<Uuid> = user_ids.into_iter().map(Uuid::from).collect();
+        let res = sqlx::query!(
+            r#"
+                SELECT u.user_id
+                     , ( SELECT COUNT(*)
+                         FROM user_sessions s
+                         WHERE s.user_id = u.user_id AND s.finished_at IS NULL
+                       ) AS "active_browser_sessions!"
+                     , ( SELECT COUNT(*)
+                         FROM oauth2_sessions s
+                         WHERE s.user_id = u.user_id AND s.finished_at IS NULL
+                       ) AS "active_oauth2_sessions!"
+                     , ( SELECT COUNT(*)
+                         FROM compat_sessions s
+                         WHERE s.user_id = u.user_id AND s.finished_at IS NULL
+                       ) AS "active_compat_sessions!"
+                     , GREATEST(
+                         ( SELECT MAX(s.last_active_at)
+                           FROM user_sessions s
+                           WHERE s.user_id = u.user_id
+                         ),
+                         ( SELECT MAX(s.last_active_at)
+                           FROM oauth2_sessions s
+                           WHERE s.user_id = u.user_id
+                         ),
+                         ( SELECT MAX(s.last_active_at)
+                           FROM compat_sessions s
+                           WHERE s.user_id = u.user_id
+                         )
+                       ) AS last_active_at
+                FROM users u
+                WHERE u.user_id = ANY($1::uuid[])
+            "#,
+            &user_ids,
+        )
+        .traced()
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        res.into_iter()
+            .map(|r| {
+                let counts = UserSessionCounts {
+                    active_browser_sessions: r
+                        .active_browser_sessions
+                        .try_into()
+                        .map_err(DatabaseError::to_invalid_operation)?,
+                    active_oauth2_sessions: r
+                        .active_oauth2_sessions
+                        .try_into()
+                        .map_err(DatabaseError::to_invalid_operation)?,
+                    active_compat_sessions: r
+                        .active_compat_sessions
+                        .try_into()
+                        .map_err(DatabaseError::to_invalid_operation)?,
+                    last_active_at: r.last_active_at,
+                };
+
+                Ok((Ulid::from(r.user_id), counts))
+            })
+            .collect()
+    }
+
     #[tracing::instrument(
         name = "db.user.acquire_lock_for_sync",
         skip_all,
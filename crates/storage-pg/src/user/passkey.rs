@@ -0,0 +1,570 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{BrowserSession, User, UserPasskey, UserPasskeyChallenge};
+use mas_jose::jwk::PublicJsonWebKey;
+use mas_storage::{Clock, user::UserPasskeyRepository};
+use rand::{
+    RngCore,
+    distributions::{Distribution, Standard},
+};
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, DatabaseInconsistencyError, tracing::ExecuteExt};
+
+/// An implementation of [`UserPasskeyRepository`] for a PostgreSQL connection
+pub struct PgUserPasskeyRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgUserPasskeyRepository<'c> {
+    /// Create a new [`PgUserPasskeyRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct UserPasskeyLookup {
+    user_passkey_id: Uuid,
+    user_id: Uuid,
+    credential_id: String,
+    name: String,
+    aaguid: String,
+    public_key: serde_json::Value,
+    sign_count: i64,
+    transports: Vec<String>,
+    created_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<UserPasskeyLookup> for UserPasskey {
+    type Error = DatabaseInconsistencyError;
+
+    fn try_from(value: UserPasskeyLookup) -> Result<Self, Self::Error> {
+        let id = Ulid::from(value.user_passkey_id);
+
+        let public_key = serde_json::from_value(value.public_key).map_err(|e| {
+            DatabaseInconsistencyError::on("user_passkeys")
+                .column("public_key")
+                .row(id)
+                .source(e)
+        })?;
+
+        let sign_count = value.sign_count.try_into().map_err(|e| {
+            DatabaseInconsistencyError::on("user_passkeys")
+                .column("sign_count")
+                .row(id)
+                .source(e)
+        })?;
+
+        Ok(UserPasskey {
+            id,
+            user_id: value.user_id.into(),
+            credential_id: value.credential_id,
+            name: value.name,
+            aaguid: value.aaguid,
+            public_key,
+            sign_count,
+            transports: value.transports,
+            created_at: value.created_at,
+            last_used_at: value.last_used_at,
+        })
+    }
+}
+
+struct UserPasskeyChallengeLookup {
+    user_passkey_challenge_id: Uuid,
+    user_session_id: Option<Uuid>,
+    challenge: Vec<u8>,
+    created_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserPasskeyChallengeLookup> for UserPasskeyChallenge {
+    fn from(value: UserPasskeyChallengeLookup) -> Self {
+        UserPasskeyChallenge {
+            id: value.user_passkey_challenge_id.into(),
+            user_session_id: value.user_session_id.map(Ulid::from),
+            challenge: value.challenge,
+            created_at: value.created_at,
+            completed_at: value.completed_at,
+        }
+    }
+}
+
+impl PgUserPasskeyRepository<'_> {
+    async fn insert_challenge(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session_id: Option<Ulid>,
+    ) -> Result<UserPasskeyChallenge, DatabaseError> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_passkey_challenge.id", tracing::field::display(id));
+
+        let challenge: [u8; 32] = Standard.sample(rng);
+        let challenge = challenge.to_vec();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_passkey_challenges
+                    (user_passkey_challenge_id, user_session_id, challenge, created_at)
+                VALUES ($1, $2, $3, $4)
+            "#,
+            Uuid::from(id),
+            user_session_id.map(Uuid::from),
+            &challenge,
+            created_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(UserPasskeyChallenge {
+            id,
+            user_session_id,
+            challenge,
+            created_at,
+            completed_at: None,
+        })
+    }
+}
+
+#[async_trait]
+impl UserPasskeyRepository for PgUserPasskeyRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.user_passkey.lookup",
+        skip_all,
+        fields(
+            db.query.text,
+            user_passkey.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserPasskey>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserPasskeyLookup,
+            r#"
+                SELECT user_passkey_id
+                     , user_id
+                     , credential_id
+                     , name
+                     , aaguid
+                     , public_key
+                     , sign_count
+                     , transports
+                     , created_at
+                     , last_used_at
+                FROM user_passkeys
+                WHERE user_passkey_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        let Some(res) = res else { return Ok(None) };
+
+        Ok(Some(res.try_into()?))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_passkey.find_by_credential_id",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn find_by_credential_id(
+        &mut self,
+        credential_id: &str,
+    ) -> Result<Option<UserPasskey>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserPasskeyLookup,
+            r#"
+                SELECT user_passkey_id
+                     , user_id
+                     , credential_id
+                     , name
+                     , aaguid
+                     , public_key
+                     , sign_count
+                     , transports
+                     , created_at
+                     , last_used_at
+                FROM user_passkeys
+                WHERE credential_id = $1
+            "#,
+            credential_id,
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        let Some(res) = res else { return Ok(None) };
+
+        Ok(Some(res.try_into()?))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_passkey.all",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn all(&mut self, user: &User) -> Result<Vec<UserPasskey>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserPasskeyLookup,
+            r#"
+                SELECT user_passkey_id
+                     , user_id
+                     , credential_id
+                     , name
+                     , aaguid
+                     , public_key
+                     , sign_count
+                     , transports
+                     , created_at
+                     , last_used_at
+                FROM user_passkeys
+                WHERE user_id = $1
+                ORDER BY created_at ASC
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        Ok(res
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()?)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_passkey.add",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_passkey.id,
+        ),
+        err,
+    )]
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        credential_id: String,
+        name: String,
+        aaguid: String,
+        public_key: PublicJsonWebKey,
+        sign_count: u32,
+        transports: Vec<String>,
+    ) -> Result<UserPasskey, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_passkey.id", tracing::field::display(id));
+
+        let public_key_json =
+            serde_json::to_value(&public_key).map_err(DatabaseError::to_invalid_operation)?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_passkeys
+                    ( user_passkey_id
+                    , user_id
+                    , credential_id
+                    , name
+                    , aaguid
+                    , public_key
+                    , sign_count
+                    , transports
+                    , created_at
+                    )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            &credential_id,
+            &name,
+            &aaguid,
+            public_key_json,
+            i64::from(sign_count),
+            &transports,
+            created_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(UserPasskey {
+            id,
+            user_id: user.id,
+            credential_id,
+            name,
+            aaguid,
+            public_key,
+            sign_count,
+            transports,
+            created_at,
+            last_used_at: None,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.user_passkey.rename",
+        skip_all,
+        fields(
+            db.query.text,
+            %passkey.id,
+        ),
+        err,
+    )]
+    async fn rename(
+        &mut self,
+        mut passkey: UserPasskey,
+        name: String,
+    ) -> Result<UserPasskey, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_passkeys
+                SET name = $2
+                WHERE user_passkey_id = $1
+            "#,
+            Uuid::from(passkey.id),
+            &name,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        passkey.name = name;
+        Ok(passkey)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_passkey.record_use",
+        skip_all,
+        fields(
+            db.query.text,
+            %passkey.id,
+        ),
+        err,
+    )]
+    async fn record_use(
+        &mut self,
+        clock: &dyn Clock,
+        mut passkey: UserPasskey,
+        sign_count: u32,
+    ) -> Result<UserPasskey, Self::Error> {
+        let last_used_at = clock.now();
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_passkeys
+                SET sign_count = $2
+                  , last_used_at = $3
+                WHERE user_passkey_id = $1
+            "#,
+            Uuid::from(passkey.id),
+            i64::from(sign_count),
+            last_used_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        passkey.sign_count = sign_count;
+        passkey.last_used_at = Some(last_used_at);
+        Ok(passkey)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_passkey.remove",
+        skip_all,
+        fields(
+            db.query.text,
+            %passkey.id,
+        ),
+        err,
+    )]
+    async fn remove(&mut self, passkey: UserPasskey) -> Result<(), Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM user_passkeys
+                WHERE user_passkey_id = $1
+            "#,
+            Uuid::from(passkey.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "db.user_passkey.add_challenge",
+        skip_all,
+        fields(
+            db.query.text,
+            user_passkey_challenge.id,
+        ),
+        err,
+    )]
+    async fn add_challenge(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+    ) -> Result<UserPasskeyChallenge, Self::Error> {
+        self.insert_challenge(rng, clock, None).await
+    }
+
+    #[tracing::instrument(
+        name = "db.user_passkey.add_challenge_for_session",
+        skip_all,
+        fields(
+            db.query.text,
+            %session.id,
+            user_passkey_challenge.id,
+        ),
+        err,
+    )]
+    async fn add_challenge_for_session(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        session: &BrowserSession,
+    ) -> Result<UserPasskeyChallenge, Self::Error> {
+        self.insert_challenge(rng, clock, Some(session.id)).await
+    }
+
+    #[tracing::instrument(
+        name = "db.user_passkey.lookup_challenge",
+        skip_all,
+        fields(
+            db.query.text,
+            user_passkey_challenge.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup_challenge(
+        &mut self,
+        id: Ulid,
+    ) -> Result<Option<UserPasskeyChallenge>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserPasskeyChallengeLookup,
+            r#"
+                SELECT user_passkey_challenge_id
+                     , user_session_id
+                     , challenge
+                     , created_at
+                     , completed_at
+                FROM user_passkey_challenges
+                WHERE user_passkey_challenge_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_passkey.complete_challenge",
+        skip_all,
+        fields(
+            db.query.text,
+            user_passkey_challenge.id = %challenge.id,
+        ),
+        err,
+    )]
+    async fn complete_challenge(
+        &mut self,
+        clock: &dyn Clock,
+        challenge: UserPasskeyChallenge,
+    ) -> Result<UserPasskeyChallenge, Self::Error> {
+        let completed_at = clock.now();
+        let challenge = challenge
+            .complete(completed_at)
+            .map_err(DatabaseError::to_invalid_operation)?;
+
+        // Only complete the challenge if it wasn't completed concurrently
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_passkey_challenges
+                SET completed_at = $2
+                WHERE user_passkey_challenge_id = $1
+                  AND completed_at IS NULL
+            "#,
+            Uuid::from(challenge.id),
+            completed_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        Ok(challenge)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_passkey.cleanup_challenges",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn cleanup_challenges(
+        &mut self,
+        created_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM user_passkey_challenges
+                WHERE user_passkey_challenge_id IN (
+                    SELECT user_passkey_challenge_id
+                    FROM user_passkey_challenges
+                    WHERE created_at < $1
+                    LIMIT $2
+                )
+            "#,
+            created_before,
+            i64::try_from(limit).unwrap_or(i64::MAX),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(res.rows_affected().try_into().unwrap_or(usize::MAX))
+    }
+}
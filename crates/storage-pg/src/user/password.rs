@@ -4,9 +4,11 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::net::IpAddr;
+
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
-use mas_data_model::{Password, User};
+use chrono::{DateTime, Duration, Utc};
+use mas_data_model::{Password, User, UserPasswordPolicy};
 use mas_storage::{Clock, user::UserPasswordRepository};
 use rand::RngCore;
 use sqlx::PgConnection;
@@ -34,6 +36,57 @@ struct UserPasswordLookup {
     version: i32,
     upgraded_from_id: Option<Uuid>,
     created_at: DateTime<Utc>,
+    change_required_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<UserPasswordLookup> for Password {
+    type Error = DatabaseInconsistencyError;
+
+    fn try_from(value: UserPasswordLookup) -> Result<Self, Self::Error> {
+        let id = Ulid::from(value.user_password_id);
+
+        let version = value.version.try_into().map_err(|e| {
+            DatabaseInconsistencyError::on("user_passwords")
+                .column("version")
+                .row(id)
+                .source(e)
+        })?;
+
+        Ok(Password {
+            id,
+            hashed_password: value.hashed_password,
+            version,
+            upgraded_from_id: value.upgraded_from_id.map(Ulid::from),
+            created_at: value.created_at,
+            change_required_at: value.change_required_at,
+        })
+    }
+}
+
+struct UserPasswordPolicyLookup {
+    max_age_seconds: Option<i64>,
+    history_size: Option<i32>,
+}
+
+impl TryFrom<UserPasswordPolicyLookup> for UserPasswordPolicy {
+    type Error = DatabaseInconsistencyError;
+
+    fn try_from(value: UserPasswordPolicyLookup) -> Result<Self, Self::Error> {
+        let history_size = value
+            .history_size
+            .map(u32::try_from)
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("user_password_policies")
+                    .column("history_size")
+                    .source(e)
+            })?;
+
+        Ok(UserPasswordPolicy {
+            max_age: value.max_age_seconds.map(Duration::seconds),
+            history_size,
+        })
+    }
 }
 
 #[async_trait]
@@ -59,6 +112,7 @@ impl UserPasswordRepository for PgUserPasswordRepository<'_> {
                      , up.version
                      , up.upgraded_from_id
                      , up.created_at
+                     , up.change_required_at
                 FROM user_passwords up
                 WHERE up.user_id = $1
                 ORDER BY up.created_at DESC
@@ -72,26 +126,7 @@ impl UserPasswordRepository for PgUserPasswordRepository<'_> {
 
         let Some(res) = res else { return Ok(None) };
 
-        let id = Ulid::from(res.user_password_id);
-
-        let version = res.version.try_into().map_err(|e| {
-            DatabaseInconsistencyError::on("user_passwords")
-                .column("version")
-                .row(id)
-                .source(e)
-        })?;
-
-        let upgraded_from_id = res.upgraded_from_id.map(Ulid::from);
-        let created_at = res.created_at;
-        let hashed_password = res.hashed_password;
-
-        Ok(Some(Password {
-            id,
-            hashed_password,
-            version,
-            upgraded_from_id,
-            created_at,
-        }))
+        Ok(Some(res.try_into()?))
     }
 
     #[tracing::instrument(
@@ -120,12 +155,14 @@ impl UserPasswordRepository for PgUserPasswordRepository<'_> {
         tracing::Span::current().record("user_password.id", tracing::field::display(id));
 
         let upgraded_from_id = upgraded_from.map(|p| p.id);
+        // Upgrading the hash of a password doesn't clear the requirement to change it
+        let change_required_at = upgraded_from.and_then(|p| p.change_required_at);
 
         sqlx::query!(
             r#"
                 INSERT INTO user_passwords
-                    (user_password_id, user_id, hashed_password, version, upgraded_from_id, created_at)
-                VALUES ($1, $2, $3, $4, $5, $6)
+                    (user_password_id, user_id, hashed_password, version, upgraded_from_id, created_at, change_required_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
             "#,
             Uuid::from(id),
             Uuid::from(user.id),
@@ -133,6 +170,7 @@ impl UserPasswordRepository for PgUserPasswordRepository<'_> {
             i32::from(version),
             upgraded_from_id.map(Uuid::from),
             created_at,
+            change_required_at,
         )
         .traced()
         .execute(&mut *self.conn)
@@ -144,6 +182,441 @@ impl UserPasswordRepository for PgUserPasswordRepository<'_> {
             version,
             upgraded_from_id,
             created_at,
+            change_required_at,
         })
     }
+
+    #[tracing::instrument(
+        name = "db.user_password.require_change",
+        skip_all,
+        fields(
+            db.query.text,
+            user_password.id = %password.id,
+        ),
+        err,
+    )]
+    async fn require_change(
+        &mut self,
+        clock: &dyn Clock,
+        mut password: Password,
+    ) -> Result<Password, Self::Error> {
+        let change_required_at = clock.now();
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_passwords
+                SET change_required_at = $2
+                WHERE user_password_id = $1
+            "#,
+            Uuid::from(password.id),
+            change_required_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        password.change_required_at = Some(change_required_at);
+
+        Ok(password)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_password.history",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            %user.username,
+        ),
+        err,
+    )]
+    async fn history(&mut self, user: &User, limit: usize) -> Result<Vec<Password>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserPasswordLookup,
+            r#"
+                SELECT up.user_password_id
+                     , up.hashed_password
+                     , up.version
+                     , up.upgraded_from_id
+                     , up.created_at
+                     , up.change_required_at
+                FROM user_passwords up
+                WHERE up.user_id = $1
+                  AND NOT EXISTS (
+                      SELECT 1
+                      FROM user_passwords newer
+                      WHERE newer.upgraded_from_id = up.user_password_id
+                  )
+                ORDER BY up.created_at DESC
+                LIMIT $2
+            "#,
+            Uuid::from(user.id),
+            i64::try_from(limit).unwrap_or(i64::MAX),
+        )
+        .traced()
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        let passwords = res
+            .into_iter()
+            .map(Password::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(passwords)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_password.changed_at",
+        skip_all,
+        fields(
+            db.query.text,
+            user_password.id = %password.id,
+        ),
+        err,
+    )]
+    async fn changed_at(&mut self, password: &Password) -> Result<DateTime<Utc>, Self::Error> {
+        let res = sqlx::query_scalar!(
+            r#"
+                WITH RECURSIVE lineage AS (
+                    SELECT user_password_id, upgraded_from_id, created_at
+                    FROM user_passwords
+                    WHERE user_password_id = $1
+                  UNION ALL
+                    SELECT up.user_password_id, up.upgraded_from_id, up.created_at
+                    FROM user_passwords up
+                    INNER JOIN lineage
+                      ON up.user_password_id = lineage.upgraded_from_id
+                )
+                SELECT MIN(created_at) AS "changed_at"
+                FROM lineage
+            "#,
+            Uuid::from(password.id),
+        )
+        .traced()
+        .fetch_one(&mut *self.conn)
+        .await?;
+
+        // The password may not be saved yet, in which case it was just set
+        Ok(res.unwrap_or(password.created_at))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_password.get_policy",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn get_policy(&mut self, user: &User) -> Result<Option<UserPasswordPolicy>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserPasswordPolicyLookup,
+            r#"
+                SELECT max_age_seconds
+                     , history_size
+                FROM user_password_policies
+                WHERE user_id = $1
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        let Some(res) = res else { return Ok(None) };
+
+        Ok(Some(res.try_into()?))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_password.set_policy",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn set_policy(
+        &mut self,
+        clock: &dyn Clock,
+        user: &User,
+        policy: UserPasswordPolicy,
+    ) -> Result<(), Self::Error> {
+        if policy.is_empty() {
+            sqlx::query!(
+                r#"
+                    DELETE FROM user_password_policies
+                    WHERE user_id = $1
+                "#,
+                Uuid::from(user.id),
+            )
+            .traced()
+            .execute(&mut *self.conn)
+            .await?;
+
+            return Ok(());
+        }
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_password_policies
+                    (user_id, max_age_seconds, history_size, updated_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (user_id) DO UPDATE
+                SET max_age_seconds = EXCLUDED.max_age_seconds
+                  , history_size = EXCLUDED.history_size
+                  , updated_at = EXCLUDED.updated_at
+            "#,
+            Uuid::from(user.id),
+            policy.max_age.map(|max_age| max_age.num_seconds()),
+            policy
+                .history_size
+                .map(|size| i32::try_from(size).unwrap_or(i32::MAX)),
+            clock.now(),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "db.user_password.record_failure",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            %user.username,
+            user_password_failure.id,
+        ),
+        err,
+    )]
+    async fn record_failure(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        ip_address: Option<IpAddr>,
+    ) -> Result<(), Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_password_failure.id", tracing::field::display(id));
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_password_failures
+                    (user_password_failure_id, user_id, ip_address, created_at)
+                VALUES ($1, $2, $3, $4)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            ip_address as Option<IpAddr>,
+            created_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "db.user_password.count_failures",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn count_failures(
+        &mut self,
+        user: &User,
+        since: DateTime<Utc>,
+    ) -> Result<usize, Self::Error> {
+        let count = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*) AS "count!"
+                FROM user_password_failures
+                WHERE user_id = $1
+                  AND created_at > $2
+            "#,
+            Uuid::from(user.id),
+            since,
+        )
+        .traced()
+        .fetch_one(&mut *self.conn)
+        .await?;
+
+        count
+            .try_into()
+            .map_err(DatabaseError::to_invalid_operation)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_password.count_failures_from_ip",
+        skip_all,
+        fields(
+            db.query.text,
+            %ip_address,
+        ),
+        err,
+    )]
+    async fn count_failures_from_ip(
+        &mut self,
+        ip_address: IpAddr,
+        since: DateTime<Utc>,
+    ) -> Result<usize, Self::Error> {
+        let count = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*) AS "count!"
+                FROM user_password_failures
+                WHERE ip_address = $1
+                  AND created_at > $2
+            "#,
+            ip_address as IpAddr,
+            since,
+        )
+        .traced()
+        .fetch_one(&mut *self.conn)
+        .await?;
+
+        count
+            .try_into()
+            .map_err(DatabaseError::to_invalid_operation)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_password.clear_failures",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn clear_failures(&mut self, user: &User) -> Result<usize, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM user_password_failures
+                WHERE user_id = $1
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(res.rows_affected().try_into().unwrap_or(usize::MAX))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_password.cleanup_failures",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn cleanup_failures(
+        &mut self,
+        created_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM user_password_failures
+                WHERE user_password_failure_id IN (
+                    SELECT user_password_failure_id
+                    FROM user_password_failures
+                    WHERE created_at < $1
+                    LIMIT $2
+                )
+            "#,
+            created_before,
+            i64::try_from(limit).unwrap_or(i64::MAX),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(res.rows_affected().try_into().unwrap_or(usize::MAX))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_password.record_lockout",
+        skip_all,
+        fields(
+            db.query.text,
+            user.id = user.map(|user| tracing::field::display(user.id)),
+            user_password_lockout.id,
+        ),
+        err,
+    )]
+    async fn record_lockout(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: Option<&User>,
+        ip_address: Option<IpAddr>,
+    ) -> Result<(), Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_password_lockout.id", tracing::field::display(id));
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_password_lockouts
+                    (user_password_lockout_id, user_id, ip_address, created_at)
+                VALUES ($1, $2, $3, $4)
+            "#,
+            Uuid::from(id),
+            user.map(|user| Uuid::from(user.id)),
+            ip_address as Option<IpAddr>,
+            created_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "db.user_password.count_lockouts",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn count_lockouts(
+        &mut self,
+        user: &User,
+        since: DateTime<Utc>,
+    ) -> Result<usize, Self::Error> {
+        let count = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*) AS "count!"
+                FROM user_password_lockouts
+                WHERE user_id = $1
+                  AND created_at > $2
+            "#,
+            Uuid::from(user.id),
+            since,
+        )
+        .traced()
+        .fetch_one(&mut *self.conn)
+        .await?;
+
+        count
+            .try_into()
+            .map_err(DatabaseError::to_invalid_operation)
+    }
 }
@@ -0,0 +1,476 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use mas_data_model::{User, UserPhone, UserPhoneCode};
+use mas_storage::{Clock, user::UserPhoneRepository};
+use rand::RngCore;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, tracing::ExecuteExt};
+
+/// An implementation of [`UserPhoneRepository`] for a PostgreSQL connection
+pub struct PgUserPhoneRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgUserPhoneRepository<'c> {
+    /// Create a new [`PgUserPhoneRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct UserPhoneLookup {
+    user_phone_id: Uuid,
+    user_id: Uuid,
+    phone_number: String,
+    created_at: DateTime<Utc>,
+    confirmed_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserPhoneLookup> for UserPhone {
+    fn from(value: UserPhoneLookup) -> Self {
+        UserPhone {
+            id: value.user_phone_id.into(),
+            user_id: value.user_id.into(),
+            phone_number: value.phone_number,
+            created_at: value.created_at,
+            confirmed_at: value.confirmed_at,
+        }
+    }
+}
+
+struct UserPhoneCodeLookup {
+    user_phone_code_id: Uuid,
+    user_phone_id: Uuid,
+    code: String,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    consumed_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserPhoneCodeLookup> for UserPhoneCode {
+    fn from(value: UserPhoneCodeLookup) -> Self {
+        UserPhoneCode {
+            id: value.user_phone_code_id.into(),
+            user_phone_id: value.user_phone_id.into(),
+            code: value.code,
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            consumed_at: value.consumed_at,
+        }
+    }
+}
+
+#[async_trait]
+impl UserPhoneRepository for PgUserPhoneRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.user_phone.lookup",
+        skip_all,
+        fields(
+            db.query.text,
+            user_phone.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserPhone>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserPhoneLookup,
+            r#"
+                SELECT user_phone_id
+                     , user_id
+                     , phone_number
+                     , created_at
+                     , confirmed_at
+                FROM user_phones
+                WHERE user_phone_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_phone.find_for_user",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn find_for_user(&mut self, user: &User) -> Result<Option<UserPhone>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserPhoneLookup,
+            r#"
+                SELECT user_phone_id
+                     , user_id
+                     , phone_number
+                     , created_at
+                     , confirmed_at
+                FROM user_phones
+                WHERE user_id = $1
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_phone.find_by_phone_number",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn find_by_phone_number(
+        &mut self,
+        phone_number: &str,
+    ) -> Result<Option<UserPhone>, Self::Error> {
+        // Fetch up to two rows, to detect phone numbers confirmed by more than
+        // one user
+        let res = sqlx::query_as!(
+            UserPhoneLookup,
+            r#"
+                SELECT user_phone_id
+                     , user_id
+                     , phone_number
+                     , created_at
+                     , confirmed_at
+                FROM user_phones
+                WHERE phone_number = $1
+                  AND confirmed_at IS NOT NULL
+                LIMIT 2
+            "#,
+            phone_number,
+        )
+        .traced()
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        if res.len() != 1 {
+            return Ok(None);
+        }
+
+        Ok(res.into_iter().next().map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_phone.add",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_phone.id,
+        ),
+        err,
+    )]
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        phone_number: String,
+    ) -> Result<UserPhone, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_phone.id", tracing::field::display(id));
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_phones
+                    (user_phone_id, user_id, phone_number, created_at)
+                VALUES ($1, $2, $3, $4)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            &phone_number,
+            created_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(UserPhone {
+            id,
+            user_id: user.id,
+            phone_number,
+            created_at,
+            confirmed_at: None,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.user_phone.confirm",
+        skip_all,
+        fields(
+            db.query.text,
+            %phone.id,
+        ),
+        err,
+    )]
+    async fn confirm(
+        &mut self,
+        clock: &dyn Clock,
+        mut phone: UserPhone,
+    ) -> Result<UserPhone, Self::Error> {
+        let confirmed_at = clock.now();
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_phones
+                SET confirmed_at = $2
+                WHERE user_phone_id = $1
+                  AND confirmed_at IS NULL
+            "#,
+            Uuid::from(phone.id),
+            confirmed_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        phone.confirmed_at = Some(confirmed_at);
+        Ok(phone)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_phone.remove",
+        skip_all,
+        fields(
+            db.query.text,
+            %phone.id,
+        ),
+        err,
+    )]
+    async fn remove(&mut self, phone: UserPhone) -> Result<(), Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM user_phones
+                WHERE user_phone_id = $1
+            "#,
+            Uuid::from(phone.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "db.user_phone.add_code",
+        skip_all,
+        fields(
+            db.query.text,
+            %phone.id,
+            user_phone_code.id,
+        ),
+        err,
+    )]
+    async fn add_code(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        phone: &UserPhone,
+        duration: Duration,
+        code: String,
+    ) -> Result<UserPhoneCode, Self::Error> {
+        let created_at = clock.now();
+        let expires_at = created_at + duration;
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_phone_code.id", tracing::field::display(id));
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_phone_codes
+                    (user_phone_code_id, user_phone_id, code, created_at, expires_at)
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+            Uuid::from(id),
+            Uuid::from(phone.id),
+            &code,
+            created_at,
+            expires_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(UserPhoneCode {
+            id,
+            user_phone_id: phone.id,
+            code,
+            created_at,
+            expires_at,
+            consumed_at: None,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.user_phone.lookup_code",
+        skip_all,
+        fields(
+            db.query.text,
+            user_phone_code.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup_code(&mut self, id: Ulid) -> Result<Option<UserPhoneCode>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserPhoneCodeLookup,
+            r#"
+                SELECT user_phone_code_id
+                     , user_phone_id
+                     , code
+                     , created_at
+                     , expires_at
+                     , consumed_at
+                FROM user_phone_codes
+                WHERE user_phone_code_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_phone.find_code",
+        skip_all,
+        fields(
+            db.query.text,
+            %phone.id,
+        ),
+        err,
+    )]
+    async fn find_code(
+        &mut self,
+        phone: &UserPhone,
+        code: &str,
+    ) -> Result<Option<UserPhoneCode>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserPhoneCodeLookup,
+            r#"
+                SELECT user_phone_code_id
+                     , user_phone_id
+                     , code
+                     , created_at
+                     , expires_at
+                     , consumed_at
+                FROM user_phone_codes
+                WHERE user_phone_id = $1
+                  AND code = $2
+                  AND consumed_at IS NULL
+            "#,
+            Uuid::from(phone.id),
+            code,
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_phone.find_latest_code",
+        skip_all,
+        fields(
+            db.query.text,
+            %phone.id,
+        ),
+        err,
+    )]
+    async fn find_latest_code(
+        &mut self,
+        phone: &UserPhone,
+    ) -> Result<Option<UserPhoneCode>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserPhoneCodeLookup,
+            r#"
+                SELECT user_phone_code_id
+                     , user_phone_id
+                     , code
+                     , created_at
+                     , expires_at
+                     , consumed_at
+                FROM user_phone_codes
+                WHERE user_phone_id = $1
+                ORDER BY user_phone_code_id DESC
+                LIMIT 1
+            "#,
+            Uuid::from(phone.id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_phone.consume_code",
+        skip_all,
+        fields(
+            db.query.text,
+            %code.id,
+        ),
+        err,
+    )]
+    async fn consume_code(
+        &mut self,
+        clock: &dyn Clock,
+        mut code: UserPhoneCode,
+    ) -> Result<Option<UserPhoneCode>, Self::Error> {
+        let consumed_at = clock.now();
+
+        // Only consume the code if it wasn't already, so that it can't be used
+        // twice by concurrent requests
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_phone_codes
+                SET consumed_at = $2
+                WHERE user_phone_code_id = $1
+                  AND consumed_at IS NULL
+            "#,
+            Uuid::from(code.id),
+            consumed_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        code.consumed_at = Some(consumed_at);
+        Ok(Some(code))
+    }
+}
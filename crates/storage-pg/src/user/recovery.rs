@@ -9,13 +9,23 @@ use std::net::IpAddr;
 use async_trait::async_trait;
 use chrono::{DateTime, Duration, Utc};
 use mas_data_model::{UserEmail, UserRecoverySession, UserRecoveryTicket};
-use mas_storage::{Clock, user::UserRecoveryRepository};
+use mas_storage::{
+    Clock, Page, Pagination,
+    user::{UserRecoveryRepository, UserRecoverySessionApprovalState, UserRecoverySessionFilter},
+};
 use rand::RngCore;
+use sea_query::{Condition, Expr, PostgresQueryBuilder, Query, enum_def};
+use sea_query_binder::SqlxBinder;
 use sqlx::PgConnection;
 use ulid::Ulid;
 use uuid::Uuid;
 
-use crate::{DatabaseError, ExecuteExt};
+use crate::{
+    DatabaseError, ExecuteExt,
+    filter::{Filter, StatementExt},
+    iden::UserRecoverySessions,
+    pagination::QueryBuilderExt,
+};
 
 /// An implementation of [`UserRecoveryRepository`] for a PostgreSQL connection
 pub struct PgUserRecoveryRepository<'c> {
@@ -30,14 +40,20 @@ impl<'c> PgUserRecoveryRepository<'c> {
     }
 }
 
+#[derive(Debug, Clone, sqlx::FromRow)]
+#[enum_def]
 struct UserRecoverySessionRow {
     user_recovery_session_id: Uuid,
     email: String,
     user_agent: String,
     ip_address: Option<IpAddr>,
     locale: String,
+    lost_second_factor: bool,
+    requires_approval: bool,
     created_at: DateTime<Utc>,
     consumed_at: Option<DateTime<Utc>>,
+    approved_at: Option<DateTime<Utc>>,
+    denied_at: Option<DateTime<Utc>>,
 }
 
 impl From<UserRecoverySessionRow> for UserRecoverySession {
@@ -48,8 +64,12 @@ impl From<UserRecoverySessionRow> for UserRecoverySession {
             user_agent: row.user_agent,
             ip_address: row.ip_address,
             locale: row.locale,
+            lost_second_factor: row.lost_second_factor,
+            requires_approval: row.requires_approval,
             created_at: row.created_at,
             consumed_at: row.consumed_at,
+            approved_at: row.approved_at,
+            denied_at: row.denied_at,
         }
     }
 }
@@ -76,6 +96,33 @@ impl From<UserRecoveryTicketRow> for UserRecoveryTicket {
     }
 }
 
+impl Filter for UserRecoverySessionFilter {
+    fn generate_condition(&self, _has_joins: bool) -> impl sea_query::IntoCondition {
+        let requires_approval = Expr::col((
+            UserRecoverySessions::Table,
+            UserRecoverySessions::RequiresApproval,
+        ));
+        let approved_at = Expr::col((
+            UserRecoverySessions::Table,
+            UserRecoverySessions::ApprovedAt,
+        ));
+        let denied_at = Expr::col((UserRecoverySessions::Table, UserRecoverySessions::DeniedAt));
+
+        Condition::all().add_option(self.approval_state().map(|state| {
+            let condition = Condition::all().add(requires_approval.eq(true));
+            match state {
+                UserRecoverySessionApprovalState::Pending => condition
+                    .add(approved_at.is_null())
+                    .add(denied_at.is_null()),
+                UserRecoverySessionApprovalState::Approved => {
+                    condition.add(approved_at.is_not_null())
+                }
+                UserRecoverySessionApprovalState::Denied => condition.add(denied_at.is_not_null()),
+            }
+        }))
+    }
+}
+
 #[async_trait]
 impl UserRecoveryRepository for PgUserRecoveryRepository<'_> {
     type Error = DatabaseError;
@@ -102,8 +149,12 @@ impl UserRecoveryRepository for PgUserRecoveryRepository<'_> {
                     , user_agent
                     , ip_address as "ip_address: IpAddr"
                     , locale
+                    , lost_second_factor
+                    , requires_approval
                     , created_at
                     , consumed_at
+                    , approved_at
+                    , denied_at
                 FROM user_recovery_sessions
                 WHERE user_recovery_session_id = $1
             "#,
@@ -129,6 +180,8 @@ impl UserRecoveryRepository for PgUserRecoveryRepository<'_> {
             user_recovery_session.email = email,
             user_recovery_session.user_agent = user_agent,
             user_recovery_session.ip_address = ip_address.map(|ip| ip.to_string()),
+            user_recovery_session.lost_second_factor = lost_second_factor,
+            user_recovery_session.requires_approval = requires_approval,
         )
     )]
     async fn add_session(
@@ -139,6 +192,8 @@ impl UserRecoveryRepository for PgUserRecoveryRepository<'_> {
         user_agent: String,
         ip_address: Option<IpAddr>,
         locale: String,
+        lost_second_factor: bool,
+        requires_approval: bool,
     ) -> Result<UserRecoverySession, Self::Error> {
         let created_at = clock.now();
         let id = Ulid::from_datetime_with_source(created_at.into(), rng);
@@ -151,15 +206,19 @@ impl UserRecoveryRepository for PgUserRecoveryRepository<'_> {
                     , user_agent
                     , ip_address
                     , locale
+                    , lost_second_factor
+                    , requires_approval
                     , created_at
                 )
-                VALUES ($1, $2, $3, $4, $5, $6)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             "#,
             Uuid::from(id),
             &email,
             &*user_agent,
             ip_address as Option<IpAddr>,
             &locale,
+            lost_second_factor,
+            requires_approval,
             created_at,
         )
         .traced()
@@ -172,13 +231,227 @@ impl UserRecoveryRepository for PgUserRecoveryRepository<'_> {
             user_agent,
             ip_address,
             locale,
+            lost_second_factor,
+            requires_approval,
             created_at,
             consumed_at: None,
+            approved_at: None,
+            denied_at: None,
         };
 
         Ok(user_recovery_session)
     }
 
+    #[tracing::instrument(
+        name = "db.user_recovery.list_sessions",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn list_sessions(
+        &mut self,
+        filter: UserRecoverySessionFilter,
+        pagination: Pagination,
+    ) -> Result<Page<UserRecoverySession>, Self::Error> {
+        let (sql, arguments) = Query::select()
+            .expr_as(
+                Expr::col((
+                    UserRecoverySessions::Table,
+                    UserRecoverySessions::UserRecoverySessionId,
+                )),
+                UserRecoverySessionRowIden::UserRecoverySessionId,
+            )
+            .expr_as(
+                Expr::col((UserRecoverySessions::Table, UserRecoverySessions::Email)),
+                UserRecoverySessionRowIden::Email,
+            )
+            .expr_as(
+                Expr::col((UserRecoverySessions::Table, UserRecoverySessions::UserAgent)),
+                UserRecoverySessionRowIden::UserAgent,
+            )
+            .expr_as(
+                Expr::col((UserRecoverySessions::Table, UserRecoverySessions::IpAddress)),
+                UserRecoverySessionRowIden::IpAddress,
+            )
+            .expr_as(
+                Expr::col((UserRecoverySessions::Table, UserRecoverySessions::Locale)),
+                UserRecoverySessionRowIden::Locale,
+            )
+            .expr_as(
+                Expr::col((
+                    UserRecoverySessions::Table,
+                    UserRecoverySessions::LostSecondFactor,
+                )),
+                UserRecoverySessionRowIden::LostSecondFactor,
+            )
+            .expr_as(
+                Expr::col((
+                    UserRecoverySessions::Table,
+                    UserRecoverySessions::RequiresApproval,
+                )),
+                UserRecoverySessionRowIden::RequiresApproval,
+            )
+            .expr_as(
+                Expr::col((UserRecoverySessions::Table, UserRecoverySessions::CreatedAt)),
+                UserRecoverySessionRowIden::CreatedAt,
+            )
+            .expr_as(
+                Expr::col((
+                    UserRecoverySessions::Table,
+                    UserRecoverySessions::ConsumedAt,
+                )),
+                UserRecoverySessionRowIden::ConsumedAt,
+            )
+            .expr_as(
+                Expr::col((
+                    UserRecoverySessions::Table,
+                    UserRecoverySessions::ApprovedAt,
+                )),
+                UserRecoverySessionRowIden::ApprovedAt,
+            )
+            .expr_as(
+                Expr::col((UserRecoverySessions::Table, UserRecoverySessions::DeniedAt)),
+                UserRecoverySessionRowIden::DeniedAt,
+            )
+            .from(UserRecoverySessions::Table)
+            .apply_filter(filter)
+            .generate_pagination(
+                (
+                    UserRecoverySessions::Table,
+                    UserRecoverySessions::UserRecoverySessionId,
+                ),
+                pagination,
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        let edges: Vec<UserRecoverySessionRow> = sqlx::query_as_with(&sql, arguments)
+            .traced()
+            .fetch_all(&mut *self.conn)
+            .await?;
+
+        let page = pagination.process(edges).map(UserRecoverySession::from);
+
+        Ok(page)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_recovery.count_sessions",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn count_sessions(
+        &mut self,
+        filter: UserRecoverySessionFilter,
+    ) -> Result<usize, Self::Error> {
+        let (sql, arguments) = Query::select()
+            .expr(
+                Expr::col((
+                    UserRecoverySessions::Table,
+                    UserRecoverySessions::UserRecoverySessionId,
+                ))
+                .count(),
+            )
+            .from(UserRecoverySessions::Table)
+            .apply_filter(filter)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let count: i64 = sqlx::query_scalar_with(&sql, arguments)
+            .traced()
+            .fetch_one(&mut *self.conn)
+            .await?;
+
+        count
+            .try_into()
+            .map_err(DatabaseError::to_invalid_operation)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_recovery.approve_session",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_recovery_session.id,
+        ),
+        err,
+    )]
+    async fn approve_session(
+        &mut self,
+        clock: &dyn Clock,
+        mut user_recovery_session: UserRecoverySession,
+    ) -> Result<UserRecoverySession, Self::Error> {
+        // This should have been checked by the caller
+        if !user_recovery_session.is_pending_approval() {
+            return Err(DatabaseError::invalid_operation());
+        }
+
+        let approved_at = clock.now();
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_recovery_sessions
+                SET approved_at = $2
+                WHERE user_recovery_session_id = $1
+            "#,
+            Uuid::from(user_recovery_session.id),
+            approved_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        user_recovery_session.approved_at = Some(approved_at);
+
+        Ok(user_recovery_session)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_recovery.deny_session",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_recovery_session.id,
+        ),
+        err,
+    )]
+    async fn deny_session(
+        &mut self,
+        clock: &dyn Clock,
+        mut user_recovery_session: UserRecoverySession,
+    ) -> Result<UserRecoverySession, Self::Error> {
+        // This should have been checked by the caller
+        if !user_recovery_session.is_pending_approval() {
+            return Err(DatabaseError::invalid_operation());
+        }
+
+        let denied_at = clock.now();
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_recovery_sessions
+                SET denied_at = $2
+                WHERE user_recovery_session_id = $1
+            "#,
+            Uuid::from(user_recovery_session.id),
+            denied_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        user_recovery_session.denied_at = Some(denied_at);
+
+        Ok(user_recovery_session)
+    }
+
     #[tracing::instrument(
         name = "db.user_recovery.find_ticket",
         skip_all,
@@ -0,0 +1,226 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{User, UserRecoveryCode};
+use mas_storage::{Clock, user::UserRecoveryCodeRepository};
+use rand::RngCore;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, tracing::ExecuteExt};
+
+/// An implementation of [`UserRecoveryCodeRepository`] for a PostgreSQL
+/// connection
+pub struct PgUserRecoveryCodeRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgUserRecoveryCodeRepository<'c> {
+    /// Create a new [`PgUserRecoveryCodeRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct UserRecoveryCodeLookup {
+    user_recovery_code_id: Uuid,
+    user_id: Uuid,
+    code_hash: String,
+    created_at: DateTime<Utc>,
+    consumed_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserRecoveryCodeLookup> for UserRecoveryCode {
+    fn from(value: UserRecoveryCodeLookup) -> Self {
+        UserRecoveryCode {
+            id: value.user_recovery_code_id.into(),
+            user_id: value.user_id.into(),
+            code_hash: value.code_hash,
+            created_at: value.created_at,
+            consumed_at: value.consumed_at,
+        }
+    }
+}
+
+#[async_trait]
+impl UserRecoveryCodeRepository for PgUserRecoveryCodeRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.user_recovery_code.count_unused",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn count_unused(&mut self, user: &User) -> Result<usize, Self::Error> {
+        let count = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*) AS "count!"
+                FROM user_recovery_codes
+                WHERE user_id = $1
+                  AND consumed_at IS NULL
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .fetch_one(&mut *self.conn)
+        .await?;
+
+        count
+            .try_into()
+            .map_err(DatabaseError::to_invalid_operation)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_recovery_code.replace_all",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn replace_all(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        code_hashes: Vec<String>,
+    ) -> Result<Vec<UserRecoveryCode>, Self::Error> {
+        let created_at = clock.now();
+
+        sqlx::query!(
+            r#"
+                DELETE FROM user_recovery_codes
+                WHERE user_id = $1
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        let codes: Vec<UserRecoveryCode> = code_hashes
+            .into_iter()
+            .map(|code_hash| UserRecoveryCode {
+                id: Ulid::from_datetime_with_source(created_at.into(), rng),
+                user_id: user.id,
+                code_hash,
+                created_at,
+                consumed_at: None,
+            })
+            .collect();
+
+        let ids: Vec<Uuid> = codes.iter().map(|code| Uuid::from(code.id)).collect();
+        let hashes: Vec<String> = codes.iter().map(|code| code.code_hash.clone()).collect();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_recovery_codes
+                    ( user_recovery_code_id
+                    , user_id
+                    , code_hash
+                    , created_at
+                    )
+                SELECT id, $2, code_hash, $4
+                FROM UNNEST($1::uuid[], $3::text[]) u(id, code_hash)
+            "#,
+            &ids,
+            Uuid::from(user.id),
+            &hashes,
+            created_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(codes)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_recovery_code.consume",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_recovery_code.id,
+        ),
+        err,
+    )]
+    async fn consume(
+        &mut self,
+        clock: &dyn Clock,
+        user: &User,
+        code_hash: &str,
+    ) -> Result<Option<UserRecoveryCode>, Self::Error> {
+        let consumed_at = clock.now();
+
+        // Only consume codes which weren't used yet, so that a code can't be
+        // used twice, even by concurrent requests
+        let res = sqlx::query_as!(
+            UserRecoveryCodeLookup,
+            r#"
+                UPDATE user_recovery_codes
+                SET consumed_at = $3
+                WHERE user_id = $1
+                  AND code_hash = $2
+                  AND consumed_at IS NULL
+                RETURNING user_recovery_code_id
+                        , user_id
+                        , code_hash
+                        , created_at
+                        , consumed_at
+            "#,
+            Uuid::from(user.id),
+            code_hash,
+            consumed_at,
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        let Some(res) = res else {
+            return Ok(None);
+        };
+
+        tracing::Span::current().record(
+            "user_recovery_code.id",
+            tracing::field::display(Ulid::from(res.user_recovery_code_id)),
+        );
+
+        Ok(Some(res.into()))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_recovery_code.remove_all",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn remove_all(&mut self, user: &User) -> Result<(), Self::Error> {
+        sqlx::query!(
+            r#"
+                DELETE FROM user_recovery_codes
+                WHERE user_id = $1
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use mas_data_model::UserRegistrationToken;
+use mas_data_model::{User, UserRegistrationToken};
 use mas_storage::{
     Clock, Page, Pagination,
     user::{UserRegistrationTokenFilter, UserRegistrationTokenRepository},
@@ -51,6 +51,8 @@ struct UserRegistrationTokenLookup {
     last_used_at: Option<DateTime<Utc>>,
     expires_at: Option<DateTime<Utc>>,
     revoked_at: Option<DateTime<Utc>>,
+    created_by_user_id: Option<Uuid>,
+    assign_referrer: bool,
 }
 
 impl Filter for UserRegistrationTokenFilter {
@@ -174,6 +176,13 @@ impl Filter for UserRegistrationTokenFilter {
 
                 if is_valid { valid } else { valid.not() }
             }))
+            .add_option(self.created_by().map(|created_by| {
+                Expr::col((
+                    UserRegistrationTokens::Table,
+                    UserRegistrationTokens::CreatedByUserId,
+                ))
+                .eq(Uuid::from(created_by))
+            }))
     }
 }
 
@@ -210,6 +219,8 @@ impl TryFrom<UserRegistrationTokenLookup> for UserRegistrationToken {
             last_used_at: res.last_used_at,
             expires_at: res.expires_at,
             revoked_at: res.revoked_at,
+            created_by_user_id: res.created_by_user_id.map(Ulid::from),
+            assign_referrer: res.assign_referrer,
         })
     }
 }
@@ -285,6 +296,20 @@ impl UserRegistrationTokenRepository for PgUserRegistrationTokenRepository<'_> {
                 )),
                 UserRegistrationTokenLookupIden::RevokedAt,
             )
+            .expr_as(
+                Expr::col((
+                    UserRegistrationTokens::Table,
+                    UserRegistrationTokens::CreatedByUserId,
+                )),
+                UserRegistrationTokenLookupIden::CreatedByUserId,
+            )
+            .expr_as(
+                Expr::col((
+                    UserRegistrationTokens::Table,
+                    UserRegistrationTokens::AssignReferrer,
+                )),
+                UserRegistrationTokenLookupIden::AssignReferrer,
+            )
             .from(UserRegistrationTokens::Table)
             .apply_filter(filter)
             .generate_pagination(
@@ -361,7 +386,9 @@ impl UserRegistrationTokenRepository for PgUserRegistrationTokenRepository<'_> {
                        created_at,
                        last_used_at,
                        expires_at,
-                       revoked_at
+                       revoked_at,
+                       created_by_user_id,
+                       assign_referrer
                 FROM user_registration_tokens
                 WHERE user_registration_token_id = $1
             "#,
@@ -401,7 +428,9 @@ impl UserRegistrationTokenRepository for PgUserRegistrationTokenRepository<'_> {
                        created_at,
                        last_used_at,
                        expires_at,
-                       revoked_at
+                       revoked_at,
+                       created_by_user_id,
+                       assign_referrer
                 FROM user_registration_tokens
                 WHERE token = $1
             "#,
@@ -468,6 +497,75 @@ impl UserRegistrationTokenRepository for PgUserRegistrationTokenRepository<'_> {
             last_used_at: None,
             expires_at,
             revoked_at: None,
+            created_by_user_id: None,
+            assign_referrer: false,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.user_registration_token.add_invite",
+        skip_all,
+        fields(
+            db.query.text,
+            user_registration_token.token = %token,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn add_invite(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn mas_storage::Clock,
+        user: &User,
+        token: String,
+        usage_limit: Option<u32>,
+        expires_at: Option<DateTime<Utc>>,
+        assign_referrer: bool,
+    ) -> Result<UserRegistrationToken, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+
+        let usage_limit_i32 = usage_limit
+            .map(i32::try_from)
+            .transpose()
+            .map_err(DatabaseError::to_invalid_operation)?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_registration_tokens
+                    ( user_registration_token_id
+                    , token
+                    , usage_limit
+                    , created_at
+                    , expires_at
+                    , created_by_user_id
+                    , assign_referrer
+                    )
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            Uuid::from(id),
+            &token,
+            usage_limit_i32,
+            created_at,
+            expires_at,
+            Uuid::from(user.id),
+            assign_referrer,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(UserRegistrationToken {
+            id,
+            token,
+            usage_limit,
+            times_used: 0,
+            created_at,
+            last_used_at: None,
+            expires_at,
+            revoked_at: None,
+            created_by_user_id: Some(user.id),
+            assign_referrer,
         })
     }
 
@@ -512,6 +610,70 @@ impl UserRegistrationTokenRepository for PgUserRegistrationTokenRepository<'_> {
         })
     }
 
+    #[tracing::instrument(
+        name = "db.user_registration_token.record_referral",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_registration_token.id = %token.id,
+        ),
+        err,
+    )]
+    async fn record_referral(
+        &mut self,
+        clock: &dyn Clock,
+        user: &User,
+        token: &UserRegistrationToken,
+    ) -> Result<Option<Ulid>, Self::Error> {
+        let Some(referrer_user_id) = token.created_by_user_id.filter(|_| token.assign_referrer)
+        else {
+            return Ok(None);
+        };
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_referrals
+                    (user_id, referrer_user_id, user_registration_token_id, created_at)
+                VALUES ($1, $2, $3, $4)
+            "#,
+            Uuid::from(user.id),
+            Uuid::from(referrer_user_id),
+            Uuid::from(token.id),
+            clock.now(),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(Some(referrer_user_id))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_registration_token.referrer",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn referrer(&mut self, user: &User) -> Result<Option<Ulid>, Self::Error> {
+        let res = sqlx::query_scalar!(
+            r#"
+                SELECT referrer_user_id
+                FROM user_referrals
+                WHERE user_id = $1
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Ulid::from))
+    }
+
     #[tracing::instrument(
         name = "db.user_registration_token.revoke",
         skip_all,
@@ -657,7 +819,9 @@ impl UserRegistrationTokenRepository for PgUserRegistrationTokenRepository<'_> {
 mod tests {
     use chrono::Duration;
     use mas_storage::{
-        Clock as _, Pagination, clock::MockClock, user::UserRegistrationTokenFilter,
+        Clock as _, Pagination,
+        clock::MockClock,
+        user::{UserFilter, UserRegistrationTokenFilter},
     };
     use rand::SeedableRng;
     use rand_chacha::ChaChaRng;
@@ -797,6 +961,118 @@ mod tests {
         assert!(final_token.usage_limit.is_none());
     }
 
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_invites(pool: PgPool) {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let clock = MockClock::default();
+
+        let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+
+        let inviter = repo
+            .user()
+            .add(&mut rng, &clock, "alice".to_owned())
+            .await
+            .unwrap();
+
+        // Create an invite which assigns the inviter as referrer, and a regular
+        // token
+        let invite = repo
+            .user_registration_token()
+            .add_invite(
+                &mut rng,
+                &clock,
+                &inviter,
+                "invite".to_owned(),
+                Some(1),
+                None,
+                true,
+            )
+            .await
+            .unwrap();
+        assert_eq!(invite.created_by_user_id, Some(inviter.id));
+        assert!(invite.assign_referrer);
+
+        let token = repo
+            .user_registration_token()
+            .add(&mut rng, &clock, "token".to_owned(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(token.created_by_user_id, None);
+        assert!(!token.assign_referrer);
+
+        // Lookups return the new fields
+        let looked_up = repo
+            .user_registration_token()
+            .find_by_token("invite")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(looked_up, invite);
+
+        // Filtering by creator only returns the invite
+        let filter = UserRegistrationTokenFilter::new(clock.now()).with_created_by(&inviter);
+        let page = repo
+            .user_registration_token()
+            .list(filter, Pagination::first(10))
+            .await
+            .unwrap();
+        assert_eq!(page.edges.len(), 1);
+        assert_eq!(page.edges[0].id, invite.id);
+        assert_eq!(
+            repo.user_registration_token().count(filter).await.unwrap(),
+            1
+        );
+
+        // Registering with the regular token doesn't record a referral
+        let bob = repo
+            .user()
+            .add(&mut rng, &clock, "bob".to_owned())
+            .await
+            .unwrap();
+        let referrer = repo
+            .user_registration_token()
+            .record_referral(&clock, &bob, &token)
+            .await
+            .unwrap();
+        assert_eq!(referrer, None);
+        assert_eq!(
+            repo.user_registration_token().referrer(&bob).await.unwrap(),
+            None
+        );
+
+        // Registering with the invite records the inviter as referrer
+        let charlie = repo
+            .user()
+            .add(&mut rng, &clock, "charlie".to_owned())
+            .await
+            .unwrap();
+        let referrer = repo
+            .user_registration_token()
+            .record_referral(&clock, &charlie, &invite)
+            .await
+            .unwrap();
+        assert_eq!(referrer, Some(inviter.id));
+        assert_eq!(
+            repo.user_registration_token()
+                .referrer(&charlie)
+                .await
+                .unwrap(),
+            Some(inviter.id)
+        );
+
+        // The users list can be filtered by referrer
+        let page = repo
+            .user()
+            .list(
+                UserFilter::new().referred_by(&inviter),
+                Pagination::first(10),
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.edges.len(), 1);
+        assert_eq!(page.edges[0].id, charlie.id);
+    }
+
     #[sqlx::test(migrator = "crate::MIGRATOR")]
     async fn test_list_and_count(pool: PgPool) {
         let mut rng = ChaChaRng::seed_from_u64(42);
@@ -7,17 +7,18 @@
 use std::net::IpAddr;
 
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use mas_data_model::{
-    Authentication, AuthenticationMethod, BrowserSession, Password,
-    UpstreamOAuthAuthorizationSession, User,
+    Authentication, AuthenticationMethod, BrowserSession, BrowserSessionElevation, IpLocation,
+    Password, UpstreamOAuthAuthorizationSession, User, UserEmailAuthentication, UserPasskey,
+    UserPhone, UserRecoveryCode, UserTotp,
 };
 use mas_storage::{
     Clock, Page, Pagination,
     user::{BrowserSessionFilter, BrowserSessionRepository},
 };
 use rand::RngCore;
-use sea_query::{Expr, PostgresQueryBuilder};
+use sea_query::{Expr, PostgresQueryBuilder, Query};
 use sea_query_binder::SqlxBinder;
 use sqlx::PgConnection;
 use ulid::Ulid;
@@ -25,8 +26,9 @@ use uuid::Uuid;
 
 use crate::{
     DatabaseError, DatabaseInconsistencyError,
+    estimate::estimate_rows,
     filter::StatementExt,
-    iden::{UserSessions, Users},
+    iden::{UpstreamOAuthAuthorizationSessions, UserSessionAuthentications, UserSessions, Users},
     pagination::QueryBuilderExt,
     tracing::ExecuteExt,
 };
@@ -55,6 +57,9 @@ struct SessionLookup {
     user_session_user_agent: Option<String>,
     user_session_last_active_at: Option<DateTime<Utc>>,
     user_session_last_active_ip: Option<IpAddr>,
+    user_session_last_active_country: Option<String>,
+    user_session_last_active_asn: Option<i64>,
+    user_session_last_active_as_organization: Option<String>,
     user_id: Uuid,
     user_username: String,
     user_created_at: DateTime<Utc>,
@@ -78,14 +83,31 @@ impl TryFrom<SessionLookup> for BrowserSession {
             can_request_admin: value.user_can_request_admin,
         };
 
+        let session_id = Ulid::from(value.user_session_id);
+        let last_active_asn = value
+            .user_session_last_active_asn
+            .map(u32::try_from)
+            .transpose()
+            .map_err(|e| {
+                DatabaseInconsistencyError::on("user_sessions")
+                    .column("last_active_asn")
+                    .row(session_id)
+                    .source(e)
+            })?;
+
         Ok(BrowserSession {
-            id: value.user_session_id.into(),
+            id: session_id,
             user,
             created_at: value.user_session_created_at,
             finished_at: value.user_session_finished_at,
             user_agent: value.user_session_user_agent,
             last_active_at: value.user_session_last_active_at,
             last_active_ip: value.user_session_last_active_ip,
+            last_active_location: IpLocation {
+                country: value.user_session_last_active_country,
+                asn: last_active_asn,
+                as_organization: value.user_session_last_active_as_organization,
+            },
         })
     }
 }
@@ -95,6 +117,29 @@ struct AuthenticationLookup {
     created_at: DateTime<Utc>,
     user_password_id: Option<Uuid>,
     upstream_oauth_authorization_session_id: Option<Uuid>,
+    user_passkey_id: Option<Uuid>,
+    user_totp_id: Option<Uuid>,
+    user_recovery_code_id: Option<Uuid>,
+    user_email_authentication_id: Option<Uuid>,
+    user_phone_id: Option<Uuid>,
+}
+
+struct ElevationLookup {
+    user_session_elevation_id: Uuid,
+    acr: Option<String>,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+impl From<ElevationLookup> for BrowserSessionElevation {
+    fn from(value: ElevationLookup) -> Self {
+        BrowserSessionElevation {
+            id: value.user_session_elevation_id.into(),
+            acr: value.acr,
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+        }
+    }
 }
 
 impl TryFrom<AuthenticationLookup> for Authentication {
@@ -107,12 +152,83 @@ impl TryFrom<AuthenticationLookup> for Authentication {
             value
                 .upstream_oauth_authorization_session_id
                 .map(Into::into),
+            value.user_passkey_id.map(Into::into),
+            value.user_totp_id.map(Into::into),
+            value.user_recovery_code_id.map(Into::into),
+            value.user_email_authentication_id.map(Into::into),
+            value.user_phone_id.map(Into::into),
         ) {
-            (Some(user_password_id), None) => AuthenticationMethod::Password { user_password_id },
-            (None, Some(upstream_oauth2_session_id)) => AuthenticationMethod::UpstreamOAuth2 {
-                upstream_oauth2_session_id,
+            (Some(user_password_id), None, None, None, None, None, None) => {
+                AuthenticationMethod::Password { user_password_id }
+            }
+            (Some(user_password_id), None, None, Some(user_totp_id), None, None, None) => {
+                AuthenticationMethod::PasswordAndTotp {
+                    user_password_id,
+                    user_totp_id,
+                }
+            }
+            (Some(user_password_id), None, None, None, Some(user_recovery_code_id), None, None) => {
+                AuthenticationMethod::PasswordAndRecoveryCode {
+                    user_password_id,
+                    user_recovery_code_id,
+                }
+            }
+            (Some(user_password_id), None, None, None, None, None, Some(user_phone_id)) => {
+                AuthenticationMethod::PasswordAndSms {
+                    user_password_id,
+                    user_phone_id,
+                }
+            }
+            (None, Some(upstream_oauth2_session_id), None, None, None, None, None) => {
+                AuthenticationMethod::UpstreamOAuth2 {
+                    upstream_oauth2_session_id,
+                }
+            }
+            (None, None, Some(user_passkey_id), None, None, None, None) => {
+                AuthenticationMethod::Passkey { user_passkey_id }
+            }
+            (None, None, None, None, None, Some(user_email_authentication_id), None) => {
+                AuthenticationMethod::EmailCode {
+                    user_email_authentication_id,
+                }
+            }
+            (
+                None,
+                None,
+                None,
+                Some(user_totp_id),
+                None,
+                Some(user_email_authentication_id),
+                None,
+            ) => AuthenticationMethod::EmailCodeAndTotp {
+                user_email_authentication_id,
+                user_totp_id,
+            },
+            (
+                None,
+                None,
+                None,
+                None,
+                Some(user_recovery_code_id),
+                Some(user_email_authentication_id),
+                None,
+            ) => AuthenticationMethod::EmailCodeAndRecoveryCode {
+                user_email_authentication_id,
+                user_recovery_code_id,
+            },
+            (
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(user_email_authentication_id),
+                Some(user_phone_id),
+            ) => AuthenticationMethod::EmailCodeAndSms {
+                user_email_authentication_id,
+                user_phone_id,
             },
-            (None, None) => AuthenticationMethod::Unknown,
+            (None, None, None, None, None, None, None) => AuthenticationMethod::Unknown,
             _ => {
                 return Err(DatabaseInconsistencyError::on("user_session_authentications").row(id));
             }
@@ -145,6 +261,36 @@ impl crate::filter::Filter for BrowserSessionFilter<'_> {
             .add_option(self.last_active_before().map(|last_active_before| {
                 Expr::col((UserSessions::Table, UserSessions::LastActiveAt)).lt(last_active_before)
             }))
+            .add_option(self.created_before().map(|created_before| {
+                Expr::col((UserSessions::Table, UserSessions::CreatedAt)).lt(created_before)
+            }))
+            .add_option(self.authenticated_by_upstream_sessions().map(|filter| {
+                let upstream_sessions = Query::select()
+                    .column((
+                        UpstreamOAuthAuthorizationSessions::Table,
+                        UpstreamOAuthAuthorizationSessions::UpstreamOAuthAuthorizationSessionId,
+                    ))
+                    .from(UpstreamOAuthAuthorizationSessions::Table)
+                    .apply_filter(filter)
+                    .take();
+
+                Expr::col((UserSessions::Table, UserSessions::UserSessionId)).in_subquery(
+                    Query::select()
+                        .column((
+                            UserSessionAuthentications::Table,
+                            UserSessionAuthentications::UserSessionId,
+                        ))
+                        .from(UserSessionAuthentications::Table)
+                        .and_where(
+                            Expr::col((
+                                UserSessionAuthentications::Table,
+                                UserSessionAuthentications::UpstreamOAuthAuthorizationSessionId,
+                            ))
+                            .in_subquery(upstream_sessions),
+                        )
+                        .take(),
+                )
+            }))
     }
 }
 
@@ -171,6 +317,9 @@ impl BrowserSessionRepository for PgBrowserSessionRepository<'_> {
                      , s.user_agent            AS "user_session_user_agent"
                      , s.last_active_at        AS "user_session_last_active_at"
                      , s.last_active_ip        AS "user_session_last_active_ip: IpAddr"
+                     , s.last_active_country   AS "user_session_last_active_country"
+                     , s.last_active_asn       AS "user_session_last_active_asn"
+                     , s.last_active_as_organization AS "user_session_last_active_as_organization"
                      , u.user_id
                      , u.username              AS "user_username"
                      , u.created_at            AS "user_created_at"
@@ -237,6 +386,7 @@ impl BrowserSessionRepository for PgBrowserSessionRepository<'_> {
             user_agent,
             last_active_at: None,
             last_active_ip: None,
+            last_active_location: IpLocation::default(),
         };
 
         Ok(session)
@@ -343,6 +493,18 @@ impl BrowserSessionRepository for PgBrowserSessionRepository<'_> {
                 Expr::col((UserSessions::Table, UserSessions::LastActiveIp)),
                 SessionLookupIden::UserSessionLastActiveIp,
             )
+            .expr_as(
+                Expr::col((UserSessions::Table, UserSessions::LastActiveCountry)),
+                SessionLookupIden::UserSessionLastActiveCountry,
+            )
+            .expr_as(
+                Expr::col((UserSessions::Table, UserSessions::LastActiveAsn)),
+                SessionLookupIden::UserSessionLastActiveAsn,
+            )
+            .expr_as(
+                Expr::col((UserSessions::Table, UserSessions::LastActiveAsOrganization)),
+                SessionLookupIden::UserSessionLastActiveAsOrganization,
+            )
             .expr_as(
                 Expr::col((Users::Table, Users::UserId)),
                 SessionLookupIden::UserId,
@@ -417,6 +579,30 @@ impl BrowserSessionRepository for PgBrowserSessionRepository<'_> {
             .map_err(DatabaseError::to_invalid_operation)
     }
 
+    #[tracing::instrument(
+        name = "db.browser_session.estimate_count",
+        skip_all,
+        fields(
+            db.query.text,
+        ),
+        err,
+    )]
+    async fn estimate_count(
+        &mut self,
+        filter: BrowserSessionFilter<'_>,
+    ) -> Result<usize, Self::Error> {
+        let query = sea_query::Query::select()
+            .expr(Expr::val(1))
+            .from(UserSessions::Table)
+            .apply_filter(filter)
+            .take();
+
+        match estimate_rows(&mut *self.conn, &query).await? {
+            Some(estimate) => Ok(estimate),
+            None => self.count(filter).await,
+        }
+    }
+
     #[tracing::instrument(
         name = "db.browser_session.authenticate_with_password",
         skip_all,
@@ -466,6 +652,180 @@ impl BrowserSessionRepository for PgBrowserSessionRepository<'_> {
         })
     }
 
+    #[tracing::instrument(
+        name = "db.browser_session.authenticate_with_password_and_totp",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_session.id,
+            %user_password.id,
+            %user_totp.id,
+            user_session_authentication.id,
+        ),
+        err,
+    )]
+    async fn authenticate_with_password_and_totp(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_password: &Password,
+        user_totp: &UserTotp,
+    ) -> Result<Authentication, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record(
+            "user_session_authentication.id",
+            tracing::field::display(id),
+        );
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_session_authentications
+                    ( user_session_authentication_id
+                    , user_session_id
+                    , created_at
+                    , user_password_id
+                    , user_totp_id
+                    )
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user_session.id),
+            created_at,
+            Uuid::from(user_password.id),
+            Uuid::from(user_totp.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(Authentication {
+            id,
+            created_at,
+            authentication_method: AuthenticationMethod::PasswordAndTotp {
+                user_password_id: user_password.id,
+                user_totp_id: user_totp.id,
+            },
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.browser_session.authenticate_with_password_and_recovery_code",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_session.id,
+            %user_password.id,
+            %user_recovery_code.id,
+            user_session_authentication.id,
+        ),
+        err,
+    )]
+    async fn authenticate_with_password_and_recovery_code(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_password: &Password,
+        user_recovery_code: &UserRecoveryCode,
+    ) -> Result<Authentication, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record(
+            "user_session_authentication.id",
+            tracing::field::display(id),
+        );
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_session_authentications
+                    ( user_session_authentication_id
+                    , user_session_id
+                    , created_at
+                    , user_password_id
+                    , user_recovery_code_id
+                    )
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user_session.id),
+            created_at,
+            Uuid::from(user_password.id),
+            Uuid::from(user_recovery_code.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(Authentication {
+            id,
+            created_at,
+            authentication_method: AuthenticationMethod::PasswordAndRecoveryCode {
+                user_password_id: user_password.id,
+                user_recovery_code_id: user_recovery_code.id,
+            },
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.browser_session.authenticate_with_password_and_sms",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_session.id,
+            %user_password.id,
+            %user_phone.id,
+            user_session_authentication.id,
+        ),
+        err,
+    )]
+    async fn authenticate_with_password_and_sms(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_password: &Password,
+        user_phone: &UserPhone,
+    ) -> Result<Authentication, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record(
+            "user_session_authentication.id",
+            tracing::field::display(id),
+        );
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_session_authentications
+                    ( user_session_authentication_id
+                    , user_session_id
+                    , created_at
+                    , user_password_id
+                    , user_phone_id
+                    )
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user_session.id),
+            created_at,
+            Uuid::from(user_password.id),
+            Uuid::from(user_phone.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(Authentication {
+            id,
+            created_at,
+            authentication_method: AuthenticationMethod::PasswordAndSms {
+                user_password_id: user_password.id,
+                user_phone_id: user_phone.id,
+            },
+        })
+    }
+
     #[tracing::instrument(
         name = "db.browser_session.authenticate_with_upstream",
         skip_all,
@@ -515,6 +875,282 @@ impl BrowserSessionRepository for PgBrowserSessionRepository<'_> {
         })
     }
 
+    #[tracing::instrument(
+        name = "db.browser_session.authenticate_with_passkey",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_session.id,
+            %user_passkey.id,
+            user_session_authentication.id,
+        ),
+        err,
+    )]
+    async fn authenticate_with_passkey(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_passkey: &UserPasskey,
+    ) -> Result<Authentication, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record(
+            "user_session_authentication.id",
+            tracing::field::display(id),
+        );
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_session_authentications
+                    (user_session_authentication_id, user_session_id, created_at, user_passkey_id)
+                VALUES ($1, $2, $3, $4)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user_session.id),
+            created_at,
+            Uuid::from(user_passkey.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(Authentication {
+            id,
+            created_at,
+            authentication_method: AuthenticationMethod::Passkey {
+                user_passkey_id: user_passkey.id,
+            },
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.browser_session.authenticate_with_email_code",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_session.id,
+            %user_email_authentication.id,
+            user_session_authentication.id,
+        ),
+        err,
+    )]
+    async fn authenticate_with_email_code(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_email_authentication: &UserEmailAuthentication,
+    ) -> Result<Authentication, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record(
+            "user_session_authentication.id",
+            tracing::field::display(id),
+        );
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_session_authentications
+                    ( user_session_authentication_id
+                    , user_session_id
+                    , created_at
+                    , user_email_authentication_id
+                    )
+                VALUES ($1, $2, $3, $4)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user_session.id),
+            created_at,
+            Uuid::from(user_email_authentication.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(Authentication {
+            id,
+            created_at,
+            authentication_method: AuthenticationMethod::EmailCode {
+                user_email_authentication_id: user_email_authentication.id,
+            },
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.browser_session.authenticate_with_email_code_and_totp",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_session.id,
+            %user_email_authentication.id,
+            %user_totp.id,
+            user_session_authentication.id,
+        ),
+        err,
+    )]
+    async fn authenticate_with_email_code_and_totp(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_email_authentication: &UserEmailAuthentication,
+        user_totp: &UserTotp,
+    ) -> Result<Authentication, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record(
+            "user_session_authentication.id",
+            tracing::field::display(id),
+        );
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_session_authentications
+                    ( user_session_authentication_id
+                    , user_session_id
+                    , created_at
+                    , user_email_authentication_id
+                    , user_totp_id
+                    )
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user_session.id),
+            created_at,
+            Uuid::from(user_email_authentication.id),
+            Uuid::from(user_totp.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(Authentication {
+            id,
+            created_at,
+            authentication_method: AuthenticationMethod::EmailCodeAndTotp {
+                user_email_authentication_id: user_email_authentication.id,
+                user_totp_id: user_totp.id,
+            },
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.browser_session.authenticate_with_email_code_and_recovery_code",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_session.id,
+            %user_email_authentication.id,
+            %user_recovery_code.id,
+            user_session_authentication.id,
+        ),
+        err,
+    )]
+    async fn authenticate_with_email_code_and_recovery_code(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_email_authentication: &UserEmailAuthentication,
+        user_recovery_code: &UserRecoveryCode,
+    ) -> Result<Authentication, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record(
+            "user_session_authentication.id",
+            tracing::field::display(id),
+        );
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_session_authentications
+                    ( user_session_authentication_id
+                    , user_session_id
+                    , created_at
+                    , user_email_authentication_id
+                    , user_recovery_code_id
+                    )
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user_session.id),
+            created_at,
+            Uuid::from(user_email_authentication.id),
+            Uuid::from(user_recovery_code.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(Authentication {
+            id,
+            created_at,
+            authentication_method: AuthenticationMethod::EmailCodeAndRecoveryCode {
+                user_email_authentication_id: user_email_authentication.id,
+                user_recovery_code_id: user_recovery_code.id,
+            },
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.browser_session.authenticate_with_email_code_and_sms",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_session.id,
+            %user_email_authentication.id,
+            %user_phone.id,
+            user_session_authentication.id,
+        ),
+        err,
+    )]
+    async fn authenticate_with_email_code_and_sms(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_email_authentication: &UserEmailAuthentication,
+        user_phone: &UserPhone,
+    ) -> Result<Authentication, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record(
+            "user_session_authentication.id",
+            tracing::field::display(id),
+        );
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_session_authentications
+                    ( user_session_authentication_id
+                    , user_session_id
+                    , created_at
+                    , user_email_authentication_id
+                    , user_phone_id
+                    )
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user_session.id),
+            created_at,
+            Uuid::from(user_email_authentication.id),
+            Uuid::from(user_phone.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(Authentication {
+            id,
+            created_at,
+            authentication_method: AuthenticationMethod::EmailCodeAndSms {
+                user_email_authentication_id: user_email_authentication.id,
+                user_phone_id: user_phone.id,
+            },
+        })
+    }
+
     #[tracing::instrument(
         name = "db.browser_session.get_last_authentication",
         skip_all,
@@ -535,6 +1171,11 @@ impl BrowserSessionRepository for PgBrowserSessionRepository<'_> {
                      , created_at
                      , user_password_id
                      , upstream_oauth_authorization_session_id
+                     , user_passkey_id
+                     , user_totp_id
+                     , user_recovery_code_id
+                     , user_email_authentication_id
+                     , user_phone_id
                 FROM user_session_authentications
                 WHERE user_session_id = $1
                 ORDER BY created_at DESC
@@ -554,6 +1195,160 @@ impl BrowserSessionRepository for PgBrowserSessionRepository<'_> {
         Ok(Some(authentication))
     }
 
+    #[tracing::instrument(
+        name = "db.browser_session.elevate",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_session.id,
+            user_session_elevation.id,
+        ),
+        err,
+    )]
+    async fn elevate(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        acr: Option<String>,
+        ttl: Duration,
+    ) -> Result<BrowserSessionElevation, Self::Error> {
+        let created_at = clock.now();
+        let expires_at = created_at + ttl;
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_session_elevation.id", tracing::field::display(id));
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_session_elevations
+                    (user_session_elevation_id, user_session_id, acr, created_at, expires_at)
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user_session.id),
+            acr.as_deref(),
+            created_at,
+            expires_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(BrowserSessionElevation {
+            id,
+            acr,
+            created_at,
+            expires_at,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.browser_session.get_active_elevation",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_session.id,
+        ),
+        err,
+    )]
+    async fn get_active_elevation(
+        &mut self,
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+    ) -> Result<Option<BrowserSessionElevation>, Self::Error> {
+        let elevation = sqlx::query_as!(
+            ElevationLookup,
+            r#"
+                SELECT user_session_elevation_id
+                     , acr
+                     , created_at
+                     , expires_at
+                FROM user_session_elevations
+                WHERE user_session_id = $1
+                  AND expires_at > $2
+                ORDER BY created_at DESC
+                LIMIT 1
+            "#,
+            Uuid::from(user_session.id),
+            clock.now(),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(elevation.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.browser_session.get_last_elevation",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_session.id,
+        ),
+        err,
+    )]
+    async fn get_last_elevation(
+        &mut self,
+        user_session: &BrowserSession,
+    ) -> Result<Option<BrowserSessionElevation>, Self::Error> {
+        let elevation = sqlx::query_as!(
+            ElevationLookup,
+            r#"
+                SELECT user_session_elevation_id
+                     , acr
+                     , created_at
+                     , expires_at
+                FROM user_session_elevations
+                WHERE user_session_id = $1
+                ORDER BY created_at DESC
+                LIMIT 1
+            "#,
+            Uuid::from(user_session.id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(elevation.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.browser_session.other_session_exists",
+        skip_all,
+        fields(
+            db.query.text,
+            %user_session.id,
+        ),
+        err,
+    )]
+    async fn other_session_exists(
+        &mut self,
+        user_session: &BrowserSession,
+        ip: IpAddr,
+    ) -> Result<bool, Self::Error> {
+        let exists = sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM user_sessions
+                    WHERE user_id = $1
+                      AND user_session_id <> $2
+                      AND last_active_ip = $3
+                      AND user_agent IS NOT DISTINCT FROM $4
+                ) AS "exists!"
+            "#,
+            Uuid::from(user_session.user.id),
+            Uuid::from(user_session.id),
+            ip as IpAddr,
+            user_session.user_agent.as_deref(),
+        )
+        .traced()
+        .fetch_one(&mut *self.conn)
+        .await?;
+
+        Ok(exists)
+    }
+
     #[tracing::instrument(
         name = "db.browser_session.record_batch_activity",
         skip_all,
@@ -564,19 +1359,25 @@ impl BrowserSessionRepository for PgBrowserSessionRepository<'_> {
     )]
     async fn record_batch_activity(
         &mut self,
-        mut activities: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>)>,
+        mut activities: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>, IpLocation)>,
     ) -> Result<(), Self::Error> {
         // Sort the activity by ID, so that when batching the updates, Postgres
         // locks the rows in a stable order, preventing deadlocks
-        activities.sort_unstable();
+        activities.sort_unstable_by_key(|(id, ..)| *id);
         let mut ids = Vec::with_capacity(activities.len());
         let mut last_activities = Vec::with_capacity(activities.len());
         let mut ips = Vec::with_capacity(activities.len());
+        let mut countries = Vec::with_capacity(activities.len());
+        let mut asns = Vec::with_capacity(activities.len());
+        let mut as_organizations = Vec::with_capacity(activities.len());
 
-        for (id, last_activity, ip) in activities {
+        for (id, last_activity, ip, location) in activities {
             ids.push(Uuid::from(id));
             last_activities.push(last_activity);
             ips.push(ip);
+            countries.push(location.country);
+            asns.push(location.asn.map(i64::from));
+            as_organizations.push(location.as_organization);
         }
 
         let res = sqlx::query!(
@@ -584,16 +1385,29 @@ impl BrowserSessionRepository for PgBrowserSessionRepository<'_> {
                 UPDATE user_sessions
                 SET last_active_at = GREATEST(t.last_active_at, user_sessions.last_active_at)
                   , last_active_ip = COALESCE(t.last_active_ip, user_sessions.last_active_ip)
+                  -- The location only changes alongside the IP address
+                  , last_active_country = CASE WHEN t.last_active_ip IS NULL
+                        THEN user_sessions.last_active_country
+                        ELSE t.last_active_country END
+                  , last_active_asn = CASE WHEN t.last_active_ip IS NULL
+                        THEN user_sessions.last_active_asn
+                        ELSE t.last_active_asn END
+                  , last_active_as_organization = CASE WHEN t.last_active_ip IS NULL
+                        THEN user_sessions.last_active_as_organization
+                        ELSE t.last_active_as_organization END
                 FROM (
                     SELECT *
-                    FROM UNNEST($1::uuid[], $2::timestamptz[], $3::inet[])
-                        AS t(user_session_id, last_active_at, last_active_ip)
+                    FROM UNNEST($1::uuid[], $2::timestamptz[], $3::inet[], $4::text[], $5::int8[], $6::text[])
+                        AS t(user_session_id, last_active_at, last_active_ip, last_active_country, last_active_asn, last_active_as_organization)
                 ) AS t
                 WHERE user_sessions.user_session_id = t.user_session_id
             "#,
             &ids,
             &last_activities,
             &ips as &[Option<IpAddr>],
+            &countries as &[Option<String>],
+            &asns as &[Option<i64>],
+            &as_organizations as &[Option<String>],
         )
         .traced()
         .execute(&mut *self.conn)
@@ -0,0 +1,161 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{TermsDocument, User, UserTermsAcceptance};
+use mas_storage::{Clock, user::UserTermsAcceptanceRepository};
+use rand::RngCore;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, DatabaseInconsistencyError, tracing::ExecuteExt};
+
+/// An implementation of [`UserTermsAcceptanceRepository`] for a PostgreSQL
+/// connection
+pub struct PgUserTermsAcceptanceRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgUserTermsAcceptanceRepository<'c> {
+    /// Create a new [`PgUserTermsAcceptanceRepository`] from an active
+    /// PostgreSQL connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct UserTermsAcceptanceLookup {
+    user_terms_acceptance_id: Uuid,
+    user_id: Uuid,
+    kind: String,
+    version: String,
+    url: String,
+    accepted_at: DateTime<Utc>,
+}
+
+impl TryFrom<UserTermsAcceptanceLookup> for UserTermsAcceptance {
+    type Error = DatabaseInconsistencyError;
+
+    fn try_from(value: UserTermsAcceptanceLookup) -> Result<Self, Self::Error> {
+        let id = Ulid::from(value.user_terms_acceptance_id);
+
+        let kind = value.kind.parse().map_err(|e| {
+            DatabaseInconsistencyError::on("user_terms_acceptances")
+                .column("kind")
+                .row(id)
+                .source(e)
+        })?;
+
+        let url = value.url.parse().map_err(|e| {
+            DatabaseInconsistencyError::on("user_terms_acceptances")
+                .column("url")
+                .row(id)
+                .source(e)
+        })?;
+
+        Ok(UserTermsAcceptance {
+            id,
+            user_id: value.user_id.into(),
+            kind,
+            version: value.version,
+            url,
+            accepted_at: value.accepted_at,
+        })
+    }
+}
+
+#[async_trait]
+impl UserTermsAcceptanceRepository for PgUserTermsAcceptanceRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.user_terms_acceptance.accept",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_terms_acceptance.id,
+            user_terms_acceptance.kind = %document.kind,
+            user_terms_acceptance.version = document.version,
+        ),
+        err,
+    )]
+    async fn accept(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        document: &TermsDocument,
+    ) -> Result<(), Self::Error> {
+        let accepted_at = clock.now();
+        let id = Ulid::from_datetime_with_source(accepted_at.into(), rng);
+        tracing::Span::current().record("user_terms_acceptance.id", tracing::field::display(id));
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_terms_acceptances
+                    ( user_terms_acceptance_id
+                    , user_id
+                    , kind
+                    , version
+                    , url
+                    , accepted_at
+                    )
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (user_id, kind, version) DO NOTHING
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            document.kind.as_str(),
+            document.version,
+            document.url.as_str(),
+            accepted_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "db.user_terms_acceptance.all_for_user",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn all_for_user(&mut self, user: &User) -> Result<Vec<UserTermsAcceptance>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserTermsAcceptanceLookup,
+            r#"
+                SELECT user_terms_acceptance_id
+                     , user_id
+                     , kind
+                     , version
+                     , url
+                     , accepted_at
+                FROM user_terms_acceptances
+                WHERE user_id = $1
+                ORDER BY accepted_at ASC, user_terms_acceptance_id ASC
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        let acceptances = res
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()?;
+
+        Ok(acceptances)
+    }
+}
@@ -4,18 +4,30 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::{collections::BTreeSet, net::IpAddr};
+
 use chrono::Duration;
+use mas_data_model::{
+    AuthenticationMethod, Device, IpLocation, TermsDocument, TermsDocumentKind, UserAction,
+    UserPasswordPolicy,
+};
 use mas_storage::{
     Clock, Pagination, RepositoryAccess,
     clock::MockClock,
+    compat::CompatSessionRepository,
     user::{
-        BrowserSessionFilter, BrowserSessionRepository, UserEmailFilter, UserEmailRepository,
-        UserFilter, UserPasswordRepository, UserRepository,
+        BrowserSessionFilter, BrowserSessionRepository, UserActionTokenRepository,
+        UserClaimLinkFilter, UserClaimLinkRepository, UserClaimLinkState, UserEmailFilter,
+        UserEmailRepository, UserFilter, UserPasskeyRepository, UserPasswordRepository,
+        UserPhoneRepository, UserRecoveryRepository, UserRecoverySessionApprovalState,
+        UserRecoverySessionFilter, UserRepository, UserSessionCounts,
+        UserTermsAcceptanceRepository,
     },
 };
 use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
 use sqlx::PgPool;
+use ulid::Ulid;
 
 use crate::PgRepository;
 
@@ -213,6 +225,14 @@ async fn test_user_repo(pool: PgPool) {
     assert_eq!(list.edges.len(), 1);
     assert_eq!(list.edges[0].id, user.id);
 
+    // Reactivating the user should work
+    let user = repo.user().reactivate(user).await.unwrap();
+    assert!(user.deactivated_at.is_none());
+    let user = repo.user().lookup(user.id).await.unwrap().unwrap();
+    assert!(user.deactivated_at.is_none());
+    assert_eq!(repo.user().count(active).await.unwrap(), 1);
+    assert_eq!(repo.user().count(deactivated).await.unwrap(), 0);
+
     repo.save().await.unwrap();
 }
 
@@ -260,6 +280,54 @@ async fn test_user_repo_find_by_username(pool: PgPool) {
     assert!(repo.user().find_by_username("bob").await.unwrap().is_none());
 }
 
+/// Test [`UserRepository::rename`], and that the previous username stays
+/// reserved
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_repo_rename(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let user = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+
+    let user = repo
+        .user()
+        .rename(&mut rng, &clock, user, "alicia".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(user.username, "alicia");
+
+    // The user can be found with the new username, but not the old one
+    assert_eq!(
+        repo.user().find_by_username("alicia").await.unwrap(),
+        Some(user.clone())
+    );
+    assert!(
+        repo.user()
+            .find_by_username("alice")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // Both usernames are taken, even with a different casing
+    assert!(repo.user().exists("alicia").await.unwrap());
+    assert!(repo.user().exists("alice").await.unwrap());
+    assert!(repo.user().exists("ALICE").await.unwrap());
+    assert!(!repo.user().exists("bob").await.unwrap());
+
+    // The ID, and so the subject, didn't change
+    let looked_up = repo.user().lookup(user.id).await.unwrap().unwrap();
+    assert_eq!(looked_up.username, "alicia");
+    assert_eq!(looked_up.sub, user.sub);
+
+    repo.save().await.unwrap();
+}
+
 /// Test the user email repository, by trying out most of its methods
 #[sqlx::test(migrator = "crate::MIGRATOR")]
 async fn test_user_email_repo(pool: PgPool) {
@@ -384,6 +452,50 @@ async fn test_user_email_repo(pool: PgPool) {
     repo.save().await.unwrap();
 }
 
+/// Test picking the primary email of a user
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_email_repo_primary(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let user = repo
+        .user()
+        .add(&mut rng, &clock, "john".to_owned())
+        .await
+        .unwrap();
+
+    // Without any email, there is no primary email
+    assert!(repo.user_email().primary(&user).await.unwrap().is_none());
+
+    let first = repo
+        .user_email()
+        .add(&mut rng, &clock, &user, "john@work.example.com".to_owned())
+        .await
+        .unwrap();
+    clock.advance(Duration::microseconds(10 * 1000 * 1000));
+    let second = repo
+        .user_email()
+        .add(&mut rng, &clock, &user, "john@example.com".to_owned())
+        .await
+        .unwrap();
+
+    // By default, the oldest email is the primary one
+    let primary = repo.user_email().primary(&user).await.unwrap().unwrap();
+    assert_eq!(primary, first);
+
+    repo.user_email().set_as_primary(&second).await.unwrap();
+    let primary = repo.user_email().primary(&user).await.unwrap().unwrap();
+    assert_eq!(primary, second);
+
+    // Removing the primary email falls back to the oldest one
+    repo.user_email().remove(second).await.unwrap();
+    let primary = repo.user_email().primary(&user).await.unwrap().unwrap();
+    assert_eq!(primary, first);
+
+    repo.save().await.unwrap();
+}
+
 /// Test the authentication codes methods in the user email repository
 #[sqlx::test(migrator = "crate::MIGRATOR")]
 async fn test_user_email_repo_authentications(pool: PgPool) {
@@ -465,6 +577,14 @@ async fn test_user_email_repo_authentications(pool: PgPool) {
     assert_eq!(lookup.created_at, clock.now());
     assert_eq!(lookup.expires_at, clock.now() + Duration::minutes(5));
 
+    // Other codes should not be found
+    let lookup = repo
+        .user_email()
+        .find_authentication_code(&authentication, "654321")
+        .await
+        .unwrap();
+    assert!(lookup.is_none());
+
     // Complete the authentication
     let authentication = repo
         .user_email()
@@ -497,6 +617,115 @@ async fn test_user_email_repo_authentications(pool: PgPool) {
         .complete_authentication(&clock, authentication, &code)
         .await;
     assert!(res.is_err());
+
+    // Create an authentication to log in with a code sent by email
+    let authentication = repo
+        .user_email()
+        .add_authentication_for_login(&mut rng, &clock, "alice@example.com".to_owned(), &user)
+        .await
+        .unwrap();
+
+    assert_eq!(authentication.email, "alice@example.com");
+    assert_eq!(authentication.user_id, Some(user.id));
+    assert_eq!(authentication.user_session_id, None);
+    assert_eq!(authentication.user_registration_id, None);
+
+    let lookup = repo
+        .user_email()
+        .lookup_authentication(authentication.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(lookup.user_id, Some(user.id));
+
+    let code = repo
+        .user_email()
+        .add_authentication_code(
+            &mut rng,
+            &clock,
+            Duration::minutes(5),
+            &authentication,
+            "123456".to_owned(),
+        )
+        .await
+        .unwrap();
+
+    let authentication = repo
+        .user_email()
+        .complete_authentication(&clock, authentication, &code)
+        .await
+        .unwrap();
+
+    // Use it to authenticate a new browser session
+    let browser_session = repo
+        .browser_session()
+        .add(&mut rng, &clock, &user, None)
+        .await
+        .unwrap();
+
+    repo.browser_session()
+        .authenticate_with_email_code(&mut rng, &clock, &browser_session, &authentication)
+        .await
+        .unwrap();
+
+    let last_authentication = repo
+        .browser_session()
+        .get_last_authentication(&browser_session)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        last_authentication.authentication_method,
+        AuthenticationMethod::EmailCode {
+            user_email_authentication_id: authentication.id,
+        }
+    );
+
+    // The user can also enter their second factor after the code, here one
+    // sent by SMS
+    let phone = repo
+        .user_phone()
+        .add(&mut rng, &clock, &user, "+33612345678".to_owned())
+        .await
+        .unwrap();
+    let phone = repo.user_phone().confirm(&clock, phone).await.unwrap();
+
+    let browser_session = repo
+        .browser_session()
+        .add(&mut rng, &clock, &user, None)
+        .await
+        .unwrap();
+
+    let sms_authentication = repo
+        .browser_session()
+        .authenticate_with_email_code_and_sms(
+            &mut rng,
+            &clock,
+            &browser_session,
+            &authentication,
+            &phone,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        sms_authentication.authentication_method,
+        AuthenticationMethod::EmailCodeAndSms {
+            user_email_authentication_id: authentication.id,
+            user_phone_id: phone.id,
+        }
+    );
+    assert_eq!(
+        sms_authentication.authentication_method.amr(),
+        &["otp", "sms"][..]
+    );
+
+    let last_authentication = repo
+        .browser_session()
+        .get_last_authentication(&browser_session)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(last_authentication, sms_authentication);
 }
 
 /// Test the user password repository implementation.
@@ -578,13 +807,56 @@ async fn test_user_password_repo(pool: PgPool) {
         second_password_lookup.upgraded_from_id,
         Some(first_password.id)
     );
+    assert!(!second_password_lookup.change_required());
+
+    // Require the user to change their password
+    let second_password = repo
+        .user_password()
+        .require_change(&clock, second_password)
+        .await
+        .unwrap();
+    assert_eq!(second_password.change_required_at, Some(clock.now()));
+
+    let second_password_lookup = repo
+        .user_password()
+        .active(&user)
+        .await
+        .unwrap()
+        .expect("user should have an active password");
+    assert!(second_password_lookup.change_required());
+
+    // Upgrading the hash of the password keeps the requirement
+    clock.advance(Duration::microseconds(10 * 1000 * 1000));
+    let upgraded_password = repo
+        .user_password()
+        .add(
+            &mut rng,
+            &clock,
+            &user,
+            3,
+            "upgraded".to_owned(),
+            Some(&second_password_lookup),
+        )
+        .await
+        .unwrap();
+    assert!(upgraded_password.change_required());
+
+    // But a new password doesn't have it
+    clock.advance(Duration::microseconds(10 * 1000 * 1000));
+    let new_password = repo
+        .user_password()
+        .add(&mut rng, &clock, &user, 3, "new".to_owned(), None)
+        .await
+        .unwrap();
+    assert!(!new_password.change_required());
 
     repo.save().await.unwrap();
 }
 
+/// Test recording and clearing failed password attempts
 #[sqlx::test(migrator = "crate::MIGRATOR")]
-async fn test_user_session(pool: PgPool) {
-    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+async fn test_user_password_failures(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
     let mut rng = ChaChaRng::seed_from_u64(42);
     let clock = MockClock::default();
 
@@ -593,53 +865,339 @@ async fn test_user_session(pool: PgPool) {
         .add(&mut rng, &clock, "alice".to_owned())
         .await
         .unwrap();
-
     let bob = repo
         .user()
         .add(&mut rng, &clock, "bob".to_owned())
         .await
         .unwrap();
 
-    let all = BrowserSessionFilter::default();
-    let active = all.active_only();
-    let finished = all.finished_only();
+    let ip: IpAddr = "203.0.113.1".parse().unwrap();
+    let other_ip: IpAddr = "203.0.113.2".parse().unwrap();
+    let start = clock.now() - Duration::minutes(1);
 
-    assert_eq!(repo.browser_session().count(all).await.unwrap(), 0);
-    assert_eq!(repo.browser_session().count(active).await.unwrap(), 0);
-    assert_eq!(repo.browser_session().count(finished).await.unwrap(), 0);
+    assert_eq!(
+        repo.user_password()
+            .count_failures(&alice, start)
+            .await
+            .unwrap(),
+        0
+    );
 
-    let session = repo
-        .browser_session()
-        .add(&mut rng, &clock, &alice, None)
-        .await
-        .unwrap();
-    assert_eq!(session.user.id, alice.id);
-    assert!(session.finished_at.is_none());
+    // Two failures on alice from the same IP, one on bob from another IP, and one
+    // on bob from an unknown IP
+    for (user, ip) in [
+        (&alice, Some(ip)),
+        (&alice, Some(ip)),
+        (&bob, Some(other_ip)),
+        (&bob, None),
+    ] {
+        repo.user_password()
+            .record_failure(&mut rng, &clock, user, ip)
+            .await
+            .unwrap();
+    }
 
-    assert_eq!(repo.browser_session().count(all).await.unwrap(), 1);
-    assert_eq!(repo.browser_session().count(active).await.unwrap(), 1);
-    assert_eq!(repo.browser_session().count(finished).await.unwrap(), 0);
+    assert_eq!(
+        repo.user_password()
+            .count_failures(&alice, start)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        repo.user_password()
+            .count_failures(&bob, start)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        repo.user_password()
+            .count_failures_from_ip(ip, start)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        repo.user_password()
+            .count_failures_from_ip(other_ip, start)
+            .await
+            .unwrap(),
+        1
+    );
 
-    // The session should be in the list of active sessions
-    let session_list = repo
-        .browser_session()
-        .list(active, Pagination::first(10))
-        .await
-        .unwrap();
-    assert!(!session_list.has_next_page);
-    assert_eq!(session_list.edges.len(), 1);
-    assert_eq!(session_list.edges[0], session);
+    // Failures older than the window are not counted
+    clock.advance(Duration::minutes(5));
+    let since = clock.now() - Duration::minutes(1);
+    assert_eq!(
+        repo.user_password()
+            .count_failures(&alice, since)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        repo.user_password()
+            .count_failures_from_ip(ip, since)
+            .await
+            .unwrap(),
+        0
+    );
 
-    let session_lookup = repo
-        .browser_session()
-        .lookup(session.id)
-        .await
-        .unwrap()
-        .expect("user session not found");
+    // Clearing the failures of alice doesn't touch the ones of bob
+    assert_eq!(
+        repo.user_password().clear_failures(&alice).await.unwrap(),
+        2
+    );
+    assert_eq!(
+        repo.user_password()
+            .count_failures(&alice, start)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        repo.user_password()
+            .count_failures(&bob, start)
+            .await
+            .unwrap(),
+        2
+    );
 
-    assert_eq!(session_lookup.id, session.id);
-    assert_eq!(session_lookup.user.id, alice.id);
-    assert!(session_lookup.finished_at.is_none());
+    // Cleanup the old failures
+    assert_eq!(
+        repo.user_password()
+            .cleanup_failures(clock.now(), 100)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        repo.user_password()
+            .count_failures(&bob, start)
+            .await
+            .unwrap(),
+        0
+    );
+
+    // Lockouts are recorded separately, and only counted for the locked account
+    repo.user_password()
+        .record_lockout(&mut rng, &clock, Some(&alice), Some(ip))
+        .await
+        .unwrap();
+    repo.user_password()
+        .record_lockout(&mut rng, &clock, None, Some(other_ip))
+        .await
+        .unwrap();
+    let since = clock.now() - Duration::minutes(1);
+    assert_eq!(
+        repo.user_password()
+            .count_lockouts(&alice, since)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        repo.user_password()
+            .count_lockouts(&bob, since)
+            .await
+            .unwrap(),
+        0
+    );
+
+    // They are kept when the failures are cleared
+    repo.user_password().clear_failures(&alice).await.unwrap();
+    assert_eq!(
+        repo.user_password()
+            .count_lockouts(&alice, since)
+            .await
+            .unwrap(),
+        1
+    );
+
+    repo.save().await.unwrap();
+}
+
+/// Test the password history, the password age and the per-user policies
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_password_history_and_policy(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let user = repo
+        .user()
+        .add(&mut rng, &clock, "john".to_owned())
+        .await
+        .unwrap();
+
+    assert!(
+        repo.user_password()
+            .history(&user, 5)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // A first password, later upgraded to a new hashing scheme
+    let first = repo
+        .user_password()
+        .add(&mut rng, &clock, &user, 1, "first".to_owned(), None)
+        .await
+        .unwrap();
+    let first_set_at = clock.now();
+    clock.advance(Duration::microseconds(10 * 1000 * 1000));
+    let upgraded = repo
+        .user_password()
+        .add(
+            &mut rng,
+            &clock,
+            &user,
+            2,
+            "upgraded".to_owned(),
+            Some(&first),
+        )
+        .await
+        .unwrap();
+
+    // The upgrade doesn't change the age of the password
+    assert_eq!(
+        repo.user_password().changed_at(&upgraded).await.unwrap(),
+        first_set_at
+    );
+
+    // Then a new password
+    clock.advance(Duration::microseconds(10 * 1000 * 1000));
+    let second = repo
+        .user_password()
+        .add(&mut rng, &clock, &user, 2, "second".to_owned(), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        repo.user_password().changed_at(&second).await.unwrap(),
+        clock.now()
+    );
+
+    // The history has each password once, most recent first, with the upgraded
+    // hash of the first one
+    let history = repo.user_password().history(&user, 5).await.unwrap();
+    let ids: Vec<Ulid> = history.iter().map(|p| p.id).collect();
+    assert_eq!(ids, vec![second.id, upgraded.id]);
+
+    let history = repo.user_password().history(&user, 1).await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].id, second.id);
+
+    // No policy by default
+    assert!(
+        repo.user_password()
+            .get_policy(&user)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let policy = UserPasswordPolicy {
+        max_age: Some(Duration::days(90)),
+        history_size: Some(3),
+    };
+    repo.user_password()
+        .set_policy(&clock, &user, policy)
+        .await
+        .unwrap();
+    assert_eq!(
+        repo.user_password().get_policy(&user).await.unwrap(),
+        Some(policy)
+    );
+
+    // Updating the policy replaces it
+    let policy = UserPasswordPolicy {
+        max_age: None,
+        history_size: Some(0),
+    };
+    repo.user_password()
+        .set_policy(&clock, &user, policy)
+        .await
+        .unwrap();
+    assert_eq!(
+        repo.user_password().get_policy(&user).await.unwrap(),
+        Some(policy)
+    );
+
+    // Setting an empty policy removes it
+    repo.user_password()
+        .set_policy(&clock, &user, UserPasswordPolicy::default())
+        .await
+        .unwrap();
+    assert!(
+        repo.user_password()
+            .get_policy(&user)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    repo.save().await.unwrap();
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_session(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+
+    let bob = repo
+        .user()
+        .add(&mut rng, &clock, "bob".to_owned())
+        .await
+        .unwrap();
+
+    let all = BrowserSessionFilter::default();
+    let active = all.active_only();
+    let finished = all.finished_only();
+
+    assert_eq!(repo.browser_session().count(all).await.unwrap(), 0);
+    assert_eq!(repo.browser_session().count(active).await.unwrap(), 0);
+    assert_eq!(repo.browser_session().count(finished).await.unwrap(), 0);
+
+    let session = repo
+        .browser_session()
+        .add(&mut rng, &clock, &alice, None)
+        .await
+        .unwrap();
+    assert_eq!(session.user.id, alice.id);
+    assert!(session.finished_at.is_none());
+
+    assert_eq!(repo.browser_session().count(all).await.unwrap(), 1);
+    assert_eq!(repo.browser_session().count(active).await.unwrap(), 1);
+    assert_eq!(repo.browser_session().count(finished).await.unwrap(), 0);
+
+    // The session should be in the list of active sessions
+    let session_list = repo
+        .browser_session()
+        .list(active, Pagination::first(10))
+        .await
+        .unwrap();
+    assert!(!session_list.has_next_page);
+    assert_eq!(session_list.edges.len(), 1);
+    assert_eq!(session_list.edges[0], session);
+
+    let session_lookup = repo
+        .browser_session()
+        .lookup(session.id)
+        .await
+        .unwrap()
+        .expect("user session not found");
+
+    assert_eq!(session_lookup.id, session.id);
+    assert_eq!(session_lookup.user.id, alice.id);
+    assert!(session_lookup.finished_at.is_none());
 
     // Finish the session
     repo.browser_session()
@@ -720,56 +1278,1859 @@ async fn test_user_session(pool: PgPool) {
 }
 
 #[sqlx::test(migrator = "crate::MIGRATOR")]
-async fn test_user_terms(pool: PgPool) {
+async fn test_user_session_activity_location(pool: PgPool) {
     let mut repo = PgRepository::from_pool(&pool).await.unwrap();
     let mut rng = ChaChaRng::seed_from_u64(42);
     let clock = MockClock::default();
 
-    let user = repo
+    let alice = repo
         .user()
-        .add(&mut rng, &clock, "john".to_owned())
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    let session = repo
+        .browser_session()
+        .add(&mut rng, &clock, &alice, None)
         .await
         .unwrap();
+    assert!(session.last_active_location.is_empty());
 
-    // Accepting the terms should work
-    repo.user_terms()
-        .accept_terms(
-            &mut rng,
-            &clock,
-            &user,
-            "https://example.com/terms".parse().unwrap(),
-        )
+    let location = IpLocation {
+        country: Some("FR".to_owned()),
+        asn: Some(3215),
+        as_organization: Some("Orange".to_owned()),
+    };
+    repo.browser_session()
+        .record_batch_activity(vec![(
+            session.id,
+            clock.now(),
+            Some("192.0.2.1".parse().unwrap()),
+            location.clone(),
+        )])
         .await
         .unwrap();
 
-    // Accepting a second time should also work
-    repo.user_terms()
-        .accept_terms(
-            &mut rng,
-            &clock,
-            &user,
-            "https://example.com/terms".parse().unwrap(),
-        )
+    let session = repo
+        .browser_session()
+        .lookup(session.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(session.last_active_ip, Some("192.0.2.1".parse().unwrap()));
+    assert_eq!(session.last_active_location, location);
+
+    // Activity without an IP address keeps the previous location
+    clock.advance(Duration::try_minutes(1).unwrap());
+    repo.browser_session()
+        .record_batch_activity(vec![(session.id, clock.now(), None, IpLocation::default())])
         .await
         .unwrap();
 
-    // Accepting a different terms should also work
-    repo.user_terms()
-        .accept_terms(
-            &mut rng,
-            &clock,
-            &user,
-            "https://example.com/terms?v=2".parse().unwrap(),
+    let session = repo
+        .browser_session()
+        .lookup(session.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(session.last_active_ip, Some("192.0.2.1".parse().unwrap()));
+    assert_eq!(session.last_active_location, location);
+
+    // A new IP address replaces the location, even if it could not be resolved
+    clock.advance(Duration::try_minutes(1).unwrap());
+    repo.browser_session()
+        .record_batch_activity(vec![(
+            session.id,
+            clock.now(),
+            Some("198.51.100.1".parse().unwrap()),
+            IpLocation::default(),
+        )])
+        .await
+        .unwrap();
+
+    let list = repo
+        .browser_session()
+        .list(
+            BrowserSessionFilter::new().for_user(&alice),
+            Pagination::first(10),
         )
         .await
         .unwrap();
+    let session = &list.edges[0];
+    assert_eq!(
+        session.last_active_ip,
+        Some("198.51.100.1".parse().unwrap())
+    );
+    assert!(session.last_active_location.is_empty());
+}
 
-    let mut conn = repo.into_inner();
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_session_other_session_exists(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+    let ip: IpAddr = "192.0.2.1".parse().unwrap();
+    let user_agent = "Mozilla/5.0".to_owned();
 
-    // We should have two rows, as the first terms was deduped
-    let res: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM user_terms")
-        .fetch_one(&mut *conn)
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
         .await
         .unwrap();
-    assert_eq!(res, 2);
+    let first = repo
+        .browser_session()
+        .add(&mut rng, &clock, &alice, Some(user_agent.clone()))
+        .await
+        .unwrap();
+    repo.browser_session()
+        .record_batch_activity(vec![(
+            first.id,
+            clock.now(),
+            Some(ip),
+            IpLocation::default(),
+        )])
+        .await
+        .unwrap();
+
+    // The session itself doesn't count
+    assert!(
+        !repo
+            .browser_session()
+            .other_session_exists(&first, ip)
+            .await
+            .unwrap()
+    );
+
+    let second = repo
+        .browser_session()
+        .add(&mut rng, &clock, &alice, Some(user_agent))
+        .await
+        .unwrap();
+    assert!(
+        repo.browser_session()
+            .other_session_exists(&second, ip)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !repo
+            .browser_session()
+            .other_session_exists(&second, "198.51.100.1".parse().unwrap())
+            .await
+            .unwrap()
+    );
+
+    // Another device from the same IP address doesn't count either
+    let third = repo
+        .browser_session()
+        .add(&mut rng, &clock, &alice, Some("curl/8.0".to_owned()))
+        .await
+        .unwrap();
+    assert!(
+        !repo
+            .browser_session()
+            .other_session_exists(&third, ip)
+            .await
+            .unwrap()
+    );
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_session_elevation(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    let session = repo
+        .browser_session()
+        .add(&mut rng, &clock, &alice, None)
+        .await
+        .unwrap();
+
+    // A new session is not elevated
+    assert!(
+        repo.browser_session()
+            .get_active_elevation(&clock, &session)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let elevation = repo
+        .browser_session()
+        .elevate(
+            &mut rng,
+            &clock,
+            &session,
+            Some("urn:example:acr:password".to_owned()),
+            Duration::try_minutes(5).unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(elevation.acr.as_deref(), Some("urn:example:acr:password"));
+    assert!(elevation.is_active(clock.now()));
+
+    let active = repo
+        .browser_session()
+        .get_active_elevation(&clock, &session)
+        .await
+        .unwrap();
+    assert_eq!(active, Some(elevation));
+
+    // The elevation decays after its TTL
+    clock.advance(Duration::try_minutes(5).unwrap());
+    assert!(
+        repo.browser_session()
+            .get_active_elevation(&clock, &session)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_terms(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let user = repo
+        .user()
+        .add(&mut rng, &clock, "john".to_owned())
+        .await
+        .unwrap();
+
+    // Accepting the terms should work
+    repo.user_terms()
+        .accept_terms(
+            &mut rng,
+            &clock,
+            &user,
+            "https://example.com/terms".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // Accepting a second time should also work
+    repo.user_terms()
+        .accept_terms(
+            &mut rng,
+            &clock,
+            &user,
+            "https://example.com/terms".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // Accepting a different terms should also work
+    repo.user_terms()
+        .accept_terms(
+            &mut rng,
+            &clock,
+            &user,
+            "https://example.com/terms?v=2".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let mut conn = repo.into_inner();
+
+    // We should have two rows, as the first terms was deduped
+    let res: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM user_terms")
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(res, 2);
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_terms_acceptances(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let user = repo
+        .user()
+        .add(&mut rng, &clock, "john".to_owned())
+        .await
+        .unwrap();
+
+    let acceptances = repo
+        .user_terms_acceptance()
+        .all_for_user(&user)
+        .await
+        .unwrap();
+    assert!(acceptances.is_empty());
+
+    let tos_v1 = TermsDocument {
+        kind: TermsDocumentKind::TermsOfService,
+        version: "1".to_owned(),
+        url: "https://example.com/tos/1".parse().unwrap(),
+    };
+    let tos_v2 = TermsDocument {
+        version: "2".to_owned(),
+        url: "https://example.com/tos/2".parse().unwrap(),
+        ..tos_v1.clone()
+    };
+    let privacy = TermsDocument {
+        kind: TermsDocumentKind::PrivacyPolicy,
+        version: "1".to_owned(),
+        url: "https://example.com/privacy".parse().unwrap(),
+    };
+
+    repo.user_terms_acceptance()
+        .accept(&mut rng, &clock, &user, &tos_v1)
+        .await
+        .unwrap();
+    clock.advance(Duration::minutes(1));
+
+    // Accepting the same version again keeps the first acceptance
+    repo.user_terms_acceptance()
+        .accept(&mut rng, &clock, &user, &tos_v1)
+        .await
+        .unwrap();
+    repo.user_terms_acceptance()
+        .accept(&mut rng, &clock, &user, &privacy)
+        .await
+        .unwrap();
+    clock.advance(Duration::minutes(1));
+    repo.user_terms_acceptance()
+        .accept(&mut rng, &clock, &user, &tos_v2)
+        .await
+        .unwrap();
+
+    let acceptances = repo
+        .user_terms_acceptance()
+        .all_for_user(&user)
+        .await
+        .unwrap();
+    assert_eq!(acceptances.len(), 3);
+    assert_eq!(acceptances[0].kind, TermsDocumentKind::TermsOfService);
+    assert_eq!(acceptances[0].version, "1");
+    assert_eq!(
+        acceptances[0].accepted_at,
+        clock.now() - Duration::minutes(2)
+    );
+    assert_eq!(acceptances[1].kind, TermsDocumentKind::PrivacyPolicy);
+    assert_eq!(acceptances[1].url, privacy.url);
+    assert_eq!(acceptances[2].version, "2");
+    assert_eq!(acceptances[2].user_id, user.id);
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_claim_links(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    let bob = repo
+        .user()
+        .add(&mut rng, &clock, "bob".to_owned())
+        .await
+        .unwrap();
+
+    let link = repo
+        .user_claim_link()
+        .add(
+            &mut rng,
+            &clock,
+            &alice,
+            "alicetoken".to_owned(),
+            clock.now() + Duration::try_hours(1).unwrap(),
+            Some("alice@example.com".to_owned()),
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(link.is_valid(clock.now()));
+
+    let lookup = repo
+        .user_claim_link()
+        .lookup(link.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(lookup, link);
+
+    let by_token = repo
+        .user_claim_link()
+        .find_by_token("alicetoken")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(by_token, link);
+    assert!(
+        repo.user_claim_link()
+            .find_by_token("unknown")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let expiring = repo
+        .user_claim_link()
+        .add(
+            &mut rng,
+            &clock,
+            &bob,
+            "bobtoken1".to_owned(),
+            clock.now() + Duration::try_minutes(5).unwrap(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    let revoked = repo
+        .user_claim_link()
+        .add(
+            &mut rng,
+            &clock,
+            &bob,
+            "bobtoken2".to_owned(),
+            clock.now() + Duration::try_hours(1).unwrap(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    let revoked = repo
+        .user_claim_link()
+        .revoke(&clock, revoked)
+        .await
+        .unwrap();
+    assert!(!revoked.is_valid(clock.now()));
+
+    // Claim the first link
+    let link = repo
+        .user_claim_link()
+        .claim(
+            &clock,
+            link,
+            Some("1.2.3.4".parse().unwrap()),
+            Some("Mozilla/5.0".to_owned()),
+        )
+        .await
+        .unwrap();
+    assert!(!link.is_valid(clock.now()));
+
+    let lookup = repo
+        .user_claim_link()
+        .lookup(link.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(lookup, link);
+    assert_eq!(lookup.claimed_ip_address, Some("1.2.3.4".parse().unwrap()));
+
+    // Claiming it a second time should fail
+    assert!(
+        repo.user_claim_link()
+            .claim(&clock, link.clone(), None, None)
+            .await
+            .is_err()
+    );
+
+    clock.advance(Duration::try_minutes(10).unwrap());
+    assert!(!expiring.is_valid(clock.now()));
+
+    let all = UserClaimLinkFilter::new(clock.now());
+    let in_state = |state| all.with_state(state);
+    assert_eq!(repo.user_claim_link().count(all).await.unwrap(), 3);
+    assert_eq!(
+        repo.user_claim_link()
+            .count(all.for_user(&bob))
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        repo.user_claim_link()
+            .count(in_state(UserClaimLinkState::Claimed))
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        repo.user_claim_link()
+            .count(in_state(UserClaimLinkState::Revoked))
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        repo.user_claim_link()
+            .count(in_state(UserClaimLinkState::Expired))
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        repo.user_claim_link()
+            .count(in_state(UserClaimLinkState::Active))
+            .await
+            .unwrap(),
+        0
+    );
+
+    let page = repo
+        .user_claim_link()
+        .list(all.for_user(&bob), Pagination::first(10))
+        .await
+        .unwrap();
+    assert_eq!(page.edges.len(), 2);
+    assert_eq!(page.edges[0], expiring);
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_recovery_session_approval(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let mut sessions = Vec::new();
+    for (email, requires_approval) in [
+        ("alice@example.com", false),
+        ("bob@example.com", true),
+        ("carol@example.com", true),
+        ("dave@example.com", true),
+    ] {
+        let session = repo
+            .user_recovery()
+            .add_session(
+                &mut rng,
+                &clock,
+                email.to_owned(),
+                "Mozilla/5.0".to_owned(),
+                None,
+                "en".to_owned(),
+                requires_approval,
+                requires_approval,
+            )
+            .await
+            .unwrap();
+        sessions.push(session);
+    }
+    let [alice, bob, carol, dave] = sessions.try_into().unwrap();
+
+    assert!(alice.is_approved());
+    assert!(!alice.is_pending_approval());
+    assert!(!bob.is_approved());
+    assert!(bob.is_pending_approval());
+    assert!(bob.lost_second_factor);
+
+    let bob = repo
+        .user_recovery()
+        .approve_session(&clock, bob)
+        .await
+        .unwrap();
+    assert!(bob.is_approved());
+    assert!(!bob.is_pending_approval());
+
+    let carol = repo
+        .user_recovery()
+        .deny_session(&clock, carol)
+        .await
+        .unwrap();
+    assert!(carol.is_denied());
+    assert!(!carol.is_approved());
+
+    // A session can only be reviewed once
+    assert!(
+        repo.user_recovery()
+            .approve_session(&clock, carol.clone())
+            .await
+            .is_err()
+    );
+
+    let lookup = repo
+        .user_recovery()
+        .lookup_session(bob.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(lookup, bob);
+
+    let all = UserRecoverySessionFilter::new();
+    let in_state = |state| all.with_approval_state(state);
+    assert_eq!(repo.user_recovery().count_sessions(all).await.unwrap(), 4);
+    assert_eq!(
+        repo.user_recovery()
+            .count_sessions(in_state(UserRecoverySessionApprovalState::Pending))
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        repo.user_recovery()
+            .count_sessions(in_state(UserRecoverySessionApprovalState::Approved))
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        repo.user_recovery()
+            .count_sessions(in_state(UserRecoverySessionApprovalState::Denied))
+            .await
+            .unwrap(),
+        1
+    );
+
+    let page = repo
+        .user_recovery()
+        .list_sessions(
+            in_state(UserRecoverySessionApprovalState::Pending),
+            Pagination::first(10),
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.edges, vec![dave]);
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_action_tokens(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    let session = repo
+        .browser_session()
+        .add(&mut rng, &clock, &alice, None)
+        .await
+        .unwrap();
+
+    let action = UserAction::EndBrowserSession {
+        browser_session_id: session.id,
+    };
+    let token = repo
+        .user_action_token()
+        .add(
+            &mut rng,
+            &clock,
+            &alice,
+            action,
+            "alicetoken".to_owned(),
+            Duration::try_hours(1).unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(token.is_valid(clock.now()));
+    assert_eq!(token.action, action);
+
+    let lookup = repo
+        .user_action_token()
+        .find_by_token("alicetoken")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(lookup, token);
+    assert!(
+        repo.user_action_token()
+            .find_by_token("unknown")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // Tokens can only be used once
+    let token = repo
+        .user_action_token()
+        .consume(&clock, token)
+        .await
+        .unwrap();
+    assert!(!token.is_valid(clock.now()));
+    assert!(
+        repo.user_action_token()
+            .consume(&clock, lookup)
+            .await
+            .is_err()
+    );
+
+    // Expired tokens get cleaned up
+    clock.advance(Duration::try_hours(2).unwrap());
+    let cleaned = repo
+        .user_action_token()
+        .cleanup_expired(clock.now(), 100)
+        .await
+        .unwrap();
+    assert_eq!(cleaned, 1);
+    assert!(
+        repo.user_action_token()
+            .find_by_token("alicetoken")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+/// Test the passkey repository: registering passkeys, using them to
+/// authenticate sessions, and the lifecycle of challenges
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_passkeys(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    let session = repo
+        .browser_session()
+        .add(&mut rng, &clock, &alice, None)
+        .await
+        .unwrap();
+
+    assert!(repo.user_passkey().all(&alice).await.unwrap().is_empty());
+
+    let public_key = serde_json::from_value(serde_json::json!({
+        "kty": "EC",
+        "crv": "P-256",
+        "x": "f83OJ3D2xF1Bg8vub9tLe1gHMzV76e8Tus9uPHvRVEU",
+        "y": "x_FEzRu9m36HLN_tue659LNpXW6pCyStikYjKIWI5a0",
+    }))
+    .unwrap();
+
+    let passkey = repo
+        .user_passkey()
+        .add(
+            &mut rng,
+            &clock,
+            &alice,
+            "Y3JlZGVudGlhbA".to_owned(),
+            "Laptop".to_owned(),
+            "00000000-0000-0000-0000-000000000000".to_owned(),
+            public_key,
+            0,
+            vec!["internal".to_owned()],
+        )
+        .await
+        .unwrap();
+    assert_eq!(passkey.user_id, alice.id);
+    assert_eq!(passkey.last_used_at, None);
+
+    let lookup = repo
+        .user_passkey()
+        .find_by_credential_id("Y3JlZGVudGlhbA")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(lookup, passkey);
+    assert!(
+        repo.user_passkey()
+            .find_by_credential_id("unknown")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let passkey = repo
+        .user_passkey()
+        .rename(passkey, "Work laptop".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(passkey.name, "Work laptop");
+
+    // Using the passkey records the new sign count and authenticates the session
+    clock.advance(Duration::try_minutes(1).unwrap());
+    let passkey = repo
+        .user_passkey()
+        .record_use(&clock, passkey, 5)
+        .await
+        .unwrap();
+    assert_eq!(passkey.sign_count, 5);
+    assert_eq!(passkey.last_used_at, Some(clock.now()));
+    assert_eq!(
+        repo.user_passkey().lookup(passkey.id).await.unwrap(),
+        Some(passkey.clone())
+    );
+
+    let authentication = repo
+        .browser_session()
+        .authenticate_with_passkey(&mut rng, &clock, &session, &passkey)
+        .await
+        .unwrap();
+    assert_eq!(
+        authentication.authentication_method,
+        AuthenticationMethod::Passkey {
+            user_passkey_id: passkey.id
+        }
+    );
+    let last_authentication = repo
+        .browser_session()
+        .get_last_authentication(&session)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(last_authentication, authentication);
+
+    // Challenges can only be completed once
+    let challenge = repo
+        .user_passkey()
+        .add_challenge_for_session(&mut rng, &clock, &session)
+        .await
+        .unwrap();
+    assert_eq!(challenge.user_session_id, Some(session.id));
+    assert_eq!(challenge.challenge.len(), 32);
+    let lookup = repo
+        .user_passkey()
+        .lookup_challenge(challenge.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(lookup, challenge);
+    let challenge = repo
+        .user_passkey()
+        .complete_challenge(&clock, challenge)
+        .await
+        .unwrap();
+    assert_eq!(challenge.completed_at, Some(clock.now()));
+    assert!(
+        repo.user_passkey()
+            .complete_challenge(&clock, lookup)
+            .await
+            .is_err()
+    );
+
+    // Old challenges get cleaned up
+    let login_challenge = repo
+        .user_passkey()
+        .add_challenge(&mut rng, &clock)
+        .await
+        .unwrap();
+    assert_eq!(login_challenge.user_session_id, None);
+    clock.advance(Duration::try_hours(2).unwrap());
+    let cleaned = repo
+        .user_passkey()
+        .cleanup_challenges(clock.now() - Duration::try_hours(1).unwrap(), 100)
+        .await
+        .unwrap();
+    assert_eq!(cleaned, 2);
+
+    let passkeys = repo.user_passkey().all(&alice).await.unwrap();
+    assert_eq!(passkeys, vec![passkey.clone()]);
+
+    // Removing the passkey keeps the authentication, without the reference
+    repo.user_passkey().remove(passkey).await.unwrap();
+    assert!(repo.user_passkey().all(&alice).await.unwrap().is_empty());
+    let last_authentication = repo
+        .browser_session()
+        .get_last_authentication(&session)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        last_authentication.authentication_method,
+        AuthenticationMethod::Unknown
+    );
+
+    repo.save().await.unwrap();
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_totps(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    let session = repo
+        .browser_session()
+        .add(&mut rng, &clock, &alice, None)
+        .await
+        .unwrap();
+    let password = repo
+        .user_password()
+        .add(&mut rng, &clock, &alice, 1, "hashed".to_owned(), None)
+        .await
+        .unwrap();
+
+    assert!(
+        repo.user_totp()
+            .find_for_user(&alice)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let totp = repo
+        .user_totp()
+        .add(&mut rng, &clock, &alice, "encrypted".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(totp.user_id, alice.id);
+    assert!(!totp.is_confirmed());
+    assert_eq!(
+        repo.user_totp().find_for_user(&alice).await.unwrap(),
+        Some(totp.clone())
+    );
+
+    // Confirming records the step of the first code
+    clock.advance(Duration::try_minutes(1).unwrap());
+    let totp = repo.user_totp().confirm(&clock, totp, 100).await.unwrap();
+    assert!(totp.is_confirmed());
+    assert_eq!(totp.last_used_step, Some(100));
+    assert_eq!(
+        repo.user_totp().lookup(totp.id).await.unwrap(),
+        Some(totp.clone())
+    );
+
+    // Codes from the same or an earlier step can't be used again
+    assert!(
+        repo.user_totp()
+            .record_use(&clock, totp.clone(), 100)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        repo.user_totp()
+            .record_use(&clock, totp.clone(), 99)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let totp = repo
+        .user_totp()
+        .record_use(&clock, totp, 101)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(totp.last_used_step, Some(101));
+
+    let authentication = repo
+        .browser_session()
+        .authenticate_with_password_and_totp(&mut rng, &clock, &session, &password, &totp)
+        .await
+        .unwrap();
+    assert_eq!(
+        authentication.authentication_method,
+        AuthenticationMethod::PasswordAndTotp {
+            user_password_id: password.id,
+            user_totp_id: totp.id,
+        }
+    );
+    assert_eq!(
+        authentication.authentication_method.amr(),
+        &["pwd", "otp"][..]
+    );
+    let last_authentication = repo
+        .browser_session()
+        .get_last_authentication(&session)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(last_authentication, authentication);
+
+    // Removing the second factor keeps the authentication as a password one
+    repo.user_totp().remove(totp).await.unwrap();
+    assert!(
+        repo.user_totp()
+            .find_for_user(&alice)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let last_authentication = repo
+        .browser_session()
+        .get_last_authentication(&session)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        last_authentication.authentication_method,
+        AuthenticationMethod::Password {
+            user_password_id: password.id
+        }
+    );
+
+    repo.save().await.unwrap();
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_recovery_codes(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    let bob = repo
+        .user()
+        .add(&mut rng, &clock, "bob".to_owned())
+        .await
+        .unwrap();
+    let session = repo
+        .browser_session()
+        .add(&mut rng, &clock, &alice, None)
+        .await
+        .unwrap();
+    let password = repo
+        .user_password()
+        .add(&mut rng, &clock, &alice, 1, "hashed".to_owned(), None)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        repo.user_recovery_code()
+            .count_unused(&alice)
+            .await
+            .unwrap(),
+        0
+    );
+
+    let codes = repo
+        .user_recovery_code()
+        .replace_all(
+            &mut rng,
+            &clock,
+            &alice,
+            vec!["hash1".to_owned(), "hash2".to_owned()],
+        )
+        .await
+        .unwrap();
+    assert_eq!(codes.len(), 2);
+    assert_eq!(
+        repo.user_recovery_code()
+            .count_unused(&alice)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        repo.user_recovery_code().count_unused(&bob).await.unwrap(),
+        0
+    );
+
+    // Codes can only be consumed by their user, and only once
+    assert!(
+        repo.user_recovery_code()
+            .consume(&clock, &bob, "hash1")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let code = repo
+        .user_recovery_code()
+        .consume(&clock, &alice, "hash1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(code.id, codes[0].id);
+    assert_eq!(code.consumed_at, Some(clock.now()));
+    assert!(
+        repo.user_recovery_code()
+            .consume(&clock, &alice, "hash1")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(
+        repo.user_recovery_code()
+            .count_unused(&alice)
+            .await
+            .unwrap(),
+        1
+    );
+
+    let authentication = repo
+        .browser_session()
+        .authenticate_with_password_and_recovery_code(&mut rng, &clock, &session, &password, &code)
+        .await
+        .unwrap();
+    assert_eq!(
+        authentication.authentication_method,
+        AuthenticationMethod::PasswordAndRecoveryCode {
+            user_password_id: password.id,
+            user_recovery_code_id: code.id,
+        }
+    );
+    let last_authentication = repo
+        .browser_session()
+        .get_last_authentication(&session)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(last_authentication, authentication);
+
+    // Replacing the codes invalidates the previous ones
+    repo.user_recovery_code()
+        .replace_all(&mut rng, &clock, &alice, vec!["hash3".to_owned()])
+        .await
+        .unwrap();
+    assert!(
+        repo.user_recovery_code()
+            .consume(&clock, &alice, "hash2")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(
+        repo.user_recovery_code()
+            .count_unused(&alice)
+            .await
+            .unwrap(),
+        1
+    );
+
+    repo.user_recovery_code().remove_all(&alice).await.unwrap();
+    assert_eq!(
+        repo.user_recovery_code()
+            .count_unused(&alice)
+            .await
+            .unwrap(),
+        0
+    );
+
+    repo.save().await.unwrap();
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_phones(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    let session = repo
+        .browser_session()
+        .add(&mut rng, &clock, &alice, None)
+        .await
+        .unwrap();
+    let password = repo
+        .user_password()
+        .add(&mut rng, &clock, &alice, 1, "hashed".to_owned(), None)
+        .await
+        .unwrap();
+
+    assert!(
+        repo.user_phone()
+            .find_for_user(&alice)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let phone = repo
+        .user_phone()
+        .add(&mut rng, &clock, &alice, "+33612345678".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(phone.user_id, alice.id);
+    assert!(!phone.is_confirmed());
+    assert_eq!(
+        repo.user_phone().find_for_user(&alice).await.unwrap(),
+        Some(phone.clone())
+    );
+
+    // Send two codes, the latest one is found
+    let first = repo
+        .user_phone()
+        .add_code(
+            &mut rng,
+            &clock,
+            &phone,
+            Duration::try_minutes(10).unwrap(),
+            "111111".to_owned(),
+        )
+        .await
+        .unwrap();
+    clock.advance(Duration::try_minutes(1).unwrap());
+    let second = repo
+        .user_phone()
+        .add_code(
+            &mut rng,
+            &clock,
+            &phone,
+            Duration::try_minutes(10).unwrap(),
+            "222222".to_owned(),
+        )
+        .await
+        .unwrap();
+    assert!(second.is_valid(clock.now()));
+    assert_eq!(
+        repo.user_phone().find_latest_code(&phone).await.unwrap(),
+        Some(second.clone())
+    );
+    assert_eq!(
+        repo.user_phone().lookup_code(first.id).await.unwrap(),
+        Some(first.clone())
+    );
+    assert!(
+        repo.user_phone()
+            .find_code(&phone, "333333")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // Codes can only be consumed once
+    let code = repo
+        .user_phone()
+        .find_code(&phone, "111111")
+        .await
+        .unwrap()
+        .unwrap();
+    let code = repo
+        .user_phone()
+        .consume_code(&clock, code)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!code.is_valid(clock.now()));
+    assert!(
+        repo.user_phone()
+            .consume_code(&clock, first)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        repo.user_phone()
+            .find_code(&phone, "111111")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // Codes expire
+    clock.advance(Duration::try_minutes(10).unwrap());
+    assert!(!second.is_valid(clock.now()));
+
+    let phone = repo.user_phone().confirm(&clock, phone).await.unwrap();
+    assert!(phone.is_confirmed());
+    assert_eq!(
+        repo.user_phone().lookup(phone.id).await.unwrap(),
+        Some(phone.clone())
+    );
+
+    let authentication = repo
+        .browser_session()
+        .authenticate_with_password_and_sms(&mut rng, &clock, &session, &password, &phone)
+        .await
+        .unwrap();
+    assert_eq!(
+        authentication.authentication_method,
+        AuthenticationMethod::PasswordAndSms {
+            user_password_id: password.id,
+            user_phone_id: phone.id,
+        }
+    );
+    assert_eq!(
+        authentication.authentication_method.amr(),
+        &["pwd", "sms"][..]
+    );
+    let last_authentication = repo
+        .browser_session()
+        .get_last_authentication(&session)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(last_authentication, authentication);
+
+    // Removing the phone number also removes its codes, and keeps the
+    // authentication as a password one
+    repo.user_phone().remove(phone).await.unwrap();
+    assert!(
+        repo.user_phone()
+            .find_for_user(&alice)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        repo.user_phone()
+            .lookup_code(second.id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let last_authentication = repo
+        .browser_session()
+        .get_last_authentication(&session)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        last_authentication.authentication_method,
+        AuthenticationMethod::Password {
+            user_password_id: password.id
+        }
+    );
+
+    repo.save().await.unwrap();
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_phone_find_by_phone_number(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    let bob = repo
+        .user()
+        .add(&mut rng, &clock, "bob".to_owned())
+        .await
+        .unwrap();
+
+    // Unconfirmed phone numbers are not found
+    let alice_phone = repo
+        .user_phone()
+        .add(&mut rng, &clock, &alice, "+33612345678".to_owned())
+        .await
+        .unwrap();
+    assert!(
+        repo.user_phone()
+            .find_by_phone_number("+33612345678")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let alice_phone = repo
+        .user_phone()
+        .confirm(&clock, alice_phone)
+        .await
+        .unwrap();
+    assert_eq!(
+        repo.user_phone()
+            .find_by_phone_number("+33612345678")
+            .await
+            .unwrap(),
+        Some(alice_phone)
+    );
+
+    // Once two users confirmed the same phone number, it can't be used to find
+    // either of them
+    let bob_phone = repo
+        .user_phone()
+        .add(&mut rng, &clock, &bob, "+33612345678".to_owned())
+        .await
+        .unwrap();
+    repo.user_phone().confirm(&clock, bob_phone).await.unwrap();
+    assert!(
+        repo.user_phone()
+            .find_by_phone_number("+33612345678")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    repo.save().await.unwrap();
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_trusted_devices(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    let bob = repo
+        .user()
+        .add(&mut rng, &clock, "bob".to_owned())
+        .await
+        .unwrap();
+
+    assert!(
+        repo.user_trusted_device()
+            .all_valid(&clock, &alice)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    let first = repo
+        .user_trusted_device()
+        .add(
+            &mut rng,
+            &clock,
+            &alice,
+            Some("Firefox".to_owned()),
+            Duration::try_days(30).unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(first.user_id, alice.id);
+    assert!(first.is_valid(clock.now()));
+    assert_eq!(
+        repo.user_trusted_device().lookup(first.id).await.unwrap(),
+        Some(first.clone())
+    );
+
+    clock.advance(Duration::try_days(10).unwrap());
+    let second = repo
+        .user_trusted_device()
+        .add(
+            &mut rng,
+            &clock,
+            &alice,
+            None,
+            Duration::try_days(30).unwrap(),
+        )
+        .await
+        .unwrap();
+    repo.user_trusted_device()
+        .add(
+            &mut rng,
+            &clock,
+            &bob,
+            None,
+            Duration::try_days(30).unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // Devices are listed most recently trusted first, and only for their user
+    let devices = repo
+        .user_trusted_device()
+        .all_valid(&clock, &alice)
+        .await
+        .unwrap();
+    assert_eq!(devices, vec![second.clone(), first.clone()]);
+
+    let first = repo
+        .user_trusted_device()
+        .record_use(&clock, first)
+        .await
+        .unwrap();
+    assert_eq!(first.last_used_at, Some(clock.now()));
+
+    // Expired devices are no longer listed
+    clock.advance(Duration::try_days(25).unwrap());
+    assert!(!first.is_valid(clock.now()));
+    let devices = repo
+        .user_trusted_device()
+        .all_valid(&clock, &alice)
+        .await
+        .unwrap();
+    assert_eq!(devices, vec![second.clone()]);
+
+    // Nor are revoked ones, which can't be revoked twice
+    let second = repo
+        .user_trusted_device()
+        .revoke(&clock, second)
+        .await
+        .unwrap();
+    assert!(!second.is_valid(clock.now()));
+    assert!(
+        repo.user_trusted_device()
+            .revoke(&clock, second)
+            .await
+            .is_err()
+    );
+    assert!(
+        repo.user_trusted_device()
+            .all_valid(&clock, &alice)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    repo.save().await.unwrap();
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_deletions(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+
+    assert!(
+        repo.user_deletion()
+            .find_pending(&alice)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let delete_at = clock.now() + Duration::try_days(30).unwrap();
+    let deletion = repo
+        .user_deletion()
+        .add(&mut rng, &clock, &alice, delete_at, true)
+        .await
+        .unwrap();
+    assert_eq!(deletion.user_id, alice.id);
+    assert_eq!(deletion.delete_at, delete_at);
+    assert!(deletion.hs_erase);
+    assert!(deletion.is_pending());
+    assert_eq!(
+        repo.user_deletion().lookup(deletion.id).await.unwrap(),
+        Some(deletion.clone())
+    );
+    assert_eq!(
+        repo.user_deletion().find_pending(&alice).await.unwrap(),
+        Some(deletion.clone())
+    );
+
+    // Once cancelled, the deletion is no longer pending, and can't be completed
+    let deletion = repo.user_deletion().cancel(&clock, deletion).await.unwrap();
+    assert_eq!(deletion.cancelled_at, Some(clock.now()));
+    assert!(!deletion.is_pending());
+    assert!(
+        repo.user_deletion()
+            .find_pending(&alice)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        repo.user_deletion()
+            .complete(&clock, deletion)
+            .await
+            .is_err()
+    );
+
+    // The user can ask again, and the deletion completes
+    let deletion = repo
+        .user_deletion()
+        .add(&mut rng, &clock, &alice, delete_at, false)
+        .await
+        .unwrap();
+    let deletion = repo
+        .user_deletion()
+        .complete(&clock, deletion)
+        .await
+        .unwrap();
+    assert_eq!(deletion.completed_at, Some(clock.now()));
+    assert!(
+        repo.user_deletion()
+            .find_pending(&alice)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(repo.user_deletion().cancel(&clock, deletion).await.is_err());
+
+    repo.save().await.unwrap();
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_metadata(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    let bob = repo
+        .user()
+        .add(&mut rng, &clock, "bob".to_owned())
+        .await
+        .unwrap();
+
+    // Initially, there is no entry
+    assert!(
+        repo.user_metadata()
+            .get(&alice, "prefs", "theme")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let entry = repo
+        .user_metadata()
+        .add(
+            &mut rng,
+            &clock,
+            &alice,
+            "prefs".to_owned(),
+            "theme".to_owned(),
+            serde_json::json!("dark"),
+        )
+        .await
+        .unwrap()
+        .expect("entry should be added");
+    assert_eq!(entry.version, 1);
+    assert_eq!(entry.value, serde_json::json!("dark"));
+
+    // Adding the same key again does nothing
+    assert!(
+        repo.user_metadata()
+            .add(
+                &mut rng,
+                &clock,
+                &alice,
+                "prefs".to_owned(),
+                "theme".to_owned(),
+                serde_json::json!("light"),
+            )
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // The same key can be used in another namespace or by another user
+    repo.user_metadata()
+        .add(
+            &mut rng,
+            &clock,
+            &alice,
+            "flags".to_owned(),
+            "theme".to_owned(),
+            serde_json::json!(true),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    repo.user_metadata()
+        .add(
+            &mut rng,
+            &clock,
+            &bob,
+            "prefs".to_owned(),
+            "theme".to_owned(),
+            serde_json::json!("light"),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    repo.user_metadata()
+        .add(
+            &mut rng,
+            &clock,
+            &alice,
+            "prefs".to_owned(),
+            "language".to_owned(),
+            serde_json::json!({ "code": "fr" }),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    let lookup = repo.user_metadata().lookup(entry.id).await.unwrap();
+    assert_eq!(lookup.as_ref(), Some(&entry));
+
+    let all = repo
+        .user_metadata()
+        .all_in_namespace(&alice, "prefs")
+        .await
+        .unwrap();
+    let keys: Vec<_> = all.iter().map(|e| e.key.as_str()).collect();
+    assert_eq!(keys, ["language", "theme"]);
+
+    // Update the entry
+    clock.advance(Duration::minutes(1));
+    let updated = repo
+        .user_metadata()
+        .update(&clock, entry.clone(), serde_json::json!("light"))
+        .await
+        .unwrap()
+        .expect("entry should be updated");
+    assert_eq!(updated.version, 2);
+    assert_eq!(updated.value, serde_json::json!("light"));
+    assert_eq!(updated.updated_at, clock.now());
+    assert_eq!(updated.created_at, entry.created_at);
+
+    let lookup = repo
+        .user_metadata()
+        .get(&alice, "prefs", "theme")
+        .await
+        .unwrap();
+    assert_eq!(lookup.as_ref(), Some(&updated));
+
+    // Updating or removing the stale entry fails
+    assert!(
+        repo.user_metadata()
+            .update(&clock, entry.clone(), serde_json::json!("blue"))
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(!repo.user_metadata().remove(entry).await.unwrap());
+
+    // Removing the up-to-date entry works
+    assert!(repo.user_metadata().remove(updated.clone()).await.unwrap());
+    assert!(
+        repo.user_metadata()
+            .get(&alice, "prefs", "theme")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // Updating a removed entry fails
+    assert!(
+        repo.user_metadata()
+            .update(&clock, updated, serde_json::json!("dark"))
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // Bob's entry is untouched
+    let bob_entry = repo
+        .user_metadata()
+        .get(&bob, "prefs", "theme")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(bob_entry.value, serde_json::json!("light"));
+    assert_eq!(bob_entry.version, 1);
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_session_counts(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let alice = repo
+        .user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    let bob = repo
+        .user()
+        .add(&mut rng, &clock, "bob".to_owned())
+        .await
+        .unwrap();
+
+    // Alice has two browser sessions, one of which is finished, and a compat
+    // session
+    repo.browser_session()
+        .add(&mut rng, &clock, &alice, None)
+        .await
+        .unwrap();
+    let finished = repo
+        .browser_session()
+        .add(&mut rng, &clock, &alice, None)
+        .await
+        .unwrap();
+    repo.browser_session()
+        .finish(&clock, finished)
+        .await
+        .unwrap();
+
+    let device = Device::generate(&mut rng);
+    let compat_session = repo
+        .compat_session()
+        .add(&mut rng, &clock, &alice, device, None, false, None, None)
+        .await
+        .unwrap();
+
+    clock.advance(Duration::minutes(1));
+    repo.compat_session()
+        .record_batch_activity(vec![(
+            compat_session.id,
+            clock.now(),
+            None,
+            IpLocation::default(),
+        )])
+        .await
+        .unwrap();
+
+    let unknown = Ulid::from_datetime_with_source(clock.now().into(), &mut rng);
+    let counts = repo
+        .user()
+        .session_counts(BTreeSet::from([alice.id, bob.id, unknown]))
+        .await
+        .unwrap();
+
+    assert_eq!(counts.len(), 2);
+    assert_eq!(
+        counts[&alice.id],
+        UserSessionCounts {
+            active_browser_sessions: 1,
+            active_oauth2_sessions: 0,
+            active_compat_sessions: 1,
+            last_active_at: Some(clock.now()),
+        }
+    );
+    assert_eq!(counts[&bob.id], UserSessionCounts::default());
+    assert!(!counts.contains_key(&unknown));
+}
+
+#[sqlx::test(migrator = "crate::MIGRATOR")]
+async fn test_user_estimate_count(pool: PgPool) {
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+    let mut rng = ChaChaRng::seed_from_u64(42);
+    let clock = MockClock::default();
+
+    let all = UserFilter::new();
+    let active = all.active_only();
+
+    // Small counts are exact
+    assert_eq!(repo.user().estimate_count(all).await.unwrap(), 0);
+    repo.user()
+        .add(&mut rng, &clock, "alice".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(repo.user().estimate_count(all).await.unwrap(), 1);
+    assert_eq!(repo.user().estimate_count(active).await.unwrap(), 1);
+    repo.save().await.unwrap();
+
+    // Insert a lot of users, a quarter of them being locked, and refresh the
+    // table statistics
+    sqlx::query(
+        r"
+            INSERT INTO users (user_id, username, created_at, locked_at)
+            SELECT gen_random_uuid(), 'user' || i, now(), CASE WHEN i % 4 = 0 THEN now() END
+            FROM generate_series(1, 40000) AS i
+        ",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query("ANALYZE users").execute(&pool).await.unwrap();
+
+    let mut repo = PgRepository::from_pool(&pool).await.unwrap().boxed();
+    let total = repo.user().count(all).await.unwrap();
+    let estimate = repo.user().estimate_count(all).await.unwrap();
+    assert_eq!(total, 40001);
+    assert!(
+        estimate.abs_diff(total) < total / 10,
+        "{estimate} vs {total}"
+    );
+
+    let total = repo.user().count(active).await.unwrap();
+    let estimate = repo.user().estimate_count(active).await.unwrap();
+    assert_eq!(total, 30001);
+    assert!(
+        estimate.abs_diff(total) < total / 10,
+        "{estimate} vs {total}"
+    );
 }
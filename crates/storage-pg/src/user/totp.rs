@@ -0,0 +1,280 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{User, UserTotp};
+use mas_storage::{Clock, user::UserTotpRepository};
+use rand::RngCore;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, tracing::ExecuteExt};
+
+/// An implementation of [`UserTotpRepository`] for a PostgreSQL connection
+pub struct PgUserTotpRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgUserTotpRepository<'c> {
+    /// Create a new [`PgUserTotpRepository`] from an active PostgreSQL
+    /// connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct UserTotpLookup {
+    user_totp_id: Uuid,
+    user_id: Uuid,
+    encrypted_secret: String,
+    created_at: DateTime<Utc>,
+    confirmed_at: Option<DateTime<Utc>>,
+    last_used_at: Option<DateTime<Utc>>,
+    last_used_step: Option<i64>,
+}
+
+impl From<UserTotpLookup> for UserTotp {
+    fn from(value: UserTotpLookup) -> Self {
+        UserTotp {
+            id: value.user_totp_id.into(),
+            user_id: value.user_id.into(),
+            encrypted_secret: value.encrypted_secret,
+            created_at: value.created_at,
+            confirmed_at: value.confirmed_at,
+            last_used_at: value.last_used_at,
+            last_used_step: value.last_used_step,
+        }
+    }
+}
+
+#[async_trait]
+impl UserTotpRepository for PgUserTotpRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.user_totp.lookup",
+        skip_all,
+        fields(
+            db.query.text,
+            user_totp.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserTotp>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserTotpLookup,
+            r#"
+                SELECT user_totp_id
+                     , user_id
+                     , encrypted_secret
+                     , created_at
+                     , confirmed_at
+                     , last_used_at
+                     , last_used_step
+                FROM user_totps
+                WHERE user_totp_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_totp.find_for_user",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn find_for_user(&mut self, user: &User) -> Result<Option<UserTotp>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserTotpLookup,
+            r#"
+                SELECT user_totp_id
+                     , user_id
+                     , encrypted_secret
+                     , created_at
+                     , confirmed_at
+                     , last_used_at
+                     , last_used_step
+                FROM user_totps
+                WHERE user_id = $1
+            "#,
+            Uuid::from(user.id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_totp.add",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_totp.id,
+        ),
+        err,
+    )]
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        encrypted_secret: String,
+    ) -> Result<UserTotp, Self::Error> {
+        let created_at = clock.now();
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_totp.id", tracing::field::display(id));
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_totps
+                    (user_totp_id, user_id, encrypted_secret, created_at)
+                VALUES ($1, $2, $3, $4)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            &encrypted_secret,
+            created_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(UserTotp {
+            id,
+            user_id: user.id,
+            encrypted_secret,
+            created_at,
+            confirmed_at: None,
+            last_used_at: None,
+            last_used_step: None,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.user_totp.confirm",
+        skip_all,
+        fields(
+            db.query.text,
+            %totp.id,
+        ),
+        err,
+    )]
+    async fn confirm(
+        &mut self,
+        clock: &dyn Clock,
+        mut totp: UserTotp,
+        step: i64,
+    ) -> Result<UserTotp, Self::Error> {
+        let confirmed_at = clock.now();
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_totps
+                SET confirmed_at = $2
+                  , last_used_at = $2
+                  , last_used_step = $3
+                WHERE user_totp_id = $1
+                  AND confirmed_at IS NULL
+            "#,
+            Uuid::from(totp.id),
+            confirmed_at,
+            step,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        totp.confirmed_at = Some(confirmed_at);
+        totp.last_used_at = Some(confirmed_at);
+        totp.last_used_step = Some(step);
+        Ok(totp)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_totp.record_use",
+        skip_all,
+        fields(
+            db.query.text,
+            %totp.id,
+        ),
+        err,
+    )]
+    async fn record_use(
+        &mut self,
+        clock: &dyn Clock,
+        mut totp: UserTotp,
+        step: i64,
+    ) -> Result<Option<UserTotp>, Self::Error> {
+        let last_used_at = clock.now();
+
+        // Only accept a step later than the last one used, so that a code
+        // can't be replayed, even by concurrent requests
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_totps
+                SET last_used_at = $2
+                  , last_used_step = $3
+                WHERE user_totp_id = $1
+                  AND (last_used_step IS NULL OR last_used_step < $3)
+            "#,
+            Uuid::from(totp.id),
+            last_used_at,
+            step,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        if res.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        totp.last_used_at = Some(last_used_at);
+        totp.last_used_step = Some(step);
+        Ok(Some(totp))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_totp.remove",
+        skip_all,
+        fields(
+            db.query.text,
+            %totp.id,
+        ),
+        err,
+    )]
+    async fn remove(&mut self, totp: UserTotp) -> Result<(), Self::Error> {
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM user_totps
+                WHERE user_totp_id = $1
+            "#,
+            Uuid::from(totp.id),
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use mas_data_model::{User, UserTrustedDevice};
+use mas_storage::{Clock, user::UserTrustedDeviceRepository};
+use rand::RngCore;
+use sqlx::PgConnection;
+use ulid::Ulid;
+use uuid::Uuid;
+
+use crate::{DatabaseError, tracing::ExecuteExt};
+
+/// An implementation of [`UserTrustedDeviceRepository`] for a PostgreSQL
+/// connection
+pub struct PgUserTrustedDeviceRepository<'c> {
+    conn: &'c mut PgConnection,
+}
+
+impl<'c> PgUserTrustedDeviceRepository<'c> {
+    /// Create a new [`PgUserTrustedDeviceRepository`] from an active
+    /// PostgreSQL connection
+    pub fn new(conn: &'c mut PgConnection) -> Self {
+        Self { conn }
+    }
+}
+
+struct UserTrustedDeviceLookup {
+    user_trusted_device_id: Uuid,
+    user_id: Uuid,
+    user_agent: Option<String>,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserTrustedDeviceLookup> for UserTrustedDevice {
+    fn from(value: UserTrustedDeviceLookup) -> Self {
+        UserTrustedDevice {
+            id: value.user_trusted_device_id.into(),
+            user_id: value.user_id.into(),
+            user_agent: value.user_agent,
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            last_used_at: value.last_used_at,
+            revoked_at: value.revoked_at,
+        }
+    }
+}
+
+#[async_trait]
+impl UserTrustedDeviceRepository for PgUserTrustedDeviceRepository<'_> {
+    type Error = DatabaseError;
+
+    #[tracing::instrument(
+        name = "db.user_trusted_device.lookup",
+        skip_all,
+        fields(
+            db.query.text,
+            user_trusted_device.id = %id,
+        ),
+        err,
+    )]
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserTrustedDevice>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserTrustedDeviceLookup,
+            r#"
+                SELECT user_trusted_device_id
+                     , user_id
+                     , user_agent
+                     , created_at
+                     , expires_at
+                     , last_used_at
+                     , revoked_at
+                FROM user_trusted_devices
+                WHERE user_trusted_device_id = $1
+            "#,
+            Uuid::from(id),
+        )
+        .traced()
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(res.map(Into::into))
+    }
+
+    #[tracing::instrument(
+        name = "db.user_trusted_device.all_valid",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+        ),
+        err,
+    )]
+    async fn all_valid(
+        &mut self,
+        clock: &dyn Clock,
+        user: &User,
+    ) -> Result<Vec<UserTrustedDevice>, Self::Error> {
+        let res = sqlx::query_as!(
+            UserTrustedDeviceLookup,
+            r#"
+                SELECT user_trusted_device_id
+                     , user_id
+                     , user_agent
+                     , created_at
+                     , expires_at
+                     , last_used_at
+                     , revoked_at
+                FROM user_trusted_devices
+                WHERE user_id = $1
+                  AND revoked_at IS NULL
+                  AND expires_at > $2
+                ORDER BY user_trusted_device_id DESC
+            "#,
+            Uuid::from(user.id),
+            clock.now(),
+        )
+        .traced()
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        Ok(res.into_iter().map(Into::into).collect())
+    }
+
+    #[tracing::instrument(
+        name = "db.user_trusted_device.add",
+        skip_all,
+        fields(
+            db.query.text,
+            %user.id,
+            user_trusted_device.id,
+        ),
+        err,
+    )]
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        user_agent: Option<String>,
+        ttl: Duration,
+    ) -> Result<UserTrustedDevice, Self::Error> {
+        let created_at = clock.now();
+        let expires_at = created_at + ttl;
+        let id = Ulid::from_datetime_with_source(created_at.into(), rng);
+        tracing::Span::current().record("user_trusted_device.id", tracing::field::display(id));
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_trusted_devices
+                    (user_trusted_device_id, user_id, user_agent, created_at, expires_at)
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+            Uuid::from(id),
+            Uuid::from(user.id),
+            user_agent.as_deref(),
+            created_at,
+            expires_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        Ok(UserTrustedDevice {
+            id,
+            user_id: user.id,
+            user_agent,
+            created_at,
+            expires_at,
+            last_used_at: None,
+            revoked_at: None,
+        })
+    }
+
+    #[tracing::instrument(
+        name = "db.user_trusted_device.record_use",
+        skip_all,
+        fields(
+            db.query.text,
+            %device.id,
+        ),
+        err,
+    )]
+    async fn record_use(
+        &mut self,
+        clock: &dyn Clock,
+        mut device: UserTrustedDevice,
+    ) -> Result<UserTrustedDevice, Self::Error> {
+        let last_used_at = clock.now();
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_trusted_devices
+                SET last_used_at = $2
+                WHERE user_trusted_device_id = $1
+            "#,
+            Uuid::from(device.id),
+            last_used_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        device.last_used_at = Some(last_used_at);
+        Ok(device)
+    }
+
+    #[tracing::instrument(
+        name = "db.user_trusted_device.revoke",
+        skip_all,
+        fields(
+            db.query.text,
+            %device.id,
+        ),
+        err,
+    )]
+    async fn revoke(
+        &mut self,
+        clock: &dyn Clock,
+        mut device: UserTrustedDevice,
+    ) -> Result<UserTrustedDevice, Self::Error> {
+        let revoked_at = clock.now();
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_trusted_devices
+                SET revoked_at = $2
+                WHERE user_trusted_device_id = $1
+                  AND revoked_at IS NULL
+            "#,
+            Uuid::from(device.id),
+            revoked_at,
+        )
+        .traced()
+        .execute(&mut *self.conn)
+        .await?;
+
+        DatabaseError::ensure_affected_rows(&res, 1)?;
+
+        device.revoked_at = Some(revoked_at);
+        Ok(device)
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Repositories to interact with the audit log
+
+use async_trait::async_trait;
+use mas_data_model::{AuditEvent, AuditEventKind, User};
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{Clock, repository_impl};
+
+/// An [`AuditEventRepository`] helps interacting with the audit log, which
+/// records security-relevant events
+#[async_trait]
+pub trait AuditEventRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Record a new event in the audit log
+    ///
+    /// Returns the newly recorded [`AuditEvent`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user_id`: The ID of the user the event relates to, if any
+    /// * `kind`: What happened
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_id: Option<Ulid>,
+        kind: AuditEventKind,
+    ) -> Result<AuditEvent, Self::Error>;
+
+    /// List the events related to a user, oldest first
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The user to list the events of
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn all_for_user(&mut self, user: &User) -> Result<Vec<AuditEvent>, Self::Error>;
+}
+
+repository_impl!(AuditEventRepository:
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_id: Option<Ulid>,
+        kind: AuditEventKind,
+    ) -> Result<AuditEvent, Self::Error>;
+
+    async fn all_for_user(&mut self, user: &User) -> Result<Vec<AuditEvent>, Self::Error>;
+);
@@ -0,0 +1,94 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Helpers to delete large amounts of rows in batches
+//!
+//! Each batch is committed in its own transaction, which keeps transactions
+//! short, lets the database vacuum the deleted rows as we go, and makes it
+//! possible to resume a deletion which was interrupted by running it again.
+
+use async_trait::async_trait;
+
+use crate::{BoxRepository, RepositoryError, RepositoryFactory};
+
+/// A filter which selects the items to delete with [`delete_in_batches`]
+#[async_trait]
+pub trait BatchDeletionFilter: Send + Sync {
+    /// Delete at most `limit` items matching this filter
+    ///
+    /// Returns the number of items which were deleted
+    ///
+    /// # Parameters
+    ///
+    /// * `repo`: The repository to use to delete the items
+    /// * `limit`: The maximum number of items to delete
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RepositoryError`] if the underlying repository fails
+    async fn delete_batch(
+        &self,
+        repo: &mut BoxRepository,
+        limit: usize,
+    ) -> Result<usize, RepositoryError>;
+}
+
+/// The progress of a [`delete_in_batches`] operation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchDeletionProgress {
+    /// The number of batches which were committed so far
+    pub batches: usize,
+
+    /// The total number of items deleted so far
+    pub deleted: usize,
+}
+
+/// Delete all the items matching a filter, in batches of `batch_size` items
+///
+/// Each batch is deleted in a separate transaction, which is committed before
+/// starting the next one. The `progress_callback` is called after each
+/// committed batch.
+///
+/// Returns the final progress once there are no more items to delete
+///
+/// # Parameters
+///
+/// * `repository_factory`: The factory used to get a repository for each batch
+/// * `filter`: The filter selecting the items to delete
+/// * `batch_size`: The maximum number of items to delete in each batch
+/// * `progress_callback`: A function called with the progress after each batch
+///
+/// # Errors
+///
+/// Returns [`RepositoryError`] if the underlying repository fails. The batches
+/// committed before the error are not rolled back.
+pub async fn delete_in_batches<R, F>(
+    repository_factory: &R,
+    filter: &F,
+    batch_size: usize,
+    mut progress_callback: impl FnMut(BatchDeletionProgress) + Send,
+) -> Result<BatchDeletionProgress, RepositoryError>
+where
+    R: RepositoryFactory + Sync + ?Sized,
+    F: BatchDeletionFilter + ?Sized,
+{
+    let batch_size = batch_size.max(1);
+    let mut progress = BatchDeletionProgress::default();
+
+    loop {
+        let mut repo = repository_factory.create().await?;
+        let deleted = filter.delete_batch(&mut repo, batch_size).await?;
+        repo.save().await?;
+
+        progress.batches += 1;
+        progress.deleted += deleted;
+        progress_callback(progress);
+
+        // A partial batch means there is nothing left to delete
+        if deleted < batch_size {
+            return Ok(progress);
+        }
+    }
+}
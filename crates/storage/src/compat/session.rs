@@ -8,7 +8,9 @@ use std::net::IpAddr;
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use mas_data_model::{BrowserSession, CompatSession, CompatSsoLogin, Device, User};
+use mas_data_model::{
+    BrowserSession, CompatSession, CompatSsoLogin, Device, IpLocation, Session, User,
+};
 use rand_core::RngCore;
 use ulid::Ulid;
 
@@ -62,6 +64,7 @@ pub struct CompatSessionFilter<'a> {
     state: Option<CompatSessionState>,
     auth_type: Option<CompatSessionType>,
     device: Option<&'a Device>,
+    device_prefix: Option<&'a str>,
     last_active_before: Option<DateTime<Utc>>,
     last_active_after: Option<DateTime<Utc>>,
 }
@@ -99,6 +102,21 @@ impl<'a> CompatSessionFilter<'a> {
         self.device
     }
 
+    /// Only return sessions whose device ID starts with the given prefix
+    #[must_use]
+    pub fn with_device_prefix(mut self, device_prefix: &'a str) -> Self {
+        self.device_prefix = Some(device_prefix);
+        self
+    }
+
+    /// Get the device prefix filter
+    ///
+    /// Returns [`None`] if no device prefix filter was set
+    #[must_use]
+    pub fn device_prefix(&self) -> Option<&'a str> {
+        self.device_prefix
+    }
+
     /// Set the browser session filter
     #[must_use]
     pub fn for_browser_session(mut self, browser_session: &'a BrowserSession) -> Self {
@@ -217,6 +235,8 @@ pub trait CompatSessionRepository: Send + Sync {
     /// * `is_synapse_admin`: Whether the session is a synapse admin session
     /// * `human_name`: The human-readable name of the session provided by the
     ///   client or the user
+    /// * `impersonated_by`: The admin API session which mints this session to
+    ///   act as the user, if this is an impersonation
     ///
     /// # Errors
     ///
@@ -231,6 +251,7 @@ pub trait CompatSessionRepository: Send + Sync {
         browser_session: Option<&BrowserSession>,
         is_synapse_admin: bool,
         human_name: Option<String>,
+        impersonated_by: Option<&Session>,
     ) -> Result<CompatSession, Self::Error>;
 
     /// End a compat session
@@ -298,19 +319,38 @@ pub trait CompatSessionRepository: Send + Sync {
     /// Returns [`Self::Error`] if the underlying repository fails
     async fn count(&mut self, filter: CompatSessionFilter<'_>) -> Result<usize, Self::Error>;
 
+    /// Estimate the number of [`CompatSession`] with the given filter
+    ///
+    /// This uses the statistics of the database instead of counting the rows,
+    /// which is much faster on large tables, but can be off. Small counts are
+    /// still exact.
+    ///
+    /// # Parameters
+    ///
+    /// * `filter`: The filter parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn estimate_count(
+        &mut self,
+        filter: CompatSessionFilter<'_>,
+    ) -> Result<usize, Self::Error>;
+
     /// Record a batch of [`CompatSession`] activity
     ///
     /// # Parameters
     ///
     /// * `activity`: A list of tuples containing the session ID, the last
-    ///   activity timestamp and the IP address of the client
+    ///   activity timestamp, the IP address of the client and where that
+    ///   address is located. The location is ignored if there is no IP address.
     ///
     /// # Errors
     ///
     /// Returns [`Self::Error`] if the underlying repository fails
     async fn record_batch_activity(
         &mut self,
-        activity: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>)>,
+        activity: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>, IpLocation)>,
     ) -> Result<(), Self::Error>;
 
     /// Record the user agent of a compat session
@@ -358,6 +398,7 @@ repository_impl!(CompatSessionRepository:
         browser_session: Option<&BrowserSession>,
         is_synapse_admin: bool,
         human_name: Option<String>,
+        impersonated_by: Option<&Session>,
     ) -> Result<CompatSession, Self::Error>;
 
     async fn finish(
@@ -379,10 +420,11 @@ repository_impl!(CompatSessionRepository:
     ) -> Result<Page<(CompatSession, Option<CompatSsoLogin>)>, Self::Error>;
 
     async fn count(&mut self, filter: CompatSessionFilter<'_>) -> Result<usize, Self::Error>;
+    async fn estimate_count(&mut self, filter: CompatSessionFilter<'_>) -> Result<usize, Self::Error>;
 
     async fn record_batch_activity(
         &mut self,
-        activity: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>)>,
+        activity: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>, IpLocation)>,
     ) -> Result<(), Self::Error>;
 
     async fn record_user_agent(
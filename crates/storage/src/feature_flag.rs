@@ -0,0 +1,134 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Repositories to interact with the feature flag overrides set through the
+//! admin API
+
+use async_trait::async_trait;
+use mas_data_model::{FeatureFlag, FeatureFlagOverride, FeatureFlagRollout};
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{Clock, Page, Pagination, repository_impl};
+
+/// A [`FeatureFlagOverrideRepository`] helps interacting with the rollouts of
+/// feature flags which override the ones from the configuration
+#[async_trait]
+pub trait FeatureFlagOverrideRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Lookup an override by its ID
+    ///
+    /// Returns `None` if no override was found
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the override to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<FeatureFlagOverride>, Self::Error>;
+
+    /// Find the override of a feature flag
+    ///
+    /// Returns `None` if the flag is not overridden
+    ///
+    /// # Parameters
+    ///
+    /// * `flag`: The feature flag
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find(&mut self, flag: FeatureFlag)
+    -> Result<Option<FeatureFlagOverride>, Self::Error>;
+
+    /// Override the rollout of a feature flag, replacing any previous override
+    /// of the same flag
+    ///
+    /// Returns the override, which keeps its ID if the flag was already
+    /// overridden
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `flag`: The feature flag to override
+    /// * `rollout`: The rollout to use for this flag
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn set(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        flag: FeatureFlag,
+        rollout: FeatureFlagRollout,
+    ) -> Result<FeatureFlagOverride, Self::Error>;
+
+    /// Remove an override, so that the rollout from the configuration applies
+    /// again
+    ///
+    /// # Parameters
+    ///
+    /// * `feature_flag_override`: The override to remove
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn remove(
+        &mut self,
+        feature_flag_override: FeatureFlagOverride,
+    ) -> Result<(), Self::Error>;
+
+    /// List the overrides, ordered by ID
+    ///
+    /// # Parameters
+    ///
+    /// * `pagination`: The pagination parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn list(
+        &mut self,
+        pagination: Pagination,
+    ) -> Result<Page<FeatureFlagOverride>, Self::Error>;
+
+    /// Count the overrides
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn count(&mut self) -> Result<usize, Self::Error>;
+}
+
+repository_impl!(FeatureFlagOverrideRepository:
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<FeatureFlagOverride>, Self::Error>;
+
+    async fn find(&mut self, flag: FeatureFlag)
+    -> Result<Option<FeatureFlagOverride>, Self::Error>;
+
+    async fn set(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        flag: FeatureFlag,
+        rollout: FeatureFlagRollout,
+    ) -> Result<FeatureFlagOverride, Self::Error>;
+
+    async fn remove(&mut self, feature_flag_override: FeatureFlagOverride)
+    -> Result<(), Self::Error>;
+
+    async fn list(
+        &mut self,
+        pagination: Pagination,
+    ) -> Result<Page<FeatureFlagOverride>, Self::Error>;
+
+    async fn count(&mut self) -> Result<usize, Self::Error>;
+);
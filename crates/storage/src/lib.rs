@@ -111,16 +111,21 @@
 #![deny(clippy::future_not_send, missing_docs)]
 #![allow(clippy::module_name_repetitions)]
 
+pub mod batch;
 pub mod clock;
 pub mod pagination;
 pub(crate) mod repository;
 mod utils;
 
 pub mod app_session;
+pub mod audit_event;
 pub mod compat;
+pub mod feature_flag;
+pub mod login_stats;
 pub mod oauth2;
 pub mod policy_data;
 pub mod queue;
+pub mod scim;
 pub mod upstream_oauth2;
 pub mod user;
 
@@ -128,8 +133,8 @@ pub use self::{
     clock::{Clock, SystemClock},
     pagination::{Page, Pagination},
     repository::{
-        BoxRepository, BoxRepositoryFactory, Repository, RepositoryAccess, RepositoryError,
-        RepositoryFactory, RepositoryTransaction,
+        ArcRepositoryFactory, BoxRepository, BoxRepositoryFactory, Repository, RepositoryAccess,
+        RepositoryError, RepositoryFactory, RepositoryTransaction,
     },
     utils::{BoxClock, BoxRng, MapErr},
 };
@@ -0,0 +1,104 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Repositories to interact with the daily login statistics rollup
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use mas_data_model::{Client, DailyLoginStats, UpstreamOAuthProvider};
+
+use crate::repository_impl;
+
+/// A [`LoginStatsRepository`] helps interacting with the daily login
+/// statistics, aggregated per upstream OAuth 2.0 provider and per OAuth 2.0
+/// client
+#[async_trait]
+pub trait LoginStatsRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Get the last day for which the statistics were aggregated
+    ///
+    /// Returns `None` if the statistics were never aggregated
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn last_day(&mut self) -> Result<Option<NaiveDate>, Self::Error>;
+
+    /// Aggregate the statistics again, starting from the given day
+    ///
+    /// Returns the number of daily entries which were updated
+    ///
+    /// # Parameters
+    ///
+    /// * `since`: The first day to aggregate, or `None` to aggregate everything
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn refresh(&mut self, since: Option<NaiveDate>) -> Result<usize, Self::Error>;
+
+    /// List the daily statistics of an upstream OAuth 2.0 provider, ordered
+    /// by day
+    ///
+    /// Days without any login are not included
+    ///
+    /// # Parameters
+    ///
+    /// * `provider`: The upstream OAuth 2.0 provider
+    /// * `since`: The first day to include
+    /// * `until`: The last day to include
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn for_upstream_oauth_provider(
+        &mut self,
+        provider: &UpstreamOAuthProvider,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<Vec<DailyLoginStats>, Self::Error>;
+
+    /// List the daily statistics of an OAuth 2.0 client, ordered by day
+    ///
+    /// Days without any login are not included
+    ///
+    /// # Parameters
+    ///
+    /// * `client`: The OAuth 2.0 client
+    /// * `since`: The first day to include
+    /// * `until`: The last day to include
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn for_oauth2_client(
+        &mut self,
+        client: &Client,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<Vec<DailyLoginStats>, Self::Error>;
+}
+
+repository_impl!(LoginStatsRepository:
+    async fn last_day(&mut self) -> Result<Option<NaiveDate>, Self::Error>;
+
+    async fn refresh(&mut self, since: Option<NaiveDate>) -> Result<usize, Self::Error>;
+
+    async fn for_upstream_oauth_provider(
+        &mut self,
+        provider: &UpstreamOAuthProvider,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<Vec<DailyLoginStats>, Self::Error>;
+
+    async fn for_oauth2_client(
+        &mut self,
+        client: &Client,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<Vec<DailyLoginStats>, Self::Error>;
+);
@@ -5,12 +5,15 @@
 // Please see LICENSE files in the repository root for full details.
 
 use async_trait::async_trait;
-use chrono::Duration;
+use chrono::{DateTime, Duration, Utc};
 use mas_data_model::{AccessToken, Session};
 use rand_core::RngCore;
 use ulid::Ulid;
 
-use crate::{Clock, repository_impl};
+use crate::{
+    BoxRepository, Clock, RepositoryAccess, RepositoryError, batch::BatchDeletionFilter,
+    repository_impl,
+};
 
 /// An [`OAuth2AccessTokenRepository`] helps interacting with [`AccessToken`]
 /// saved in the storage backend
@@ -91,6 +94,24 @@ pub trait OAuth2AccessTokenRepository: Send + Sync {
         access_token: AccessToken,
     ) -> Result<AccessToken, Self::Error>;
 
+    /// Revoke all the active access tokens of a session
+    ///
+    /// Returns the number of access tokens which were revoked
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `session`: The session to revoke the access tokens of
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn revoke_for_session(
+        &mut self,
+        clock: &dyn Clock,
+        session: &Session,
+    ) -> Result<usize, Self::Error>;
+
     /// Mark the access token as used, to track when it was first used
     ///
     /// # Parameters
@@ -107,18 +128,41 @@ pub trait OAuth2AccessTokenRepository: Send + Sync {
         access_token: AccessToken,
     ) -> Result<AccessToken, Self::Error>;
 
+    /// Bind the access token to a DPoP key
+    ///
+    /// Returns the bound access token
+    ///
+    /// # Parameters
+    ///
+    /// * `access_token`: The access token to bind
+    /// * `jkt`: The JWK SHA-256 thumbprint of the key
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn bind_dpop_key(
+        &mut self,
+        access_token: AccessToken,
+        jkt: String,
+    ) -> Result<AccessToken, Self::Error>;
+
     /// Cleanup revoked access tokens
     ///
     /// Returns the number of access tokens that were cleaned up
     ///
     /// # Parameters
     ///
-    /// * `clock`: The clock used to get the current time
+    /// * `revoked_before`: Only cleanup access tokens revoked before this time
+    /// * `limit`: The maximum number of access tokens to cleanup
     ///
     /// # Errors
     ///
     /// Returns [`Self::Error`] if the underlying repository fails
-    async fn cleanup_revoked(&mut self, clock: &dyn Clock) -> Result<usize, Self::Error>;
+    async fn cleanup_revoked(
+        &mut self,
+        revoked_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
 }
 
 repository_impl!(OAuth2AccessTokenRepository:
@@ -144,11 +188,48 @@ repository_impl!(OAuth2AccessTokenRepository:
         access_token: AccessToken,
     ) -> Result<AccessToken, Self::Error>;
 
+    async fn revoke_for_session(
+        &mut self,
+        clock: &dyn Clock,
+        session: &Session,
+    ) -> Result<usize, Self::Error>;
+
     async fn mark_used(
         &mut self,
         clock: &dyn Clock,
         access_token: AccessToken,
     ) -> Result<AccessToken, Self::Error>;
 
-    async fn cleanup_revoked(&mut self, clock: &dyn Clock) -> Result<usize, Self::Error>;
+    async fn bind_dpop_key(
+        &mut self,
+        access_token: AccessToken,
+        jkt: String,
+    ) -> Result<AccessToken, Self::Error>;
+
+    async fn cleanup_revoked(
+        &mut self,
+        revoked_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
 );
+
+/// A [`BatchDeletionFilter`] selecting the access tokens revoked before a
+/// given time
+#[derive(Debug, Clone, Copy)]
+pub struct RevokedAccessTokens {
+    /// Only select access tokens revoked before this time
+    pub revoked_before: DateTime<Utc>,
+}
+
+#[async_trait]
+impl BatchDeletionFilter for RevokedAccessTokens {
+    async fn delete_batch(
+        &self,
+        repo: &mut BoxRepository,
+        limit: usize,
+    ) -> Result<usize, RepositoryError> {
+        repo.oauth2_access_token()
+            .cleanup_revoked(self.revoked_before, limit)
+            .await
+    }
+}
@@ -4,15 +4,72 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::num::NonZeroU32;
+
 use async_trait::async_trait;
 use mas_data_model::{AuthorizationCode, AuthorizationGrant, Client, Session};
-use oauth2_types::{requests::ResponseMode, scope::Scope};
+use oauth2_types::{
+    authorization_details::AuthorizationDetail, claims::ClaimsRequest, requests::ResponseMode,
+    scope::Scope,
+};
 use rand_core::RngCore;
 use ulid::Ulid;
 use url::Url;
 
 use crate::{Clock, repository_impl};
 
+/// Parameters used to create a new [`AuthorizationGrant`]
+#[derive(Debug, Clone, Default)]
+pub struct OAuth2AuthorizationGrantParams {
+    /// The authorization code used by this grant, if the `code` `response_type`
+    /// was requested
+    pub code: Option<AuthorizationCode>,
+
+    /// The state the client sent, if set
+    pub state: Option<String>,
+
+    /// The nonce the client sent, if set
+    pub nonce: Option<String>,
+
+    /// Whether the `id_token` `response_type` was requested
+    pub response_type_id_token: bool,
+
+    /// The `login_hint` the client sent, if set
+    pub login_hint: Option<String>,
+
+    /// The locale detected when the user asked for the authorization grant
+    pub locale: Option<String>,
+
+    /// The hashed device identifier the client sent, if set
+    pub device_fingerprint: Option<String>,
+
+    /// The rich authorization request details the client sent, if any
+    pub authorization_details: Vec<AuthorizationDetail>,
+
+    /// The resource indicator the client sent, if set
+    pub resource: Option<Url>,
+
+    /// The weakest ACR value the session must satisfy for the grant to be
+    /// fulfilled, if any
+    pub required_acr: Option<String>,
+
+    /// The maximum age of the authentication, in seconds, the client sent, if
+    /// set
+    pub max_age: Option<NonZeroU32>,
+
+    /// Whether the client asked for the user to authenticate again with
+    /// `prompt=login`
+    pub prompt_login: bool,
+
+    /// Whether the client asked for the user to consent again with
+    /// `prompt=consent`
+    pub prompt_consent: bool,
+
+    /// The claims the client requested individually with the `claims`
+    /// parameter, if set
+    pub claims: Option<ClaimsRequest>,
+}
+
 /// An [`OAuth2AuthorizationGrantRepository`] helps interacting with
 /// [`AuthorizationGrant`] saved in the storage backend
 #[async_trait]
@@ -31,21 +88,13 @@ pub trait OAuth2AuthorizationGrantRepository: Send + Sync {
     /// * `client`: The client that requested the authorization grant
     /// * `redirect_uri`: The redirect URI the client requested
     /// * `scope`: The scope the client requested
-    /// * `code`: The authorization code used by this grant, if the `code`
-    ///   `response_type` was requested
-    /// * `state`: The state the client sent, if set
-    /// * `nonce`: The nonce the client sent, if set
     /// * `response_mode`: The response mode the client requested
-    /// * `response_type_id_token`: Whether the `id_token` `response_type` was
-    ///   requested
-    /// * `login_hint`: The login_hint the client sent, if set
-    /// * `locale`: The locale the detected when the user asked for the
-    ///   authorization grant
+    /// * `params`: The other parameters the client sent. See the fields of
+    ///   [`OAuth2AuthorizationGrantParams`]
     ///
     /// # Errors
     ///
     /// Returns [`Self::Error`] if the underlying repository fails
-    #[allow(clippy::too_many_arguments)]
     async fn add(
         &mut self,
         rng: &mut (dyn RngCore + Send),
@@ -53,13 +102,8 @@ pub trait OAuth2AuthorizationGrantRepository: Send + Sync {
         client: &Client,
         redirect_uri: Url,
         scope: Scope,
-        code: Option<AuthorizationCode>,
-        state: Option<String>,
-        nonce: Option<String>,
         response_mode: ResponseMode,
-        response_type_id_token: bool,
-        login_hint: Option<String>,
-        locale: Option<String>,
+        params: OAuth2AuthorizationGrantParams,
     ) -> Result<AuthorizationGrant, Self::Error>;
 
     /// Lookup an authorization grant by its ID
@@ -137,13 +181,8 @@ repository_impl!(OAuth2AuthorizationGrantRepository:
         client: &Client,
         redirect_uri: Url,
         scope: Scope,
-        code: Option<AuthorizationCode>,
-        state: Option<String>,
-        nonce: Option<String>,
         response_mode: ResponseMode,
-        response_type_id_token: bool,
-        login_hint: Option<String>,
-        locale: Option<String>,
+        params: OAuth2AuthorizationGrantParams,
     ) -> Result<AuthorizationGrant, Self::Error>;
 
     async fn lookup(&mut self, id: Ulid) -> Result<Option<AuthorizationGrant>, Self::Error>;
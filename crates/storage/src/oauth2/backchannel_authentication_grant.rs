@@ -0,0 +1,262 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use mas_data_model::{BackchannelAuthenticationGrant, BrowserSession, Client, Session, User};
+use oauth2_types::scope::Scope;
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{
+    BoxRepository, Clock, RepositoryAccess, RepositoryError, batch::BatchDeletionFilter,
+    repository_impl,
+};
+
+/// Parameters used to create a new [`BackchannelAuthenticationGrant`]
+pub struct OAuth2BackchannelAuthenticationGrantParams<'a> {
+    /// The client which started the backchannel authentication
+    pub client: &'a Client,
+
+    /// The user which has to approve the request
+    pub user: &'a User,
+
+    /// The scope requested by the client
+    pub scope: Scope,
+
+    /// The identifier which the client uses to get the tokens
+    pub auth_req_id: String,
+
+    /// The message to display on the approval screen, if any
+    pub binding_message: Option<String>,
+
+    /// The token used to notify the client, if it uses the `ping` mode
+    pub client_notification_token: Option<String>,
+
+    /// After how long the request expires
+    pub expires_in: Duration,
+}
+
+/// An [`OAuth2BackchannelAuthenticationGrantRepository`] helps interacting
+/// with [`BackchannelAuthenticationGrant`] saved in the storage backend.
+#[async_trait]
+pub trait OAuth2BackchannelAuthenticationGrantRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Create a new backchannel authentication grant
+    ///
+    /// Returns the newly created backchannel authentication grant
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: A random number generator
+    /// * `clock`: The clock used to generate timestamps
+    /// * `params`: The parameters used to create the grant. See the fields of
+    ///   [`OAuth2BackchannelAuthenticationGrantParams`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        params: OAuth2BackchannelAuthenticationGrantParams<'_>,
+    ) -> Result<BackchannelAuthenticationGrant, Self::Error>;
+
+    /// Lookup a backchannel authentication grant by its ID
+    ///
+    /// Returns the backchannel authentication grant if found, [`None`]
+    /// otherwise
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the backchannel authentication grant
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(
+        &mut self,
+        id: Ulid,
+    ) -> Result<Option<BackchannelAuthenticationGrant>, Self::Error>;
+
+    /// Lookup a backchannel authentication grant by its `auth_req_id`
+    ///
+    /// Returns the backchannel authentication grant if found, [`None`]
+    /// otherwise
+    ///
+    /// # Parameters
+    ///
+    /// * `auth_req_id`: The `auth_req_id` given to the client
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_by_auth_req_id(
+        &mut self,
+        auth_req_id: &str,
+    ) -> Result<Option<BackchannelAuthenticationGrant>, Self::Error>;
+
+    /// Mark the backchannel authentication grant as fulfilled with the given
+    /// browser session
+    ///
+    /// Returns the updated backchannel authentication grant
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `grant`: The backchannel authentication grant to fulfill
+    /// * `browser_session`: The browser session which was used to approve the
+    ///   request
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails or if the
+    /// grant is not in the [`Pending`] state
+    ///
+    /// [`Pending`]: mas_data_model::BackchannelAuthenticationGrantState::Pending
+    async fn fulfill(
+        &mut self,
+        clock: &dyn Clock,
+        grant: BackchannelAuthenticationGrant,
+        browser_session: &BrowserSession,
+    ) -> Result<BackchannelAuthenticationGrant, Self::Error>;
+
+    /// Mark the backchannel authentication grant as rejected with the given
+    /// browser session
+    ///
+    /// Returns the updated backchannel authentication grant
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `grant`: The backchannel authentication grant to reject
+    /// * `browser_session`: The browser session which was used to reject the
+    ///   request
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails or if the
+    /// grant is not in the [`Pending`] state
+    ///
+    /// [`Pending`]: mas_data_model::BackchannelAuthenticationGrantState::Pending
+    async fn reject(
+        &mut self,
+        clock: &dyn Clock,
+        grant: BackchannelAuthenticationGrant,
+        browser_session: &BrowserSession,
+    ) -> Result<BackchannelAuthenticationGrant, Self::Error>;
+
+    /// Mark the backchannel authentication grant as exchanged and store the
+    /// session which was created
+    ///
+    /// Returns the updated backchannel authentication grant
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `grant`: The backchannel authentication grant to exchange
+    /// * `session`: The OAuth 2.0 session which was created
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails or if the
+    /// grant is not in the [`Fulfilled`] state
+    ///
+    /// [`Fulfilled`]: mas_data_model::BackchannelAuthenticationGrantState::Fulfilled
+    async fn exchange(
+        &mut self,
+        clock: &dyn Clock,
+        grant: BackchannelAuthenticationGrant,
+        session: &Session,
+    ) -> Result<BackchannelAuthenticationGrant, Self::Error>;
+
+    /// Cleanup the backchannel authentication grants which expired
+    ///
+    /// Returns the number of grants that were cleaned up
+    ///
+    /// # Parameters
+    ///
+    /// * `expired_before`: Only cleanup grants which expired before this time
+    /// * `limit`: The maximum number of grants to cleanup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn cleanup_expired(
+        &mut self,
+        expired_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
+}
+
+repository_impl!(OAuth2BackchannelAuthenticationGrantRepository:
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        params: OAuth2BackchannelAuthenticationGrantParams<'_>,
+    ) -> Result<BackchannelAuthenticationGrant, Self::Error>;
+
+    async fn lookup(
+        &mut self,
+        id: Ulid,
+    ) -> Result<Option<BackchannelAuthenticationGrant>, Self::Error>;
+
+    async fn find_by_auth_req_id(
+        &mut self,
+        auth_req_id: &str,
+    ) -> Result<Option<BackchannelAuthenticationGrant>, Self::Error>;
+
+    async fn fulfill(
+        &mut self,
+        clock: &dyn Clock,
+        grant: BackchannelAuthenticationGrant,
+        browser_session: &BrowserSession,
+    ) -> Result<BackchannelAuthenticationGrant, Self::Error>;
+
+    async fn reject(
+        &mut self,
+        clock: &dyn Clock,
+        grant: BackchannelAuthenticationGrant,
+        browser_session: &BrowserSession,
+    ) -> Result<BackchannelAuthenticationGrant, Self::Error>;
+
+    async fn exchange(
+        &mut self,
+        clock: &dyn Clock,
+        grant: BackchannelAuthenticationGrant,
+        session: &Session,
+    ) -> Result<BackchannelAuthenticationGrant, Self::Error>;
+
+    async fn cleanup_expired(
+        &mut self,
+        expired_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
+);
+
+/// A [`BatchDeletionFilter`] selecting the backchannel authentication grants
+/// which expired before a given time
+#[derive(Debug, Clone, Copy)]
+pub struct ExpiredBackchannelAuthenticationGrants {
+    /// Only select grants which expired before this time
+    pub expired_before: DateTime<Utc>,
+}
+
+#[async_trait]
+impl BatchDeletionFilter for ExpiredBackchannelAuthenticationGrants {
+    async fn delete_batch(
+        &self,
+        repo: &mut BoxRepository,
+        limit: usize,
+    ) -> Result<usize, RepositoryError> {
+        repo.oauth2_backchannel_authentication_grant()
+            .cleanup_expired(self.expired_before, limit)
+            .await
+    }
+}
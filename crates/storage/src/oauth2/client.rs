@@ -7,16 +7,131 @@
 use std::collections::{BTreeMap, BTreeSet};
 
 use async_trait::async_trait;
-use mas_data_model::Client;
-use mas_iana::{jose::JsonWebSignatureAlg, oauth::OAuthClientAuthenticationMethod};
+use chrono::{DateTime, Duration, Utc};
+use mas_data_model::{AccessTokenFormat, Client, RefreshTokenRotation, ScopePolicy, TokenAudience};
+use mas_iana::{
+    jose::{JsonWebEncryptionAlg, JsonWebEncryptionEnc, JsonWebSignatureAlg},
+    oauth::OAuthClientAuthenticationMethod,
+};
 use mas_jose::jwk::PublicJsonWebKeySet;
-use oauth2_types::{oidc::ApplicationType, requests::GrantType};
+use oauth2_types::{oidc::ApplicationType, requests::GrantType, scope::Scope};
 use rand_core::RngCore;
 use ulid::Ulid;
 use url::Url;
 
 use crate::{Clock, repository_impl};
 
+/// Parameters used to add or replace a static [`Client`]
+///
+/// The defaults describe a client without secret, redirect URIs nor any of the
+/// optional features enabled.
+#[derive(Debug, Clone, Default)]
+pub struct OAuth2StaticClientParams {
+    /// The human-readable name of the client, if any
+    pub client_name: Option<String>,
+
+    /// The encrypted client secret, if any
+    pub encrypted_client_secret: Option<String>,
+
+    /// The client JWKS, if any
+    pub jwks: Option<PublicJsonWebKeySet>,
+
+    /// The client JWKS URI, if any
+    pub jwks_uri: Option<Url>,
+
+    /// The list of redirect URIs used by this client
+    pub redirect_uris: Vec<Url>,
+
+    /// Whether the client can only start authorization requests through the
+    /// pushed authorization request endpoint
+    pub require_pushed_authorization_requests: bool,
+
+    /// The algorithm used to sign JWT-secured authorization responses, if any
+    pub authorization_signed_response_alg: Option<JsonWebSignatureAlg>,
+
+    /// The scope this client is allowed to request with the client credentials
+    /// grant, if it is a service account
+    pub service_account_scope: Option<Scope>,
+
+    /// The endpoint to notify when a backchannel authentication request
+    /// completes, if the client uses the `ping` mode
+    pub backchannel_client_notification_endpoint: Option<Url>,
+
+    /// The resources this client can request tokens for
+    pub allowed_resources: Vec<Url>,
+
+    /// Whether the refresh tokens of this client are rotated each time they are
+    /// used
+    pub refresh_token_rotation: RefreshTokenRotation,
+
+    /// The URI on which the client receives back-channel logout tokens, if any
+    pub backchannel_logout_uri: Option<Url>,
+
+    /// Whether the `sid` claim is included in back-channel logout tokens
+    pub backchannel_logout_session_required: bool,
+
+    /// Whether the `sub` claim is included in back-channel logout tokens
+    pub backchannel_logout_include_sub: bool,
+
+    /// The list of URIs the client can redirect to after an RP-initiated logout
+    pub post_logout_redirect_uris: Vec<Url>,
+
+    /// The format of the access tokens issued to this client
+    pub access_token_format: AccessTokenFormat,
+
+    /// The lifetime of the access tokens issued to this client, if it overrides
+    /// the default one
+    pub access_token_ttl: Option<Duration>,
+
+    /// The lifetime of the refresh tokens issued to this client, if they expire
+    pub refresh_token_ttl: Option<Duration>,
+
+    /// The lifetime of the device codes issued to this client, if it overrides
+    /// the default one
+    pub device_code_ttl: Option<Duration>,
+
+    /// The algorithm request objects sent by this client must be signed with,
+    /// if any
+    pub request_object_signing_alg: Option<JsonWebSignatureAlg>,
+
+    /// Whether the client must send its authorization request parameters in a
+    /// signed request object
+    pub require_signed_request_object: bool,
+
+    /// The algorithm used to encrypt the ID tokens issued to this client, if
+    /// they are encrypted
+    pub id_token_encrypted_response_alg: Option<JsonWebEncryptionAlg>,
+
+    /// The algorithm used to encrypt the content of the ID tokens issued to
+    /// this client
+    pub id_token_encrypted_response_enc: Option<JsonWebEncryptionEnc>,
+
+    /// The ACR values required by default when the client doesn't send
+    /// `acr_values`
+    pub default_acr_values: Vec<String>,
+
+    /// The audience of the tokens this client accepts when introspecting them,
+    /// if it is a resource server
+    pub resource_server_audience: Option<TokenAudience>,
+
+    /// When the client secret stops being accepted, if it expires
+    pub client_secret_expires_at: Option<DateTime<Utc>>,
+
+    /// The encrypted secret accepted alongside the current one during a
+    /// rotation, if any
+    pub encrypted_next_client_secret: Option<String>,
+
+    /// When the next client secret stops being accepted, if it expires
+    pub next_client_secret_expires_at: Option<DateTime<Utc>>,
+
+    /// The sector identifier used to compute the pairwise subject identifiers
+    /// given to this client, if any
+    pub pairwise_sector_identifier: Option<String>,
+
+    /// The scopes this client is allowed to request, if restricted
+    pub scope_policy: Option<ScopePolicy>,
+}
+
 /// An [`OAuth2ClientRepository`] helps interacting with [`Client`] saved in the
 /// storage backend
 #[async_trait]
@@ -109,6 +224,32 @@ pub trait OAuth2ClientRepository: Send + Sync {
     ///   when using the `client_secret_jwt` or `private_key_jwt` authentication
     ///   methods
     /// * `initiate_login_uri`: The URI used to initiate a login, if given
+    /// * `authorization_signed_response_alg`: The algorithm used to sign
+    ///   JWT-secured authorization responses, if given
+    /// * `backchannel_logout_uri`: The URI on which the client receives
+    ///   back-channel logout tokens, if given
+    /// * `backchannel_logout_session_required`: Whether the client requires the
+    ///   `sid` claim in back-channel logout tokens
+    /// * `post_logout_redirect_uris`: The list of URIs the client can redirect
+    ///   to after an RP-initiated logout
+    /// * `access_token_ttl`: The lifetime of the access tokens issued to this
+    ///   client, if it overrides the default one
+    /// * `refresh_token_ttl`: The lifetime of the refresh tokens issued to this
+    ///   client, if they expire
+    /// * `device_code_ttl`: The lifetime of the device codes issued to this
+    ///   client, if it overrides the default one
+    /// * `request_object_signing_alg`: The algorithm request objects sent by
+    ///   this client must be signed with, if any
+    /// * `require_signed_request_object`: Whether the client must send its
+    ///   authorization request parameters in a signed request object
+    /// * `id_token_encrypted_response_alg`: The algorithm used to encrypt the
+    ///   ID tokens issued to this client, if they are encrypted
+    /// * `id_token_encrypted_response_enc`: The algorithm used to encrypt the
+    ///   content of the ID tokens issued to this client
+    /// * `default_acr_values`: The ACR values required by default when the
+    ///   client doesn't send `acr_values`
+    /// * `pairwise_sector_identifier`: The sector identifier used to compute
+    ///   the pairwise subject identifiers given to this client, if any
     ///
     /// # Errors
     ///
@@ -135,6 +276,19 @@ pub trait OAuth2ClientRepository: Send + Sync {
         token_endpoint_auth_method: Option<OAuthClientAuthenticationMethod>,
         token_endpoint_auth_signing_alg: Option<JsonWebSignatureAlg>,
         initiate_login_uri: Option<Url>,
+        authorization_signed_response_alg: Option<JsonWebSignatureAlg>,
+        backchannel_logout_uri: Option<Url>,
+        backchannel_logout_session_required: bool,
+        post_logout_redirect_uris: Vec<Url>,
+        access_token_ttl: Option<Duration>,
+        refresh_token_ttl: Option<Duration>,
+        device_code_ttl: Option<Duration>,
+        request_object_signing_alg: Option<JsonWebSignatureAlg>,
+        require_signed_request_object: bool,
+        id_token_encrypted_response_alg: Option<JsonWebEncryptionAlg>,
+        id_token_encrypted_response_enc: Option<JsonWebEncryptionEnc>,
+        default_acr_values: Vec<String>,
+        pairwise_sector_identifier: Option<String>,
     ) -> Result<Client, Self::Error>;
 
     /// Add or replace a static client
@@ -145,26 +299,59 @@ pub trait OAuth2ClientRepository: Send + Sync {
     ///
     /// * `client_id`: The client ID
     /// * `client_auth_method`: The authentication method this client uses
-    /// * `encrypted_client_secret`: The encrypted client secret, if any
-    /// * `jwks`: The client JWKS, if any
-    /// * `jwks_uri`: The client JWKS URI, if any
-    /// * `redirect_uris`: The list of redirect URIs used by this client
+    /// * `params`: The metadata of the client. See the fields of
+    ///   [`OAuth2StaticClientParams`]
     ///
     /// # Errors
     ///
     /// Returns [`Self::Error`] if the underlying repository fails
-    #[allow(clippy::too_many_arguments)]
     async fn upsert_static(
         &mut self,
         client_id: Ulid,
-        client_name: Option<String>,
         client_auth_method: OAuthClientAuthenticationMethod,
-        encrypted_client_secret: Option<String>,
-        jwks: Option<PublicJsonWebKeySet>,
-        jwks_uri: Option<Url>,
-        redirect_uris: Vec<Url>,
+        params: OAuth2StaticClientParams,
     ) -> Result<Client, Self::Error>;
 
+    /// Set the secret which will replace the current one of a client
+    ///
+    /// Both secrets are accepted until one of them expires or the next one is
+    /// promoted.
+    ///
+    /// Returns the updated client
+    ///
+    /// # Parameters
+    ///
+    /// * `client`: The client to update
+    /// * `encrypted_next_client_secret`: The encrypted next client secret
+    /// * `next_client_secret_expires_at`: When the next client secret stops
+    ///   being accepted, if it expires
+    /// * `client_secret_expires_at`: When the current client secret stops
+    ///   being accepted, if it expires
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn set_next_secret(
+        &mut self,
+        client: Client,
+        encrypted_next_client_secret: String,
+        next_client_secret_expires_at: Option<DateTime<Utc>>,
+        client_secret_expires_at: Option<DateTime<Utc>>,
+    ) -> Result<Client, Self::Error>;
+
+    /// Replace the secret of a client with its next one, ending the rotation
+    ///
+    /// Returns the updated client
+    ///
+    /// # Parameters
+    ///
+    /// * `client`: The client to update, which must have a next secret
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn promote_next_secret(&mut self, client: Client) -> Result<Client, Self::Error>;
+
     /// List all static clients
     ///
     /// # Errors
@@ -233,19 +420,38 @@ repository_impl!(OAuth2ClientRepository:
         token_endpoint_auth_method: Option<OAuthClientAuthenticationMethod>,
         token_endpoint_auth_signing_alg: Option<JsonWebSignatureAlg>,
         initiate_login_uri: Option<Url>,
+        authorization_signed_response_alg: Option<JsonWebSignatureAlg>,
+        backchannel_logout_uri: Option<Url>,
+        backchannel_logout_session_required: bool,
+        post_logout_redirect_uris: Vec<Url>,
+        access_token_ttl: Option<Duration>,
+        refresh_token_ttl: Option<Duration>,
+        device_code_ttl: Option<Duration>,
+        request_object_signing_alg: Option<JsonWebSignatureAlg>,
+        require_signed_request_object: bool,
+        id_token_encrypted_response_alg: Option<JsonWebEncryptionAlg>,
+        id_token_encrypted_response_enc: Option<JsonWebEncryptionEnc>,
+        default_acr_values: Vec<String>,
+        pairwise_sector_identifier: Option<String>,
     ) -> Result<Client, Self::Error>;
 
     async fn upsert_static(
         &mut self,
         client_id: Ulid,
-        client_name: Option<String>,
         client_auth_method: OAuthClientAuthenticationMethod,
-        encrypted_client_secret: Option<String>,
-        jwks: Option<PublicJsonWebKeySet>,
-        jwks_uri: Option<Url>,
-        redirect_uris: Vec<Url>,
+        params: OAuth2StaticClientParams,
     ) -> Result<Client, Self::Error>;
 
+    async fn set_next_secret(
+        &mut self,
+        client: Client,
+        encrypted_next_client_secret: String,
+        next_client_secret_expires_at: Option<DateTime<Utc>>,
+        client_secret_expires_at: Option<DateTime<Utc>>,
+    ) -> Result<Client, Self::Error>;
+
+    async fn promote_next_secret(&mut self, client: Client) -> Result<Client, Self::Error>;
+
     async fn all_static(&mut self) -> Result<Vec<Client>, Self::Error>;
 
     async fn delete(&mut self, client: Client) -> Result<(), Self::Error>;
@@ -0,0 +1,138 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use mas_data_model::{Client, Consent, User};
+use oauth2_types::scope::Scope;
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{Clock, repository_impl};
+
+/// An [`OAuth2ConsentRepository`] helps interacting with the [`Consent`] users
+/// gave to clients, saved in the storage backend
+#[async_trait]
+pub trait OAuth2ConsentRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Lookup a consent by its ID
+    ///
+    /// Returns `None` if no [`Consent`] was found
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the [`Consent`] to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<Consent>, Self::Error>;
+
+    /// List all the scope tokens a user granted, across all clients
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The user to list the consents for
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn all_for_user(&mut self, user: &User) -> Result<Vec<Consent>, Self::Error>;
+
+    /// Get the scope a user previously granted to a client
+    ///
+    /// # Parameters
+    ///
+    /// * `client`: The client to get the consent for
+    /// * `user`: The user who gave the consent
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn get_for_client(&mut self, client: &Client, user: &User) -> Result<Scope, Self::Error>;
+
+    /// Remember that a user granted a scope to a client
+    ///
+    /// Scope tokens which were already granted have their refresh time
+    /// updated
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: A random number generator
+    /// * `clock`: The clock used to generate timestamps
+    /// * `client`: The client the scope was granted to
+    /// * `user`: The user who granted the scope
+    /// * `scope`: The scope which was granted
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn remember(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        client: &Client,
+        user: &User,
+        scope: &Scope,
+    ) -> Result<(), Self::Error>;
+
+    /// Revoke a single scope token a user granted to a client
+    ///
+    /// The next authorization request asking for this scope token will prompt
+    /// the user again
+    ///
+    /// # Parameters
+    ///
+    /// * `consent`: The [`Consent`] to revoke
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn revoke(&mut self, consent: Consent) -> Result<(), Self::Error>;
+
+    /// Revoke all the scope tokens a user granted to a client
+    ///
+    /// Returns the number of scope tokens which were revoked
+    ///
+    /// # Parameters
+    ///
+    /// * `client`: The client to revoke the consent for
+    /// * `user`: The user who gave the consent
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn revoke_for_client(
+        &mut self,
+        client: &Client,
+        user: &User,
+    ) -> Result<usize, Self::Error>;
+}
+
+repository_impl!(OAuth2ConsentRepository:
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<Consent>, Self::Error>;
+
+    async fn all_for_user(&mut self, user: &User) -> Result<Vec<Consent>, Self::Error>;
+
+    async fn get_for_client(&mut self, client: &Client, user: &User) -> Result<Scope, Self::Error>;
+
+    async fn remember(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        client: &Client,
+        user: &User,
+        scope: &Scope,
+    ) -> Result<(), Self::Error>;
+
+    async fn revoke(&mut self, consent: Consent) -> Result<(), Self::Error>;
+
+    async fn revoke_for_client(
+        &mut self,
+        client: &Client,
+        user: &User,
+    ) -> Result<usize, Self::Error>;
+);
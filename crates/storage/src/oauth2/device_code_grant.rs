@@ -32,11 +32,18 @@ pub struct OAuth2DeviceCodeGrantParams<'a> {
     /// After how long the device code expires
     pub expires_in: Duration,
 
+    /// The minimum amount of time the client must wait between two polling
+    /// requests
+    pub interval: Duration,
+
     /// IP address from which the request was made
     pub ip_address: Option<IpAddr>,
 
     /// The user agent from which the request was made
     pub user_agent: Option<String>,
+
+    /// The hashed device identifier provided by the client, if any
+    pub device_fingerprint: Option<String>,
 }
 
 /// An [`OAuth2DeviceCodeGrantRepository`] helps interacting with
@@ -183,6 +190,28 @@ pub trait OAuth2DeviceCodeGrantRepository: Send + Sync {
         device_code_grant: DeviceCodeGrant,
         session: &Session,
     ) -> Result<DeviceCodeGrant, Self::Error>;
+
+    /// Record that the client polled the token endpoint for this device code
+    /// grant
+    ///
+    /// Returns the updated device code grant
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `device_code_grant`: The device code grant which was polled
+    /// * `slow_down`: Whether the client polled too fast, in which case the
+    ///   polling interval is increased
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn record_poll(
+        &mut self,
+        clock: &dyn Clock,
+        device_code_grant: DeviceCodeGrant,
+        slow_down: bool,
+    ) -> Result<DeviceCodeGrant, Self::Error>;
 }
 
 repository_impl!(OAuth2DeviceCodeGrantRepository:
@@ -225,4 +254,11 @@ repository_impl!(OAuth2DeviceCodeGrantRepository:
         device_code_grant: DeviceCodeGrant,
         session: &Session,
     ) -> Result<DeviceCodeGrant, Self::Error>;
+
+    async fn record_poll(
+        &mut self,
+        clock: &dyn Clock,
+        device_code_grant: DeviceCodeGrant,
+        slow_down: bool,
+    ) -> Result<DeviceCodeGrant, Self::Error>;
 );
@@ -0,0 +1,103 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rand_core::RngCore;
+
+use crate::{
+    BoxRepository, Clock, RepositoryAccess, RepositoryError, batch::BatchDeletionFilter,
+    repository_impl,
+};
+
+/// An [`OAuth2DPoPProofRepository`] keeps track of the DPoP proofs which were
+/// already presented, to detect replays
+#[async_trait]
+pub trait OAuth2DPoPProofRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Record a DPoP proof as used
+    ///
+    /// Returns `true` if the proof was recorded, `false` if a proof with the
+    /// same `jti` was already recorded for the same key
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: A random number generator
+    /// * `clock`: The clock used to generate timestamps
+    /// * `jkt`: The JWK SHA-256 thumbprint of the key which signed the proof
+    /// * `jti`: The unique identifier of the proof
+    /// * `expires_at`: When the proof stops being accepted, after which it can
+    ///   be forgotten
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn record(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        jkt: &str,
+        jti: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool, Self::Error>;
+
+    /// Cleanup the proofs which expired
+    ///
+    /// Returns the number of proofs that were cleaned up
+    ///
+    /// # Parameters
+    ///
+    /// * `expired_before`: Only cleanup proofs which expired before this time
+    /// * `limit`: The maximum number of proofs to cleanup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn cleanup_expired(
+        &mut self,
+        expired_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
+}
+
+repository_impl!(OAuth2DPoPProofRepository:
+    async fn record(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        jkt: &str,
+        jti: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool, Self::Error>;
+
+    async fn cleanup_expired(
+        &mut self,
+        expired_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
+);
+
+/// A [`BatchDeletionFilter`] selecting the DPoP proofs which expired before a
+/// given time
+#[derive(Debug, Clone, Copy)]
+pub struct ExpiredDPoPProofs {
+    /// Only select proofs which expired before this time
+    pub expired_before: DateTime<Utc>,
+}
+
+#[async_trait]
+impl BatchDeletionFilter for ExpiredDPoPProofs {
+    async fn delete_batch(
+        &self,
+        repo: &mut BoxRepository,
+        limit: usize,
+    ) -> Result<usize, RepositoryError> {
+        repo.oauth2_dpop_proof()
+            .cleanup_expired(self.expired_before, limit)
+            .await
+    }
+}
@@ -8,16 +8,31 @@
 
 mod access_token;
 mod authorization_grant;
+mod backchannel_authentication_grant;
 mod client;
+mod consent;
 mod device_code_grant;
+mod dpop_proof;
+mod pairwise_subject;
+mod pushed_authorization_request;
 mod refresh_token;
 mod session;
 
 pub use self::{
-    access_token::OAuth2AccessTokenRepository,
-    authorization_grant::OAuth2AuthorizationGrantRepository,
-    client::OAuth2ClientRepository,
+    access_token::{OAuth2AccessTokenRepository, RevokedAccessTokens},
+    authorization_grant::{OAuth2AuthorizationGrantParams, OAuth2AuthorizationGrantRepository},
+    backchannel_authentication_grant::{
+        ExpiredBackchannelAuthenticationGrants, OAuth2BackchannelAuthenticationGrantParams,
+        OAuth2BackchannelAuthenticationGrantRepository,
+    },
+    client::{OAuth2ClientRepository, OAuth2StaticClientParams},
+    consent::OAuth2ConsentRepository,
     device_code_grant::{OAuth2DeviceCodeGrantParams, OAuth2DeviceCodeGrantRepository},
+    dpop_proof::{ExpiredDPoPProofs, OAuth2DPoPProofRepository},
+    pairwise_subject::OAuth2PairwiseSubjectRepository,
+    pushed_authorization_request::{
+        ExpiredPushedAuthorizationRequests, OAuth2PushedAuthorizationRequestRepository,
+    },
     refresh_token::OAuth2RefreshTokenRepository,
     session::{OAuth2SessionFilter, OAuth2SessionRepository},
 };
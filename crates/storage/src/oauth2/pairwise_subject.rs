@@ -0,0 +1,78 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use mas_data_model::User;
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{Clock, repository_impl};
+
+/// An [`OAuth2PairwiseSubjectRepository`] keeps track of the pairwise subject
+/// identifiers given out to clients, so that they are stable and can be mapped
+/// back to the user they identify
+#[async_trait]
+pub trait OAuth2PairwiseSubjectRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Get the pairwise subject identifier of a user for a sector identifier,
+    /// generating a new random one if the user doesn't have one yet
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: A random number generator
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The user the subject identifier identifies
+    /// * `sector_identifier`: The sector identifier of the client asking for
+    ///   it
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_or_add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        sector_identifier: &str,
+    ) -> Result<String, Self::Error>;
+
+    /// Find the ID of the user identified by a pairwise subject identifier
+    ///
+    /// Returns `None` if no user has this subject identifier for this sector
+    /// identifier
+    ///
+    /// # Parameters
+    ///
+    /// * `sector_identifier`: The sector identifier the subject identifier was
+    ///   given out for
+    /// * `subject`: The pairwise subject identifier
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_user_id(
+        &mut self,
+        sector_identifier: &str,
+        subject: &str,
+    ) -> Result<Option<Ulid>, Self::Error>;
+}
+
+repository_impl!(OAuth2PairwiseSubjectRepository:
+    async fn find_or_add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        sector_identifier: &str,
+    ) -> Result<String, Self::Error>;
+
+    async fn find_user_id(
+        &mut self,
+        sector_identifier: &str,
+        subject: &str,
+    ) -> Result<Option<Ulid>, Self::Error>;
+);
@@ -0,0 +1,155 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use mas_data_model::{Client, PushedAuthorizationRequest};
+use rand_core::RngCore;
+
+use crate::{
+    BoxRepository, Clock, RepositoryAccess, RepositoryError, batch::BatchDeletionFilter,
+    repository_impl,
+};
+
+/// An [`OAuth2PushedAuthorizationRequestRepository`] helps interacting with
+/// [`PushedAuthorizationRequest`] saved in the storage backend
+#[async_trait]
+pub trait OAuth2PushedAuthorizationRequestRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Save the parameters of a pushed authorization request
+    ///
+    /// Returns the newly created pushed authorization request
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: A random number generator
+    /// * `clock`: The clock used to generate timestamps
+    /// * `client`: The client which pushed the request
+    /// * `reference`: The random part of the `request_uri`
+    /// * `parameters`: The authorization request parameters
+    /// * `expires_in`: How long the request can be used for
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        client: &Client,
+        reference: String,
+        parameters: BTreeMap<String, String>,
+        expires_in: Duration,
+    ) -> Result<PushedAuthorizationRequest, Self::Error>;
+
+    /// Find a pushed authorization request by the random part of its
+    /// `request_uri`
+    ///
+    /// Returns `None` if no request was found
+    ///
+    /// # Parameters
+    ///
+    /// * `reference`: The random part of the `request_uri`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_by_reference(
+        &mut self,
+        reference: &str,
+    ) -> Result<Option<PushedAuthorizationRequest>, Self::Error>;
+
+    /// Mark a pushed authorization request as used
+    ///
+    /// Returns the updated pushed authorization request
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `request`: The pushed authorization request to mark as used
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails, or if the
+    /// request was already used
+    async fn consume(
+        &mut self,
+        clock: &dyn Clock,
+        request: PushedAuthorizationRequest,
+    ) -> Result<PushedAuthorizationRequest, Self::Error>;
+
+    /// Cleanup the pushed authorization requests which expired
+    ///
+    /// Returns the number of requests that were cleaned up
+    ///
+    /// # Parameters
+    ///
+    /// * `expired_before`: Only cleanup requests which expired before this
+    ///   time
+    /// * `limit`: The maximum number of requests to cleanup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn cleanup_expired(
+        &mut self,
+        expired_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
+}
+
+repository_impl!(OAuth2PushedAuthorizationRequestRepository:
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        client: &Client,
+        reference: String,
+        parameters: BTreeMap<String, String>,
+        expires_in: Duration,
+    ) -> Result<PushedAuthorizationRequest, Self::Error>;
+
+    async fn find_by_reference(
+        &mut self,
+        reference: &str,
+    ) -> Result<Option<PushedAuthorizationRequest>, Self::Error>;
+
+    async fn consume(
+        &mut self,
+        clock: &dyn Clock,
+        request: PushedAuthorizationRequest,
+    ) -> Result<PushedAuthorizationRequest, Self::Error>;
+
+    async fn cleanup_expired(
+        &mut self,
+        expired_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
+);
+
+/// A [`BatchDeletionFilter`] selecting the pushed authorization requests which
+/// expired before a given time
+#[derive(Debug, Clone, Copy)]
+pub struct ExpiredPushedAuthorizationRequests {
+    /// Only select requests which expired before this time
+    pub expired_before: DateTime<Utc>,
+}
+
+#[async_trait]
+impl BatchDeletionFilter for ExpiredPushedAuthorizationRequests {
+    async fn delete_batch(
+        &self,
+        repo: &mut BoxRepository,
+        limit: usize,
+    ) -> Result<usize, RepositoryError> {
+        repo.oauth2_pushed_authorization_request()
+            .cleanup_expired(self.expired_before, limit)
+            .await
+    }
+}
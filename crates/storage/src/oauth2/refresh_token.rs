@@ -93,6 +93,26 @@ pub trait OAuth2RefreshTokenRepository: Send + Sync {
         replaced_by: &RefreshToken,
     ) -> Result<RefreshToken, Self::Error>;
 
+    /// Attach a new access token to a refresh token, used when refresh tokens
+    /// are not rotated
+    ///
+    /// Returns the updated [`RefreshToken`]
+    ///
+    /// # Parameters
+    ///
+    /// * `refresh_token`: The [`RefreshToken`] to update
+    /// * `access_token`: The [`AccessToken`] which was issued with this
+    ///   [`RefreshToken`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn replace_access_token(
+        &mut self,
+        refresh_token: RefreshToken,
+        access_token: &AccessToken,
+    ) -> Result<RefreshToken, Self::Error>;
+
     /// Revoke a refresh token
     ///
     /// Returns the updated [`RefreshToken`]
@@ -111,6 +131,25 @@ pub trait OAuth2RefreshTokenRepository: Send + Sync {
         clock: &dyn Clock,
         refresh_token: RefreshToken,
     ) -> Result<RefreshToken, Self::Error>;
+
+    /// Revoke all the refresh tokens of a session which were neither consumed
+    /// nor revoked yet
+    ///
+    /// Returns the number of refresh tokens which were revoked
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `session`: The session to revoke the refresh tokens of
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn revoke_for_session(
+        &mut self,
+        clock: &dyn Clock,
+        session: &Session,
+    ) -> Result<usize, Self::Error>;
 }
 
 repository_impl!(OAuth2RefreshTokenRepository:
@@ -137,9 +176,21 @@ repository_impl!(OAuth2RefreshTokenRepository:
         replaced_by: &RefreshToken,
     ) -> Result<RefreshToken, Self::Error>;
 
+    async fn replace_access_token(
+        &mut self,
+        refresh_token: RefreshToken,
+        access_token: &AccessToken,
+    ) -> Result<RefreshToken, Self::Error>;
+
     async fn revoke(
         &mut self,
         clock: &dyn Clock,
         refresh_token: RefreshToken,
     ) -> Result<RefreshToken, Self::Error>;
+
+    async fn revoke_for_session(
+        &mut self,
+        clock: &dyn Clock,
+        session: &Session,
+    ) -> Result<usize, Self::Error>;
 );
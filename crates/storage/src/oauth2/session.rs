@@ -8,10 +8,11 @@ use std::net::IpAddr;
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use mas_data_model::{BrowserSession, Client, Device, Session, User};
-use oauth2_types::scope::Scope;
+use mas_data_model::{BrowserSession, Client, Device, IpLocation, Session, User};
+use oauth2_types::{authorization_details::AuthorizationDetail, scope::Scope};
 use rand_core::RngCore;
 use ulid::Ulid;
+use url::Url;
 
 use crate::{Clock, Pagination, pagination::Page, repository_impl};
 
@@ -35,12 +36,17 @@ impl OAuth2SessionState {
 pub enum ClientKind {
     Static,
     Dynamic,
+    ServiceAccount,
 }
 
 impl ClientKind {
     pub fn is_static(self) -> bool {
         matches!(self, Self::Static)
     }
+
+    pub fn is_service_account(self) -> bool {
+        matches!(self, Self::ServiceAccount)
+    }
 }
 
 /// Filter parameters for listing OAuth 2.0 sessions
@@ -56,6 +62,8 @@ pub struct OAuth2SessionFilter<'a> {
     scope: Option<&'a Scope>,
     last_active_before: Option<DateTime<Utc>>,
     last_active_after: Option<DateTime<Utc>>,
+    device_fingerprint: Option<&'a str>,
+    offline_access: Option<bool>,
 }
 
 impl<'a> OAuth2SessionFilter<'a> {
@@ -146,6 +154,13 @@ impl<'a> OAuth2SessionFilter<'a> {
         self
     }
 
+    /// List only clients acting as service accounts
+    #[must_use]
+    pub fn only_service_account_clients(mut self) -> Self {
+        self.client_kind = Some(ClientKind::ServiceAccount);
+        self
+    }
+
     /// Get the client kind filter
     ///
     /// Returns [`None`] if no client kind filter was set
@@ -235,6 +250,43 @@ impl<'a> OAuth2SessionFilter<'a> {
     pub fn device(&self) -> Option<&'a Device> {
         self.device
     }
+
+    /// Only return sessions with the given client-provided device fingerprint
+    #[must_use]
+    pub fn for_device_fingerprint(mut self, device_fingerprint: &'a str) -> Self {
+        self.device_fingerprint = Some(device_fingerprint);
+        self
+    }
+
+    /// Get the device fingerprint filter
+    ///
+    /// Returns [`None`] if no device fingerprint filter was set
+    #[must_use]
+    pub fn device_fingerprint(&self) -> Option<&'a str> {
+        self.device_fingerprint
+    }
+
+    /// Only return sessions to which the user granted offline access
+    #[must_use]
+    pub fn with_offline_access(mut self) -> Self {
+        self.offline_access = Some(true);
+        self
+    }
+
+    /// Only return sessions to which the user didn't grant offline access
+    #[must_use]
+    pub fn without_offline_access(mut self) -> Self {
+        self.offline_access = Some(false);
+        self
+    }
+
+    /// Get the offline access filter
+    ///
+    /// Returns [`None`] if no offline access filter was set
+    #[must_use]
+    pub fn offline_access(&self) -> Option<bool> {
+        self.offline_access
+    }
 }
 
 /// An [`OAuth2SessionRepository`] helps interacting with [`Session`]
@@ -404,19 +456,38 @@ pub trait OAuth2SessionRepository: Send + Sync {
     /// Returns [`Self::Error`] if the underlying repository fails
     async fn count(&mut self, filter: OAuth2SessionFilter<'_>) -> Result<usize, Self::Error>;
 
+    /// Estimate the number of [`Session`]s matching the given filter
+    ///
+    /// This uses the statistics of the database instead of counting the rows,
+    /// which is much faster on large tables, but can be off. Small counts are
+    /// still exact.
+    ///
+    /// # Parameters
+    ///
+    /// * `filter`: The filter parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn estimate_count(
+        &mut self,
+        filter: OAuth2SessionFilter<'_>,
+    ) -> Result<usize, Self::Error>;
+
     /// Record a batch of [`Session`] activity
     ///
     /// # Parameters
     ///
     /// * `activity`: A list of tuples containing the session ID, the last
-    ///   activity timestamp and the IP address of the client
+    ///   activity timestamp, the IP address of the client and where that
+    ///   address is located. The location is ignored if there is no IP address.
     ///
     /// # Errors
     ///
     /// Returns [`Self::Error`] if the underlying repository fails
     async fn record_batch_activity(
         &mut self,
-        activity: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>)>,
+        activity: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>, IpLocation)>,
     ) -> Result<(), Self::Error>;
 
     /// Record the user agent of a [`Session`]
@@ -431,6 +502,88 @@ pub trait OAuth2SessionRepository: Send + Sync {
         user_agent: String,
     ) -> Result<Session, Self::Error>;
 
+    /// Record the device fingerprint provided by the client for a [`Session`]
+    ///
+    /// # Parameters
+    ///
+    /// * `session`: The [`Session`] to record the device fingerprint for
+    /// * `device_fingerprint`: The device fingerprint to record
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn record_device_fingerprint(
+        &mut self,
+        session: Session,
+        device_fingerprint: String,
+    ) -> Result<Session, Self::Error>;
+
+    /// Record the rich authorization request details granted to a [`Session`]
+    ///
+    /// # Parameters
+    ///
+    /// * `session`: The [`Session`] to record the authorization details for
+    /// * `authorization_details`: The authorization details to record
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn record_authorization_details(
+        &mut self,
+        session: Session,
+        authorization_details: Vec<AuthorizationDetail>,
+    ) -> Result<Session, Self::Error>;
+
+    /// Record the resource a [`Session`] is restricted to, as per RFC 8707
+    ///
+    /// # Parameters
+    ///
+    /// * `session`: The [`Session`] to record the resource for
+    /// * `resource`: The resource the tokens of the session are issued for
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn record_resource(
+        &mut self,
+        session: Session,
+        resource: Url,
+    ) -> Result<Session, Self::Error>;
+
+    /// Record the claims the client of a [`Session`] requested individually in
+    /// the userinfo response
+    ///
+    /// # Parameters
+    ///
+    /// * `session`: The [`Session`] to record the claims for
+    /// * `userinfo_claims`: The names of the requested claims
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn record_userinfo_claims(
+        &mut self,
+        session: Session,
+        userinfo_claims: Vec<String>,
+    ) -> Result<Session, Self::Error>;
+
+    /// Record that the user explicitly granted offline access to the client
+    /// of a [`Session`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `session`: The [`Session`] to record the offline access for
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn record_offline_access(
+        &mut self,
+        clock: &dyn Clock,
+        session: Session,
+    ) -> Result<Session, Self::Error>;
+
     /// Set the human name of a [`Session`]
     ///
     /// # Parameters
@@ -490,10 +643,11 @@ repository_impl!(OAuth2SessionRepository:
     ) -> Result<Page<Session>, Self::Error>;
 
     async fn count(&mut self, filter: OAuth2SessionFilter<'_>) -> Result<usize, Self::Error>;
+    async fn estimate_count(&mut self, filter: OAuth2SessionFilter<'_>) -> Result<usize, Self::Error>;
 
     async fn record_batch_activity(
         &mut self,
-        activity: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>)>,
+        activity: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>, IpLocation)>,
     ) -> Result<(), Self::Error>;
 
     async fn record_user_agent(
@@ -502,6 +656,36 @@ repository_impl!(OAuth2SessionRepository:
         user_agent: String,
     ) -> Result<Session, Self::Error>;
 
+    async fn record_device_fingerprint(
+        &mut self,
+        session: Session,
+        device_fingerprint: String,
+    ) -> Result<Session, Self::Error>;
+
+    async fn record_authorization_details(
+        &mut self,
+        session: Session,
+        authorization_details: Vec<AuthorizationDetail>,
+    ) -> Result<Session, Self::Error>;
+
+    async fn record_resource(
+        &mut self,
+        session: Session,
+        resource: Url,
+    ) -> Result<Session, Self::Error>;
+
+    async fn record_userinfo_claims(
+        &mut self,
+        session: Session,
+        userinfo_claims: Vec<String>,
+    ) -> Result<Session, Self::Error>;
+
+    async fn record_offline_access(
+        &mut self,
+        clock: &dyn Clock,
+        session: Session,
+    ) -> Result<Session, Self::Error>;
+
     async fn set_human_name(
         &mut self,
         session: Session,
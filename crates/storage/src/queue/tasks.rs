@@ -5,7 +5,8 @@
 
 use chrono::{DateTime, Utc};
 use mas_data_model::{
-    BrowserSession, CompatSession, Device, Session, User, UserEmailAuthentication,
+    BackchannelAuthenticationGrant, BrowserSession, CompatSession, Device, Session,
+    UpstreamOAuthLink, User, UserClaimLink, UserDeletion, UserEmailAuthentication, UserPhone,
     UserRecoverySession,
 };
 use serde::{Deserialize, Serialize};
@@ -69,6 +70,57 @@ impl InsertableJob for SendEmailAuthenticationCodeJob {
     const QUEUE_NAME: &'static str = "send-email-authentication-code";
 }
 
+/// A job to send a verification code to the phone number of a user.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SendPhoneCodeJob {
+    user_phone_id: Ulid,
+    language: String,
+    #[serde(default)]
+    login: bool,
+}
+
+impl SendPhoneCodeJob {
+    /// Create a new job to send a code to a phone number, to confirm it.
+    #[must_use]
+    pub fn new(user_phone: &UserPhone, language: String) -> Self {
+        Self {
+            user_phone_id: user_phone.id,
+            language,
+            login: false,
+        }
+    }
+
+    /// Send the code as a second factor for logging in, instead of to
+    /// confirm the phone number.
+    #[must_use]
+    pub fn for_login(mut self) -> Self {
+        self.login = true;
+        self
+    }
+
+    /// The language to use for the message.
+    #[must_use]
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The ID of the phone number to send the code to.
+    #[must_use]
+    pub fn user_phone_id(&self) -> Ulid {
+        self.user_phone_id
+    }
+
+    /// Whether the code is sent as a second factor for logging in.
+    #[must_use]
+    pub fn is_login(&self) -> bool {
+        self.login
+    }
+}
+
+impl InsertableJob for SendPhoneCodeJob {
+    const QUEUE_NAME: &'static str = "send-phone-code";
+}
+
 /// A job to provision the user on the homeserver.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProvisionUserJob {
@@ -218,11 +270,98 @@ impl InsertableJob for SyncDevicesJob {
     const QUEUE_NAME: &'static str = "sync-devices";
 }
 
+/// A job to refresh the attributes of a user with the values the upstream
+/// provider gave when they logged in
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SyncUpstreamAttributesJob {
+    upstream_oauth_link_id: Ulid,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    avatar_url: Option<String>,
+}
+
+impl SyncUpstreamAttributesJob {
+    /// Create a new job to refresh the attributes of the user associated with
+    /// the given upstream OAuth link
+    #[must_use]
+    pub fn new(upstream_oauth_link: &UpstreamOAuthLink) -> Self {
+        Self {
+            upstream_oauth_link_id: upstream_oauth_link.id,
+            display_name: None,
+            email: None,
+            avatar_url: None,
+        }
+    }
+
+    /// Set the display name given by the upstream provider
+    #[must_use]
+    pub fn with_display_name(mut self, display_name: String) -> Self {
+        self.display_name = Some(display_name);
+        self
+    }
+
+    /// Set the email address given by the upstream provider
+    #[must_use]
+    pub fn with_email(mut self, email: String) -> Self {
+        self.email = Some(email);
+        self
+    }
+
+    /// Set the URL of the avatar given by the upstream provider
+    #[must_use]
+    pub fn with_avatar_url(mut self, avatar_url: String) -> Self {
+        self.avatar_url = Some(avatar_url);
+        self
+    }
+
+    /// Returns `true` if there is no attribute to refresh
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.display_name.is_none() && self.email.is_none() && self.avatar_url.is_none()
+    }
+
+    /// The ID of the upstream OAuth link through which the user logged in
+    #[must_use]
+    pub fn upstream_oauth_link_id(&self) -> Ulid {
+        self.upstream_oauth_link_id
+    }
+
+    /// The display name given by the upstream provider
+    #[must_use]
+    pub fn display_name(&self) -> Option<&str> {
+        self.display_name.as_deref()
+    }
+
+    /// The email address given by the upstream provider
+    #[must_use]
+    pub fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+
+    /// The URL of the avatar given by the upstream provider
+    #[must_use]
+    pub fn avatar_url(&self) -> Option<&str> {
+        self.avatar_url.as_deref()
+    }
+}
+
+impl InsertableJob for SyncUpstreamAttributesJob {
+    const QUEUE_NAME: &'static str = "sync-upstream-attributes";
+}
+
 /// A job to deactivate and lock a user
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DeactivateUserJob {
     user_id: Ulid,
     hs_erase: bool,
+    #[serde(default)]
+    user_deletion_id: Option<Ulid>,
 }
 
 impl DeactivateUserJob {
@@ -237,9 +376,20 @@ impl DeactivateUserJob {
         Self {
             user_id: user.id,
             hs_erase,
+            user_deletion_id: None,
         }
     }
 
+    /// Deactivate the user at the start of the grace period of an account
+    /// deletion. The deactivation is skipped if the deletion was cancelled in
+    /// the meantime, and the email addresses are kept until the deletion
+    /// completes.
+    #[must_use]
+    pub fn for_deletion(mut self, user_deletion: &UserDeletion) -> Self {
+        self.user_deletion_id = Some(user_deletion.id);
+        self
+    }
+
     /// The ID of the user to deactivate
     #[must_use]
     pub fn user_id(&self) -> Ulid {
@@ -251,12 +401,49 @@ impl DeactivateUserJob {
     pub fn hs_erase(&self) -> bool {
         self.hs_erase
     }
+
+    /// The ID of the account deletion this deactivation is part of, if any
+    #[must_use]
+    pub fn user_deletion_id(&self) -> Option<Ulid> {
+        self.user_deletion_id
+    }
 }
 
 impl InsertableJob for DeactivateUserJob {
     const QUEUE_NAME: &'static str = "deactivate-user";
 }
 
+/// A job to delete a user once the grace period of their account deletion is
+/// over
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeleteUserJob {
+    user_deletion_id: Ulid,
+}
+
+impl DeleteUserJob {
+    /// Create a new job to delete a user at the end of the grace period
+    ///
+    /// # Parameters
+    ///
+    /// * `user_deletion` - The account deletion to complete
+    #[must_use]
+    pub fn new(user_deletion: &UserDeletion) -> Self {
+        Self {
+            user_deletion_id: user_deletion.id,
+        }
+    }
+
+    /// The ID of the account deletion to complete
+    #[must_use]
+    pub fn user_deletion_id(&self) -> Ulid {
+        self.user_deletion_id
+    }
+}
+
+impl InsertableJob for DeleteUserJob {
+    const QUEUE_NAME: &'static str = "delete-user";
+}
+
 /// A job to reactivate a user
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ReactivateUserJob {
@@ -317,6 +504,223 @@ impl InsertableJob for SendAccountRecoveryEmailsJob {
     const QUEUE_NAME: &'static str = "send-account-recovery-email";
 }
 
+/// Send an account claim link by email
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SendUserClaimLinkEmailJob {
+    user_claim_link_id: Ulid,
+    language: String,
+}
+
+impl SendUserClaimLinkEmailJob {
+    /// Create a new job to send an account claim link by email
+    ///
+    /// # Parameters
+    ///
+    /// * `user_claim_link` - The claim link to send. Its `email` must be set
+    /// * `language` - The locale to send the email in
+    #[must_use]
+    pub fn new(user_claim_link: &UserClaimLink, language: String) -> Self {
+        Self {
+            user_claim_link_id: user_claim_link.id,
+            language,
+        }
+    }
+
+    /// The ID of the claim link to send
+    #[must_use]
+    pub fn user_claim_link_id(&self) -> Ulid {
+        self.user_claim_link_id
+    }
+
+    /// The locale to send the email in
+    #[must_use]
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+}
+
+impl InsertableJob for SendUserClaimLinkEmailJob {
+    const QUEUE_NAME: &'static str = "send-user-claim-link-email";
+}
+
+/// Tell a user by email that their account was used to log in from a new IP
+/// address or device
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SendSignInNotificationJob {
+    user_session_id: Ulid,
+    language: String,
+}
+
+impl SendSignInNotificationJob {
+    /// Create a new job to send a sign-in notification
+    ///
+    /// # Parameters
+    ///
+    /// * `user_session` - The browser session which was just logged in
+    /// * `language` - The locale to send the email in
+    #[must_use]
+    pub fn new(user_session: &BrowserSession, language: String) -> Self {
+        Self {
+            user_session_id: user_session.id,
+            language,
+        }
+    }
+
+    /// The ID of the browser session which was logged in
+    #[must_use]
+    pub fn user_session_id(&self) -> Ulid {
+        self.user_session_id
+    }
+
+    /// The locale to send the email in
+    #[must_use]
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+}
+
+impl InsertableJob for SendSignInNotificationJob {
+    const QUEUE_NAME: &'static str = "send-sign-in-notification";
+}
+
+/// A job to ask a user by email to approve a backchannel authentication
+/// request
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SendBackchannelAuthenticationEmailJob {
+    grant_id: Ulid,
+    language: String,
+}
+
+impl SendBackchannelAuthenticationEmailJob {
+    /// Create a new job to send the approval link of a backchannel
+    /// authentication request to its user
+    ///
+    /// # Parameters
+    ///
+    /// * `grant` - The backchannel authentication request to approve
+    /// * `language` - The locale to send the email in
+    #[must_use]
+    pub fn new(grant: &BackchannelAuthenticationGrant, language: String) -> Self {
+        Self {
+            grant_id: grant.id,
+            language,
+        }
+    }
+
+    /// The ID of the backchannel authentication request
+    #[must_use]
+    pub fn grant_id(&self) -> Ulid {
+        self.grant_id
+    }
+
+    /// The locale to send the email in
+    #[must_use]
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+}
+
+impl InsertableJob for SendBackchannelAuthenticationEmailJob {
+    const QUEUE_NAME: &'static str = "send-backchannel-authentication-email";
+}
+
+/// A job to notify a client using the `ping` mode that a backchannel
+/// authentication request was approved or rejected
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NotifyBackchannelClientJob {
+    grant_id: Ulid,
+}
+
+impl NotifyBackchannelClientJob {
+    /// Create a new job to notify the client of a backchannel authentication
+    /// request
+    ///
+    /// # Parameters
+    ///
+    /// * `grant` - The backchannel authentication request which completed
+    #[must_use]
+    pub fn new(grant: &BackchannelAuthenticationGrant) -> Self {
+        Self { grant_id: grant.id }
+    }
+
+    /// The ID of the backchannel authentication request
+    #[must_use]
+    pub fn grant_id(&self) -> Ulid {
+        self.grant_id
+    }
+}
+
+impl InsertableJob for NotifyBackchannelClientJob {
+    const QUEUE_NAME: &'static str = "notify-backchannel-client";
+}
+
+/// A job to send a back-channel logout token to the client of an OAuth 2.0
+/// session which ended
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SendBackchannelLogoutJob {
+    oauth2_session_id: Ulid,
+}
+
+impl SendBackchannelLogoutJob {
+    /// Create a new job to notify the client of an OAuth 2.0 session that the
+    /// session ended
+    ///
+    /// # Parameters
+    ///
+    /// * `session` - The OAuth 2.0 session which ended
+    #[must_use]
+    pub fn new(session: &Session) -> Self {
+        Self {
+            oauth2_session_id: session.id,
+        }
+    }
+
+    /// The ID of the OAuth 2.0 session which ended
+    #[must_use]
+    pub fn oauth2_session_id(&self) -> Ulid {
+        self.oauth2_session_id
+    }
+}
+
+impl InsertableJob for SendBackchannelLogoutJob {
+    const QUEUE_NAME: &'static str = "send-backchannel-logout";
+}
+
+/// A job to send back-channel logout tokens to the clients of all the OAuth
+/// 2.0 sessions started from a browser session which ended
+///
+/// If the browser session was last authenticated through an upstream
+/// provider, those OAuth 2.0 sessions are also ended and their tokens revoked.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SendBrowserSessionBackchannelLogoutsJob {
+    browser_session_id: Ulid,
+}
+
+impl SendBrowserSessionBackchannelLogoutsJob {
+    /// Create a new job to notify the clients of the OAuth 2.0 sessions
+    /// started from the given browser session
+    ///
+    /// # Parameters
+    ///
+    /// * `browser_session` - The browser session which ended
+    #[must_use]
+    pub fn new(browser_session: &BrowserSession) -> Self {
+        Self {
+            browser_session_id: browser_session.id,
+        }
+    }
+
+    /// The ID of the browser session which ended
+    #[must_use]
+    pub fn browser_session_id(&self) -> Ulid {
+        self.browser_session_id
+    }
+}
+
+impl InsertableJob for SendBrowserSessionBackchannelLogoutsJob {
+    const QUEUE_NAME: &'static str = "send-browser-session-backchannel-logouts";
+}
+
 /// Cleanup expired tokens
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct CleanupExpiredTokensJob;
@@ -328,7 +732,7 @@ impl InsertableJob for CleanupExpiredTokensJob {
 /// Scheduled job to expire inactive sessions
 ///
 /// This job will trigger jobs to expire inactive compat, oauth and user
-/// sessions.
+/// sessions, and user sessions past their maximum lifetime.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ExpireInactiveSessionsJob;
 
@@ -450,6 +854,37 @@ impl InsertableJob for ExpireInactiveCompatSessionsJob {
     const QUEUE_NAME: &'static str = "expire-inactive-compat-sessions";
 }
 
+/// Finish a compatibility session, once an impersonation of the user by an
+/// administrator ran out
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FinishCompatSessionJob {
+    compat_session_id: Ulid,
+}
+
+impl FinishCompatSessionJob {
+    /// Create a new job to finish a compatibility session
+    ///
+    /// # Parameters
+    ///
+    /// * `compat_session` - The compatibility session to finish
+    #[must_use]
+    pub fn new(compat_session: &CompatSession) -> Self {
+        Self {
+            compat_session_id: compat_session.id,
+        }
+    }
+
+    /// The ID of the compatibility session to finish
+    #[must_use]
+    pub fn compat_session_id(&self) -> Ulid {
+        self.compat_session_id
+    }
+}
+
+impl InsertableJob for FinishCompatSessionJob {
+    const QUEUE_NAME: &'static str = "finish-compat-session";
+}
+
 /// Expire inactive user sessions
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExpireInactiveUserSessionsJob {
@@ -507,6 +942,63 @@ impl InsertableJob for ExpireInactiveUserSessionsJob {
     const QUEUE_NAME: &'static str = "expire-inactive-user-sessions";
 }
 
+/// Expire user sessions older than their maximum lifetime
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExpireOldUserSessionsJob {
+    threshold: DateTime<Utc>,
+    after: Option<Ulid>,
+}
+
+impl ExpireOldUserSessionsJob {
+    /// Create a new job to expire old user/browser sessions
+    ///
+    /// # Parameters
+    ///
+    /// * `threshold` - The creation time before which sessions are expired
+    #[must_use]
+    pub fn new(threshold: DateTime<Utc>) -> Self {
+        Self {
+            threshold,
+            after: None,
+        }
+    }
+
+    /// Get the creation time before which sessions are expired
+    #[must_use]
+    pub fn threshold(&self) -> DateTime<Utc> {
+        self.threshold
+    }
+
+    /// Get the pagination cursor
+    #[must_use]
+    pub fn pagination(&self, batch_size: usize) -> Pagination {
+        let pagination = Pagination::first(batch_size);
+        if let Some(after) = self.after {
+            pagination.after(after)
+        } else {
+            pagination
+        }
+    }
+
+    /// Get the next job given the page returned by the database
+    #[must_use]
+    pub fn next(&self, page: &Page<BrowserSession>) -> Option<Self> {
+        if !page.has_next_page {
+            return None;
+        }
+
+        let last_edge = page.edges.last()?;
+        Some(Self {
+            threshold: self.threshold,
+            after: Some(last_edge.id),
+        })
+    }
+}
+
+impl InsertableJob for ExpireOldUserSessionsJob {
+    const QUEUE_NAME: &'static str = "expire-old-user-sessions";
+}
+
 /// Prune stale policy data
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PruneStalePolicyDataJob;
@@ -514,3 +1006,28 @@ pub struct PruneStalePolicyDataJob;
 impl InsertableJob for PruneStalePolicyDataJob {
     const QUEUE_NAME: &'static str = "prune-stale-policy-data";
 }
+
+/// Synchronise users from the upstream SCIM directory
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncScimDirectoryJob;
+
+impl InsertableJob for SyncScimDirectoryJob {
+    const QUEUE_NAME: &'static str = "sync-scim-directory";
+}
+
+/// Refresh the daily login statistics rollup
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshLoginStatsJob;
+
+impl InsertableJob for RefreshLoginStatsJob {
+    const QUEUE_NAME: &'static str = "refresh-login-stats";
+}
+
+/// Lock or deactivate the users whose upstream identity was removed, for the
+/// upstream providers with deprovisioning enabled
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeprovisionUpstreamUsersJob;
+
+impl InsertableJob for DeprovisionUpstreamUsersJob {
+    const QUEUE_NAME: &'static str = "deprovision-upstream-users";
+}
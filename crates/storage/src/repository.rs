@@ -4,30 +4,42 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use futures_util::future::BoxFuture;
 use thiserror::Error;
 
 use crate::{
     app_session::AppSessionRepository,
+    audit_event::AuditEventRepository,
     compat::{
         CompatAccessTokenRepository, CompatRefreshTokenRepository, CompatSessionRepository,
         CompatSsoLoginRepository,
     },
+    feature_flag::FeatureFlagOverrideRepository,
+    login_stats::LoginStatsRepository,
     oauth2::{
-        OAuth2AccessTokenRepository, OAuth2AuthorizationGrantRepository, OAuth2ClientRepository,
-        OAuth2DeviceCodeGrantRepository, OAuth2RefreshTokenRepository, OAuth2SessionRepository,
+        OAuth2AccessTokenRepository, OAuth2AuthorizationGrantRepository,
+        OAuth2BackchannelAuthenticationGrantRepository, OAuth2ClientRepository,
+        OAuth2ConsentRepository, OAuth2DPoPProofRepository, OAuth2DeviceCodeGrantRepository,
+        OAuth2PairwiseSubjectRepository, OAuth2PushedAuthorizationRequestRepository,
+        OAuth2RefreshTokenRepository, OAuth2SessionRepository,
     },
     policy_data::PolicyDataRepository,
     queue::{QueueJobRepository, QueueScheduleRepository, QueueWorkerRepository},
+    scim::{ScimSyncRunRepository, ScimUserLinkRepository},
     upstream_oauth2::{
-        UpstreamOAuthLinkRepository, UpstreamOAuthProviderRepository,
-        UpstreamOAuthSessionRepository,
+        UpstreamOAuthCachedDocumentRepository, UpstreamOAuthLinkRepository,
+        UpstreamOAuthProviderRepository, UpstreamOAuthSessionRepository,
     },
     user::{
-        BrowserSessionRepository, UserEmailRepository, UserPasswordRepository,
+        BrowserSessionRepository, UserActionTokenRepository, UserClaimLinkRepository,
+        UserDeletionRepository, UserEmailRepository, UserMetadataRepository, UserPasskeyRepository,
+        UserPasswordRepository, UserPhoneRepository, UserRecoveryCodeRepository,
         UserRecoveryRepository, UserRegistrationRepository, UserRegistrationTokenRepository,
-        UserRepository, UserTermsRepository,
+        UserRepository, UserTermsAcceptanceRepository, UserTermsRepository, UserTotpRepository,
+        UserTrustedDeviceRepository,
     },
 };
 
@@ -38,11 +50,35 @@ use crate::{
 pub trait RepositoryFactory {
     /// Create a new [`BoxRepository`]
     async fn create(&self) -> Result<BoxRepository, RepositoryError>;
+
+    /// Check that the underlying storage backend is reachable
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the storage backend can't be reached
+    async fn health_check(&self) -> Result<(), RepositoryError>;
+}
+
+#[async_trait]
+impl<T> RepositoryFactory for Arc<T>
+where
+    T: RepositoryFactory + Send + Sync + ?Sized,
+{
+    async fn create(&self) -> Result<BoxRepository, RepositoryError> {
+        (**self).create().await
+    }
+
+    async fn health_check(&self) -> Result<(), RepositoryError> {
+        (**self).health_check().await
+    }
 }
 
 /// A type-erased [`RepositoryFactory`]
 pub type BoxRepositoryFactory = Box<dyn RepositoryFactory + Send + Sync + 'static>;
 
+/// A type-erased [`RepositoryFactory`] which can be cheaply cloned
+pub type ArcRepositoryFactory = Arc<dyn RepositoryFactory + Send + Sync + 'static>;
+
 /// A [`Repository`] helps interacting with the underlying storage backend.
 pub trait Repository<E>:
     RepositoryAccess<Error = E> + RepositoryTransaction<Error = E> + Send
@@ -130,6 +166,11 @@ pub trait RepositoryAccess: Send {
         &'c mut self,
     ) -> Box<dyn UpstreamOAuthSessionRepository<Error = Self::Error> + 'c>;
 
+    /// Get an [`UpstreamOAuthCachedDocumentRepository`]
+    fn upstream_oauth_cached_document<'c>(
+        &'c mut self,
+    ) -> Box<dyn UpstreamOAuthCachedDocumentRepository<Error = Self::Error> + 'c>;
+
     /// Get an [`UserRepository`]
     fn user<'c>(&'c mut self) -> Box<dyn UserRepository<Error = Self::Error> + 'c>;
 
@@ -140,6 +181,9 @@ pub trait RepositoryAccess: Send {
     fn user_password<'c>(&'c mut self)
     -> Box<dyn UserPasswordRepository<Error = Self::Error> + 'c>;
 
+    /// Get an [`UserPasskeyRepository`]
+    fn user_passkey<'c>(&'c mut self) -> Box<dyn UserPasskeyRepository<Error = Self::Error> + 'c>;
+
     /// Get an [`UserRecoveryRepository`]
     fn user_recovery<'c>(&'c mut self)
     -> Box<dyn UserRecoveryRepository<Error = Self::Error> + 'c>;
@@ -157,6 +201,31 @@ pub trait RepositoryAccess: Send {
     /// Get an [`UserTermsRepository`]
     fn user_terms<'c>(&'c mut self) -> Box<dyn UserTermsRepository<Error = Self::Error> + 'c>;
 
+    /// Get an [`UserTermsAcceptanceRepository`]
+    fn user_terms_acceptance<'c>(
+        &'c mut self,
+    ) -> Box<dyn UserTermsAcceptanceRepository<Error = Self::Error> + 'c>;
+
+    /// Get an [`UserTotpRepository`]
+    fn user_totp<'c>(&'c mut self) -> Box<dyn UserTotpRepository<Error = Self::Error> + 'c>;
+
+    /// Get an [`UserPhoneRepository`]
+    fn user_phone<'c>(&'c mut self) -> Box<dyn UserPhoneRepository<Error = Self::Error> + 'c>;
+
+    /// Get an [`UserRecoveryCodeRepository`]
+    fn user_recovery_code<'c>(
+        &'c mut self,
+    ) -> Box<dyn UserRecoveryCodeRepository<Error = Self::Error> + 'c>;
+
+    /// Get an [`UserTrustedDeviceRepository`]
+    fn user_trusted_device<'c>(
+        &'c mut self,
+    ) -> Box<dyn UserTrustedDeviceRepository<Error = Self::Error> + 'c>;
+
+    /// Get an [`UserDeletionRepository`]
+    fn user_deletion<'c>(&'c mut self)
+    -> Box<dyn UserDeletionRepository<Error = Self::Error> + 'c>;
+
     /// Get a [`BrowserSessionRepository`]
     fn browser_session<'c>(
         &'c mut self,
@@ -169,6 +238,11 @@ pub trait RepositoryAccess: Send {
     fn oauth2_client<'c>(&'c mut self)
     -> Box<dyn OAuth2ClientRepository<Error = Self::Error> + 'c>;
 
+    /// Get an [`OAuth2ConsentRepository`]
+    fn oauth2_consent<'c>(
+        &'c mut self,
+    ) -> Box<dyn OAuth2ConsentRepository<Error = Self::Error> + 'c>;
+
     /// Get an [`OAuth2AuthorizationGrantRepository`]
     fn oauth2_authorization_grant<'c>(
         &'c mut self,
@@ -227,6 +301,59 @@ pub trait RepositoryAccess: Send {
 
     /// Get a [`PolicyDataRepository`]
     fn policy_data<'c>(&'c mut self) -> Box<dyn PolicyDataRepository<Error = Self::Error> + 'c>;
+
+    /// Get a [`ScimUserLinkRepository`]
+    fn scim_user_link<'c>(
+        &'c mut self,
+    ) -> Box<dyn ScimUserLinkRepository<Error = Self::Error> + 'c>;
+
+    /// Get a [`ScimSyncRunRepository`]
+    fn scim_sync_run<'c>(&'c mut self) -> Box<dyn ScimSyncRunRepository<Error = Self::Error> + 'c>;
+
+    /// Get a [`UserMetadataRepository`]
+    fn user_metadata<'c>(&'c mut self)
+    -> Box<dyn UserMetadataRepository<Error = Self::Error> + 'c>;
+
+    /// Get a [`LoginStatsRepository`]
+    fn login_stats<'c>(&'c mut self) -> Box<dyn LoginStatsRepository<Error = Self::Error> + 'c>;
+
+    /// Get a [`UserClaimLinkRepository`]
+    fn user_claim_link<'c>(
+        &'c mut self,
+    ) -> Box<dyn UserClaimLinkRepository<Error = Self::Error> + 'c>;
+
+    /// Get a [`FeatureFlagOverrideRepository`]
+    fn feature_flag_override<'c>(
+        &'c mut self,
+    ) -> Box<dyn FeatureFlagOverrideRepository<Error = Self::Error> + 'c>;
+
+    /// Get a [`OAuth2DPoPProofRepository`]
+    fn oauth2_dpop_proof<'c>(
+        &'c mut self,
+    ) -> Box<dyn OAuth2DPoPProofRepository<Error = Self::Error> + 'c>;
+
+    /// Get a [`OAuth2PairwiseSubjectRepository`]
+    fn oauth2_pairwise_subject<'c>(
+        &'c mut self,
+    ) -> Box<dyn OAuth2PairwiseSubjectRepository<Error = Self::Error> + 'c>;
+
+    /// Get a [`OAuth2PushedAuthorizationRequestRepository`]
+    fn oauth2_pushed_authorization_request<'c>(
+        &'c mut self,
+    ) -> Box<dyn OAuth2PushedAuthorizationRequestRepository<Error = Self::Error> + 'c>;
+
+    /// Get an [`UserActionTokenRepository`]
+    fn user_action_token<'c>(
+        &'c mut self,
+    ) -> Box<dyn UserActionTokenRepository<Error = Self::Error> + 'c>;
+
+    /// Get a [`OAuth2BackchannelAuthenticationGrantRepository`]
+    fn oauth2_backchannel_authentication_grant<'c>(
+        &'c mut self,
+    ) -> Box<dyn OAuth2BackchannelAuthenticationGrantRepository<Error = Self::Error> + 'c>;
+
+    /// Get an [`AuditEventRepository`]
+    fn audit_event<'c>(&'c mut self) -> Box<dyn AuditEventRepository<Error = Self::Error> + 'c>;
 }
 
 /// Implementations of the [`RepositoryAccess`], [`RepositoryTransaction`] and
@@ -238,25 +365,34 @@ mod impls {
     use crate::{
         MapErr, Repository, RepositoryTransaction,
         app_session::AppSessionRepository,
+        audit_event::AuditEventRepository,
         compat::{
             CompatAccessTokenRepository, CompatRefreshTokenRepository, CompatSessionRepository,
             CompatSsoLoginRepository,
         },
+        feature_flag::FeatureFlagOverrideRepository,
+        login_stats::LoginStatsRepository,
         oauth2::{
             OAuth2AccessTokenRepository, OAuth2AuthorizationGrantRepository,
-            OAuth2ClientRepository, OAuth2DeviceCodeGrantRepository, OAuth2RefreshTokenRepository,
-            OAuth2SessionRepository,
+            OAuth2BackchannelAuthenticationGrantRepository, OAuth2ClientRepository,
+            OAuth2ConsentRepository, OAuth2DPoPProofRepository, OAuth2DeviceCodeGrantRepository,
+            OAuth2PairwiseSubjectRepository, OAuth2PushedAuthorizationRequestRepository,
+            OAuth2RefreshTokenRepository, OAuth2SessionRepository,
         },
         policy_data::PolicyDataRepository,
         queue::{QueueJobRepository, QueueScheduleRepository, QueueWorkerRepository},
+        scim::{ScimSyncRunRepository, ScimUserLinkRepository},
         upstream_oauth2::{
-            UpstreamOAuthLinkRepository, UpstreamOAuthProviderRepository,
-            UpstreamOAuthSessionRepository,
+            UpstreamOAuthCachedDocumentRepository, UpstreamOAuthLinkRepository,
+            UpstreamOAuthProviderRepository, UpstreamOAuthSessionRepository,
         },
         user::{
-            BrowserSessionRepository, UserEmailRepository, UserPasswordRepository,
+            BrowserSessionRepository, UserClaimLinkRepository, UserDeletionRepository,
+            UserEmailRepository, UserMetadataRepository, UserPasskeyRepository,
+            UserPasswordRepository, UserPhoneRepository, UserRecoveryCodeRepository,
             UserRegistrationRepository, UserRegistrationTokenRepository, UserRepository,
-            UserTermsRepository,
+            UserTermsAcceptanceRepository, UserTermsRepository, UserTotpRepository,
+            UserTrustedDeviceRepository,
         },
     };
 
@@ -326,6 +462,15 @@ mod impls {
             ))
         }
 
+        fn upstream_oauth_cached_document<'c>(
+            &'c mut self,
+        ) -> Box<dyn UpstreamOAuthCachedDocumentRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(
+                self.inner.upstream_oauth_cached_document(),
+                &mut self.mapper,
+            ))
+        }
+
         fn user<'c>(&'c mut self) -> Box<dyn UserRepository<Error = Self::Error> + 'c> {
             Box::new(MapErr::new(self.inner.user(), &mut self.mapper))
         }
@@ -340,6 +485,12 @@ mod impls {
             Box::new(MapErr::new(self.inner.user_password(), &mut self.mapper))
         }
 
+        fn user_passkey<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserPasskeyRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(self.inner.user_passkey(), &mut self.mapper))
+        }
+
         fn user_recovery<'c>(
             &'c mut self,
         ) -> Box<dyn crate::user::UserRecoveryRepository<Error = Self::Error> + 'c> {
@@ -368,6 +519,47 @@ mod impls {
             Box::new(MapErr::new(self.inner.user_terms(), &mut self.mapper))
         }
 
+        fn user_terms_acceptance<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserTermsAcceptanceRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(
+                self.inner.user_terms_acceptance(),
+                &mut self.mapper,
+            ))
+        }
+
+        fn user_totp<'c>(&'c mut self) -> Box<dyn UserTotpRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(self.inner.user_totp(), &mut self.mapper))
+        }
+
+        fn user_phone<'c>(&'c mut self) -> Box<dyn UserPhoneRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(self.inner.user_phone(), &mut self.mapper))
+        }
+
+        fn user_recovery_code<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserRecoveryCodeRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(
+                self.inner.user_recovery_code(),
+                &mut self.mapper,
+            ))
+        }
+
+        fn user_trusted_device<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserTrustedDeviceRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(
+                self.inner.user_trusted_device(),
+                &mut self.mapper,
+            ))
+        }
+
+        fn user_deletion<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserDeletionRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(self.inner.user_deletion(), &mut self.mapper))
+        }
+
         fn browser_session<'c>(
             &'c mut self,
         ) -> Box<dyn BrowserSessionRepository<Error = Self::Error> + 'c> {
@@ -386,6 +578,12 @@ mod impls {
             Box::new(MapErr::new(self.inner.oauth2_client(), &mut self.mapper))
         }
 
+        fn oauth2_consent<'c>(
+            &'c mut self,
+        ) -> Box<dyn OAuth2ConsentRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(self.inner.oauth2_consent(), &mut self.mapper))
+        }
+
         fn oauth2_authorization_grant<'c>(
             &'c mut self,
         ) -> Box<dyn OAuth2AuthorizationGrantRepository<Error = Self::Error> + 'c> {
@@ -479,6 +677,97 @@ mod impls {
         ) -> Box<dyn PolicyDataRepository<Error = Self::Error> + 'c> {
             Box::new(MapErr::new(self.inner.policy_data(), &mut self.mapper))
         }
+
+        fn scim_user_link<'c>(
+            &'c mut self,
+        ) -> Box<dyn ScimUserLinkRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(self.inner.scim_user_link(), &mut self.mapper))
+        }
+
+        fn scim_sync_run<'c>(
+            &'c mut self,
+        ) -> Box<dyn ScimSyncRunRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(self.inner.scim_sync_run(), &mut self.mapper))
+        }
+
+        fn user_metadata<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserMetadataRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(self.inner.user_metadata(), &mut self.mapper))
+        }
+
+        fn login_stats<'c>(
+            &'c mut self,
+        ) -> Box<dyn LoginStatsRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(self.inner.login_stats(), &mut self.mapper))
+        }
+
+        fn user_claim_link<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserClaimLinkRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(self.inner.user_claim_link(), &mut self.mapper))
+        }
+
+        fn feature_flag_override<'c>(
+            &'c mut self,
+        ) -> Box<dyn FeatureFlagOverrideRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(
+                self.inner.feature_flag_override(),
+                &mut self.mapper,
+            ))
+        }
+
+        fn oauth2_dpop_proof<'c>(
+            &'c mut self,
+        ) -> Box<dyn OAuth2DPoPProofRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(
+                self.inner.oauth2_dpop_proof(),
+                &mut self.mapper,
+            ))
+        }
+
+        fn oauth2_pairwise_subject<'c>(
+            &'c mut self,
+        ) -> Box<dyn OAuth2PairwiseSubjectRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(
+                self.inner.oauth2_pairwise_subject(),
+                &mut self.mapper,
+            ))
+        }
+
+        fn oauth2_pushed_authorization_request<'c>(
+            &'c mut self,
+        ) -> Box<dyn OAuth2PushedAuthorizationRequestRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(
+                self.inner.oauth2_pushed_authorization_request(),
+                &mut self.mapper,
+            ))
+        }
+
+        fn user_action_token<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserActionTokenRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(
+                self.inner.user_action_token(),
+                &mut self.mapper,
+            ))
+        }
+
+        fn oauth2_backchannel_authentication_grant<'c>(
+            &'c mut self,
+        ) -> Box<dyn OAuth2BackchannelAuthenticationGrantRepository<Error = Self::Error> + 'c>
+        {
+            Box::new(MapErr::new(
+                self.inner.oauth2_backchannel_authentication_grant(),
+                &mut self.mapper,
+            ))
+        }
+
+        fn audit_event<'c>(
+            &'c mut self,
+        ) -> Box<dyn AuditEventRepository<Error = Self::Error> + 'c> {
+            Box::new(MapErr::new(self.inner.audit_event(), &mut self.mapper))
+        }
     }
 
     impl<R: RepositoryAccess + ?Sized> RepositoryAccess for Box<R> {
@@ -502,6 +791,12 @@ mod impls {
             (**self).upstream_oauth_session()
         }
 
+        fn upstream_oauth_cached_document<'c>(
+            &'c mut self,
+        ) -> Box<dyn UpstreamOAuthCachedDocumentRepository<Error = Self::Error> + 'c> {
+            (**self).upstream_oauth_cached_document()
+        }
+
         fn user<'c>(&'c mut self) -> Box<dyn UserRepository<Error = Self::Error> + 'c> {
             (**self).user()
         }
@@ -516,6 +811,12 @@ mod impls {
             (**self).user_password()
         }
 
+        fn user_passkey<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserPasskeyRepository<Error = Self::Error> + 'c> {
+            (**self).user_passkey()
+        }
+
         fn user_recovery<'c>(
             &'c mut self,
         ) -> Box<dyn crate::user::UserRecoveryRepository<Error = Self::Error> + 'c> {
@@ -538,6 +839,38 @@ mod impls {
             (**self).user_terms()
         }
 
+        fn user_terms_acceptance<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserTermsAcceptanceRepository<Error = Self::Error> + 'c> {
+            (**self).user_terms_acceptance()
+        }
+
+        fn user_totp<'c>(&'c mut self) -> Box<dyn UserTotpRepository<Error = Self::Error> + 'c> {
+            (**self).user_totp()
+        }
+
+        fn user_phone<'c>(&'c mut self) -> Box<dyn UserPhoneRepository<Error = Self::Error> + 'c> {
+            (**self).user_phone()
+        }
+
+        fn user_recovery_code<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserRecoveryCodeRepository<Error = Self::Error> + 'c> {
+            (**self).user_recovery_code()
+        }
+
+        fn user_trusted_device<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserTrustedDeviceRepository<Error = Self::Error> + 'c> {
+            (**self).user_trusted_device()
+        }
+
+        fn user_deletion<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserDeletionRepository<Error = Self::Error> + 'c> {
+            (**self).user_deletion()
+        }
+
         fn browser_session<'c>(
             &'c mut self,
         ) -> Box<dyn BrowserSessionRepository<Error = Self::Error> + 'c> {
@@ -556,6 +889,12 @@ mod impls {
             (**self).oauth2_client()
         }
 
+        fn oauth2_consent<'c>(
+            &'c mut self,
+        ) -> Box<dyn OAuth2ConsentRepository<Error = Self::Error> + 'c> {
+            (**self).oauth2_consent()
+        }
+
         fn oauth2_authorization_grant<'c>(
             &'c mut self,
         ) -> Box<dyn OAuth2AuthorizationGrantRepository<Error = Self::Error> + 'c> {
@@ -631,5 +970,78 @@ mod impls {
         ) -> Box<dyn PolicyDataRepository<Error = Self::Error> + 'c> {
             (**self).policy_data()
         }
+
+        fn scim_user_link<'c>(
+            &'c mut self,
+        ) -> Box<dyn ScimUserLinkRepository<Error = Self::Error> + 'c> {
+            (**self).scim_user_link()
+        }
+
+        fn scim_sync_run<'c>(
+            &'c mut self,
+        ) -> Box<dyn ScimSyncRunRepository<Error = Self::Error> + 'c> {
+            (**self).scim_sync_run()
+        }
+
+        fn user_metadata<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserMetadataRepository<Error = Self::Error> + 'c> {
+            (**self).user_metadata()
+        }
+
+        fn login_stats<'c>(
+            &'c mut self,
+        ) -> Box<dyn LoginStatsRepository<Error = Self::Error> + 'c> {
+            (**self).login_stats()
+        }
+
+        fn user_claim_link<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserClaimLinkRepository<Error = Self::Error> + 'c> {
+            (**self).user_claim_link()
+        }
+
+        fn feature_flag_override<'c>(
+            &'c mut self,
+        ) -> Box<dyn FeatureFlagOverrideRepository<Error = Self::Error> + 'c> {
+            (**self).feature_flag_override()
+        }
+
+        fn oauth2_dpop_proof<'c>(
+            &'c mut self,
+        ) -> Box<dyn OAuth2DPoPProofRepository<Error = Self::Error> + 'c> {
+            (**self).oauth2_dpop_proof()
+        }
+
+        fn oauth2_pairwise_subject<'c>(
+            &'c mut self,
+        ) -> Box<dyn OAuth2PairwiseSubjectRepository<Error = Self::Error> + 'c> {
+            (**self).oauth2_pairwise_subject()
+        }
+
+        fn oauth2_pushed_authorization_request<'c>(
+            &'c mut self,
+        ) -> Box<dyn OAuth2PushedAuthorizationRequestRepository<Error = Self::Error> + 'c> {
+            (**self).oauth2_pushed_authorization_request()
+        }
+
+        fn user_action_token<'c>(
+            &'c mut self,
+        ) -> Box<dyn UserActionTokenRepository<Error = Self::Error> + 'c> {
+            (**self).user_action_token()
+        }
+
+        fn oauth2_backchannel_authentication_grant<'c>(
+            &'c mut self,
+        ) -> Box<dyn OAuth2BackchannelAuthenticationGrantRepository<Error = Self::Error> + 'c>
+        {
+            (**self).oauth2_backchannel_authentication_grant()
+        }
+
+        fn audit_event<'c>(
+            &'c mut self,
+        ) -> Box<dyn AuditEventRepository<Error = Self::Error> + 'c> {
+            (**self).audit_event()
+        }
     }
 }
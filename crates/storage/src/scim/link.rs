@@ -0,0 +1,115 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use mas_data_model::{ScimUserLink, User};
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{Clock, Page, Pagination, repository_impl};
+
+/// A [`ScimUserLinkRepository`] helps interacting with [`ScimUserLink`] saved
+/// in the storage backend
+#[async_trait]
+pub trait ScimUserLinkRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Lookup a SCIM user link by its ID
+    ///
+    /// Returns `None` if the link does not exist
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the link to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<ScimUserLink>, Self::Error>;
+
+    /// Find a SCIM user link by the ID of the user resource in the directory
+    ///
+    /// Returns `None` if no matching link was found
+    ///
+    /// # Parameters
+    ///
+    /// * `external_id`: The `id` of the user resource in the SCIM directory
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_by_external_id(
+        &mut self,
+        external_id: &str,
+    ) -> Result<Option<ScimUserLink>, Self::Error>;
+
+    /// Find the SCIM user link of a user
+    ///
+    /// Returns `None` if the user is not linked to the directory
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The user to find the link for
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_by_user(&mut self, user: &User) -> Result<Option<ScimUserLink>, Self::Error>;
+
+    /// Link a user to a user resource in the SCIM directory
+    ///
+    /// Returns the newly created link
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The user to link
+    /// * `external_id`: The `id` of the user resource in the SCIM directory
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        external_id: String,
+    ) -> Result<ScimUserLink, Self::Error>;
+
+    /// List SCIM user links
+    ///
+    /// # Parameters
+    ///
+    /// * `pagination`: The pagination parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn list(&mut self, pagination: Pagination) -> Result<Page<ScimUserLink>, Self::Error>;
+}
+
+repository_impl!(ScimUserLinkRepository:
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<ScimUserLink>, Self::Error>;
+
+    async fn find_by_external_id(
+        &mut self,
+        external_id: &str,
+    ) -> Result<Option<ScimUserLink>, Self::Error>;
+
+    async fn find_by_user(&mut self, user: &User) -> Result<Option<ScimUserLink>, Self::Error>;
+
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        external_id: String,
+    ) -> Result<ScimUserLink, Self::Error>;
+
+    async fn list(&mut self, pagination: Pagination) -> Result<Page<ScimUserLink>, Self::Error>;
+);
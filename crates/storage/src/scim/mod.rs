@@ -0,0 +1,15 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Repositories to interact with entities related to the synchronisation of
+//! users from an upstream SCIM directory
+
+mod link;
+mod sync_run;
+
+pub use self::{
+    link::ScimUserLinkRepository,
+    sync_run::{ScimSyncRunFilter, ScimSyncRunFilterState, ScimSyncRunRepository},
+};
@@ -0,0 +1,205 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use mas_data_model::{ScimSyncChange, ScimSyncRun};
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{Clock, Page, Pagination, repository_impl};
+
+/// The state of a SCIM sync run, used for filtering
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScimSyncRunFilterState {
+    /// The run is still in progress
+    Running,
+
+    /// The run completed successfully
+    Completed,
+
+    /// The run failed
+    Failed,
+}
+
+/// Filter parameters for listing SCIM sync runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct ScimSyncRunFilter {
+    state: Option<ScimSyncRunFilterState>,
+}
+
+impl ScimSyncRunFilter {
+    /// Create a new [`ScimSyncRunFilter`] with default values
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only return runs which are still in progress
+    #[must_use]
+    pub fn running_only(mut self) -> Self {
+        self.state = Some(ScimSyncRunFilterState::Running);
+        self
+    }
+
+    /// Only return runs which completed successfully
+    #[must_use]
+    pub fn completed_only(mut self) -> Self {
+        self.state = Some(ScimSyncRunFilterState::Completed);
+        self
+    }
+
+    /// Only return runs which failed
+    #[must_use]
+    pub fn failed_only(mut self) -> Self {
+        self.state = Some(ScimSyncRunFilterState::Failed);
+        self
+    }
+
+    /// Get the state filter
+    ///
+    /// Returns [`None`] if no state filter was set
+    #[must_use]
+    pub fn state(&self) -> Option<ScimSyncRunFilterState> {
+        self.state
+    }
+}
+
+/// A [`ScimSyncRunRepository`] helps interacting with [`ScimSyncRun`] saved in
+/// the storage backend
+#[async_trait]
+pub trait ScimSyncRunRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Lookup a SCIM sync run by its ID
+    ///
+    /// Returns `None` if the sync run does not exist
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the sync run to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<ScimSyncRun>, Self::Error>;
+
+    /// Record the start of a new SCIM sync run
+    ///
+    /// Returns the newly created sync run
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn start(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+    ) -> Result<ScimSyncRun, Self::Error>;
+
+    /// Mark a SCIM sync run as completed, recording the changes it applied
+    ///
+    /// Returns the updated sync run
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `run`: The sync run to complete
+    /// * `changes`: The changes applied to users during the run
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn complete(
+        &mut self,
+        clock: &dyn Clock,
+        run: ScimSyncRun,
+        changes: Vec<ScimSyncChange>,
+    ) -> Result<ScimSyncRun, Self::Error>;
+
+    /// Mark a SCIM sync run as failed
+    ///
+    /// Returns the updated sync run
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `run`: The sync run which failed
+    /// * `error`: A description of the error which made the run fail
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn fail(
+        &mut self,
+        clock: &dyn Clock,
+        run: ScimSyncRun,
+        error: String,
+    ) -> Result<ScimSyncRun, Self::Error>;
+
+    /// List SCIM sync runs with the given filter and pagination
+    ///
+    /// # Parameters
+    ///
+    /// * `filter`: The filter to apply
+    /// * `pagination`: The pagination parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn list(
+        &mut self,
+        filter: ScimSyncRunFilter,
+        pagination: Pagination,
+    ) -> Result<Page<ScimSyncRun>, Self::Error>;
+
+    /// Count the SCIM sync runs matching the given filter
+    ///
+    /// # Parameters
+    ///
+    /// * `filter`: The filter to apply
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn count(&mut self, filter: ScimSyncRunFilter) -> Result<usize, Self::Error>;
+}
+
+repository_impl!(ScimSyncRunRepository:
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<ScimSyncRun>, Self::Error>;
+
+    async fn start(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+    ) -> Result<ScimSyncRun, Self::Error>;
+
+    async fn complete(
+        &mut self,
+        clock: &dyn Clock,
+        run: ScimSyncRun,
+        changes: Vec<ScimSyncChange>,
+    ) -> Result<ScimSyncRun, Self::Error>;
+
+    async fn fail(
+        &mut self,
+        clock: &dyn Clock,
+        run: ScimSyncRun,
+        error: String,
+    ) -> Result<ScimSyncRun, Self::Error>;
+
+    async fn list(
+        &mut self,
+        filter: ScimSyncRunFilter,
+        pagination: Pagination,
+    ) -> Result<Page<ScimSyncRun>, Self::Error>;
+
+    async fn count(&mut self, filter: ScimSyncRunFilter) -> Result<usize, Self::Error>;
+);
@@ -0,0 +1,75 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use mas_data_model::{UpstreamOAuthCachedDocument, UpstreamOAuthCachedDocumentKind};
+
+use crate::{Clock, repository_impl};
+
+/// An [`UpstreamOAuthCachedDocumentRepository`] keeps the last documents
+/// successfully fetched from the upstream providers, so that they can be
+/// shared between instances and survive restarts
+#[async_trait]
+pub trait UpstreamOAuthCachedDocumentRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Lookup the last document of the given kind fetched at the given URL
+    ///
+    /// Returns `None` if no such document was fetched yet
+    ///
+    /// # Parameters
+    ///
+    /// * `kind`: The kind of document to lookup
+    /// * `url`: The URL the document is keyed by
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(
+        &mut self,
+        kind: UpstreamOAuthCachedDocumentKind,
+        url: &str,
+    ) -> Result<Option<UpstreamOAuthCachedDocument>, Self::Error>;
+
+    /// Save a freshly fetched document, replacing the previous one of the
+    /// same kind fetched at the same URL
+    ///
+    /// Returns the saved document
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `kind`: The kind of document to save
+    /// * `url`: The URL the document is keyed by
+    /// * `document`: The document itself
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn save(
+        &mut self,
+        clock: &dyn Clock,
+        kind: UpstreamOAuthCachedDocumentKind,
+        url: &str,
+        document: serde_json::Value,
+    ) -> Result<UpstreamOAuthCachedDocument, Self::Error>;
+}
+
+repository_impl!(UpstreamOAuthCachedDocumentRepository:
+    async fn lookup(
+        &mut self,
+        kind: UpstreamOAuthCachedDocumentKind,
+        url: &str,
+    ) -> Result<Option<UpstreamOAuthCachedDocument>, Self::Error>;
+
+    async fn save(
+        &mut self,
+        clock: &dyn Clock,
+        kind: UpstreamOAuthCachedDocumentKind,
+        url: &str,
+        document: serde_json::Value,
+    ) -> Result<UpstreamOAuthCachedDocument, Self::Error>;
+);
@@ -5,7 +5,11 @@
 // Please see LICENSE files in the repository root for full details.
 
 use async_trait::async_trait;
-use mas_data_model::{UpstreamOAuthLink, UpstreamOAuthProvider, User};
+use chrono::{DateTime, Utc};
+use mas_data_model::{
+    UpstreamOAuthLink, UpstreamOAuthLinkImportedAttributes, UpstreamOAuthLinkTokens,
+    UpstreamOAuthProvider, User,
+};
 use rand_core::RngCore;
 use ulid::Ulid;
 
@@ -174,6 +178,109 @@ pub trait UpstreamOAuthLinkRepository: Send + Sync {
         user: &User,
     ) -> Result<(), Self::Error>;
 
+    /// Record the attributes which were last imported through an upstream
+    /// OAuth link
+    ///
+    /// Returns the updated upstream OAuth link
+    ///
+    /// # Parameters
+    ///
+    /// * `upstream_oauth_link`: The upstream OAuth link to update
+    /// * `imported_attributes`: The attributes which were imported
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn set_imported_attributes(
+        &mut self,
+        upstream_oauth_link: UpstreamOAuthLink,
+        imported_attributes: UpstreamOAuthLinkImportedAttributes,
+    ) -> Result<UpstreamOAuthLink, Self::Error>;
+
+    /// Change the subject of an upstream OAuth link, after the upstream
+    /// provider changed the format of its subjects
+    ///
+    /// Returns the updated upstream OAuth link
+    ///
+    /// # Parameters
+    ///
+    /// * `upstream_oauth_link`: The upstream OAuth link to update
+    /// * `subject`: The new subject of the upstream account
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails, or if
+    /// another link of the same provider already has this subject
+    async fn set_subject(
+        &mut self,
+        upstream_oauth_link: UpstreamOAuthLink,
+        subject: String,
+    ) -> Result<UpstreamOAuthLink, Self::Error>;
+
+    /// Get the tokens the upstream provider last issued for an upstream OAuth
+    /// link
+    ///
+    /// Returns `None` if no tokens were stored for this link
+    ///
+    /// # Parameters
+    ///
+    /// * `upstream_oauth_link`: The upstream OAuth link to get the tokens for
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn tokens(
+        &mut self,
+        upstream_oauth_link: &UpstreamOAuthLink,
+    ) -> Result<Option<UpstreamOAuthLinkTokens>, Self::Error>;
+
+    /// Store the tokens the upstream provider issued for an upstream OAuth
+    /// link, replacing the ones previously stored
+    ///
+    /// Returns the stored tokens
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `upstream_oauth_link`: The upstream OAuth link the tokens were issued
+    ///   for
+    /// * `encrypted_access_token`: The encrypted access token
+    /// * `encrypted_refresh_token`: The encrypted refresh token, if any
+    /// * `access_token_expires_at`: When the access token expires, if known
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn set_tokens(
+        &mut self,
+        clock: &dyn Clock,
+        upstream_oauth_link: &UpstreamOAuthLink,
+        encrypted_access_token: String,
+        encrypted_refresh_token: Option<String>,
+        access_token_expires_at: Option<DateTime<Utc>>,
+    ) -> Result<UpstreamOAuthLinkTokens, Self::Error>;
+
+    /// Record that the upstream provider refused the refresh token stored for
+    /// an upstream OAuth link
+    ///
+    /// This is cleared the next time tokens are stored for the link
+    ///
+    /// Returns the updated tokens
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `tokens`: The tokens whose refresh token was refused
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn mark_refresh_failed(
+        &mut self,
+        clock: &dyn Clock,
+        tokens: UpstreamOAuthLinkTokens,
+    ) -> Result<UpstreamOAuthLinkTokens, Self::Error>;
+
     /// List [`UpstreamOAuthLink`] with the given filter and pagination
     ///
     /// # Parameters
@@ -242,6 +349,38 @@ repository_impl!(UpstreamOAuthLinkRepository:
         user: &User,
     ) -> Result<(), Self::Error>;
 
+    async fn set_imported_attributes(
+        &mut self,
+        upstream_oauth_link: UpstreamOAuthLink,
+        imported_attributes: UpstreamOAuthLinkImportedAttributes,
+    ) -> Result<UpstreamOAuthLink, Self::Error>;
+
+    async fn set_subject(
+        &mut self,
+        upstream_oauth_link: UpstreamOAuthLink,
+        subject: String,
+    ) -> Result<UpstreamOAuthLink, Self::Error>;
+
+    async fn tokens(
+        &mut self,
+        upstream_oauth_link: &UpstreamOAuthLink,
+    ) -> Result<Option<UpstreamOAuthLinkTokens>, Self::Error>;
+
+    async fn set_tokens(
+        &mut self,
+        clock: &dyn Clock,
+        upstream_oauth_link: &UpstreamOAuthLink,
+        encrypted_access_token: String,
+        encrypted_refresh_token: Option<String>,
+        access_token_expires_at: Option<DateTime<Utc>>,
+    ) -> Result<UpstreamOAuthLinkTokens, Self::Error>;
+
+    async fn mark_refresh_failed(
+        &mut self,
+        clock: &dyn Clock,
+        tokens: UpstreamOAuthLinkTokens,
+    ) -> Result<UpstreamOAuthLinkTokens, Self::Error>;
+
     async fn list(
         &mut self,
         filter: UpstreamOAuthLinkFilter<'_>,
@@ -7,14 +7,16 @@
 //! Repositories to interact with entities related to the upstream OAuth 2.0
 //! providers
 
+mod cached_document;
 mod link;
 mod provider;
 mod session;
 
 pub use self::{
+    cached_document::UpstreamOAuthCachedDocumentRepository,
     link::{UpstreamOAuthLinkFilter, UpstreamOAuthLinkRepository},
     provider::{
         UpstreamOAuthProviderFilter, UpstreamOAuthProviderParams, UpstreamOAuthProviderRepository,
     },
-    session::UpstreamOAuthSessionRepository,
+    session::{UpstreamOAuthSessionFilter, UpstreamOAuthSessionRepository},
 };
@@ -8,9 +8,9 @@ use std::marker::PhantomData;
 
 use async_trait::async_trait;
 use mas_data_model::{
-    UpstreamOAuthProvider, UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderDiscoveryMode,
-    UpstreamOAuthProviderPkceMode, UpstreamOAuthProviderResponseMode,
-    UpstreamOAuthProviderTokenAuthMethod,
+    UpstreamOAuthProvider, UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderDeprovisioning,
+    UpstreamOAuthProviderDiscoveryMode, UpstreamOAuthProviderPkceMode,
+    UpstreamOAuthProviderResponseMode, UpstreamOAuthProviderTokenAuthMethod,
 };
 use mas_iana::jose::JsonWebSignatureAlg;
 use oauth2_types::scope::Scope;
@@ -42,6 +42,11 @@ pub struct UpstreamOAuthProviderParams {
     /// `private_key_jwt` authentication methods are used
     pub token_endpoint_signing_alg: Option<JsonWebSignatureAlg>,
 
+    /// The ID of the key to sign the client assertions with when the
+    /// `private_key_jwt` authentication method is used. If `None`, any key
+    /// compatible with the signing algorithm is used
+    pub token_endpoint_signing_key_id: Option<String>,
+
     /// Expected signature for the JWT payload returned by the token
     /// authentication endpoint.
     ///
@@ -66,6 +71,12 @@ pub struct UpstreamOAuthProviderParams {
     /// The encrypted client secret to use when authenticating to the upstream
     pub encrypted_client_secret: Option<String>,
 
+    /// The PEM-encoded TLS client certificate chain to present to the upstream
+    pub tls_client_certificate: Option<String>,
+
+    /// The encrypted PEM-encoded private key of the TLS client certificate
+    pub encrypted_tls_client_key: Option<String>,
+
     /// How claims should be imported from the upstream provider
     pub claims_imports: UpstreamOAuthProviderClaimsImports,
 
@@ -99,6 +110,22 @@ pub struct UpstreamOAuthProviderParams {
     /// Whether to forward the login hint to the upstream provider.
     pub forward_login_hint: bool,
 
+    /// Whether to forward `prompt=login` to the upstream provider.
+    pub forward_prompt: bool,
+
+    /// Whether to forward the requested ACR value to the upstream provider.
+    pub forward_acr_values: bool,
+
+    /// Whether to forward the user's locale to the upstream provider.
+    pub forward_ui_locales: bool,
+
+    /// Whether to keep the tokens issued by the upstream provider on login
+    pub store_tokens: bool,
+
+    /// How to deprovision the accounts whose upstream identity was removed,
+    /// if at all
+    pub deprovisioning: Option<UpstreamOAuthProviderDeprovisioning>,
+
     /// The position of the provider in the UI
     pub ui_order: i32,
 }
@@ -11,6 +11,67 @@ use ulid::Ulid;
 
 use crate::{Clock, repository_impl};
 
+/// Filter parameters for finding upstream OAuth 2.0 authorization sessions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct UpstreamOAuthSessionFilter<'a> {
+    provider: Option<&'a UpstreamOAuthProvider>,
+    sub_claim: Option<&'a str>,
+    sid_claim: Option<&'a str>,
+}
+
+impl<'a> UpstreamOAuthSessionFilter<'a> {
+    /// Create a new [`UpstreamOAuthSessionFilter`] with default values
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the upstream OAuth provider with which the sessions were started
+    #[must_use]
+    pub fn for_provider(mut self, provider: &'a UpstreamOAuthProvider) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Get the upstream OAuth provider filter
+    ///
+    /// Returns [`None`] if no filter was set
+    #[must_use]
+    pub fn provider(&self) -> Option<&UpstreamOAuthProvider> {
+        self.provider
+    }
+
+    /// Only return sessions whose ID token has the given `sub` claim
+    #[must_use]
+    pub fn with_sub_claim(mut self, sub_claim: &'a str) -> Self {
+        self.sub_claim = Some(sub_claim);
+        self
+    }
+
+    /// Get the `sub` claim filter
+    ///
+    /// Returns [`None`] if no filter was set
+    #[must_use]
+    pub fn sub_claim(&self) -> Option<&str> {
+        self.sub_claim
+    }
+
+    /// Only return sessions whose ID token has the given `sid` claim
+    #[must_use]
+    pub fn with_sid_claim(mut self, sid_claim: &'a str) -> Self {
+        self.sid_claim = Some(sid_claim);
+        self
+    }
+
+    /// Get the `sid` claim filter
+    ///
+    /// Returns [`None`] if no filter was set
+    #[must_use]
+    pub fn sid_claim(&self) -> Option<&str> {
+        self.sid_claim
+    }
+}
+
 /// An [`UpstreamOAuthSessionRepository`] helps interacting with
 /// [`UpstreamOAuthAuthorizationSession`] saved in the storage backend
 #[async_trait]
@@ -34,6 +95,22 @@ pub trait UpstreamOAuthSessionRepository: Send + Sync {
         id: Ulid,
     ) -> Result<Option<UpstreamOAuthAuthorizationSession>, Self::Error>;
 
+    /// Find the most recent session which was completed with the given link
+    ///
+    /// Returns `None` if no session was completed with this link
+    ///
+    /// # Parameters
+    ///
+    /// * `upstream_oauth_link`: the link to look for sessions of
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_latest_for_link(
+        &mut self,
+        upstream_oauth_link: &UpstreamOAuthLink,
+    ) -> Result<Option<UpstreamOAuthAuthorizationSession>, Self::Error>;
+
     /// Add a session to the database
     ///
     /// Returns the newly created session
@@ -74,6 +151,7 @@ pub trait UpstreamOAuthSessionRepository: Send + Sync {
     /// * `upstream_oauth_link`: the link to associate with the session
     /// * `id_token`: the ID token returned by the upstream OAuth provider, if
     ///   present
+    /// * `id_token_claims`: the verified claims of the ID token, if present
     /// * `extra_callback_parameters`: the extra query parameters returned in
     ///   the callback, if any
     ///
@@ -86,6 +164,7 @@ pub trait UpstreamOAuthSessionRepository: Send + Sync {
         upstream_oauth_authorization_session: UpstreamOAuthAuthorizationSession,
         upstream_oauth_link: &UpstreamOAuthLink,
         id_token: Option<String>,
+        id_token_claims: Option<serde_json::Value>,
         extra_callback_parameters: Option<serde_json::Value>,
         userinfo: Option<serde_json::Value>,
     ) -> Result<UpstreamOAuthAuthorizationSession, Self::Error>;
@@ -115,6 +194,11 @@ repository_impl!(UpstreamOAuthSessionRepository:
         id: Ulid,
     ) -> Result<Option<UpstreamOAuthAuthorizationSession>, Self::Error>;
 
+    async fn find_latest_for_link(
+        &mut self,
+        upstream_oauth_link: &UpstreamOAuthLink,
+    ) -> Result<Option<UpstreamOAuthAuthorizationSession>, Self::Error>;
+
     async fn add(
         &mut self,
         rng: &mut (dyn RngCore + Send),
@@ -131,6 +215,7 @@ repository_impl!(UpstreamOAuthSessionRepository:
         upstream_oauth_authorization_session: UpstreamOAuthAuthorizationSession,
         upstream_oauth_link: &UpstreamOAuthLink,
         id_token: Option<String>,
+        id_token_claims: Option<serde_json::Value>,
         extra_callback_parameters: Option<serde_json::Value>,
         userinfo: Option<serde_json::Value>,
     ) -> Result<UpstreamOAuthAuthorizationSession, Self::Error>;
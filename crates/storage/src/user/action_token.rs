@@ -0,0 +1,146 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use mas_data_model::{User, UserAction, UserActionToken};
+use rand_core::RngCore;
+
+use crate::{
+    BoxRepository, Clock, RepositoryAccess, RepositoryError, batch::BatchDeletionFilter,
+    repository_impl,
+};
+
+/// A [`UserActionTokenRepository`] helps interacting with [`UserActionToken`]
+/// saved in the storage backend
+#[async_trait]
+pub trait UserActionTokenRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Find a [`UserActionToken`] by its token
+    ///
+    /// Returns `None` if no [`UserActionToken`] was found
+    ///
+    /// # Parameters
+    ///
+    /// * `token`: The token of the [`UserActionToken`] to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_by_token(&mut self, token: &str) -> Result<Option<UserActionToken>, Self::Error>;
+
+    /// Create a new [`UserActionToken`] for the given user
+    ///
+    /// Returns the newly created [`UserActionToken`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] the token was issued to
+    /// * `action`: The action the token lets its bearer perform
+    /// * `token`: The secret token embedded in the link
+    /// * `expires_in`: How long the token can be used for
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        action: UserAction,
+        token: String,
+        expires_in: Duration,
+    ) -> Result<UserActionToken, Self::Error>;
+
+    /// Mark a [`UserActionToken`] as used
+    ///
+    /// Returns the updated [`UserActionToken`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `token`: The [`UserActionToken`] to mark as used
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails, or if the
+    /// token was already used
+    async fn consume(
+        &mut self,
+        clock: &dyn Clock,
+        token: UserActionToken,
+    ) -> Result<UserActionToken, Self::Error>;
+
+    /// Cleanup the [`UserActionToken`]s which expired
+    ///
+    /// Returns the number of tokens that were cleaned up
+    ///
+    /// # Parameters
+    ///
+    /// * `expired_before`: Only cleanup tokens which expired before this time
+    /// * `limit`: The maximum number of tokens to cleanup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn cleanup_expired(
+        &mut self,
+        expired_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
+}
+
+repository_impl!(UserActionTokenRepository:
+    async fn find_by_token(&mut self, token: &str)
+        -> Result<Option<UserActionToken>, Self::Error>;
+
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        action: UserAction,
+        token: String,
+        expires_in: Duration,
+    ) -> Result<UserActionToken, Self::Error>;
+
+    async fn consume(
+        &mut self,
+        clock: &dyn Clock,
+        token: UserActionToken,
+    ) -> Result<UserActionToken, Self::Error>;
+
+    async fn cleanup_expired(
+        &mut self,
+        expired_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
+);
+
+/// A [`BatchDeletionFilter`] selecting the [`UserActionToken`]s which expired
+/// before a given time
+#[derive(Debug, Clone, Copy)]
+pub struct ExpiredUserActionTokens {
+    /// Only select tokens which expired before this time
+    pub expired_before: DateTime<Utc>,
+}
+
+#[async_trait]
+impl BatchDeletionFilter for ExpiredUserActionTokens {
+    async fn delete_batch(
+        &self,
+        repo: &mut BoxRepository,
+        limit: usize,
+    ) -> Result<usize, RepositoryError> {
+        repo.user_action_token()
+            .cleanup_expired(self.expired_before, limit)
+            .await
+    }
+}
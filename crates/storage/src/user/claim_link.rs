@@ -0,0 +1,249 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::net::IpAddr;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{Session, User, UserClaimLink};
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{Clock, Page, Pagination, repository_impl};
+
+/// The state of a [`UserClaimLink`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserClaimLinkState {
+    /// The link can still be used
+    Active,
+
+    /// The link was used to claim the account
+    Claimed,
+
+    /// The link was revoked by an administrator
+    Revoked,
+
+    /// The link was neither claimed nor revoked, but has expired
+    Expired,
+}
+
+/// A filter to apply when listing [`UserClaimLink`]s
+#[derive(Debug, Clone, Copy)]
+pub struct UserClaimLinkFilter<'a> {
+    now: DateTime<Utc>,
+    user: Option<&'a User>,
+    state: Option<UserClaimLinkState>,
+}
+
+impl<'a> UserClaimLinkFilter<'a> {
+    /// Create a new empty filter
+    #[must_use]
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now,
+            user: None,
+            state: None,
+        }
+    }
+
+    /// Filter for links of a specific user
+    #[must_use]
+    pub fn for_user(mut self, user: &'a User) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    /// Filter for links in a specific state
+    #[must_use]
+    pub fn with_state(mut self, state: UserClaimLinkState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Get the user filter
+    ///
+    /// Returns [`None`] if no user filter was set
+    #[must_use]
+    pub fn user(&self) -> Option<&User> {
+        self.user
+    }
+
+    /// Get the state filter
+    ///
+    /// Returns [`None`] if no state filter was set
+    #[must_use]
+    pub fn state(&self) -> Option<UserClaimLinkState> {
+        self.state
+    }
+
+    /// Get the current time for this filter evaluation
+    #[must_use]
+    pub fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+}
+
+/// A [`UserClaimLinkRepository`] helps interacting with [`UserClaimLink`]s
+/// saved in the storage backend
+#[async_trait]
+pub trait UserClaimLinkRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Lookup a [`UserClaimLink`] by its ID
+    ///
+    /// Returns `None` if no [`UserClaimLink`] was found
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the [`UserClaimLink`] to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserClaimLink>, Self::Error>;
+
+    /// Find a [`UserClaimLink`] by its token
+    ///
+    /// Returns `None` if no [`UserClaimLink`] was found
+    ///
+    /// # Parameters
+    ///
+    /// * `token`: The token of the [`UserClaimLink`] to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_by_token(&mut self, token: &str) -> Result<Option<UserClaimLink>, Self::Error>;
+
+    /// Create a new [`UserClaimLink`] for the given user
+    ///
+    /// Returns the newly created [`UserClaimLink`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] the link lets claim
+    /// * `token`: The secret token embedded in the link
+    /// * `expires_at`: When the link expires
+    /// * `email`: The email address the link is sent to, if any
+    /// * `created_by`: The admin [`Session`] which created the link, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    #[expect(clippy::too_many_arguments)]
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        token: String,
+        expires_at: DateTime<Utc>,
+        email: Option<String>,
+        created_by: Option<&Session>,
+    ) -> Result<UserClaimLink, Self::Error>;
+
+    /// Mark a [`UserClaimLink`] as claimed
+    ///
+    /// Returns the updated [`UserClaimLink`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `link`: The [`UserClaimLink`] to mark as claimed
+    /// * `ip_address`: The IP address of the person claiming the account
+    /// * `user_agent`: The user agent of the person claiming the account
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn claim(
+        &mut self,
+        clock: &dyn Clock,
+        link: UserClaimLink,
+        ip_address: Option<IpAddr>,
+        user_agent: Option<String>,
+    ) -> Result<UserClaimLink, Self::Error>;
+
+    /// Revoke a [`UserClaimLink`]
+    ///
+    /// Returns the updated [`UserClaimLink`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `link`: The [`UserClaimLink`] to revoke
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn revoke(
+        &mut self,
+        clock: &dyn Clock,
+        link: UserClaimLink,
+    ) -> Result<UserClaimLink, Self::Error>;
+
+    /// List [`UserClaimLink`]s matching the given filter
+    ///
+    /// # Parameters
+    ///
+    /// * `filter`: The filter to apply
+    /// * `pagination`: The pagination parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn list(
+        &mut self,
+        filter: UserClaimLinkFilter<'_>,
+        pagination: Pagination,
+    ) -> Result<Page<UserClaimLink>, Self::Error>;
+
+    /// Count the [`UserClaimLink`]s matching the given filter
+    ///
+    /// # Parameters
+    ///
+    /// * `filter`: The filter to apply
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn count(&mut self, filter: UserClaimLinkFilter<'_>) -> Result<usize, Self::Error>;
+}
+
+repository_impl!(UserClaimLinkRepository:
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserClaimLink>, Self::Error>;
+    async fn find_by_token(&mut self, token: &str) -> Result<Option<UserClaimLink>, Self::Error>;
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        token: String,
+        expires_at: DateTime<Utc>,
+        email: Option<String>,
+        created_by: Option<&Session>,
+    ) -> Result<UserClaimLink, Self::Error>;
+    async fn claim(
+        &mut self,
+        clock: &dyn Clock,
+        link: UserClaimLink,
+        ip_address: Option<IpAddr>,
+        user_agent: Option<String>,
+    ) -> Result<UserClaimLink, Self::Error>;
+    async fn revoke(
+        &mut self,
+        clock: &dyn Clock,
+        link: UserClaimLink,
+    ) -> Result<UserClaimLink, Self::Error>;
+    async fn list(
+        &mut self,
+        filter: UserClaimLinkFilter<'_>,
+        pagination: Pagination,
+    ) -> Result<Page<UserClaimLink>, Self::Error>;
+    async fn count(&mut self, filter: UserClaimLinkFilter<'_>) -> Result<usize, Self::Error>;
+);
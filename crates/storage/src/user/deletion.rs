@@ -0,0 +1,135 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{User, UserDeletion};
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{Clock, repository_impl};
+
+/// A [`UserDeletionRepository`] helps interacting with [`UserDeletion`] saved
+/// in the storage backend
+#[async_trait]
+pub trait UserDeletionRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Lookup an [`UserDeletion`] by its ID
+    ///
+    /// Returns `None` if no [`UserDeletion`] was found
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the [`UserDeletion`] to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserDeletion>, Self::Error>;
+
+    /// Find the [`UserDeletion`] of a [`User`] which was neither cancelled nor
+    /// completed yet, if any
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The [`User`] for whom to find the deletion
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_pending(&mut self, user: &User) -> Result<Option<UserDeletion>, Self::Error>;
+
+    /// Schedule the deletion of a [`User`]
+    ///
+    /// Returns the newly created [`UserDeletion`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] to delete
+    /// * `delete_at`: When the grace period ends, and the user gets erased
+    /// * `hs_erase`: Whether to ask the homeserver to erase the user
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        delete_at: DateTime<Utc>,
+        hs_erase: bool,
+    ) -> Result<UserDeletion, Self::Error>;
+
+    /// Cancel a pending [`UserDeletion`]
+    ///
+    /// Returns the updated [`UserDeletion`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `deletion`: The [`UserDeletion`] to cancel
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails, or if the
+    /// deletion is no longer pending
+    async fn cancel(
+        &mut self,
+        clock: &dyn Clock,
+        deletion: UserDeletion,
+    ) -> Result<UserDeletion, Self::Error>;
+
+    /// Mark a pending [`UserDeletion`] as completed, once the grace period is
+    /// over
+    ///
+    /// Returns the updated [`UserDeletion`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `deletion`: The [`UserDeletion`] to complete
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails, or if the
+    /// deletion is no longer pending
+    async fn complete(
+        &mut self,
+        clock: &dyn Clock,
+        deletion: UserDeletion,
+    ) -> Result<UserDeletion, Self::Error>;
+}
+
+repository_impl!(UserDeletionRepository:
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserDeletion>, Self::Error>;
+
+    async fn find_pending(&mut self, user: &User) -> Result<Option<UserDeletion>, Self::Error>;
+
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        delete_at: DateTime<Utc>,
+        hs_erase: bool,
+    ) -> Result<UserDeletion, Self::Error>;
+
+    async fn cancel(
+        &mut self,
+        clock: &dyn Clock,
+        deletion: UserDeletion,
+    ) -> Result<UserDeletion, Self::Error>;
+
+    async fn complete(
+        &mut self,
+        clock: &dyn Clock,
+        deletion: UserDeletion,
+    ) -> Result<UserDeletion, Self::Error>;
+);
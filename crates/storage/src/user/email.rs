@@ -117,6 +117,32 @@ pub trait UserEmailRepository: Send + Sync {
     /// Returns [`Self::Error`] if the underlying repository fails
     async fn all(&mut self, user: &User) -> Result<Vec<UserEmail>, Self::Error>;
 
+    /// Get the primary [`UserEmail`] of a [`User`]
+    ///
+    /// This is the one the user picked as primary, or their oldest
+    /// [`UserEmail`] if they didn't pick one. Returns `None` if the user has
+    /// no [`UserEmail`]
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The [`User`] for whom to lookup the primary [`UserEmail`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn primary(&mut self, user: &User) -> Result<Option<UserEmail>, Self::Error>;
+
+    /// Set a [`UserEmail`] as the primary email address of its [`User`]
+    ///
+    /// # Parameters
+    ///
+    /// * `user_email`: The [`UserEmail`] to set as primary
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn set_as_primary(&mut self, user_email: &UserEmail) -> Result<(), Self::Error>;
+
     /// List [`UserEmail`] with the given filter and pagination
     ///
     /// # Parameters
@@ -232,6 +258,27 @@ pub trait UserEmailRepository: Send + Sync {
         registration: &UserRegistration,
     ) -> Result<UserEmailAuthentication, Self::Error>;
 
+    /// Add a new [`UserEmailAuthentication`] to let a [`User`] log in with a
+    /// code sent to one of their email addresses
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock to use
+    /// * `email`: The email address to send the code to
+    /// * `user`: The [`User`] who is logging in
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying repository fails
+    async fn add_authentication_for_login(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        email: String,
+        user: &User,
+    ) -> Result<UserEmailAuthentication, Self::Error>;
+
     /// Add a new [`UserEmailAuthenticationCode`] for a
     /// [`UserEmailAuthentication`]
     ///
@@ -314,6 +361,8 @@ repository_impl!(UserEmailRepository:
     async fn find_by_email(&mut self, email: &str) -> Result<Option<UserEmail>, Self::Error>;
 
     async fn all(&mut self, user: &User) -> Result<Vec<UserEmail>, Self::Error>;
+    async fn primary(&mut self, user: &User) -> Result<Option<UserEmail>, Self::Error>;
+    async fn set_as_primary(&mut self, user_email: &UserEmail) -> Result<(), Self::Error>;
     async fn list(
         &mut self,
         filter: UserEmailFilter<'_>,
@@ -348,6 +397,14 @@ repository_impl!(UserEmailRepository:
         registration: &UserRegistration,
     ) -> Result<UserEmailAuthentication, Self::Error>;
 
+    async fn add_authentication_for_login(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        email: String,
+        user: &User,
+    ) -> Result<UserEmailAuthentication, Self::Error>;
+
     async fn add_authentication_code(
         &mut self,
         rng: &mut (dyn RngCore + Send),
@@ -0,0 +1,170 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use mas_data_model::{User, UserMetadata};
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{Clock, repository_impl};
+
+/// A [`UserMetadataRepository`] helps interacting with arbitrary namespaced
+/// key-value [`UserMetadata`] attached to a [`User`]
+///
+/// Writes use optimistic concurrency control: updating or removing an entry
+/// only succeeds if it was not modified since it was read.
+#[async_trait]
+pub trait UserMetadataRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Lookup a [`UserMetadata`] entry by its ID
+    ///
+    /// Returns `None` if no entry was found
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the entry to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserMetadata>, Self::Error>;
+
+    /// Get the [`UserMetadata`] entry of a [`User`] for the given namespace
+    /// and key
+    ///
+    /// Returns `None` if no entry was found
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The [`User`] owning the entry
+    /// * `namespace`: The namespace of the entry
+    /// * `key`: The key of the entry
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn get(
+        &mut self,
+        user: &User,
+        namespace: &str,
+        key: &str,
+    ) -> Result<Option<UserMetadata>, Self::Error>;
+
+    /// Get all the [`UserMetadata`] entries of a [`User`] in a namespace,
+    /// ordered by key
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The [`User`] owning the entries
+    /// * `namespace`: The namespace of the entries
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn all_in_namespace(
+        &mut self,
+        user: &User,
+        namespace: &str,
+    ) -> Result<Vec<UserMetadata>, Self::Error>;
+
+    /// Add a new [`UserMetadata`] entry to a [`User`]
+    ///
+    /// Returns `None` if an entry already exists for the given namespace and
+    /// key, in which case it should be updated instead
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: A random number generator used to generate IDs
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] owning the entry
+    /// * `namespace`: The namespace of the entry
+    /// * `key`: The key of the entry
+    /// * `value`: The value of the entry
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        namespace: String,
+        key: String,
+        value: serde_json::Value,
+    ) -> Result<Option<UserMetadata>, Self::Error>;
+
+    /// Replace the value of a [`UserMetadata`] entry
+    ///
+    /// Returns `None` if the entry was modified or removed since it was read,
+    /// in which case the caller should read it again and retry
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `entry`: The [`UserMetadata`] entry to update, as previously read
+    /// * `value`: The new value of the entry
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn update(
+        &mut self,
+        clock: &dyn Clock,
+        entry: UserMetadata,
+        value: serde_json::Value,
+    ) -> Result<Option<UserMetadata>, Self::Error>;
+
+    /// Remove a [`UserMetadata`] entry
+    ///
+    /// Returns `false` if the entry was modified or removed since it was read
+    ///
+    /// # Parameters
+    ///
+    /// * `entry`: The [`UserMetadata`] entry to remove, as previously read
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn remove(&mut self, entry: UserMetadata) -> Result<bool, Self::Error>;
+}
+
+repository_impl!(UserMetadataRepository:
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserMetadata>, Self::Error>;
+
+    async fn get(
+        &mut self,
+        user: &User,
+        namespace: &str,
+        key: &str,
+    ) -> Result<Option<UserMetadata>, Self::Error>;
+
+    async fn all_in_namespace(
+        &mut self,
+        user: &User,
+        namespace: &str,
+    ) -> Result<Vec<UserMetadata>, Self::Error>;
+
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        namespace: String,
+        key: String,
+        value: serde_json::Value,
+    ) -> Result<Option<UserMetadata>, Self::Error>;
+
+    async fn update(
+        &mut self,
+        clock: &dyn Clock,
+        entry: UserMetadata,
+        value: serde_json::Value,
+    ) -> Result<Option<UserMetadata>, Self::Error>;
+
+    async fn remove(&mut self, entry: UserMetadata) -> Result<bool, Self::Error>;
+);
@@ -6,29 +6,54 @@
 
 //! Repositories to interact with entities related to user accounts
 
+use std::collections::{BTreeMap, BTreeSet};
+
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use mas_data_model::User;
 use rand_core::RngCore;
 use ulid::Ulid;
 
 use crate::{Clock, Page, Pagination, repository_impl};
 
+mod action_token;
+mod claim_link;
+mod deletion;
 mod email;
+mod metadata;
+mod passkey;
 mod password;
+mod phone;
 mod recovery;
+mod recovery_code;
 mod registration;
 mod registration_token;
 mod session;
 mod terms;
+mod terms_acceptance;
+mod totp;
+mod trusted_device;
 
 pub use self::{
+    action_token::{ExpiredUserActionTokens, UserActionTokenRepository},
+    claim_link::{UserClaimLinkFilter, UserClaimLinkRepository, UserClaimLinkState},
+    deletion::UserDeletionRepository,
     email::{UserEmailFilter, UserEmailRepository},
-    password::UserPasswordRepository,
-    recovery::UserRecoveryRepository,
+    metadata::UserMetadataRepository,
+    passkey::{StaleUserPasskeyChallenges, UserPasskeyRepository},
+    password::{StaleUserPasswordFailures, UserPasswordRepository},
+    phone::UserPhoneRepository,
+    recovery::{
+        UserRecoveryRepository, UserRecoverySessionApprovalState, UserRecoverySessionFilter,
+    },
+    recovery_code::UserRecoveryCodeRepository,
     registration::UserRegistrationRepository,
     registration_token::{UserRegistrationTokenFilter, UserRegistrationTokenRepository},
     session::{BrowserSessionFilter, BrowserSessionRepository},
     terms::UserTermsRepository,
+    terms_acceptance::UserTermsAcceptanceRepository,
+    totp::UserTotpRepository,
+    trusted_device::UserTrustedDeviceRepository,
 };
 
 /// The state of a user account
@@ -75,7 +100,7 @@ impl UserState {
 pub struct UserFilter<'a> {
     state: Option<UserState>,
     can_request_admin: Option<bool>,
-    _phantom: std::marker::PhantomData<&'a ()>,
+    referrer: Option<&'a User>,
 }
 
 impl UserFilter<'_> {
@@ -120,6 +145,13 @@ impl UserFilter<'_> {
         self
     }
 
+    /// Filter for users who were referred by the given user
+    #[must_use]
+    pub fn referred_by(mut self, referrer: &'a User) -> Self {
+        self.referrer = Some(referrer);
+        self
+    }
+
     /// Get the state filter
     ///
     /// Returns [`None`] if no state filter was set
@@ -135,6 +167,30 @@ impl UserFilter<'_> {
     pub fn can_request_admin(&self) -> Option<bool> {
         self.can_request_admin
     }
+
+    /// Get the referrer filter
+    ///
+    /// Returns [`None`] if no referrer filter was set
+    #[must_use]
+    pub fn referrer(&self) -> Option<&User> {
+        self.referrer
+    }
+}
+
+/// Aggregated figures about the sessions of a [`User`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct UserSessionCounts {
+    /// The number of active browser sessions
+    pub active_browser_sessions: usize,
+
+    /// The number of active OAuth 2.0 sessions
+    pub active_oauth2_sessions: usize,
+
+    /// The number of active compatibility sessions
+    pub active_compat_sessions: usize,
+
+    /// The last time any of the sessions of the user was active
+    pub last_active_at: Option<DateTime<Utc>>,
 }
 
 /// A [`UserRepository`] helps interacting with [`User`] saved in the storage
@@ -192,7 +248,8 @@ pub trait UserRepository: Send + Sync {
 
     /// Check if a [`User`] exists
     ///
-    /// Returns `true` if the [`User`] exists, `false` otherwise
+    /// Returns `true` if the [`User`] exists, or if a [`User`] used to have
+    /// this username before changing it, `false` otherwise
     ///
     /// # Parameters
     ///
@@ -244,6 +301,44 @@ pub trait UserRepository: Send + Sync {
     /// Returns [`Self::Error`] if the underlying repository fails
     async fn deactivate(&mut self, clock: &dyn Clock, user: User) -> Result<User, Self::Error>;
 
+    /// Reactivate a deactivated [`User`]
+    ///
+    /// Returns the reactivated [`User`]
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The [`User`] to reactivate
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn reactivate(&mut self, user: User) -> Result<User, Self::Error>;
+
+    /// Change the username of a [`User`]
+    ///
+    /// The previous username is recorded, and stays reserved so that no one
+    /// else can take it.
+    ///
+    /// Returns the [`User`] with the new username
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] to rename
+    /// * `username`: The new username of the [`User`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn rename(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: User,
+        username: String,
+    ) -> Result<User, Self::Error>;
+
     /// Set whether a [`User`] can request admin
     ///
     /// Returns the [`User`] with the new `can_request_admin` value
@@ -288,6 +383,37 @@ pub trait UserRepository: Send + Sync {
     /// Returns [`Self::Error`] if the underlying repository fails
     async fn count(&mut self, filter: UserFilter<'_>) -> Result<usize, Self::Error>;
 
+    /// Estimate the number of [`User`] with the given filter
+    ///
+    /// This uses the statistics of the database instead of counting the rows,
+    /// which is much faster on large tables, but can be off. Small counts are
+    /// still exact.
+    ///
+    /// # Parameters
+    ///
+    /// * `filter`: The filter parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn estimate_count(&mut self, filter: UserFilter<'_>) -> Result<usize, Self::Error>;
+
+    /// Get the [`UserSessionCounts`] of a batch of [`User`]s
+    ///
+    /// Users which don't exist are absent from the returned map
+    ///
+    /// # Parameters
+    ///
+    /// * `user_ids`: The IDs of the users to get the counts for
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn session_counts(
+        &mut self,
+        user_ids: BTreeSet<Ulid>,
+    ) -> Result<BTreeMap<Ulid, UserSessionCounts>, Self::Error>;
+
     /// Acquire a lock on the user to make sure device operations are done in a
     /// sequential way. The lock is released when the repository is saved or
     /// rolled back.
@@ -315,6 +441,14 @@ repository_impl!(UserRepository:
     async fn lock(&mut self, clock: &dyn Clock, user: User) -> Result<User, Self::Error>;
     async fn unlock(&mut self, user: User) -> Result<User, Self::Error>;
     async fn deactivate(&mut self, clock: &dyn Clock, user: User) -> Result<User, Self::Error>;
+    async fn reactivate(&mut self, user: User) -> Result<User, Self::Error>;
+    async fn rename(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: User,
+        username: String,
+    ) -> Result<User, Self::Error>;
     async fn set_can_request_admin(
         &mut self,
         user: User,
@@ -326,5 +460,10 @@ repository_impl!(UserRepository:
         pagination: Pagination,
     ) -> Result<Page<User>, Self::Error>;
     async fn count(&mut self, filter: UserFilter<'_>) -> Result<usize, Self::Error>;
+    async fn estimate_count(&mut self, filter: UserFilter<'_>) -> Result<usize, Self::Error>;
+    async fn session_counts(
+        &mut self,
+        user_ids: BTreeSet<Ulid>,
+    ) -> Result<BTreeMap<Ulid, UserSessionCounts>, Self::Error>;
     async fn acquire_lock_for_sync(&mut self, user: &User) -> Result<(), Self::Error>;
 );
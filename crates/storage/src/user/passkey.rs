@@ -0,0 +1,329 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mas_data_model::{BrowserSession, User, UserPasskey, UserPasskeyChallenge};
+use mas_jose::jwk::PublicJsonWebKey;
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{
+    BoxRepository, Clock, RepositoryAccess, RepositoryError, batch::BatchDeletionFilter,
+    repository_impl,
+};
+
+/// A [`UserPasskeyRepository`] helps interacting with [`UserPasskey`] and
+/// [`UserPasskeyChallenge`] saved in the storage backend
+#[async_trait]
+pub trait UserPasskeyRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Lookup an [`UserPasskey`] by its ID
+    ///
+    /// Returns `None` if no [`UserPasskey`] was found
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the [`UserPasskey`] to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserPasskey>, Self::Error>;
+
+    /// Find an [`UserPasskey`] by its credential ID
+    ///
+    /// Returns `None` if no [`UserPasskey`] was found
+    ///
+    /// # Parameters
+    ///
+    /// * `credential_id`: The credential ID, encoded as unpadded base64url
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_by_credential_id(
+        &mut self,
+        credential_id: &str,
+    ) -> Result<Option<UserPasskey>, Self::Error>;
+
+    /// Get all the [`UserPasskey`] of a [`User`], oldest first
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The [`User`] for whom to get the passkeys
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn all(&mut self, user: &User) -> Result<Vec<UserPasskey>, Self::Error>;
+
+    /// Add a new [`UserPasskey`] for a [`User`]
+    ///
+    /// Returns the newly created [`UserPasskey`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] who registered the passkey
+    /// * `credential_id`: The credential ID, encoded as unpadded base64url
+    /// * `name`: The name the user gave to the passkey
+    /// * `aaguid`: The AAGUID of the authenticator model
+    /// * `public_key`: The public key of the credential
+    /// * `sign_count`: The signature counter reported by the authenticator
+    /// * `transports`: The transports hints given by the authenticator
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    #[allow(clippy::too_many_arguments)]
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        credential_id: String,
+        name: String,
+        aaguid: String,
+        public_key: PublicJsonWebKey,
+        sign_count: u32,
+        transports: Vec<String>,
+    ) -> Result<UserPasskey, Self::Error>;
+
+    /// Rename an [`UserPasskey`]
+    ///
+    /// Returns the updated [`UserPasskey`]
+    ///
+    /// # Parameters
+    ///
+    /// * `passkey`: The [`UserPasskey`] to rename
+    /// * `name`: The new name of the passkey
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn rename(
+        &mut self,
+        passkey: UserPasskey,
+        name: String,
+    ) -> Result<UserPasskey, Self::Error>;
+
+    /// Record that an [`UserPasskey`] was used to log in
+    ///
+    /// Returns the updated [`UserPasskey`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `passkey`: The [`UserPasskey`] which was used
+    /// * `sign_count`: The new signature counter reported by the authenticator
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn record_use(
+        &mut self,
+        clock: &dyn Clock,
+        passkey: UserPasskey,
+        sign_count: u32,
+    ) -> Result<UserPasskey, Self::Error>;
+
+    /// Delete an [`UserPasskey`]
+    ///
+    /// # Parameters
+    ///
+    /// * `passkey`: The [`UserPasskey`] to delete
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn remove(&mut self, passkey: UserPasskey) -> Result<(), Self::Error>;
+
+    /// Add a new [`UserPasskeyChallenge`] to log in with a passkey
+    ///
+    /// Returns the newly created [`UserPasskeyChallenge`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn add_challenge(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+    ) -> Result<UserPasskeyChallenge, Self::Error>;
+
+    /// Add a new [`UserPasskeyChallenge`] to register a passkey from a
+    /// [`BrowserSession`]
+    ///
+    /// Returns the newly created [`UserPasskeyChallenge`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `session`: The [`BrowserSession`] registering the passkey
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn add_challenge_for_session(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        session: &BrowserSession,
+    ) -> Result<UserPasskeyChallenge, Self::Error>;
+
+    /// Lookup an [`UserPasskeyChallenge`] by its ID
+    ///
+    /// Returns `None` if no [`UserPasskeyChallenge`] was found
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the [`UserPasskeyChallenge`] to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup_challenge(
+        &mut self,
+        id: Ulid,
+    ) -> Result<Option<UserPasskeyChallenge>, Self::Error>;
+
+    /// Mark an [`UserPasskeyChallenge`] as completed, so that it can't be used
+    /// again
+    ///
+    /// Returns the updated [`UserPasskeyChallenge`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `challenge`: The [`UserPasskeyChallenge`] to complete
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails, or if the
+    /// challenge was already completed
+    async fn complete_challenge(
+        &mut self,
+        clock: &dyn Clock,
+        challenge: UserPasskeyChallenge,
+    ) -> Result<UserPasskeyChallenge, Self::Error>;
+
+    /// Cleanup the [`UserPasskeyChallenge`]s created before the given time
+    ///
+    /// Returns the number of challenges that were cleaned up
+    ///
+    /// # Parameters
+    ///
+    /// * `created_before`: Only cleanup challenges created before this time
+    /// * `limit`: The maximum number of challenges to cleanup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn cleanup_challenges(
+        &mut self,
+        created_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
+}
+
+repository_impl!(UserPasskeyRepository:
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserPasskey>, Self::Error>;
+
+    async fn find_by_credential_id(
+        &mut self,
+        credential_id: &str,
+    ) -> Result<Option<UserPasskey>, Self::Error>;
+
+    async fn all(&mut self, user: &User) -> Result<Vec<UserPasskey>, Self::Error>;
+
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        credential_id: String,
+        name: String,
+        aaguid: String,
+        public_key: PublicJsonWebKey,
+        sign_count: u32,
+        transports: Vec<String>,
+    ) -> Result<UserPasskey, Self::Error>;
+
+    async fn rename(
+        &mut self,
+        passkey: UserPasskey,
+        name: String,
+    ) -> Result<UserPasskey, Self::Error>;
+
+    async fn record_use(
+        &mut self,
+        clock: &dyn Clock,
+        passkey: UserPasskey,
+        sign_count: u32,
+    ) -> Result<UserPasskey, Self::Error>;
+
+    async fn remove(&mut self, passkey: UserPasskey) -> Result<(), Self::Error>;
+
+    async fn add_challenge(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+    ) -> Result<UserPasskeyChallenge, Self::Error>;
+
+    async fn add_challenge_for_session(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        session: &BrowserSession,
+    ) -> Result<UserPasskeyChallenge, Self::Error>;
+
+    async fn lookup_challenge(
+        &mut self,
+        id: Ulid,
+    ) -> Result<Option<UserPasskeyChallenge>, Self::Error>;
+
+    async fn complete_challenge(
+        &mut self,
+        clock: &dyn Clock,
+        challenge: UserPasskeyChallenge,
+    ) -> Result<UserPasskeyChallenge, Self::Error>;
+
+    async fn cleanup_challenges(
+        &mut self,
+        created_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
+);
+
+/// A [`BatchDeletionFilter`] selecting the [`UserPasskeyChallenge`]s created
+/// before a given time
+#[derive(Debug, Clone, Copy)]
+pub struct StaleUserPasskeyChallenges {
+    /// Only select challenges which were created before this time
+    pub created_before: DateTime<Utc>,
+}
+
+#[async_trait]
+impl BatchDeletionFilter for StaleUserPasskeyChallenges {
+    async fn delete_batch(
+        &self,
+        repo: &mut BoxRepository,
+        limit: usize,
+    ) -> Result<usize, RepositoryError> {
+        repo.user_passkey()
+            .cleanup_challenges(self.created_before, limit)
+            .await
+    }
+}
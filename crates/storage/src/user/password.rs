@@ -4,11 +4,17 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::net::IpAddr;
+
 use async_trait::async_trait;
-use mas_data_model::{Password, User};
+use chrono::{DateTime, Utc};
+use mas_data_model::{Password, User, UserPasswordPolicy};
 use rand_core::RngCore;
 
-use crate::{Clock, repository_impl};
+use crate::{
+    BoxRepository, Clock, RepositoryAccess, RepositoryError, batch::BatchDeletionFilter,
+    repository_impl,
+};
 
 /// A [`UserPasswordRepository`] helps interacting with [`Password`] saved in
 /// the storage backend
@@ -55,6 +61,214 @@ pub trait UserPasswordRepository: Send + Sync {
         hashed_password: String,
         upgraded_from: Option<&Password>,
     ) -> Result<Password, Self::Error>;
+
+    /// Require the user to change a password the next time they log in
+    ///
+    /// Returns the updated password
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `password`: The password the user has to change
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if underlying repository fails
+    async fn require_change(
+        &mut self,
+        clock: &dyn Clock,
+        password: Password,
+    ) -> Result<Password, Self::Error>;
+
+    /// List the last passwords set for a user, most recent first
+    ///
+    /// Passwords re-hashed when upgrading the hashing scheme are not counted
+    /// twice: only the latest hash of each password is returned.
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The user to list the passwords of
+    /// * `limit`: The maximum number of passwords to return
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if underlying repository fails
+    async fn history(&mut self, user: &User, limit: usize) -> Result<Vec<Password>, Self::Error>;
+
+    /// Get when a password was set by the user
+    ///
+    /// This follows the upgrades of the hashing scheme back to the original
+    /// password, so that upgrading a password doesn't reset its age.
+    ///
+    /// # Parameters
+    ///
+    /// * `password`: The password to get the age of
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if underlying repository fails
+    async fn changed_at(&mut self, password: &Password) -> Result<DateTime<Utc>, Self::Error>;
+
+    /// Get the overrides of the password policies for a user
+    ///
+    /// Returns `None` if the user follows the global policies
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The user to get the overrides of
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if underlying repository fails
+    async fn get_policy(&mut self, user: &User) -> Result<Option<UserPasswordPolicy>, Self::Error>;
+
+    /// Set the overrides of the password policies for a user
+    ///
+    /// An empty policy removes the overrides, so that the user follows the
+    /// global policies again.
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The user to set the overrides of
+    /// * `policy`: The overrides to set
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if underlying repository fails
+    async fn set_policy(
+        &mut self,
+        clock: &dyn Clock,
+        user: &User,
+        policy: UserPasswordPolicy,
+    ) -> Result<(), Self::Error>;
+
+    /// Record a failed password attempt on a user account
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The user whose password was wrong
+    /// * `ip_address`: The IP address the attempt came from, if known
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if underlying repository fails
+    async fn record_failure(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        ip_address: Option<IpAddr>,
+    ) -> Result<(), Self::Error>;
+
+    /// Count the failed password attempts on a user account since the given
+    /// time
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The user to count the failed attempts of
+    /// * `since`: Only count the attempts made after this time
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if underlying repository fails
+    async fn count_failures(
+        &mut self,
+        user: &User,
+        since: DateTime<Utc>,
+    ) -> Result<usize, Self::Error>;
+
+    /// Count the failed password attempts coming from an IP address since the
+    /// given time, across all user accounts
+    ///
+    /// # Parameters
+    ///
+    /// * `ip_address`: The IP address to count the failed attempts of
+    /// * `since`: Only count the attempts made after this time
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if underlying repository fails
+    async fn count_failures_from_ip(
+        &mut self,
+        ip_address: IpAddr,
+        since: DateTime<Utc>,
+    ) -> Result<usize, Self::Error>;
+
+    /// Clear the failed password attempts on a user account
+    ///
+    /// Returns the number of failed attempts which were cleared
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The user to clear the failed attempts of
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if underlying repository fails
+    async fn clear_failures(&mut self, user: &User) -> Result<usize, Self::Error>;
+
+    /// Cleanup the failed password attempts made before the given time
+    ///
+    /// Returns the number of failed attempts that were cleaned up
+    ///
+    /// # Parameters
+    ///
+    /// * `created_before`: Only cleanup attempts made before this time
+    /// * `limit`: The maximum number of attempts to cleanup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if underlying repository fails
+    async fn cleanup_failures(
+        &mut self,
+        created_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
+
+    /// Record that too many failed password attempts locked a user account,
+    /// or restricted the password logins from an IP address
+    ///
+    /// Unlike the failed attempts, lockouts are kept after the user
+    /// successfully logs in.
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The user whose account was locked, or `None` if only the IP
+    ///   address was restricted
+    /// * `ip_address`: The IP address the attempt which triggered the lockout
+    ///   came from, if known
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if underlying repository fails
+    async fn record_lockout(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: Option<&User>,
+        ip_address: Option<IpAddr>,
+    ) -> Result<(), Self::Error>;
+
+    /// Count the lockouts of a user account since the given time
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The user to count the lockouts of
+    /// * `since`: Only count the lockouts which happened after this time
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if underlying repository fails
+    async fn count_lockouts(
+        &mut self,
+        user: &User,
+        since: DateTime<Utc>,
+    ) -> Result<usize, Self::Error>;
 }
 
 repository_impl!(UserPasswordRepository:
@@ -68,4 +282,86 @@ repository_impl!(UserPasswordRepository:
         hashed_password: String,
         upgraded_from: Option<&Password>,
     ) -> Result<Password, Self::Error>;
+
+    async fn require_change(
+        &mut self,
+        clock: &dyn Clock,
+        password: Password,
+    ) -> Result<Password, Self::Error>;
+
+    async fn history(&mut self, user: &User, limit: usize) -> Result<Vec<Password>, Self::Error>;
+
+    async fn changed_at(&mut self, password: &Password) -> Result<DateTime<Utc>, Self::Error>;
+
+    async fn get_policy(&mut self, user: &User) -> Result<Option<UserPasswordPolicy>, Self::Error>;
+
+    async fn set_policy(
+        &mut self,
+        clock: &dyn Clock,
+        user: &User,
+        policy: UserPasswordPolicy,
+    ) -> Result<(), Self::Error>;
+
+    async fn record_failure(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        ip_address: Option<IpAddr>,
+    ) -> Result<(), Self::Error>;
+
+    async fn count_failures(
+        &mut self,
+        user: &User,
+        since: DateTime<Utc>,
+    ) -> Result<usize, Self::Error>;
+
+    async fn count_failures_from_ip(
+        &mut self,
+        ip_address: IpAddr,
+        since: DateTime<Utc>,
+    ) -> Result<usize, Self::Error>;
+
+    async fn clear_failures(&mut self, user: &User) -> Result<usize, Self::Error>;
+
+    async fn cleanup_failures(
+        &mut self,
+        created_before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<usize, Self::Error>;
+
+    async fn record_lockout(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: Option<&User>,
+        ip_address: Option<IpAddr>,
+    ) -> Result<(), Self::Error>;
+
+    async fn count_lockouts(
+        &mut self,
+        user: &User,
+        since: DateTime<Utc>,
+    ) -> Result<usize, Self::Error>;
 );
+
+/// A [`BatchDeletionFilter`] selecting the failed password attempts made
+/// before a given time
+#[derive(Debug, Clone, Copy)]
+pub struct StaleUserPasswordFailures {
+    /// Only select attempts which were made before this time
+    pub created_before: DateTime<Utc>,
+}
+
+#[async_trait]
+impl BatchDeletionFilter for StaleUserPasswordFailures {
+    async fn delete_batch(
+        &self,
+        repo: &mut BoxRepository,
+        limit: usize,
+    ) -> Result<usize, RepositoryError> {
+        repo.user_password()
+            .cleanup_failures(self.created_before, limit)
+            .await
+    }
+}
@@ -0,0 +1,261 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::Duration;
+use mas_data_model::{User, UserPhone, UserPhoneCode};
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{Clock, repository_impl};
+
+/// A [`UserPhoneRepository`] helps interacting with [`UserPhone`] and
+/// [`UserPhoneCode`] saved in the storage backend
+#[async_trait]
+pub trait UserPhoneRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Lookup an [`UserPhone`] by its ID
+    ///
+    /// Returns `None` if no [`UserPhone`] was found
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the [`UserPhone`] to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserPhone>, Self::Error>;
+
+    /// Find the [`UserPhone`] of a [`User`], confirmed or not
+    ///
+    /// Returns `None` if the user has no phone number
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The [`User`] for whom to get the phone number
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_for_user(&mut self, user: &User) -> Result<Option<UserPhone>, Self::Error>;
+
+    /// Find the confirmed [`UserPhone`] with the given phone number
+    ///
+    /// Returns `None` if no user confirmed this phone number, or if more than
+    /// one did, as the phone number then can't identify a single user
+    ///
+    /// # Parameters
+    ///
+    /// * `phone_number`: The phone number to look for, in the E.164 format
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_by_phone_number(
+        &mut self,
+        phone_number: &str,
+    ) -> Result<Option<UserPhone>, Self::Error>;
+
+    /// Add a new, unconfirmed [`UserPhone`] for a [`User`]
+    ///
+    /// Returns the newly created [`UserPhone`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] adding the phone number
+    /// * `phone_number`: The phone number, in the E.164 format
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails, or if the
+    /// user already has a phone number
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        phone_number: String,
+    ) -> Result<UserPhone, Self::Error>;
+
+    /// Confirm an [`UserPhone`], after the user entered a first valid code
+    ///
+    /// Returns the updated [`UserPhone`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `phone`: The [`UserPhone`] to confirm
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn confirm(
+        &mut self,
+        clock: &dyn Clock,
+        phone: UserPhone,
+    ) -> Result<UserPhone, Self::Error>;
+
+    /// Delete an [`UserPhone`], along with its codes
+    ///
+    /// # Parameters
+    ///
+    /// * `phone`: The [`UserPhone`] to delete
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn remove(&mut self, phone: UserPhone) -> Result<(), Self::Error>;
+
+    /// Add a new [`UserPhoneCode`] for an [`UserPhone`]
+    ///
+    /// Returns the newly created [`UserPhoneCode`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `phone`: The [`UserPhone`] the code is sent to
+    /// * `duration`: How long the code is valid for
+    /// * `code`: The code itself
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn add_code(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        phone: &UserPhone,
+        duration: Duration,
+        code: String,
+    ) -> Result<UserPhoneCode, Self::Error>;
+
+    /// Lookup an [`UserPhoneCode`] by its ID
+    ///
+    /// Returns `None` if no [`UserPhoneCode`] was found
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the [`UserPhoneCode`] to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup_code(&mut self, id: Ulid) -> Result<Option<UserPhoneCode>, Self::Error>;
+
+    /// Find an unconsumed [`UserPhoneCode`] of an [`UserPhone`] by its code
+    ///
+    /// Returns `None` if no such [`UserPhoneCode`] was found. The code may
+    /// have expired.
+    ///
+    /// # Parameters
+    ///
+    /// * `phone`: The [`UserPhone`] the code was sent to
+    /// * `code`: The code to find
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_code(
+        &mut self,
+        phone: &UserPhone,
+        code: &str,
+    ) -> Result<Option<UserPhoneCode>, Self::Error>;
+
+    /// Find the last [`UserPhoneCode`] created for an [`UserPhone`]
+    ///
+    /// Returns `None` if no code was ever sent to the phone number
+    ///
+    /// # Parameters
+    ///
+    /// * `phone`: The [`UserPhone`] the codes were sent to
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_latest_code(
+        &mut self,
+        phone: &UserPhone,
+    ) -> Result<Option<UserPhoneCode>, Self::Error>;
+
+    /// Consume an [`UserPhoneCode`], so that it can't be used again
+    ///
+    /// Returns the updated [`UserPhoneCode`], or `None` if it was already
+    /// consumed
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `code`: The [`UserPhoneCode`] to consume
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn consume_code(
+        &mut self,
+        clock: &dyn Clock,
+        code: UserPhoneCode,
+    ) -> Result<Option<UserPhoneCode>, Self::Error>;
+}
+
+repository_impl!(UserPhoneRepository:
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserPhone>, Self::Error>;
+
+    async fn find_for_user(&mut self, user: &User) -> Result<Option<UserPhone>, Self::Error>;
+
+    async fn find_by_phone_number(
+        &mut self,
+        phone_number: &str,
+    ) -> Result<Option<UserPhone>, Self::Error>;
+
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        phone_number: String,
+    ) -> Result<UserPhone, Self::Error>;
+
+    async fn confirm(
+        &mut self,
+        clock: &dyn Clock,
+        phone: UserPhone,
+    ) -> Result<UserPhone, Self::Error>;
+
+    async fn remove(&mut self, phone: UserPhone) -> Result<(), Self::Error>;
+
+    async fn add_code(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        phone: &UserPhone,
+        duration: Duration,
+        code: String,
+    ) -> Result<UserPhoneCode, Self::Error>;
+
+    async fn lookup_code(&mut self, id: Ulid) -> Result<Option<UserPhoneCode>, Self::Error>;
+
+    async fn find_code(
+        &mut self,
+        phone: &UserPhone,
+        code: &str,
+    ) -> Result<Option<UserPhoneCode>, Self::Error>;
+
+    async fn find_latest_code(
+        &mut self,
+        phone: &UserPhone,
+    ) -> Result<Option<UserPhoneCode>, Self::Error>;
+
+    async fn consume_code(
+        &mut self,
+        clock: &dyn Clock,
+        code: UserPhoneCode,
+    ) -> Result<Option<UserPhoneCode>, Self::Error>;
+);
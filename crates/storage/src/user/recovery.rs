@@ -11,7 +11,49 @@ use mas_data_model::{UserEmail, UserRecoverySession, UserRecoveryTicket};
 use rand_core::RngCore;
 use ulid::Ulid;
 
-use crate::{Clock, repository_impl};
+use crate::{Clock, Page, Pagination, repository_impl};
+
+/// The approval state of a [`UserRecoverySession`] which requires one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserRecoverySessionApprovalState {
+    /// The session is waiting for an administrator to review it
+    Pending,
+
+    /// An administrator approved the session
+    Approved,
+
+    /// An administrator denied the session
+    Denied,
+}
+
+/// A filter to apply when listing [`UserRecoverySession`]s
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserRecoverySessionFilter {
+    approval_state: Option<UserRecoverySessionApprovalState>,
+}
+
+impl UserRecoverySessionFilter {
+    /// Create a new empty filter
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Filter for sessions requiring an approval, in a specific approval state
+    #[must_use]
+    pub fn with_approval_state(mut self, state: UserRecoverySessionApprovalState) -> Self {
+        self.approval_state = Some(state);
+        self
+    }
+
+    /// Get the approval state filter
+    ///
+    /// Returns [`None`] if no approval state filter was set
+    #[must_use]
+    pub fn approval_state(&self) -> Option<UserRecoverySessionApprovalState> {
+        self.approval_state
+    }
+}
 
 /// A [`UserRecoveryRepository`] helps interacting with [`UserRecoverySession`]
 /// and [`UserRecoveryTicket`] saved in the storage backend
@@ -50,10 +92,15 @@ pub trait UserRecoveryRepository: Send + Sync {
     /// * `ip_address`: The IP address of the browser which initiated the
     ///   session, if known
     /// * `locale`: The locale of the browser which initiated the session
+    /// * `lost_second_factor`: Whether the user also lost access to their
+    ///   second factor
+    /// * `requires_approval`: Whether an administrator has to approve the
+    ///   session before the recovery links are sent
     ///
     /// # Errors
     ///
     /// Returns [`Self::Error`] if the underlying repository fails
+    #[expect(clippy::too_many_arguments)]
     async fn add_session(
         &mut self,
         rng: &mut (dyn RngCore + Send),
@@ -62,6 +109,75 @@ pub trait UserRecoveryRepository: Send + Sync {
         user_agent: String,
         ip_address: Option<IpAddr>,
         locale: String,
+        lost_second_factor: bool,
+        requires_approval: bool,
+    ) -> Result<UserRecoverySession, Self::Error>;
+
+    /// List [`UserRecoverySession`]s matching the given filter
+    ///
+    /// # Parameters
+    ///
+    /// * `filter`: The filter to apply
+    /// * `pagination`: The pagination parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn list_sessions(
+        &mut self,
+        filter: UserRecoverySessionFilter,
+        pagination: Pagination,
+    ) -> Result<Page<UserRecoverySession>, Self::Error>;
+
+    /// Count the [`UserRecoverySession`]s matching the given filter
+    ///
+    /// # Parameters
+    ///
+    /// * `filter`: The filter to apply
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn count_sessions(
+        &mut self,
+        filter: UserRecoverySessionFilter,
+    ) -> Result<usize, Self::Error>;
+
+    /// Approve a [`UserRecoverySession`] waiting for an administrator, so that
+    /// the recovery links can be sent
+    ///
+    /// Returns the updated [`UserRecoverySession`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock to use
+    /// * `user_recovery_session`: The [`UserRecoverySession`] to approve
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn approve_session(
+        &mut self,
+        clock: &dyn Clock,
+        user_recovery_session: UserRecoverySession,
+    ) -> Result<UserRecoverySession, Self::Error>;
+
+    /// Deny a [`UserRecoverySession`] waiting for an administrator
+    ///
+    /// Returns the updated [`UserRecoverySession`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock to use
+    /// * `user_recovery_session`: The [`UserRecoverySession`] to deny
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn deny_session(
+        &mut self,
+        clock: &dyn Clock,
+        user_recovery_session: UserRecoverySession,
     ) -> Result<UserRecoverySession, Self::Error>;
 
     /// Find a [`UserRecoveryTicket`] by its ticket
@@ -134,6 +250,31 @@ repository_impl!(UserRecoveryRepository:
         user_agent: String,
         ip_address: Option<IpAddr>,
         locale: String,
+        lost_second_factor: bool,
+        requires_approval: bool,
+    ) -> Result<UserRecoverySession, Self::Error>;
+
+    async fn list_sessions(
+        &mut self,
+        filter: UserRecoverySessionFilter,
+        pagination: Pagination,
+    ) -> Result<Page<UserRecoverySession>, Self::Error>;
+
+    async fn count_sessions(
+        &mut self,
+        filter: UserRecoverySessionFilter,
+    ) -> Result<usize, Self::Error>;
+
+    async fn approve_session(
+        &mut self,
+        clock: &dyn Clock,
+        user_recovery_session: UserRecoverySession,
+    ) -> Result<UserRecoverySession, Self::Error>;
+
+    async fn deny_session(
+        &mut self,
+        clock: &dyn Clock,
+        user_recovery_session: UserRecoverySession,
     ) -> Result<UserRecoverySession, Self::Error>;
 
     async fn find_ticket(
@@ -0,0 +1,105 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use mas_data_model::{User, UserRecoveryCode};
+use rand_core::RngCore;
+
+use crate::{Clock, repository_impl};
+
+/// A [`UserRecoveryCodeRepository`] helps interacting with
+/// [`UserRecoveryCode`] saved in the storage backend
+#[async_trait]
+pub trait UserRecoveryCodeRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Count the [`UserRecoveryCode`]s of a [`User`] which were not used yet
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The [`User`] for whom to count the recovery codes
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn count_unused(&mut self, user: &User) -> Result<usize, Self::Error>;
+
+    /// Replace all the [`UserRecoveryCode`]s of a [`User`] with a new set
+    ///
+    /// Returns the newly created [`UserRecoveryCode`]s
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] for whom to replace the recovery codes
+    /// * `code_hashes`: The hashes of the new codes
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn replace_all(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        code_hashes: Vec<String>,
+    ) -> Result<Vec<UserRecoveryCode>, Self::Error>;
+
+    /// Mark the unused [`UserRecoveryCode`] of a [`User`] with the given hash
+    /// as used
+    ///
+    /// Returns the consumed [`UserRecoveryCode`], or `None` if there is no
+    /// unused code with this hash
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] who entered the code
+    /// * `code_hash`: The hash of the code the user entered
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn consume(
+        &mut self,
+        clock: &dyn Clock,
+        user: &User,
+        code_hash: &str,
+    ) -> Result<Option<UserRecoveryCode>, Self::Error>;
+
+    /// Delete all the [`UserRecoveryCode`]s of a [`User`]
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The [`User`] for whom to delete the recovery codes
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn remove_all(&mut self, user: &User) -> Result<(), Self::Error>;
+}
+
+repository_impl!(UserRecoveryCodeRepository:
+    async fn count_unused(&mut self, user: &User) -> Result<usize, Self::Error>;
+
+    async fn replace_all(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        code_hashes: Vec<String>,
+    ) -> Result<Vec<UserRecoveryCode>, Self::Error>;
+
+    async fn consume(
+        &mut self,
+        clock: &dyn Clock,
+        user: &User,
+        code_hash: &str,
+    ) -> Result<Option<UserRecoveryCode>, Self::Error>;
+
+    async fn remove_all(&mut self, user: &User) -> Result<(), Self::Error>;
+);
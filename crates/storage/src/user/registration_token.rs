@@ -5,7 +5,7 @@
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use mas_data_model::UserRegistrationToken;
+use mas_data_model::{User, UserRegistrationToken};
 use rand_core::RngCore;
 use ulid::Ulid;
 
@@ -19,6 +19,7 @@ pub struct UserRegistrationTokenFilter {
     is_revoked: Option<bool>,
     is_expired: Option<bool>,
     is_valid: Option<bool>,
+    created_by: Option<Ulid>,
 }
 
 impl UserRegistrationTokenFilter {
@@ -31,6 +32,7 @@ impl UserRegistrationTokenFilter {
             is_revoked: None,
             is_expired: None,
             is_valid: None,
+            created_by: None,
         }
     }
 
@@ -63,6 +65,13 @@ impl UserRegistrationTokenFilter {
         self
     }
 
+    /// Filter the tokens issued by the given user
+    #[must_use]
+    pub fn with_created_by(mut self, user: &User) -> Self {
+        self.created_by = Some(user.id);
+        self
+    }
+
     /// Get the used status filter
     ///
     /// Returns [`None`] if no used status filter was set
@@ -95,6 +104,14 @@ impl UserRegistrationTokenFilter {
         self.is_valid
     }
 
+    /// Get the ID of the user who issued the tokens to filter on
+    ///
+    /// Returns [`None`] if no issuer filter was set
+    #[must_use]
+    pub fn created_by(&self) -> Option<Ulid> {
+        self.created_by
+    }
+
     /// Get the current time for this filter evaluation
     #[must_use]
     pub fn now(&self) -> DateTime<Utc> {
@@ -162,6 +179,36 @@ pub trait UserRegistrationTokenRepository: Send + Sync {
         expires_at: Option<DateTime<Utc>>,
     ) -> Result<UserRegistrationToken, Self::Error>;
 
+    /// Create a new [`UserRegistrationToken`] issued by a user, as an invite
+    ///
+    /// Returns the newly created [`UserRegistrationToken`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] issuing the token
+    /// * `token`: The token string
+    /// * `usage_limit`: Optional limit on how many times the token can be used
+    /// * `expires_at`: Optional expiration time for the token
+    /// * `assign_referrer`: Whether the users registering with the token get
+    ///   the issuing user as their referrer
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    #[expect(clippy::too_many_arguments)]
+    async fn add_invite(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        token: String,
+        usage_limit: Option<u32>,
+        expires_at: Option<DateTime<Utc>>,
+        assign_referrer: bool,
+    ) -> Result<UserRegistrationToken, Self::Error>;
+
     /// Increment the usage count of a [`UserRegistrationToken`]
     ///
     /// Returns the updated [`UserRegistrationToken`]
@@ -180,6 +227,38 @@ pub trait UserRegistrationTokenRepository: Send + Sync {
         token: UserRegistrationToken,
     ) -> Result<UserRegistrationToken, Self::Error>;
 
+    /// Record the user who issued a [`UserRegistrationToken`] as the referrer
+    /// of a [`User`] who registered with it, if the token assigns referrers
+    ///
+    /// Returns the ID of the referrer, if one was recorded
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] who registered with the token
+    /// * `token`: The [`UserRegistrationToken`] they used
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn record_referral(
+        &mut self,
+        clock: &dyn Clock,
+        user: &User,
+        token: &UserRegistrationToken,
+    ) -> Result<Option<Ulid>, Self::Error>;
+
+    /// Get the ID of the user who referred a [`User`], if any
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The [`User`] to get the referrer of
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn referrer(&mut self, user: &User) -> Result<Option<Ulid>, Self::Error>;
+
     /// Revoke a [`UserRegistrationToken`]
     ///
     /// # Parameters
@@ -286,11 +365,28 @@ repository_impl!(UserRegistrationTokenRepository:
         usage_limit: Option<u32>,
         expires_at: Option<DateTime<Utc>>,
     ) -> Result<UserRegistrationToken, Self::Error>;
+    async fn add_invite(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        token: String,
+        usage_limit: Option<u32>,
+        expires_at: Option<DateTime<Utc>>,
+        assign_referrer: bool,
+    ) -> Result<UserRegistrationToken, Self::Error>;
     async fn use_token(
         &mut self,
         clock: &dyn Clock,
         token: UserRegistrationToken,
     ) -> Result<UserRegistrationToken, Self::Error>;
+    async fn record_referral(
+        &mut self,
+        clock: &dyn Clock,
+        user: &User,
+        token: &UserRegistrationToken,
+    ) -> Result<Option<Ulid>, Self::Error>;
+    async fn referrer(&mut self, user: &User) -> Result<Option<Ulid>, Self::Error>;
     async fn revoke(
         &mut self,
         clock: &dyn Clock,
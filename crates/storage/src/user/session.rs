@@ -7,14 +7,19 @@
 use std::net::IpAddr;
 
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use mas_data_model::{
-    Authentication, BrowserSession, Password, UpstreamOAuthAuthorizationSession, User,
+    Authentication, BrowserSession, BrowserSessionElevation, IpLocation, Password,
+    UpstreamOAuthAuthorizationSession, User, UserEmailAuthentication, UserPasskey, UserPhone,
+    UserRecoveryCode, UserTotp,
 };
 use rand_core::RngCore;
 use ulid::Ulid;
 
-use crate::{Clock, Pagination, pagination::Page, repository_impl};
+use crate::{
+    Clock, Pagination, pagination::Page, repository_impl,
+    upstream_oauth2::UpstreamOAuthSessionFilter,
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BrowserSessionState {
@@ -39,6 +44,8 @@ pub struct BrowserSessionFilter<'a> {
     state: Option<BrowserSessionState>,
     last_active_before: Option<DateTime<Utc>>,
     last_active_after: Option<DateTime<Utc>>,
+    created_before: Option<DateTime<Utc>>,
+    authenticated_by_upstream_sessions: Option<UpstreamOAuthSessionFilter<'a>>,
 }
 
 impl<'a> BrowserSessionFilter<'a> {
@@ -91,6 +98,21 @@ impl<'a> BrowserSessionFilter<'a> {
         self.last_active_after
     }
 
+    /// Only return sessions created before the given time
+    #[must_use]
+    pub fn with_created_before(mut self, created_before: DateTime<Utc>) -> Self {
+        self.created_before = Some(created_before);
+        self
+    }
+
+    /// Get the created before filter
+    ///
+    /// Returns [`None`] if no created before filter was set
+    #[must_use]
+    pub fn created_before(&self) -> Option<DateTime<Utc>> {
+        self.created_before
+    }
+
     /// Only return active browser sessions
     #[must_use]
     pub fn active_only(mut self) -> Self {
@@ -110,6 +132,25 @@ impl<'a> BrowserSessionFilter<'a> {
     pub fn state(&self) -> Option<BrowserSessionState> {
         self.state
     }
+
+    /// Only return browser sessions which were authenticated by an upstream
+    /// OAuth 2.0 session matching the given filter
+    #[must_use]
+    pub fn authenticated_by_upstream_sessions_only(
+        mut self,
+        filter: UpstreamOAuthSessionFilter<'a>,
+    ) -> Self {
+        self.authenticated_by_upstream_sessions = Some(filter);
+        self
+    }
+
+    /// Get the upstream OAuth 2.0 session filter
+    ///
+    /// Returns [`None`] if no filter was set
+    #[must_use]
+    pub fn authenticated_by_upstream_sessions(&self) -> Option<UpstreamOAuthSessionFilter<'a>> {
+        self.authenticated_by_upstream_sessions
+    }
 }
 
 /// A [`BrowserSessionRepository`] helps interacting with [`BrowserSession`]
@@ -217,6 +258,24 @@ pub trait BrowserSessionRepository: Send + Sync {
     /// Returns [`Self::Error`] if the underlying repository fails
     async fn count(&mut self, filter: BrowserSessionFilter<'_>) -> Result<usize, Self::Error>;
 
+    /// Estimate the number of [`BrowserSession`] with the given filter
+    ///
+    /// This uses the statistics of the database instead of counting the rows,
+    /// which is much faster on large tables, but can be off. Small counts are
+    /// still exact.
+    ///
+    /// # Parameters
+    ///
+    /// * `filter`: The filter parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn estimate_count(
+        &mut self,
+        filter: BrowserSessionFilter<'_>,
+    ) -> Result<usize, Self::Error>;
+
     /// Authenticate a [`BrowserSession`] with the given [`Password`]
     ///
     /// # Parameters
@@ -237,6 +296,76 @@ pub trait BrowserSessionRepository: Send + Sync {
         user_password: &Password,
     ) -> Result<Authentication, Self::Error>;
 
+    /// Authenticate a [`BrowserSession`] with the given [`Password`] and
+    /// [`UserTotp`] second factor
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user_session`: The session to authenticate
+    /// * `user_password`: The password which was used to authenticate
+    /// * `user_totp`: The TOTP second factor which was used to authenticate
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn authenticate_with_password_and_totp(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_password: &Password,
+        user_totp: &UserTotp,
+    ) -> Result<Authentication, Self::Error>;
+
+    /// Authenticate a [`BrowserSession`] with the given [`Password`] and
+    /// [`UserRecoveryCode`], used instead of the second factor
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user_session`: The session to authenticate
+    /// * `user_password`: The password which was used to authenticate
+    /// * `user_recovery_code`: The recovery code which was used to
+    ///   authenticate
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn authenticate_with_password_and_recovery_code(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_password: &Password,
+        user_recovery_code: &UserRecoveryCode,
+    ) -> Result<Authentication, Self::Error>;
+
+    /// Authenticate a [`BrowserSession`] with the given [`Password`] and a
+    /// code sent by SMS to the given [`UserPhone`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user_session`: The session to authenticate
+    /// * `user_password`: The password which was used to authenticate
+    /// * `user_phone`: The phone number the code was sent to
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn authenticate_with_password_and_sms(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_password: &Password,
+        user_phone: &UserPhone,
+    ) -> Result<Authentication, Self::Error>;
+
     /// Authenticate a [`BrowserSession`] with the given
     /// [`UpstreamOAuthAuthorizationSession`]
     ///
@@ -259,6 +388,123 @@ pub trait BrowserSessionRepository: Send + Sync {
         upstream_oauth_session: &UpstreamOAuthAuthorizationSession,
     ) -> Result<Authentication, Self::Error>;
 
+    /// Authenticate a [`BrowserSession`] with the given [`UserPasskey`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user_session`: The session to authenticate
+    /// * `user_passkey`: The passkey which was used to authenticate
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn authenticate_with_passkey(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_passkey: &UserPasskey,
+    ) -> Result<Authentication, Self::Error>;
+
+    /// Authenticate a [`BrowserSession`] with the given completed
+    /// [`UserEmailAuthentication`], after the user entered the code sent to
+    /// their email address
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user_session`: The session to authenticate
+    /// * `user_email_authentication`: The email authentication which was used
+    ///   to authenticate
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn authenticate_with_email_code(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_email_authentication: &UserEmailAuthentication,
+    ) -> Result<Authentication, Self::Error>;
+
+    /// Authenticate a [`BrowserSession`] with the given completed
+    /// [`UserEmailAuthentication`] and [`UserTotp`] second factor
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user_session`: The session to authenticate
+    /// * `user_email_authentication`: The email authentication which was used
+    ///   to authenticate
+    /// * `user_totp`: The second factor which was used to authenticate
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn authenticate_with_email_code_and_totp(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_email_authentication: &UserEmailAuthentication,
+        user_totp: &UserTotp,
+    ) -> Result<Authentication, Self::Error>;
+
+    /// Authenticate a [`BrowserSession`] with the given completed
+    /// [`UserEmailAuthentication`] and [`UserRecoveryCode`], used instead of
+    /// the second factor
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user_session`: The session to authenticate
+    /// * `user_email_authentication`: The email authentication which was used
+    ///   to authenticate
+    /// * `user_recovery_code`: The recovery code which was consumed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn authenticate_with_email_code_and_recovery_code(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_email_authentication: &UserEmailAuthentication,
+        user_recovery_code: &UserRecoveryCode,
+    ) -> Result<Authentication, Self::Error>;
+
+    /// Authenticate a [`BrowserSession`] with the given completed
+    /// [`UserEmailAuthentication`] and a code sent by SMS to the given
+    /// [`UserPhone`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user_session`: The session to authenticate
+    /// * `user_email_authentication`: The email authentication which was used
+    ///   to authenticate
+    /// * `user_phone`: The phone number the code was sent to
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn authenticate_with_email_code_and_sms(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_email_authentication: &UserEmailAuthentication,
+        user_phone: &UserPhone,
+    ) -> Result<Authentication, Self::Error>;
+
     /// Get the last successful authentication for a [`BrowserSession`]
     ///
     /// # Params
@@ -273,19 +519,91 @@ pub trait BrowserSessionRepository: Send + Sync {
         user_session: &BrowserSession,
     ) -> Result<Option<Authentication>, Self::Error>;
 
+    /// Record that the user stepped up the authentication of a
+    /// [`BrowserSession`], elevating it for a limited time
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user_session`: The session to elevate
+    /// * `acr`: The strongest ACR value satisfied by the step-up, if any
+    /// * `ttl`: How long the session stays elevated
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn elevate(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        acr: Option<String>,
+        ttl: Duration,
+    ) -> Result<BrowserSessionElevation, Self::Error>;
+
+    /// Get the last elevation of a [`BrowserSession`] which didn't expire yet
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to check for expiration
+    /// * `user_session`: The session for which to get the elevation
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn get_active_elevation(
+        &mut self,
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+    ) -> Result<Option<BrowserSessionElevation>, Self::Error>;
+
+    /// Get the last elevation of a [`BrowserSession`], even if it expired
+    ///
+    /// # Parameters
+    ///
+    /// * `user_session`: The session for which to get the elevation
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn get_last_elevation(
+        &mut self,
+        user_session: &BrowserSession,
+    ) -> Result<Option<BrowserSessionElevation>, Self::Error>;
+
+    /// Check whether the user of a [`BrowserSession`] has another session,
+    /// active or not, which was last active from the given IP address with the
+    /// same user agent
+    ///
+    /// # Parameters
+    ///
+    /// * `user_session`: The session to compare the other sessions to
+    /// * `ip`: The IP address the session is used from
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn other_session_exists(
+        &mut self,
+        user_session: &BrowserSession,
+        ip: IpAddr,
+    ) -> Result<bool, Self::Error>;
+
     /// Record a batch of [`BrowserSession`] activity
     ///
     /// # Parameters
     ///
     /// * `activity`: A list of tuples containing the session ID, the last
-    ///   activity timestamp and the IP address of the client
+    ///   activity timestamp, the IP address of the client and where that
+    ///   address is located. The location is ignored if there is no IP address.
     ///
     /// # Errors
     ///
     /// Returns [`Self::Error`] if the underlying repository fails
     async fn record_batch_activity(
         &mut self,
-        activity: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>)>,
+        activity: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>, IpLocation)>,
     ) -> Result<(), Self::Error>;
 }
 
@@ -317,6 +635,7 @@ repository_impl!(BrowserSessionRepository:
     ) -> Result<Page<BrowserSession>, Self::Error>;
 
     async fn count(&mut self, filter: BrowserSessionFilter<'_>) -> Result<usize, Self::Error>;
+    async fn estimate_count(&mut self, filter: BrowserSessionFilter<'_>) -> Result<usize, Self::Error>;
 
     async fn authenticate_with_password(
         &mut self,
@@ -326,6 +645,33 @@ repository_impl!(BrowserSessionRepository:
         user_password: &Password,
     ) -> Result<Authentication, Self::Error>;
 
+    async fn authenticate_with_password_and_totp(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_password: &Password,
+        user_totp: &UserTotp,
+    ) -> Result<Authentication, Self::Error>;
+
+    async fn authenticate_with_password_and_recovery_code(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_password: &Password,
+        user_recovery_code: &UserRecoveryCode,
+    ) -> Result<Authentication, Self::Error>;
+
+    async fn authenticate_with_password_and_sms(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_password: &Password,
+        user_phone: &UserPhone,
+    ) -> Result<Authentication, Self::Error>;
+
     async fn authenticate_with_upstream(
         &mut self,
         rng: &mut (dyn RngCore + Send),
@@ -334,13 +680,82 @@ repository_impl!(BrowserSessionRepository:
         upstream_oauth_session: &UpstreamOAuthAuthorizationSession,
     ) -> Result<Authentication, Self::Error>;
 
+    async fn authenticate_with_passkey(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_passkey: &UserPasskey,
+    ) -> Result<Authentication, Self::Error>;
+
+    async fn authenticate_with_email_code(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_email_authentication: &UserEmailAuthentication,
+    ) -> Result<Authentication, Self::Error>;
+
+    async fn authenticate_with_email_code_and_totp(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_email_authentication: &UserEmailAuthentication,
+        user_totp: &UserTotp,
+    ) -> Result<Authentication, Self::Error>;
+
+    async fn authenticate_with_email_code_and_recovery_code(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_email_authentication: &UserEmailAuthentication,
+        user_recovery_code: &UserRecoveryCode,
+    ) -> Result<Authentication, Self::Error>;
+
+    async fn authenticate_with_email_code_and_sms(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        user_email_authentication: &UserEmailAuthentication,
+        user_phone: &UserPhone,
+    ) -> Result<Authentication, Self::Error>;
+
     async fn get_last_authentication(
         &mut self,
         user_session: &BrowserSession,
     ) -> Result<Option<Authentication>, Self::Error>;
 
+    async fn elevate(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+        acr: Option<String>,
+        ttl: Duration,
+    ) -> Result<BrowserSessionElevation, Self::Error>;
+
+    async fn get_active_elevation(
+        &mut self,
+        clock: &dyn Clock,
+        user_session: &BrowserSession,
+    ) -> Result<Option<BrowserSessionElevation>, Self::Error>;
+
+    async fn get_last_elevation(
+        &mut self,
+        user_session: &BrowserSession,
+    ) -> Result<Option<BrowserSessionElevation>, Self::Error>;
+
+    async fn other_session_exists(
+        &mut self,
+        user_session: &BrowserSession,
+        ip: IpAddr,
+    ) -> Result<bool, Self::Error>;
+
     async fn record_batch_activity(
         &mut self,
-        activity: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>)>,
+        activity: Vec<(Ulid, DateTime<Utc>, Option<IpAddr>, IpLocation)>,
     ) -> Result<(), Self::Error>;
 );
@@ -0,0 +1,65 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use mas_data_model::{TermsDocument, User, UserTermsAcceptance};
+use rand_core::RngCore;
+
+use crate::{Clock, repository_impl};
+
+/// A [`UserTermsAcceptanceRepository`] helps interacting with the
+/// [`UserTermsAcceptance`] saved in the storage backend, which record the
+/// versions of the legal documents accepted by users
+#[async_trait]
+pub trait UserTermsAcceptanceRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Record that a [`User`] accepted a version of a legal document
+    ///
+    /// If the user already accepted this version, the existing acceptance is
+    /// kept as is.
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: A random number generator used to generate IDs
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] accepting the document
+    /// * `document`: The version of the document the user is accepting
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn accept(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        document: &TermsDocument,
+    ) -> Result<(), Self::Error>;
+
+    /// Get all the [`UserTermsAcceptance`] of a [`User`], oldest first
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The [`User`] for whom to get the acceptances
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn all_for_user(&mut self, user: &User) -> Result<Vec<UserTermsAcceptance>, Self::Error>;
+}
+
+repository_impl!(UserTermsAcceptanceRepository:
+    async fn accept(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        document: &TermsDocument,
+    ) -> Result<(), Self::Error>;
+
+    async fn all_for_user(&mut self, user: &User) -> Result<Vec<UserTermsAcceptance>, Self::Error>;
+);
@@ -0,0 +1,151 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use mas_data_model::{User, UserTotp};
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{Clock, repository_impl};
+
+/// A [`UserTotpRepository`] helps interacting with [`UserTotp`] saved in the
+/// storage backend
+#[async_trait]
+pub trait UserTotpRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Lookup an [`UserTotp`] by its ID
+    ///
+    /// Returns `None` if no [`UserTotp`] was found
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the [`UserTotp`] to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserTotp>, Self::Error>;
+
+    /// Find the [`UserTotp`] of a [`User`], confirmed or not
+    ///
+    /// Returns `None` if the user has no TOTP second factor
+    ///
+    /// # Parameters
+    ///
+    /// * `user`: The [`User`] for whom to get the TOTP second factor
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn find_for_user(&mut self, user: &User) -> Result<Option<UserTotp>, Self::Error>;
+
+    /// Add a new, unconfirmed [`UserTotp`] for a [`User`]
+    ///
+    /// Returns the newly created [`UserTotp`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] enrolling the second factor
+    /// * `encrypted_secret`: The shared secret, encrypted with the site
+    ///   encryption key
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails, or if the
+    /// user already has a TOTP second factor
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        encrypted_secret: String,
+    ) -> Result<UserTotp, Self::Error>;
+
+    /// Confirm an [`UserTotp`], after the user entered a first valid code
+    ///
+    /// Returns the updated [`UserTotp`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `totp`: The [`UserTotp`] to confirm
+    /// * `step`: The time step of the code the user entered
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn confirm(
+        &mut self,
+        clock: &dyn Clock,
+        totp: UserTotp,
+        step: i64,
+    ) -> Result<UserTotp, Self::Error>;
+
+    /// Record that a code of an [`UserTotp`] was used
+    ///
+    /// Returns the updated [`UserTotp`], or `None` if a code of the same time
+    /// step or of a later one was already used
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `totp`: The [`UserTotp`] which was used
+    /// * `step`: The time step of the code the user entered
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn record_use(
+        &mut self,
+        clock: &dyn Clock,
+        totp: UserTotp,
+        step: i64,
+    ) -> Result<Option<UserTotp>, Self::Error>;
+
+    /// Delete an [`UserTotp`]
+    ///
+    /// # Parameters
+    ///
+    /// * `totp`: The [`UserTotp`] to delete
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn remove(&mut self, totp: UserTotp) -> Result<(), Self::Error>;
+}
+
+repository_impl!(UserTotpRepository:
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserTotp>, Self::Error>;
+
+    async fn find_for_user(&mut self, user: &User) -> Result<Option<UserTotp>, Self::Error>;
+
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        encrypted_secret: String,
+    ) -> Result<UserTotp, Self::Error>;
+
+    async fn confirm(
+        &mut self,
+        clock: &dyn Clock,
+        totp: UserTotp,
+        step: i64,
+    ) -> Result<UserTotp, Self::Error>;
+
+    async fn record_use(
+        &mut self,
+        clock: &dyn Clock,
+        totp: UserTotp,
+        step: i64,
+    ) -> Result<Option<UserTotp>, Self::Error>;
+
+    async fn remove(&mut self, totp: UserTotp) -> Result<(), Self::Error>;
+);
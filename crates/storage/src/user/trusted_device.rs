@@ -0,0 +1,142 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::Duration;
+use mas_data_model::{User, UserTrustedDevice};
+use rand_core::RngCore;
+use ulid::Ulid;
+
+use crate::{Clock, repository_impl};
+
+/// A [`UserTrustedDeviceRepository`] helps interacting with
+/// [`UserTrustedDevice`] saved in the storage backend
+#[async_trait]
+pub trait UserTrustedDeviceRepository: Send + Sync {
+    /// The error type returned by the repository
+    type Error;
+
+    /// Lookup an [`UserTrustedDevice`] by its ID
+    ///
+    /// Returns `None` if no [`UserTrustedDevice`] was found
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The ID of the [`UserTrustedDevice`] to lookup
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserTrustedDevice>, Self::Error>;
+
+    /// Get the [`UserTrustedDevice`] of a [`User`] which are still valid,
+    /// most recently trusted first
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to check whether the devices expired
+    /// * `user`: The [`User`] for whom to get the trusted devices
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn all_valid(
+        &mut self,
+        clock: &dyn Clock,
+        user: &User,
+    ) -> Result<Vec<UserTrustedDevice>, Self::Error>;
+
+    /// Trust a new device for a [`User`]
+    ///
+    /// Returns the newly created [`UserTrustedDevice`]
+    ///
+    /// # Parameters
+    ///
+    /// * `rng`: The random number generator to use
+    /// * `clock`: The clock used to generate timestamps
+    /// * `user`: The [`User`] who trusts the device
+    /// * `user_agent`: The user agent of the browser being trusted
+    /// * `ttl`: How long the device stays trusted
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        user_agent: Option<String>,
+        ttl: Duration,
+    ) -> Result<UserTrustedDevice, Self::Error>;
+
+    /// Record that an [`UserTrustedDevice`] was used to skip the second factor
+    ///
+    /// Returns the updated [`UserTrustedDevice`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `device`: The [`UserTrustedDevice`] which was used
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn record_use(
+        &mut self,
+        clock: &dyn Clock,
+        device: UserTrustedDevice,
+    ) -> Result<UserTrustedDevice, Self::Error>;
+
+    /// Revoke an [`UserTrustedDevice`], so that logins from it ask for the
+    /// second factor again
+    ///
+    /// Returns the revoked [`UserTrustedDevice`]
+    ///
+    /// # Parameters
+    ///
+    /// * `clock`: The clock used to generate timestamps
+    /// * `device`: The [`UserTrustedDevice`] to revoke
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Self::Error`] if the underlying repository fails
+    async fn revoke(
+        &mut self,
+        clock: &dyn Clock,
+        device: UserTrustedDevice,
+    ) -> Result<UserTrustedDevice, Self::Error>;
+}
+
+repository_impl!(UserTrustedDeviceRepository:
+    async fn lookup(&mut self, id: Ulid) -> Result<Option<UserTrustedDevice>, Self::Error>;
+
+    async fn all_valid(
+        &mut self,
+        clock: &dyn Clock,
+        user: &User,
+    ) -> Result<Vec<UserTrustedDevice>, Self::Error>;
+
+    async fn add(
+        &mut self,
+        rng: &mut (dyn RngCore + Send),
+        clock: &dyn Clock,
+        user: &User,
+        user_agent: Option<String>,
+        ttl: Duration,
+    ) -> Result<UserTrustedDevice, Self::Error>;
+
+    async fn record_use(
+        &mut self,
+        clock: &dyn Clock,
+        device: UserTrustedDevice,
+    ) -> Result<UserTrustedDevice, Self::Error>;
+
+    async fn revoke(
+        &mut self,
+        clock: &dyn Clock,
+        device: UserTrustedDevice,
+    ) -> Result<UserTrustedDevice, Self::Error>;
+);
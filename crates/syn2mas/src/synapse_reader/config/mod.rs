@@ -53,6 +53,9 @@ pub struct Config {
     #[serde(default = "default_true")]
     enable_set_display_name: bool,
 
+    #[serde(default = "default_true")]
+    enable_set_avatar_url: bool,
+
     #[serde(default)]
     pub user_consent: Option<UserConsentSection>,
 
@@ -169,6 +172,7 @@ impl Config {
             mas_config.account.email_change_allowed = enable_3pid_changes;
         }
         mas_config.account.displayname_change_allowed = self.enable_set_display_name;
+        mas_config.account.avatar_change_allowed = self.enable_set_avatar_url;
         if self.password_config.enabled {
             mas_config.passwords.enabled = true;
             mas_config.passwords.schemes = vec![
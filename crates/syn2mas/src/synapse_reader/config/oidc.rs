@@ -274,16 +274,17 @@ impl OidcProvider {
         };
 
         // "auto" doesn't mean the same thing depending on whether we request the openid
-        // scope or not
+        // scope or not: without it, there is no ID token and Synapse uses the userinfo
+        // endpoint instead
         let has_openid_scope = scope.contains(&OPENID);
         let fetch_userinfo = match self.user_profile_method {
-            UserProfileMethod::Auto => has_openid_scope,
+            UserProfileMethod::Auto => !has_openid_scope,
             UserProfileMethod::UserinfoEndpoint => true,
             UserProfileMethod::Other => {
                 warn!(
                     "The `user_profile_method` option is not supported, expected 'auto' or 'userinfo_endpoint'; assuming 'auto'."
                 );
-                has_openid_scope
+                !has_openid_scope
             }
         };
 
@@ -323,14 +324,17 @@ impl OidcProvider {
             enabled: true,
             id,
             synapse_idp_id: self.idp_id,
+            preset: None,
             issuer: self.issuer,
             human_name: self.idp_name,
             brand_name: self.idp_brand,
             client_id,
             client_secret: self.client_secret,
-            token_endpoint_auth_method,
+            token_endpoint_auth_method: Some(token_endpoint_auth_method),
             sign_in_with_apple: None,
             token_endpoint_auth_signing_alg: None,
+            token_endpoint_auth_signing_key_id: None,
+            tls_client_certificate: None,
             id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
             scope: scope.to_string(),
             discovery_mode,
@@ -345,6 +349,12 @@ impl OidcProvider {
             claims_imports,
             additional_authorization_parameters,
             forward_login_hint: self.forward_login_hint,
+            forward_prompt: true,
+            forward_acr_values: false,
+            forward_ui_locales: false,
+            store_tokens: false,
+            deprovisioning: None,
+            subject_migrations: Vec::new(),
         })
     }
 }
@@ -0,0 +1,166 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Tasks related to client-initiated backchannel authentication
+
+use anyhow::Context;
+use async_trait::async_trait;
+use mas_email::{Address, Mailbox};
+use mas_http::RequestBuilderExt as _;
+use mas_storage::queue::{NotifyBackchannelClientJob, SendBackchannelAuthenticationEmailJob};
+use mas_templates::{EmailBackchannelContext, TemplateContext as _};
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::{
+    State,
+    new_queue::{JobContext, JobError, RunnableJob},
+};
+
+#[async_trait]
+impl RunnableJob for SendBackchannelAuthenticationEmailJob {
+    #[tracing::instrument(
+        name = "job.send_backchannel_authentication_email",
+        fields(oauth2_backchannel_authentication_grant.id = %self.grant_id()),
+        skip_all,
+    )]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let clock = state.clock();
+        let mailer = state.mailer();
+        let url_builder = state.url_builder();
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+
+        let grant = repo
+            .oauth2_backchannel_authentication_grant()
+            .lookup(self.grant_id())
+            .await
+            .map_err(JobError::retry)?
+            .context("Backchannel authentication request not found")
+            .map_err(JobError::fail)?;
+
+        // The request may have expired or been handled before we got a chance to
+        // send the email
+        if !grant.is_pending() || grant.is_expired(clock.now()) {
+            info!("Backchannel authentication request is no longer pending, not sending email");
+            return Ok(());
+        }
+
+        let client = repo
+            .oauth2_client()
+            .lookup(grant.client_id)
+            .await
+            .map_err(JobError::retry)?
+            .context("Client not found")
+            .map_err(JobError::fail)?;
+
+        let user = repo
+            .user()
+            .lookup(grant.user_id)
+            .await
+            .map_err(JobError::retry)?
+            .context("User not found")
+            .map_err(JobError::fail)?;
+
+        let Some(email) = repo
+            .user_email()
+            .primary(&user)
+            .await
+            .map_err(JobError::retry)?
+        else {
+            info!("User has no email address, not sending backchannel authentication email");
+            return Ok(());
+        };
+
+        let language = self.language().parse().map_err(JobError::fail)?;
+        let url = url_builder.backchannel_authentication_consent_link(grant.id);
+        let context =
+            EmailBackchannelContext::new(user.clone(), client, grant, url).with_language(language);
+
+        let address: Address = email.email.parse().map_err(JobError::fail)?;
+        let mailbox = Mailbox::new(Some(user.username.clone()), address);
+
+        info!("Sending backchannel authentication email to {}", mailbox);
+
+        // XXX: we only log if the email fails to send, as the client will time out
+        if let Err(e) = mailer.send_backchannel_email(mailbox, &context).await {
+            error!(
+                error = &e as &dyn std::error::Error,
+                "Failed to send backchannel authentication email"
+            );
+        }
+
+        repo.save().await.map_err(JobError::fail)?;
+
+        Ok(())
+    }
+}
+
+/// The body of the notification sent to clients using the `ping` mode
+#[derive(Serialize)]
+struct PingNotification<'a> {
+    auth_req_id: &'a str,
+}
+
+#[async_trait]
+impl RunnableJob for NotifyBackchannelClientJob {
+    #[tracing::instrument(
+        name = "job.notify_backchannel_client",
+        fields(
+            oauth2_backchannel_authentication_grant.id = %self.grant_id(),
+            client.id,
+        ),
+        skip_all,
+    )]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+
+        let grant = repo
+            .oauth2_backchannel_authentication_grant()
+            .lookup(self.grant_id())
+            .await
+            .map_err(JobError::retry)?
+            .context("Backchannel authentication request not found")
+            .map_err(JobError::fail)?;
+
+        let client = repo
+            .oauth2_client()
+            .lookup(grant.client_id)
+            .await
+            .map_err(JobError::retry)?
+            .context("Client not found")
+            .map_err(JobError::fail)?;
+
+        repo.cancel().await.map_err(JobError::retry)?;
+
+        tracing::Span::current().record("client.id", &client.client_id);
+
+        let (Some(endpoint), Some(token)) = (
+            client.backchannel_client_notification_endpoint.as_ref(),
+            grant.client_notification_token.as_deref(),
+        ) else {
+            info!("Client doesn't use the ping mode, not notifying it");
+            return Ok(());
+        };
+
+        state
+            .http_client()
+            .post(endpoint.clone())
+            .bearer_auth(token)
+            .json(&PingNotification {
+                auth_req_id: &grant.auth_req_id,
+            })
+            .send_traced()
+            .await
+            .context("Failed to notify the client")
+            .map_err(JobError::retry)?
+            .error_for_status()
+            .context("Client rejected the notification")
+            .map_err(JobError::retry)?;
+
+        info!("Notified the client of the backchannel authentication result");
+
+        Ok(())
+    }
+}
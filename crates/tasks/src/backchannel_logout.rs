@@ -0,0 +1,324 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Tasks related to OpenID Connect Back-Channel Logout
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Duration;
+use mas_data_model::{AuditEventKind, AuthenticationMethod, Client};
+use mas_http::RequestBuilderExt as _;
+use mas_iana::jose::JsonWebSignatureAlg;
+use mas_jose::{
+    claims,
+    jwt::{JsonWebSignatureHeader, Jwt},
+};
+use mas_storage::{
+    Pagination,
+    oauth2::OAuth2SessionFilter,
+    queue::{
+        QueueJobRepositoryExt as _, SendBackchannelLogoutJob,
+        SendBrowserSessionBackchannelLogoutsJob, SyncDevicesJob,
+    },
+};
+use serde::Serialize;
+use tracing::info;
+use ulid::Ulid;
+
+use crate::{
+    State,
+    new_queue::{JobContext, JobError, RunnableJob},
+};
+
+/// The event identifying a logout token, as per the specification
+const BACKCHANNEL_LOGOUT_EVENT: &str = "http://schemas.openid.net/event/backchannel-logout";
+
+/// The body of the request sent to the client
+#[derive(Serialize)]
+struct LogoutTokenRequest<'a> {
+    logout_token: &'a str,
+}
+
+/// Build and sign a logout token for the given client
+fn sign_logout_token(
+    state: &State,
+    client: &Client,
+    sub: Option<&str>,
+    sid: Option<Ulid>,
+) -> Result<String, anyhow::Error> {
+    let clock = state.clock();
+    let mut rng = state.rng();
+    let now = clock.now();
+
+    let mut claims = HashMap::new();
+    claims::ISS.insert(&mut claims, state.url_builder().oidc_issuer().to_string())?;
+    claims::AUD.insert(&mut claims, client.client_id.clone())?;
+    claims::IAT.insert(&mut claims, now)?;
+    claims::EXP.insert(
+        &mut claims,
+        now + Duration::microseconds(2 * 60 * 1000 * 1000),
+    )?;
+    claims::JTI.insert(
+        &mut claims,
+        Ulid::from_datetime_with_source(now.into(), &mut rng).to_string(),
+    )?;
+    claims::EVENTS.insert(
+        &mut claims,
+        serde_json::json!({ BACKCHANNEL_LOGOUT_EVENT: {} }),
+    )?;
+
+    if let Some(sub) = sub {
+        claims::SUB.insert(&mut claims, sub)?;
+    }
+
+    if let Some(sid) = sid {
+        claims::SID.insert(&mut claims, sid.to_string())?;
+    }
+
+    // Logout tokens are signed the same way as ID tokens
+    let alg = client
+        .id_token_signed_response_alg
+        .clone()
+        .unwrap_or(JsonWebSignatureAlg::Rs256);
+    let key = state
+        .key_store()
+        .signing_key_for_algorithm(&alg)
+        .context("No signing key found for the algorithm")?;
+    let signer = key.params().signing_key_for_alg(&alg)?;
+    let header = JsonWebSignatureHeader::new(alg)
+        .with_kid(key.kid().context("Signing key has no key ID")?)
+        .with_typ("logout+jwt".to_owned());
+
+    let logout_token = Jwt::sign_with_rng(&mut rng, header, claims, &signer)?;
+    Ok(logout_token.into_string())
+}
+
+#[async_trait]
+impl RunnableJob for SendBackchannelLogoutJob {
+    #[tracing::instrument(
+        name = "job.send_backchannel_logout",
+        fields(
+            oauth2_session.id = %self.oauth2_session_id(),
+            client.id,
+        ),
+        skip_all,
+    )]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+
+        let session = repo
+            .oauth2_session()
+            .lookup(self.oauth2_session_id())
+            .await
+            .map_err(JobError::retry)?
+            .context("OAuth 2.0 session not found")
+            .map_err(JobError::fail)?;
+
+        let client = repo
+            .oauth2_client()
+            .lookup(session.client_id)
+            .await
+            .map_err(JobError::retry)?
+            .context("Client not found")
+            .map_err(JobError::fail)?;
+
+        tracing::Span::current().record("client.id", &client.client_id);
+
+        let Some(backchannel_logout_uri) = client.backchannel_logout_uri.as_ref() else {
+            info!("Client doesn't use back-channel logout, not notifying it");
+            return Ok(());
+        };
+
+        let user = match session
+            .user_id
+            .filter(|_| client.backchannel_logout_include_sub)
+        {
+            Some(user_id) => Some(
+                repo.user()
+                    .lookup(user_id)
+                    .await
+                    .map_err(JobError::retry)?
+                    .context("User not found")
+                    .map_err(JobError::fail)?,
+            ),
+            None => None,
+        };
+
+        // The client knows the user by their pairwise subject identifier if it
+        // has a sector identifier
+        let sub = match (&user, &client.pairwise_sector_identifier) {
+            (Some(user), Some(sector_identifier)) => Some(
+                repo.oauth2_pairwise_subject()
+                    .find_or_add(&mut state.rng(), &state.clock(), user, sector_identifier)
+                    .await
+                    .map_err(JobError::retry)?,
+            ),
+            (Some(user), None) => Some(user.sub.clone()),
+            (None, _) => None,
+        };
+
+        repo.save().await.map_err(JobError::retry)?;
+
+        let sid = session
+            .user_session_id
+            .filter(|_| client.backchannel_logout_session_required);
+
+        // A logout token must identify either the user or the browser session
+        if sub.is_none() && sid.is_none() {
+            info!(
+                "Logout token would have neither a sub nor a sid claim, not notifying the client"
+            );
+            return Ok(());
+        }
+
+        let logout_token =
+            sign_logout_token(state, &client, sub.as_deref(), sid).map_err(JobError::fail)?;
+
+        state
+            .http_client()
+            .post(backchannel_logout_uri.clone())
+            .form(&LogoutTokenRequest {
+                logout_token: &logout_token,
+            })
+            .send_traced()
+            .await
+            .context("Failed to send the logout token")
+            .map_err(JobError::retry)?
+            .error_for_status()
+            .context("Client rejected the logout token")
+            .map_err(JobError::retry)?;
+
+        info!("Sent the back-channel logout token to the client");
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RunnableJob for SendBrowserSessionBackchannelLogoutsJob {
+    #[tracing::instrument(
+        name = "job.send_browser_session_backchannel_logouts",
+        fields(browser_session.id = %self.browser_session_id()),
+        skip_all,
+    )]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let clock = state.clock();
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+
+        let browser_session = repo
+            .browser_session()
+            .lookup(self.browser_session_id())
+            .await
+            .map_err(JobError::retry)?
+            .context("Browser session not found")
+            .map_err(JobError::fail)?;
+
+        // Sessions authenticated through an upstream provider don't outlive the
+        // browser session: when it ends, the OAuth 2.0 sessions started from it
+        // are ended as well, and their tokens revoked
+        let last_authentication = repo
+            .browser_session()
+            .get_last_authentication(&browser_session)
+            .await
+            .map_err(JobError::retry)?;
+        let cascade = matches!(
+            last_authentication.map(|a| a.authentication_method),
+            Some(AuthenticationMethod::UpstreamOAuth2 { .. })
+        );
+
+        let filter = OAuth2SessionFilter::new()
+            .for_browser_session(&browser_session)
+            .active_only();
+
+        let mut ended_sessions = 0;
+        let mut pagination = Pagination::first(100);
+        loop {
+            let page = repo
+                .oauth2_session()
+                .list(filter, pagination)
+                .await
+                .map_err(JobError::retry)?;
+
+            let next_cursor = page
+                .edges
+                .last()
+                .filter(|_| page.has_next_page)
+                .map(|last| last.id);
+
+            for session in page.edges {
+                // The job itself checks whether the client wants to be notified
+                repo.queue_job()
+                    .schedule_job(&mut rng, &clock, SendBackchannelLogoutJob::new(&session))
+                    .await
+                    .map_err(JobError::retry)?;
+
+                if !cascade {
+                    continue;
+                }
+
+                let revoked_access_tokens = repo
+                    .oauth2_access_token()
+                    .revoke_for_session(&clock, &session)
+                    .await
+                    .map_err(JobError::retry)?;
+                let revoked_refresh_tokens = repo
+                    .oauth2_refresh_token()
+                    .revoke_for_session(&clock, &session)
+                    .await
+                    .map_err(JobError::retry)?;
+
+                info!(
+                    oauth2_session.id = %session.id,
+                    oauth2_client.id = %session.client_id,
+                    revoked_access_tokens,
+                    revoked_refresh_tokens,
+                    "Ended the OAuth 2.0 session authenticated by the upstream-linked browser session"
+                );
+
+                repo.audit_event()
+                    .add(
+                        &mut rng,
+                        &clock,
+                        Some(browser_session.user.id),
+                        AuditEventKind::OAuth2SessionEndedWithBrowserSession {
+                            oauth2_session_id: session.id,
+                            oauth2_client_id: session.client_id,
+                            browser_session_id: browser_session.id,
+                            revoked_access_tokens,
+                            revoked_refresh_tokens,
+                        },
+                    )
+                    .await
+                    .map_err(JobError::retry)?;
+
+                repo.oauth2_session()
+                    .finish(&clock, session)
+                    .await
+                    .map_err(JobError::retry)?;
+                ended_sessions += 1;
+            }
+
+            let Some(cursor) = next_cursor else {
+                break;
+            };
+            pagination = pagination.after(cursor);
+        }
+
+        if ended_sessions > 0 {
+            // Remove the devices of the ended sessions from the homeserver
+            repo.queue_job()
+                .schedule_job(&mut rng, &clock, SyncDevicesJob::new(&browser_session.user))
+                .await
+                .map_err(JobError::retry)?;
+        }
+
+        repo.save().await.map_err(JobError::retry)?;
+
+        Ok(())
+    }
+}
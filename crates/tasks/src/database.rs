@@ -7,7 +7,17 @@
 //! Database-related tasks
 
 use async_trait::async_trait;
-use mas_storage::queue::{CleanupExpiredTokensJob, PruneStalePolicyDataJob};
+use chrono::Duration;
+use mas_storage::{
+    Clock,
+    batch::delete_in_batches,
+    oauth2::{
+        ExpiredBackchannelAuthenticationGrants, ExpiredDPoPProofs,
+        ExpiredPushedAuthorizationRequests, RevokedAccessTokens,
+    },
+    queue::{CleanupExpiredTokensJob, PruneStalePolicyDataJob, RefreshLoginStatsJob},
+    user::{ExpiredUserActionTokens, StaleUserPasskeyChallenges, StaleUserPasswordFailures},
+};
 use tracing::{debug, info};
 
 use crate::{
@@ -19,22 +29,163 @@ use crate::{
 impl RunnableJob for CleanupExpiredTokensJob {
     #[tracing::instrument(name = "job.cleanup_expired_tokens", skip_all)]
     async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        // Delete in small batches, so that we don't hold a long-running transaction
+        // on a potentially large table
+        const BATCH_SIZE: usize = 1000;
+
         let clock = state.clock();
-        let mut repo = state.repository().await.map_err(JobError::retry)?;
 
-        let count = repo
-            .oauth2_access_token()
-            .cleanup_revoked(&clock)
-            .await
-            .map_err(JobError::retry)?;
-        repo.save().await.map_err(JobError::retry)?;
+        // Cleanup tokens that were revoked more than an hour ago
+        let filter = RevokedAccessTokens {
+            revoked_before: clock.now() - Duration::hours(1),
+        };
 
+        let progress = delete_in_batches(&state.repository_factory, &filter, BATCH_SIZE, |p| {
+            debug!(
+                batches = p.batches,
+                deleted = p.deleted,
+                "cleaning up revoked tokens"
+            );
+        })
+        .await
+        .map_err(JobError::retry)?;
+
+        let count = progress.deleted;
         if count == 0 {
             debug!("no token to clean up");
         } else {
             info!(count, "cleaned up revoked tokens");
         }
 
+        // DPoP proofs are only remembered for as long as they could be replayed
+        let filter = ExpiredDPoPProofs {
+            expired_before: clock.now(),
+        };
+
+        let progress = delete_in_batches(&state.repository_factory, &filter, BATCH_SIZE, |p| {
+            debug!(
+                batches = p.batches,
+                deleted = p.deleted,
+                "cleaning up expired DPoP proofs"
+            );
+        })
+        .await
+        .map_err(JobError::retry)?;
+
+        if progress.deleted > 0 {
+            info!(count = progress.deleted, "cleaned up expired DPoP proofs");
+        }
+
+        // Pushed authorization requests are short-lived and single-use
+        let filter = ExpiredPushedAuthorizationRequests {
+            expired_before: clock.now(),
+        };
+
+        let progress = delete_in_batches(&state.repository_factory, &filter, BATCH_SIZE, |p| {
+            debug!(
+                batches = p.batches,
+                deleted = p.deleted,
+                "cleaning up expired pushed authorization requests"
+            );
+        })
+        .await
+        .map_err(JobError::retry)?;
+
+        if progress.deleted > 0 {
+            info!(
+                count = progress.deleted,
+                "cleaned up expired pushed authorization requests"
+            );
+        }
+
+        // Backchannel authentication requests can't be used once they expired
+        let filter = ExpiredBackchannelAuthenticationGrants {
+            expired_before: clock.now(),
+        };
+
+        let progress = delete_in_batches(&state.repository_factory, &filter, BATCH_SIZE, |p| {
+            debug!(
+                batches = p.batches,
+                deleted = p.deleted,
+                "cleaning up expired backchannel authentication requests"
+            );
+        })
+        .await
+        .map_err(JobError::retry)?;
+
+        if progress.deleted > 0 {
+            info!(
+                count = progress.deleted,
+                "cleaned up expired backchannel authentication requests"
+            );
+        }
+
+        let filter = ExpiredUserActionTokens {
+            expired_before: clock.now(),
+        };
+
+        let progress = delete_in_batches(&state.repository_factory, &filter, BATCH_SIZE, |p| {
+            debug!(
+                batches = p.batches,
+                deleted = p.deleted,
+                "cleaning up expired user action tokens"
+            );
+        })
+        .await
+        .map_err(JobError::retry)?;
+
+        if progress.deleted > 0 {
+            info!(
+                count = progress.deleted,
+                "cleaned up expired user action tokens"
+            );
+        }
+
+        // Passkey challenges are only valid for a few minutes, so cleanup the ones
+        // created more than an hour ago
+        let filter = StaleUserPasskeyChallenges {
+            created_before: clock.now() - Duration::hours(1),
+        };
+
+        let progress = delete_in_batches(&state.repository_factory, &filter, BATCH_SIZE, |p| {
+            debug!(
+                batches = p.batches,
+                deleted = p.deleted,
+                "cleaning up stale passkey challenges"
+            );
+        })
+        .await
+        .map_err(JobError::retry)?;
+
+        if progress.deleted > 0 {
+            info!(
+                count = progress.deleted,
+                "cleaned up stale passkey challenges"
+            );
+        }
+
+        // Failed password attempts only count towards a lockout for at most a day
+        let filter = StaleUserPasswordFailures {
+            created_before: clock.now() - Duration::days(1),
+        };
+
+        let progress = delete_in_batches(&state.repository_factory, &filter, BATCH_SIZE, |p| {
+            debug!(
+                batches = p.batches,
+                deleted = p.deleted,
+                "cleaning up stale failed password attempts"
+            );
+        })
+        .await
+        .map_err(JobError::retry)?;
+
+        if progress.deleted > 0 {
+            info!(
+                count = progress.deleted,
+                "cleaned up stale failed password attempts"
+            );
+        }
+
         Ok(())
     }
 }
@@ -63,3 +214,33 @@ impl RunnableJob for PruneStalePolicyDataJob {
         Ok(())
     }
 }
+
+#[async_trait]
+impl RunnableJob for RefreshLoginStatsJob {
+    #[tracing::instrument(name = "job.refresh_login_stats", skip_all)]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+
+        // Logins started on a day can finish on the next one, so we always
+        // aggregate again the day before the last aggregated one. If nothing was
+        // aggregated yet, this aggregates everything.
+        let since = repo
+            .login_stats()
+            .last_day()
+            .await
+            .map_err(JobError::retry)?
+            .map(|day| day - Duration::days(1));
+
+        let count = repo
+            .login_stats()
+            .refresh(since)
+            .await
+            .map_err(JobError::retry)?;
+
+        repo.save().await.map_err(JobError::retry)?;
+
+        info!(count, ?since, "refreshed login statistics");
+
+        Ok(())
+    }
+}
@@ -4,19 +4,72 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE files in the repository root for full details.
 
+use std::collections::HashMap;
+
+use anyhow::Context as _;
 use async_trait::async_trait;
 use chrono::Duration;
+use mas_data_model::{User, UserAction, UserEmailAuthenticationCode, UserMetadata};
 use mas_email::{Address, EmailVerificationContext, Mailbox};
-use mas_storage::queue::{SendEmailAuthenticationCodeJob, VerifyEmailJob};
-use mas_templates::TemplateContext as _;
-use rand::{Rng, distributions::Uniform};
-use tracing::info;
+use mas_i18n::DataLocale;
+use mas_iana::jose::JsonWebSignatureAlg;
+use mas_jose::{
+    claims::{self, Claim},
+    jwt::{JsonWebSignatureHeader, Jwt},
+};
+use mas_storage::queue::{
+    SendEmailAuthenticationCodeJob, SendSignInNotificationJob, SendUserClaimLinkEmailJob,
+    VerifyEmailJob,
+};
+use mas_templates::{EmailClaimContext, EmailSignInContext, TemplateContext as _};
+use rand::{
+    Rng,
+    distributions::{Alphanumeric, DistString, Uniform},
+};
+use tracing::{error, info, warn};
 
 use crate::{
     State,
     new_queue::{JobContext, JobError, RunnableJob},
 };
 
+/// The code embedded in login link tokens
+const LOGIN_LINK_CODE: Claim<String> = Claim::new("code");
+
+/// Build and sign the token of the link sent alongside a login code, which
+/// logs the user in like entering the code would.
+///
+/// The token is checked by the `login_email` views of the handlers, and is
+/// single-use as the email authentication can only be completed once.
+fn sign_login_link_token(
+    state: &State,
+    user: &User,
+    code: &UserEmailAuthenticationCode,
+) -> Result<String, anyhow::Error> {
+    let mut rng = state.rng();
+
+    let mut claims = HashMap::new();
+    claims::ISS.insert(&mut claims, state.url_builder().oidc_issuer().to_string())?;
+    claims::SUB.insert(&mut claims, user.id.to_string())?;
+    claims::JTI.insert(&mut claims, code.user_email_authentication_id.to_string())?;
+    claims::IAT.insert(&mut claims, code.created_at)?;
+    claims::EXP.insert(&mut claims, code.expires_at)?;
+    LOGIN_LINK_CODE.insert(&mut claims, code.code.clone())?;
+
+    let alg = JsonWebSignatureAlg::Rs256;
+    let key = state
+        .key_store()
+        .signing_key_for_algorithm(&alg)
+        .context("No signing key found for the algorithm")?;
+    let signer = key.params().signing_key_for_alg(&alg)?;
+    let header = JsonWebSignatureHeader::new(alg)
+        .with_kid(key.kid().context("Signing key has no key ID")?)
+        .with_typ("login-link+jwt".to_owned());
+
+    let token = Jwt::sign_with_rng(&mut rng, header, claims, &signer)?;
+    Ok(token.into_string())
+}
+
 #[async_trait]
 impl RunnableJob for VerifyEmailJob {
     #[tracing::instrument(
@@ -42,6 +95,7 @@ impl RunnableJob for SendEmailAuthenticationCodeJob {
     async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
         let clock = state.clock();
         let mailer = state.mailer();
+        let url_builder = state.url_builder();
         let mut rng = state.rng();
         let mut repo = state.repository().await.map_err(JobError::retry)?;
 
@@ -92,6 +146,19 @@ impl RunnableJob for SendEmailAuthenticationCodeJob {
                 None
             };
 
+        // Load the user logging in, if any
+        let login_user = if let Some(user_id) = user_email_authentication.user_id {
+            Some(
+                repo.user()
+                    .lookup(user_id)
+                    .await
+                    .map_err(JobError::retry)?
+                    .ok_or(JobError::fail(anyhow::anyhow!("Failed to load user")))?,
+            )
+        } else {
+            None
+        };
+
         // Generate a new 6-digit authentication code
         let range = Uniform::<u32>::from(0..1_000_000);
         let code = rng.sample(range);
@@ -114,15 +181,72 @@ impl RunnableJob for SendEmailAuthenticationCodeJob {
             .map_err(JobError::fail)?;
         let username_from_session = browser_session.as_ref().map(|s| s.user.username.clone());
         let username_from_registration = registration.as_ref().map(|r| r.username.clone());
-        let username = username_from_registration.or(username_from_session);
+        let username_from_login = login_user.as_ref().map(|u| u.username.clone());
+        let username = username_from_registration
+            .or(username_from_session)
+            .or(username_from_login);
         let mailbox = Mailbox::new(username, address);
 
         info!("Sending email verification code to {}", mailbox);
 
         let language = self.language().parse().map_err(JobError::fail)?;
 
-        let context = EmailVerificationContext::new(code, browser_session, registration)
-            .with_language(language);
+        // If the verification was requested from a browser session, give the
+        // recipient a way to end that session in case it wasn't them
+        let end_session_link = if let Some(browser_session) = &browser_session {
+            let token = repo
+                .user_action_token()
+                .add(
+                    &mut rng,
+                    &clock,
+                    &browser_session.user,
+                    UserAction::EndBrowserSession {
+                        browser_session_id: browser_session.id,
+                    },
+                    Alphanumeric.sample_string(&mut rng, 32),
+                    Duration::days(1),
+                )
+                .await
+                .map_err(JobError::retry)?;
+
+            Some(url_builder.user_action_link(token.token))
+        } else {
+            None
+        };
+
+        // Logins by email code also get a link, which works like entering the
+        // code. It is left out when those logins are disabled, as the code is
+        // then only used to confirm a suspicious password login. The code still
+        // works without it, so the email is sent even if signing fails
+        let login_user_for_link = login_user
+            .as_ref()
+            .filter(|_| state.site_config().email_code_login_enabled);
+        let login_link = if let Some(login_user) = login_user_for_link {
+            match sign_login_link_token(state, login_user, &code) {
+                Ok(token) => Some(url_builder.login_email_link(token)),
+                Err(e) => {
+                    warn!(
+                        error = &*e as &dyn std::error::Error,
+                        "Failed to sign the login link"
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut context = EmailVerificationContext::new(code, browser_session, registration);
+        if let Some(end_session_link) = end_session_link {
+            context = context.with_end_session_link(end_session_link);
+        }
+        if let Some(login_user) = login_user {
+            context = context.for_login(login_user);
+        }
+        if let Some(login_link) = login_link {
+            context = context.with_login_link(login_link);
+        }
+        let context = context.with_language(language);
         mailer
             .send_verification_email(mailbox, &context)
             .await
@@ -133,3 +257,165 @@ impl RunnableJob for SendEmailAuthenticationCodeJob {
         Ok(())
     }
 }
+
+#[async_trait]
+impl RunnableJob for SendUserClaimLinkEmailJob {
+    #[tracing::instrument(
+        name = "job.send_user_claim_link_email",
+        fields(user_claim_link.id = %self.user_claim_link_id()),
+        skip_all,
+    )]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let clock = state.clock();
+        let mailer = state.mailer();
+        let url_builder = state.url_builder();
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+
+        let link = repo
+            .user_claim_link()
+            .lookup(self.user_claim_link_id())
+            .await
+            .map_err(JobError::retry)?
+            .ok_or(JobError::fail(anyhow::anyhow!("User claim link not found")))?;
+
+        // The link may have been revoked before we got a chance to send it
+        if !link.is_valid(clock.now()) {
+            info!("User claim link is no longer valid, not sending email");
+            return Ok(());
+        }
+
+        let email = link.email.as_deref().ok_or(JobError::fail(anyhow::anyhow!(
+            "User claim link has no email address"
+        )))?;
+
+        let user = repo
+            .user()
+            .lookup(link.user_id)
+            .await
+            .map_err(JobError::retry)?
+            .ok_or(JobError::fail(anyhow::anyhow!("User not found")))?;
+
+        let address: Address = email.parse().map_err(JobError::fail)?;
+        let mailbox = Mailbox::new(Some(user.username.clone()), address);
+
+        info!("Sending account claim link to {}", mailbox);
+
+        let language = self.language().parse().map_err(JobError::fail)?;
+
+        let url = url_builder.account_claim_link(link.token.clone());
+        let context = EmailClaimContext::new(user, url, link.expires_at).with_language(language);
+        mailer
+            .send_claim_email(mailbox, &context)
+            .await
+            .map_err(JobError::fail)?;
+
+        repo.save().await.map_err(JobError::fail)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RunnableJob for SendSignInNotificationJob {
+    #[tracing::instrument(
+        name = "job.send_sign_in_notification",
+        fields(user_session.id = %self.user_session_id()),
+        skip_all,
+    )]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let mailer = state.mailer();
+        let url_builder = state.url_builder();
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+
+        if !state.site_config().sign_in_notifications_enabled {
+            info!("Sign-in notifications are disabled, not sending email");
+            return Ok(());
+        }
+
+        let session = repo
+            .browser_session()
+            .lookup(self.user_session_id())
+            .await
+            .map_err(JobError::retry)?
+            .ok_or(JobError::fail(anyhow::anyhow!("User session not found")))?;
+
+        // The user may have logged out already, in which case there is nothing to
+        // review anymore
+        if !session.active() {
+            info!("User session is no longer active, not sending email");
+            return Ok(());
+        }
+
+        let setting = repo
+            .user_metadata()
+            .get(
+                &session.user,
+                UserMetadata::SETTINGS_NAMESPACE,
+                UserMetadata::SIGN_IN_NOTIFICATIONS_SETTING,
+            )
+            .await
+            .map_err(JobError::retry)?;
+        if setting.is_some_and(|entry| entry.value == serde_json::Value::Bool(false)) {
+            info!("User opted out of sign-in notifications, not sending email");
+            return Ok(());
+        }
+
+        // The IP address is recorded by the activity tracker, which may not have
+        // seen the session yet
+        let Some(ip) = session.last_active_ip else {
+            info!("User session has no known IP address, not sending email");
+            return Ok(());
+        };
+
+        if repo
+            .browser_session()
+            .other_session_exists(&session, ip)
+            .await
+            .map_err(JobError::retry)?
+        {
+            info!("User already logged in from this IP address and device, not sending email");
+            return Ok(());
+        }
+
+        let language: DataLocale = self.language().parse().map_err(JobError::fail)?;
+        let sessions_link = url_builder.absolute_url_for(&mas_router::AccountBrowserSessions);
+        let settings_link = url_builder.absolute_url_for(&mas_router::Notifications);
+
+        // Notifications only go to the primary email address of the user
+        let Some(email) = repo
+            .user_email()
+            .primary(&session.user)
+            .await
+            .map_err(JobError::retry)?
+        else {
+            info!("User has no email address, not sending sign-in notification");
+            return Ok(());
+        };
+
+        let address: Address = email.email.parse().map_err(JobError::fail)?;
+        let mailbox = Mailbox::new(Some(session.user.username.clone()), address);
+
+        info!("Sending sign-in notification to {}", mailbox);
+        let context = EmailSignInContext::new(
+            session.user.clone(),
+            session.clone(),
+            ip,
+            session.last_active_location.clone(),
+            sessions_link,
+            settings_link,
+        )
+        .with_language(language);
+
+        // We only log if the email fails to send, as there is no point in retrying
+        if let Err(e) = mailer.send_sign_in_email(mailbox, &context).await {
+            error!(
+                error = &e as &dyn std::error::Error,
+                "Failed to send sign-in notification"
+            );
+        }
+
+        repo.save().await.map_err(JobError::fail)?;
+
+        Ok(())
+    }
+}
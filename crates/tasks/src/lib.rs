@@ -8,8 +8,10 @@ use std::sync::{Arc, LazyLock};
 
 use mas_data_model::SiteConfig;
 use mas_email::Mailer;
+use mas_keystore::Keystore;
 use mas_matrix::HomeserverConnection;
 use mas_router::UrlBuilder;
+use mas_sms::SmsSender;
 use mas_storage::{BoxClock, BoxRepository, RepositoryError, RepositoryFactory, SystemClock};
 use mas_storage_pg::PgRepositoryFactory;
 use new_queue::QueueRunnerError;
@@ -18,12 +20,17 @@ use rand::SeedableRng;
 use sqlx::{Pool, Postgres};
 use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
+mod backchannel;
+mod backchannel_logout;
 mod database;
 mod email;
 mod matrix;
 mod new_queue;
 mod recovery;
+mod scim;
 mod sessions;
+mod sms;
+mod upstream_oauth2;
 mod user;
 
 static METER: LazyLock<Meter> = LazyLock::new(|| {
@@ -39,10 +46,13 @@ static METER: LazyLock<Meter> = LazyLock::new(|| {
 struct State {
     repository_factory: PgRepositoryFactory,
     mailer: Mailer,
+    sms_sender: SmsSender,
     clock: SystemClock,
     homeserver: Arc<dyn HomeserverConnection>,
     url_builder: UrlBuilder,
     site_config: SiteConfig,
+    key_store: Keystore,
+    http_client: reqwest::Client,
 }
 
 impl State {
@@ -50,17 +60,23 @@ impl State {
         repository_factory: PgRepositoryFactory,
         clock: SystemClock,
         mailer: Mailer,
+        sms_sender: SmsSender,
         homeserver: impl HomeserverConnection + 'static,
         url_builder: UrlBuilder,
         site_config: SiteConfig,
+        key_store: Keystore,
+        http_client: reqwest::Client,
     ) -> Self {
         Self {
             repository_factory,
             mailer,
+            sms_sender,
             clock,
             homeserver: Arc::new(homeserver),
             url_builder,
             site_config,
+            key_store,
+            http_client,
         }
     }
 
@@ -76,6 +92,10 @@ impl State {
         &self.mailer
     }
 
+    pub fn sms_sender(&self) -> &SmsSender {
+        &self.sms_sender
+    }
+
     // This is fine for now, we may move that to a trait at some point.
     #[allow(clippy::unused_self, clippy::disallowed_methods)]
     pub fn rng(&self) -> rand_chacha::ChaChaRng {
@@ -97,6 +117,14 @@ impl State {
     pub fn site_config(&self) -> &SiteConfig {
         &self.site_config
     }
+
+    pub fn key_store(&self) -> &Keystore {
+        &self.key_store
+    }
+
+    pub fn http_client(&self) -> &reqwest::Client {
+        &self.http_client
+    }
 }
 
 /// Initialise the workers.
@@ -107,9 +135,12 @@ impl State {
 pub async fn init(
     repository_factory: PgRepositoryFactory,
     mailer: &Mailer,
+    sms_sender: &SmsSender,
     homeserver: impl HomeserverConnection + 'static,
     url_builder: UrlBuilder,
     site_config: &SiteConfig,
+    key_store: Keystore,
+    http_client: reqwest::Client,
     cancellation_token: CancellationToken,
     task_tracker: &TaskTracker,
 ) -> Result<(), QueueRunnerError> {
@@ -117,28 +148,45 @@ pub async fn init(
         repository_factory,
         SystemClock::default(),
         mailer.clone(),
+        sms_sender.clone(),
         homeserver,
         url_builder,
         site_config.clone(),
+        key_store,
+        http_client,
     );
     let mut worker = self::new_queue::QueueWorker::new(state, cancellation_token).await?;
 
     worker
         .register_handler::<mas_storage::queue::CleanupExpiredTokensJob>()
         .register_handler::<mas_storage::queue::DeactivateUserJob>()
+        .register_handler::<mas_storage::queue::DeleteUserJob>()
         .register_handler::<mas_storage::queue::DeleteDeviceJob>()
         .register_handler::<mas_storage::queue::ProvisionDeviceJob>()
         .register_handler::<mas_storage::queue::ProvisionUserJob>()
         .register_handler::<mas_storage::queue::ReactivateUserJob>()
         .register_handler::<mas_storage::queue::SendAccountRecoveryEmailsJob>()
         .register_handler::<mas_storage::queue::SendEmailAuthenticationCodeJob>()
+        .register_handler::<mas_storage::queue::SendPhoneCodeJob>()
+        .register_handler::<mas_storage::queue::SendUserClaimLinkEmailJob>()
+        .register_handler::<mas_storage::queue::SendSignInNotificationJob>()
+        .register_handler::<mas_storage::queue::SendBackchannelAuthenticationEmailJob>()
+        .register_handler::<mas_storage::queue::NotifyBackchannelClientJob>()
+        .register_handler::<mas_storage::queue::SendBackchannelLogoutJob>()
+        .register_handler::<mas_storage::queue::SendBrowserSessionBackchannelLogoutsJob>()
         .register_handler::<mas_storage::queue::SyncDevicesJob>()
+        .register_handler::<mas_storage::queue::SyncUpstreamAttributesJob>()
         .register_handler::<mas_storage::queue::VerifyEmailJob>()
         .register_handler::<mas_storage::queue::ExpireInactiveSessionsJob>()
         .register_handler::<mas_storage::queue::ExpireInactiveCompatSessionsJob>()
+        .register_handler::<mas_storage::queue::FinishCompatSessionJob>()
         .register_handler::<mas_storage::queue::ExpireInactiveOAuthSessionsJob>()
         .register_handler::<mas_storage::queue::ExpireInactiveUserSessionsJob>()
+        .register_handler::<mas_storage::queue::ExpireOldUserSessionsJob>()
         .register_handler::<mas_storage::queue::PruneStalePolicyDataJob>()
+        .register_handler::<mas_storage::queue::RefreshLoginStatsJob>()
+        .register_handler::<mas_storage::queue::SyncScimDirectoryJob>()
+        .register_handler::<mas_storage::queue::DeprovisionUpstreamUsersJob>()
         .add_schedule(
             "cleanup-expired-tokens",
             "0 0 * * * *".parse()?,
@@ -155,6 +203,24 @@ pub async fn init(
             // Run once a day
             "0 0 2 * * *".parse()?,
             mas_storage::queue::PruneStalePolicyDataJob,
+        )
+        .add_schedule(
+            "refresh-login-stats",
+            // Run this job every hour
+            "0 20 * * * *".parse()?,
+            mas_storage::queue::RefreshLoginStatsJob,
+        )
+        .add_schedule(
+            "sync-scim-directory",
+            // Run this job every 15 minutes
+            "0 */15 * * * *".parse()?,
+            mas_storage::queue::SyncScimDirectoryJob,
+        )
+        .add_schedule(
+            "deprovision-upstream-users",
+            // Run this job every hour
+            "0 40 * * * *".parse()?,
+            mas_storage::queue::DeprovisionUpstreamUsersJob,
         );
 
     task_tracker.spawn(worker.run());
@@ -18,7 +18,7 @@ use mas_storage::{
         DeleteDeviceJob, ProvisionDeviceJob, ProvisionUserJob, QueueJobRepositoryExt as _,
         SyncDevicesJob,
     },
-    user::{UserEmailRepository, UserRepository},
+    user::{UserEmailRepository, UserPhoneRepository, UserRepository},
 };
 use tracing::info;
 
@@ -60,7 +60,19 @@ impl RunnableJob for ProvisionUserJob {
             .into_iter()
             .map(|email| email.email)
             .collect();
-        let mut request = ProvisionRequest::new(mxid.clone(), user.sub.clone()).set_emails(emails);
+        // Only confirmed phone numbers are shared with the homeserver
+        let phone_numbers = repo
+            .user_phone()
+            .find_for_user(&user)
+            .await
+            .map_err(JobError::retry)?
+            .filter(|phone| phone.is_confirmed())
+            .map(|phone| phone.phone_number)
+            .into_iter()
+            .collect();
+        let mut request = ProvisionRequest::new(mxid.clone(), user.sub.clone())
+            .set_emails(emails)
+            .set_phone_numbers(phone_numbers);
 
         if let Some(display_name) = self.display_name_to_set() {
             request = request.set_displayname(display_name.to_owned());
@@ -13,7 +13,7 @@ use mas_storage::{
     queue::SendAccountRecoveryEmailsJob,
     user::{UserEmailFilter, UserRecoveryRepository},
 };
-use mas_templates::{EmailRecoveryContext, TemplateContext};
+use mas_templates::{EmailRecoveryContext, EmailRecoveryReviewContext, TemplateContext};
 use rand::distributions::{Alphanumeric, DistString};
 use tracing::{error, info};
 
@@ -71,14 +71,6 @@ impl RunnableJob for SendAccountRecoveryEmailsJob {
                 .map_err(JobError::retry)?;
 
             for email in page.edges {
-                let ticket = Alphanumeric.sample_string(&mut rng, 32);
-
-                let ticket = repo
-                    .user_recovery()
-                    .add_ticket(&mut rng, &clock, &session, &email, ticket)
-                    .await
-                    .map_err(JobError::retry)?;
-
                 let user_email = repo
                     .user_email()
                     .lookup(email.id)
@@ -95,11 +87,38 @@ impl RunnableJob for SendAccountRecoveryEmailsJob {
                     .context("User not found")
                     .map_err(JobError::fail)?;
 
-                let url = url_builder.account_recovery_link(ticket.ticket);
-
                 let address: Address = user_email.email.parse().map_err(JobError::fail)?;
                 let mailbox = Mailbox::new(Some(user.username.clone()), address);
 
+                // Sessions waiting for an administrator, or which were
+                // denied, don't get a recovery link: we only tell the user
+                // where their request stands
+                if !session.is_approved() {
+                    info!("Sending recovery review email to {}", mailbox);
+                    let context = EmailRecoveryReviewContext::new(user, session.clone())
+                        .with_language(lang.clone());
+
+                    if let Err(e) = mailer.send_recovery_review_email(mailbox, &context).await {
+                        error!(
+                            error = &e as &dyn std::error::Error,
+                            "Failed to send recovery review email"
+                        );
+                    }
+
+                    cursor = cursor.after(email.id);
+                    continue;
+                }
+
+                let ticket = Alphanumeric.sample_string(&mut rng, 32);
+
+                let ticket = repo
+                    .user_recovery()
+                    .add_ticket(&mut rng, &clock, &session, &email, ticket)
+                    .await
+                    .map_err(JobError::retry)?;
+
+                let url = url_builder.account_recovery_link(ticket.ticket);
+
                 info!("Sending recovery email to {}", mailbox);
                 let context = EmailRecoveryContext::new(user, session.clone(), url)
                     .with_language(lang.clone());
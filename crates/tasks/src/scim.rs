@@ -0,0 +1,401 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::HashSet;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use mas_data_model::{ScimClientConfig, ScimSyncAction, ScimSyncChange, User};
+use mas_http::RequestBuilderExt as _;
+use mas_storage::{
+    BoxClock, BoxRepository, Pagination, RepositoryAccess,
+    queue::{ProvisionUserJob, QueueJobRepositoryExt, SyncScimDirectoryJob},
+    scim::{ScimSyncRunRepository, ScimUserLinkRepository},
+    user::{UserEmailRepository, UserRepository},
+};
+use rand_chacha::ChaChaRng;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    State,
+    new_queue::{JobContext, JobError, RunnableJob},
+};
+
+/// How many users to fetch per request to the directory
+const PAGE_SIZE: usize = 100;
+
+/// A `ListResponse` message, as defined in RFC 7644 section 3.4.2
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListResponse {
+    total_results: usize,
+    #[serde(rename = "Resources", default)]
+    resources: Vec<ScimUser>,
+}
+
+/// The subset of the SCIM `User` resource we care about, as defined in RFC
+/// 7643 section 4.1
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScimUser {
+    id: String,
+    external_id: Option<String>,
+    user_name: String,
+    #[serde(default = "default_active")]
+    active: bool,
+    display_name: Option<String>,
+    #[serde(default)]
+    emails: Vec<ScimEmail>,
+    #[serde(default)]
+    groups: Vec<ScimGroup>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct ScimEmail {
+    value: String,
+    #[serde(default)]
+    primary: bool,
+}
+
+#[derive(Deserialize)]
+struct ScimGroup {
+    value: String,
+    display: Option<String>,
+}
+
+impl ScimUser {
+    fn is_member_of(&self, group: &str) -> bool {
+        self.groups
+            .iter()
+            .any(|g| g.value == group || g.display.as_deref() == Some(group))
+    }
+
+    /// The primary email address of the user, or the first one if none is
+    /// marked as primary
+    fn primary_email(&self) -> Option<&str> {
+        self.emails
+            .iter()
+            .find(|e| e.primary)
+            .or_else(|| self.emails.first())
+            .map(|e| e.value.as_str())
+    }
+
+    /// Derive a localpart from the `userName`, stripping any domain part
+    fn localpart(&self) -> Option<String> {
+        let localpart = self
+            .user_name
+            .split_once('@')
+            .map_or(self.user_name.as_str(), |(localpart, _)| localpart)
+            .to_lowercase();
+
+        let valid = !localpart.is_empty()
+            && localpart.len() <= 255
+            && !localpart.starts_with('_')
+            && localpart
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "=_-./+".contains(c));
+
+        valid.then_some(localpart)
+    }
+}
+
+/// Fetch all the users from the directory, following pagination
+async fn fetch_users(
+    http_client: &reqwest::Client,
+    config: &ScimClientConfig,
+) -> Result<Vec<ScimUser>, anyhow::Error> {
+    let mut url = config.endpoint.clone();
+    url.path_segments_mut()
+        .map_err(|()| anyhow::anyhow!("The SCIM endpoint is not a base URL"))?
+        .pop_if_empty()
+        .push("Users");
+
+    let mut users = Vec::new();
+    loop {
+        // SCIM indexes are 1-based
+        let start_index = users.len() + 1;
+        let response: ListResponse = http_client
+            .get(url.clone())
+            .query(&[("startIndex", start_index), ("count", PAGE_SIZE)])
+            .bearer_auth(&config.token)
+            .header(reqwest::header::ACCEPT, "application/scim+json")
+            .send_traced()
+            .await
+            .context("Failed to query the SCIM directory")?
+            .error_for_status()
+            .context("Unexpected HTTP response from the SCIM directory")?
+            .json()
+            .await
+            .context("Failed to deserialize the response from the SCIM directory")?;
+
+        let fetched = response.resources.len();
+        users.extend(response.resources);
+
+        if fetched == 0 || users.len() >= response.total_results {
+            break;
+        }
+    }
+
+    Ok(users)
+}
+
+/// Fetch the identifiers of the active users of the directory, both the `id`
+/// the directory gave them and their `externalId`
+pub(crate) async fn active_user_ids(
+    http_client: &reqwest::Client,
+    config: &ScimClientConfig,
+) -> Result<HashSet<String>, anyhow::Error> {
+    let users = fetch_users(http_client, config).await?;
+
+    Ok(users
+        .into_iter()
+        .filter(|user| user.active)
+        .flat_map(|user| std::iter::once(user.id).chain(user.external_id))
+        .collect())
+}
+
+fn change(action: ScimSyncAction, user: &User, external_id: &str) -> ScimSyncChange {
+    ScimSyncChange {
+        action,
+        user_id: user.id,
+        username: user.username.clone(),
+        external_id: external_id.to_owned(),
+    }
+}
+
+/// Apply the state of the directory to the local users, returning the list of
+/// changes which were made
+async fn apply(
+    state: &State,
+    config: &ScimClientConfig,
+    repo: &mut BoxRepository,
+    rng: &mut ChaChaRng,
+    clock: &BoxClock,
+    scim_users: Vec<ScimUser>,
+) -> Result<Vec<ScimSyncChange>, anyhow::Error> {
+    let matrix = state.matrix_connection();
+    let mut changes = Vec::new();
+    let mut seen = HashSet::new();
+
+    for scim_user in scim_users {
+        seen.insert(scim_user.id.clone());
+
+        let linked_user = match repo
+            .scim_user_link()
+            .find_by_external_id(&scim_user.id)
+            .await?
+        {
+            Some(link) => Some(
+                repo.user()
+                    .lookup(link.user_id)
+                    .await?
+                    .context("Linked user not found")?,
+            ),
+            None => None,
+        };
+
+        let allowed = scim_user.active
+            && config
+                .group
+                .as_deref()
+                .is_none_or(|group| scim_user.is_member_of(group));
+
+        if !allowed {
+            // Lock users which are not supposed to have access anymore
+            if let Some(user) = linked_user.filter(|user| user.locked_at.is_none()) {
+                let user = repo.user().lock(clock, user).await?;
+                changes.push(change(ScimSyncAction::Locked, &user, &scim_user.id));
+            }
+            continue;
+        }
+
+        let user = if let Some(user) = linked_user {
+            user
+        } else {
+            let Some(username) = scim_user.localpart() else {
+                warn!(
+                    scim_user.id = scim_user.id,
+                    scim_user.user_name = scim_user.user_name,
+                    "Skipping SCIM user with an invalid username"
+                );
+                continue;
+            };
+
+            let (user, action) = if let Some(user) = repo.user().find_by_username(&username).await?
+            {
+                if repo.scim_user_link().find_by_user(&user).await?.is_some() {
+                    warn!(
+                        scim_user.id = scim_user.id,
+                        user.username = user.username,
+                        "User is already linked to another SCIM user, skipping"
+                    );
+                    continue;
+                }
+
+                (user, ScimSyncAction::Linked)
+            } else {
+                if !matrix.is_localpart_available(&username).await? {
+                    warn!(
+                        scim_user.id = scim_user.id,
+                        user.username = username,
+                        "Username is not available on the homeserver, skipping"
+                    );
+                    continue;
+                }
+
+                let user = repo.user().add(rng, clock, username).await?;
+
+                if let Some(email) = scim_user.primary_email() {
+                    repo.user_email()
+                        .add(rng, clock, &user, email.to_owned())
+                        .await?;
+                }
+
+                let mut job = ProvisionUserJob::new(&user);
+                if let Some(display_name) = scim_user.display_name.clone() {
+                    job = job.set_display_name(display_name);
+                }
+                repo.queue_job().schedule_job(rng, clock, job).await?;
+
+                (user, ScimSyncAction::Created)
+            };
+
+            repo.scim_user_link()
+                .add(rng, clock, &user, scim_user.id.clone())
+                .await?;
+            changes.push(change(action, &user, &scim_user.id));
+            user
+        };
+
+        // Deactivated users stay deactivated, regardless of the directory
+        if user.deactivated_at.is_some() {
+            continue;
+        }
+
+        let user = if user.locked_at.is_some() {
+            let user = repo.user().unlock(user).await?;
+            changes.push(change(ScimSyncAction::Unlocked, &user, &scim_user.id));
+            user
+        } else {
+            user
+        };
+
+        if let Some(admin_group) = config.admin_group.as_deref() {
+            let is_admin = scim_user.is_member_of(admin_group);
+            if user.can_request_admin != is_admin {
+                let user = repo.user().set_can_request_admin(user, is_admin).await?;
+                let action = if is_admin {
+                    ScimSyncAction::AdminGranted
+                } else {
+                    ScimSyncAction::AdminRevoked
+                };
+                changes.push(change(action, &user, &scim_user.id));
+            }
+        }
+    }
+
+    if config.lock_missing_users {
+        // Lock the linked users which disappeared from the directory
+        let mut cursor = Pagination::first(100);
+        loop {
+            let page = repo.scim_user_link().list(cursor).await?;
+
+            for link in &page.edges {
+                if seen.contains(&link.external_id) {
+                    continue;
+                }
+
+                let user = repo
+                    .user()
+                    .lookup(link.user_id)
+                    .await?
+                    .context("Linked user not found")?;
+
+                if user.locked_at.is_none() {
+                    let user = repo.user().lock(clock, user).await?;
+                    changes.push(change(ScimSyncAction::Locked, &user, &link.external_id));
+                }
+            }
+
+            let Some(last) = page.edges.last() else {
+                break;
+            };
+
+            if !page.has_next_page {
+                break;
+            }
+
+            cursor = cursor.after(last.id);
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Job to synchronise the users from an upstream SCIM directory
+#[async_trait]
+impl RunnableJob for SyncScimDirectoryJob {
+    #[tracing::instrument(name = "job.sync_scim_directory", skip_all)]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let Some(config) = state.site_config().scim_client.as_ref() else {
+            // The SCIM client is disabled
+            return Ok(());
+        };
+
+        let clock = state.clock();
+        let mut rng = state.rng();
+
+        // Record the start of the run in a separate transaction, so that it is
+        // visible while the sync is running
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+        let run = repo
+            .scim_sync_run()
+            .start(&mut rng, &clock)
+            .await
+            .map_err(JobError::retry)?;
+        repo.save().await.map_err(JobError::retry)?;
+
+        let result = async {
+            let scim_users = fetch_users(state.http_client(), config).await?;
+
+            // All the changes are applied in a single transaction, which gets
+            // rolled back if anything goes wrong
+            let mut repo = state.repository().await?;
+            let changes = apply(state, config, &mut repo, &mut rng, &clock, scim_users).await?;
+            repo.scim_sync_run()
+                .complete(&clock, run.clone(), changes)
+                .await?;
+            repo.save().await?;
+
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => {
+                info!(scim_sync_run.id = %run.id, "SCIM directory synchronised");
+                Ok(())
+            }
+
+            Err(e) => {
+                let mut repo = state.repository().await.map_err(JobError::retry)?;
+                repo.scim_sync_run()
+                    .fail(&clock, run, format!("{e:#}"))
+                    .await
+                    .map_err(JobError::retry)?;
+                repo.save().await.map_err(JobError::retry)?;
+
+                // The failure is recorded on the run, and the job will be
+                // scheduled again later on
+                Err(JobError::fail(e))
+            }
+        }
+    }
+}
@@ -12,7 +12,9 @@ use mas_storage::{
     oauth2::OAuth2SessionFilter,
     queue::{
         ExpireInactiveCompatSessionsJob, ExpireInactiveOAuthSessionsJob, ExpireInactiveSessionsJob,
-        ExpireInactiveUserSessionsJob, QueueJobRepositoryExt, SyncDevicesJob,
+        ExpireInactiveUserSessionsJob, ExpireOldUserSessionsJob, FinishCompatSessionJob,
+        QueueJobRepositoryExt, SendBackchannelLogoutJob, SendBrowserSessionBackchannelLogoutsJob,
+        SyncDevicesJob,
     },
     user::BrowserSessionFilter,
 };
@@ -68,6 +70,17 @@ impl RunnableJob for ExpireInactiveSessionsJob {
                 .map_err(JobError::retry)?;
         }
 
+        if let Some(max_lifetime) = config.user_session_max_lifetime {
+            repo.queue_job()
+                .schedule_job(
+                    &mut rng,
+                    &clock,
+                    ExpireOldUserSessionsJob::new(now - max_lifetime),
+                )
+                .await
+                .map_err(JobError::retry)?;
+        }
+
         repo.save().await.map_err(JobError::retry)?;
 
         Ok(())
@@ -127,6 +140,11 @@ impl RunnableJob for ExpireInactiveOAuthSessionsJob {
                 }
             }
 
+            repo.queue_job()
+                .schedule_job(&mut rng, &clock, SendBackchannelLogoutJob::new(&edge))
+                .await
+                .map_err(JobError::retry)?;
+
             repo.oauth2_session()
                 .finish(&clock, edge)
                 .await
@@ -201,6 +219,51 @@ impl RunnableJob for ExpireInactiveCompatSessionsJob {
     }
 }
 
+#[async_trait]
+impl RunnableJob for FinishCompatSessionJob {
+    #[tracing::instrument(
+        name = "job.finish_compat_session",
+        fields(compat_session.id = %self.compat_session_id()),
+        skip_all,
+    )]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+        let clock = state.clock();
+        let mut rng = state.rng();
+
+        let Some(session) = repo
+            .compat_session()
+            .lookup(self.compat_session_id())
+            .await
+            .map_err(JobError::retry)?
+        else {
+            tracing::warn!("Compatibility session not found, skipping");
+            return Ok(());
+        };
+
+        if session.is_finished() {
+            tracing::info!("Compatibility session already finished");
+            return Ok(());
+        }
+
+        let user_id = session.user_id;
+        repo.compat_session()
+            .finish(&clock, session)
+            .await
+            .map_err(JobError::retry)?;
+
+        tracing::info!(user.id = %user_id, "Scheduling devices sync for user");
+        repo.queue_job()
+            .schedule_job(&mut rng, &clock, SyncDevicesJob::new_for_id(user_id))
+            .await
+            .map_err(JobError::retry)?;
+
+        repo.save().await.map_err(JobError::retry)?;
+
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl RunnableJob for ExpireInactiveUserSessionsJob {
     async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
@@ -229,6 +292,64 @@ impl RunnableJob for ExpireInactiveUserSessionsJob {
         }
 
         for edge in page.edges {
+            repo.queue_job()
+                .schedule_job(
+                    &mut rng,
+                    &clock,
+                    SendBrowserSessionBackchannelLogoutsJob::new(&edge),
+                )
+                .await
+                .map_err(JobError::retry)?;
+
+            repo.browser_session()
+                .finish(&clock, edge)
+                .await
+                .map_err(JobError::retry)?;
+        }
+
+        repo.save().await.map_err(JobError::retry)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RunnableJob for ExpireOldUserSessionsJob {
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+        let clock = state.clock();
+        let mut rng = state.rng();
+
+        let filter = BrowserSessionFilter::new()
+            .with_created_before(self.threshold())
+            .active_only();
+
+        let pagination = self.pagination(100);
+
+        let page = repo
+            .browser_session()
+            .list(filter, pagination)
+            .await
+            .map_err(JobError::retry)?;
+
+        if let Some(job) = self.next(&page) {
+            tracing::info!("Scheduling job to expire the next batch of old sessions");
+            repo.queue_job()
+                .schedule_job(&mut rng, &clock, job)
+                .await
+                .map_err(JobError::retry)?;
+        }
+
+        for edge in page.edges {
+            repo.queue_job()
+                .schedule_job(
+                    &mut rng,
+                    &clock,
+                    SendBrowserSessionBackchannelLogoutsJob::new(&edge),
+                )
+                .await
+                .map_err(JobError::retry)?;
+
             repo.browser_session()
                 .finish(&clock, edge)
                 .await
@@ -0,0 +1,84 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use async_trait::async_trait;
+use chrono::Duration;
+use mas_sms::SmsVerificationContext;
+use mas_storage::queue::SendPhoneCodeJob;
+use mas_templates::TemplateContext as _;
+use rand::{Rng, distributions::Uniform};
+use tracing::info;
+
+use crate::{
+    State,
+    new_queue::{JobContext, JobError, RunnableJob},
+};
+
+#[async_trait]
+impl RunnableJob for SendPhoneCodeJob {
+    #[tracing::instrument(
+        name = "job.send_phone_code",
+        fields(user_phone.id = %self.user_phone_id()),
+        skip_all,
+    )]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let clock = state.clock();
+        let sms_sender = state.sms_sender();
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+
+        // The phone number may have been removed in the meantime
+        let Some(user_phone) = repo
+            .user_phone()
+            .lookup(self.user_phone_id())
+            .await
+            .map_err(JobError::retry)?
+        else {
+            info!("Phone number not found, not sending a code");
+            return Ok(());
+        };
+
+        let user = repo
+            .user()
+            .lookup(user_phone.user_id)
+            .await
+            .map_err(JobError::retry)?
+            .ok_or(JobError::fail(anyhow::anyhow!("Failed to load user")))?;
+
+        // Generate a new 6-digit code
+        let range = Uniform::<u32>::from(0..1_000_000);
+        let code = rng.sample(range);
+        let code = format!("{code:06}");
+        let code = repo
+            .user_phone()
+            .add_code(
+                &mut rng,
+                &clock,
+                &user_phone,
+                Duration::minutes(10), // TODO: make this configurable
+                code,
+            )
+            .await
+            .map_err(JobError::retry)?;
+
+        info!("Sending a verification code by SMS");
+
+        let language = self.language().parse().map_err(JobError::fail)?;
+
+        let mut context = SmsVerificationContext::new(user, code);
+        if self.is_login() {
+            context = context.for_login();
+        }
+        let context = context.with_language(language);
+        sms_sender
+            .send_verification_code(&user_phone.phone_number, &context)
+            .await
+            .map_err(JobError::fail)?;
+
+        repo.save().await.map_err(JobError::fail)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,449 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::collections::HashSet;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use mas_data_model::{
+    UpstreamOAuthLink, UpstreamOAuthProviderDeprovisioningAction,
+    UpstreamOAuthProviderDeprovisioningSource,
+};
+use mas_http::RequestBuilderExt as _;
+use mas_storage::{
+    BoxRepository, Pagination, RepositoryAccess, RepositoryError,
+    queue::{
+        DeactivateUserJob, DeprovisionUpstreamUsersJob, ProvisionUserJob,
+        QueueJobRepositoryExt as _, SyncUpstreamAttributesJob,
+    },
+    upstream_oauth2::{
+        UpstreamOAuthLinkFilter, UpstreamOAuthLinkRepository, UpstreamOAuthProviderRepository,
+    },
+    user::{UserEmailRepository, UserRepository},
+};
+use tracing::{info, warn};
+
+use crate::{
+    State,
+    new_queue::{JobContext, JobError, RunnableJob},
+    scim,
+};
+
+/// The default maximum size of an avatar imported from an upstream provider, in
+/// bytes
+const DEFAULT_MAX_AVATAR_SIZE: u32 = 1024 * 1024;
+
+/// The image formats accepted for avatars
+const AVATAR_CONTENT_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Download an avatar from an upstream provider, checking that it is an image
+/// of a reasonable size
+async fn download_avatar(
+    http_client: &reqwest::Client,
+    url: &str,
+    max_size: u32,
+) -> Result<(String, Vec<u8>), anyhow::Error> {
+    let mut response = http_client
+        .get(url)
+        .send_traced()
+        .await
+        .context("Failed to download the avatar")?
+        .error_for_status()
+        .context("The upstream provider refused to serve the avatar")?;
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .filter(|content_type| AVATAR_CONTENT_TYPES.contains(&content_type.as_str()))
+        .context("The avatar isn't in a supported image format")?;
+
+    if response
+        .content_length()
+        .is_some_and(|length| length > u64::from(max_size))
+    {
+        anyhow::bail!("The avatar is too large");
+    }
+
+    // The length announced by the provider can't be trusted, so the body is
+    // read chunk by chunk, and the download stops as soon as it is too large
+    let mut data = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .context("Failed to download the avatar")?
+    {
+        if data.len() + chunk.len() > max_size as usize {
+            anyhow::bail!("The avatar is too large");
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    Ok((content_type, data))
+}
+
+/// Job to refresh the attributes of a user from the values the upstream
+/// provider gave on login.
+///
+/// Attributes which the user changed locally since they were last imported
+/// are only replaced if the provider is configured to overwrite them.
+#[async_trait]
+impl RunnableJob for SyncUpstreamAttributesJob {
+    #[tracing::instrument(
+        name = "job.sync_upstream_attributes",
+        fields(upstream_oauth_link.id = %self.upstream_oauth_link_id()),
+        skip_all,
+    )]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let matrix = state.matrix_connection();
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+        let mut rng = state.rng();
+        let clock = state.clock();
+
+        let link = repo
+            .upstream_oauth_link()
+            .lookup(self.upstream_oauth_link_id())
+            .await
+            .map_err(JobError::retry)?
+            .context("Upstream OAuth link not found")
+            .map_err(JobError::fail)?;
+
+        let user_id = link
+            .user_id
+            .context("Upstream OAuth link isn't associated with a user")
+            .map_err(JobError::fail)?;
+
+        let user = repo
+            .user()
+            .lookup(user_id)
+            .await
+            .map_err(JobError::retry)?
+            .context("User not found")
+            .map_err(JobError::fail)?;
+
+        let provider = repo
+            .upstream_oauth_provider()
+            .lookup(link.provider_id)
+            .await
+            .map_err(JobError::retry)?
+            .context("Upstream OAuth provider not found")
+            .map_err(JobError::fail)?;
+        let claims_imports = &provider.claims_imports;
+
+        let mxid = matrix.mxid(&user.username);
+        let mut imported = link.imported_attributes.clone();
+
+        // The display name and avatar live on the homeserver, so we need to
+        // look at what they currently are there to tell whether the user
+        // changed them
+        let matrix_user = if self.display_name().is_some() || self.avatar_url().is_some() {
+            Some(matrix.query_user(&mxid).await.map_err(JobError::retry)?)
+        } else {
+            None
+        };
+
+        if let (Some(display_name), Some(matrix_user)) = (self.display_name(), &matrix_user) {
+            if imported.displayname.as_deref() != Some(display_name) {
+                let current = matrix_user.displayname.as_deref();
+                let changed_locally =
+                    current != imported.displayname.as_deref() && current != Some(display_name);
+
+                if changed_locally && !claims_imports.displayname.on_conflict.overwrite() {
+                    info!(%user.id, "Keeping the display name the user set");
+                } else {
+                    if current != Some(display_name) {
+                        matrix
+                            .set_displayname(&mxid, display_name)
+                            .await
+                            .map_err(JobError::retry)?;
+                    }
+                    imported.displayname = Some(display_name.to_owned());
+                }
+            }
+        }
+
+        if let Some(email) = self.email() {
+            if imported.email.as_deref() != Some(email) {
+                let previous = if let Some(previous) = &imported.email {
+                    repo.user_email()
+                        .find(&user, previous)
+                        .await
+                        .map_err(JobError::retry)?
+                } else {
+                    None
+                };
+
+                // The previously imported address was removed by the user
+                let changed_locally = imported.email.is_some() && previous.is_none();
+
+                if changed_locally && !claims_imports.email.on_conflict.overwrite() {
+                    info!(%user.id, "Keeping the email addresses the user set");
+                } else {
+                    let primary = repo
+                        .user_email()
+                        .primary(&user)
+                        .await
+                        .map_err(JobError::retry)?;
+
+                    let user_email = if let Some(user_email) = repo
+                        .user_email()
+                        .find(&user, email)
+                        .await
+                        .map_err(JobError::retry)?
+                    {
+                        user_email
+                    } else {
+                        repo.user_email()
+                            .add(&mut rng, &clock, &user, email.to_owned())
+                            .await
+                            .map_err(JobError::retry)?
+                    };
+
+                    if let Some(previous) = previous {
+                        // Keep the imported address as primary if it was
+                        if primary.is_some_and(|primary| primary.id == previous.id) {
+                            repo.user_email()
+                                .set_as_primary(&user_email)
+                                .await
+                                .map_err(JobError::retry)?;
+                        }
+
+                        repo.user_email()
+                            .remove(previous)
+                            .await
+                            .map_err(JobError::retry)?;
+                    }
+
+                    imported.email = Some(email.to_owned());
+
+                    // Let the homeserver know about the new address
+                    repo.queue_job()
+                        .schedule_job(&mut rng, &clock, ProvisionUserJob::new(&user))
+                        .await
+                        .map_err(JobError::retry)?;
+                }
+            }
+        }
+
+        if let (Some(avatar_source), Some(matrix_user)) = (self.avatar_url(), &matrix_user) {
+            if imported.avatar_source.as_deref() != Some(avatar_source) {
+                let current = matrix_user.avatar_url.as_deref();
+                let changed_locally = current != imported.avatar_url.as_deref();
+
+                if changed_locally && !claims_imports.avatar.on_conflict.overwrite() {
+                    info!(%user.id, "Keeping the avatar the user set");
+                } else {
+                    let max_size = claims_imports
+                        .avatar_max_size
+                        .unwrap_or(DEFAULT_MAX_AVATAR_SIZE);
+                    match download_avatar(state.http_client(), avatar_source, max_size).await {
+                        Ok((content_type, data)) => {
+                            let avatar_url = matrix
+                                .upload_media(&content_type, data)
+                                .await
+                                .map_err(JobError::retry)?;
+                            matrix
+                                .set_avatar_url(&mxid, &avatar_url)
+                                .await
+                                .map_err(JobError::retry)?;
+
+                            imported.avatar_source = Some(avatar_source.to_owned());
+                            imported.avatar_url = Some(avatar_url);
+                        }
+                        Err(e) => {
+                            warn!(
+                                error = &*e as &dyn std::error::Error,
+                                %user.id,
+                                "Failed to import the avatar from the upstream provider"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        if imported != link.imported_attributes {
+            repo.upstream_oauth_link()
+                .set_imported_attributes(link, imported)
+                .await
+                .map_err(JobError::retry)?;
+        }
+
+        repo.save().await.map_err(JobError::retry)?;
+
+        Ok(())
+    }
+}
+
+/// Check whether the upstream identity behind a link was removed, according to
+/// the deprovisioning source of its provider
+async fn upstream_identity_removed(
+    repo: &mut BoxRepository,
+    source: UpstreamOAuthProviderDeprovisioningSource,
+    scim_ids: &HashSet<String>,
+    link: &UpstreamOAuthLink,
+) -> Result<bool, RepositoryError> {
+    match source {
+        UpstreamOAuthProviderDeprovisioningSource::Scim => Ok(!scim_ids.contains(&link.subject)),
+        UpstreamOAuthProviderDeprovisioningSource::RefreshToken => Ok(repo
+            .upstream_oauth_link()
+            .tokens(link)
+            .await?
+            .is_some_and(|tokens| tokens.refresh_failed_at.is_some())),
+    }
+}
+
+/// Job to lock or deactivate the users whose upstream identity was removed,
+/// for the upstream providers with deprovisioning enabled.
+///
+/// In dry-run mode, the users which would be deprovisioned are only reported
+/// in the logs.
+#[async_trait]
+impl RunnableJob for DeprovisionUpstreamUsersJob {
+    #[tracing::instrument(name = "job.deprovision_upstream_users", skip_all)]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let clock = state.clock();
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+
+        let providers = repo
+            .upstream_oauth_provider()
+            .all_enabled()
+            .await
+            .map_err(JobError::retry)?;
+
+        // The SCIM directory is fetched once, and only if a provider needs it
+        let needs_scim = providers.iter().any(|provider| {
+            provider.deprovisioning.is_some_and(|deprovisioning| {
+                deprovisioning.source == UpstreamOAuthProviderDeprovisioningSource::Scim
+            })
+        });
+        let scim_ids = match state.site_config().scim_client.as_ref() {
+            Some(config) if needs_scim => scim::active_user_ids(state.http_client(), config)
+                .await
+                .map_err(JobError::retry)?,
+            _ => HashSet::new(),
+        };
+
+        for provider in providers {
+            let Some(deprovisioning) = provider.deprovisioning else {
+                continue;
+            };
+
+            // Don't lock out everyone if the directory is missing or empty
+            if deprovisioning.source == UpstreamOAuthProviderDeprovisioningSource::Scim
+                && scim_ids.is_empty()
+            {
+                warn!(
+                    upstream_oauth_provider.id = %provider.id,
+                    "The SCIM directory is not configured or has no active users, skipping deprovisioning"
+                );
+                continue;
+            }
+
+            let mut deprovisioned = 0;
+            let filter = UpstreamOAuthLinkFilter::new().for_provider(&provider);
+            let mut cursor = Pagination::first(100);
+            loop {
+                let page = repo
+                    .upstream_oauth_link()
+                    .list(filter, cursor)
+                    .await
+                    .map_err(JobError::retry)?;
+
+                for link in &page.edges {
+                    let Some(user_id) = link.user_id else {
+                        continue;
+                    };
+
+                    if !upstream_identity_removed(&mut repo, deprovisioning.source, &scim_ids, link)
+                        .await
+                        .map_err(JobError::retry)?
+                    {
+                        continue;
+                    }
+
+                    let user = repo
+                        .user()
+                        .lookup(user_id)
+                        .await
+                        .map_err(JobError::retry)?
+                        .context("Linked user not found")
+                        .map_err(JobError::fail)?;
+
+                    let already_done = match deprovisioning.action {
+                        UpstreamOAuthProviderDeprovisioningAction::Lock => user.locked_at.is_some(),
+                        UpstreamOAuthProviderDeprovisioningAction::Deactivate => {
+                            user.deactivated_at.is_some()
+                        }
+                    };
+                    if already_done {
+                        continue;
+                    }
+
+                    deprovisioned += 1;
+                    if deprovisioning.dry_run {
+                        info!(
+                            %user.id,
+                            %user.username,
+                            upstream_oauth_link.id = %link.id,
+                            action = ?deprovisioning.action,
+                            "Upstream identity was removed, the user would be deprovisioned"
+                        );
+                        continue;
+                    }
+
+                    info!(
+                        %user.id,
+                        %user.username,
+                        upstream_oauth_link.id = %link.id,
+                        action = ?deprovisioning.action,
+                        "Upstream identity was removed, deprovisioning the user"
+                    );
+
+                    let user = if user.locked_at.is_none() {
+                        repo.user()
+                            .lock(&clock, user)
+                            .await
+                            .map_err(JobError::retry)?
+                    } else {
+                        user
+                    };
+
+                    if deprovisioning.action
+                        == UpstreamOAuthProviderDeprovisioningAction::Deactivate
+                    {
+                        repo.queue_job()
+                            .schedule_job(&mut rng, &clock, DeactivateUserJob::new(&user, false))
+                            .await
+                            .map_err(JobError::retry)?;
+                    }
+                }
+
+                let Some(last) = page.edges.last() else {
+                    break;
+                };
+
+                if !page.has_next_page {
+                    break;
+                }
+
+                cursor = cursor.after(last.id);
+            }
+
+            info!(
+                upstream_oauth_provider.id = %provider.id,
+                dry_run = deprovisioning.dry_run,
+                "Deprovisioned {deprovisioned} users whose upstream identity was removed"
+            );
+        }
+
+        repo.save().await.map_err(JobError::retry)?;
+
+        Ok(())
+    }
+}
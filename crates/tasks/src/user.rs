@@ -10,7 +10,7 @@ use mas_storage::{
     RepositoryAccess,
     compat::CompatSessionFilter,
     oauth2::OAuth2SessionFilter,
-    queue::{DeactivateUserJob, ReactivateUserJob},
+    queue::{DeactivateUserJob, DeleteUserJob, QueueJobRepositoryExt as _, ReactivateUserJob},
     user::{BrowserSessionFilter, UserEmailFilter, UserRepository},
 };
 use tracing::info;
@@ -41,6 +41,27 @@ impl RunnableJob for DeactivateUserJob {
             .context("User not found")
             .map_err(JobError::fail)?;
 
+        // If this deactivation starts the grace period of an account deletion,
+        // the user may have cancelled it in the meantime
+        let pending_deletion = if let Some(user_deletion_id) = self.user_deletion_id() {
+            let deletion = repo
+                .user_deletion()
+                .lookup(user_deletion_id)
+                .await
+                .map_err(JobError::retry)?
+                .context("Account deletion not found")
+                .map_err(JobError::fail)?;
+
+            if !deletion.is_pending() {
+                info!(%deletion.id, "Account deletion is no longer pending, skipping");
+                return Ok(());
+            }
+
+            true
+        } else {
+            false
+        };
+
         // Let's first lock & deactivate the user
         let user = repo
             .user()
@@ -87,13 +108,18 @@ impl RunnableJob for DeactivateUserJob {
             .map_err(JobError::retry)?;
         info!(affected = n, "Killed all compatibility sessions for user");
 
-        // Delete all the email addresses for the user
-        let n = repo
-            .user_email()
-            .remove_bulk(UserEmailFilter::new().for_user(&user))
-            .await
-            .map_err(JobError::retry)?;
-        info!(affected = n, "Removed all email addresses for user");
+        // Delete all the email addresses for the user, unless the user can still
+        // cancel the deletion of their account
+        if pending_deletion {
+            info!("Keeping the email addresses until the account deletion completes");
+        } else {
+            let n = repo
+                .user_email()
+                .remove_bulk(UserEmailFilter::new().for_user(&user))
+                .await
+                .map_err(JobError::retry)?;
+            info!(affected = n, "Removed all email addresses for user");
+        }
 
         // Before calling back to the homeserver, commit the changes to the database, as
         // we want the user to be locked out as soon as possible
@@ -110,6 +136,66 @@ impl RunnableJob for DeactivateUserJob {
     }
 }
 
+/// Job to delete a user once the grace period of their account deletion is
+/// over.
+#[async_trait]
+impl RunnableJob for DeleteUserJob {
+    #[tracing::instrument(
+        name = "job.delete_user",
+        fields(user_deletion.id = %self.user_deletion_id()),
+        skip_all,
+    )]
+    async fn run(&self, state: &State, _context: JobContext) -> Result<(), JobError> {
+        let clock = state.clock();
+        let mut rng = state.rng();
+        let mut repo = state.repository().await.map_err(JobError::retry)?;
+
+        let deletion = repo
+            .user_deletion()
+            .lookup(self.user_deletion_id())
+            .await
+            .map_err(JobError::retry)?
+            .context("Account deletion not found")
+            .map_err(JobError::fail)?;
+
+        if !deletion.is_pending() {
+            info!("Account deletion is no longer pending, skipping");
+            return Ok(());
+        }
+
+        let user = repo
+            .user()
+            .lookup(deletion.user_id)
+            .await
+            .map_err(JobError::retry)?
+            .context("User not found")
+            .map_err(JobError::fail)?;
+
+        let deletion = repo
+            .user_deletion()
+            .complete(&clock, deletion)
+            .await
+            .map_err(JobError::retry)?;
+
+        // Now that the deletion is no longer pending, the deactivation job
+        // removes what was kept during the grace period, and asks the homeserver
+        // to erase the user if they wanted to
+        info!(%user.id, "Grace period is over, deleting user");
+        repo.queue_job()
+            .schedule_job(
+                &mut rng,
+                &clock,
+                DeactivateUserJob::new(&user, deletion.hs_erase),
+            )
+            .await
+            .map_err(JobError::retry)?;
+
+        repo.save().await.map_err(JobError::retry)?;
+
+        Ok(())
+    }
+}
+
 /// Job to reactivate a user, both locally and on the Matrix homeserver.
 #[async_trait]
 impl RunnableJob for ReactivateUserJob {
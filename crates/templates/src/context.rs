@@ -19,15 +19,17 @@ use std::{
 use chrono::{DateTime, Duration, Utc};
 use http::{Method, Uri, Version};
 use mas_data_model::{
-    AuthorizationGrant, BrowserSession, Client, CompatSsoLogin, CompatSsoLoginState,
-    DeviceCodeGrant, UpstreamOAuthLink, UpstreamOAuthProvider, UpstreamOAuthProviderClaimsImports,
-    UpstreamOAuthProviderDiscoveryMode, UpstreamOAuthProviderPkceMode,
-    UpstreamOAuthProviderTokenAuthMethod, User, UserEmailAuthentication,
-    UserEmailAuthenticationCode, UserRecoverySession, UserRegistration,
+    AuthorizationGrant, BackchannelAuthenticationGrant, BackchannelAuthenticationGrantState,
+    BrowserSession, Client, CompatSsoLogin, CompatSsoLoginState, DeviceCodeGrant, IpLocation,
+    TermsDocument, TermsDocumentKind, UpstreamOAuthLink, UpstreamOAuthLinkImportedAttributes,
+    UpstreamOAuthProvider, UpstreamOAuthProviderClaimsImports, UpstreamOAuthProviderDiscoveryMode,
+    UpstreamOAuthProviderPkceMode, UpstreamOAuthProviderTokenAuthMethod, User, UserAgent,
+    UserDeletion, UserEmailAuthentication, UserEmailAuthenticationCode, UserPhone, UserPhoneCode,
+    UserRecoverySession, UserRegistration, UserRegistrationToken,
 };
 use mas_i18n::DataLocale;
 use mas_iana::jose::JsonWebSignatureAlg;
-use mas_router::{Account, GraphQL, PostAuthAction, UrlBuilder};
+use mas_router::{Account, GraphQL, PostAuthAction, Reauth, UrlBuilder};
 use oauth2_types::scope::{OPENID, Scope};
 use rand::{
     Rng,
@@ -40,7 +42,7 @@ use url::Url;
 pub use self::{
     branding::SiteBranding, captcha::WithCaptcha, ext::SiteConfigExt, features::SiteFeatures,
 };
-use crate::{FieldError, FormField, FormState};
+use crate::{FieldError, FormError, FormField, FormState};
 
 /// Helper trait to construct context wrappers
 pub trait TemplateContext: Serialize {
@@ -315,6 +317,7 @@ impl TemplateContext for IndexContext {
 pub struct AppConfig {
     root: String,
     graphql_endpoint: String,
+    reauth_endpoint: String,
 }
 
 /// Context used by the `app.html` template
@@ -329,10 +332,14 @@ impl AppContext {
     pub fn from_url_builder(url_builder: &UrlBuilder) -> Self {
         let root = url_builder.relative_url_for(&Account::default());
         let graphql_endpoint = url_builder.relative_url_for(&GraphQL);
+        // Users are sent back to their account after authenticating again
+        let reauth_endpoint =
+            url_builder.relative_url_for(&Reauth::and_then(PostAuthAction::manage_account(None)));
         Self {
             app_config: AppConfig {
                 root,
                 graphql_endpoint,
+                reauth_endpoint,
             },
         }
     }
@@ -421,6 +428,13 @@ pub enum PostAuthContextInner {
         grant: Box<DeviceCodeGrant>,
     },
 
+    /// Continue a backchannel authentication request
+    ContinueBackchannelAuthentication {
+        /// The backchannel authentication request that will be continued after
+        /// authentication
+        grant: Box<BackchannelAuthenticationGrant>,
+    },
+
     /// Continue legacy login
     /// TODO: add the login context in there
     ContinueCompatSsoLogin {
@@ -440,30 +454,997 @@ pub enum PostAuthContextInner {
         link: Box<UpstreamOAuthLink>,
     },
 
-    /// Go to the account management page
-    ManageAccount,
+    /// Go to the account management page
+    ManageAccount,
+}
+
+/// Context used in login screen, for the post-auth action to do
+#[derive(Serialize)]
+pub struct PostAuthContext {
+    /// The post auth action params from the URL
+    pub params: PostAuthAction,
+
+    /// The loaded post auth context
+    #[serde(flatten)]
+    pub ctx: PostAuthContextInner,
+}
+
+/// A challenge to log in with a passkey, used by the `login.html` and
+/// `reauth.html` templates
+#[derive(Serialize)]
+pub struct PasskeyLoginChallenge {
+    /// The ID of the challenge, sent back with the response
+    id: Ulid,
+
+    /// The options to pass to `navigator.credentials.get()`
+    options: serde_json::Value,
+}
+
+impl PasskeyLoginChallenge {
+    /// Create a new [`PasskeyLoginChallenge`]
+    #[must_use]
+    pub fn new(id: Ulid, options: serde_json::Value) -> Self {
+        Self { id, options }
+    }
+}
+
+/// Context used by the `login.html` template
+#[derive(Serialize, Default)]
+pub struct LoginContext {
+    form: FormState<LoginFormField>,
+    next: Option<PostAuthContext>,
+    providers: Vec<UpstreamOAuthProvider>,
+    unavailable_providers: Vec<Ulid>,
+    passkey: Option<PasskeyLoginChallenge>,
+    discovery: bool,
+}
+
+impl TemplateContext for LoginContext {
+    fn sample(
+        _now: chrono::DateTime<Utc>,
+        _rng: &mut impl Rng,
+        _locales: &[DataLocale],
+    ) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        // TODO: samples with errors
+        vec![
+            LoginContext {
+                form: FormState::default(),
+                next: None,
+                providers: Vec::new(),
+                unavailable_providers: Vec::new(),
+                passkey: None,
+                discovery: false,
+            },
+            LoginContext {
+                form: FormState::default(),
+                next: None,
+                providers: Vec::new(),
+                unavailable_providers: Vec::new(),
+                passkey: None,
+                discovery: false,
+            },
+            LoginContext {
+                form: FormState::default()
+                    .with_error_on_field(LoginFormField::Username, FieldError::Required)
+                    .with_error_on_field(
+                        LoginFormField::Password,
+                        FieldError::Policy {
+                            code: None,
+                            message: "password too short".to_owned(),
+                        },
+                    ),
+                next: None,
+                providers: Vec::new(),
+                unavailable_providers: Vec::new(),
+                passkey: None,
+                discovery: false,
+            },
+            LoginContext {
+                form: FormState::default()
+                    .with_error_on_field(LoginFormField::Username, FieldError::Exists),
+                next: None,
+                providers: Vec::new(),
+                unavailable_providers: Vec::new(),
+                passkey: None,
+                discovery: false,
+            },
+            LoginContext {
+                form: FormState::default(),
+                next: None,
+                providers: Vec::new(),
+                unavailable_providers: Vec::new(),
+                passkey: Some(PasskeyLoginChallenge::new(
+                    Ulid::nil(),
+                    serde_json::json!({
+                        "challenge": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA",
+                        "rpId": "example.com",
+                        "timeout": 300_000,
+                        "userVerification": "required",
+                        "allowCredentials": [],
+                    }),
+                )),
+                discovery: false,
+            },
+            LoginContext {
+                form: FormState::default(),
+                next: None,
+                providers: Vec::new(),
+                unavailable_providers: Vec::new(),
+                passkey: None,
+                discovery: true,
+            },
+        ]
+    }
+}
+
+impl LoginContext {
+    /// Set the form state
+    #[must_use]
+    pub fn with_form_state(self, form: FormState<LoginFormField>) -> Self {
+        Self { form, ..self }
+    }
+
+    /// Mutably borrow the form state
+    pub fn form_state_mut(&mut self) -> &mut FormState<LoginFormField> {
+        &mut self.form
+    }
+
+    /// Set the upstream OAuth 2.0 providers
+    #[must_use]
+    pub fn with_upstream_providers(self, providers: Vec<UpstreamOAuthProvider>) -> Self {
+        Self { providers, ..self }
+    }
+
+    /// Set the upstream OAuth 2.0 providers which are shown as unavailable
+    /// because their last health check failed
+    #[must_use]
+    pub fn with_unavailable_providers(self, unavailable_providers: Vec<Ulid>) -> Self {
+        Self {
+            unavailable_providers,
+            ..self
+        }
+    }
+
+    /// Add a post authentication action to the context
+    #[must_use]
+    pub fn with_post_action(self, context: PostAuthContext) -> Self {
+        Self {
+            next: Some(context),
+            ..self
+        }
+    }
+
+    /// Set the challenge to log in with a passkey
+    #[must_use]
+    pub fn with_passkey_challenge(self, passkey: PasskeyLoginChallenge) -> Self {
+        Self {
+            passkey: Some(passkey),
+            ..self
+        }
+    }
+
+    /// Only ask for the username or email address, which is used to find the
+    /// upstream provider to send the user to
+    #[must_use]
+    pub fn with_discovery(self) -> Self {
+        Self {
+            discovery: true,
+            ..self
+        }
+    }
+}
+
+/// Fields of the reauthentication form
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReauthFormField {
+    /// The password field
+    Password,
+}
+
+impl FormField for ReauthFormField {
+    fn keep(&self) -> bool {
+        match self {
+            Self::Password => false,
+        }
+    }
+}
+
+/// Context used by the `reauth.html` template
+#[derive(Serialize, Default)]
+pub struct ReauthContext {
+    form: FormState<ReauthFormField>,
+    next: Option<PostAuthContext>,
+    password: bool,
+    providers: Vec<UpstreamOAuthProvider>,
+    passkey: Option<PasskeyLoginChallenge>,
+}
+
+impl TemplateContext for ReauthContext {
+    fn sample(
+        _now: chrono::DateTime<Utc>,
+        _rng: &mut impl Rng,
+        _locales: &[DataLocale],
+    ) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        vec![
+            ReauthContext {
+                form: FormState::default(),
+                next: None,
+                password: true,
+                providers: Vec::new(),
+                passkey: None,
+            },
+            ReauthContext {
+                form: FormState::default().with_error_on_form(FormError::InvalidCredentials),
+                next: None,
+                password: true,
+                providers: Vec::new(),
+                passkey: None,
+            },
+            ReauthContext {
+                form: FormState::default(),
+                next: None,
+                password: false,
+                providers: Vec::new(),
+                passkey: Some(PasskeyLoginChallenge::new(
+                    Ulid::nil(),
+                    serde_json::json!({
+                        "challenge": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA",
+                        "rpId": "example.com",
+                        "timeout": 300_000,
+                        "userVerification": "required",
+                        "allowCredentials": [],
+                    }),
+                )),
+            },
+        ]
+    }
+}
+
+impl ReauthContext {
+    /// Set the form state
+    #[must_use]
+    pub fn with_form_state(self, form: FormState<ReauthFormField>) -> Self {
+        Self { form, ..self }
+    }
+
+    /// Add a post authentication action to the context
+    #[must_use]
+    pub fn with_post_action(self, next: PostAuthContext) -> Self {
+        Self {
+            next: Some(next),
+            ..self
+        }
+    }
+
+    /// Let the user confirm their password
+    #[must_use]
+    pub fn with_password(self) -> Self {
+        Self {
+            password: true,
+            ..self
+        }
+    }
+
+    /// Set the upstream OAuth 2.0 providers the user can authenticate again
+    /// with
+    #[must_use]
+    pub fn with_upstream_providers(self, providers: Vec<UpstreamOAuthProvider>) -> Self {
+        Self { providers, ..self }
+    }
+
+    /// Set the challenge to authenticate again with a passkey
+    #[must_use]
+    pub fn with_passkey_challenge(self, passkey: PasskeyLoginChallenge) -> Self {
+        Self {
+            passkey: Some(passkey),
+            ..self
+        }
+    }
+}
+
+/// Context used by the `accept_terms.html` template
+#[derive(Serialize)]
+pub struct AcceptTermsContext {
+    documents: Vec<TermsDocument>,
+    next: Option<PostAuthContext>,
+}
+
+impl TemplateContext for AcceptTermsContext {
+    fn sample(
+        _now: chrono::DateTime<Utc>,
+        _rng: &mut impl Rng,
+        _locales: &[DataLocale],
+    ) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        let terms_of_service = TermsDocument {
+            kind: TermsDocumentKind::TermsOfService,
+            version: "2025-07".to_owned(),
+            url: "https://example.com/terms".parse().unwrap(),
+        };
+        let privacy_policy = TermsDocument {
+            kind: TermsDocumentKind::PrivacyPolicy,
+            version: "2".to_owned(),
+            url: "https://example.com/privacy".parse().unwrap(),
+        };
+
+        vec![
+            Self::new(vec![terms_of_service.clone()]),
+            Self::new(vec![terms_of_service, privacy_policy]),
+        ]
+    }
+}
+
+impl AcceptTermsContext {
+    /// Constructs a context with the documents the user has to accept
+    #[must_use]
+    pub fn new(documents: Vec<TermsDocument>) -> Self {
+        Self {
+            documents,
+            next: None,
+        }
+    }
+
+    /// Add a post authentication action to the context
+    #[must_use]
+    pub fn with_post_action(self, next: PostAuthContext) -> Self {
+        Self {
+            next: Some(next),
+            ..self
+        }
+    }
+}
+
+/// Context used by the `invites.html` template
+#[derive(Serialize)]
+pub struct InvitesContext {
+    invites: Vec<UserRegistrationToken>,
+    max_active: u32,
+}
+
+impl TemplateContext for InvitesContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        let invite = UserRegistrationToken {
+            id: Ulid::from_datetime_with_source(now.into(), rng),
+            token: Alphanumeric.sample_string(rng, 12),
+            usage_limit: Some(1),
+            times_used: 0,
+            created_at: now,
+            last_used_at: None,
+            expires_at: Some(now + Duration::days(7)),
+            revoked_at: None,
+            created_by_user_id: Some(Ulid::from_datetime_with_source(now.into(), rng)),
+            assign_referrer: true,
+        };
+
+        vec![
+            Self::new(Vec::new(), 5),
+            Self::new(vec![invite.clone()], 5),
+            Self::new(vec![invite], 1),
+        ]
+    }
+}
+
+impl InvitesContext {
+    /// Constructs a context with the valid invites issued by the user and the
+    /// maximum number of valid invites they can have
+    #[must_use]
+    pub fn new(invites: Vec<UserRegistrationToken>, max_active: u32) -> Self {
+        Self {
+            invites,
+            max_active,
+        }
+    }
+}
+
+/// Context used by the `notifications.html` template
+#[derive(Serialize)]
+pub struct NotificationsContext {
+    sign_in_notifications: bool,
+    saved: bool,
+}
+
+impl TemplateContext for NotificationsContext {
+    fn sample(
+        _now: chrono::DateTime<Utc>,
+        _rng: &mut impl Rng,
+        _locales: &[DataLocale],
+    ) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        vec![Self::new(true), Self::new(false).saved()]
+    }
+}
+
+impl NotificationsContext {
+    /// Constructs a context with whether the user gets an email when logging
+    /// in from a new IP address or device
+    #[must_use]
+    pub fn new(sign_in_notifications: bool) -> Self {
+        Self {
+            sign_in_notifications,
+            saved: false,
+        }
+    }
+
+    /// Tell the user their settings were saved
+    #[must_use]
+    pub fn saved(self) -> Self {
+        Self {
+            saved: true,
+            ..self
+        }
+    }
+}
+
+/// Fields of the TOTP verification form
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginTotpFormField {
+    /// The code field
+    Code,
+}
+
+impl FormField for LoginTotpFormField {
+    fn keep(&self) -> bool {
+        match self {
+            Self::Code => false,
+        }
+    }
+}
+
+/// A TOTP second factor the user has to add to their authenticator app, used
+/// by the `login_totp.html` template
+#[derive(Serialize)]
+pub struct TotpEnrollment {
+    /// The shared secret, encoded in base32
+    secret: String,
+
+    /// The `otpauth://` provisioning URI, to show as a QR code
+    uri: String,
+}
+
+impl TotpEnrollment {
+    /// Create a new [`TotpEnrollment`]
+    #[must_use]
+    pub fn new(secret: String, uri: String) -> Self {
+        Self { secret, uri }
+    }
+}
+
+/// Context used by the `login_totp.html` template
+#[derive(Serialize)]
+pub struct LoginTotpContext {
+    form: FormState<LoginTotpFormField>,
+    user: User,
+    enrollment: Option<TotpEnrollment>,
+    next: Option<PostAuthContext>,
+}
+
+impl TemplateContext for LoginTotpContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        User::samples(now, rng)
+            .into_iter()
+            .flat_map(|user| {
+                let enrollment = TotpEnrollment::new(
+                    "JBSWY3DPEHPK3PXP".to_owned(),
+                    "otpauth://totp/example.com:john?secret=JBSWY3DPEHPK3PXP&issuer=example.com"
+                        .to_owned(),
+                );
+                [
+                    LoginTotpContext::new(user.clone()),
+                    LoginTotpContext::new(user.clone()).with_form_state(
+                        FormState::default()
+                            .with_error_on_field(LoginTotpFormField::Code, FieldError::Invalid),
+                    ),
+                    LoginTotpContext::new(user).with_enrollment(enrollment),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl LoginTotpContext {
+    /// Constructs a context for the TOTP verification page of the given user
+    #[must_use]
+    pub fn new(user: User) -> Self {
+        Self {
+            form: FormState::default(),
+            user,
+            enrollment: None,
+            next: None,
+        }
+    }
+
+    /// Set the form state
+    #[must_use]
+    pub fn with_form_state(self, form: FormState<LoginTotpFormField>) -> Self {
+        Self { form, ..self }
+    }
+
+    /// Ask the user to add a new second factor to their authenticator app
+    /// before entering a code
+    #[must_use]
+    pub fn with_enrollment(self, enrollment: TotpEnrollment) -> Self {
+        Self {
+            enrollment: Some(enrollment),
+            ..self
+        }
+    }
+
+    /// Add a post authentication action to the context
+    #[must_use]
+    pub fn with_post_action(self, next: PostAuthContext) -> Self {
+        Self {
+            next: Some(next),
+            ..self
+        }
+    }
+}
+
+/// Context used by the `login_totp_recovery_codes.html` template, which shows
+/// the recovery codes generated when a user adds a second factor during login
+#[derive(Serialize)]
+pub struct LoginTotpRecoveryCodesContext {
+    codes: Vec<String>,
+    next: Option<PostAuthContext>,
+}
+
+impl TemplateContext for LoginTotpRecoveryCodesContext {
+    fn sample(
+        _now: chrono::DateTime<Utc>,
+        _rng: &mut impl Rng,
+        _locales: &[DataLocale],
+    ) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        let codes = ["ABCD-EFGH-IJKL-MNOP", "QRST-UVWX-YZ23-4567"]
+            .map(ToOwned::to_owned)
+            .to_vec();
+        vec![LoginTotpRecoveryCodesContext::new(codes)]
+    }
+}
+
+impl LoginTotpRecoveryCodesContext {
+    /// Constructs a context showing the given recovery codes
+    #[must_use]
+    pub fn new(codes: Vec<String>) -> Self {
+        Self { codes, next: None }
+    }
+
+    /// Add a post authentication action to the context
+    #[must_use]
+    pub fn with_post_action(self, next: PostAuthContext) -> Self {
+        Self {
+            next: Some(next),
+            ..self
+        }
+    }
+}
+
+/// Fields of the recovery code form
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginRecoveryCodeFormField {
+    /// The code field
+    Code,
+}
+
+impl FormField for LoginRecoveryCodeFormField {
+    fn keep(&self) -> bool {
+        match self {
+            Self::Code => false,
+        }
+    }
+}
+
+/// Context used by the `login_recovery_code.html` template
+#[derive(Serialize)]
+pub struct LoginRecoveryCodeContext {
+    form: FormState<LoginRecoveryCodeFormField>,
+    user: User,
+    next: Option<PostAuthContext>,
+}
+
+impl TemplateContext for LoginRecoveryCodeContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        User::samples(now, rng)
+            .into_iter()
+            .flat_map(|user| {
+                [
+                    LoginRecoveryCodeContext::new(user.clone()),
+                    LoginRecoveryCodeContext::new(user).with_form_state(
+                        FormState::default().with_error_on_field(
+                            LoginRecoveryCodeFormField::Code,
+                            FieldError::Invalid,
+                        ),
+                    ),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl LoginRecoveryCodeContext {
+    /// Constructs a context for the recovery code page of the given user
+    #[must_use]
+    pub fn new(user: User) -> Self {
+        Self {
+            form: FormState::default(),
+            user,
+            next: None,
+        }
+    }
+
+    /// Set the form state
+    #[must_use]
+    pub fn with_form_state(self, form: FormState<LoginRecoveryCodeFormField>) -> Self {
+        Self { form, ..self }
+    }
+
+    /// Add a post authentication action to the context
+    #[must_use]
+    pub fn with_post_action(self, next: PostAuthContext) -> Self {
+        Self {
+            next: Some(next),
+            ..self
+        }
+    }
+}
+
+/// Fields of the SMS code form
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginSmsFormField {
+    /// The code field
+    Code,
+}
+
+impl FormField for LoginSmsFormField {
+    fn keep(&self) -> bool {
+        match self {
+            Self::Code => false,
+        }
+    }
+}
+
+/// Context used by the `login_sms.html` template, where the user enters the
+/// code sent by SMS to their phone after their password
+#[derive(Serialize)]
+pub struct LoginSmsContext {
+    form: FormState<LoginSmsFormField>,
+    user: User,
+    masked_phone_number: String,
+    next: Option<PostAuthContext>,
+}
+
+impl TemplateContext for LoginSmsContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        User::samples(now, rng)
+            .into_iter()
+            .zip(UserPhone::samples(now, rng))
+            .flat_map(|(user, phone)| {
+                [
+                    LoginSmsContext::new(user.clone(), &phone),
+                    LoginSmsContext::new(user, &phone).with_form_state(
+                        FormState::default()
+                            .with_error_on_field(LoginSmsFormField::Code, FieldError::Invalid),
+                    ),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl LoginSmsContext {
+    /// Constructs a context for the SMS code page of the given user, showing
+    /// a masked version of the phone number the code was sent to
+    #[must_use]
+    pub fn new(user: User, phone: &UserPhone) -> Self {
+        Self {
+            form: FormState::default(),
+            user,
+            masked_phone_number: phone.masked_phone_number(),
+            next: None,
+        }
+    }
+
+    /// Set the form state
+    #[must_use]
+    pub fn with_form_state(self, form: FormState<LoginSmsFormField>) -> Self {
+        Self { form, ..self }
+    }
+
+    /// Add a post authentication action to the context
+    #[must_use]
+    pub fn with_post_action(self, next: PostAuthContext) -> Self {
+        Self {
+            next: Some(next),
+            ..self
+        }
+    }
+}
+
+/// Fields of the form confirming a suspicious login with a code sent by email
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginConfirmEmailFormField {
+    /// The code field
+    Code,
+}
+
+impl FormField for LoginConfirmEmailFormField {
+    fn keep(&self) -> bool {
+        match self {
+            Self::Code => false,
+        }
+    }
+}
+
+/// Context used by the `login_confirm_email.html` template, where the user
+/// enters the code sent by email to confirm a login which looked suspicious
+#[derive(Serialize)]
+pub struct LoginConfirmEmailContext {
+    form: FormState<LoginConfirmEmailFormField>,
+    user: User,
+    email: String,
+    next: Option<PostAuthContext>,
+}
+
+impl TemplateContext for LoginConfirmEmailContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        User::samples(now, rng)
+            .into_iter()
+            .flat_map(|user| {
+                let email = format!("{}@example.com", user.username);
+                [
+                    LoginConfirmEmailContext::new(user.clone(), email.clone()),
+                    LoginConfirmEmailContext::new(user, email).with_form_state(
+                        FormState::default().with_error_on_field(
+                            LoginConfirmEmailFormField::Code,
+                            FieldError::Invalid,
+                        ),
+                    ),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl LoginConfirmEmailContext {
+    /// Constructs a context for the login confirmation page of the given user,
+    /// showing the email address the code was sent to
+    #[must_use]
+    pub fn new(user: User, email: String) -> Self {
+        Self {
+            form: FormState::default(),
+            user,
+            email,
+            next: None,
+        }
+    }
+
+    /// Set the form state
+    #[must_use]
+    pub fn with_form_state(self, form: FormState<LoginConfirmEmailFormField>) -> Self {
+        Self { form, ..self }
+    }
+
+    /// Add a post authentication action to the context
+    #[must_use]
+    pub fn with_post_action(self, next: PostAuthContext) -> Self {
+        Self {
+            next: Some(next),
+            ..self
+        }
+    }
+}
+
+/// Fields of the form to change an expired password during the login
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginPasswordChangeFormField {
+    /// The new password field
+    NewPassword,
+
+    /// The new password confirmation field
+    NewPasswordConfirm,
+}
+
+impl FormField for LoginPasswordChangeFormField {
+    fn keep(&self) -> bool {
+        match self {
+            Self::NewPassword | Self::NewPasswordConfirm => false,
+        }
+    }
+}
+
+/// Context used by the `login_password_change.html` template, where the user
+/// has to choose a new password after logging in with an expired one, or
+/// because an administrator required it
+#[derive(Serialize)]
+pub struct LoginPasswordChangeContext {
+    form: FormState<LoginPasswordChangeFormField>,
+    user: User,
+    reset_by_admin: bool,
+    next: Option<PostAuthContext>,
+}
+
+impl TemplateContext for LoginPasswordChangeContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        User::samples(now, rng)
+            .into_iter()
+            .flat_map(|user| {
+                [
+                    LoginPasswordChangeContext::new(user.clone()),
+                    LoginPasswordChangeContext::new(user.clone()).with_reset_by_admin(),
+                    LoginPasswordChangeContext::new(user).with_form_state(
+                        FormState::default().with_error_on_field(
+                            LoginPasswordChangeFormField::NewPassword,
+                            FieldError::Policy {
+                                code: Some("password-reused"),
+                                message: "Password was used recently".to_owned(),
+                            },
+                        ),
+                    ),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl LoginPasswordChangeContext {
+    /// Constructs a context for the password change page of the given user
+    #[must_use]
+    pub fn new(user: User) -> Self {
+        Self {
+            form: FormState::default(),
+            user,
+            reset_by_admin: false,
+            next: None,
+        }
+    }
+
+    /// Set the form state
+    #[must_use]
+    pub fn with_form_state(self, form: FormState<LoginPasswordChangeFormField>) -> Self {
+        Self { form, ..self }
+    }
+
+    /// Mark that an administrator required the user to change their password,
+    /// rather than it having expired
+    #[must_use]
+    pub fn with_reset_by_admin(self) -> Self {
+        Self {
+            reset_by_admin: true,
+            ..self
+        }
+    }
+
+    /// Add a post authentication action to the context
+    #[must_use]
+    pub fn with_post_action(self, next: PostAuthContext) -> Self {
+        Self {
+            next: Some(next),
+            ..self
+        }
+    }
+}
+
+/// Fields of the email login form
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginEmailFormField {
+    /// The email field
+    Email,
+}
+
+impl FormField for LoginEmailFormField {
+    fn keep(&self) -> bool {
+        match self {
+            Self::Email => true,
+        }
+    }
+}
+
+/// Context used by the `login_email.html` template, where the user enters
+/// the email address to send a login code to
+#[derive(Serialize, Default)]
+pub struct LoginEmailContext {
+    form: FormState<LoginEmailFormField>,
+    next: Option<PostAuthContext>,
+}
+
+impl TemplateContext for LoginEmailContext {
+    fn sample(
+        _now: chrono::DateTime<Utc>,
+        _rng: &mut impl Rng,
+        _locales: &[DataLocale],
+    ) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        vec![
+            LoginEmailContext::default(),
+            LoginEmailContext::default().with_form_state(
+                FormState::default()
+                    .with_error_on_field(LoginEmailFormField::Email, FieldError::Invalid),
+            ),
+        ]
+    }
+}
+
+impl LoginEmailContext {
+    /// Set the form state
+    #[must_use]
+    pub fn with_form_state(self, form: FormState<LoginEmailFormField>) -> Self {
+        Self { form, ..self }
+    }
+
+    /// Add a post authentication action to the context
+    #[must_use]
+    pub fn with_post_action(self, next: PostAuthContext) -> Self {
+        Self {
+            next: Some(next),
+            ..self
+        }
+    }
 }
 
-/// Context used in login screen, for the post-auth action to do
-#[derive(Serialize)]
-pub struct PostAuthContext {
-    /// The post auth action params from the URL
-    pub params: PostAuthAction,
+/// Fields of the email login code form
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoginEmailCodeFormField {
+    /// The code field
+    Code,
+}
 
-    /// The loaded post auth context
-    #[serde(flatten)]
-    pub ctx: PostAuthContextInner,
+impl FormField for LoginEmailCodeFormField {
+    fn keep(&self) -> bool {
+        match self {
+            Self::Code => false,
+        }
+    }
 }
 
-/// Context used by the `login.html` template
-#[derive(Serialize, Default)]
-pub struct LoginContext {
-    form: FormState<LoginFormField>,
+/// Context used by the `login_email_code.html` template, where the user
+/// enters the code sent to their email address
+#[derive(Serialize)]
+pub struct LoginEmailCodeContext {
+    form: FormState<LoginEmailCodeFormField>,
+    email: String,
     next: Option<PostAuthContext>,
-    providers: Vec<UpstreamOAuthProvider>,
 }
 
-impl TemplateContext for LoginContext {
+impl TemplateContext for LoginEmailCodeContext {
     fn sample(
         _now: chrono::DateTime<Utc>,
         _rng: &mut impl Rng,
@@ -472,69 +1453,68 @@ impl TemplateContext for LoginContext {
     where
         Self: Sized,
     {
-        // TODO: samples with errors
+        let email = "john@example.com".to_owned();
         vec![
-            LoginContext {
-                form: FormState::default(),
-                next: None,
-                providers: Vec::new(),
-            },
-            LoginContext {
-                form: FormState::default(),
-                next: None,
-                providers: Vec::new(),
-            },
-            LoginContext {
-                form: FormState::default()
-                    .with_error_on_field(LoginFormField::Username, FieldError::Required)
-                    .with_error_on_field(
-                        LoginFormField::Password,
-                        FieldError::Policy {
-                            code: None,
-                            message: "password too short".to_owned(),
-                        },
-                    ),
-                next: None,
-                providers: Vec::new(),
-            },
-            LoginContext {
-                form: FormState::default()
-                    .with_error_on_field(LoginFormField::Username, FieldError::Exists),
-                next: None,
-                providers: Vec::new(),
-            },
+            LoginEmailCodeContext::new(email.clone()),
+            LoginEmailCodeContext::new(email).with_form_state(
+                FormState::default()
+                    .with_error_on_field(LoginEmailCodeFormField::Code, FieldError::Invalid),
+            ),
         ]
     }
 }
 
-impl LoginContext {
-    /// Set the form state
+impl LoginEmailCodeContext {
+    /// Constructs a context for the code sent to the given email address
     #[must_use]
-    pub fn with_form_state(self, form: FormState<LoginFormField>) -> Self {
-        Self { form, ..self }
-    }
-
-    /// Mutably borrow the form state
-    pub fn form_state_mut(&mut self) -> &mut FormState<LoginFormField> {
-        &mut self.form
+    pub fn new(email: String) -> Self {
+        Self {
+            form: FormState::default(),
+            email,
+            next: None,
+        }
     }
 
-    /// Set the upstream OAuth 2.0 providers
+    /// Set the form state
     #[must_use]
-    pub fn with_upstream_providers(self, providers: Vec<UpstreamOAuthProvider>) -> Self {
-        Self { providers, ..self }
+    pub fn with_form_state(self, form: FormState<LoginEmailCodeFormField>) -> Self {
+        Self { form, ..self }
     }
 
     /// Add a post authentication action to the context
     #[must_use]
-    pub fn with_post_action(self, context: PostAuthContext) -> Self {
+    pub fn with_post_action(self, next: PostAuthContext) -> Self {
         Self {
-            next: Some(context),
+            next: Some(next),
             ..self
         }
     }
 }
 
+/// Context used by the `login_email_link.html` template, shown when a login
+/// link is opened in another browser than the one which asked for it
+#[derive(Serialize)]
+pub struct LoginEmailLinkContext {
+    user: User,
+}
+
+impl TemplateContext for LoginEmailLinkContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        User::samples(now, rng).into_iter().map(Self::new).collect()
+    }
+}
+
+impl LoginEmailLinkContext {
+    /// Constructs a context for a login link of the given user
+    #[must_use]
+    pub fn new(user: User) -> Self {
+        Self { user }
+    }
+}
+
 /// Fields of the registration form
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -654,6 +1634,9 @@ pub struct ConsentContext {
     grant: AuthorizationGrant,
     client: Client,
     action: PostAuthAction,
+    requested_claims: Vec<String>,
+    new_scope: Scope,
+    remembered_scope: Scope,
 }
 
 impl TemplateContext for ConsentContext {
@@ -665,14 +1648,9 @@ impl TemplateContext for ConsentContext {
             .into_iter()
             .map(|client| {
                 let mut grant = AuthorizationGrant::sample(now, rng);
-                let action = PostAuthAction::continue_grant(grant.id);
                 // XXX
                 grant.client_id = client.id;
-                Self {
-                    grant,
-                    client,
-                    action,
-                }
+                Self::new(grant, client)
             })
             .collect()
     }
@@ -683,12 +1661,36 @@ impl ConsentContext {
     #[must_use]
     pub fn new(grant: AuthorizationGrant, client: Client) -> Self {
         let action = PostAuthAction::continue_grant(grant.id);
+        let requested_claims = grant
+            .requested_claims()
+            .into_iter()
+            .map(ToOwned::to_owned)
+            .collect();
+        let new_scope = grant.scope.clone();
         Self {
             grant,
             client,
             action,
+            requested_claims,
+            new_scope,
+            remembered_scope: std::iter::empty().collect(),
         }
     }
+
+    /// Set the scope the user previously granted to the client, so that only
+    /// the new scopes are highlighted
+    #[must_use]
+    pub fn with_remembered_scope(mut self, remembered_scope: &Scope) -> Self {
+        let (remembered, new): (Vec<_>, Vec<_>) = self
+            .grant
+            .scope
+            .iter()
+            .cloned()
+            .partition(|token| remembered_scope.contains(token));
+        self.new_scope = new.into_iter().collect();
+        self.remembered_scope = remembered.into_iter().collect();
+        self
+    }
 }
 
 #[derive(Serialize)]
@@ -698,6 +1700,8 @@ enum PolicyViolationGrant {
     Authorization(AuthorizationGrant),
     #[serde(rename = "urn:ietf:params:oauth:grant-type:device_code")]
     DeviceCode(DeviceCodeGrant),
+    #[serde(rename = "urn:openid:params:grant-type:ciba")]
+    Backchannel(BackchannelAuthenticationGrant),
 }
 
 /// Context used by the `policy_violation.html` template
@@ -734,6 +1738,9 @@ impl TemplateContext for PolicyViolationContext {
                         expires_at: now + Duration::try_minutes(25).unwrap(),
                         ip_address: None,
                         user_agent: None,
+                        device_fingerprint: None,
+                        interval: Duration::try_seconds(5).unwrap(),
+                        last_polled_at: None,
                     },
                     client,
                 );
@@ -768,6 +1775,21 @@ impl PolicyViolationContext {
             action,
         }
     }
+
+    /// Constructs a context for the policy violation page for a backchannel
+    /// authentication request
+    #[must_use]
+    pub const fn for_backchannel_authentication_grant(
+        grant: BackchannelAuthenticationGrant,
+        client: Client,
+    ) -> Self {
+        let action = PostAuthAction::continue_backchannel_authentication(grant.id);
+        Self {
+            grant: PolicyViolationGrant::Backchannel(grant),
+            client,
+            action,
+        }
+    }
 }
 
 /// Context used by the `sso.html` template
@@ -847,14 +1869,299 @@ impl TemplateContext for EmailRecoveryContext {
                 user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_8_4) AppleWebKit/536.30.1 (KHTML, like Gecko) Version/6.0.5 Safari/536.30.1".to_owned(),
                 ip_address: Some(IpAddr::from([192_u8, 0, 2, 1])),
                 locale: "en".to_owned(),
+                lost_second_factor: false,
+                requires_approval: false,
                 created_at: now,
                 consumed_at: None,
+                approved_at: None,
+                denied_at: None,
             };
 
             let link = "https://example.com/recovery/complete?ticket=abcdefghijklmnopqrstuvwxyz0123456789".parse().unwrap();
 
-            Self::new(user, session, link)
-        }).collect()
+            Self::new(user, session, link)
+        }).collect()
+    }
+}
+
+/// Context used by the `emails/recovery_review.{txt,html,subject}` templates
+#[derive(Serialize)]
+pub struct EmailRecoveryReviewContext {
+    user: User,
+    session: UserRecoverySession,
+}
+
+impl EmailRecoveryReviewContext {
+    /// Constructs a context for the email telling the user their recovery
+    /// request is pending review or was denied
+    #[must_use]
+    pub fn new(user: User, session: UserRecoverySession) -> Self {
+        Self { user, session }
+    }
+
+    /// Returns the user associated with the recovery request
+    #[must_use]
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+
+    /// Returns the recovery session under review
+    #[must_use]
+    pub fn session(&self) -> &UserRecoverySession {
+        &self.session
+    }
+}
+
+impl TemplateContext for EmailRecoveryReviewContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        User::samples(now, rng)
+            .into_iter()
+            .flat_map(|user| {
+                let pending = UserRecoverySession {
+                    id: Ulid::from_datetime_with_source(now.into(), rng),
+                    email: "hello@example.com".to_owned(),
+                    user_agent: "Mozilla/5.0".to_owned(),
+                    ip_address: Some(IpAddr::from([192_u8, 0, 2, 1])),
+                    locale: "en".to_owned(),
+                    lost_second_factor: true,
+                    requires_approval: true,
+                    created_at: now,
+                    consumed_at: None,
+                    approved_at: None,
+                    denied_at: None,
+                };
+
+                let denied = UserRecoverySession {
+                    denied_at: Some(now),
+                    ..pending.clone()
+                };
+
+                [Self::new(user.clone(), pending), Self::new(user, denied)]
+            })
+            .collect()
+    }
+}
+
+/// Context used by the `emails/claim.{txt,html,subject}` templates
+#[derive(Serialize)]
+pub struct EmailClaimContext {
+    user: User,
+    claim_link: Url,
+    expires_at: DateTime<Utc>,
+}
+
+impl EmailClaimContext {
+    /// Constructs a context for the account claim email
+    #[must_use]
+    pub fn new(user: User, claim_link: Url, expires_at: DateTime<Utc>) -> Self {
+        Self {
+            user,
+            claim_link,
+            expires_at,
+        }
+    }
+
+    /// Returns the user which the email invites to claim their account
+    #[must_use]
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+}
+
+impl TemplateContext for EmailClaimContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        User::samples(now, rng)
+            .into_iter()
+            .map(|user| {
+                let link = "https://example.com/claim/abcdefghijklmnopqrstuvwxyz012345"
+                    .parse()
+                    .unwrap();
+
+                Self::new(user, link, now + Duration::try_days(7).unwrap())
+            })
+            .collect()
+    }
+}
+
+/// Context used by the `emails/backchannel.{txt,html,subject}` templates
+#[derive(Serialize)]
+pub struct EmailBackchannelContext {
+    user: User,
+    client: Client,
+    grant: BackchannelAuthenticationGrant,
+    approval_link: Url,
+}
+
+impl EmailBackchannelContext {
+    /// Constructs a context for the backchannel authentication email
+    #[must_use]
+    pub fn new(
+        user: User,
+        client: Client,
+        grant: BackchannelAuthenticationGrant,
+        approval_link: Url,
+    ) -> Self {
+        Self {
+            user,
+            client,
+            grant,
+            approval_link,
+        }
+    }
+
+    /// Returns the user which is asked to approve the request
+    #[must_use]
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+
+    /// Returns the backchannel authentication request
+    #[must_use]
+    pub fn grant(&self) -> &BackchannelAuthenticationGrant {
+        &self.grant
+    }
+}
+
+impl TemplateContext for EmailBackchannelContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        let clients = Client::samples(now, rng);
+        User::samples(now, rng)
+            .into_iter()
+            .zip(clients)
+            .map(|(user, client)| {
+                let grant = sample_backchannel_grant(now, rng, &client, &user);
+                let link = format!("https://example.com/backchannel/{}", grant.id)
+                    .parse()
+                    .unwrap();
+
+                Self::new(user, client, grant, link)
+            })
+            .collect()
+    }
+}
+
+fn sample_backchannel_grant(
+    now: chrono::DateTime<Utc>,
+    rng: &mut impl Rng,
+    client: &Client,
+    user: &User,
+) -> BackchannelAuthenticationGrant {
+    BackchannelAuthenticationGrant {
+        id: Ulid::from_datetime_with_source(now.into(), rng),
+        state: BackchannelAuthenticationGrantState::Pending,
+        client_id: client.id,
+        user_id: user.id,
+        scope: [OPENID].into_iter().collect(),
+        auth_req_id: Alphanumeric.sample_string(rng, 32),
+        binding_message: Some("W4SCT".to_owned()),
+        client_notification_token: None,
+        created_at: now - Duration::try_minutes(1).unwrap(),
+        expires_at: now + Duration::try_minutes(4).unwrap(),
+    }
+}
+
+/// Context used by the `emails/sign_in.{txt,html,subject}` templates
+#[derive(Serialize)]
+pub struct EmailSignInContext {
+    user: User,
+    session: BrowserSession,
+    user_agent: Option<UserAgent>,
+    ip: IpAddr,
+    location: IpLocation,
+    sessions_link: Url,
+    settings_link: Url,
+}
+
+impl EmailSignInContext {
+    /// Constructs a context for the sign-in notification email
+    ///
+    /// The user agent of the session is parsed to tell which browser and
+    /// operating system were used to log in.
+    #[must_use]
+    pub fn new(
+        user: User,
+        session: BrowserSession,
+        ip: IpAddr,
+        location: IpLocation,
+        sessions_link: Url,
+        settings_link: Url,
+    ) -> Self {
+        let user_agent = session.user_agent.clone().map(UserAgent::parse);
+        Self {
+            user,
+            session,
+            user_agent,
+            ip,
+            location,
+            sessions_link,
+            settings_link,
+        }
+    }
+
+    /// Returns the user whose account was logged in to
+    #[must_use]
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+
+    /// Returns the session which was logged in
+    #[must_use]
+    pub fn session(&self) -> &BrowserSession {
+        &self.session
+    }
+}
+
+impl TemplateContext for EmailSignInContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        BrowserSession::samples(now, rng)
+            .into_iter()
+            .flat_map(|session| {
+                let sessions_link: Url = "https://example.com/account/sessions/browsers"
+                    .parse()
+                    .unwrap();
+                let settings_link: Url = "https://example.com/notifications".parse().unwrap();
+                let ip = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+                let location = IpLocation {
+                    country: Some("FR".to_owned()),
+                    asn: Some(64496),
+                    as_organization: Some("Example ISP".to_owned()),
+                };
+
+                [
+                    Self::new(
+                        session.user.clone(),
+                        session.clone(),
+                        ip,
+                        location,
+                        sessions_link.clone(),
+                        settings_link.clone(),
+                    ),
+                    Self::new(
+                        session.user.clone(),
+                        BrowserSession {
+                            user_agent: None,
+                            ..session
+                        },
+                        ip,
+                        IpLocation::default(),
+                        sessions_link,
+                        settings_link,
+                    ),
+                ]
+            })
+            .collect()
     }
 }
 
@@ -865,7 +2172,13 @@ pub struct EmailVerificationContext {
     browser_session: Option<BrowserSession>,
     #[serde(skip_serializing_if = "Option::is_none")]
     user_registration: Option<UserRegistration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    login_user: Option<User>,
     authentication_code: UserEmailAuthenticationCode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_session_link: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    login_link: Option<Url>,
 }
 
 impl EmailVerificationContext {
@@ -879,14 +2192,42 @@ impl EmailVerificationContext {
         Self {
             browser_session,
             user_registration,
+            login_user: None,
             authentication_code,
+            end_session_link: None,
+            login_link: None,
         }
     }
 
+    /// Mark the code as being sent to let the given user log in
+    #[must_use]
+    pub fn for_login(mut self, user: User) -> Self {
+        self.login_user = Some(user);
+        self
+    }
+
+    /// Set the link letting the recipient log in without entering the code
+    #[must_use]
+    pub fn with_login_link(mut self, login_link: Url) -> Self {
+        self.login_link = Some(login_link);
+        self
+    }
+
+    /// Set the link letting the recipient end the browser session which
+    /// requested the verification, in case it wasn't them
+    #[must_use]
+    pub fn with_end_session_link(mut self, end_session_link: Url) -> Self {
+        self.end_session_link = Some(end_session_link);
+        self
+    }
+
     /// Get the user to which this email is being sent
     #[must_use]
     pub fn user(&self) -> Option<&User> {
-        self.browser_session.as_ref().map(|s| &s.user)
+        self.browser_session
+            .as_ref()
+            .map(|s| &s.user)
+            .or(self.login_user.as_ref())
     }
 
     /// Get the verification code being sent
@@ -903,7 +2244,7 @@ impl TemplateContext for EmailVerificationContext {
     {
         BrowserSession::samples(now, rng)
             .into_iter()
-            .map(|browser_session| {
+            .flat_map(|browser_session| {
                 let authentication_code = UserEmailAuthenticationCode {
                     id: Ulid::from_datetime_with_source(now.into(), rng),
                     user_email_authentication_id: Ulid::from_datetime_with_source(now.into(), rng),
@@ -912,11 +2253,98 @@ impl TemplateContext for EmailVerificationContext {
                     expires_at: now + Duration::try_minutes(25).unwrap(),
                 };
 
-                Self {
-                    browser_session: Some(browser_session),
-                    user_registration: None,
-                    authentication_code,
-                }
+                let end_session_link =
+                    "https://example.com/action/abcdefghijklmnopqrstuvwxyz012345"
+                        .parse()
+                        .unwrap();
+
+                let login_link = "https://example.com/login/email/link/eyJhbGciOiJSUzI1NiJ9"
+                    .parse()
+                    .unwrap();
+
+                let login = Self::new(authentication_code.clone(), None, None)
+                    .for_login(browser_session.user.clone())
+                    .with_login_link(login_link);
+
+                [
+                    Self {
+                        browser_session: Some(browser_session),
+                        user_registration: None,
+                        login_user: None,
+                        authentication_code,
+                        end_session_link: Some(end_session_link),
+                        login_link: None,
+                    },
+                    login,
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Context used by the `sms/verification.txt` template
+#[derive(Serialize)]
+pub struct SmsVerificationContext {
+    user: User,
+    code: UserPhoneCode,
+    login: bool,
+}
+
+impl SmsVerificationContext {
+    /// Constructs a context for a text message confirming the phone number of
+    /// the given user
+    #[must_use]
+    pub fn new(user: User, code: UserPhoneCode) -> Self {
+        Self {
+            user,
+            code,
+            login: false,
+        }
+    }
+
+    /// Mark the code as being sent to complete a login
+    #[must_use]
+    pub fn for_login(self) -> Self {
+        Self {
+            login: true,
+            ..self
+        }
+    }
+
+    /// Get the user to which this message is being sent
+    #[must_use]
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+
+    /// Get the code being sent
+    #[must_use]
+    pub fn code(&self) -> &str {
+        &self.code.code
+    }
+}
+
+impl TemplateContext for SmsVerificationContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        User::samples(now, rng)
+            .into_iter()
+            .flat_map(|user| {
+                let code = UserPhoneCode {
+                    id: Ulid::from_datetime_with_source(now.into(), rng),
+                    user_phone_id: Ulid::from_datetime_with_source(now.into(), rng),
+                    code: "123456".to_owned(),
+                    created_at: now,
+                    expires_at: now + Duration::try_minutes(10).unwrap(),
+                    consumed_at: None,
+                };
+
+                [
+                    Self::new(user.clone(), code.clone()),
+                    Self::new(user, code).for_login(),
+                ]
             })
             .collect()
     }
@@ -971,6 +2399,7 @@ impl TemplateContext for RegisterStepsVerifyEmailContext {
             id: Ulid::from_datetime_with_source(now.into(), rng),
             user_session_id: None,
             user_registration_id: None,
+            user_id: None,
             email: "foobar@example.com".to_owned(),
             created_at: now,
             completed_at: None,
@@ -1212,8 +2641,23 @@ impl TemplateContext for RecoveryProgressContext {
             user_agent: "Mozilla/5.0".to_owned(),
             ip_address: None,
             locale: "en".to_owned(),
+            lost_second_factor: false,
+            requires_approval: false,
             created_at: now,
             consumed_at: None,
+            approved_at: None,
+            denied_at: None,
+        };
+
+        let pending = UserRecoverySession {
+            lost_second_factor: true,
+            requires_approval: true,
+            ..session.clone()
+        };
+
+        let denied = UserRecoverySession {
+            denied_at: Some(now),
+            ..pending.clone()
         };
 
         vec![
@@ -1225,6 +2669,14 @@ impl TemplateContext for RecoveryProgressContext {
                 session,
                 resend_failed_due_to_rate_limit: true,
             },
+            Self {
+                session: pending,
+                resend_failed_due_to_rate_limit: false,
+            },
+            Self {
+                session: denied,
+                resend_failed_due_to_rate_limit: false,
+            },
         ]
     }
 }
@@ -1254,8 +2706,12 @@ impl TemplateContext for RecoveryExpiredContext {
             user_agent: "Mozilla/5.0".to_owned(),
             ip_address: None,
             locale: "en".to_owned(),
+            lost_second_factor: false,
+            requires_approval: false,
             created_at: now,
             consumed_at: None,
+            approved_at: None,
+            denied_at: None,
         };
 
         vec![Self { session }]
@@ -1332,6 +2788,93 @@ impl TemplateContext for RecoveryFinishContext {
     }
 }
 
+/// Fields of the account claim form
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountClaimFormField {
+    /// The new password
+    NewPassword,
+
+    /// The new password confirmation
+    NewPasswordConfirm,
+}
+
+impl FormField for AccountClaimFormField {
+    fn keep(&self) -> bool {
+        false
+    }
+}
+
+/// Context used by the `pages/claim/index.html` template
+#[derive(Serialize)]
+pub struct AccountClaimContext {
+    user: User,
+    form: FormState<AccountClaimFormField>,
+}
+
+impl AccountClaimContext {
+    /// Constructs a context for the account claim page
+    #[must_use]
+    pub fn new(user: User) -> Self {
+        Self {
+            user,
+            form: FormState::default(),
+        }
+    }
+
+    /// Set the form state
+    #[must_use]
+    pub fn with_form_state(mut self, form: FormState<AccountClaimFormField>) -> Self {
+        self.form = form;
+        self
+    }
+}
+
+impl TemplateContext for AccountClaimContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        User::samples(now, rng)
+            .into_iter()
+            .flat_map(|user| {
+                vec![
+                    Self::new(user.clone()),
+                    Self::new(user.clone()).with_form_state(
+                        FormState::default().with_error_on_field(
+                            AccountClaimFormField::NewPasswordConfirm,
+                            FieldError::PasswordMismatch,
+                        ),
+                    ),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Context used by the `pages/user_action/index.html` template
+#[derive(Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum UserActionContext {
+    /// Confirm ending a browser session
+    EndBrowserSession {
+        /// The session which would be ended
+        browser_session: BrowserSession,
+    },
+}
+
+impl TemplateContext for UserActionContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        BrowserSession::samples(now, rng)
+            .into_iter()
+            .map(|browser_session| Self::EndBrowserSession { browser_session })
+            .collect()
+    }
+}
+
 /// Context used by the `pages/upstream_oauth2/{link_mismatch,do_login}.html`
 /// templates
 #[derive(Serialize)]
@@ -1359,6 +2902,105 @@ impl TemplateContext for UpstreamExistingLinkContext {
     }
 }
 
+/// Context used by the `pages/upstream_oauth2/email_domain_not_allowed.html`
+/// template
+#[derive(Serialize)]
+pub struct UpstreamEmailDomainNotAllowedContext {
+    email: Option<String>,
+}
+
+impl UpstreamEmailDomainNotAllowedContext {
+    /// Constructs a new context with the email address the upstream provider
+    /// asserted, if any
+    #[must_use]
+    pub fn new(email: Option<String>) -> Self {
+        Self { email }
+    }
+}
+
+impl TemplateContext for UpstreamEmailDomainNotAllowedContext {
+    fn sample(
+        _now: chrono::DateTime<Utc>,
+        _rng: &mut impl Rng,
+        _locales: &[DataLocale],
+    ) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        vec![
+            Self::new(Some("john@example.com".to_owned())),
+            Self::new(None),
+        ]
+    }
+}
+
+/// A reason for which the `pages/upstream_oauth2/login_denied.html` template
+/// denies a login
+#[derive(Serialize)]
+pub struct UpstreamLoginDeniedViolation {
+    code: Option<&'static str>,
+    claim: Option<String>,
+    message: String,
+}
+
+/// Context used by the `pages/upstream_oauth2/login_denied.html` template
+#[derive(Serialize)]
+pub struct UpstreamLoginDeniedContext {
+    provider: UpstreamOAuthProvider,
+    violations: Vec<UpstreamLoginDeniedViolation>,
+}
+
+impl UpstreamLoginDeniedContext {
+    /// Constructs a new context for a login denied through the given provider
+    #[must_use]
+    pub fn new(provider: UpstreamOAuthProvider) -> Self {
+        Self {
+            provider,
+            violations: Vec::new(),
+        }
+    }
+
+    /// Add a reason for which the login was denied, with the well-known policy
+    /// code and the claim it is about, if any
+    #[must_use]
+    pub fn with_violation(
+        mut self,
+        code: Option<&'static str>,
+        claim: Option<String>,
+        message: String,
+    ) -> Self {
+        self.violations.push(UpstreamLoginDeniedViolation {
+            code,
+            claim,
+            message,
+        });
+        self
+    }
+}
+
+impl TemplateContext for UpstreamLoginDeniedContext {
+    fn sample(now: chrono::DateTime<Utc>, _rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        let provider = sample_upstream_provider(now);
+        vec![
+            Self::new(provider.clone()).with_violation(
+                Some("upstream-claim-not-allowed"),
+                Some("groups".to_owned()),
+                "claim groups not allowed".to_owned(),
+            ),
+            Self::new(provider)
+                .with_violation(
+                    Some("upstream-claim-missing"),
+                    Some("email_verified".to_owned()),
+                    "missing claim email_verified".to_owned(),
+                )
+                .with_violation(None, None, "account is not allowed".to_owned()),
+        ]
+    }
+}
+
 /// Context used by the `pages/upstream_oauth2/suggest_link.html`
 /// templates
 #[derive(Serialize)]
@@ -1504,6 +3146,44 @@ impl UpstreamRegister {
     }
 }
 
+/// A sample upstream provider, used by the upstream OAuth 2.0 templates
+fn sample_upstream_provider(now: chrono::DateTime<Utc>) -> UpstreamOAuthProvider {
+    UpstreamOAuthProvider {
+        id: Ulid::nil(),
+        issuer: Some("https://example.com/".to_owned()),
+        human_name: Some("Example Ltd.".to_owned()),
+        brand_name: None,
+        scope: Scope::from_iter([OPENID]),
+        token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::ClientSecretBasic,
+        token_endpoint_signing_alg: None,
+        token_endpoint_signing_key_id: None,
+        tls_client_certificate: None,
+        encrypted_tls_client_key: None,
+        id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
+        client_id: "client-id".to_owned(),
+        encrypted_client_secret: None,
+        claims_imports: UpstreamOAuthProviderClaimsImports::default(),
+        authorization_endpoint_override: None,
+        token_endpoint_override: None,
+        jwks_uri_override: None,
+        userinfo_endpoint_override: None,
+        fetch_userinfo: false,
+        userinfo_signed_response_alg: None,
+        discovery_mode: UpstreamOAuthProviderDiscoveryMode::Oidc,
+        pkce_mode: UpstreamOAuthProviderPkceMode::Auto,
+        response_mode: None,
+        additional_authorization_parameters: Vec::new(),
+        forward_login_hint: false,
+        forward_prompt: false,
+        forward_acr_values: false,
+        forward_ui_locales: false,
+        store_tokens: false,
+        deprovisioning: None,
+        created_at: now,
+        disabled_at: None,
+    }
+}
+
 impl TemplateContext for UpstreamRegister {
     fn sample(now: chrono::DateTime<Utc>, _rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
     where
@@ -1516,34 +3196,10 @@ impl TemplateContext for UpstreamRegister {
                 user_id: None,
                 subject: "subject".to_owned(),
                 human_account_name: Some("@john".to_owned()),
+                imported_attributes: UpstreamOAuthLinkImportedAttributes::default(),
                 created_at: now,
             },
-            UpstreamOAuthProvider {
-                id: Ulid::nil(),
-                issuer: Some("https://example.com/".to_owned()),
-                human_name: Some("Example Ltd.".to_owned()),
-                brand_name: None,
-                scope: Scope::from_iter([OPENID]),
-                token_endpoint_auth_method: UpstreamOAuthProviderTokenAuthMethod::ClientSecretBasic,
-                token_endpoint_signing_alg: None,
-                id_token_signed_response_alg: JsonWebSignatureAlg::Rs256,
-                client_id: "client-id".to_owned(),
-                encrypted_client_secret: None,
-                claims_imports: UpstreamOAuthProviderClaimsImports::default(),
-                authorization_endpoint_override: None,
-                token_endpoint_override: None,
-                jwks_uri_override: None,
-                userinfo_endpoint_override: None,
-                fetch_userinfo: false,
-                userinfo_signed_response_alg: None,
-                discovery_mode: UpstreamOAuthProviderDiscoveryMode::Oidc,
-                pkce_mode: UpstreamOAuthProviderPkceMode::Auto,
-                response_mode: None,
-                additional_authorization_parameters: Vec::new(),
-                forward_login_hint: false,
-                created_at: now,
-                disabled_at: None,
-            },
+            sample_upstream_provider(now),
         )]
     }
 }
@@ -1565,16 +3221,28 @@ impl FormField for DeviceLinkFormField {
 }
 
 /// Context used by the `device_link.html` template
-#[derive(Serialize, Default, Debug)]
+#[derive(Serialize, Debug)]
 pub struct DeviceLinkContext {
     form_state: FormState<DeviceLinkFormField>,
+    code_length: usize,
+    verification_uri: Url,
 }
 
 impl DeviceLinkContext {
-    /// Constructs a new context with an existing linked user
+    /// Constructs a new context
+    ///
+    /// # Parameters
+    ///
+    /// * `code_length`: The length of the user codes issued by this server
+    /// * `verification_uri`: The URL of this page, rendered as a QR code to
+    ///   continue on another device
     #[must_use]
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(code_length: usize, verification_uri: Url) -> Self {
+        Self {
+            form_state: FormState::default(),
+            code_length,
+            verification_uri,
+        }
     }
 
     /// Set the form state
@@ -1594,9 +3262,10 @@ impl TemplateContext for DeviceLinkContext {
     where
         Self: Sized,
     {
+        let verification_uri = Url::parse("https://example.com/link").unwrap();
         vec![
-            Self::new(),
-            Self::new().with_form_state(
+            Self::new(6, verification_uri.clone()),
+            Self::new(8, verification_uri).with_form_state(
                 FormState::default()
                     .with_error_on_field(DeviceLinkFormField::Code, FieldError::Required),
             ),
@@ -1638,6 +3307,9 @@ impl TemplateContext for DeviceConsentContext {
                     expires_at: now + Duration::try_minutes(25).unwrap(),
                     ip_address: Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))),
                     user_agent: Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/93.0.0.0 Safari/537.36".to_owned()),
+                    device_fingerprint: None,
+                    interval: Duration::try_seconds(5).unwrap(),
+                    last_polled_at: None,
                 };
                 Self { grant, client }
             })
@@ -1645,18 +3317,67 @@ impl TemplateContext for DeviceConsentContext {
     }
 }
 
+/// Context used by the `backchannel_consent.html` template
+#[derive(Serialize, Debug)]
+pub struct BackchannelConsentContext {
+    grant: BackchannelAuthenticationGrant,
+    client: Client,
+}
+
+impl BackchannelConsentContext {
+    /// Constructs a new context for a backchannel authentication request
+    #[must_use]
+    pub fn new(grant: BackchannelAuthenticationGrant, client: Client) -> Self {
+        Self { grant, client }
+    }
+}
+
+impl TemplateContext for BackchannelConsentContext {
+    fn sample(now: chrono::DateTime<Utc>, rng: &mut impl Rng, _locales: &[DataLocale]) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        let users = User::samples(now, rng);
+        Client::samples(now, rng)
+            .into_iter()
+            .zip(users)
+            .map(|(client, user)| {
+                let grant = sample_backchannel_grant(now, rng, &client, &user);
+                Self { grant, client }
+            })
+            .collect()
+    }
+}
+
 /// Context used by the `account/deactivated.html` and `account/locked.html`
 /// templates
 #[derive(Serialize)]
 pub struct AccountInactiveContext {
     user: User,
+
+    /// The deletion requested by the user, if their account is deactivated
+    /// because of it
+    deletion: Option<UserDeletion>,
 }
 
 impl AccountInactiveContext {
     /// Constructs a new context with an existing linked user
     #[must_use]
     pub fn new(user: User) -> Self {
-        Self { user }
+        Self {
+            user,
+            deletion: None,
+        }
+    }
+
+    /// Add the deletion which deactivated the account, so that the user can
+    /// cancel it while it is still pending
+    #[must_use]
+    pub fn with_deletion(self, deletion: UserDeletion) -> Self {
+        Self {
+            deletion: Some(deletion),
+            ..self
+        }
     }
 }
 
@@ -1665,10 +3386,18 @@ impl TemplateContext for AccountInactiveContext {
     where
         Self: Sized,
     {
-        User::samples(now, rng)
-            .into_iter()
-            .map(|user| AccountInactiveContext { user })
-            .collect()
+        let users = User::samples(now, rng);
+        let deletions = UserDeletion::samples(now, rng);
+
+        let mut samples: Vec<Self> = users.iter().cloned().map(Self::new).collect();
+        if let Some(user) = users.into_iter().next() {
+            samples.extend(
+                deletions
+                    .into_iter()
+                    .map(|deletion| Self::new(user.clone()).with_deletion(deletion)),
+            );
+        }
+        samples
     }
 }
 
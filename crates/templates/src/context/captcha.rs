@@ -27,6 +27,7 @@ impl Object for CaptchaConfig {
                     "cloudflare_turnstile".into()
                 }
                 mas_data_model::CaptchaService::HCaptcha => "hcaptcha".into(),
+                mas_data_model::CaptchaService::ProofOfWork => "proof_of_work".into(),
             }),
             Some("site_key") => Some(self.0.site_key.clone().into()),
             _ => None,
@@ -47,7 +47,12 @@ impl SiteConfigExt for SiteConfig {
             password_registration: self.password_registration_enabled,
             password_login: self.password_login_enabled,
             account_recovery: self.account_recovery_allowed,
+            account_recovery_approval: self.account_recovery_requires_approval,
             login_with_email_allowed: self.login_with_email_allowed,
+            login_with_phone_number_allowed: self.login_with_phone_number_allowed,
+            passkeys: self.passkeys_enabled,
+            email_code_login: self.email_code_login_enabled,
+            trusted_devices: self.trusted_device_ttl.is_some(),
         }
     }
 }
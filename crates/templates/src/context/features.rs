@@ -24,8 +24,24 @@ pub struct SiteFeatures {
     /// Whether email-based account recovery is enabled.
     pub account_recovery: bool,
 
+    /// Whether account recovery requests have to be approved by an
+    /// administrator.
+    pub account_recovery_approval: bool,
+
     /// Whether users can log in with their email address.
     pub login_with_email_allowed: bool,
+
+    /// Whether users can log in with their phone number.
+    pub login_with_phone_number_allowed: bool,
+
+    /// Whether users can log in with a passkey.
+    pub passkeys: bool,
+
+    /// Whether users can log in with a code sent to their email address.
+    pub email_code_login: bool,
+
+    /// Whether users can trust a browser after entering their second factor.
+    pub trusted_devices: bool,
 }
 
 impl Object for SiteFeatures {
@@ -34,7 +50,14 @@ impl Object for SiteFeatures {
             "password_registration" => Some(Value::from(self.password_registration)),
             "password_login" => Some(Value::from(self.password_login)),
             "account_recovery" => Some(Value::from(self.account_recovery)),
+            "account_recovery_approval" => Some(Value::from(self.account_recovery_approval)),
             "login_with_email_allowed" => Some(Value::from(self.login_with_email_allowed)),
+            "login_with_phone_number_allowed" => {
+                Some(Value::from(self.login_with_phone_number_allowed))
+            }
+            "passkeys" => Some(Value::from(self.passkeys)),
+            "email_code_login" => Some(Value::from(self.email_code_login)),
+            "trusted_devices" => Some(Value::from(self.trusted_devices)),
             _ => None,
         }
     }
@@ -44,7 +67,12 @@ impl Object for SiteFeatures {
             "password_registration",
             "password_login",
             "account_recovery",
+            "account_recovery_approval",
             "login_with_email_allowed",
+            "login_with_phone_number_allowed",
+            "passkeys",
+            "email_code_login",
+            "trusted_devices",
         ])
     }
 }
@@ -25,6 +25,7 @@ use minijinja::{
     machinery::make_string_output,
     value::{Kwargs, Object, ViaDeserialize, from_args},
 };
+use qrcode::{QrCode, render::svg};
 use url::Url;
 
 pub fn register(
@@ -43,6 +44,7 @@ pub fn register(
     env.add_filter("parse_user_agent", filter_parse_user_agent);
     env.add_function("add_params_to_url", function_add_params_to_url);
     env.add_function("counter", || Ok(Value::from_object(Counter::default())));
+    env.add_function("qr_code", function_qr_code);
     env.add_global(
         "include_asset",
         Value::from_object(IncludeAsset {
@@ -140,6 +142,26 @@ fn filter_parse_user_agent(user_agent: String) -> Value {
     Value::from_serialize(user_agent)
 }
 
+/// Function which renders some data as an inline SVG QR code
+fn function_qr_code(data: &str) -> Result<Value, Error> {
+    let code = QrCode::new(data.as_bytes()).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidOperation,
+            "Could not encode data as a QR code",
+        )
+        .with_source(e)
+    })?;
+
+    let image = code
+        .render::<svg::Color<'_>>()
+        .min_dimensions(200, 200)
+        .dark_color(svg::Color("currentColor"))
+        .light_color(svg::Color("transparent"))
+        .build();
+
+    Ok(Value::from_safe_string(image))
+}
+
 enum ParamsWhere {
     Fragment,
     Query,
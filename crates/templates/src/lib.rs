@@ -34,19 +34,30 @@ mod macros;
 
 pub use self::{
     context::{
-        AccountInactiveContext, ApiDocContext, AppContext, CompatSsoContext, ConsentContext,
+        AcceptTermsContext, AccountClaimContext, AccountClaimFormField, AccountInactiveContext,
+        ApiDocContext, AppContext, BackchannelConsentContext, CompatSsoContext, ConsentContext,
         DeviceConsentContext, DeviceLinkContext, DeviceLinkFormField, DeviceNameContext,
-        EmailRecoveryContext, EmailVerificationContext, EmptyContext, ErrorContext,
-        FormPostContext, IndexContext, LoginContext, LoginFormField, NotFoundContext,
-        PasswordRegisterContext, PolicyViolationContext, PostAuthContext, PostAuthContextInner,
-        RecoveryExpiredContext, RecoveryFinishContext, RecoveryFinishFormField,
-        RecoveryProgressContext, RecoveryStartContext, RecoveryStartFormField, RegisterContext,
-        RegisterFormField, RegisterStepsDisplayNameContext, RegisterStepsDisplayNameFormField,
+        EmailBackchannelContext, EmailClaimContext, EmailRecoveryContext,
+        EmailRecoveryReviewContext, EmailSignInContext, EmailVerificationContext, EmptyContext,
+        ErrorContext, FormPostContext, IndexContext, InvitesContext, LoginConfirmEmailContext,
+        LoginConfirmEmailFormField, LoginContext, LoginEmailCodeContext, LoginEmailCodeFormField,
+        LoginEmailContext, LoginEmailFormField, LoginEmailLinkContext, LoginFormField,
+        LoginPasswordChangeContext, LoginPasswordChangeFormField, LoginRecoveryCodeContext,
+        LoginRecoveryCodeFormField, LoginSmsContext, LoginSmsFormField, LoginTotpContext,
+        LoginTotpFormField, LoginTotpRecoveryCodesContext, NotFoundContext, NotificationsContext,
+        PasskeyLoginChallenge, PasswordRegisterContext, PolicyViolationContext, PostAuthContext,
+        PostAuthContextInner, ReauthContext, ReauthFormField, RecoveryExpiredContext,
+        RecoveryFinishContext, RecoveryFinishFormField, RecoveryProgressContext,
+        RecoveryStartContext, RecoveryStartFormField, RegisterContext, RegisterFormField,
+        RegisterStepsDisplayNameContext, RegisterStepsDisplayNameFormField,
         RegisterStepsEmailInUseContext, RegisterStepsRegistrationTokenContext,
         RegisterStepsRegistrationTokenFormField, RegisterStepsVerifyEmailContext,
         RegisterStepsVerifyEmailFormField, SiteBranding, SiteConfigExt, SiteFeatures,
-        TemplateContext, UpstreamExistingLinkContext, UpstreamRegister, UpstreamRegisterFormField,
-        UpstreamSuggestLink, WithCaptcha, WithCsrf, WithLanguage, WithOptionalSession, WithSession,
+        SmsVerificationContext, TemplateContext, TotpEnrollment,
+        UpstreamEmailDomainNotAllowedContext, UpstreamExistingLinkContext,
+        UpstreamLoginDeniedContext, UpstreamRegister, UpstreamRegisterFormField,
+        UpstreamSuggestLink, UserActionContext, WithCaptcha, WithCsrf, WithLanguage,
+        WithOptionalSession, WithSession,
     },
     forms::{FieldError, FormError, FormField, FormState, ToFormState},
 };
@@ -324,7 +335,49 @@ register_templates! {
     pub fn render_swagger_callback(ApiDocContext) { "swagger/oauth2-redirect.html" }
 
     /// Render the login page
-    pub fn render_login(WithLanguage<WithCsrf<LoginContext>>) { "pages/login.html" }
+    pub fn render_login(WithLanguage<WithCsrf<WithCaptcha<LoginContext>>>) { "pages/login.html" }
+
+    /// Render the TOTP second factor page of the login
+    pub fn render_login_totp(WithLanguage<WithCsrf<LoginTotpContext>>) { "pages/login_totp.html" }
+
+    /// Render the recovery codes generated when adding a second factor during the login
+    pub fn render_login_totp_recovery_codes(WithLanguage<LoginTotpRecoveryCodesContext>) { "pages/login_totp_recovery_codes.html" }
+
+    /// Render the recovery code page of the login
+    pub fn render_login_recovery_code(WithLanguage<WithCsrf<LoginRecoveryCodeContext>>) { "pages/login_recovery_code.html" }
+
+    /// Render the page asking for the code sent by SMS after a password login
+    pub fn render_login_sms(WithLanguage<WithCsrf<LoginSmsContext>>) { "pages/login_sms.html" }
+
+    /// Render the page asking for the code sent by email to confirm a suspicious login
+    pub fn render_login_confirm_email(WithLanguage<WithCsrf<LoginConfirmEmailContext>>) { "pages/login_confirm_email.html" }
+
+    /// Render the page asking for a new password after a login with an expired one
+    pub fn render_login_password_change(WithLanguage<WithCsrf<LoginPasswordChangeContext>>) { "pages/login_password_change.html" }
+
+    /// Render the page to start a login with a code sent by email
+    pub fn render_login_email(WithLanguage<WithCsrf<LoginEmailContext>>) { "pages/login_email.html" }
+
+    /// Render the page where the user enters the login code sent by email
+    pub fn render_login_email_code(WithLanguage<WithCsrf<LoginEmailCodeContext>>) { "pages/login_email_code.html" }
+
+    /// Render the page confirming a login with a link sent by email
+    pub fn render_login_email_link(WithLanguage<WithCsrf<LoginEmailLinkContext>>) { "pages/login_email_link.html" }
+
+    /// Render the page shown when a login link sent by email can't be used
+    pub fn render_login_email_link_invalid(WithLanguage<EmptyContext>) { "pages/login_email_link_invalid.html" }
+
+    /// Render the page where users issue invite codes
+    pub fn render_invites(WithLanguage<WithCsrf<WithSession<InvitesContext>>>) { "pages/invites.html" }
+
+    /// Render the page where users choose which emails they get
+    pub fn render_notifications(WithLanguage<WithCsrf<WithSession<NotificationsContext>>>) { "pages/notifications.html" }
+
+    /// Render the page asking users to accept new versions of the terms
+    pub fn render_accept_terms(WithLanguage<WithCsrf<WithSession<AcceptTermsContext>>>) { "pages/accept_terms.html" }
+
+    /// Render the reauthentication page
+    pub fn render_reauth(WithLanguage<WithCsrf<WithSession<ReauthContext>>>) { "pages/reauth.html" }
 
     /// Render the registration page
     pub fn render_register(WithLanguage<WithCsrf<RegisterContext>>) { "pages/register/index.html" }
@@ -374,6 +427,21 @@ register_templates! {
     /// Render the account recovery disabled page
     pub fn render_recovery_disabled(WithLanguage<EmptyContext>) { "pages/recovery/disabled.html" }
 
+    /// Render the account claim page
+    pub fn render_account_claim(WithLanguage<WithCsrf<AccountClaimContext>>) { "pages/claim/index.html" }
+
+    /// Render the page shown when an account claim link can't be used
+    pub fn render_account_claim_invalid(WithLanguage<EmptyContext>) { "pages/claim/invalid.html" }
+
+    /// Render the page asking to confirm the action of a user action link
+    pub fn render_user_action(WithLanguage<WithCsrf<UserActionContext>>) { "pages/user_action/index.html" }
+
+    /// Render the page shown when a user action link can't be used
+    pub fn render_user_action_invalid(WithLanguage<EmptyContext>) { "pages/user_action/invalid.html" }
+
+    /// Render the page shown once the action of a user action link was performed
+    pub fn render_user_action_done(WithLanguage<EmptyContext>) { "pages/user_action/done.html" }
+
     /// Render the form used by the form_post response mode
     pub fn render_form_post<T: Serialize>(WithLanguage<FormPostContext<T>>) { "form_post.html" }
 
@@ -389,6 +457,42 @@ register_templates! {
     /// Render the email recovery subject
     pub fn render_email_recovery_subject(WithLanguage<EmailRecoveryContext>) { "emails/recovery.subject" }
 
+    /// Render the recovery review email (plain text variant)
+    pub fn render_email_recovery_review_txt(WithLanguage<EmailRecoveryReviewContext>) { "emails/recovery_review.txt" }
+
+    /// Render the recovery review email (HTML text variant)
+    pub fn render_email_recovery_review_html(WithLanguage<EmailRecoveryReviewContext>) { "emails/recovery_review.html" }
+
+    /// Render the recovery review email subject
+    pub fn render_email_recovery_review_subject(WithLanguage<EmailRecoveryReviewContext>) { "emails/recovery_review.subject" }
+
+    /// Render the account claim email (plain text variant)
+    pub fn render_email_claim_txt(WithLanguage<EmailClaimContext>) { "emails/claim.txt" }
+
+    /// Render the account claim email (HTML text variant)
+    pub fn render_email_claim_html(WithLanguage<EmailClaimContext>) { "emails/claim.html" }
+
+    /// Render the account claim email subject
+    pub fn render_email_claim_subject(WithLanguage<EmailClaimContext>) { "emails/claim.subject" }
+
+    /// Render the backchannel authentication email (plain text variant)
+    pub fn render_email_backchannel_txt(WithLanguage<EmailBackchannelContext>) { "emails/backchannel.txt" }
+
+    /// Render the backchannel authentication email (HTML text variant)
+    pub fn render_email_backchannel_html(WithLanguage<EmailBackchannelContext>) { "emails/backchannel.html" }
+
+    /// Render the backchannel authentication email subject
+    pub fn render_email_backchannel_subject(WithLanguage<EmailBackchannelContext>) { "emails/backchannel.subject" }
+
+    /// Render the sign-in notification email (plain text variant)
+    pub fn render_email_sign_in_txt(WithLanguage<EmailSignInContext>) { "emails/sign_in.txt" }
+
+    /// Render the sign-in notification email (HTML text variant)
+    pub fn render_email_sign_in_html(WithLanguage<EmailSignInContext>) { "emails/sign_in.html" }
+
+    /// Render the sign-in notification email subject
+    pub fn render_email_sign_in_subject(WithLanguage<EmailSignInContext>) { "emails/sign_in.subject" }
+
     /// Render the email verification email (plain text variant)
     pub fn render_email_verification_txt(WithLanguage<EmailVerificationContext>) { "emails/verification.txt" }
 
@@ -398,9 +502,18 @@ register_templates! {
     /// Render the email verification subject
     pub fn render_email_verification_subject(WithLanguage<EmailVerificationContext>) { "emails/verification.subject" }
 
+    /// Render the text message containing a phone verification code
+    pub fn render_sms_verification(WithLanguage<SmsVerificationContext>) { "sms/verification.txt" }
+
     /// Render the upstream link mismatch message
     pub fn render_upstream_oauth2_link_mismatch(WithLanguage<WithCsrf<WithSession<UpstreamExistingLinkContext>>>) { "pages/upstream_oauth2/link_mismatch.html" }
 
+    /// Render the page shown when an upstream provider asserts an email address it isn't allowed to
+    pub fn render_upstream_oauth2_email_domain_not_allowed(WithLanguage<UpstreamEmailDomainNotAllowedContext>) { "pages/upstream_oauth2/email_domain_not_allowed.html" }
+
+    /// Render the page shown when the policy denies a login through an upstream provider
+    pub fn render_upstream_oauth2_login_denied(WithLanguage<UpstreamLoginDeniedContext>) { "pages/upstream_oauth2/login_denied.html" }
+
     /// Render the upstream suggest link message
     pub fn render_upstream_oauth2_suggest_link(WithLanguage<WithCsrf<WithSession<UpstreamSuggestLink>>>) { "pages/upstream_oauth2/suggest_link.html" }
 
@@ -413,6 +526,9 @@ register_templates! {
     /// Render the device code consent page
     pub fn render_device_consent(WithLanguage<WithCsrf<WithSession<DeviceConsentContext>>>) { "pages/device_consent.html" }
 
+    /// Render the backchannel authentication consent page
+    pub fn render_backchannel_consent(WithLanguage<WithCsrf<WithSession<BackchannelConsentContext>>>) { "pages/backchannel_consent.html" }
+
     /// Render the 'account deactivated' page
     pub fn render_account_deactivated(WithLanguage<WithCsrf<AccountInactiveContext>>) { "pages/account/deactivated.html" }
 
@@ -443,6 +559,20 @@ impl Templates {
         check::render_swagger(self, now, rng)?;
         check::render_swagger_callback(self, now, rng)?;
         check::render_login(self, now, rng)?;
+        check::render_login_totp(self, now, rng)?;
+        check::render_login_totp_recovery_codes(self, now, rng)?;
+        check::render_login_recovery_code(self, now, rng)?;
+        check::render_login_sms(self, now, rng)?;
+        check::render_login_confirm_email(self, now, rng)?;
+        check::render_login_password_change(self, now, rng)?;
+        check::render_login_email(self, now, rng)?;
+        check::render_login_email_code(self, now, rng)?;
+        check::render_login_email_link(self, now, rng)?;
+        check::render_login_email_link_invalid(self, now, rng)?;
+        check::render_invites(self, now, rng)?;
+        check::render_notifications(self, now, rng)?;
+        check::render_accept_terms(self, now, rng)?;
+        check::render_reauth(self, now, rng)?;
         check::render_register(self, now, rng)?;
         check::render_password_register(self, now, rng)?;
         check::render_register_steps_verify_email(self, now, rng)?;
@@ -459,19 +589,40 @@ impl Templates {
         check::render_recovery_expired(self, now, rng)?;
         check::render_recovery_consumed(self, now, rng)?;
         check::render_recovery_disabled(self, now, rng)?;
+        check::render_account_claim(self, now, rng)?;
+        check::render_account_claim_invalid(self, now, rng)?;
+        check::render_user_action(self, now, rng)?;
+        check::render_user_action_invalid(self, now, rng)?;
+        check::render_user_action_done(self, now, rng)?;
         check::render_form_post::<EmptyContext>(self, now, rng)?;
         check::render_error(self, now, rng)?;
         check::render_email_recovery_txt(self, now, rng)?;
         check::render_email_recovery_html(self, now, rng)?;
         check::render_email_recovery_subject(self, now, rng)?;
+        check::render_email_recovery_review_txt(self, now, rng)?;
+        check::render_email_recovery_review_html(self, now, rng)?;
+        check::render_email_recovery_review_subject(self, now, rng)?;
+        check::render_email_claim_txt(self, now, rng)?;
+        check::render_email_claim_html(self, now, rng)?;
+        check::render_email_claim_subject(self, now, rng)?;
+        check::render_email_backchannel_txt(self, now, rng)?;
+        check::render_email_backchannel_html(self, now, rng)?;
+        check::render_email_backchannel_subject(self, now, rng)?;
+        check::render_email_sign_in_txt(self, now, rng)?;
+        check::render_email_sign_in_html(self, now, rng)?;
+        check::render_email_sign_in_subject(self, now, rng)?;
         check::render_email_verification_txt(self, now, rng)?;
         check::render_email_verification_html(self, now, rng)?;
         check::render_email_verification_subject(self, now, rng)?;
+        check::render_sms_verification(self, now, rng)?;
         check::render_upstream_oauth2_link_mismatch(self, now, rng)?;
+        check::render_upstream_oauth2_email_domain_not_allowed(self, now, rng)?;
+        check::render_upstream_oauth2_login_denied(self, now, rng)?;
         check::render_upstream_oauth2_suggest_link(self, now, rng)?;
         check::render_upstream_oauth2_do_register(self, now, rng)?;
         check::render_device_link(self, now, rng)?;
         check::render_device_consent(self, now, rng)?;
+        check::render_backchannel_consent(self, now, rng)?;
         check::render_account_deactivated(self, now, rng)?;
         check::render_account_locked(self, now, rng)?;
         check::render_account_logged_out(self, now, rng)?;
@@ -498,7 +649,12 @@ mod tests {
             password_login: true,
             password_registration: true,
             account_recovery: true,
+            account_recovery_approval: true,
             login_with_email_allowed: true,
+            login_with_phone_number_allowed: false,
+            passkeys: true,
+            email_code_login: true,
+            trusted_devices: true,
         };
         let vite_manifest_path =
             Utf8Path::new(env!("CARGO_MANIFEST_DIR")).join("../../frontend/dist/manifest.json");
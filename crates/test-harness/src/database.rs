@@ -0,0 +1,96 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Temporary databases, created for the lifetime of a test.
+
+use mas_storage::{Clock, SystemClock};
+use sqlx::{
+    Connection, Executor, PgConnection, PgPool,
+    postgres::{PgConnectOptions, PgPoolOptions},
+};
+use ulid::Ulid;
+
+/// A temporary PostgreSQL database, with all the migrations applied
+///
+/// The database is created on the server the given URL points to, with a
+/// random name. It has to be explicitly dropped with [`TempDatabase::drop`]
+/// once the test is done, as this can't be done in a [`Drop`] implementation.
+///
+/// Only PostgreSQL is supported for now, as it is the only storage backend of
+/// the service. SQLite databases will be supported once the service has a
+/// storage backend for it.
+pub struct TempDatabase {
+    options: PgConnectOptions,
+    name: String,
+    pool: PgPool,
+}
+
+impl TempDatabase {
+    /// Create a new temporary database and run the migrations on it
+    ///
+    /// # Parameters
+    ///
+    /// * `database_url`: The URL of the PostgreSQL server to use. The user must
+    ///   be allowed to create databases.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL is invalid, if the database could not be
+    /// created, or if the migrations failed to run
+    pub async fn create(database_url: &str) -> Result<Self, sqlx::Error> {
+        let options: PgConnectOptions = database_url.parse()?;
+
+        // This rng is only used to get a unique database name
+        #[allow(clippy::disallowed_methods)]
+        let mut rng = rand::thread_rng();
+        let id = Ulid::from_datetime_with_source(SystemClock::default().now().into(), &mut rng);
+        let name = format!("mas_test_{}", id.to_string().to_lowercase());
+
+        let mut conn = PgConnection::connect_with(&options).await?;
+        conn.execute(format!(r#"CREATE DATABASE "{name}""#).as_str())
+            .await?;
+        conn.close().await?;
+
+        let pool = PgPoolOptions::new()
+            .connect_with(options.clone().database(&name))
+            .await?;
+
+        mas_storage_pg::MIGRATOR.run(&pool).await?;
+
+        Ok(Self {
+            options,
+            name,
+            pool,
+        })
+    }
+
+    /// The name of the database
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get a connection pool to the database
+    #[must_use]
+    pub fn pool(&self) -> PgPool {
+        self.pool.clone()
+    }
+
+    /// Close all the connections to the database and drop it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database could not be dropped
+    pub async fn drop(self) -> Result<(), sqlx::Error> {
+        self.pool.close().await;
+
+        let mut conn = PgConnection::connect_with(&self.options).await?;
+        conn.execute(format!(r#"DROP DATABASE IF EXISTS "{}" WITH (FORCE)"#, self.name).as_str())
+            .await?;
+        conn.close().await?;
+
+        Ok(())
+    }
+}
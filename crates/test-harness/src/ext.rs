@@ -0,0 +1,133 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Helpers to build requests and inspect responses.
+
+use headers::{Authorization, ContentType, HeaderMapExt, HeaderName};
+use hyper::{Response, StatusCode, header::CONTENT_TYPE};
+use serde::{Serialize, de::DeserializeOwned};
+
+/// Extension trait to build requests to send to the [`TestHarness`]
+///
+/// [`TestHarness`]: crate::TestHarness
+pub trait RequestBuilderExt {
+    /// Builds the request with the given JSON value as body.
+    fn json<T: Serialize>(self, body: T) -> hyper::Request<String>;
+
+    /// Builds the request with the given form value as body.
+    fn form<T: Serialize>(self, body: T) -> hyper::Request<String>;
+
+    /// Sets the request Authorization header to the given bearer token.
+    fn bearer(self, token: &str) -> Self;
+
+    /// Sets the request Authorization header to the given basic auth
+    /// credentials.
+    fn basic_auth(self, username: &str, password: &str) -> Self;
+
+    /// Builds the request with an empty body.
+    fn empty(self) -> hyper::Request<String>;
+}
+
+impl RequestBuilderExt for hyper::http::request::Builder {
+    fn json<T: Serialize>(mut self, body: T) -> hyper::Request<String> {
+        self.headers_mut()
+            .unwrap()
+            .typed_insert(ContentType::json());
+
+        self.body(serde_json::to_string(&body).unwrap()).unwrap()
+    }
+
+    fn form<T: Serialize>(mut self, body: T) -> hyper::Request<String> {
+        self.headers_mut()
+            .unwrap()
+            .typed_insert(ContentType::form_url_encoded());
+
+        self.body(serde_urlencoded::to_string(&body).unwrap())
+            .unwrap()
+    }
+
+    fn bearer(mut self, token: &str) -> Self {
+        self.headers_mut()
+            .unwrap()
+            .typed_insert(Authorization::bearer(token).unwrap());
+        self
+    }
+
+    fn basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.headers_mut()
+            .unwrap()
+            .typed_insert(Authorization::basic(username, password));
+        self
+    }
+
+    fn empty(self) -> hyper::Request<String> {
+        self.body(String::new()).unwrap()
+    }
+}
+
+/// Extension trait to make assertions on responses returned by the
+/// [`TestHarness`]
+///
+/// [`TestHarness`]: crate::TestHarness
+pub trait ResponseExt {
+    /// Asserts that the response has the given status code.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the response has a different status code.
+    fn assert_status(&self, status: StatusCode);
+
+    /// Asserts that the response has the given header value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the response does not have the given header or if the header
+    /// value does not match.
+    fn assert_header_value(&self, header: HeaderName, value: &str);
+
+    /// Get the response body as JSON.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the response is missing the `Content-Type: application/json`,
+    /// or if the body is not valid JSON.
+    fn json<T: DeserializeOwned>(&self) -> T;
+}
+
+impl ResponseExt for Response<String> {
+    #[track_caller]
+    fn assert_status(&self, status: StatusCode) {
+        assert_eq!(
+            self.status(),
+            status,
+            "HTTP status code mismatch: got {}, expected {}. Body: {}",
+            self.status(),
+            status,
+            self.body()
+        );
+    }
+
+    #[track_caller]
+    fn assert_header_value(&self, header: HeaderName, value: &str) {
+        let actual_value = self
+            .headers()
+            .get(&header)
+            .unwrap_or_else(|| panic!("Missing header {header}"));
+
+        assert_eq!(
+            actual_value,
+            value,
+            "Header mismatch: got {:?}, expected {:?}",
+            self.headers().get(header),
+            value
+        );
+    }
+
+    #[track_caller]
+    fn json<T: DeserializeOwned>(&self) -> T {
+        self.assert_header_value(CONTENT_TYPE, "application/json");
+        serde_json::from_str(self.body()).expect("JSON deserialization failed")
+    }
+}
@@ -0,0 +1,900 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use axum::{
+    Router,
+    body::{Bytes, HttpBody},
+};
+use camino::Utf8PathBuf;
+use chrono::Duration;
+use hyper::{Request, Response, StatusCode};
+use mas_config::RateLimitingConfig;
+use mas_data_model::{AuthorizationCode, SiteConfig, TokenType, TotpPolicy, User};
+use mas_handlers::{
+    ActivityTracker, ClientJwksCache, CookieManager, FeatureFlags, GeoIpResolver,
+    IntrospectionCache, Limiter, MetadataCache, ProofOfWorkReplayCache, ProviderCircuitBreaker,
+    ProviderHealthTracker,
+    passwords::{Hasher, PasswordManager},
+};
+use mas_keystore::{Encrypter, JsonWebKey, JsonWebKeySet, Keystore, PrivateKey};
+use mas_matrix::{HomeserverConnection, MockHomeserverConnection, ProvisionRequest};
+use mas_policy::PolicyFactory;
+use mas_router::{SimpleRoute, UrlBuilder};
+use mas_storage::{
+    BoxRepository, RepositoryAccess, RepositoryError, RepositoryFactory, SystemClock,
+    oauth2::OAuth2AuthorizationGrantParams,
+};
+use mas_storage_pg::PgRepositoryFactory;
+use mas_templates::{SiteConfigExt, Templates};
+use oauth2_types::{
+    registration::ClientRegistrationResponse,
+    requests::{AccessTokenResponse, ResponseMode},
+    scope::Scope,
+};
+use rand::{
+    SeedableRng,
+    distributions::{Alphanumeric, DistString},
+};
+use rand_chacha::ChaChaRng;
+use sqlx::PgPool;
+use tokio_util::{
+    sync::{CancellationToken, DropGuard},
+    task::TaskTracker,
+};
+use tower::{Service, ServiceExt};
+use url::Url;
+use zeroize::Zeroizing;
+
+use crate::{
+    database::TempDatabase,
+    ext::{RequestBuilderExt, ResponseExt},
+    state::HarnessState,
+};
+
+/// The redirect URI used by the clients registered by
+/// [`TestHarness::authorization_code_flow`]
+const REDIRECT_URI: &str = "https://client.example.com/callback";
+
+/// Options to start a [`TestHarness`]
+#[derive(Clone)]
+pub struct HarnessOptions {
+    /// The site configuration of the service
+    pub site_config: SiteConfig,
+
+    /// Whether to also serve the service over HTTP on a random local port.
+    ///
+    /// This is needed when the code under test talks to the service over the
+    /// network. The URL of the service is then available through
+    /// [`TestHarness::base_url`].
+    pub listen: bool,
+
+    /// The directory containing the templates, translations, frontend
+    /// manifest and policy of the service.
+    ///
+    /// Defaults to the root of the repository this crate is built from.
+    pub resources_dir: Utf8PathBuf,
+}
+
+impl Default for HarnessOptions {
+    fn default() -> Self {
+        Self {
+            site_config: TestHarness::default_site_config(),
+            listen: false,
+            resources_dir: camino::Utf8Path::new(env!("CARGO_MANIFEST_DIR")).join("../.."),
+        }
+    }
+}
+
+/// An instance of the service, running in-process against a real database
+///
+/// Requests can be sent directly to the service with [`TestHarness::request`],
+/// without going through the network. The homeserver is mocked, and can be
+/// inspected through [`TestHarness::homeserver`].
+///
+/// It uses the system clock and a real random number generator, so that
+/// tokens expire and sessions are recorded as they would in production.
+pub struct TestHarness {
+    state: HarnessState,
+    resources_dir: Utf8PathBuf,
+    base_url: Url,
+    database: Option<TempDatabase>,
+    task_tracker: TaskTracker,
+    cancellation_drop_guard: DropGuard,
+}
+
+impl TestHarness {
+    /// The site configuration used by default by the harness
+    ///
+    /// Password login and registration are enabled, and the server name is
+    /// `example.com`.
+    #[must_use]
+    pub fn default_site_config() -> SiteConfig {
+        SiteConfig {
+            access_token_ttl: Duration::minutes(5),
+            compat_token_ttl: Duration::minutes(5),
+            server_name: "example.com".to_owned(),
+            policy_uri: None,
+            tos_uri: None,
+            imprint: None,
+            password_login_enabled: true,
+            password_registration_enabled: true,
+            registration_token_required: false,
+            email_change_allowed: true,
+            displayname_change_allowed: true,
+            avatar_change_allowed: true,
+            password_change_allowed: true,
+            account_recovery_allowed: true,
+            account_recovery_requires_approval: false,
+            account_deactivation_allowed: true,
+        account_deletion_grace_period: None,
+            captcha: None,
+            minimum_password_complexity: 1,
+            password_lockout: None,
+            password_max_age: None,
+            password_history_size: 0,
+            session_expiration: None,
+            login_with_email_allowed: true,
+            login_with_phone_number_allowed: false,
+            passkeys_enabled: false,
+            email_code_login_enabled: false,
+            totp_policy: TotpPolicy::Disabled,
+            phone_number_change_allowed: false,
+            sms_second_factor_enabled: false,
+            trusted_device_ttl: None,
+            sudo_mode_ttl: None,
+            user_invites: None,
+            home_realm_discovery: None,
+            single_provider_auto_redirect: true,
+            terms_documents: Vec::new(),
+            sign_in_notifications_enabled: false,
+            plan_management_iframe_uri: None,
+            scim_client: None,
+            user_attributes: Vec::new(),
+            authorization_details_types: Vec::new(),
+            software_statement_issuers: Vec::new(),
+            software_statement_required: false,
+            acr_values: Vec::new(),
+            step_up_scopes: Vec::new(),
+            step_up_ttl: Duration::minutes(5),
+            device_code_user_code_length: 6,
+            device_code_user_code_alphabet: "ABCDEFGHJKLMNPQRSTUVWXYZ23456789".to_owned(),
+            oauth2_1_strict: false,
+        }
+    }
+
+    /// Start the service on a new temporary database
+    ///
+    /// The database is dropped by [`TestHarness::shutdown`].
+    ///
+    /// # Parameters
+    ///
+    /// * `database_url`: The URL of the PostgreSQL server on which to create
+    ///   the temporary database
+    /// * `options`: The options of the harness
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database could not be created, or if the
+    /// service failed to start
+    pub async fn start(database_url: &str, options: HarnessOptions) -> Result<Self, anyhow::Error> {
+        let database = TempDatabase::create(database_url)
+            .await
+            .context("could not create the temporary database")?;
+
+        let mut harness = Self::from_pool(database.pool(), options).await?;
+        harness.database = Some(database);
+        Ok(harness)
+    }
+
+    /// Start the service on an existing database
+    ///
+    /// The migrations must have been applied on the database, which is the
+    /// case with `#[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the service failed to start
+    pub async fn from_pool(pool: PgPool, options: HarnessOptions) -> Result<Self, anyhow::Error> {
+        let HarnessOptions {
+            site_config,
+            listen,
+            resources_dir,
+        } = options;
+        let resources_dir = resources_dir
+            .canonicalize_utf8()
+            .context("could not find the resources directory")?;
+
+        let task_tracker = TaskTracker::new();
+        let shutdown_token = CancellationToken::new();
+
+        // Bind the listener first, as the public base of the service depends on it
+        let listener = if listen {
+            Some(tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?)
+        } else {
+            None
+        };
+
+        let base_url: Url = match &listener {
+            Some(listener) => format!("http://{}/", listener.local_addr()?).parse()?,
+            None => "https://example.com/".parse()?,
+        };
+
+        let url_builder = UrlBuilder::new(base_url.clone(), None, None);
+
+        let templates = Templates::load(
+            resources_dir.join("templates"),
+            url_builder.clone(),
+            resources_dir.join("frontend/dist/manifest.json"),
+            resources_dir.join("translations"),
+            site_config.templates_branding(),
+            site_config.templates_features(),
+        )
+        .await?;
+
+        let http_client = mas_http::reqwest_client();
+
+        let rsa = PrivateKey::load_pem(include_str!("../../keystore/tests/keys/rsa.pkcs1.pem"))?;
+        let rsa = JsonWebKey::new(rsa).with_kid("test-rsa");
+
+        let jwks = JsonWebKeySet::new(vec![rsa]);
+        let key_store = Keystore::new(jwks);
+
+        let encrypter = Encrypter::new(&[0x42; 32]);
+        let cookie_manager = CookieManager::derive_from(url_builder.http_base(), &[0x42; 32]);
+
+        let metadata_cache = MetadataCache::new();
+        let introspection_cache = IntrospectionCache::default();
+        let client_jwks_cache = ClientJwksCache::new();
+
+        let password_manager = if site_config.password_login_enabled {
+            PasswordManager::new(
+                site_config.minimum_password_complexity,
+                [(1, Hasher::argon2id(None, false))],
+            )?
+        } else {
+            PasswordManager::disabled()
+        };
+
+        let policy_factory = load_policy_factory(
+            &resources_dir,
+            &site_config.server_name,
+            serde_json::json!({}),
+        )
+        .await?;
+
+        let homeserver_connection =
+            Arc::new(MockHomeserverConnection::new(&site_config.server_name));
+
+        let limiter = Limiter::new(&RateLimitingConfig::default())
+            .context("invalid rate limiting configuration")?;
+
+        let graphql_schema = mas_handlers::graphql_schema(
+            PgRepositoryFactory::new(pool.clone()).boxed(),
+            &policy_factory,
+            Arc::clone(&homeserver_connection),
+            site_config.clone(),
+            password_manager.clone(),
+            url_builder.clone(),
+            limiter.clone(),
+            encrypter.clone(),
+        );
+
+        let activity_tracker = ActivityTracker::new(
+            PgRepositoryFactory::new(pool.clone()).boxed(),
+            GeoIpResolver::disabled(),
+            std::time::Duration::from_secs(60),
+            &task_tracker,
+            shutdown_token.child_token(),
+        );
+
+        let state = HarnessState {
+            repository_factory: PgRepositoryFactory::new(pool),
+            templates,
+            key_store,
+            cookie_manager,
+            metadata_cache,
+            provider_health: ProviderHealthTracker::default(),
+            circuit_breaker: ProviderCircuitBreaker::default(),
+            proof_of_work_replay_cache: ProofOfWorkReplayCache::default(),
+            introspection_cache,
+            client_jwks_cache,
+            encrypter,
+            url_builder,
+            homeserver_connection,
+            policy_factory,
+            graphql_schema,
+            password_manager,
+            site_config,
+            activity_tracker,
+            limiter,
+            feature_flags: FeatureFlags::default(),
+            http_client,
+        };
+
+        if let Some(listener) = listener {
+            let app = router(state.clone());
+            let token = shutdown_token.child_token();
+            task_tracker.spawn(async move {
+                axum::serve(listener, app)
+                    .with_graceful_shutdown(token.cancelled_owned())
+                    .await
+            });
+        }
+
+        Ok(Self {
+            state,
+            resources_dir,
+            base_url,
+            database: None,
+            task_tracker,
+            cancellation_drop_guard: shutdown_token.drop_guard(),
+        })
+    }
+
+    /// Stop the service, wait for the background tasks to finish, and drop
+    /// the temporary database if there is one
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the temporary database could not be dropped
+    pub async fn shutdown(self) -> Result<(), anyhow::Error> {
+        let Self {
+            state,
+            database,
+            task_tracker,
+            cancellation_drop_guard,
+            ..
+        } = self;
+
+        // This cancels the background tasks and the HTTP server
+        drop(cancellation_drop_guard);
+        task_tracker.close();
+        task_tracker.wait().await;
+
+        // Make sure nothing holds a connection to the database anymore
+        drop(state);
+
+        if let Some(database) = database {
+            database.drop().await?;
+        }
+
+        Ok(())
+    }
+
+    /// The public base URL of the service
+    ///
+    /// This is only reachable over the network if the harness was started
+    /// with [`HarnessOptions::listen`].
+    #[must_use]
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// The [`UrlBuilder`] of the service, to build URLs to its endpoints
+    #[must_use]
+    pub fn url_builder(&self) -> &UrlBuilder {
+        &self.state.url_builder
+    }
+
+    /// The site configuration of the service
+    #[must_use]
+    pub fn site_config(&self) -> &SiteConfig {
+        &self.state.site_config
+    }
+
+    /// The mock homeserver the service is connected to
+    #[must_use]
+    pub fn homeserver(&self) -> &MockHomeserverConnection {
+        &self.state.homeserver_connection
+    }
+
+    /// Get a connection pool to the database of the service
+    #[must_use]
+    pub fn pool(&self) -> PgPool {
+        self.state.repository_factory.pool()
+    }
+
+    /// Get a new repository, to look up or set up data directly in the
+    /// database
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no connection to the database could be acquired
+    pub async fn repository(&self) -> Result<BoxRepository, RepositoryError> {
+        self.state.repository_factory.create().await
+    }
+
+    /// Returns a new random number generator, to pass to the repository
+    ///
+    /// # Panics
+    ///
+    /// Panics if the system random number generator failed
+    #[must_use]
+    pub fn rng(&self) -> ChaChaRng {
+        // This rng is used to source the local rng
+        #[allow(clippy::disallowed_methods)]
+        let rng = rand::thread_rng();
+
+        ChaChaRng::from_rng(rng).expect("Failed to seed RNG")
+    }
+
+    /// Returns the clock used by the service, to pass to the repository
+    #[must_use]
+    pub fn clock(&self) -> SystemClock {
+        SystemClock::default()
+    }
+
+    /// Send a request to the service, without going through the network
+    ///
+    /// The response body is collected as a string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the response body could not be read or is not valid UTF-8
+    pub async fn request<B>(&self, request: Request<B>) -> Response<String>
+    where
+        B: HttpBody<Data = Bytes> + Send + 'static,
+        B::Error: std::error::Error + Send + Sync,
+    {
+        send(self.state.clone(), request).await
+    }
+
+    /// Create a user, and provision it on the mock homeserver
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the user could not be saved or provisioned
+    pub async fn create_user(&self, username: &str) -> Result<User, anyhow::Error> {
+        let mut repo = self.repository().await?;
+        let user = repo
+            .user()
+            .add(&mut self.rng(), &self.clock(), username.to_owned())
+            .await?;
+        repo.save().await?;
+
+        let mxid = self.state.homeserver_connection.mxid(&user.username);
+        self.state
+            .homeserver_connection
+            .provision_user(&ProvisionRequest::new(mxid, &user.sub))
+            .await?;
+
+        Ok(user)
+    }
+
+    /// Set the password of a user, so that they can log in with it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if password login is disabled, or if the password
+    /// could not be saved
+    pub async fn set_password(&self, user: &User, password: &str) -> Result<(), anyhow::Error> {
+        let (version, hashed_password) = self
+            .state
+            .password_manager
+            .hash(&mut self.rng(), Zeroizing::new(password.to_owned()))
+            .await?;
+
+        let mut repo = self.repository().await?;
+        repo.user_password()
+            .add(
+                &mut self.rng(),
+                &self.clock(),
+                user,
+                version,
+                hashed_password,
+                None,
+            )
+            .await?;
+        repo.save().await?;
+
+        Ok(())
+    }
+
+    /// Register a public client using the authorization code grant, through
+    /// the dynamic client registration endpoint
+    ///
+    /// # Panics
+    ///
+    /// Panics if the registration failed
+    pub async fn register_client(&self, redirect_uri: &Url) -> ClientRegistrationResponse {
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": redirect_uri.origin().ascii_serialization(),
+                "redirect_uris": [redirect_uri],
+                "token_endpoint_auth_method": "none",
+                "response_types": ["code"],
+                "grant_types": ["authorization_code", "refresh_token"],
+            }));
+
+        let response = self.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        response.json()
+    }
+
+    /// Get an access token for the admin API, with the `urn:mas:admin` scope
+    ///
+    /// This registers a client using the client credentials grant, and allows
+    /// it to request admin tokens.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the policy could not be loaded
+    ///
+    /// # Panics
+    ///
+    /// Panics if the client registration or the token request failed
+    pub async fn admin_token(&self) -> Result<String, anyhow::Error> {
+        let request =
+            Request::post(mas_router::OAuth2RegistrationEndpoint::PATH).json(serde_json::json!({
+                "client_uri": "https://example.com/",
+                "token_endpoint_auth_method": "client_secret_post",
+                "grant_types": ["client_credentials"],
+            }));
+        let response = self.request(request).await;
+        response.assert_status(StatusCode::CREATED);
+        let response: ClientRegistrationResponse = response.json();
+        let client_id = response.client_id;
+        let client_secret = response.client_secret.expect("to have a client secret");
+
+        // Make the client admin, only for this request
+        let mut state = self.state.clone();
+        state.policy_factory = load_policy_factory(
+            &self.resources_dir,
+            &self.state.site_config.server_name,
+            serde_json::json!({
+                "admin_clients": [client_id],
+            }),
+        )
+        .await?;
+
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "client_credentials",
+                "client_id": client_id,
+                "client_secret": client_secret,
+                "scope": "urn:mas:admin",
+            }));
+
+        let response = send(state, request).await;
+        response.assert_status(StatusCode::OK);
+        let AccessTokenResponse { access_token, .. } = response.json();
+
+        Ok(access_token)
+    }
+
+    /// Mint an access token for a user, with the given scope
+    ///
+    /// This directly creates a browser session and an OAuth 2.0 session for
+    /// the user, without going through the authorization endpoint. Use
+    /// [`TestHarness::authorization_code_flow`] to go through the token
+    /// endpoint instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sessions or the token could not be saved
+    pub async fn access_token(&self, user: &User, scope: &Scope) -> Result<String, anyhow::Error> {
+        let registration = self.register_client(&REDIRECT_URI.parse()?).await;
+
+        let mut repo = self.repository().await?;
+        let mut rng = self.rng();
+        let clock = self.clock();
+
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&registration.client_id)
+            .await?
+            .context("client not found")?;
+
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &clock, user, None)
+            .await?;
+
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(&mut rng, &clock, &client, &browser_session, scope.clone())
+            .await?;
+
+        let access_token = TokenType::AccessToken.generate(&mut rng);
+        let access_token = repo
+            .oauth2_access_token()
+            .add(
+                &mut rng,
+                &clock,
+                &session,
+                access_token,
+                Some(self.state.site_config.access_token_ttl),
+            )
+            .await?;
+
+        repo.save().await?;
+
+        Ok(access_token.access_token)
+    }
+
+    /// Run an authorization code flow for a user, and exchange the code at
+    /// the token endpoint
+    ///
+    /// This registers a new client, and records the user consent directly in
+    /// the database, as if the user had logged in and consented through the
+    /// web interface.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the grant or the sessions could not be saved
+    ///
+    /// # Panics
+    ///
+    /// Panics if the client registration or the token request failed
+    pub async fn authorization_code_flow(
+        &self,
+        user: &User,
+        scope: &Scope,
+    ) -> Result<AccessTokenResponse, anyhow::Error> {
+        let redirect_uri: Url = REDIRECT_URI.parse()?;
+        let registration = self.register_client(&redirect_uri).await;
+
+        let mut repo = self.repository().await?;
+        let mut rng = self.rng();
+        let clock = self.clock();
+
+        let client = repo
+            .oauth2_client()
+            .find_by_client_id(&registration.client_id)
+            .await?
+            .context("client not found")?;
+
+        let browser_session = repo
+            .browser_session()
+            .add(&mut rng, &clock, user, None)
+            .await?;
+
+        let code = Alphanumeric.sample_string(&mut rng, 32);
+        let grant = repo
+            .oauth2_authorization_grant()
+            .add(
+                &mut rng,
+                &clock,
+                &client,
+                redirect_uri.clone(),
+                scope.clone(),
+                ResponseMode::Query,
+                OAuth2AuthorizationGrantParams {
+                    code: Some(AuthorizationCode {
+                        code: code.clone(),
+                        pkce: None,
+                    }),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        let session = repo
+            .oauth2_session()
+            .add_from_browser_session(&mut rng, &clock, &client, &browser_session, scope.clone())
+            .await?;
+
+        repo.oauth2_authorization_grant()
+            .fulfill(&clock, &session, grant)
+            .await?;
+
+        repo.save().await?;
+
+        let request =
+            Request::post(mas_router::OAuth2TokenEndpoint::PATH).form(serde_json::json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": redirect_uri,
+                "client_id": client.client_id,
+            }));
+
+        let response = self.request(request).await;
+        response.assert_status(StatusCode::OK);
+        Ok(response.json())
+    }
+
+    /// Do a call to the userinfo endpoint to check if the given token is valid
+    ///
+    /// # Panics
+    ///
+    /// Panics if the response status code is not 200 or 401.
+    pub async fn is_access_token_valid(&self, token: &str) -> bool {
+        let request = Request::get(mas_router::OidcUserinfo::PATH)
+            .bearer(token)
+            .empty();
+
+        let response = self.request(request).await;
+
+        match response.status() {
+            StatusCode::OK => true,
+            StatusCode::UNAUTHORIZED => false,
+            _ => panic!("Unexpected status code: {}", response.status()),
+        }
+    }
+}
+
+/// Build the router of the service, with all the routes it serves
+fn router(state: HarnessState) -> Router {
+    mas_handlers::healthcheck_router()
+        .merge(mas_handlers::discovery_router())
+        .merge(mas_handlers::api_router())
+        .merge(mas_handlers::compat_router())
+        .merge(mas_handlers::human_router(state.templates.clone()))
+        // Allow plain OAuth 2.0 access to the GraphQL API, as it makes it easier to
+        // query it from tests
+        .merge(mas_handlers::graphql_router(false, true))
+        .merge(mas_handlers::admin_api_router().1)
+        .with_state(state)
+}
+
+async fn send<B>(state: HarnessState, request: Request<B>) -> Response<String>
+where
+    B: HttpBody<Data = Bytes> + Send + 'static,
+    B::Error: std::error::Error + Send + Sync,
+{
+    let app = router(state).into_service();
+
+    let Ok(mut service) = app.ready_oneshot().await;
+    let Ok(response) = service.call(request).await;
+
+    let (parts, body) = response.into_parts();
+
+    let body = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .expect("Failed to read response body");
+    let body = std::str::from_utf8(&body)
+        .expect("Response body is not valid UTF-8")
+        .to_owned();
+
+    Response::from_parts(parts, body)
+}
+
+async fn load_policy_factory(
+    resources_dir: &camino::Utf8Path,
+    server_name: &str,
+    data: serde_json::Value,
+) -> Result<Arc<PolicyFactory>, anyhow::Error> {
+    let file = tokio::fs::File::open(resources_dir.join("policies").join("policy.wasm"))
+        .await
+        .context("could not open the policy")?;
+
+    let entrypoints = mas_policy::Entrypoints {
+        register: "register/violation".to_owned(),
+        client_registration: "client_registration/violation".to_owned(),
+        authorization_grant: "authorization_grant/violation".to_owned(),
+        email: "email/violation".to_owned(),
+        login: "login/violation".to_owned(),
+        display_name: "display_name/violation".to_owned(),
+        upstream_login: "upstream_login/violation".to_owned(),
+    };
+
+    let data = mas_policy::Data::new(server_name.to_owned()).with_rest(data);
+
+    let policy_factory = PolicyFactory::load(file, data, entrypoints).await?;
+    Ok(Arc::new(policy_factory))
+}
+
+#[cfg(test)]
+mod tests {
+    use mas_http::RequestBuilderExt as _;
+    use oauth2_types::scope::{OPENID, ScopeToken};
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[allow(unused_must_use)]
+    fn setup() {
+        rustls::crypto::aws_lc_rs::default_provider().install_default();
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_access_token(pool: PgPool) {
+        setup();
+        let harness = TestHarness::from_pool(pool, HarnessOptions::default())
+            .await
+            .unwrap();
+
+        let user = harness.create_user("alice").await.unwrap();
+        assert!(
+            harness
+                .homeserver()
+                .query_user("@alice:example.com")
+                .await
+                .is_ok()
+        );
+
+        let scope = Scope::from_iter([
+            OPENID,
+            ScopeToken::from_static("urn:matrix:org.matrix.msc2967.client:api:*"),
+        ]);
+        let token = harness.access_token(&user, &scope).await.unwrap();
+        assert!(harness.is_access_token_valid(&token).await);
+        assert!(!harness.is_access_token_valid("not-a-token").await);
+
+        harness.shutdown().await.unwrap();
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_authorization_code_flow(pool: PgPool) {
+        setup();
+        let harness = TestHarness::from_pool(pool, HarnessOptions::default())
+            .await
+            .unwrap();
+
+        let user = harness.create_user("alice").await.unwrap();
+        harness.set_password(&user, "hunter2").await.unwrap();
+
+        let response = harness
+            .authorization_code_flow(&user, &Scope::from_iter([OPENID]))
+            .await
+            .unwrap();
+        assert!(response.id_token.is_some());
+        assert!(response.refresh_token.is_some());
+        assert!(harness.is_access_token_valid(&response.access_token).await);
+
+        harness.shutdown().await.unwrap();
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_admin_token(pool: PgPool) {
+        setup();
+        let harness = TestHarness::from_pool(pool, HarnessOptions::default())
+            .await
+            .unwrap();
+
+        harness.create_user("alice").await.unwrap();
+        let token = harness.admin_token().await.unwrap();
+
+        let request = Request::get("/api/admin/v1/users").bearer(&token).empty();
+        let response = harness.request(request).await;
+        response.assert_status(StatusCode::OK);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["data"][0]["attributes"]["username"], "alice");
+
+        harness.shutdown().await.unwrap();
+    }
+
+    #[sqlx::test(migrator = "mas_storage_pg::MIGRATOR")]
+    async fn test_listen(pool: PgPool) {
+        setup();
+        let harness = TestHarness::from_pool(
+            pool,
+            HarnessOptions {
+                listen: true,
+                ..HarnessOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let url = harness.base_url().join("health").unwrap();
+        let response = mas_http::reqwest_client()
+            .get(url)
+            .send_traced()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The issuer advertised in the discovery document is the local address
+        let url = harness
+            .base_url()
+            .join(".well-known/openid-configuration")
+            .unwrap();
+        let metadata: serde_json::Value = mas_http::reqwest_client()
+            .get(url)
+            .send_traced()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(metadata["issuer"], harness.base_url().as_str());
+
+        harness.shutdown().await.unwrap();
+    }
+}
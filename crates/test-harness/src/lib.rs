@@ -0,0 +1,44 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+//! Run an in-process [Matrix Authentication Service] in integration tests.
+//!
+//! The [`TestHarness`] boots the service against a real PostgreSQL database,
+//! with a mock homeserver, and has helpers to create users, mint tokens and
+//! drive OAuth 2.0 and OpenID Connect flows programmatically.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), anyhow::Error> {
+//! use mas_test_harness::{HarnessOptions, TestHarness};
+//! use oauth2_types::scope::{OPENID, Scope};
+//!
+//! let harness =
+//!     TestHarness::start("postgres://localhost/postgres", HarnessOptions::default()).await?;
+//!
+//! let user = harness.create_user("alice").await?;
+//! let tokens = harness
+//!     .authorization_code_flow(&user, &Scope::from_iter([OPENID]))
+//!     .await?;
+//! assert!(harness.is_access_token_valid(&tokens.access_token).await);
+//!
+//! harness.shutdown().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [Matrix Authentication Service]: https://github.com/element-hq/matrix-authentication-service
+
+#![deny(missing_docs)]
+
+mod database;
+pub mod ext;
+mod harness;
+mod state;
+
+pub use self::{
+    database::TempDatabase,
+    ext::{RequestBuilderExt, ResponseExt},
+    harness::{HarnessOptions, TestHarness},
+};
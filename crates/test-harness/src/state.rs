@@ -0,0 +1,272 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+use std::{convert::Infallible, sync::Arc};
+
+use axum::extract::{FromRef, FromRequestParts};
+use mas_data_model::SiteConfig;
+use mas_handlers::{
+    ActivityTracker, BoundActivityTracker, ClientJwksCache, CookieManager, ErrorWrapper,
+    FeatureFlags, GraphQLSchema, IntrospectionCache, Limiter, MetadataCache,
+    ProofOfWorkReplayCache, ProviderCircuitBreaker, ProviderHealthTracker, RequesterFingerprint,
+    passwords::PasswordManager,
+};
+use mas_i18n::Translator;
+use mas_keystore::{Encrypter, Keystore};
+use mas_matrix::{HomeserverConnection, MockHomeserverConnection};
+use mas_policy::{Policy, PolicyFactory};
+use mas_router::UrlBuilder;
+use mas_storage::{
+    BoxClock, BoxRepository, BoxRepositoryFactory, BoxRng, RepositoryFactory, SystemClock,
+};
+use mas_storage_pg::PgRepositoryFactory;
+use mas_templates::Templates;
+use rand::SeedableRng;
+use sqlx::PgPool;
+
+/// The state shared by all the routes of the harness
+///
+/// This mirrors the application state of the server, with a mock homeserver
+/// connection.
+#[derive(Clone)]
+pub(crate) struct HarnessState {
+    pub repository_factory: PgRepositoryFactory,
+    pub templates: Templates,
+    pub key_store: Keystore,
+    pub cookie_manager: CookieManager,
+    pub metadata_cache: MetadataCache,
+    pub provider_health: ProviderHealthTracker,
+    pub circuit_breaker: ProviderCircuitBreaker,
+    pub proof_of_work_replay_cache: ProofOfWorkReplayCache,
+    pub introspection_cache: IntrospectionCache,
+    pub client_jwks_cache: ClientJwksCache,
+    pub encrypter: Encrypter,
+    pub url_builder: UrlBuilder,
+    pub homeserver_connection: Arc<MockHomeserverConnection>,
+    pub policy_factory: Arc<PolicyFactory>,
+    pub graphql_schema: GraphQLSchema,
+    pub password_manager: PasswordManager,
+    pub site_config: SiteConfig,
+    pub activity_tracker: ActivityTracker,
+    pub limiter: Limiter,
+    pub feature_flags: FeatureFlags,
+    pub http_client: reqwest::Client,
+}
+
+impl FromRef<HarnessState> for PgPool {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.repository_factory.pool()
+    }
+}
+
+impl FromRef<HarnessState> for BoxRepositoryFactory {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.repository_factory.clone().boxed()
+    }
+}
+
+impl FromRef<HarnessState> for GraphQLSchema {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.graphql_schema.clone()
+    }
+}
+
+impl FromRef<HarnessState> for Templates {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.templates.clone()
+    }
+}
+
+impl FromRef<HarnessState> for Arc<Translator> {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.templates.translator()
+    }
+}
+
+impl FromRef<HarnessState> for Keystore {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.key_store.clone()
+    }
+}
+
+impl FromRef<HarnessState> for Encrypter {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.encrypter.clone()
+    }
+}
+
+impl FromRef<HarnessState> for UrlBuilder {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.url_builder.clone()
+    }
+}
+
+impl FromRef<HarnessState> for PasswordManager {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.password_manager.clone()
+    }
+}
+
+impl FromRef<HarnessState> for CookieManager {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.cookie_manager.clone()
+    }
+}
+
+impl FromRef<HarnessState> for MetadataCache {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.metadata_cache.clone()
+    }
+}
+
+impl FromRef<HarnessState> for ProviderHealthTracker {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.provider_health.clone()
+    }
+}
+
+impl FromRef<HarnessState> for ProviderCircuitBreaker {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.circuit_breaker.clone()
+    }
+}
+
+impl FromRef<HarnessState> for ProofOfWorkReplayCache {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.proof_of_work_replay_cache.clone()
+    }
+}
+
+impl FromRef<HarnessState> for IntrospectionCache {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.introspection_cache.clone()
+    }
+}
+
+impl FromRef<HarnessState> for ClientJwksCache {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.client_jwks_cache.clone()
+    }
+}
+
+impl FromRef<HarnessState> for SiteConfig {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.site_config.clone()
+    }
+}
+
+impl FromRef<HarnessState> for Arc<PolicyFactory> {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.policy_factory.clone()
+    }
+}
+
+impl FromRef<HarnessState> for Arc<dyn HomeserverConnection> {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.homeserver_connection.clone()
+    }
+}
+
+impl FromRef<HarnessState> for Limiter {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.limiter.clone()
+    }
+}
+
+impl FromRef<HarnessState> for FeatureFlags {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.feature_flags.clone()
+    }
+}
+
+impl FromRef<HarnessState> for reqwest::Client {
+    fn from_ref(input: &HarnessState) -> Self {
+        input.http_client.clone()
+    }
+}
+
+impl FromRequestParts<HarnessState> for ActivityTracker {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        _parts: &mut axum::http::request::Parts,
+        state: &HarnessState,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(state.activity_tracker.clone())
+    }
+}
+
+impl FromRequestParts<HarnessState> for BoundActivityTracker {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        _parts: &mut axum::http::request::Parts,
+        state: &HarnessState,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(state.activity_tracker.clone().bind(None))
+    }
+}
+
+impl FromRequestParts<HarnessState> for RequesterFingerprint {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        _parts: &mut axum::http::request::Parts,
+        _state: &HarnessState,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(RequesterFingerprint::EMPTY)
+    }
+}
+
+impl FromRequestParts<HarnessState> for BoxClock {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        _parts: &mut axum::http::request::Parts,
+        _state: &HarnessState,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Box::new(SystemClock::default()))
+    }
+}
+
+impl FromRequestParts<HarnessState> for BoxRng {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        _parts: &mut axum::http::request::Parts,
+        _state: &HarnessState,
+    ) -> Result<Self, Self::Rejection> {
+        // This rng is used to source the local rng
+        #[allow(clippy::disallowed_methods)]
+        let rng = rand::thread_rng();
+
+        let rng = rand_chacha::ChaChaRng::from_rng(rng).expect("Failed to seed RNG");
+        Ok(Box::new(rng))
+    }
+}
+
+impl FromRequestParts<HarnessState> for BoxRepository {
+    type Rejection = ErrorWrapper<mas_storage::RepositoryError>;
+
+    async fn from_request_parts(
+        _parts: &mut axum::http::request::Parts,
+        state: &HarnessState,
+    ) -> Result<Self, Self::Rejection> {
+        let repo = state.repository_factory.create().await?;
+        Ok(repo)
+    }
+}
+
+impl FromRequestParts<HarnessState> for Policy {
+    type Rejection = ErrorWrapper<mas_policy::InstantiateError>;
+
+    async fn from_request_parts(
+        _parts: &mut axum::http::request::Parts,
+        state: &HarnessState,
+    ) -> Result<Self, Self::Rejection> {
+        let policy = state.policy_factory.instantiate().await?;
+        Ok(policy)
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+// Solves the proof-of-work CAPTCHA challenges issued by MAS. They use the
+// ALTCHA format: the solution is the number which, appended to the salt, has
+// the challenge as its SHA-256 hash.
+
+type Challenge = {
+  algorithm: string;
+  challenge: string;
+  maxnumber: number;
+  salt: string;
+  signature: string;
+};
+
+const encoder = new TextEncoder();
+
+const sha256 = async (data: string): Promise<string> => {
+  const digest = await crypto.subtle.digest("SHA-256", encoder.encode(data));
+  return Array.from(new Uint8Array(digest), (byte) =>
+    byte.toString(16).padStart(2, "0"),
+  ).join("");
+};
+
+const solve = async (challenge: Challenge): Promise<number | null> => {
+  for (let number = 0; number <= challenge.maxnumber; number++) {
+    if ((await sha256(challenge.salt + number)) === challenge.challenge) {
+      return number;
+    }
+  }
+
+  return null;
+};
+
+const setup = async (element: HTMLElement): Promise<void> => {
+  const input = element.querySelector<HTMLInputElement>("input[name=altcha]");
+  const status = element.querySelector<HTMLElement>(".pow-captcha-status");
+  const url = element.dataset.challengeUrl;
+  if (!input || !status || !url) {
+    return;
+  }
+
+  // Hold the form submission until the challenge is solved
+  const form = input.form;
+  let done = false;
+  let submitRequested = false;
+  form?.addEventListener("submit", (event) => {
+    if (!done) {
+      event.preventDefault();
+      submitRequested = true;
+    }
+  });
+
+  try {
+    const response = await fetch(url, { cache: "no-store" });
+    if (!response.ok) {
+      throw new Error(`Unexpected status code ${response.status}`);
+    }
+
+    const challenge: Challenge = await response.json();
+    const number = await solve(challenge);
+    if (number === null) {
+      throw new Error("No solution found for the challenge");
+    }
+
+    input.value = btoa(
+      JSON.stringify({
+        algorithm: challenge.algorithm,
+        challenge: challenge.challenge,
+        number,
+        salt: challenge.salt,
+        signature: challenge.signature,
+      }),
+    );
+    status.textContent = element.dataset.solvedLabel ?? "";
+  } catch (error) {
+    // Let the form be submitted anyway, the server will show an error
+    console.error("Failed to solve the CAPTCHA challenge", error);
+    status.textContent = element.dataset.failedLabel ?? "";
+  } finally {
+    done = true;
+    if (submitRequested) {
+      form?.requestSubmit();
+    }
+  }
+};
+
+for (const element of document.querySelectorAll<HTMLElement>(".pow-captcha")) {
+  setup(element);
+}
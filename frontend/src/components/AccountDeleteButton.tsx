@@ -12,6 +12,7 @@ import { type FragmentType, graphql, useFragment } from "../gql";
 import { graphqlRequest } from "../graphql";
 import * as Dialog from "./Dialog";
 import LoadingSpinner from "./LoadingSpinner";
+import ReauthenticationRequired from "./ReauthenticationRequired";
 import Separator from "./Separator";
 
 export const USER_FRAGMENT = graphql(/* GraphQL */ `
@@ -28,6 +29,7 @@ export const USER_FRAGMENT = graphql(/* GraphQL */ `
 export const CONFIG_FRAGMENT = graphql(/* GraphQL */ `
   fragment AccountDeleteButton_siteConfig on SiteConfig {
     passwordLoginEnabled
+    accountDeletionGracePeriodDays
   }
 `);
 
@@ -140,6 +142,9 @@ const AccountDeleteButton: React.FC<Props> = (props) => {
   const incorrectPassword =
     mutation.data?.deactivateUser.status === "INCORRECT_PASSWORD";
 
+  const reauthenticationRequired =
+    mutation.data?.deactivateUser.status === "REAUTHENTICATION_REQUIRED";
+
   // We still consider the form as submitted if the mutation is pending, or if
   // the mutation has returned a success, so that we continue showing the
   // loading spinner during the page reload
@@ -185,6 +190,14 @@ const AccountDeleteButton: React.FC<Props> = (props) => {
             ),
           }}
         />
+
+        {siteConfig.accountDeletionGracePeriodDays != null && (
+          <Text type="body" weight="regular" size="md">
+            {t("frontend.account.delete_account.grace_period", {
+              count: siteConfig.accountDeletionGracePeriodDays,
+            })}
+          </Text>
+        )}
       </Dialog.Description>
 
       <Form.Root onSubmit={onSubmit}>
@@ -242,6 +255,8 @@ const AccountDeleteButton: React.FC<Props> = (props) => {
           </Form.Field>
         )}
 
+        {reauthenticationRequired && <ReauthenticationRequired />}
+
         {isMaybeValid && (
           <Alert
             type="critical"
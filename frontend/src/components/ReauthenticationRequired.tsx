@@ -0,0 +1,30 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+import { Alert, Link } from "@vector-im/compound-web";
+import { useTranslation } from "react-i18next";
+
+import config from "../config";
+
+// Shown when the server refuses a sensitive change because the user didn't
+// authenticate recently enough. The reauth page sends them back to their
+// account once they're done.
+const ReauthenticationRequired: React.FC = () => {
+  const { t } = useTranslation();
+
+  return (
+    <Alert
+      type="critical"
+      title={t("frontend.reauthentication_required.title")}
+    >
+      {t("frontend.reauthentication_required.description")}{" "}
+      <Link href={config.reauthEndpoint}>
+        {t("frontend.reauthentication_required.action")}
+      </Link>
+    </Alert>
+  );
+};
+
+export default ReauthenticationRequired;
@@ -21,6 +21,7 @@ describe("<CompatSessionDetail>", () => {
     finishedAt: null,
     lastActiveIp: "1.2.3.4",
     lastActiveAt: "2023-07-29T03:35:17.451292+00:00",
+    impersonated: false,
     userAgent: null,
     ssoLogin: {
       id: "test-id",
@@ -5,7 +5,7 @@
 // Please see LICENSE files in the repository root for full details.
 
 import { useMutation, useQueryClient } from "@tanstack/react-query";
-import { VisualList } from "@vector-im/compound-web";
+import { Alert, VisualList } from "@vector-im/compound-web";
 import { parseISO } from "date-fns";
 import { useTranslation } from "react-i18next";
 import { type FragmentType, graphql, useFragment } from "../../gql";
@@ -35,6 +35,7 @@ export const FRAGMENT = graphql(/* GraphQL */ `
     lastActiveIp
     lastActiveAt
     humanName
+    impersonated
 
     ...EndCompatSessionButton_session
 
@@ -95,6 +96,14 @@ const CompatSessionDetail: React.FC<Props> = ({ session }) => {
         {sessionName}
         <EditSessionName mutation={setDisplayName} deviceName={sessionName} />
       </SessionHeader>
+      {data.impersonated && (
+        <Alert
+          type="info"
+          title={t("frontend.compat_session_detail.impersonated_title")}
+        >
+          {t("frontend.compat_session_detail.impersonated_description")}
+        </Alert>
+      )}
       <Info.DataSection>
         <Info.DataSectionHeader>
           {t("frontend.session.title")}
@@ -35,6 +35,7 @@ export const FRAGMENT = graphql(/* GraphQL */ `
     lastActiveIp
     lastActiveAt
     humanName
+    offlineAccessGrantedAt
 
     ...EndOAuth2SessionButton_session
 
@@ -133,6 +134,17 @@ const OAuth2SessionDetail: React.FC<Props> = ({ session }) => {
             </Info.Data>
           )}
 
+          {data.offlineAccessGrantedAt && (
+            <Info.Data>
+              <Info.DataLabel>
+                {t("frontend.oauth2_session_detail.offline_access_label")}
+              </Info.DataLabel>
+              <Info.DataValue>
+                <DateTime datetime={data.offlineAccessGrantedAt} />
+              </Info.DataValue>
+            </Info.Data>
+          )}
+
           <Info.Data>
             <Info.DataLabel>
               {t("frontend.session.device_id_label")}
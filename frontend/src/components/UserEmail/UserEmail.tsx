@@ -23,9 +23,11 @@ import LoadingSpinner from "../LoadingSpinner";
 import PasswordConfirmationModal, {
   usePasswordConfirmation,
 } from "../PasswordConfirmation";
+import ReauthenticationRequired from "../ReauthenticationRequired";
 import styles from "./UserEmail.module.css";
 
 // This component shows a single user email address, with controls to remove it
+// and to make it the primary one
 
 export const FRAGMENT = graphql(/* GraphQL */ `
   fragment UserEmail_email on UserEmail {
@@ -46,6 +48,18 @@ const REMOVE_EMAIL_MUTATION = graphql(/* GraphQL */ `
   }
 `);
 
+const SET_PRIMARY_EMAIL_MUTATION = graphql(/* GraphQL */ `
+  mutation SetPrimaryEmail($id: ID!) {
+    setPrimaryEmail(input: { userEmailId: $id }) {
+      status
+
+      user {
+        id
+      }
+    }
+  }
+`);
+
 const DeleteButton: React.FC<{ disabled?: boolean; onClick?: () => void }> = ({
   disabled,
   onClick,
@@ -69,13 +83,23 @@ const DeleteButton: React.FC<{ disabled?: boolean; onClick?: () => void }> = ({
 
 const UserEmail: React.FC<{
   email: FragmentType<typeof FRAGMENT>;
+  primaryEmailId?: string;
   canRemove?: boolean;
+  canSetPrimary?: boolean;
   shouldPromptPassword?: boolean;
   onRemove?: () => void;
-}> = ({ email, canRemove, shouldPromptPassword, onRemove }) => {
+}> = ({
+  email,
+  primaryEmailId,
+  canRemove,
+  canSetPrimary,
+  shouldPromptPassword,
+  onRemove,
+}) => {
   const { t } = useTranslation();
   const [open, setOpen] = useState(false);
   const data = useFragment(FRAGMENT, email);
+  const isPrimary = data.id === primaryEmailId;
   const queryClient = useQueryClient();
   const [promptPassword, passwordConfirmationRef] = usePasswordConfirmation();
 
@@ -103,6 +127,18 @@ const UserEmail: React.FC<{
     },
   });
 
+  const setPrimaryEmail = useMutation({
+    mutationFn: (id: string) =>
+      graphqlRequest({
+        query: SET_PRIMARY_EMAIL_MUTATION,
+        variables: { id },
+      }),
+
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["userEmails"] });
+    },
+  });
+
   const onRemoveClick = useCallback(
     async (_e: React.MouseEvent<HTMLButtonElement>): Promise<void> => {
       let password = undefined;
@@ -170,6 +206,10 @@ const UserEmail: React.FC<{
                   </ErrorMessage>
                 )}
 
+                {status === "REAUTHENTICATION_REQUIRED" && (
+                  <ReauthenticationRequired />
+                )}
+
                 <div className="flex flex-col gap-4">
                   <Button
                     kind="primary"
@@ -193,6 +233,26 @@ const UserEmail: React.FC<{
               </Dialog>
             )}
           </div>
+
+          {isPrimary ? (
+            <Form.HelpMessage>
+              {t("frontend.user_email.primary")}
+            </Form.HelpMessage>
+          ) : (
+            canSetPrimary && (
+              <Button
+                kind="tertiary"
+                size="sm"
+                type="button"
+                className="self-start"
+                onClick={() => setPrimaryEmail.mutate(data.id)}
+                disabled={setPrimaryEmail.isPending}
+              >
+                {!!setPrimaryEmail.isPending && <LoadingSpinner inline />}
+                {t("frontend.user_email.make_primary_button")}
+              </Button>
+            )
+          )}
         </Form.Field>
       </Form.Root>
     </>
@@ -15,13 +15,20 @@ const Template: React.FC<{
   displayName?: string;
   mxid: string;
   displayNameChangeAllowed: boolean;
-}> = ({ displayName, mxid, displayNameChangeAllowed }) => {
+  avatarChangeAllowed: boolean;
+}> = ({
+  displayName,
+  mxid,
+  displayNameChangeAllowed,
+  avatarChangeAllowed,
+}) => {
   const user = makeFragmentData(
     {
       id: "user id",
       matrix: {
         mxid,
         displayName,
+        avatarUrl: null,
       },
     },
     FRAGMENT,
@@ -31,6 +38,7 @@ const Template: React.FC<{
     {
       id: "site config id",
       displayNameChangeAllowed,
+      avatarChangeAllowed,
     },
     CONFIG_FRAGMENT,
   );
@@ -47,6 +55,7 @@ const meta = {
   component: Template,
   args: {
     displayNameChangeAllowed: true,
+    avatarChangeAllowed: true,
     displayName: "Kilgore Trout",
     mxid: "@kilgore:matrix.org",
   },
@@ -54,6 +63,9 @@ const meta = {
     displayNameChangeAllowed: {
       control: "boolean",
     },
+    avatarChangeAllowed: {
+      control: "boolean",
+    },
     displayName: {
       control: "text",
     },
@@ -79,3 +91,10 @@ export const DisplayNameChangeNotAllowed: Story = {
     displayNameChangeAllowed: false,
   },
 };
+
+export const ProfileChangeNotAllowed: Story = {
+  args: {
+    displayNameChangeAllowed: false,
+    avatarChangeAllowed: false,
+  },
+};
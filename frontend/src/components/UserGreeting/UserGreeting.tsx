@@ -8,6 +8,7 @@ import { useMutation, useQueryClient } from "@tanstack/react-query";
 import IconClose from "@vector-im/compound-design-tokens/assets/web/icons/close";
 import IconEdit from "@vector-im/compound-design-tokens/assets/web/icons/edit";
 import {
+  Alert,
   Avatar,
   Button,
   Form,
@@ -34,6 +35,7 @@ export const FRAGMENT = graphql(/* GraphQL */ `
     matrix {
       mxid
       displayName
+      avatarUrl
     }
   }
 `);
@@ -41,6 +43,7 @@ export const FRAGMENT = graphql(/* GraphQL */ `
 export const CONFIG_FRAGMENT = graphql(/* GraphQL */ `
   fragment UserGreeting_siteConfig on SiteConfig {
     displayNameChangeAllowed
+    avatarChangeAllowed
   }
 `);
 
@@ -48,10 +51,22 @@ const SET_DISPLAYNAME_MUTATION = graphql(/* GraphQL */ `
   mutation SetDisplayName($userId: ID!, $displayName: String) {
     setDisplayName(input: { userId: $userId, displayName: $displayName }) {
       status
+      violations
     }
   }
 `);
 
+const SET_AVATAR_MUTATION = graphql(/* GraphQL */ `
+  mutation SetAvatar($userId: ID!, $avatar: Upload) {
+    setAvatar(input: { userId: $userId, avatar: $avatar }) {
+      status
+    }
+  }
+`);
+
+// Keep in sync with the formats accepted by the `setAvatar` mutation
+const AVATAR_CONTENT_TYPES = "image/png,image/jpeg,image/gif,image/webp";
+
 // This needs to be its own component because else props and refs aren't passed properly in the trigger
 const EditButton = forwardRef<
   HTMLButtonElement,
@@ -77,8 +92,12 @@ type Props = {
 
 const UserGreeting: React.FC<Props> = ({ user, siteConfig }) => {
   const fieldRef = useRef<HTMLInputElement>(null);
+  const avatarRef = useRef<HTMLInputElement>(null);
   const data = useFragment(FRAGMENT, user);
-  const { displayNameChangeAllowed } = useFragment(CONFIG_FRAGMENT, siteConfig);
+  const { displayNameChangeAllowed, avatarChangeAllowed } = useFragment(
+    CONFIG_FRAGMENT,
+    siteConfig,
+  );
   const queryClient = useQueryClient();
 
   const setDisplayName = useMutation({
@@ -101,6 +120,23 @@ const UserGreeting: React.FC<Props> = ({ user, siteConfig }) => {
     },
   });
 
+  const setAvatar = useMutation({
+    mutationFn: ({
+      userId,
+      avatar,
+    }: {
+      userId: string;
+      avatar: File | null;
+    }) =>
+      graphqlRequest({
+        query: SET_AVATAR_MUTATION,
+        variables: { userId, avatar },
+      }),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["currentUserGreeting"] });
+    },
+  });
+
   const [open, setOpen] = useState(false);
   const { t } = useTranslation();
 
@@ -113,6 +149,21 @@ const UserGreeting: React.FC<Props> = ({ user, siteConfig }) => {
     setDisplayName.mutate({ displayName, userId: data.id });
   };
 
+  const onAvatarChange = (
+    event: React.ChangeEvent<HTMLInputElement>,
+  ): void => {
+    const avatar = event.currentTarget.files?.[0];
+    // Reset the input so that picking the same file again triggers a change
+    event.currentTarget.value = "";
+    if (avatar) {
+      setAvatar.mutate({ avatar, userId: data.id });
+    }
+  };
+
+  const displayNameStatus = setDisplayName.data?.setDisplayName.status;
+  const violations = setDisplayName.data?.setDisplayName.violations ?? [];
+  const avatarStatus = setAvatar.data?.setAvatar.status;
+
   return (
     <div className={styles.user}>
       <Avatar
@@ -138,13 +189,15 @@ const UserGreeting: React.FC<Props> = ({ user, siteConfig }) => {
         )}
       </div>
 
-      {displayNameChangeAllowed && (
+      {(displayNameChangeAllowed || avatarChangeAllowed) && (
         <Dialog.Dialog
           trigger={<EditButton label={t("action.edit")} />}
           open={open}
           onOpenChange={(open) => {
             // Reset the form when the dialog is opened or closed
             fieldRef.current?.form?.reset();
+            setDisplayName.reset();
+            setAvatar.reset();
             setOpen(open);
           }}
         >
@@ -159,37 +212,108 @@ const UserGreeting: React.FC<Props> = ({ user, siteConfig }) => {
             name={data.matrix.displayName || data.matrix.mxid}
           />
 
+          {avatarChangeAllowed && (
+            <div className={styles.avatarActions}>
+              <input
+                ref={avatarRef}
+                type="file"
+                accept={AVATAR_CONTENT_TYPES}
+                hidden
+                onChange={onAvatarChange}
+              />
+
+              <Button
+                kind="secondary"
+                size="sm"
+                type="button"
+                disabled={setAvatar.isPending}
+                onClick={() => avatarRef.current?.click()}
+              >
+                {setAvatar.isPending && <LoadingSpinner inline />}
+                {t("frontend.account.edit_profile.avatar_upload")}
+              </Button>
+
+              {data.matrix.avatarUrl && (
+                <Button
+                  kind="tertiary"
+                  size="sm"
+                  type="button"
+                  destructive
+                  disabled={setAvatar.isPending}
+                  onClick={() =>
+                    setAvatar.mutate({ avatar: null, userId: data.id })
+                  }
+                >
+                  {t("frontend.account.edit_profile.avatar_remove")}
+                </Button>
+              )}
+            </div>
+          )}
+
+          {avatarStatus === "INVALID_FORMAT" && (
+            <Alert
+              type="critical"
+              title={t("frontend.account.edit_profile.avatar_invalid_format")}
+            />
+          )}
+
+          {avatarStatus === "TOO_LARGE" && (
+            <Alert
+              type="critical"
+              title={t("frontend.account.edit_profile.avatar_too_large")}
+            />
+          )}
+
           <Form.Root onSubmit={onSubmit}>
             <div className={styles.dialogForm}>
-              <Form.Field
-                name="displayname"
-                serverInvalid={
-                  setDisplayName.data?.setDisplayName.status === "INVALID"
-                }
-              >
-                <Form.Label>
-                  {t("frontend.account.edit_profile.display_name_label")}
-                </Form.Label>
+              {displayNameChangeAllowed && (
+                <Form.Field
+                  name="displayname"
+                  serverInvalid={
+                    displayNameStatus === "INVALID" ||
+                    displayNameStatus === "DENIED"
+                  }
+                >
+                  <Form.Label>
+                    {t("frontend.account.edit_profile.display_name_label")}
+                  </Form.Label>
 
-                <Form.ActionControl
-                  type="text"
-                  Icon={IconClose}
-                  autoComplete="name"
-                  defaultValue={data.matrix.displayName || undefined}
-                  actionLabel={t("action.clear")}
-                  ref={fieldRef}
-                  onActionClick={() => {
-                    if (fieldRef.current) {
-                      fieldRef.current.value = "";
-                      fieldRef.current.focus();
-                    }
-                  }}
-                />
-
-                <Form.HelpMessage>
-                  {t("frontend.account.edit_profile.display_name_help")}
-                </Form.HelpMessage>
-              </Form.Field>
+                  <Form.ActionControl
+                    type="text"
+                    Icon={IconClose}
+                    autoComplete="name"
+                    defaultValue={data.matrix.displayName || undefined}
+                    actionLabel={t("action.clear")}
+                    ref={fieldRef}
+                    onActionClick={() => {
+                      if (fieldRef.current) {
+                        fieldRef.current.value = "";
+                        fieldRef.current.focus();
+                      }
+                    }}
+                  />
+
+                  <Form.HelpMessage>
+                    {t("frontend.account.edit_profile.display_name_help")}
+                  </Form.HelpMessage>
+
+                  {displayNameStatus === "DENIED" && (
+                    <>
+                      <Form.ErrorMessage>
+                        {t(
+                          "frontend.account.edit_profile.display_name_denied_error",
+                        )}
+                      </Form.ErrorMessage>
+
+                      {violations.map((violation) => (
+                        <Form.HelpMessage key={violation}>
+                          {violation}
+                        </Form.HelpMessage>
+                      ))}
+                    </>
+                  )}
+                </Form.Field>
+              )}
 
               <Form.Field name="mxid">
                 <Form.Label>
@@ -199,10 +323,12 @@ const UserGreeting: React.FC<Props> = ({ user, siteConfig }) => {
               </Form.Field>
             </div>
 
-            <Form.Submit disabled={setDisplayName.isPending}>
-              {setDisplayName.isPending && <LoadingSpinner inline />}
-              {t("action.save")}
-            </Form.Submit>
+            {displayNameChangeAllowed && (
+              <Form.Submit disabled={setDisplayName.isPending}>
+                {setDisplayName.isPending && <LoadingSpinner inline />}
+                {t("action.save")}
+              </Form.Submit>
+            )}
           </Form.Root>
 
           <Dialog.Close asChild>
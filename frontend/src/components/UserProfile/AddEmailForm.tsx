@@ -17,6 +17,7 @@ import { graphqlRequest } from "../../graphql";
 import PasswordConfirmationModal, {
   usePasswordConfirmation,
 } from "../PasswordConfirmation";
+import ReauthenticationRequired from "../ReauthenticationRequired";
 
 export const USER_FRAGMENT = graphql(/* GraphQL */ `
   fragment AddEmailForm_user on User {
@@ -174,6 +175,8 @@ const AddEmailForm: React.FC<{
             {t("frontend.add_email_form.incorrect_password_error")}
           </ErrorMessage>
         )}
+
+        {status === "REAUTHENTICATION_REQUIRED" && <ReauthenticationRequired />}
       </EditInPlace>
     </>
   );
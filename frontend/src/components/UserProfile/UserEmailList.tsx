@@ -44,6 +44,9 @@ const QUERY = graphql(/* GraphQL */ `
             endCursor
           }
         }
+        primaryEmail {
+          id
+        }
       }
     }
   }
@@ -90,6 +93,9 @@ const UserEmailList: React.FC<{
   const result = useSuspenseQuery(query(pagination));
   if (result.data.viewer.__typename !== "User") throw notFound();
   const emails = result.data.viewer.emails;
+  // Only point out the primary email if the user has more than one
+  const primaryEmailId =
+    emails.totalCount > 1 ? result.data.viewer.primaryEmail?.id : undefined;
 
   const [prevPage, nextPage] = usePages(pagination, emails.pageInfo);
 
@@ -106,8 +112,9 @@ const UserEmailList: React.FC<{
     });
   };
 
-  // Is it allowed to remove an email? If there's only one, we can't
-  const canRemove = emailChangeAllowed && emails.totalCount > 1;
+  // Is it allowed to remove an email or to pick another primary one? If
+  // there's only one, we can't
+  const canChange = emailChangeAllowed && emails.totalCount > 1;
 
   return (
     <>
@@ -115,7 +122,9 @@ const UserEmailList: React.FC<{
         <UserEmail
           email={edge.node}
           key={edge.cursor}
-          canRemove={canRemove}
+          primaryEmailId={primaryEmailId}
+          canRemove={canChange}
+          canSetPrimary={canChange}
           shouldPromptPassword={shouldPromptPassword}
           onRemove={onRemove}
         />
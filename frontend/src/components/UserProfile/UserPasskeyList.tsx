@@ -0,0 +1,389 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+import {
+  queryOptions,
+  useMutation,
+  useQueryClient,
+  useSuspenseQuery,
+} from "@tanstack/react-query";
+import { notFound } from "@tanstack/react-router";
+import IconDelete from "@vector-im/compound-design-tokens/assets/web/icons/delete";
+import {
+  Button,
+  EditInPlace,
+  ErrorMessage,
+  IconButton,
+  Text,
+  Tooltip,
+} from "@vector-im/compound-web";
+import { useCallback, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { graphql } from "../../gql";
+import { graphqlRequest } from "../../graphql";
+import DateTime from "../DateTime";
+import { Close, Dialog, Title } from "../Dialog";
+import LoadingSpinner from "../LoadingSpinner";
+import PasswordConfirmationModal, {
+  usePasswordConfirmation,
+} from "../PasswordConfirmation";
+import ReauthenticationRequired from "../ReauthenticationRequired";
+
+const QUERY = graphql(/* GraphQL */ `
+  query UserPasskeyList {
+    viewer {
+      __typename
+      ... on User {
+        id
+        passkeys {
+          id
+          name
+          createdAt
+          lastUsedAt
+        }
+      }
+    }
+  }
+`);
+
+export const query = queryOptions({
+  queryKey: ["userPasskeys"],
+  queryFn: ({ signal }) => graphqlRequest({ query: QUERY, signal }),
+});
+
+const START_REGISTER_PASSKEY_MUTATION = graphql(/* GraphQL */ `
+  mutation StartRegisterPasskey {
+    startRegisterPasskey {
+      id
+      options
+    }
+  }
+`);
+
+const COMPLETE_REGISTER_PASSKEY_MUTATION = graphql(/* GraphQL */ `
+  mutation CompleteRegisterPasskey(
+    $id: ID!
+    $name: String!
+    $response: String!
+  ) {
+    completeRegisterPasskey(
+      input: { id: $id, name: $name, response: $response }
+    ) {
+      status
+    }
+  }
+`);
+
+const REMOVE_PASSKEY_MUTATION = graphql(/* GraphQL */ `
+  mutation RemovePasskey($id: ID!, $password: String) {
+    removePasskey(input: { userPasskeyId: $id, password: $password }) {
+      status
+    }
+  }
+`);
+
+const decodeBase64Url = (value: string): Uint8Array =>
+  Uint8Array.from(atob(value.replace(/-/g, "+").replace(/_/g, "/")), (c) =>
+    c.charCodeAt(0),
+  );
+
+const encodeBase64Url = (buffer: ArrayBuffer): string =>
+  btoa(String.fromCharCode(...new Uint8Array(buffer)))
+    .replace(/\+/g, "-")
+    .replace(/\//g, "_")
+    .replace(/=+$/, "");
+
+/**
+ * Ask the browser to create a new passkey with the options given by the
+ * server, and serialize its response as JSON
+ */
+const createCredential = async (options: string): Promise<string> => {
+  const publicKey = JSON.parse(options);
+  publicKey.challenge = decodeBase64Url(publicKey.challenge);
+  publicKey.user.id = decodeBase64Url(publicKey.user.id);
+  publicKey.excludeCredentials = publicKey.excludeCredentials.map(
+    (credential: { id: string }) => ({
+      ...credential,
+      id: decodeBase64Url(credential.id),
+    }),
+  );
+
+  const credential = (await navigator.credentials.create({
+    publicKey,
+  })) as PublicKeyCredential | null;
+  if (!credential) {
+    throw new Error("No credential was created");
+  }
+
+  const response = credential.response as AuthenticatorAttestationResponse;
+  return JSON.stringify({
+    id: credential.id,
+    type: credential.type,
+    response: {
+      clientDataJSON: encodeBase64Url(response.clientDataJSON),
+      attestationObject: encodeBase64Url(response.attestationObject),
+      transports: response.getTransports?.() ?? [],
+    },
+  });
+};
+
+const AddPasskeyForm: React.FC = () => {
+  const { t } = useTranslation();
+  const queryClient = useQueryClient();
+  const [failed, setFailed] = useState(false);
+
+  const register = useMutation({
+    mutationFn: async (name: string) => {
+      const start = await graphqlRequest({
+        query: START_REGISTER_PASSKEY_MUTATION,
+      });
+      const { id, options } = start.startRegisterPasskey;
+      const response = await createCredential(options);
+      return graphqlRequest({
+        query: COMPLETE_REGISTER_PASSKEY_MUTATION,
+        variables: { id, name, response },
+      });
+    },
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["userPasskeys"] });
+    },
+  });
+
+  const handleSubmit = useCallback(
+    async (e: React.FormEvent<HTMLFormElement>): Promise<void> => {
+      e.preventDefault();
+      setFailed(false);
+
+      const formData = new FormData(e.currentTarget);
+      const name = formData.get("input") as string;
+
+      let data: Awaited<ReturnType<typeof register.mutateAsync>>;
+      try {
+        data = await register.mutateAsync(name);
+      } catch (error) {
+        // The user may have cancelled the browser prompt
+        setFailed(true);
+        throw error;
+      }
+
+      if (data.completeRegisterPasskey.status !== "ADDED") {
+        // This is so that the 'Edit in place' component doesn't show a 'Saved' message
+        throw new Error();
+      }
+    },
+    [register.mutateAsync],
+  );
+
+  const status = register.data?.completeRegisterPasskey.status ?? null;
+
+  return (
+    <EditInPlace
+      onSave={handleSubmit}
+      required
+      serverInvalid={failed || (!!status && status !== "ADDED")}
+      label={t("frontend.user_passkey_list.add_field_label")}
+      helpLabel={t("frontend.user_passkey_list.add_field_help")}
+      saveButtonLabel={t("frontend.user_passkey_list.add_button")}
+      savingLabel={t("common.saving")}
+      savedLabel={t("common.saved")}
+      cancelButtonLabel={t("action.cancel")}
+    >
+      {status === "INVALID_NAME" && (
+        <ErrorMessage>
+          {t("frontend.user_passkey_list.invalid_name_error")}
+        </ErrorMessage>
+      )}
+
+      {status === "EXISTS" && (
+        <ErrorMessage>
+          {t("frontend.user_passkey_list.exists_error")}
+        </ErrorMessage>
+      )}
+
+      {(failed ||
+        status === "INVALID_CHALLENGE" ||
+        status === "INVALID_RESPONSE") && (
+        <ErrorMessage>
+          {t("frontend.user_passkey_list.failed_error")}
+        </ErrorMessage>
+      )}
+    </EditInPlace>
+  );
+};
+
+const UserPasskey: React.FC<{
+  passkey: {
+    id: string;
+    name: string;
+    createdAt: string;
+    lastUsedAt?: string | null;
+  };
+  shouldPromptPassword: boolean;
+}> = ({ passkey, shouldPromptPassword }) => {
+  const { t } = useTranslation();
+  const [open, setOpen] = useState(false);
+  const queryClient = useQueryClient();
+  const [promptPassword, passwordConfirmationRef] = usePasswordConfirmation();
+
+  const removePasskey = useMutation({
+    mutationFn: ({ id, password }: { id: string; password?: string }) =>
+      graphqlRequest({
+        query: REMOVE_PASSKEY_MUTATION,
+        variables: { id, password },
+      }),
+
+    onSuccess: (data) => {
+      queryClient.invalidateQueries({ queryKey: ["userPasskeys"] });
+
+      // Don't close the modal unless the passkey was removed (or not found)
+      if (
+        data.removePasskey.status !== "NOT_FOUND" &&
+        data.removePasskey.status !== "REMOVED"
+      ) {
+        return;
+      }
+
+      setOpen(false);
+    },
+  });
+
+  const onRemoveClick = useCallback(async (): Promise<void> => {
+    let password = undefined;
+    if (shouldPromptPassword) {
+      password = await promptPassword();
+    }
+    removePasskey.mutate({ id: passkey.id, password });
+  }, [passkey.id, promptPassword, shouldPromptPassword, removePasskey.mutate]);
+
+  const onOpenChange = useCallback(
+    (open: boolean) => {
+      // Don't change the modal state if the mutation is pending
+      if (removePasskey.isPending) return;
+      removePasskey.reset();
+      setOpen(open);
+    },
+    [removePasskey.isPending, removePasskey.reset],
+  );
+
+  const status = removePasskey.data?.removePasskey.status ?? null;
+
+  return (
+    <>
+      <PasswordConfirmationModal
+        title={t(
+          "frontend.user_passkey_list.delete_confirmation_modal.password_confirmation",
+        )}
+        destructive
+        ref={passwordConfirmationRef}
+      />
+      <div className="flex items-center gap-2">
+        <div className="flex flex-1 flex-col">
+          <Text size="md" weight="semibold">
+            {passkey.name}
+          </Text>
+          <Text size="sm" className="text-secondary">
+            {t("frontend.user_passkey_list.created_at")}{" "}
+            <DateTime datetime={passkey.createdAt} />
+            {" · "}
+            {passkey.lastUsedAt ? (
+              <>
+                {t("frontend.user_passkey_list.last_used_at")}{" "}
+                <DateTime datetime={passkey.lastUsedAt} />
+              </>
+            ) : (
+              t("frontend.user_passkey_list.never_used")
+            )}
+          </Text>
+        </div>
+
+        <Dialog
+          trigger={
+            <Tooltip
+              label={t("frontend.user_passkey_list.delete_button_title")}
+            >
+              <IconButton type="button" size="var(--cpd-space-8x)">
+                <IconDelete />
+              </IconButton>
+            </Tooltip>
+          }
+          open={open}
+          onOpenChange={onOpenChange}
+        >
+          <Title>
+            {t("frontend.user_passkey_list.delete_confirmation_modal.body", {
+              name: passkey.name,
+            })}
+          </Title>
+
+          {status === "INCORRECT_PASSWORD" && (
+            <ErrorMessage>
+              {t(
+                "frontend.user_passkey_list.delete_confirmation_modal.incorrect_password",
+              )}
+            </ErrorMessage>
+          )}
+
+          {status === "REAUTHENTICATION_REQUIRED" && (
+            <ReauthenticationRequired />
+          )}
+
+          <div className="flex flex-col gap-4">
+            <Button
+              kind="primary"
+              type="button"
+              destructive
+              onClick={onRemoveClick}
+              disabled={removePasskey.isPending}
+              Icon={removePasskey.isPending ? undefined : IconDelete}
+            >
+              {!!removePasskey.isPending && <LoadingSpinner inline />}
+              {t("frontend.user_passkey_list.delete_confirmation_modal.action")}
+            </Button>
+            <Close asChild>
+              <Button disabled={removePasskey.isPending} kind="tertiary">
+                {t("action.cancel")}
+              </Button>
+            </Close>
+          </div>
+        </Dialog>
+      </div>
+    </>
+  );
+};
+
+// This component lists the passkeys of the current user, and lets them add or
+// remove passkeys
+const UserPasskeyList: React.FC<{ shouldPromptPassword: boolean }> = ({
+  shouldPromptPassword,
+}) => {
+  const { t } = useTranslation();
+  const result = useSuspenseQuery(query);
+  if (result.data.viewer.__typename !== "User") throw notFound();
+  const passkeys = result.data.viewer.passkeys;
+
+  return (
+    <>
+      {passkeys.length === 0 && (
+        <Text size="md" className="text-secondary">
+          {t("frontend.user_passkey_list.no_passkeys")}
+        </Text>
+      )}
+
+      {passkeys.map((passkey) => (
+        <UserPasskey
+          key={passkey.id}
+          passkey={passkey}
+          shouldPromptPassword={shouldPromptPassword}
+        />
+      ))}
+
+      {typeof window.PublicKeyCredential !== "undefined" && (
+        <AddPasskeyForm />
+      )}
+    </>
+  );
+};
+
+export default UserPasskeyList;
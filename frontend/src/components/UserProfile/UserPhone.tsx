@@ -0,0 +1,423 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+import {
+  queryOptions,
+  useMutation,
+  useQueryClient,
+  useSuspenseQuery,
+} from "@tanstack/react-query";
+import { notFound } from "@tanstack/react-router";
+import IconDelete from "@vector-im/compound-design-tokens/assets/web/icons/delete";
+import { Alert, Button, Form, Text } from "@vector-im/compound-web";
+import { useCallback, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { graphql } from "../../gql";
+import { graphqlRequest } from "../../graphql";
+import { Close, Dialog, Title } from "../Dialog";
+import LoadingSpinner from "../LoadingSpinner";
+import PasswordConfirmationModal, {
+  usePasswordConfirmation,
+} from "../PasswordConfirmation";
+import ReauthenticationRequired from "../ReauthenticationRequired";
+
+const QUERY = graphql(/* GraphQL */ `
+  query UserPhone {
+    viewer {
+      __typename
+      ... on User {
+        id
+        phone {
+          id
+          phoneNumber
+          confirmedAt
+        }
+      }
+    }
+  }
+`);
+
+export const query = queryOptions({
+  queryKey: ["userPhone"],
+  queryFn: ({ signal }) => graphqlRequest({ query: QUERY, signal }),
+});
+
+const SET_PHONE_NUMBER_MUTATION = graphql(/* GraphQL */ `
+  mutation SetPhoneNumber(
+    $phoneNumber: String!
+    $password: String
+    $language: String!
+  ) {
+    setPhoneNumber(
+      input: {
+        phoneNumber: $phoneNumber
+        password: $password
+        language: $language
+      }
+    ) {
+      status
+    }
+  }
+`);
+
+const RESEND_PHONE_NUMBER_CODE_MUTATION = graphql(/* GraphQL */ `
+  mutation ResendPhoneNumberCode($language: String!) {
+    resendPhoneNumberCode(input: { language: $language }) {
+      status
+    }
+  }
+`);
+
+const CONFIRM_PHONE_NUMBER_MUTATION = graphql(/* GraphQL */ `
+  mutation ConfirmPhoneNumber($code: String!) {
+    confirmPhoneNumber(input: { code: $code }) {
+      status
+    }
+  }
+`);
+
+const REMOVE_PHONE_NUMBER_MUTATION = graphql(/* GraphQL */ `
+  mutation RemovePhoneNumber($password: String) {
+    removePhoneNumber(input: { password: $password }) {
+      status
+    }
+  }
+`);
+
+const SetPhoneNumberForm: React.FC<{ shouldPromptPassword: boolean }> = ({
+  shouldPromptPassword,
+}) => {
+  const { t, i18n } = useTranslation();
+  const queryClient = useQueryClient();
+  const [promptPassword, passwordConfirmationRef] = usePasswordConfirmation();
+
+  const setPhoneNumber = useMutation({
+    mutationFn: ({
+      phoneNumber,
+      password,
+      language,
+    }: { phoneNumber: string; password?: string; language: string }) =>
+      graphqlRequest({
+        query: SET_PHONE_NUMBER_MUTATION,
+        variables: { phoneNumber, password, language },
+      }),
+    onSuccess: (data) => {
+      if (data.setPhoneNumber.status === "STARTED") {
+        queryClient.invalidateQueries({ queryKey: ["userPhone"] });
+      }
+    },
+  });
+
+  const onFormSubmit = async (
+    e: React.FormEvent<HTMLFormElement>,
+  ): Promise<void> => {
+    e.preventDefault();
+    const formData = new FormData(e.currentTarget);
+    const phoneNumber = formData.get("phone_number") as string;
+    let password = undefined;
+    if (shouldPromptPassword) {
+      password = await promptPassword();
+    }
+    setPhoneNumber.mutate({
+      phoneNumber,
+      password,
+      language: i18n.languages[0],
+    });
+  };
+
+  const status = setPhoneNumber.data?.setPhoneNumber.status ?? null;
+
+  return (
+    <>
+      <PasswordConfirmationModal
+        title={t("frontend.user_phone.password_confirmation")}
+        ref={passwordConfirmationRef}
+      />
+
+      <Text size="md" className="text-secondary">
+        {t("frontend.user_phone.no_phone_number")}
+      </Text>
+
+      <Form.Root onSubmit={onFormSubmit}>
+        <Form.Field
+          name="phone_number"
+          serverInvalid={
+            status === "INVALID_PHONE_NUMBER" ||
+            status === "RATE_LIMITED" ||
+            status === "INCORRECT_PASSWORD"
+          }
+        >
+          <Form.Label>{t("frontend.user_phone.phone_number_label")}</Form.Label>
+          <Form.TextControl
+            type="tel"
+            autoComplete="tel"
+            placeholder="+44 7700 900000"
+            required
+          />
+
+          {status === "INVALID_PHONE_NUMBER" && (
+            <Form.ErrorMessage>
+              {t("frontend.user_phone.invalid_phone_number")}
+            </Form.ErrorMessage>
+          )}
+
+          {status === "RATE_LIMITED" && (
+            <Form.ErrorMessage>
+              {t("frontend.errors.rate_limit_exceeded")}
+            </Form.ErrorMessage>
+          )}
+
+          {status === "INCORRECT_PASSWORD" && (
+            <Form.ErrorMessage>
+              {t("frontend.user_phone.incorrect_password")}
+            </Form.ErrorMessage>
+          )}
+        </Form.Field>
+
+        {status === "REAUTHENTICATION_REQUIRED" && <ReauthenticationRequired />}
+
+        <Form.Submit type="submit" disabled={setPhoneNumber.isPending}>
+          {setPhoneNumber.isPending && <LoadingSpinner inline />}
+          {t("frontend.user_phone.add_button")}
+        </Form.Submit>
+      </Form.Root>
+    </>
+  );
+};
+
+const ConfirmPhoneNumberForm: React.FC<{ phoneNumber: string }> = ({
+  phoneNumber,
+}) => {
+  const { t, i18n } = useTranslation();
+  const queryClient = useQueryClient();
+
+  const confirm = useMutation({
+    mutationFn: (code: string) =>
+      graphqlRequest({
+        query: CONFIRM_PHONE_NUMBER_MUTATION,
+        variables: { code },
+      }),
+    onSuccess: (data) => {
+      if (data.confirmPhoneNumber.status !== "INVALID_CODE") {
+        queryClient.invalidateQueries({ queryKey: ["userPhone"] });
+      }
+    },
+  });
+
+  const resend = useMutation({
+    mutationFn: () =>
+      graphqlRequest({
+        query: RESEND_PHONE_NUMBER_CODE_MUTATION,
+        variables: { language: i18n.languages[0] },
+      }),
+  });
+
+  const onFormSubmit = (e: React.FormEvent<HTMLFormElement>): void => {
+    e.preventDefault();
+    const form = e.currentTarget;
+    const formData = new FormData(form);
+    const code = formData.get("code") as string;
+    confirm.mutateAsync(code).finally(() => form.reset());
+  };
+
+  const status = confirm.data?.confirmPhoneNumber.status ?? null;
+  const resendStatus = resend.data?.resendPhoneNumberCode.status ?? null;
+
+  return (
+    <Form.Root onSubmit={onFormSubmit}>
+      <Text size="md" className="text-secondary">
+        {t("frontend.user_phone.code_sent", { phoneNumber })}
+      </Text>
+
+      {resendStatus === "RESENT" && (
+        <Alert type="success" title={t("frontend.user_phone.code_resent")} />
+      )}
+
+      {resendStatus === "RATE_LIMITED" && (
+        <Alert
+          type="critical"
+          title={t("frontend.errors.rate_limit_exceeded")}
+        />
+      )}
+
+      <Form.Field
+        name="code"
+        serverInvalid={status === "INVALID_CODE"}
+        className="self-center mb-4"
+      >
+        <Form.Label>{t("frontend.user_phone.code_field_label")}</Form.Label>
+        <Form.MFAControl />
+
+        {status === "INVALID_CODE" && (
+          <Form.ErrorMessage>
+            {t("frontend.user_phone.code_field_error")}
+          </Form.ErrorMessage>
+        )}
+
+        <Form.ErrorMessage match="patternMismatch">
+          {t("frontend.verify_email.code_field_wrong_shape")}
+        </Form.ErrorMessage>
+      </Form.Field>
+
+      <Form.Submit type="submit" disabled={confirm.isPending}>
+        {confirm.isPending && <LoadingSpinner inline />}
+        {t("action.continue")}
+      </Form.Submit>
+
+      <Button
+        type="button"
+        kind="tertiary"
+        onClick={() => resend.mutate()}
+        disabled={resend.isPending}
+      >
+        {resend.isPending && <LoadingSpinner inline />}
+        {t("frontend.user_phone.resend_button")}
+      </Button>
+    </Form.Root>
+  );
+};
+
+const RemovePhoneNumberButton: React.FC<{ shouldPromptPassword: boolean }> = ({
+  shouldPromptPassword,
+}) => {
+  const { t } = useTranslation();
+  const [open, setOpen] = useState(false);
+  const queryClient = useQueryClient();
+  const [promptPassword, passwordConfirmationRef] = usePasswordConfirmation();
+
+  const removePhoneNumber = useMutation({
+    mutationFn: (password?: string) =>
+      graphqlRequest({
+        query: REMOVE_PHONE_NUMBER_MUTATION,
+        variables: { password },
+      }),
+
+    onSuccess: (data) => {
+      queryClient.invalidateQueries({ queryKey: ["userPhone"] });
+
+      // Don't close the modal unless the phone number was removed (or not
+      // found)
+      if (
+        data.removePhoneNumber.status !== "NOT_FOUND" &&
+        data.removePhoneNumber.status !== "REMOVED"
+      ) {
+        return;
+      }
+
+      setOpen(false);
+    },
+  });
+
+  const onRemoveClick = useCallback(async (): Promise<void> => {
+    let password = undefined;
+    if (shouldPromptPassword) {
+      password = await promptPassword();
+    }
+    removePhoneNumber.mutate(password);
+  }, [promptPassword, shouldPromptPassword, removePhoneNumber.mutate]);
+
+  const onOpenChange = useCallback(
+    (open: boolean) => {
+      // Don't change the modal state if the mutation is pending
+      if (removePhoneNumber.isPending) return;
+      removePhoneNumber.reset();
+      setOpen(open);
+    },
+    [removePhoneNumber.isPending, removePhoneNumber.reset],
+  );
+
+  const status = removePhoneNumber.data?.removePhoneNumber.status ?? null;
+
+  return (
+    <>
+      <PasswordConfirmationModal
+        title={t("frontend.user_phone.password_confirmation")}
+        destructive
+        ref={passwordConfirmationRef}
+      />
+      <Dialog
+        trigger={
+          <Button kind="secondary" destructive size="sm" Icon={IconDelete}>
+            {t("frontend.user_phone.remove_button")}
+          </Button>
+        }
+        open={open}
+        onOpenChange={onOpenChange}
+      >
+        <Title>
+          {t("frontend.user_phone.remove_confirmation_modal.body")}
+        </Title>
+
+        {status === "INCORRECT_PASSWORD" && (
+          <Alert
+            type="critical"
+            title={t("frontend.user_phone.incorrect_password")}
+          />
+        )}
+
+        {status === "REAUTHENTICATION_REQUIRED" && <ReauthenticationRequired />}
+
+        <div className="flex flex-col gap-4">
+          <Button
+            kind="primary"
+            type="button"
+            destructive
+            onClick={onRemoveClick}
+            disabled={removePhoneNumber.isPending}
+            Icon={removePhoneNumber.isPending ? undefined : IconDelete}
+          >
+            {!!removePhoneNumber.isPending && <LoadingSpinner inline />}
+            {t("frontend.user_phone.remove_confirmation_modal.action")}
+          </Button>
+          <Close asChild>
+            <Button disabled={removePhoneNumber.isPending} kind="tertiary">
+              {t("action.cancel")}
+            </Button>
+          </Close>
+        </div>
+      </Dialog>
+    </>
+  );
+};
+
+// This component shows the phone number of the current user, and lets them
+// add, confirm or remove it
+const UserPhone: React.FC<{ shouldPromptPassword: boolean }> = ({
+  shouldPromptPassword,
+}) => {
+  const { t } = useTranslation();
+  const result = useSuspenseQuery(query);
+  if (result.data.viewer.__typename !== "User") throw notFound();
+  const phone = result.data.viewer.phone;
+
+  if (!phone) {
+    return <SetPhoneNumberForm shouldPromptPassword={shouldPromptPassword} />;
+  }
+
+  return (
+    <div className="flex flex-col gap-4">
+      <div className="flex items-center gap-2">
+        <div className="flex flex-1 flex-col">
+          <Text size="md" weight="semibold">
+            {phone.phoneNumber}
+          </Text>
+          {!phone.confirmedAt && (
+            <Text size="sm" className="text-secondary">
+              {t("frontend.user_phone.unconfirmed")}
+            </Text>
+          )}
+        </div>
+
+        <RemovePhoneNumberButton shouldPromptPassword={shouldPromptPassword} />
+      </div>
+
+      {!phone.confirmedAt && (
+        <ConfirmPhoneNumberForm phoneNumber={phone.phoneNumber} />
+      )}
+    </div>
+  );
+};
+
+export default UserPhone;
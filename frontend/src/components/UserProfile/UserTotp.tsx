@@ -0,0 +1,515 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+import {
+  queryOptions,
+  useMutation,
+  useQueryClient,
+  useSuspenseQuery,
+} from "@tanstack/react-query";
+import { notFound } from "@tanstack/react-router";
+import IconDelete from "@vector-im/compound-design-tokens/assets/web/icons/delete";
+import { Alert, Button, Form, Text } from "@vector-im/compound-web";
+import { useCallback, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { graphql } from "../../gql";
+import { graphqlRequest } from "../../graphql";
+import DateTime from "../DateTime";
+import { Close, Dialog, Title } from "../Dialog";
+import LoadingSpinner from "../LoadingSpinner";
+import PasswordConfirmationModal, {
+  usePasswordConfirmation,
+} from "../PasswordConfirmation";
+import ReauthenticationRequired from "../ReauthenticationRequired";
+
+const QUERY = graphql(/* GraphQL */ `
+  query UserTotp {
+    viewer {
+      __typename
+      ... on User {
+        id
+        totp {
+          id
+          createdAt
+          lastUsedAt
+        }
+        recoveryCodesLeft
+      }
+    }
+  }
+`);
+
+export const query = queryOptions({
+  queryKey: ["userTotp"],
+  queryFn: ({ signal }) => graphqlRequest({ query: QUERY, signal }),
+});
+
+const START_TOTP_ENROLLMENT_MUTATION = graphql(/* GraphQL */ `
+  mutation StartTotpEnrollment {
+    startTotpEnrollment {
+      status
+      secret
+      qrCode
+    }
+  }
+`);
+
+const CONFIRM_TOTP_ENROLLMENT_MUTATION = graphql(/* GraphQL */ `
+  mutation ConfirmTotpEnrollment($code: String!) {
+    confirmTotpEnrollment(input: { code: $code }) {
+      status
+      recoveryCodes
+    }
+  }
+`);
+
+const REMOVE_TOTP_MUTATION = graphql(/* GraphQL */ `
+  mutation RemoveTotp($password: String) {
+    removeTotp(input: { password: $password }) {
+      status
+    }
+  }
+`);
+
+const REGENERATE_RECOVERY_CODES_MUTATION = graphql(/* GraphQL */ `
+  mutation RegenerateRecoveryCodes($password: String) {
+    regenerateRecoveryCodes(input: { password: $password }) {
+      status
+      recoveryCodes
+    }
+  }
+`);
+
+// Shows a set of recovery codes, which are only returned once by the server
+const RecoveryCodes: React.FC<{ codes: string[] }> = ({ codes }) => {
+  const { t } = useTranslation();
+
+  return (
+    <>
+      <Text size="md" className="text-secondary">
+        {t("frontend.user_totp.recovery_codes_description")}
+      </Text>
+
+      <ul className="grid grid-cols-2 gap-2 text-center">
+        {codes.map((code) => (
+          <li key={code}>
+            <code>{code}</code>
+          </li>
+        ))}
+      </ul>
+    </>
+  );
+};
+
+const EnrollTotpForm: React.FC = () => {
+  const { t } = useTranslation();
+  const queryClient = useQueryClient();
+
+  const start = useMutation({
+    mutationFn: () => graphqlRequest({ query: START_TOTP_ENROLLMENT_MUTATION }),
+    onSuccess: (data) => {
+      if (data.startTotpEnrollment.status === "ALREADY_ENROLLED") {
+        queryClient.invalidateQueries({ queryKey: ["userTotp"] });
+      }
+    },
+  });
+
+  const confirm = useMutation({
+    mutationFn: (code: string) =>
+      graphqlRequest({
+        query: CONFIRM_TOTP_ENROLLMENT_MUTATION,
+        variables: { code },
+      }),
+  });
+
+  const onFormSubmit = (e: React.FormEvent<HTMLFormElement>): void => {
+    e.preventDefault();
+    const form = e.currentTarget;
+    const formData = new FormData(form);
+    const code = formData.get("code") as string;
+    confirm.mutateAsync(code).finally(() => form.reset());
+  };
+
+  const enrollment = start.data?.startTotpEnrollment;
+  const status = confirm.data?.confirmTotpEnrollment.status ?? null;
+  const recoveryCodes = confirm.data?.confirmTotpEnrollment.recoveryCodes;
+
+  // Once the second factor is confirmed, show the recovery codes before
+  // refreshing the list, as they are only returned once
+  if (status === "CONFIRMED" && recoveryCodes) {
+    return (
+      <>
+        <RecoveryCodes codes={recoveryCodes} />
+
+        <Button
+          type="button"
+          kind="primary"
+          onClick={() =>
+            queryClient.invalidateQueries({ queryKey: ["userTotp"] })
+          }
+        >
+          {t("action.continue")}
+        </Button>
+      </>
+    );
+  }
+
+  if (enrollment?.status !== "STARTED") {
+    return (
+      <>
+        <Text size="md" className="text-secondary">
+          {t("frontend.user_totp.not_enrolled")}
+        </Text>
+
+        {enrollment?.status === "REAUTHENTICATION_REQUIRED" && (
+          <ReauthenticationRequired />
+        )}
+
+        <Button
+          type="button"
+          kind="secondary"
+          onClick={() => start.mutate()}
+          disabled={start.isPending}
+        >
+          {start.isPending && <LoadingSpinner inline />}
+          {t("frontend.user_totp.enroll_button")}
+        </Button>
+      </>
+    );
+  }
+
+  return (
+    <Form.Root onSubmit={onFormSubmit}>
+      <Text size="md" className="text-secondary">
+        {t("frontend.user_totp.scan_qr_code")}
+      </Text>
+
+      {enrollment.qrCode && (
+        <img
+          className="self-center w-[200px] h-[200px]"
+          src={enrollment.qrCode}
+          alt=""
+        />
+      )}
+
+      <Text size="sm" className="text-secondary">
+        {t("frontend.user_totp.secret_help")}{" "}
+        <code className="break-all">{enrollment.secret}</code>
+      </Text>
+
+      {status === "NOT_FOUND" && (
+        <Alert
+          type="critical"
+          title={t("frontend.user_totp.enrollment_expired")}
+        />
+      )}
+
+      <Form.Field
+        name="code"
+        serverInvalid={status === "INVALID_CODE"}
+        className="self-center mb-4"
+      >
+        <Form.Label>{t("frontend.user_totp.code_field_label")}</Form.Label>
+        <Form.MFAControl />
+
+        {status === "INVALID_CODE" && (
+          <Form.ErrorMessage>
+            {t("frontend.user_totp.code_field_error")}
+          </Form.ErrorMessage>
+        )}
+
+        <Form.ErrorMessage match="patternMismatch">
+          {t("frontend.verify_email.code_field_wrong_shape")}
+        </Form.ErrorMessage>
+      </Form.Field>
+
+      <Form.Submit type="submit" disabled={confirm.isPending}>
+        {confirm.isPending && <LoadingSpinner inline />}
+        {t("action.continue")}
+      </Form.Submit>
+    </Form.Root>
+  );
+};
+
+const RemoveTotpButton: React.FC<{ shouldPromptPassword: boolean }> = ({
+  shouldPromptPassword,
+}) => {
+  const { t } = useTranslation();
+  const [open, setOpen] = useState(false);
+  const queryClient = useQueryClient();
+  const [promptPassword, passwordConfirmationRef] = usePasswordConfirmation();
+
+  const removeTotp = useMutation({
+    mutationFn: (password?: string) =>
+      graphqlRequest({
+        query: REMOVE_TOTP_MUTATION,
+        variables: { password },
+      }),
+
+    onSuccess: (data) => {
+      queryClient.invalidateQueries({ queryKey: ["userTotp"] });
+
+      // Don't close the modal unless the second factor was removed (or not
+      // found)
+      if (
+        data.removeTotp.status !== "NOT_FOUND" &&
+        data.removeTotp.status !== "REMOVED"
+      ) {
+        return;
+      }
+
+      setOpen(false);
+    },
+  });
+
+  const onRemoveClick = useCallback(async (): Promise<void> => {
+    let password = undefined;
+    if (shouldPromptPassword) {
+      password = await promptPassword();
+    }
+    removeTotp.mutate(password);
+  }, [promptPassword, shouldPromptPassword, removeTotp.mutate]);
+
+  const onOpenChange = useCallback(
+    (open: boolean) => {
+      // Don't change the modal state if the mutation is pending
+      if (removeTotp.isPending) return;
+      removeTotp.reset();
+      setOpen(open);
+    },
+    [removeTotp.isPending, removeTotp.reset],
+  );
+
+  const status = removeTotp.data?.removeTotp.status ?? null;
+
+  return (
+    <>
+      <PasswordConfirmationModal
+        title={t(
+          "frontend.user_totp.remove_confirmation_modal.password_confirmation",
+        )}
+        destructive
+        ref={passwordConfirmationRef}
+      />
+      <Dialog
+        trigger={
+          <Button kind="secondary" destructive size="sm" Icon={IconDelete}>
+            {t("frontend.user_totp.remove_button")}
+          </Button>
+        }
+        open={open}
+        onOpenChange={onOpenChange}
+      >
+        <Title>{t("frontend.user_totp.remove_confirmation_modal.body")}</Title>
+
+        {status === "INCORRECT_PASSWORD" && (
+          <Alert
+            type="critical"
+            title={t(
+              "frontend.user_totp.remove_confirmation_modal.incorrect_password",
+            )}
+          />
+        )}
+
+        {status === "REAUTHENTICATION_REQUIRED" && <ReauthenticationRequired />}
+
+        {status === "REQUIRED" && (
+          <Alert
+            type="critical"
+            title={t("frontend.user_totp.remove_confirmation_modal.required")}
+          />
+        )}
+
+        <div className="flex flex-col gap-4">
+          <Button
+            kind="primary"
+            type="button"
+            destructive
+            onClick={onRemoveClick}
+            disabled={removeTotp.isPending}
+            Icon={removeTotp.isPending ? undefined : IconDelete}
+          >
+            {!!removeTotp.isPending && <LoadingSpinner inline />}
+            {t("frontend.user_totp.remove_confirmation_modal.action")}
+          </Button>
+          <Close asChild>
+            <Button disabled={removeTotp.isPending} kind="tertiary">
+              {t("action.cancel")}
+            </Button>
+          </Close>
+        </div>
+      </Dialog>
+    </>
+  );
+};
+
+const RegenerateRecoveryCodesButton: React.FC<{
+  shouldPromptPassword: boolean;
+}> = ({ shouldPromptPassword }) => {
+  const { t } = useTranslation();
+  const [open, setOpen] = useState(false);
+  const queryClient = useQueryClient();
+  const [promptPassword, passwordConfirmationRef] = usePasswordConfirmation();
+
+  const regenerate = useMutation({
+    mutationFn: (password?: string) =>
+      graphqlRequest({
+        query: REGENERATE_RECOVERY_CODES_MUTATION,
+        variables: { password },
+      }),
+
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["userTotp"] });
+    },
+  });
+
+  const onRegenerateClick = useCallback(async (): Promise<void> => {
+    let password = undefined;
+    if (shouldPromptPassword) {
+      password = await promptPassword();
+    }
+    regenerate.mutate(password);
+  }, [promptPassword, shouldPromptPassword, regenerate.mutate]);
+
+  const onOpenChange = useCallback(
+    (open: boolean) => {
+      // Don't change the modal state if the mutation is pending
+      if (regenerate.isPending) return;
+      regenerate.reset();
+      setOpen(open);
+    },
+    [regenerate.isPending, regenerate.reset],
+  );
+
+  const status = regenerate.data?.regenerateRecoveryCodes.status ?? null;
+  const recoveryCodes = regenerate.data?.regenerateRecoveryCodes.recoveryCodes;
+
+  return (
+    <>
+      <PasswordConfirmationModal
+        title={t(
+          "frontend.user_totp.regenerate_confirmation_modal.password_confirmation",
+        )}
+        ref={passwordConfirmationRef}
+      />
+      <Dialog
+        trigger={
+          <Button kind="secondary" size="sm">
+            {t("frontend.user_totp.regenerate_button")}
+          </Button>
+        }
+        open={open}
+        onOpenChange={onOpenChange}
+      >
+        <Title>
+          {t("frontend.user_totp.regenerate_confirmation_modal.title")}
+        </Title>
+
+        {status === "REGENERATED" && recoveryCodes ? (
+          <>
+            <RecoveryCodes codes={recoveryCodes} />
+
+            <Close asChild>
+              <Button kind="primary">{t("action.close")}</Button>
+            </Close>
+          </>
+        ) : (
+          <>
+            <Text size="md" className="text-secondary">
+              {t("frontend.user_totp.regenerate_confirmation_modal.body")}
+            </Text>
+
+            {status === "INCORRECT_PASSWORD" && (
+              <Alert
+                type="critical"
+                title={t(
+                  "frontend.user_totp.regenerate_confirmation_modal.incorrect_password",
+                )}
+              />
+            )}
+
+            {status === "REAUTHENTICATION_REQUIRED" && (
+              <ReauthenticationRequired />
+            )}
+
+            <div className="flex flex-col gap-4">
+              <Button
+                kind="primary"
+                type="button"
+                onClick={onRegenerateClick}
+                disabled={regenerate.isPending}
+              >
+                {!!regenerate.isPending && <LoadingSpinner inline />}
+                {t("frontend.user_totp.regenerate_confirmation_modal.action")}
+              </Button>
+              <Close asChild>
+                <Button disabled={regenerate.isPending} kind="tertiary">
+                  {t("action.cancel")}
+                </Button>
+              </Close>
+            </div>
+          </>
+        )}
+      </Dialog>
+    </>
+  );
+};
+
+// This component shows whether the current user has a TOTP second factor, and
+// lets them add or remove it
+const UserTotp: React.FC<{ shouldPromptPassword: boolean }> = ({
+  shouldPromptPassword,
+}) => {
+  const { t } = useTranslation();
+  const result = useSuspenseQuery(query);
+  if (result.data.viewer.__typename !== "User") throw notFound();
+  const totp = result.data.viewer.totp;
+  const recoveryCodesLeft = result.data.viewer.recoveryCodesLeft;
+
+  if (!totp) {
+    return <EnrollTotpForm />;
+  }
+
+  return (
+    <div className="flex flex-col gap-4">
+      <div className="flex items-center gap-2">
+        <div className="flex flex-1 flex-col">
+          <Text size="md" weight="semibold">
+            {t("frontend.user_totp.enrolled")}
+          </Text>
+          <Text size="sm" className="text-secondary">
+            {t("frontend.user_totp.created_at")}{" "}
+            <DateTime datetime={totp.createdAt} />
+            {" · "}
+            {totp.lastUsedAt ? (
+              <>
+                {t("frontend.user_totp.last_used_at")}{" "}
+                <DateTime datetime={totp.lastUsedAt} />
+              </>
+            ) : (
+              t("frontend.user_totp.never_used")
+            )}
+          </Text>
+        </div>
+
+        <RemoveTotpButton shouldPromptPassword={shouldPromptPassword} />
+      </div>
+
+      <div className="flex items-center gap-2">
+        <Text size="sm" className="flex-1 text-secondary">
+          {t("frontend.user_totp.recovery_codes_left", {
+            count: recoveryCodesLeft,
+          })}
+        </Text>
+
+        <RegenerateRecoveryCodesButton
+          shouldPromptPassword={shouldPromptPassword}
+        />
+      </div>
+    </div>
+  );
+};
+
+export default UserTotp;
@@ -0,0 +1,194 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+import {
+  queryOptions,
+  useMutation,
+  useQueryClient,
+  useSuspenseQuery,
+} from "@tanstack/react-query";
+import { notFound } from "@tanstack/react-router";
+import IconDelete from "@vector-im/compound-design-tokens/assets/web/icons/delete";
+import { Button, IconButton, Text, Tooltip } from "@vector-im/compound-web";
+import { useCallback, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { graphql } from "../../gql";
+import { graphqlRequest } from "../../graphql";
+import DateTime from "../DateTime";
+import { Close, Dialog, Title } from "../Dialog";
+import LoadingSpinner from "../LoadingSpinner";
+
+const QUERY = graphql(/* GraphQL */ `
+  query UserTrustedDeviceList {
+    viewer {
+      __typename
+      ... on User {
+        id
+        trustedDevices {
+          id
+          createdAt
+          expiresAt
+          lastUsedAt
+          userAgent {
+            name
+            os
+          }
+        }
+      }
+    }
+  }
+`);
+
+export const query = queryOptions({
+  queryKey: ["userTrustedDevices"],
+  queryFn: ({ signal }) => graphqlRequest({ query: QUERY, signal }),
+});
+
+const REVOKE_TRUSTED_DEVICE_MUTATION = graphql(/* GraphQL */ `
+  mutation RevokeTrustedDevice($id: ID!) {
+    revokeTrustedDevice(input: { userTrustedDeviceId: $id }) {
+      status
+    }
+  }
+`);
+
+const UserTrustedDevice: React.FC<{
+  device: {
+    id: string;
+    createdAt: string;
+    expiresAt: string;
+    lastUsedAt?: string | null;
+    userAgent?: { name?: string | null; os?: string | null } | null;
+  };
+}> = ({ device }) => {
+  const { t } = useTranslation();
+  const [open, setOpen] = useState(false);
+  const queryClient = useQueryClient();
+
+  const revokeTrustedDevice = useMutation({
+    mutationFn: (id: string) =>
+      graphqlRequest({
+        query: REVOKE_TRUSTED_DEVICE_MUTATION,
+        variables: { id },
+      }),
+
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["userTrustedDevices"] });
+      setOpen(false);
+    },
+  });
+
+  const onOpenChange = useCallback(
+    (open: boolean) => {
+      // Don't change the modal state if the mutation is pending
+      if (revokeTrustedDevice.isPending) return;
+      revokeTrustedDevice.reset();
+      setOpen(open);
+    },
+    [revokeTrustedDevice.isPending, revokeTrustedDevice.reset],
+  );
+
+  const browser =
+    device.userAgent?.name ?? t("frontend.session.unknown_browser");
+  const name = device.userAgent?.os
+    ? t("frontend.session.name_for_platform", {
+        name: browser,
+        platform: device.userAgent.os,
+      })
+    : browser;
+
+  return (
+    <div className="flex items-center gap-2">
+      <div className="flex flex-1 flex-col">
+        <Text size="md" weight="semibold">
+          {name}
+        </Text>
+        <Text size="sm" className="text-secondary">
+          {t("frontend.user_trusted_device_list.created_at")}{" "}
+          <DateTime datetime={device.createdAt} />
+          {" · "}
+          {device.lastUsedAt ? (
+            <>
+              {t("frontend.user_trusted_device_list.last_used_at")}{" "}
+              <DateTime datetime={device.lastUsedAt} />
+            </>
+          ) : (
+            t("frontend.user_trusted_device_list.never_used")
+          )}
+          {" · "}
+          {t("frontend.user_trusted_device_list.expires_at")}{" "}
+          <DateTime datetime={device.expiresAt} />
+        </Text>
+      </div>
+
+      <Dialog
+        trigger={
+          <Tooltip
+            label={t("frontend.user_trusted_device_list.revoke_button_title")}
+          >
+            <IconButton type="button" size="var(--cpd-space-8x)">
+              <IconDelete />
+            </IconButton>
+          </Tooltip>
+        }
+        open={open}
+        onOpenChange={onOpenChange}
+      >
+        <Title>
+          {t(
+            "frontend.user_trusted_device_list.revoke_confirmation_modal.body",
+            { name },
+          )}
+        </Title>
+
+        <div className="flex flex-col gap-4">
+          <Button
+            kind="primary"
+            type="button"
+            destructive
+            onClick={() => revokeTrustedDevice.mutate(device.id)}
+            disabled={revokeTrustedDevice.isPending}
+            Icon={revokeTrustedDevice.isPending ? undefined : IconDelete}
+          >
+            {!!revokeTrustedDevice.isPending && <LoadingSpinner inline />}
+            {t(
+              "frontend.user_trusted_device_list.revoke_confirmation_modal.action",
+            )}
+          </Button>
+          <Close asChild>
+            <Button disabled={revokeTrustedDevice.isPending} kind="tertiary">
+              {t("action.cancel")}
+            </Button>
+          </Close>
+        </div>
+      </Dialog>
+    </div>
+  );
+};
+
+// This component lists the browsers in which the current user skips their
+// second factor, and lets them stop trusting them
+const UserTrustedDeviceList: React.FC = () => {
+  const { t } = useTranslation();
+  const result = useSuspenseQuery(query);
+  if (result.data.viewer.__typename !== "User") throw notFound();
+  const devices = result.data.viewer.trustedDevices;
+
+  return (
+    <>
+      {devices.length === 0 && (
+        <Text size="md" className="text-secondary">
+          {t("frontend.user_trusted_device_list.no_trusted_devices")}
+        </Text>
+      )}
+
+      {devices.map((device) => (
+        <UserTrustedDevice key={device.id} device={device} />
+      ))}
+    </>
+  );
+};
+
+export default UserTrustedDeviceList;
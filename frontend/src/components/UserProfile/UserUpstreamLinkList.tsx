@@ -0,0 +1,272 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE files in the repository root for full details.
+
+import {
+  queryOptions,
+  useMutation,
+  useQueryClient,
+  useSuspenseQuery,
+} from "@tanstack/react-query";
+import { notFound } from "@tanstack/react-router";
+import IconDelete from "@vector-im/compound-design-tokens/assets/web/icons/delete";
+import IconLink from "@vector-im/compound-design-tokens/assets/web/icons/link";
+import {
+  Button,
+  ErrorMessage,
+  IconButton,
+  Text,
+  Tooltip,
+} from "@vector-im/compound-web";
+import { useCallback, useState } from "react";
+import { useTranslation } from "react-i18next";
+import { graphql } from "../../gql";
+import { graphqlRequest } from "../../graphql";
+import DateTime from "../DateTime";
+import { Close, Dialog, Title } from "../Dialog";
+import LoadingSpinner from "../LoadingSpinner";
+import PasswordConfirmationModal, {
+  usePasswordConfirmation,
+} from "../PasswordConfirmation";
+import ReauthenticationRequired from "../ReauthenticationRequired";
+
+const QUERY = graphql(/* GraphQL */ `
+  query UserUpstreamLinkList {
+    viewer {
+      __typename
+      ... on User {
+        id
+        upstreamOauth2Links(first: 100) {
+          nodes {
+            id
+            createdAt
+            humanAccountName
+            provider {
+              id
+              humanName
+            }
+          }
+        }
+      }
+    }
+
+    upstreamOauth2Providers(first: 100) {
+      nodes {
+        id
+        humanName
+        linkUrl
+      }
+    }
+  }
+`);
+
+export const query = queryOptions({
+  queryKey: ["userUpstreamLinks"],
+  queryFn: ({ signal }) => graphqlRequest({ query: QUERY, signal }),
+});
+
+const REMOVE_UPSTREAM_LINK_MUTATION = graphql(/* GraphQL */ `
+  mutation RemoveUpstreamLink($id: ID!, $password: String) {
+    removeUpstreamLink(input: { upstreamLinkId: $id, password: $password }) {
+      status
+    }
+  }
+`);
+
+const UpstreamLink: React.FC<{
+  link: {
+    id: string;
+    createdAt: string;
+    humanAccountName?: string | null;
+    provider: { humanName?: string | null };
+  };
+  shouldPromptPassword: boolean;
+}> = ({ link, shouldPromptPassword }) => {
+  const { t } = useTranslation();
+  const [open, setOpen] = useState(false);
+  const queryClient = useQueryClient();
+  const [promptPassword, passwordConfirmationRef] = usePasswordConfirmation();
+
+  const providerName =
+    link.provider.humanName ?? t("frontend.user_upstream_link_list.unknown");
+
+  const removeLink = useMutation({
+    mutationFn: ({ id, password }: { id: string; password?: string }) =>
+      graphqlRequest({
+        query: REMOVE_UPSTREAM_LINK_MUTATION,
+        variables: { id, password },
+      }),
+
+    onSuccess: (data) => {
+      queryClient.invalidateQueries({ queryKey: ["userUpstreamLinks"] });
+
+      // Don't close the modal unless the link was removed (or not found)
+      if (
+        data.removeUpstreamLink.status !== "NOT_FOUND" &&
+        data.removeUpstreamLink.status !== "REMOVED"
+      ) {
+        return;
+      }
+
+      setOpen(false);
+    },
+  });
+
+  const onRemoveClick = useCallback(async (): Promise<void> => {
+    let password = undefined;
+    if (shouldPromptPassword) {
+      password = await promptPassword();
+    }
+    removeLink.mutate({ id: link.id, password });
+  }, [link.id, promptPassword, shouldPromptPassword, removeLink.mutate]);
+
+  const onOpenChange = useCallback(
+    (open: boolean) => {
+      // Don't change the modal state if the mutation is pending
+      if (removeLink.isPending) return;
+      removeLink.reset();
+      setOpen(open);
+    },
+    [removeLink.isPending, removeLink.reset],
+  );
+
+  const status = removeLink.data?.removeUpstreamLink.status ?? null;
+
+  return (
+    <>
+      <PasswordConfirmationModal
+        title={t(
+          "frontend.user_upstream_link_list.remove_confirmation_modal.password_confirmation",
+        )}
+        destructive
+        ref={passwordConfirmationRef}
+      />
+      <div className="flex items-center gap-2">
+        <div className="flex flex-1 flex-col">
+          <Text size="md" weight="semibold">
+            {providerName}
+          </Text>
+          <Text size="sm" className="text-secondary">
+            {link.humanAccountName && <>{link.humanAccountName} · </>}
+            {t("frontend.user_upstream_link_list.created_at")}{" "}
+            <DateTime datetime={link.createdAt} />
+          </Text>
+        </div>
+
+        <Dialog
+          trigger={
+            <Tooltip
+              label={t(
+                "frontend.user_upstream_link_list.remove_button_title",
+              )}
+            >
+              <IconButton type="button" size="var(--cpd-space-8x)">
+                <IconDelete />
+              </IconButton>
+            </Tooltip>
+          }
+          open={open}
+          onOpenChange={onOpenChange}
+        >
+          <Title>
+            {t(
+              "frontend.user_upstream_link_list.remove_confirmation_modal.body",
+              { name: providerName },
+            )}
+          </Title>
+
+          {status === "INCORRECT_PASSWORD" && (
+            <ErrorMessage>
+              {t(
+                "frontend.user_upstream_link_list.remove_confirmation_modal.incorrect_password",
+              )}
+            </ErrorMessage>
+          )}
+
+          {status === "LAST_LOGIN_METHOD" && (
+            <ErrorMessage>
+              {t(
+                "frontend.user_upstream_link_list.remove_confirmation_modal.last_login_method",
+              )}
+            </ErrorMessage>
+          )}
+
+          {status === "REAUTHENTICATION_REQUIRED" && (
+            <ReauthenticationRequired />
+          )}
+
+          <div className="flex flex-col gap-4">
+            <Button
+              kind="primary"
+              type="button"
+              destructive
+              onClick={onRemoveClick}
+              disabled={removeLink.isPending}
+              Icon={removeLink.isPending ? undefined : IconDelete}
+            >
+              {!!removeLink.isPending && <LoadingSpinner inline />}
+              {t(
+                "frontend.user_upstream_link_list.remove_confirmation_modal.action",
+              )}
+            </Button>
+            <Close asChild>
+              <Button disabled={removeLink.isPending} kind="tertiary">
+                {t("action.cancel")}
+              </Button>
+            </Close>
+          </div>
+        </Dialog>
+      </div>
+    </>
+  );
+};
+
+// This component lists the upstream accounts linked to the current user, and
+// lets them link another provider or unlink one
+const UserUpstreamLinkList: React.FC<{ shouldPromptPassword: boolean }> = ({
+  shouldPromptPassword,
+}) => {
+  const { t } = useTranslation();
+  const result = useSuspenseQuery(query);
+  if (result.data.viewer.__typename !== "User") throw notFound();
+  const links = result.data.viewer.upstreamOauth2Links.nodes;
+  const providers = result.data.upstreamOauth2Providers.nodes;
+
+  return (
+    <>
+      {links.length === 0 && (
+        <Text size="md" className="text-secondary">
+          {t("frontend.user_upstream_link_list.no_links")}
+        </Text>
+      )}
+
+      {links.map((link) => (
+        <UpstreamLink
+          key={link.id}
+          link={link}
+          shouldPromptPassword={shouldPromptPassword}
+        />
+      ))}
+
+      {providers.map((provider) => (
+        <Button
+          key={provider.id}
+          as="a"
+          href={provider.linkUrl}
+          kind="secondary"
+          size="sm"
+          Icon={IconLink}
+        >
+          {t("frontend.user_upstream_link_list.link_button", {
+            name:
+              provider.humanName ??
+              t("frontend.user_upstream_link_list.unknown"),
+          })}
+        </Button>
+      ))}
+    </>
+  );
+};
+
+export default UserUpstreamLinkList;
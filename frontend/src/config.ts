@@ -7,6 +7,7 @@
 type AppConfig = {
   root: string;
   graphqlEndpoint: string;
+  reauthEndpoint: string;
 };
 
 interface IWindow {
@@ -17,6 +18,7 @@ const config: AppConfig = (typeof window !== "undefined" &&
   (window as IWindow).APP_CONFIG) || {
   root: "/",
   graphqlEndpoint: "/graphql",
+  reauthEndpoint: "/reauth?kind=manage_account",
 };
 
 export default config;
@@ -16,7 +16,7 @@ import * as types from './graphql';
  */
 type Documents = {
     "\n  fragment AccountDeleteButton_user on User {\n    username\n    hasPassword\n    matrix {\n      mxid\n      displayName\n    }\n  }\n": typeof types.AccountDeleteButton_UserFragmentDoc,
-    "\n  fragment AccountDeleteButton_siteConfig on SiteConfig {\n    passwordLoginEnabled\n  }\n": typeof types.AccountDeleteButton_SiteConfigFragmentDoc,
+    "\n  fragment AccountDeleteButton_siteConfig on SiteConfig {\n    passwordLoginEnabled\n    accountDeletionGracePeriodDays\n  }\n": typeof types.AccountDeleteButton_SiteConfigFragmentDoc,
     "\n  mutation DeactivateUser($hsErase: Boolean!, $password: String) {\n    deactivateUser(input: { hsErase: $hsErase, password: $password }) {\n      status\n    }\n  }\n": typeof types.DeactivateUserDocument,
     "\n  fragment PasswordChange_siteConfig on SiteConfig {\n    passwordChangeAllowed\n  }\n": typeof types.PasswordChange_SiteConfigFragmentDoc,
     "\n  fragment BrowserSession_session on BrowserSession {\n    id\n    createdAt\n    finishedAt\n    ...EndBrowserSessionButton_session\n    userAgent {\n      deviceType\n      name\n      os\n      model\n    }\n    lastActiveAt\n  }\n": typeof types.BrowserSession_SessionFragmentDoc,
@@ -34,22 +34,42 @@ type Documents = {
     "\n  mutation EndOAuth2Session($id: ID!) {\n    endOauth2Session(input: { oauth2SessionId: $id }) {\n      status\n      oauth2Session {\n        id\n      }\n    }\n  }\n": typeof types.EndOAuth2SessionDocument,
     "\n  fragment BrowserSession_detail on BrowserSession {\n    id\n    createdAt\n    finishedAt\n    ...EndBrowserSessionButton_session\n    userAgent {\n      name\n      model\n      os\n    }\n    lastActiveIp\n    lastActiveAt\n    lastAuthentication {\n      id\n      createdAt\n    }\n    user {\n      id\n      username\n    }\n  }\n": typeof types.BrowserSession_DetailFragmentDoc,
     "\n  mutation SetCompatSessionName($sessionId: ID!, $displayName: String!) {\n    setCompatSessionName(input: { compatSessionId: $sessionId, humanName: $displayName }) {\n      status\n    }\n  }\n": typeof types.SetCompatSessionNameDocument,
-    "\n  fragment CompatSession_detail on CompatSession {\n    id\n    createdAt\n    deviceId\n    finishedAt\n    lastActiveIp\n    lastActiveAt\n    humanName\n\n    ...EndCompatSessionButton_session\n\n    userAgent {\n      name\n      os\n      model\n    }\n\n    ssoLogin {\n      id\n      redirectUri\n    }\n  }\n": typeof types.CompatSession_DetailFragmentDoc,
+    "\n  fragment CompatSession_detail on CompatSession {\n    id\n    createdAt\n    deviceId\n    finishedAt\n    lastActiveIp\n    lastActiveAt\n    humanName\n    impersonated\n\n    ...EndCompatSessionButton_session\n\n    userAgent {\n      name\n      os\n      model\n    }\n\n    ssoLogin {\n      id\n      redirectUri\n    }\n  }\n": typeof types.CompatSession_DetailFragmentDoc,
     "\n  mutation SetOAuth2SessionName($sessionId: ID!, $displayName: String!) {\n    setOauth2SessionName(input: { oauth2SessionId: $sessionId, humanName: $displayName }) {\n      status\n    }\n  }\n": typeof types.SetOAuth2SessionNameDocument,
-    "\n  fragment OAuth2Session_detail on Oauth2Session {\n    id\n    scope\n    createdAt\n    finishedAt\n    lastActiveIp\n    lastActiveAt\n    humanName\n\n    ...EndOAuth2SessionButton_session\n\n    userAgent {\n      name\n      model\n      os\n    }\n\n    client {\n      id\n      clientId\n      clientName\n      clientUri\n      logoUri\n    }\n  }\n": typeof types.OAuth2Session_DetailFragmentDoc,
+    "\n  fragment OAuth2Session_detail on Oauth2Session {\n    id\n    scope\n    createdAt\n    finishedAt\n    lastActiveIp\n    lastActiveAt\n    humanName\n    offlineAccessGrantedAt\n\n    ...EndOAuth2SessionButton_session\n\n    userAgent {\n      name\n      model\n      os\n    }\n\n    client {\n      id\n      clientId\n      clientName\n      clientUri\n      logoUri\n    }\n  }\n": typeof types.OAuth2Session_DetailFragmentDoc,
     "\n  fragment UserEmail_email on UserEmail {\n    id\n    email\n  }\n": typeof types.UserEmail_EmailFragmentDoc,
     "\n  mutation RemoveEmail($id: ID!, $password: String) {\n    removeEmail(input: { userEmailId: $id, password: $password }) {\n      status\n\n      user {\n        id\n      }\n    }\n  }\n": typeof types.RemoveEmailDocument,
-    "\n  fragment UserGreeting_user on User {\n    id\n    matrix {\n      mxid\n      displayName\n    }\n  }\n": typeof types.UserGreeting_UserFragmentDoc,
-    "\n  fragment UserGreeting_siteConfig on SiteConfig {\n    displayNameChangeAllowed\n  }\n": typeof types.UserGreeting_SiteConfigFragmentDoc,
-    "\n  mutation SetDisplayName($userId: ID!, $displayName: String) {\n    setDisplayName(input: { userId: $userId, displayName: $displayName }) {\n      status\n    }\n  }\n": typeof types.SetDisplayNameDocument,
+    "\n  mutation SetPrimaryEmail($id: ID!) {\n    setPrimaryEmail(input: { userEmailId: $id }) {\n      status\n\n      user {\n        id\n      }\n    }\n  }\n": typeof types.SetPrimaryEmailDocument,
+    "\n  fragment UserGreeting_user on User {\n    id\n    matrix {\n      mxid\n      displayName\n      avatarUrl\n    }\n  }\n": typeof types.UserGreeting_UserFragmentDoc,
+    "\n  fragment UserGreeting_siteConfig on SiteConfig {\n    displayNameChangeAllowed\n    avatarChangeAllowed\n  }\n": typeof types.UserGreeting_SiteConfigFragmentDoc,
+    "\n  mutation SetDisplayName($userId: ID!, $displayName: String) {\n    setDisplayName(input: { userId: $userId, displayName: $displayName }) {\n      status\n      violations\n    }\n  }\n": typeof types.SetDisplayNameDocument,
+    "\n  mutation SetAvatar($userId: ID!, $avatar: Upload) {\n    setAvatar(input: { userId: $userId, avatar: $avatar }) {\n      status\n    }\n  }\n": typeof types.SetAvatarDocument,
     "\n  fragment AddEmailForm_user on User {\n    hasPassword\n  }\n": typeof types.AddEmailForm_UserFragmentDoc,
     "\n  fragment AddEmailForm_siteConfig on SiteConfig {\n    passwordLoginEnabled\n  }\n": typeof types.AddEmailForm_SiteConfigFragmentDoc,
     "\n  mutation AddEmail($email: String!, $password: String, $language: String!) {\n    startEmailAuthentication(input: {\n      email: $email,\n      password: $password,\n      language: $language\n    }) {\n      status\n      violations\n      authentication {\n        id\n      }\n    }\n  }\n": typeof types.AddEmailDocument,
-    "\n  query UserEmailList(\n    $first: Int\n    $after: String\n    $last: Int\n    $before: String\n  ) {\n    viewer {\n      __typename\n      ... on User {\n        emails(first: $first, after: $after, last: $last, before: $before) {\n          edges {\n            cursor\n            node {\n              ...UserEmail_email\n            }\n          }\n          totalCount\n          pageInfo {\n            hasNextPage\n            hasPreviousPage\n            startCursor\n            endCursor\n          }\n        }\n      }\n    }\n  }\n": typeof types.UserEmailListDocument,
+    "\n  query UserEmailList(\n    $first: Int\n    $after: String\n    $last: Int\n    $before: String\n  ) {\n    viewer {\n      __typename\n      ... on User {\n        emails(first: $first, after: $after, last: $last, before: $before) {\n          edges {\n            cursor\n            node {\n              ...UserEmail_email\n            }\n          }\n          totalCount\n          pageInfo {\n            hasNextPage\n            hasPreviousPage\n            startCursor\n            endCursor\n          }\n        }\n        primaryEmail {\n          id\n        }\n      }\n    }\n  }\n": typeof types.UserEmailListDocument,
     "\n  fragment UserEmailList_user on User {\n    hasPassword\n  }\n": typeof types.UserEmailList_UserFragmentDoc,
     "\n  fragment UserEmailList_siteConfig on SiteConfig {\n    emailChangeAllowed\n    passwordLoginEnabled\n  }\n": typeof types.UserEmailList_SiteConfigFragmentDoc,
+    "\n  query UserPasskeyList {\n    viewer {\n      __typename\n      ... on User {\n        id\n        passkeys {\n          id\n          name\n          createdAt\n          lastUsedAt\n        }\n      }\n    }\n  }\n": typeof types.UserPasskeyListDocument,
+    "\n  mutation StartRegisterPasskey {\n    startRegisterPasskey {\n      id\n      options\n    }\n  }\n": typeof types.StartRegisterPasskeyDocument,
+    "\n  mutation CompleteRegisterPasskey(\n    $id: ID!\n    $name: String!\n    $response: String!\n  ) {\n    completeRegisterPasskey(\n      input: { id: $id, name: $name, response: $response }\n    ) {\n      status\n    }\n  }\n": typeof types.CompleteRegisterPasskeyDocument,
+    "\n  mutation RemovePasskey($id: ID!, $password: String) {\n    removePasskey(input: { userPasskeyId: $id, password: $password }) {\n      status\n    }\n  }\n": typeof types.RemovePasskeyDocument,
+    "\n  query UserPhone {\n    viewer {\n      __typename\n      ... on User {\n        id\n        phone {\n          id\n          phoneNumber\n          confirmedAt\n        }\n      }\n    }\n  }\n": typeof types.UserPhoneDocument,
+    "\n  mutation SetPhoneNumber(\n    $phoneNumber: String!\n    $password: String\n    $language: String!\n  ) {\n    setPhoneNumber(\n      input: {\n        phoneNumber: $phoneNumber\n        password: $password\n        language: $language\n      }\n    ) {\n      status\n    }\n  }\n": typeof types.SetPhoneNumberDocument,
+    "\n  mutation ResendPhoneNumberCode($language: String!) {\n    resendPhoneNumberCode(input: { language: $language }) {\n      status\n    }\n  }\n": typeof types.ResendPhoneNumberCodeDocument,
+    "\n  mutation ConfirmPhoneNumber($code: String!) {\n    confirmPhoneNumber(input: { code: $code }) {\n      status\n    }\n  }\n": typeof types.ConfirmPhoneNumberDocument,
+    "\n  mutation RemovePhoneNumber($password: String) {\n    removePhoneNumber(input: { password: $password }) {\n      status\n    }\n  }\n": typeof types.RemovePhoneNumberDocument,
+    "\n  query UserTotp {\n    viewer {\n      __typename\n      ... on User {\n        id\n        totp {\n          id\n          createdAt\n          lastUsedAt\n        }\n        recoveryCodesLeft\n      }\n    }\n  }\n": typeof types.UserTotpDocument,
+    "\n  mutation StartTotpEnrollment {\n    startTotpEnrollment {\n      status\n      secret\n      qrCode\n    }\n  }\n": typeof types.StartTotpEnrollmentDocument,
+    "\n  mutation ConfirmTotpEnrollment($code: String!) {\n    confirmTotpEnrollment(input: { code: $code }) {\n      status\n      recoveryCodes\n    }\n  }\n": typeof types.ConfirmTotpEnrollmentDocument,
+    "\n  mutation RemoveTotp($password: String) {\n    removeTotp(input: { password: $password }) {\n      status\n    }\n  }\n": typeof types.RemoveTotpDocument,
+    "\n  mutation RegenerateRecoveryCodes($password: String) {\n    regenerateRecoveryCodes(input: { password: $password }) {\n      status\n      recoveryCodes\n    }\n  }\n": typeof types.RegenerateRecoveryCodesDocument,
+    "\n  query UserTrustedDeviceList {\n    viewer {\n      __typename\n      ... on User {\n        id\n        trustedDevices {\n          id\n          createdAt\n          expiresAt\n          lastUsedAt\n          userAgent {\n            name\n            os\n          }\n        }\n      }\n    }\n  }\n": typeof types.UserTrustedDeviceListDocument,
+    "\n  mutation RevokeTrustedDevice($id: ID!) {\n    revokeTrustedDevice(input: { userTrustedDeviceId: $id }) {\n      status\n    }\n  }\n": typeof types.RevokeTrustedDeviceDocument,
+    "\n  query UserUpstreamLinkList {\n    viewer {\n      __typename\n      ... on User {\n        id\n        upstreamOauth2Links(first: 100) {\n          nodes {\n            id\n            createdAt\n            humanAccountName\n            provider {\n              id\n              humanName\n            }\n          }\n        }\n      }\n    }\n\n    upstreamOauth2Providers(first: 100) {\n      nodes {\n        id\n        humanName\n        linkUrl\n      }\n    }\n  }\n": typeof types.UserUpstreamLinkListDocument,
+    "\n  mutation RemoveUpstreamLink($id: ID!, $password: String) {\n    removeUpstreamLink(input: { upstreamLinkId: $id, password: $password }) {\n      status\n    }\n  }\n": typeof types.RemoveUpstreamLinkDocument,
     "\n  fragment BrowserSessionsOverview_user on User {\n    id\n\n    browserSessions(first: 0, state: ACTIVE) {\n      totalCount\n    }\n  }\n": typeof types.BrowserSessionsOverview_UserFragmentDoc,
-    "\n  query UserProfile {\n    viewerSession {\n      __typename\n      ... on BrowserSession {\n        id\n        user {\n          ...AddEmailForm_user\n          ...UserEmailList_user\n          ...AccountDeleteButton_user\n          hasPassword\n          emails(first: 0) {\n            totalCount\n          }\n        }\n      }\n    }\n\n    siteConfig {\n      emailChangeAllowed\n      passwordLoginEnabled\n      accountDeactivationAllowed\n      ...AddEmailForm_siteConfig\n      ...UserEmailList_siteConfig\n      ...PasswordChange_siteConfig\n      ...AccountDeleteButton_siteConfig\n    }\n  }\n": typeof types.UserProfileDocument,
+    "\n  query UserProfile {\n    viewerSession {\n      __typename\n      ... on BrowserSession {\n        id\n        user {\n          ...AddEmailForm_user\n          ...UserEmailList_user\n          ...AccountDeleteButton_user\n          hasPassword\n          emails(first: 0) {\n            totalCount\n          }\n          upstreamOauth2Links(first: 0) {\n            totalCount\n          }\n        }\n      }\n    }\n\n    upstreamOauth2Providers(first: 0) {\n      totalCount\n    }\n\n    siteConfig {\n      emailChangeAllowed\n      passwordLoginEnabled\n      passkeysEnabled\n      totpPolicy\n      phoneNumberChangeAllowed\n      trustedDevicesEnabled\n      accountDeactivationAllowed\n      ...AddEmailForm_siteConfig\n      ...UserEmailList_siteConfig\n      ...PasswordChange_siteConfig\n      ...AccountDeleteButton_siteConfig\n    }\n  }\n": typeof types.UserProfileDocument,
     "\n  query PlanManagementTab {\n    siteConfig {\n      planManagementIframeUri\n    }\n  }\n": typeof types.PlanManagementTabDocument,
     "\n  query BrowserSessionList(\n    $first: Int\n    $after: String\n    $last: Int\n    $before: String\n    $lastActive: DateFilter\n  ) {\n    viewerSession {\n      __typename\n      ... on BrowserSession {\n        id\n\n        user {\n          id\n\n          browserSessions(\n            first: $first\n            after: $after\n            last: $last\n            before: $before\n            lastActive: $lastActive\n            state: ACTIVE\n          ) {\n            totalCount\n\n            edges {\n              cursor\n              node {\n                id\n                ...BrowserSession_session\n              }\n            }\n\n            pageInfo {\n              hasNextPage\n              hasPreviousPage\n              startCursor\n              endCursor\n            }\n          }\n        }\n      }\n    }\n  }\n": typeof types.BrowserSessionListDocument,
     "\n  query SessionsOverview {\n    viewer {\n      __typename\n\n      ... on User {\n        id\n        ...BrowserSessionsOverview_user\n      }\n    }\n  }\n": typeof types.SessionsOverviewDocument,
@@ -73,7 +93,7 @@ type Documents = {
 };
 const documents: Documents = {
     "\n  fragment AccountDeleteButton_user on User {\n    username\n    hasPassword\n    matrix {\n      mxid\n      displayName\n    }\n  }\n": types.AccountDeleteButton_UserFragmentDoc,
-    "\n  fragment AccountDeleteButton_siteConfig on SiteConfig {\n    passwordLoginEnabled\n  }\n": types.AccountDeleteButton_SiteConfigFragmentDoc,
+    "\n  fragment AccountDeleteButton_siteConfig on SiteConfig {\n    passwordLoginEnabled\n    accountDeletionGracePeriodDays\n  }\n": types.AccountDeleteButton_SiteConfigFragmentDoc,
     "\n  mutation DeactivateUser($hsErase: Boolean!, $password: String) {\n    deactivateUser(input: { hsErase: $hsErase, password: $password }) {\n      status\n    }\n  }\n": types.DeactivateUserDocument,
     "\n  fragment PasswordChange_siteConfig on SiteConfig {\n    passwordChangeAllowed\n  }\n": types.PasswordChange_SiteConfigFragmentDoc,
     "\n  fragment BrowserSession_session on BrowserSession {\n    id\n    createdAt\n    finishedAt\n    ...EndBrowserSessionButton_session\n    userAgent {\n      deviceType\n      name\n      os\n      model\n    }\n    lastActiveAt\n  }\n": types.BrowserSession_SessionFragmentDoc,
@@ -91,22 +111,42 @@ const documents: Documents = {
     "\n  mutation EndOAuth2Session($id: ID!) {\n    endOauth2Session(input: { oauth2SessionId: $id }) {\n      status\n      oauth2Session {\n        id\n      }\n    }\n  }\n": types.EndOAuth2SessionDocument,
     "\n  fragment BrowserSession_detail on BrowserSession {\n    id\n    createdAt\n    finishedAt\n    ...EndBrowserSessionButton_session\n    userAgent {\n      name\n      model\n      os\n    }\n    lastActiveIp\n    lastActiveAt\n    lastAuthentication {\n      id\n      createdAt\n    }\n    user {\n      id\n      username\n    }\n  }\n": types.BrowserSession_DetailFragmentDoc,
     "\n  mutation SetCompatSessionName($sessionId: ID!, $displayName: String!) {\n    setCompatSessionName(input: { compatSessionId: $sessionId, humanName: $displayName }) {\n      status\n    }\n  }\n": types.SetCompatSessionNameDocument,
-    "\n  fragment CompatSession_detail on CompatSession {\n    id\n    createdAt\n    deviceId\n    finishedAt\n    lastActiveIp\n    lastActiveAt\n    humanName\n\n    ...EndCompatSessionButton_session\n\n    userAgent {\n      name\n      os\n      model\n    }\n\n    ssoLogin {\n      id\n      redirectUri\n    }\n  }\n": types.CompatSession_DetailFragmentDoc,
+    "\n  fragment CompatSession_detail on CompatSession {\n    id\n    createdAt\n    deviceId\n    finishedAt\n    lastActiveIp\n    lastActiveAt\n    humanName\n    impersonated\n\n    ...EndCompatSessionButton_session\n\n    userAgent {\n      name\n      os\n      model\n    }\n\n    ssoLogin {\n      id\n      redirectUri\n    }\n  }\n": types.CompatSession_DetailFragmentDoc,
     "\n  mutation SetOAuth2SessionName($sessionId: ID!, $displayName: String!) {\n    setOauth2SessionName(input: { oauth2SessionId: $sessionId, humanName: $displayName }) {\n      status\n    }\n  }\n": types.SetOAuth2SessionNameDocument,
-    "\n  fragment OAuth2Session_detail on Oauth2Session {\n    id\n    scope\n    createdAt\n    finishedAt\n    lastActiveIp\n    lastActiveAt\n    humanName\n\n    ...EndOAuth2SessionButton_session\n\n    userAgent {\n      name\n      model\n      os\n    }\n\n    client {\n      id\n      clientId\n      clientName\n      clientUri\n      logoUri\n    }\n  }\n": types.OAuth2Session_DetailFragmentDoc,
+    "\n  fragment OAuth2Session_detail on Oauth2Session {\n    id\n    scope\n    createdAt\n    finishedAt\n    lastActiveIp\n    lastActiveAt\n    humanName\n    offlineAccessGrantedAt\n\n    ...EndOAuth2SessionButton_session\n\n    userAgent {\n      name\n      model\n      os\n    }\n\n    client {\n      id\n      clientId\n      clientName\n      clientUri\n      logoUri\n    }\n  }\n": types.OAuth2Session_DetailFragmentDoc,
     "\n  fragment UserEmail_email on UserEmail {\n    id\n    email\n  }\n": types.UserEmail_EmailFragmentDoc,
     "\n  mutation RemoveEmail($id: ID!, $password: String) {\n    removeEmail(input: { userEmailId: $id, password: $password }) {\n      status\n\n      user {\n        id\n      }\n    }\n  }\n": types.RemoveEmailDocument,
-    "\n  fragment UserGreeting_user on User {\n    id\n    matrix {\n      mxid\n      displayName\n    }\n  }\n": types.UserGreeting_UserFragmentDoc,
-    "\n  fragment UserGreeting_siteConfig on SiteConfig {\n    displayNameChangeAllowed\n  }\n": types.UserGreeting_SiteConfigFragmentDoc,
-    "\n  mutation SetDisplayName($userId: ID!, $displayName: String) {\n    setDisplayName(input: { userId: $userId, displayName: $displayName }) {\n      status\n    }\n  }\n": types.SetDisplayNameDocument,
+    "\n  mutation SetPrimaryEmail($id: ID!) {\n    setPrimaryEmail(input: { userEmailId: $id }) {\n      status\n\n      user {\n        id\n      }\n    }\n  }\n": types.SetPrimaryEmailDocument,
+    "\n  fragment UserGreeting_user on User {\n    id\n    matrix {\n      mxid\n      displayName\n      avatarUrl\n    }\n  }\n": types.UserGreeting_UserFragmentDoc,
+    "\n  fragment UserGreeting_siteConfig on SiteConfig {\n    displayNameChangeAllowed\n    avatarChangeAllowed\n  }\n": types.UserGreeting_SiteConfigFragmentDoc,
+    "\n  mutation SetDisplayName($userId: ID!, $displayName: String) {\n    setDisplayName(input: { userId: $userId, displayName: $displayName }) {\n      status\n      violations\n    }\n  }\n": types.SetDisplayNameDocument,
+    "\n  mutation SetAvatar($userId: ID!, $avatar: Upload) {\n    setAvatar(input: { userId: $userId, avatar: $avatar }) {\n      status\n    }\n  }\n": types.SetAvatarDocument,
     "\n  fragment AddEmailForm_user on User {\n    hasPassword\n  }\n": types.AddEmailForm_UserFragmentDoc,
     "\n  fragment AddEmailForm_siteConfig on SiteConfig {\n    passwordLoginEnabled\n  }\n": types.AddEmailForm_SiteConfigFragmentDoc,
     "\n  mutation AddEmail($email: String!, $password: String, $language: String!) {\n    startEmailAuthentication(input: {\n      email: $email,\n      password: $password,\n      language: $language\n    }) {\n      status\n      violations\n      authentication {\n        id\n      }\n    }\n  }\n": types.AddEmailDocument,
-    "\n  query UserEmailList(\n    $first: Int\n    $after: String\n    $last: Int\n    $before: String\n  ) {\n    viewer {\n      __typename\n      ... on User {\n        emails(first: $first, after: $after, last: $last, before: $before) {\n          edges {\n            cursor\n            node {\n              ...UserEmail_email\n            }\n          }\n          totalCount\n          pageInfo {\n            hasNextPage\n            hasPreviousPage\n            startCursor\n            endCursor\n          }\n        }\n      }\n    }\n  }\n": types.UserEmailListDocument,
+    "\n  query UserEmailList(\n    $first: Int\n    $after: String\n    $last: Int\n    $before: String\n  ) {\n    viewer {\n      __typename\n      ... on User {\n        emails(first: $first, after: $after, last: $last, before: $before) {\n          edges {\n            cursor\n            node {\n              ...UserEmail_email\n            }\n          }\n          totalCount\n          pageInfo {\n            hasNextPage\n            hasPreviousPage\n            startCursor\n            endCursor\n          }\n        }\n        primaryEmail {\n          id\n        }\n      }\n    }\n  }\n": types.UserEmailListDocument,
     "\n  fragment UserEmailList_user on User {\n    hasPassword\n  }\n": types.UserEmailList_UserFragmentDoc,
     "\n  fragment UserEmailList_siteConfig on SiteConfig {\n    emailChangeAllowed\n    passwordLoginEnabled\n  }\n": types.UserEmailList_SiteConfigFragmentDoc,
+    "\n  query UserPasskeyList {\n    viewer {\n      __typename\n      ... on User {\n        id\n        passkeys {\n          id\n          name\n          createdAt\n          lastUsedAt\n        }\n      }\n    }\n  }\n": types.UserPasskeyListDocument,
+    "\n  mutation StartRegisterPasskey {\n    startRegisterPasskey {\n      id\n      options\n    }\n  }\n": types.StartRegisterPasskeyDocument,
+    "\n  mutation CompleteRegisterPasskey(\n    $id: ID!\n    $name: String!\n    $response: String!\n  ) {\n    completeRegisterPasskey(\n      input: { id: $id, name: $name, response: $response }\n    ) {\n      status\n    }\n  }\n": types.CompleteRegisterPasskeyDocument,
+    "\n  mutation RemovePasskey($id: ID!, $password: String) {\n    removePasskey(input: { userPasskeyId: $id, password: $password }) {\n      status\n    }\n  }\n": types.RemovePasskeyDocument,
+    "\n  query UserPhone {\n    viewer {\n      __typename\n      ... on User {\n        id\n        phone {\n          id\n          phoneNumber\n          confirmedAt\n        }\n      }\n    }\n  }\n": types.UserPhoneDocument,
+    "\n  mutation SetPhoneNumber(\n    $phoneNumber: String!\n    $password: String\n    $language: String!\n  ) {\n    setPhoneNumber(\n      input: {\n        phoneNumber: $phoneNumber\n        password: $password\n        language: $language\n      }\n    ) {\n      status\n    }\n  }\n": types.SetPhoneNumberDocument,
+    "\n  mutation ResendPhoneNumberCode($language: String!) {\n    resendPhoneNumberCode(input: { language: $language }) {\n      status\n    }\n  }\n": types.ResendPhoneNumberCodeDocument,
+    "\n  mutation ConfirmPhoneNumber($code: String!) {\n    confirmPhoneNumber(input: { code: $code }) {\n      status\n    }\n  }\n": types.ConfirmPhoneNumberDocument,
+    "\n  mutation RemovePhoneNumber($password: String) {\n    removePhoneNumber(input: { password: $password }) {\n      status\n    }\n  }\n": types.RemovePhoneNumberDocument,
+    "\n  query UserTotp {\n    viewer {\n      __typename\n      ... on User {\n        id\n        totp {\n          id\n          createdAt\n          lastUsedAt\n        }\n        recoveryCodesLeft\n      }\n    }\n  }\n": types.UserTotpDocument,
+    "\n  mutation StartTotpEnrollment {\n    startTotpEnrollment {\n      status\n      secret\n      qrCode\n    }\n  }\n": types.StartTotpEnrollmentDocument,
+    "\n  mutation ConfirmTotpEnrollment($code: String!) {\n    confirmTotpEnrollment(input: { code: $code }) {\n      status\n      recoveryCodes\n    }\n  }\n": types.ConfirmTotpEnrollmentDocument,
+    "\n  mutation RemoveTotp($password: String) {\n    removeTotp(input: { password: $password }) {\n      status\n    }\n  }\n": types.RemoveTotpDocument,
+    "\n  mutation RegenerateRecoveryCodes($password: String) {\n    regenerateRecoveryCodes(input: { password: $password }) {\n      status\n      recoveryCodes\n    }\n  }\n": types.RegenerateRecoveryCodesDocument,
+    "\n  query UserTrustedDeviceList {\n    viewer {\n      __typename\n      ... on User {\n        id\n        trustedDevices {\n          id\n          createdAt\n          expiresAt\n          lastUsedAt\n          userAgent {\n            name\n            os\n          }\n        }\n      }\n    }\n  }\n": types.UserTrustedDeviceListDocument,
+    "\n  mutation RevokeTrustedDevice($id: ID!) {\n    revokeTrustedDevice(input: { userTrustedDeviceId: $id }) {\n      status\n    }\n  }\n": types.RevokeTrustedDeviceDocument,
+    "\n  query UserUpstreamLinkList {\n    viewer {\n      __typename\n      ... on User {\n        id\n        upstreamOauth2Links(first: 100) {\n          nodes {\n            id\n            createdAt\n            humanAccountName\n            provider {\n              id\n              humanName\n            }\n          }\n        }\n      }\n    }\n\n    upstreamOauth2Providers(first: 100) {\n      nodes {\n        id\n        humanName\n        linkUrl\n      }\n    }\n  }\n": types.UserUpstreamLinkListDocument,
+    "\n  mutation RemoveUpstreamLink($id: ID!, $password: String) {\n    removeUpstreamLink(input: { upstreamLinkId: $id, password: $password }) {\n      status\n    }\n  }\n": types.RemoveUpstreamLinkDocument,
     "\n  fragment BrowserSessionsOverview_user on User {\n    id\n\n    browserSessions(first: 0, state: ACTIVE) {\n      totalCount\n    }\n  }\n": types.BrowserSessionsOverview_UserFragmentDoc,
-    "\n  query UserProfile {\n    viewerSession {\n      __typename\n      ... on BrowserSession {\n        id\n        user {\n          ...AddEmailForm_user\n          ...UserEmailList_user\n          ...AccountDeleteButton_user\n          hasPassword\n          emails(first: 0) {\n            totalCount\n          }\n        }\n      }\n    }\n\n    siteConfig {\n      emailChangeAllowed\n      passwordLoginEnabled\n      accountDeactivationAllowed\n      ...AddEmailForm_siteConfig\n      ...UserEmailList_siteConfig\n      ...PasswordChange_siteConfig\n      ...AccountDeleteButton_siteConfig\n    }\n  }\n": types.UserProfileDocument,
+    "\n  query UserProfile {\n    viewerSession {\n      __typename\n      ... on BrowserSession {\n        id\n        user {\n          ...AddEmailForm_user\n          ...UserEmailList_user\n          ...AccountDeleteButton_user\n          hasPassword\n          emails(first: 0) {\n            totalCount\n          }\n          upstreamOauth2Links(first: 0) {\n            totalCount\n          }\n        }\n      }\n    }\n\n    upstreamOauth2Providers(first: 0) {\n      totalCount\n    }\n\n    siteConfig {\n      emailChangeAllowed\n      passwordLoginEnabled\n      passkeysEnabled\n      totpPolicy\n      phoneNumberChangeAllowed\n      trustedDevicesEnabled\n      accountDeactivationAllowed\n      ...AddEmailForm_siteConfig\n      ...UserEmailList_siteConfig\n      ...PasswordChange_siteConfig\n      ...AccountDeleteButton_siteConfig\n    }\n  }\n": types.UserProfileDocument,
     "\n  query PlanManagementTab {\n    siteConfig {\n      planManagementIframeUri\n    }\n  }\n": types.PlanManagementTabDocument,
     "\n  query BrowserSessionList(\n    $first: Int\n    $after: String\n    $last: Int\n    $before: String\n    $lastActive: DateFilter\n  ) {\n    viewerSession {\n      __typename\n      ... on BrowserSession {\n        id\n\n        user {\n          id\n\n          browserSessions(\n            first: $first\n            after: $after\n            last: $last\n            before: $before\n            lastActive: $lastActive\n            state: ACTIVE\n          ) {\n            totalCount\n\n            edges {\n              cursor\n              node {\n                id\n                ...BrowserSession_session\n              }\n            }\n\n            pageInfo {\n              hasNextPage\n              hasPreviousPage\n              startCursor\n              endCursor\n            }\n          }\n        }\n      }\n    }\n  }\n": types.BrowserSessionListDocument,
     "\n  query SessionsOverview {\n    viewer {\n      __typename\n\n      ... on User {\n        id\n        ...BrowserSessionsOverview_user\n      }\n    }\n  }\n": types.SessionsOverviewDocument,
@@ -136,7 +176,7 @@ export function graphql(source: "\n  fragment AccountDeleteButton_user on User {
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
-export function graphql(source: "\n  fragment AccountDeleteButton_siteConfig on SiteConfig {\n    passwordLoginEnabled\n  }\n"): typeof import('./graphql').AccountDeleteButton_SiteConfigFragmentDoc;
+export function graphql(source: "\n  fragment AccountDeleteButton_siteConfig on SiteConfig {\n    passwordLoginEnabled\n    accountDeletionGracePeriodDays\n  }\n"): typeof import('./graphql').AccountDeleteButton_SiteConfigFragmentDoc;
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
@@ -208,7 +248,7 @@ export function graphql(source: "\n  mutation SetCompatSessionName($sessionId: I
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
-export function graphql(source: "\n  fragment CompatSession_detail on CompatSession {\n    id\n    createdAt\n    deviceId\n    finishedAt\n    lastActiveIp\n    lastActiveAt\n    humanName\n\n    ...EndCompatSessionButton_session\n\n    userAgent {\n      name\n      os\n      model\n    }\n\n    ssoLogin {\n      id\n      redirectUri\n    }\n  }\n"): typeof import('./graphql').CompatSession_DetailFragmentDoc;
+export function graphql(source: "\n  fragment CompatSession_detail on CompatSession {\n    id\n    createdAt\n    deviceId\n    finishedAt\n    lastActiveIp\n    lastActiveAt\n    humanName\n    impersonated\n\n    ...EndCompatSessionButton_session\n\n    userAgent {\n      name\n      os\n      model\n    }\n\n    ssoLogin {\n      id\n      redirectUri\n    }\n  }\n"): typeof import('./graphql').CompatSession_DetailFragmentDoc;
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
@@ -216,7 +256,7 @@ export function graphql(source: "\n  mutation SetOAuth2SessionName($sessionId: I
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
-export function graphql(source: "\n  fragment OAuth2Session_detail on Oauth2Session {\n    id\n    scope\n    createdAt\n    finishedAt\n    lastActiveIp\n    lastActiveAt\n    humanName\n\n    ...EndOAuth2SessionButton_session\n\n    userAgent {\n      name\n      model\n      os\n    }\n\n    client {\n      id\n      clientId\n      clientName\n      clientUri\n      logoUri\n    }\n  }\n"): typeof import('./graphql').OAuth2Session_DetailFragmentDoc;
+export function graphql(source: "\n  fragment OAuth2Session_detail on Oauth2Session {\n    id\n    scope\n    createdAt\n    finishedAt\n    lastActiveIp\n    lastActiveAt\n    humanName\n    offlineAccessGrantedAt\n\n    ...EndOAuth2SessionButton_session\n\n    userAgent {\n      name\n      model\n      os\n    }\n\n    client {\n      id\n      clientId\n      clientName\n      clientUri\n      logoUri\n    }\n  }\n"): typeof import('./graphql').OAuth2Session_DetailFragmentDoc;
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
@@ -228,15 +268,23 @@ export function graphql(source: "\n  mutation RemoveEmail($id: ID!, $password: S
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
-export function graphql(source: "\n  fragment UserGreeting_user on User {\n    id\n    matrix {\n      mxid\n      displayName\n    }\n  }\n"): typeof import('./graphql').UserGreeting_UserFragmentDoc;
+export function graphql(source: "\n  mutation SetPrimaryEmail($id: ID!) {\n    setPrimaryEmail(input: { userEmailId: $id }) {\n      status\n\n      user {\n        id\n      }\n    }\n  }\n"): typeof import('./graphql').SetPrimaryEmailDocument;
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
-export function graphql(source: "\n  fragment UserGreeting_siteConfig on SiteConfig {\n    displayNameChangeAllowed\n  }\n"): typeof import('./graphql').UserGreeting_SiteConfigFragmentDoc;
+export function graphql(source: "\n  fragment UserGreeting_user on User {\n    id\n    matrix {\n      mxid\n      displayName\n      avatarUrl\n    }\n  }\n"): typeof import('./graphql').UserGreeting_UserFragmentDoc;
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
-export function graphql(source: "\n  mutation SetDisplayName($userId: ID!, $displayName: String) {\n    setDisplayName(input: { userId: $userId, displayName: $displayName }) {\n      status\n    }\n  }\n"): typeof import('./graphql').SetDisplayNameDocument;
+export function graphql(source: "\n  fragment UserGreeting_siteConfig on SiteConfig {\n    displayNameChangeAllowed\n    avatarChangeAllowed\n  }\n"): typeof import('./graphql').UserGreeting_SiteConfigFragmentDoc;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation SetDisplayName($userId: ID!, $displayName: String) {\n    setDisplayName(input: { userId: $userId, displayName: $displayName }) {\n      status\n      violations\n    }\n  }\n"): typeof import('./graphql').SetDisplayNameDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation SetAvatar($userId: ID!, $avatar: Upload) {\n    setAvatar(input: { userId: $userId, avatar: $avatar }) {\n      status\n    }\n  }\n"): typeof import('./graphql').SetAvatarDocument;
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
@@ -252,7 +300,7 @@ export function graphql(source: "\n  mutation AddEmail($email: String!, $passwor
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
-export function graphql(source: "\n  query UserEmailList(\n    $first: Int\n    $after: String\n    $last: Int\n    $before: String\n  ) {\n    viewer {\n      __typename\n      ... on User {\n        emails(first: $first, after: $after, last: $last, before: $before) {\n          edges {\n            cursor\n            node {\n              ...UserEmail_email\n            }\n          }\n          totalCount\n          pageInfo {\n            hasNextPage\n            hasPreviousPage\n            startCursor\n            endCursor\n          }\n        }\n      }\n    }\n  }\n"): typeof import('./graphql').UserEmailListDocument;
+export function graphql(source: "\n  query UserEmailList(\n    $first: Int\n    $after: String\n    $last: Int\n    $before: String\n  ) {\n    viewer {\n      __typename\n      ... on User {\n        emails(first: $first, after: $after, last: $last, before: $before) {\n          edges {\n            cursor\n            node {\n              ...UserEmail_email\n            }\n          }\n          totalCount\n          pageInfo {\n            hasNextPage\n            hasPreviousPage\n            startCursor\n            endCursor\n          }\n        }\n        primaryEmail {\n          id\n        }\n      }\n    }\n  }\n"): typeof import('./graphql').UserEmailListDocument;
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
@@ -261,6 +309,78 @@ export function graphql(source: "\n  fragment UserEmailList_user on User {\n
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
 export function graphql(source: "\n  fragment UserEmailList_siteConfig on SiteConfig {\n    emailChangeAllowed\n    passwordLoginEnabled\n  }\n"): typeof import('./graphql').UserEmailList_SiteConfigFragmentDoc;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  query UserPasskeyList {\n    viewer {\n      __typename\n      ... on User {\n        id\n        passkeys {\n          id\n          name\n          createdAt\n          lastUsedAt\n        }\n      }\n    }\n  }\n"): typeof import('./graphql').UserPasskeyListDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation StartRegisterPasskey {\n    startRegisterPasskey {\n      id\n      options\n    }\n  }\n"): typeof import('./graphql').StartRegisterPasskeyDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation CompleteRegisterPasskey(\n    $id: ID!\n    $name: String!\n    $response: String!\n  ) {\n    completeRegisterPasskey(\n      input: { id: $id, name: $name, response: $response }\n    ) {\n      status\n    }\n  }\n"): typeof import('./graphql').CompleteRegisterPasskeyDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation RemovePasskey($id: ID!, $password: String) {\n    removePasskey(input: { userPasskeyId: $id, password: $password }) {\n      status\n    }\n  }\n"): typeof import('./graphql').RemovePasskeyDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  query UserPhone {\n    viewer {\n      __typename\n      ... on User {\n        id\n        phone {\n          id\n          phoneNumber\n          confirmedAt\n        }\n      }\n    }\n  }\n"): typeof import('./graphql').UserPhoneDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation SetPhoneNumber(\n    $phoneNumber: String!\n    $password: String\n    $language: String!\n  ) {\n    setPhoneNumber(\n      input: {\n        phoneNumber: $phoneNumber\n        password: $password\n        language: $language\n      }\n    ) {\n      status\n    }\n  }\n"): typeof import('./graphql').SetPhoneNumberDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation ResendPhoneNumberCode($language: String!) {\n    resendPhoneNumberCode(input: { language: $language }) {\n      status\n    }\n  }\n"): typeof import('./graphql').ResendPhoneNumberCodeDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation ConfirmPhoneNumber($code: String!) {\n    confirmPhoneNumber(input: { code: $code }) {\n      status\n    }\n  }\n"): typeof import('./graphql').ConfirmPhoneNumberDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation RemovePhoneNumber($password: String) {\n    removePhoneNumber(input: { password: $password }) {\n      status\n    }\n  }\n"): typeof import('./graphql').RemovePhoneNumberDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  query UserTotp {\n    viewer {\n      __typename\n      ... on User {\n        id\n        totp {\n          id\n          createdAt\n          lastUsedAt\n        }\n        recoveryCodesLeft\n      }\n    }\n  }\n"): typeof import('./graphql').UserTotpDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation StartTotpEnrollment {\n    startTotpEnrollment {\n      status\n      secret\n      qrCode\n    }\n  }\n"): typeof import('./graphql').StartTotpEnrollmentDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation ConfirmTotpEnrollment($code: String!) {\n    confirmTotpEnrollment(input: { code: $code }) {\n      status\n      recoveryCodes\n    }\n  }\n"): typeof import('./graphql').ConfirmTotpEnrollmentDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation RemoveTotp($password: String) {\n    removeTotp(input: { password: $password }) {\n      status\n    }\n  }\n"): typeof import('./graphql').RemoveTotpDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation RegenerateRecoveryCodes($password: String) {\n    regenerateRecoveryCodes(input: { password: $password }) {\n      status\n      recoveryCodes\n    }\n  }\n"): typeof import('./graphql').RegenerateRecoveryCodesDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  query UserTrustedDeviceList {\n    viewer {\n      __typename\n      ... on User {\n        id\n        trustedDevices {\n          id\n          createdAt\n          expiresAt\n          lastUsedAt\n          userAgent {\n            name\n            os\n          }\n        }\n      }\n    }\n  }\n"): typeof import('./graphql').UserTrustedDeviceListDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation RevokeTrustedDevice($id: ID!) {\n    revokeTrustedDevice(input: { userTrustedDeviceId: $id }) {\n      status\n    }\n  }\n"): typeof import('./graphql').RevokeTrustedDeviceDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  query UserUpstreamLinkList {\n    viewer {\n      __typename\n      ... on User {\n        id\n        upstreamOauth2Links(first: 100) {\n          nodes {\n            id\n            createdAt\n            humanAccountName\n            provider {\n              id\n              humanName\n            }\n          }\n        }\n      }\n    }\n\n    upstreamOauth2Providers(first: 100) {\n      nodes {\n        id\n        humanName\n        linkUrl\n      }\n    }\n  }\n"): typeof import('./graphql').UserUpstreamLinkListDocument;
+/**
+ * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
+ */
+export function graphql(source: "\n  mutation RemoveUpstreamLink($id: ID!, $password: String) {\n    removeUpstreamLink(input: { upstreamLinkId: $id, password: $password }) {\n      status\n    }\n  }\n"): typeof import('./graphql').RemoveUpstreamLinkDocument;
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
@@ -268,7 +388,7 @@ export function graphql(source: "\n  fragment BrowserSessionsOverview_user on Us
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
-export function graphql(source: "\n  query UserProfile {\n    viewerSession {\n      __typename\n      ... on BrowserSession {\n        id\n        user {\n          ...AddEmailForm_user\n          ...UserEmailList_user\n          ...AccountDeleteButton_user\n          hasPassword\n          emails(first: 0) {\n            totalCount\n          }\n        }\n      }\n    }\n\n    siteConfig {\n      emailChangeAllowed\n      passwordLoginEnabled\n      accountDeactivationAllowed\n      ...AddEmailForm_siteConfig\n      ...UserEmailList_siteConfig\n      ...PasswordChange_siteConfig\n      ...AccountDeleteButton_siteConfig\n    }\n  }\n"): typeof import('./graphql').UserProfileDocument;
+export function graphql(source: "\n  query UserProfile {\n    viewerSession {\n      __typename\n      ... on BrowserSession {\n        id\n        user {\n          ...AddEmailForm_user\n          ...UserEmailList_user\n          ...AccountDeleteButton_user\n          hasPassword\n          emails(first: 0) {\n            totalCount\n          }\n          upstreamOauth2Links(first: 0) {\n            totalCount\n          }\n        }\n      }\n    }\n\n    upstreamOauth2Providers(first: 0) {\n      totalCount\n    }\n\n    siteConfig {\n      emailChangeAllowed\n      passwordLoginEnabled\n      passkeysEnabled\n      totpPolicy\n      phoneNumberChangeAllowed\n      trustedDevicesEnabled\n      accountDeactivationAllowed\n      ...AddEmailForm_siteConfig\n      ...UserEmailList_siteConfig\n      ...PasswordChange_siteConfig\n      ...AccountDeleteButton_siteConfig\n    }\n  }\n"): typeof import('./graphql').UserProfileDocument;
 /**
  * The graphql function is used to parse GraphQL queries into a document that can be used by GraphQL clients.
  */
@@ -21,6 +21,7 @@ export type Scalars = {
    * The input/output is a string in RFC3339 format.
    */
   DateTime: { input: string; output: string; }
+  Upload: { input: any; output: any; }
   /** URL is a String implementing the [URL Standard](http://url.spec.whatwg.org/) */
   Url: { input: string; output: string; }
 };
@@ -149,6 +150,51 @@ export type Authentication = CreationEvent & Node & {
   id: Scalars['ID']['output'];
 };
 
+/**
+ * What to do with the device code grant in the `authorizeDeviceCode`
+ * mutation.
+ */
+export type AuthorizeDeviceCodeAction =
+  /** Let the other device sign in. */
+  | 'APPROVE'
+  /** Refuse to let the other device sign in. */
+  | 'REJECT';
+
+/** The input of the `authorizeDeviceCode` mutation. */
+export type AuthorizeDeviceCodeInput = {
+  /** Whether to approve or reject the sign in. */
+  action: AuthorizeDeviceCodeAction;
+  /** The code shown on the other device. */
+  userCode: Scalars['String']['input'];
+};
+
+export type AuthorizeDeviceCodePayload = {
+  __typename?: 'AuthorizeDeviceCodePayload';
+  /**
+   * The client which started the sign in on the other device, if the code
+   * was valid.
+   */
+  oauth2Client?: Maybe<Oauth2Client>;
+  /** The status of the mutation. */
+  status: AuthorizeDeviceCodeStatus;
+};
+
+/** The status of the `authorizeDeviceCode` mutation. */
+export type AuthorizeDeviceCodeStatus =
+  /** The other device is now signed in. */
+  | 'APPROVED'
+  /** The code is unknown, expired, or was already used. */
+  | 'INVALID_CODE'
+  /**
+   * The requester isn't tied to a browser session in which the other device
+   * could be signed in.
+   */
+  | 'NOT_ALLOWED'
+  /** The policy doesn't allow the user to sign in on this client. */
+  | 'POLICY_VIOLATION'
+  /** The sign in on the other device was rejected. */
+  | 'REJECTED';
+
 /** A browser session represents a logged in user in a browser. */
 export type BrowserSession = CreationEvent & Node & {
   __typename?: 'BrowserSession';
@@ -163,8 +209,23 @@ export type BrowserSession = CreationEvent & Node & {
   finishedAt?: Maybe<Scalars['DateTime']['output']>;
   /** ID of the object. */
   id: Scalars['ID']['output'];
+  /**
+   * The name of the organization operating the autonomous system the last
+   * IP address belongs to, if known.
+   */
+  lastActiveAsOrganization?: Maybe<Scalars['String']['output']>;
+  /**
+   * The number of the autonomous system the last IP address belongs to, if
+   * known.
+   */
+  lastActiveAsn?: Maybe<Scalars['Int']['output']>;
   /** The last time the session was active. */
   lastActiveAt?: Maybe<Scalars['DateTime']['output']>;
+  /**
+   * The ISO 3166-1 code of the country the last IP address is located in,
+   * if known.
+   */
+  lastActiveCountry?: Maybe<Scalars['String']['output']>;
   /** The last IP address used by the session. */
   lastActiveIp?: Maybe<Scalars['String']['output']>;
   /** The most recent authentication of this session. */
@@ -222,6 +283,7 @@ export type CaptchaConfig = {
 export type CaptchaService =
   | 'CLOUDFLARE_TURNSTILE'
   | 'H_CAPTCHA'
+  | 'PROOF_OF_WORK'
   | 'RECAPTCHA_V2';
 
 /**
@@ -242,8 +304,28 @@ export type CompatSession = CreationEvent & Node & {
   humanName?: Maybe<Scalars['String']['output']>;
   /** ID of the object. */
   id: Scalars['ID']['output'];
+  /**
+   * Whether this session was created by an administrator acting on behalf
+   * of the user.
+   */
+  impersonated: Scalars['Boolean']['output'];
+  /**
+   * The name of the organization operating the autonomous system the last
+   * IP address belongs to, if known.
+   */
+  lastActiveAsOrganization?: Maybe<Scalars['String']['output']>;
+  /**
+   * The number of the autonomous system the last IP address belongs to, if
+   * known.
+   */
+  lastActiveAsn?: Maybe<Scalars['Int']['output']>;
   /** The last time the session was active. */
   lastActiveAt?: Maybe<Scalars['DateTime']['output']>;
+  /**
+   * The ISO 3166-1 code of the country the last IP address is located in,
+   * if known.
+   */
+  lastActiveCountry?: Maybe<Scalars['String']['output']>;
   /** The last IP address used by the session. */
   lastActiveIp?: Maybe<Scalars['String']['output']>;
   /** The associated SSO login, if any. */
@@ -356,6 +438,94 @@ export type CompleteEmailAuthenticationStatus =
   /** Too many attempts to complete an email authentication */
   | 'RATE_LIMITED';
 
+/** The input for the `completeRegisterPasskey` mutation */
+export type CompleteRegisterPasskeyInput = {
+  /**
+   * The ID of the challenge, as returned by the `startRegisterPasskey`
+   * mutation
+   */
+  id: Scalars['ID']['input'];
+  /** The name to give to the passkey */
+  name: Scalars['String']['input'];
+  /** The response of `navigator.credentials.create()`, serialized as JSON */
+  response: Scalars['String']['input'];
+};
+
+/** The payload of the `completeRegisterPasskey` mutation */
+export type CompleteRegisterPasskeyPayload = {
+  __typename?: 'CompleteRegisterPasskeyPayload';
+  /** The passkey that was added */
+  passkey?: Maybe<UserPasskey>;
+  /** Status of the operation */
+  status: CompleteRegisterPasskeyStatus;
+};
+
+/** The status of the `completeRegisterPasskey` mutation */
+export type CompleteRegisterPasskeyStatus =
+  /** The passkey was added */
+  | 'ADDED'
+  /** The passkey is already registered */
+  | 'EXISTS'
+  /** The challenge is invalid, expired or was already used */
+  | 'INVALID_CHALLENGE'
+  /** The name of the passkey is invalid */
+  | 'INVALID_NAME'
+  /** The response of the authenticator could not be verified */
+  | 'INVALID_RESPONSE';
+
+/** The input for the `confirmPhoneNumber` mutation */
+export type ConfirmPhoneNumberInput = {
+  /** The code received by SMS */
+  code: Scalars['String']['input'];
+};
+
+/** The payload of the `confirmPhoneNumber` mutation */
+export type ConfirmPhoneNumberPayload = {
+  __typename?: 'ConfirmPhoneNumberPayload';
+  /** The phone number that was confirmed */
+  phone?: Maybe<UserPhone>;
+  /** Status of the operation */
+  status: ConfirmPhoneNumberStatus;
+};
+
+/** The status of the `confirmPhoneNumber` mutation */
+export type ConfirmPhoneNumberStatus =
+  /** The phone number was confirmed */
+  | 'CONFIRMED'
+  /** The code is invalid or expired */
+  | 'INVALID_CODE'
+  /** There is no phone number pending confirmation */
+  | 'NOT_FOUND';
+
+/** The input for the `confirmTotpEnrollment` mutation */
+export type ConfirmTotpEnrollmentInput = {
+  /** The code shown by the authenticator app */
+  code: Scalars['String']['input'];
+};
+
+/** The payload of the `confirmTotpEnrollment` mutation */
+export type ConfirmTotpEnrollmentPayload = {
+  __typename?: 'ConfirmTotpEnrollmentPayload';
+  /**
+   * The recovery codes generated for the user. They are only shown once,
+   * and let the user log in if their second factor is unavailable.
+   */
+  recoveryCodes?: Maybe<Array<Scalars['String']['output']>>;
+  /** Status of the operation */
+  status: ConfirmTotpEnrollmentStatus;
+  /** The second factor that was confirmed */
+  totp?: Maybe<UserTotp>;
+};
+
+/** The status of the `confirmTotpEnrollment` mutation */
+export type ConfirmTotpEnrollmentStatus =
+  /** The second factor was confirmed */
+  | 'CONFIRMED'
+  /** The code is invalid */
+  | 'INVALID_CODE'
+  /** There is no pending second factor to confirm */
+  | 'NOT_FOUND';
+
 /** The input of the `createOauth2Session` mutation. */
 export type CreateOAuth2SessionInput = {
   /** Whether the session should issue a never-expiring access token */
@@ -421,7 +591,9 @@ export type DeactivateUserStatus =
   /** The user was deactivated. */
   | 'DEACTIVATED'
   /** The password was wrong. */
-  | 'INCORRECT_PASSWORD';
+  | 'INCORRECT_PASSWORD'
+  /** The user has to authenticate again first. */
+  | 'REAUTHENTICATION_REQUIRED';
 
 /** The type of a user agent */
 export type DeviceType =
@@ -545,8 +717,34 @@ export type Mutation = {
   addUser: AddUserPayload;
   /** Temporarily allow user to reset their cross-signing keys. */
   allowUserCrossSigningReset: AllowUserCrossSigningResetPayload;
+  /**
+   * Approve or reject a sign in started on another device, using the code
+   * it shows.
+   *
+   * This lets a device without a convenient browser, like a TV, sign in
+   * through a session which is already signed in on another device. The
+   * other device ends up signed in to the same browser session as the
+   * requester, so OAuth 2.0 sessions can only use this if they were started
+   * from a browser session which is still active.
+   */
+  authorizeDeviceCode: AuthorizeDeviceCodePayload;
   /** Complete the email authentication flow */
   completeEmailAuthentication: CompleteEmailAuthenticationPayload;
+  /**
+   * Complete the registration of a passkey, with the response of the
+   * browser
+   */
+  completeRegisterPasskey: CompleteRegisterPasskeyPayload;
+  /**
+   * Confirm the phone number of the current user, with the code it received
+   * by SMS
+   */
+  confirmPhoneNumber: ConfirmPhoneNumberPayload;
+  /**
+   * Confirm the pending TOTP second factor of the current user, with a code
+   * from their authenticator app
+   */
+  confirmTotpEnrollment: ConfirmTotpEnrollmentPayload;
   /**
    * Create a new arbitrary OAuth 2.0 Session.
    *
@@ -565,10 +763,35 @@ export type Mutation = {
   endOauth2Session: EndOAuth2SessionPayload;
   /** Lock a user. This is only available to administrators. */
   lockUser: LockUserPayload;
+  /**
+   * Generate a new set of recovery codes for the current user, replacing
+   * the previous ones
+   */
+  regenerateRecoveryCodes: RegenerateRecoveryCodesPayload;
   /** Remove an email address */
   removeEmail: RemoveEmailPayload;
+  /** Remove a passkey */
+  removePasskey: RemovePasskeyPayload;
+  /** Remove the phone number of the current user */
+  removePhoneNumber: RemovePhoneNumberPayload;
+  /** Remove the TOTP second factor of the current user */
+  removeTotp: RemoveTotpPayload;
+  /**
+   * Remove an upstream link from an account, so that the upstream account
+   * can't be used to sign in to it anymore.
+   *
+   * This is refused if the user would be left without any way to sign in.
+   */
+  removeUpstreamLink: RemoveUpstreamLinkPayload;
+  /** Rename a passkey */
+  renamePasskey: RenamePasskeyPayload;
   /** Resend the email authentication code */
   resendEmailAuthenticationCode: ResendEmailAuthenticationCodePayload;
+  /**
+   * Send a new code to the phone number of the current user, if it is
+   * still pending confirmation
+   */
+  resendPhoneNumberCode: ResendPhoneNumberCodePayload;
   /**
    * Resend a user recovery email
    *
@@ -577,6 +800,28 @@ export type Mutation = {
    * calls this mutation.
    */
   resendRecoveryEmail: ResendRecoveryEmailPayload;
+  /**
+   * Revoke all the scopes a user consented to grant to a client.
+   *
+   * This does not end existing sessions, but the user will be asked for
+   * consent again the next time the client requests authorization.
+   */
+  revokeOauth2ClientConsents: RevokeOAuth2ClientConsentsPayload;
+  /**
+   * Revoke a single scope a user consented to grant to a client.
+   *
+   * This does not end existing sessions, but the user will be asked again
+   * the next time the client requests this scope.
+   */
+  revokeOauth2Consent: RevokeOAuth2ConsentPayload;
+  /**
+   * Stop trusting a browser, so that logging in from it asks for the second
+   * factor again. Unlike adding a second factor, this doesn't need the
+   * password, as it can only make the account safer.
+   */
+  revokeTrustedDevice: RevokeTrustedDevicePayload;
+  /** Set the avatar of a user, by uploading an image to the homeserver */
+  setAvatar: SetAvatarPayload;
   /**
    * Set whether a user can request admin. This is only available to
    * administrators.
@@ -598,12 +843,30 @@ export type Mutation = {
   /** Set the password for yourself, using a recovery ticket sent by e-mail. */
   setPasswordByRecovery: SetPasswordPayload;
   /**
-   * Set an email address as primary
-   * @deprecated This doesn't do anything anymore, but is kept to avoid breaking existing queries
+   * Set the phone number of the current user, replacing the previous one.
+   * A code is sent by SMS, which has to be entered with the
+   * `confirmPhoneNumber` mutation.
+   */
+  setPhoneNumber: SetPhoneNumberPayload;
+  /**
+   * Set an email address as primary, so that it is used for notifications
+   * and as the `email` claim
    */
   setPrimaryEmail: SetPrimaryEmailPayload;
   /** Start a new email authentication flow */
   startEmailAuthentication: StartEmailAuthenticationPayload;
+  /**
+   * Start registering a new passkey for the current user. The returned
+   * options must be passed to the browser, and its response given back to
+   * the `completeRegisterPasskey` mutation.
+   */
+  startRegisterPasskey: StartRegisterPasskeyPayload;
+  /**
+   * Start adding a TOTP second factor for the current user. It has to be
+   * confirmed with a code from the authenticator app, with the
+   * `confirmTotpEnrollment` mutation.
+   */
+  startTotpEnrollment: StartTotpEnrollmentPayload;
   /** Unlock a user. This is only available to administrators. */
   unlockUser: UnlockUserPayload;
 };
@@ -627,12 +890,36 @@ export type MutationAllowUserCrossSigningResetArgs = {
 };
 
 
+/** The mutations root of the GraphQL interface. */
+export type MutationAuthorizeDeviceCodeArgs = {
+  input: AuthorizeDeviceCodeInput;
+};
+
+
 /** The mutations root of the GraphQL interface. */
 export type MutationCompleteEmailAuthenticationArgs = {
   input: CompleteEmailAuthenticationInput;
 };
 
 
+/** The mutations root of the GraphQL interface. */
+export type MutationCompleteRegisterPasskeyArgs = {
+  input: CompleteRegisterPasskeyInput;
+};
+
+
+/** The mutations root of the GraphQL interface. */
+export type MutationConfirmPhoneNumberArgs = {
+  input: ConfirmPhoneNumberInput;
+};
+
+
+/** The mutations root of the GraphQL interface. */
+export type MutationConfirmTotpEnrollmentArgs = {
+  input: ConfirmTotpEnrollmentInput;
+};
+
+
 /** The mutations root of the GraphQL interface. */
 export type MutationCreateOauth2SessionArgs = {
   input: CreateOAuth2SessionInput;
@@ -669,24 +956,90 @@ export type MutationLockUserArgs = {
 };
 
 
+/** The mutations root of the GraphQL interface. */
+export type MutationRegenerateRecoveryCodesArgs = {
+  input: RegenerateRecoveryCodesInput;
+};
+
+
 /** The mutations root of the GraphQL interface. */
 export type MutationRemoveEmailArgs = {
   input: RemoveEmailInput;
 };
 
 
+/** The mutations root of the GraphQL interface. */
+export type MutationRemovePasskeyArgs = {
+  input: RemovePasskeyInput;
+};
+
+
+/** The mutations root of the GraphQL interface. */
+export type MutationRemovePhoneNumberArgs = {
+  input: RemovePhoneNumberInput;
+};
+
+
+/** The mutations root of the GraphQL interface. */
+export type MutationRemoveTotpArgs = {
+  input: RemoveTotpInput;
+};
+
+
+/** The mutations root of the GraphQL interface. */
+export type MutationRemoveUpstreamLinkArgs = {
+  input: RemoveUpstreamLinkInput;
+};
+
+
+/** The mutations root of the GraphQL interface. */
+export type MutationRenamePasskeyArgs = {
+  input: RenamePasskeyInput;
+};
+
+
 /** The mutations root of the GraphQL interface. */
 export type MutationResendEmailAuthenticationCodeArgs = {
   input: ResendEmailAuthenticationCodeInput;
 };
 
 
+/** The mutations root of the GraphQL interface. */
+export type MutationResendPhoneNumberCodeArgs = {
+  input: ResendPhoneNumberCodeInput;
+};
+
+
 /** The mutations root of the GraphQL interface. */
 export type MutationResendRecoveryEmailArgs = {
   input: ResendRecoveryEmailInput;
 };
 
 
+/** The mutations root of the GraphQL interface. */
+export type MutationRevokeOauth2ClientConsentsArgs = {
+  input: RevokeOAuth2ClientConsentsInput;
+};
+
+
+/** The mutations root of the GraphQL interface. */
+export type MutationRevokeOauth2ConsentArgs = {
+  input: RevokeOAuth2ConsentInput;
+};
+
+
+/** The mutations root of the GraphQL interface. */
+export type MutationRevokeTrustedDeviceArgs = {
+  input: RevokeTrustedDeviceInput;
+};
+
+
+/** The mutations root of the GraphQL interface. */
+export type MutationSetAvatarArgs = {
+  input: SetAvatarInput;
+};
+
+
 /** The mutations root of the GraphQL interface. */
 export type MutationSetCanRequestAdminArgs = {
   input: SetCanRequestAdminInput;
@@ -723,6 +1076,12 @@ export type MutationSetPasswordByRecoveryArgs = {
 };
 
 
+/** The mutations root of the GraphQL interface. */
+export type MutationSetPhoneNumberArgs = {
+  input: SetPhoneNumberInput;
+};
+
+
 /** The mutations root of the GraphQL interface. */
 export type MutationSetPrimaryEmailArgs = {
   input: SetPrimaryEmailInput;
@@ -776,6 +1135,24 @@ export type Oauth2Client = Node & {
   tosUri?: Maybe<Scalars['Url']['output']>;
 };
 
+/**
+ * An OAuth 2.0 consent represents a scope a user consented to grant to a
+ * client.
+ */
+export type Oauth2Consent = Node & {
+  __typename?: 'Oauth2Consent';
+  /** OAuth 2.0 client for which the user granted access. */
+  client: Oauth2Client;
+  /** When the user first granted this scope to the client. */
+  createdAt: Scalars['DateTime']['output'];
+  /** ID of the object. */
+  id: Scalars['ID']['output'];
+  /** When the user last granted this scope to the client. */
+  lastGrantedAt: Scalars['DateTime']['output'];
+  /** Scope token consented by the user for this client. */
+  scope: Scalars['String']['output'];
+};
+
 /**
  * An OAuth 2.0 session represents a client session which used the OAuth APIs
  * to login.
@@ -794,10 +1171,30 @@ export type Oauth2Session = CreationEvent & Node & {
   humanName?: Maybe<Scalars['String']['output']>;
   /** ID of the object. */
   id: Scalars['ID']['output'];
+  /**
+   * The name of the organization operating the autonomous system the last
+   * IP address belongs to, if known.
+   */
+  lastActiveAsOrganization?: Maybe<Scalars['String']['output']>;
+  /**
+   * The number of the autonomous system the last IP address belongs to, if
+   * known.
+   */
+  lastActiveAsn?: Maybe<Scalars['Int']['output']>;
   /** The last time the session was active. */
   lastActiveAt?: Maybe<Scalars['DateTime']['output']>;
+  /**
+   * The ISO 3166-1 code of the country the last IP address is located in,
+   * if known.
+   */
+  lastActiveCountry?: Maybe<Scalars['String']['output']>;
   /** The last IP address used by the session. */
   lastActiveIp?: Maybe<Scalars['String']['output']>;
+  /**
+   * When the user explicitly granted offline access to the client, if they
+   * did.
+   */
+  offlineAccessGrantedAt?: Maybe<Scalars['DateTime']['output']>;
   /** Scope granted for this session. */
   scope: Scalars['String']['output'];
   /** The state of the session. */
@@ -883,6 +1280,8 @@ export type Query = {
   userEmail?: Maybe<UserEmail>;
   /** Fetch a user email authentication session */
   userEmailAuthentication?: Maybe<UserEmailAuthentication>;
+  /** Fetch a user passkey by its ID. */
+  userPasskey?: Maybe<UserPasskey>;
   /** Fetch a user recovery ticket. */
   userRecoveryTicket?: Maybe<UserRecoveryTicket>;
   /**
@@ -980,6 +1379,12 @@ export type QueryUserEmailAuthenticationArgs = {
 };
 
 
+/** The query root of the GraphQL interface. */
+export type QueryUserPasskeyArgs = {
+  id: Scalars['ID']['input'];
+};
+
+
 /** The query root of the GraphQL interface. */
 export type QueryUserRecoveryTicketArgs = {
   ticket: Scalars['String']['input'];
@@ -996,6 +1401,38 @@ export type QueryUsersArgs = {
   state?: InputMaybe<UserState>;
 };
 
+/** The input for the `regenerateRecoveryCodes` mutation */
+export type RegenerateRecoveryCodesInput = {
+  /**
+   * The user's current password. This is required if the user is not an
+   * admin and it has a password on its account.
+   */
+  password?: InputMaybe<Scalars['String']['input']>;
+};
+
+/** The payload of the `regenerateRecoveryCodes` mutation */
+export type RegenerateRecoveryCodesPayload = {
+  __typename?: 'RegenerateRecoveryCodesPayload';
+  /**
+   * The new recovery codes, which replace the previous ones. They are only
+   * shown once.
+   */
+  recoveryCodes?: Maybe<Array<Scalars['String']['output']>>;
+  /** Status of the operation */
+  status: RegenerateRecoveryCodesStatus;
+};
+
+/** The status of the `regenerateRecoveryCodes` mutation */
+export type RegenerateRecoveryCodesStatus =
+  /** The password provided is incorrect */
+  | 'INCORRECT_PASSWORD'
+  /** The user has no second factor */
+  | 'NOT_ENROLLED'
+  /** The user has to authenticate again first */
+  | 'REAUTHENTICATION_REQUIRED'
+  /** A new set of recovery codes was generated */
+  | 'REGENERATED';
+
 /** The input for the `removeEmail` mutation */
 export type RemoveEmailInput = {
   /**
@@ -1024,67 +1461,329 @@ export type RemoveEmailStatus =
   | 'INCORRECT_PASSWORD'
   /** The email address was not found */
   | 'NOT_FOUND'
+  /** The user has to authenticate again first */
+  | 'REAUTHENTICATION_REQUIRED'
   /** The email address was removed */
   | 'REMOVED';
 
-/** The input for the `resendEmailAuthenticationCode` mutation */
-export type ResendEmailAuthenticationCodeInput = {
-  /** The ID of the authentication session to resend the code for */
-  id: Scalars['ID']['input'];
-  /** The language to use for the email */
-  language?: Scalars['String']['input'];
+/** The input for the `removePasskey` mutation */
+export type RemovePasskeyInput = {
+  /**
+   * The user's current password. This is required if the user is not an
+   * admin and it has a password on its account.
+   */
+  password?: InputMaybe<Scalars['String']['input']>;
+  /** The ID of the passkey to remove */
+  userPasskeyId: Scalars['ID']['input'];
 };
 
-/** The payload of the `resendEmailAuthenticationCode` mutation */
-export type ResendEmailAuthenticationCodePayload = {
-  __typename?: 'ResendEmailAuthenticationCodePayload';
+/** The payload of the `removePasskey` mutation */
+export type RemovePasskeyPayload = {
+  __typename?: 'RemovePasskeyPayload';
   /** Status of the operation */
-  status: ResendEmailAuthenticationCodeStatus;
+  status: RemovePasskeyStatus;
+  /** The user to whom the passkey belonged */
+  user?: Maybe<User>;
 };
 
-/** The status of the `resendEmailAuthenticationCode` mutation */
-export type ResendEmailAuthenticationCodeStatus =
-  /** The email authentication session is already completed */
-  | 'COMPLETED'
-  /** Too many attempts to resend an email authentication code */
-  | 'RATE_LIMITED'
-  /** The email was resent */
-  | 'RESENT';
+/** The status of the `removePasskey` mutation */
+export type RemovePasskeyStatus =
+  /** The password provided is incorrect */
+  | 'INCORRECT_PASSWORD'
+  /** The passkey was not found */
+  | 'NOT_FOUND'
+  /** The user has to authenticate again first */
+  | 'REAUTHENTICATION_REQUIRED'
+  /** The passkey was removed */
+  | 'REMOVED';
 
-/** The input for the `resendRecoveryEmail` mutation. */
-export type ResendRecoveryEmailInput = {
-  /** The recovery ticket to use. */
-  ticket: Scalars['String']['input'];
+/** The input for the `removePhoneNumber` mutation */
+export type RemovePhoneNumberInput = {
+  /**
+   * The user's current password. This is required if the user is not an
+   * admin and it has a password on its account.
+   */
+  password?: InputMaybe<Scalars['String']['input']>;
 };
 
-/** The return type for the `resendRecoveryEmail` mutation. */
-export type ResendRecoveryEmailPayload = {
-  __typename?: 'ResendRecoveryEmailPayload';
-  /** URL to continue the recovery process */
-  progressUrl?: Maybe<Scalars['Url']['output']>;
+/** The payload of the `removePhoneNumber` mutation */
+export type RemovePhoneNumberPayload = {
+  __typename?: 'RemovePhoneNumberPayload';
   /** Status of the operation */
-  status: ResendRecoveryEmailStatus;
+  status: RemovePhoneNumberStatus;
 };
 
-/** The status of the `resendRecoveryEmail` mutation. */
-export type ResendRecoveryEmailStatus =
-  /** The recovery ticket was not found. */
-  | 'NO_SUCH_RECOVERY_TICKET'
-  /** The rate limit was exceeded. */
-  | 'RATE_LIMITED'
-  /** The recovery email was sent. */
-  | 'SENT';
+/** The status of the `removePhoneNumber` mutation */
+export type RemovePhoneNumberStatus =
+  /** The password provided is incorrect */
+  | 'INCORRECT_PASSWORD'
+  /** The user has no phone number */
+  | 'NOT_FOUND'
+  /** The user has to authenticate again first */
+  | 'REAUTHENTICATION_REQUIRED'
+  /** The phone number was removed */
+  | 'REMOVED';
 
-/** A client session, either compat or OAuth 2.0 */
-export type Session = CompatSession | Oauth2Session;
+/** The input for the `removeTotp` mutation */
+export type RemoveTotpInput = {
+  /**
+   * The user's current password. This is required if the user is not an
+   * admin and it has a password on its account.
+   */
+  password?: InputMaybe<Scalars['String']['input']>;
+};
 
-/** The state of a session */
-export type SessionState =
+/** The payload of the `removeTotp` mutation */
+export type RemoveTotpPayload = {
+  __typename?: 'RemoveTotpPayload';
+  /** Status of the operation */
+  status: RemoveTotpStatus;
+};
+
+/** The status of the `removeTotp` mutation */
+export type RemoveTotpStatus =
+  /** The password provided is incorrect */
+  | 'INCORRECT_PASSWORD'
+  /** The user has no second factor */
+  | 'NOT_FOUND'
+  /** The user has to authenticate again first */
+  | 'REAUTHENTICATION_REQUIRED'
+  /** The second factor was removed */
+  | 'REMOVED'
+  /** The server requires the user to have a second factor */
+  | 'REQUIRED';
+
+/** The input for the `removeUpstreamLink` mutation */
+export type RemoveUpstreamLinkInput = {
+  /**
+   * The user's current password. This is required if the user is not an
+   * admin and it has a password on its account.
+   */
+  password?: InputMaybe<Scalars['String']['input']>;
+  /** The ID of the upstream link to remove */
+  upstreamLinkId: Scalars['ID']['input'];
+};
+
+/** The payload of the `removeUpstreamLink` mutation */
+export type RemoveUpstreamLinkPayload = {
+  __typename?: 'RemoveUpstreamLinkPayload';
+  /** Status of the operation */
+  status: RemoveUpstreamLinkStatus;
+  /** The user to whom the upstream link belonged */
+  user?: Maybe<User>;
+};
+
+/** The status of the `removeUpstreamLink` mutation */
+export type RemoveUpstreamLinkStatus =
+  /** The password provided is incorrect */
+  | 'INCORRECT_PASSWORD'
+  /** The upstream link is the only way left for the user to sign in */
+  | 'LAST_LOGIN_METHOD'
+  /** The upstream link was not found */
+  | 'NOT_FOUND'
+  /** The user has to authenticate again first */
+  | 'REAUTHENTICATION_REQUIRED'
+  /** The upstream link was removed */
+  | 'REMOVED';
+
+/** The input for the `renamePasskey` mutation */
+export type RenamePasskeyInput = {
+  /** The new name of the passkey */
+  name: Scalars['String']['input'];
+  /** The ID of the passkey to rename */
+  userPasskeyId: Scalars['ID']['input'];
+};
+
+/** The payload of the `renamePasskey` mutation */
+export type RenamePasskeyPayload = {
+  __typename?: 'RenamePasskeyPayload';
+  /** The passkey that was renamed */
+  passkey?: Maybe<UserPasskey>;
+  /** Status of the operation */
+  status: RenamePasskeyStatus;
+};
+
+/** The status of the `renamePasskey` mutation */
+export type RenamePasskeyStatus =
+  /** The new name is invalid */
+  | 'INVALID_NAME'
+  /** The passkey was not found */
+  | 'NOT_FOUND'
+  /** The passkey was renamed */
+  | 'RENAMED';
+
+/** The input for the `resendEmailAuthenticationCode` mutation */
+export type ResendEmailAuthenticationCodeInput = {
+  /** The ID of the authentication session to resend the code for */
+  id: Scalars['ID']['input'];
+  /** The language to use for the email */
+  language?: Scalars['String']['input'];
+};
+
+/** The payload of the `resendEmailAuthenticationCode` mutation */
+export type ResendEmailAuthenticationCodePayload = {
+  __typename?: 'ResendEmailAuthenticationCodePayload';
+  /** Status of the operation */
+  status: ResendEmailAuthenticationCodeStatus;
+};
+
+/** The status of the `resendEmailAuthenticationCode` mutation */
+export type ResendEmailAuthenticationCodeStatus =
+  /** The email authentication session is already completed */
+  | 'COMPLETED'
+  /** Too many attempts to resend an email authentication code */
+  | 'RATE_LIMITED'
+  /** The email was resent */
+  | 'RESENT';
+
+/** The input for the `resendPhoneNumberCode` mutation */
+export type ResendPhoneNumberCodeInput = {
+  /** The language to use for the text message */
+  language?: Scalars['String']['input'];
+};
+
+/** The payload of the `resendPhoneNumberCode` mutation */
+export type ResendPhoneNumberCodePayload = {
+  __typename?: 'ResendPhoneNumberCodePayload';
+  /** Status of the operation */
+  status: ResendPhoneNumberCodeStatus;
+};
+
+/** The status of the `resendPhoneNumberCode` mutation */
+export type ResendPhoneNumberCodeStatus =
+  /** There is no phone number pending confirmation */
+  | 'NOT_FOUND'
+  /** A code was sent too recently, try again later */
+  | 'RATE_LIMITED'
+  /** A new code was sent */
+  | 'RESENT';
+
+/** The input for the `resendRecoveryEmail` mutation. */
+export type ResendRecoveryEmailInput = {
+  /** The recovery ticket to use. */
+  ticket: Scalars['String']['input'];
+};
+
+/** The return type for the `resendRecoveryEmail` mutation. */
+export type ResendRecoveryEmailPayload = {
+  __typename?: 'ResendRecoveryEmailPayload';
+  /** URL to continue the recovery process */
+  progressUrl?: Maybe<Scalars['Url']['output']>;
+  /** Status of the operation */
+  status: ResendRecoveryEmailStatus;
+};
+
+/** The status of the `resendRecoveryEmail` mutation. */
+export type ResendRecoveryEmailStatus =
+  /** The recovery ticket was not found. */
+  | 'NO_SUCH_RECOVERY_TICKET'
+  /** The rate limit was exceeded. */
+  | 'RATE_LIMITED'
+  /** The recovery email was sent. */
+  | 'SENT';
+
+/** The input of the `revokeOauth2ClientConsents` mutation. */
+export type RevokeOAuth2ClientConsentsInput = {
+  /** The ID of the client to revoke the consents for. */
+  oauth2ClientId: Scalars['ID']['input'];
+  /** The ID of the user who gave the consents. */
+  userId: Scalars['ID']['input'];
+};
+
+export type RevokeOAuth2ClientConsentsPayload = {
+  __typename?: 'RevokeOAuth2ClientConsentsPayload';
+  /** The number of scope tokens which were revoked. */
+  revokedCount: Scalars['Int']['output'];
+  /** The status of the mutation. */
+  status: RevokeOAuth2ClientConsentsStatus;
+};
+
+/** The status of the `revokeOauth2ClientConsents` mutation. */
+export type RevokeOAuth2ClientConsentsStatus =
+  /** The user or the client was not found. */
+  | 'NOT_FOUND'
+  /** The consents were revoked. */
+  | 'REVOKED';
+
+/** The input of the `revokeOauth2Consent` mutation. */
+export type RevokeOAuth2ConsentInput = {
+  /** The ID of the consent to revoke. */
+  oauth2ConsentId: Scalars['ID']['input'];
+};
+
+export type RevokeOAuth2ConsentPayload = {
+  __typename?: 'RevokeOAuth2ConsentPayload';
+  /** Returns the revoked consent. */
+  oauth2Consent?: Maybe<Oauth2Consent>;
+  /** The status of the mutation. */
+  status: RevokeOAuth2ConsentStatus;
+};
+
+/** The status of the `revokeOauth2Consent` mutation. */
+export type RevokeOAuth2ConsentStatus =
+  /** The consent was not found. */
+  | 'NOT_FOUND'
+  /** The consent was revoked. */
+  | 'REVOKED';
+
+/** The input for the `revokeTrustedDevice` mutation */
+export type RevokeTrustedDeviceInput = {
+  /** The ID of the trusted device to revoke */
+  userTrustedDeviceId: Scalars['ID']['input'];
+};
+
+/** The payload of the `revokeTrustedDevice` mutation */
+export type RevokeTrustedDevicePayload = {
+  __typename?: 'RevokeTrustedDevicePayload';
+  /** Status of the operation */
+  status: RevokeTrustedDeviceStatus;
+  /** The user who trusted the device */
+  user?: Maybe<User>;
+};
+
+/** The status of the `revokeTrustedDevice` mutation */
+export type RevokeTrustedDeviceStatus =
+  /** The trusted device was not found */
+  | 'NOT_FOUND'
+  /** The device is no longer trusted */
+  | 'REVOKED';
+
+/** A client session, either compat or OAuth 2.0 */
+export type Session = CompatSession | Oauth2Session;
+
+/** The state of a session */
+export type SessionState =
   /** The session is active. */
   | 'ACTIVE'
   /** The session is no longer active. */
   | 'FINISHED';
 
+/** The input for the `setAvatar` mutation */
+export type SetAvatarInput = {
+  /** The image to use as avatar. If `None`, the avatar will be removed. */
+  avatar?: InputMaybe<Scalars['Upload']['input']>;
+  /** The ID of the user to set the avatar of */
+  userId: Scalars['ID']['input'];
+};
+
+/** The payload of the `setAvatar` mutation */
+export type SetAvatarPayload = {
+  __typename?: 'SetAvatarPayload';
+  /** Status of the operation */
+  status: SetAvatarStatus;
+  /** The user that was updated */
+  user?: Maybe<User>;
+};
+
+/** The status of the `setAvatar` mutation */
+export type SetAvatarStatus =
+  /** The image is not in a supported format */
+  | 'INVALID_FORMAT'
+  /** The avatar was set */
+  | 'SET'
+  /** The image is too large */
+  | 'TOO_LARGE';
+
 /** The input for the `setCanRequestAdmin` mutation. */
 export type SetCanRequestAdminInput = {
   /** Whether the user can request admin. */
@@ -1138,10 +1837,14 @@ export type SetDisplayNamePayload = {
   status: SetDisplayNameStatus;
   /** The user that was updated */
   user?: Maybe<User>;
+  /** The list of policy violations if the display name was denied */
+  violations?: Maybe<Array<Scalars['String']['output']>>;
 };
 
 /** The status of the `setDisplayName` mutation */
 export type SetDisplayNameStatus =
+  /** The display name was denied by the policy */
+  | 'DENIED'
   /** The display name is invalid */
   | 'INVALID'
   /** The display name was set */
@@ -1237,6 +1940,11 @@ export type SetPasswordStatus =
    * provider.
    */
   | 'PASSWORD_CHANGES_DISABLED'
+  /**
+   * The new password is one of the previous passwords of the user, which
+   * the password history policy doesn't allow to reuse.
+   */
+  | 'PASSWORD_REUSED'
   /**
    * The specified recovery ticket has already been used and cannot be used
    * again.
@@ -1245,6 +1953,41 @@ export type SetPasswordStatus =
   /** The supplied current password was wrong. */
   | 'WRONG_PASSWORD';
 
+/** The input for the `setPhoneNumber` mutation */
+export type SetPhoneNumberInput = {
+  /** The language to use for the text message */
+  language?: Scalars['String']['input'];
+  /**
+   * The user's current password. This is required if the user is not an
+   * admin and it has a password on its account.
+   */
+  password?: InputMaybe<Scalars['String']['input']>;
+  /** The phone number to set, in the international format */
+  phoneNumber: Scalars['String']['input'];
+};
+
+/** The payload of the `setPhoneNumber` mutation */
+export type SetPhoneNumberPayload = {
+  __typename?: 'SetPhoneNumberPayload';
+  /** The phone number that was set, pending confirmation */
+  phone?: Maybe<UserPhone>;
+  /** Status of the operation */
+  status: SetPhoneNumberStatus;
+};
+
+/** The status of the `setPhoneNumber` mutation */
+export type SetPhoneNumberStatus =
+  /** The password provided is incorrect */
+  | 'INCORRECT_PASSWORD'
+  /** The phone number is invalid */
+  | 'INVALID_PHONE_NUMBER'
+  /** A code was sent too recently, try again later */
+  | 'RATE_LIMITED'
+  /** The user has to authenticate again first */
+  | 'REAUTHENTICATION_REQUIRED'
+  /** The phone number was set, and a code was sent to confirm it */
+  | 'STARTED';
+
 /** The input for the `setPrimaryEmail` mutation */
 export type SetPrimaryEmailInput = {
   /** The ID of the email address to set as primary */
@@ -1272,6 +2015,15 @@ export type SiteConfig = Node & {
   __typename?: 'SiteConfig';
   /** Whether users can delete their own account. */
   accountDeactivationAllowed: Scalars['Boolean']['output'];
+  /**
+   * How many days, rounded up, accounts stay deactivated after their owner
+   * asked to delete them, before being erased. Users can get their account
+   * back by signing in again during that time. Not set if accounts are
+   * erased straight away.
+   */
+  accountDeletionGracePeriodDays?: Maybe<Scalars['Int']['output']>;
+  /** Whether users can change their avatar. */
+  avatarChangeAllowed: Scalars['Boolean']['output'];
   /** The configuration of CAPTCHA provider. */
   captchaConfig?: Maybe<CaptchaConfig>;
   /** Whether users can change their display name. */
@@ -1290,20 +2042,36 @@ export type SiteConfig = Node & {
    * in use is <https://crates.io/crates/zxcvbn>.
    */
   minimumPasswordComplexity: Scalars['Int']['output'];
+  /** Whether users can register passkeys and log in with them. */
+  passkeysEnabled: Scalars['Boolean']['output'];
   /** Whether passwords are enabled and users can change their own passwords. */
   passwordChangeAllowed: Scalars['Boolean']['output'];
   /** Whether passwords are enabled for login. */
   passwordLoginEnabled: Scalars['Boolean']['output'];
   /** Whether passwords are enabled and users can register using a password. */
   passwordRegistrationEnabled: Scalars['Boolean']['output'];
+  /** Whether users can add and change the phone number on their account. */
+  phoneNumberChangeAllowed: Scalars['Boolean']['output'];
   /** Experimental plan management iframe URI. */
   planManagementIframeUri?: Maybe<Scalars['String']['output']>;
   /** The URL to the privacy policy. */
   policyUri?: Maybe<Scalars['Url']['output']>;
   /** The server name of the homeserver. */
   serverName: Scalars['String']['output'];
+  /**
+   * Whether users with a confirmed phone number get a code by SMS when
+   * logging in with their password.
+   */
+  smsSecondFactorEnabled: Scalars['Boolean']['output'];
   /** The URL to the terms of service. */
   tosUri?: Maybe<Scalars['Url']['output']>;
+  /** Whether users can add a TOTP second factor, and who must have one. */
+  totpPolicy: TotpPolicy;
+  /**
+   * Whether users can choose not to be asked for their second factor again
+   * in a browser.
+   */
+  trustedDevicesEnabled: Scalars['Boolean']['output'];
 };
 
 /** The input for the `startEmailAuthentication` mutation */
@@ -1342,9 +2110,65 @@ export type StartEmailAuthenticationStatus =
   | 'IN_USE'
   /** Too many attempts to start an email authentication */
   | 'RATE_LIMITED'
+  /** The user has to authenticate again first */
+  | 'REAUTHENTICATION_REQUIRED'
   /** The email address was started */
   | 'STARTED';
 
+/** The payload of the `startRegisterPasskey` mutation */
+export type StartRegisterPasskeyPayload = {
+  __typename?: 'StartRegisterPasskeyPayload';
+  /**
+   * The ID of the challenge, to pass to the `completeRegisterPasskey`
+   * mutation
+   */
+  id: Scalars['ID']['output'];
+  /**
+   * The options to pass to `navigator.credentials.create()`, serialized as
+   * JSON
+   */
+  options: Scalars['String']['output'];
+};
+
+/** The payload of the `startTotpEnrollment` mutation */
+export type StartTotpEnrollmentPayload = {
+  __typename?: 'StartTotpEnrollmentPayload';
+  /** The `otpauth://` URI to add the second factor to an authenticator app */
+  provisioningUri?: Maybe<Scalars['String']['output']>;
+  /** The provisioning URI as a QR code, in a `data:` URI of an SVG image */
+  qrCode?: Maybe<Scalars['String']['output']>;
+  /**
+   * The shared secret, encoded in base32, for users who can't scan the QR
+   * code
+   */
+  secret?: Maybe<Scalars['String']['output']>;
+  /** Status of the operation */
+  status: StartTotpEnrollmentStatus;
+};
+
+/** The status of the `startTotpEnrollment` mutation */
+export type StartTotpEnrollmentStatus =
+  /** The user already has a confirmed second factor */
+  | 'ALREADY_ENROLLED'
+  /** The user has to authenticate again first */
+  | 'REAUTHENTICATION_REQUIRED'
+  /** A new secret was generated, and must be confirmed with a code */
+  | 'STARTED';
+
+/** Whether users can add a TOTP second factor, and who must have one */
+export type TotpPolicy =
+  /** Users can't add a second factor */
+  | 'DISABLED'
+  /** Users can add a second factor if they want to */
+  | 'OPTIONAL'
+  /** All users must have a second factor */
+  | 'REQUIRED'
+  /**
+   * Users who can request admin access must have a second factor, others
+   * can add one if they want to
+   */
+  | 'REQUIRED_FOR_ADMINS';
+
 /** The input for the `unlockUser` mutation. */
 export type UnlockUserInput = {
   /** The ID of the user to unlock */
@@ -1422,7 +2246,11 @@ export type UpstreamOAuth2Provider = CreationEvent & Node & {
   id: Scalars['ID']['output'];
   /** OpenID Connect issuer URL. */
   issuer?: Maybe<Scalars['String']['output']>;
-  /** URL to start the linking process of the current user with this provider. */
+  /**
+   * URL to start the linking process of the current user with this provider.
+   *
+   * Once the link is made, the user is sent back to their account.
+   */
   linkUrl: Scalars['Url']['output'];
 };
 
@@ -1475,8 +2303,41 @@ export type User = Node & {
   lockedAt?: Maybe<Scalars['DateTime']['output']>;
   /** Access to the user's Matrix account information. */
   matrix: MatrixUser;
+  /**
+   * Get the list of scopes the user consented to grant to OAuth 2.0 clients,
+   * grouped by client
+   */
+  oauth2Consents: Array<Oauth2Consent>;
   /** Get the list of OAuth 2.0 sessions, chronologically sorted */
   oauth2Sessions: Oauth2SessionConnection;
+  /** Get the list of passkeys registered by the user, oldest first. */
+  passkeys: Array<UserPasskey>;
+  /**
+   * The phone number of the user, confirmed or not. Is `null` if the user
+   * didn't add one.
+   */
+  phone?: Maybe<UserPhone>;
+  /**
+   * The primary email address of the user, used for notifications and as
+   * the `email` claim. This is the oldest email address of the user, unless
+   * they picked another one. Is `null` if the user has no email address.
+   */
+  primaryEmail?: Maybe<UserEmail>;
+  /**
+   * The number of recovery codes the user can still use to log in if their
+   * second factor is unavailable.
+   */
+  recoveryCodesLeft: Scalars['Int']['output'];
+  /**
+   * The TOTP second factor of the user. Is `null` if the user didn't add
+   * one, or didn't confirm it yet.
+   */
+  totp?: Maybe<UserTotp>;
+  /**
+   * Get the list of browsers in which the user chose not to be asked for
+   * their second factor again, most recently trusted first.
+   */
+  trustedDevices: Array<UserTrustedDevice>;
   /** Get the list of upstream OAuth 2.0 links */
   upstreamOauth2Links: UpstreamOAuth2LinkConnection;
   /** Username chosen by the user. */
@@ -1547,6 +2408,7 @@ export type UserOauth2SessionsArgs = {
   first?: InputMaybe<Scalars['Int']['input']>;
   last?: InputMaybe<Scalars['Int']['input']>;
   lastActive?: InputMaybe<DateFilter>;
+  offlineAccess?: InputMaybe<Scalars['Boolean']['input']>;
   state?: InputMaybe<SessionState>;
 };
 
@@ -1657,6 +2519,40 @@ export type UserEmailState =
   /** The email address is pending confirmation. */
   | 'PENDING';
 
+/** A passkey registered by a user, which they can use to log in */
+export type UserPasskey = CreationEvent & Node & {
+  __typename?: 'UserPasskey';
+  /** The AAGUID of the authenticator model, which identifies its vendor */
+  aaguid: Scalars['String']['output'];
+  /** When the object was created. */
+  createdAt: Scalars['DateTime']['output'];
+  /** ID of the object. */
+  id: Scalars['ID']['output'];
+  /**
+   * When the passkey was last used to log in. Is `null` if it was never
+   * used.
+   */
+  lastUsedAt?: Maybe<Scalars['DateTime']['output']>;
+  /** The name the user gave to the passkey */
+  name: Scalars['String']['output'];
+};
+
+/** A phone number of a user, which receives verification codes by SMS */
+export type UserPhone = CreationEvent & Node & {
+  __typename?: 'UserPhone';
+  /**
+   * When the phone number was confirmed with a code. Is `null` if it is
+   * still pending confirmation.
+   */
+  confirmedAt?: Maybe<Scalars['DateTime']['output']>;
+  /** When the object was created. */
+  createdAt: Scalars['DateTime']['output'];
+  /** ID of the object. */
+  id: Scalars['ID']['output'];
+  /** The phone number, in the E.164 format. */
+  phoneNumber: Scalars['String']['output'];
+};
+
 /** A recovery ticket */
 export type UserRecoveryTicket = CreationEvent & Node & {
   __typename?: 'UserRecoveryTicket';
@@ -1688,6 +2584,43 @@ export type UserState =
   /** The user is locked. */
   | 'LOCKED';
 
+/**
+ * A TOTP second factor, which the user has to enter a code from when logging
+ * in with their password
+ */
+export type UserTotp = CreationEvent & Node & {
+  __typename?: 'UserTotp';
+  /** When the second factor was confirmed with a first code. */
+  confirmedAt?: Maybe<Scalars['DateTime']['output']>;
+  /** When the object was created. */
+  createdAt: Scalars['DateTime']['output'];
+  /** ID of the object. */
+  id: Scalars['ID']['output'];
+  /** When a code was last used to log in. Is `null` if it was never used. */
+  lastUsedAt?: Maybe<Scalars['DateTime']['output']>;
+};
+
+/**
+ * A browser in which the user chose not to be asked for their second factor
+ * again
+ */
+export type UserTrustedDevice = CreationEvent & Node & {
+  __typename?: 'UserTrustedDevice';
+  /** When the object was created. */
+  createdAt: Scalars['DateTime']['output'];
+  /** When the browser stops being trusted. */
+  expiresAt: Scalars['DateTime']['output'];
+  /** ID of the object. */
+  id: Scalars['ID']['output'];
+  /**
+   * When the browser was last used to skip the second factor. Is `null` if
+   * it was never used.
+   */
+  lastUsedAt?: Maybe<Scalars['DateTime']['output']>;
+  /** The user-agent of the browser when it was trusted. */
+  userAgent?: Maybe<UserAgent>;
+};
+
 /** Represents the current viewer */
 export type Viewer = Anonymous | User;
 
@@ -1696,7 +2629,7 @@ export type ViewerSession = Anonymous | BrowserSession | Oauth2Session;
 
 export type AccountDeleteButton_UserFragment = { __typename?: 'User', username: string, hasPassword: boolean, matrix: { __typename?: 'MatrixUser', mxid: string, displayName?: string | null } } & { ' $fragmentName'?: 'AccountDeleteButton_UserFragment' };
 
-export type AccountDeleteButton_SiteConfigFragment = { __typename?: 'SiteConfig', passwordLoginEnabled: boolean } & { ' $fragmentName'?: 'AccountDeleteButton_SiteConfigFragment' };
+export type AccountDeleteButton_SiteConfigFragment = { __typename?: 'SiteConfig', passwordLoginEnabled: boolean, accountDeletionGracePeriodDays?: number | null } & { ' $fragmentName'?: 'AccountDeleteButton_SiteConfigFragment' };
 
 export type DeactivateUserMutationVariables = Exact<{
   hsErase: Scalars['Boolean']['input'];
@@ -1778,7 +2711,7 @@ export type SetCompatSessionNameMutationVariables = Exact<{
 export type SetCompatSessionNameMutation = { __typename?: 'Mutation', setCompatSessionName: { __typename?: 'SetCompatSessionNamePayload', status: SetCompatSessionNameStatus } };
 
 export type CompatSession_DetailFragment = (
-  { __typename?: 'CompatSession', id: string, createdAt: string, deviceId?: string | null, finishedAt?: string | null, lastActiveIp?: string | null, lastActiveAt?: string | null, humanName?: string | null, userAgent?: { __typename?: 'UserAgent', name?: string | null, os?: string | null, model?: string | null } | null, ssoLogin?: { __typename?: 'CompatSsoLogin', id: string, redirectUri: string } | null }
+  { __typename?: 'CompatSession', id: string, createdAt: string, deviceId?: string | null, finishedAt?: string | null, lastActiveIp?: string | null, lastActiveAt?: string | null, humanName?: string | null, impersonated: boolean, userAgent?: { __typename?: 'UserAgent', name?: string | null, os?: string | null, model?: string | null } | null, ssoLogin?: { __typename?: 'CompatSsoLogin', id: string, redirectUri: string } | null }
   & { ' $fragmentRefs'?: { 'EndCompatSessionButton_SessionFragment': EndCompatSessionButton_SessionFragment } }
 ) & { ' $fragmentName'?: 'CompatSession_DetailFragment' };
 
@@ -1791,7 +2724,7 @@ export type SetOAuth2SessionNameMutationVariables = Exact<{
 export type SetOAuth2SessionNameMutation = { __typename?: 'Mutation', setOauth2SessionName: { __typename?: 'SetOAuth2SessionNamePayload', status: SetOAuth2SessionNameStatus } };
 
 export type OAuth2Session_DetailFragment = (
-  { __typename?: 'Oauth2Session', id: string, scope: string, createdAt: string, finishedAt?: string | null, lastActiveIp?: string | null, lastActiveAt?: string | null, humanName?: string | null, userAgent?: { __typename?: 'UserAgent', name?: string | null, model?: string | null, os?: string | null } | null, client: { __typename?: 'Oauth2Client', id: string, clientId: string, clientName?: string | null, clientUri?: string | null, logoUri?: string | null } }
+  { __typename?: 'Oauth2Session', id: string, scope: string, createdAt: string, finishedAt?: string | null, lastActiveIp?: string | null, lastActiveAt?: string | null, humanName?: string | null, offlineAccessGrantedAt?: string | null, userAgent?: { __typename?: 'UserAgent', name?: string | null, model?: string | null, os?: string | null } | null, client: { __typename?: 'Oauth2Client', id: string, clientId: string, clientName?: string | null, clientUri?: string | null, logoUri?: string | null } }
   & { ' $fragmentRefs'?: { 'EndOAuth2SessionButton_SessionFragment': EndOAuth2SessionButton_SessionFragment } }
 ) & { ' $fragmentName'?: 'OAuth2Session_DetailFragment' };
 
@@ -1805,9 +2738,16 @@ export type RemoveEmailMutationVariables = Exact<{
 
 export type RemoveEmailMutation = { __typename?: 'Mutation', removeEmail: { __typename?: 'RemoveEmailPayload', status: RemoveEmailStatus, user?: { __typename?: 'User', id: string } | null } };
 
-export type UserGreeting_UserFragment = { __typename?: 'User', id: string, matrix: { __typename?: 'MatrixUser', mxid: string, displayName?: string | null } } & { ' $fragmentName'?: 'UserGreeting_UserFragment' };
+export type SetPrimaryEmailMutationVariables = Exact<{
+  id: Scalars['ID']['input'];
+}>;
+
+
+export type SetPrimaryEmailMutation = { __typename?: 'Mutation', setPrimaryEmail: { __typename?: 'SetPrimaryEmailPayload', status: SetPrimaryEmailStatus, user?: { __typename?: 'User', id: string } | null } };
 
-export type UserGreeting_SiteConfigFragment = { __typename?: 'SiteConfig', displayNameChangeAllowed: boolean } & { ' $fragmentName'?: 'UserGreeting_SiteConfigFragment' };
+export type UserGreeting_UserFragment = { __typename?: 'User', id: string, matrix: { __typename?: 'MatrixUser', mxid: string, displayName?: string | null, avatarUrl?: string | null } } & { ' $fragmentName'?: 'UserGreeting_UserFragment' };
+
+export type UserGreeting_SiteConfigFragment = { __typename?: 'SiteConfig', displayNameChangeAllowed: boolean, avatarChangeAllowed: boolean } & { ' $fragmentName'?: 'UserGreeting_SiteConfigFragment' };
 
 export type SetDisplayNameMutationVariables = Exact<{
   userId: Scalars['ID']['input'];
@@ -1815,7 +2755,15 @@ export type SetDisplayNameMutationVariables = Exact<{
 }>;
 
 
-export type SetDisplayNameMutation = { __typename?: 'Mutation', setDisplayName: { __typename?: 'SetDisplayNamePayload', status: SetDisplayNameStatus } };
+export type SetDisplayNameMutation = { __typename?: 'Mutation', setDisplayName: { __typename?: 'SetDisplayNamePayload', status: SetDisplayNameStatus, violations?: Array<string> | null } };
+
+export type SetAvatarMutationVariables = Exact<{
+  userId: Scalars['ID']['input'];
+  avatar?: InputMaybe<Scalars['Upload']['input']>;
+}>;
+
+
+export type SetAvatarMutation = { __typename?: 'Mutation', setAvatar: { __typename?: 'SetAvatarPayload', status: SetAvatarStatus } };
 
 export type AddEmailForm_UserFragment = { __typename?: 'User', hasPassword: boolean } & { ' $fragmentName'?: 'AddEmailForm_UserFragment' };
 
@@ -1841,48 +2789,166 @@ export type UserEmailListQueryVariables = Exact<{
 export type UserEmailListQuery = { __typename?: 'Query', viewer: { __typename: 'Anonymous' } | { __typename: 'User', emails: { __typename?: 'UserEmailConnection', totalCount: number, edges: Array<{ __typename?: 'UserEmailEdge', cursor: string, node: (
           { __typename?: 'UserEmail' }
           & { ' $fragmentRefs'?: { 'UserEmail_EmailFragment': UserEmail_EmailFragment } }
-        ) }>, pageInfo: { __typename?: 'PageInfo', hasNextPage: boolean, hasPreviousPage: boolean, startCursor?: string | null, endCursor?: string | null } } } };
+        ) }>, pageInfo: { __typename?: 'PageInfo', hasNextPage: boolean, hasPreviousPage: boolean, startCursor?: string | null, endCursor?: string | null } }, primaryEmail?: { __typename?: 'UserEmail', id: string } | null } };
 
 export type UserEmailList_UserFragment = { __typename?: 'User', hasPassword: boolean } & { ' $fragmentName'?: 'UserEmailList_UserFragment' };
 
 export type UserEmailList_SiteConfigFragment = { __typename?: 'SiteConfig', emailChangeAllowed: boolean, passwordLoginEnabled: boolean } & { ' $fragmentName'?: 'UserEmailList_SiteConfigFragment' };
 
-export type BrowserSessionsOverview_UserFragment = { __typename?: 'User', id: string, browserSessions: { __typename?: 'BrowserSessionConnection', totalCount: number } } & { ' $fragmentName'?: 'BrowserSessionsOverview_UserFragment' };
+export type UserPasskeyListQueryVariables = Exact<{ [key: string]: never; }>;
 
-export type UserProfileQueryVariables = Exact<{ [key: string]: never; }>;
 
+export type UserPasskeyListQuery = { __typename?: 'Query', viewer: { __typename: 'Anonymous' } | { __typename: 'User', id: string, passkeys: Array<{ __typename?: 'UserPasskey', id: string, name: string, createdAt: string, lastUsedAt?: string | null }> } };
 
-export type UserProfileQuery = { __typename?: 'Query', viewerSession: { __typename: 'Anonymous' } | { __typename: 'BrowserSession', id: string, user: (
-      { __typename?: 'User', hasPassword: boolean, emails: { __typename?: 'UserEmailConnection', totalCount: number } }
-      & { ' $fragmentRefs'?: { 'AddEmailForm_UserFragment': AddEmailForm_UserFragment;'UserEmailList_UserFragment': UserEmailList_UserFragment;'AccountDeleteButton_UserFragment': AccountDeleteButton_UserFragment } }
-    ) } | { __typename: 'Oauth2Session' }, siteConfig: (
-    { __typename?: 'SiteConfig', emailChangeAllowed: boolean, passwordLoginEnabled: boolean, accountDeactivationAllowed: boolean }
-    & { ' $fragmentRefs'?: { 'AddEmailForm_SiteConfigFragment': AddEmailForm_SiteConfigFragment;'UserEmailList_SiteConfigFragment': UserEmailList_SiteConfigFragment;'PasswordChange_SiteConfigFragment': PasswordChange_SiteConfigFragment;'AccountDeleteButton_SiteConfigFragment': AccountDeleteButton_SiteConfigFragment } }
-  ) };
+export type StartRegisterPasskeyMutationVariables = Exact<{ [key: string]: never; }>;
 
-export type PlanManagementTabQueryVariables = Exact<{ [key: string]: never; }>;
 
+export type StartRegisterPasskeyMutation = { __typename?: 'Mutation', startRegisterPasskey: { __typename?: 'StartRegisterPasskeyPayload', id: string, options: string } };
 
-export type PlanManagementTabQuery = { __typename?: 'Query', siteConfig: { __typename?: 'SiteConfig', planManagementIframeUri?: string | null } };
+export type CompleteRegisterPasskeyMutationVariables = Exact<{
+  id: Scalars['ID']['input'];
+  name: Scalars['String']['input'];
+  response: Scalars['String']['input'];
+}>;
 
-export type BrowserSessionListQueryVariables = Exact<{
-  first?: InputMaybe<Scalars['Int']['input']>;
-  after?: InputMaybe<Scalars['String']['input']>;
-  last?: InputMaybe<Scalars['Int']['input']>;
-  before?: InputMaybe<Scalars['String']['input']>;
-  lastActive?: InputMaybe<DateFilter>;
+
+export type CompleteRegisterPasskeyMutation = { __typename?: 'Mutation', completeRegisterPasskey: { __typename?: 'CompleteRegisterPasskeyPayload', status: CompleteRegisterPasskeyStatus } };
+
+export type RemovePasskeyMutationVariables = Exact<{
+  id: Scalars['ID']['input'];
+  password?: InputMaybe<Scalars['String']['input']>;
 }>;
 
 
-export type BrowserSessionListQuery = { __typename?: 'Query', viewerSession: { __typename: 'Anonymous' } | { __typename: 'BrowserSession', id: string, user: { __typename?: 'User', id: string, browserSessions: { __typename?: 'BrowserSessionConnection', totalCount: number, edges: Array<{ __typename?: 'BrowserSessionEdge', cursor: string, node: (
-            { __typename?: 'BrowserSession', id: string }
-            & { ' $fragmentRefs'?: { 'BrowserSession_SessionFragment': BrowserSession_SessionFragment } }
-          ) }>, pageInfo: { __typename?: 'PageInfo', hasNextPage: boolean, hasPreviousPage: boolean, startCursor?: string | null, endCursor?: string | null } } } } | { __typename: 'Oauth2Session' } };
+export type RemovePasskeyMutation = { __typename?: 'Mutation', removePasskey: { __typename?: 'RemovePasskeyPayload', status: RemovePasskeyStatus } };
 
-export type SessionsOverviewQueryVariables = Exact<{ [key: string]: never; }>;
+export type UserPhoneQueryVariables = Exact<{ [key: string]: never; }>;
 
 
-export type SessionsOverviewQuery = { __typename?: 'Query', viewer: { __typename: 'Anonymous' } | (
+export type UserPhoneQuery = { __typename?: 'Query', viewer: { __typename: 'Anonymous' } | { __typename: 'User', id: string, phone?: { __typename?: 'UserPhone', id: string, phoneNumber: string, confirmedAt?: string | null } | null } };
+
+export type SetPhoneNumberMutationVariables = Exact<{
+  phoneNumber: Scalars['String']['input'];
+  password?: InputMaybe<Scalars['String']['input']>;
+  language: Scalars['String']['input'];
+}>;
+
+
+export type SetPhoneNumberMutation = { __typename?: 'Mutation', setPhoneNumber: { __typename?: 'SetPhoneNumberPayload', status: SetPhoneNumberStatus } };
+
+export type ResendPhoneNumberCodeMutationVariables = Exact<{
+  language: Scalars['String']['input'];
+}>;
+
+
+export type ResendPhoneNumberCodeMutation = { __typename?: 'Mutation', resendPhoneNumberCode: { __typename?: 'ResendPhoneNumberCodePayload', status: ResendPhoneNumberCodeStatus } };
+
+export type ConfirmPhoneNumberMutationVariables = Exact<{
+  code: Scalars['String']['input'];
+}>;
+
+
+export type ConfirmPhoneNumberMutation = { __typename?: 'Mutation', confirmPhoneNumber: { __typename?: 'ConfirmPhoneNumberPayload', status: ConfirmPhoneNumberStatus } };
+
+export type RemovePhoneNumberMutationVariables = Exact<{
+  password?: InputMaybe<Scalars['String']['input']>;
+}>;
+
+
+export type RemovePhoneNumberMutation = { __typename?: 'Mutation', removePhoneNumber: { __typename?: 'RemovePhoneNumberPayload', status: RemovePhoneNumberStatus } };
+
+export type UserTotpQueryVariables = Exact<{ [key: string]: never; }>;
+
+
+export type UserTotpQuery = { __typename?: 'Query', viewer: { __typename: 'Anonymous' } | { __typename: 'User', id: string, totp?: { __typename?: 'UserTotp', id: string, createdAt: string, lastUsedAt?: string | null } | null, recoveryCodesLeft: number } };
+
+export type StartTotpEnrollmentMutationVariables = Exact<{ [key: string]: never; }>;
+
+
+export type StartTotpEnrollmentMutation = { __typename?: 'Mutation', startTotpEnrollment: { __typename?: 'StartTotpEnrollmentPayload', status: StartTotpEnrollmentStatus, secret?: string | null, qrCode?: string | null } };
+
+export type ConfirmTotpEnrollmentMutationVariables = Exact<{
+  code: Scalars['String']['input'];
+}>;
+
+
+export type ConfirmTotpEnrollmentMutation = { __typename?: 'Mutation', confirmTotpEnrollment: { __typename?: 'ConfirmTotpEnrollmentPayload', status: ConfirmTotpEnrollmentStatus, recoveryCodes?: Array<string> | null } };
+
+export type RemoveTotpMutationVariables = Exact<{
+  password?: InputMaybe<Scalars['String']['input']>;
+}>;
+
+
+export type RemoveTotpMutation = { __typename?: 'Mutation', removeTotp: { __typename?: 'RemoveTotpPayload', status: RemoveTotpStatus } };
+
+export type RegenerateRecoveryCodesMutationVariables = Exact<{
+  password?: InputMaybe<Scalars['String']['input']>;
+}>;
+
+
+export type RegenerateRecoveryCodesMutation = { __typename?: 'Mutation', regenerateRecoveryCodes: { __typename?: 'RegenerateRecoveryCodesPayload', status: RegenerateRecoveryCodesStatus, recoveryCodes?: Array<string> | null } };
+
+export type UserTrustedDeviceListQueryVariables = Exact<{ [key: string]: never; }>;
+
+
+export type UserTrustedDeviceListQuery = { __typename?: 'Query', viewer: { __typename: 'Anonymous' } | { __typename: 'User', id: string, trustedDevices: Array<{ __typename?: 'UserTrustedDevice', id: string, createdAt: string, expiresAt: string, lastUsedAt?: string | null, userAgent?: { __typename?: 'UserAgent', name?: string | null, os?: string | null } | null }> } };
+
+export type RevokeTrustedDeviceMutationVariables = Exact<{
+  id: Scalars['ID']['input'];
+}>;
+
+
+export type RevokeTrustedDeviceMutation = { __typename?: 'Mutation', revokeTrustedDevice: { __typename?: 'RevokeTrustedDevicePayload', status: RevokeTrustedDeviceStatus } };
+
+export type UserUpstreamLinkListQueryVariables = Exact<{ [key: string]: never; }>;
+
+
+export type UserUpstreamLinkListQuery = { __typename?: 'Query', viewer: { __typename: 'Anonymous' } | { __typename: 'User', id: string, upstreamOauth2Links: { __typename?: 'UpstreamOAuth2LinkConnection', nodes: Array<{ __typename?: 'UpstreamOAuth2Link', id: string, createdAt: string, humanAccountName?: string | null, provider: { __typename?: 'UpstreamOAuth2Provider', id: string, humanName?: string | null } }> } }, upstreamOauth2Providers: { __typename?: 'UpstreamOAuth2ProviderConnection', nodes: Array<{ __typename?: 'UpstreamOAuth2Provider', id: string, humanName?: string | null, linkUrl: string }> } };
+
+export type RemoveUpstreamLinkMutationVariables = Exact<{
+  id: Scalars['ID']['input'];
+  password?: InputMaybe<Scalars['String']['input']>;
+}>;
+
+
+export type RemoveUpstreamLinkMutation = { __typename?: 'Mutation', removeUpstreamLink: { __typename?: 'RemoveUpstreamLinkPayload', status: RemoveUpstreamLinkStatus } };
+
+export type BrowserSessionsOverview_UserFragment = { __typename?: 'User', id: string, browserSessions: { __typename?: 'BrowserSessionConnection', totalCount: number } } & { ' $fragmentName'?: 'BrowserSessionsOverview_UserFragment' };
+
+export type UserProfileQueryVariables = Exact<{ [key: string]: never; }>;
+
+
+export type UserProfileQuery = { __typename?: 'Query', viewerSession: { __typename: 'Anonymous' } | { __typename: 'BrowserSession', id: string, user: (
+      { __typename?: 'User', hasPassword: boolean, emails: { __typename?: 'UserEmailConnection', totalCount: number }, upstreamOauth2Links: { __typename?: 'UpstreamOAuth2LinkConnection', totalCount: number } }
+      & { ' $fragmentRefs'?: { 'AddEmailForm_UserFragment': AddEmailForm_UserFragment;'UserEmailList_UserFragment': UserEmailList_UserFragment;'AccountDeleteButton_UserFragment': AccountDeleteButton_UserFragment } }
+    ) } | { __typename: 'Oauth2Session' }, upstreamOauth2Providers: { __typename?: 'UpstreamOAuth2ProviderConnection', totalCount: number }, siteConfig: (
+    { __typename?: 'SiteConfig', emailChangeAllowed: boolean, passwordLoginEnabled: boolean, passkeysEnabled: boolean, totpPolicy: TotpPolicy, phoneNumberChangeAllowed: boolean, trustedDevicesEnabled: boolean, accountDeactivationAllowed: boolean }
+    & { ' $fragmentRefs'?: { 'AddEmailForm_SiteConfigFragment': AddEmailForm_SiteConfigFragment;'UserEmailList_SiteConfigFragment': UserEmailList_SiteConfigFragment;'PasswordChange_SiteConfigFragment': PasswordChange_SiteConfigFragment;'AccountDeleteButton_SiteConfigFragment': AccountDeleteButton_SiteConfigFragment } }
+  ) };
+
+export type PlanManagementTabQueryVariables = Exact<{ [key: string]: never; }>;
+
+
+export type PlanManagementTabQuery = { __typename?: 'Query', siteConfig: { __typename?: 'SiteConfig', planManagementIframeUri?: string | null } };
+
+export type BrowserSessionListQueryVariables = Exact<{
+  first?: InputMaybe<Scalars['Int']['input']>;
+  after?: InputMaybe<Scalars['String']['input']>;
+  last?: InputMaybe<Scalars['Int']['input']>;
+  before?: InputMaybe<Scalars['String']['input']>;
+  lastActive?: InputMaybe<DateFilter>;
+}>;
+
+
+export type BrowserSessionListQuery = { __typename?: 'Query', viewerSession: { __typename: 'Anonymous' } | { __typename: 'BrowserSession', id: string, user: { __typename?: 'User', id: string, browserSessions: { __typename?: 'BrowserSessionConnection', totalCount: number, edges: Array<{ __typename?: 'BrowserSessionEdge', cursor: string, node: (
+            { __typename?: 'BrowserSession', id: string }
+            & { ' $fragmentRefs'?: { 'BrowserSession_SessionFragment': BrowserSession_SessionFragment } }
+          ) }>, pageInfo: { __typename?: 'PageInfo', hasNextPage: boolean, hasPreviousPage: boolean, startCursor?: string | null, endCursor?: string | null } } } } | { __typename: 'Oauth2Session' } };
+
+export type SessionsOverviewQueryVariables = Exact<{ [key: string]: never; }>;
+
+
+export type SessionsOverviewQuery = { __typename?: 'Query', viewer: { __typename: 'Anonymous' } | (
     { __typename: 'User', id: string }
     & { ' $fragmentRefs'?: { 'BrowserSessionsOverview_UserFragment': BrowserSessionsOverview_UserFragment } }
   ) };
@@ -2067,6 +3133,7 @@ export const AccountDeleteButton_UserFragmentDoc = new TypedDocumentString(`
 export const AccountDeleteButton_SiteConfigFragmentDoc = new TypedDocumentString(`
     fragment AccountDeleteButton_siteConfig on SiteConfig {
   passwordLoginEnabled
+  accountDeletionGracePeriodDays
 }
     `, {"fragmentName":"AccountDeleteButton_siteConfig"}) as unknown as TypedDocumentString<AccountDeleteButton_SiteConfigFragment, unknown>;
 export const PasswordChange_SiteConfigFragmentDoc = new TypedDocumentString(`
@@ -2273,6 +3340,7 @@ export const CompatSession_DetailFragmentDoc = new TypedDocumentString(`
   lastActiveIp
   lastActiveAt
   humanName
+  impersonated
   ...EndCompatSessionButton_session
   userAgent {
     name
@@ -2306,6 +3374,7 @@ export const OAuth2Session_DetailFragmentDoc = new TypedDocumentString(`
   lastActiveIp
   lastActiveAt
   humanName
+  offlineAccessGrantedAt
   ...EndOAuth2SessionButton_session
   userAgent {
     name
@@ -2347,12 +3416,14 @@ export const UserGreeting_UserFragmentDoc = new TypedDocumentString(`
   matrix {
     mxid
     displayName
+    avatarUrl
   }
 }
     `, {"fragmentName":"UserGreeting_user"}) as unknown as TypedDocumentString<UserGreeting_UserFragment, unknown>;
 export const UserGreeting_SiteConfigFragmentDoc = new TypedDocumentString(`
     fragment UserGreeting_siteConfig on SiteConfig {
   displayNameChangeAllowed
+  avatarChangeAllowed
 }
     `, {"fragmentName":"UserGreeting_siteConfig"}) as unknown as TypedDocumentString<UserGreeting_SiteConfigFragment, unknown>;
 export const AddEmailForm_UserFragmentDoc = new TypedDocumentString(`
@@ -2482,13 +3553,31 @@ export const RemoveEmailDocument = new TypedDocumentString(`
   }
 }
     `) as unknown as TypedDocumentString<RemoveEmailMutation, RemoveEmailMutationVariables>;
+export const SetPrimaryEmailDocument = new TypedDocumentString(`
+    mutation SetPrimaryEmail($id: ID!) {
+  setPrimaryEmail(input: {userEmailId: $id}) {
+    status
+    user {
+      id
+    }
+  }
+}
+    `) as unknown as TypedDocumentString<SetPrimaryEmailMutation, SetPrimaryEmailMutationVariables>;
 export const SetDisplayNameDocument = new TypedDocumentString(`
     mutation SetDisplayName($userId: ID!, $displayName: String) {
   setDisplayName(input: {userId: $userId, displayName: $displayName}) {
     status
+    violations
   }
 }
     `) as unknown as TypedDocumentString<SetDisplayNameMutation, SetDisplayNameMutationVariables>;
+export const SetAvatarDocument = new TypedDocumentString(`
+    mutation SetAvatar($userId: ID!, $avatar: Upload) {
+  setAvatar(input: {userId: $userId, avatar: $avatar}) {
+    status
+  }
+}
+    `) as unknown as TypedDocumentString<SetAvatarMutation, SetAvatarMutationVariables>;
 export const AddEmailDocument = new TypedDocumentString(`
     mutation AddEmail($email: String!, $password: String, $language: String!) {
   startEmailAuthentication(
@@ -2522,6 +3611,9 @@ export const UserEmailListDocument = new TypedDocumentString(`
           endCursor
         }
       }
+      primaryEmail {
+        id
+      }
     }
   }
 }
@@ -2529,6 +3621,199 @@ export const UserEmailListDocument = new TypedDocumentString(`
   id
   email
 }`) as unknown as TypedDocumentString<UserEmailListQuery, UserEmailListQueryVariables>;
+export const UserPasskeyListDocument = new TypedDocumentString(`
+    query UserPasskeyList {
+  viewer {
+    __typename
+    ... on User {
+      id
+      passkeys {
+        id
+        name
+        createdAt
+        lastUsedAt
+      }
+    }
+  }
+}
+    `) as unknown as TypedDocumentString<UserPasskeyListQuery, UserPasskeyListQueryVariables>;
+export const StartRegisterPasskeyDocument = new TypedDocumentString(`
+    mutation StartRegisterPasskey {
+  startRegisterPasskey {
+    id
+    options
+  }
+}
+    `) as unknown as TypedDocumentString<StartRegisterPasskeyMutation, StartRegisterPasskeyMutationVariables>;
+export const CompleteRegisterPasskeyDocument = new TypedDocumentString(`
+    mutation CompleteRegisterPasskey($id: ID!, $name: String!, $response: String!) {
+  completeRegisterPasskey(input: {id: $id, name: $name, response: $response}) {
+    status
+  }
+}
+    `) as unknown as TypedDocumentString<CompleteRegisterPasskeyMutation, CompleteRegisterPasskeyMutationVariables>;
+export const RemovePasskeyDocument = new TypedDocumentString(`
+    mutation RemovePasskey($id: ID!, $password: String) {
+  removePasskey(input: {userPasskeyId: $id, password: $password}) {
+    status
+  }
+}
+    `) as unknown as TypedDocumentString<RemovePasskeyMutation, RemovePasskeyMutationVariables>;
+export const UserPhoneDocument = new TypedDocumentString(`
+    query UserPhone {
+  viewer {
+    __typename
+    ... on User {
+      id
+      phone {
+        id
+        phoneNumber
+        confirmedAt
+      }
+    }
+  }
+}
+    `) as unknown as TypedDocumentString<UserPhoneQuery, UserPhoneQueryVariables>;
+export const SetPhoneNumberDocument = new TypedDocumentString(`
+    mutation SetPhoneNumber($phoneNumber: String!, $password: String, $language: String!) {
+  setPhoneNumber(
+    input: {phoneNumber: $phoneNumber, password: $password, language: $language}
+  ) {
+    status
+  }
+}
+    `) as unknown as TypedDocumentString<SetPhoneNumberMutation, SetPhoneNumberMutationVariables>;
+export const ResendPhoneNumberCodeDocument = new TypedDocumentString(`
+    mutation ResendPhoneNumberCode($language: String!) {
+  resendPhoneNumberCode(input: {language: $language}) {
+    status
+  }
+}
+    `) as unknown as TypedDocumentString<ResendPhoneNumberCodeMutation, ResendPhoneNumberCodeMutationVariables>;
+export const ConfirmPhoneNumberDocument = new TypedDocumentString(`
+    mutation ConfirmPhoneNumber($code: String!) {
+  confirmPhoneNumber(input: {code: $code}) {
+    status
+  }
+}
+    `) as unknown as TypedDocumentString<ConfirmPhoneNumberMutation, ConfirmPhoneNumberMutationVariables>;
+export const RemovePhoneNumberDocument = new TypedDocumentString(`
+    mutation RemovePhoneNumber($password: String) {
+  removePhoneNumber(input: {password: $password}) {
+    status
+  }
+}
+    `) as unknown as TypedDocumentString<RemovePhoneNumberMutation, RemovePhoneNumberMutationVariables>;
+export const UserTotpDocument = new TypedDocumentString(`
+    query UserTotp {
+  viewer {
+    __typename
+    ... on User {
+      id
+      totp {
+        id
+        createdAt
+        lastUsedAt
+      }
+      recoveryCodesLeft
+    }
+  }
+}
+    `) as unknown as TypedDocumentString<UserTotpQuery, UserTotpQueryVariables>;
+export const StartTotpEnrollmentDocument = new TypedDocumentString(`
+    mutation StartTotpEnrollment {
+  startTotpEnrollment {
+    status
+    secret
+    qrCode
+  }
+}
+    `) as unknown as TypedDocumentString<StartTotpEnrollmentMutation, StartTotpEnrollmentMutationVariables>;
+export const ConfirmTotpEnrollmentDocument = new TypedDocumentString(`
+    mutation ConfirmTotpEnrollment($code: String!) {
+  confirmTotpEnrollment(input: {code: $code}) {
+    status
+    recoveryCodes
+  }
+}
+    `) as unknown as TypedDocumentString<ConfirmTotpEnrollmentMutation, ConfirmTotpEnrollmentMutationVariables>;
+export const RemoveTotpDocument = new TypedDocumentString(`
+    mutation RemoveTotp($password: String) {
+  removeTotp(input: {password: $password}) {
+    status
+  }
+}
+    `) as unknown as TypedDocumentString<RemoveTotpMutation, RemoveTotpMutationVariables>;
+export const RegenerateRecoveryCodesDocument = new TypedDocumentString(`
+    mutation RegenerateRecoveryCodes($password: String) {
+  regenerateRecoveryCodes(input: {password: $password}) {
+    status
+    recoveryCodes
+  }
+}
+    `) as unknown as TypedDocumentString<RegenerateRecoveryCodesMutation, RegenerateRecoveryCodesMutationVariables>;
+export const UserTrustedDeviceListDocument = new TypedDocumentString(`
+    query UserTrustedDeviceList {
+  viewer {
+    __typename
+    ... on User {
+      id
+      trustedDevices {
+        id
+        createdAt
+        expiresAt
+        lastUsedAt
+        userAgent {
+          name
+          os
+        }
+      }
+    }
+  }
+}
+    `) as unknown as TypedDocumentString<UserTrustedDeviceListQuery, UserTrustedDeviceListQueryVariables>;
+export const RevokeTrustedDeviceDocument = new TypedDocumentString(`
+    mutation RevokeTrustedDevice($id: ID!) {
+  revokeTrustedDevice(input: {userTrustedDeviceId: $id}) {
+    status
+  }
+}
+    `) as unknown as TypedDocumentString<RevokeTrustedDeviceMutation, RevokeTrustedDeviceMutationVariables>;
+export const UserUpstreamLinkListDocument = new TypedDocumentString(`
+    query UserUpstreamLinkList {
+  viewer {
+    __typename
+    ... on User {
+      id
+      upstreamOauth2Links(first: 100) {
+        nodes {
+          id
+          createdAt
+          humanAccountName
+          provider {
+            id
+            humanName
+          }
+        }
+      }
+    }
+  }
+  upstreamOauth2Providers(first: 100) {
+    nodes {
+      id
+      humanName
+      linkUrl
+    }
+  }
+}
+    `) as unknown as TypedDocumentString<UserUpstreamLinkListQuery, UserUpstreamLinkListQueryVariables>;
+export const RemoveUpstreamLinkDocument = new TypedDocumentString(`
+    mutation RemoveUpstreamLink($id: ID!, $password: String) {
+  removeUpstreamLink(input: {upstreamLinkId: $id, password: $password}) {
+    status
+  }
+}
+    `) as unknown as TypedDocumentString<RemoveUpstreamLinkMutation, RemoveUpstreamLinkMutationVariables>;
 export const UserProfileDocument = new TypedDocumentString(`
     query UserProfile {
   viewerSession {
@@ -2543,12 +3828,22 @@ export const UserProfileDocument = new TypedDocumentString(`
         emails(first: 0) {
           totalCount
         }
+        upstreamOauth2Links(first: 0) {
+          totalCount
+        }
       }
     }
   }
+  upstreamOauth2Providers(first: 0) {
+    totalCount
+  }
   siteConfig {
     emailChangeAllowed
     passwordLoginEnabled
+    passkeysEnabled
+    totpPolicy
+    phoneNumberChangeAllowed
+    trustedDevicesEnabled
     accountDeactivationAllowed
     ...AddEmailForm_siteConfig
     ...UserEmailList_siteConfig
@@ -2566,6 +3861,7 @@ export const UserProfileDocument = new TypedDocumentString(`
 }
 fragment AccountDeleteButton_siteConfig on SiteConfig {
   passwordLoginEnabled
+  accountDeletionGracePeriodDays
 }
 fragment PasswordChange_siteConfig on SiteConfig {
   passwordChangeAllowed
@@ -2785,10 +4081,12 @@ export const CurrentUserGreetingDocument = new TypedDocumentString(`
   matrix {
     mxid
     displayName
+    avatarUrl
   }
 }
 fragment UserGreeting_siteConfig on SiteConfig {
   displayNameChangeAllowed
+  avatarChangeAllowed
 }`) as unknown as TypedDocumentString<CurrentUserGreetingQuery, CurrentUserGreetingQueryVariables>;
 export const OAuth2ClientDocument = new TypedDocumentString(`
     query OAuth2Client($id: ID!) {
@@ -3000,6 +4298,7 @@ fragment CompatSession_detail on CompatSession {
   lastActiveIp
   lastActiveAt
   humanName
+  impersonated
   ...EndCompatSessionButton_session
   userAgent {
     name
@@ -3019,6 +4318,7 @@ fragment OAuth2Session_detail on Oauth2Session {
   lastActiveIp
   lastActiveAt
   humanName
+  offlineAccessGrantedAt
   ...EndOAuth2SessionButton_session
   userAgent {
     name
@@ -3209,6 +4509,28 @@ export const mockRemoveEmailMutation = (resolver: GraphQLResponseResolver<Remove
     options
   )
 
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockSetPrimaryEmailMutation(
+ *   ({ query, variables }) => {
+ *     const { id } = variables;
+ *     return HttpResponse.json({
+ *       data: { setPrimaryEmail }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockSetPrimaryEmailMutation = (resolver: GraphQLResponseResolver<SetPrimaryEmailMutation, SetPrimaryEmailMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<SetPrimaryEmailMutation, SetPrimaryEmailMutationVariables>(
+    'SetPrimaryEmail',
+    resolver,
+    options
+  )
+
 /**
  * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
  * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
@@ -3231,6 +4553,28 @@ export const mockSetDisplayNameMutation = (resolver: GraphQLResponseResolver<Set
     options
   )
 
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockSetAvatarMutation(
+ *   ({ query, variables }) => {
+ *     const { userId, avatar } = variables;
+ *     return HttpResponse.json({
+ *       data: { setAvatar }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockSetAvatarMutation = (resolver: GraphQLResponseResolver<SetAvatarMutation, SetAvatarMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<SetAvatarMutation, SetAvatarMutationVariables>(
+    'SetAvatar',
+    resolver,
+    options
+  )
+
 /**
  * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
  * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
@@ -3275,6 +4619,395 @@ export const mockUserEmailListQuery = (resolver: GraphQLResponseResolver<UserEma
     options
   )
 
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockUserPasskeyListQuery(
+ *   ({ query, variables }) => {
+ *     return HttpResponse.json({
+ *       data: { viewer }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockUserPasskeyListQuery = (resolver: GraphQLResponseResolver<UserPasskeyListQuery, UserPasskeyListQueryVariables>, options?: RequestHandlerOptions) =>
+  graphql.query<UserPasskeyListQuery, UserPasskeyListQueryVariables>(
+    'UserPasskeyList',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockStartRegisterPasskeyMutation(
+ *   ({ query, variables }) => {
+ *     return HttpResponse.json({
+ *       data: { startRegisterPasskey }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockStartRegisterPasskeyMutation = (resolver: GraphQLResponseResolver<StartRegisterPasskeyMutation, StartRegisterPasskeyMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<StartRegisterPasskeyMutation, StartRegisterPasskeyMutationVariables>(
+    'StartRegisterPasskey',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockCompleteRegisterPasskeyMutation(
+ *   ({ query, variables }) => {
+ *     const { id, name, response } = variables;
+ *     return HttpResponse.json({
+ *       data: { completeRegisterPasskey }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockCompleteRegisterPasskeyMutation = (resolver: GraphQLResponseResolver<CompleteRegisterPasskeyMutation, CompleteRegisterPasskeyMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<CompleteRegisterPasskeyMutation, CompleteRegisterPasskeyMutationVariables>(
+    'CompleteRegisterPasskey',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockRemovePasskeyMutation(
+ *   ({ query, variables }) => {
+ *     const { id, password } = variables;
+ *     return HttpResponse.json({
+ *       data: { removePasskey }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockRemovePasskeyMutation = (resolver: GraphQLResponseResolver<RemovePasskeyMutation, RemovePasskeyMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<RemovePasskeyMutation, RemovePasskeyMutationVariables>(
+    'RemovePasskey',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockUserPhoneQuery(
+ *   ({ query, variables }) => {
+ *     return HttpResponse.json({
+ *       data: { viewer }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockUserPhoneQuery = (resolver: GraphQLResponseResolver<UserPhoneQuery, UserPhoneQueryVariables>, options?: RequestHandlerOptions) =>
+  graphql.query<UserPhoneQuery, UserPhoneQueryVariables>(
+    'UserPhone',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockSetPhoneNumberMutation(
+ *   ({ query, variables }) => {
+ *     const { phoneNumber, password, language } = variables;
+ *     return HttpResponse.json({
+ *       data: { setPhoneNumber }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockSetPhoneNumberMutation = (resolver: GraphQLResponseResolver<SetPhoneNumberMutation, SetPhoneNumberMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<SetPhoneNumberMutation, SetPhoneNumberMutationVariables>(
+    'SetPhoneNumber',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockResendPhoneNumberCodeMutation(
+ *   ({ query, variables }) => {
+ *     const { language } = variables;
+ *     return HttpResponse.json({
+ *       data: { resendPhoneNumberCode }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockResendPhoneNumberCodeMutation = (resolver: GraphQLResponseResolver<ResendPhoneNumberCodeMutation, ResendPhoneNumberCodeMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<ResendPhoneNumberCodeMutation, ResendPhoneNumberCodeMutationVariables>(
+    'ResendPhoneNumberCode',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockConfirmPhoneNumberMutation(
+ *   ({ query, variables }) => {
+ *     const { code } = variables;
+ *     return HttpResponse.json({
+ *       data: { confirmPhoneNumber }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockConfirmPhoneNumberMutation = (resolver: GraphQLResponseResolver<ConfirmPhoneNumberMutation, ConfirmPhoneNumberMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<ConfirmPhoneNumberMutation, ConfirmPhoneNumberMutationVariables>(
+    'ConfirmPhoneNumber',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockRemovePhoneNumberMutation(
+ *   ({ query, variables }) => {
+ *     const { password } = variables;
+ *     return HttpResponse.json({
+ *       data: { removePhoneNumber }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockRemovePhoneNumberMutation = (resolver: GraphQLResponseResolver<RemovePhoneNumberMutation, RemovePhoneNumberMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<RemovePhoneNumberMutation, RemovePhoneNumberMutationVariables>(
+    'RemovePhoneNumber',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockUserTotpQuery(
+ *   ({ query, variables }) => {
+ *     return HttpResponse.json({
+ *       data: { viewer }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockUserTotpQuery = (resolver: GraphQLResponseResolver<UserTotpQuery, UserTotpQueryVariables>, options?: RequestHandlerOptions) =>
+  graphql.query<UserTotpQuery, UserTotpQueryVariables>(
+    'UserTotp',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockStartTotpEnrollmentMutation(
+ *   ({ query, variables }) => {
+ *     return HttpResponse.json({
+ *       data: { startTotpEnrollment }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockStartTotpEnrollmentMutation = (resolver: GraphQLResponseResolver<StartTotpEnrollmentMutation, StartTotpEnrollmentMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<StartTotpEnrollmentMutation, StartTotpEnrollmentMutationVariables>(
+    'StartTotpEnrollment',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockConfirmTotpEnrollmentMutation(
+ *   ({ query, variables }) => {
+ *     const { code } = variables;
+ *     return HttpResponse.json({
+ *       data: { confirmTotpEnrollment }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockConfirmTotpEnrollmentMutation = (resolver: GraphQLResponseResolver<ConfirmTotpEnrollmentMutation, ConfirmTotpEnrollmentMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<ConfirmTotpEnrollmentMutation, ConfirmTotpEnrollmentMutationVariables>(
+    'ConfirmTotpEnrollment',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockRemoveTotpMutation(
+ *   ({ query, variables }) => {
+ *     const { password } = variables;
+ *     return HttpResponse.json({
+ *       data: { removeTotp }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockRemoveTotpMutation = (resolver: GraphQLResponseResolver<RemoveTotpMutation, RemoveTotpMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<RemoveTotpMutation, RemoveTotpMutationVariables>(
+    'RemoveTotp',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockRegenerateRecoveryCodesMutation(
+ *   ({ query, variables }) => {
+ *     const { password } = variables;
+ *     return HttpResponse.json({
+ *       data: { regenerateRecoveryCodes }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockRegenerateRecoveryCodesMutation = (resolver: GraphQLResponseResolver<RegenerateRecoveryCodesMutation, RegenerateRecoveryCodesMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<RegenerateRecoveryCodesMutation, RegenerateRecoveryCodesMutationVariables>(
+    'RegenerateRecoveryCodes',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockUserTrustedDeviceListQuery(
+ *   ({ query, variables }) => {
+ *     return HttpResponse.json({
+ *       data: { viewer }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockUserTrustedDeviceListQuery = (resolver: GraphQLResponseResolver<UserTrustedDeviceListQuery, UserTrustedDeviceListQueryVariables>, options?: RequestHandlerOptions) =>
+  graphql.query<UserTrustedDeviceListQuery, UserTrustedDeviceListQueryVariables>(
+    'UserTrustedDeviceList',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockRevokeTrustedDeviceMutation(
+ *   ({ query, variables }) => {
+ *     const { id } = variables;
+ *     return HttpResponse.json({
+ *       data: { revokeTrustedDevice }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockRevokeTrustedDeviceMutation = (resolver: GraphQLResponseResolver<RevokeTrustedDeviceMutation, RevokeTrustedDeviceMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<RevokeTrustedDeviceMutation, RevokeTrustedDeviceMutationVariables>(
+    'RevokeTrustedDevice',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockUserUpstreamLinkListQuery(
+ *   ({ query, variables }) => {
+ *     return HttpResponse.json({
+ *       data: { viewer, upstreamOauth2Providers }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockUserUpstreamLinkListQuery = (resolver: GraphQLResponseResolver<UserUpstreamLinkListQuery, UserUpstreamLinkListQueryVariables>, options?: RequestHandlerOptions) =>
+  graphql.query<UserUpstreamLinkListQuery, UserUpstreamLinkListQueryVariables>(
+    'UserUpstreamLinkList',
+    resolver,
+    options
+  )
+
+/**
+ * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
+ * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
+ * @see https://mswjs.io/docs/basics/response-resolver
+ * @example
+ * mockRemoveUpstreamLinkMutation(
+ *   ({ query, variables }) => {
+ *     const { id, password } = variables;
+ *     return HttpResponse.json({
+ *       data: { removeUpstreamLink }
+ *     })
+ *   },
+ *   requestOptions
+ * )
+ */
+export const mockRemoveUpstreamLinkMutation = (resolver: GraphQLResponseResolver<RemoveUpstreamLinkMutation, RemoveUpstreamLinkMutationVariables>, options?: RequestHandlerOptions) =>
+  graphql.mutation<RemoveUpstreamLinkMutation, RemoveUpstreamLinkMutationVariables>(
+    'RemoveUpstreamLink',
+    resolver,
+    options
+  )
+
 /**
  * @param resolver A function that accepts [resolver arguments](https://mswjs.io/docs/api/graphql#resolver-argument) and must always return the instruction on what to do with the intercepted request. ([see more](https://mswjs.io/docs/concepts/response-resolver#resolver-instructions))
  * @param options Options object to customize the behavior of the mock. ([see more](https://mswjs.io/docs/api/graphql#handler-options))
@@ -3283,7 +5016,7 @@ export const mockUserEmailListQuery = (resolver: GraphQLResponseResolver<UserEma
  * mockUserProfileQuery(
  *   ({ query, variables }) => {
  *     return HttpResponse.json({
- *       data: { viewerSession, siteConfig }
+ *       data: { viewerSession, upstreamOauth2Providers, siteConfig }
  *     })
  *   },
  *   requestOptions
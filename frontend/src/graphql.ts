@@ -30,6 +30,54 @@ type RequestOptions<TData, TVariables> = {
   ? { variables?: TVariables }
   : { variables: TVariables });
 
+/**
+ * Build the body of a request, following the GraphQL multipart request spec
+ * if some top-level variables are files
+ *
+ * See https://github.com/jaydenseric/graphql-multipart-request-spec
+ */
+const requestInit = (
+  query: unknown,
+  variables: unknown,
+): Pick<RequestInit, "headers" | "body"> => {
+  const files = Object.entries(
+    (variables ?? {}) as Record<string, unknown>,
+  ).filter(([, value]) => value instanceof Blob) as [string, Blob][];
+
+  if (files.length === 0) {
+    return {
+      headers: {
+        "Content-Type": "application/json",
+      },
+      body: JSON.stringify({
+        query,
+        variables,
+      }),
+    };
+  }
+
+  // Files are replaced by null in the operation, and sent as separate parts
+  const operationVariables = { ...(variables as Record<string, unknown>) };
+  const map: Record<string, string[]> = {};
+  for (const [index, [name]] of files.entries()) {
+    operationVariables[name] = null;
+    map[index] = [`variables.${name}`];
+  }
+
+  const body = new FormData();
+  body.append(
+    "operations",
+    JSON.stringify({ query, variables: operationVariables }),
+  );
+  body.append("map", JSON.stringify(map));
+  for (const [index, [, file]] of files.entries()) {
+    body.append(String(index), file);
+  }
+
+  // The browser sets the Content-Type with the right boundary
+  return { body };
+};
+
 export const graphqlRequest = async <TData, TVariables>({
   query,
   variables,
@@ -39,13 +87,7 @@ export const graphqlRequest = async <TData, TVariables>({
   try {
     response = await fetch(graphqlEndpoint, {
       method: "POST",
-      headers: {
-        "Content-Type": "application/json",
-      },
-      body: JSON.stringify({
-        query,
-        variables,
-      }),
+      ...requestInit(query, variables),
       signal,
     });
   } catch (cause) {
@@ -33,6 +33,8 @@ export function translateSetPasswordError(
       return t(
         "frontend.password_change.failure.description.password_changes_disabled",
       );
+    case "PASSWORD_REUSED":
+      return t("frontend.password_change.failure.description.password_reused");
     case "ACCOUNT_LOCKED":
       return t("frontend.password_change.failure.description.account_locked");
     case "EXPIRED_RECOVERY_TICKET":
@@ -22,6 +22,21 @@ import AddEmailForm from "../components/UserProfile/AddEmailForm";
 import UserEmailList, {
   query as userEmailListQuery,
 } from "../components/UserProfile/UserEmailList";
+import UserPasskeyList, {
+  query as userPasskeyListQuery,
+} from "../components/UserProfile/UserPasskeyList";
+import UserPhone, {
+  query as userPhoneQuery,
+} from "../components/UserProfile/UserPhone";
+import UserTotp, {
+  query as userTotpQuery,
+} from "../components/UserProfile/UserTotp";
+import UserTrustedDeviceList, {
+  query as userTrustedDeviceListQuery,
+} from "../components/UserProfile/UserTrustedDeviceList";
+import UserUpstreamLinkList, {
+  query as userUpstreamLinkListQuery,
+} from "../components/UserProfile/UserUpstreamLinkList";
 import { graphql } from "../gql";
 import { graphqlRequest } from "../graphql";
 
@@ -39,13 +54,24 @@ const QUERY = graphql(/* GraphQL */ `
           emails(first: 0) {
             totalCount
           }
+          upstreamOauth2Links(first: 0) {
+            totalCount
+          }
         }
       }
     }
 
+    upstreamOauth2Providers(first: 0) {
+      totalCount
+    }
+
     siteConfig {
       emailChangeAllowed
       passwordLoginEnabled
+      passkeysEnabled
+      totpPolicy
+      phoneNumberChangeAllowed
+      trustedDevicesEnabled
       accountDeactivationAllowed
       ...AddEmailForm_siteConfig
       ...UserEmailList_siteConfig
@@ -132,11 +158,27 @@ export const Route = createFileRoute({
     }
   },
 
-  loader: ({ context }) =>
-    Promise.all([
+  loader: async ({ context }) => {
+    const data = await context.queryClient.ensureQueryData(query);
+    const user =
+      data.viewerSession.__typename === "BrowserSession"
+        ? data.viewerSession.user
+        : null;
+    await Promise.all([
       context.queryClient.ensureQueryData(userEmailListQuery()),
-      context.queryClient.ensureQueryData(query),
-    ]),
+      data.siteConfig.passkeysEnabled &&
+        context.queryClient.ensureQueryData(userPasskeyListQuery),
+      data.siteConfig.totpPolicy !== "DISABLED" &&
+        context.queryClient.ensureQueryData(userTotpQuery),
+      data.siteConfig.phoneNumberChangeAllowed &&
+        context.queryClient.ensureQueryData(userPhoneQuery),
+      data.siteConfig.trustedDevicesEnabled &&
+        context.queryClient.ensureQueryData(userTrustedDeviceListQuery),
+      (data.upstreamOauth2Providers.totalCount > 0 ||
+        (user?.upstreamOauth2Links.totalCount ?? 0) > 0) &&
+        context.queryClient.ensureQueryData(userUpstreamLinkListQuery),
+    ]);
+  },
 
   component: Index,
 });
@@ -178,7 +220,7 @@ function Index(): React.ReactElement {
   const navigate = useNavigate();
   const { t } = useTranslation();
   const {
-    data: { viewerSession, siteConfig },
+    data: { viewerSession, siteConfig, upstreamOauth2Providers },
   } = useSuspenseQuery(query);
   if (viewerSession?.__typename !== "BrowserSession") throw notFound();
 
@@ -230,6 +272,92 @@ function Index(): React.ReactElement {
           </>
         )}
 
+        {siteConfig.passkeysEnabled && (
+          <>
+            <Collapsible.Section
+              defaultOpen
+              title={t("frontend.account.passkeys")}
+            >
+              <UserPasskeyList
+                shouldPromptPassword={
+                  siteConfig.passwordLoginEnabled &&
+                  viewerSession.user.hasPassword
+                }
+              />
+            </Collapsible.Section>
+
+            <Separator kind="section" />
+          </>
+        )}
+
+        {(upstreamOauth2Providers.totalCount > 0 ||
+          viewerSession.user.upstreamOauth2Links.totalCount > 0) && (
+          <>
+            <Collapsible.Section
+              defaultOpen
+              title={t("frontend.account.linked_accounts")}
+            >
+              <UserUpstreamLinkList
+                shouldPromptPassword={
+                  siteConfig.passwordLoginEnabled &&
+                  viewerSession.user.hasPassword
+                }
+              />
+            </Collapsible.Section>
+
+            <Separator kind="section" />
+          </>
+        )}
+
+        {siteConfig.phoneNumberChangeAllowed && (
+          <>
+            <Collapsible.Section
+              defaultOpen
+              title={t("frontend.account.phone_number")}
+            >
+              <UserPhone
+                shouldPromptPassword={
+                  siteConfig.passwordLoginEnabled &&
+                  viewerSession.user.hasPassword
+                }
+              />
+            </Collapsible.Section>
+
+            <Separator kind="section" />
+          </>
+        )}
+
+        {/* Second factors are only asked for password logins */}
+        {siteConfig.totpPolicy !== "DISABLED" &&
+          siteConfig.passwordLoginEnabled &&
+          viewerSession.user.hasPassword && (
+            <>
+              <Collapsible.Section
+                defaultOpen
+                title={t("frontend.account.two_factor_authentication")}
+              >
+                <UserTotp shouldPromptPassword />
+              </Collapsible.Section>
+
+              <Separator kind="section" />
+            </>
+          )}
+
+        {siteConfig.trustedDevicesEnabled &&
+          siteConfig.passwordLoginEnabled &&
+          viewerSession.user.hasPassword && (
+            <>
+              <Collapsible.Section
+                defaultOpen
+                title={t("frontend.account.trusted_devices")}
+              >
+                <UserTrustedDeviceList />
+              </Collapsible.Section>
+
+              <Separator kind="section" />
+            </>
+          )}
+
         <Collapsible.Section title={t("common.e2ee")}>
           <Text className="text-secondary" size="md">
             {t("frontend.reset_cross_signing.description")}
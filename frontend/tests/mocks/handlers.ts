@@ -76,6 +76,7 @@ export const handlers = [
               matrix: {
                 mxid: "@alice:example.com",
                 displayName: "Alice",
+                avatarUrl: null,
               },
             },
             USER_GREETING_FRAGMENT,
@@ -85,6 +86,7 @@ export const handlers = [
         siteConfig: makeFragmentData(
           {
             displayNameChangeAllowed: true,
+            avatarChangeAllowed: true,
           },
           USER_GREETING_CONFIG_FRAGMENT,
         ),
@@ -104,6 +106,9 @@ export const handlers = [
               emails: {
                 totalCount: 1,
               },
+              upstreamOauth2Links: {
+                totalCount: 0,
+              },
             },
             makeFragmentData(
               {
@@ -131,10 +136,18 @@ export const handlers = [
           ),
         },
 
+        upstreamOauth2Providers: {
+          totalCount: 0,
+        },
+
         siteConfig: Object.assign(
           {
             emailChangeAllowed: true,
             passwordLoginEnabled: true,
+            passkeysEnabled: false,
+            totpPolicy: "DISABLED",
+            phoneNumberChangeAllowed: false,
+            trustedDevicesEnabled: false,
             accountDeactivationAllowed: true,
           },
           makeFragmentData(
@@ -160,6 +173,7 @@ export const handlers = [
           makeFragmentData(
             {
               passwordLoginEnabled: true,
+              accountDeletionGracePeriodDays: null,
             },
             ACCOUNT_DELETE_BUTTON_CONFIG_FRAGMENT,
           ),
@@ -196,6 +210,9 @@ export const handlers = [
               endCursor: null,
             },
           },
+          primaryEmail: {
+            id: "primary-email-id",
+          },
         },
       },
     }),
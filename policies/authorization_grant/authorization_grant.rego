@@ -30,10 +30,27 @@ can_request_admin(user) if {
 	user.can_request_admin
 }
 
+# 3. They have a verified email address in one of the admin_email_domains
+can_request_admin(_) if {
+	some email in input.emails
+	[_, domain] := split(email, "@")
+	some admin_domain in data.admin_email_domains
+	glob.match(admin_domain, ["."], domain)
+}
+
 interactive_grant_type("authorization_code") := true
 
 interactive_grant_type("urn:ietf:params:oauth:grant-type:device_code") := true
 
+interactive_grant_type("urn:openid:params:grant-type:ciba") := true
+
+# Grant types where a user is present on the resulting session
+user_grant_type(grant_type) if {
+	interactive_grant_type(grant_type)
+}
+
+user_grant_type("password") := true
+
 # Special case to make empty scope work
 allowed_scope("") := true
 
@@ -68,13 +85,20 @@ allowed_scope("urn:mas:admin") if {
 
 allowed_scope(scope) if {
 	# Grant access to the C-S API only if there is a user
-	interactive_grant_type(input.grant_type)
+	user_grant_type(input.grant_type)
 	regex.match(`^urn:matrix:org.matrix.msc2967.client:device:[A-Za-z0-9._~!$&'()*+,;=:@/-]{10,}$`, scope)
 }
 
 allowed_scope("urn:matrix:org.matrix.msc2967.client:api:*") if {
 	# Grant access to the C-S API only if there is a user
+	user_grant_type(input.grant_type)
+}
+
+# This grants access to the tokens issued to the user by an upstream provider,
+# which the user has to consent to
+allowed_scope(scope) if {
 	interactive_grant_type(input.grant_type)
+	regex.match(`^urn:mas:upstream-tokens:[0-9A-HJKMNP-TV-Z]{26}$`, scope)
 }
 
 # METADATA
@@ -85,6 +109,46 @@ violation contains {"msg": msg} if {
 	msg := sprintf("scope '%s' not allowed", [scope])
 }
 
+# Clients can be restricted to a list of scopes with their scope policy, where
+# a `*` at the end of a scope matches any scope starting with the rest of it.
+# Clients set up to filter the other scopes drop them from their requests
+# before they get here, so this only rejects the requests of the other ones.
+violation contains {"msg": msg} if {
+	some scope in split(input.scope, " ")
+	scope != ""
+	not client_allows_scope(input.client, scope)
+	msg := sprintf("scope '%s' not allowed for this client", [scope])
+}
+
+client_allows_scope(client, _) if {
+	object.get(client, "scope_policy", null) == null
+}
+
+client_allows_scope(client, scope) if {
+	some allowed in client.scope_policy.allowed_scopes
+	scope_matches(allowed, scope)
+}
+
+scope_matches(pattern, scope) if {
+	pattern == scope
+}
+
+scope_matches(pattern, scope) if {
+	endswith(pattern, "*")
+	startswith(scope, trim_suffix(pattern, "*"))
+}
+
+# The password grant is only available to allow-listed first-party clients
+violation contains {"msg": "client is not allowed to use the password grant"} if {
+	input.grant_type == "password"
+	not direct_login_client(input.client)
+}
+
+direct_login_client(client) if {
+	some allowed_client in data.direct_login_clients
+	client.id == allowed_client
+}
+
 violation contains {"msg": "only one device scope is allowed at a time"} if {
 	scope_list := split(input.scope, " ")
 	count({scope | some scope in scope_list; startswith(scope, "urn:matrix:org.matrix.msc2967.client:device:")}) > 1
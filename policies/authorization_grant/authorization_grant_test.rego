@@ -51,6 +51,11 @@ test_matrix_scopes if {
 		with input.grant_type as "urn:ietf:params:oauth:grant-type:device_code"
 		with input.scope as "urn:matrix:org.matrix.msc2967.client:api:*"
 
+	authorization_grant.allow with input.user as user
+		with input.client as client
+		with input.grant_type as "urn:openid:params:grant-type:ciba"
+		with input.scope as "urn:matrix:org.matrix.msc2967.client:api:*"
+
 	not authorization_grant.allow with input.user as user
 		with input.client as client
 		with input.grant_type as "client_credentials"
@@ -122,6 +127,30 @@ test_synapse_admin_scopes if {
 		with input.scope as "urn:synapse:admin:*"
 }
 
+test_admin_email_domains if {
+	# Any of the verified email addresses of the user can match
+	authorization_grant.allow with input.user as user
+		with input.emails as ["john@example.com", "john@corp.example.com"]
+		with input.client as client
+		with data.admin_email_domains as ["*.example.com"]
+		with input.grant_type as "authorization_code"
+		with input.scope as "urn:synapse:admin:*"
+
+	not authorization_grant.allow with input.user as user
+		with input.emails as ["john@example.com"]
+		with input.client as client
+		with data.admin_email_domains as ["*.example.com"]
+		with input.grant_type as "authorization_code"
+		with input.scope as "urn:synapse:admin:*"
+
+	not authorization_grant.allow with input.user as user
+		with input.emails as []
+		with input.client as client
+		with data.admin_email_domains as ["*.example.com"]
+		with input.grant_type as "authorization_code"
+		with input.scope as "urn:mas:admin"
+}
+
 test_mas_scopes if {
 	authorization_grant.allow with input.user as user
 		with input.client as client
@@ -139,3 +168,84 @@ test_mas_scopes if {
 		with input.grant_type as "authorization_code"
 		with input.scope as "urn:mas:admin"
 }
+
+test_upstream_tokens_scope if {
+	authorization_grant.allow with input.user as user
+		with input.client as client
+		with input.grant_type as "authorization_code"
+		with input.scope as "urn:mas:upstream-tokens:01JZK6Q4YTPEYKXHMRG0T3K1ZA"
+
+	# Not allowed without a user consenting to it
+	not authorization_grant.allow with input.client as client
+		with input.grant_type as "client_credentials"
+		with input.scope as "urn:mas:upstream-tokens:01JZK6Q4YTPEYKXHMRG0T3K1ZA"
+
+	# Only for a valid provider ID
+	not authorization_grant.allow with input.user as user
+		with input.client as client
+		with input.grant_type as "authorization_code"
+		with input.scope as "urn:mas:upstream-tokens:*"
+}
+
+test_password_grant if {
+	direct_login_client := {"id": "01JZK6Q4YTPEYKXHMRG0T3K1ZA"}
+
+	authorization_grant.allow with input.user as user
+		with input.client as direct_login_client
+		with data.direct_login_clients as ["01JZK6Q4YTPEYKXHMRG0T3K1ZA"]
+		with input.grant_type as "password"
+		with input.scope as "openid urn:matrix:org.matrix.msc2967.client:api:* urn:matrix:org.matrix.msc2967.client:device:AAbbCCdd01"
+
+	# Not allowed for clients which are not in the allow-list
+	not authorization_grant.allow with input.user as user
+		with input.client as direct_login_client
+		with data.direct_login_clients as []
+		with input.grant_type as "password"
+		with input.scope as "openid"
+
+	# Admin scopes still require an interactive grant
+	not authorization_grant.allow with input.user as user
+		with input.user.can_request_admin as true
+		with input.client as direct_login_client
+		with data.direct_login_clients as ["01JZK6Q4YTPEYKXHMRG0T3K1ZA"]
+		with input.grant_type as "password"
+		with input.scope as "urn:mas:admin"
+}
+
+test_client_scope_policy if {
+	restricted_client := {
+		"client_id": "client",
+		"scope_policy": {
+			"allowed_scopes": ["openid", "urn:matrix:org.matrix.msc2967.client:device:*"],
+			"disallowed": "reject",
+		},
+	}
+
+	authorization_grant.allow with input.user as user
+		with input.client as restricted_client
+		with input.grant_type as "authorization_code"
+		with input.scope as "openid urn:matrix:org.matrix.msc2967.client:device:AAbbCCdd01"
+
+	# The scope is allowed by the global policy, but not for this client
+	not authorization_grant.allow with input.user as user
+		with input.client as restricted_client
+		with input.grant_type as "authorization_code"
+		with input.scope as "openid urn:matrix:org.matrix.msc2967.client:api:*"
+
+	# The client policy doesn't lift the global restrictions
+	not authorization_grant.allow with input.user as user
+		with input.client as restricted_client
+		with input.grant_type as "authorization_code"
+		with input.scope as "urn:matrix:org.matrix.msc2967.client:device:abcd"
+
+	# Clients without a scope policy can request anything the global policy allows
+	authorization_grant.allow with input.user as user
+		with input.client as client
+		with input.grant_type as "authorization_code"
+		with input.scope as "openid urn:matrix:org.matrix.msc2967.client:api:*"
+
+	authorization_grant.allow with input.user as user
+		with input.client as {"client_id": "client", "scope_policy": null}
+		with input.grant_type as "authorization_code"
+		with input.scope as "openid urn:matrix:org.matrix.msc2967.client:api:*"
+}
@@ -0,0 +1,31 @@
+# Copyright 2025 New Vector Ltd.
+#
+# SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+# Please see LICENSE files in the repository root for full details.
+
+# METADATA
+# schemas:
+#   - input: schema["display_name_input"]
+package display_name
+
+import rego.v1
+
+import data.common
+
+default allow := false
+
+allow if {
+	count(violation) == 0
+}
+
+# METADATA
+# entrypoint: true
+violation contains {"field": "display_name", "code": "display-name-too-long", "msg": "display name too long"} if {
+	count(input.display_name) > data.display_names.max_length
+}
+
+# Display names are lowercased before being checked against the ban list,
+# so that the ban list doesn't have to cover every case variation
+violation contains {"field": "display_name", "code": "display-name-banned", "msg": "display name is banned"} if {
+	common.matches_string_constraints(lower(input.display_name), data.display_names.banned)
+}
@@ -0,0 +1,34 @@
+# Copyright 2025 New Vector Ltd.
+#
+# SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+# Please see LICENSE files in the repository root for full details.
+
+package display_name_test
+
+import data.display_name
+import rego.v1
+
+test_allow_by_default if {
+	display_name.allow with input.display_name as "Alice"
+}
+
+test_max_length if {
+	display_name.allow with input.display_name as "Alice"
+		with data.display_names.max_length as 5
+
+	not display_name.allow with input.display_name as "Alice Smith"
+		with data.display_names.max_length as 5
+}
+
+test_banned_substring if {
+	not display_name.allow with input.display_name as "The Admin"
+		with data.display_names.banned.substrings as ["admin"]
+
+	display_name.allow with input.display_name as "Alice"
+		with data.display_names.banned.substrings as ["admin"]
+}
+
+test_banned_regex if {
+	not display_name.allow with input.display_name as "Support Team"
+		with data.display_names.banned.regexes as ["^support"]
+}
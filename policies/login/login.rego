@@ -0,0 +1,58 @@
+# Copyright 2025 New Vector Ltd.
+#
+# SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+# Please see LICENSE files in the repository root for full details.
+
+# METADATA
+# schemas:
+#   - input: schema["login_input"]
+package login
+
+import rego.v1
+
+import data.common
+
+default allow := false
+
+allow if {
+	count(violation) == 0
+}
+
+# Countries the recently active sessions of the user were in
+known_countries contains session.country if {
+	some session in input.recent_sessions
+	session.country
+}
+
+# METADATA
+# entrypoint: true
+violation contains {"code": "login-new-country", "msg": "login from a new country"} if {
+	data.login.new_country
+	input.location.country
+
+	# Users without any known location yet can't be compared to anything
+	count(known_countries) > 0
+	not input.location.country in known_countries
+}
+
+violation contains {"code": "login-anonymizing-network", "msg": "login from an anonymizing network"} if {
+	some asn in data.login.anonymizing_networks.asns
+	input.location.asn == asn
+}
+
+violation contains {"code": "login-anonymizing-network", "msg": "login from an anonymizing network"} if {
+	common.matches_string_constraints(
+		input.location.as_organization,
+		data.login.anonymizing_networks.organizations,
+	)
+}
+
+# Deny logins from another country than a session which was active too
+# recently for the user to have travelled since
+violation contains {"code": "login-impossible-travel", "msg": "login too far from recent activity"} if {
+	input.location.country
+	some session in input.recent_sessions
+	session.country
+	session.country != input.location.country
+	session.inactive_for < data.login.impossible_travel_seconds
+}
@@ -0,0 +1,80 @@
+# Copyright 2025 New Vector Ltd.
+#
+# SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+# Please see LICENSE files in the repository root for full details.
+
+package login_test
+
+import data.login
+import rego.v1
+
+user := {"username": "alice"}
+
+recent_sessions := [
+	{"country": "FR", "inactive_for": 7200},
+	{"inactive_for": 60},
+]
+
+test_allow_by_default if {
+	login.allow with input.user as user
+		with input.location as {"country": "US", "asn": 9009}
+		with input.recent_sessions as recent_sessions
+}
+
+test_new_country if {
+	not login.allow with input.user as user
+		with input.location as {"country": "US"}
+		with input.recent_sessions as recent_sessions
+		with data.login.new_country as true
+
+	login.allow with input.user as user
+		with input.location as {"country": "FR"}
+		with input.recent_sessions as recent_sessions
+		with data.login.new_country as true
+
+	# Nothing to compare to
+	login.allow with input.user as user
+		with input.location as {"country": "US"}
+		with input.recent_sessions as [{"inactive_for": 60}]
+		with data.login.new_country as true
+
+	# Unknown location
+	login.allow with input.user as user
+		with input.location as {}
+		with input.recent_sessions as recent_sessions
+		with data.login.new_country as true
+}
+
+test_anonymizing_network if {
+	not login.allow with input.user as user
+		with input.location as {"asn": 9009, "as_organization": "M247 Europe SRL"}
+		with input.recent_sessions as []
+		with data.login.anonymizing_networks.asns as [9009]
+
+	not login.allow with input.user as user
+		with input.location as {"asn": 1234, "as_organization": "Some VPN Provider"}
+		with input.recent_sessions as []
+		with data.login.anonymizing_networks.organizations as {"substrings": ["VPN"]}
+
+	login.allow with input.user as user
+		with input.location as {"asn": 3215, "as_organization": "Orange"}
+		with input.recent_sessions as []
+		with data.login.anonymizing_networks as {"asns": [9009], "organizations": {"substrings": ["VPN"]}}
+}
+
+test_impossible_travel if {
+	not login.allow with input.user as user
+		with input.location as {"country": "US"}
+		with input.recent_sessions as recent_sessions
+		with data.login.impossible_travel_seconds as 10800
+
+	login.allow with input.user as user
+		with input.location as {"country": "US"}
+		with input.recent_sessions as recent_sessions
+		with data.login.impossible_travel_seconds as 3600
+
+	login.allow with input.user as user
+		with input.location as {"country": "FR"}
+		with input.recent_sessions as recent_sessions
+		with data.login.impossible_travel_seconds as 10800
+}
@@ -0,0 +1,55 @@
+# Copyright 2025 New Vector Ltd.
+#
+# SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+# Please see LICENSE files in the repository root for full details.
+
+# METADATA
+# schemas:
+#   - input: schema["upstream_login_input"]
+package upstream_login
+
+import rego.v1
+
+default allow := false
+
+allow if {
+	count(violation) == 0
+}
+
+# The claims returned by the userinfo endpoint take precedence over the ones
+# of the ID token
+claims := object.union(
+	object.get(input, "id_token_claims", {}),
+	object.get(input, "userinfo_claims", {}),
+)
+
+# Rules without a list of providers apply to all of them
+applies(rule) if not rule.providers
+
+applies(rule) if input.provider_id in rule.providers
+
+# Claims can either hold a single value, or a list of values like groups
+claim_values(value) := value if is_array(value)
+
+claim_values(value) := [value] if not is_array(value)
+
+claim_allowed(rule) if {
+	some value in claim_values(claims[rule.claim])
+	value in rule.values
+}
+
+# METADATA
+# entrypoint: true
+violation contains {"field": rule.claim, "code": "upstream-claim-missing", "msg": sprintf("missing claim %s", [rule.claim])} if {
+	some rule in data.upstream_login.required_claims
+	applies(rule)
+	not rule.claim in object.keys(claims)
+}
+
+violation contains {"field": rule.claim, "code": "upstream-claim-not-allowed", "msg": sprintf("claim %s not allowed", [rule.claim])} if {
+	some rule in data.upstream_login.required_claims
+	applies(rule)
+	rule.values
+	rule.claim in object.keys(claims)
+	not claim_allowed(rule)
+}
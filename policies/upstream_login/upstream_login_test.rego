@@ -0,0 +1,57 @@
+# Copyright 2025 New Vector Ltd.
+#
+# SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+# Please see LICENSE files in the repository root for full details.
+
+package upstream_login_test
+
+import data.upstream_login
+import rego.v1
+
+provider_id := "01JCS8TTWV6F9CRDHE4R1XG8KN"
+
+test_allow_by_default if {
+	upstream_login.allow with input.provider_id as provider_id
+		with input.id_token_claims as {"sub": "alice"}
+}
+
+test_required_claim if {
+	upstream_login.allow with input.provider_id as provider_id
+		with input.id_token_claims as {"sub": "alice", "email_verified": true}
+		with data.upstream_login.required_claims as [{"claim": "email_verified"}]
+
+	not upstream_login.allow with input.provider_id as provider_id
+		with input.id_token_claims as {"sub": "alice"}
+		with data.upstream_login.required_claims as [{"claim": "email_verified"}]
+}
+
+test_allowed_values if {
+	upstream_login.allow with input.provider_id as provider_id
+		with input.id_token_claims as {"sub": "alice", "groups": ["staff", "matrix-users"]}
+		with data.upstream_login.required_claims as [{"claim": "groups", "values": ["matrix-users"]}]
+
+	not upstream_login.allow with input.provider_id as provider_id
+		with input.id_token_claims as {"sub": "alice", "groups": ["staff"]}
+		with data.upstream_login.required_claims as [{"claim": "groups", "values": ["matrix-users"]}]
+
+	upstream_login.allow with input.provider_id as provider_id
+		with input.id_token_claims as {"sub": "alice", "department": "it"}
+		with data.upstream_login.required_claims as [{"claim": "department", "values": ["it", "hr"]}]
+}
+
+test_userinfo_claims if {
+	upstream_login.allow with input.provider_id as provider_id
+		with input.id_token_claims as {"sub": "alice", "groups": ["staff"]}
+		with input.userinfo_claims as {"sub": "alice", "groups": ["matrix-users"]}
+		with data.upstream_login.required_claims as [{"claim": "groups", "values": ["matrix-users"]}]
+}
+
+test_providers if {
+	upstream_login.allow with input.provider_id as provider_id
+		with input.id_token_claims as {"sub": "alice"}
+		with data.upstream_login.required_claims as [{"claim": "groups", "providers": ["01JCS8V5ZQGX7A5Y7K7TYX7D8B"]}]
+
+	not upstream_login.allow with input.provider_id as provider_id
+		with input.id_token_claims as {"sub": "alice"}
+		with data.upstream_login.required_claims as [{"claim": "groups", "providers": [provider_id]}]
+}